@@ -0,0 +1,372 @@
+//! Replays a captured workload against two pgsqlite builds and compares them
+//! query fingerprint by query fingerprint.
+//!
+//! ```bash
+//! cargo bench --bench replay_bench -- \
+//!     --baseline ./pgsqlite-v0.0.15 --candidate target/release/pgsqlite \
+//!     --workload captured.log --setup schema.sql --iterations 10
+//! ```
+//!
+//! The workload is either a SQL script or a server log captured with
+//! `SET pgsqlite.trace = on`, whose `statement:` lines are replayed in order.
+//! Both servers run side by side and each iteration replays the workload on one
+//! and then the other, so machine noise hits both builds alike.
+
+use clap::Parser;
+use once_cell::sync::Lazy;
+use pgsqlite::cache::QueryFingerprint;
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+use tokio_postgres::{Client, NoTls};
+
+static TRACE_STATEMENT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"pgsqlite\.trace \[[^\]]+\] statement: (.*)$").unwrap()
+});
+static LOG_LINE_START: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*\d{4}-\d{2}-\d{2}T").unwrap());
+static ANSI_ESCAPE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\x1b\[[0-9;]*m").unwrap());
+
+#[derive(Parser, Debug)]
+#[command(about = "Replay a workload against two pgsqlite builds and report per-fingerprint differences")]
+struct Args {
+    /// pgsqlite binary to compare against
+    #[arg(long)]
+    baseline: Option<PathBuf>,
+
+    /// pgsqlite binary under test
+    #[arg(long)]
+    candidate: Option<PathBuf>,
+
+    /// SQL script or pgsqlite.trace server log to replay
+    #[arg(long)]
+    workload: Option<PathBuf>,
+
+    /// SQL script run once on each server before measuring
+    #[arg(long)]
+    setup: Option<PathBuf>,
+
+    /// Database file to start from; each build works on its own copy (default: in-memory)
+    #[arg(long)]
+    database: Option<PathBuf>,
+
+    /// Measured replays of the workload
+    #[arg(long, default_value = "5")]
+    iterations: usize,
+
+    /// Unmeasured replays before measuring
+    #[arg(long, default_value = "1")]
+    warmup: usize,
+
+    /// Slowdown of the median latency, in percent, reported as a regression
+    #[arg(long, default_value = "10")]
+    threshold: f64,
+
+    /// Exit with an error when any fingerprint regressed
+    #[arg(long)]
+    fail_on_regression: bool,
+
+    /// Passed by `cargo bench`
+    #[arg(long, hide = true)]
+    bench: bool,
+}
+
+/// Measurements of one fingerprint on one build
+#[derive(Debug, Default)]
+struct Samples {
+    query: String,
+    latencies_ms: Vec<f64>,
+    /// Resident memory growth in KiB per execution, where the platform reports it
+    rss_growth_kb: Vec<i64>,
+    errors: usize,
+}
+
+impl Samples {
+    fn median_ms(&self) -> Option<f64> {
+        if self.latencies_ms.is_empty() {
+            return None;
+        }
+        let mut sorted = self.latencies_ms.clone();
+        sorted.sort_by(f64::total_cmp);
+        Some(sorted[sorted.len() / 2])
+    }
+
+    fn mean_rss_growth_kb(&self) -> Option<f64> {
+        if self.rss_growth_kb.is_empty() {
+            return None;
+        }
+        Some(self.rss_growth_kb.iter().sum::<i64>() as f64 / self.rss_growth_kb.len() as f64)
+    }
+}
+
+/// A pgsqlite server started for the replay, killed when dropped
+struct Server {
+    process: Child,
+    client: Client,
+    _dir: tempfile::TempDir,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+impl Server {
+    async fn start(binary: &Path, database: Option<&Path>) -> anyhow::Result<Self> {
+        let dir = tempfile::tempdir()?;
+        let port = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+
+        let mut command = Command::new(binary);
+        command
+            .args(["--port", &port.to_string(), "--log-level", "error"])
+            .arg("--socket-dir")
+            .arg(dir.path())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        match database {
+            Some(database) => {
+                let copy = dir.path().join("replay.db");
+                std::fs::copy(database, &copy)?;
+                command.arg("--database").arg(copy);
+            }
+            None => {
+                command.arg("--in-memory");
+            }
+        }
+        let mut process = command.spawn()
+            .map_err(|e| anyhow::anyhow!("Failed to start {}: {}", binary.display(), e))?;
+
+        let started = Instant::now();
+        let client = loop {
+            match tokio_postgres::connect(&format!("host=127.0.0.1 port={port} dbname=main user=postgres"), NoTls).await {
+                Ok((client, connection)) => {
+                    tokio::spawn(connection);
+                    break client;
+                }
+                Err(e) if started.elapsed() > Duration::from_secs(30) => {
+                    let _ = process.kill();
+                    return Err(anyhow::anyhow!("{} did not accept connections: {}", binary.display(), e));
+                }
+                Err(_) => tokio::time::sleep(Duration::from_millis(50)).await,
+            }
+        };
+        Ok(Server { process, client, _dir: dir })
+    }
+
+    /// Resident set size of the server in KiB (Linux only)
+    fn rss_kb(&self) -> Option<i64> {
+        let status = std::fs::read_to_string(format!("/proc/{}/status", self.process.id())).ok()?;
+        status.lines()
+            .find_map(|line| line.strip_prefix("VmRSS:"))
+            .and_then(|value| value.trim().trim_end_matches("kB").trim().parse().ok())
+    }
+
+    async fn replay(&self, statements: &[String], samples: Option<&mut HashMap<u64, Samples>>) {
+        let mut samples = samples;
+        for statement in statements {
+            let rss_before = self.rss_kb();
+            let started = Instant::now();
+            let result = self.client.simple_query(statement).await;
+            let elapsed = started.elapsed().as_secs_f64() * 1000.0;
+            let Some(samples) = samples.as_deref_mut() else { continue };
+
+            let entry = samples.entry(QueryFingerprint::generate(statement)).or_default();
+            if entry.query.is_empty() {
+                entry.query = statement.clone();
+            }
+            match result {
+                Ok(_) => {
+                    entry.latencies_ms.push(elapsed);
+                    if let (Some(before), Some(after)) = (rss_before, self.rss_kb()) {
+                        entry.rss_growth_kb.push(after - before);
+                    }
+                }
+                Err(_) => entry.errors += 1,
+            }
+        }
+    }
+}
+
+/// Statements of a workload: the `statement:` lines of a pgsqlite.trace log, or a SQL script
+fn load_workload(path: &Path) -> anyhow::Result<Vec<String>> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+    let text = ANSI_ESCAPE.replace_all(&text, "");
+    if text.lines().any(|line| TRACE_STATEMENT.is_match(line)) {
+        Ok(parse_trace_log(&text))
+    } else {
+        Ok(split_script(&text))
+    }
+}
+
+/// Statements from a server log; a statement spanning lines continues until the next log line
+fn parse_trace_log(log: &str) -> Vec<String> {
+    let mut statements: Vec<String> = Vec::new();
+    let mut in_statement = false;
+    for line in log.lines() {
+        if let Some(caps) = TRACE_STATEMENT.captures(line) {
+            statements.push(caps[1].to_string());
+            in_statement = true;
+        } else if LOG_LINE_START.is_match(line) {
+            in_statement = false;
+        } else if in_statement && let Some(last) = statements.last_mut() {
+            last.push('\n');
+            last.push_str(line);
+        }
+    }
+    statements
+}
+
+/// Split a SQL script on semicolons outside string literals, identifiers and comments
+fn split_script(script: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut chars = script.chars().peekable();
+    let mut quote: Option<char> = None;
+    while let Some(ch) = chars.next() {
+        match (quote, ch) {
+            (Some(q), c) if c == q => {
+                quote = None;
+                current.push(c);
+            }
+            (Some(_), c) => current.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(ch);
+                current.push(ch);
+            }
+            (None, '-') if chars.peek() == Some(&'-') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        current.push('\n');
+                        break;
+                    }
+                }
+            }
+            (None, ';') => {
+                if !current.trim().is_empty() {
+                    statements.push(current.trim().to_string());
+                }
+                current.clear();
+            }
+            (None, c) => current.push(c),
+        }
+    }
+    if !current.trim().is_empty() {
+        statements.push(current.trim().to_string());
+    }
+    statements
+}
+
+fn format_ms(value: Option<f64>) -> String {
+    value.map_or("-".to_string(), |v| format!("{v:.3}"))
+}
+
+fn format_kb(value: Option<f64>) -> String {
+    value.map_or("-".to_string(), |v| format!("{v:+.1}"))
+}
+
+/// Print the comparison as a Markdown table, slowest regressions first; returns the number of regressions
+fn report(baseline: &HashMap<u64, Samples>, candidate: &HashMap<u64, Samples>, threshold: f64) -> usize {
+    let mut rows: Vec<(u64, Option<f64>)> = baseline.keys()
+        .map(|fingerprint| {
+            let change = match (baseline[fingerprint].median_ms(), candidate.get(fingerprint).and_then(Samples::median_ms)) {
+                (Some(before), Some(after)) if before > 0.0 => Some((after - before) / before * 100.0),
+                _ => None,
+            };
+            (*fingerprint, change)
+        })
+        .collect();
+    rows.sort_by(|a, b| b.1.unwrap_or(f64::MIN).total_cmp(&a.1.unwrap_or(f64::MIN)));
+
+    println!("| Query | Calls | Baseline median ms | Candidate median ms | Change | Baseline RSS KiB/call | Candidate RSS KiB/call | Errors |");
+    println!("|-------|-------|--------------------|---------------------|--------|-----------------------|------------------------|--------|");
+    let mut regressions = 0;
+    for (fingerprint, change) in rows {
+        let before = &baseline[&fingerprint];
+        let after = candidate.get(&fingerprint);
+        let regressed = change.is_some_and(|c| c > threshold);
+        if regressed {
+            regressions += 1;
+        }
+        let mut query: String = before.query.split_whitespace().collect::<Vec<_>>().join(" ");
+        if query.chars().count() > 60 {
+            query = query.chars().take(57).collect::<String>() + "...";
+        }
+        println!(
+            "| `{}` | {} | {} | {} | {}{} | {} | {} | {}/{} |",
+            query.replace('|', "\\|"),
+            before.latencies_ms.len() + before.errors,
+            format_ms(before.median_ms()),
+            format_ms(after.and_then(Samples::median_ms)),
+            change.map_or("-".to_string(), |c| format!("{c:+.1}%")),
+            if regressed { " REGRESSION" } else { "" },
+            format_kb(before.mean_rss_growth_kb()),
+            format_kb(after.and_then(Samples::mean_rss_growth_kb)),
+            before.errors,
+            after.map_or(0, |a| a.errors),
+        );
+    }
+    regressions
+}
+
+async fn run(args: Args) -> anyhow::Result<usize> {
+    let (Some(baseline_bin), Some(candidate_bin), Some(workload)) = (&args.baseline, &args.candidate, &args.workload) else {
+        unreachable!("checked by main");
+    };
+    let statements = load_workload(workload)?;
+    if statements.is_empty() {
+        return Err(anyhow::anyhow!("No statements found in {}", workload.display()));
+    }
+    let setup = match &args.setup {
+        Some(path) => load_workload(path)?,
+        None => Vec::new(),
+    };
+
+    let baseline = Server::start(baseline_bin, args.database.as_deref()).await?;
+    let candidate = Server::start(candidate_bin, args.database.as_deref()).await?;
+    for server in [&baseline, &candidate] {
+        for statement in &setup {
+            server.client.simple_query(statement).await
+                .map_err(|e| anyhow::anyhow!("Setup statement failed: {statement}: {e}"))?;
+        }
+    }
+
+    eprintln!("Replaying {} statements: {} warmup and {} measured iterations", statements.len(), args.warmup, args.iterations);
+    for _ in 0..args.warmup {
+        baseline.replay(&statements, None).await;
+        candidate.replay(&statements, None).await;
+    }
+    let mut baseline_samples = HashMap::new();
+    let mut candidate_samples = HashMap::new();
+    for _ in 0..args.iterations {
+        baseline.replay(&statements, Some(&mut baseline_samples)).await;
+        candidate.replay(&statements, Some(&mut candidate_samples)).await;
+    }
+
+    println!("Baseline: {}", baseline_bin.display());
+    println!("Candidate: {}", candidate_bin.display());
+    println!();
+    let regressions = report(&baseline_samples, &candidate_samples, args.threshold);
+    println!();
+    println!("{regressions} of {} fingerprints slower by more than {}%", baseline_samples.len(), args.threshold);
+    Ok(regressions)
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    if args.baseline.is_none() || args.candidate.is_none() || args.workload.is_none() {
+        // Plain `cargo bench` runs every bench; this one needs two builds to compare
+        eprintln!("replay_bench: pass --baseline <bin> --candidate <bin> --workload <file> to compare two builds");
+        return Ok(());
+    }
+
+    let fail_on_regression = args.fail_on_regression;
+    let regressions = tokio::runtime::Runtime::new()?.block_on(run(args))?;
+    if fail_on_regression && regressions > 0 {
+        return Err(anyhow::anyhow!("{regressions} fingerprints regressed"));
+    }
+    Ok(())
+}
@@ -0,0 +1,11 @@
+-- A small OLTP-style mix; statements differing only in literals share a fingerprint
+SELECT id, name, email FROM users WHERE id = 42;
+SELECT id, name, email FROM users WHERE id = 137;
+SELECT * FROM orders WHERE user_id = 42 ORDER BY id DESC LIMIT 10;
+SELECT u.name, count(o.id), sum(o.total) FROM users u JOIN orders o ON o.user_id = u.id GROUP BY u.name ORDER BY 3 DESC LIMIT 5;
+SELECT status, count(*) FROM orders GROUP BY status;
+UPDATE orders SET status = 'shipped' WHERE id = 7;
+INSERT INTO orders (id, user_id, total, status) VALUES (100000, 1, 10.50, 'pending');
+DELETE FROM orders WHERE id = 100000;
+SELECT name FROM users WHERE email LIKE 'user1%' ORDER BY name LIMIT 20;
+SELECT count(*) FROM orders WHERE total > 50.0 AND status = 'pending';
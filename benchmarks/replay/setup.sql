@@ -0,0 +1,9 @@
+-- Schema and data for sample_workload.sql
+CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL, email TEXT);
+CREATE TABLE orders (id INTEGER PRIMARY KEY, user_id INTEGER NOT NULL, total NUMERIC(10, 2), status VARCHAR(20));
+WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 500)
+    INSERT INTO users (id, name, email)
+    SELECT i, 'user ' || i, 'user' || i || '@example.com' FROM n;
+WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 2000)
+    INSERT INTO orders (id, user_id, total, status)
+    SELECT i, (i % 500) + 1, (i % 97) + 0.99, CASE WHEN i % 3 = 0 THEN 'shipped' ELSE 'pending' END FROM n;
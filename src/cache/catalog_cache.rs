@@ -0,0 +1,155 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use crate::session::db_handler::DbResponse;
+use crate::session::SessionState;
+
+/// Bumped after every statement that may have changed what the catalog shows
+static SCHEMA_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Statements whose effects show up in pg_catalog or information_schema
+const CATALOG_CHANGES: [&str; 6] = ["CREATE", "DROP", "ALTER", "COMMENT", "GRANT", "REVOKE"];
+
+/// Statements that end a transaction block, publishing or discarding its catalog changes
+const TRANSACTION_ENDS: [&str; 4] = ["COMMIT", "END", "ROLLBACK", "ABORT"];
+
+/// Identifies one state of the schema: pgsqlite's generation counter, which covers
+/// its own metadata tables, and SQLite's `schema_version`, which also moves when
+/// another process changes the schema
+pub type SchemaSnapshot = (u64, i64);
+
+/// Results of intercepted catalog queries, kept until the schema changes.
+///
+/// Tools like pgAdmin and ORMs send the same catalog queries over and over, and
+/// each one rebuilds pg_class, pg_attribute or pg_type from sqlite_master. Entries
+/// belong to one [`SchemaSnapshot`]; the first lookup under a newer snapshot drops
+/// them all.
+pub struct CatalogCache {
+    entries: super::LruCache<String, DbResponse>,
+    snapshot: Mutex<SchemaSnapshot>,
+    capacity: AtomicUsize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl CatalogCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: super::LruCache::new(capacity, Duration::MAX),
+            snapshot: Mutex::new((0, -1)),
+            capacity: AtomicUsize::new(capacity),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.capacity.load(Ordering::Relaxed) > 0
+    }
+
+    /// Change how many results are kept; 0 disables the cache
+    pub fn resize(&self, capacity: usize) {
+        self.capacity.store(capacity, Ordering::Relaxed);
+        self.entries.resize(capacity);
+    }
+
+    /// The current value of the schema generation counter
+    pub fn generation() -> u64 {
+        SCHEMA_GENERATION.load(Ordering::Acquire)
+    }
+
+    /// Invalidate every cached result
+    pub fn bump_generation() {
+        SCHEMA_GENERATION.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Track a statement the session has just run.
+    ///
+    /// Changes made inside a transaction block only become visible to other
+    /// sessions when it commits, and results cached in the meantime still show the
+    /// old schema, so the generation moves again when the block ends.
+    pub fn statement_finished(session: &SessionState, query: &str) {
+        let keyword = query.trim_start().split(|c: char| !c.is_ascii_alphabetic()).next().unwrap_or("");
+        if CATALOG_CHANGES.iter().any(|k| keyword.eq_ignore_ascii_case(k)) {
+            session.record_schema_change();
+            Self::bump_generation();
+        } else if TRANSACTION_ENDS.iter().any(|k| keyword.eq_ignore_ascii_case(k)) && session.take_schema_change() {
+            Self::bump_generation();
+        }
+    }
+
+    /// A cached result for `query` taken under `snapshot`
+    pub fn get(&self, query: &str, snapshot: SchemaSnapshot) -> Option<DbResponse> {
+        {
+            let mut current = self.snapshot.lock().unwrap();
+            if *current != snapshot {
+                *current = snapshot;
+                self.entries.clear();
+            }
+        }
+        let result = self.entries.get(&query.to_string());
+        let counter = if result.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        result
+    }
+
+    /// Cache the result of `query`, unless the schema moved on while it ran
+    pub fn insert(&self, query: &str, snapshot: SchemaSnapshot, response: DbResponse) {
+        let current = self.snapshot.lock().unwrap();
+        if *current == snapshot {
+            self.entries.insert(query.to_string(), response);
+        }
+    }
+
+    /// Hits, misses and the number of cached results
+    pub fn stats(&self) -> (u64, u64, usize) {
+        (self.hits.load(Ordering::Relaxed), self.misses.load(Ordering::Relaxed), self.entries.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(value: &str) -> DbResponse {
+        DbResponse {
+            columns: vec!["relname".to_string()],
+            rows: vec![vec![Some(value.as_bytes().to_vec())]],
+            rows_affected: 1,
+        }
+    }
+
+    #[test]
+    fn test_entries_belong_to_one_snapshot() {
+        let cache = CatalogCache::new(4);
+        let query = "SELECT relname FROM pg_class";
+        assert!(cache.get(query, (1, 7)).is_none());
+        cache.insert(query, (1, 7), response("a"));
+        assert_eq!(cache.get(query, (1, 7)).unwrap().rows, response("a").rows);
+
+        // A result computed under an older snapshot is not kept
+        assert!(cache.get(query, (2, 7)).is_none());
+        cache.insert(query, (1, 7), response("a"));
+        assert!(cache.get(query, (2, 7)).is_none());
+        cache.insert(query, (2, 7), response("b"));
+        assert_eq!(cache.get(query, (2, 7)).unwrap().rows, response("b").rows);
+
+        assert!(cache.get(query, (2, 8)).is_none());
+        assert_eq!(cache.stats(), (2, 4, 0));
+    }
+
+    #[test]
+    fn test_statement_tracking() {
+        let session = SessionState::new("main".to_string(), "postgres".to_string());
+        CatalogCache::statement_finished(&session, "SELECT 1");
+        assert!(!session.take_schema_change());
+
+        let start = CatalogCache::generation();
+        CatalogCache::statement_finished(&session, "  comment ON TABLE t IS 'x'");
+        let after_ddl = CatalogCache::generation();
+        assert!(after_ddl > start);
+        CatalogCache::statement_finished(&session, "COMMIT;");
+        assert!(CatalogCache::generation() > after_ddl);
+        assert!(!session.take_schema_change());
+    }
+}
@@ -0,0 +1,90 @@
+use crate::config::Config;
+use super::{CatalogCache, ResultSetCache, RowDescriptionCache, StatementPool};
+
+/// The caches whose entries belong to one database.
+///
+/// Every [`DbHandler`](crate::session::DbHandler) owns its own partition, sized by
+/// the cache settings, so a busy database can only evict its own entries, and a
+/// query text that means different things in two databases never shares an entry.
+pub struct DatabaseCaches {
+    pub row_descriptions: RowDescriptionCache,
+    pub results: ResultSetCache,
+    pub statements: StatementPool,
+    pub catalog: CatalogCache,
+}
+
+impl DatabaseCaches {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            row_descriptions: RowDescriptionCache::new(config.row_desc_cache_size, config.row_desc_cache_ttl_duration()),
+            results: ResultSetCache::new(config.result_cache_size, 10000, config.result_cache_ttl),
+            statements: StatementPool::new(config.statement_pool_size),
+            catalog: CatalogCache::new(config.catalog_cache_size),
+        }
+    }
+
+    /// Apply the cache sizes of a reloaded configuration
+    pub fn resize(&self, config: &Config) {
+        self.row_descriptions.resize(config.row_desc_cache_size);
+        self.results.resize(config.result_cache_size);
+        self.statements.resize(config.statement_pool_size);
+        self.catalog.resize(config.catalog_cache_size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use crate::cache::ResultCacheKey;
+
+    fn caches(size: usize) -> DatabaseCaches {
+        let mut config = Config::parse_from(["pgsqlite"]);
+        config.row_desc_cache_size = size;
+        config.result_cache_size = size;
+        DatabaseCaches::new(&config)
+    }
+
+    #[test]
+    fn test_partitions_evict_independently() {
+        let (busy, quiet) = (caches(2), caches(2));
+        let key = |query: &str| RowDescriptionCache::create_key(query, None, &["id".to_string()]);
+
+        quiet.row_descriptions.insert(key("SELECT id FROM items"), Vec::new());
+        quiet.results.insert(ResultCacheKey::new("SELECT id FROM items", &[]), vec!["id".to_string()], Vec::new(), 0, 0);
+        for i in 0..10 {
+            let query = format!("SELECT id FROM items WHERE id = {i}");
+            busy.row_descriptions.insert(key(&query), Vec::new());
+            busy.results.insert(ResultCacheKey::new(&query, &[]), vec!["id".to_string()], Vec::new(), 0, 0);
+        }
+
+        assert!(quiet.row_descriptions.get(&key("SELECT id FROM items")).is_some());
+        assert!(quiet.results.get(&ResultCacheKey::new("SELECT id FROM items", &[])).is_some());
+        assert!(busy.row_descriptions.get(&key("SELECT id FROM items")).is_none());
+        assert_eq!(busy.row_descriptions.stats().entries, 2);
+    }
+
+    #[test]
+    fn test_resize() {
+        let caches = caches(4);
+        let key = |i: usize| RowDescriptionCache::create_key(&format!("SELECT {i}"), None, &[]);
+        for i in 0..4 {
+            caches.row_descriptions.insert(key(i), Vec::new());
+            caches.results.insert(ResultCacheKey::new(&format!("SELECT {i}"), &[]), Vec::new(), Vec::new(), 0, 0);
+        }
+
+        let mut config = Config::parse_from(["pgsqlite"]);
+        config.row_desc_cache_size = 1;
+        config.result_cache_size = 2;
+        config.catalog_cache_size = 0;
+        caches.resize(&config);
+        assert_eq!(caches.row_descriptions.stats().entries, 1);
+        let cached = (0..4).filter(|i| caches.results.get(&ResultCacheKey::new(&format!("SELECT {i}"), &[])).is_some()).count();
+        assert_eq!(cached, 2);
+        assert!(!caches.catalog.enabled());
+
+        // The new sizes also hold for later inserts
+        caches.row_descriptions.insert(key(5), Vec::new());
+        assert_eq!(caches.row_descriptions.stats().entries, 1);
+    }
+}
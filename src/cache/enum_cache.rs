@@ -2,7 +2,8 @@ use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 use rusqlite::Connection;
-use crate::metadata::{EnumType, EnumValue, EnumMetadata};
+use crate::metadata::{EnumType, EnumValue, EnumMetadata, OidAllocator};
+use crate::metadata::oid_allocator::ARRAY_TYPE;
 
 /// Cache entry for ENUM metadata
 #[derive(Clone)]
@@ -19,6 +20,8 @@ pub struct EnumCache {
     types_by_oid: Arc<RwLock<HashMap<i32, CacheEntry<EnumType>>>>,
     /// Map from type OID to its values
     values_by_type: Arc<RwLock<HashMap<i32, CacheEntry<Vec<EnumValue>>>>>,
+    /// Map from type OID to the OID of its array type
+    array_types: Arc<RwLock<HashMap<i32, i32>>>,
     /// Cache TTL
     ttl: Duration,
 }
@@ -30,6 +33,7 @@ impl EnumCache {
             types_by_name: Arc::new(RwLock::new(HashMap::new())),
             types_by_oid: Arc::new(RwLock::new(HashMap::new())),
             values_by_type: Arc::new(RwLock::new(HashMap::new())),
+            array_types: Arc::new(RwLock::new(HashMap::new())),
             ttl: Duration::from_secs(ttl_seconds),
         }
     }
@@ -39,6 +43,7 @@ impl EnumCache {
         self.types_by_name.write().unwrap().clear();
         self.types_by_oid.write().unwrap().clear();
         self.values_by_type.write().unwrap().clear();
+        self.array_types.write().unwrap().clear();
     }
     
     /// Get ENUM type by name (with caching)
@@ -67,6 +72,10 @@ impl EnumCache {
             
             name_cache.insert(type_name.to_string(), entry.clone());
             oid_cache.insert(et.type_oid, entry);
+            
+            if let Some(array_oid) = OidAllocator::lookup(conn, ARRAY_TYPE, &et.type_name)? {
+                self.array_types.write().unwrap().insert(et.type_oid, array_oid as i32);
+            }
         }
         
         Ok(enum_type)
@@ -98,6 +107,10 @@ impl EnumCache {
             
             name_cache.insert(et.type_name.clone(), entry.clone());
             oid_cache.insert(type_oid, entry);
+            
+            if let Some(array_oid) = OidAllocator::lookup(conn, ARRAY_TYPE, &et.type_name)? {
+                self.array_types.write().unwrap().insert(et.type_oid, array_oid as i32);
+            }
         }
         
         Ok(enum_type)
@@ -129,6 +142,54 @@ impl EnumCache {
         Ok(values)
     }
     
+    /// Cache all ENUM types of a database so `type_oid` can resolve them
+    pub fn load_types(&self, conn: &Connection) -> rusqlite::Result<()> {
+        let types = EnumMetadata::get_all_enum_types(conn)?;
+        let mut name_cache = self.types_by_name.write().unwrap();
+        let mut oid_cache = self.types_by_oid.write().unwrap();
+        for enum_type in types {
+            let entry = CacheEntry {
+                data: enum_type.clone(),
+                timestamp: Instant::now(),
+            };
+            name_cache.insert(enum_type.type_name.clone(), entry.clone());
+            oid_cache.insert(enum_type.type_oid, entry);
+        }
+        let mut array_cache = self.array_types.write().unwrap();
+        for (_, type_oid, array_oid) in OidAllocator::array_types(conn)? {
+            if oid_cache.contains_key(&(type_oid as i32)) {
+                array_cache.insert(type_oid as i32, array_oid as i32);
+            }
+        }
+        Ok(())
+    }
+    
+    /// OID of a cached ENUM type, for callers without a connection. Entries are kept
+    /// past their TTL here, as ENUM DDL invalidates the types it changes.
+    pub fn type_oid(&self, type_name: &str) -> Option<i32> {
+        let cache = self.types_by_name.read().unwrap();
+        cache.get(type_name)
+            .or_else(|| cache.get(&type_name.to_lowercase()))
+            .map(|entry| entry.data.type_oid)
+    }
+    
+    /// Whether an OID belongs to a cached ENUM type, on the same terms as `type_oid`
+    pub fn is_enum_oid(&self, type_oid: i32) -> bool {
+        self.types_by_oid.read().unwrap().contains_key(&type_oid)
+    }
+    
+    /// OID of the array type of a cached ENUM type
+    pub fn array_type_oid(&self, type_oid: i32) -> Option<i32> {
+        self.array_types.read().unwrap().get(&type_oid).copied()
+    }
+    
+    /// OID of the ENUM type whose array type has the given OID
+    pub fn array_element_oid(&self, array_oid: i32) -> Option<i32> {
+        self.array_types.read().unwrap().iter()
+            .find(|(_, oid)| **oid == array_oid)
+            .map(|(type_oid, _)| *type_oid)
+    }
+    
     /// Validate if a value is valid for an ENUM type (uses cache)
     pub fn is_valid_enum_value(&self, conn: &Connection, type_oid: i32, label: &str) -> rusqlite::Result<bool> {
         let values = self.get_enum_values(conn, type_oid)?;
@@ -139,6 +200,7 @@ impl EnumCache {
     pub fn invalidate_type(&self, type_oid: i32) {
         self.types_by_oid.write().unwrap().remove(&type_oid);
         self.values_by_type.write().unwrap().remove(&type_oid);
+        self.array_types.write().unwrap().remove(&type_oid);
         
         // Also remove from name cache if we can find it
         let mut name_cache = self.types_by_name.write().unwrap();
@@ -171,4 +233,24 @@ mod tests {
         // Cache should be empty initially
         assert!(cache.get_enum_type(&conn, "test_enum").unwrap().is_none());
     }
+    
+    #[test]
+    fn test_load_types() {
+        let cache = EnumCache::new(60);
+        let mut conn = Connection::open_in_memory().unwrap();
+        let type_oid = EnumMetadata::create_enum_type(&mut conn, "mood", &["happy", "sad"], None).unwrap();
+        
+        assert_eq!(cache.type_oid("mood"), None);
+        cache.load_types(&conn).unwrap();
+        assert_eq!(cache.type_oid("mood"), Some(type_oid));
+        let array_oid = cache.array_type_oid(type_oid).unwrap();
+        assert_eq!(cache.type_oid("MOOD"), Some(type_oid));
+        assert!(cache.is_enum_oid(type_oid));
+        assert_eq!(cache.array_element_oid(array_oid), Some(type_oid));
+        
+        cache.invalidate_type(type_oid);
+        assert_eq!(cache.type_oid("mood"), None);
+        assert!(!cache.is_enum_oid(type_oid));
+        assert_eq!(cache.array_element_oid(array_oid), None);
+    }
 }
\ No newline at end of file
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
 
 pub mod schema;
@@ -16,6 +17,8 @@ pub mod translation_cache;
 pub mod query_fingerprint;
 pub mod lazy_schema_loader;
 pub mod wire_protocol_cache;
+pub mod catalog_cache;
+pub mod database_caches;
 
 pub use schema::SchemaCache;
 pub use query::{QueryCache, CachedQuery, CacheMetrics};
@@ -23,19 +26,21 @@ pub use status::{CacheStatus, get_cache_status, format_cache_status_as_table, lo
 pub use statement_pool::{StatementPool, StatementMetadata, StatementPoolStats};
 pub use enhanced_statement_pool::{EnhancedStatementPool, StatementMetadata as EnhancedStatementMetadata, PoolStats};
 pub use execution::{ExecutionCache, ExecutionMetadata, global_execution_cache, global_type_converter_table};
-pub use result_cache::{ResultSetCache, ResultCacheKey, CachedResultSet, global_result_cache};
-pub use row_description::{RowDescriptionCache, RowDescriptionKey, CachedRowDescription, GLOBAL_ROW_DESCRIPTION_CACHE};
+pub use result_cache::{ResultSetCache, ResultCacheKey, CachedResultSet};
+pub use row_description::{RowDescriptionCache, RowDescriptionKey, CachedRowDescription};
 pub use parameter_cache::{ParameterTypeCache, CachedParameterInfo, GLOBAL_PARAMETER_CACHE, GLOBAL_PARAM_VALUE_CACHE};
 pub use enum_cache::{EnumCache, global_enum_cache};
 pub use translation_cache::{TranslationCache, global_translation_cache};
 pub use query_fingerprint::QueryFingerprint;
 pub use lazy_schema_loader::LazySchemaLoader;
 pub use wire_protocol_cache::{WireProtocolCache, CachedWireResponse, WIRE_PROTOCOL_CACHE, is_cacheable_for_wire_protocol, encode_data_row};
+pub use catalog_cache::{CatalogCache, SchemaSnapshot};
+pub use database_caches::DatabaseCaches;
 
 /// Simple LRU cache with TTL support
 pub struct LruCache<K, V> {
     cache: Arc<RwLock<HashMap<K, CacheEntry<V>>>>,
-    capacity: usize,
+    capacity: AtomicUsize,
     ttl: Duration,
 }
 
@@ -48,7 +53,7 @@ impl<K: Eq + std::hash::Hash + Clone, V: Clone> LruCache<K, V> {
     pub fn new(capacity: usize, ttl: Duration) -> Self {
         Self {
             cache: Arc::new(RwLock::new(HashMap::with_capacity(capacity))),
-            capacity,
+            capacity: AtomicUsize::new(capacity),
             ttl,
         }
     }
@@ -72,12 +77,9 @@ impl<K: Eq + std::hash::Hash + Clone, V: Clone> LruCache<K, V> {
         let mut cache = self.cache.write().unwrap();
         
         // Simple eviction: remove oldest entry if at capacity
-        if cache.len() >= self.capacity && !cache.contains_key(&key)
-            && let Some((oldest_key, _)) = cache.iter()
-                .min_by_key(|(_, entry)| entry.last_accessed) {
-                let oldest_key = oldest_key.clone();
-                cache.remove(&oldest_key);
-            }
+        if cache.len() >= self.capacity.load(Ordering::Relaxed) && !cache.contains_key(&key) {
+            Self::evict_oldest(&mut cache);
+        }
         
         cache.insert(key, CacheEntry {
             value,
@@ -85,6 +87,22 @@ impl<K: Eq + std::hash::Hash + Clone, V: Clone> LruCache<K, V> {
         });
     }
 
+    /// Change the capacity, evicting the oldest entries over it
+    pub fn resize(&self, capacity: usize) {
+        self.capacity.store(capacity, Ordering::Relaxed);
+        let mut cache = self.cache.write().unwrap();
+        while cache.len() > capacity {
+            Self::evict_oldest(&mut cache);
+        }
+    }
+
+    fn evict_oldest(cache: &mut HashMap<K, CacheEntry<V>>) {
+        if let Some((oldest_key, _)) = cache.iter().min_by_key(|(_, entry)| entry.last_accessed) {
+            let oldest_key = oldest_key.clone();
+            cache.remove(&oldest_key);
+        }
+    }
+
     pub fn invalidate(&self, key: &K) {
         self.cache.write().unwrap().remove(key);
     }
@@ -92,4 +110,12 @@ impl<K: Eq + std::hash::Hash + Clone, V: Clone> LruCache<K, V> {
     pub fn clear(&self) {
         self.cache.write().unwrap().clear();
     }
+
+    pub fn len(&self) -> usize {
+        self.cache.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.read().unwrap().is_empty()
+    }
 }
\ No newline at end of file
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
 use sqlparser::ast::Statement;
 use crate::types::type_mapper::PgType;
@@ -21,7 +22,7 @@ pub struct CachedQuery {
 /// Cache for parsed queries to avoid re-parsing
 pub struct QueryCache {
     cache: Arc<RwLock<HashMap<u64, CacheEntry>>>,
-    capacity: usize,
+    capacity: AtomicUsize,
     ttl: Duration,
     metrics: Arc<RwLock<CacheMetrics>>,
 }
@@ -47,7 +48,7 @@ impl QueryCache {
     pub fn new(capacity: usize, ttl_seconds: u64) -> Self {
         Self {
             cache: Arc::new(RwLock::new(HashMap::with_capacity(capacity))),
-            capacity,
+            capacity: AtomicUsize::new(capacity),
             ttl: Duration::from_secs(ttl_seconds),
             metrics: Arc::new(RwLock::new(CacheMetrics::default())),
         }
@@ -85,13 +86,9 @@ impl QueryCache {
         let mut metrics = self.metrics.write().unwrap();
         
         // LRU eviction: remove least recently used entry if at capacity
-        if cache.len() >= self.capacity && !cache.contains_key(&fingerprint)
-            && let Some((key_to_remove, _)) = cache.iter()
-                .min_by_key(|(_, entry)| entry.last_accessed) {
-                let key_to_remove = *key_to_remove;
-                cache.remove(&key_to_remove);
-                metrics.evictions += 1;
-            }
+        if cache.len() >= self.capacity.load(Ordering::Relaxed) && !cache.contains_key(&fingerprint) {
+            Self::evict_lru(&mut cache, &mut metrics);
+        }
         
         cache.insert(fingerprint, CacheEntry {
             query,
@@ -101,6 +98,25 @@ impl QueryCache {
         });
     }
 
+    /// Change the capacity, evicting the least recently used entries over it
+    pub fn resize(&self, capacity: usize) {
+        self.capacity.store(capacity, Ordering::Relaxed);
+        let mut cache = self.cache.write().unwrap();
+        let mut metrics = self.metrics.write().unwrap();
+        while cache.len() > capacity {
+            Self::evict_lru(&mut cache, &mut metrics);
+        }
+    }
+
+    fn evict_lru(cache: &mut HashMap<u64, CacheEntry>, metrics: &mut CacheMetrics) {
+        if let Some((key_to_remove, _)) = cache.iter()
+            .min_by_key(|(_, entry)| entry.last_accessed) {
+            let key_to_remove = *key_to_remove;
+            cache.remove(&key_to_remove);
+            metrics.evictions += 1;
+        }
+    }
+
     /// Invalidate cache entries for a specific table
     pub fn invalidate_table(&self, table_name: &str) {
         let mut cache = self.cache.write().unwrap();
@@ -1,9 +1,9 @@
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
 use std::hash::{Hash, Hasher};
 use std::collections::hash_map::DefaultHasher;
-use crate::config::CONFIG;
 
 /// Cached result set for a query
 #[derive(Clone, Debug)]
@@ -67,7 +67,7 @@ pub struct ResultSetCache {
     /// Cache storage - using hash key for faster lookups
     cache: Arc<RwLock<HashMap<u64, CacheEntry>>>,
     /// Maximum number of cached results
-    max_entries: usize,
+    max_entries: AtomicUsize,
     /// Maximum size of result set to cache (in rows)
     max_result_rows: usize,
     /// Time-to-live for cached results
@@ -94,7 +94,7 @@ impl ResultSetCache {
     pub fn new(max_entries: usize, max_result_rows: usize, ttl_seconds: u64) -> Self {
         Self {
             cache: Arc::new(RwLock::new(HashMap::with_capacity(max_entries))),
-            max_entries,
+            max_entries: AtomicUsize::new(max_entries),
             max_result_rows,
             ttl: Duration::from_secs(ttl_seconds),
             stats: Arc::new(RwLock::new(CacheStats::default())),
@@ -157,13 +157,8 @@ impl ResultSetCache {
         let mut cache = self.cache.write().unwrap();
         
         // Evict entries if cache is full
-        if cache.len() >= self.max_entries {
-            // Simple eviction: remove oldest entry
-            if let Some((&oldest_key, _)) = cache.iter()
-                .min_by_key(|(_, entry)| entry.result.cached_at) {
-                cache.remove(&oldest_key);
-                self.stats.write().unwrap().evictions += 1;
-            }
+        if cache.len() >= self.max_entries.load(Ordering::Relaxed) {
+            self.evict_oldest(&mut cache);
         }
         
         let result = CachedResultSet {
@@ -179,6 +174,24 @@ impl ResultSetCache {
         true
     }
     
+    /// Change the maximum number of cached results, evicting the oldest over it
+    pub fn resize(&self, max_entries: usize) {
+        self.max_entries.store(max_entries, Ordering::Relaxed);
+        let mut cache = self.cache.write().unwrap();
+        while cache.len() > max_entries {
+            self.evict_oldest(&mut cache);
+        }
+    }
+
+    /// Simple eviction: remove oldest entry
+    fn evict_oldest(&self, cache: &mut HashMap<u64, CacheEntry>) {
+        if let Some((&oldest_key, _)) = cache.iter()
+            .min_by_key(|(_, entry)| entry.result.cached_at) {
+            cache.remove(&oldest_key);
+            self.stats.write().unwrap().evictions += 1;
+        }
+    }
+
     /// Clear the cache
     pub fn clear(&self) {
         self.cache.write().unwrap().clear();
@@ -241,15 +254,6 @@ fn estimate_result_size(result: &CachedResultSet) -> u64 {
     size
 }
 
-/// Global result cache instance
-static GLOBAL_RESULT_CACHE: std::sync::LazyLock<ResultSetCache> = 
-    std::sync::LazyLock::new(|| ResultSetCache::new(CONFIG.result_cache_size, 10000, CONFIG.result_cache_ttl));
-
-/// Get the global result cache
-pub fn global_result_cache() -> &'static ResultSetCache {
-    &GLOBAL_RESULT_CACHE
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
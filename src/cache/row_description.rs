@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
 use crate::protocol::FieldDescription;
 use tracing::{debug, info};
@@ -29,7 +30,7 @@ pub struct CachedRowDescription {
 /// RowDescription cache with LRU eviction and TTL
 pub struct RowDescriptionCache {
     cache: Arc<RwLock<HashMap<RowDescriptionKey, CachedRowDescription>>>,
-    capacity: usize,
+    capacity: AtomicUsize,
     ttl: Duration,
     stats: Arc<RwLock<RowDescriptionCacheStats>>,
 }
@@ -47,7 +48,7 @@ impl RowDescriptionCache {
     pub fn new(capacity: usize, ttl: Duration) -> Self {
         Self {
             cache: Arc::new(RwLock::new(HashMap::with_capacity(capacity))),
-            capacity,
+            capacity: AtomicUsize::new(capacity),
             ttl,
             stats: Arc::new(RwLock::new(RowDescriptionCacheStats::default())),
         }
@@ -104,15 +105,8 @@ impl RowDescriptionCache {
         let mut stats = self.stats.write().unwrap();
         
         // Check capacity and evict if necessary
-        if cache.len() >= self.capacity && !cache.contains_key(&key) {
-            // Find and remove the least recently used entry
-            if let Some((lru_key, _)) = cache.iter()
-                .min_by_key(|(_, entry)| (entry.hit_count, entry.created_at)) {
-                let lru_key = lru_key.clone();
-                cache.remove(&lru_key);
-                stats.evictions += 1;
-                debug!("Evicted LRU RowDescription cache entry");
-            }
+        if cache.len() >= self.capacity.load(Ordering::Relaxed) && !cache.contains_key(&key) {
+            Self::evict_lru(&mut cache, &mut stats);
         }
         
         let entry = CachedRowDescription {
@@ -126,6 +120,28 @@ impl RowDescriptionCache {
         debug!("Cached RowDescription for query: {}", &key.query[..50.min(key.query.len())]);
     }
 
+    /// Change the capacity, evicting the least used entries over it
+    pub fn resize(&self, capacity: usize) {
+        self.capacity.store(capacity, Ordering::Relaxed);
+        let mut cache = self.cache.write().unwrap();
+        let mut stats = self.stats.write().unwrap();
+        while cache.len() > capacity {
+            Self::evict_lru(&mut cache, &mut stats);
+        }
+        stats.entries = cache.len();
+    }
+
+    /// Find and remove the least recently used entry
+    fn evict_lru(cache: &mut HashMap<RowDescriptionKey, CachedRowDescription>, stats: &mut RowDescriptionCacheStats) {
+        if let Some((lru_key, _)) = cache.iter()
+            .min_by_key(|(_, entry)| (entry.hit_count, entry.created_at)) {
+            let lru_key = lru_key.clone();
+            cache.remove(&lru_key);
+            stats.evictions += 1;
+            debug!("Evicted LRU RowDescription cache entry");
+        }
+    }
+
     /// Clear all cache entries
     pub fn clear(&self) {
         let mut cache = self.cache.write().unwrap();
@@ -164,25 +180,6 @@ impl Clone for RowDescriptionCacheStats {
     }
 }
 
-use once_cell::sync::Lazy;
-
-/// Global RowDescription cache instance
-pub static GLOBAL_ROW_DESCRIPTION_CACHE: Lazy<RowDescriptionCache> = Lazy::new(|| {
-    // Default: 1000 entries, 10 minute TTL
-    let capacity = std::env::var("PGSQLITE_ROW_DESC_CACHE_SIZE")
-        .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(1000);
-    
-    let ttl_minutes = std::env::var("PGSQLITE_ROW_DESC_CACHE_TTL_MINUTES")
-        .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(10);
-    
-    info!("Initializing RowDescription cache with capacity {} and TTL {} minutes", capacity, ttl_minutes);
-    RowDescriptionCache::new(capacity, Duration::from_secs(ttl_minutes * 60))
-});
-
 #[cfg(test)]
 mod tests {
     use super::*;
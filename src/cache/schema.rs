@@ -57,6 +57,11 @@ impl SchemaCache {
 
     /// Cache schema for a table
     pub fn insert(&self, table_name: String, schema: TableSchema) {
+        if Self::schema_has_decimal(&schema) {
+            self.decimal_tables.write().unwrap().insert(table_name.clone());
+        } else {
+            self.decimal_tables.write().unwrap().remove(&table_name);
+        }
         let mut cache = self.cache.write().unwrap();
         
         cache.insert(table_name, CacheEntry {
@@ -68,6 +73,7 @@ impl SchemaCache {
     /// Invalidate cache for a specific table (e.g., after ALTER TABLE)
     pub fn invalidate(&self, table_name: &str) {
         self.cache.write().unwrap().remove(table_name);
+        self.decimal_tables.write().unwrap().remove(table_name);
     }
 
     /// Clear entire cache (e.g., after CREATE TABLE or DROP TABLE)
@@ -105,6 +111,17 @@ impl SchemaCache {
         self.decimal_tables.read().unwrap().contains(table_name)
     }
 
+    /// Check if a table has decimal columns, loading its schema first when it isn't cached
+    pub fn has_decimal_columns_or_load(&self, conn: &Connection, table_name: &str) -> bool {
+        self.get_or_load(conn, table_name).is_ok() && self.has_decimal_columns(table_name)
+    }
+
+    fn schema_has_decimal(schema: &TableSchema) -> bool {
+        schema.columns.iter().any(|col| {
+            col.pg_type == "numeric" || col.pg_oid == PgType::Numeric.to_oid()
+        })
+    }
+
     /// Preload all table schemas from the database
     pub fn preload_all_schemas(&self, conn: &Connection) -> Result<(), rusqlite::Error> {
         // Check if already loaded
@@ -131,11 +148,7 @@ impl SchemaCache {
         for table_name in table_names {
             if let Ok(schema) = self.load_table_schema_direct(conn, &table_name) {
                 // Check for decimal columns
-                let has_decimal = schema.columns.iter().any(|col| {
-                    col.pg_type == "numeric" || col.pg_oid == PgType::Numeric.to_oid()
-                });
-                
-                if has_decimal {
+                if Self::schema_has_decimal(&schema) {
                     decimal_tables_set.insert(table_name.clone());
                 }
 
@@ -221,12 +234,16 @@ impl SchemaCache {
                     crate::types::PgType::Int4range => "int4range",
                     crate::types::PgType::Int8range => "int8range",
                     crate::types::PgType::Numrange => "numrange",
+                    crate::types::PgType::Tsrange => "tsrange",
+                    crate::types::PgType::Tstzrange => "tstzrange",
+                    crate::types::PgType::Daterange => "daterange",
                     crate::types::PgType::Cidr => "cidr",
                     crate::types::PgType::Inet => "inet",
                     crate::types::PgType::Macaddr => "macaddr",
                     crate::types::PgType::Macaddr8 => "macaddr8",
                     crate::types::PgType::Bit => "bit",
                     crate::types::PgType::Varbit => "varbit",
+                    crate::types::PgType::Xml => "xml",
                     crate::types::PgType::Varchar => "varchar",
                     crate::types::PgType::Char => "char",
                     crate::types::PgType::Time => "time",
@@ -263,17 +280,10 @@ impl SchemaCache {
         }
 
         // If still not found, load this specific table
+        // Inserting also records whether it has decimal columns
         let schema = self.load_table_schema_direct(conn, table_name)?;
         self.insert(table_name.to_string(), schema.clone());
         
-        // Check for decimal columns and update bloom filter
-        let has_decimal = schema.columns.iter().any(|col| {
-            col.pg_type == "numeric" || col.pg_oid == PgType::Numeric.to_oid()
-        });
-        if has_decimal {
-            self.decimal_tables.write().unwrap().insert(table_name.to_string());
-        }
-        
         Ok(schema)
     }
     
@@ -1,14 +1,13 @@
 use std::collections::HashMap;
 use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use rusqlite::{Connection, Statement, Params};
-use once_cell::sync::Lazy;
-use crate::config::CONFIG;
 
 /// A pool of prepared SQLite statements for reuse
 /// This avoids the overhead of preparing the same statement multiple times
 pub struct StatementPool {
     statements: Mutex<HashMap<String, CachedStatement>>,
-    max_size: usize,
+    max_size: AtomicUsize,
 }
 
 /// Metadata about a cached prepared statement
@@ -28,23 +27,13 @@ struct CachedStatement {
     last_used: std::time::Instant,
 }
 
-/// Global statement pool instance
-static GLOBAL_STATEMENT_POOL: Lazy<StatementPool> = Lazy::new(|| {
-    StatementPool::new(CONFIG.statement_pool_size)
-});
-
 impl StatementPool {
     pub fn new(max_size: usize) -> Self {
         Self {
             statements: Mutex::new(HashMap::new()),
-            max_size,
+            max_size: AtomicUsize::new(max_size),
         }
     }
-
-    /// Get the global statement pool instance
-    pub fn global() -> &'static StatementPool {
-        &GLOBAL_STATEMENT_POOL
-    }
     
     /// Generate a normalized fingerprint for batch INSERT queries
     /// This allows caching the same prepared statement for different batch sizes
@@ -176,7 +165,7 @@ impl StatementPool {
     fn cache_metadata(&self, query: String, metadata: StatementMetadata) {
         if let Ok(mut statements) = self.statements.lock() {
             // Evict old entries if we're at capacity
-            if statements.len() >= self.max_size {
+            if statements.len() >= self.max_size.load(Ordering::Relaxed) {
                 self.evict_oldest(&mut statements);
             }
 
@@ -222,6 +211,16 @@ impl StatementPool {
         }
     }
 
+    /// Change the maximum number of cached statements, evicting the oldest over it
+    pub fn resize(&self, max_size: usize) {
+        self.max_size.store(max_size, Ordering::Relaxed);
+        if let Ok(mut statements) = self.statements.lock() {
+            while statements.len() > max_size {
+                self.evict_oldest(&mut statements);
+            }
+        }
+    }
+
     /// Update the last used time for a cached statement
     pub fn touch(&self, query: &str) {
         if let Ok(mut statements) = self.statements.lock()
@@ -242,12 +241,12 @@ impl StatementPool {
         if let Ok(statements) = self.statements.lock() {
             StatementPoolStats {
                 cached_statements: statements.len(),
-                max_capacity: self.max_size,
+                max_capacity: self.max_size.load(Ordering::Relaxed),
             }
         } else {
             StatementPoolStats {
                 cached_statements: 0,
-                max_capacity: self.max_size,
+                max_capacity: self.max_size.load(Ordering::Relaxed),
             }
         }
     }
@@ -59,7 +59,7 @@ impl TranslationCache {
         let size = cache.cache.read().unwrap().len();
         TranslationCacheStats {
             size,
-            capacity: cache.capacity,
+            capacity: cache.capacity.load(std::sync::atomic::Ordering::Relaxed),
         }
     }
 }
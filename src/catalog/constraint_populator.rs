@@ -3,30 +3,24 @@ use anyhow::Result;
 use tracing::{debug, info};
 use once_cell::sync::Lazy;
 use regex::Regex;
+use crate::metadata::oid_allocator::{OidAllocator, RELATION};
 
 // Pre-compiled regex patterns for constraint parsing
-static PK_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"(?i)\b(\w+)\s+[^,\)]*\bPRIMARY\s+KEY\b").unwrap()
+static CHECK_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\bCHECK\s*\(").unwrap()
 });
 
-static TABLE_PK_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"(?i)PRIMARY\s+KEY\s*\(\s*([^)]+)\s*\)").unwrap()
+/// `CONSTRAINT name` ending right before a CHECK
+static CONSTRAINT_NAME_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)\bCONSTRAINT\s+("(?:[^"]|"")+"|[\w$]+)\s*$"#).unwrap()
 });
 
-static UNIQUE_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"(?i)\b(\w+)\s+[^,\)]*\bUNIQUE\b").unwrap()
+static STRING_LITERAL_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"'(?:[^']|'')*'").unwrap()
 });
 
-static TABLE_UNIQUE_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"(?i)UNIQUE\s*\(\s*([^)]+)\s*\)").unwrap()
-});
-
-static CHECK_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"(?i)CHECK\s*\(\s*([^)]+)\s*\)").unwrap()
-});
-
-static NOT_NULL_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"(?i)\b(\w+)\s+[^,\)]*\bNOT\s+NULL\b").unwrap()
+static IDENTIFIER_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#""(?:[^"]|"")+"|[A-Za-z_][\w$]*"#).unwrap()
 });
 
 static DEFAULT_REGEX: Lazy<Regex> = Lazy::new(|| {
@@ -38,6 +32,10 @@ static TABLE_REGEX: Lazy<Regex> = Lazy::new(|| {
 });
 
 /// Populate PostgreSQL catalog tables with constraint information for a newly created table
+///
+/// Primary keys, unique and foreign key constraints and indexes are read from SQLite's
+/// pragmas by the pg_constraint and pg_index views; only CHECK constraints and column
+/// defaults have to be recorded here.
 pub fn populate_constraints_for_table(conn: &Connection, table_name: &str) -> Result<()> {
     info!("Populating constraints for table: {}", table_name);
     
@@ -45,18 +43,18 @@ pub fn populate_constraints_for_table(conn: &Connection, table_name: &str) -> Re
     let create_sql = get_create_table_sql(conn, table_name)?;
     debug!("CREATE TABLE SQL: {}", create_sql);
     
-    // Generate table OID (consistent with pg_class view)
-    let table_oid = generate_table_oid(table_name);
+    // The table's OID as pg_class reports it
+    let table_oid = OidAllocator::assign(conn, RELATION, table_name)?.to_string();
+    
+    // Parse and record CHECK constraints
+    store_check_constraints(conn, table_name, &create_sql)?;
     
-    // Parse and populate constraints
-    populate_table_constraints(conn, table_name, &create_sql, &table_oid)?;
+    // Defaults are recorded afresh, since ALTER TABLE can change or drop them
+    conn.execute("DELETE FROM pg_attrdef WHERE adrelid = ?1", [&table_oid])?;
     
     // Parse and populate column defaults
     populate_column_defaults(conn, table_name, &create_sql, &table_oid)?;
     
-    // Populate indexes (including those created by UNIQUE constraints)
-    populate_table_indexes(conn, table_name, &table_oid)?;
-    
     info!("Successfully populated constraints for table: {}", table_name);
     Ok(())
 }
@@ -68,8 +66,8 @@ fn get_create_table_sql(conn: &Connection, table_name: &str) -> Result<String> {
     Ok(sql)
 }
 
-/// Generate table OID using the same algorithm as the pg_class view
-fn generate_table_oid(name: &str) -> String {
+/// Generate a stable OID for a default or constraint from its name
+pub(crate) fn generate_table_oid(name: &str) -> String {
     use std::collections::hash_map::DefaultHasher;
     use std::hash::{Hash, Hasher};
     
@@ -78,32 +76,80 @@ fn generate_table_oid(name: &str) -> String {
     (((hasher.finish() & 0x7FFFFFFF) % 1000000 + 16384) as i32).to_string()
 }
 
-/// Populate pg_constraint table with constraint information
-fn populate_table_constraints(conn: &Connection, table_name: &str, create_sql: &str, table_oid: &str) -> Result<()> {
-    let constraints = parse_table_constraints(table_name, create_sql);
-    
-    for constraint in constraints {
+/// Record the CHECK constraints of a table, which SQLite has no pragma for
+///
+/// Unnamed constraints are named like PostgreSQL does: `<table>_<column>_check` when the
+/// expression uses a single column, `<table>_check` otherwise, numbered when taken.
+pub fn store_check_constraints(conn: &Connection, table_name: &str, create_sql: &str) -> Result<()> {
+    // A table recreated under the same name must not inherit the old constraints
+    conn.execute("DELETE FROM __pgsqlite_check_constraints WHERE tablename = ?1", [table_name])?;
+
+    let columns: Vec<String> = conn.prepare("SELECT name FROM pragma_table_info(?1)")?
+        .query_map([table_name], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    let mut names: Vec<String> = Vec::new();
+    let mut from = 0;
+    while let Some(check) = CHECK_REGEX.find_at(create_sql, from) {
+        let open = check.end() - 1;
+        let Some(close) = closing_paren(create_sql, open) else {
+            break;
+        };
+        from = close + 1;
+        let check_expr = create_sql[open + 1..close].trim();
+
+        let constraint_name = match CONSTRAINT_NAME_REGEX.captures(&create_sql[..check.start()]) {
+            Some(caps) => crate::query::sql_utils::unquote_identifier(&caps[1]),
+            None => {
+                let base = match single_column(check_expr, &columns) {
+                    Some(column) => format!("{table_name}_{column}_check"),
+                    None => format!("{table_name}_check"),
+                };
+                (0..).map(|n| if n == 0 { base.clone() } else { format!("{base}{n}") })
+                    .find(|name| !names.contains(name))
+                    .unwrap_or(base)
+            }
+        };
         conn.execute(
-            "INSERT OR IGNORE INTO pg_constraint (
-                oid, conname, contype, conrelid, conkey, consrc
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            [
-                &constraint.oid,
-                &constraint.name,
-                &constraint.contype,
-                table_oid,
-                &constraint.columns.join(","),
-                &constraint.definition,
-            ]
+            "INSERT INTO __pgsqlite_check_constraints (tablename, conname, consrc) VALUES (?1, ?2, ?3)",
+            [table_name, &constraint_name, &format!("CHECK ({check_expr})")],
         )?;
-        
-        debug!("Inserted constraint: {} (type: {}) for table: {}", 
-               constraint.name, constraint.contype, table_name);
+        debug!("Inserted CHECK constraint: {} for table: {}", constraint_name, table_name);
+        names.push(constraint_name);
     }
-    
+
     Ok(())
 }
 
+/// Index of the parenthesis closing the one at `open`, skipping string literals
+fn closing_paren(sql: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut in_string = false;
+    for (i, c) in sql[open..].char_indices() {
+        match c {
+            '\'' => in_string = !in_string,
+            '(' if !in_string => depth += 1,
+            ')' if !in_string => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The table column a CHECK expression uses, when it uses exactly one
+fn single_column<'a>(expr: &str, columns: &'a [String]) -> Option<&'a str> {
+    let expr = STRING_LITERAL_REGEX.replace_all(expr, "''");
+    let mut used = IDENTIFIER_REGEX.find_iter(&expr)
+        .map(|m| crate::query::sql_utils::unquote_identifier(m.as_str()))
+        .filter_map(|name| columns.iter().find(|column| column.eq_ignore_ascii_case(&name)));
+    let first = used.next()?;
+    used.all(|column| column == first).then_some(first.as_str())
+}
+
 /// Populate pg_attrdef table with column default information
 fn populate_column_defaults(conn: &Connection, table_name: &str, create_sql: &str, table_oid: &str) -> Result<()> {
     let defaults = parse_column_defaults(table_name, create_sql);
@@ -128,56 +174,6 @@ fn populate_column_defaults(conn: &Connection, table_name: &str, create_sql: &st
     Ok(())
 }
 
-/// Populate pg_index table with index information
-fn populate_table_indexes(conn: &Connection, table_name: &str, table_oid: &str) -> Result<()> {
-    // Get indexes for this table from sqlite_master
-    let mut stmt = conn.prepare("
-        SELECT name, sql FROM sqlite_master 
-        WHERE type = 'index' AND tbl_name = ?1 AND sql IS NOT NULL
-    ")?;
-    
-    let indexes = stmt.query_map([table_name], |row| {
-        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
-    })?;
-    
-    for index_result in indexes {
-        let (index_name, index_sql) = index_result?;
-        let index_oid = generate_table_oid(&index_name);
-        
-        // Parse index information
-        let is_unique = index_sql.to_uppercase().contains("UNIQUE");
-        let is_primary = index_name.contains("primary") || index_name.contains("pkey");
-        
-        conn.execute(
-            "INSERT OR IGNORE INTO pg_index (
-                indexrelid, indrelid, indnatts, indnkeyatts, 
-                indisunique, indisprimary
-            ) VALUES (?1, ?2, 1, 1, ?3, ?4)",
-            [
-                &index_oid,
-                table_oid,
-                &(is_unique as i32).to_string(),
-                &(is_primary as i32).to_string(),
-            ]
-        )?;
-        
-        debug!("Inserted index: {} (unique: {}, primary: {}) for table: {}", 
-               index_name, is_unique, is_primary, table_name);
-    }
-    
-    Ok(())
-}
-
-/// Information about a constraint
-#[derive(Debug)]
-struct ConstraintInfo {
-    oid: String,
-    name: String,
-    contype: String,
-    columns: Vec<String>,
-    definition: String,
-}
-
 /// Information about a column default
 #[derive(Debug)]
 struct DefaultInfo {
@@ -186,103 +182,6 @@ struct DefaultInfo {
     default_expr: String,
 }
 
-/// Parse table constraints from CREATE TABLE statement
-fn parse_table_constraints(table_name: &str, create_sql: &str) -> Vec<ConstraintInfo> {
-    let mut constraints = Vec::new();
-    
-    // Parse PRIMARY KEY constraints
-    // Look for both inline PRIMARY KEY and table-level PRIMARY KEY
-    for cap in PK_REGEX.captures_iter(create_sql) {
-        if let Some(column_name) = cap.get(1) {
-            constraints.push(ConstraintInfo {
-                oid: generate_table_oid(&format!("{table_name}_pkey")),
-                name: format!("{table_name}_pkey"),
-                contype: "p".to_string(),
-                columns: vec![column_name.as_str().to_string()],
-                definition: "PRIMARY KEY".to_string(),
-            });
-        }
-    }
-    
-    // Parse table-level PRIMARY KEY constraints
-    for cap in TABLE_PK_REGEX.captures_iter(create_sql) {
-        if let Some(columns_str) = cap.get(1) {
-            let columns: Vec<String> = columns_str.as_str()
-                .split(',')
-                .map(|s| s.trim().to_string())
-                .collect();
-            constraints.push(ConstraintInfo {
-                oid: generate_table_oid(&format!("{table_name}_pkey")),
-                name: format!("{table_name}_pkey"),
-                contype: "p".to_string(),
-                columns,
-                definition: "PRIMARY KEY".to_string(),
-            });
-        }
-    }
-    
-    // Parse UNIQUE constraints
-    for cap in UNIQUE_REGEX.captures_iter(create_sql) {
-        if let Some(column_name) = cap.get(1) {
-            constraints.push(ConstraintInfo {
-                oid: generate_table_oid(&format!("{}_{}_key", table_name, column_name.as_str())),
-                name: format!("{}_{}_key", table_name, column_name.as_str()),
-                contype: "u".to_string(),
-                columns: vec![column_name.as_str().to_string()],
-                definition: "UNIQUE".to_string(),
-            });
-        }
-    }
-    
-    // Parse table-level UNIQUE constraints
-    for cap in TABLE_UNIQUE_REGEX.captures_iter(create_sql) {
-        if let Some(columns_str) = cap.get(1) {
-            let columns: Vec<String> = columns_str.as_str()
-                .split(',')
-                .map(|s| s.trim().to_string())
-                .collect();
-            let constraint_name = format!("{}_{}_key", table_name, columns.join("_"));
-            constraints.push(ConstraintInfo {
-                oid: generate_table_oid(&constraint_name),
-                name: constraint_name,
-                contype: "u".to_string(),
-                columns,
-                definition: "UNIQUE".to_string(),
-            });
-        }
-    }
-    
-    // Parse CHECK constraints
-    for (i, cap) in CHECK_REGEX.captures_iter(create_sql).enumerate() {
-        if let Some(check_expr) = cap.get(1) {
-            let constraint_name = format!("{}_check{}", table_name, i + 1);
-            constraints.push(ConstraintInfo {
-                oid: generate_table_oid(&constraint_name),
-                name: constraint_name,
-                contype: "c".to_string(),
-                columns: vec![], // CHECK constraints don't have specific columns
-                definition: format!("CHECK ({})", check_expr.as_str()),
-            });
-        }
-    }
-    
-    // Parse NOT NULL constraints (treated as check constraints in PostgreSQL)
-    for cap in NOT_NULL_REGEX.captures_iter(create_sql) {
-        if let Some(column_name) = cap.get(1) {
-            let constraint_name = format!("{}_{}_not_null", table_name, column_name.as_str());
-            constraints.push(ConstraintInfo {
-                oid: generate_table_oid(&constraint_name),
-                name: constraint_name,
-                contype: "c".to_string(),
-                columns: vec![column_name.as_str().to_string()],
-                definition: format!("{} IS NOT NULL", column_name.as_str()),
-            });
-        }
-    }
-    
-    constraints
-}
-
 /// Parse column defaults from CREATE TABLE statement
 fn parse_column_defaults(table_name: &str, create_sql: &str) -> Vec<DefaultInfo> {
     let mut defaults = Vec::new();
@@ -0,0 +1,244 @@
+use crate::session::{DbResponse, SessionStorage};
+use crate::PgSqliteError;
+use sqlparser::ast::{Select, Expr, SelectItem};
+use tracing::debug;
+use std::collections::HashMap;
+use super::where_evaluator::WhereEvaluator;
+
+pub struct InformationSchemaColumnsHandler;
+
+impl InformationSchemaColumnsHandler {
+    pub async fn handle_query(
+        select: &Select,
+        db: &dyn SessionStorage,
+    ) -> Result<DbResponse, PgSqliteError> {
+        debug!("Handling information_schema.columns query");
+
+        let all_columns = vec![
+            "table_catalog".to_string(),
+            "table_schema".to_string(),
+            "table_name".to_string(),
+            "column_name".to_string(),
+            "ordinal_position".to_string(),
+            "column_default".to_string(),
+            "is_nullable".to_string(),
+            "data_type".to_string(),
+            "character_maximum_length".to_string(),
+            "numeric_precision".to_string(),
+            "numeric_scale".to_string(),
+            "udt_name".to_string(),
+            "is_identity".to_string(),
+            "identity_generation".to_string(),
+            "is_generated".to_string(),
+        ];
+
+        // Determine which columns are selected
+        let (selected_columns, selected_indices) = if select.projection.is_empty() ||
+            (select.projection.len() == 1 && matches!(&select.projection[0], SelectItem::Wildcard(_))) {
+            let indices: Vec<usize> = (0..all_columns.len()).collect();
+            (all_columns.clone(), indices)
+        } else {
+            let mut columns = Vec::new();
+            let mut indices = Vec::new();
+
+            for item in &select.projection {
+                match item {
+                    SelectItem::UnnamedExpr(Expr::Identifier(ident)) => {
+                        let col_name = ident.value.to_lowercase();
+                        if let Some(idx) = all_columns.iter().position(|c| c == &col_name) {
+                            columns.push(col_name);
+                            indices.push(idx);
+                        }
+                    }
+                    SelectItem::ExprWithAlias { expr: Expr::Identifier(ident), alias } => {
+                        let col_name = ident.value.to_lowercase();
+                        if let Some(idx) = all_columns.iter().position(|c| c == &col_name) {
+                            columns.push(alias.value.clone());
+                            indices.push(idx);
+                        }
+                    }
+                    _ => {} // Ignore other types for now
+                }
+            }
+            (columns, indices)
+        };
+
+        let column_mapping: HashMap<String, usize> = all_columns
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.clone(), i))
+            .collect();
+
+        let tables_response = db.query("SELECT name FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%' AND name NOT LIKE '__pgsqlite_%' ORDER BY name").await?;
+
+        let mut rows = Vec::new();
+        for table_row in &tables_response.rows {
+            if let Some(Some(table_name_bytes)) = table_row.first() {
+                let table_name = String::from_utf8_lossy(table_name_bytes).to_string();
+                add_table_columns(&table_name, db, &mut rows, select, &column_mapping, &selected_indices).await?;
+            }
+        }
+
+        let rows_affected = rows.len();
+        Ok(DbResponse {
+            columns: selected_columns,
+            rows,
+            rows_affected,
+        })
+    }
+}
+
+async fn add_table_columns(
+    table_name: &str,
+    db: &dyn SessionStorage,
+    rows: &mut Vec<Vec<Option<Vec<u8>>>>,
+    select: &Select,
+    column_mapping: &HashMap<String, usize>,
+    selected_indices: &[usize],
+) -> Result<(), PgSqliteError> {
+    let col_info = db.query(&format!("PRAGMA table_info({table_name})")).await?;
+
+    let type_map = query_string_pairs(
+        db,
+        &format!("SELECT column_name, pg_type FROM __pgsqlite_schema WHERE table_name = '{table_name}'"),
+    ).await;
+    let identity_map = query_string_pairs(
+        db,
+        &format!("SELECT column_name, identity_kind FROM __pgsqlite_identity_columns WHERE table_name = '{table_name}'"),
+    ).await;
+
+    for (idx, col_row) in col_info.rows.iter().enumerate() {
+        // PRAGMA table_info returns: cid, name, type, notnull, dflt_value, pk
+        let Some(Some(col_name_bytes)) = col_row.get(1) else { continue };
+        let col_name = String::from_utf8_lossy(col_name_bytes).to_string();
+        let text_at = |i: usize| col_row.get(i).and_then(|v| v.as_ref()).map(|v| String::from_utf8_lossy(v).to_string());
+
+        let sqlite_type = text_at(2).unwrap_or_else(|| "TEXT".to_string());
+        let is_primary_key = text_at(5).is_some_and(|v| v != "0");
+        let notnull = text_at(3).as_deref() == Some("1") || is_primary_key;
+
+        let pg_type = type_map.get(&col_name).cloned().unwrap_or(sqlite_type);
+        let (data_type, udt_name, char_length, precision, scale) = describe_pg_type(&pg_type);
+
+        let identity_kind = identity_map.get(&col_name).map(|k| k.as_str());
+        let column_default = match identity_kind {
+            Some("serial") => Some(format!("nextval('{table_name}_{col_name}_seq'::regclass)")),
+            Some(_) => None,
+            None => text_at(4),
+        };
+        let identity_generation = match identity_kind {
+            Some("always") => Some("ALWAYS".to_string()),
+            Some("by default") => Some("BY DEFAULT".to_string()),
+            _ => None,
+        };
+        let is_identity = if identity_generation.is_some() { "YES" } else { "NO" };
+
+        let full_row: Vec<Option<String>> = vec![
+            Some("main".to_string()),                                   // table_catalog
+            Some("public".to_string()),                                 // table_schema
+            Some(table_name.to_string()),                               // table_name
+            Some(col_name),                                             // column_name
+            Some((idx + 1).to_string()),                                // ordinal_position
+            column_default,                                             // column_default
+            Some(if notnull { "NO" } else { "YES" }.to_string()),       // is_nullable
+            Some(data_type),                                            // data_type
+            char_length.map(|v| v.to_string()),                         // character_maximum_length
+            precision.map(|v| v.to_string()),                           // numeric_precision
+            scale.map(|v| v.to_string()),                               // numeric_scale
+            Some(udt_name),                                             // udt_name
+            Some(is_identity.to_string()),                              // is_identity
+            identity_generation,                                        // identity_generation
+            Some("NEVER".to_string()),                                  // is_generated
+        ];
+
+        // Build row data for WHERE evaluation (NULLs are left out)
+        let row_data: HashMap<String, String> = column_mapping.iter()
+            .filter_map(|(name, &i)| full_row[i].clone().map(|v| (name.clone(), v)))
+            .collect();
+
+        let include_row = select.selection.as_ref()
+            .is_none_or(|selection| WhereEvaluator::evaluate(selection, &row_data, column_mapping));
+
+        if include_row {
+            rows.push(selected_indices.iter()
+                .map(|&i| full_row[i].clone().map(String::into_bytes))
+                .collect());
+        }
+    }
+
+    Ok(())
+}
+
+/// Run a two-column text query, returning an empty map if the metadata table is missing
+async fn query_string_pairs(db: &dyn SessionStorage, query: &str) -> HashMap<String, String> {
+    let mut map = HashMap::new();
+    if let Ok(response) = db.query(query).await {
+        for row in &response.rows {
+            if let (Some(Some(key)), Some(Some(value))) = (row.first(), row.get(1)) {
+                map.insert(String::from_utf8_lossy(key).to_string(), String::from_utf8_lossy(value).to_string());
+            }
+        }
+    }
+    map
+}
+
+/// Returns (data_type, udt_name, character_maximum_length, numeric_precision, numeric_scale)
+fn describe_pg_type(pg_type: &str) -> (String, String, Option<i32>, Option<i32>, Option<i32>) {
+    let upper = pg_type.trim().to_uppercase();
+    let (base, modifiers): (&str, Vec<i32>) = match upper.find('(') {
+        Some(pos) => (
+            upper[..pos].trim(),
+            upper[pos + 1..].trim_end_matches(')').split(',').filter_map(|m| m.trim().parse().ok()).collect(),
+        ),
+        None => (upper.as_str(), Vec::new()),
+    };
+
+    let (data_type, udt_name, precision) = match base {
+        "SMALLINT" | "INT2" | "SMALLSERIAL" | "SERIAL2" => ("smallint", "int2", Some(16)),
+        "INTEGER" | "INT" | "INT4" | "SERIAL" | "SERIAL4" => ("integer", "int4", Some(32)),
+        "BIGINT" | "INT8" | "BIGSERIAL" | "SERIAL8" => ("bigint", "int8", Some(64)),
+        "REAL" | "FLOAT4" => ("real", "float4", Some(24)),
+        "DOUBLE PRECISION" | "FLOAT8" | "FLOAT" => ("double precision", "float8", Some(53)),
+        "NUMERIC" | "DECIMAL" => ("numeric", "numeric", modifiers.first().copied()),
+        "BOOLEAN" | "BOOL" => ("boolean", "bool", None),
+        "VARCHAR" | "CHARACTER VARYING" => ("character varying", "varchar", None),
+        "CHAR" | "CHARACTER" | "BPCHAR" => ("character", "bpchar", None),
+        "BYTEA" | "BLOB" => ("bytea", "bytea", None),
+        "DATE" => ("date", "date", None),
+        "TIME" | "TIME WITHOUT TIME ZONE" => ("time without time zone", "time", None),
+        "TIMESTAMP" | "TIMESTAMP WITHOUT TIME ZONE" => ("timestamp without time zone", "timestamp", None),
+        "TIMESTAMPTZ" | "TIMESTAMP WITH TIME ZONE" => ("timestamp with time zone", "timestamptz", None),
+        "UUID" => ("uuid", "uuid", None),
+        "JSON" => ("json", "json", None),
+        "JSONB" => ("jsonb", "jsonb", None),
+        "TEXT" => ("text", "text", None),
+        // ENUMs and other user-defined types
+        other => return ("USER-DEFINED".to_string(), other.to_lowercase(), None, None, None),
+    };
+
+    let char_length = match data_type {
+        "character varying" | "character" => modifiers.first().copied(),
+        _ => None,
+    };
+    let scale = match data_type {
+        "smallint" | "integer" | "bigint" => Some(0),
+        "numeric" => modifiers.get(1).copied().or(precision.map(|_| 0)),
+        _ => None,
+    };
+
+    (data_type.to_string(), udt_name.to_string(), char_length, precision, scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_pg_type() {
+        assert_eq!(describe_pg_type("SERIAL"), ("integer".to_string(), "int4".to_string(), None, Some(32), Some(0)));
+        assert_eq!(describe_pg_type("bigserial").1, "int8");
+        assert_eq!(describe_pg_type("VARCHAR(50)"), ("character varying".to_string(), "varchar".to_string(), Some(50), None, None));
+        assert_eq!(describe_pg_type("NUMERIC(10,2)"), ("numeric".to_string(), "numeric".to_string(), None, Some(10), Some(2)));
+        assert_eq!(describe_pg_type("mood").0, "USER-DEFINED");
+    }
+}
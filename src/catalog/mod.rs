@@ -3,8 +3,10 @@ pub mod query_interceptor;
 pub mod pg_class;
 pub mod pg_attribute;
 pub mod pg_enum;
+pub mod information_schema_columns;
 pub mod system_functions;
 pub mod where_evaluator;
 pub mod constraint_populator;
+pub mod psql_compat;
 
 pub use query_interceptor::CatalogInterceptor;
\ No newline at end of file
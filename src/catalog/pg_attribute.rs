@@ -1,4 +1,4 @@
-use crate::session::db_handler::{DbHandler, DbResponse};
+use crate::session::{DbResponse, SessionStorage};
 use crate::PgSqliteError;
 use crate::types::PgType;
 use sqlparser::ast::{Select, Expr, Value as SqlValue, SelectItem};
@@ -11,9 +11,9 @@ pub struct PgAttributeHandler;
 impl PgAttributeHandler {
     pub async fn handle_query(
         select: &Select,
-        db: &DbHandler,
+        db: &dyn SessionStorage,
     ) -> Result<DbResponse, PgSqliteError> {
-        // println!("PG_ATTRIBUTE DEBUG: Handling pg_attribute query, DbHandler id: {:p}", db);
+        // println!("PG_ATTRIBUTE DEBUG: Handling pg_attribute query, storage: {:p}", db);
         debug!("Handling pg_attribute query");
         debug!("SELECT clause: {:?}", select);
         if let Some(selection) = &select.selection {
@@ -120,6 +120,9 @@ impl PgAttributeHandler {
                 }
             }
             
+            // Fields of composite types, attached to the relation in pg_type.typrelid
+            add_composite_type_attributes(db, &mut rows, select, &column_mapping, &selected_indices).await;
+            
             // Debug: Also check all tables without filters
             let all_tables_response = db.query("SELECT name, type FROM sqlite_master").await?;
             debug!("Total objects in sqlite_master: {}", all_tables_response.rows.len());
@@ -145,13 +148,13 @@ impl PgAttributeHandler {
 
 async fn add_table_attributes(
     table_name: &str,
-    db: &DbHandler,
+    db: &dyn SessionStorage,
     rows: &mut Vec<Vec<Option<Vec<u8>>>>,
     select: &Select,
     column_mapping: &HashMap<String, usize>,
     selected_indices: &[usize],
 ) -> Result<(), PgSqliteError> {
-    let table_oid = generate_oid_from_name(table_name);
+    let table_oid = super::pg_class::relation_oid(db, table_name).await?;
     
     debug!("Getting column info for table: {}", table_name);
     
@@ -179,6 +182,33 @@ async fn add_table_attributes(
         }
     }
     
+    // Columns declared with a composite type report the composite type's OID
+    let mut composite_oids = std::collections::HashMap::new();
+    if let Ok(composites) = db.query("SELECT type_name, type_oid FROM __pgsqlite_composite_types").await {
+        for row in &composites.rows {
+            if let (Some(Some(name_bytes)), Some(Some(oid_bytes))) = (row.first(), row.get(1))
+                && let Ok(oid) = String::from_utf8_lossy(oid_bytes).parse::<i32>() {
+                    composite_oids.insert(String::from_utf8_lossy(name_bytes).to_string(), oid);
+                }
+        }
+    }
+    
+    // SERIAL and GENERATED ... AS IDENTITY columns recorded at CREATE TABLE time
+    let identity_query = format!(
+        "SELECT column_name, identity_kind FROM __pgsqlite_identity_columns WHERE table_name = '{table_name}'"
+    );
+    let mut identity_map = std::collections::HashMap::new();
+    if let Ok(identity_info) = db.query(&identity_query).await {
+        for row in &identity_info.rows {
+            if let (Some(Some(col_bytes)), Some(Some(kind_bytes))) = (row.first(), row.get(1)) {
+                identity_map.insert(
+                    String::from_utf8_lossy(col_bytes).to_string(),
+                    String::from_utf8_lossy(kind_bytes).to_string(),
+                );
+            }
+        }
+    }
+    
     for (idx, col_row) in col_info.rows.iter().enumerate() {
         // PRAGMA table_info returns: cid, name, type, notnull, dflt_value, pk
         if let Some(Some(col_name_bytes)) = col_row.get(1) {
@@ -193,7 +223,16 @@ async fn add_table_attributes(
                 .map(|v| String::from_utf8_lossy(v) == "1")
                 .unwrap_or(false);
                 
-            let has_default = col_row.get(4).and_then(|v| v.as_ref()).is_some();
+            // SERIAL columns have an implicit nextval() default; identity columns are flagged
+            // through attidentity instead ('a' = ALWAYS, 'd' = BY DEFAULT)
+            let identity_kind = identity_map.get(col_name.as_ref()).map(|k| k.as_str());
+            let has_default = col_row.get(4).and_then(|v| v.as_ref()).is_some()
+                || identity_kind == Some("serial");
+            let attidentity = match identity_kind {
+                Some("always") => "a",
+                Some("by default") => "d",
+                _ => "",
+            };
             
             // Check if this column is a primary key (pk flag is at index 5)
             let is_primary_key = col_row.get(5)
@@ -210,7 +249,11 @@ async fn add_table_attributes(
             }
             
             // Determine PostgreSQL type
-            let (pg_type_oid, attlen, atttypmod) = if let Some(pg_type_str) = type_map.get(col_name.as_ref()) {
+            let composite_oid = type_map.get(col_name.as_ref())
+                .and_then(|pg_type_str| composite_oids.get(&pg_type_str.to_lowercase()));
+            let (pg_type_oid, attlen, atttypmod) = if let Some(&oid) = composite_oid {
+                (oid, -1, -1)
+            } else if let Some(pg_type_str) = type_map.get(col_name.as_ref()) {
                 // Check if this is an ENUM type
                 let type_upper = pg_type_str.to_uppercase();
                 let base_type = if let Some(paren_pos) = type_upper.find('(') {
@@ -281,7 +324,7 @@ async fn add_table_attributes(
             row_data.insert("attnotnull".to_string(), if notnull { "t" } else { "f" }.to_string());
             row_data.insert("atthasdef".to_string(), if has_default { "t" } else { "f" }.to_string());
             row_data.insert("atthasmissing".to_string(), "f".to_string());
-            row_data.insert("attidentity".to_string(), "".to_string());
+            row_data.insert("attidentity".to_string(), attidentity.to_string());
             row_data.insert("attgenerated".to_string(), "".to_string());
             row_data.insert("attisdropped".to_string(), "f".to_string());
             row_data.insert("attislocal".to_string(), "t".to_string());
@@ -314,7 +357,7 @@ async fn add_table_attributes(
                     Some(if notnull { b"t".to_vec() } else { b"f".to_vec() }),   // 12: attnotnull
                     Some(if has_default { b"t".to_vec() } else { b"f".to_vec() }), // 13: atthasdef
                     Some(b"f".to_vec()),                                // 14: atthasmissing
-                    Some(b"".to_vec()),                                 // 15: attidentity
+                    Some(attidentity.as_bytes().to_vec()),                 // 15: attidentity
                     Some(b"".to_vec()),                                 // 16: attgenerated
                     Some(b"f".to_vec()),                                // 17: attisdropped
                     Some(b"t".to_vec()),                                // 18: attislocal
@@ -340,6 +383,74 @@ async fn add_table_attributes(
     Ok(())
 }
 
+async fn add_composite_type_attributes(
+    db: &dyn SessionStorage,
+    rows: &mut Vec<Vec<Option<Vec<u8>>>>,
+    select: &Select,
+    column_mapping: &HashMap<String, usize>,
+    selected_indices: &[usize],
+) {
+    // Nested composite fields point at their own type
+    let Ok(attributes) = db.query(
+        "SELECT t.relation_oid, a.attname, a.atttype, a.attnum, n.type_oid
+         FROM __pgsqlite_composite_attributes a
+         JOIN __pgsqlite_composite_types t ON t.type_oid = a.type_oid
+         LEFT JOIN __pgsqlite_composite_types n ON n.type_name = a.atttype
+         ORDER BY t.type_name, a.attnum"
+    ).await else {
+        return; // No composite types yet
+    };
+    
+    for attribute in &attributes.rows {
+        let text = |i: usize| attribute.get(i).and_then(|v| v.as_ref()).map(|v| String::from_utf8_lossy(v).to_string());
+        let (Some(relation_oid), Some(attname), Some(atttype), Some(attnum)) = (text(0), text(1), text(2), text(3)) else {
+            continue;
+        };
+        let (atttypid, attlen, atttypmod) = match text(4) {
+            Some(nested_oid) => (nested_oid.parse().unwrap_or(0), -1, -1),
+            None => parse_pg_type(&atttype),
+        };
+        
+        let values = [
+            ("attrelid", relation_oid),
+            ("attname", attname),
+            ("atttypid", atttypid.to_string()),
+            ("attstattarget", "-1".to_string()),
+            ("attlen", attlen.to_string()),
+            ("attnum", attnum),
+            ("attndims", "0".to_string()),
+            ("attcacheoff", "-1".to_string()),
+            ("atttypmod", atttypmod.to_string()),
+            ("attbyval", "f".to_string()),
+            ("attstorage", "p".to_string()),
+            ("attalign", "i".to_string()),
+            ("attnotnull", "f".to_string()),
+            ("atthasdef", "f".to_string()),
+            ("atthasmissing", "f".to_string()),
+            ("attidentity", "".to_string()),
+            ("attgenerated", "".to_string()),
+            ("attisdropped", "f".to_string()),
+            ("attislocal", "t".to_string()),
+            ("attinhcount", "0".to_string()),
+            ("attcollation", "0".to_string()),
+        ];
+        let row_data: HashMap<String, String> = values.iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect();
+        
+        let include_row = select.selection.as_ref()
+            .is_none_or(|selection| WhereEvaluator::evaluate(selection, &row_data, column_mapping));
+        if include_row {
+            // attacl, attoptions, attfdwoptions and attmissingval are NULL
+            let full_row: Vec<Option<Vec<u8>>> = values.into_iter()
+                .map(|(_, value)| Some(value.into_bytes()))
+                .chain(std::iter::repeat_n(None, 4))
+                .collect();
+            rows.push(selected_indices.iter().map(|&idx| full_row.get(idx).cloned().flatten()).collect());
+        }
+    }
+}
+
 fn extract_table_filter(select: &Select) -> Option<String> {
     // Look for WHERE attrelid = 'schema.table'::regclass or similar
     if let Some(selection) = &select.selection
@@ -385,9 +496,9 @@ fn parse_pg_type(pg_type_str: &str) -> (i32, i16, i32) {
     // Map base type to OID and attlen
     let (oid, attlen) = match base_type.as_str() {
         "BOOL" | "BOOLEAN" => (PgType::Bool.to_oid(), 1),
-        "INT2" | "SMALLINT" => (PgType::Int2.to_oid(), 2),
-        "INT4" | "INT" | "INTEGER" => (PgType::Int4.to_oid(), 4),
-        "INT8" | "BIGINT" => (PgType::Int8.to_oid(), 8),
+        "INT2" | "SMALLINT" | "SMALLSERIAL" | "SERIAL2" => (PgType::Int2.to_oid(), 2),
+        "INT4" | "INT" | "INTEGER" | "SERIAL" | "SERIAL4" => (PgType::Int4.to_oid(), 4),
+        "INT8" | "BIGINT" | "BIGSERIAL" | "SERIAL8" => (PgType::Int8.to_oid(), 8),
         "FLOAT4" | "REAL" => (PgType::Float4.to_oid(), 4),
         "FLOAT8" | "DOUBLE PRECISION" => (PgType::Float8.to_oid(), 8),
         "TEXT" => (PgType::Text.to_oid(), -1),
@@ -491,13 +602,3 @@ fn map_sqlite_to_pg_type(sqlite_type: &str) -> (i32, i16, i32) {
     
     (oid, attlen, -1) // atttypmod = -1 for no modifier
 }
-
-fn generate_oid_from_name(name: &str) -> u32 {
-    // Generate a stable OID from name using a simple hash
-    // Start at 16384 to avoid conflicts with system OIDs
-    let mut hash = 0u32;
-    for byte in name.bytes() {
-        hash = hash.wrapping_mul(31).wrapping_add(byte as u32);
-    }
-    16384 + (hash % 1000000)
-}
\ No newline at end of file
@@ -1,21 +1,25 @@
-use crate::session::db_handler::{DbHandler, DbResponse};
+use crate::session::{DbResponse, SessionStorage};
 use crate::PgSqliteError;
-use sqlparser::ast::{Select, SelectItem, Expr};
+use sqlparser::ast::{Select, SelectItem, Expr, FunctionArg, FunctionArgExpr, FunctionArguments};
 use tracing::debug;
 use std::collections::HashMap;
 use super::where_evaluator::WhereEvaluator;
+use crate::metadata::{Namespaces, OidAllocator};
+use crate::metadata::namespaces::{PG_CATALOG_OID, PUBLIC_OID};
 
 pub struct PgClassHandler;
 
 impl PgClassHandler {
     pub async fn handle_query(
         select: &Select,
-        db: &DbHandler,
+        db: &dyn SessionStorage,
     ) -> Result<DbResponse, PgSqliteError> {
         debug!("Handling pg_class query");
         
-        // Get list of tables from SQLite
-        let tables_response = db.query("SELECT name FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%' AND name NOT LIKE '__pgsqlite_%'").await?;
+        // Get list of tables and views from SQLite, leaving out the views emulating the catalogs
+        let tables_response = db.query("SELECT name, type FROM sqlite_master WHERE (type = 'table' OR (type = 'view' AND name NOT LIKE 'pg\\_%' ESCAPE '\\')) AND name NOT LIKE 'sqlite_%' AND name NOT LIKE '__pgsqlite_%'").await?;
+        
+        let schemas = schemas(db).await;
         
         // Define all available columns - PostgreSQL has 33 columns in pg_class
         let all_columns = vec![
@@ -55,7 +59,16 @@ impl PgClassHandler {
         ];
         
         // Determine which columns to return based on projection
-        let (columns, column_indices) = Self::get_projected_columns(select, &all_columns);
+        let (columns, column_indices, schema_names) = Self::get_projected_columns(select, &all_columns);
+        // Project only the requested columns, relnamespace::regnamespace as the schema name
+        let project = |full_row: &[Option<Vec<u8>>]| -> Vec<Option<Vec<u8>>> {
+            column_indices.iter().zip(&schema_names)
+                .map(|(&idx, &schema_name)| match &full_row[idx] {
+                    Some(oid) if schema_name => Some(namespace_name(&schemas, &String::from_utf8_lossy(oid)).into_bytes()),
+                    value => value.clone(),
+                })
+                .collect()
+        };
         
         // Create column mapping for WHERE evaluation (uses all columns)
         let column_mapping: HashMap<String, usize> = all_columns
@@ -66,29 +79,34 @@ impl PgClassHandler {
         
         let mut rows = Vec::new();
         
-        // Process each table
+        // Process each table and view
         for table_row in &tables_response.rows {
             if let Some(Some(table_name_bytes)) = table_row.first() {
                 let table_name = String::from_utf8_lossy(table_name_bytes);
+                let relkind = match table_row.get(1) {
+                    Some(Some(kind)) if kind.as_slice() == b"view" => "v",
+                    _ => "r",
+                };
+                
+                let (relnamespace, relname) = Namespaces::split(&schemas, &table_name);
                 
                 // Get column count for this table
-                let col_count_query = format!("PRAGMA table_info({table_name})");
+                let col_count_query = format!("PRAGMA table_info(\"{}\")", table_name.replace('"', "\"\""));
                 let col_info = db.query(&col_count_query).await?;
                 let relnatts = col_info.rows.len() as i16;
                 
-                // Generate a stable OID from table name
-                let oid = generate_oid_from_name(&table_name);
+                let oid = relation_oid(db, &table_name).await?;
                 
                 // Check if table has indexes
-                let index_query = format!("PRAGMA index_list({table_name})");
+                let index_query = format!("PRAGMA index_list(\"{}\")", table_name.replace('"', "\"\""));
                 let index_info = db.query(&index_query).await?;
                 let relhasindex = !index_info.rows.is_empty();
                 
                 // Build row data for WHERE evaluation
                 let mut row_data = HashMap::new();
                 row_data.insert("oid".to_string(), oid.to_string());
-                row_data.insert("relname".to_string(), table_name.to_string());
-                row_data.insert("relnamespace".to_string(), "2200".to_string());
+                row_data.insert("relname".to_string(), relname.to_string());
+                row_data.insert("relnamespace".to_string(), relnamespace.to_string());
                 row_data.insert("reltype".to_string(), (oid + 1).to_string());
                 row_data.insert("reloftype".to_string(), "0".to_string());
                 row_data.insert("relowner".to_string(), "10".to_string());
@@ -102,7 +120,7 @@ impl PgClassHandler {
                 row_data.insert("relhasindex".to_string(), if relhasindex { "t" } else { "f" }.to_string());
                 row_data.insert("relisshared".to_string(), "f".to_string());
                 row_data.insert("relpersistence".to_string(), "p".to_string());
-                row_data.insert("relkind".to_string(), "r".to_string());
+                row_data.insert("relkind".to_string(), relkind.to_string());
                 row_data.insert("relnatts".to_string(), relnatts.to_string());
                 row_data.insert("relchecks".to_string(), "0".to_string());
                 row_data.insert("relhasrules".to_string(), "f".to_string());
@@ -133,8 +151,8 @@ impl PgClassHandler {
                     // Build full row with all columns (33 total)
                     let full_row = vec![
                         Some(oid.to_string().into_bytes()),                    // oid
-                        Some(table_name.to_string().into_bytes()),            // relname
-                        Some("2200".to_string().into_bytes()),                 // relnamespace (public schema)
+                        Some(relname.to_string().into_bytes()),               // relname
+                        Some(relnamespace.to_string().into_bytes()),           // relnamespace
                         Some((oid + 1).to_string().into_bytes()),             // reltype
                         Some("0".to_string().into_bytes()),                    // reloftype
                         Some("10".to_string().into_bytes()),                   // relowner (postgres user)
@@ -148,7 +166,7 @@ impl PgClassHandler {
                         Some(if relhasindex { b"t".to_vec() } else { b"f".to_vec() }), // relhasindex
                         Some(b"f".to_vec()),                                // relisshared
                         Some(b"p".to_vec()),                                // relpersistence (permanent)
-                        Some(b"r".to_vec()),                                // relkind (regular table)
+                        Some(relkind.as_bytes().to_vec()),                  // relkind (regular table or view)
                         Some(relnatts.to_string().into_bytes()),              // relnatts
                         Some("0".to_string().into_bytes()),                    // relchecks
                         Some(b"f".to_vec()),                                // relhasrules
@@ -167,18 +185,17 @@ impl PgClassHandler {
                         None,                                                   // relpartbound (NULL)
                     ];
                     
-                    // Project only the requested columns
-                    let projected_row: Vec<Option<Vec<u8>>> = column_indices.iter()
-                        .map(|&idx| full_row[idx].clone())
-                        .collect();
-                    
-                    rows.push(projected_row);
+                    rows.push(project(&full_row));
                 }
             }
         }
         
-        // Also add indexes to pg_class
-        let indexes_response = db.query("SELECT name, tbl_name FROM sqlite_master WHERE type='index' AND name NOT LIKE 'sqlite_%'").await?;
+        // Also add indexes to pg_class, including the implicit primary key and unique ones
+        // Databases without the catalog views only list the explicitly created indexes
+        let indexes_response = match db.query("SELECT indexname, tablename FROM __pgsqlite_index_catalog").await {
+            Ok(response) => response,
+            Err(_) => db.query("SELECT name, tbl_name FROM sqlite_master WHERE type='index' AND name NOT LIKE 'sqlite_%'").await?,
+        };
         
         for index_row in &indexes_response.rows {
             if let (Some(Some(index_name_bytes)), Some(Some(table_name_bytes))) = 
@@ -186,14 +203,14 @@ impl PgClassHandler {
                 let index_name = String::from_utf8_lossy(index_name_bytes);
                 let table_name = String::from_utf8_lossy(table_name_bytes);
                 
-                let index_oid = generate_oid_from_name(&index_name);
-                let _table_oid = generate_oid_from_name(&table_name);
+                let index_oid = relation_oid(db, &index_name).await?;
+                let (relnamespace, relname) = Namespaces::split(&schemas, &index_name);
                 
                 // Build row data for WHERE evaluation
                 let mut row_data = HashMap::new();
                 row_data.insert("oid".to_string(), index_oid.to_string());
-                row_data.insert("relname".to_string(), index_name.to_string());
-                row_data.insert("relnamespace".to_string(), "2200".to_string());
+                row_data.insert("relname".to_string(), relname.to_string());
+                row_data.insert("relnamespace".to_string(), relnamespace.to_string());
                 row_data.insert("reltype".to_string(), "0".to_string());
                 row_data.insert("reloftype".to_string(), "0".to_string());
                 row_data.insert("relowner".to_string(), "10".to_string());
@@ -238,8 +255,8 @@ impl PgClassHandler {
                     // Build full row with all columns (33 total)
                     let full_row = vec![
                         Some(index_oid.to_string().into_bytes()),              // oid
-                        Some(index_name.to_string().into_bytes()),            // relname
-                        Some("2200".to_string().into_bytes()),                 // relnamespace (public schema)
+                        Some(relname.to_string().into_bytes()),               // relname
+                        Some(relnamespace.to_string().into_bytes()),           // relnamespace
                         Some("0".to_string().into_bytes()),                    // reltype (0 for indexes)
                         Some("0".to_string().into_bytes()),                    // reloftype
                         Some("10".to_string().into_bytes()),                   // relowner (postgres user)
@@ -272,12 +289,7 @@ impl PgClassHandler {
                         None,                                                   // relpartbound (NULL)
                     ];
                     
-                    // Project only the requested columns
-                    let projected_row: Vec<Option<Vec<u8>>> = column_indices.iter()
-                        .map(|&idx| full_row[idx].clone())
-                        .collect();
-                    
-                    rows.push(projected_row);
+                    rows.push(project(&full_row));
                 }
             }
         }
@@ -291,10 +303,12 @@ impl PgClassHandler {
         })
     }
     
-    /// Determine which columns to return based on the SELECT projection
-    fn get_projected_columns(select: &Select, all_columns: &[String]) -> (Vec<String>, Vec<usize>) {
+    /// Determine which columns to return based on the SELECT projection, and which
+    /// of them are cast to regnamespace
+    fn get_projected_columns(select: &Select, all_columns: &[String]) -> (Vec<String>, Vec<usize>, Vec<bool>) {
         let mut columns = Vec::new();
         let mut column_indices = Vec::new();
+        let mut schema_names = Vec::new();
         
         
         // Check if it's SELECT *
@@ -314,6 +328,7 @@ impl PgClassHandler {
                             if let Some(idx) = all_columns.iter().position(|c| c == &col_name) {
                                 columns.push(col_name);
                                 column_indices.push(idx);
+                                schema_names.push(Self::is_regnamespace(expr));
                             }
                         }
                     }
@@ -323,6 +338,7 @@ impl PgClassHandler {
                             if let Some(idx) = all_columns.iter().position(|c| c == &col_name) {
                                 columns.push(alias.value.clone());
                                 column_indices.push(idx);
+                                schema_names.push(Self::is_regnamespace(expr));
                             }
                         }
                     }
@@ -342,7 +358,13 @@ impl PgClassHandler {
             }
         }
         
-        (columns, column_indices)
+        schema_names.resize(columns.len(), false);
+        (columns, column_indices, schema_names)
+    }
+
+    /// `regnamespace(col)`, which the cast translator makes of `col::regnamespace`
+    fn is_regnamespace(expr: &Expr) -> bool {
+        matches!(expr, Expr::Function(func) if func.name.to_string().eq_ignore_ascii_case("regnamespace"))
     }
     
     /// Extract column name from an expression
@@ -357,17 +379,52 @@ impl PgClassHandler {
                 // Handle CAST expressions like CAST(oid AS TEXT)
                 Self::extract_column_name(expr)
             }
+            Expr::Function(func) if Self::is_regnamespace(expr) => match &func.args {
+                FunctionArguments::List(list) => match list.args.as_slice() {
+                    [FunctionArg::Unnamed(FunctionArgExpr::Expr(arg))] => Self::extract_column_name(arg),
+                    _ => None,
+                },
+                _ => None,
+            },
             _ => None,
         }
     }
 }
 
-fn generate_oid_from_name(name: &str) -> u32 {
-    // Generate a stable OID from name using a simple hash
-    // Start at 16384 to avoid conflicts with system OIDs
-    let mut hash = 0u32;
-    for byte in name.bytes() {
-        hash = hash.wrapping_mul(31).wrapping_add(byte as u32);
+/// The OID pg_class reports for a table, view or index
+/// The schemas created with CREATE SCHEMA, for splitting relation names into schema and name
+async fn schemas(db: &dyn SessionStorage) -> Vec<(String, i64)> {
+    let kind = crate::metadata::oid_allocator::NAMESPACE;
+    // Databases that predate the OID table have no schemas of their own
+    let Ok(response) = db.query(&format!("SELECT name, oid FROM __pgsqlite_oids WHERE kind = '{kind}'")).await else {
+        return Vec::new();
+    };
+    response.rows.iter()
+        .filter_map(|row| {
+            let name = String::from_utf8_lossy(row.first()?.as_ref()?).into_owned();
+            let oid = String::from_utf8_lossy(row.get(1)?.as_ref()?).parse().ok()?;
+            Some((name, oid))
+        })
+        .collect()
+}
+
+/// The name of the schema with this OID, as regnamespace prints it
+fn namespace_name(schemas: &[(String, i64)], oid: &str) -> String {
+    match oid.parse::<i64>() {
+        Ok(PG_CATALOG_OID) => "pg_catalog".to_string(),
+        Ok(PUBLIC_OID) => "public".to_string(),
+        Ok(oid) => schemas.iter()
+            .find(|(_, schema_oid)| *schema_oid == oid)
+            .map(|(schema, _)| schema.clone())
+            .unwrap_or_else(|| oid.to_string()),
+        Err(_) => oid.to_string(),
     }
-    16384 + (hash % 1000000)
+}
+
+pub(crate) async fn relation_oid(db: &dyn SessionStorage, name: &str) -> Result<u32, PgSqliteError> {
+    let response = db.query(&format!("SELECT pgsqlite_relation_oid('{}')", name.replace('\'', "''"))).await?;
+    Ok(response.rows.first()
+        .and_then(|row| row.first().cloned().flatten())
+        .and_then(|oid| String::from_utf8_lossy(&oid).parse().ok())
+        .unwrap_or_else(|| OidAllocator::seed(name) as u32))
 }
\ No newline at end of file
@@ -1,4 +1,4 @@
-use crate::session::db_handler::{DbHandler, DbResponse};
+use crate::session::{DbResponse, SessionStorage};
 use crate::metadata::EnumMetadata;
 use sqlparser::ast::{Select, SelectItem, Expr};
 use tracing::{debug, info};
@@ -8,7 +8,7 @@ pub struct PgEnumHandler;
 
 impl PgEnumHandler {
     /// Handle queries to pg_enum table
-    pub async fn handle_query(select: &Select, db: &DbHandler) -> Result<DbResponse, String> {
+    pub async fn handle_query(select: &Select, db: &dyn SessionStorage) -> Result<DbResponse, String> {
         info!("Handling pg_enum query");
         debug!("pg_enum query selection: {:?}", select.selection);
         
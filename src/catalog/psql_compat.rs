@@ -0,0 +1,956 @@
+use crate::metadata::Namespaces;
+use crate::session::{DbResponse, SessionState, SessionStorage};
+use crate::PgSqliteError;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashMap;
+use tracing::debug;
+use super::system_functions::SystemFunctions;
+
+/// Role every object is owned by, as reported by pg_get_userbyid()
+const OWNER: &str = "postgres";
+
+/// Whether an __pgsqlite_index_catalog row is the index its table was last clustered on
+const CLUSTERED: &str = "EXISTS(SELECT 1 FROM __pgsqlite_clustered_indexes c WHERE c.table_name = tablename AND c.index_name = indexname)";
+
+/// Catalogs with no SQLite counterpart; describe queries against them find nothing
+const UNMODELLED_CATALOGS: [&str; 9] = [
+    "pg_inherits",
+    "pg_policy",
+    "pg_statistic_ext",
+    "pg_publication",
+    "pg_trigger",
+    "pg_rewrite",
+    "pg_partitioned_table",
+    "pg_foreign_table",
+    "pg_sequence",
+];
+
+static WHITESPACE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\s+").unwrap());
+
+// `c.relname OPERATOR(pg_catalog.~) '^(orders)$'` and the `~` / `!~` forms of older psql releases
+static NAME_FILTER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b([a-z]\w*\.[a-z]\w*)\s+(OPERATOR\(pg_catalog\.~\)|!~|~)\s+'((?:[^']|'')*)'").unwrap()
+});
+
+static RELKIND_LIST: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\bc\.relkind\s+IN\s+\(([^)]*)\)").unwrap());
+
+static OID_LITERAL: Lazy<Regex> = Lazy::new(|| Regex::new(r"'(\d+)'").unwrap());
+
+static ALIASED_ITEM: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(?is)^(.*\S)\s+AS\s+(?:"([^"]+)"|(\w+))$"#).unwrap());
+
+static COLUMN_ITEM: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?:\w+\.)?(\w+)$").unwrap());
+
+static REFERENCED_TABLE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(?i)(\bREFERENCES\s+)"?(\w+)"?"#).unwrap());
+
+static FUNCTION_ITEM: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?:pg_catalog\.)?(\w+)\s*\(").unwrap());
+
+static VIEW_BODY: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)^\s*CREATE\s+(?:TEMP\w*\s+)?VIEW\s+(?:IF\s+NOT\s+EXISTS\s+)?\S+\s+AS\s+(.*?);?\s*$").unwrap()
+});
+
+/// The psql describe queries answered here
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DescribeQuery {
+    Databases,
+    Relations,
+    RelationLookup,
+    RelationInfo,
+    Columns,
+    IndexInfo,
+    TableIndexes,
+    CheckConstraints,
+    ForeignKeys,
+    ReferencedBy,
+    ViewDefinition,
+    Functions,
+    Schemas,
+    Roles,
+    Unmodelled,
+}
+
+/// Values of one result row, looked up by column name or alias.
+///
+/// Entries are matched in order, so function names that also appear inside
+/// subqueries go first.
+type Row = Vec<(&'static str, Option<String>)>;
+
+/// A table, view or index as psql sees it
+#[derive(Debug, Clone)]
+struct Relation {
+    oid: String,
+    /// The name within its schema
+    name: String,
+    /// The schema pg_namespace has it in
+    namespace: String,
+    kind: char,
+    /// The indexed table, for indexes, which share its schema
+    table: Option<String>,
+}
+
+impl Relation {
+    /// Catalog objects keep PostgreSQL's reserved pg_ prefix and belong in pg_catalog
+    fn schema(&self) -> &str {
+        if self.namespace == "public" && self.table.as_deref().unwrap_or(&self.name).starts_with("pg_") {
+            "pg_catalog"
+        } else {
+            &self.namespace
+        }
+    }
+
+    /// The name SQLite stores the relation under
+    fn sqlite_name(&self) -> String {
+        Namespaces::qualify(&self.namespace, &self.name)
+    }
+
+    /// The name SQLite stores the indexed table under, for indexes
+    fn sqlite_table(&self) -> Option<String> {
+        self.table.as_ref().map(|table| Namespaces::qualify(&self.namespace, table))
+    }
+
+    /// The name as regclass prints it: qualified unless the search_path finds it
+    fn display_name(&self, visible: &[Relation]) -> String {
+        if visible.iter().any(|relation| relation.oid == self.oid) {
+            self.name.clone()
+        } else {
+            format!("{}.{}", self.schema(), self.name)
+        }
+    }
+}
+
+/// One column of a table or view
+#[derive(Debug, Clone)]
+struct Attribute {
+    num: usize,
+    name: String,
+    type_name: String,
+    not_null: bool,
+    default: Option<String>,
+    identity: &'static str,
+}
+
+/// A `column ~ 'pattern'` condition from the WHERE clause
+struct NameFilter {
+    column: String,
+    negated: bool,
+    pattern: Option<Regex>,
+}
+
+/// Answers the catalog queries psql sends for its describe meta-commands.
+///
+/// `\l`, `\dt`, `\d table`, `\di`, `\df`, `\dn` and `\du` rely on catalog columns,
+/// regclass casts and functions of row values that the generic catalog path cannot
+/// evaluate, so each query shape psql issues is recognized here and answered from
+/// the SQLite schema with the columns psql reads by position.
+pub struct PsqlCompat;
+
+impl PsqlCompat {
+    /// Answer a psql describe query, or None for anything else
+    pub async fn handle_query(query: &str, db: &dyn SessionStorage, session: Option<&SessionState>) -> Option<Result<DbResponse, PgSqliteError>> {
+        if !query.contains("pg_catalog.") {
+            return None;
+        }
+        let sql = WHITESPACE.replace_all(query.trim().trim_end_matches(';'), " ").into_owned();
+        let lower = sql.to_ascii_lowercase();
+        let kind = Self::classify(&lower)?;
+        debug!("Answering psql {:?} query", kind);
+        // pg_catalog is searched before the schemas of the search_path
+        let mut search_path = vec!["pg_catalog".to_string()];
+        match session {
+            Some(session) => search_path.extend(session.search_path().await.into_iter()
+                .map(|schema| if schema == "$user" { session.user.clone() } else { schema })),
+            None => search_path.extend(crate::query::SchemaHandler::parse_search_path(crate::query::schema_handler::DEFAULT_SEARCH_PATH)),
+        }
+        Some(Self::answer(kind, &sql, &lower, db, &search_path).await)
+    }
+
+    fn classify(q: &str) -> Option<DescribeQuery> {
+        let kind = if q.contains("from pg_catalog.pg_database d") {
+            DescribeQuery::Databases
+        } else if q.contains("case c.relkind when 'r' then 'table'") {
+            DescribeQuery::Relations
+        } else if q.starts_with("select c.oid, n.nspname, c.relname from pg_catalog.pg_class c") {
+            DescribeQuery::RelationLookup
+        } else if q.starts_with("select c.relchecks, c.relkind") {
+            DescribeQuery::RelationInfo
+        } else if q.starts_with("select a.attname") && q.contains("from pg_catalog.pg_attribute a") {
+            DescribeQuery::Columns
+        } else if q.starts_with("select i.indisunique, i.indisprimary") {
+            DescribeQuery::IndexInfo
+        } else if q.starts_with("select c2.relname, i.indisprimary") {
+            DescribeQuery::TableIndexes
+        } else if q.contains("from pg_catalog.pg_constraint r") && q.contains("r.contype = 'c'") {
+            DescribeQuery::CheckConstraints
+        } else if q.contains("from pg_catalog.pg_constraint r") && q.contains("r.contype = 'f'") {
+            DescribeQuery::ForeignKeys
+        } else if q.contains("from pg_catalog.pg_constraint c") && q.contains("confrelid") {
+            DescribeQuery::ReferencedBy
+        } else if q.contains("pg_catalog.pg_get_viewdef(") {
+            DescribeQuery::ViewDefinition
+        } else if q.contains("pg_catalog.pg_get_function_result(p.oid)") {
+            DescribeQuery::Functions
+        } else if q.contains("from pg_catalog.pg_namespace n") && q.contains("as \"name\"") {
+            DescribeQuery::Schemas
+        } else if q.contains("from pg_catalog.pg_roles r") {
+            DescribeQuery::Roles
+        } else if UNMODELLED_CATALOGS.iter().any(|catalog| q.contains(&format!("pg_catalog.{catalog} "))) {
+            DescribeQuery::Unmodelled
+        } else {
+            return None;
+        };
+        Some(kind)
+    }
+
+    async fn answer(kind: DescribeQuery, sql: &str, q: &str, db: &dyn SessionStorage, search_path: &[String]) -> Result<DbResponse, PgSqliteError> {
+        let filters = Self::name_filters(sql);
+        let oid = OID_LITERAL.captures(sql).map(|caps| caps[1].to_string()).unwrap_or_default();
+        let rows = match kind {
+            DescribeQuery::Databases => Self::databases(db, &filters).await?,
+            DescribeQuery::Relations => Self::list_relations(db, q, &filters, search_path).await?,
+            DescribeQuery::RelationLookup => Self::lookup_relations(db, &filters, search_path).await?,
+            DescribeQuery::RelationInfo => Self::relation_info(db, &oid).await?,
+            DescribeQuery::Columns => Self::columns(db, &oid).await?,
+            DescribeQuery::IndexInfo => Self::index_info(db, &oid).await?,
+            DescribeQuery::TableIndexes => Self::table_indexes(db, &oid).await?,
+            DescribeQuery::CheckConstraints => Self::check_constraints(db, &oid).await?,
+            DescribeQuery::ForeignKeys => Self::foreign_keys(db, &oid, false, search_path).await?,
+            DescribeQuery::ReferencedBy => Self::foreign_keys(db, &oid, true, search_path).await?,
+            DescribeQuery::ViewDefinition => Self::view_definition(db, &oid).await?,
+            DescribeQuery::Functions => Self::functions(db, q, &filters).await?,
+            DescribeQuery::Schemas => Self::schemas(db, &filters).await?,
+            DescribeQuery::Roles => Self::roles(&filters),
+            DescribeQuery::Unmodelled => Vec::new(),
+        };
+
+        let items = Self::select_items(sql);
+        // Counting rows of a catalog that is always empty still yields a row
+        if kind == DescribeQuery::Unmodelled && items.len() == 1 && items[0].to_ascii_lowercase().ends_with("count(*)") {
+            return Ok(DbResponse {
+                columns: vec!["count".to_string()],
+                rows: vec![vec![Some(b"0".to_vec())]],
+                rows_affected: 1,
+            });
+        }
+
+        let columns = items.iter().map(|item| Self::column_name(item)).collect();
+        let rows: Vec<Vec<Option<Vec<u8>>>> = rows.iter()
+            .map(|row| items.iter().map(|item| Self::value(item, row).map(String::into_bytes)).collect())
+            .collect();
+        let rows_affected = rows.len();
+        Ok(DbResponse { columns, rows, rows_affected })
+    }
+
+    /// \l
+    async fn databases(db: &dyn SessionStorage, filters: &[NameFilter]) -> Result<Vec<Row>, PgSqliteError> {
+        let response = db.query(
+            "SELECT datname, datcollate, datctype, \
+                 pg_size_pretty(CASE WHEN datname = pgsqlite_datname() THEN page_count * page_size \
+                                     ELSE pg_database_size(datname) END) \
+             FROM pg_database, pragma_page_count(), pragma_page_size()"
+        ).await?;
+        Ok(response.rows.iter()
+            .filter(|row| Self::passes(filters, "d.datname", &Self::text(row, 0).unwrap_or_default()))
+            .map(|row| vec![
+                ("name", Self::text(row, 0)),
+                ("owner", Some(OWNER.to_string())),
+                ("encoding", Some("UTF8".to_string())),
+                ("collate", Self::text(row, 1)),
+                ("ctype", Self::text(row, 2)),
+                ("size", Self::text(row, 3)),
+                ("tablespace", Some("pg_default".to_string())),
+            ])
+            .collect())
+    }
+
+    /// \dt, \di, \dv and \d without a pattern
+    async fn list_relations(db: &dyn SessionStorage, q: &str, filters: &[NameFilter], search_path: &[String]) -> Result<Vec<Row>, PgSqliteError> {
+        let relkinds: Vec<char> = RELKIND_LIST.captures(q)
+            .map(|caps| caps[1].split(',').filter_map(|kind| kind.trim().trim_matches('\'').chars().next()).collect())
+            .unwrap_or_default();
+        let hide_catalog = q.contains("n.nspname <> 'pg_catalog'");
+        let descriptions = Self::descriptions(db).await?;
+
+        let mut relations: Vec<Relation> = Self::listed_relations(db, filters, search_path).await?.into_iter()
+            .filter(|relation| relkinds.contains(&relation.kind))
+            .filter(|relation| !(hide_catalog && relation.schema() == "pg_catalog"))
+            .filter(|relation| Self::passes(filters, "c.relname", &relation.name))
+            .filter(|relation| Self::passes(filters, "n.nspname", relation.schema()))
+            .collect();
+        relations.sort_by(|a, b| (a.schema(), &a.name).cmp(&(b.schema(), &b.name)));
+
+        Ok(relations.into_iter()
+            .map(|relation| {
+                let (type_name, access_method) = match relation.kind {
+                    'r' => ("table", Some("heap")),
+                    'v' => ("view", None),
+                    _ => ("index", Some("btree")),
+                };
+                vec![
+                    ("schema", Some(relation.schema().to_string())),
+                    ("name", Some(relation.name.clone())),
+                    ("type", Some(type_name.to_string())),
+                    ("owner", Some(OWNER.to_string())),
+                    ("table", relation.table.clone()),
+                    ("persistence", Some("permanent".to_string())),
+                    ("access method", access_method.map(str::to_string)),
+                    ("description", descriptions.get(&(relation.oid.clone(), 0)).cloned()),
+                ]
+            })
+            .collect())
+    }
+
+    /// The OID lookup \d starts with
+    async fn lookup_relations(db: &dyn SessionStorage, filters: &[NameFilter], search_path: &[String]) -> Result<Vec<Row>, PgSqliteError> {
+        let mut relations: Vec<Relation> = Self::listed_relations(db, filters, search_path).await?.into_iter()
+            .filter(|relation| Self::passes(filters, "c.relname", &relation.name))
+            .filter(|relation| Self::passes(filters, "n.nspname", relation.schema()))
+            .collect();
+        relations.sort_by(|a, b| (a.schema(), &a.name).cmp(&(b.schema(), &b.name)));
+
+        Ok(relations.into_iter()
+            .map(|relation| vec![
+                ("oid", Some(relation.oid.clone())),
+                ("nspname", Some(relation.schema().to_string())),
+                ("relname", Some(relation.name)),
+            ])
+            .collect())
+    }
+
+    /// The pg_class flags \d uses to decide which footers to print
+    async fn relation_info(db: &dyn SessionStorage, oid: &str) -> Result<Vec<Row>, PgSqliteError> {
+        let Some(relation) = Self::relation_by_oid(db, oid).await? else {
+            return Ok(Vec::new());
+        };
+        let name = Self::quote(&relation.sqlite_name());
+        let checks = db.query(&format!("SELECT count(*) FROM __pgsqlite_check_constraints WHERE tablename = '{name}'")).await?;
+        let indexes = db.query(&format!("SELECT count(*) FROM __pgsqlite_index_catalog WHERE tablename = '{name}'")).await?;
+        // PostgreSQL enforces foreign keys with triggers, and psql only looks for them when there are some
+        let foreign_keys = db.query(&format!(
+            "SELECT count(*) FROM pg_constraint WHERE contype = 'f' AND (conrelid = '{oid}' OR confrelid = '{oid}')"
+        )).await?;
+        let has_index = Self::text(&indexes.rows[0], 0).is_some_and(|count| count != "0");
+        let has_triggers = relation.kind == 'r' && Self::text(&foreign_keys.rows[0], 0).is_some_and(|count| count != "0");
+
+        Ok(vec![vec![
+            ("relchecks", Self::text(&checks.rows[0], 0)),
+            ("relkind", Some(relation.kind.to_string())),
+            ("relhasindex", Some(Self::flag(has_index))),
+            ("relhasrules", Some(Self::flag(false))),
+            ("relhastriggers", Some(Self::flag(has_triggers))),
+            ("relrowsecurity", Some(Self::flag(false))),
+            ("relforcerowsecurity", Some(Self::flag(false))),
+            ("relispartition", Some(Self::flag(false))),
+            ("reltablespace", Some("0".to_string())),
+            ("reloftype", Some(String::new())),
+            ("relpersistence", Some("p".to_string())),
+            ("relreplident", Some("d".to_string())),
+            ("amname", match relation.kind {
+                'r' => Some("heap".to_string()),
+                'i' => Some("btree".to_string()),
+                _ => None,
+            }),
+        ]])
+    }
+
+    /// The column list of \d, for tables, views and indexes
+    async fn columns(db: &dyn SessionStorage, oid: &str) -> Result<Vec<Row>, PgSqliteError> {
+        let Some(relation) = Self::relation_by_oid(db, oid).await? else {
+            return Ok(Vec::new());
+        };
+        let descriptions = Self::descriptions(db).await?;
+
+        if relation.kind == 'i' {
+            let table = relation.sqlite_table().unwrap_or_default();
+            let attributes = Self::attributes(db, &table).await?;
+            let columns = db.query(&format!(
+                "SELECT columns FROM __pgsqlite_index_catalog WHERE indexname = '{}'",
+                Self::quote(&relation.sqlite_name())
+            )).await?;
+            let columns = columns.rows.first().and_then(|row| Self::text(row, 0)).unwrap_or_default();
+            return Ok(columns.split(", ")
+                .filter(|column| !column.is_empty())
+                .map(|column| {
+                    let type_name = attributes.iter()
+                        .find(|attribute| attribute.name == column)
+                        .map(|attribute| attribute.type_name.clone());
+                    vec![
+                        ("format_type", type_name),
+                        ("pg_get_indexdef", Some(column.to_string())),
+                        ("attname", Some(column.to_string())),
+                        ("indexdef", Some(column.to_string())),
+                        ("is_key", Some("yes".to_string())),
+                        ("attnotnull", Some(Self::flag(false))),
+                        ("attidentity", Some(String::new())),
+                        ("attgenerated", Some(String::new())),
+                        ("attstorage", Some("p".to_string())),
+                    ]
+                })
+                .collect());
+        }
+
+        // Sequences of serial columns are named after the table within its schema
+        let sequence_prefix = (relation.namespace != "public")
+            .then(|| (format!("'{}_", relation.sqlite_name()), format!("'{}.{}_", relation.namespace, relation.name)));
+        Ok(Self::attributes(db, &relation.sqlite_name()).await?.into_iter()
+            .map(|mut attribute| {
+                if let (Some((stored, shown)), Some(default)) = (&sequence_prefix, &attribute.default) {
+                    attribute.default = Some(default.replacen(stored.as_str(), shown, 1));
+                }
+                let storage = if matches!(attribute.type_name.as_str(), "integer" | "smallint" | "bigint" | "real" | "double precision" | "boolean" | "date")
+                    || attribute.type_name.starts_with("time") {
+                    "p"
+                } else {
+                    "x"
+                };
+                vec![
+                    ("pg_get_expr", attribute.default.clone()),
+                    ("format_type", Some(attribute.type_name.clone())),
+                    ("col_description", descriptions.get(&(relation.oid.clone(), attribute.num as i64)).cloned()),
+                    ("attname", Some(attribute.name.clone())),
+                    ("attnotnull", Some(Self::flag(attribute.not_null))),
+                    ("attcollation", None),
+                    ("attidentity", Some(attribute.identity.to_string())),
+                    ("attgenerated", Some(String::new())),
+                    ("attstorage", Some(storage.to_string())),
+                    ("attcompression", Some(String::new())),
+                    ("attstattarget", None),
+                ]
+            })
+            .collect())
+    }
+
+    /// The index properties \d prints for an index
+    async fn index_info(db: &dyn SessionStorage, oid: &str) -> Result<Vec<Row>, PgSqliteError> {
+        let Some(relation) = Self::relation_by_oid(db, oid).await? else {
+            return Ok(Vec::new());
+        };
+        let response = db.query(&format!(
+            "SELECT pgsqlite_relname(tablename), origin, is_unique, {CLUSTERED} FROM __pgsqlite_index_catalog WHERE indexname = '{}'",
+            Self::quote(&relation.sqlite_name())
+        )).await?;
+        Ok(response.rows.iter()
+            .map(|row| vec![
+                ("pg_get_expr", None),
+                ("indisunique", Some(Self::flag(Self::text(row, 2).as_deref() == Some("1")))),
+                ("indisprimary", Some(Self::flag(Self::text(row, 1).as_deref() == Some("pk")))),
+                ("indisclustered", Some(Self::flag(Self::text(row, 3).as_deref() == Some("1")))),
+                ("indisvalid", Some(Self::flag(true))),
+                ("condeferrable", Some(Self::flag(false))),
+                ("condeferred", Some(Self::flag(false))),
+                ("indisreplident", Some(Self::flag(false))),
+                ("indnullsnotdistinct", Some(Self::flag(false))),
+                ("amname", Some("btree".to_string())),
+                ("relname", Self::text(row, 0)),
+            ])
+            .collect())
+    }
+
+    /// The "Indexes:" footer of \d
+    async fn table_indexes(db: &dyn SessionStorage, oid: &str) -> Result<Vec<Row>, PgSqliteError> {
+        let Some(relation) = Self::relation_by_oid(db, oid).await? else {
+            return Ok(Vec::new());
+        };
+        let response = db.query(&format!(
+            "SELECT pgsqlite_relname(indexname), origin, is_unique, indexdef, columns, {CLUSTERED} FROM __pgsqlite_index_catalog \
+             WHERE tablename = '{}' ORDER BY origin = 'pk' DESC, indexname",
+            Self::quote(&relation.sqlite_name())
+        )).await?;
+        Ok(response.rows.iter()
+            .map(|row| {
+                let origin = Self::text(row, 1).unwrap_or_default();
+                let columns = Self::text(row, 4).unwrap_or_default();
+                let (contype, constraintdef) = match origin.as_str() {
+                    "pk" => (Some("p".to_string()), Some(format!("PRIMARY KEY ({columns})"))),
+                    "u" => (Some("u".to_string()), Some(format!("UNIQUE ({columns})"))),
+                    _ => (None, None),
+                };
+                vec![
+                    ("pg_get_indexdef", Self::text(row, 3)),
+                    ("pg_get_constraintdef", constraintdef),
+                    ("relname", Self::text(row, 0)),
+                    ("indisprimary", Some(Self::flag(origin == "pk"))),
+                    ("indisunique", Some(Self::flag(Self::text(row, 2).as_deref() == Some("1")))),
+                    ("indisclustered", Some(Self::flag(Self::text(row, 5).as_deref() == Some("1")))),
+                    ("indisvalid", Some(Self::flag(true))),
+                    ("condeferrable", contype.as_ref().map(|_| Self::flag(false))),
+                    ("condeferred", contype.as_ref().map(|_| Self::flag(false))),
+                    ("contype", contype),
+                    ("indisreplident", Some(Self::flag(false))),
+                    ("reltablespace", Some("0".to_string())),
+                ]
+            })
+            .collect())
+    }
+
+    /// The "Check constraints:" footer of \d
+    async fn check_constraints(db: &dyn SessionStorage, oid: &str) -> Result<Vec<Row>, PgSqliteError> {
+        let Some(relation) = Self::relation_by_oid(db, oid).await? else {
+            return Ok(Vec::new());
+        };
+        let response = db.query(&format!(
+            "SELECT conname, consrc FROM __pgsqlite_check_constraints WHERE tablename = '{}' ORDER BY conname",
+            Self::quote(&relation.sqlite_name())
+        )).await?;
+        Ok(response.rows.iter()
+            .map(|row| vec![
+                ("pg_get_constraintdef", Self::text(row, 1)),
+                ("conname", Self::text(row, 0)),
+            ])
+            .collect())
+    }
+
+    /// The "Foreign-key constraints:" and "Referenced by:" footers of \d
+    async fn foreign_keys(db: &dyn SessionStorage, oid: &str, referencing: bool, search_path: &[String]) -> Result<Vec<Row>, PgSqliteError> {
+        let column = if referencing { "confrelid" } else { "conrelid" };
+        let response = db.query(&format!(
+            "SELECT conname, consrc, conrelid FROM pg_constraint WHERE contype = 'f' AND {column} = '{oid}' ORDER BY conname"
+        )).await?;
+        if response.rows.is_empty() {
+            return Ok(Vec::new());
+        }
+        let relations = Self::relations(db).await?;
+        let visible = Self::visible(&relations, search_path);
+        let tables: Vec<&Relation> = relations.iter().filter(|relation| relation.kind == 'r').collect();
+        Ok(response.rows.iter()
+            .map(|row| {
+                let table = Self::text(row, 2).and_then(|conrelid| tables.iter().find(|relation| relation.oid == conrelid).copied());
+                // Constraint names and the referenced table are stored schema-qualified
+                let conname = Self::text(row, 0).map(|conname| match table {
+                    Some(table) if table.namespace != "public" => conname
+                        .strip_prefix(&format!("{}__", table.namespace))
+                        .map(str::to_string)
+                        .unwrap_or(conname),
+                    _ => conname,
+                });
+                let condef = Self::text(row, 1).map(|condef| REFERENCED_TABLE.replace(&condef, |caps: &regex::Captures| {
+                    let name = tables.iter()
+                        .find(|relation| relation.sqlite_name() == caps[2])
+                        .map(|relation| relation.display_name(&visible))
+                        .unwrap_or_else(|| caps[2].to_string());
+                    format!("{}{name}", &caps[1])
+                }).into_owned());
+                vec![
+                    ("pg_get_constraintdef", condef.clone()),
+                    ("sametable", Some(Self::flag(true))),
+                    ("conname", conname),
+                    ("condef", condef),
+                    ("ontable", table.map(|table| table.display_name(&visible))),
+                ]
+            })
+            .collect())
+    }
+
+    /// The "View definition:" footer of \d+
+    async fn view_definition(db: &dyn SessionStorage, oid: &str) -> Result<Vec<Row>, PgSqliteError> {
+        let Some(relation) = Self::relation_by_oid(db, oid).await? else {
+            return Ok(vec![vec![("pg_get_viewdef", None)]]);
+        };
+        let response = db.query(&format!(
+            "SELECT sql FROM sqlite_master WHERE type = 'view' AND name = '{}'",
+            Self::quote(&relation.sqlite_name())
+        )).await?;
+        let definition = response.rows.first()
+            .and_then(|row| Self::text(row, 0))
+            .and_then(|sql| VIEW_BODY.captures(&sql).map(|caps| format!(" {};", caps[1].trim())));
+        Ok(vec![vec![("pg_get_viewdef", definition)]])
+    }
+
+    /// \df
+    async fn functions(db: &dyn SessionStorage, q: &str, filters: &[NameFilter]) -> Result<Vec<Row>, PgSqliteError> {
+        let response = db.query("SELECT proname, prorettype, proargtypes, provariadic, prokind, provolatile, pronamespace, prolang, prosrc FROM pg_proc").await?;
+        let hide_catalog = q.contains("n.nspname <> 'pg_catalog'");
+        let type_name = |oid: &str| SystemFunctions::format_type_name(oid.parse().unwrap_or(0), None);
+
+        let mut functions: Vec<(String, String, Row)> = Vec::new();
+        for row in &response.rows {
+            // Registered functions live in pg_catalog, created ones in public
+            let name = Self::text(row, 0).unwrap_or_default();
+            let schema = if Self::text(row, 6).as_deref() == Some("2200") { "public" } else { "pg_catalog" };
+            if (hide_catalog && schema == "pg_catalog") || !Self::passes(filters, "p.proname", &name) || !Self::passes(filters, "n.nspname", schema) {
+                continue;
+            }
+            let mut arguments: Vec<String> = Self::text(row, 2).unwrap_or_default()
+                .split_whitespace()
+                .map(type_name)
+                .collect();
+            if Self::text(row, 3).is_some_and(|variadic| variadic != "0")
+                && let Some(last) = arguments.last_mut() {
+                    *last = format!("VARIADIC {last}");
+                }
+            let arguments = arguments.join(", ");
+            let kind = match Self::text(row, 4).as_deref() {
+                Some("a") => "agg",
+                Some("w") => "window",
+                _ => "func",
+            };
+            let volatility = match Self::text(row, 5).as_deref() {
+                Some("i") => "immutable",
+                Some("s") => "stable",
+                _ => "volatile",
+            };
+            let language = match Self::text(row, 7).as_deref() {
+                Some("12") => "internal",
+                Some("14") => "sql",
+                _ => "plpgsql",
+            };
+            functions.push((name.clone(), arguments.clone(), vec![
+                ("schema", Some(schema.to_string())),
+                ("name", Some(name.clone())),
+                ("result data type", Self::text(row, 1).map(|oid| type_name(&oid))),
+                ("argument data types", Some(arguments)),
+                ("type", Some(kind.to_string())),
+                ("volatility", Some(volatility.to_string())),
+                ("parallel", Some("safe".to_string())),
+                ("owner", Some(OWNER.to_string())),
+                ("security", Some("invoker".to_string())),
+                ("language", Some(language.to_string())),
+                ("source code", Self::text(row, 8)),
+                ("internal name", (language == "internal").then_some(name)),
+            ]));
+        }
+        functions.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+        Ok(functions.into_iter().map(|(_, _, row)| row).collect())
+    }
+
+    /// \dn
+    async fn schemas(db: &dyn SessionStorage, filters: &[NameFilter]) -> Result<Vec<Row>, PgSqliteError> {
+        let response = db.query("SELECT nspname FROM pg_namespace ORDER BY nspname").await?;
+        Ok(response.rows.iter()
+            .filter_map(|row| Self::text(row, 0))
+            .filter(|schema| Self::passes(filters, "n.nspname", schema))
+            .map(|schema| vec![
+                ("name", Some(schema)),
+                ("owner", Some(OWNER.to_string())),
+            ])
+            .collect())
+    }
+
+    /// \du and \dg
+    fn roles(filters: &[NameFilter]) -> Vec<Row> {
+        if !Self::passes(filters, "r.rolname", OWNER) {
+            return Vec::new();
+        }
+        vec![vec![
+            ("rolname", Some(OWNER.to_string())),
+            ("rolsuper", Some(Self::flag(true))),
+            ("rolinherit", Some(Self::flag(true))),
+            ("rolcreaterole", Some(Self::flag(true))),
+            ("rolcreatedb", Some(Self::flag(true))),
+            ("rolcanlogin", Some(Self::flag(true))),
+            ("rolconnlimit", Some("-1".to_string())),
+            ("rolvaliduntil", None),
+            ("memberof", Some("{}".to_string())),
+            ("rolreplication", Some(Self::flag(true))),
+            ("rolbypassrls", Some(Self::flag(true))),
+        ]]
+    }
+
+    /// Tables and views from pg_class plus every index, including the implicit ones,
+    /// with their schemas
+    async fn relations(db: &dyn SessionStorage) -> Result<Vec<Relation>, PgSqliteError> {
+        let response = db.query(
+            "SELECT c.oid, c.relname, c.relkind, NULL, n.nspname FROM pg_class c \
+                 LEFT JOIN pg_namespace n ON n.oid = c.relnamespace WHERE c.relkind IN ('r', 'v') \
+             UNION ALL \
+             SELECT i.indexrelid, pgsqlite_relname(i.indexname), 'i', pgsqlite_relname(i.tablename), n.nspname \
+             FROM __pgsqlite_index_catalog i LEFT JOIN pg_namespace n ON n.oid = pgsqlite_relnamespace(i.indexname)"
+        ).await?;
+        Ok(response.rows.iter()
+            .map(|row| Relation {
+                oid: Self::text(row, 0).unwrap_or_default(),
+                name: Self::text(row, 1).unwrap_or_default(),
+                namespace: Self::text(row, 4).unwrap_or_else(|| "public".to_string()),
+                kind: Self::text(row, 2).and_then(|kind| kind.chars().next()).unwrap_or('r'),
+                table: Self::text(row, 3),
+            })
+            .collect())
+    }
+
+    /// The relations a query lists: without a schema pattern, psql restricts them with
+    /// pg_table_is_visible() (stripped by the translator) to those the search_path finds
+    async fn listed_relations(db: &dyn SessionStorage, filters: &[NameFilter], search_path: &[String]) -> Result<Vec<Relation>, PgSqliteError> {
+        let relations = Self::relations(db).await?;
+        Ok(if Self::has_schema_pattern(filters) {
+            relations
+        } else {
+            Self::visible(&relations, search_path)
+        })
+    }
+
+    fn has_schema_pattern(filters: &[NameFilter]) -> bool {
+        filters.iter().any(|filter| filter.column == "n.nspname" && !filter.negated)
+    }
+
+    /// Whether the answer to a query depends on the session's search_path
+    pub fn depends_on_search_path(query: &str) -> bool {
+        let lower = WHITESPACE.replace_all(query.trim(), " ").to_ascii_lowercase();
+        matches!(Self::classify(&lower), Some(DescribeQuery::Relations | DescribeQuery::RelationLookup))
+            && !Self::has_schema_pattern(&Self::name_filters(&lower))
+    }
+
+    /// The relations an unqualified name finds: those of the first schema on the
+    /// search_path with a relation of that name
+    fn visible(relations: &[Relation], search_path: &[String]) -> Vec<Relation> {
+        let rank = |relation: &Relation| search_path.iter().position(|schema| schema == relation.schema());
+        relations.iter()
+            .filter(|relation| rank(relation).is_some_and(|own| {
+                !relations.iter().any(|other| other.name == relation.name && rank(other).is_some_and(|earlier| earlier < own))
+            }))
+            .cloned()
+            .collect()
+    }
+
+    /// OIDs are derived from names and can collide, in which case tables and views win
+    async fn relation_by_oid(db: &dyn SessionStorage, oid: &str) -> Result<Option<Relation>, PgSqliteError> {
+        let matching: Vec<Relation> = Self::relations(db).await?.into_iter()
+            .filter(|relation| relation.oid == oid)
+            .collect();
+        Ok(matching.iter().find(|relation| relation.kind != 'i').or(matching.first()).cloned())
+    }
+
+    async fn attributes(db: &dyn SessionStorage, table: &str) -> Result<Vec<Attribute>, PgSqliteError> {
+        let table = Self::quote(table);
+        let response = db.query(&format!(
+            "SELECT p.cid, p.name, COALESCE(s.pg_type, p.type), p.\"notnull\" OR p.pk > 0, p.dflt_value, i.identity_kind \
+             FROM pragma_table_info('{table}') p \
+             LEFT JOIN __pgsqlite_schema s ON s.table_name = '{table}' AND s.column_name = p.name \
+             LEFT JOIN __pgsqlite_identity_columns i ON i.table_name = '{table}' AND i.column_name = p.name \
+             ORDER BY p.cid"
+        )).await?;
+        Ok(response.rows.iter()
+            .map(|row| {
+                let name = Self::text(row, 1).unwrap_or_default();
+                let (identity, default) = match Self::text(row, 5).as_deref() {
+                    Some("serial") => ("", Some(format!("nextval('{table}_{name}_seq'::regclass)"))),
+                    Some("always") => ("a", None),
+                    Some(_) => ("d", None),
+                    None => ("", Self::text(row, 4)),
+                };
+                Attribute {
+                    num: Self::text(row, 0).and_then(|cid| cid.parse::<usize>().ok()).unwrap_or(0) + 1,
+                    type_name: Self::display_type(&Self::text(row, 2).unwrap_or_default()),
+                    not_null: Self::text(row, 3).as_deref() == Some("1"),
+                    name,
+                    default,
+                    identity,
+                }
+            })
+            .collect())
+    }
+
+    /// pg_class comments by (objoid, objsubid)
+    async fn descriptions(db: &dyn SessionStorage) -> Result<HashMap<(String, i64), String>, PgSqliteError> {
+        let response = db.query("SELECT CAST(objoid AS TEXT), objsubid, description FROM pg_description WHERE classoid = 1259").await?;
+        Ok(response.rows.iter()
+            .filter_map(|row| Some((
+                (Self::text(row, 0)?, Self::text(row, 1)?.parse().ok()?),
+                Self::text(row, 2)?,
+            )))
+            .collect())
+    }
+
+    /// format_type() output for a type as declared in CREATE TABLE
+    fn display_type(declared: &str) -> String {
+        let declared = declared.trim();
+        if declared.is_empty() {
+            return "text".to_string();
+        }
+        if let Some(element) = declared.strip_suffix("[]") {
+            return format!("{}[]", Self::display_type(element));
+        }
+        let upper = declared.to_ascii_uppercase();
+        let (base, modifier) = match upper.find('(') {
+            Some(pos) => (upper[..pos].trim(), upper[pos..].replace(' ', "")),
+            None => (upper.as_str(), String::new()),
+        };
+        let name = match base {
+            "INT" | "INT4" | "INTEGER" | "SERIAL" | "SERIAL4" => "integer",
+            "INT2" | "SMALLINT" | "SMALLSERIAL" | "SERIAL2" => "smallint",
+            "INT8" | "BIGINT" | "BIGSERIAL" | "SERIAL8" => "bigint",
+            "FLOAT4" | "REAL" => "real",
+            "FLOAT8" | "FLOAT" | "DOUBLE" | "DOUBLE PRECISION" => "double precision",
+            "NUMERIC" | "DECIMAL" => "numeric",
+            "VARCHAR" | "CHARACTER VARYING" => "character varying",
+            "CHAR" | "CHARACTER" | "BPCHAR" => "character",
+            "BOOL" | "BOOLEAN" => "boolean",
+            "TIMESTAMP" | "TIMESTAMP WITHOUT TIME ZONE" => "timestamp without time zone",
+            "TIMESTAMPTZ" | "TIMESTAMP WITH TIME ZONE" => "timestamp with time zone",
+            "TIME" | "TIME WITHOUT TIME ZONE" => "time without time zone",
+            "TIMETZ" | "TIME WITH TIME ZONE" => "time with time zone",
+            "BLOB" | "BYTEA" => "bytea",
+            _ => return declared.to_lowercase(),
+        };
+        format!("{name}{}", modifier.to_lowercase())
+    }
+
+    fn name_filters(sql: &str) -> Vec<NameFilter> {
+        NAME_FILTER.captures_iter(sql)
+            .map(|caps| NameFilter {
+                column: caps[1].to_ascii_lowercase(),
+                negated: &caps[2] == "!~",
+                pattern: Regex::new(&caps[3].replace("''", "'")).ok(),
+            })
+            .collect()
+    }
+
+    /// Whether a value satisfies every pattern condition on the column
+    fn passes(filters: &[NameFilter], column: &str, value: &str) -> bool {
+        filters.iter()
+            .filter(|filter| filter.column == column)
+            .all(|filter| filter.pattern.as_ref().is_some_and(|pattern| pattern.is_match(value) != filter.negated))
+    }
+
+    /// The comma-separated items of the outermost select list
+    fn select_items(sql: &str) -> Vec<String> {
+        let lower = sql.to_ascii_lowercase();
+        let Some(select) = lower.find("select ") else {
+            return Vec::new();
+        };
+        let bytes = sql.as_bytes();
+        let mut items = Vec::new();
+        let mut depth = 0i32;
+        let mut quote: Option<u8> = None;
+        let list_start = select + "select ".len();
+        let mut start = list_start;
+        let mut end = bytes.len();
+        for (i, &c) in bytes.iter().enumerate().skip(list_start) {
+            if let Some(q) = quote {
+                if c == q {
+                    quote = None;
+                }
+                continue;
+            }
+            match c {
+                b'\'' | b'"' => quote = Some(c),
+                b'(' => depth += 1,
+                b')' => depth -= 1,
+                b',' if depth == 0 => {
+                    items.push(sql[start..i].trim().to_string());
+                    start = i + 1;
+                }
+                b' ' if depth == 0 && lower[i..].starts_with(" from ") => {
+                    end = i;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        items.push(sql[start..end].trim().to_string());
+        items.retain(|item| !item.is_empty());
+        items
+    }
+
+    fn column_name(item: &str) -> String {
+        if let Some(caps) = ALIASED_ITEM.captures(item) {
+            return caps.get(2).or(caps.get(3)).map(|alias| alias.as_str().to_string()).unwrap_or_default();
+        }
+        if let Some(caps) = COLUMN_ITEM.captures(item).or_else(|| FUNCTION_ITEM.captures(item)) {
+            return caps[1].to_string();
+        }
+        "?column?".to_string()
+    }
+
+    /// Value of a select-list item: the row entry named by its alias or column,
+    /// else the entry whose name the expression mentions, else the item as a literal
+    fn value(item: &str, row: &Row) -> Option<String> {
+        if let Some(caps) = ALIASED_ITEM.captures(item) {
+            let alias = caps.get(2).or(caps.get(3)).map(|alias| alias.as_str().to_ascii_lowercase()).unwrap_or_default();
+            return match row.iter().find(|(key, _)| *key == alias) {
+                Some((_, value)) => value.clone(),
+                None => Self::literal(&caps[1]),
+            };
+        }
+        if let Some(caps) = COLUMN_ITEM.captures(item) {
+            let column = caps[1].to_ascii_lowercase();
+            return match row.iter().find(|(key, _)| *key == column) {
+                Some((_, value)) => value.clone(),
+                None => Self::literal(item),
+            };
+        }
+        let expression = item.to_ascii_lowercase();
+        match row.iter().find(|(key, _)| expression.contains(key)) {
+            Some((_, value)) => value.clone(),
+            None => Self::literal(item),
+        }
+    }
+
+    fn literal(expression: &str) -> Option<String> {
+        let expression = expression.trim();
+        match expression.to_ascii_lowercase().as_str() {
+            "true" => return Some(Self::flag(true)),
+            "false" => return Some(Self::flag(false)),
+            _ => {}
+        }
+        if let Some(text) = expression.strip_prefix('\'').and_then(|rest| rest.strip_suffix('\'')) {
+            return Some(text.replace("''", "'"));
+        }
+        expression.parse::<i64>().ok().map(|number| number.to_string())
+    }
+
+    fn flag(value: bool) -> String {
+        if value { "t" } else { "f" }.to_string()
+    }
+
+    fn text(row: &[Option<Vec<u8>>], index: usize) -> Option<String> {
+        row.get(index)?.as_ref().map(|value| String::from_utf8_lossy(value).into_owned())
+    }
+
+    fn quote(value: &str) -> String {
+        value.replace('\'', "''")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_items() {
+        let sql = "SELECT n.nspname as \"Schema\", CASE c.relkind WHEN 'r' THEN 'table' END as \"Type\", \
+                   pg_catalog.format_type(a.atttypid, a.atttypmod), 'a, b' FROM pg_catalog.pg_class c";
+        let items = PsqlCompat::select_items(sql);
+        assert_eq!(items.len(), 4);
+        let names: Vec<String> = items.iter().map(|item| PsqlCompat::column_name(item)).collect();
+        assert_eq!(names, ["Schema", "Type", "format_type", "?column?"]);
+    }
+
+    #[test]
+    fn test_values() {
+        let row: Row = vec![
+            ("pg_get_expr", Some("0".to_string())),
+            ("format_type", Some("integer".to_string())),
+            ("attname", Some("id".to_string())),
+        ];
+        assert_eq!(PsqlCompat::value("a.attname", &row).as_deref(), Some("id"));
+        assert_eq!(PsqlCompat::value("pg_catalog.format_type(a.atttypid, a.atttypmod)", &row).as_deref(), Some("integer"));
+        assert_eq!(
+            PsqlCompat::value("(SELECT pg_catalog.pg_get_expr(d.adbin, d.adrelid, true) FROM pg_catalog.pg_attrdef d WHERE d.adnum = a.attnum)", &row).as_deref(),
+            Some("0")
+        );
+        assert_eq!(PsqlCompat::value("false AS relhasoids", &row).as_deref(), Some("f"));
+        assert_eq!(PsqlCompat::value("'libc' AS \"Locale Provider\"", &row).as_deref(), Some("libc"));
+        assert_eq!(PsqlCompat::value("NULL as \"ICU Locale\"", &row), None);
+    }
+
+    #[test]
+    fn test_name_filters() {
+        let filters = PsqlCompat::name_filters(
+            "WHERE c.relname OPERATOR(pg_catalog.~) '^(ord.*)$' COLLATE pg_catalog.default AND n.nspname !~ '^pg_'"
+        );
+        assert!(PsqlCompat::passes(&filters, "c.relname", "orders"));
+        assert!(!PsqlCompat::passes(&filters, "c.relname", "customers"));
+        assert!(PsqlCompat::passes(&filters, "n.nspname", "public"));
+        assert!(!PsqlCompat::passes(&filters, "n.nspname", "pg_catalog"));
+        assert!(PsqlCompat::passes(&filters, "p.proname", "anything"));
+    }
+
+    #[test]
+    fn test_display_type() {
+        assert_eq!(PsqlCompat::display_type("VARCHAR(50)"), "character varying(50)");
+        assert_eq!(PsqlCompat::display_type("NUMERIC(10, 2)"), "numeric(10,2)");
+        assert_eq!(PsqlCompat::display_type("SERIAL"), "integer");
+        assert_eq!(PsqlCompat::display_type("TIMESTAMPTZ"), "timestamp with time zone");
+        assert_eq!(PsqlCompat::display_type("INTEGER[]"), "integer[]");
+        assert_eq!(PsqlCompat::display_type("mood"), "mood");
+    }
+}
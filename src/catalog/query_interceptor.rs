@@ -1,4 +1,4 @@
-use crate::session::db_handler::{DbHandler, DbResponse};
+use crate::session::{DbResponse, SessionStorage};
 use crate::session::SessionState;
 use crate::PgSqliteError;
 use crate::translator::{RegexTranslator, SchemaPrefixTranslator};
@@ -7,17 +7,79 @@ use sqlparser::dialect::PostgreSqlDialect;
 use sqlparser::parser::Parser;
 use sqlparser::tokenizer::{Location, Span};
 use tracing::{debug, info};
-use super::{pg_class::PgClassHandler, pg_attribute::PgAttributeHandler, pg_enum::PgEnumHandler, system_functions::SystemFunctions};
+use super::{pg_class::PgClassHandler, pg_attribute::PgAttributeHandler, pg_enum::PgEnumHandler, psql_compat::PsqlCompat, system_functions::SystemFunctions};
+
+/// Built-in range types as (rngtypid, rngsubtype, range array oid)
+const RANGE_TYPES: [(i32, i32, i32); 6] = [
+    (3904, 23, 3905),   // int4range
+    (3926, 20, 3927),   // int8range
+    (3906, 1700, 3907), // numrange
+    (3908, 1114, 3909), // tsrange
+    (3910, 1184, 3911), // tstzrange
+    (3912, 1082, 3913), // daterange
+];
+use super::information_schema_columns::InformationSchemaColumnsHandler;
 use std::sync::Arc;
 use std::pin::Pin;
 use std::future::Future;
 
+/// Names whose values differ between sessions or calls, so queries using them bypass the catalog cache
+const SESSION_DEPENDENT: [&str; 13] = [
+    "pgsqlite_cache_status", "pg_backend_pid", "current_user", "session_user", "current_schema",
+    "current_setting", "pg_settings", "pg_stat", "pg_locks", "pg_prepared", "pg_cursors", "txid_",
+    "pg_current_",
+];
+
 /// Intercepts and handles queries to pg_catalog tables
 pub struct CatalogInterceptor;
 
 impl CatalogInterceptor {
-    /// Check if a query is targeting pg_catalog and handle it
-    pub async fn intercept_query(query: &str, db: Arc<DbHandler>, session: Option<Arc<SessionState>>) -> Option<Result<DbResponse, PgSqliteError>> {
+    /// Check if a query is targeting pg_catalog and handle it, reusing the result
+    /// from the catalog cache while the schema stays the same
+    pub async fn intercept_query(query: &str, db: Arc<dyn SessionStorage>, session: Option<Arc<SessionState>>) -> Option<Result<DbResponse, PgSqliteError>> {
+        // Each database has a catalog cache of its own
+        let caches = db.get_caches().clone();
+        let cache = &caches.catalog;
+        let snapshot = match &session {
+            Some(session) if cache.enabled() && Self::is_cacheable(query) => Self::schema_snapshot(db.as_ref(), session).await,
+            _ => None,
+        };
+        let Some(snapshot) = snapshot else {
+            return Self::intercept_uncached(query, db, session).await;
+        };
+
+        if let Some(response) = cache.get(query, snapshot) {
+            debug!("Catalog cache hit: {}", query);
+            return Some(Ok(response));
+        }
+        let result = Self::intercept_uncached(query, db, session).await;
+        if let Some(Ok(response)) = &result {
+            cache.insert(query, snapshot, response.clone());
+        }
+        result
+    }
+
+    /// Catalog queries whose results only depend on the schema
+    fn is_cacheable(query: &str) -> bool {
+        let lower_query = query.to_lowercase();
+        !crate::query::simple_query_detector::contains_non_deterministic_functions(query)
+            && !SESSION_DEPENDENT.iter().any(|name| lower_query.contains(name))
+            && !PsqlCompat::depends_on_search_path(query)
+    }
+
+    /// The schema the session sees, or `None` inside a transaction block, where it
+    /// may see changes of its own that no other session can
+    async fn schema_snapshot(db: &dyn SessionStorage, session: &SessionState) -> Option<crate::cache::SchemaSnapshot> {
+        let version = db.with_session_connection(&session.id, |conn| {
+            if !conn.is_autocommit() {
+                return Ok(None);
+            }
+            conn.query_row("PRAGMA schema_version", [], |row| row.get::<_, i64>(0)).map(Some)
+        }).await.ok()??;
+        Some((crate::cache::CatalogCache::generation(), version))
+    }
+
+    async fn intercept_uncached(query: &str, db: Arc<dyn SessionStorage>, session: Option<Arc<SessionState>>) -> Option<Result<DbResponse, PgSqliteError>> {
         // Quick check to avoid parsing if not a catalog query
         let lower_query = query.to_lowercase();
         
@@ -37,6 +99,11 @@ impl CatalogInterceptor {
            lower_query.trim() == "select version()" {
             return None;
         }
+
+        // psql's describe meta-commands read catalog columns the generic path cannot produce
+        if let Some(result) = PsqlCompat::handle_query(query, db.as_ref(), session.as_deref()).await {
+            return Some(result);
+        }
         
         // Check for catalog tables
         let has_catalog_tables = lower_query.contains("pg_catalog") || lower_query.contains("pg_type") || 
@@ -150,7 +217,7 @@ impl CatalogInterceptor {
                         
                         // Normal catalog table handling
                         if let Some(response) = Self::handle_catalog_query(query_stmt, db.clone(), session.clone()).await {
-                            return Some(Ok(response));
+                            return Some(response);
                         }
                     }
                 
@@ -169,9 +236,15 @@ impl CatalogInterceptor {
         None
     }
 
-    async fn handle_catalog_query(query: &sqlparser::ast::Query, db: Arc<DbHandler>, session: Option<Arc<SessionState>>) -> Option<DbResponse> {
+    async fn handle_catalog_query(query: &sqlparser::ast::Query, db: Arc<dyn SessionStorage>, session: Option<Arc<SessionState>>) -> Option<Result<DbResponse, PgSqliteError>> {
         // Check if this is a SELECT from pg_catalog tables
         if let SetExpr::Select(select) = &*query.body {
+            // Description lookups are SQLite functions over pg_description, so let the views answer them
+            if query.to_string().to_lowercase().contains("_description(") {
+                debug!("Passing description lookup to SQLite views");
+                return None;
+            }
+
             // Check if this is a JOIN query involving catalog tables
             if !select.from.is_empty() && !select.from[0].joins.is_empty() {
                 // Check if the query contains system functions that need special handling
@@ -206,18 +279,18 @@ impl CatalogInterceptor {
                                         // If we found the table, return a result with the table name
                                         if !response.rows.is_empty() {
                                             debug!("Table {} exists", table_name_str);
-                                            return Some(DbResponse {
+                                            return Some(Ok(DbResponse {
                                                 columns: vec!["relname".to_string()],
                                                 rows: vec![vec![Some(table_name_str.as_bytes().to_vec())]],
                                                 rows_affected: 1,
-                                            });
+                                            }));
                                         } else {
                                             debug!("Table {} does not exist", table_name_str);
-                                            return Some(DbResponse {
+                                            return Some(Ok(DbResponse {
                                                 columns: vec!["relname".to_string()],
                                                 rows: vec![],
                                                 rows_affected: 0,
-                                            });
+                                            }));
                                         }
                                     }
                                     Err(_) => {
@@ -237,18 +310,19 @@ impl CatalogInterceptor {
                         let table_name = name.to_string().to_lowercase();
                         if table_name.contains("pg_") && (table_name.contains("pg_class") || 
                             table_name.contains("pg_namespace") || table_name.contains("pg_attribute") ||
-                            table_name.contains("pg_constraint") || table_name.contains("pg_index")) {
+                            table_name.contains("pg_constraint") || table_name.contains("pg_index") ||
+                            table_name.contains("pg_proc") || table_name.contains("pg_description")) {
                             debug!("Passing JOIN query on catalog tables to SQLite views");
                             return None;
                         }
                     }
                     
-                    // Keep special handling for pg_type JOINs since they need custom logic
+                    // pg_type JOINs, like the type lookup of tokio-postgres, list the same types
+                    // as queries on pg_type alone, including ENUM and composite types
                     if let TableFactor::Table { name, .. } = &select.from[0].relation {
                         let table_name = name.to_string().to_lowercase();
                         if table_name.contains("pg_type") || table_name.contains("pg_catalog.pg_type") {
-                            // This is a pg_type JOIN query - handle it specially
-                            return Some(Self::handle_pg_type_join_query(select));
+                            return Some(Ok(Self::handle_pg_type_query(select, db, session).await));
                         }
                     }
                 }
@@ -273,37 +347,32 @@ impl CatalogInterceptor {
         None
     }
     
-    async fn check_table_factor(table_factor: &TableFactor, select: &Select, db: Arc<DbHandler>, session: Option<Arc<SessionState>>) -> Option<DbResponse> {
+    async fn check_table_factor(table_factor: &TableFactor, select: &Select, db: Arc<dyn SessionStorage>, session: Option<Arc<SessionState>>) -> Option<Result<DbResponse, PgSqliteError>> {
         if let TableFactor::Table { name, .. } = table_factor {
             let table_name = name.to_string().to_lowercase();
             
             // Handle pg_type queries
             if table_name.contains("pg_type") || table_name.contains("pg_catalog.pg_type") {
-                return Some(Self::handle_pg_type_query(select, db.clone(), session.clone()).await);
-            }
-            
-            // Handle pg_namespace queries
-            if table_name.contains("pg_namespace") || table_name.contains("pg_catalog.pg_namespace") {
-                return Some(Self::handle_pg_namespace_query(select));
+                return Some(Ok(Self::handle_pg_type_query(select, db.clone(), session.clone()).await));
             }
             
             // Handle pg_range queries (usually empty)
             if table_name.contains("pg_range") || table_name.contains("pg_catalog.pg_range") {
-                return Some(Self::handle_pg_range_query(select));
+                return Some(Ok(Self::handle_pg_range_query(select)));
             }
             
             // Handle pg_class queries
             if table_name.contains("pg_class") || table_name.contains("pg_catalog.pg_class") {
-                return (PgClassHandler::handle_query(select, &db).await).ok();
+                return (PgClassHandler::handle_query(select, db.as_ref()).await).ok().map(Ok);
             }
             
             // Handle pg_attribute queries
             if table_name.contains("pg_attribute") || table_name.contains("pg_catalog.pg_attribute") {
                 info!("Routing to PgAttributeHandler for table: {}", table_name);
-                return match PgAttributeHandler::handle_query(select, &db).await {
+                return match PgAttributeHandler::handle_query(select, db.as_ref()).await {
                     Ok(response) => {
                         debug!("PgAttributeHandler returned {} rows", response.rows.len());
-                        Some(response)
+                        Some(Ok(response))
                     },
                     Err(_) => {
                         // PgAttributeHandler error
@@ -314,23 +383,28 @@ impl CatalogInterceptor {
             
             // Handle pg_enum queries
             if table_name.contains("pg_enum") || table_name.contains("pg_catalog.pg_enum") {
-                return (PgEnumHandler::handle_query(select, &db).await).ok();
+                return (PgEnumHandler::handle_query(select, db.as_ref()).await).ok().map(Ok);
             }
             
             // Handle information_schema.tables queries
             if table_name.contains("information_schema.tables") {
-                return Some(Self::handle_information_schema_tables_query(select, &db).await);
+                return Some(Ok(Self::handle_information_schema_tables_query(select, db.as_ref()).await));
+            }
+
+            // Handle information_schema.columns queries; SQLite has no such table to fall back to
+            if table_name.contains("information_schema.columns") {
+                return Some(InformationSchemaColumnsHandler::handle_query(select, db.as_ref()).await);
             }
 
             // Handle information_schema.schemata queries
             if table_name.contains("information_schema.schemata") {
-                return Some(Self::handle_information_schema_schemata_query(select));
+                return Some(Ok(Self::handle_information_schema_schemata_query(select)));
             }
         }
         None
     }
 
-    async fn handle_pg_type_query(select: &Select, db: Arc<DbHandler>, session: Option<Arc<SessionState>>) -> DbResponse {
+    async fn handle_pg_type_query(select: &Select, db: Arc<dyn SessionStorage>, session: Option<Arc<SessionState>>) -> DbResponse {
         // Extract which columns are being selected
         let mut columns = Vec::new();
         let mut column_indices = Vec::new();
@@ -471,6 +545,13 @@ impl CatalogInterceptor {
             (1700, "numeric", "b", 0, 0, 11, 0),   // numeric
             (2950, "uuid", "b", 0, 0, 11, 0),      // uuid
             (3802, "jsonb", "b", 0, 0, 11, 0),     // jsonb
+            // Range types
+            (3904, "int4range", "r", 0, 0, 11, 0), // int4range
+            (3926, "int8range", "r", 0, 0, 11, 0), // int8range
+            (3906, "numrange", "r", 0, 0, 11, 0),  // numrange
+            (3908, "tsrange", "r", 0, 0, 11, 0),   // tsrange
+            (3910, "tstzrange", "r", 0, 0, 11, 0), // tstzrange
+            (3912, "daterange", "r", 0, 0, 11, 0), // daterange
             // Array types
             (1000, "_bool", "b", 16, 0, 11, 0),    // bool array
             (1001, "_bytea", "b", 17, 0, 11, 0),   // bytea array
@@ -490,6 +571,12 @@ impl CatalogInterceptor {
             (2951, "_uuid", "b", 2950, 0, 11, 0),  // uuid array
             (199, "_json", "b", 114, 0, 11, 0),    // json array
             (3807, "_jsonb", "b", 3802, 0, 11, 0), // jsonb array
+            (3905, "_int4range", "b", 3904, 0, 11, 0), // int4range array
+            (3927, "_int8range", "b", 3926, 0, 11, 0), // int8range array
+            (3907, "_numrange", "b", 3906, 0, 11, 0), // numrange array
+            (3909, "_tsrange", "b", 3908, 0, 11, 0), // tsrange array
+            (3911, "_tstzrange", "b", 3910, 0, 11, 0), // tstzrange array
+            (3913, "_daterange", "b", 3912, 0, 11, 0), // daterange array
         ];
 
         for (oid, typname, typtype, typelem, typbasetype, _typnamespace, typrelid) in types {
@@ -516,7 +603,9 @@ impl CatalogInterceptor {
                     "typnamespace" => Some(_typnamespace.to_string().into_bytes()),
                     "typrelid" => Some(typrelid.to_string().into_bytes()),
                     "nspname" => Some("pg_catalog".to_string().into_bytes()),
-                    "rngsubtype" => None, // NULL for non-range types
+                    "rngsubtype" => RANGE_TYPES.iter()
+                        .find(|(rngtypid, _, _)| *rngtypid == oid)
+                        .map(|(_, rngsubtype, _)| rngsubtype.to_string().into_bytes()), // NULL for non-range types
                     "typarray" => {
                         // Find the array type OID for this base type
                         let array_oid = match oid {
@@ -538,7 +627,9 @@ impl CatalogInterceptor {
                             2950 => 2951, // uuid -> _uuid
                             114 => 199,   // json -> _json
                             3802 => 3807, // jsonb -> _jsonb
-                            _ => 0,       // No array type
+                            _ => RANGE_TYPES.iter()
+                                .find(|(rngtypid, _, _)| *rngtypid == oid)
+                                .map_or(0, |(_, _, array_oid)| *array_oid), // ranges, or no array type
                         };
                         Some(array_oid.to_string().into_bytes())
                     }
@@ -553,6 +644,18 @@ impl CatalogInterceptor {
             }
         }
         
+        // Array types of ENUM and composite types, as (element name, element OID, array OID)
+        let array_types = if let Some(ref session) = session {
+            db.with_session_connection(&session.id, crate::metadata::OidAllocator::array_types).await
+        } else {
+            db.get_mut_connection()
+                .and_then(|conn| crate::metadata::OidAllocator::array_types(&conn))
+                .map_err(PgSqliteError::Sqlite)
+        }.unwrap_or_default();
+        let array_oid_of = |type_oid: i32| array_types.iter()
+            .find(|(_, element_oid, _)| *element_oid == i64::from(type_oid))
+            .map_or(0, |(_, _, array_oid)| *array_oid);
+
         // Add ENUM types from metadata only if typtype filter allows it
         if filter_typtype.is_none() || filter_typtype.as_ref() == Some(&"e".to_string()) {
             // Use session connection if available, otherwise fall back to get_mut_connection
@@ -592,7 +695,7 @@ impl CatalogInterceptor {
                                 "typrelid" => Some("0".to_string().into_bytes()),
                                 "nspname" => Some("public".to_string().into_bytes()),
                                 "rngsubtype" => None, // NULL for non-range types
-                                "typarray" => Some("0".to_string().into_bytes()), // ENUMs don't have array types
+                                "typarray" => Some(array_oid_of(enum_type.type_oid).to_string().into_bytes()),
                                 "typdelim" => Some(",".to_string().into_bytes()), // Default delimiter
                                 _ => None,
                             };
@@ -606,32 +709,74 @@ impl CatalogInterceptor {
             }
         }
 
-        let rows_affected = rows.len();
-        info!("pg_type query: filter_oid={:?}, filter_typtype={:?}, has_placeholder={}", filter_oid, filter_typtype, has_placeholder);
-        info!("Returning {} rows for pg_type query with {} columns: {:?}", rows_affected, columns.len(), columns);
-        DbResponse {
-            columns,
-            rows,
-            rows_affected,
+        // Add composite types created with CREATE TYPE ... AS (...)
+        if filter_typtype.is_none() || filter_typtype.as_deref() == Some("c") {
+            let composite_types_result = if let Some(ref session) = session {
+                db.with_session_connection(&session.id, |conn| {
+                    crate::metadata::CompositeTypes::get_all_types(conn)
+                }).await
+            } else {
+                db.get_mut_connection()
+                    .and_then(|conn| crate::metadata::CompositeTypes::get_all_types(&conn))
+                    .map_err(PgSqliteError::Sqlite)
+            };
+            
+            if let Ok(composite_types) = composite_types_result {
+                for composite in composite_types {
+                    if let Some(filter) = filter_oid
+                        && composite.type_oid != filter {
+                            continue;
+                        }
+                    
+                    let row = columns.iter().map(|col| match col.as_str() {
+                        "oid" => Some(composite.type_oid.to_string().into_bytes()),
+                        "typname" => Some(composite.type_name.clone().into_bytes()),
+                        "typtype" => Some(b"c".to_vec()), // 'c' for composite
+                        "typelem" | "typbasetype" => Some(b"0".to_vec()),
+                        "typarray" => Some(array_oid_of(composite.type_oid).to_string().into_bytes()),
+                        "typnamespace" => Some(composite.namespace_oid.to_string().into_bytes()),
+                        "typrelid" => Some(composite.relation_oid.to_string().into_bytes()),
+                        "nspname" => Some(b"public".to_vec()),
+                        "typdelim" => Some(b",".to_vec()),
+                        _ => None,
+                    }).collect::<Vec<_>>();
+                    
+                    if !row.is_empty() {
+                        rows.push(row);
+                    }
+                }
+            }
         }
-    }
 
-    fn handle_pg_namespace_query(_select: &Select) -> DbResponse {
-        // Return basic namespaces
-        let columns = vec!["oid".to_string(), "nspname".to_string()];
-        let rows = vec![
-            vec![
-                Some("11".to_string().into_bytes()),
-                Some("pg_catalog".to_string().into_bytes()),
-            ],
-            vec![
-                Some("2200".to_string().into_bytes()),
-                Some("public".to_string().into_bytes()),
-            ],
-        ];
+        // Add the array types of ENUM and composite types
+        if filter_typtype.is_none() || filter_typtype.as_deref() == Some("b") {
+            for (type_name, element_oid, array_oid) in &array_types {
+                if let Some(filter) = filter_oid
+                    && i64::from(filter) != *array_oid {
+                        continue;
+                    }
+                
+                let row = columns.iter().map(|col| match col.as_str() {
+                    "oid" => Some(array_oid.to_string().into_bytes()),
+                    "typname" => Some(format!("_{type_name}").into_bytes()),
+                    "typtype" => Some(b"b".to_vec()),
+                    "typelem" => Some(element_oid.to_string().into_bytes()),
+                    "typbasetype" | "typarray" | "typrelid" => Some(b"0".to_vec()),
+                    "typnamespace" => Some(b"2200".to_vec()),
+                    "nspname" => Some(b"public".to_vec()),
+                    "typdelim" => Some(b",".to_vec()),
+                    _ => None,
+                }).collect::<Vec<_>>();
+                
+                if !row.is_empty() {
+                    rows.push(row);
+                }
+            }
+        }
 
         let rows_affected = rows.len();
-        debug!("Returning {} rows for pg_type query with {} columns: {:?}", rows_affected, columns.len(), columns);
+        info!("pg_type query: filter_oid={:?}, filter_typtype={:?}, has_placeholder={}", filter_oid, filter_typtype, has_placeholder);
+        info!("Returning {} rows for pg_type query with {} columns: {:?}", rows_affected, columns.len(), columns);
         DbResponse {
             columns,
             rows,
@@ -640,9 +785,13 @@ impl CatalogInterceptor {
     }
 
     fn handle_pg_range_query(_select: &Select) -> DbResponse {
-        // pg_range is typically empty for basic types
         let columns = vec!["rngtypid".to_string(), "rngsubtype".to_string()];
-        let rows = vec![];
+        let rows: Vec<Vec<Option<Vec<u8>>>> = RANGE_TYPES.iter()
+            .map(|(rngtypid, rngsubtype, _)| vec![
+                Some(rngtypid.to_string().into_bytes()),
+                Some(rngsubtype.to_string().into_bytes()),
+            ])
+            .collect();
         let rows_affected = rows.len();
 
         DbResponse {
@@ -652,165 +801,6 @@ impl CatalogInterceptor {
         }
     }
     
-    fn handle_pg_type_join_query(select: &Select) -> DbResponse {
-        // Handle the complex JOIN query that tokio-postgres uses
-        // Extract which columns are being selected
-        let mut columns = Vec::new();
-        
-        debug!("Processing pg_type JOIN query with {} projections", select.projection.len());
-        
-        for item in &select.projection {
-            match item {
-                SelectItem::UnnamedExpr(Expr::CompoundIdentifier(parts)) => {
-                    let col_name = parts.last().unwrap().value.to_lowercase();
-                    debug!("  Column: {}", col_name);
-                    columns.push(col_name);
-                }
-                SelectItem::UnnamedExpr(Expr::Identifier(ident)) => {
-                    let col_name = ident.value.to_lowercase();
-                    debug!("  Column: {}", col_name);
-                    columns.push(col_name);
-                }
-                _ => {
-                    debug!("  Unknown projection type");
-                }
-            }
-        }
-
-        // Check if there's a WHERE clause filtering by OID
-        let mut filter_oid = None;
-        
-        if let Some(selection) = &select.selection
-            && let Expr::BinaryOp { left, op, right } = selection
-                && matches!(op, sqlparser::ast::BinaryOperator::Eq) {
-                    let is_oid_column = if let Expr::CompoundIdentifier(left_parts) = left.as_ref() {
-                        left_parts.last().unwrap().value.to_lowercase() == "oid"
-                    } else if let Expr::Identifier(ident) = left.as_ref() {
-                        ident.value.to_lowercase() == "oid"
-                    } else {
-                        false
-                    };
-                    
-                    if is_oid_column {
-                        // Check if right side is a number or placeholder
-                        if let Expr::Value(sqlparser::ast::ValueWithSpan { value: sqlparser::ast::Value::Number(n, _), .. }) = right.as_ref() {
-                            filter_oid = n.parse::<i32>().ok();
-                        } else if let Expr::Value(sqlparser::ast::ValueWithSpan { value: sqlparser::ast::Value::Placeholder(_), .. }) = right.as_ref() {
-                            // For placeholders in JOIN queries, we return all types
-                            // tokio-postgres will filter client-side
-                            filter_oid = None;
-                        }
-                    }
-                }
-        
-        // Build response with all requested columns
-        let mut rows = Vec::new();
-        
-        // Define our types with proper values for JOIN query
-        let types = vec![
-            // Basic types
-            (16, "bool", "b", 0, 0, 11, 0),
-            (17, "bytea", "b", 0, 0, 11, 0),
-            (20, "int8", "b", 0, 0, 11, 0),
-            (21, "int2", "b", 0, 0, 11, 0),
-            (23, "int4", "b", 0, 0, 11, 0),
-            (25, "text", "b", 0, 0, 11, 0),
-            (114, "json", "b", 0, 0, 11, 0),
-            (700, "float4", "b", 0, 0, 11, 0),
-            (701, "float8", "b", 0, 0, 11, 0),
-            (1042, "char", "b", 0, 0, 11, 0),
-            (1043, "varchar", "b", 0, 0, 11, 0),
-            (1082, "date", "b", 0, 0, 11, 0),
-            (1083, "time", "b", 0, 0, 11, 0),
-            (1114, "timestamp", "b", 0, 0, 11, 0),
-            (1184, "timestamptz", "b", 0, 0, 11, 0),
-            (1700, "numeric", "b", 0, 0, 11, 0),
-            (2950, "uuid", "b", 0, 0, 11, 0),
-            (3802, "jsonb", "b", 0, 0, 11, 0),
-            // Array types - typtype is still 'b' for arrays in PostgreSQL
-            (1000, "_bool", "b", 16, 0, 11, 0),
-            (1001, "_bytea", "b", 17, 0, 11, 0),
-            (1005, "_int2", "b", 21, 0, 11, 0),
-            (1007, "_int4", "b", 23, 0, 11, 0),
-            (1009, "_text", "b", 25, 0, 11, 0),
-            (1014, "_char", "b", 1042, 0, 11, 0),
-            (1015, "_varchar", "b", 1043, 0, 11, 0),
-            (1016, "_int8", "b", 20, 0, 11, 0),
-            (1021, "_float4", "b", 700, 0, 11, 0),
-            (1022, "_float8", "b", 701, 0, 11, 0),
-            (1115, "_timestamp", "b", 1114, 0, 11, 0),
-            (1182, "_date", "b", 1082, 0, 11, 0),
-            (1183, "_time", "b", 1083, 0, 11, 0),
-            (1185, "_timestamptz", "b", 1184, 0, 11, 0),
-            (1231, "_numeric", "b", 1700, 0, 11, 0),
-            (2951, "_uuid", "b", 2950, 0, 11, 0),
-            (199, "_json", "b", 114, 0, 11, 0),
-            (3807, "_jsonb", "b", 3802, 0, 11, 0),
-        ];
-
-        for (oid, typname, typtype, typelem, typbasetype, _typnamespace, typrelid) in types {
-            // Apply filter if specified
-            if let Some(filter) = filter_oid
-                && oid != filter {
-                    continue;
-                }
-
-            let mut row = Vec::new();
-            for col in &columns {
-                let value = match col.as_str() {
-                    "oid" => Some(oid.to_string().into_bytes()),
-                    "typname" => Some(typname.to_string().into_bytes()),
-                    "typtype" => Some(typtype.to_string().into_bytes()),
-                    "typelem" => Some(typelem.to_string().into_bytes()),
-                    "rngsubtype" => None, // NULL for non-range types
-                    "typbasetype" => Some(typbasetype.to_string().into_bytes()),
-                    "nspname" => Some("pg_catalog".to_string().into_bytes()),
-                    "typrelid" => Some(typrelid.to_string().into_bytes()),
-                    "typarray" => {
-                        // Find the array type OID for this base type
-                        let array_oid = match oid {
-                            16 => 1000,   // bool -> _bool
-                            17 => 1001,   // bytea -> _bytea
-                            20 => 1016,   // int8 -> _int8
-                            21 => 1005,   // int2 -> _int2
-                            23 => 1007,   // int4 -> _int4
-                            25 => 1009,   // text -> _text
-                            700 => 1021,  // float4 -> _float4
-                            701 => 1022,  // float8 -> _float8
-                            1042 => 1014, // char -> _char
-                            1043 => 1015, // varchar -> _varchar
-                            1082 => 1182, // date -> _date
-                            1083 => 1183, // time -> _time
-                            1114 => 1115, // timestamp -> _timestamp
-                            1184 => 1185, // timestamptz -> _timestamptz
-                            1700 => 1231, // numeric -> _numeric
-                            2950 => 2951, // uuid -> _uuid
-                            114 => 199,   // json -> _json
-                            3802 => 3807, // jsonb -> _jsonb
-                            _ => 0,       // No array type
-                        };
-                        Some(array_oid.to_string().into_bytes())
-                    }
-                    "typdelim" => Some(",".to_string().into_bytes()), // Default delimiter
-                    _ => None,
-                };
-                row.push(value);
-            }
-            
-            if !row.is_empty() {
-                rows.push(row);
-            }
-        }
-
-        let rows_affected = rows.len();
-        debug!("Returning {} rows for pg_type JOIN query", rows_affected);
-        DbResponse {
-            columns,
-            rows,
-            rows_affected,
-        }
-    }
-
     /// Check if a query contains system function calls
     pub fn query_contains_system_functions(query: &sqlparser::ast::Query) -> bool {
         if let SetExpr::Select(select) = &*query.body {
@@ -875,7 +865,7 @@ impl CatalogInterceptor {
     /// Process system functions in a query by replacing them with their results
     pub async fn process_system_functions_in_query(
         mut query: Box<sqlparser::ast::Query>,
-        db: Arc<DbHandler>,
+        db: Arc<dyn SessionStorage>,
     ) -> Result<Box<sqlparser::ast::Query>, Box<dyn std::error::Error + Send + Sync>> {
         
         if let SetExpr::Select(select) = &mut *query.body {
@@ -976,7 +966,7 @@ impl CatalogInterceptor {
     /// Process an expression and replace system function calls with their results
     fn process_expression<'a>(
         expr: &'a mut Expr,
-        db: Arc<DbHandler>,
+        db: Arc<dyn SessionStorage>,
     ) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error + Send + Sync>>> + Send + 'a>> {
         Box::pin(async move {
         match expr {
@@ -1051,7 +1041,7 @@ impl CatalogInterceptor {
         })
     }
 
-    async fn handle_information_schema_tables_query(select: &Select, db: &DbHandler) -> DbResponse {
+    async fn handle_information_schema_tables_query(select: &Select, db: &dyn SessionStorage) -> DbResponse {
         debug!("Handling information_schema.tables query");
         
         // Get list of tables from SQLite
@@ -1,10 +1,15 @@
-use clap::Parser;
+use clap::{CommandFactory, Parser, Subcommand};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
 use std::env;
+use std::ffi::OsString;
+use std::sync::Arc;
 
 #[derive(Parser, Debug, Clone)]
 #[command(name = "pgsqlite")]
 #[command(about = concat!("pgsqlite v", env!("CARGO_PKG_VERSION"), " - 🐘 PostgreSQL + 🪶 SQLite = ♥\nPostgreSQL wire protocol server on top of SQLite"), long_about = None)]
 #[command(version)]
+#[command(args_override_self = true)]
 pub struct Config {
     // Basic configuration
     #[arg(short, long, default_value = "5432", env = "PGSQLITE_PORT")]
@@ -13,18 +18,71 @@ pub struct Config {
     #[arg(short, long, default_value = "sqlite.db", env = "PGSQLITE_DATABASE")]
     pub database: String,
 
+    #[arg(long, env = "PGSQLITE_DATABASES", value_delimiter = ',', value_parser = parse_database_entry, help = "Comma-separated name=path SQLite files clients select with their database name, besides --database; other names are then refused")]
+    pub databases: Vec<(String, String)>,
+
     #[arg(long, default_value = "info", env = "PGSQLITE_LOG_LEVEL")]
     pub log_level: String,
 
+    #[arg(long, default_value = "text", value_parser = ["text", "json"], env = "PGSQLITE_LOG_FORMAT", help = "Log as text lines, or as JSON objects with connection and query events carrying the session UUID, pid and statement ID")]
+    pub log_format: String,
+
+    #[arg(long, env = "PGSQLITE_CONFIG_FILE", help = "File of name = value settings, like postgresql.conf, read again on SIGHUP or pg_reload_conf(); flags win over it, it wins over environment variables")]
+    pub config_file: Option<String>,
+
+    /// The settings read from --config-file, as (name, value) with names like `log_level`
+    #[arg(skip)]
+    pub file_settings: Vec<(String, String)>,
+
     #[arg(long, env = "PGSQLITE_IN_MEMORY", help = "Use in-memory SQLite database (for testing/benchmarking only)")]
     pub in_memory: bool,
 
+    #[arg(long, env = "PGSQLITE_READ_ONLY", help = "Serve the database read-only, e.g. a file another pgsqlite writes over a network filesystem or a Litestream replica: it is opened read-only, statements that write fail with SQLSTATE 25006 and sessions report default_transaction_read_only = on")]
+    pub read_only: bool,
+
     #[arg(long, default_value = "/tmp", env = "PGSQLITE_SOCKET_DIR", help = "Directory for Unix domain socket")]
     pub socket_dir: String,
 
-    #[arg(long, env = "PGSQLITE_NO_TCP", help = "Disable TCP listener and use only Unix socket")]
+    #[arg(long, env = "PGSQLITE_NO_TCP", help = "Disable TCP listener and use only Unix socket (named pipe on Windows)")]
     pub no_tcp: bool,
 
+    #[arg(long, env = "PGSQLITE_PIPE_NAME", help = "Windows named pipe to listen on [default: \\\\.\\pipe\\pgsqlite.<port>]")]
+    pub pipe_name: Option<String>,
+
+    #[arg(long, env = "PGSQLITE_NO_PIPE", help = "Disable the Windows named pipe listener")]
+    pub no_pipe: bool,
+
+    #[arg(long, help = "Run under the Windows service control manager")]
+    pub windows_service: bool,
+
+    #[arg(long, default_value = "100", env = "PGSQLITE_MAX_CONNECTIONS", help = "Maximum number of concurrent client connections (admin connections are not counted)")]
+    pub max_connections: usize,
+
+    #[arg(long, default_value = "0", env = "PGSQLITE_SHUTDOWN_GRACE", help = "Seconds to keep listening after a shutdown signal, refusing new clients with SQLSTATE 57P03 while open sessions finish")]
+    pub shutdown_grace: u64,
+
+    #[arg(long, env = "PGSQLITE_PRINT_URLS", help = "Print a postgresql:// URL for each database once the server listens")]
+    pub print_urls: bool,
+
+    #[arg(long, env = "PGSQLITE_SERVICE_FILE", help = "Write a section per database to this libpq connection service file (like ~/.pg_service.conf) on startup, for clients to connect with service=<database>; other services in it are kept")]
+    pub service_file: Option<String>,
+
+    #[arg(long, default_value = "15.0", value_parser = parse_server_version, env = "PGSQLITE_SERVER_VERSION", help = "PostgreSQL version reported in server_version, server_version_num and version(), for drivers and ORMs that pick features by it")]
+    pub server_version: String,
+
+    // Admin listener configuration
+    #[arg(long, env = "PGSQLITE_ADMIN_PORT", help = "Reserved admin port, served on 127.0.0.1 and as a Unix socket in --socket-dir; bypasses --max-connections")]
+    pub admin_port: Option<u16>,
+
+    #[arg(long, default_value = "postgres", env = "PGSQLITE_ADMIN_USERS", help = "Comma-separated roles allowed to connect on the admin port and to set column masks, which they read unmasked")]
+    pub admin_users: String,
+
+    #[arg(long, default_value = "3", env = "PGSQLITE_ADMIN_MAX_CONNECTIONS", help = "Maximum number of concurrent connections on the admin port")]
+    pub admin_max_connections: usize,
+
+    #[arg(long, env = "PGSQLITE_FAST_STARTUP", help = "Send a trimmed, pre-serialized startup handshake to loopback and local socket clients")]
+    pub fast_startup: bool,
+
     // Connection pool configuration
     #[arg(long, env = "PGSQLITE_USE_POOLING", help = "Enable connection pooling with read/write separation")]
     pub use_pooling: bool,
@@ -45,7 +103,7 @@ pub struct Config {
     pub pool_max_retries: usize,
 
     // Cache configuration
-    #[arg(long, default_value = "1000", env = "PGSQLITE_ROW_DESC_CACHE_SIZE", help = "Maximum number of RowDescription entries to cache")]
+    #[arg(long, default_value = "1000", env = "PGSQLITE_ROW_DESC_CACHE_SIZE", help = "Maximum number of RowDescription entries to cache per database")]
     pub row_desc_cache_size: usize,
 
     #[arg(long, default_value = "10", env = "PGSQLITE_ROW_DESC_CACHE_TTL_MINUTES", help = "TTL for RowDescription cache entries in minutes")]
@@ -66,13 +124,13 @@ pub struct Config {
     #[arg(long, default_value = "300", env = "PGSQLITE_EXECUTION_CACHE_TTL", help = "TTL for execution metadata cache in seconds")]
     pub execution_cache_ttl: u64,
 
-    #[arg(long, default_value = "100", env = "PGSQLITE_RESULT_CACHE_SIZE", help = "Maximum number of result set entries to cache")]
+    #[arg(long, default_value = "100", env = "PGSQLITE_RESULT_CACHE_SIZE", help = "Maximum number of result set entries to cache per database")]
     pub result_cache_size: usize,
 
     #[arg(long, default_value = "60", env = "PGSQLITE_RESULT_CACHE_TTL", help = "TTL for result cache entries in seconds")]
     pub result_cache_ttl: u64,
 
-    #[arg(long, default_value = "100", env = "PGSQLITE_STATEMENT_POOL_SIZE", help = "Maximum number of prepared statements to cache")]
+    #[arg(long, default_value = "100", env = "PGSQLITE_STATEMENT_POOL_SIZE", help = "Maximum number of prepared statements to cache per database")]
     pub statement_pool_size: usize,
 
     #[arg(long, default_value = "300", env = "PGSQLITE_CACHE_METRICS_INTERVAL", help = "Interval for logging cache metrics in seconds")]
@@ -81,6 +139,9 @@ pub struct Config {
     #[arg(long, default_value = "300", env = "PGSQLITE_SCHEMA_CACHE_TTL", help = "TTL for schema cache entries in seconds")]
     pub schema_cache_ttl: u64,
 
+    #[arg(long, default_value = "256", env = "PGSQLITE_CATALOG_CACHE_SIZE", help = "Maximum number of pg_catalog query results to cache per database until the schema changes (0 disables)")]
+    pub catalog_cache_size: usize,
+
     // Buffer pool configuration
     #[arg(long, env = "PGSQLITE_BUFFER_MONITORING", help = "Enable buffer pool monitoring and statistics")]
     pub buffer_monitoring: bool,
@@ -136,6 +197,9 @@ pub struct Config {
     #[arg(long, default_value = "268435456", env = "PGSQLITE_MMAP_SIZE", help = "SQLite memory-mapped I/O size in bytes")]
     pub pragma_mmap_size: u64,
 
+    #[arg(long, default_value = "0", env = "PGSQLITE_GROUP_COMMIT_DELAY_MS", help = "Sync the commits of concurrent sessions with one fsync, waiting up to this many milliseconds for others to join (0 syncs each commit on its own; needs WAL mode)")]
+    pub group_commit_delay_ms: u64,
+
     // SSL/TLS configuration
     #[arg(long, env = "PGSQLITE_SSL", help = "Enable SSL/TLS support")]
     pub ssl: bool,
@@ -152,25 +216,253 @@ pub struct Config {
     #[arg(long, env = "PGSQLITE_SSL_EPHEMERAL", help = "Generate ephemeral SSL certificates on startup")]
     pub ssl_ephemeral: bool,
 
+    #[arg(long, default_value = "1.2", value_parser = ["1.2", "1.3"], env = "PGSQLITE_SSL_MIN_VERSION", help = "Minimum TLS protocol version")]
+    pub ssl_min_version: String,
+
+    #[arg(long, env = "PGSQLITE_SSL_CIPHERS", help = "Comma-separated TLS cipher suites to allow, e.g. TLS13_AES_256_GCM_SHA384 (default: all supported)")]
+    pub ssl_ciphers: Option<String>,
+
+    #[arg(long, env = "PGSQLITE_SSL_ALPN", help = "Comma-separated ALPN protocols to offer, e.g. postgresql")]
+    pub ssl_alpn: Option<String>,
+
+    #[arg(long, default_value = "1024", env = "PGSQLITE_SSL_SESSION_CACHE_SIZE", help = "TLS sessions kept for resumption (0 disables the session cache)")]
+    pub ssl_session_cache_size: usize,
+
+    #[arg(long, env = "PGSQLITE_SSL_SESSION_TICKETS", help = "Issue stateless TLS session tickets for resumption")]
+    pub ssl_session_tickets: bool,
+
+    #[arg(long, env = "PGSQLITE_SSL_SNI_DOMAIN", help = "Route TLS clients by server name: connecting to <database>.<domain> selects <database> whatever database the client asks for")]
+    pub ssl_sni_domain: Option<String>,
+
+    #[arg(long, default_value = "off", value_parser = ["off", "verify-ca", "verify-full"], env = "PGSQLITE_SSL_CLIENT_CERT", help = "Require TCP clients to present a certificate signed by --ssl-ca: verify-ca accepts any such certificate, verify-full also needs its common name to map to the role connecting")]
+    pub ssl_client_cert: String,
+
+    #[arg(long, env = "PGSQLITE_SSL_CERT_USER_MAP", help = "Comma-separated common-name=role pairs for --ssl-client-cert verify-full; a common name without a pair has to equal the role")]
+    pub ssl_cert_user_map: Option<String>,
+
+    #[arg(long, default_value = "60", env = "PGSQLITE_SSL_CERT_CHECK_INTERVAL", help = "Seconds between checks of the certificate, key and CA files, which are read again when they change (0 disables the checks)")]
+    pub ssl_cert_check_interval: u64,
+
+    // Compatibility configuration
+    #[arg(long, default_value = "off", value_parser = ["off", "warn", "error"], env = "PGSQLITE_STRICT_COMPATIBILITY", help = "Report features pgsqlite only approximates (locking clauses, unsupported COLLATE, unenforced constraints): off, warn or error")]
+    pub strict_compatibility: String,
+
+    #[arg(long, env = "PGSQLITE_AUTO_INDEX_FOREIGN_KEYS", help = "Create an index on foreign key columns no existing index covers when a table is created or altered")]
+    pub auto_index_foreign_keys: bool,
+
+    // Statistics configuration
+    #[arg(long, default_value = "5000", env = "PGSQLITE_STAT_STATEMENTS_MAX", help = "Maximum number of distinct statements tracked in pg_stat_statements (0 disables tracking)")]
+    pub stat_statements_max: usize,
+
+    // Maintenance configuration
+    #[arg(long, default_value = "60", env = "PGSQLITE_RETENTION_INTERVAL", help = "Seconds between runs of the retention policies set with pgsqlite.set_retention() (0 disables the scheduler)")]
+    pub retention_interval: u64,
+
+    #[arg(long, default_value = "1000", env = "PGSQLITE_RETENTION_BATCH_SIZE", help = "Rows a retention policy deletes per statement")]
+    pub retention_batch_size: usize,
+
+    #[arg(long, default_value = "1000", env = "PGSQLITE_AUTO_ANALYZE_MIN_ROWS", help = "Run ANALYZE on a never-analyzed table with at least this many rows when a join, grouping or subquery first reads it (0 disables)")]
+    pub auto_analyze_min_rows: u64,
+
+    #[arg(long, env = "PGSQLITE_SNAPSHOT_VOLATILE_COLUMNS", value_delimiter = ',', help = "Comma-separated columns `pgsqlite snapshot --normalize` leaves out, as column or table.column where * matches any characters")]
+    pub snapshot_volatile_columns: Vec<String>,
+
     // Migration configuration
     #[arg(long, help = "Run pending database migrations and exit")]
     pub migrate: bool,
+
+    // Remote storage configuration
+    #[arg(long, env = "PGSQLITE_LIBSQL_URL", help = "Serve a remote libSQL/Turso database (libsql://, https:// or http://); --database then holds the local schema and metadata cache")]
+    pub libsql_url: Option<String>,
+
+    #[arg(long, env = "PGSQLITE_LIBSQL_AUTH_TOKEN", hide_env_values = true, help = "Auth token for the libSQL database")]
+    pub libsql_auth_token: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Ways to run pgsqlite other than serving clients
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Interactive SQL shell on a database, without starting a server
+    Shell {
+        /// SQLite database file [default: --database]
+        database: Option<String>,
+    },
+    /// Write the rows of every table as COPY blocks, to compare database states with diff
+    Snapshot {
+        /// SQLite database file [default: --database]
+        database: Option<String>,
+        /// Sort tables and rows and leave out --snapshot-volatile-columns, so equal data gives an identical file
+        #[arg(long)]
+        normalize: bool,
+        /// File to write instead of stdout
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Check that a server accepts connections and answers SELECT 1, for container health checks
+    Ping {
+        /// Server host, or a Unix socket directory when it starts with /
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+        /// Server port [default: --port]
+        #[arg(short, long)]
+        port: Option<u16>,
+        /// Role to connect as
+        #[arg(short = 'U', long, default_value = "postgres")]
+        user: String,
+        /// Database to connect to
+        #[arg(short, long, default_value = "postgres")]
+        dbname: String,
+        /// Seconds to wait for the connection and each response
+        #[arg(short, long, default_value = "3")]
+        timeout: u64,
+    },
 }
 
+/// Settings a reload changes; the others keep the value the server started with
+pub const RELOADABLE_SETTINGS: [&str; 19] = [
+    "log_level",
+    "admin_users",
+    "ssl_cert",
+    "ssl_key",
+    "ssl_ca",
+    "ssl_min_version",
+    "ssl_ciphers",
+    "ssl_alpn",
+    "ssl_session_cache_size",
+    "ssl_session_tickets",
+    "ssl_client_cert",
+    "ssl_cert_user_map",
+    "ssl_cert_check_interval",
+    "auto_analyze_min_rows",
+    "row_desc_cache_size",
+    "query_cache_size",
+    "result_cache_size",
+    "statement_pool_size",
+    "catalog_cache_size",
+];
+
 impl Config {
-    /// Get a configuration instance with all values resolved from CLI args and environment variables
+    /// Get a configuration instance with all values resolved from CLI args, --config-file and environment variables
     pub fn load() -> Self {
-        let config = Config::parse();
-        
-        // Validate SSL configuration
-        if config.ssl && config.no_tcp {
-            eprintln!("Error: SSL cannot be enabled when TCP is disabled (Unix sockets don't support SSL)");
+        let config = match Config::parse().with_config_file() {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
+        };
+        if let Err(e) = config.validate() {
+            eprintln!("Error: {e}");
             std::process::exit(1);
         }
-        
         config
     }
 
+    /// Combinations of settings the server cannot run with
+    fn validate(&self) -> Result<(), String> {
+        // Validate SSL configuration
+        if self.ssl && self.no_tcp {
+            return Err("SSL cannot be enabled when TCP is disabled (Unix sockets don't support SSL)".to_string());
+        }
+        if self.ssl_sni_domain.is_some() && !self.ssl {
+            return Err("--ssl-sni-domain needs --ssl, clients only send a server name in the TLS handshake".to_string());
+        }
+        if self.ssl_client_cert != "off" && (!self.ssl || self.ssl_ca.is_none()) {
+            return Err("--ssl-client-cert needs --ssl and --ssl-ca, the CA client certificates are checked against".to_string());
+        }
+        
+        // Each name has to pick one file
+        let mut names = vec![self.default_database_name()];
+        for (name, _) in &self.databases {
+            if names.contains(name) {
+                return Err(format!("database \"{name}\" is configured more than once"));
+            }
+            names.push(name.clone());
+        }
+        
+        // A read-only server never creates or migrates its database
+        if self.read_only && (self.in_memory || self.database == ":memory:") {
+            return Err("--read-only needs a database file, an in-memory database starts out empty".to_string());
+        }
+        if self.read_only && self.libsql_url.is_some() {
+            return Err("--read-only cannot be used with --libsql-url, which caches the remote schema in --database".to_string());
+        }
+        
+        // Pooled readers query the local database, which only caches the remote schema
+        if self.libsql_url.is_some() && self.use_pooling {
+            return Err("connection pooling cannot be used with --libsql-url".to_string());
+        }
+        
+        Ok(())
+    }
+
+    /// This configuration with the settings of --config-file applied
+    fn with_config_file(self) -> Result<Self, String> {
+        match &self.config_file {
+            Some(path) => Self::parse_with_settings(env::args_os(), read_config_file(path)?),
+            None => Ok(self),
+        }
+    }
+
+    /// The configuration of the command line with `settings` from the configuration file.
+    /// They go in before the command line arguments, so flags win over them, and they
+    /// win over environment variables, which clap only reads for missing arguments.
+    fn parse_with_settings(
+        args: impl IntoIterator<Item = OsString>,
+        settings: Vec<(String, String)>,
+    ) -> Result<Self, String> {
+        let mut file_args = Vec::new();
+        for (name, value) in &settings {
+            let flag = format!("--{}", name.replace('_', "-"));
+            if takes_value(name) {
+                file_args.push(OsString::from(format!("{flag}={value}")));
+            } else if parse_bool(value).ok_or_else(|| format!("parameter \"{name}\" requires a Boolean value"))? {
+                file_args.push(OsString::from(flag));
+            }
+        }
+        let mut args = args.into_iter();
+        let args = args.next().into_iter().chain(file_args).chain(args);
+        let mut config = Config::try_parse_from(args).map_err(|e| e.to_string())?;
+        config.file_settings = settings;
+        Ok(config)
+    }
+
+    /// The configuration after rereading --config-file: changed settings in
+    /// [`RELOADABLE_SETTINGS`] take their new value, the others keep theirs until a
+    /// restart and are returned by name so the reload can say so.
+    pub fn reload(&self) -> Result<(Self, Vec<String>), String> {
+        self.reload_with_args(env::args_os())
+    }
+
+    fn reload_with_args(&self, args: impl IntoIterator<Item = OsString>) -> Result<(Self, Vec<String>), String> {
+        let Some(path) = &self.config_file else {
+            return Ok((self.clone(), Vec::new()));
+        };
+        let settings = read_config_file(path)?;
+        let value = |settings: &[(String, String)], name: &str| settings.iter()
+            .find(|(setting, _)| setting == name)
+            .map(|(_, value)| value.clone());
+
+        let mut needs_restart: Vec<String> = settings.iter().chain(&self.file_settings)
+            .map(|(name, _)| name.clone())
+            .filter(|name| !RELOADABLE_SETTINGS.contains(&name.as_str()))
+            .filter(|name| value(&settings, name) != value(&self.file_settings, name))
+            .collect();
+        needs_restart.sort();
+        needs_restart.dedup();
+
+        let settings = settings.into_iter()
+            .filter(|(name, _)| RELOADABLE_SETTINGS.contains(&name.as_str()))
+            .chain(self.file_settings.iter().filter(|(name, _)| !RELOADABLE_SETTINGS.contains(&name.as_str())).cloned())
+            .collect();
+        let config = Self::parse_with_settings(args, settings)?;
+        config.validate()?;
+        Ok((config, needs_restart))
+    }
+
     /// Get the cache metrics interval as Duration
     pub fn cache_metrics_interval_duration(&self) -> std::time::Duration {
         std::time::Duration::from_secs(self.cache_metrics_interval)
@@ -206,6 +498,88 @@ impl Config {
         std::time::Duration::from_secs(self.schema_cache_ttl)
     }
 
+    /// Get the full Windows named pipe path, prefixing bare names with `\\.\pipe\`
+    pub fn pipe_path(&self) -> String {
+        match &self.pipe_name {
+            Some(name) if name.starts_with(r"\\") => name.clone(),
+            Some(name) => format!(r"\\.\pipe\{name}"),
+            None => format!(r"\\.\pipe\pgsqlite.{}", self.port),
+        }
+    }
+
+    /// server_version_num of --server-version: 150004 for 15.4
+    pub fn server_version_num(&self) -> u32 {
+        let mut parts = self.server_version.split('.').map(|part| part.parse::<u32>().unwrap_or(0));
+        let major = parts.next().unwrap_or(0);
+        let minor = parts.next().unwrap_or(0);
+        major * 10000 + minor
+    }
+
+    /// The name the --database file is listed under: its file name without extension
+    pub fn default_database_name(&self) -> String {
+        if self.in_memory || self.database == ":memory:" {
+            return "memory".to_string();
+        }
+        let path = std::path::Path::new(&self.database);
+        path.file_stem()
+            .or_else(|| path.file_name())
+            .and_then(|name| name.to_str())
+            .unwrap_or(&self.database)
+            .to_string()
+    }
+
+    /// The names of the databases clients can connect to, the --database one first
+    pub fn database_names(&self) -> Vec<String> {
+        std::iter::once(self.default_database_name())
+            .chain(self.databases.iter().map(|(name, _)| name.clone()))
+            .collect()
+    }
+
+    /// The SQLite file of the database called `name`, None for unknown names and in-memory databases
+    pub fn database_path(&self, name: &str) -> Option<&str> {
+        if name == self.default_database_name() {
+            return (!self.in_memory).then_some(self.database.as_str());
+        }
+        self.databases.iter().find(|(database, _)| database == name).map(|(_, path)| path.as_str())
+    }
+
+    /// The name of the database stored in the SQLite file at `path`
+    pub fn database_name_for_path(&self, path: &str) -> Option<String> {
+        let file = std::fs::canonicalize(path).ok()?;
+        self.databases.iter()
+            .find(|(_, database)| std::fs::canonicalize(database).is_ok_and(|database| database == file))
+            .map(|(name, _)| name.clone())
+    }
+
+    /// The database a TLS client asks for through its server name, `tenant` for
+    /// `tenant.<--ssl-sni-domain>`. Other names leave the choice to the startup packet.
+    pub fn sni_database(&self, server_name: &str) -> Option<String> {
+        let domain = self.ssl_sni_domain.as_deref()?.trim_matches('.');
+        let server_name = server_name.trim_end_matches('.').to_ascii_lowercase();
+        let label = server_name.strip_suffix(&domain.to_ascii_lowercase())?.strip_suffix('.')?;
+        (!label.is_empty() && !label.contains('.')).then(|| label.to_string())
+    }
+
+    /// Whether a client whose certificate has the common name `common_name` may connect
+    /// as `user` under --ssl-client-cert verify-full
+    pub fn is_certificate_user(&self, common_name: &str, user: &str) -> bool {
+        let mut pairs = self.ssl_cert_user_map.as_deref().unwrap_or_default()
+            .split(',')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(name, role)| (name.trim(), role.trim()))
+            .filter(|(name, _)| *name == common_name)
+            .peekable();
+        if pairs.peek().is_none() {
+            return common_name == user;
+        }
+        pairs.any(|(_, role)| role == user)
+    }
+
+    /// Whether `user` may connect on the admin port
+    pub fn is_admin_user(&self, user: &str) -> bool {
+        self.admin_users.split(',').map(str::trim).any(|admin| admin == user)
+    }
+
     /// Get the temp directory, defaulting to system temp if not specified
     pub fn get_temp_dir(&self) -> String {
         self.temp_dir.clone().unwrap_or_else(|| {
@@ -214,7 +588,215 @@ impl Config {
     }
 }
 
+/// The `name = value` lines of a configuration file, names checked against the flags.
+/// Blank lines and `#` comments are skipped, values may be single-quoted, and a name
+/// set twice takes its last value, as in postgresql.conf.
+fn read_config_file(path: &str) -> Result<Vec<(String, String)>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("could not read configuration file \"{path}\": {e}"))?;
+    let mut settings: Vec<(String, String)> = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((name, value)) = line.split_once('=') else {
+            return Err(format!("syntax error in file \"{path}\" line {}, expected name = value", number + 1));
+        };
+        let name = name.trim().to_ascii_lowercase().replace('-', "_");
+        let value = value.trim();
+        let value = match value.strip_prefix('\'').and_then(|quoted| quoted.strip_suffix('\'')) {
+            Some(quoted) => quoted.replace("''", "'"),
+            // Comments may follow unquoted values
+            None => value.split('#').next().unwrap_or_default().trim().to_string(),
+        };
+        if name == "config_file" || !Config::command().get_arguments().any(|arg| arg.get_id() == name.as_str() && arg.get_long().is_some()) {
+            return Err(format!("unrecognized configuration parameter \"{name}\" in file \"{path}\" line {}", number + 1));
+        }
+        settings.retain(|(setting, _)| *setting != name);
+        settings.push((name, value));
+    }
+    Ok(settings)
+}
+
+/// Whether the flag for a setting takes a value, rather than being a switch
+fn takes_value(name: &str) -> bool {
+    Config::command().get_arguments()
+        .find(|arg| arg.get_id() == name)
+        .is_some_and(|arg| arg.get_action().takes_values())
+}
+
+/// A Boolean setting the way PostgreSQL spells them
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "on" | "true" | "yes" | "1" => Some(true),
+        "off" | "false" | "no" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+/// Parse a `name=path` entry of --databases
+/// A `major[.minor]` PostgreSQL version, as in server_version
+fn parse_server_version(version: &str) -> Result<String, String> {
+    let valid = version.split('.').count() <= 2
+        && version.split('.').all(|part| !part.is_empty() && part.len() <= 4 && part.bytes().all(|b| b.is_ascii_digit()));
+    if valid && !version.starts_with('0') {
+        Ok(version.to_string())
+    } else {
+        Err(format!("expected a PostgreSQL version like 15.4, got \"{version}\""))
+    }
+}
+
+fn parse_database_entry(entry: &str) -> Result<(String, String), String> {
+    match entry.trim().split_once('=') {
+        Some((name, path)) if !name.trim().is_empty() && !path.trim().is_empty() => {
+            Ok((name.trim().to_string(), path.trim().to_string()))
+        }
+        _ => Err(format!("expected name=path, got \"{entry}\"")),
+    }
+}
+
 // Global configuration instance
 lazy_static::lazy_static! {
     pub static ref CONFIG: Config = Config::load();
+}
+
+/// The configuration as of the last reload; CONFIG keeps the values the server started with
+static CURRENT: Lazy<RwLock<Arc<Config>>> = Lazy::new(|| RwLock::new(Arc::new(CONFIG.clone())));
+
+/// When the configuration was last loaded
+static LOAD_TIME: Lazy<RwLock<chrono::DateTime<chrono::Utc>>> = Lazy::new(|| RwLock::new(chrono::Utc::now()));
+
+/// When the server started, for pg_postmaster_start_time()
+static START_TIME: Lazy<chrono::DateTime<chrono::Utc>> = Lazy::new(chrono::Utc::now);
+
+/// Wakes the server's reload task, see [`request_reload`]
+static RELOAD_REQUESTED: Lazy<tokio::sync::Notify> = Lazy::new(tokio::sync::Notify::new);
+
+/// The configuration with the settings of the last reload
+pub fn current() -> Arc<Config> {
+    CURRENT.read().clone()
+}
+
+/// Switch to a newly loaded configuration
+pub fn set_current(config: Config) {
+    *CURRENT.write() = Arc::new(config);
+    *LOAD_TIME.write() = chrono::Utc::now();
+}
+
+/// When the current configuration was loaded, for pg_conf_load_time()
+pub fn load_time() -> chrono::DateTime<chrono::Utc> {
+    *LOAD_TIME.read()
+}
+
+/// When the server started; the first call fixes it, so the server makes it on startup
+pub fn start_time() -> chrono::DateTime<chrono::Utc> {
+    *START_TIME
+}
+
+/// Ask the server to reread --config-file, as SIGHUP does
+pub fn request_reload() {
+    RELOAD_REQUESTED.notify_one();
+}
+
+/// Resolves once [`request_reload`] was called
+pub async fn reload_requested() {
+    RELOAD_REQUESTED.notified().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn config_file(contents: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_read_config_file() {
+        let file = config_file(
+            "# pgsqlite settings\n\
+             log_level = debug\n\
+             admin-users = 'postgres, o''brien'  \n\
+             row_desc_cache_size = 10 # per database\n\
+             ssl_session_tickets = on\n\
+             log_level = warn\n",
+        );
+        let settings = read_config_file(file.path().to_str().unwrap()).unwrap();
+        assert_eq!(settings, vec![
+            ("admin_users".to_string(), "postgres, o'brien".to_string()),
+            ("row_desc_cache_size".to_string(), "10".to_string()),
+            ("ssl_session_tickets".to_string(), "on".to_string()),
+            ("log_level".to_string(), "warn".to_string()),
+        ]);
+
+        for contents in ["no_such_setting = 1", "config_file = other.conf", "log_level debug"] {
+            let file = config_file(contents);
+            assert!(read_config_file(file.path().to_str().unwrap()).is_err(), "{contents}");
+        }
+    }
+
+    #[test]
+    fn test_reload_keeps_restart_settings() {
+        let file = config_file("log_level = debug\nport = 6000\n");
+        let path = file.path().to_str().unwrap().to_string();
+        let started = Config {
+            config_file: Some(path.clone()),
+            file_settings: read_config_file(&path).unwrap(),
+            log_level: "debug".to_string(),
+            port: 6000,
+            ..Config::parse_from(["pgsqlite"])
+        };
+
+        std::fs::write(&path, "log_level = warn\nport = 7000\nstatement_pool_size = 5\nssl_session_tickets = yes\n").unwrap();
+        let args = || [OsString::from("pgsqlite"), OsString::from("--config-file"), OsString::from(&path)];
+        let (reloaded, needs_restart) = started.reload_with_args(args()).unwrap();
+        assert_eq!(reloaded.log_level, "warn");
+        assert_eq!(reloaded.statement_pool_size, 5);
+        assert!(reloaded.ssl_session_tickets);
+        assert_eq!(reloaded.port, 6000);
+        assert_eq!(needs_restart, vec!["port".to_string()]);
+
+        std::fs::write(&path, "statement_pool_size = lots\n").unwrap();
+        assert!(started.reload_with_args(args()).is_err());
+    }
+
+    #[test]
+    fn test_certificate_users() {
+        let config = Config::parse_from([
+            "pgsqlite", "--ssl", "--ssl-ca", "ca.crt", "--ssl-client-cert", "verify-full",
+            "--ssl-cert-user-map", "alice.example.com=alice, ops=postgres, ops=admin",
+        ]);
+        assert!(config.validate().is_ok());
+        assert!(config.is_certificate_user("alice.example.com", "alice"));
+        assert!(!config.is_certificate_user("alice.example.com", "alice.example.com"));
+        assert!(config.is_certificate_user("ops", "admin"));
+        assert!(config.is_certificate_user("ops", "postgres"));
+        assert!(!config.is_certificate_user("ops", "ops"));
+        assert!(config.is_certificate_user("bob", "bob"));
+        assert!(!config.is_certificate_user("bob", "alice"));
+
+        // Client certificates are checked against --ssl-ca
+        assert!(Config::parse_from(["pgsqlite", "--ssl", "--ssl-client-cert", "verify-ca"]).validate().is_err());
+    }
+
+    #[test]
+    fn test_read_only_needs_a_file() {
+        assert!(Config::parse_from(["pgsqlite", "--read-only", "--database", "replica.db"]).validate().is_ok());
+        assert!(Config::parse_from(["pgsqlite", "--read-only", "--in-memory"]).validate().is_err());
+        assert!(Config::parse_from(["pgsqlite", "--read-only", "--libsql-url", "libsql://db.example.com"]).validate().is_err());
+    }
+
+    #[test]
+    fn test_server_version() {
+        assert_eq!(Config::parse_from(["pgsqlite"]).server_version_num(), 150000);
+        assert_eq!(Config::parse_from(["pgsqlite", "--server-version", "16.4"]).server_version_num(), 160004);
+        assert_eq!(Config::parse_from(["pgsqlite", "--server-version", "17"]).server_version_num(), 170000);
+        for version in ["", "16.", "16.4.1", "v16", "16beta1", "016"] {
+            assert!(Config::try_parse_from(["pgsqlite", "--server-version", version]).is_err(), "{version}");
+        }
+    }
 }
\ No newline at end of file
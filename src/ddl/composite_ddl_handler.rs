@@ -0,0 +1,174 @@
+use rusqlite::Connection;
+use crate::metadata::{CompositeTypes, EnumMetadata};
+use crate::error::PgError;
+use crate::PgSqliteError;
+use tracing::info;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+// Pre-compiled regex patterns
+static CREATE_TYPE_COMPOSITE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)^\s*CREATE\s+TYPE\s+(\w+)\s+AS\s*\((.*)\)\s*;?\s*$").unwrap()
+});
+
+pub struct CompositeDdlHandler;
+
+impl CompositeDdlHandler {
+    /// Check if a query is CREATE TYPE ... AS (...)
+    pub fn is_composite_ddl(query: &str) -> bool {
+        CREATE_TYPE_COMPOSITE_REGEX.is_match(query)
+    }
+
+    /// Handle CREATE TYPE name AS (field type, ...)
+    pub fn handle_create_type(conn: &mut Connection, query: &str) -> Result<(), PgSqliteError> {
+        let (type_name, fields) = Self::parse_create_type(query)?;
+
+        let exists = EnumMetadata::is_enum_type(conn, &type_name).unwrap_or(false)
+            || CompositeTypes::get_type(conn, &type_name)
+                .map_err(|e| PgSqliteError::Protocol(format!("Failed to look up type: {e}")))?
+                .is_some();
+        if exists {
+            return Err(PgSqliteError::Validation(PgError::Generic {
+                code: "42710".to_string(), // duplicate_object
+                message: format!("type \"{type_name}\" already exists"),
+            }));
+        }
+
+        info!("Creating composite type '{}' with {} fields", type_name, fields.len());
+
+        let type_oid = CompositeTypes::create_type(conn, &type_name, &fields)
+            .map_err(|e| PgSqliteError::Protocol(format!("Failed to create composite type: {e}")))?;
+
+        info!("Successfully created composite type '{}' with OID {}", type_name, type_oid);
+        Ok(())
+    }
+
+    /// Drop a composite type, returning false if no composite type has this name.
+    ///
+    /// Without CASCADE the type cannot be dropped while columns use it. With CASCADE
+    /// the columns keep their record text but lose their validation triggers.
+    pub fn drop_type(conn: &Connection, type_name: &str, cascade: bool) -> Result<bool, PgSqliteError> {
+        let Some(composite) = CompositeTypes::get_type(conn, type_name)
+            .map_err(|e| PgSqliteError::Protocol(format!("Failed to look up type: {e}")))? else {
+            return Ok(false);
+        };
+
+        let usage = CompositeTypes::get_type_usage(conn, &composite.type_name)
+            .map_err(|e| PgSqliteError::Protocol(format!("Failed to check dependencies: {e}")))?;
+        if !usage.is_empty() && !cascade {
+            return Err(PgSqliteError::Validation(PgError::Generic {
+                code: "2BP01".to_string(), // dependent_objects_still_exist
+                message: format!("cannot drop type {type_name} because other objects depend on it"),
+            }));
+        }
+        for (table_name, column_name) in usage {
+            CompositeTypes::drop_validation_triggers(conn, &table_name, &column_name)
+                .map_err(|e| PgSqliteError::Protocol(format!("Failed to drop composite triggers: {e}")))?;
+        }
+
+        CompositeTypes::drop_type(conn, composite.type_oid)
+            .map_err(|e| PgSqliteError::Protocol(format!("Failed to drop composite type: {e}")))?;
+
+        info!("Successfully dropped composite type '{}'", type_name);
+        Ok(true)
+    }
+
+    /// Create the validation triggers for a new column if its type is a composite type.
+    /// Returns whether the column is a composite column.
+    pub fn create_column_triggers(
+        conn: &Connection,
+        table_name: &str,
+        column_name: &str,
+        pg_type: &str,
+    ) -> Result<bool, rusqlite::Error> {
+        // Arrays of a composite type check each of their records
+        let element_type = pg_type.trim().trim_end_matches("[]").trim_end();
+        match CompositeTypes::get_type(conn, element_type)? {
+            Some(composite) if element_type.len() < pg_type.trim().len() => {
+                CompositeTypes::create_array_validation_triggers(conn, table_name, column_name, &composite)?;
+                Ok(true)
+            }
+            Some(composite) => {
+                CompositeTypes::create_validation_triggers(conn, table_name, column_name, &composite)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Parse CREATE TYPE ... AS (...) into the type name and its (name, type) fields
+    fn parse_create_type(query: &str) -> Result<(String, Vec<(String, String)>), PgSqliteError> {
+        let captures = CREATE_TYPE_COMPOSITE_REGEX.captures(query)
+            .ok_or_else(|| PgSqliteError::Protocol("Invalid CREATE TYPE AS syntax".to_string()))?;
+
+        let type_name = captures[1].to_lowercase();
+        let mut fields: Vec<(String, String)> = Vec::new();
+
+        for definition in Self::split_fields(&captures[2]) {
+            let definition = definition.trim();
+            if definition.is_empty() {
+                continue;
+            }
+            let (name, pg_type) = definition.split_once(char::is_whitespace)
+                .ok_or_else(|| PgSqliteError::Protocol(format!("Missing type for composite field '{definition}'")))?;
+            let name = name.trim_matches('"').to_string();
+            if fields.iter().any(|(existing, _)| existing.eq_ignore_ascii_case(&name)) {
+                return Err(PgSqliteError::Validation(PgError::Generic {
+                    code: "42701".to_string(), // duplicate_column
+                    message: format!("column \"{name}\" specified more than once"),
+                }));
+            }
+            fields.push((name, pg_type.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()));
+        }
+
+        Ok((type_name, fields))
+    }
+
+    /// Split a field list on commas outside parentheses, so NUMERIC(10, 2) stays whole
+    fn split_fields(fields: &str) -> Vec<&str> {
+        let mut parts = Vec::new();
+        let mut depth = 0;
+        let mut start = 0;
+        for (i, c) in fields.char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                ',' if depth == 0 => {
+                    parts.push(&fields[start..i]);
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        parts.push(&fields[start..]);
+        parts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_create_type() {
+        let (type_name, fields) = CompositeDdlHandler::parse_create_type(
+            "CREATE TYPE Inventory_Item AS (name TEXT, supplier_id INTEGER, price NUMERIC(10, 2))"
+        ).unwrap();
+        assert_eq!(type_name, "inventory_item");
+        assert_eq!(fields, vec![
+            ("name".to_string(), "text".to_string()),
+            ("supplier_id".to_string(), "integer".to_string()),
+            ("price".to_string(), "numeric(10, 2)".to_string()),
+        ]);
+
+        assert!(CompositeDdlHandler::parse_create_type("CREATE TYPE t AS (a int, A text)").is_err());
+    }
+
+    #[test]
+    fn test_is_composite_ddl() {
+        assert!(CompositeDdlHandler::is_composite_ddl("CREATE TYPE point2 AS (x INT, y INT)"));
+        assert!(CompositeDdlHandler::is_composite_ddl("create type pair as(a text, b text);"));
+        assert!(!CompositeDdlHandler::is_composite_ddl("CREATE TYPE mood AS ENUM ('happy')"));
+        assert!(!CompositeDdlHandler::is_composite_ddl("CREATE TABLE t (id INT)"));
+    }
+}
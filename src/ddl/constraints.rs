@@ -0,0 +1,200 @@
+use super::foreign_keys::DML_TARGET;
+use crate::query::sql_utils::unquote_identifier;
+use crate::error::PgError;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rusqlite::{Connection, OptionalExtension};
+
+/// `UNIQUE constraint failed: t.a, t.b`, or `index 'name'` for expression indexes
+static UNIQUE_FAILED: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"UNIQUE constraint failed: (?:index '([^']+)'|(.+))$").unwrap()
+});
+
+/// `CHECK constraint failed: name`, with the expression in place of the name for unnamed constraints
+static CHECK_FAILED: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?s)CHECK constraint failed: (.+)$").unwrap()
+});
+
+static NOT_NULL_FAILED: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"NOT NULL constraint failed: (.+)\.(.+)$").unwrap()
+});
+
+/// `CONSTRAINT name UNIQUE (columns)` and `CONSTRAINT name PRIMARY KEY (columns)` table constraints
+static NAMED_TABLE_KEY: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)\bCONSTRAINT\s+("(?:[^"]|"")+"|[\w$]+)\s+(?:UNIQUE|PRIMARY\s+KEY)\s*\(([^)]*)\)"#).unwrap()
+});
+
+/// `column type ... CONSTRAINT name UNIQUE` and `... CONSTRAINT name PRIMARY KEY` column constraints
+static NAMED_COLUMN_KEY: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)(?:^|[(,])\s*("(?:[^"]|"")+"|[\w$]+)\s[^,(]*?\bCONSTRAINT\s+("(?:[^"]|"")+"|[\w$]+)\s+(?:UNIQUE|PRIMARY\s+KEY)\b"#).unwrap()
+});
+
+static IDENTIFIER: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Za-z_][\w$]*$").unwrap());
+
+/// UNIQUE, PRIMARY KEY, CHECK and NOT NULL failures as PostgreSQL reports them.
+///
+/// SQLite's messages name the table and columns, or the CHECK expression, but not
+/// the constraint; `PgError::from_message` makes do with default names, and this
+/// looks up the ones pg_constraint and pg_indexes show, so ORMs can tell which
+/// constraint failed. Foreign keys are handled by [`super::ForeignKeys`].
+pub struct Constraints;
+
+impl Constraints {
+    /// Whether SQLite rejected a statement for one of the constraints handled here
+    pub fn is_violation(error: &rusqlite::Error) -> bool {
+        use rusqlite::ffi::{SQLITE_CONSTRAINT_CHECK, SQLITE_CONSTRAINT_NOTNULL, SQLITE_CONSTRAINT_PRIMARYKEY, SQLITE_CONSTRAINT_UNIQUE};
+        matches!(error, rusqlite::Error::SqliteFailure(e, _)
+            if matches!(e.extended_code, SQLITE_CONSTRAINT_UNIQUE | SQLITE_CONSTRAINT_PRIMARYKEY | SQLITE_CONSTRAINT_CHECK | SQLITE_CONSTRAINT_NOTNULL))
+    }
+
+    /// The PostgreSQL error for a failure of `sql`, whose target table names CHECK constraints
+    pub fn explain_violation(conn: &Connection, sql: &str, error: &rusqlite::Error) -> Option<PgError> {
+        let rusqlite::Error::SqliteFailure(_, Some(message)) = error else {
+            return None;
+        };
+
+        if let Some(caps) = NOT_NULL_FAILED.captures(message) {
+            return Some(PgError::NotNullViolation { table_name: caps[1].to_string(), column_name: caps[2].to_string() });
+        }
+
+        if let Some(caps) = UNIQUE_FAILED.captures(message) {
+            if let Some(index) = caps.get(1) {
+                let table = conn.query_row("SELECT tbl_name FROM sqlite_master WHERE type = 'index' AND name = ?1", [index.as_str()], |row| row.get(0))
+                    .optional().ok()??;
+                return Some(PgError::UniqueViolation { table_name: table, constraint_name: index.as_str().to_string(), detail: None });
+            }
+            let qualified: Vec<&str> = caps[2].split(", ").collect();
+            let table = qualified.first()?.rsplit_once('.')?.0.to_string();
+            let columns: Vec<String> = qualified.iter()
+                .map(|column| column.rsplit_once('.').map_or(*column, |(_, name)| name).to_string())
+                .collect();
+            let constraint_name = Self::unique_name(conn, &table, &columns).ok().flatten()
+                .unwrap_or_else(|| format!("{table}_{}_key", columns.join("_")));
+            return Some(PgError::UniqueViolation { table_name: table, constraint_name, detail: None });
+        }
+
+        let check = CHECK_FAILED.captures(message)?[1].trim().to_string();
+        let table = unquote_identifier(&DML_TARGET.captures(sql)?[2]);
+        let recorded: Option<String> = conn.query_row(
+            "SELECT conname FROM __pgsqlite_check_constraints WHERE tablename = ?1 AND replace(consrc, ' ', '') = replace('CHECK (' || ?2 || ')', ' ', '')",
+            [&table, &check],
+            |row| row.get(0),
+        ).optional().ok().flatten();
+        // Named constraints are reported by name, unnamed ones by their expression
+        let constraint_name = recorded.unwrap_or_else(|| if IDENTIFIER.is_match(&check) { check } else { format!("{table}_check") });
+        Some(PgError::CheckViolation { table_name: table, constraint_name })
+    }
+
+    /// The name of the primary key or unique index over exactly `columns`
+    fn unique_name(conn: &Connection, table: &str, columns: &[String]) -> rusqlite::Result<Option<String>> {
+        let same_columns = |other: &[String]| other.len() == columns.len() && other.iter().zip(columns).all(|(a, b)| a.eq_ignore_ascii_case(b));
+
+        // Constraints declared with a name keep it
+        let sql: Option<String> = conn.query_row("SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?1", [table], |row| row.get(0))
+            .optional()?;
+        if let Some(sql) = sql {
+            let declared = NAMED_TABLE_KEY.captures_iter(&sql)
+                .map(|caps| (unquote_identifier(&caps[1]), caps[2].split(',').map(unquote_identifier).collect::<Vec<_>>()))
+                .chain(NAMED_COLUMN_KEY.captures_iter(&sql).map(|caps| (unquote_identifier(&caps[2]), vec![unquote_identifier(&caps[1])])));
+            for (name, declared_columns) in declared {
+                if same_columns(&declared_columns) {
+                    return Ok(Some(name));
+                }
+            }
+        }
+
+        let indexes: Vec<(String, String)> = {
+            let mut stmt = conn.prepare("SELECT name, origin FROM pragma_index_list(?1) WHERE \"unique\" = 1 ORDER BY seq DESC")?;
+            let rows = stmt.query_map([table], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        for (index, origin) in indexes {
+            let mut stmt = conn.prepare("SELECT name FROM pragma_index_info(?1) ORDER BY seqno")?;
+            let indexed: Vec<String> = stmt.query_map([&index], |row| Ok(row.get::<_, Option<String>>(0)?.unwrap_or_default()))?
+                .collect::<rusqlite::Result<_>>()?;
+            if same_columns(&indexed) {
+                // Implicit indexes get PostgreSQL's constraint names, as in pg_indexes
+                return Ok(Some(match origin.as_str() {
+                    "pk" => format!("{table}_pkey"),
+                    "u" => format!("{table}_{}_key", columns.join("_")),
+                    _ => index,
+                }));
+            }
+        }
+
+        // An INTEGER PRIMARY KEY is the rowid and has no index
+        let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?1) WHERE pk > 0 ORDER BY pk")?;
+        let primary_key: Vec<String> = stmt.query_map([table], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+        Ok(same_columns(&primary_key).then(|| format!("{table}_pkey")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE __pgsqlite_check_constraints (tablename TEXT, conname TEXT, consrc TEXT);
+             CREATE TABLE accounts (
+                 id INTEGER PRIMARY KEY,
+                 email TEXT UNIQUE,
+                 region TEXT NOT NULL,
+                 code TEXT,
+                 serial TEXT CONSTRAINT accounts_serial_unique UNIQUE,
+                 balance INTEGER CHECK (balance >= 0),
+                 CONSTRAINT balance_cap CHECK (balance < 1000),
+                 UNIQUE (region, code)
+             );
+             CREATE UNIQUE INDEX accounts_lower_region ON accounts (lower(region));
+             CREATE UNIQUE INDEX accounts_code_idx ON accounts (code);
+             INSERT INTO __pgsqlite_check_constraints VALUES ('accounts', 'accounts_balance_check', 'CHECK (balance >= 0)');
+             INSERT INTO accounts VALUES (1, 'a@x', 'north', 'a', 'S1', 5);"
+        ).unwrap();
+        conn
+    }
+
+    fn explain(conn: &Connection, sql: &str) -> PgError {
+        let error = conn.execute(sql, []).unwrap_err();
+        assert!(Constraints::is_violation(&error), "{error:?}");
+        Constraints::explain_violation(conn, sql, &error).unwrap()
+    }
+
+    fn unique_name(error: PgError) -> String {
+        match error {
+            PgError::UniqueViolation { table_name, constraint_name, .. } => {
+                assert_eq!(table_name, "accounts");
+                constraint_name
+            }
+            other => panic!("expected a unique violation, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_unique_names() {
+        let conn = connection();
+        assert_eq!(unique_name(explain(&conn, "INSERT INTO accounts (id, region) VALUES (1, 'south')")), "accounts_pkey");
+        assert_eq!(unique_name(explain(&conn, "INSERT INTO accounts (id, email, region) VALUES (2, 'a@x', 'south')")), "accounts_email_key");
+        assert_eq!(unique_name(explain(&conn, "INSERT INTO accounts (id, region) VALUES (2, 'NORTH')")), "accounts_lower_region");
+        assert_eq!(unique_name(explain(&conn, "INSERT INTO accounts (id, region, code) VALUES (2, 'south', 'a')")), "accounts_code_idx");
+        assert_eq!(unique_name(explain(&conn, "INSERT INTO accounts (id, region, serial) VALUES (2, 'south', 'S1')")), "accounts_serial_unique");
+    }
+
+    #[test]
+    fn test_check_and_not_null() {
+        let conn = connection();
+        match explain(&conn, "UPDATE accounts SET balance = -1") {
+            PgError::CheckViolation { table_name, constraint_name } => assert_eq!((table_name.as_str(), constraint_name.as_str()), ("accounts", "accounts_balance_check")),
+            other => panic!("{other:?}"),
+        }
+        match explain(&conn, "UPDATE \"accounts\" SET balance = 5000") {
+            PgError::CheckViolation { constraint_name, .. } => assert_eq!(constraint_name, "balance_cap"),
+            other => panic!("{other:?}"),
+        }
+        match explain(&conn, "INSERT INTO accounts (id) VALUES (3)") {
+            PgError::NotNullViolation { table_name, column_name } => assert_eq!((table_name.as_str(), column_name.as_str()), ("accounts", "region")),
+            other => panic!("{other:?}"),
+        }
+    }
+}
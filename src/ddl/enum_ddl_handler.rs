@@ -1,4 +1,5 @@
-use rusqlite::Connection;
+use rusqlite::{Connection, OptionalExtension};
+use crate::error::PgError;
 use crate::metadata::EnumMetadata;
 use crate::cache::global_enum_cache;
 use crate::PgSqliteError;
@@ -12,7 +13,11 @@ static CREATE_TYPE_ENUM_REGEX: Lazy<Regex> = Lazy::new(|| {
 });
 
 static ALTER_TYPE_ADD_VALUE_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"(?i)ALTER\s+TYPE\s+(\w+)\s+ADD\s+VALUE\s+(?:IF\s+NOT\s+EXISTS\s+)?'([^']+)'(?:\s+(BEFORE|AFTER)\s+'([^']+)')?").unwrap()
+    Regex::new(r"(?i)ALTER\s+TYPE\s+(\w+)\s+ADD\s+VALUE\s+(IF\s+NOT\s+EXISTS\s+)?'([^']+)'(?:\s+(BEFORE|AFTER)\s+'([^']+)')?").unwrap()
+});
+
+static ALTER_TYPE_RENAME_VALUE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)ALTER\s+TYPE\s+(\w+)\s+RENAME\s+VALUE\s+'([^']+)'\s+TO\s+'([^']+)'").unwrap()
 });
 
 static DROP_TYPE_REGEX: Lazy<Regex> = Lazy::new(|| {
@@ -44,15 +49,30 @@ impl EnumDdlHandler {
         false
     }
     
-    /// Handle ENUM-related DDL statements
+    /// The command tag completing an ENUM DDL statement
+    pub fn command_tag(query: &str) -> &'static str {
+        let upper = query.trim().to_uppercase();
+        if upper.starts_with("CREATE TYPE") {
+            "CREATE TYPE"
+        } else if upper.starts_with("ALTER TYPE") {
+            "ALTER TYPE"
+        } else if upper.starts_with("DROP TYPE") {
+            "DROP TYPE"
+        } else {
+            "OK"
+        }
+    }
+    
+    /// Handle ENUM-related DDL statements, returning the tables whose columns or
+    /// stored values changed, as DROP TYPE ... CASCADE and RENAME VALUE do
     pub fn handle_enum_ddl(
         conn: &mut Connection,
         query: &str,
-    ) -> Result<(), PgSqliteError> {
+    ) -> Result<Vec<String>, PgSqliteError> {
         let upper = query.trim().to_uppercase();
         
         if upper.starts_with("CREATE TYPE") && upper.contains("AS ENUM") {
-            Self::handle_create_type_enum(conn, query)
+            Self::handle_create_type_enum(conn, query).map(|()| Vec::new())
         } else if upper.starts_with("ALTER TYPE") {
             Self::handle_alter_type(conn, query)
         } else if upper.starts_with("DROP TYPE") {
@@ -80,8 +100,10 @@ impl EnumDdlHandler {
             None, // Use default namespace
         ).map_err(|e| PgSqliteError::Protocol(format!("Failed to create ENUM type: {e}")))?;
         
-        // Clear the cache
+        // Refresh the cache, through which type descriptions resolve the type's name to its OID
         global_enum_cache().invalidate_type(type_oid);
+        global_enum_cache().get_enum_type(conn, &type_name)
+            .map_err(|e| PgSqliteError::Protocol(format!("Failed to get ENUM type: {e}")))?;
         
         info!("Successfully created ENUM type '{}' with OID {}", type_name, type_oid);
         Ok(())
@@ -168,24 +190,34 @@ impl EnumDdlHandler {
     fn handle_alter_type(
         conn: &mut Connection,
         query: &str,
-    ) -> Result<(), PgSqliteError> {
+    ) -> Result<Vec<String>, PgSqliteError> {
         // Parse ALTER TYPE for ADD VALUE
         if let Some(captures) = ALTER_TYPE_ADD_VALUE_REGEX.captures(query) {
             let type_name = captures.get(1).unwrap().as_str();
-            let new_value = captures.get(2).unwrap().as_str();
-            let position = captures.get(3).map(|m| m.as_str());
-            let relative_value = captures.get(4).map(|m| m.as_str());
+            let if_not_exists = captures.get(2).is_some();
+            let new_value = captures.get(3).unwrap().as_str();
+            let position = captures.get(4).map(|m| m.as_str().to_uppercase());
+            let relative_value = captures.get(5).map(|m| m.as_str());
             
             info!("Adding value '{}' to ENUM type '{}'", new_value, type_name);
             
             // Get type OID for cache invalidation
-            let type_oid = EnumMetadata::get_enum_type(conn, type_name)
-                .map_err(|e| PgSqliteError::Protocol(format!("Failed to get ENUM type: {e}")))?
-                .ok_or_else(|| PgSqliteError::Protocol(format!("Type '{type_name}' does not exist")))?
-                .type_oid;
+            let type_oid = Self::existing_type_oid(conn, type_name)?;
+            
+            if EnumMetadata::get_enum_value(conn, type_oid, new_value)?.is_some() {
+                if if_not_exists {
+                    info!("ENUM label '{}' already exists, skipping", new_value);
+                    return Ok(Vec::new());
+                }
+                return Err(pg_error("42710", format!("enum label \"{new_value}\" already exists")));
+            }
+            if let Some(relative_value) = relative_value
+                && EnumMetadata::get_enum_value(conn, type_oid, relative_value)?.is_none() {
+                return Err(pg_error("22023", format!("\"{relative_value}\" is not an existing enum label")));
+            }
             
             // Add the value
-            match position {
+            match position.as_deref() {
                 Some("BEFORE") => {
                     EnumMetadata::add_enum_value(conn, type_name, new_value, relative_value, None)
                 }
@@ -197,22 +229,130 @@ impl EnumDdlHandler {
                 }
             }.map_err(|e| PgSqliteError::Protocol(format!("Failed to add ENUM value: {e}")))?;
             
-            // Clear the cache
-            global_enum_cache().invalidate_type(type_oid);
+            Self::refresh_cache(conn, type_name, type_oid)?;
             
             info!("Successfully added value '{}' to ENUM type '{}'", new_value, type_name);
-            return Ok(());
+            return Ok(Vec::new());
         }
         
-        // TODO: Handle RENAME VALUE, RENAME TO, etc.
+        // Parse ALTER TYPE for RENAME VALUE
+        if let Some(captures) = ALTER_TYPE_RENAME_VALUE_REGEX.captures(query) {
+            let type_name = captures.get(1).unwrap().as_str();
+            let old_value = captures.get(2).unwrap().as_str();
+            let new_value = captures.get(3).unwrap().as_str();
+            
+            info!("Renaming value '{}' of ENUM type '{}' to '{}'", old_value, type_name, new_value);
+            
+            let type_oid = Self::existing_type_oid(conn, type_name)?;
+            if EnumMetadata::get_enum_value(conn, type_oid, old_value)?.is_none() {
+                return Err(pg_error("22023", format!("\"{old_value}\" is not an existing enum label")));
+            }
+            if EnumMetadata::get_enum_value(conn, type_oid, new_value)?.is_some() {
+                return Err(pg_error("42710", format!("enum label \"{new_value}\" already exists")));
+            }
+            
+            // The label and the values stored under it change together
+            let savepoint = conn.savepoint()?;
+            EnumMetadata::rename_enum_value(&savepoint, type_name, old_value, new_value)?;
+            let tables = Self::rename_stored_values(&savepoint, type_name, old_value, new_value)?;
+            savepoint.commit()?;
+            
+            Self::refresh_cache(conn, type_name, type_oid)?;
+            
+            info!("Successfully renamed value '{}' of ENUM type '{}' to '{}'", old_value, type_name, new_value);
+            return Ok(tables);
+        }
+        
+        // TODO: Handle RENAME TO, OWNER TO, etc.
         Err(PgSqliteError::Protocol("Unsupported ALTER TYPE operation".to_string()))
     }
     
+    /// The OID of an ENUM type, or the error PostgreSQL reports for an unknown type
+    fn existing_type_oid(conn: &Connection, type_name: &str) -> Result<i32, PgSqliteError> {
+        EnumMetadata::get_enum_type(conn, type_name)
+            .map_err(|e| PgSqliteError::Protocol(format!("Failed to get ENUM type: {e}")))?
+            .map(|enum_type| enum_type.type_oid)
+            .ok_or_else(|| pg_error("42704", format!("type \"{type_name}\" does not exist")))
+    }
+    
+    /// Reload an altered ENUM type into the cache
+    fn refresh_cache(conn: &Connection, type_name: &str, type_oid: i32) -> Result<(), PgSqliteError> {
+        global_enum_cache().invalidate_type(type_oid);
+        global_enum_cache().get_enum_type(conn, type_name)
+            .map_err(|e| PgSqliteError::Protocol(format!("Failed to get ENUM type: {e}")))?;
+        Ok(())
+    }
+    
+    /// The (table, column) pairs of the columns using an ENUM type, including arrays of it
+    fn enum_usage(conn: &Connection, type_name: &str) -> Result<Vec<(String, String)>, PgSqliteError> {
+        let mut stmt = conn.prepare("SELECT table_name, column_name FROM __pgsqlite_enum_usage WHERE enum_type = ?1")
+            .map_err(|e| PgSqliteError::Protocol(format!("Failed to prepare usage query: {e}")))?;
+        stmt.query_map([type_name], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| PgSqliteError::Protocol(format!("Failed to query usage: {e}")))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| PgSqliteError::Protocol(format!("Failed to collect usage: {e}")))
+    }
+    
+    /// Whether a column stores arrays, which are kept as JSON
+    fn is_array_column(conn: &Connection, table_name: &str, column_name: &str) -> Result<bool, PgSqliteError> {
+        let array_column = conn.query_row(
+            "SELECT 1 FROM __pgsqlite_array_types WHERE table_name = ?1 AND column_name = ?2",
+            [table_name, column_name],
+            |_| Ok(()),
+        ).optional();
+        match array_column {
+            Ok(found) => Ok(found.is_some()),
+            Err(rusqlite::Error::SqliteFailure(_, Some(message))) if message.contains("no such table") => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    /// Rewrite the values stored under a renamed label, returning the tables that use the type
+    fn rename_stored_values(
+        conn: &Connection,
+        type_name: &str,
+        old_value: &str,
+        new_value: &str,
+    ) -> Result<Vec<String>, PgSqliteError> {
+        let mut tables = Vec::new();
+        for (table_name, column_name) in Self::enum_usage(conn, type_name)? {
+            if Self::is_array_column(conn, &table_name, &column_name)? {
+                let mut stmt = conn.prepare(&format!(
+                    r#"SELECT DISTINCT "{column_name}" FROM "{table_name}"
+                    WHERE json_valid("{column_name}") AND EXISTS (
+                        SELECT 1 FROM json_tree("{table_name}"."{column_name}") j WHERE j.type = 'text' AND j.value = ?1
+                    )"#
+                ))?;
+                let arrays = stmt.query_map([old_value], |row| row.get::<_, String>(0))?
+                    .collect::<Result<Vec<_>, _>>()?;
+                for array in arrays {
+                    let Ok(mut elements) = serde_json::from_str::<serde_json::Value>(&array) else {
+                        continue;
+                    };
+                    rename_label(&mut elements, old_value, new_value);
+                    conn.execute(
+                        &format!(r#"UPDATE "{table_name}" SET "{column_name}" = ?1 WHERE "{column_name}" = ?2"#),
+                        [elements.to_string(), array],
+                    )?;
+                }
+            } else {
+                conn.execute(
+                    &format!(r#"UPDATE "{table_name}" SET "{column_name}" = ?1 WHERE "{column_name}" = ?2"#),
+                    [new_value, old_value],
+                )?;
+            }
+            if !tables.contains(&table_name) {
+                tables.push(table_name);
+            }
+        }
+        Ok(tables)
+    }
+    
     /// Handle DROP TYPE statements
     fn handle_drop_type(
         conn: &mut Connection,
         query: &str,
-    ) -> Result<(), PgSqliteError> {
+    ) -> Result<Vec<String>, PgSqliteError> {
         // Parse DROP TYPE
         let captures = DROP_TYPE_REGEX.captures(query)
             .ok_or_else(|| PgSqliteError::Protocol("Invalid DROP TYPE syntax".to_string()))?;
@@ -227,57 +367,23 @@ impl EnumDdlHandler {
         let enum_type = EnumMetadata::get_enum_type(conn, type_name)
             .map_err(|e| PgSqliteError::Protocol(format!("Failed to get ENUM type: {e}")))?;
         
+        let mut tables = Vec::new();
         if let Some(et) = enum_type {
-            // Check if type is used in any tables (unless CASCADE)
-            if !cascade {
-                // Check for dependencies in __pgsqlite_enum_usage table
-                let check_sql = "
-                    SELECT table_name 
-                    FROM __pgsqlite_enum_usage 
-                    WHERE enum_type = ?1
-                ";
-                
-                let mut stmt = conn.prepare(check_sql)
-                    .map_err(|e| PgSqliteError::Protocol(format!("Failed to prepare dependency check: {e}")))?;
-                
-                let dependent_tables: Vec<String> = stmt.query_map([type_name], |row| {
-                    row.get::<_, String>(0)
-                }).map_err(|e| PgSqliteError::Protocol(format!("Failed to check dependencies: {e}")))?
-                    .collect::<Result<Vec<_>, _>>()
-                    .map_err(|e| PgSqliteError::Protocol(format!("Failed to collect dependencies: {e}")))?;
-                
-                if !dependent_tables.is_empty() {
-                    return Err(PgSqliteError::Protocol(
-                        format!("cannot drop type {type_name} because other objects depend on it")
-                    ));
-                }
-            } else {
-                // CASCADE specified - drop all triggers and clean up usage
-                let usage_sql = "
-                    SELECT table_name, column_name 
-                    FROM __pgsqlite_enum_usage 
-                    WHERE enum_type = ?1
-                ";
-                
-                let mut stmt = conn.prepare(usage_sql)
-                    .map_err(|e| PgSqliteError::Protocol(format!("Failed to prepare usage query: {e}")))?;
-                
-                let usages: Vec<(String, String)> = stmt.query_map([type_name], |row| {
-                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
-                }).map_err(|e| PgSqliteError::Protocol(format!("Failed to query usage: {e}")))?
-                    .collect::<Result<Vec<_>, _>>()
-                    .map_err(|e| PgSqliteError::Protocol(format!("Failed to collect usage: {e}")))?;
-                
-                // Drop all triggers
-                for (table_name, column_name) in usages {
-                    crate::metadata::EnumTriggers::drop_enum_validation_triggers(
-                        conn, &table_name, &column_name, type_name
-                    ).ok(); // Ignore errors on trigger drop
+            let usages = Self::enum_usage(conn, type_name)?;
+            if !usages.is_empty() && !cascade {
+                return Err(pg_error(
+                    "2BP01", // dependent_objects_still_exist
+                    format!("cannot drop type {type_name} because other objects depend on it"),
+                ));
+            }
+            
+            // CASCADE drops the columns of the type along with their triggers and usage records
+            for (table_name, column_name) in usages {
+                for table in crate::query::alter_table_handler::drop_dependent_column(conn, &table_name, &column_name)? {
+                    if !tables.contains(&table) {
+                        tables.push(table);
+                    }
                 }
-                
-                // Clean up enum usage records
-                conn.execute("DELETE FROM __pgsqlite_enum_usage WHERE enum_type = ?1", [type_name])
-                    .map_err(|e| PgSqliteError::Protocol(format!("Failed to clean enum usage: {e}")))?;
             }
             
             // Drop the type
@@ -288,14 +394,33 @@ impl EnumDdlHandler {
             global_enum_cache().invalidate_type(et.type_oid);
             
             info!("Successfully dropped ENUM type '{}'", type_name);
+        } else if super::CompositeDdlHandler::drop_type(conn, type_name, cascade)? {
+            // Not an ENUM, but a composite type of that name was dropped
         } else if !if_exists {
-            return Err(PgSqliteError::Protocol(format!("Type '{type_name}' does not exist")));
+            return Err(pg_error("42704", format!("type \"{type_name}\" does not exist")));
         }
         
-        Ok(())
+        Ok(tables)
     }
 }
 
+/// Replace every string equal to a renamed ENUM label in a stored array
+fn rename_label(value: &mut serde_json::Value, old_value: &str, new_value: &str) {
+    match value {
+        serde_json::Value::String(label) if label == old_value => *label = new_value.to_string(),
+        serde_json::Value::Array(elements) => {
+            for element in elements {
+                rename_label(element, old_value, new_value);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn pg_error(code: &str, message: String) -> PgSqliteError {
+    PgSqliteError::Validation(PgError::Generic { code: code.to_string(), message })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,227 @@
+use crate::protocol::BackendMessage;
+use crate::protocol::messages::NoticeResponse;
+use crate::session::{SessionState, SessionStorage};
+use crate::PgSqliteError;
+use futures::SinkExt;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rusqlite::Connection;
+use std::sync::Arc;
+use tokio_util::codec::Framed;
+use tracing::{debug, info};
+
+static TABLE_DDL_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)^\s*(?:CREATE\s+(?:(?:GLOBAL\s+|LOCAL\s+)?(?:TEMP|TEMPORARY|UNLOGGED)\s+)?TABLE\s+(?:IF\s+NOT\s+EXISTS\s+)?|ALTER\s+TABLE\s+(?:IF\s+EXISTS\s+)?(?:ONLY\s+)?)(?:"?public"?\.)?"?([A-Za-z_][A-Za-z0-9_$]*)"?"#).unwrap()
+});
+
+/// An index to create over the referencing columns of a foreign key
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForeignKeyIndex {
+    pub name: String,
+    pub table: String,
+    pub columns: Vec<String>,
+}
+
+impl ForeignKeyIndex {
+    pub fn create_sql(&self) -> String {
+        let columns: Vec<String> = self.columns.iter().map(|c| format!("\"{c}\"")).collect();
+        format!("CREATE INDEX \"{}\" ON \"{}\" ({})", self.name, self.table, columns.join(", "))
+    }
+}
+
+/// Indexes the referencing columns of foreign keys at DDL time.
+///
+/// PostgreSQL (and SQLite) only index the referenced side of a foreign key, so
+/// joins and cascading deletes on the referencing side scan the table unless
+/// the user adds an index. With `--auto-index-foreign-keys` or
+/// `SET pgsqlite.auto_index_foreign_keys = on`, CREATE TABLE and ALTER TABLE add
+/// one for every foreign key no existing index covers and report it as a NOTICE.
+pub struct ForeignKeyIndexer;
+
+impl ForeignKeyIndexer {
+    /// The table a CREATE TABLE or ALTER TABLE statement defines or changes
+    pub fn table_from_ddl(query: &str) -> Option<String> {
+        TABLE_DDL_PATTERN.captures(query).map(|caps| caps[1].to_string())
+    }
+
+    /// Foreign keys of `table` whose referencing columns are not the leading columns of any index
+    pub fn missing_indexes(conn: &Connection, table: &str) -> rusqlite::Result<Vec<ForeignKeyIndex>> {
+        let mut foreign_keys: Vec<(i64, Vec<String>)> = Vec::new();
+        {
+            let mut stmt = conn.prepare("SELECT id, \"from\" FROM pragma_foreign_key_list(?1) ORDER BY id, seq")?;
+            let rows = stmt.query_map([table], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+            for row in rows {
+                let (id, column) = row?;
+                match foreign_keys.last_mut() {
+                    Some((last_id, columns)) if *last_id == id => columns.push(column),
+                    _ => foreign_keys.push((id, vec![column])),
+                }
+            }
+        }
+        if foreign_keys.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut indexed = Self::index_columns(conn, table)?;
+        let mut missing = Vec::new();
+        for (_, columns) in foreign_keys {
+            if indexed.iter().any(|index| Self::covers(index, &columns)) {
+                continue;
+            }
+            let name = Self::unused_name(conn, table, &columns, &missing)?;
+            indexed.push(columns.clone());
+            missing.push(ForeignKeyIndex { name, table: table.to_string(), columns });
+        }
+        Ok(missing)
+    }
+
+    /// Create the missing foreign key indexes of the table a DDL statement touched,
+    /// sending a NOTICE for each, when the session has the option enabled
+    pub async fn index_foreign_keys<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        query: &str,
+    ) -> Result<(), PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        if !session.auto_index_foreign_keys().await {
+            return Ok(());
+        }
+        let Some(table) = Self::table_from_ddl(query) else {
+            return Ok(());
+        };
+
+        let missing = db.with_session_connection(&session.id, |conn| Self::missing_indexes(conn, &table)).await?;
+        for index in missing {
+            db.execute_with_session(&index.create_sql(), &session.id).await?;
+            info!("Created index {} on foreign key columns {}.{:?}", index.name, index.table, index.columns);
+            framed.send(BackendMessage::NoticeResponse(NoticeResponse {
+                severity: "NOTICE".to_string(),
+                code: "00000".to_string(),
+                message: format!(
+                    "created index \"{}\" on foreign key column{} {} of table \"{}\"",
+                    index.name,
+                    if index.columns.len() == 1 { "" } else { "s" },
+                    index.columns.iter().map(|c| format!("\"{c}\"")).collect::<Vec<_>>().join(", "),
+                    index.table
+                ),
+                detail: None,
+                hint: None,
+                position: None,
+                where_: None,
+            })).await.map_err(PgSqliteError::Io)?;
+        }
+        Ok(())
+    }
+
+    /// Leading-column lists of every index on the table, including the rowid alias
+    fn index_columns(conn: &Connection, table: &str) -> rusqlite::Result<Vec<Vec<String>>> {
+        let mut result = Vec::new();
+
+        // An INTEGER PRIMARY KEY is the rowid and never shows up in index_list
+        let primary_key: Vec<(String, String)> = conn
+            .prepare("SELECT name, type FROM pragma_table_info(?1) WHERE pk > 0 ORDER BY pk")?
+            .query_map([table], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+        if let [(column, column_type)] = primary_key.as_slice()
+            && column_type.eq_ignore_ascii_case("INTEGER")
+        {
+            result.push(vec![column.clone()]);
+        }
+
+        let index_names: Vec<String> = conn
+            .prepare("SELECT name FROM pragma_index_list(?1) WHERE partial = 0")?
+            .query_map([table], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        for index_name in index_names {
+            let columns: Vec<Option<String>> = conn
+                .prepare("SELECT name FROM pragma_index_info(?1) ORDER BY seqno")?
+                .query_map([&index_name], |row| row.get(0))?
+                .collect::<Result<_, _>>()?;
+            // Expression columns have no name and end the usable prefix
+            result.push(columns.into_iter().map_while(|c| c).collect());
+        }
+        Ok(result)
+    }
+
+    /// Whether an index with these leading columns serves lookups on the foreign key columns
+    fn covers(index: &[String], columns: &[String]) -> bool {
+        index.len() >= columns.len()
+            && columns.iter().all(|column| {
+                index[..columns.len()].iter().any(|c| c.eq_ignore_ascii_case(column))
+            })
+    }
+
+    /// `<table>_<columns>_idx`, numbered like PostgreSQL's generated names when taken
+    fn unused_name(
+        conn: &Connection,
+        table: &str,
+        columns: &[String],
+        pending: &[ForeignKeyIndex],
+    ) -> rusqlite::Result<String> {
+        let base = format!("{}_{}_idx", table, columns.join("_"));
+        let mut candidate = base.clone();
+        let mut suffix = 0;
+        loop {
+            let taken: bool = conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE name = ?1 UNION ALL SELECT 1 FROM sqlite_temp_master WHERE name = ?1)",
+                [&candidate],
+                |row| row.get(0),
+            )?;
+            if !taken && !pending.iter().any(|index| index.name == candidate) {
+                debug!("Foreign key index name for {}.{:?}: {}", table, columns, candidate);
+                return Ok(candidate);
+            }
+            suffix += 1;
+            candidate = format!("{base}{suffix}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_from_ddl() {
+        assert_eq!(ForeignKeyIndexer::table_from_ddl("CREATE TABLE orders (id INT)"), Some("orders".to_string()));
+        assert_eq!(ForeignKeyIndexer::table_from_ddl("create temp table if not exists \"Items\" (id int)"), Some("Items".to_string()));
+        assert_eq!(ForeignKeyIndexer::table_from_ddl("ALTER TABLE ONLY public.orders ADD COLUMN x INT"), Some("orders".to_string()));
+        assert_eq!(ForeignKeyIndexer::table_from_ddl("CREATE INDEX idx ON orders (id)"), None);
+    }
+
+    #[test]
+    fn test_missing_indexes() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE customers (id INTEGER PRIMARY KEY, region TEXT, code TEXT, UNIQUE (region, code));
+             CREATE TABLE orders (
+                 id INTEGER PRIMARY KEY,
+                 customer_id INTEGER REFERENCES customers(id),
+                 region TEXT,
+                 code TEXT,
+                 FOREIGN KEY (region, code) REFERENCES customers(region, code)
+             );
+             CREATE TABLE order_notes (
+                 order_id INTEGER PRIMARY KEY REFERENCES orders(id),
+                 author_id INTEGER REFERENCES customers(id)
+             );
+             CREATE INDEX order_notes_author_id_idx ON order_notes (author_id, order_id);",
+        ).unwrap();
+
+        let missing = ForeignKeyIndexer::missing_indexes(&conn, "orders").unwrap();
+        let names: Vec<&str> = missing.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, vec!["orders_region_code_idx", "orders_customer_id_idx"]);
+        assert_eq!(missing[0].create_sql(), "CREATE INDEX \"orders_region_code_idx\" ON \"orders\" (\"region\", \"code\")");
+
+        // The rowid primary key and a composite index leading with the column both count
+        assert!(ForeignKeyIndexer::missing_indexes(&conn, "order_notes").unwrap().is_empty());
+
+        // Generated names skip ones already in use
+        conn.execute_batch("CREATE TABLE orders_customer_id_idx (x INT)").unwrap();
+        let missing = ForeignKeyIndexer::missing_indexes(&conn, "orders").unwrap();
+        assert_eq!(missing[1].name, "orders_customer_id_idx1");
+    }
+}
@@ -0,0 +1,554 @@
+use crate::error::PgError;
+use crate::query::sql_utils::{quote_identifier, unqualified, unquote_identifier};
+use crate::PgSqliteError;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rusqlite::types::Value;
+use rusqlite::{Connection, OptionalExtension, Params};
+use tracing::debug;
+
+/// `CONSTRAINT name FOREIGN KEY (columns) REFERENCES parent` table constraints
+static NAMED_TABLE_CONSTRAINT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)\bCONSTRAINT\s+("(?:[^"]|"")+"|[\w$]+)\s+FOREIGN\s+KEY\s*\(([^)]*)\)\s*REFERENCES\s+("(?:[^"]|"")+"|[\w$]+)"#).unwrap()
+});
+
+/// `column type ... CONSTRAINT name REFERENCES parent` column constraints
+static NAMED_COLUMN_CONSTRAINT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)(?:^|[(,])\s*("(?:[^"]|"")+"|[\w$]+)\s[^,]*?\bCONSTRAINT\s+("(?:[^"]|"")+"|[\w$]+)\s+REFERENCES\s+("(?:[^"]|"")+"|[\w$]+)"#).unwrap()
+});
+
+/// The table an INSERT, UPDATE or DELETE writes to
+pub(super) static DML_TARGET: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)\b(INSERT\s+(?:OR\s+\w+\s+)?INTO|REPLACE\s+INTO|UPDATE(?:\s+OR\s+\w+)?|DELETE\s+FROM)\s+(?:(?:"main"|main)\.)?("(?:[^"]|"")+"|[\w$]+)"#).unwrap()
+});
+
+static DROP_TABLE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^\s*DROP\s+TABLE\s+(IF\s+EXISTS\s+)?((?:"(?:[^"]|"")+"|[\w$]+)(?:\.(?:"(?:[^"]|"")+"|[\w$]+))?)\s*(CASCADE|RESTRICT)?\s*;?\s*$"#).unwrap()
+});
+
+/// A foreign key under the name PostgreSQL gives it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForeignKey {
+    /// The declared CONSTRAINT name, or `{table}_{columns}_fkey` as in pg_constraint
+    pub name: String,
+    /// The referencing table
+    pub table: String,
+    pub columns: Vec<String>,
+    /// The referenced table
+    pub parent: String,
+    /// The referenced columns, the parent's primary key when the declaration named none
+    pub parent_columns: Vec<String>,
+    /// SQLite's id of the foreign key within its table, as pragma_foreign_key_check reports it
+    pub id: i64,
+}
+
+/// What a DROP TABLE has to run once the foreign keys referencing the table were dealt with
+#[derive(Debug, Clone, PartialEq)]
+pub struct DropTable {
+    /// The statement without the CASCADE or RESTRICT SQLite doesn't know
+    pub sql: String,
+    pub notices: Vec<String>,
+}
+
+/// Foreign keys as PostgreSQL reports them.
+///
+/// Every pgsqlite connection enforces foreign keys, but SQLite names neither the
+/// constraint nor the key when one fails. The statement is rerun with the checks
+/// deferred to find the offending row, so the client gets PostgreSQL's 23503 error
+/// with the constraint, table and key, as ORMs expect when mapping it to an exception.
+pub struct ForeignKeys;
+
+impl ForeignKeys {
+    /// Whether the connection enforces foreign keys; they can only be paused outside a transaction
+    pub fn enforced(conn: &Connection) -> rusqlite::Result<bool> {
+        conn.query_row("PRAGMA foreign_keys", [], |row| row.get(0))
+    }
+
+    /// Whether an error is SQLite's foreign key failure
+    pub fn is_violation(error: &rusqlite::Error) -> bool {
+        matches!(error, rusqlite::Error::SqliteFailure(e, _) if e.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_FOREIGNKEY)
+    }
+
+    /// The foreign keys declared by a table
+    pub fn of_table(conn: &Connection, table: &str) -> rusqlite::Result<Vec<ForeignKey>> {
+        let mut rows: Vec<(i64, i64, String, String, Option<String>)> = {
+            let mut stmt = conn.prepare(r#"SELECT id, seq, "table", "from", "to" FROM pragma_foreign_key_list(?1) ORDER BY id, seq"#)?;
+            let rows = stmt.query_map([table], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        if rows.is_empty() {
+            return Ok(Vec::new());
+        }
+        let declared_names = Self::declared_names(conn, table)?;
+
+        let mut foreign_keys: Vec<ForeignKey> = Vec::new();
+        for (id, seq, parent, column, parent_column) in rows.drain(..) {
+            // A reference without columns targets the parent's primary key
+            let parent_column = match parent_column {
+                Some(parent_column) => parent_column,
+                None => conn.query_row("SELECT name FROM pragma_table_info(?1) WHERE pk = ?2", rusqlite::params![parent, seq + 1], |row| row.get(0))
+                    .optional()?
+                    .unwrap_or_default(),
+            };
+            match foreign_keys.last_mut() {
+                Some(foreign_key) if foreign_key.id == id => {
+                    foreign_key.columns.push(column);
+                    foreign_key.parent_columns.push(parent_column);
+                }
+                _ => foreign_keys.push(ForeignKey {
+                    name: String::new(),
+                    table: table.to_string(),
+                    columns: vec![column],
+                    parent,
+                    parent_columns: vec![parent_column],
+                    id,
+                }),
+            }
+        }
+
+        for foreign_key in &mut foreign_keys {
+            foreign_key.name = declared_names.iter()
+                .find(|(_, columns, parent)| parent.eq_ignore_ascii_case(&foreign_key.parent) && same_columns(columns, &foreign_key.columns))
+                .map(|(name, _, _)| name.clone())
+                .unwrap_or_else(|| default_name(table, &foreign_key.columns));
+        }
+        Ok(foreign_keys)
+    }
+
+    /// The foreign keys of every table that reference `table`, including its own
+    pub fn referencing(conn: &Connection, table: &str) -> rusqlite::Result<Vec<ForeignKey>> {
+        let tables: Vec<String> = {
+            let mut stmt = conn.prepare(
+                r"SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite\_%' ESCAPE '\' AND name NOT LIKE '\_\_pgsqlite\_%' ESCAPE '\'"
+            )?;
+            let rows = stmt.query_map([], |row| row.get(0))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        let mut referencing = Vec::new();
+        for child in tables {
+            referencing.extend(Self::of_table(conn, &child)?.into_iter().filter(|foreign_key| foreign_key.parent.eq_ignore_ascii_case(table)));
+        }
+        Ok(referencing)
+    }
+
+    /// Check a foreign key about to be created the way PostgreSQL does: the referenced
+    /// table and columns must exist and be a primary key or unique, and unless
+    /// `check_rows` is false (NOT VALID) every existing row must have its parent
+    pub fn validate(conn: &Connection, foreign_key: &ForeignKey, check_rows: bool) -> Result<(), PgSqliteError> {
+        let parent_exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1 COLLATE NOCASE
+             UNION ALL SELECT 1 FROM sqlite_temp_master WHERE type = 'table' AND name = ?1 COLLATE NOCASE)",
+            [&foreign_key.parent],
+            |row| row.get(0),
+        )?;
+        if !parent_exists {
+            return Err(pg_error("42P01", format!("relation \"{}\" does not exist", foreign_key.parent)));
+        }
+        if foreign_key.parent_columns.iter().any(String::is_empty) {
+            return Err(pg_error("42830", format!("there is no primary key for referenced table \"{}\"", foreign_key.parent)));
+        }
+        if foreign_key.columns.len() != foreign_key.parent_columns.len() {
+            return Err(pg_error("42830", "number of referencing and referenced columns for foreign key disagree".to_string()));
+        }
+        for (table, column) in foreign_key.columns.iter().map(|c| (&foreign_key.table, c))
+            .chain(foreign_key.parent_columns.iter().map(|c| (&foreign_key.parent, c))) {
+            let exists: bool = conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2 COLLATE NOCASE)",
+                [table, column],
+                |row| row.get(0),
+            )?;
+            if !exists {
+                return Err(pg_error("42703", format!("column \"{column}\" referenced in foreign key constraint does not exist")));
+            }
+        }
+        if !Self::is_unique_key(conn, &foreign_key.parent, &foreign_key.parent_columns)? {
+            return Err(pg_error("42830", format!("there is no unique constraint matching given keys for referenced table \"{}\"", foreign_key.parent)));
+        }
+
+        if check_rows && let Some(key) = Self::orphan_key(conn, foreign_key)? {
+            return Err(PgSqliteError::Validation(PgError::ForeignKeyViolation {
+                table_name: foreign_key.table.clone(),
+                constraint_name: foreign_key.name.clone(),
+                referencing_table: None,
+                detail: format!("Key ({})=({key}) is not present in table \"{}\".", foreign_key.columns.join(", "), foreign_key.parent),
+            }));
+        }
+        Ok(())
+    }
+
+    /// Validate the foreign keys of a table CREATE TABLE just made
+    pub fn validate_table(conn: &Connection, table: &str) -> Result<(), PgSqliteError> {
+        for foreign_key in Self::of_table(conn, table)? {
+            Self::validate(conn, &foreign_key, false)?;
+        }
+        Ok(())
+    }
+
+    /// Work out which foreign key a statement that failed with SQLite's bare
+    /// "FOREIGN KEY constraint failed" violated, by rerunning it inside a savepoint
+    /// with the checks deferred and looking for the rows left without a parent.
+    ///
+    /// COMMIT is not rerun; the deferred violations are still in the database.
+    pub fn explain_violation<P: Params>(conn: &Connection, sql: &str, params: P) -> Option<PgError> {
+        let trimmed = sql.trim().trim_end_matches(';').trim();
+        if trimmed.eq_ignore_ascii_case("COMMIT") || trimmed.eq_ignore_ascii_case("END") {
+            return Self::first_violation(conn, None, true).ok().flatten();
+        }
+
+        let caps = DML_TARGET.captures(sql)?;
+        let target = unquote_identifier(&caps[2]);
+        let is_delete = caps[1].to_uppercase().starts_with("DELETE");
+
+        // The session may have deferred its checks itself, and that has to survive the diagnosis
+        let deferred: bool = conn.query_row("PRAGMA defer_foreign_keys", [], |row| row.get(0)).ok()?;
+        conn.execute_batch("SAVEPOINT __pgsqlite_fk_check; PRAGMA defer_foreign_keys = ON").ok()?;
+        let violation = (|| -> rusqlite::Result<Option<PgError>> {
+            let mut stmt = conn.prepare(sql)?;
+            let mut rows = stmt.query(params)?;
+            while rows.next()?.is_some() {}
+            if !is_delete && let Some(violation) = Self::first_violation(conn, Some(&target), true)? {
+                return Ok(Some(violation));
+            }
+            let mut children: Vec<String> = Self::referencing(conn, &target)?.into_iter().map(|foreign_key| foreign_key.table).collect();
+            children.dedup();
+            for child in children {
+                if let Some(violation) = Self::first_violation(conn, Some(&child), child.eq_ignore_ascii_case(&target) && !is_delete)? {
+                    return Ok(Some(violation));
+                }
+            }
+            Ok(None)
+        })();
+        let restore = format!(
+            "PRAGMA defer_foreign_keys = {}; ROLLBACK TO __pgsqlite_fk_check; RELEASE __pgsqlite_fk_check",
+            if deferred { "ON" } else { "OFF" },
+        );
+        if let Err(e) = conn.execute_batch(&restore) {
+            debug!("Failed to roll back foreign key diagnosis: {}", e);
+        }
+
+        match violation {
+            Ok(violation) => violation,
+            // RESTRICT is checked even when deferred, so the rerun fails the same way
+            Err(e) if Self::is_violation(&e) => Self::restricting_key(conn, &target, is_delete).ok().flatten(),
+            Err(e) => {
+                debug!("Could not rerun statement to explain foreign key failure: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Check a DROP TABLE against the foreign keys of other tables referencing it.
+    ///
+    /// PostgreSQL refuses to drop a referenced table; with CASCADE it drops the
+    /// referencing constraints and leaves the rows of the other tables alone, where
+    /// SQLite would run their ON DELETE actions. Returns None for statements that
+    /// need nothing done.
+    pub fn prepare_drop_table(conn: &Connection, query: &str) -> Result<Option<DropTable>, PgSqliteError> {
+        let Some(caps) = DROP_TABLE_PATTERN.captures(query) else {
+            return Ok(None);
+        };
+        let table = unqualified(&caps[2]);
+        let cascade = caps.get(3).is_some_and(|option| option.as_str().eq_ignore_ascii_case("CASCADE"));
+        let sql = format!("DROP TABLE {}{}", if caps.get(1).is_some() { "IF EXISTS " } else { "" }, &caps[2]);
+
+        let referencing: Vec<ForeignKey> = Self::referencing(conn, &table)?.into_iter()
+            .filter(|foreign_key| !foreign_key.table.eq_ignore_ascii_case(&table))
+            .collect();
+        let mut notices = Vec::new();
+        if let Some(foreign_key) = referencing.first() && !cascade {
+            return Err(pg_error("2BP01", format!(
+                "cannot drop table {table} because other objects depend on it (constraint {} on table {} depends on table {table})",
+                foreign_key.name, foreign_key.table,
+            )));
+        }
+        for foreign_key in &referencing {
+            crate::query::alter_table_handler::drop_foreign_key(conn, &foreign_key.table, foreign_key)?;
+            notices.push(format!("drop cascades to constraint {} on table {}", foreign_key.name, foreign_key.table));
+        }
+
+        if caps.get(3).is_none() && notices.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(DropTable { sql, notices }))
+    }
+
+    /// Explicit CONSTRAINT names in the table's definition, with their columns and parent
+    fn declared_names(conn: &Connection, table: &str) -> rusqlite::Result<Vec<(String, Vec<String>, String)>> {
+        let Some(sql) = conn.query_row("SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?1", [table], |row| row.get::<_, String>(0))
+            .optional()? else {
+            return Ok(Vec::new());
+        };
+        let mut names: Vec<(String, Vec<String>, String)> = NAMED_TABLE_CONSTRAINT.captures_iter(&sql)
+            .map(|caps| (unquote_identifier(&caps[1]), caps[2].split(',').map(unquote_identifier).collect(), unquote_identifier(&caps[3])))
+            .collect();
+        names.extend(NAMED_COLUMN_CONSTRAINT.captures_iter(&sql).map(|caps| (unquote_identifier(&caps[2]), vec![unquote_identifier(&caps[1])], unquote_identifier(&caps[3]))));
+        Ok(names)
+    }
+
+    /// Whether the columns are the table's primary key or have a unique index of their own
+    fn is_unique_key(conn: &Connection, table: &str, columns: &[String]) -> rusqlite::Result<bool> {
+        let primary_key: Vec<String> = {
+            let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?1) WHERE pk > 0 ORDER BY pk")?;
+            let rows = stmt.query_map([table], |row| row.get(0))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        if same_columns(&primary_key, columns) {
+            return Ok(true);
+        }
+        let indexes: Vec<String> = {
+            let mut stmt = conn.prepare("SELECT name FROM pragma_index_list(?1) WHERE \"unique\" = 1 AND partial = 0")?;
+            let rows = stmt.query_map([table], |row| row.get(0))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        for index in indexes {
+            let mut stmt = conn.prepare("SELECT name FROM pragma_index_info(?1) ORDER BY seqno")?;
+            let indexed: Vec<String> = stmt.query_map([&index], |row| Ok(row.get::<_, Option<String>>(0)?.unwrap_or_default()))?
+                .collect::<rusqlite::Result<_>>()?;
+            if same_columns(&indexed, columns) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// The key of a row of the referencing table that has no parent row, formatted for DETAIL
+    fn orphan_key(conn: &Connection, foreign_key: &ForeignKey) -> rusqlite::Result<Option<String>> {
+        let not_null: Vec<String> = foreign_key.columns.iter().map(|column| format!("c.{} IS NOT NULL", quote_identifier(column))).collect();
+        let matches: Vec<String> = foreign_key.columns.iter().zip(&foreign_key.parent_columns)
+            .map(|(column, parent_column)| format!("p.{} = c.{}", quote_identifier(parent_column), quote_identifier(column)))
+            .collect();
+        let columns: Vec<String> = foreign_key.columns.iter().map(|column| format!("c.{}", quote_identifier(column))).collect();
+        let sql = format!(
+            "SELECT {} FROM {} c WHERE {} AND NOT EXISTS (SELECT 1 FROM {} p WHERE {}) LIMIT 1",
+            columns.join(", "),
+            quote_identifier(&foreign_key.table),
+            not_null.join(" AND "),
+            quote_identifier(&foreign_key.parent),
+            matches.join(" AND "),
+        );
+        conn.query_row(&sql, [], |row| key_text(row, foreign_key.columns.len())).optional()
+    }
+
+    /// The first row pragma_foreign_key_check finds, in `table` or anywhere, as the error
+    /// PostgreSQL raises for it: from the referencing side when the statement wrote
+    /// that row, from the referenced side otherwise. SQLite numbers foreign keys last
+    /// declared first, so the highest id is the one PostgreSQL would check first.
+    fn first_violation(conn: &Connection, table: Option<&str>, wrote_child: bool) -> rusqlite::Result<Option<PgError>> {
+        let found = match table {
+            Some(table) => conn.query_row(
+                "SELECT \"table\", rowid, fkid FROM pragma_foreign_key_check(?1) ORDER BY fkid DESC LIMIT 1",
+                [table],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<i64>>(1)?, row.get::<_, i64>(2)?)),
+            ),
+            None => conn.query_row(
+                "SELECT \"table\", rowid, fkid FROM pragma_foreign_key_check ORDER BY fkid DESC LIMIT 1",
+                [],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<i64>>(1)?, row.get::<_, i64>(2)?)),
+            ),
+        }.optional()?;
+        let Some((child, rowid, fkid)) = found else {
+            return Ok(None);
+        };
+        let Some(foreign_key) = Self::of_table(conn, &child)?.into_iter().find(|foreign_key| foreign_key.id == fkid) else {
+            return Ok(None);
+        };
+
+        let key = match rowid {
+            Some(rowid) => {
+                let columns: Vec<String> = foreign_key.columns.iter().map(|column| quote_identifier(column)).collect();
+                conn.query_row(
+                    &format!("SELECT {} FROM {} WHERE rowid = ?1", columns.join(", "), quote_identifier(&child)),
+                    [rowid],
+                    |row| key_text(row, columns.len()),
+                ).optional()?
+            }
+            None => None,
+        };
+
+        Ok(Some(if wrote_child {
+            PgError::ForeignKeyViolation {
+                table_name: child.clone(),
+                constraint_name: foreign_key.name.clone(),
+                referencing_table: None,
+                detail: match key {
+                    Some(key) => format!("Key ({})=({key}) is not present in table \"{}\".", foreign_key.columns.join(", "), foreign_key.parent),
+                    None => format!("Key is not present in table \"{}\".", foreign_key.parent),
+                },
+            }
+        } else {
+            PgError::ForeignKeyViolation {
+                table_name: foreign_key.parent.clone(),
+                constraint_name: foreign_key.name.clone(),
+                referencing_table: Some(child.clone()),
+                detail: match key {
+                    Some(key) => format!("Key ({})=({key}) is still referenced from table \"{child}\".", foreign_key.parent_columns.join(", ")),
+                    None => format!("Key is still referenced from table \"{child}\"."),
+                },
+            }
+        }))
+    }
+
+    /// The ON DELETE/ON UPDATE RESTRICT key of another table that stopped a change to `table`
+    fn restricting_key(conn: &Connection, table: &str, is_delete: bool) -> rusqlite::Result<Option<PgError>> {
+        let action = if is_delete { "on_delete" } else { "on_update" };
+        for foreign_key in Self::referencing(conn, table)? {
+            let restricts: bool = conn.query_row(
+                &format!("SELECT EXISTS(SELECT 1 FROM pragma_foreign_key_list(?1) WHERE id = ?2 AND {action} = 'RESTRICT')"),
+                rusqlite::params![foreign_key.table, foreign_key.id],
+                |row| row.get(0),
+            )?;
+            if restricts {
+                return Ok(Some(PgError::ForeignKeyViolation {
+                    table_name: foreign_key.parent,
+                    constraint_name: foreign_key.name,
+                    referencing_table: Some(foreign_key.table.clone()),
+                    detail: format!("Key is still referenced from table \"{}\".", foreign_key.table),
+                }));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// `{table}_{columns}_fkey`, the name pg_constraint shows for an unnamed foreign key
+fn default_name(table: &str, columns: &[String]) -> String {
+    format!("{table}_{}_fkey", columns.join("_"))
+}
+
+fn same_columns(a: &[String], b: &[String]) -> bool {
+    a.len() == b.len() && a.iter().all(|column| b.iter().any(|other| other.eq_ignore_ascii_case(column)))
+}
+
+/// Key values as PostgreSQL prints them in DETAIL: comma-separated, unquoted
+fn key_text(row: &rusqlite::Row, count: usize) -> rusqlite::Result<String> {
+    let mut values = Vec::with_capacity(count);
+    for i in 0..count {
+        values.push(match row.get::<_, Value>(i)? {
+            Value::Null => "null".to_string(),
+            Value::Integer(value) => value.to_string(),
+            Value::Real(value) => value.to_string(),
+            Value::Text(value) => value,
+            Value::Blob(value) => format!("\\x{}", value.iter().map(|byte| format!("{byte:02x}")).collect::<String>()),
+        });
+    }
+    Ok(values.join(", "))
+}
+
+fn pg_error(code: &str, message: String) -> PgSqliteError {
+    PgSqliteError::Validation(PgError::Generic { code: code.to_string(), message })
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "PRAGMA foreign_keys = ON;
+             CREATE TABLE customers (id INTEGER PRIMARY KEY, code TEXT UNIQUE, region TEXT);
+             CREATE TABLE orders (
+                 id INTEGER PRIMARY KEY,
+                 customer_id INTEGER REFERENCES customers ON DELETE RESTRICT,
+                 customer_code TEXT,
+                 CONSTRAINT orders_code_fk FOREIGN KEY (customer_code) REFERENCES customers (code) ON UPDATE CASCADE
+             );
+             INSERT INTO customers VALUES (1, 'a', 'north'), (2, 'b', 'south');
+             INSERT INTO orders VALUES (10, 1, 'a');"
+        ).unwrap();
+        conn
+    }
+
+    fn violation(error: Option<PgError>) -> (String, String, Option<String>, String) {
+        match error {
+            Some(PgError::ForeignKeyViolation { table_name, constraint_name, referencing_table, detail }) => (table_name, constraint_name, referencing_table, detail),
+            other => panic!("expected a foreign key violation, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_names_follow_pg_constraint() {
+        let conn = connection();
+        let foreign_keys = ForeignKeys::of_table(&conn, "orders").unwrap();
+        let summary: Vec<(&str, &[String], &[String])> = foreign_keys.iter()
+            .map(|fk| (fk.name.as_str(), fk.columns.as_slice(), fk.parent_columns.as_slice()))
+            .collect();
+        assert_eq!(summary, [
+            ("orders_code_fk", &["customer_code".to_string()][..], &["code".to_string()][..]),
+            ("orders_customer_id_fkey", &["customer_id".to_string()][..], &["id".to_string()][..]),
+        ]);
+        assert_eq!(ForeignKeys::referencing(&conn, "customers").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_explain_violation_from_either_side() {
+        let conn = connection();
+        let sql = "INSERT INTO orders (id, customer_id) VALUES (?1, ?2)";
+        let error = conn.execute(sql, [11, 99]).unwrap_err();
+        assert!(ForeignKeys::is_violation(&error));
+        assert_eq!(violation(ForeignKeys::explain_violation(&conn, sql, [11, 99])), (
+            "orders".to_string(),
+            "orders_customer_id_fkey".to_string(),
+            None,
+            "Key (customer_id)=(99) is not present in table \"customers\".".to_string(),
+        ));
+        // The rerun leaves nothing behind
+        assert_eq!(conn.query_row("SELECT COUNT(*) FROM orders", [], |row| row.get::<_, i64>(0)).unwrap(), 1);
+
+        assert_eq!(violation(ForeignKeys::explain_violation(&conn, "UPDATE customers SET id = 5 WHERE id = 1", [])), (
+            "customers".to_string(),
+            "orders_customer_id_fkey".to_string(),
+            Some("orders".to_string()),
+            "Key (id)=(1) is still referenced from table \"orders\".".to_string(),
+        ));
+        // ON DELETE RESTRICT fails even while the checks are deferred
+        let (_, constraint, referencing, _) = violation(ForeignKeys::explain_violation(&conn, "DELETE FROM customers WHERE id = 1", []));
+        assert_eq!((constraint.as_str(), referencing.as_deref()), ("orders_customer_id_fkey", Some("orders")));
+    }
+
+    #[test]
+    fn test_explain_violation_keeps_deferred_checks() {
+        let conn = connection();
+        conn.execute_batch("BEGIN; PRAGMA defer_foreign_keys = ON").unwrap();
+        let (_, constraint, _, _) = violation(ForeignKeys::explain_violation(&conn, "DELETE FROM customers WHERE id = 1", []));
+        assert_eq!(constraint, "orders_customer_id_fkey");
+        assert!(conn.query_row("PRAGMA defer_foreign_keys", [], |row| row.get::<_, bool>(0)).unwrap());
+
+        // Still deferred: the orphan only fails the commit
+        conn.execute("UPDATE orders SET customer_code = 'zz'", []).unwrap();
+        assert!(ForeignKeys::is_violation(&conn.execute_batch("COMMIT").unwrap_err()));
+        conn.execute_batch("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_validate_new_foreign_key() {
+        let conn = connection();
+        let mut foreign_key = ForeignKey {
+            name: "orders_region_fkey".to_string(),
+            table: "orders".to_string(),
+            columns: vec!["customer_code".to_string()],
+            parent: "customers".to_string(),
+            parent_columns: vec!["region".to_string()],
+            id: 0,
+        };
+        let code = |result: Result<(), PgSqliteError>| match result {
+            Err(PgSqliteError::Validation(PgError::Generic { code, .. })) => code,
+            Err(PgSqliteError::Validation(PgError::ForeignKeyViolation { .. })) => "23503".to_string(),
+            other => format!("{other:?}"),
+        };
+        assert_eq!(code(ForeignKeys::validate(&conn, &foreign_key, true)), "42830");
+
+        foreign_key.parent = "missing".to_string();
+        assert_eq!(code(ForeignKeys::validate(&conn, &foreign_key, true)), "42P01");
+
+        foreign_key.parent = "customers".to_string();
+        foreign_key.parent_columns = vec!["code".to_string()];
+        conn.execute("INSERT INTO orders (id) VALUES (12)", []).unwrap();
+        assert_eq!(code(ForeignKeys::validate(&conn, &foreign_key, true)), "Ok(())");
+        conn.execute_batch("PRAGMA foreign_keys = OFF; UPDATE orders SET customer_code = 'zz' WHERE id = 12; PRAGMA foreign_keys = ON").unwrap();
+        assert_eq!(code(ForeignKeys::validate(&conn, &foreign_key, true)), "23503");
+        assert_eq!(code(ForeignKeys::validate(&conn, &foreign_key, false)), "Ok(())");
+    }
+}
@@ -1,3 +1,11 @@
+pub mod composite_ddl_handler;
+pub mod constraints;
 pub mod enum_ddl_handler;
+pub mod foreign_key_index;
+pub mod foreign_keys;
 
-pub use enum_ddl_handler::EnumDdlHandler;
\ No newline at end of file
+pub use composite_ddl_handler::CompositeDdlHandler;
+pub use constraints::Constraints;
+pub use enum_ddl_handler::EnumDdlHandler;
+pub use foreign_key_index::ForeignKeyIndexer;
+pub use foreign_keys::{DropTable, ForeignKey, ForeignKeys};
\ No newline at end of file
@@ -1,6 +1,64 @@
 use crate::protocol::messages::ErrorResponse;
+use once_cell::sync::Lazy;
+use regex::Regex;
 use std::fmt;
 
+/// Matches the `StringDataRightTruncation` message raised by the length triggers
+static STRING_TRUNCATION_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"value too long for type (.+?) in column (.+) \((\d+) characters, maximum is (\d+)\)").unwrap()
+});
+
+/// Matches the bit string errors raised by the bit column triggers and bit functions
+static BIT_STRING_ERROR_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(bit string length \d+ does not match type bit\(\d+\)|cannot (?:AND|OR|XOR) bit strings of different sizes)|(bit string too long for type bit varying\(\d+\))|("[^"]*" is not a valid binary digit)"#).unwrap()
+});
+
+/// Matches the type errors raised by the composite column triggers, the type input
+/// functions and the type DDL handlers
+static TYPE_ERROR_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?s)(malformed (?:record|array) literal: ".*"|invalid input syntax for type [\w ]+: ".*")|(cannot drop type \w+ because other objects depend on it)|(type "\w+" already exists)"#).unwrap()
+});
+
+/// Matches the errors raised by xml input and the xml functions
+static XML_ERROR_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(invalid XML content)|(invalid XML document|could not parse XML document)|(not an XML document)|(invalid XPath expression)").unwrap()
+});
+
+/// Matches the argument errors raised by width_bucket() and the percentile aggregates
+static STATISTICS_ERROR_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(percentile value \S+ is not between 0 and 1)|(count must be greater than zero|lower bound cannot equal upper bound|lower and upper bounds must be finite)").unwrap()
+});
+
+/// Matches the errors raised by the EXCLUDE constraint triggers
+static EXCLUSION_ERROR_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"conflicting key value violates exclusion constraint "[^"]+""#).unwrap()
+});
+
+/// Matches the errors raised by the GENERATED ALWAYS identity triggers
+static IDENTITY_ERROR_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"cannot insert a non-DEFAULT value into column "[^"]+"|column "[^"]+" can only be updated to DEFAULT"#).unwrap()
+});
+
+/// Matches the RAISE EXCEPTION messages of translated trigger functions, which carry their SQLSTATE
+static TRIGGER_RAISE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?s)\[raise ([0-9A-Z]{5})\] (.*)$").unwrap()
+});
+
+/// Matches SQLite's error for a statement aborted by pg_cancel_backend() or pg_terminate_backend()
+static INTERRUPTED_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?:^|SQLite error: )interrupted$").unwrap()
+});
+
+/// Matches SQLite's messages for failed UNIQUE, CHECK and NOT NULL constraints
+static CONSTRAINT_FAILED_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?:UNIQUE constraint failed: (?:index '([^']+)'|(\S+)\.(.+?))|CHECK constraint failed: (.+?)|NOT NULL constraint failed: (\S+)\.(\S+))$").unwrap()
+});
+
+/// Matches the errors raised by pg_crosstab() for unusable queries and column definition lists
+static CROSSTAB_ERROR_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(invalid crosstab (?:source data|categories) query: [^\n]*)|(invalid crosstab return type: [^\n]*)").unwrap()
+});
+
 /// PostgreSQL error types
 #[derive(Debug)]
 pub enum PgError {
@@ -19,12 +77,28 @@ pub enum PgError {
     },
     /// 23505: Unique constraint violation
     UniqueViolation {
+        table_name: String,
+        /// The primary key or unique constraint, or the unique index, as pg_constraint and pg_indexes name it
         constraint_name: String,
-        detail: String,
+        detail: Option<String>,
+    },
+    /// 23514: Check constraint violation
+    CheckViolation {
+        table_name: String,
+        constraint_name: String,
+    },
+    /// 23502: Not-null constraint violation
+    NotNullViolation {
+        table_name: String,
+        column_name: String,
     },
     /// 23503: Foreign key violation
     ForeignKeyViolation {
+        /// Table the failing statement wrote to
+        table_name: String,
         constraint_name: String,
+        /// Table holding the foreign key, when the statement changed a row it references
+        referencing_table: Option<String>,
         detail: String,
     },
     /// 42601: Syntax error
@@ -40,6 +114,171 @@ pub enum PgError {
 }
 
 impl PgError {
+    /// Recover a PgError from an error message, for errors raised inside SQLite
+    /// (e.g. by validation triggers) that reach us as plain SQLite errors
+    pub fn from_message(message: &str) -> Option<PgError> {
+        if INTERRUPTED_REGEX.is_match(message) {
+            return Some(PgError::Generic {
+                code: "57014".to_string(),
+                message: "canceling statement due to user request".to_string(),
+            });
+        }
+        
+        // Writes that got past the --read-only check, e.g. nextval() in a SELECT
+        if message.contains("attempt to write a readonly database") {
+            return Some(PgError::Generic {
+                code: "25006".to_string(),
+                message: "cannot modify the database in a read-only transaction".to_string(),
+            });
+        }
+        
+        // Statements the DbHandler could not rerun to name the constraint
+        if message.contains("FOREIGN KEY constraint failed") {
+            return Some(PgError::Generic {
+                code: "23503".to_string(),
+                message: "foreign key constraint violation".to_string(),
+            });
+        }
+        
+        // Named the way pg_constraint would when nothing better is known; the DbHandler looks the names up
+        if let Some(caps) = CONSTRAINT_FAILED_REGEX.captures(message) {
+            if let Some(index) = caps.get(1) {
+                return Some(PgError::Generic {
+                    code: "23505".to_string(),
+                    message: format!("duplicate key value violates unique constraint \"{}\"", index.as_str()),
+                });
+            }
+            if let (Some(table), Some(columns)) = (caps.get(2), caps.get(3)) {
+                let columns: Vec<&str> = columns.as_str().split(", ").map(|column| column.rsplit('.').next().unwrap_or(column)).collect();
+                return Some(PgError::UniqueViolation {
+                    table_name: table.as_str().to_string(),
+                    constraint_name: format!("{}_{}_key", table.as_str(), columns.join("_")),
+                    detail: None,
+                });
+            }
+            if let Some(check) = caps.get(4) {
+                return Some(PgError::Generic {
+                    code: "23514".to_string(),
+                    message: format!("new row violates check constraint \"{}\"", check.as_str()),
+                });
+            }
+            return Some(PgError::NotNullViolation {
+                table_name: caps[5].to_string(),
+                column_name: caps[6].to_string(),
+            });
+        }
+        
+        if let Some(caps) = TRIGGER_RAISE_REGEX.captures(message) {
+            return Some(PgError::Generic {
+                code: caps[1].to_string(),
+                message: caps[2].to_string(),
+            });
+        }
+        
+        if let Some(matched) = IDENTITY_ERROR_REGEX.find(message) {
+            return Some(PgError::Generic {
+                code: "428C9".to_string(),
+                message: matched.as_str().to_string(),
+            });
+        }
+        
+        if let Some(matched) = EXCLUSION_ERROR_REGEX.find(message) {
+            return Some(PgError::Generic {
+                code: "23P01".to_string(),
+                message: matched.as_str().to_string(),
+            });
+        }
+        
+        if let Some(caps) = STRING_TRUNCATION_REGEX.captures(message) {
+            return Some(PgError::StringDataRightTruncation {
+                type_name: caps[1].to_string(),
+                column_name: caps[2].to_string(),
+                actual_length: caps[3].parse().ok()?,
+                max_length: caps[4].parse().ok()?,
+            });
+        }
+        
+        if let Some(caps) = TYPE_ERROR_REGEX.captures(message) {
+            let (code, matched) = if let Some(m) = caps.get(1) {
+                ("22P02", m)
+            } else if let Some(m) = caps.get(2) {
+                ("2BP01", m)
+            } else {
+                ("42710", caps.get(3)?)
+            };
+            return Some(PgError::Generic {
+                code: code.to_string(),
+                message: matched.as_str().to_string(),
+            });
+        }
+        
+        if let Some(caps) = XML_ERROR_REGEX.captures(message) {
+            let (code, matched) = if let Some(m) = caps.get(1) {
+                ("2200N", m)
+            } else if let Some(m) = caps.get(2) {
+                ("2200M", m)
+            } else if let Some(m) = caps.get(3) {
+                ("2200L", m)
+            } else {
+                ("10608", caps.get(4)?)
+            };
+            return Some(PgError::Generic {
+                code: code.to_string(),
+                message: matched.as_str().to_string(),
+            });
+        }
+        
+        if let Some(caps) = STATISTICS_ERROR_REGEX.captures(message) {
+            let (code, matched) = if let Some(m) = caps.get(1) {
+                ("22003", m)
+            } else {
+                ("2201G", caps.get(2)?)
+            };
+            return Some(PgError::Generic {
+                code: code.to_string(),
+                message: matched.as_str().to_string(),
+            });
+        }
+        
+        if let Some(caps) = CROSSTAB_ERROR_REGEX.captures(message) {
+            let (code, matched) = if let Some(m) = caps.get(1) {
+                ("22023", m)
+            } else {
+                ("42804", caps.get(2)?)
+            };
+            return Some(PgError::Generic {
+                code: code.to_string(),
+                message: matched.as_str().to_string(),
+            });
+        }
+        
+        let caps = BIT_STRING_ERROR_REGEX.captures(message)?;
+        let (code, matched) = if let Some(m) = caps.get(1) {
+            ("22026", m)
+        } else if let Some(m) = caps.get(2) {
+            ("22001", m)
+        } else {
+            ("22P02", caps.get(3)?)
+        };
+        Some(PgError::Generic {
+            code: code.to_string(),
+            message: matched.as_str().to_string(),
+        })
+    }
+    
+    /// The message of a ForeignKeyViolation, which depends on the side of the key the statement changed
+    fn foreign_key_message(&self) -> String {
+        match self {
+            PgError::ForeignKeyViolation { table_name, constraint_name, referencing_table: None, .. } => {
+                format!("insert or update on table \"{table_name}\" violates foreign key constraint \"{constraint_name}\"")
+            }
+            PgError::ForeignKeyViolation { table_name, constraint_name, referencing_table: Some(referencing_table), .. } => {
+                format!("update or delete on table \"{table_name}\" violates foreign key constraint \"{constraint_name}\" on table \"{referencing_table}\"")
+            }
+            _ => self.to_string(),
+        }
+    }
+    
     /// Convert to ErrorResponse for protocol
     pub fn to_error_response(&self) -> ErrorResponse {
         match self {
@@ -98,19 +337,40 @@ impl PgError {
                     routine: None,
                 }
             }
-            PgError::UniqueViolation { constraint_name, detail } => {
+            PgError::UniqueViolation { table_name, constraint_name, detail } => {
                 ErrorResponse {
                     severity: "ERROR".to_string(),
                     code: "23505".to_string(),
                     message: format!("duplicate key value violates unique constraint \"{constraint_name}\""),
-                    detail: Some(detail.clone()),
+                    detail: detail.clone(),
                     hint: None,
                     position: None,
                     internal_position: None,
                     internal_query: None,
                     where_: None,
-                    schema: None,
-                    table: None,
+                    schema: Some("public".to_string()),
+                    table: Some(table_name.clone()),
+                    column: None,
+                    datatype: None,
+                    constraint: Some(constraint_name.clone()),
+                    file: None,
+                    line: None,
+                    routine: None,
+                }
+            }
+            PgError::CheckViolation { table_name, constraint_name } => {
+                ErrorResponse {
+                    severity: "ERROR".to_string(),
+                    code: "23514".to_string(),
+                    message: format!("new row for relation \"{table_name}\" violates check constraint \"{constraint_name}\""),
+                    detail: None,
+                    hint: None,
+                    position: None,
+                    internal_position: None,
+                    internal_query: None,
+                    where_: None,
+                    schema: Some("public".to_string()),
+                    table: Some(table_name.clone()),
                     column: None,
                     datatype: None,
                     constraint: Some(constraint_name.clone()),
@@ -119,19 +379,41 @@ impl PgError {
                     routine: None,
                 }
             }
-            PgError::ForeignKeyViolation { constraint_name, detail } => {
+            PgError::NotNullViolation { table_name, column_name } => {
+                ErrorResponse {
+                    severity: "ERROR".to_string(),
+                    code: "23502".to_string(),
+                    message: format!("null value in column \"{column_name}\" of relation \"{table_name}\" violates not-null constraint"),
+                    detail: None,
+                    hint: None,
+                    position: None,
+                    internal_position: None,
+                    internal_query: None,
+                    where_: None,
+                    schema: Some("public".to_string()),
+                    table: Some(table_name.clone()),
+                    column: Some(column_name.clone()),
+                    datatype: None,
+                    constraint: None,
+                    file: None,
+                    line: None,
+                    routine: None,
+                }
+            }
+            PgError::ForeignKeyViolation { table_name, constraint_name, referencing_table, detail } => {
                 ErrorResponse {
                     severity: "ERROR".to_string(),
                     code: "23503".to_string(),
-                    message: format!("insert or update on table violates foreign key constraint \"{constraint_name}\""),
+                    message: self.foreign_key_message(),
                     detail: Some(detail.clone()),
                     hint: None,
                     position: None,
                     internal_position: None,
                     internal_query: None,
                     where_: None,
-                    schema: None,
-                    table: None,
+                    // PostgreSQL reports the table the constraint belongs to
+                    schema: Some("public".to_string()),
+                    table: Some(referencing_table.as_ref().unwrap_or(table_name).clone()),
                     column: None,
                     datatype: None,
                     constraint: Some(constraint_name.clone()),
@@ -195,11 +477,21 @@ impl fmt::Display for PgError {
             PgError::NumericValueOutOfRange { type_name, column_name, value } => {
                 write!(f, "numeric field overflow for column {column_name} (type: {type_name}, value: {value})")
             }
-            PgError::UniqueViolation { constraint_name, detail } => {
-                write!(f, "duplicate key value violates unique constraint \"{constraint_name}\": {detail}")
+            PgError::UniqueViolation { constraint_name, detail, .. } => {
+                write!(f, "duplicate key value violates unique constraint \"{constraint_name}\"")?;
+                match detail {
+                    Some(detail) => write!(f, ": {detail}"),
+                    None => Ok(()),
+                }
+            }
+            PgError::CheckViolation { table_name, constraint_name } => {
+                write!(f, "new row for relation \"{table_name}\" violates check constraint \"{constraint_name}\"")
+            }
+            PgError::NotNullViolation { table_name, column_name } => {
+                write!(f, "null value in column \"{column_name}\" of relation \"{table_name}\" violates not-null constraint")
             }
-            PgError::ForeignKeyViolation { constraint_name, detail } => {
-                write!(f, "foreign key constraint \"{constraint_name}\" violation: {detail}")
+            PgError::ForeignKeyViolation { detail, .. } => {
+                write!(f, "{}: {detail}", self.foreign_key_message())
             }
             PgError::SyntaxError { message, position } => {
                 if let Some(pos) = position {
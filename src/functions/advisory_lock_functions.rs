@@ -0,0 +1,63 @@
+use rusqlite::{Connection, Error, Result, functions::{Context, FunctionFlags}};
+use tracing::debug;
+use uuid::Uuid;
+use crate::session::advisory_locks::{self, LockKey, LockScope, LockWaitError};
+
+/// Register the pg_advisory_lock() family for the session owning the connection
+///
+/// Each function takes a bigint key or a pair of integer keys, like in PostgreSQL. The
+/// waiting ones block the statement; a standalone `SELECT pg_advisory_lock(...)` is
+/// intercepted by AdvisoryLockHandler and waits on the runtime instead. `should_abort`
+/// ends a wait once the statement is canceled or times out.
+pub fn register_advisory_lock_functions<F>(conn: &Connection, session_id: Uuid, should_abort: F) -> Result<()>
+where
+    F: Fn() -> bool + Clone + Send + Sync + 'static,
+{
+    debug!("Registering advisory lock functions");
+
+    for arity in [1, 2] {
+        for (name, scope) in [("pg_advisory_lock", LockScope::Session), ("pg_advisory_xact_lock", LockScope::Transaction)] {
+            let should_abort = should_abort.clone();
+            conn.create_scalar_function(name, arity, FunctionFlags::SQLITE_UTF8, move |ctx| {
+                let Some(key) = lock_key(ctx)? else {
+                    return Ok(None::<String>);
+                };
+                advisory_locks::lock_blocking(session_id, key, scope, &should_abort).map_err(wait_error)?;
+                Ok(None)
+            })?;
+        }
+
+        for (name, scope) in [("pg_try_advisory_lock", LockScope::Session), ("pg_try_advisory_xact_lock", LockScope::Transaction)] {
+            conn.create_scalar_function(name, arity, FunctionFlags::SQLITE_UTF8, move |ctx| {
+                Ok(lock_key(ctx)?.map(|key| advisory_locks::try_lock(session_id, key, scope)))
+            })?;
+        }
+
+        conn.create_scalar_function("pg_advisory_unlock", arity, FunctionFlags::SQLITE_UTF8, move |ctx| {
+            Ok(lock_key(ctx)?.map(|key| advisory_locks::unlock(session_id, key)))
+        })?;
+    }
+
+    conn.create_scalar_function("pg_advisory_unlock_all", 0, FunctionFlags::SQLITE_UTF8, move |_ctx| {
+        advisory_locks::unlock_all(session_id);
+        Ok(None::<String>)
+    })?;
+
+    Ok(())
+}
+
+/// The key of a one- or two-argument call, None if an argument is NULL
+fn lock_key(ctx: &Context) -> Result<Option<LockKey>> {
+    if ctx.len() == 1 {
+        return Ok(ctx.get::<Option<i64>>(0)?.map(LockKey::Single));
+    }
+    Ok(ctx.get::<Option<i32>>(0)?.zip(ctx.get::<Option<i32>>(1)?).map(|(key1, key2)| LockKey::Pair(key1, key2)))
+}
+
+/// A failed wait as the error of the statement it was part of
+fn wait_error(error: LockWaitError) -> Error {
+    match error {
+        LockWaitError::Canceled => Error::SqliteFailure(rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_INTERRUPT), None),
+        LockWaitError::Deadlock => Error::UserFunctionError("[raise 40P01] deadlock detected".into()),
+    }
+}
@@ -1,5 +1,77 @@
-use rusqlite::{Connection, Result, functions::FunctionFlags};
+use rusqlite::{Connection, Result, functions::{Context, FunctionFlags}, types::ValueRef};
 use serde_json::{Value as JsonValue, json};
+use crate::types::ArrayHandler;
+use crate::functions::{network_functions, range_functions};
+
+/// Parse an array argument stored as JSON or given as a PostgreSQL array literal ('{1,2}')
+fn parse_array(text: &str) -> Option<Vec<JsonValue>> {
+    let value = serde_json::from_str::<JsonValue>(text).ok()
+        .filter(JsonValue::is_array)
+        .or_else(|| ArrayHandler::parse_array_literal(text, None).ok())?;
+    match value {
+        JsonValue::Array(elements) => Some(elements),
+        _ => None,
+    }
+}
+
+/// Read a subscript argument, accepting integers as well as numeric text from bound parameters
+fn get_index(ctx: &rusqlite::functions::Context, idx: usize) -> Result<Option<i64>> {
+    match ctx.get_raw(idx) {
+        rusqlite::types::ValueRef::Integer(i) => Ok(Some(i)),
+        rusqlite::types::ValueRef::Real(f) => Ok(Some(f as i64)),
+        rusqlite::types::ValueRef::Text(s) => std::str::from_utf8(s).ok()
+            .and_then(|text| text.trim().parse::<i64>().ok())
+            .map(Some)
+            .ok_or_else(|| rusqlite::Error::UserFunctionError("array subscript must have type integer".into())),
+        rusqlite::types::ValueRef::Null => Ok(None),
+        rusqlite::types::ValueRef::Blob(_) => Err(rusqlite::Error::UserFunctionError("array subscript must have type integer".into())),
+    }
+}
+
+/// Read an array argument; NULL and values that are not arrays yield None
+fn array_arg(ctx: &Context, idx: usize) -> Result<Option<Vec<JsonValue>>> {
+    Ok(ctx.get::<Option<String>>(idx)?.and_then(|text| parse_array(&text)))
+}
+
+/// Read an array argument, treating NULL as an empty array; values that are not arrays yield None
+fn array_arg_or_empty(ctx: &Context, idx: usize) -> Result<Option<Vec<JsonValue>>> {
+    match ctx.get::<Option<String>>(idx)? {
+        Some(text) => Ok(parse_array(&text)),
+        None => Ok(Some(Vec::new())),
+    }
+}
+
+/// Read an element argument as a JSON value
+fn element_arg(ctx: &Context, idx: usize) -> JsonValue {
+    match ctx.get_raw(idx) {
+        ValueRef::Text(s) => {
+            let text = std::str::from_utf8(s).unwrap_or("");
+            serde_json::from_str::<JsonValue>(text)
+                .unwrap_or_else(|_| JsonValue::String(text.to_string()))
+        }
+        ValueRef::Integer(i) => JsonValue::Number(serde_json::Number::from(i)),
+        ValueRef::Real(f) => {
+            JsonValue::Number(serde_json::Number::from_f64(f).unwrap_or_else(|| serde_json::Number::from(0)))
+        }
+        ValueRef::Null => JsonValue::Null,
+        ValueRef::Blob(b) => JsonValue::String(format!("\\x{}", hex::encode(b))),
+    }
+}
+
+/// Compare elements with IS NOT DISTINCT FROM semantics, so NULL matches NULL.
+/// Numbers compare by value, including against numeric text and booleans stored as 0/1.
+fn elements_equal(a: &JsonValue, b: &JsonValue) -> bool {
+    match (a, b) {
+        (JsonValue::Number(x), JsonValue::Number(y)) => x.as_f64() == y.as_f64(),
+        (JsonValue::String(s), JsonValue::Number(n)) | (JsonValue::Number(n), JsonValue::String(s)) => {
+            s.trim().parse::<f64>().ok() == n.as_f64()
+        }
+        (JsonValue::Bool(flag), JsonValue::Number(n)) | (JsonValue::Number(n), JsonValue::Bool(flag)) => {
+            n.as_i64() == Some(*flag as i64)
+        }
+        _ => a == b,
+    }
+}
 
 /// Register array-related functions in SQLite
 pub fn register_array_functions(conn: &Connection) -> Result<()> {
@@ -8,6 +80,7 @@ pub fn register_array_functions(conn: &Connection) -> Result<()> {
     register_array_upper(conn)?;
     register_array_lower(conn)?;
     register_array_ndims(conn)?;
+    register_cardinality(conn)?;
     
     // Array manipulation functions
     register_array_append(conn)?;
@@ -23,6 +96,8 @@ pub fn register_array_functions(conn: &Connection) -> Result<()> {
     
     // Array utility functions
     register_array_slice(conn)?;
+    register_array_subscript(conn)?;
+    register_array_set(conn)?;
     register_array_position(conn)?;
     register_array_positions(conn)?;
     
@@ -34,39 +109,54 @@ pub fn register_array_functions(conn: &Connection) -> Result<()> {
     // Array constructor functions
     register_string_to_array(conn)?;
     register_array_to_string(conn)?;
+    register_array_from_text(conn)?;
+    
+    Ok(())
+}
+
+/// pg_array_from_text(value, type) - Cast a PostgreSQL array literal ('{1,2}') to an array type
+///
+/// The elements are checked against the element type of `type` (e.g. 'int[]') and the array
+/// is returned as the stored JSON. Stored arrays are converted again, element by element.
+fn register_array_from_text(conn: &Connection) -> Result<()> {
+    conn.create_scalar_function(
+        "pg_array_from_text",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let Some(text) = ctx.get::<Option<String>>(0)? else {
+                return Ok(None);
+            };
+            let array_type: String = ctx.get(1)?;
+            let element_type = ArrayHandler::element_type(crate::types::SchemaTypeMapper::pg_type_string_to_oid(&array_type));
+            let literal = match serde_json::from_str::<JsonValue>(&text) {
+                Ok(JsonValue::Array(elements)) => ArrayHandler::format_elements(&elements),
+                _ => text,
+            };
+            ArrayHandler::array_literal_to_json(&literal, element_type)
+                .map(Some)
+                .map_err(|e| rusqlite::Error::UserFunctionError(e.to_string().into()))
+        },
+    )?;
     
     Ok(())
 }
 
 /// array_length(array, dimension) - Get length of array in specified dimension
+///
+/// NULL for a NULL or empty array and for dimensions the array does not have.
 fn register_array_length(conn: &Connection) -> Result<()> {
     conn.create_scalar_function(
         "array_length",
         2,
         FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
         |ctx| {
-            let array_json: String = ctx.get(0)?;
-            let dimension: i32 = ctx.get(1)?;
+            let array = array_arg(ctx, 0)?;
+            let dimension: Option<i64> = ctx.get(1)?;
             
-            match serde_json::from_str::<JsonValue>(&array_json) {
-                Ok(JsonValue::Array(arr)) => {
-                    if dimension == 1 {
-                        Ok(Some(arr.len() as i32))
-                    } else {
-                        // For higher dimensions, check first element
-                        if let Some(JsonValue::Array(inner)) = arr.first() {
-                            if dimension == 2 {
-                                Ok(Some(inner.len() as i32))
-                            } else {
-                                Ok(None) // Higher dimensions not yet supported
-                            }
-                        } else {
-                            Ok(None)
-                        }
-                    }
-                }
-                _ => Ok(None),
-            }
+            Ok(array.zip(dimension)
+                .and_then(|(arr, dimension)| dimension_length(&arr, dimension))
+                .map(|len| len as i32))
         },
     )?;
     
@@ -80,26 +170,13 @@ fn register_array_upper(conn: &Connection) -> Result<()> {
         2,
         FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
         |ctx| {
-            let array_json: String = ctx.get(0)?;
-            let dimension: i32 = ctx.get(1)?;
+            let array = array_arg(ctx, 0)?;
+            let dimension: Option<i64> = ctx.get(1)?;
             
-            match serde_json::from_str::<JsonValue>(&array_json) {
-                Ok(JsonValue::Array(arr)) => {
-                    if dimension == 1 {
-                        Ok(Some(arr.len() as i32))
-                    } else if dimension == 2 {
-                        // For 2D arrays, check first element
-                        if let Some(JsonValue::Array(inner)) = arr.first() {
-                            Ok(Some(inner.len() as i32))
-                        } else {
-                            Ok(None)
-                        }
-                    } else {
-                        Ok(None)
-                    }
-                }
-                _ => Ok(None),
-            }
+            // Arrays are 1-based, so the upper bound is the dimension's length
+            Ok(array.zip(dimension)
+                .and_then(|(arr, dimension)| dimension_length(&arr, dimension))
+                .map(|len| len as i32))
         },
     )?;
     
@@ -113,47 +190,42 @@ fn register_array_lower(conn: &Connection) -> Result<()> {
         2,
         FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
         |ctx| {
-            let array_json: String = ctx.get(0)?;
-            let dimension: i32 = ctx.get(1)?;
+            let array = array_arg(ctx, 0)?;
+            let dimension: Option<i64> = ctx.get(1)?;
             
-            match serde_json::from_str::<JsonValue>(&array_json) {
-                Ok(JsonValue::Array(arr)) => {
-                    if arr.is_empty() {
-                        Ok(None)
-                    } else if dimension == 1 {
-                        Ok(Some(1))
-                    } else if dimension == 2 {
-                        // Check if it's actually a 2D array
-                        if let Some(JsonValue::Array(_)) = arr.first() {
-                            Ok(Some(1))
-                        } else {
-                            Ok(None)
-                        }
-                    } else {
-                        Ok(None)
-                    }
-                }
-                _ => Ok(None),
-            }
+            Ok(array.zip(dimension)
+                .and_then(|(arr, dimension)| dimension_length(&arr, dimension))
+                .map(|_| 1))
         },
     )?;
     
     Ok(())
 }
 
-/// array_ndims(array) - Get number of dimensions
+/// array_ndims(array) - Get number of dimensions, NULL for an empty array
 fn register_array_ndims(conn: &Connection) -> Result<()> {
     conn.create_scalar_function(
         "array_ndims",
         1,
         FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
         |ctx| {
-            let array_json: String = ctx.get(0)?;
-            
-            match serde_json::from_str::<JsonValue>(&array_json) {
-                Ok(json) => Ok(Some(count_dimensions(&json))),
-                _ => Ok(None),
-            }
+            Ok(array_arg(ctx, 0)?
+                .filter(|arr| !arr.is_empty())
+                .map(|arr| count_dimensions(&JsonValue::Array(arr))))
+        },
+    )?;
+    
+    Ok(())
+}
+
+/// cardinality(array) - Total number of elements across all dimensions, 0 for an empty array
+fn register_cardinality(conn: &Connection) -> Result<()> {
+    conn.create_scalar_function(
+        "cardinality",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            Ok(array_arg(ctx, 0)?.map(|arr| count_elements(&arr) as i32))
         },
     )?;
     
@@ -167,31 +239,15 @@ fn register_array_append(conn: &Connection) -> Result<()> {
         2,
         FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
         |ctx| {
-            let array_json: String = ctx.get(0)?;
-            
-            // Handle element parameter of different types
-            let elem_value = match ctx.get_raw(1) {
-                rusqlite::types::ValueRef::Text(s) => {
-                    let text = std::str::from_utf8(s).unwrap_or("");
-                    serde_json::from_str::<JsonValue>(text)
-                        .unwrap_or_else(|_| JsonValue::String(text.to_string()))
-                }
-                rusqlite::types::ValueRef::Integer(i) => JsonValue::Number(serde_json::Number::from(i)),
-                rusqlite::types::ValueRef::Real(f) => {
-                    JsonValue::Number(serde_json::Number::from_f64(f).unwrap_or_else(|| serde_json::Number::from(0)))
-                }
-                rusqlite::types::ValueRef::Null => JsonValue::Null,
-                rusqlite::types::ValueRef::Blob(b) => {
-                    JsonValue::String(format!("\\x{}", hex::encode(b)))
-                }
-            };
+            let elem_value = element_arg(ctx, 1);
             
-            match serde_json::from_str::<JsonValue>(&array_json) {
-                Ok(JsonValue::Array(mut arr)) => {
+            // Appending to a NULL array yields a one-element array
+            match array_arg_or_empty(ctx, 0)? {
+                Some(mut arr) => {
                     arr.push(elem_value);
                     Ok(serde_json::to_string(&arr).ok())
                 }
-                _ => Ok(None),
+                None => Ok(None),
             }
         },
     )?;
@@ -206,31 +262,14 @@ fn register_array_prepend(conn: &Connection) -> Result<()> {
         2,
         FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
         |ctx| {
-            // Handle element parameter of different types
-            let elem_value = match ctx.get_raw(0) {
-                rusqlite::types::ValueRef::Text(s) => {
-                    let text = std::str::from_utf8(s).unwrap_or("");
-                    serde_json::from_str::<JsonValue>(text)
-                        .unwrap_or_else(|_| JsonValue::String(text.to_string()))
-                }
-                rusqlite::types::ValueRef::Integer(i) => JsonValue::Number(serde_json::Number::from(i)),
-                rusqlite::types::ValueRef::Real(f) => {
-                    JsonValue::Number(serde_json::Number::from_f64(f).unwrap_or_else(|| serde_json::Number::from(0)))
-                }
-                rusqlite::types::ValueRef::Null => JsonValue::Null,
-                rusqlite::types::ValueRef::Blob(b) => {
-                    JsonValue::String(format!("\\x{}", hex::encode(b)))
-                }
-            };
-            
-            let array_json: String = ctx.get(1)?;
+            let elem_value = element_arg(ctx, 0);
             
-            match serde_json::from_str::<JsonValue>(&array_json) {
-                Ok(JsonValue::Array(mut arr)) => {
+            match array_arg_or_empty(ctx, 1)? {
+                Some(mut arr) => {
                     arr.insert(0, elem_value);
                     Ok(serde_json::to_string(&arr).ok())
                 }
-                _ => Ok(None),
+                None => Ok(None),
             }
         },
     )?;
@@ -238,21 +277,19 @@ fn register_array_prepend(conn: &Connection) -> Result<()> {
     Ok(())
 }
 
-/// array_cat(array1, array2) - Concatenate two arrays
+/// array_cat(array1, array2) - Concatenate two arrays; a NULL side is treated as empty
 fn register_array_cat(conn: &Connection) -> Result<()> {
     conn.create_scalar_function(
         "array_cat",
         2,
         FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
         |ctx| {
-            let array1_json: String = ctx.get(0)?;
-            let array2_json: String = ctx.get(1)?;
+            if matches!(ctx.get_raw(0), ValueRef::Null) && matches!(ctx.get_raw(1), ValueRef::Null) {
+                return Ok(None);
+            }
             
-            match (
-                serde_json::from_str::<JsonValue>(&array1_json),
-                serde_json::from_str::<JsonValue>(&array2_json),
-            ) {
-                (Ok(JsonValue::Array(mut arr1)), Ok(JsonValue::Array(arr2))) => {
+            match (array_arg_or_empty(ctx, 0)?, array_arg_or_empty(ctx, 1)?) {
+                (Some(mut arr1), Some(arr2)) => {
                     arr1.extend(arr2);
                     Ok(serde_json::to_string(&arr1).ok())
                 }
@@ -264,41 +301,24 @@ fn register_array_cat(conn: &Connection) -> Result<()> {
     Ok(())
 }
 
-/// array_remove(array, element) - Remove all occurrences of element
+/// array_remove(array, element) - Remove all occurrences of element, including NULLs
 fn register_array_remove(conn: &Connection) -> Result<()> {
     conn.create_scalar_function(
         "array_remove",
         2,
         FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
         |ctx| {
-            let array_json: String = ctx.get(0)?;
+            let elem_value = element_arg(ctx, 1);
             
-            // Handle element parameter of different types
-            let elem_value = match ctx.get_raw(1) {
-                rusqlite::types::ValueRef::Text(s) => {
-                    let text = std::str::from_utf8(s).unwrap_or("");
-                    serde_json::from_str::<JsonValue>(text)
-                        .unwrap_or_else(|_| JsonValue::String(text.to_string()))
-                }
-                rusqlite::types::ValueRef::Integer(i) => JsonValue::Number(serde_json::Number::from(i)),
-                rusqlite::types::ValueRef::Real(f) => {
-                    JsonValue::Number(serde_json::Number::from_f64(f).unwrap_or_else(|| serde_json::Number::from(0)))
-                }
-                rusqlite::types::ValueRef::Null => JsonValue::Null,
-                rusqlite::types::ValueRef::Blob(b) => {
-                    JsonValue::String(format!("\\x{}", hex::encode(b)))
-                }
-            };
-            
-            match serde_json::from_str::<JsonValue>(&array_json) {
-                Ok(JsonValue::Array(arr)) => {
+            match array_arg(ctx, 0)? {
+                Some(arr) => {
                     let filtered: Vec<JsonValue> = arr.into_iter()
-                        .filter(|v| v != &elem_value)
+                        .filter(|v| !elements_equal(v, &elem_value))
                         .collect();
                     
                     Ok(serde_json::to_string(&filtered).ok())
                 }
-                _ => Ok(None),
+                None => Ok(None),
             }
         },
     )?;
@@ -313,51 +333,18 @@ fn register_array_replace(conn: &Connection) -> Result<()> {
         3,
         FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
         |ctx| {
-            let array_json: String = ctx.get(0)?;
-            
-            // Handle old_element parameter of different types
-            let old_value = match ctx.get_raw(1) {
-                rusqlite::types::ValueRef::Text(s) => {
-                    let text = std::str::from_utf8(s).unwrap_or("");
-                    serde_json::from_str::<JsonValue>(text)
-                        .unwrap_or_else(|_| JsonValue::String(text.to_string()))
-                }
-                rusqlite::types::ValueRef::Integer(i) => JsonValue::Number(serde_json::Number::from(i)),
-                rusqlite::types::ValueRef::Real(f) => {
-                    JsonValue::Number(serde_json::Number::from_f64(f).unwrap_or_else(|| serde_json::Number::from(0)))
-                }
-                rusqlite::types::ValueRef::Null => JsonValue::Null,
-                rusqlite::types::ValueRef::Blob(b) => {
-                    JsonValue::String(format!("\\x{}", hex::encode(b)))
-                }
-            };
-            
-            // Handle new_element parameter of different types
-            let new_value = match ctx.get_raw(2) {
-                rusqlite::types::ValueRef::Text(s) => {
-                    let text = std::str::from_utf8(s).unwrap_or("");
-                    serde_json::from_str::<JsonValue>(text)
-                        .unwrap_or_else(|_| JsonValue::String(text.to_string()))
-                }
-                rusqlite::types::ValueRef::Integer(i) => JsonValue::Number(serde_json::Number::from(i)),
-                rusqlite::types::ValueRef::Real(f) => {
-                    JsonValue::Number(serde_json::Number::from_f64(f).unwrap_or_else(|| serde_json::Number::from(0)))
-                }
-                rusqlite::types::ValueRef::Null => JsonValue::Null,
-                rusqlite::types::ValueRef::Blob(b) => {
-                    JsonValue::String(format!("\\x{}", hex::encode(b)))
-                }
-            };
+            let old_value = element_arg(ctx, 1);
+            let new_value = element_arg(ctx, 2);
             
-            match serde_json::from_str::<JsonValue>(&array_json) {
-                Ok(JsonValue::Array(arr)) => {
+            match array_arg(ctx, 0)? {
+                Some(arr) => {
                     let replaced: Vec<JsonValue> = arr.into_iter()
-                        .map(|v| if v == old_value { new_value.clone() } else { v })
+                        .map(|v| if elements_equal(&v, &old_value) { new_value.clone() } else { v })
                         .collect();
                     
                     Ok(serde_json::to_string(&replaced).ok())
                 }
-                _ => Ok(None),
+                None => Ok(None),
             }
         },
     )?;
@@ -372,19 +359,36 @@ fn register_array_contains(conn: &Connection) -> Result<()> {
         2,
         FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
         |ctx| {
-            let array1_json: String = ctx.get(0)?;
-            let array2_json: String = ctx.get(1)?;
+            // Range columns share @> with arrays
+            if let Some(result) = range_functions::range_contains(ctx.get_raw(0), ctx.get_raw(1)) {
+                return Ok(Some(result));
+            }
+
+            let array1_json: Option<String> = ctx.get(0)?;
+            let array2_json: Option<String> = ctx.get(1)?;
+            
+            // NULL operands yield NULL
+            let (Some(array1_json), Some(array2_json)) = (array1_json, array2_json) else {
+                return Ok(None);
+            };
             
-            match (
-                serde_json::from_str::<JsonValue>(&array1_json),
-                serde_json::from_str::<JsonValue>(&array2_json),
-            ) {
-                (Ok(JsonValue::Array(arr1)), Ok(JsonValue::Array(arr2))) => {
+            match (parse_array(&array1_json), parse_array(&array2_json)) {
+                (Some(arr1), Some(arr2)) => {
                     // Check if all elements of arr2 are in arr1
                     let contains_all = arr2.iter().all(|elem| arr1.contains(elem));
-                    Ok(contains_all)
-                }
-                _ => Ok(false),
+                    Ok(Some(contains_all))
+                }
+                // JSONB columns share @> / <@ with arrays, so fall back to
+                // JSON containment when either side is not an array
+                _ => match (
+                    serde_json::from_str::<JsonValue>(&array1_json),
+                    serde_json::from_str::<JsonValue>(&array2_json),
+                ) {
+                    (Ok(container), Ok(contained)) => {
+                        Ok(Some(crate::functions::json_functions::json_contains(&container, &contained)))
+                    }
+                    _ => Ok(Some(false)),
+                },
             }
         },
     )?;
@@ -399,19 +403,36 @@ fn register_array_contained(conn: &Connection) -> Result<()> {
         2,
         FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
         |ctx| {
-            let array1_json: String = ctx.get(0)?;
-            let array2_json: String = ctx.get(1)?;
+            // Range columns share <@ with arrays
+            if let Some(result) = range_functions::range_contains(ctx.get_raw(1), ctx.get_raw(0)) {
+                return Ok(Some(result));
+            }
+
+            let array1_json: Option<String> = ctx.get(0)?;
+            let array2_json: Option<String> = ctx.get(1)?;
             
-            match (
-                serde_json::from_str::<JsonValue>(&array1_json),
-                serde_json::from_str::<JsonValue>(&array2_json),
-            ) {
-                (Ok(JsonValue::Array(arr1)), Ok(JsonValue::Array(arr2))) => {
+            // NULL operands yield NULL
+            let (Some(array1_json), Some(array2_json)) = (array1_json, array2_json) else {
+                return Ok(None);
+            };
+            
+            match (parse_array(&array1_json), parse_array(&array2_json)) {
+                (Some(arr1), Some(arr2)) => {
                     // Check if all elements of arr1 are in arr2
                     let contained_all = arr1.iter().all(|elem| arr2.contains(elem));
-                    Ok(contained_all)
-                }
-                _ => Ok(false),
+                    Ok(Some(contained_all))
+                }
+                // JSONB columns share @> / <@ with arrays, so fall back to
+                // JSON containment when either side is not an array
+                _ => match (
+                    serde_json::from_str::<JsonValue>(&array2_json),
+                    serde_json::from_str::<JsonValue>(&array1_json),
+                ) {
+                    (Ok(container), Ok(contained)) => {
+                        Ok(Some(crate::functions::json_functions::json_contains(&container, &contained)))
+                    }
+                    _ => Ok(Some(false)),
+                },
             }
         },
     )?;
@@ -426,19 +447,31 @@ fn register_array_overlap(conn: &Connection) -> Result<()> {
         2,
         FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
         |ctx| {
-            let array1_json: String = ctx.get(0)?;
-            let array2_json: String = ctx.get(1)?;
+            // Range columns share && with arrays
+            if let Some(result) = range_functions::range_overlaps(ctx.get_raw(0), ctx.get_raw(1)) {
+                return Ok(Some(result));
+            }
             
-            match (
-                serde_json::from_str::<JsonValue>(&array1_json),
-                serde_json::from_str::<JsonValue>(&array2_json),
-            ) {
-                (Ok(JsonValue::Array(arr1)), Ok(JsonValue::Array(arr2))) => {
+            // inet/cidr columns share && with arrays
+            if let Some(result) = network_functions::network_overlaps(ctx.get_raw(0), ctx.get_raw(1)) {
+                return Ok(Some(result));
+            }
+
+            let array1_json: Option<String> = ctx.get(0)?;
+            let array2_json: Option<String> = ctx.get(1)?;
+            
+            // NULL operands yield NULL
+            let (Some(array1_json), Some(array2_json)) = (array1_json, array2_json) else {
+                return Ok(None);
+            };
+            
+            match (parse_array(&array1_json), parse_array(&array2_json)) {
+                (Some(arr1), Some(arr2)) => {
                     // Check if any element of arr1 is in arr2
                     let has_overlap = arr1.iter().any(|elem| arr2.contains(elem));
-                    Ok(has_overlap)
+                    Ok(Some(has_overlap))
                 }
-                _ => Ok(false),
+                _ => Ok(Some(false)),
             }
         },
     )?;
@@ -478,50 +511,107 @@ fn register_array_slice(conn: &Connection) -> Result<()> {
     Ok(())
 }
 
-/// array_position(array, element) - Find position of element (1-based)
-fn register_array_position(conn: &Connection) -> Result<()> {
+/// array_subscript(array, index) - Get element at a computed 1-based index, NULL when out of range
+fn register_array_subscript(conn: &Connection) -> Result<()> {
     conn.create_scalar_function(
-        "array_position",
+        "array_subscript",
         2,
         FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
         |ctx| {
-            let array_json: String = ctx.get(0)?;
+            let array_json: Option<String> = ctx.get(0)?;
+            let index = get_index(ctx, 1)?;
             
-            // Handle element parameter of different types
-            let elem_value = match ctx.get_raw(1) {
-                rusqlite::types::ValueRef::Text(s) => {
-                    let text = std::str::from_utf8(s).unwrap_or("");
-                    serde_json::from_str::<JsonValue>(text)
-                        .unwrap_or_else(|_| JsonValue::String(text.to_string()))
-                }
-                rusqlite::types::ValueRef::Integer(i) => JsonValue::Number(serde_json::Number::from(i)),
-                rusqlite::types::ValueRef::Real(f) => {
-                    JsonValue::Number(serde_json::Number::from_f64(f).unwrap_or_else(|| serde_json::Number::from(0)))
-                }
-                rusqlite::types::ValueRef::Null => JsonValue::Null,
-                rusqlite::types::ValueRef::Blob(b) => {
-                    JsonValue::String(format!("\\x{}", hex::encode(b)))
-                }
+            let (Some(array_json), Some(index)) = (array_json, index) else {
+                return Ok(rusqlite::types::Value::Null);
             };
+            let element = parse_array(&array_json)
+                .filter(|_| index >= 1)
+                .and_then(|arr| arr.into_iter().nth((index - 1) as usize));
             
-            match serde_json::from_str::<JsonValue>(&array_json) {
-                Ok(JsonValue::Array(arr)) => {
-                    // Find first occurrence (1-based index)
-                    for (i, val) in arr.iter().enumerate() {
-                        if val == &elem_value {
-                            return Ok(Some((i + 1) as i32));
-                        }
-                    }
-                    Ok(None)
-                }
-                _ => Ok(None),
+            Ok(match element {
+                Some(JsonValue::String(s)) => rusqlite::types::Value::Text(s),
+                Some(JsonValue::Bool(b)) => rusqlite::types::Value::Integer(b as i64),
+                Some(JsonValue::Number(n)) => match n.as_i64() {
+                    Some(i) => rusqlite::types::Value::Integer(i),
+                    None => rusqlite::types::Value::Real(n.as_f64().unwrap_or(0.0)),
+                },
+                Some(JsonValue::Null) | None => rusqlite::types::Value::Null,
+                Some(nested) => rusqlite::types::Value::Text(nested.to_string()),
+            })
+        },
+    )?;
+    
+    Ok(())
+}
+
+/// array_set(array, index, value) - Replace element at a 1-based index, padding with NULLs past the end
+fn register_array_set(conn: &Connection) -> Result<()> {
+    conn.create_scalar_function(
+        "array_set",
+        3,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let Some(index) = get_index(ctx, 1)? else {
+                return Err(rusqlite::Error::UserFunctionError("array subscript in assignment must not be null".into()));
+            };
+            let elem_value = element_arg(ctx, 2);
+            
+            if index < 1 {
+                return Err(rusqlite::Error::UserFunctionError("array subscript out of range".into()));
+            }
+            
+            // Assigning into a NULL array starts from an empty one, as in PostgreSQL
+            let Some(mut arr) = array_arg_or_empty(ctx, 0)? else {
+                return Ok(None);
+            };
+            
+            let position = (index - 1) as usize;
+            if position >= arr.len() {
+                arr.resize(position + 1, JsonValue::Null);
             }
+            arr[position] = elem_value;
+            Ok(serde_json::to_string(&arr).ok())
         },
     )?;
     
     Ok(())
 }
 
+/// array_position(array, element [, start]) - Find position of element (1-based)
+fn register_array_position(conn: &Connection) -> Result<()> {
+    for n_args in [2, 3] {
+        conn.create_scalar_function(
+            "array_position",
+            n_args,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            move |ctx| {
+                let elem_value = element_arg(ctx, 1);
+                let start = if n_args == 3 {
+                    get_index(ctx, 2)?.ok_or_else(|| {
+                        rusqlite::Error::UserFunctionError("initial position must not be null".into())
+                    })?
+                } else {
+                    1
+                };
+                
+                let Some(arr) = array_arg(ctx, 0)? else {
+                    return Ok(None);
+                };
+                
+                // Find first occurrence at or after the start position (1-based index)
+                let skip = (start.max(1) - 1) as usize;
+                Ok(arr.iter()
+                    .enumerate()
+                    .skip(skip)
+                    .find(|(_, val)| elements_equal(val, &elem_value))
+                    .map(|(i, _)| (i + 1) as i32))
+            },
+        )?;
+    }
+    
+    Ok(())
+}
+
 /// array_positions(array, element) - Find all positions of element
 fn register_array_positions(conn: &Connection) -> Result<()> {
     conn.create_scalar_function(
@@ -529,42 +619,20 @@ fn register_array_positions(conn: &Connection) -> Result<()> {
         2,
         FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
         |ctx| {
-            let array_json: String = ctx.get(0)?;
+            let elem_value = element_arg(ctx, 1);
             
-            // Handle element parameter of different types
-            let elem_value = match ctx.get_raw(1) {
-                rusqlite::types::ValueRef::Text(s) => {
-                    let text = std::str::from_utf8(s).unwrap_or("");
-                    serde_json::from_str::<JsonValue>(text)
-                        .unwrap_or_else(|_| JsonValue::String(text.to_string()))
-                }
-                rusqlite::types::ValueRef::Integer(i) => JsonValue::Number(serde_json::Number::from(i)),
-                rusqlite::types::ValueRef::Real(f) => {
-                    JsonValue::Number(serde_json::Number::from_f64(f).unwrap_or_else(|| serde_json::Number::from(0)))
-                }
-                rusqlite::types::ValueRef::Null => JsonValue::Null,
-                rusqlite::types::ValueRef::Blob(b) => {
-                    JsonValue::String(format!("\\x{}", hex::encode(b)))
-                }
-            };
-            
-            match serde_json::from_str::<JsonValue>(&array_json) {
-                Ok(JsonValue::Array(arr)) => {
+            match array_arg(ctx, 0)? {
+                Some(arr) => {
                     // Find all occurrences (1-based indices)
                     let positions: Vec<i32> = arr.iter()
                         .enumerate()
-                        .filter_map(|(i, val)| {
-                            if val == &elem_value {
-                                Some((i + 1) as i32)
-                            } else {
-                                None
-                            }
-                        })
+                        .filter(|(_, val)| elements_equal(val, &elem_value))
+                        .map(|(i, _)| (i + 1) as i32)
                         .collect();
                     
                     Ok(serde_json::to_string(&positions).ok())
                 }
-                _ => Ok(Some("[]".to_string())),
+                None => Ok(None),
             }
         },
     )?;
@@ -710,70 +778,124 @@ fn register_array_agg_distinct(conn: &Connection) -> Result<()> {
 }
 
 
-/// string_to_array(string, delimiter) - Split string into array
+/// string_to_array(string, delimiter [, null_string]) - Split string into array
+///
+/// A NULL delimiter splits into characters, an empty one yields the whole string as
+/// the only element, and elements equal to null_string become NULL.
 fn register_string_to_array(conn: &Connection) -> Result<()> {
-    conn.create_scalar_function(
-        "string_to_array",
-        2,
-        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
-        |ctx| {
-            let input_string: String = ctx.get(0)?;
-            let delimiter: String = ctx.get(1)?;
-            
-            if input_string.is_empty() {
-                return Ok(Some("[]".to_string()));
-            }
-            
-            let parts: Vec<JsonValue> = if delimiter.is_empty() {
-                // Split into individual characters
-                input_string.chars()
-                    .map(|c| JsonValue::String(c.to_string()))
-                    .collect()
-            } else {
-                // Split by delimiter
-                input_string.split(&delimiter)
-                    .map(|s| JsonValue::String(s.to_string()))
-                    .collect()
-            };
-            
-            Ok(serde_json::to_string(&parts).ok())
-        },
-    )?;
+    for n_args in [2, 3] {
+        conn.create_scalar_function(
+            "string_to_array",
+            n_args,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            move |ctx| {
+                let Some(input_string) = ctx.get::<Option<String>>(0)? else {
+                    return Ok(None);
+                };
+                let delimiter: Option<String> = ctx.get(1)?;
+                let null_string: Option<String> = if n_args == 3 { ctx.get(2)? } else { None };
+                
+                if input_string.is_empty() {
+                    return Ok(Some("[]".to_string()));
+                }
+                
+                let parts: Vec<&str> = match delimiter.as_deref() {
+                    // Split into individual characters
+                    None => input_string.char_indices()
+                        .map(|(i, c)| &input_string[i..i + c.len_utf8()])
+                        .collect(),
+                    Some("") => vec![input_string.as_str()],
+                    Some(delimiter) => input_string.split(delimiter).collect(),
+                };
+                
+                let elements: Vec<JsonValue> = parts.into_iter()
+                    .map(|part| if null_string.as_deref() == Some(part) {
+                        JsonValue::Null
+                    } else {
+                        JsonValue::String(part.to_string())
+                    })
+                    .collect();
+                
+                Ok(serde_json::to_string(&elements).ok())
+            },
+        )?;
+    }
     
     Ok(())
 }
 
-/// array_to_string(array, delimiter) - Join array elements into string
+/// array_to_string(array, delimiter [, null_string]) - Join array elements into string
+///
+/// NULL elements are skipped unless null_string is given; multidimensional arrays are flattened.
 fn register_array_to_string(conn: &Connection) -> Result<()> {
-    conn.create_scalar_function(
-        "array_to_string",
-        2,
-        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
-        |ctx| {
-            let array_json: String = ctx.get(0)?;
-            let delimiter: String = ctx.get(1)?;
-            
-            match serde_json::from_str::<JsonValue>(&array_json) {
-                Ok(JsonValue::Array(arr)) => {
-                    let elements: Vec<String> = arr.iter()
-                        .filter_map(|v| match v {
-                            JsonValue::String(s) => Some(s.clone()),
-                            JsonValue::Number(n) => Some(n.to_string()),
-                            JsonValue::Bool(b) => Some(b.to_string()),
-                            JsonValue::Null => None, // Skip NULL values like PostgreSQL
-                            _ => Some(serde_json::to_string(v).unwrap_or_default()),
-                        })
-                        .collect();
-                    Ok(Some(elements.join(&delimiter)))
-                }
-                _ => Ok(None),
-            }
-        },
-    )?;
+    for n_args in [2, 3] {
+        conn.create_scalar_function(
+            "array_to_string",
+            n_args,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            move |ctx| {
+                let array = array_arg(ctx, 0)?;
+                let delimiter: Option<String> = ctx.get(1)?;
+                let null_string: Option<String> = if n_args == 3 { ctx.get(2)? } else { None };
+                
+                let (Some(arr), Some(delimiter)) = (array, delimiter) else {
+                    return Ok(None);
+                };
+                
+                let mut elements = Vec::new();
+                flatten_elements(&arr, &mut elements);
+                let elements: Vec<String> = elements.into_iter()
+                    .filter_map(|v| match v {
+                        JsonValue::String(s) => Some(s.clone()),
+                        JsonValue::Number(n) => Some(n.to_string()),
+                        JsonValue::Bool(b) => Some(if *b { "t" } else { "f" }.to_string()),
+                        JsonValue::Null => null_string.clone(),
+                        _ => Some(serde_json::to_string(v).unwrap_or_default()),
+                    })
+                    .collect();
+                Ok(Some(elements.join(&delimiter)))
+            },
+        )?;
+    }
     
     Ok(())
 }
 
+/// Length of a 1-based dimension, None for an empty array or a dimension it does not have
+fn dimension_length(arr: &[JsonValue], dimension: i64) -> Option<usize> {
+    if arr.is_empty() || dimension < 1 {
+        return None;
+    }
+    let mut current = arr;
+    for _ in 1..dimension {
+        match current.first() {
+            Some(JsonValue::Array(inner)) => current = inner,
+            _ => return None,
+        }
+    }
+    Some(current.len())
+}
+
+/// Count the elements of an array across all dimensions
+fn count_elements(arr: &[JsonValue]) -> usize {
+    arr.iter()
+        .map(|v| match v {
+            JsonValue::Array(inner) => count_elements(inner),
+            _ => 1,
+        })
+        .sum()
+}
+
+/// Collect the elements of an array across all dimensions in storage order
+fn flatten_elements<'a>(arr: &'a [JsonValue], out: &mut Vec<&'a JsonValue>) {
+    for v in arr {
+        match v {
+            JsonValue::Array(inner) => flatten_elements(inner, out),
+            _ => out.push(v),
+        }
+    }
+}
+
 /// Helper function to count array dimensions
 fn count_dimensions(value: &JsonValue) -> i32 {
     match value {
@@ -847,4 +969,52 @@ mod tests {
         ).unwrap();
         assert!(overlap);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_array_null_and_empty_semantics() {
+        let conn = Connection::open_in_memory().unwrap();
+        register_array_functions(&conn).unwrap();
+        
+        let query_int = |sql: &str| conn.query_row(sql, [], |row| row.get::<_, Option<i32>>(0)).unwrap();
+        let query_text = |sql: &str| conn.query_row(sql, [], |row| row.get::<_, Option<String>>(0)).unwrap();
+        
+        // Empty and NULL arrays have no dimensions
+        assert_eq!(query_int("SELECT array_length('[]', 1)"), None);
+        assert_eq!(query_int("SELECT array_length(NULL, 1)"), None);
+        assert_eq!(query_int("SELECT array_length('[1,2]', 2)"), None);
+        assert_eq!(query_int("SELECT array_length('[[1,2],[3,4]]', 2)"), Some(2));
+        assert_eq!(query_int("SELECT array_upper('[]', 1)"), None);
+        assert_eq!(query_int("SELECT array_ndims('[]')"), None);
+        
+        // cardinality counts every element and is 0 for an empty array
+        assert_eq!(query_int("SELECT cardinality('[[1,2],[3,4]]')"), Some(4));
+        assert_eq!(query_int("SELECT cardinality('{}')"), Some(0));
+        assert_eq!(query_int("SELECT cardinality(NULL)"), None);
+        
+        // NULL arrays act as empty for append, prepend and cat
+        assert_eq!(query_text("SELECT array_append(NULL, 1)"), Some("[1]".to_string()));
+        assert_eq!(query_text("SELECT array_prepend(1, NULL)"), Some("[1]".to_string()));
+        assert_eq!(query_text("SELECT array_cat(NULL, '[1,2]')"), Some("[1,2]".to_string()));
+        assert_eq!(query_text("SELECT array_cat(NULL, NULL)"), None);
+        
+        // NULL elements are found and removed; NULL arrays stay NULL
+        assert_eq!(query_int("SELECT array_position('[1,null,3]', NULL)"), Some(2));
+        assert_eq!(query_int("SELECT array_position('[1,2,1]', 1, 2)"), Some(3));
+        assert_eq!(query_int("SELECT array_position(NULL, 1)"), None);
+        assert_eq!(query_text("SELECT array_positions(NULL, 1)"), None);
+        assert_eq!(query_text("SELECT array_remove('[1,null,2,null]', NULL)"), Some("[1,2]".to_string()));
+        assert_eq!(query_text("SELECT array_remove(NULL, 1)"), None);
+        assert_eq!(query_text(r#"SELECT array_replace('["1","2"]', 2, 5)"#), Some(r#"["1",5]"#.to_string()));
+        
+        // string_to_array / array_to_string
+        assert_eq!(query_text("SELECT string_to_array('', ',')"), Some("[]".to_string()));
+        assert_eq!(query_text("SELECT string_to_array(NULL, ',')"), None);
+        assert_eq!(query_text("SELECT string_to_array('abc', NULL)"), Some(r#"["a","b","c"]"#.to_string()));
+        assert_eq!(query_text("SELECT string_to_array('abc', '')"), Some(r#"["abc"]"#.to_string()));
+        assert_eq!(query_text("SELECT string_to_array('a,*,c', ',', '*')"), Some(r#"["a",null,"c"]"#.to_string()));
+        assert_eq!(query_text("SELECT array_to_string('[1,null,3]', ',')"), Some("1,3".to_string()));
+        assert_eq!(query_text("SELECT array_to_string('[1,null,3]', ',', '*')"), Some("1,*,3".to_string()));
+        assert_eq!(query_text("SELECT array_to_string('[[1,2],[3,4]]', '-')"), Some("1-2-3-4".to_string()));
+        assert_eq!(query_text("SELECT array_to_string(NULL, ',')"), None);
+    }
+}
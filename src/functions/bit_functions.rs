@@ -0,0 +1,214 @@
+use rusqlite::{Connection, Result, functions::FunctionFlags, types::{Value, ValueRef}};
+use tracing::debug;
+
+/// Register the bit string operators and the target of `::bit` / `::varbit` casts
+///
+/// Bit strings are stored as text of `0` and `1` digits. The operator functions
+/// work bitwise on two bit strings of equal length and fall back to SQLite's
+/// integer semantics for anything else, so `5 & 3` keeps working.
+pub fn register_bit_functions(conn: &Connection) -> Result<()> {
+    debug!("Registering bit string functions");
+
+    type BitOp = fn(bool, bool) -> bool;
+    type IntOp = fn(i64, i64) -> i64;
+    let operators: [(&str, &str, BitOp, IntOp); 3] = [
+        ("bit_and", "AND", |a, b| a & b, |a, b| a & b),
+        ("bit_or", "OR", |a, b| a | b, |a, b| a | b),
+        ("bit_xor", "XOR", |a, b| a ^ b, |a, b| a ^ b),
+    ];
+    for (name, verb, bit_op, int_op) in operators {
+        conn.create_scalar_function(
+            name,
+            2,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            move |ctx| {
+                let (left, right) = (ctx.get_raw(0), ctx.get_raw(1));
+                if matches!(left, ValueRef::Null) || matches!(right, ValueRef::Null) {
+                    return Ok(Value::Null);
+                }
+                if let (Some(left), Some(right)) = (bit_string(left), bit_string(right)) {
+                    if left.len() != right.len() {
+                        return Err(user_error(format!("cannot {verb} bit strings of different sizes")));
+                    }
+                    let bits = left.bytes().zip(right.bytes())
+                        .map(|(a, b)| if bit_op(a == b'1', b == b'1') { '1' } else { '0' })
+                        .collect();
+                    return Ok(Value::Text(bits));
+                }
+                Ok(Value::Integer(int_op(integer_arg(left), integer_arg(right))))
+            },
+        )?;
+    }
+
+    // bit_not(value) - unary ~
+    conn.create_scalar_function(
+        "bit_not",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let value = ctx.get_raw(0);
+            if matches!(value, ValueRef::Null) {
+                return Ok(Value::Null);
+            }
+            match bit_string(value) {
+                Some(bits) => Ok(Value::Text(bits.bytes().map(|b| if b == b'1' { '0' } else { '1' }).collect())),
+                None => Ok(Value::Integer(!integer_arg(value))),
+            }
+        },
+    )?;
+
+    // pg_bit_cast(value, length, varying) - length is NULL when the type has no modifier
+    conn.create_scalar_function(
+        "pg_bit_cast",
+        3,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let length = ctx.get::<Option<i64>>(1)?;
+            let varying = ctx.get::<bool>(2)?;
+            match ctx.get_raw(0) {
+                ValueRef::Null => Ok(None),
+                ValueRef::Integer(value) => {
+                    if varying {
+                        return Err(user_error("cannot cast type integer to bit varying"));
+                    }
+                    let length = length.unwrap_or(1).clamp(0, 64) as usize;
+                    let bits = (0..length).rev()
+                        .map(|i| if (value >> i) & 1 == 1 { '1' } else { '0' })
+                        .collect();
+                    Ok(Some(bits))
+                }
+                value => {
+                    let text = text_arg(value);
+                    validate_bit_digits(&text).map_err(user_error)?;
+                    Ok(Some(cast_bits(&text, length, varying)))
+                }
+            }
+        },
+    )?;
+
+    Ok(())
+}
+
+/// Check that a value only contains binary digits, reporting the first one that is not
+pub fn validate_bit_digits(text: &str) -> std::result::Result<(), String> {
+    match text.chars().find(|c| *c != '0' && *c != '1') {
+        Some(c) => Err(format!("\"{c}\" is not a valid binary digit")),
+        None => Ok(()),
+    }
+}
+
+/// Shift a bit string left or right by `count` positions, keeping its length
+pub fn shift_bits(bits: &str, count: i64, left: bool) -> String {
+    let len = bits.len() as i64;
+    // A negative count shifts the other way, as in PostgreSQL
+    let (count, left) = if count < 0 { (-count, !left) } else { (count, left) };
+    if count >= len {
+        return "0".repeat(bits.len());
+    }
+    let count = count as usize;
+    if left {
+        format!("{}{}", &bits[count..], "0".repeat(count))
+    } else {
+        format!("{}{}", "0".repeat(count), &bits[..bits.len() - count])
+    }
+}
+
+/// Apply the length of a BIT(n) or VARBIT(n) cast. Explicit casts to BIT(n)
+/// truncate or zero-pad on the right; a bare BIT means BIT(1).
+fn cast_bits(bits: &str, length: Option<i64>, varying: bool) -> String {
+    let length = match (length, varying) {
+        (Some(length), _) => length.max(0) as usize,
+        (None, true) => return bits.to_string(),
+        (None, false) => 1,
+    };
+    if bits.len() >= length {
+        bits[..length].to_string()
+    } else if varying {
+        bits.to_string()
+    } else {
+        format!("{bits:0<length$}")
+    }
+}
+
+/// Interpret a text value as a bit string, if it consists of binary digits only
+pub fn bit_string(value: ValueRef) -> Option<String> {
+    match value {
+        ValueRef::Text(text) if !text.is_empty() && text.iter().all(|b| *b == b'0' || *b == b'1') => {
+            Some(String::from_utf8_lossy(text).into_owned())
+        }
+        _ => None,
+    }
+}
+
+/// Coerce a value to an integer the way SQLite's bitwise operators do
+fn integer_arg(value: ValueRef) -> i64 {
+    match value {
+        ValueRef::Integer(i) => i,
+        ValueRef::Real(f) => f as i64,
+        ValueRef::Text(text) => {
+            let text = String::from_utf8_lossy(text);
+            let text = text.trim();
+            text.parse::<i64>().ok()
+                .or_else(|| text.parse::<f64>().ok().map(|f| f as i64))
+                .unwrap_or(0)
+        }
+        _ => 0,
+    }
+}
+
+fn text_arg(value: ValueRef) -> String {
+    match value {
+        ValueRef::Text(text) | ValueRef::Blob(text) => String::from_utf8_lossy(text).into_owned(),
+        ValueRef::Real(f) => f.to_string(),
+        ValueRef::Integer(i) => i.to_string(),
+        ValueRef::Null => String::new(),
+    }
+}
+
+fn user_error(message: impl Into<String>) -> rusqlite::Error {
+    rusqlite::Error::UserFunctionError(message.into().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(conn: &Connection, sql: &str) -> Option<String> {
+        conn.query_row(sql, [], |row| row.get::<_, Option<String>>(0)).unwrap()
+    }
+
+    #[test]
+    fn test_bit_operators() {
+        let conn = Connection::open_in_memory().unwrap();
+        register_bit_functions(&conn).unwrap();
+
+        assert_eq!(query(&conn, "SELECT bit_and('1100', '1010')"), Some("1000".to_string()));
+        assert_eq!(query(&conn, "SELECT bit_or('1100', '1010')"), Some("1110".to_string()));
+        assert_eq!(query(&conn, "SELECT bit_xor('1100', '1010')"), Some("0110".to_string()));
+        assert_eq!(query(&conn, "SELECT bit_not('1100')"), Some("0011".to_string()));
+        assert_eq!(query(&conn, "SELECT bit_and(NULL, '1010')"), None);
+        assert!(conn.query_row("SELECT bit_and('11', '101')", [], |row| row.get::<_, String>(0)).is_err());
+
+        // Integers keep SQLite's semantics
+        let value: i64 = conn.query_row("SELECT bit_and(12, 10) + bit_or(12, 10) + bit_xor(12, 10) + bit_not(0)", [], |row| row.get(0)).unwrap();
+        assert_eq!(value, 8 + 14 + 6 - 1);
+    }
+
+    #[test]
+    fn test_bit_cast_and_shift() {
+        let conn = Connection::open_in_memory().unwrap();
+        register_bit_functions(&conn).unwrap();
+
+        assert_eq!(query(&conn, "SELECT pg_bit_cast('101', 5, 0)"), Some("10100".to_string()));
+        assert_eq!(query(&conn, "SELECT pg_bit_cast('10111', 3, 0)"), Some("101".to_string()));
+        assert_eq!(query(&conn, "SELECT pg_bit_cast('10111', NULL, 0)"), Some("1".to_string()));
+        assert_eq!(query(&conn, "SELECT pg_bit_cast('101', 8, 1)"), Some("101".to_string()));
+        assert_eq!(query(&conn, "SELECT pg_bit_cast(5, 4, 0)"), Some("0101".to_string()));
+        assert!(conn.query_row("SELECT pg_bit_cast('102', 3, 0)", [], |row| row.get::<_, String>(0)).is_err());
+
+        assert_eq!(shift_bits("10110", 2, true), "11000");
+        assert_eq!(shift_bits("10110", 2, false), "00101");
+        assert_eq!(shift_bits("10110", -1, true), "01011");
+        assert_eq!(shift_bits("101", 7, true), "000");
+    }
+}
@@ -1,5 +1,7 @@
-use rusqlite::{Connection, Result, functions::FunctionFlags};
+use rusqlite::{Connection, Result, functions::{Context, FunctionFlags}, types::Value};
 use tracing::debug;
+use crate::metadata::{Namespaces, OidAllocator};
+use crate::metadata::namespaces::{PG_CATALOG_OID, PUBLIC_OID};
 
 /// Register PostgreSQL catalog-related functions
 pub fn register_catalog_functions(conn: &Connection) -> Result<()> {
@@ -31,33 +33,227 @@ pub fn register_catalog_functions(conn: &Connection) -> Result<()> {
     // Note: SQLite doesn't support schema-qualified function names,
     // so we handle pg_catalog.pg_table_is_visible through query rewriting
     
-    // regclass type cast function
+    // regclass(name) - what 'name'::regclass::oid gives: the OID pg_class reports for the relation
     conn.create_scalar_function(
         "regclass",
         1,
-        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        FunctionFlags::SQLITE_UTF8,
+        |ctx| {
+            let name = match ctx.get::<Value>(0)? {
+                Value::Integer(oid) => return Ok(Some(oid)),
+                Value::Text(name) => name,
+                _ => return Ok(None),
+            };
+            // SAFETY: the connection is only used for the duration of this call, on this thread
+            let conn = unsafe { ctx.get_connection()? };
+            OidAllocator::relation_oid(&conn, &relation_name(&name)).map(Some)
+        },
+    )?;
+    
+    // regnamespace(oid) - what oid::regnamespace prints: the name of the schema
+    conn.create_scalar_function(
+        "regnamespace",
+        1,
+        FunctionFlags::SQLITE_UTF8,
+        |ctx| {
+            let oid = match ctx.get::<Value>(0)? {
+                Value::Integer(oid) => oid,
+                Value::Text(name) => match name.parse::<i64>() {
+                    Ok(oid) => oid,
+                    Err(_) => return Ok(Some(name)),
+                },
+                _ => return Ok(None),
+            };
+            let name = match oid {
+                PG_CATALOG_OID => "pg_catalog".to_string(),
+                PUBLIC_OID => "public".to_string(),
+                _ => {
+                    // SAFETY: the connection is only used for the duration of this call, on this thread
+                    let conn = unsafe { ctx.get_connection()? };
+                    Namespaces::list(&conn)?.into_iter()
+                        .find(|(_, schema_oid)| *schema_oid == oid)
+                        .map(|(schema, _)| schema)
+                        .unwrap_or_else(|| oid.to_string())
+                }
+            };
+            Ok(Some(name))
+        },
+    )?;
+    
+    // pgsqlite_relation_oid(name) - the OID of a table, view or index, for the catalog views
+    conn.create_scalar_function(
+        "pgsqlite_relation_oid",
+        1,
+        FunctionFlags::SQLITE_UTF8,
         |ctx| {
-            let table_name: String = ctx.get(0)?;
-            
-            // Look up table OID from pg_class view
-            // For now, just generate a consistent OID
-            let oid = generate_table_oid(&table_name);
-            Ok(oid)
+            let Some(name) = ctx.get::<Option<String>>(0)? else {
+                return Ok(None);
+            };
+            // SAFETY: the connection is only used for the duration of this call, on this thread
+            let conn = unsafe { ctx.get_connection()? };
+            OidAllocator::relation_oid(&conn, &name).map(Some)
         },
     )?;
     
+    // col_description(table_oid, column_number) - comment on a table column
+    // pgsqlite_relname(name) / pgsqlite_relnamespace(name) - the name within its schema and
+    // the schema OID of a table, view, index or type SQLite stores as `name`
+    conn.create_scalar_function(
+        "pgsqlite_relname",
+        1,
+        FunctionFlags::SQLITE_UTF8,
+        |ctx| {
+            let Some(name) = ctx.get::<Option<String>>(0)? else {
+                return Ok(None);
+            };
+            // SAFETY: the connection is only used for the duration of this call, on this thread
+            let conn = unsafe { ctx.get_connection()? };
+            Ok(Some(Namespaces::split(&Namespaces::list(&conn)?, &name).1.to_string()))
+        },
+    )?;
+    conn.create_scalar_function(
+        "pgsqlite_relnamespace",
+        1,
+        FunctionFlags::SQLITE_UTF8,
+        |ctx| {
+            let Some(name) = ctx.get::<Option<String>>(0)? else {
+                return Ok(None);
+            };
+            // SAFETY: the connection is only used for the duration of this call, on this thread
+            let conn = unsafe { ctx.get_connection()? };
+            Ok(Some(Namespaces::split(&Namespaces::list(&conn)?, &name).0))
+        },
+    )?;
+    
+    conn.create_scalar_function(
+        "col_description",
+        2,
+        FunctionFlags::SQLITE_UTF8,
+        |ctx| {
+            let objoid: Value = ctx.get(0)?;
+            let objsubid: Value = ctx.get(1)?;
+            lookup_description(ctx, "classoid = 1259 AND objsubid = ?2", objoid, Some(objsubid))
+        },
+    )?;
+    
+    // obj_description(oid, catalog_name) - comment on an object of the named catalog
+    conn.create_scalar_function(
+        "obj_description",
+        2,
+        FunctionFlags::SQLITE_UTF8,
+        |ctx| {
+            let objoid: Value = ctx.get(0)?;
+            let catalog: String = ctx.get(1)?;
+            let Some(classoid) = catalog_oid(&catalog) else {
+                return Ok(None);
+            };
+            lookup_description(ctx, &format!("classoid = {classoid} AND objsubid = 0"), objoid, None)
+        },
+    )?;
+    
+    // obj_description(oid) - deprecated form that matches any catalog
+    conn.create_scalar_function(
+        "obj_description",
+        1,
+        FunctionFlags::SQLITE_UTF8,
+        |ctx| {
+            let objoid: Value = ctx.get(0)?;
+            lookup_description(ctx, "objsubid = 0", objoid, None)
+        },
+    )?;
+    
+    // shobj_description(oid, catalog_name) - comment on a shared object such as a database
+    conn.create_scalar_function(
+        "shobj_description",
+        2,
+        FunctionFlags::SQLITE_UTF8,
+        |ctx| {
+            let objoid: Value = ctx.get(0)?;
+            let catalog: String = ctx.get(1)?;
+            let Some(classoid) = catalog_oid(&catalog) else {
+                return Ok(None);
+            };
+            lookup_description(ctx, &format!("classoid = {classoid} AND objsubid = 0"), objoid, None)
+        },
+    )?;
+    
+    // pg_get_triggerdef(trigger_oid [, pretty]) - the CREATE TRIGGER statement of a trigger
+    for n_args in [1, 2] {
+        conn.create_scalar_function(
+            "pg_get_triggerdef",
+            n_args,
+            FunctionFlags::SQLITE_UTF8,
+            |ctx| {
+                let oid: Value = ctx.get(0)?;
+                // SAFETY: the connection is only used for the duration of this call, on this thread
+                let conn = unsafe { ctx.get_connection()? };
+                match conn.query_row("SELECT definition FROM __pgsqlite_triggers WHERE oid = CAST(?1 AS INTEGER)", [oid], |row| row.get(0)) {
+                    Ok(definition) => Ok(Some(definition)),
+                    Err(rusqlite::Error::QueryReturnedNoRows | rusqlite::Error::SqliteFailure(_, _)) => Ok(None::<String>),
+                    Err(e) => Err(e),
+                }
+            },
+        )?;
+    }
+    
     debug!("Catalog functions registered successfully");
     Ok(())
 }
 
-// Generate a stable OID from table name
-fn generate_table_oid(name: &str) -> i32 {
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
-    
-    let mut hasher = DefaultHasher::new();
-    name.hash(&mut hasher);
-    ((hasher.finish() & 0x7FFFFFFF) % 1000000 + 16384) as i32
+/// OID of a system catalog as used in pg_description.classoid
+pub fn catalog_oid(catalog: &str) -> Option<i64> {
+    Some(match catalog.strip_prefix("pg_catalog.").unwrap_or(catalog) {
+        "pg_class" => 1259,
+        "pg_proc" => 1255,
+        "pg_type" => 1247,
+        "pg_namespace" => 2615,
+        "pg_constraint" => 2606,
+        "pg_database" => 1262,
+        _ => return None,
+    })
+}
+
+/// Read a comment from pg_description, NULL when there is none
+fn lookup_description(ctx: &Context<'_>, filter: &str, objoid: Value, objsubid: Option<Value>) -> Result<Option<String>> {
+    // SAFETY: the connection is only used for the duration of this call, on this thread
+    let conn = unsafe { ctx.get_connection()? };
+    let sql = format!("SELECT description FROM pg_description WHERE objoid = ?1 AND {filter}");
+    let description = match objsubid {
+        Some(objsubid) => conn.query_row(&sql, [objoid, objsubid], |row| row.get(0)),
+        None => conn.query_row(&sql, [objoid], |row| row.get(0)),
+    };
+    match description {
+        Ok(description) => Ok(Some(description)),
+        // Databases created before pg_description existed have no comments
+        Err(rusqlite::Error::QueryReturnedNoRows | rusqlite::Error::SqliteFailure(_, _)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// The SQLite name of the relation a regclass literal names, see Namespaces
+fn relation_name(literal: &str) -> String {
+    let mut quoted = false;
+    let mut parts = vec![0];
+    for (i, c) in literal.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '.' if !quoted => parts.push(i + 1),
+            _ => {}
+        }
+    }
+    let part = |start: usize, end: usize| {
+        let name = literal[start..end].trim_end_matches('.').trim();
+        match name.strip_prefix('"').and_then(|n| n.strip_suffix('"')) {
+            Some(quoted) => quoted.replace("\"\"", "\""),
+            None => name.to_lowercase(),
+        }
+    };
+    let start = parts[parts.len() - 1];
+    let name = part(start, literal.len());
+    match parts.len() {
+        1 => name,
+        n => Namespaces::qualify(&part(parts[n - 2], start), &name),
+    }
 }
 
 #[cfg(test)]
@@ -92,5 +288,19 @@ mod tests {
             .query_row("SELECT regclass('test_table')", [], |row| row.get(0))
             .unwrap();
         assert_eq!(oid, oid2);
+        
+        // Schema-qualified and quoted names, and OIDs, name the same relation
+        conn.execute_batch("CREATE TABLE test_table (id INTEGER)").unwrap();
+        crate::metadata::OidAllocator::sync_relations(&conn).unwrap();
+        for literal in ["public.test_table", "\"test_table\"", "Test_Table"] {
+            let same: i32 = conn
+                .query_row("SELECT regclass(?1)", [literal], |row| row.get(0))
+                .unwrap();
+            assert_eq!(same, oid, "{literal}");
+        }
+        let same: i32 = conn
+            .query_row("SELECT regclass(?1)", [oid], |row| row.get(0))
+            .unwrap();
+        assert_eq!(same, oid);
     }
 }
\ No newline at end of file
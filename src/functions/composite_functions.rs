@@ -0,0 +1,238 @@
+use rusqlite::{Connection, Result, functions::FunctionFlags, types::{Value, ValueRef}};
+use serde_json::Value as JsonValue;
+use tracing::debug;
+
+/// Register the functions behind composite type values
+///
+/// Composite values are stored as PostgreSQL record text, e.g. `(1,"Main St")`,
+/// so they come back out unchanged. `ROW(...)` is translated to pg_row(), field
+/// access `(col).field` to pg_record_field() and row_to_json() nests them through
+/// pg_record_to_json().
+pub fn register_composite_functions(conn: &Connection) -> Result<()> {
+    debug!("Registering composite type functions");
+
+    // pg_row(value, ...) - ROW(...) constructor
+    conn.create_scalar_function(
+        "pg_row",
+        -1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let fields: Vec<Option<String>> = (0..ctx.len()).map(|i| field_text(ctx.get_raw(i))).collect();
+            Ok(format_record(&fields))
+        },
+    )?;
+
+    // pg_record_is_valid(value, field_count) - used by the composite column triggers
+    conn.create_scalar_function(
+        "pg_record_is_valid",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let field_count = ctx.get::<i64>(1)?;
+            match ctx.get_raw(0) {
+                ValueRef::Text(text) => Ok(parse_record(&String::from_utf8_lossy(text))
+                    .is_some_and(|fields| fields.len() as i64 == field_count)),
+                _ => Ok(false),
+            }
+        },
+    )?;
+
+    // pg_record_field(value, attnum) - (col).field, with 1-based field positions
+    conn.create_scalar_function(
+        "pg_record_field",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let Some(text) = ctx.get::<Option<String>>(0)? else {
+                return Ok(None);
+            };
+            let attnum = ctx.get::<i64>(1)?;
+            let fields = parse_record(&text)
+                .ok_or_else(|| user_error(format!("malformed record literal: \"{text}\"")))?;
+            Ok(usize::try_from(attnum - 1).ok().and_then(|i| fields.into_iter().nth(i)).flatten())
+        },
+    )?;
+
+    // pg_record_to_json(value, descriptor) - the descriptor is a JSON array of
+    // [name, type] pairs, where the type of a nested composite field is its own descriptor
+    conn.create_scalar_function(
+        "pg_record_to_json",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let Some(text) = ctx.get::<Option<String>>(0)? else {
+                return Ok(Value::Null);
+            };
+            let descriptor: JsonValue = serde_json::from_str(&ctx.get::<String>(1)?)
+                .map_err(|e| user_error(format!("invalid composite descriptor: {e}")))?;
+            let json = record_to_json(&text, &descriptor)
+                .ok_or_else(|| user_error(format!("malformed record literal: \"{text}\"")))?;
+            Ok(Value::Text(json))
+        },
+    )?;
+
+    Ok(())
+}
+
+/// Format fields as record text, quoting them the way PostgreSQL's record_out does.
+/// NULL fields are left empty.
+pub fn format_record(fields: &[Option<String>]) -> String {
+    let mut out = String::from("(");
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        let Some(field) = field else { continue };
+        let needs_quotes = field.is_empty()
+            || field.chars().any(|c| matches!(c, '"' | '\\' | '(' | ')' | ',') || c.is_whitespace());
+        if needs_quotes {
+            out.push('"');
+            for c in field.chars() {
+                if c == '"' || c == '\\' {
+                    out.push(c);
+                }
+                out.push(c);
+            }
+            out.push('"');
+        } else {
+            out.push_str(field);
+        }
+    }
+    out.push(')');
+    out
+}
+
+/// Parse record text into its fields, following PostgreSQL's record_in rules:
+/// an empty unquoted field is NULL, double quotes group characters and `""`
+/// or a backslash escape a quote. Returns None for malformed input.
+pub fn parse_record(text: &str) -> Option<Vec<Option<String>>> {
+    let inner = text.trim().strip_prefix('(')?;
+    let mut fields = Vec::new();
+    let mut chars = inner.chars().peekable();
+
+    loop {
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut in_quotes = false;
+        loop {
+            let c = chars.next()?;
+            match c {
+                '\\' => field.push(chars.next()?),
+                '"' if in_quotes && chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => {
+                    in_quotes = !in_quotes;
+                    quoted = true;
+                }
+                ',' | ')' if !in_quotes => {
+                    fields.push(if field.is_empty() && !quoted { None } else { Some(field) });
+                    if c == ')' {
+                        return chars.all(char::is_whitespace).then_some(fields);
+                    }
+                    break;
+                }
+                _ => field.push(c),
+            }
+        }
+    }
+}
+
+/// Convert record text to a JSON object using a composite descriptor. The object
+/// is written out by hand to keep the fields in declaration order.
+fn record_to_json(text: &str, descriptor: &JsonValue) -> Option<String> {
+    let attributes = descriptor.as_array()?;
+    let fields = parse_record(text)?;
+    let mut members = Vec::with_capacity(attributes.len());
+    for (i, attribute) in attributes.iter().enumerate() {
+        let name = attribute.get(0)?.as_str()?;
+        let field_type = attribute.get(1)?;
+        let value = match fields.get(i).cloned().flatten() {
+            None => "null".to_string(),
+            Some(value) => field_to_json(&value, field_type)?,
+        };
+        members.push(format!("{}:{value}", JsonValue::String(name.to_string())));
+    }
+    Some(format!("{{{}}}", members.join(",")))
+}
+
+fn field_to_json(value: &str, field_type: &JsonValue) -> Option<String> {
+    let type_name = match field_type {
+        JsonValue::Array(_) => return record_to_json(value, field_type),
+        JsonValue::String(type_name) => type_name.to_lowercase(),
+        _ => return None,
+    };
+    let base_type = type_name.split('(').next().unwrap_or(&type_name).trim();
+    let json = match base_type {
+        "smallint" | "int2" | "integer" | "int" | "int4" | "bigint" | "int8" | "real" | "float4"
+        | "double precision" | "float8" | "numeric" | "decimal" => serde_json::from_str::<serde_json::Number>(value)
+            .map(JsonValue::Number)
+            .unwrap_or_else(|_| JsonValue::String(value.to_string())),
+        "boolean" | "bool" => match value {
+            "t" | "true" | "1" => JsonValue::Bool(true),
+            "f" | "false" | "0" => JsonValue::Bool(false),
+            _ => JsonValue::String(value.to_string()),
+        },
+        "json" | "jsonb" => serde_json::from_str(value).unwrap_or_else(|_| JsonValue::String(value.to_string())),
+        _ => JsonValue::String(value.to_string()),
+    };
+    Some(json.to_string())
+}
+
+fn field_text(value: ValueRef) -> Option<String> {
+    match value {
+        ValueRef::Null => None,
+        ValueRef::Integer(i) => Some(i.to_string()),
+        ValueRef::Real(f) => Some(f.to_string()),
+        ValueRef::Text(text) => Some(String::from_utf8_lossy(text).into_owned()),
+        ValueRef::Blob(blob) => Some(format!("\\x{}", hex::encode(blob))),
+    }
+}
+
+fn user_error(message: impl Into<String>) -> rusqlite::Error {
+    rusqlite::Error::UserFunctionError(message.into().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(conn: &Connection, sql: &str) -> Option<String> {
+        conn.query_row(sql, [], |row| row.get::<_, Option<String>>(0)).unwrap()
+    }
+
+    #[test]
+    fn test_record_text_round_trip() {
+        let fields = vec![Some("1".to_string()), Some("Main St".to_string()), None, Some(String::new()), Some("say \"hi\"".to_string())];
+        let text = format_record(&fields);
+        assert_eq!(text, r#"(1,"Main St",,"","say ""hi""")"#);
+        assert_eq!(parse_record(&text), Some(fields));
+
+        assert_eq!(parse_record(r#" (a,"(b,c)",d\,e) "#), Some(vec![Some("a".to_string()), Some("(b,c)".to_string()), Some("d,e".to_string())]));
+        assert_eq!(parse_record("()"), Some(vec![None]));
+        assert_eq!(parse_record("(1,2"), None);
+        assert_eq!(parse_record("1,2"), None);
+        assert_eq!(parse_record("(1,2) x"), None);
+    }
+
+    #[test]
+    fn test_composite_functions() {
+        let conn = Connection::open_in_memory().unwrap();
+        register_composite_functions(&conn).unwrap();
+
+        assert_eq!(query(&conn, "SELECT pg_row(1, 'Main St', NULL)"), Some(r#"(1,"Main St",)"#.to_string()));
+        assert_eq!(query(&conn, r#"SELECT pg_record_field('(1,"Main St")', 2)"#), Some("Main St".to_string()));
+        assert_eq!(query(&conn, "SELECT pg_record_field('(1,)', 2)"), None);
+
+        let valid: bool = conn.query_row("SELECT pg_record_is_valid('(1,2)', 2)", [], |row| row.get(0)).unwrap();
+        assert!(valid);
+        let valid: bool = conn.query_row("SELECT pg_record_is_valid('(1,2)', 3)", [], |row| row.get(0)).unwrap();
+        assert!(!valid);
+
+        assert_eq!(
+            query(&conn, r#"SELECT pg_record_to_json('(5,"(x,t)")', '[["n","integer"],["inner",[["s","text"],["b","boolean"]]]]')"#),
+            Some(r#"{"n":5,"inner":{"s":"x","b":true}}"#.to_string())
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use rusqlite::{Connection, Result, functions::{Context, FunctionFlags}, types::{Value, ValueRef}};
+use serde_json::Value as JsonValue;
+use tracing::debug;
+
+/// Register the functions behind tablefunc's crosstab()
+///
+/// `crosstab(source_sql [, category_sql]) AS ct(row_name type, ...)` is translated
+/// to json_each() over pg_crosstab(source_sql, [category_sql,] column_count), which
+/// runs the queries on the current connection and returns the pivoted rows as a
+/// JSON array of arrays.
+pub fn register_crosstab_functions(conn: &Connection) -> Result<()> {
+    debug!("Registering crosstab functions");
+
+    // pg_crosstab(source_sql, column_count) - values fill the columns in order
+    conn.create_scalar_function(
+        "pg_crosstab",
+        2,
+        FunctionFlags::SQLITE_UTF8,
+        |ctx| {
+            let source_sql = ctx.get::<String>(0)?;
+            let column_count = column_count(ctx, 1)?;
+            let rows = run_query(ctx, &source_sql)?;
+            Ok(rows_to_json(crosstab_by_position(rows, column_count)?))
+        },
+    )?;
+
+    // pg_crosstab(source_sql, category_sql, column_count) - values go to the column of their category
+    conn.create_scalar_function(
+        "pg_crosstab",
+        3,
+        FunctionFlags::SQLITE_UTF8,
+        |ctx| {
+            let source_sql = ctx.get::<String>(0)?;
+            let category_sql = ctx.get::<String>(1)?;
+            let column_count = column_count(ctx, 2)?;
+
+            let categories = run_query(ctx, &category_sql)?;
+            if categories.first().is_some_and(|row| row.len() != 1) {
+                return Err(user_error("invalid crosstab categories query: the query must return one column"));
+            }
+            let categories: Vec<Value> = categories.into_iter().filter_map(|mut row| row.pop()).collect();
+            let rows = run_query(ctx, &source_sql)?;
+            Ok(rows_to_json(crosstab_by_category(rows, &categories, column_count)?))
+        },
+    )?;
+
+    Ok(())
+}
+
+/// crosstab(text): the source returns (row_name, category, value) sorted by row_name;
+/// each row_name's values fill the value columns left to right and extras are dropped
+fn crosstab_by_position(rows: Vec<Vec<Value>>, column_count: usize) -> Result<Vec<Vec<Value>>> {
+    if rows.first().is_some_and(|row| row.len() != 3) {
+        return Err(user_error("invalid crosstab source data query: the query must return 3 columns: row_name, category, and value"));
+    }
+
+    let mut result: Vec<Vec<Value>> = Vec::new();
+    let mut filled = 0;
+    for mut row in rows {
+        let value = row.pop().unwrap_or(Value::Null);
+        let row_name = row.swap_remove(0);
+        if result.last().is_none_or(|current| current[0] != row_name) {
+            let mut output = vec![Value::Null; column_count];
+            output[0] = row_name;
+            result.push(output);
+            filled = 0;
+        }
+        filled += 1;
+        if let Some(current) = result.last_mut()
+            && filled < column_count {
+                current[filled] = value;
+            }
+    }
+    Ok(result)
+}
+
+/// crosstab(text, text): the source returns (row_name, extra columns..., category, value);
+/// the extra columns come from a row_name's first row and values are placed by category
+fn crosstab_by_category(rows: Vec<Vec<Value>>, categories: &[Value], column_count: usize) -> Result<Vec<Vec<Value>>> {
+    let Some(source_columns) = rows.first().map(Vec::len) else {
+        return Ok(Vec::new());
+    };
+    if source_columns < 3 {
+        return Err(user_error("invalid crosstab source data query: the query must return at least 3 columns"));
+    }
+    let extra_columns = source_columns - 3;
+    let expected = 1 + extra_columns + categories.len();
+    if column_count != expected {
+        return Err(user_error(format!(
+            "invalid crosstab return type: query-specified return tuple has {column_count} columns but crosstab returns {expected}"
+        )));
+    }
+
+    let mut result: Vec<Vec<Value>> = Vec::new();
+    for mut row in rows {
+        let value = row.pop().unwrap_or(Value::Null);
+        let category = row.pop().unwrap_or(Value::Null);
+        if result.last().is_none_or(|current| current[0] != row[0]) {
+            let mut output = row;
+            output.resize(column_count, Value::Null);
+            result.push(output);
+        }
+        if let (Some(current), Some(position)) = (result.last_mut(), categories.iter().position(|c| *c == category)) {
+            current[1 + extra_columns + position] = value;
+        }
+    }
+    Ok(result)
+}
+
+fn run_query(ctx: &Context<'_>, sql: &str) -> Result<Vec<Vec<Value>>> {
+    // SAFETY: the connection is only used for the duration of this call, on this thread
+    let conn = unsafe { ctx.get_connection()? };
+    let mut stmt = conn.prepare(sql)?;
+    let column_count = stmt.column_count();
+    let rows = stmt.query_map([], |row| {
+        (0..column_count).map(|i| row.get::<_, Value>(i)).collect::<Result<Vec<_>>>()
+    })?;
+    rows.collect()
+}
+
+fn column_count(ctx: &Context<'_>, idx: usize) -> Result<usize> {
+    usize::try_from(ctx.get::<i64>(idx)?).ok()
+        .filter(|&count| count >= 2)
+        .ok_or_else(|| user_error("invalid crosstab return type: at least a row name and one value column are required"))
+}
+
+fn rows_to_json(rows: Vec<Vec<Value>>) -> String {
+    let rows: Vec<JsonValue> = rows.into_iter()
+        .map(|row| JsonValue::Array(row.iter().map(|value| match ValueRef::from(value) {
+            ValueRef::Null => JsonValue::Null,
+            ValueRef::Integer(i) => JsonValue::from(i),
+            ValueRef::Real(f) => JsonValue::from(f),
+            ValueRef::Text(text) => JsonValue::String(String::from_utf8_lossy(text).into_owned()),
+            ValueRef::Blob(blob) => JsonValue::String(format!("\\x{}", hex::encode(blob))),
+        }).collect()))
+        .collect();
+    JsonValue::Array(rows).to_string()
+}
+
+fn user_error(message: impl Into<String>) -> rusqlite::Error {
+    rusqlite::Error::UserFunctionError(message.into().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        register_crosstab_functions(&conn).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE sales (region TEXT, quarter TEXT, amount INTEGER);
+             INSERT INTO sales VALUES ('east', 'q1', 10), ('east', 'q2', 20), ('west', 'q2', 5), ('west', 'q3', 7);"
+        ).unwrap();
+        conn
+    }
+
+    fn query(conn: &Connection, sql: &str) -> String {
+        conn.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn test_crosstab_by_position() {
+        let conn = setup();
+        assert_eq!(
+            query(&conn, "SELECT pg_crosstab('SELECT region, quarter, amount FROM sales ORDER BY 1, 2', 3)"),
+            r#"[["east",10,20],["west",5,7]]"#
+        );
+        assert_eq!(
+            query(&conn, "SELECT pg_crosstab('SELECT region, quarter, amount FROM sales ORDER BY 1, 2', 2)"),
+            r#"[["east",10],["west",5]]"#
+        );
+    }
+
+    #[test]
+    fn test_crosstab_by_category() {
+        let conn = setup();
+        assert_eq!(
+            query(&conn, "SELECT pg_crosstab('SELECT region, quarter, amount FROM sales ORDER BY 1', 'SELECT DISTINCT quarter FROM sales ORDER BY 1', 4)"),
+            r#"[["east",10,20,null],["west",null,5,7]]"#
+        );
+
+        let err = conn.query_row(
+            "SELECT pg_crosstab('SELECT region, quarter, amount FROM sales', 'SELECT DISTINCT quarter FROM sales', 3)",
+            [],
+            |row| row.get::<_, String>(0),
+        ).unwrap_err();
+        assert!(err.to_string().contains("has 3 columns but crosstab returns 4"), "{err}");
+    }
+}
@@ -1,6 +1,10 @@
 use rusqlite::{Connection, Result, Error};
 use rusqlite::functions::FunctionFlags;
-use chrono::{DateTime, NaiveDate, NaiveTime, Utc, Datelike, Timelike};
+use rusqlite::functions::{Aggregate, Context};
+use rusqlite::types::{Value, ValueRef};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc, Datelike, Timelike};
+use crate::types::datetime_utils;
+use crate::types::Interval;
 
 /// Register datetime-related functions in SQLite
 pub fn register_datetime_functions(conn: &Connection) -> Result<()> {
@@ -78,11 +82,31 @@ pub fn register_datetime_functions(conn: &Connection) -> Result<()> {
                 _ => return Err(Error::UserFunctionError("Expected text field name".into())),
             };
             
+            // Interval text (stored intervals with months) is extracted as an interval
+            if let ValueRef::Text(text) = ctx.get_raw(1)
+                && let Some(interval) = std::str::from_utf8(text).ok().and_then(|t| Interval::parse(t).ok())
+            {
+                return extract_interval_part(&field, &interval);
+            }
             let timestamp: i64 = ctx.get(1)?;
             extract_date_part(&field, timestamp)
         },
     )?;
     
+    // interval_part(field, interval) - EXTRACT(field FROM interval)
+    conn.create_scalar_function(
+        "interval_part",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let field: String = ctx.get(0)?;
+            match interval_arg(ctx, 1)? {
+                Some(interval) => extract_interval_part(&field, &interval).map(Some),
+                None => Ok(None),
+            }
+        },
+    )?;
+    
     // date_trunc(field, timestamp) - Truncate timestamp to specified precision
     conn.create_scalar_function(
         "date_trunc",
@@ -111,30 +135,114 @@ pub fn register_datetime_functions(conn: &Connection) -> Result<()> {
         },
     )?;
     
-    // age(timestamp1, timestamp2) - Calculate interval between timestamps
+    // age(timestamp1, timestamp2) - Symbolic difference in years, months and days
     conn.create_scalar_function(
         "age",
         2,
-        FunctionFlags::SQLITE_UTF8,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
         |ctx| {
-            let ts1: i64 = ctx.get(0)?;
-            let ts2: i64 = ctx.get(1)?;
-            Ok(ts1 - ts2) // Return difference in microseconds
+            match (timestamp_arg(ctx, 0)?, timestamp_arg(ctx, 1)?) {
+                (Some(ts1), Some(ts2)) => Ok(interval_value(&Interval::age(&ts1, &ts2))),
+                _ => Ok(Value::Null),
+            }
         },
     )?;
     
-    // age(timestamp) - Calculate interval from current time
+    // age(timestamp) - Symbolic difference from midnight of the current date
     conn.create_scalar_function(
         "age",
         1,
         FunctionFlags::SQLITE_UTF8,
         |ctx| {
-            let ts: i64 = ctx.get(0)?;
-            let now = Utc::now().timestamp_micros();
-            Ok(now - ts) // Return difference in microseconds
+            match timestamp_arg(ctx, 0)? {
+                Some(ts) => {
+                    let today = Utc::now().date_naive().and_time(NaiveTime::MIN);
+                    Ok(interval_value(&Interval::age(&today, &ts)))
+                }
+                None => Ok(Value::Null),
+            }
         },
     )?;
     
+    // justify_hours / justify_days / justify_interval
+    type Justify = fn(&Interval) -> Interval;
+    let justify_functions: [(&str, Justify); 3] = [
+        ("justify_hours", Interval::justify_hours),
+        ("justify_days", Interval::justify_days),
+        ("justify_interval", Interval::justify_interval),
+    ];
+    for (name, justify) in justify_functions {
+        conn.create_scalar_function(
+            name,
+            1,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            move |ctx| {
+                Ok(interval_arg(ctx, 0)?.map_or(Value::Null, |interval| interval_value(&justify(&interval))))
+            },
+        )?;
+    }
+    
+    // interval_add(timestamp, interval) - timestamp + interval with calendar months, in the
+    // representation of the timestamp: INTEGER microseconds stay INTEGER and timestamp text
+    // such as now() stays text. interval_add(interval, interval) adds two intervals.
+    conn.create_scalar_function(
+        "interval_add",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| add_interval(ctx, false),
+    )?;
+    
+    // interval_sub(timestamp or interval, interval)
+    conn.create_scalar_function(
+        "interval_sub",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| add_interval(ctx, true),
+    )?;
+    
+    // interval_mul(interval, factor) / interval_div(interval, divisor)
+    for (name, divide) in [("interval_mul", false), ("interval_div", true)] {
+        conn.create_scalar_function(
+            name,
+            2,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            move |ctx| {
+                let (Some(interval), Some(factor)) = (interval_arg(ctx, 0)?, number_arg(ctx, 1)?) else {
+                    return Ok(Value::Null);
+                };
+                let factor = if divide {
+                    if factor == 0.0 {
+                        return Err(Error::UserFunctionError("division by zero".into()));
+                    }
+                    1.0 / factor
+                } else {
+                    factor
+                };
+                interval.scale(factor)
+                    .map(|interval| interval_value(&interval))
+                    .ok_or_else(|| Error::UserFunctionError("interval out of range".into()))
+            },
+        )?;
+    }
+    
+    // interval_key(interval) - the length in microseconds intervals compare and sort by
+    conn.create_scalar_function(
+        "interval_key",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            Ok(interval_arg(ctx, 0)?.map(|interval| {
+                interval.span_micros().clamp(i64::MIN as i128, i64::MAX as i128) as i64
+            }))
+        },
+    )?;
+    
+    // sum, avg, min and max over intervals
+    conn.create_aggregate_function("interval_sum", 1, FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC, IntervalAggregate::Sum)?;
+    conn.create_aggregate_function("interval_avg", 1, FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC, IntervalAggregate::Avg)?;
+    conn.create_aggregate_function("interval_min", 1, FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC, IntervalAggregate::Min)?;
+    conn.create_aggregate_function("interval_max", 1, FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC, IntervalAggregate::Max)?;
+    
     // to_timestamp(double) - Convert seconds to microseconds
     conn.create_scalar_function(
         "to_timestamp",
@@ -245,10 +353,14 @@ pub fn register_datetime_functions(conn: &Connection) -> Result<()> {
         1,
         FunctionFlags::SQLITE_UTF8,
         |ctx| {
+            if let ValueRef::Null = ctx.get_raw(0) {
+                return Ok(None);
+            }
+            
             // Check if the parameter is already an integer (microseconds)
             if let Ok(micros) = ctx.get::<i64>(0) {
                 // Already converted to microseconds, just return it
-                return Ok(micros);
+                return Ok(Some(micros));
             }
             
             // Otherwise, try to get as text and parse
@@ -258,7 +370,7 @@ pub fn register_datetime_functions(conn: &Connection) -> Result<()> {
             // First try ISO 8601 format with fractional seconds
             if let Ok(dt) = DateTime::parse_from_rfc3339(&text) {
                 let micros = dt.timestamp() * 1_000_000 + (dt.timestamp_subsec_micros() as i64);
-                return Ok(micros);
+                return Ok(Some(micros));
             }
             
             // Handle PostgreSQL-style timezone offsets (+00, -05, etc.)
@@ -278,7 +390,7 @@ pub fn register_datetime_functions(conn: &Connection) -> Result<()> {
             if normalized_text != text
                 && let Ok(dt) = DateTime::parse_from_rfc3339(&normalized_text) {
                     let micros = dt.timestamp() * 1_000_000 + (dt.timestamp_subsec_micros() as i64);
-                    return Ok(micros);
+                    return Ok(Some(micros));
                 }
             
             // Try custom format for PostgreSQL timestamps with timezone
@@ -290,7 +402,7 @@ pub fn register_datetime_functions(conn: &Connection) -> Result<()> {
             for format in &formats_with_tz {
                 if let Ok(dt) = DateTime::parse_from_str(&text, format) {
                     let micros = dt.timestamp() * 1_000_000 + (dt.timestamp_subsec_micros() as i64);
-                    return Ok(micros);
+                    return Ok(Some(micros));
                 }
             }
             
@@ -298,27 +410,32 @@ pub fn register_datetime_functions(conn: &Connection) -> Result<()> {
             if let Ok(naive_dt) = chrono::NaiveDateTime::parse_from_str(&text, "%Y-%m-%dT%H:%M:%S%.f") {
                 let dt = DateTime::<Utc>::from_naive_utc_and_offset(naive_dt, Utc);
                 let micros = dt.timestamp() * 1_000_000 + (dt.timestamp_subsec_micros() as i64);
-                return Ok(micros);
+                return Ok(Some(micros));
             }
             
             // Try without fractional seconds
             if let Ok(naive_dt) = chrono::NaiveDateTime::parse_from_str(&text, "%Y-%m-%dT%H:%M:%S") {
                 let dt = DateTime::<Utc>::from_naive_utc_and_offset(naive_dt, Utc);
                 let micros = dt.timestamp() * 1_000_000 + (dt.timestamp_subsec_micros() as i64);
-                return Ok(micros);
+                return Ok(Some(micros));
             }
             
             // Try space separator
             if let Ok(naive_dt) = chrono::NaiveDateTime::parse_from_str(&text, "%Y-%m-%d %H:%M:%S%.f") {
                 let dt = DateTime::<Utc>::from_naive_utc_and_offset(naive_dt, Utc);
                 let micros = dt.timestamp() * 1_000_000 + (dt.timestamp_subsec_micros() as i64);
-                return Ok(micros);
+                return Ok(Some(micros));
             }
             
             if let Ok(naive_dt) = chrono::NaiveDateTime::parse_from_str(&text, "%Y-%m-%d %H:%M:%S") {
                 let dt = DateTime::<Utc>::from_naive_utc_and_offset(naive_dt, Utc);
                 let micros = dt.timestamp() * 1_000_000 + (dt.timestamp_subsec_micros() as i64);
-                return Ok(micros);
+                return Ok(Some(micros));
+            }
+            
+            // Minutes without seconds and the other forms the datetime utilities read
+            if let Some(micros) = datetime_utils::parse_timestamp_to_microseconds(&text) {
+                return Ok(Some(micros));
             }
             
             Err(Error::UserFunctionError(
@@ -398,6 +515,132 @@ pub fn register_datetime_functions(conn: &Connection) -> Result<()> {
 }
 
 /// Extract a date part from microseconds since epoch
+/// Timestamp argument: INTEGER microseconds since epoch, or timestamp/date text
+fn timestamp_arg(ctx: &Context<'_>, idx: usize) -> Result<Option<NaiveDateTime>> {
+    let micros = match ctx.get_raw(idx) {
+        ValueRef::Null => return Ok(None),
+        ValueRef::Integer(i) => Some(i),
+        ValueRef::Real(f) => Some(f as i64),
+        ValueRef::Text(text) => std::str::from_utf8(text).ok().and_then(|t| {
+            datetime_utils::parse_timestamp_to_microseconds(t)
+                .or_else(|| datetime_utils::parse_date_to_days(t).map(|days| days * 86_400_000_000))
+        }),
+        ValueRef::Blob(_) => None,
+    };
+    micros
+        .and_then(datetime_utils::microseconds_to_datetime)
+        .map(Some)
+        .ok_or_else(|| Error::UserFunctionError("invalid input syntax for type timestamp".into()))
+}
+
+/// Interval argument: INTEGER microseconds, or interval text
+fn interval_arg(ctx: &Context<'_>, idx: usize) -> Result<Option<Interval>> {
+    match ctx.get_raw(idx) {
+        ValueRef::Null => Ok(None),
+        ValueRef::Integer(i) => Ok(Some(Interval::from_micros(i))),
+        ValueRef::Real(f) => Ok(Some(Interval::from_micros(f as i64))),
+        ValueRef::Text(text) => std::str::from_utf8(text)
+            .map_err(|e| Error::UserFunctionError(e.to_string().into()))
+            .and_then(|t| Interval::parse(t).map_err(|e| Error::UserFunctionError(e.into())))
+            .map(Some),
+        ValueRef::Blob(_) => Err(Error::UserFunctionError("invalid input syntax for type interval".into())),
+    }
+}
+
+/// Number argument: INTEGER, REAL, or numeric text such as a NUMERIC value
+fn number_arg(ctx: &Context<'_>, idx: usize) -> Result<Option<f64>> {
+    match ctx.get_raw(idx) {
+        ValueRef::Null => Ok(None),
+        ValueRef::Integer(i) => Ok(Some(i as f64)),
+        ValueRef::Real(f) => Ok(Some(f)),
+        ValueRef::Text(text) => std::str::from_utf8(text).ok()
+            .and_then(|t| t.trim().parse::<f64>().ok())
+            .map(Some)
+            .ok_or_else(|| Error::UserFunctionError("invalid input syntax for type double precision".into())),
+        ValueRef::Blob(_) => Err(Error::UserFunctionError("invalid input syntax for type double precision".into())),
+    }
+}
+
+/// Interval result in its storage form, the interval text
+fn interval_value(interval: &Interval) -> Value {
+    Value::Text(interval.to_storage())
+}
+
+/// `timestamp ± interval` or `interval ± interval`, see interval_add()
+fn add_interval(ctx: &Context<'_>, subtract: bool) -> Result<Value> {
+    let Some(interval) = interval_arg(ctx, 1)? else {
+        return Ok(Value::Null);
+    };
+    let interval = if subtract { interval.negate() } else { interval };
+    let shift = |micros: i64| interval.add_to_timestamp(micros)
+        .ok_or_else(|| Error::UserFunctionError("timestamp out of range".into()));
+    match ctx.get_raw(0) {
+        ValueRef::Null => Ok(Value::Null),
+        ValueRef::Integer(micros) => shift(micros).map(Value::Integer),
+        ValueRef::Real(micros) => shift(micros as i64).map(Value::Integer),
+        ValueRef::Text(text) => {
+            let text = std::str::from_utf8(text).map_err(|e| Error::UserFunctionError(e.to_string().into()))?;
+            let timestamp = datetime_utils::parse_timestamp_to_microseconds(text)
+                .or_else(|| datetime_utils::parse_date_to_days(text).map(|days| days * 86_400_000_000));
+            match timestamp {
+                Some(micros) => shift(micros).map(|micros| Value::Text(datetime_utils::format_microseconds_to_timestamp(micros))),
+                None => Interval::parse(text)
+                    .map(|left| interval_value(&left.add(&interval)))
+                    .map_err(|_| Error::UserFunctionError(format!("invalid input syntax for type timestamp: \"{text}\"").into())),
+            }
+        }
+        ValueRef::Blob(_) => Err(Error::UserFunctionError("invalid input syntax for type timestamp".into())),
+    }
+}
+
+/// interval_sum / interval_avg / interval_min / interval_max; NULL when there are no
+/// non-NULL inputs
+enum IntervalAggregate {
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl Aggregate<(Option<Interval>, i64), Option<String>> for IntervalAggregate {
+    fn init(&self, _: &mut Context<'_>) -> Result<(Option<Interval>, i64)> {
+        Ok((None, 0))
+    }
+    
+    fn step(&self, ctx: &mut Context<'_>, (state, count): &mut (Option<Interval>, i64)) -> Result<()> {
+        let Some(interval) = interval_arg(ctx, 0)? else {
+            return Ok(());
+        };
+        *count += 1;
+        *state = Some(match (self, *state) {
+            (_, None) => interval,
+            (Self::Sum | Self::Avg, Some(total)) => total.add(&interval),
+            (Self::Min, Some(min)) if min.span_micros() <= interval.span_micros() => min,
+            (Self::Max, Some(max)) if max.span_micros() >= interval.span_micros() => max,
+            (Self::Min | Self::Max, Some(_)) => interval,
+        });
+        Ok(())
+    }
+    
+    fn finalize(&self, _: &mut Context<'_>, state: Option<(Option<Interval>, i64)>) -> Result<Option<String>> {
+        let Some((Some(interval), count)) = state else {
+            return Ok(None);
+        };
+        let result = match self {
+            Self::Avg => interval.scale(1.0 / count as f64)
+                .ok_or_else(|| Error::UserFunctionError("interval out of range".into()))?,
+            _ => interval,
+        };
+        Ok(Some(result.to_storage()))
+    }
+}
+
+fn extract_interval_part(field: &str, interval: &Interval) -> Result<f64> {
+    interval.extract(field).ok_or_else(|| Error::UserFunctionError(
+        format!("unit \"{field}\" not supported for type interval").into()
+    ))
+}
+
 fn extract_date_part(field: &str, timestamp: i64) -> Result<f64> {
     let secs = timestamp / 1_000_000;
     let micros = timestamp % 1_000_000;
@@ -562,6 +805,8 @@ mod tests {
             ("2025-01-15 12:00:00-05", 1736960400000000i64), // EST offset
             // RFC3339 format
             ("2025-01-15T12:00:00+00:00", 1736942400000000i64),
+            // Without seconds
+            ("2025-01-15 12:00", 1736942400000000i64),
         ];
         
         for (input, expected) in test_cases {
@@ -1,7 +1,9 @@
-use rusqlite::{Connection, Result, functions::{FunctionFlags, Context}};
+use rusqlite::{Connection, Result, functions::{Aggregate, FunctionFlags, Context, WindowAggregate}};
 use rust_decimal::Decimal;
+use std::cmp::Ordering;
 use std::str::FromStr;
 use std::panic::AssertUnwindSafe;
+use crate::types::DecimalHandler;
 
 /// Register all decimal-related functions with the SQLite connection
 pub fn register_decimal_functions(conn: &Connection) -> Result<()> {
@@ -71,9 +73,26 @@ pub fn register_decimal_functions(conn: &Connection) -> Result<()> {
         decimal_gt,
     )?;
     
-    // Note: Aggregate functions will be implemented using SQLite's built-in aggregates
-    // with proper wrapping of decimal values. For now, we provide the scalar functions
-    // that can be used with GROUP BY for basic aggregation.
+    conn.create_scalar_function(
+        "decimal_le",
+        2,
+        FunctionFlags::SQLITE_DETERMINISTIC | FunctionFlags::SQLITE_INNOCUOUS,
+        decimal_le,
+    )?;
+    
+    conn.create_scalar_function(
+        "decimal_ge",
+        2,
+        FunctionFlags::SQLITE_DETERMINISTIC | FunctionFlags::SQLITE_INNOCUOUS,
+        decimal_ge,
+    )?;
+    
+    conn.create_scalar_function(
+        "decimal_ne",
+        2,
+        FunctionFlags::SQLITE_DETERMINISTIC | FunctionFlags::SQLITE_INNOCUOUS,
+        decimal_ne,
+    )?;
     
     // Formatting function for NUMERIC type with precision and scale
     conn.create_scalar_function(
@@ -106,6 +125,25 @@ pub fn register_decimal_functions(conn: &Connection) -> Result<()> {
         decimal_abs,
     )?;
     
+    // Exact aggregates, so sums of NUMERIC columns don't go through floating point,
+    // also usable as window functions
+    conn.create_window_function(
+        "decimal_sum",
+        1,
+        FunctionFlags::SQLITE_DETERMINISTIC | FunctionFlags::SQLITE_INNOCUOUS,
+        DecimalSum,
+    )?;
+    
+    conn.create_window_function(
+        "decimal_avg",
+        1,
+        FunctionFlags::SQLITE_DETERMINISTIC | FunctionFlags::SQLITE_INNOCUOUS,
+        DecimalAvg,
+    )?;
+    
+    // Orders the text the decimal functions return by value
+    conn.create_collation("decimal", compare_decimal_text)?;
+    
     Ok(())
 }
 
@@ -159,9 +197,9 @@ fn decimal_from_text(ctx: &Context<'_>) -> Result<Option<Vec<u8>>> {
             Ok(Some(decimal.serialize().to_vec()))
         }
         rusqlite::types::ValueRef::Real(f) => {
-            match Decimal::try_from(f) {
+            match DecimalHandler::from_f64(f) {
                 Ok(decimal) => Ok(Some(decimal.serialize().to_vec())),
-                Err(e) => Err(rusqlite::Error::UserFunctionError(format!("Cannot convert float to decimal: {e}").into()))
+                Err(e) => Err(rusqlite::Error::UserFunctionError(e.into()))
             }
         }
         _ => Err(rusqlite::Error::UserFunctionError("Expected text, integer, or real value".into()))
@@ -205,7 +243,9 @@ fn get_decimal(ctx: &Context<'_>, idx: usize) -> Result<Option<Decimal>> {
         }
         rusqlite::types::ValueRef::Text(s) => {
             let text = std::str::from_utf8(s).map_err(|e| rusqlite::Error::UserFunctionError(Box::new(e)))?;
+            let text = text.trim();
             Decimal::from_str(text)
+                .or_else(|e| Decimal::from_scientific(text).map_err(|_| e))
                 .map(Some)
                 .map_err(|e| rusqlite::Error::UserFunctionError(Box::new(e)))
         }
@@ -213,18 +253,9 @@ fn get_decimal(ctx: &Context<'_>, idx: usize) -> Result<Option<Decimal>> {
             Ok(Some(Decimal::new(i, 0)))
         }
         rusqlite::types::ValueRef::Real(f) => {
-            // Check for special floating point values
-            if f.is_nan() {
-                return Err(rusqlite::Error::UserFunctionError("Cannot convert NaN to decimal".into()));
-            }
-            if f.is_infinite() {
-                return Err(rusqlite::Error::UserFunctionError("Cannot convert infinity to decimal".into()));
-            }
-            
-            // Try conversion and provide detailed error information
-            Decimal::try_from(f)
+            DecimalHandler::from_f64(f)
                 .map(Some)
-                .map_err(|e| rusqlite::Error::UserFunctionError(format!("Invalid function parameter type Real at index 0: Cannot convert float {f} to decimal: {e}").into()))
+                .map_err(|e| rusqlite::Error::UserFunctionError(format!("Invalid function parameter type Real at index {idx}: {e}").into()))
         }
     }
 }
@@ -299,6 +330,27 @@ fn decimal_gt(ctx: &Context<'_>) -> Result<bool> {
     }
 }
 
+fn decimal_le(ctx: &Context<'_>) -> Result<bool> {
+    match (get_decimal(ctx, 0)?, get_decimal(ctx, 1)?) {
+        (Some(a), Some(b)) => Ok(a <= b),
+        _ => Ok(false)
+    }
+}
+
+fn decimal_ge(ctx: &Context<'_>) -> Result<bool> {
+    match (get_decimal(ctx, 0)?, get_decimal(ctx, 1)?) {
+        (Some(a), Some(b)) => Ok(a >= b),
+        _ => Ok(false)
+    }
+}
+
+fn decimal_ne(ctx: &Context<'_>) -> Result<bool> {
+    match (get_decimal(ctx, 0)?, get_decimal(ctx, 1)?) {
+        (Some(a), Some(b)) => Ok(a != b),
+        _ => Ok(false)
+    }
+}
+
 // Formatting function for NUMERIC type
 fn numeric_format(ctx: &Context<'_>) -> Result<Option<String>> {
     // Get the value to format
@@ -328,6 +380,11 @@ fn numeric_format(ctx: &Context<'_>) -> Result<Option<String>> {
     let _precision = ctx.get::<i32>(1)?;
     let scale = ctx.get::<i32>(2)?;
     
+    Ok(Some(format_numeric(&value, scale)))
+}
+
+/// Format a numeric value to the given scale, rounding extra digits
+pub fn format_numeric(value: &str, scale: i32) -> String {
     // For very large numbers that exceed rust_decimal's capacity,
     // we'll do string-based formatting
     
@@ -335,18 +392,17 @@ fn numeric_format(ctx: &Context<'_>) -> Result<Option<String>> {
     if scale == 0 {
         // For scale 0, just remove any decimal part
         if let Some(dot_pos) = value.find('.') {
-            Ok(Some(value[..dot_pos].to_string()))
+            value[..dot_pos].to_string()
         } else {
-            Ok(Some(value))
+            value.to_string()
         }
     } else {
         // Try to use Decimal for normal-sized numbers
-        match Decimal::from_str(&value) {
+        match Decimal::from_str(value) {
             Ok(decimal) => {
                 // Round to the specified scale
                 let rounded = decimal.round_dp(scale as u32);
-                let formatted = format!("{:.prec$}", rounded, prec = scale as usize);
-                Ok(Some(formatted))
+                format!("{:.prec$}", rounded, prec = scale as usize)
             }
             Err(_) => {
                 // For very large numbers or invalid decimals, do string-based formatting
@@ -365,10 +421,10 @@ fn numeric_format(ctx: &Context<'_>) -> Result<Option<String>> {
                         padded
                     };
                     
-                    Ok(Some(format!("{integer_part}.{formatted_decimal}")))
+                    format!("{integer_part}.{formatted_decimal}")
                 } else {
                     // No decimal point, add one with zeros
-                    Ok(Some(format!("{}.{}", value, "0".repeat(scale as usize))))
+                    format!("{}.{}", value, "0".repeat(scale as usize))
                 }
             }
         }
@@ -436,6 +492,106 @@ fn decimal_abs(ctx: &Context<'_>) -> Result<Option<String>> {
     }
 }
 
+// Aggregate functions
+
+/// decimal_sum(numeric) - exact SUM; NULL when there are no non-NULL inputs
+#[derive(Default)]
+struct DecimalSum;
+
+impl Aggregate<(Decimal, i64), Option<String>> for DecimalSum {
+    fn init(&self, _: &mut Context<'_>) -> Result<(Decimal, i64)> {
+        Ok((Decimal::ZERO, 0))
+    }
+    
+    fn step(&self, ctx: &mut Context<'_>, state: &mut (Decimal, i64)) -> Result<()> {
+        accumulate(ctx, state, Decimal::checked_add, 1)
+    }
+    
+    fn finalize(&self, _: &mut Context<'_>, state: Option<(Decimal, i64)>) -> Result<Option<String>> {
+        Ok(state.and_then(total))
+    }
+}
+
+impl WindowAggregate<(Decimal, i64), Option<String>> for DecimalSum {
+    fn value(&self, state: Option<&mut (Decimal, i64)>) -> Result<Option<String>> {
+        Ok(state.copied().and_then(total))
+    }
+    
+    fn inverse(&self, ctx: &mut Context<'_>, state: &mut (Decimal, i64)) -> Result<()> {
+        accumulate(ctx, state, Decimal::checked_sub, -1)
+    }
+}
+
+/// decimal_avg(numeric) - exact AVG; NULL when there are no non-NULL inputs
+#[derive(Default)]
+struct DecimalAvg;
+
+impl Aggregate<(Decimal, i64), Option<String>> for DecimalAvg {
+    fn init(&self, _: &mut Context<'_>) -> Result<(Decimal, i64)> {
+        Ok((Decimal::ZERO, 0))
+    }
+    
+    fn step(&self, ctx: &mut Context<'_>, state: &mut (Decimal, i64)) -> Result<()> {
+        accumulate(ctx, state, Decimal::checked_add, 1)
+    }
+    
+    fn finalize(&self, _: &mut Context<'_>, state: Option<(Decimal, i64)>) -> Result<Option<String>> {
+        Ok(state.and_then(average))
+    }
+}
+
+impl WindowAggregate<(Decimal, i64), Option<String>> for DecimalAvg {
+    fn value(&self, state: Option<&mut (Decimal, i64)>) -> Result<Option<String>> {
+        Ok(state.copied().and_then(average))
+    }
+    
+    fn inverse(&self, ctx: &mut Context<'_>, state: &mut (Decimal, i64)) -> Result<()> {
+        accumulate(ctx, state, Decimal::checked_sub, -1)
+    }
+}
+
+/// Add a row's value to the sum and count of a decimal aggregate, or take it away
+/// again when it leaves a window frame; NULLs don't count
+fn accumulate(
+    ctx: &Context<'_>,
+    (sum, count): &mut (Decimal, i64),
+    apply: fn(Decimal, Decimal) -> Option<Decimal>,
+    step: i64,
+) -> Result<()> {
+    if let Some(value) = get_decimal(ctx, 0)? {
+        *sum = apply(*sum, value)
+            .ok_or_else(|| rusqlite::Error::UserFunctionError("numeric field overflow".into()))?;
+        *count += step;
+    }
+    Ok(())
+}
+
+fn total((sum, count): (Decimal, i64)) -> Option<String> {
+    (count > 0).then(|| sum.to_string())
+}
+
+fn average((sum, count): (Decimal, i64)) -> Option<String> {
+    (count > 0).then(|| (sum / Decimal::from(count)).to_string())
+}
+
+/// The decimal collation: numeric text in order of value, exactly as far as
+/// rust_decimal's precision goes, where a cast to REAL would round past 15 digits.
+/// Text that isn't a number sorts after numbers.
+fn compare_decimal_text(a: &str, b: &str) -> Ordering {
+    let exact = |text: &str| {
+        let text = text.trim();
+        Decimal::from_str(text).or_else(|_| Decimal::from_scientific(text)).ok()
+    };
+    if let (Some(a), Some(b)) = (exact(a), exact(b)) {
+        return a.cmp(&b);
+    }
+    match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        (Ok(x), Ok(y)) => x.total_cmp(&y),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -479,4 +635,82 @@ mod tests {
         Ok(())
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_decimal_comparisons() -> Result<()> {
+        let conn = Connection::open_in_memory()?;
+        register_decimal_functions(&conn)?;
+
+        // Text comparison would put '10.00' before '9.00'
+        let result: (bool, bool, bool, bool) = conn.query_row(
+            "SELECT decimal_ge('10.00', '9.00'), decimal_le('10.00', '9.00'), decimal_ne('9.0', '9.00'), decimal_ge('9.00', 9)",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        )?;
+        assert_eq!(result, (true, false, false, true));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decimal_aggregates_are_exact() -> Result<()> {
+        let conn = Connection::open_in_memory()?;
+        register_decimal_functions(&conn)?;
+        conn.execute("CREATE TABLE amounts (amount DECIMAL)", [])?;
+        for _ in 0..10 {
+            conn.execute("INSERT INTO amounts VALUES ('0.1')", [])?;
+        }
+
+        // The REAL sum would be 0.9999999999999999
+        let result: String = conn.query_row("SELECT decimal_sum(amount) FROM amounts", [], |row| row.get(0))?;
+        assert_eq!(result, "1.0");
+
+        let result: String = conn.query_row("SELECT decimal_avg(amount) FROM amounts", [], |row| row.get(0))?;
+        assert_eq!(result, "0.1");
+
+        let result: Option<String> = conn.query_row("SELECT decimal_sum(amount) FROM amounts WHERE 0", [], |row| row.get(0))?;
+        assert_eq!(result, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decimal_window_aggregates() -> Result<()> {
+        let conn = Connection::open_in_memory()?;
+        register_decimal_functions(&conn)?;
+
+        // Rows leaving the frame are taken away exactly, and an empty frame is NULL
+        let mut stmt = conn.prepare(
+            "SELECT decimal_sum(column2) OVER w, decimal_avg(column2) OVER w
+             FROM (VALUES (1, '0.1'), (2, '0.2'), (3, NULL), (4, NULL))
+             WINDOW w AS (ORDER BY column1 ROWS BETWEEN 1 PRECEDING AND CURRENT ROW)",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, Option<String>>(0)?, row.get::<_, Option<String>>(1)?)))?
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(rows, vec![
+            (Some("0.1".to_string()), Some("0.1".to_string())),
+            (Some("0.3".to_string()), Some("0.150".to_string())),
+            (Some("0.2".to_string()), Some("0.2".to_string())),
+            (None, None),
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decimal_collation() -> Result<()> {
+        let conn = Connection::open_in_memory()?;
+        register_decimal_functions(&conn)?;
+
+        // As REAL the first two are the same number, as text 10.00 sorts before 9.5
+        let sorted: String = conn.query_row(
+            "SELECT group_concat(v, ' ') FROM (SELECT column1 AS v FROM (VALUES
+                 ('12345678901234567.02'), ('12345678901234567.01'), ('10.00'), ('9.5'), ('-1'), ('1.0e+20'), ('n/a'))
+             ORDER BY v COLLATE decimal)",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(sorted, "-1 9.5 10.00 12345678901234567.01 12345678901234567.02 1.0e+20 n/a");
+
+        Ok(())
+    }
+}
@@ -1,5 +1,43 @@
 use rusqlite::{Connection, Result, functions::FunctionFlags, types::ValueRef};
 use serde_json::Value as JsonValue;
+use crate::functions::json_path::JsonPath;
+use crate::functions::range_functions;
+
+/// Parse an operand of @> / <@. Array columns share these operators with JSONB,
+/// so PostgreSQL array literals ('{a,b}') are accepted as well as JSON.
+fn parse_containment_operand(text: &str) -> Option<JsonValue> {
+    serde_json::from_str::<JsonValue>(text).ok()
+        .or_else(|| crate::types::ArrayHandler::parse_array_literal(text, None).ok())
+}
+
+/// Parse the key list of ?| / ?&, given as a JSON array, a PostgreSQL array
+/// literal or a plain comma-separated list.
+fn parse_key_list(text: &str) -> Vec<String> {
+    let trimmed = text.trim();
+    if (trimmed.starts_with('[') || trimmed.starts_with('{'))
+        && let Some(JsonValue::Array(items)) = parse_containment_operand(trimmed)
+    {
+        return items.into_iter()
+            .filter_map(|item| match item {
+                JsonValue::String(s) => Some(s),
+                JsonValue::Null => None,
+                other => Some(other.to_string()),
+            })
+            .collect();
+    }
+    trimmed.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect()
+}
+
+/// PostgreSQL ? semantics: a key of an object, a string element of an array,
+/// or the string itself for a scalar.
+fn json_has_key(json: &JsonValue, key: &str) -> bool {
+    match json {
+        JsonValue::Object(map) => map.contains_key(key),
+        JsonValue::Array(items) => items.iter().any(|item| item.as_str() == Some(key)),
+        JsonValue::String(s) => s == key,
+        _ => false,
+    }
+}
 
 /// Register JSON/JSONB-related functions in SQLite
 pub fn register_json_functions(conn: &Connection) -> Result<()> {
@@ -9,8 +47,9 @@ pub fn register_json_functions(conn: &Connection) -> Result<()> {
         1,
         FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
         |ctx| {
-            let value: String = ctx.get(0)?;
-            Ok(serde_json::from_str::<JsonValue>(&value).is_ok())
+            // NULL in, NULL out, like the built-in
+            let value: Option<String> = ctx.get(0)?;
+            Ok(value.map(|value| serde_json::from_str::<JsonValue>(&value).is_ok()))
         },
     )?;
     
@@ -60,23 +99,26 @@ pub fn register_json_functions(conn: &Connection) -> Result<()> {
         },
     )?;
     
-    // jsonb_object_keys(jsonb) - Get object keys (returns them as comma-separated for now)
-    conn.create_scalar_function(
-        "jsonb_object_keys",
-        1,
-        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
-        |ctx| {
-            let value: String = ctx.get(0)?;
-            match serde_json::from_str::<JsonValue>(&value) {
-                Ok(JsonValue::Object(obj)) => {
-                    let keys: Vec<String> = obj.keys().cloned().collect();
-                    Ok(Some(keys.join(",")))
+    // json_object_keys(json) / jsonb_object_keys(jsonb) - Get object keys (returns them as
+    // comma-separated outside FROM; in FROM they are expanded to rows by JsonEachTranslator)
+    for name in ["json_object_keys", "jsonb_object_keys"] {
+        conn.create_scalar_function(
+            name,
+            1,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| {
+                let value: String = ctx.get(0)?;
+                match serde_json::from_str::<JsonValue>(&value) {
+                    Ok(JsonValue::Object(obj)) => {
+                        let keys: Vec<String> = obj.keys().cloned().collect();
+                        Ok(Some(keys.join(",")))
+                    }
+                    Ok(_) => Ok(None),
+                    Err(_) => Ok(None),
                 }
-                Ok(_) => Ok(None),
-                Err(_) => Ok(None),
-            }
-        },
-    )?;
+            },
+        )?;
+    }
     
     // to_json(anyelement) - Convert to JSON
     conn.create_scalar_function(
@@ -128,29 +170,48 @@ pub fn register_json_functions(conn: &Connection) -> Result<()> {
         },
     )?;
     
-    // json_build_object(variadic) - Build JSON object from key-value pairs
-    // For simplicity, we'll implement a 2-argument version
-    conn.create_scalar_function(
-        "json_build_object",
-        2,
-        FunctionFlags::SQLITE_UTF8,
-        |ctx| {
-            let key: String = ctx.get(0)?;
-            let value: String = match ctx.get_raw(1) {
-                ValueRef::Null => "null".to_string(),
-                ValueRef::Integer(i) => i.to_string(),
-                ValueRef::Real(f) => f.to_string(),
-                ValueRef::Text(s) => serde_json::to_string(&s).unwrap(),
-                ValueRef::Blob(b) => {
-                    // Convert blob to hex string for JSON  
-                    let hex = b.iter().map(|byte| format!("{byte:02x}")).collect::<String>();
-                    serde_json::to_string(&hex).unwrap()
-                },
-            };
-            
-            Ok(format!("{{\"{key}\": {value}}}"))
-        },
-    )?;
+    // json_build_object(VARIADIC "any") / jsonb_build_object - Build a JSON object from alternating keys and values
+    for name in ["json_build_object", "jsonb_build_object"] {
+        conn.create_scalar_function(
+            name,
+            -1,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            move |ctx| {
+                if ctx.len() % 2 != 0 {
+                    return Err(rusqlite::Error::UserFunctionError(
+                        format!("argument list must have even number of elements (calling {name})").into(),
+                    ));
+                }
+                // Built by hand so keys keep their argument order
+                let mut members = Vec::with_capacity(ctx.len() / 2);
+                for i in (0..ctx.len()).step_by(2) {
+                    let key = match ctx.get_raw(i) {
+                        ValueRef::Null => {
+                            return Err(rusqlite::Error::UserFunctionError("null value not allowed for object key".into()));
+                        }
+                        ValueRef::Integer(n) => n.to_string(),
+                        ValueRef::Real(f) => f.to_string(),
+                        ValueRef::Text(t) | ValueRef::Blob(t) => String::from_utf8_lossy(t).into_owned(),
+                    };
+                    members.push(format!("{}:{}", JsonValue::String(key), sql_value_to_json(ctx.get_raw(i + 1))));
+                }
+                Ok(format!("{{{}}}", members.join(",")))
+            },
+        )?;
+    }
+    
+    // json_build_array(VARIADIC "any") / jsonb_build_array - Build a JSON array from the arguments
+    for name in ["json_build_array", "jsonb_build_array"] {
+        conn.create_scalar_function(
+            name,
+            -1,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| {
+                let items: Vec<JsonValue> = (0..ctx.len()).map(|i| sql_value_to_json(ctx.get_raw(i))).collect();
+                Ok(JsonValue::Array(items).to_string())
+            },
+        )?;
+    }
     
     // json_extract_scalar(json, path) - Extract scalar value from JSON path
     conn.create_scalar_function(
@@ -190,8 +251,14 @@ pub fn register_json_functions(conn: &Connection) -> Result<()> {
             let json1: String = ctx.get(0)?;
             let json2: String = ctx.get(1)?;
             
-            match (serde_json::from_str::<JsonValue>(&json1), serde_json::from_str::<JsonValue>(&json2)) {
-                (Ok(container), Ok(contained)) => Ok(json_contains(&container, &contained)),
+            // Range columns share @> with JSONB; canonical ranges like [1,11) are not JSON
+            if serde_json::from_str::<JsonValue>(&json1).is_err()
+                && let Some(result) = range_functions::range_contains(ctx.get_raw(0), ctx.get_raw(1)) {
+                return Ok(result);
+            }
+            
+            match (parse_containment_operand(&json1), parse_containment_operand(&json2)) {
+                (Some(container), Some(contained)) => Ok(json_contains(&container, &contained)),
                 _ => Ok(false),
             }
         },
@@ -206,8 +273,14 @@ pub fn register_json_functions(conn: &Connection) -> Result<()> {
             let json1: String = ctx.get(0)?;
             let json2: String = ctx.get(1)?;
             
-            match (serde_json::from_str::<JsonValue>(&json1), serde_json::from_str::<JsonValue>(&json2)) {
-                (Ok(contained), Ok(container)) => Ok(json_contains(&container, &contained)),
+            // Range columns share <@ with JSONB; canonical ranges like [1,11) are not JSON
+            if serde_json::from_str::<JsonValue>(&json2).is_err()
+                && let Some(result) = range_functions::range_contains(ctx.get_raw(1), ctx.get_raw(0)) {
+                return Ok(result);
+            }
+            
+            match (parse_containment_operand(&json1), parse_containment_operand(&json2)) {
+                (Some(contained), Some(container)) => Ok(json_contains(&container, &contained)),
                 _ => Ok(false),
             }
         },
@@ -310,29 +383,34 @@ pub fn register_json_functions(conn: &Connection) -> Result<()> {
         },
     )?;
     
-    // jsonb_set(jsonb, text[], jsonb, boolean) - Set value at path
-    // For simplicity, implement a 3-arg version without create_missing flag
-    conn.create_scalar_function(
-        "jsonb_set",
-        3,
-        FunctionFlags::SQLITE_UTF8,
-        |ctx| {
-            let json_str: String = ctx.get(0)?;
-            let path_str: String = ctx.get(1)?;
-            let new_value_str: String = ctx.get(2)?;
-            
-            match (serde_json::from_str::<JsonValue>(&json_str), 
-                   serde_json::from_str::<JsonValue>(&new_value_str)) {
-                (Ok(mut json), Ok(new_value)) => {
-                    // Parse path - expecting format like '{key1,key2}'
-                    let path = parse_json_path(&path_str);
-                    set_json_value(&mut json, &path, new_value);
-                    Ok(serde_json::to_string(&json).ok())
+    // jsonb_set(target, path, new_value [, create_missing]) - Replace the value at path,
+    // adding it when missing unless create_missing is false (defaults to true)
+    for arity in [3, 4] {
+        conn.create_scalar_function(
+            "jsonb_set",
+            arity,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| {
+                let (Some(json_str), Some(path_str), Some(new_value_str)) =
+                    (ctx.get::<Option<String>>(0)?, ctx.get::<Option<String>>(1)?, ctx.get::<Option<String>>(2)?)
+                else {
+                    return Ok(None);
+                };
+                let create_missing = ctx.len() < 4 || ctx.get::<Option<bool>>(3)?.unwrap_or(true);
+                
+                match (serde_json::from_str::<JsonValue>(&json_str), 
+                       serde_json::from_str::<JsonValue>(&new_value_str)) {
+                    (Ok(mut json), Ok(new_value)) => {
+                        // Parse path - expecting format like '{key1,key2}'
+                        let path = parse_json_path(&path_str);
+                        set_json_value(&mut json, &path, new_value, create_missing);
+                        Ok(serde_json::to_string(&json).ok())
+                    }
+                    _ => Ok(Some(json_str)),
                 }
-                _ => Ok(Some(json_str)),
-            }
-        },
-    )?;
+            },
+        )?;
+    }
     
     // json_extract_path(json, variadic text) - Extract value at path
     // For simplicity, implement a 2-arg version
@@ -591,11 +669,13 @@ pub fn register_json_functions(conn: &Connection) -> Result<()> {
                 ValueRef::Blob(_) => return Ok(false),
             };
             
-            let key: String = ctx.get(1)?;
+            let Some(key): Option<String> = ctx.get(1)? else {
+                return Ok(false);
+            };
             
             match serde_json::from_str::<JsonValue>(&json_str) {
-                Ok(JsonValue::Object(map)) => Ok(map.contains_key(&key)),
-                _ => Ok(false),
+                Ok(json) => Ok(json_has_key(&json, &key)),
+                Err(_) => Ok(false),
             }
         },
     )?;
@@ -614,14 +694,14 @@ pub fn register_json_functions(conn: &Connection) -> Result<()> {
                 ValueRef::Blob(_) => return Ok(false),
             };
             
-            let keys_str: String = ctx.get(1)?;
-            let keys: Vec<&str> = keys_str.split(',').map(|s| s.trim()).collect();
+            let Some(keys_str): Option<String> = ctx.get(1)? else {
+                return Ok(false);
+            };
+            let keys = parse_key_list(&keys_str);
             
             match serde_json::from_str::<JsonValue>(&json_str) {
-                Ok(JsonValue::Object(map)) => {
-                    Ok(keys.iter().any(|key| map.contains_key(*key)))
-                }
-                _ => Ok(false),
+                Ok(json) => Ok(keys.iter().any(|key| json_has_key(&json, key))),
+                Err(_) => Ok(false),
             }
         },
     )?;
@@ -640,14 +720,14 @@ pub fn register_json_functions(conn: &Connection) -> Result<()> {
                 ValueRef::Blob(_) => return Ok(false),
             };
             
-            let keys_str: String = ctx.get(1)?;
-            let keys: Vec<&str> = keys_str.split(',').map(|s| s.trim()).collect();
+            let Some(keys_str): Option<String> = ctx.get(1)? else {
+                return Ok(false);
+            };
+            let keys = parse_key_list(&keys_str);
             
             match serde_json::from_str::<JsonValue>(&json_str) {
-                Ok(JsonValue::Object(map)) => {
-                    Ok(keys.iter().all(|key| map.contains_key(*key)))
-                }
-                _ => Ok(false),
+                Ok(json) => Ok(keys.iter().all(|key| json_has_key(&json, key))),
+                Err(_) => Ok(false),
             }
         },
     )?;
@@ -865,6 +945,37 @@ pub fn register_json_functions(conn: &Connection) -> Result<()> {
         },
     )?;
     
+    // SQL/JSON path functions taking (target, path [, vars [, silent]]). jsonb_path_query
+    // itself is set-returning and is rewritten onto jsonb_path_query_array in FROM clauses.
+    for arity in 2..=4 {
+        conn.create_scalar_function(
+            "jsonb_path_exists",
+            arity,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| eval_json_path(ctx, |path, target, vars| path.exists(target, vars)),
+        )?;
+        conn.create_scalar_function(
+            "jsonb_path_match",
+            arity,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| Ok(eval_json_path(ctx, |path, target, vars| path.matches(target, vars))?.flatten()),
+        )?;
+        conn.create_scalar_function(
+            "jsonb_path_query_array",
+            arity,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| eval_json_path(ctx, |path, target, vars| Ok(JsonValue::Array(path.query(target, vars)?).to_string())),
+        )?;
+        conn.create_scalar_function(
+            "jsonb_path_query_first",
+            arity,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| Ok(eval_json_path(ctx, |path, target, vars| {
+                Ok(path.query(target, vars)?.first().map(|item| item.to_string()))
+            })?.flatten()),
+        )?;
+    }
+    
     // JSON aggregation functions
     register_json_agg(conn)?;
     register_jsonb_agg(conn)?;
@@ -1181,6 +1292,36 @@ fn register_row_to_json(conn: &Connection) -> Result<()> {
     Ok(())
 }
 
+/// Evaluate a jsonb_path_* call: NULL target or path gives NULL, and errors are raised
+/// unless the `silent` argument is true, in which case they also give NULL
+fn eval_json_path<T>(
+    ctx: &rusqlite::functions::Context,
+    eval: impl Fn(&JsonPath, &JsonValue, &JsonValue) -> std::result::Result<T, String>,
+) -> Result<Option<T>> {
+    let (Some(target), Some(path)) = (ctx.get::<Option<String>>(0)?, ctx.get::<Option<String>>(1)?) else {
+        return Ok(None);
+    };
+    let vars = if ctx.len() > 2 { ctx.get::<Option<String>>(2)? } else { None };
+    let silent = ctx.len() > 3 && ctx.get::<Option<bool>>(3)?.unwrap_or(false);
+    
+    let result = serde_json::from_str::<JsonValue>(&target)
+        .map_err(|e| format!("invalid input syntax for type json: {e}"))
+        .and_then(|target| {
+            let vars = match vars {
+                Some(vars) => serde_json::from_str::<JsonValue>(&vars)
+                    .map_err(|e| format!("invalid input syntax for type json: {e}"))?,
+                None => JsonValue::Null,
+            };
+            eval(&JsonPath::parse(&path)?, &target, &vars)
+        });
+    
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(_) if silent => Ok(None),
+        Err(e) => Err(rusqlite::Error::UserFunctionError(e.into())),
+    }
+}
+
 /// Convert a SQLite value to JSON format
 fn convert_value_to_json(value: rusqlite::types::ValueRef, pretty: bool) -> Result<Option<String>> {
     use rusqlite::types::ValueRef;
@@ -1228,8 +1369,10 @@ fn parse_json_path(path_str: &str) -> Vec<String> {
     }
 }
 
-/// Set value at path in JSON
-fn set_json_value(json: &mut JsonValue, path: &[String], new_value: JsonValue) {
+/// Set value at path in JSON with PostgreSQL jsonb_set semantics: every step before the
+/// last must exist, negative array indexes count from the end, and a missing last step is
+/// added (appended/prepended for out-of-range array indexes) only when `create_missing`
+fn set_json_value(json: &mut JsonValue, path: &[String], new_value: JsonValue, create_missing: bool) {
     if path.is_empty() {
         *json = new_value;
         return;
@@ -1241,40 +1384,71 @@ fn set_json_value(json: &mut JsonValue, path: &[String], new_value: JsonValue) {
     
     let mut current = json;
     for key in parent_path {
-        match current {
-            JsonValue::Object(map) => {
-                current = map.entry(key.clone()).or_insert(JsonValue::Object(serde_json::Map::new()));
-            }
-            JsonValue::Array(arr) => {
-                if let Ok(index) = key.parse::<usize>() {
-                    if index < arr.len() {
-                        current = &mut arr[index];
-                    } else {
-                        return;
-                    }
-                } else {
-                    return;
-                }
-            }
+        current = match current {
+            JsonValue::Object(map) => match map.get_mut(key) {
+                Some(next) => next,
+                None => return,
+            },
+            JsonValue::Array(arr) => match array_position(arr.len(), key) {
+                Some(index) if index < arr.len() => &mut arr[index],
+                _ => return,
+            },
             _ => return,
-        }
+        };
     }
     
     // Set the value at the last key
     match current {
-        JsonValue::Object(map) => {
+        JsonValue::Object(map) if create_missing || map.contains_key(last_key) => {
             map.insert(last_key.clone(), new_value);
         }
         JsonValue::Array(arr) => {
-            if let Ok(index) = last_key.parse::<usize>()
-                && index < arr.len() {
-                    arr[index] = new_value;
-                }
+            let Ok(index) = last_key.parse::<i64>() else {
+                return;
+            };
+            match array_position(arr.len(), last_key) {
+                Some(position) if position < arr.len() => arr[position] = new_value,
+                _ if !create_missing => {}
+                _ if index < 0 => arr.insert(0, new_value),
+                _ => arr.push(new_value),
+            }
         }
         _ => {},
     }
 }
 
+/// Resolve a path step against an array of `len` elements; negative indexes count from the end
+fn array_position(len: usize, key: &str) -> Option<usize> {
+    let index = key.parse::<i64>().ok()?;
+    if index >= 0 {
+        usize::try_from(index).ok()
+    } else {
+        len.checked_sub(usize::try_from(index.unsigned_abs()).ok()?)
+    }
+}
+
+/// Convert a SQL argument to a JSON value for the json_build_* functions. Text holding a
+/// JSON object or array (how json/jsonb values are stored) is nested as JSON; other text
+/// becomes a JSON string.
+fn sql_value_to_json(value: ValueRef) -> JsonValue {
+    match value {
+        ValueRef::Null => JsonValue::Null,
+        ValueRef::Integer(i) => JsonValue::from(i),
+        ValueRef::Real(f) => serde_json::Number::from_f64(f).map_or(JsonValue::Null, JsonValue::Number),
+        ValueRef::Text(t) => {
+            let text = String::from_utf8_lossy(t);
+            let trimmed = text.trim_start();
+            if (trimmed.starts_with('{') || trimmed.starts_with('['))
+                && let Ok(json) = serde_json::from_str::<JsonValue>(&text)
+            {
+                return json;
+            }
+            JsonValue::String(text.into_owned())
+        }
+        ValueRef::Blob(b) => JsonValue::String(format!("\\x{}", hex::encode(b))),
+    }
+}
+
 /// Delete value at path in JSON
 /// For objects: removes the key-value pair
 /// For arrays: removes the element at specified index
@@ -1525,7 +1699,7 @@ fn strip_nulls(json: &JsonValue) -> JsonValue {
 }
 
 /// Check if container JSON contains the contained JSON
-fn json_contains(container: &JsonValue, contained: &JsonValue) -> bool {
+pub(crate) fn json_contains(container: &JsonValue, contained: &JsonValue) -> bool {
     match (container, contained) {
         (JsonValue::Object(cont_map), JsonValue::Object(item_map)) => {
             // All keys in item must exist in container with same values
@@ -1650,6 +1824,9 @@ mod tests {
         let invalid: bool = conn.query_row("SELECT json_valid(?)", ["{invalid}"], |row| row.get(0)).unwrap();
         assert!(!invalid);
         
+        let null: Option<bool> = conn.query_row("SELECT json_valid(NULL)", [], |row| row.get(0)).unwrap();
+        assert_eq!(null, None);
+        
         // Test json_typeof
         let typ: Option<String> = conn.query_row("SELECT json_typeof(?)", ["[1,2,3]"], |row| row.get(0)).unwrap();
         assert_eq!(typ, Some("array".to_string()));
@@ -2394,4 +2571,66 @@ mod tests {
         let result_str = result.unwrap();
         assert!(result_str.contains("invalid JSON"));
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_jsonb_set_function() {
+        let conn = Connection::open_in_memory().unwrap();
+        register_json_functions(&conn).unwrap();
+        let query = |sql: &str| -> Option<String> { conn.query_row(sql, [], |row| row.get(0)).unwrap() };
+        
+        assert_eq!(query(r#"SELECT jsonb_set('{"a": {"b": 1}}', '{a,b}', '2')"#), Some(r#"{"a":{"b":2}}"#.to_string()));
+        assert_eq!(query(r#"SELECT jsonb_set('{"a": 1}', '{c}', '"new"')"#), Some(r#"{"a":1,"c":"new"}"#.to_string()));
+        assert_eq!(query(r#"SELECT jsonb_set('{"a": 1}', '{c}', '"new"', false)"#), Some(r#"{"a":1}"#.to_string()));
+        // Intermediate path steps must exist
+        assert_eq!(query(r#"SELECT jsonb_set('{"a": 1}', '{x,y}', '1')"#), Some(r#"{"a":1}"#.to_string()));
+        // Negative indexes count from the end; out-of-range indexes append or prepend
+        assert_eq!(query("SELECT jsonb_set('[1, 2, 3]', '{-1}', '9')"), Some("[1,2,9]".to_string()));
+        assert_eq!(query("SELECT jsonb_set('[1, 2, 3]', '{10}', '9')"), Some("[1,2,3,9]".to_string()));
+        assert_eq!(query("SELECT jsonb_set('[1, 2, 3]', '{-10}', '9')"), Some("[9,1,2,3]".to_string()));
+        assert_eq!(query("SELECT jsonb_set(NULL, '{a}', '1')"), None);
+    }
+    
+    #[test]
+    fn test_json_build_functions() {
+        let conn = Connection::open_in_memory().unwrap();
+        register_json_functions(&conn).unwrap();
+        let query = |sql: &str| -> String { conn.query_row(sql, [], |row| row.get(0)).unwrap() };
+        
+        assert_eq!(
+            query(r#"SELECT jsonb_build_object('id', 1, 'name', 'Ann', 'score', 9.5, 'tags', '["x"]', 'gone', NULL)"#),
+            r#"{"id":1,"name":"Ann","score":9.5,"tags":["x"],"gone":null}"#
+        );
+        assert_eq!(query("SELECT json_build_object()"), "{}");
+        assert_eq!(query(r#"SELECT json_build_array(1, 'two', NULL, '{"three": 3}')"#), r#"[1,"two",null,{"three":3}]"#);
+        assert!(conn.query_row("SELECT jsonb_build_object('a')", [], |row| row.get::<_, String>(0)).is_err());
+    }
+    
+    #[test]
+    fn test_jsonb_path_functions() {
+        let conn = Connection::open_in_memory().unwrap();
+        register_json_functions(&conn).unwrap();
+        let doc = r#"{"items": [{"name": "pen", "qty": 3}, {"name": "ink", "qty": 0}, {"name": "pad", "qty": 12}]}"#;
+        
+        let names: String = conn.query_row(
+            "SELECT jsonb_path_query_array(?, '$.items[*] ? (@.qty > $min).name', '{\"min\": 1}')",
+            [doc],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(names, r#"["pen","pad"]"#);
+        
+        let first: Option<String> = conn.query_row("SELECT jsonb_path_query_first(?, '$.items[*].qty')", [doc], |row| row.get(0)).unwrap();
+        assert_eq!(first, Some("3".to_string()));
+        let first: Option<String> = conn.query_row("SELECT jsonb_path_query_first(?, '$.missing')", [doc], |row| row.get(0)).unwrap();
+        assert_eq!(first, None);
+        
+        let exists: bool = conn.query_row("SELECT jsonb_path_exists(?, '$.items[*] ? (@.qty == 0)')", [doc], |row| row.get(0)).unwrap();
+        assert!(exists);
+        let matched: bool = conn.query_row("SELECT jsonb_path_match(?, 'exists($.items[*] ? (@.name == \"pad\"))')", [doc], |row| row.get(0)).unwrap();
+        assert!(matched);
+        
+        // Strict-mode errors are raised unless silent is set
+        assert!(conn.query_row("SELECT jsonb_path_exists(?, 'strict $.nope')", [doc], |row| row.get::<_, Option<bool>>(0)).is_err());
+        let silent: Option<bool> = conn.query_row("SELECT jsonb_path_exists(?, 'strict $.nope', '{}', true)", [doc], |row| row.get(0)).unwrap();
+        assert_eq!(silent, None);
+    }
+}
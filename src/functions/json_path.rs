@@ -0,0 +1,973 @@
+//! SQL/JSON path language evaluation for the jsonb_path_* functions
+//!
+//! Supports the commonly used subset of PostgreSQL's jsonpath: `lax`/`strict` modes,
+//! `$`, `@` and `$var` references, member (`.key`, `."key"`, `.*`) and element
+//! (`[n]`, `[n to m]`, `[last]`, `[*]`) accessors, filters (`? (...)`) with comparisons,
+//! `&&`, `||`, `!`, `exists`, `like_regex`, `starts with` and `is unknown`, arithmetic,
+//! and the item methods `size()`, `type()`, `double()`, `floor()`, `ceiling()`, `abs()`,
+//! `string()` and `keyvalue()`.
+
+use serde_json::{Map, Number, Value as JsonValue};
+
+/// A parsed SQL/JSON path expression
+#[derive(Debug, Clone)]
+pub struct JsonPath {
+    strict: bool,
+    expr: PathExpr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Dollar,
+    At,
+    Variable(String),
+    Ident(String),
+    Number(String),
+    Str(String),
+    Punct(&'static str),
+}
+
+#[derive(Debug, Clone)]
+enum PathExpr {
+    Root,
+    Current,
+    Last,
+    Variable(String),
+    Literal(JsonValue),
+    Accessor(Box<PathExpr>, Accessor),
+    Arith(Box<PathExpr>, ArithOp, Box<PathExpr>),
+    Neg(Box<PathExpr>),
+    Compare(Box<PathExpr>, CompareOp, Box<PathExpr>),
+    And(Box<PathExpr>, Box<PathExpr>),
+    Or(Box<PathExpr>, Box<PathExpr>),
+    Not(Box<PathExpr>),
+    IsUnknown(Box<PathExpr>),
+    Exists(Box<PathExpr>),
+    LikeRegex(Box<PathExpr>, regex::Regex),
+    StartsWith(Box<PathExpr>, Box<PathExpr>),
+}
+
+#[derive(Debug, Clone)]
+enum Accessor {
+    Member(String),
+    WildcardMember,
+    WildcardElement,
+    Elements(Vec<(PathExpr, Option<PathExpr>)>),
+    Filter(Box<PathExpr>),
+    Method(Method),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Method {
+    Size,
+    Type,
+    Double,
+    Floor,
+    Ceiling,
+    Abs,
+    String,
+    KeyValue,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ArithOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// Evaluation context shared by the whole expression
+struct Context<'a> {
+    root: &'a JsonValue,
+    vars: &'a JsonValue,
+    strict: bool,
+    /// Index of the last element of the array being subscripted
+    last: Option<i64>,
+}
+
+impl JsonPath {
+    /// Parse a jsonpath expression such as `lax $.items[*] ? (@.price > 10).name`
+    pub fn parse(path: &str) -> Result<Self, String> {
+        let mut parser = PathParser { tokens: tokenize(path)?, pos: 0 };
+
+        let mut strict = false;
+        if let Some(Token::Ident(mode)) = parser.peek()
+            && (mode == "strict" || mode == "lax")
+            && parser.tokens.len() > 1
+        {
+            strict = mode == "strict";
+            parser.pos += 1;
+        }
+
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            return Err(format!("syntax error at or near {token:?} of jsonpath input"));
+        }
+        Ok(JsonPath { strict, expr })
+    }
+
+    /// All items the path selects from `target`; `vars` must be an object holding `$var` values
+    pub fn query(&self, target: &JsonValue, vars: &JsonValue) -> Result<Vec<JsonValue>, String> {
+        if !vars.is_object() && !vars.is_null() {
+            return Err("\"vars\" argument is not an object".to_string());
+        }
+        let ctx = Context { root: target, vars, strict: self.strict, last: None };
+        eval(&self.expr, target, &ctx)
+    }
+
+    /// Whether the path selects any item
+    pub fn exists(&self, target: &JsonValue, vars: &JsonValue) -> Result<bool, String> {
+        Ok(!self.query(target, vars)?.is_empty())
+    }
+
+    /// The result of a predicate path; None when the predicate is unknown
+    pub fn matches(&self, target: &JsonValue, vars: &JsonValue) -> Result<Option<bool>, String> {
+        match self.query(target, vars)?.as_slice() {
+            [JsonValue::Bool(b)] => Ok(Some(*b)),
+            [JsonValue::Null] => Ok(None),
+            _ => Err("single boolean result is expected".to_string()),
+        }
+    }
+}
+
+fn tokenize(path: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = path.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if c == '"' {
+            let mut s = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err("unterminated quoted string in jsonpath input".to_string()),
+                    Some('"') => break,
+                    Some('\\') => {
+                        i += 1;
+                        match chars.get(i) {
+                            Some('n') => s.push('\n'),
+                            Some('t') => s.push('\t'),
+                            Some('r') => s.push('\r'),
+                            Some(other) => s.push(*other),
+                            None => return Err("unterminated quoted string in jsonpath input".to_string()),
+                        }
+                    }
+                    Some(ch) => s.push(*ch),
+                }
+                i += 1;
+            }
+            i += 1;
+            tokens.push(Token::Str(s));
+            continue;
+        }
+
+        if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            if chars.get(i) == Some(&'.') && chars.get(i + 1).is_some_and(|ch| ch.is_ascii_digit()) {
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            if matches!(chars.get(i), Some('e' | 'E')) {
+                let mut j = i + 1;
+                if matches!(chars.get(j), Some('+' | '-')) {
+                    j += 1;
+                }
+                if chars.get(j).is_some_and(|ch| ch.is_ascii_digit()) {
+                    i = j;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            tokens.push(Token::Number(chars[start..i].iter().collect()));
+            continue;
+        }
+
+        let is_ident_char = |ch: char| ch.is_alphanumeric() || ch == '_';
+        if c == '$' {
+            i += 1;
+            let start = i;
+            while i < chars.len() && is_ident_char(chars[i]) {
+                i += 1;
+            }
+            tokens.push(if i > start { Token::Variable(chars[start..i].iter().collect()) } else { Token::Dollar });
+            continue;
+        }
+        if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && is_ident_char(chars[i]) {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            continue;
+        }
+
+        let next = chars.get(i + 1).copied();
+        let two_char = match (c, next) {
+            ('=', Some('=')) => Some("=="),
+            ('!', Some('=')) | ('<', Some('>')) => Some("!="),
+            ('<', Some('=')) => Some("<="),
+            ('>', Some('=')) => Some(">="),
+            ('&', Some('&')) => Some("&&"),
+            ('|', Some('|')) => Some("||"),
+            _ => None,
+        };
+        if let Some(op) = two_char {
+            tokens.push(Token::Punct(op));
+            i += 2;
+            continue;
+        }
+
+        let punct = match c {
+            '@' => {
+                tokens.push(Token::At);
+                i += 1;
+                continue;
+            }
+            '.' => ".",
+            '[' => "[",
+            ']' => "]",
+            '(' => "(",
+            ')' => ")",
+            ',' => ",",
+            '*' => "*",
+            '?' => "?",
+            '<' => "<",
+            '>' => ">",
+            '!' => "!",
+            '+' => "+",
+            '-' => "-",
+            '/' => "/",
+            '%' => "%",
+            // A single = is accepted as equality, as in many path dialects
+            '=' => "==",
+            other => return Err(format!("unexpected character \"{other}\" in jsonpath input")),
+        };
+        tokens.push(Token::Punct(punct));
+        i += 1;
+    }
+
+    Ok(tokens)
+}
+
+struct PathParser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl PathParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + offset)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat_punct(&mut self, punct: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Punct(p)) if *p == punct) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Ident(word)) if word.eq_ignore_ascii_case(keyword)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_punct(&mut self, punct: &str) -> Result<(), String> {
+        if self.eat_punct(punct) {
+            Ok(())
+        } else {
+            Err(format!("syntax error in jsonpath input: expected \"{punct}\""))
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<PathExpr, String> {
+        let mut left = self.parse_and()?;
+        while self.eat_punct("||") {
+            left = PathExpr::Or(Box::new(left), Box::new(self.parse_and()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<PathExpr, String> {
+        let mut left = self.parse_not()?;
+        while self.eat_punct("&&") {
+            left = PathExpr::And(Box::new(left), Box::new(self.parse_not()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Result<PathExpr, String> {
+        if self.eat_punct("!") {
+            self.expect_punct("(")?;
+            let inner = self.parse_or()?;
+            self.expect_punct(")")?;
+            return Ok(PathExpr::Not(Box::new(inner)));
+        }
+        self.parse_predicate()
+    }
+
+    fn parse_predicate(&mut self) -> Result<PathExpr, String> {
+        if matches!(self.peek(), Some(Token::Ident(word)) if word == "exists")
+            && matches!(self.peek_at(1), Some(Token::Punct("(")))
+        {
+            self.pos += 2;
+            let inner = self.parse_or()?;
+            self.expect_punct(")")?;
+            return Ok(PathExpr::Exists(Box::new(inner)));
+        }
+
+        let left = self.parse_additive()?;
+
+        let op = match self.peek() {
+            Some(Token::Punct("==")) => Some(CompareOp::Eq),
+            Some(Token::Punct("!=")) => Some(CompareOp::Ne),
+            Some(Token::Punct("<")) => Some(CompareOp::Lt),
+            Some(Token::Punct("<=")) => Some(CompareOp::Le),
+            Some(Token::Punct(">")) => Some(CompareOp::Gt),
+            Some(Token::Punct(">=")) => Some(CompareOp::Ge),
+            _ => None,
+        };
+        if let Some(op) = op {
+            self.pos += 1;
+            let right = self.parse_additive()?;
+            return Ok(PathExpr::Compare(Box::new(left), op, Box::new(right)));
+        }
+
+        if self.eat_keyword("like_regex") {
+            let Some(Token::Str(pattern)) = self.next() else {
+                return Err("syntax error in jsonpath input: like_regex expects a string".to_string());
+            };
+            let mut flags = String::new();
+            if self.eat_keyword("flag") {
+                let Some(Token::Str(f)) = self.next() else {
+                    return Err("syntax error in jsonpath input: flag expects a string".to_string());
+                };
+                flags = f;
+            }
+            let mut builder = regex::RegexBuilder::new(&pattern);
+            for flag in flags.chars() {
+                match flag {
+                    'i' => { builder.case_insensitive(true); }
+                    's' => { builder.dot_matches_new_line(true); }
+                    'm' => { builder.multi_line(true); }
+                    'x' => { builder.ignore_whitespace(true); }
+                    'q' => { builder = regex::RegexBuilder::new(&regex::escape(&pattern)); }
+                    other => return Err(format!("invalid flag \"{other}\" in like_regex predicate")),
+                }
+            }
+            let regex = builder.build().map_err(|e| format!("invalid regular expression: {e}"))?;
+            return Ok(PathExpr::LikeRegex(Box::new(left), regex));
+        }
+
+        if matches!(self.peek(), Some(Token::Ident(word)) if word == "starts")
+            && matches!(self.peek_at(1), Some(Token::Ident(word)) if word == "with")
+        {
+            self.pos += 2;
+            let prefix = self.parse_additive()?;
+            return Ok(PathExpr::StartsWith(Box::new(left), Box::new(prefix)));
+        }
+
+        if matches!(self.peek(), Some(Token::Ident(word)) if word == "is")
+            && matches!(self.peek_at(1), Some(Token::Ident(word)) if word == "unknown")
+        {
+            self.pos += 2;
+            return Ok(PathExpr::IsUnknown(Box::new(left)));
+        }
+
+        Ok(left)
+    }
+
+    fn parse_additive(&mut self) -> Result<PathExpr, String> {
+        let mut left = self.parse_multiplicative()?;
+        loop {
+            let op = if self.eat_punct("+") {
+                ArithOp::Add
+            } else if self.eat_punct("-") {
+                ArithOp::Sub
+            } else {
+                return Ok(left);
+            };
+            left = PathExpr::Arith(Box::new(left), op, Box::new(self.parse_multiplicative()?));
+        }
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<PathExpr, String> {
+        let mut left = self.parse_unary()?;
+        loop {
+            let op = if self.eat_punct("*") {
+                ArithOp::Mul
+            } else if self.eat_punct("/") {
+                ArithOp::Div
+            } else if self.eat_punct("%") {
+                ArithOp::Mod
+            } else {
+                return Ok(left);
+            };
+            left = PathExpr::Arith(Box::new(left), op, Box::new(self.parse_unary()?));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<PathExpr, String> {
+        if self.eat_punct("-") {
+            return Ok(PathExpr::Neg(Box::new(self.parse_unary()?)));
+        }
+        if self.eat_punct("+") {
+            return self.parse_unary();
+        }
+        self.parse_accessors()
+    }
+
+    fn parse_accessors(&mut self) -> Result<PathExpr, String> {
+        let mut expr = self.parse_primary()?;
+        loop {
+            if self.eat_punct(".") {
+                let accessor = match self.next() {
+                    Some(Token::Punct("*")) => Accessor::WildcardMember,
+                    Some(Token::Ident(name)) if matches!(self.peek(), Some(Token::Punct("("))) => {
+                        self.pos += 1;
+                        self.expect_punct(")")?;
+                        Accessor::Method(match name.as_str() {
+                            "size" => Method::Size,
+                            "type" => Method::Type,
+                            "double" => Method::Double,
+                            "floor" => Method::Floor,
+                            "ceiling" => Method::Ceiling,
+                            "abs" => Method::Abs,
+                            "string" => Method::String,
+                            "keyvalue" => Method::KeyValue,
+                            other => return Err(format!("unsupported jsonpath item method {other}()")),
+                        })
+                    }
+                    Some(Token::Ident(name)) | Some(Token::Str(name)) | Some(Token::Variable(name)) => Accessor::Member(name),
+                    _ => return Err("syntax error in jsonpath input: expected member name after \".\"".to_string()),
+                };
+                expr = PathExpr::Accessor(Box::new(expr), accessor);
+            } else if self.eat_punct("[") {
+                if self.eat_punct("*") {
+                    self.expect_punct("]")?;
+                    expr = PathExpr::Accessor(Box::new(expr), Accessor::WildcardElement);
+                    continue;
+                }
+                let mut subscripts = Vec::new();
+                loop {
+                    let from = self.parse_additive()?;
+                    let to = if self.eat_keyword("to") { Some(self.parse_additive()?) } else { None };
+                    subscripts.push((from, to));
+                    if !self.eat_punct(",") {
+                        break;
+                    }
+                }
+                self.expect_punct("]")?;
+                expr = PathExpr::Accessor(Box::new(expr), Accessor::Elements(subscripts));
+            } else if self.eat_punct("?") {
+                self.expect_punct("(")?;
+                let filter = self.parse_or()?;
+                self.expect_punct(")")?;
+                expr = PathExpr::Accessor(Box::new(expr), Accessor::Filter(Box::new(filter)));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<PathExpr, String> {
+        match self.next() {
+            Some(Token::Dollar) => Ok(PathExpr::Root),
+            Some(Token::At) => Ok(PathExpr::Current),
+            Some(Token::Variable(name)) => Ok(PathExpr::Variable(name)),
+            Some(Token::Str(s)) => Ok(PathExpr::Literal(JsonValue::String(s))),
+            Some(Token::Number(n)) => {
+                let value = serde_json::from_str::<JsonValue>(&n)
+                    .map_err(|_| format!("invalid numeric literal \"{n}\" in jsonpath input"))?;
+                Ok(PathExpr::Literal(value))
+            }
+            Some(Token::Ident(word)) => match word.as_str() {
+                "true" => Ok(PathExpr::Literal(JsonValue::Bool(true))),
+                "false" => Ok(PathExpr::Literal(JsonValue::Bool(false))),
+                "null" => Ok(PathExpr::Literal(JsonValue::Null)),
+                "last" => Ok(PathExpr::Last),
+                other => Err(format!("syntax error at or near \"{other}\" of jsonpath input")),
+            },
+            Some(Token::Punct("(")) => {
+                let inner = self.parse_or()?;
+                self.expect_punct(")")?;
+                Ok(inner)
+            }
+            Some(token) => Err(format!("syntax error at or near {token:?} of jsonpath input")),
+            None => Err("syntax error at end of jsonpath input".to_string()),
+        }
+    }
+}
+
+fn is_predicate(expr: &PathExpr) -> bool {
+    matches!(expr, PathExpr::Compare(..) | PathExpr::And(..) | PathExpr::Or(..) | PathExpr::Not(..)
+        | PathExpr::IsUnknown(..) | PathExpr::Exists(..) | PathExpr::LikeRegex(..) | PathExpr::StartsWith(..))
+}
+
+fn eval(expr: &PathExpr, current: &JsonValue, ctx: &Context) -> Result<Vec<JsonValue>, String> {
+    if is_predicate(expr) {
+        // A predicate used as a path yields true, false or null (unknown)
+        return Ok(vec![predicate(expr, current, ctx).map_or(JsonValue::Null, JsonValue::Bool)]);
+    }
+
+    match expr {
+        PathExpr::Root => Ok(vec![ctx.root.clone()]),
+        PathExpr::Current => Ok(vec![current.clone()]),
+        PathExpr::Last => ctx.last
+            .map(|last| vec![JsonValue::from(last)])
+            .ok_or_else(|| "LAST is allowed only in array subscripts".to_string()),
+        PathExpr::Variable(name) => ctx.vars.get(name)
+            .map(|value| vec![value.clone()])
+            .ok_or_else(|| format!("could not find jsonpath variable \"{name}\"")),
+        PathExpr::Literal(value) => Ok(vec![value.clone()]),
+        PathExpr::Accessor(base, accessor) => {
+            let mut out = Vec::new();
+            for item in eval(base, current, ctx)? {
+                apply_accessor(accessor, &item, ctx, &mut out)?;
+            }
+            Ok(out)
+        }
+        PathExpr::Arith(left, op, right) => {
+            let left = single_number(eval(left, current, ctx)?, ctx, "left")?;
+            let right = single_number(eval(right, current, ctx)?, ctx, "right")?;
+            arithmetic(&left, *op, &right).map(|value| vec![value])
+        }
+        PathExpr::Neg(inner) => {
+            let mut out = Vec::new();
+            for item in unwrap_arrays(eval(inner, current, ctx)?, ctx) {
+                let JsonValue::Number(n) = &item else {
+                    return Err("operand of unary jsonpath operator - is not a numeric value".to_string());
+                };
+                out.push(match n.as_i64() {
+                    Some(i) => JsonValue::from(-i),
+                    None => number(-n.as_f64().unwrap_or_default())?,
+                });
+            }
+            Ok(out)
+        }
+        _ => unreachable!("predicates are handled above"),
+    }
+}
+
+fn apply_accessor(accessor: &Accessor, item: &JsonValue, ctx: &Context, out: &mut Vec<JsonValue>) -> Result<(), String> {
+    match accessor {
+        Accessor::Member(key) => match item {
+            JsonValue::Object(map) => match map.get(key) {
+                Some(value) => out.push(value.clone()),
+                None if ctx.strict => return Err(format!("JSON object does not contain key \"{key}\"")),
+                None => {}
+            },
+            JsonValue::Array(items) if !ctx.strict => {
+                for element in items {
+                    apply_accessor(accessor, element, ctx, out)?;
+                }
+            }
+            _ if ctx.strict => return Err("jsonpath member accessor can only be applied to an object".to_string()),
+            _ => {}
+        },
+        Accessor::WildcardMember => match item {
+            JsonValue::Object(map) => out.extend(map.values().cloned()),
+            JsonValue::Array(items) if !ctx.strict => {
+                for element in items {
+                    if let JsonValue::Object(map) = element {
+                        out.extend(map.values().cloned());
+                    }
+                }
+            }
+            _ if ctx.strict => return Err("jsonpath wildcard member accessor can only be applied to an object".to_string()),
+            _ => {}
+        },
+        Accessor::WildcardElement => match item {
+            JsonValue::Array(items) => out.extend(items.iter().cloned()),
+            _ if ctx.strict => return Err("jsonpath wildcard array accessor can only be applied to an array".to_string()),
+            other => out.push(other.clone()),
+        },
+        Accessor::Elements(subscripts) => {
+            // Lax mode treats a non-array as a single-element array
+            let wrapped;
+            let items = match item {
+                JsonValue::Array(items) => items,
+                _ if ctx.strict => return Err("jsonpath array accessor can only be applied to an array".to_string()),
+                other => {
+                    wrapped = vec![other.clone()];
+                    &wrapped
+                }
+            };
+            let subscript_ctx = Context { last: Some(items.len() as i64 - 1), ..*ctx };
+            for (from, to) in subscripts {
+                let from = subscript(from, item, &subscript_ctx)?;
+                let to = match to {
+                    Some(to) => subscript(to, item, &subscript_ctx)?,
+                    None => from,
+                };
+                for index in from..=to {
+                    match usize::try_from(index).ok().and_then(|i| items.get(i)) {
+                        Some(element) => out.push(element.clone()),
+                        None if ctx.strict => return Err("jsonpath array subscript is out of bounds".to_string()),
+                        None => {}
+                    }
+                }
+            }
+        }
+        Accessor::Filter(condition) => match item {
+            JsonValue::Array(items) if !ctx.strict => {
+                for element in items {
+                    if predicate(condition, element, ctx) == Some(true) {
+                        out.push(element.clone());
+                    }
+                }
+            }
+            other => {
+                if predicate(condition, other, ctx) == Some(true) {
+                    out.push(other.clone());
+                }
+            }
+        },
+        Accessor::Method(method) => {
+            if let JsonValue::Array(items) = item
+                && !ctx.strict
+                && !matches!(method, Method::Size | Method::Type)
+            {
+                for element in items {
+                    apply_accessor(accessor, element, ctx, out)?;
+                }
+                return Ok(());
+            }
+            out.extend(apply_method(*method, item, ctx)?);
+        }
+    }
+    Ok(())
+}
+
+fn apply_method(method: Method, item: &JsonValue, ctx: &Context) -> Result<Vec<JsonValue>, String> {
+    let numeric = |name: &str| -> Result<f64, String> {
+        match item {
+            JsonValue::Number(n) => Ok(n.as_f64().unwrap_or_default()),
+            _ => Err(format!("jsonpath item method .{name}() can only be applied to a numeric value")),
+        }
+    };
+
+    let value = match method {
+        Method::Size => match item {
+            JsonValue::Array(items) => JsonValue::from(items.len()),
+            _ if ctx.strict => return Err("jsonpath item method .size() can only be applied to an array".to_string()),
+            _ => JsonValue::from(1),
+        },
+        Method::Type => JsonValue::String(match item {
+            JsonValue::Null => "null",
+            JsonValue::Bool(_) => "boolean",
+            JsonValue::Number(_) => "number",
+            JsonValue::String(_) => "string",
+            JsonValue::Array(_) => "array",
+            JsonValue::Object(_) => "object",
+        }.to_string()),
+        Method::Double => match item {
+            JsonValue::Number(n) => number(n.as_f64().unwrap_or_default())?,
+            JsonValue::String(s) => number(s.trim().parse::<f64>()
+                .map_err(|_| format!("string argument of jsonpath item method .double() is not a valid representation of a double precision number: \"{s}\""))?)?,
+            _ => return Err("jsonpath item method .double() can only be applied to a string or numeric value".to_string()),
+        },
+        Method::Floor => integral(numeric("floor")?.floor())?,
+        Method::Ceiling => integral(numeric("ceiling")?.ceil())?,
+        Method::Abs => match item {
+            JsonValue::Number(n) if n.is_i64() => JsonValue::from(n.as_i64().unwrap_or_default().abs()),
+            _ => number(numeric("abs")?.abs())?,
+        },
+        Method::String => match item {
+            JsonValue::String(_) => item.clone(),
+            JsonValue::Number(n) => JsonValue::String(n.to_string()),
+            JsonValue::Bool(b) => JsonValue::String(b.to_string()),
+            _ => return Err("jsonpath item method .string() can only be applied to a boolean, string, or numeric value".to_string()),
+        },
+        Method::KeyValue => {
+            let JsonValue::Object(map) = item else {
+                return Err("jsonpath item method .keyvalue() can only be applied to an object".to_string());
+            };
+            return Ok(map.iter().map(|(key, value)| {
+                let mut pair = Map::new();
+                pair.insert("key".to_string(), JsonValue::String(key.clone()));
+                pair.insert("value".to_string(), value.clone());
+                JsonValue::Object(pair)
+            }).collect());
+        }
+    };
+    Ok(vec![value])
+}
+
+/// Evaluate an array subscript to an index, resolving `last`
+fn subscript(expr: &PathExpr, current: &JsonValue, ctx: &Context) -> Result<i64, String> {
+    match eval(expr, current, ctx)?.as_slice() {
+        [JsonValue::Number(n)] => n.as_i64()
+            .or_else(|| n.as_f64().map(|f| f.trunc() as i64))
+            .ok_or_else(|| "jsonpath array subscript is out of integer range".to_string()),
+        _ => Err("jsonpath array subscript is not a single numeric value".to_string()),
+    }
+}
+
+/// Predicate result under SQL/JSON three-valued logic; None is unknown
+fn predicate(expr: &PathExpr, current: &JsonValue, ctx: &Context) -> Option<bool> {
+    match expr {
+        PathExpr::And(left, right) => {
+            let left = predicate(left, current, ctx);
+            if left == Some(false) {
+                return Some(false);
+            }
+            match (left, predicate(right, current, ctx)) {
+                (_, Some(false)) => Some(false),
+                (Some(true), Some(true)) => Some(true),
+                _ => None,
+            }
+        }
+        PathExpr::Or(left, right) => {
+            let left = predicate(left, current, ctx);
+            if left == Some(true) {
+                return Some(true);
+            }
+            match (left, predicate(right, current, ctx)) {
+                (_, Some(true)) => Some(true),
+                (Some(false), Some(false)) => Some(false),
+                _ => None,
+            }
+        }
+        PathExpr::Not(inner) => predicate(inner, current, ctx).map(|b| !b),
+        PathExpr::IsUnknown(inner) => Some(predicate(inner, current, ctx).is_none()),
+        PathExpr::Exists(inner) => eval(inner, current, ctx).ok().map(|items| !items.is_empty()),
+        PathExpr::Compare(left, op, right) => {
+            let left = unwrap_arrays(eval(left, current, ctx).ok()?, ctx);
+            let right = unwrap_arrays(eval(right, current, ctx).ok()?, ctx);
+            existential(&left, |l| {
+                let mut unknown = false;
+                for r in &right {
+                    match compare(l, *op, r) {
+                        Some(true) => return Some(true),
+                        None => unknown = true,
+                        Some(false) => {}
+                    }
+                }
+                if unknown { None } else { Some(false) }
+            })
+        }
+        PathExpr::LikeRegex(inner, regex) => {
+            let items = unwrap_arrays(eval(inner, current, ctx).ok()?, ctx);
+            existential(&items, |item| item.as_str().map(|s| regex.is_match(s)))
+        }
+        PathExpr::StartsWith(inner, prefix) => {
+            let items = unwrap_arrays(eval(inner, current, ctx).ok()?, ctx);
+            let prefix = match eval(prefix, current, ctx).ok()?.as_slice() {
+                [JsonValue::String(prefix)] => prefix.clone(),
+                _ => return None,
+            };
+            existential(&items, |item| item.as_str().map(|s| s.starts_with(&prefix)))
+        }
+        _ => match eval(expr, current, ctx).ok()?.as_slice() {
+            [JsonValue::Bool(b)] => Some(*b),
+            _ => None,
+        },
+    }
+}
+
+/// True if any item satisfies the test, unknown if none does and some test was unknown
+fn existential(items: &[JsonValue], test: impl Fn(&JsonValue) -> Option<bool>) -> Option<bool> {
+    let mut unknown = false;
+    for item in items {
+        match test(item) {
+            Some(true) => return Some(true),
+            None => unknown = true,
+            Some(false) => {}
+        }
+    }
+    if unknown { None } else { Some(false) }
+}
+
+fn compare(left: &JsonValue, op: CompareOp, right: &JsonValue) -> Option<bool> {
+    use std::cmp::Ordering;
+
+    let ordering = match (left, right) {
+        (JsonValue::Null, JsonValue::Null) => Ordering::Equal,
+        (JsonValue::Null, _) | (_, JsonValue::Null) => {
+            // null is only equal to null and is not ordered against anything else
+            return Some(matches!(op, CompareOp::Ne));
+        }
+        (JsonValue::Number(a), JsonValue::Number(b)) => match (a.as_i64(), b.as_i64()) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            _ => a.as_f64()?.partial_cmp(&b.as_f64()?)?,
+        },
+        (JsonValue::String(a), JsonValue::String(b)) => a.cmp(b),
+        (JsonValue::Bool(a), JsonValue::Bool(b)) => a.cmp(b),
+        _ => return None,
+    };
+
+    Some(match op {
+        CompareOp::Eq => ordering == Ordering::Equal,
+        CompareOp::Ne => ordering != Ordering::Equal,
+        CompareOp::Lt => ordering == Ordering::Less,
+        CompareOp::Le => ordering != Ordering::Greater,
+        CompareOp::Gt => ordering == Ordering::Greater,
+        CompareOp::Ge => ordering != Ordering::Less,
+    })
+}
+
+/// Lax mode compares and matches the elements of arrays rather than the arrays themselves
+fn unwrap_arrays(items: Vec<JsonValue>, ctx: &Context) -> Vec<JsonValue> {
+    if ctx.strict {
+        return items;
+    }
+    items.into_iter()
+        .flat_map(|item| match item {
+            JsonValue::Array(elements) => elements,
+            other => vec![other],
+        })
+        .collect()
+}
+
+fn single_number(items: Vec<JsonValue>, ctx: &Context, side: &str) -> Result<Number, String> {
+    match unwrap_arrays(items, ctx).as_slice() {
+        [JsonValue::Number(n)] => Ok(n.clone()),
+        _ => Err(format!("{side} operand of jsonpath operator is not a single numeric value")),
+    }
+}
+
+fn arithmetic(left: &Number, op: ArithOp, right: &Number) -> Result<JsonValue, String> {
+    if let (Some(a), Some(b)) = (left.as_i64(), right.as_i64()) {
+        let result = match op {
+            ArithOp::Add => a.checked_add(b),
+            ArithOp::Sub => a.checked_sub(b),
+            ArithOp::Mul => a.checked_mul(b),
+            ArithOp::Mod if b == 0 => return Err("division by zero".to_string()),
+            ArithOp::Mod => a.checked_rem(b),
+            ArithOp::Div if b == 0 => return Err("division by zero".to_string()),
+            ArithOp::Div if a % b == 0 => a.checked_div(b),
+            ArithOp::Div => None,
+        };
+        if let Some(result) = result {
+            return Ok(JsonValue::from(result));
+        }
+    }
+
+    let (a, b) = (left.as_f64().unwrap_or_default(), right.as_f64().unwrap_or_default());
+    let result = match op {
+        ArithOp::Add => a + b,
+        ArithOp::Sub => a - b,
+        ArithOp::Mul => a * b,
+        ArithOp::Div | ArithOp::Mod if b == 0.0 => return Err("division by zero".to_string()),
+        ArithOp::Div => a / b,
+        ArithOp::Mod => a % b,
+    };
+    number(result)
+}
+
+fn number(value: f64) -> Result<JsonValue, String> {
+    Number::from_f64(value)
+        .map(JsonValue::Number)
+        .ok_or_else(|| "numeric value is out of range for jsonpath".to_string())
+}
+
+/// floor()/ceiling() results are whole numbers
+fn integral(value: f64) -> Result<JsonValue, String> {
+    if value.abs() < i64::MAX as f64 {
+        Ok(JsonValue::from(value as i64))
+    } else {
+        number(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn query(target: JsonValue, path: &str) -> Result<Vec<JsonValue>, String> {
+        JsonPath::parse(path)?.query(&target, &JsonValue::Null)
+    }
+
+    #[test]
+    fn test_accessors() {
+        let doc = json!({"a": {"b": [1, 2, 3, 4]}, "list": [{"n": 1}, {"n": 2}], "key with space": true});
+        assert_eq!(query(doc.clone(), "$.a.b[*]").unwrap(), vec![json!(1), json!(2), json!(3), json!(4)]);
+        assert_eq!(query(doc.clone(), "$.a.b[0, 2 to last]").unwrap(), vec![json!(1), json!(3), json!(4)]);
+        assert_eq!(query(doc.clone(), "$.a.b[last - 1]").unwrap(), vec![json!(3)]);
+        assert_eq!(query(doc.clone(), "$.\"key with space\"").unwrap(), vec![json!(true)]);
+        // Lax mode unwraps arrays for member access
+        assert_eq!(query(doc.clone(), "$.list.n").unwrap(), vec![json!(1), json!(2)]);
+        assert!(query(doc.clone(), "$.missing").unwrap().is_empty());
+        assert!(query(doc.clone(), "strict $.missing").is_err());
+        assert!(query(doc, "strict $.list.n").is_err());
+    }
+
+    #[test]
+    fn test_filters_and_predicates() {
+        let doc = json!({"items": [
+            {"name": "apple", "price": 3, "tags": ["fruit"]},
+            {"name": "bread", "price": 12.5},
+            {"name": "avocado", "price": 7, "tags": ["fruit", "green"]}
+        ]});
+        assert_eq!(query(doc.clone(), "$.items[*] ? (@.price > 5).name").unwrap(), vec![json!("bread"), json!("avocado")]);
+        assert_eq!(
+            query(doc.clone(), "$.items[*] ? (@.name starts with \"a\" && exists(@.tags)).name").unwrap(),
+            vec![json!("apple"), json!("avocado")]
+        );
+        assert_eq!(query(doc.clone(), "$.items[*] ? (@.tags == \"green\").name").unwrap(), vec![json!("avocado")]);
+        assert_eq!(query(doc.clone(), "$.items[*] ? (@.name like_regex \"^B\" flag \"i\").price").unwrap(), vec![json!(12.5)]);
+        assert_eq!(query(doc.clone(), "$.items[*] ? (!(@.price < 10)).name").unwrap(), vec![json!("bread")]);
+
+        let path = JsonPath::parse("$.items[*].price > $min").unwrap();
+        assert_eq!(path.matches(&doc, &json!({"min": 10})).unwrap(), Some(true));
+        assert_eq!(path.matches(&doc, &json!({"min": 20})).unwrap(), Some(false));
+        // Comparing a string with a number is unknown
+        assert_eq!(JsonPath::parse("$.items[0].name > 1").unwrap().matches(&doc, &JsonValue::Null).unwrap(), None);
+    }
+
+    #[test]
+    fn test_arithmetic_and_methods() {
+        let doc = json!({"a": [1, 2, 3], "x": 7, "s": "2.5", "o": {"k": 1}});
+        assert_eq!(query(doc.clone(), "$.x * 2 + 1").unwrap(), vec![json!(15)]);
+        assert_eq!(query(doc.clone(), "$.x / 2").unwrap(), vec![json!(3.5)]);
+        assert_eq!(query(doc.clone(), "$.a.size()").unwrap(), vec![json!(3)]);
+        assert_eq!(query(doc.clone(), "$.o.type()").unwrap(), vec![json!("object")]);
+        assert_eq!(query(doc.clone(), "$.s.double().floor()").unwrap(), vec![json!(2)]);
+        assert_eq!(query(doc.clone(), "$.o.keyvalue()").unwrap(), vec![json!({"key": "k", "value": 1})]);
+        assert_eq!(query(doc.clone(), "-$.a[*]").unwrap(), vec![json!(-1), json!(-2), json!(-3)]);
+        assert!(query(doc.clone(), "$.x / 0").is_err());
+        assert!(query(doc, "$.a[").is_err());
+    }
+}
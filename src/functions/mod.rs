@@ -1,17 +1,29 @@
 // Module for PostgreSQL function implementations
 pub mod uuid_functions;
 pub mod json_functions;
+pub mod json_path;
 pub mod decimal_functions;
 pub mod datetime_functions;
 pub mod regex_functions;
 pub mod catalog_functions;
 pub mod hash_functions;
 pub mod array_functions;
+pub mod range_functions;
+pub mod network_functions;
+pub mod xml_functions;
+pub mod bit_functions;
+pub mod composite_functions;
+pub mod crosstab_functions;
 pub mod unnest_vtab;
+pub mod series_vtab;
 pub mod string_functions;
 pub mod math_functions;
+pub mod statistical_functions;
 pub mod system_functions;
+pub mod advisory_lock_functions;
 pub mod fts_functions;
+pub mod sql_functions;
+pub mod signatures;
 
 use rusqlite::{Connection, Result};
 
@@ -25,10 +37,20 @@ pub fn register_all_functions(conn: &Connection) -> Result<()> {
     catalog_functions::register_catalog_functions(conn)?;
     hash_functions::register_hash_functions(conn)?;
     array_functions::register_array_functions(conn)?;
+    range_functions::register_range_functions(conn)?;
+    network_functions::register_network_functions(conn)?;
+    xml_functions::register_xml_functions(conn)?;
+    bit_functions::register_bit_functions(conn)?;
+    composite_functions::register_composite_functions(conn)?;
+    crosstab_functions::register_crosstab_functions(conn)?;
     unnest_vtab::register_unnest_vtab(conn)?;
+    series_vtab::register_series_vtab(conn)?;
     string_functions::register_string_functions(conn)?;
     math_functions::register_math_functions(conn)?;
+    statistical_functions::register_statistical_functions(conn)?;
     system_functions::register_system_functions(conn)?;
+    // Session connections register these again, locking on behalf of their session
+    advisory_lock_functions::register_advisory_lock_functions(conn, uuid::Uuid::nil(), || false)?;
     fts_functions::register_fts_functions(conn)?;
     Ok(())
 }
\ No newline at end of file
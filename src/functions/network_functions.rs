@@ -0,0 +1,109 @@
+use rusqlite::{Connection, Result, functions::{Context, FunctionFlags}, types::{Value, ValueRef}};
+use tracing::debug;
+use crate::types::network::{InetValue, NetworkKind};
+use super::bit_functions::{bit_string, shift_bits};
+
+/// Register inet/cidr accessor functions and the network containment operators
+pub fn register_network_functions(conn: &Connection) -> Result<()> {
+    debug!("Registering network functions");
+
+    // pg_network_from_text(text, type_name) - the target of '...'::inet casts
+    conn.create_scalar_function(
+        "pg_network_from_text",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let Some(text) = ctx.get::<Option<String>>(0)? else {
+                return Ok(None);
+            };
+            let type_name = ctx.get::<String>(1)?;
+            let kind = NetworkKind::from_name(&type_name)
+                .ok_or_else(|| user_error(format!("type \"{type_name}\" is not a network address type")))?;
+            kind.canonicalize(&text).map(Some).map_err(user_error)
+        },
+    )?;
+
+    // host(), network(), netmask(), broadcast() return text; masklen() and family() integers
+    type InetAccessor = fn(&InetValue) -> Value;
+    let accessors: [(&str, InetAccessor); 6] = [
+        ("host", |value| Value::Text(value.addr.to_string())),
+        ("network", |value| Value::Text(value.network().to_string())),
+        ("netmask", |value| Value::Text(value.netmask_addr().to_string())),
+        ("broadcast", |value| Value::Text(InetValue { addr: value.broadcast_addr(), ..*value }.to_string())),
+        ("masklen", |value| Value::Integer(value.bits as i64)),
+        ("family", |value| Value::Integer(value.family() as i64)),
+    ];
+    for (name, accessor) in accessors {
+        conn.create_scalar_function(
+            name,
+            1,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            move |ctx| {
+                match inet_arg(ctx, 0)? {
+                    Some(value) => Ok(accessor(&value)),
+                    None => Ok(Value::Null),
+                }
+            },
+        )?;
+    }
+
+    // << and >> are bit shifts on integers and bit strings and containment on
+    // networks; <<= and >>= only exist for networks
+    type Containment = fn(&InetValue, &InetValue) -> bool;
+    type Shift = fn(i64, i64) -> i64;
+    let operators: [(&str, Containment, Option<Shift>); 4] = [
+        ("network_sub", |left, right| right.contains(left, false), Some(|a, b| a.checked_shl(b as u32).unwrap_or(0))),
+        ("network_subeq", |left, right| right.contains(left, true), None),
+        ("network_sup", |left, right| left.contains(right, false), Some(|a, b| a.checked_shr(b as u32).unwrap_or(if a < 0 { -1 } else { 0 }))),
+        ("network_supeq", |left, right| left.contains(right, true), None),
+    ];
+    for (name, containment, shift) in operators {
+        conn.create_scalar_function(
+            name,
+            2,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            move |ctx| {
+                if let (Some(shift), ValueRef::Integer(a), ValueRef::Integer(b)) = (shift, ctx.get_raw(0), ctx.get_raw(1)) {
+                    return Ok(Value::Integer(shift(a, b)));
+                }
+                if shift.is_some()
+                    && let (Some(bits), ValueRef::Integer(count)) = (bit_string(ctx.get_raw(0)), ctx.get_raw(1)) {
+                    return Ok(Value::Text(shift_bits(&bits, count, name == "network_sub")));
+                }
+                match (inet_arg(ctx, 0)?, inet_arg(ctx, 1)?) {
+                    (Some(left), Some(right)) => Ok(Value::Integer(containment(&left, &right) as i64)),
+                    _ => Ok(Value::Null),
+                }
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Evaluate `left && right` when both sides are inet/cidr values
+///
+/// Returns None for anything else, so the caller can fall back to array overlap.
+pub fn network_overlaps(left: ValueRef, right: ValueRef) -> Option<bool> {
+    let (ValueRef::Text(left), ValueRef::Text(right)) = (left, right) else {
+        return None;
+    };
+    let left = InetValue::parse_inet(std::str::from_utf8(left).ok()?).ok()?;
+    let right = InetValue::parse_inet(std::str::from_utf8(right).ok()?).ok()?;
+    Some(left.overlaps(&right))
+}
+
+fn inet_arg(ctx: &Context, idx: usize) -> Result<Option<InetValue>> {
+    match ctx.get_raw(idx) {
+        ValueRef::Null => Ok(None),
+        ValueRef::Text(text) => {
+            let text = String::from_utf8_lossy(text);
+            InetValue::parse_inet(&text).map(Some).map_err(user_error)
+        }
+        _ => Err(user_error("argument must be of type inet or cidr")),
+    }
+}
+
+fn user_error(message: impl Into<String>) -> rusqlite::Error {
+    rusqlite::Error::UserFunctionError(message.into().into())
+}
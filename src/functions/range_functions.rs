@@ -0,0 +1,237 @@
+use rusqlite::{Connection, Result, functions::{Context, FunctionFlags}, types::{Value, ValueRef}};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::FromPrimitive;
+use tracing::debug;
+use crate::types::range::{Range, RangeKind, RangeValue};
+
+const RANGE_TYPES: [&str; 6] = ["int4range", "int8range", "numrange", "tsrange", "tstzrange", "daterange"];
+
+/// Register range constructors and range accessor functions
+pub fn register_range_functions(conn: &Connection) -> Result<()> {
+    debug!("Registering range functions");
+
+    // int4range(lower, upper [, bounds]) and friends
+    for name in RANGE_TYPES {
+        let kind = RangeKind::from_name(name).expect("built-in range type");
+        for n_args in [2, 3] {
+            conn.create_scalar_function(
+                name,
+                n_args,
+                FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+                move |ctx| {
+                    let lower = range_value_arg(ctx, 0, kind)?;
+                    let upper = range_value_arg(ctx, 1, kind)?;
+                    let bounds = if ctx.len() > 2 { ctx.get::<Option<String>>(2)? } else { None };
+                    Range::with_bounds(kind, lower, upper, bounds.as_deref().unwrap_or("[)"))
+                        .map(|range| range.to_string())
+                        .map_err(user_error)
+                },
+            )?;
+        }
+    }
+
+    // pg_range_from_text(text, type_name) - the target of '...'::int4range casts
+    conn.create_scalar_function(
+        "pg_range_from_text",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let Some(text) = ctx.get::<Option<String>>(0)? else {
+                return Ok(None);
+            };
+            let type_name = ctx.get::<String>(1)?;
+            let kind = RangeKind::from_name(&type_name)
+                .ok_or_else(|| user_error(format!("type \"{type_name}\" is not a range type")))?;
+            Range::parse(kind, &text).map(|range| Some(range.to_string())).map_err(user_error)
+        },
+    )?;
+
+    // lower()/upper() are shared with the text functions, so anything that is
+    // not a range literal keeps SQLite's ASCII case mapping
+    for (name, is_lower) in [("lower", true), ("upper", false)] {
+        conn.create_scalar_function(
+            name,
+            1,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            move |ctx| {
+                let text = match ctx.get_raw(0) {
+                    ValueRef::Null => return Ok(Value::Null),
+                    ValueRef::Integer(i) => return Ok(Value::Text(i.to_string())),
+                    ValueRef::Real(f) => return Ok(Value::Text(f.to_string())),
+                    ValueRef::Text(bytes) | ValueRef::Blob(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+                };
+                if let Some(range) = range_literal(&text) {
+                    let bound = if is_lower { &range.lower } else { &range.upper };
+                    let bound_text = if is_lower { range.lower_text() } else { range.upper_text() };
+                    return Ok(match (bound, bound_text) {
+                        (Some(RangeValue::Int(v)), _) => Value::Integer(*v),
+                        (_, Some(text)) => Value::Text(text),
+                        _ => Value::Null,
+                    });
+                }
+                Ok(Value::Text(if is_lower { text.to_ascii_lowercase() } else { text.to_ascii_uppercase() }))
+            },
+        )?;
+    }
+
+    // isempty(), lower_inc(), upper_inc(), lower_inf(), upper_inf()
+    type RangePredicate = fn(&Range) -> bool;
+    let predicates: [(&str, RangePredicate); 5] = [
+        ("isempty", |range| range.empty),
+        ("lower_inc", |range| range.lower_inc),
+        ("upper_inc", |range| range.upper_inc),
+        ("lower_inf", |range| !range.empty && range.lower.is_none()),
+        ("upper_inf", |range| !range.empty && range.upper.is_none()),
+    ];
+    for (name, predicate) in predicates {
+        conn.create_scalar_function(
+            name,
+            1,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            move |ctx| {
+                let Some(text) = ctx.get::<Option<String>>(0)? else {
+                    return Ok(None);
+                };
+                let range = Range::parse_inferred(&text)
+                    .ok_or_else(|| user_error(format!("malformed range literal: \"{text}\"")))?;
+                Ok(Some(predicate(&range)))
+            },
+        )?;
+    }
+
+    // range_overlaps(), range_before() and the other functions behind the range operators,
+    // for exclusion constraints and for queries that want to call them by name
+    type RangeRelation = fn(&Range, &Range) -> bool;
+    let relations: [(&str, RangeRelation); 6] = [
+        ("range_overlaps", |left, right| left.overlaps(right)),
+        ("range_contains", |left, right| left.contains_range(right)),
+        ("range_contained_by", |left, right| right.contains_range(left)),
+        ("range_adjacent", |left, right| left.is_adjacent(right)),
+        ("range_before", |left, right| left.is_before(right)),
+        ("range_after", |left, right| right.is_before(left)),
+    ];
+    for (name, relation) in relations {
+        conn.create_scalar_function(
+            name,
+            2,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            move |ctx| {
+                let (Some(left), Some(right)) = (range_arg(ctx, 0)?, range_arg(ctx, 1)?) else {
+                    return Ok(None);
+                };
+                Ok(Some(relation(&left, &right)))
+            },
+        )?;
+    }
+
+    // range_contains_elem(range, element) and elem_contained_by_range(element, range)
+    for (name, range_idx) in [("range_contains_elem", 0), ("elem_contained_by_range", 1)] {
+        conn.create_scalar_function(
+            name,
+            2,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            move |ctx| {
+                let element_idx = 1 - range_idx;
+                if matches!(ctx.get_raw(element_idx), ValueRef::Null) {
+                    return Ok(None);
+                }
+                let Some(range) = range_arg(ctx, range_idx)? else {
+                    return Ok(None);
+                };
+                let element = range_value_arg(ctx, element_idx, range.kind)?;
+                Ok(element.map(|element| range.contains_value(&element)))
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Evaluate `container @> contained` when the container is a range. The element
+/// may be a range, a stored element (integer days or microseconds for dates and
+/// timestamps) or element text.
+///
+/// Returns None when the operands are not a range and something a range can
+/// contain, so the caller can fall back to array or JSON containment.
+pub fn range_contains(container: ValueRef, contained: ValueRef) -> Option<bool> {
+    let ValueRef::Text(container) = container else {
+        return None;
+    };
+    let container = std::str::from_utf8(container).ok()?;
+    if is_json_array(container) && matches!(contained, ValueRef::Text(text) if is_json_array_bytes(text)) {
+        return None;
+    }
+    let range = Range::parse_inferred(container)?;
+
+    let element = match contained {
+        ValueRef::Integer(i) => range.kind.value_from_integer(i),
+        ValueRef::Real(f) => RangeValue::Num(Decimal::from_f64(f)?),
+        ValueRef::Text(text) => {
+            let text = std::str::from_utf8(text).ok()?;
+            if let Some(other) = Range::parse_inferred(text) {
+                return Some(range.contains_range(&other));
+            }
+            range.kind.parse_value(text)?
+        }
+        ValueRef::Null | ValueRef::Blob(_) => return None,
+    };
+    Some(range.contains_value(&element))
+}
+
+/// Evaluate `left && right` when both sides are ranges
+pub fn range_overlaps(left: ValueRef, right: ValueRef) -> Option<bool> {
+    let (ValueRef::Text(left), ValueRef::Text(right)) = (left, right) else {
+        return None;
+    };
+    let (left, right) = (std::str::from_utf8(left).ok()?, std::str::from_utf8(right).ok()?);
+    if is_json_array(left) && is_json_array(right) {
+        return None;
+    }
+    Some(Range::parse_inferred(left)?.overlaps(&Range::parse_inferred(right)?))
+}
+
+/// A bracketed range literal; the bare word `empty` stays ordinary text
+fn range_literal(text: &str) -> Option<Range> {
+    if text.trim().eq_ignore_ascii_case("empty") {
+        return None;
+    }
+    Range::parse_inferred(text)
+}
+
+/// Arrays are stored as JSON, and `[a,b]` is both a JSON array and an
+/// inclusive range. When both operands are arrays, array semantics win.
+fn is_json_array(text: &str) -> bool {
+    text.trim_start().starts_with('[') && serde_json::from_str::<serde_json::Value>(text).is_ok_and(|v| v.is_array())
+}
+
+fn is_json_array_bytes(bytes: &[u8]) -> bool {
+    std::str::from_utf8(bytes).is_ok_and(is_json_array)
+}
+
+/// A range argument, None if it is NULL
+fn range_arg(ctx: &Context, idx: usize) -> Result<Option<Range>> {
+    let Some(text) = ctx.get::<Option<String>>(idx)? else {
+        return Ok(None);
+    };
+    Range::parse_inferred(&text)
+        .map(Some)
+        .ok_or_else(|| user_error(format!("malformed range literal: \"{text}\"")))
+}
+
+fn range_value_arg(ctx: &Context, idx: usize, kind: RangeKind) -> Result<Option<RangeValue>> {
+    let value = match ctx.get_raw(idx) {
+        ValueRef::Null => return Ok(None),
+        ValueRef::Integer(i) => Some(kind.value_from_integer(i)),
+        ValueRef::Real(f) => match kind {
+            RangeKind::Num => Decimal::from_f64(f).map(RangeValue::Num),
+            _ => kind.parse_value(&f.to_string()),
+        },
+        ValueRef::Text(text) => kind.parse_value(&String::from_utf8_lossy(text)),
+        ValueRef::Blob(_) => None,
+    };
+    value.map(Some).ok_or_else(|| user_error(format!("invalid bound for {kind}")))
+}
+
+fn user_error(message: impl Into<String>) -> rusqlite::Error {
+    rusqlite::Error::UserFunctionError(message.into().into())
+}
@@ -71,10 +71,72 @@ pub fn register_regex_functions(conn: &Connection) -> Result<()> {
         },
     )?;
     
+    // regexp_split_to_array(string, pattern [, flags]) - also expanded by regexp_split_to_table
+    for n_args in [2, 3] {
+        conn.create_scalar_function(
+            "regexp_split_to_array",
+            n_args,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            move |ctx| {
+                let Some(text) = ctx.get::<Option<String>>(0)? else {
+                    return Ok(None);
+                };
+                let Some(pattern) = ctx.get::<Option<String>>(1)? else {
+                    return Ok(None);
+                };
+                let flags = if n_args == 3 { ctx.get::<Option<String>>(2)?.unwrap_or_default() } else { String::new() };
+                
+                let re = compile_with_flags(&pattern, &flags, "regexp_split_to_array")
+                    .map_err(|e| rusqlite::Error::UserFunctionError(e.into()))?;
+                let parts: Vec<serde_json::Value> = regexp_split(&re, &text)
+                    .into_iter()
+                    .map(|part| serde_json::Value::String(part.to_string()))
+                    .collect();
+                Ok(serde_json::to_string(&parts).ok())
+            },
+        )?;
+    }
+    
     debug!("Regex functions registered successfully");
     Ok(())
 }
 
+/// Compile a pattern with PostgreSQL's regex option letters
+fn compile_with_flags(pattern: &str, flags: &str, function: &str) -> std::result::Result<Regex, String> {
+    let mut prefix = String::new();
+    for flag in flags.chars() {
+        match flag {
+            'i' => prefix.push_str("(?i)"),
+            'c' => prefix.push_str("(?-i)"),
+            'x' => prefix.push_str("(?x)"),
+            'n' | 'm' => prefix.push_str("(?m)"),
+            's' => prefix.push_str("(?s)"),
+            'g' => return Err(format!("{function}() does not support the \"global\" option")),
+            other => return Err(format!("invalid regular expression option: \"{other}\"")),
+        }
+    }
+    Regex::new(&format!("{prefix}{pattern}"))
+        .map_err(|e| format!("invalid regular expression: {e}"))
+}
+
+/// Split text at each match of a regex, following PostgreSQL: empty matches at the
+/// start or end of the string, or right after a previous match, do not split.
+fn regexp_split<'a>(re: &Regex, text: &'a str) -> Vec<&'a str> {
+    let mut parts = Vec::new();
+    let mut last = 0;
+    let mut previous_end = None;
+    for m in re.find_iter(text) {
+        if m.start() == m.end() && (m.start() == 0 || m.start() == text.len() || previous_end == Some(m.start())) {
+            continue;
+        }
+        parts.push(&text[last..m.start()]);
+        last = m.end();
+        previous_end = Some(m.end());
+    }
+    parts.push(&text[last..]);
+    parts
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -133,4 +195,18 @@ mod tests {
             .unwrap();
         assert!(!result);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_regexp_split_to_array() {
+        let conn = Connection::open_in_memory().unwrap();
+        register_regex_functions(&conn).unwrap();
+        let split = |sql: &str| conn.query_row(sql, [], |row| row.get::<_, Option<String>>(0));
+        
+        assert_eq!(split(r"SELECT regexp_split_to_array('the quick  fox', '\s+')").unwrap(), Some(r#"["the","quick","fox"]"#.to_string()));
+        assert_eq!(split("SELECT regexp_split_to_array('hello', '')").unwrap(), Some(r#"["h","e","l","l","o"]"#.to_string()));
+        assert_eq!(split("SELECT regexp_split_to_array('aXbxc', 'x', 'i')").unwrap(), Some(r#"["a","b","c"]"#.to_string()));
+        assert_eq!(split("SELECT regexp_split_to_array(',a,', ',')").unwrap(), Some(r#"["","a",""]"#.to_string()));
+        assert_eq!(split("SELECT regexp_split_to_array(NULL, ',')").unwrap(), None);
+        assert!(split("SELECT regexp_split_to_array('a', 'a', 'g')").is_err());
+    }
+}
@@ -0,0 +1,215 @@
+use std::ffi::c_int;
+use std::marker::PhantomData;
+use rusqlite::{ffi, Connection, Error, Result};
+use rusqlite::vtab::{
+    eponymous_only_module, Context, Filters, IndexConstraintOp, IndexInfo, VTab, VTabConfig,
+    VTabConnection, VTabCursor,
+};
+
+/// The hidden start, stop, step and at columns follow value
+const COLUMN_START: c_int = 1;
+const COLUMN_AT: c_int = 4;
+
+/// Register generate_series(start, stop [, step]) as a table-valued function
+///
+/// Unlike SQLite's series extension it follows PostgreSQL: a negative step counts
+/// down from start to stop, a zero step is an error and a NULL argument yields no rows.
+/// The single output column is `value`.
+///
+/// `__pgsqlite_series(start, stop [, step [, at]])` is the same series for translated
+/// queries. Its column names can't clash with the user's, and `at` picks the at-th
+/// value alone.
+pub fn register_series_vtab(conn: &Connection) -> Result<()> {
+    conn.create_module(
+        c"generate_series",
+        eponymous_only_module::<SeriesTab>(),
+        Some("CREATE TABLE x(value, start HIDDEN, stop HIDDEN, step HIDDEN)"),
+    )?;
+    conn.create_module(
+        c"__pgsqlite_series",
+        eponymous_only_module::<SeriesTab>(),
+        Some("CREATE TABLE x(__pgsqlite_value, __pgsqlite_start HIDDEN, __pgsqlite_stop HIDDEN, __pgsqlite_step HIDDEN, __pgsqlite_at HIDDEN)"),
+    )
+}
+
+/// Pass equality constraints on the hidden columns `first..=last` to filter as its
+/// arguments, in column order. Returns idx_num with a bit per argument passed, so
+/// filter knows which ones it got.
+pub(super) fn hidden_arguments(info: &mut IndexInfo, first: c_int, last: c_int) -> Result<c_int> {
+    let mut arguments = vec![None; (last - first + 1) as usize];
+    let mut unusable = false;
+    for (i, constraint) in info.constraints().enumerate() {
+        let column = constraint.column();
+        if !(first..=last).contains(&column) {
+            continue;
+        }
+        if !constraint.is_usable() {
+            unusable = true;
+        } else if constraint.operator() == IndexConstraintOp::SQLITE_INDEX_CONSTRAINT_EQ {
+            arguments[(column - first) as usize] = Some(i);
+        }
+    }
+    // Arguments that reference a table not yet scanned: have SQLite scan it first
+    if unusable {
+        return Err(Error::SqliteFailure(ffi::Error::new(ffi::SQLITE_CONSTRAINT), None));
+    }
+
+    let mut idx_num = 0;
+    let mut argv_index = 0;
+    for (bit, constraint) in arguments.iter().enumerate() {
+        if let Some(constraint) = constraint {
+            idx_num |= 1 << bit;
+            argv_index += 1;
+            let mut usage = info.constraint_usage(*constraint);
+            usage.set_argv_index(argv_index);
+            usage.set_omit(true);
+        }
+    }
+    info.set_idx_num(idx_num);
+    info.set_estimated_cost(1.0);
+    info.set_estimated_rows(1000);
+    Ok(idx_num)
+}
+
+#[repr(C)]
+struct SeriesTab {
+    /// Base class, must be first
+    base: ffi::sqlite3_vtab,
+}
+
+unsafe impl<'vtab> VTab<'vtab> for SeriesTab {
+    type Aux = &'static str;
+    type Cursor = SeriesCursor<'vtab>;
+
+    fn connect(db: &mut VTabConnection, schema: Option<&&'static str>, _args: &[&[u8]]) -> Result<(String, Self)> {
+        db.config(VTabConfig::Innocuous)?;
+        Ok((schema.unwrap().to_string(), SeriesTab { base: ffi::sqlite3_vtab::default() }))
+    }
+
+    fn best_index(&self, info: &mut IndexInfo) -> Result<()> {
+        hidden_arguments(info, COLUMN_START, COLUMN_AT)?;
+        Ok(())
+    }
+
+    fn open(&mut self) -> Result<SeriesCursor<'_>> {
+        Ok(SeriesCursor {
+            base: ffi::sqlite3_vtab_cursor::default(),
+            value: None,
+            stop: 0,
+            step: 1,
+            row_id: 0,
+            phantom: PhantomData,
+        })
+    }
+}
+
+#[repr(C)]
+struct SeriesCursor<'vtab> {
+    /// Base class, must be first
+    base: ffi::sqlite3_vtab_cursor,
+    /// The current value, None once the series is done
+    value: Option<i64>,
+    stop: i64,
+    step: i64,
+    row_id: i64,
+    phantom: PhantomData<&'vtab SeriesTab>,
+}
+
+impl SeriesCursor<'_> {
+    fn in_range(&self, value: i64) -> bool {
+        if self.step > 0 { value <= self.stop } else { value >= self.stop }
+    }
+}
+
+unsafe impl VTabCursor for SeriesCursor<'_> {
+    fn filter(&mut self, idx_num: c_int, _idx_str: Option<&str>, args: &Filters<'_>) -> Result<()> {
+        if idx_num & 0b11 != 0b11 {
+            return Err(Error::ModuleError("generate_series requires start and stop arguments".to_string()));
+        }
+        let start: Option<i64> = args.get(0)?;
+        let stop: Option<i64> = args.get(1)?;
+        let step: Option<i64> = if idx_num & 0b100 != 0 { args.get(2)? } else { Some(1) };
+        let at: Option<i64> = if idx_num & 0b1000 != 0 { args.get(args.len() - 1)? } else { Some(1) };
+        if step == Some(0) {
+            return Err(Error::ModuleError("step size cannot equal zero".to_string()));
+        }
+        self.value = None;
+        if let (Some(start), Some(stop), Some(step), Some(at)) = (start, stop, step, at) {
+            self.stop = stop;
+            self.step = step;
+            self.row_id = at;
+            self.value = at.checked_sub(1)
+                .and_then(|skipped| skipped.checked_mul(step))
+                .and_then(|offset| start.checked_add(offset))
+                .filter(|&value| at >= 1 && self.in_range(value));
+            // A single value was asked for
+            if idx_num & 0b1000 != 0 {
+                self.stop = self.value.unwrap_or(stop);
+            }
+        }
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.value = self.value
+            .and_then(|value| value.checked_add(self.step))
+            .filter(|&value| self.in_range(value));
+        self.row_id += 1;
+        Ok(())
+    }
+
+    fn eof(&self) -> bool {
+        self.value.is_none()
+    }
+
+    fn column(&self, ctx: &mut Context, _i: c_int) -> Result<()> {
+        ctx.set_result(&self.value)
+    }
+
+    fn rowid(&self) -> Result<i64> {
+        Ok(self.row_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(conn: &Connection, sql: &str) -> Result<Vec<i64>> {
+        let mut stmt = conn.prepare(sql)?;
+        let values = stmt.query_map([], |row| row.get(0))?.collect();
+        values
+    }
+
+    #[test]
+    fn test_generate_series() {
+        let conn = Connection::open_in_memory().unwrap();
+        register_series_vtab(&conn).unwrap();
+
+        assert_eq!(series(&conn, "SELECT value FROM generate_series(1, 3)").unwrap(), [1, 2, 3]);
+        assert_eq!(series(&conn, "SELECT value FROM generate_series(0, 10, 4)").unwrap(), [0, 4, 8]);
+        assert_eq!(series(&conn, "SELECT value FROM generate_series(5, 0, -2)").unwrap(), [5, 3, 1]);
+        assert!(series(&conn, "SELECT value FROM generate_series(3, 1)").unwrap().is_empty());
+        assert!(series(&conn, "SELECT value FROM generate_series(1, NULL)").unwrap().is_empty());
+        let top = i64::MAX;
+        assert_eq!(series(&conn, &format!("SELECT value FROM generate_series({}, {top})", top - 1)).unwrap(), [top - 1, top]);
+
+        let err = series(&conn, "SELECT value FROM generate_series(1, 3, 0)").unwrap_err();
+        assert!(err.to_string().contains("step size cannot equal zero"), "{err}");
+
+        // Arguments can come from tables scanned before it
+        conn.execute_batch("CREATE TABLE t (n INTEGER); INSERT INTO t VALUES (1), (3)").unwrap();
+        assert_eq!(series(&conn, "SELECT t.n * 10 + s.value FROM t, generate_series(1, t.n) AS s ORDER BY 1").unwrap(), [11, 31, 32, 33]);
+    }
+
+    #[test]
+    fn test_series_at() {
+        let conn = Connection::open_in_memory().unwrap();
+        register_series_vtab(&conn).unwrap();
+
+        assert_eq!(series(&conn, "SELECT __pgsqlite_value FROM __pgsqlite_series(10, 0, -3, 2)").unwrap(), [7]);
+        assert!(series(&conn, "SELECT __pgsqlite_value FROM __pgsqlite_series(10, 0, -3, 5)").unwrap().is_empty());
+        assert!(series(&conn, "SELECT __pgsqlite_value FROM __pgsqlite_series(1, 3, 1, 0)").unwrap().is_empty());
+        assert_eq!(series(&conn, "SELECT __pgsqlite_value FROM __pgsqlite_series(1, 3)").unwrap(), [1, 2, 3]);
+    }
+}
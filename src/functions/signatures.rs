@@ -0,0 +1,408 @@
+//! PostgreSQL signatures of the registered functions, the source of the pg_proc view.
+//!
+//! SQLite only knows a function's name and argument count, so the argument and
+//! result types clients see in pg_proc are listed here. Internal helpers the
+//! translators call (decimal_*, pgsqlite_*, ...) are left out.
+
+use once_cell::sync::Lazy;
+
+/// PostgreSQL signature of a registered function
+pub struct FunctionSignature {
+    pub name: &'static str,
+    /// Argument types; a `VARIADIC` prefix marks the last one as variadic
+    pub args: &'static [&'static str],
+    pub returns: &'static str,
+}
+
+const fn sig(name: &'static str, args: &'static [&'static str], returns: &'static str) -> FunctionSignature {
+    FunctionSignature { name, args, returns }
+}
+
+pub const FUNCTION_SIGNATURES: &[FunctionSignature] = &[
+    // Math
+    sig("abs", &["numeric"], "numeric"),
+    sig("acos", &["double precision"], "double precision"),
+    sig("asin", &["double precision"], "double precision"),
+    sig("atan", &["double precision"], "double precision"),
+    sig("atan2", &["double precision", "double precision"], "double precision"),
+    sig("ceil", &["numeric"], "numeric"),
+    sig("ceiling", &["numeric"], "numeric"),
+    sig("cos", &["double precision"], "double precision"),
+    sig("degrees", &["double precision"], "double precision"),
+    sig("exp", &["double precision"], "double precision"),
+    sig("floor", &["numeric"], "numeric"),
+    sig("ln", &["double precision"], "double precision"),
+    sig("log", &["numeric"], "numeric"),
+    sig("log", &["numeric", "numeric"], "numeric"),
+    sig("mod", &["integer", "integer"], "integer"),
+    sig("pi", &[], "double precision"),
+    sig("pow", &["double precision", "double precision"], "double precision"),
+    sig("power", &["double precision", "double precision"], "double precision"),
+    sig("radians", &["double precision"], "double precision"),
+    sig("random", &[], "double precision"),
+    sig("round", &["numeric", "integer"], "numeric"),
+    sig("sign", &["numeric"], "numeric"),
+    sig("sin", &["double precision"], "double precision"),
+    sig("sqrt", &["double precision"], "double precision"),
+    sig("tan", &["double precision"], "double precision"),
+    sig("trunc", &["numeric"], "numeric"),
+    sig("trunc", &["numeric", "integer"], "numeric"),
+    sig("width_bucket", &["anyelement", "anyarray"], "integer"),
+    sig("width_bucket", &["numeric", "numeric", "numeric", "integer"], "integer"),
+    // Strings
+    sig("ascii", &["text"], "integer"),
+    sig("chr", &["integer"], "text"),
+    sig("left", &["text", "integer"], "text"),
+    sig("lower", &["text"], "text"),
+    sig("lpad", &["text", "integer", "text"], "text"),
+    sig("regexp_split_to_array", &["text", "text"], "text[]"),
+    sig("regexp_split_to_array", &["text", "text", "text"], "text[]"),
+    sig("repeat", &["text", "integer"], "text"),
+    sig("reverse", &["text"], "text"),
+    sig("right", &["text", "integer"], "text"),
+    sig("rpad", &["text", "integer", "text"], "text"),
+    sig("split_part", &["text", "text", "integer"], "text"),
+    sig("string_agg", &["text", "text"], "text"),
+    sig("string_to_array", &["text", "text"], "text[]"),
+    sig("string_to_array", &["text", "text", "text"], "text[]"),
+    sig("translate", &["text", "text", "text"], "text"),
+    sig("upper", &["text"], "text"),
+    // Arrays
+    sig("array_agg", &["anyelement"], "anyarray"),
+    sig("array_append", &["anyarray", "anyelement"], "anyarray"),
+    sig("array_cat", &["anyarray", "anyarray"], "anyarray"),
+    sig("array_length", &["anyarray", "integer"], "integer"),
+    sig("array_lower", &["anyarray", "integer"], "integer"),
+    sig("array_ndims", &["anyarray"], "integer"),
+    sig("array_position", &["anyarray", "anyelement"], "integer"),
+    sig("array_position", &["anyarray", "anyelement", "integer"], "integer"),
+    sig("array_positions", &["anyarray", "anyelement"], "integer[]"),
+    sig("array_prepend", &["anyelement", "anyarray"], "anyarray"),
+    sig("array_remove", &["anyarray", "anyelement"], "anyarray"),
+    sig("array_replace", &["anyarray", "anyelement", "anyelement"], "anyarray"),
+    sig("array_to_string", &["anyarray", "text"], "text"),
+    sig("array_to_string", &["anyarray", "text", "text"], "text"),
+    sig("array_upper", &["anyarray", "integer"], "integer"),
+    sig("cardinality", &["anyarray"], "integer"),
+    // Date and time
+    sig("age", &["timestamp"], "interval"),
+    sig("age", &["timestamp", "timestamp"], "interval"),
+    sig("date_part", &["text", "timestamp"], "double precision"),
+    sig("date_trunc", &["text", "timestamp"], "timestamp"),
+    sig("justify_days", &["interval"], "interval"),
+    sig("justify_hours", &["interval"], "interval"),
+    sig("justify_interval", &["interval"], "interval"),
+    sig("make_date", &["integer", "integer", "integer"], "date"),
+    sig("make_time", &["integer", "integer", "double precision"], "time"),
+    sig("now", &[], "timestamptz"),
+    sig("to_timestamp", &["double precision"], "timestamptz"),
+    // JSON
+    sig("json_agg", &["anyelement"], "json"),
+    sig("json_array_elements", &["json"], "json"),
+    sig("json_array_elements_text", &["json"], "text"),
+    sig("json_array_length", &["json"], "integer"),
+    sig("json_build_array", &["VARIADIC any"], "json"),
+    sig("json_build_object", &["VARIADIC any"], "json"),
+    sig("json_extract_path", &["json", "VARIADIC text[]"], "json"),
+    sig("json_extract_path_text", &["json", "VARIADIC text[]"], "text"),
+    sig("json_object_agg", &["any", "any"], "json"),
+    sig("json_object_keys", &["json"], "text"),
+    sig("json_populate_record", &["anyelement", "json"], "anyelement"),
+    sig("json_strip_nulls", &["json"], "json"),
+    sig("json_to_record", &["json"], "record"),
+    sig("json_typeof", &["json"], "text"),
+    sig("jsonb_agg", &["anyelement"], "jsonb"),
+    sig("jsonb_array_elements", &["jsonb"], "jsonb"),
+    sig("jsonb_array_length", &["jsonb"], "integer"),
+    sig("jsonb_build_array", &["VARIADIC any"], "jsonb"),
+    sig("jsonb_build_object", &["VARIADIC any"], "jsonb"),
+    sig("jsonb_contained", &["jsonb", "jsonb"], "boolean"),
+    sig("jsonb_contains", &["jsonb", "jsonb"], "boolean"),
+    sig("jsonb_delete", &["jsonb", "text"], "jsonb"),
+    sig("jsonb_delete_path", &["jsonb", "text[]"], "jsonb"),
+    sig("jsonb_insert", &["jsonb", "text[]", "jsonb"], "jsonb"),
+    sig("jsonb_insert", &["jsonb", "text[]", "jsonb", "boolean"], "jsonb"),
+    sig("jsonb_object_agg", &["any", "any"], "jsonb"),
+    sig("jsonb_object_keys", &["jsonb"], "text"),
+    sig("jsonb_path_exists", &["jsonb", "jsonpath"], "boolean"),
+    sig("jsonb_path_exists", &["jsonb", "jsonpath", "jsonb"], "boolean"),
+    sig("jsonb_path_exists", &["jsonb", "jsonpath", "jsonb", "boolean"], "boolean"),
+    sig("jsonb_path_match", &["jsonb", "jsonpath"], "boolean"),
+    sig("jsonb_path_match", &["jsonb", "jsonpath", "jsonb"], "boolean"),
+    sig("jsonb_path_match", &["jsonb", "jsonpath", "jsonb", "boolean"], "boolean"),
+    sig("jsonb_path_query_array", &["jsonb", "jsonpath"], "jsonb"),
+    sig("jsonb_path_query_array", &["jsonb", "jsonpath", "jsonb"], "jsonb"),
+    sig("jsonb_path_query_array", &["jsonb", "jsonpath", "jsonb", "boolean"], "jsonb"),
+    sig("jsonb_path_query_first", &["jsonb", "jsonpath"], "jsonb"),
+    sig("jsonb_path_query_first", &["jsonb", "jsonpath", "jsonb"], "jsonb"),
+    sig("jsonb_path_query_first", &["jsonb", "jsonpath", "jsonb", "boolean"], "jsonb"),
+    sig("jsonb_pretty", &["jsonb"], "text"),
+    sig("jsonb_set", &["jsonb", "text[]", "jsonb"], "jsonb"),
+    sig("jsonb_set", &["jsonb", "text[]", "jsonb", "boolean"], "jsonb"),
+    sig("jsonb_strip_nulls", &["jsonb"], "jsonb"),
+    sig("jsonb_typeof", &["jsonb"], "text"),
+    sig("row_to_json", &["record"], "json"),
+    sig("row_to_json", &["record", "boolean"], "json"),
+    sig("to_json", &["anyelement"], "json"),
+    sig("to_jsonb", &["anyelement"], "jsonb"),
+    // Network addresses
+    sig("broadcast", &["inet"], "inet"),
+    sig("family", &["inet"], "integer"),
+    sig("host", &["inet"], "text"),
+    sig("inet_client_addr", &[], "inet"),
+    sig("inet_client_port", &[], "integer"),
+    sig("inet_server_addr", &[], "inet"),
+    sig("inet_server_port", &[], "integer"),
+    sig("masklen", &["inet"], "integer"),
+    sig("netmask", &["inet"], "inet"),
+    sig("network", &["inet"], "cidr"),
+    sig("network_sub", &["inet", "inet"], "boolean"),
+    sig("network_subeq", &["inet", "inet"], "boolean"),
+    sig("network_sup", &["inet", "inet"], "boolean"),
+    sig("network_supeq", &["inet", "inet"], "boolean"),
+    // XML
+    sig("pg_xml_parse", &["text", "text"], "xml"),
+    sig("pg_xml_serialize", &["xml", "text"], "text"),
+    sig("xml_is_well_formed", &["text"], "boolean"),
+    sig("xml_is_well_formed_content", &["text"], "boolean"),
+    sig("xml_is_well_formed_document", &["text"], "boolean"),
+    sig("xpath", &["text", "xml"], "xml[]"),
+    sig("xpath", &["text", "xml", "text[]"], "xml[]"),
+    sig("xpath_exists", &["text", "xml"], "boolean"),
+    sig("xpath_exists", &["text", "xml", "text[]"], "boolean"),
+    // Ranges
+    sig("daterange", &["date", "date"], "daterange"),
+    sig("daterange", &["date", "date", "text"], "daterange"),
+    sig("int4range", &["integer", "integer"], "int4range"),
+    sig("int4range", &["integer", "integer", "text"], "int4range"),
+    sig("int8range", &["bigint", "bigint"], "int8range"),
+    sig("int8range", &["bigint", "bigint", "text"], "int8range"),
+    sig("isempty", &["anyrange"], "boolean"),
+    sig("lower_inc", &["anyrange"], "boolean"),
+    sig("lower_inf", &["anyrange"], "boolean"),
+    sig("elem_contained_by_range", &["anyelement", "anyrange"], "boolean"),
+    sig("numrange", &["numeric", "numeric"], "numrange"),
+    sig("numrange", &["numeric", "numeric", "text"], "numrange"),
+    sig("range_adjacent", &["anyrange", "anyrange"], "boolean"),
+    sig("range_after", &["anyrange", "anyrange"], "boolean"),
+    sig("range_before", &["anyrange", "anyrange"], "boolean"),
+    sig("range_contained_by", &["anyrange", "anyrange"], "boolean"),
+    sig("range_contains", &["anyrange", "anyrange"], "boolean"),
+    sig("range_contains_elem", &["anyrange", "anyelement"], "boolean"),
+    sig("range_overlaps", &["anyrange", "anyrange"], "boolean"),
+    sig("tsrange", &["timestamp", "timestamp"], "tsrange"),
+    sig("tsrange", &["timestamp", "timestamp", "text"], "tsrange"),
+    sig("tstzrange", &["timestamptz", "timestamptz"], "tstzrange"),
+    sig("tstzrange", &["timestamptz", "timestamptz", "text"], "tstzrange"),
+    sig("upper_inc", &["anyrange"], "boolean"),
+    sig("upper_inf", &["anyrange"], "boolean"),
+    // Aggregates
+    sig("bool_and", &["boolean"], "boolean"),
+    sig("bool_or", &["boolean"], "boolean"),
+    sig("corr", &["double precision", "double precision"], "double precision"),
+    sig("covar_pop", &["double precision", "double precision"], "double precision"),
+    sig("covar_samp", &["double precision", "double precision"], "double precision"),
+    sig("every", &["boolean"], "boolean"),
+    sig("mode", &["anyelement"], "anyelement"),
+    sig("percentile_cont", &["double precision", "double precision"], "double precision"),
+    sig("percentile_disc", &["double precision", "anyelement"], "anyelement"),
+    sig("stddev", &["numeric"], "numeric"),
+    sig("stddev_pop", &["numeric"], "numeric"),
+    sig("stddev_samp", &["numeric"], "numeric"),
+    sig("var_pop", &["numeric"], "numeric"),
+    sig("var_samp", &["numeric"], "numeric"),
+    sig("variance", &["numeric"], "numeric"),
+    // Full text search
+    sig("phraseto_tsquery", &["regconfig", "text"], "tsquery"),
+    sig("plainto_tsquery", &["regconfig", "text"], "tsquery"),
+    sig("to_tsquery", &["regconfig", "text"], "tsquery"),
+    sig("to_tsvector", &["regconfig", "text"], "tsvector"),
+    sig("ts_rank", &["tsvector", "tsquery"], "real"),
+    sig("ts_rank_cd", &["tsvector", "tsquery"], "real"),
+    sig("websearch_to_tsquery", &["regconfig", "text"], "tsquery"),
+    // UUIDs
+    sig("gen_random_uuid", &[], "uuid"),
+    sig("uuid_generate_v4", &[], "uuid"),
+    // System information and administration
+    sig("current_database", &[], "name"),
+    sig("current_schema", &[], "name"),
+    sig("current_schemas", &["boolean"], "name[]"),
+    sig("has_database_privilege", &["name", "text", "text"], "boolean"),
+    sig("has_schema_privilege", &["name", "text", "text"], "boolean"),
+    sig("has_table_privilege", &["name", "text", "text"], "boolean"),
+    sig("pg_advisory_lock", &["bigint"], "void"),
+    sig("pg_advisory_lock", &["integer", "integer"], "void"),
+    sig("pg_advisory_unlock", &["bigint"], "boolean"),
+    sig("pg_advisory_unlock", &["integer", "integer"], "boolean"),
+    sig("pg_advisory_unlock_all", &[], "void"),
+    sig("pg_advisory_xact_lock", &["bigint"], "void"),
+    sig("pg_advisory_xact_lock", &["integer", "integer"], "void"),
+    sig("pg_backend_pid", &[], "integer"),
+    sig("pg_cancel_backend", &["integer"], "boolean"),
+    sig("pg_conf_load_time", &[], "timestamptz"),
+    sig("pg_current_snapshot", &[], "text"),
+    sig("pg_current_wal_flush_lsn", &[], "text"),
+    sig("pg_current_wal_insert_lsn", &[], "text"),
+    sig("pg_current_wal_lsn", &[], "text"),
+    sig("pg_current_xact_id", &[], "bigint"),
+    sig("pg_current_xact_id_if_assigned", &[], "bigint"),
+    sig("pg_database_size", &["name"], "bigint"),
+    sig("pg_get_userbyid", &["oid"], "name"),
+    sig("pg_has_role", &["name", "name", "text"], "boolean"),
+    sig("pg_is_in_recovery", &[], "boolean"),
+    sig("pg_last_wal_receive_lsn", &[], "text"),
+    sig("pg_last_wal_replay_lsn", &[], "text"),
+    sig("pg_postmaster_start_time", &[], "timestamptz"),
+    sig("pg_reload_conf", &[], "boolean"),
+    sig("pg_size_pretty", &["bigint"], "text"),
+    sig("pg_sleep", &["double precision"], "void"),
+    sig("pg_snapshot_xmax", &["text"], "bigint"),
+    sig("pg_snapshot_xmin", &["text"], "bigint"),
+    sig("pg_sleep_for", &["interval"], "void"),
+    sig("pg_sleep_until", &["timestamptz"], "void"),
+    sig("pg_stat_statements_reset", &[], "void"),
+    sig("pg_table_is_visible", &["oid"], "boolean"),
+    sig("pg_terminate_backend", &["integer"], "boolean"),
+    sig("pg_terminate_backend", &["integer", "bigint"], "boolean"),
+    sig("pg_try_advisory_lock", &["bigint"], "boolean"),
+    sig("pg_try_advisory_lock", &["integer", "integer"], "boolean"),
+    sig("pg_try_advisory_xact_lock", &["bigint"], "boolean"),
+    sig("pg_try_advisory_xact_lock", &["integer", "integer"], "boolean"),
+    sig("pg_visible_in_snapshot", &["bigint", "text"], "boolean"),
+    sig("pg_wal_lsn_diff", &["text", "text"], "numeric"),
+    sig("txid_current", &[], "bigint"),
+    sig("txid_current_if_assigned", &[], "bigint"),
+    sig("txid_current_snapshot", &[], "text"),
+    sig("txid_snapshot_xmax", &["text"], "bigint"),
+    sig("txid_snapshot_xmin", &["text"], "bigint"),
+    sig("txid_visible_in_snapshot", &["bigint", "text"], "boolean"),
+    sig("version", &[], "text"),
+    // Comments
+    sig("col_description", &["oid", "integer"], "text"),
+    sig("obj_description", &["oid"], "text"),
+    sig("obj_description", &["oid", "name"], "text"),
+    sig("shobj_description", &["oid", "name"], "text"),
+    // Triggers
+    sig("pg_get_triggerdef", &["oid"], "text"),
+    sig("pg_get_triggerdef", &["oid", "boolean"], "text"),
+];
+
+/// OID of a type named in a signature, including the pseudo-types
+pub fn type_oid(name: &str) -> Option<i32> {
+    Some(match name {
+        "any" => 2276,
+        "anyarray" => 2277,
+        "anyelement" => 2283,
+        "anyrange" => 3831,
+        "bigint" => 20,
+        "boolean" => 16,
+        "cidr" => 650,
+        "date" => 1082,
+        "daterange" => 3912,
+        "double precision" => 701,
+        "inet" => 869,
+        "int4range" => 3904,
+        "int8range" => 3926,
+        "integer" => 23,
+        "integer[]" => 1007,
+        "interval" => 1186,
+        "json" => 114,
+        "jsonb" => 3802,
+        "jsonpath" => 4072,
+        "name" => 19,
+        "name[]" => 1003,
+        "numeric" => 1700,
+        "numrange" => 3906,
+        "oid" => 26,
+        "real" => 700,
+        "record" => 2249,
+        "regconfig" => 3734,
+        "text" => 25,
+        "text[]" => 1009,
+        "time" => 1083,
+        "timestamp" => 1114,
+        "timestamptz" => 1184,
+        "tsquery" => 3615,
+        "tsrange" => 3908,
+        "tstzrange" => 3910,
+        "tsvector" => 3614,
+        "uuid" => 2950,
+        "void" => 2278,
+        "xml" => 142,
+        "xml[]" => 143,
+        _ => return None,
+    })
+}
+
+impl FunctionSignature {
+    /// Stable OID derived from the name and argument types, so comments survive upgrades
+    pub fn oid(&self) -> i64 {
+        // FNV-1a, which unlike the std hasher is fixed across Rust releases
+        let hash = format!("{}({})", self.name, self.args.join(",")).bytes()
+            .fold(0x811c9dc5u32, |hash, byte| (hash ^ u32::from(byte)).wrapping_mul(0x01000193));
+        i64::from(hash % 1_000_000) + 16384
+    }
+
+    fn arg_type(arg: &str) -> &str {
+        arg.strip_prefix("VARIADIC ").unwrap_or(arg)
+    }
+}
+
+/// The signatures as a JSON array of pg_proc fields, read by the pg_proc view
+pub fn functions_json() -> &'static str {
+    static JSON: Lazy<String> = Lazy::new(build_functions_json);
+    &JSON
+}
+
+fn build_functions_json() -> String {
+    let oid_of = |name: &str| type_oid(name).unwrap_or(0);
+    let rows: Vec<serde_json::Value> = FUNCTION_SIGNATURES.iter()
+        .map(|signature| {
+            let variadic = signature.args.last()
+                .and_then(|arg| arg.strip_prefix("VARIADIC "))
+                .map_or(0, |arg| match arg.strip_suffix("[]") {
+                    Some(element) => oid_of(element),
+                    None => oid_of(arg),
+                });
+            let arg_types: Vec<String> = signature.args.iter()
+                .map(|arg| oid_of(FunctionSignature::arg_type(arg)).to_string())
+                .collect();
+            serde_json::json!({
+                "oid": signature.oid(),
+                "proname": signature.name,
+                "provariadic": variadic,
+                "pronargs": signature.args.len(),
+                "prorettype": oid_of(signature.returns),
+                "proargtypes": arg_types.join(" "),
+            })
+        })
+        .collect();
+    serde_json::Value::Array(rows).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_signatures_match_registered_functions() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::functions::register_all_functions(&conn).unwrap();
+
+        let mut oids = HashSet::new();
+        for signature in FUNCTION_SIGNATURES {
+            let registered: bool = conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM pragma_function_list WHERE builtin = 0 AND name = ?1 AND narg IN (?2, -1))",
+                rusqlite::params![signature.name, signature.args.len() as i64],
+                |row| row.get(0),
+            ).unwrap();
+            assert!(registered, "{}/{} is not registered", signature.name, signature.args.len());
+
+            for arg in signature.args.iter().map(|arg| FunctionSignature::arg_type(arg)).chain([signature.returns]) {
+                assert!(type_oid(arg).is_some(), "unknown type {arg} in {}", signature.name);
+            }
+            assert!(oids.insert(signature.oid()), "duplicate oid for {}", signature.name);
+        }
+    }
+}
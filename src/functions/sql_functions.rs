@@ -0,0 +1,172 @@
+//! SQL-language functions created with `CREATE FUNCTION ... LANGUAGE sql`.
+//!
+//! Each is registered on every connection as a scalar function that runs the
+//! function's translated body. The body is read from __pgsqlite_functions on each
+//! call, so a replaced or dropped function is seen by connections that registered
+//! it before.
+//!
+//! `CREATE TEMP FUNCTION` registers a function on the creating session's connection
+//! only, with its body in the closure. It shadows any function of the same name and
+//! argument count there, such as now(), and goes away with the connection.
+
+use rusqlite::functions::{Context, FunctionFlags};
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{Connection, OptionalExtension, Result};
+
+/// Register the SQL functions stored in the database, for a new connection
+pub fn register_stored_functions(conn: &Connection) -> Result<()> {
+    // Databases from before the functions had translated bodies have no SQL functions
+    let has_sql_functions: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM pragma_table_info('__pgsqlite_functions') WHERE name = 'sqlite_body')",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_sql_functions {
+        return Ok(());
+    }
+    let mut stmt = conn.prepare("SELECT name, nargs, volatility FROM __pgsqlite_functions WHERE language = 'sql'")?;
+    let functions: Vec<(String, i32, String)> = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<Result<_>>()?;
+    for (name, nargs, volatility) in functions {
+        register_sql_function(conn, &name, nargs, volatility == "i")?;
+    }
+    Ok(())
+}
+
+/// Register the scalar function that runs a stored SQL function's body.
+/// IMMUTABLE functions are deterministic, so SQLite accepts them in indexes.
+pub fn register_sql_function(conn: &Connection, name: &str, nargs: i32, immutable: bool) -> Result<()> {
+    let mut flags = FunctionFlags::SQLITE_UTF8;
+    if immutable {
+        flags |= FunctionFlags::SQLITE_DETERMINISTIC;
+    }
+    let function = name.to_string();
+    conn.create_scalar_function(name, nargs, flags, move |ctx| call(ctx, &function))
+}
+
+/// A session's `CREATE TEMP FUNCTION`, kept so it can be registered again after
+/// DROP FUNCTION brings back what another one shadowed
+#[derive(Debug, Clone, PartialEq)]
+pub struct TempFunction {
+    pub nargs: i32,
+    pub immutable: bool,
+    pub strict: bool,
+    /// The translated body, with ?1, ?2, ... for the arguments
+    pub body: String,
+}
+
+/// Register a session's temporary function on its connection, shadowing any function
+/// with the same name and argument count
+pub fn register_temp_function(conn: &Connection, name: &str, function: &TempFunction) -> Result<()> {
+    let mut flags = FunctionFlags::SQLITE_UTF8;
+    if function.immutable {
+        flags |= FunctionFlags::SQLITE_DETERMINISTIC;
+    }
+    let body = function.body.clone();
+    let strict = function.strict;
+    conn.create_scalar_function(name, function.nargs, flags, move |ctx| {
+        // SAFETY: as in call(), the body runs on the calling connection during the call
+        let conn = unsafe { ctx.get_connection()? };
+        run_body(ctx, &conn, &body, strict)
+    })
+}
+
+/// Remove a temporary function, bringing back the built-in or SQL function it shadowed.
+/// Temporary functions the session still has must be registered again afterwards.
+pub fn unregister_temp_function(conn: &Connection, name: &str, nargs: i32) -> Result<()> {
+    conn.remove_function(name, nargs)?;
+    // SQLite keeps one function per name and argument count, so the shadowed one is gone too
+    super::register_all_functions(conn)?;
+    register_stored_functions(conn)
+}
+
+/// Run a SQL function's body with the call's arguments bound to ?1, ?2, ...
+fn call(ctx: &Context<'_>, function: &str) -> Result<Value> {
+    // SAFETY: the body runs on the connection that is calling the function, which
+    // outlives the call; nothing here closes it or keeps it past the call
+    let conn = unsafe { ctx.get_connection()? };
+    let stored: Option<(Option<String>, bool)> = conn.prepare_cached(
+        "SELECT sqlite_body, is_strict FROM __pgsqlite_functions WHERE name = ?1 AND language = 'sql'",
+    )?
+        .query_row([function], |row| Ok((row.get(0)?, row.get(1)?)))
+        .optional()?;
+    // Dropped since this connection registered it; SQLite reports unknown functions the same way
+    let Some((Some(body), strict)) = stored else {
+        return Err(rusqlite::Error::UserFunctionError(format!("no such function: {function}").into()));
+    };
+    run_body(ctx, &conn, &body, strict)
+}
+
+/// Run a translated body with the call's arguments bound to ?1, ?2, ...
+fn run_body(ctx: &Context<'_>, conn: &Connection, body: &str, strict: bool) -> Result<Value> {
+    if strict && (0..ctx.len()).any(|i| ctx.get_raw(i) == ValueRef::Null) {
+        return Ok(Value::Null);
+    }
+
+    let mut stmt = conn.prepare(body)?;
+    // Arguments the body doesn't use have no parameter to bind to
+    for i in 1..=stmt.parameter_count().min(ctx.len()) {
+        stmt.raw_bind_parameter(i, Value::from(ctx.get_raw(i - 1)))?;
+    }
+    if stmt.column_count() == 0 {
+        stmt.raw_execute()?;
+        return Ok(Value::Null);
+    }
+    let mut rows = stmt.raw_query();
+    match rows.next()? {
+        Some(row) => row.get(0),
+        None => Ok(Value::Null),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sql_function_runs_stored_body() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE __pgsqlite_functions (oid INTEGER PRIMARY KEY, name TEXT NOT NULL UNIQUE, language TEXT NOT NULL, nargs INTEGER NOT NULL, volatility TEXT NOT NULL, is_strict INTEGER NOT NULL, sqlite_body TEXT);
+             INSERT INTO __pgsqlite_functions VALUES (1, 'add_tax', 'sql', 2, 'i', 0, 'SELECT ?1 * (1 + ?2)');
+             INSERT INTO __pgsqlite_functions VALUES (2, 'greet', 'sql', 1, 'v', 1, 'SELECT ''hello '' || ?1');"
+        ).unwrap();
+        register_stored_functions(&conn).unwrap();
+
+        let taxed: f64 = conn.query_row("SELECT add_tax(10, 0.5)", [], |row| row.get(0)).unwrap();
+        assert_eq!(taxed, 15.0);
+        let greeting: Option<String> = conn.query_row("SELECT greet(NULL)", [], |row| row.get(0)).unwrap();
+        assert_eq!(greeting, None);
+
+        conn.execute("UPDATE __pgsqlite_functions SET sqlite_body = 'SELECT ''hi '' || ?1' WHERE name = 'greet'", []).unwrap();
+        let greeting: String = conn.query_row("SELECT greet('bob')", [], |row| row.get(0)).unwrap();
+        assert_eq!(greeting, "hi bob");
+
+        conn.execute("DELETE FROM __pgsqlite_functions WHERE name = 'greet'", []).unwrap();
+        let err = conn.query_row("SELECT greet('bob')", [], |row| row.get::<_, String>(0)).unwrap_err();
+        assert!(err.to_string().contains("no such function: greet"), "{err}");
+    }
+
+    #[test]
+    fn test_temp_function_shadows_and_restores() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::functions::register_all_functions(&conn).unwrap();
+        conn.execute_batch("CREATE TABLE __pgsqlite_functions (oid INTEGER PRIMARY KEY, name TEXT NOT NULL UNIQUE, language TEXT NOT NULL, nargs INTEGER NOT NULL, volatility TEXT NOT NULL, is_strict INTEGER NOT NULL, sqlite_body TEXT)").unwrap();
+
+        let stub = TempFunction { nargs: 0, immutable: false, strict: false, body: "SELECT '2020-01-01 00:00:00'".to_string() };
+        register_temp_function(&conn, "now", &stub).unwrap();
+        let shadowed = TempFunction { nargs: 1, immutable: true, strict: true, body: "SELECT 'x' || ?1".to_string() };
+        register_temp_function(&conn, "upper", &shadowed).unwrap();
+        let now: String = conn.query_row("SELECT now()", [], |row| row.get(0)).unwrap();
+        assert_eq!(now, "2020-01-01 00:00:00");
+        let upper: Option<String> = conn.query_row("SELECT upper(NULL)", [], |row| row.get(0)).unwrap();
+        assert_eq!(upper, None);
+
+        unregister_temp_function(&conn, "now", 0).unwrap();
+        unregister_temp_function(&conn, "upper", 1).unwrap();
+        let now: String = conn.query_row("SELECT now()", [], |row| row.get(0)).unwrap();
+        assert_ne!(now, "2020-01-01 00:00:00");
+        let upper: String = conn.query_row("SELECT upper('a')", [], |row| row.get(0)).unwrap();
+        assert_eq!(upper, "A");
+    }
+}
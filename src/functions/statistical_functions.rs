@@ -0,0 +1,489 @@
+use rusqlite::{Connection, Result, functions::{Aggregate, Context, FunctionFlags}, types::{Value, ValueRef}};
+use rust_decimal::Decimal;
+use serde_json::Value as JsonValue;
+use std::cmp::Ordering;
+use tracing::debug;
+
+/// Register the statistical functions used for analytics
+///
+/// percentile_cont, percentile_disc and mode are ordered-set aggregates in
+/// PostgreSQL; `percentile_cont(0.5) WITHIN GROUP (ORDER BY x DESC)` is rewritten
+/// to `percentile_cont(0.5, x, 1)`, where the optional last argument requests
+/// descending order.
+pub fn register_statistical_functions(conn: &Connection) -> Result<()> {
+    debug!("Registering statistical functions");
+
+    // width_bucket(operand, low, high, count) - equal-width histogram buckets
+    conn.create_scalar_function(
+        "width_bucket",
+        4,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let (Some(operand), Some(low), Some(high), Some(count)) =
+                (numeric_arg(ctx, 0)?, numeric_arg(ctx, 1)?, numeric_arg(ctx, 2)?, ctx.get::<Option<i64>>(3)?) else {
+                return Ok(None);
+            };
+            width_bucket(operand, low, high, count).map(Some)
+        },
+    )?;
+
+    // width_bucket(operand, thresholds) - buckets bounded by a sorted array
+    conn.create_scalar_function(
+        "width_bucket",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let (Some(operand), Some(thresholds)) = (numeric_arg(ctx, 0)?, ctx.get::<Option<String>>(1)?) else {
+                return Ok(None);
+            };
+            let thresholds: Vec<f64> = serde_json::from_str::<Vec<JsonValue>>(&thresholds)
+                .map_err(|_| user_error("thresholds must be a one-dimensional array"))?
+                .iter()
+                .map(json_number)
+                .collect::<Option<_>>()
+                .ok_or_else(|| user_error("thresholds array must not contain NULLs"))?;
+            // Number of thresholds less than or equal to the operand
+            Ok(Some(thresholds.partition_point(|&threshold| threshold <= operand) as i64))
+        },
+    )?;
+
+    for (name, sample) in [("stddev", true), ("stddev_samp", true), ("stddev_pop", false)] {
+        conn.create_aggregate_function(name, 1, FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC, Variance { sample, sqrt: true })?;
+    }
+    for (name, sample) in [("variance", true), ("var_samp", true), ("var_pop", false)] {
+        conn.create_aggregate_function(name, 1, FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC, Variance { sample, sqrt: false })?;
+    }
+    for (name, statistic) in [("corr", Bivariate::Corr), ("covar_samp", Bivariate::CovarSamp), ("covar_pop", Bivariate::CovarPop)] {
+        conn.create_aggregate_function(name, 2, FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC, statistic)?;
+    }
+
+    for n_args in [2, 3] {
+        conn.create_aggregate_function("percentile_cont", n_args, FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC, Percentile { continuous: true })?;
+        conn.create_aggregate_function("percentile_disc", n_args, FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC, Percentile { continuous: false })?;
+    }
+    for n_args in [1, 2] {
+        conn.create_aggregate_function("mode", n_args, FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC, Mode)?;
+    }
+
+    // every() is the SQL-standard spelling of bool_and()
+    for (name, all) in [("bool_and", true), ("every", true), ("bool_or", false)] {
+        conn.create_aggregate_function(name, 1, FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC, BoolAggregate { all })?;
+    }
+
+    Ok(())
+}
+
+/// Bucket number of operand in count equal-width buckets spanning low..high,
+/// with 0 and count + 1 for values outside the range. low may exceed high.
+fn width_bucket(operand: f64, low: f64, high: f64, count: i64) -> Result<i64> {
+    if count <= 0 {
+        return Err(user_error("count must be greater than zero"));
+    }
+    if low == high {
+        return Err(user_error("lower bound cannot equal upper bound"));
+    }
+    if !low.is_finite() || !high.is_finite() {
+        return Err(user_error("lower and upper bounds must be finite"));
+    }
+
+    // Rounding must not push values just inside the upper bound into the overflow bucket
+    let bucket = if low < high {
+        if operand < low {
+            0
+        } else if operand >= high {
+            count + 1
+        } else {
+            (((operand - low) / (high - low) * count as f64).floor() as i64 + 1).min(count)
+        }
+    } else if operand > low {
+        0
+    } else if operand <= high {
+        count + 1
+    } else {
+        (((low - operand) / (low - high) * count as f64).floor() as i64 + 1).min(count)
+    };
+    Ok(bucket)
+}
+
+/// stddev/variance family, using Welford's online algorithm
+struct Variance {
+    sample: bool,
+    sqrt: bool,
+}
+
+impl Aggregate<(i64, f64, f64), Option<f64>> for Variance {
+    fn init(&self, _: &mut Context<'_>) -> Result<(i64, f64, f64)> {
+        Ok((0, 0.0, 0.0))
+    }
+
+    fn step(&self, ctx: &mut Context<'_>, (count, mean, m2): &mut (i64, f64, f64)) -> Result<()> {
+        if let Some(value) = numeric_arg(ctx, 0)? {
+            *count += 1;
+            let delta = value - *mean;
+            *mean += delta / *count as f64;
+            *m2 += delta * (value - *mean);
+        }
+        Ok(())
+    }
+
+    fn finalize(&self, _: &mut Context<'_>, state: Option<(i64, f64, f64)>) -> Result<Option<f64>> {
+        let Some((count, _, m2)) = state else {
+            return Ok(None);
+        };
+        let divisor = if self.sample { count - 1 } else { count };
+        if divisor <= 0 {
+            return Ok(None);
+        }
+        let variance = (m2 / divisor as f64).max(0.0);
+        Ok(Some(if self.sqrt { variance.sqrt() } else { variance }))
+    }
+}
+
+/// Two-variable statistics over (Y, X) pairs where neither is NULL
+#[derive(Clone, Copy)]
+enum Bivariate {
+    Corr,
+    CovarSamp,
+    CovarPop,
+}
+
+#[derive(Default)]
+struct BivariateState {
+    count: i64,
+    mean_x: f64,
+    mean_y: f64,
+    sxx: f64,
+    syy: f64,
+    sxy: f64,
+}
+
+impl Aggregate<BivariateState, Option<f64>> for Bivariate {
+    fn init(&self, _: &mut Context<'_>) -> Result<BivariateState> {
+        Ok(BivariateState::default())
+    }
+
+    fn step(&self, ctx: &mut Context<'_>, state: &mut BivariateState) -> Result<()> {
+        let (Some(y), Some(x)) = (numeric_arg(ctx, 0)?, numeric_arg(ctx, 1)?) else {
+            return Ok(());
+        };
+        state.count += 1;
+        let n = state.count as f64;
+        let dx = x - state.mean_x;
+        let dy = y - state.mean_y;
+        state.mean_x += dx / n;
+        state.mean_y += dy / n;
+        state.sxx += dx * (x - state.mean_x);
+        state.syy += dy * (y - state.mean_y);
+        state.sxy += dx * (y - state.mean_y);
+        Ok(())
+    }
+
+    fn finalize(&self, _: &mut Context<'_>, state: Option<BivariateState>) -> Result<Option<f64>> {
+        let Some(state) = state.filter(|state| state.count > 0) else {
+            return Ok(None);
+        };
+        Ok(match self {
+            Bivariate::CovarPop => Some(state.sxy / state.count as f64),
+            Bivariate::CovarSamp => (state.count > 1).then(|| state.sxy / (state.count - 1) as f64),
+            // Undefined when either variable is constant
+            Bivariate::Corr => (state.sxx > 0.0 && state.syy > 0.0)
+                .then(|| (state.sxy / (state.sxx * state.syy).sqrt()).clamp(-1.0, 1.0)),
+        })
+    }
+}
+
+/// percentile_cont(fraction, value [, descending]) and percentile_disc(...)
+///
+/// The fraction may also be a JSON array of fractions, giving an array of results.
+struct Percentile {
+    continuous: bool,
+}
+
+#[derive(Default)]
+struct PercentileState {
+    fractions: Option<JsonValue>,
+    descending: bool,
+    values: Vec<Value>,
+}
+
+impl Aggregate<PercentileState, Value> for Percentile {
+    fn init(&self, _: &mut Context<'_>) -> Result<PercentileState> {
+        Ok(PercentileState::default())
+    }
+
+    fn step(&self, ctx: &mut Context<'_>, state: &mut PercentileState) -> Result<()> {
+        if state.fractions.is_none() {
+            // The fraction is a direct argument, constant across the group
+            state.fractions = Some(match ctx.get_raw(0) {
+                ValueRef::Text(text) => serde_json::from_str(&String::from_utf8_lossy(text))
+                    .map_err(|_| user_error(format!("invalid percentile value: {}", String::from_utf8_lossy(text))))?,
+                ValueRef::Null => JsonValue::Null,
+                _ => numeric_arg(ctx, 0)?.map_or(JsonValue::Null, JsonValue::from),
+            });
+            state.descending = ctx.len() > 2 && ctx.get::<Option<bool>>(2)?.unwrap_or(false);
+        }
+        let value = ctx.get::<Value>(1)?;
+        if value != Value::Null {
+            state.values.push(value);
+        }
+        Ok(())
+    }
+
+    fn finalize(&self, _: &mut Context<'_>, state: Option<PercentileState>) -> Result<Value> {
+        let Some(mut state) = state else {
+            return Ok(Value::Null);
+        };
+        if state.values.is_empty() {
+            return Ok(Value::Null);
+        }
+        state.values.sort_by(compare_values);
+        if state.descending {
+            state.values.reverse();
+        }
+
+        match state.fractions.take() {
+            Some(JsonValue::Array(fractions)) => {
+                let results = fractions.iter()
+                    .map(|fraction| match json_number(fraction) {
+                        Some(fraction) => self.percentile(&state.values, fraction).map(|value| value_to_json(&value)),
+                        None => Ok(JsonValue::Null),
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Value::Text(JsonValue::Array(results).to_string()))
+            }
+            Some(fraction) => match json_number(&fraction) {
+                Some(fraction) => self.percentile(&state.values, fraction),
+                None => Ok(Value::Null),
+            },
+            None => Ok(Value::Null),
+        }
+    }
+}
+
+impl Percentile {
+    fn percentile(&self, sorted: &[Value], fraction: f64) -> Result<Value> {
+        if !(0.0..=1.0).contains(&fraction) {
+            return Err(user_error(format!("percentile value {fraction} is not between 0 and 1")));
+        }
+        if !self.continuous {
+            // First value whose position in the ordering reaches the fraction
+            let index = ((fraction * sorted.len() as f64).ceil() as usize).max(1) - 1;
+            return Ok(sorted[index.min(sorted.len() - 1)].clone());
+        }
+
+        let position = fraction * (sorted.len() - 1) as f64;
+        let (lower, upper) = (position.floor() as usize, position.ceil() as usize);
+        let (Some(low), Some(high)) = (value_as_f64(&sorted[lower]), value_as_f64(&sorted[upper])) else {
+            return Err(user_error("percentile_cont requires numeric input"));
+        };
+        Ok(Value::Real(low + (high - low) * (position - lower as f64)))
+    }
+}
+
+/// mode(value [, descending]) - the most frequent value, ties going to the
+/// value that sorts first
+struct Mode;
+
+impl Aggregate<(bool, Vec<Value>), Value> for Mode {
+    fn init(&self, _: &mut Context<'_>) -> Result<(bool, Vec<Value>)> {
+        Ok((false, Vec::new()))
+    }
+
+    fn step(&self, ctx: &mut Context<'_>, (descending, values): &mut (bool, Vec<Value>)) -> Result<()> {
+        if ctx.len() > 1 {
+            *descending = ctx.get::<Option<bool>>(1)?.unwrap_or(false);
+        }
+        let value = ctx.get::<Value>(0)?;
+        if value != Value::Null {
+            values.push(value);
+        }
+        Ok(())
+    }
+
+    fn finalize(&self, _: &mut Context<'_>, state: Option<(bool, Vec<Value>)>) -> Result<Value> {
+        let Some((descending, mut values)) = state else {
+            return Ok(Value::Null);
+        };
+        values.sort_by(compare_values);
+        if descending {
+            values.reverse();
+        }
+
+        let mut best: Option<(&Value, usize)> = None;
+        for run in values.chunk_by(|a, b| compare_values(a, b) == Ordering::Equal) {
+            if best.is_none_or(|(_, count)| run.len() > count) {
+                best = Some((&run[0], run.len()));
+            }
+        }
+        Ok(best.map_or(Value::Null, |(value, _)| value.clone()))
+    }
+}
+
+/// bool_and/every (all) and bool_or (any) over the non-NULL inputs, NULL when there are none
+struct BoolAggregate {
+    all: bool,
+}
+
+impl Aggregate<Option<bool>, Option<bool>> for BoolAggregate {
+    fn init(&self, _: &mut Context<'_>) -> Result<Option<bool>> {
+        Ok(None)
+    }
+
+    fn step(&self, ctx: &mut Context<'_>, result: &mut Option<bool>) -> Result<()> {
+        let value = match ctx.get_raw(0) {
+            ValueRef::Null => return Ok(()),
+            ValueRef::Integer(i) => i != 0,
+            ValueRef::Real(f) => f != 0.0,
+            ValueRef::Text(text) => match String::from_utf8_lossy(text).trim().to_lowercase().as_str() {
+                "t" | "true" | "y" | "yes" | "on" | "1" => true,
+                "f" | "false" | "n" | "no" | "off" | "0" => false,
+                other => return Err(user_error(format!("invalid input syntax for type boolean: \"{other}\""))),
+            },
+            ValueRef::Blob(_) => return Err(user_error("cannot use a binary value as a boolean")),
+        };
+        *result = Some(match *result {
+            Some(current) if self.all => current && value,
+            Some(current) => current || value,
+            None => value,
+        });
+        Ok(())
+    }
+
+    fn finalize(&self, _: &mut Context<'_>, result: Option<Option<bool>>) -> Result<Option<bool>> {
+        Ok(result.flatten())
+    }
+}
+
+/// Read a numeric argument, accepting integers, reals, numeric text and the
+/// 16-byte DECIMAL blobs; NULL gives None
+fn numeric_arg(ctx: &Context<'_>, idx: usize) -> Result<Option<f64>> {
+    match ctx.get_raw(idx) {
+        ValueRef::Null => Ok(None),
+        ValueRef::Integer(i) => Ok(Some(i as f64)),
+        ValueRef::Real(f) => Ok(Some(f)),
+        ValueRef::Text(text) => {
+            let text = String::from_utf8_lossy(text);
+            text.trim().parse::<f64>().map(Some)
+                .map_err(|_| user_error(format!("invalid input syntax for type double precision: \"{text}\"")))
+        }
+        ValueRef::Blob(blob) => value_as_f64(&Value::Blob(blob.to_vec())).map(Some)
+            .ok_or_else(|| user_error("cannot use a binary value as a number")),
+    }
+}
+
+fn value_as_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Integer(i) => Some(*i as f64),
+        Value::Real(f) => Some(*f),
+        Value::Text(text) => text.trim().parse().ok(),
+        Value::Blob(blob) if blob.len() == 16 => {
+            let mut bytes = [0u8; 16];
+            bytes.copy_from_slice(blob);
+            Decimal::deserialize(bytes).to_string().parse().ok()
+        }
+        _ => None,
+    }
+}
+
+/// Order values numerically when both are numbers, otherwise as text
+fn compare_values(a: &Value, b: &Value) -> Ordering {
+    match (value_as_f64(a), value_as_f64(b)) {
+        (Some(x), Some(y)) => x.total_cmp(&y),
+        _ => value_text(a).cmp(&value_text(b)),
+    }
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Integer(i) => i.to_string(),
+        Value::Real(f) => f.to_string(),
+        Value::Text(text) => text.clone(),
+        Value::Blob(blob) => format!("\\x{}", hex::encode(blob)),
+    }
+}
+
+fn value_to_json(value: &Value) -> JsonValue {
+    match value {
+        Value::Integer(i) => JsonValue::from(*i),
+        Value::Real(f) => JsonValue::from(*f),
+        Value::Null => JsonValue::Null,
+        other => JsonValue::String(value_text(other)),
+    }
+}
+
+fn json_number(value: &JsonValue) -> Option<f64> {
+    match value {
+        JsonValue::Number(n) => n.as_f64(),
+        JsonValue::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn user_error(message: impl Into<String>) -> rusqlite::Error {
+    rusqlite::Error::UserFunctionError(message.into().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        register_statistical_functions(&conn).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE t (x INTEGER, y REAL);
+             INSERT INTO t VALUES (1, 2.0), (2, 4.1), (2, 5.9), (3, 8.0), (10, NULL), (NULL, 1.0);"
+        ).unwrap();
+        conn
+    }
+
+    fn query<T: rusqlite::types::FromSql>(conn: &Connection, sql: &str) -> T {
+        conn.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn test_width_bucket() {
+        assert_eq!(width_bucket(5.35, 0.024, 10.06, 5).unwrap(), 3);
+        assert_eq!(width_bucket(-1.0, 0.0, 10.0, 5).unwrap(), 0);
+        assert_eq!(width_bucket(10.0, 0.0, 10.0, 5).unwrap(), 6);
+        assert_eq!(width_bucket(7.0, 10.0, 0.0, 5).unwrap(), 2);
+        assert!(width_bucket(1.0, 0.0, 10.0, 0).is_err());
+        assert!(width_bucket(1.0, 5.0, 5.0, 3).is_err());
+
+        let conn = setup();
+        assert_eq!(query::<i64>(&conn, "SELECT width_bucket(5, '[1, 3, 5, 7]')"), 3);
+        assert_eq!(query::<i64>(&conn, "SELECT width_bucket(0, '[1, 3, 5, 7]')"), 0);
+    }
+
+    #[test]
+    fn test_ordered_set_aggregates() {
+        let conn = setup();
+        assert_eq!(query::<f64>(&conn, "SELECT percentile_cont(0.5, x) FROM t"), 2.0);
+        assert_eq!(query::<f64>(&conn, "SELECT percentile_cont(0.25, x) FROM t"), 2.0);
+        assert!((query::<f64>(&conn, "SELECT percentile_cont(0.9, x) FROM t") - 7.2).abs() < 1e-9);
+        assert_eq!(query::<i64>(&conn, "SELECT percentile_disc(0.5, x) FROM t"), 2);
+        assert_eq!(query::<i64>(&conn, "SELECT percentile_disc(0.9, x, 1) FROM t"), 1);
+        assert_eq!(query::<String>(&conn, "SELECT percentile_disc('[0, 1]', x) FROM t"), "[1,10]");
+        assert_eq!(query::<i64>(&conn, "SELECT mode(x) FROM t"), 2);
+        assert_eq!(query::<Option<i64>>(&conn, "SELECT mode(x) FROM t WHERE x > 100"), None);
+
+        let err = conn.query_row("SELECT percentile_cont(1.5, x) FROM t", [], |row| row.get::<_, f64>(0)).unwrap_err();
+        assert!(err.to_string().contains("percentile value 1.5 is not between 0 and 1"));
+    }
+
+    #[test]
+    fn test_variance_and_correlation() {
+        let conn = setup();
+        assert!((query::<f64>(&conn, "SELECT var_samp(x) FROM t") - 13.3).abs() < 1e-9);
+        assert!((query::<f64>(&conn, "SELECT var_pop(x) FROM t") - 10.64).abs() < 1e-9);
+        assert!((query::<f64>(&conn, "SELECT stddev(x) FROM t") - 13.3_f64.sqrt()).abs() < 1e-9);
+        assert_eq!(query::<Option<f64>>(&conn, "SELECT stddev_samp(x) FROM t WHERE x = 10"), None);
+        assert_eq!(query::<Option<f64>>(&conn, "SELECT stddev_pop(x) FROM t WHERE x = 10"), Some(0.0));
+
+        let corr: f64 = query(&conn, "SELECT corr(y, x) FROM t");
+        assert!(corr > 0.9 && corr <= 1.0, "corr = {corr}");
+        assert!((query::<f64>(&conn, "SELECT covar_pop(y, x) FROM t") - 1.5).abs() < 1e-9);
+        assert_eq!(query::<Option<f64>>(&conn, "SELECT corr(y, x) FROM t WHERE x = 2"), None);
+    }
+}
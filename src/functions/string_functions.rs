@@ -1,4 +1,5 @@
-use rusqlite::{Connection, Result, functions::FunctionFlags};
+use rusqlite::{Connection, Result, functions::FunctionFlags, types::ValueRef};
+use crate::types::ByteaFormat;
 use tracing::debug;
 
 /// Register all PostgreSQL string functions
@@ -31,6 +32,7 @@ pub fn register_string_functions(conn: &Connection) -> Result<()> {
     )?;
     
     // Register string_agg function - this is an aggregate function
+    // ORDER BY inside the call (string_agg(x, ',' ORDER BY y)) is handled by SQLite itself
     conn.create_aggregate_function(
         "string_agg",
         2,
@@ -225,6 +227,29 @@ pub fn register_string_functions(conn: &Connection) -> Result<()> {
         },
     )?;
     
+    // pg_bytea_from_text(value) - the target of '...'::bytea casts; text is read as hex or
+    // escape format input, BLOBs pass through unchanged
+    conn.create_scalar_function(
+        "pg_bytea_from_text",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            match ctx.get_raw(0) {
+                ValueRef::Null => Ok(None),
+                ValueRef::Blob(bytes) => Ok(Some(bytes.to_vec())),
+                ValueRef::Text(text) => {
+                    let text = std::str::from_utf8(text)
+                        .map_err(|e| rusqlite::Error::UserFunctionError(e.into()))?;
+                    ByteaFormat::decode(text)
+                        .map(Some)
+                        .map_err(|e| rusqlite::Error::UserFunctionError(e.into()))
+                }
+                ValueRef::Integer(i) => Ok(Some(i.to_string().into_bytes())),
+                ValueRef::Real(f) => Ok(Some(f.to_string().into_bytes())),
+            }
+        },
+    )?;
+    
     debug!("Successfully registered string functions");
     Ok(())
 }
@@ -239,11 +264,14 @@ impl rusqlite::functions::Aggregate<(Vec<String>, Option<String>), Option<String
     }
     
     fn step(&self, ctx: &mut rusqlite::functions::Context<'_>, agg: &mut (Vec<String>, Option<String>)) -> rusqlite::Result<()> {
-        let value = ctx.get::<String>(0)?;
+        // NULL values are skipped, and a NULL delimiter concatenates without one
+        let Some(value) = ctx.get::<Option<String>>(0)? else {
+            return Ok(());
+        };
         agg.0.push(value);
         
         if agg.1.is_none() {
-            let delimiter = ctx.get::<String>(1)?;
+            let delimiter = ctx.get::<Option<String>>(1)?.unwrap_or_default();
             agg.1 = Some(delimiter);
         }
         
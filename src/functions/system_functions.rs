@@ -1,7 +1,7 @@
 use rusqlite::{Connection, Result, functions::FunctionFlags};
 use tracing::debug;
-use std::path::Path;
 use crate::config::CONFIG;
+use crate::session::transaction_ids::TransactionSnapshot;
 
 /// Register PostgreSQL system information functions
 pub fn register_system_functions(conn: &Connection) -> Result<()> {
@@ -13,10 +13,10 @@ pub fn register_system_functions(conn: &Connection) -> Result<()> {
         0,
         FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
         |_ctx| {
-            // Return a PostgreSQL-compatible version string
+            // Return a PostgreSQL-compatible version string, with the --server-version
             // This format is what SQLAlchemy expects to parse
-            Ok(format!("PostgreSQL 15.0 (pgsqlite {}) on x86_64-pc-linux-gnu, compiled by rustc, 64-bit", 
-                env!("CARGO_PKG_VERSION")))
+            Ok(format!("PostgreSQL {} (pgsqlite {}) on x86_64-pc-linux-gnu, compiled by rustc, 64-bit",
+                CONFIG.server_version, env!("CARGO_PKG_VERSION")))
         },
     )?;
 
@@ -115,6 +115,7 @@ pub fn register_system_functions(conn: &Connection) -> Result<()> {
     )?;
     
     // pg_backend_pid() - Returns the backend process ID
+    // Session connections override this with the session's own pid in register_session_functions
     conn.create_scalar_function(
         "pg_backend_pid",
         0,
@@ -125,34 +126,241 @@ pub fn register_system_functions(conn: &Connection) -> Result<()> {
         },
     )?;
 
+    // pgsqlite_session_user() / pgsqlite_application_name() - Who made a change, recorded by audit triggers
+    // Session connections override these in register_session_functions
+    conn.create_scalar_function(
+        "pgsqlite_session_user",
+        0,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_INNOCUOUS,
+        |_ctx| Ok("postgres".to_string()),
+    )?;
+    conn.create_scalar_function(
+        "pgsqlite_application_name",
+        0,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_INNOCUOUS,
+        |_ctx| Ok(String::new()),
+    )?;
+
+    // pgsqlite_soft_delete_now() - The deleted_at a soft DELETE stores, in the microseconds TIMESTAMP columns hold
+    conn.create_scalar_function(
+        "pgsqlite_soft_delete_now",
+        0,
+        FunctionFlags::SQLITE_UTF8,
+        |_ctx| Ok(chrono::Utc::now().timestamp_micros()),
+    )?;
+
+    // pgsqlite_mask(value, method, pattern) - A masked column's value as roles other than --admin-users read it
+    conn.create_scalar_function(
+        "pgsqlite_mask",
+        3,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let Some(value) = ctx.get::<Option<String>>(0)? else {
+                return Ok(None);
+            };
+            let method: String = ctx.get(1)?;
+            let pattern: Option<String> = ctx.get(2)?;
+            crate::query::MaskHandler::mask_value(&value, &method, pattern.as_deref())
+                .map(Some)
+                .map_err(|e| rusqlite::Error::UserFunctionError(e.into()))
+        },
+    )?;
+
+    // pg_cancel_backend(pid) - Interrupts the statement another session is running
+    conn.create_scalar_function(
+        "pg_cancel_backend",
+        1,
+        FunctionFlags::SQLITE_UTF8,
+        |ctx| {
+            let pid: i32 = ctx.get(0)?;
+            Ok(crate::session::backend_registry::cancel_backend(pid))
+        },
+    )?;
+
+    // pg_terminate_backend(pid [, timeout]) - Interrupts another session and closes its connection;
+    // with a timeout in milliseconds, waits for the session to end
+    for n_args in [1, 2] {
+        conn.create_scalar_function(
+            "pg_terminate_backend",
+            n_args,
+            FunctionFlags::SQLITE_UTF8,
+            |ctx| {
+                let pid: i32 = ctx.get(0)?;
+                let timeout = if ctx.len() > 1 { ctx.get::<Option<i64>>(1)?.unwrap_or(0) } else { 0 };
+                if timeout < 0 {
+                    return Err(rusqlite::Error::UserFunctionError("\"timeout\" must not be negative".into()));
+                }
+                let timeout = std::time::Duration::from_millis(timeout as u64);
+                Ok(crate::session::backend_registry::terminate_backend(pid, timeout))
+            },
+        )?;
+    }
+
+    // pgsqlite_backends() - Connected sessions as JSON, read by the pg_stat_activity view
+    conn.create_scalar_function(
+        "pgsqlite_backends",
+        0,
+        FunctionFlags::SQLITE_UTF8,
+        |_ctx| Ok(crate::session::backend_registry::backends_json()),
+    )?;
+
+    // pgsqlite_functions() - Signatures of the registered functions as JSON, read by the pg_proc view
+    conn.create_scalar_function(
+        "pgsqlite_functions",
+        0,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |_ctx| Ok(super::signatures::functions_json()),
+    )?;
+
+    // pgsqlite_progress(kind) - Running COPY, VACUUM, import, retention, CREATE INDEX or CLUSTER operations as JSON, read by the pg_stat_progress_* views
+    conn.create_scalar_function(
+        "pgsqlite_progress",
+        1,
+        FunctionFlags::SQLITE_UTF8,
+        |ctx| {
+            let kind: String = ctx.get(0)?;
+            let command = crate::query::progress::ProgressCommand::from_name(&kind)
+                .ok_or_else(|| rusqlite::Error::UserFunctionError(format!("unknown progress kind: {kind}").into()))?;
+            Ok(crate::query::progress::progress_json(command))
+        },
+    )?;
+
+    // pgsqlite_stat_statements() - Per-statement execution statistics as JSON, read by the pg_stat_statements view
+    conn.create_scalar_function(
+        "pgsqlite_stat_statements",
+        0,
+        FunctionFlags::SQLITE_UTF8,
+        |_ctx| Ok(crate::query::statement_stats::statement_stats_json()),
+    )?;
+
+    // pg_stat_statements_reset() - Discards all statement statistics
+    conn.create_scalar_function(
+        "pg_stat_statements_reset",
+        0,
+        FunctionFlags::SQLITE_UTF8,
+        |_ctx| {
+            crate::query::statement_stats::reset();
+            Ok(None::<String>)
+        },
+    )?;
+
     // pg_is_in_recovery() - Returns whether server is in recovery mode
     conn.create_scalar_function(
         "pg_is_in_recovery",
         0,
         FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
         |_ctx| {
-            // SQLite is never in recovery mode
-            Ok(0i32) // false in SQLite boolean representation
+            // A --read-only server serves a database some other server writes, like a standby
+            Ok(CONFIG.read_only as i32) // SQLite boolean representation
         },
     )?;
 
-    // pgsqlite_datname() - Returns logical database name (filename basename)
+    // Session connections make these cancellable in register_sleep_functions
+    register_sleep_functions(conn, || false)?;
+
+    // txid_current() and pg_current_xact_id() - A new transaction id per call; session
+    // connections keep one per transaction
+    for name in ["txid_current", "pg_current_xact_id"] {
+        conn.create_scalar_function(
+            name,
+            0,
+            FunctionFlags::SQLITE_UTF8,
+            |_ctx| Ok(crate::session::transaction_ids::allocate_transaction_id()),
+        )?;
+    }
+    // txid_current_if_assigned() - NULL, as only session connections keep a transaction's id
+    for name in ["txid_current_if_assigned", "pg_current_xact_id_if_assigned"] {
+        conn.create_scalar_function(name, 0, FunctionFlags::SQLITE_UTF8, |_ctx| Ok(None::<i64>))?;
+    }
+    // txid_current_snapshot() - The transactions other sessions are running; session
+    // connections keep one snapshot per transaction
+    for name in ["txid_current_snapshot", "pg_current_snapshot"] {
+        conn.create_scalar_function(
+            name,
+            0,
+            FunctionFlags::SQLITE_UTF8,
+            |_ctx| Ok(crate::session::backend_registry::transaction_snapshot(None).to_string()),
+        )?;
+    }
+
+    // txid_snapshot_xmin()/txid_snapshot_xmax() and the pg_snapshot_* names
+    type SnapshotBound = fn(&TransactionSnapshot) -> i64;
+    let bounds: [(&str, SnapshotBound); 4] = [
+        ("txid_snapshot_xmin", |snapshot| snapshot.xmin),
+        ("txid_snapshot_xmax", |snapshot| snapshot.xmax),
+        ("pg_snapshot_xmin", |snapshot| snapshot.xmin),
+        ("pg_snapshot_xmax", |snapshot| snapshot.xmax),
+    ];
+    for (name, bound) in bounds {
+        conn.create_scalar_function(
+            name,
+            1,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            move |ctx| Ok(snapshot_arg(ctx, 0)?.map(|snapshot| bound(&snapshot))),
+        )?;
+    }
+    // txid_visible_in_snapshot(xid, snapshot) - Whether the transaction had committed when the snapshot was taken
+    for name in ["txid_visible_in_snapshot", "pg_visible_in_snapshot"] {
+        conn.create_scalar_function(
+            name,
+            2,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| {
+                let (Some(xid), Some(snapshot)) = (ctx.get::<Option<i64>>(0)?, snapshot_arg(ctx, 1)?) else {
+                    return Ok(None);
+                };
+                Ok(Some(snapshot.is_visible(xid)))
+            },
+        )?;
+    }
+
+    // pg_current_wal_lsn() and friends - A position every commit advances, as SQLite's
+    // WAL doesn't expose one that increases across checkpoints
+    for name in ["pg_current_wal_lsn", "pg_current_wal_insert_lsn", "pg_current_wal_flush_lsn"] {
+        conn.create_scalar_function(
+            name,
+            0,
+            FunctionFlags::SQLITE_UTF8,
+            |_ctx| Ok(crate::session::transaction_ids::current_wal_lsn()),
+        )?;
+    }
+    // pg_last_wal_receive_lsn()/pg_last_wal_replay_lsn() - NULL, as the server is never a standby
+    for name in ["pg_last_wal_receive_lsn", "pg_last_wal_replay_lsn"] {
+        conn.create_scalar_function(name, 0, FunctionFlags::SQLITE_UTF8, |_ctx| Ok(None::<String>))?;
+    }
+    // pg_wal_lsn_diff(lsn, lsn) - The distance between two positions
+    conn.create_scalar_function(
+        "pg_wal_lsn_diff",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let (Some(left), Some(right)) = (lsn_arg(ctx, 0)?, lsn_arg(ctx, 1)?) else {
+                return Ok(None);
+            };
+            Ok(Some(left - right))
+        },
+    )?;
+
+    // pgsqlite_datname() - Returns logical database name: its --databases name, else the
+    // --database filename basename
+    let datname = conn.path()
+        .and_then(|path| CONFIG.database_name_for_path(path))
+        .unwrap_or_else(|| CONFIG.default_database_name());
     conn.create_scalar_function(
         "pgsqlite_datname",
         0,
         FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        move |_ctx| Ok(datname.clone()),
+    )?;
+
+    // pgsqlite_databases() - JSON array of the database names clients can connect to
+    conn.create_scalar_function(
+        "pgsqlite_databases",
+        0,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
         |_ctx| {
-            let db_path = &CONFIG.database;
-            if db_path == ":memory:" || CONFIG.in_memory {
-                return Ok("memory".to_string());
-            }
-            let p = Path::new(db_path);
-            // Prefer stem (without extension); fallback to file_name; then to full path string
-            let name = p.file_stem()
-                .and_then(|s| s.to_str())
-                .or_else(|| p.file_name().and_then(|s| s.to_str()))
-                .unwrap_or(db_path);
-            Ok(name.to_string())
+            serde_json::to_string(&CONFIG.database_names())
+                .map_err(|e| rusqlite::Error::UserFunctionError(Box::new(e)))
         },
     )?;
 
@@ -162,10 +370,12 @@ pub fn register_system_functions(conn: &Connection) -> Result<()> {
         1,
         FunctionFlags::SQLITE_UTF8,
         |ctx| {
-            let _db_name: String = ctx.get(0)?;
-            // For SQLite, we can't easily get the database size without file access
-            // Return a reasonable default size
-            Ok(8192i64) // 8KB minimum SQLite database size
+            let db_name: String = ctx.get(0)?;
+            // Size of the database's file; in-memory databases get the 8KB minimum SQLite database size
+            let size = CONFIG.database_path(&db_name)
+                .and_then(|path| std::fs::metadata(path).ok())
+                .map_or(8192, |metadata| metadata.len() as i64);
+            Ok(size)
         },
     )?;
     
@@ -174,28 +384,30 @@ pub fn register_system_functions(conn: &Connection) -> Result<()> {
         "pg_postmaster_start_time",
         0,
         FunctionFlags::SQLITE_UTF8,
-        |_ctx| {
-            // Return current timestamp as a reasonable approximation
-            use chrono::{DateTime, Utc};
-            let now: DateTime<Utc> = Utc::now();
-            Ok(now.format("%Y-%m-%d %H:%M:%S.%f%:z").to_string())
-        },
+        |_ctx| Ok(crate::config::start_time().format("%Y-%m-%d %H:%M:%S.%f%:z").to_string()),
     )?;
     
-    // pg_conf_load_time() - Returns configuration load time
+    // pg_conf_load_time() - Returns when the configuration was last loaded or reloaded
     conn.create_scalar_function(
         "pg_conf_load_time",
         0,
         FunctionFlags::SQLITE_UTF8,
+        |_ctx| Ok(crate::config::load_time().format("%Y-%m-%d %H:%M:%S.%f%:z").to_string()),
+    )?;
+
+    // pg_reload_conf() - Asks the server to reread its configuration file, as SIGHUP does
+    conn.create_scalar_function(
+        "pg_reload_conf",
+        0,
+        FunctionFlags::SQLITE_UTF8,
         |_ctx| {
-            // Return current timestamp
-            use chrono::{DateTime, Utc};
-            let now: DateTime<Utc> = Utc::now();
-            Ok(now.format("%Y-%m-%d %H:%M:%S.%f%:z").to_string())
+            crate::config::request_reload();
+            Ok(true)
         },
     )?;
     
     // inet_client_addr() - Returns client's IP address
+    // Session connections return the addresses of their connection in register_session_functions
     conn.create_scalar_function(
         "inet_client_addr",
         0,
@@ -312,6 +524,188 @@ pub fn register_system_functions(conn: &Connection) -> Result<()> {
     Ok(())
 }
 
+/// Register pg_sleep(seconds), pg_sleep_for(interval) and pg_sleep_until(timestamp)
+///
+/// Standalone `SELECT pg_sleep(...)` is intercepted by SleepHandler and awaited
+/// asynchronously; these are only reached when the call is part of a larger statement.
+/// SQLite's progress handler doesn't run while a function sleeps, so they sleep in
+/// slices and abort the statement once `should_abort` says it was canceled.
+pub fn register_sleep_functions<F>(conn: &Connection, should_abort: F) -> Result<()>
+where
+    F: Fn() -> bool + Clone + Send + Sync + 'static,
+{
+    let abort = should_abort.clone();
+    conn.create_scalar_function(
+        "pg_sleep",
+        1,
+        FunctionFlags::SQLITE_UTF8,
+        move |ctx| {
+            if let Ok(Some(seconds)) = ctx.get::<Option<f64>>(0) {
+                sleep(crate::query::SleepHandler::seconds_to_duration(seconds), &abort)?;
+            }
+            Ok(None::<String>)
+        },
+    )?;
+
+    let abort = should_abort.clone();
+    conn.create_scalar_function(
+        "pg_sleep_for",
+        1,
+        FunctionFlags::SQLITE_UTF8,
+        move |ctx| {
+            if let Ok(Some(interval)) = ctx.get::<Option<String>>(0)
+                && let Some(duration) = crate::query::SleepHandler::parse_interval(&interval) {
+                    sleep(duration, &abort)?;
+                }
+            Ok(None::<String>)
+        },
+    )?;
+
+    conn.create_scalar_function(
+        "pg_sleep_until",
+        1,
+        FunctionFlags::SQLITE_UTF8,
+        move |ctx| {
+            if let Ok(Some(timestamp)) = ctx.get::<Option<String>>(0)
+                && let Some(duration) = crate::query::SleepHandler::duration_until(&timestamp) {
+                    sleep(duration, &should_abort)?;
+                }
+            Ok(None::<String>)
+        },
+    )?;
+    Ok(())
+}
+
+/// Sleep for `duration`, failing like an interrupted statement once `should_abort` is true
+fn sleep(duration: std::time::Duration, should_abort: &impl Fn() -> bool) -> Result<()> {
+    let deadline = std::time::Instant::now() + duration;
+    loop {
+        if should_abort() {
+            return Err(rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_INTERRUPT), None));
+        }
+        let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+        if remaining.is_zero() {
+            return Ok(());
+        }
+        std::thread::sleep(remaining.min(crate::query::sleep_handler::CANCEL_CHECK_INTERVAL));
+    }
+}
+
+/// Register functions whose result depends on the session owning the connection
+/// A txid_snapshot argument in its `xmin:xmax:xip,...` text form, None if it is NULL
+fn snapshot_arg(ctx: &rusqlite::functions::Context, idx: usize) -> Result<Option<TransactionSnapshot>> {
+    let Some(text) = ctx.get::<Option<String>>(idx)? else {
+        return Ok(None);
+    };
+    TransactionSnapshot::parse(&text).map(Some).ok_or_else(|| rusqlite::Error::UserFunctionError(
+        format!("invalid input syntax for type txid_snapshot: \"{text}\"").into(),
+    ))
+}
+
+/// A pg_lsn argument, None if it is NULL
+fn lsn_arg(ctx: &rusqlite::functions::Context, idx: usize) -> Result<Option<i64>> {
+    let Some(text) = ctx.get::<Option<String>>(idx)? else {
+        return Ok(None);
+    };
+    crate::session::transaction_ids::parse_lsn(&text).map(Some).ok_or_else(|| rusqlite::Error::UserFunctionError(
+        format!("invalid input syntax for type pg_lsn: \"{text}\"").into(),
+    ))
+}
+
+pub fn register_session_functions(conn: &Connection, session_id: uuid::Uuid) -> Result<()> {
+    // pg_backend_pid() - The session's backend pid, as listed in pg_stat_activity
+    conn.create_scalar_function(
+        "pg_backend_pid",
+        0,
+        FunctionFlags::SQLITE_UTF8,
+        move |_ctx| {
+            Ok(crate::session::backend_registry::backend_pid_of(&session_id)
+                .unwrap_or(std::process::id() as i32))
+        },
+    )?;
+
+    // pgsqlite_session_user() / pgsqlite_application_name() - The user and application_name the session connected with
+    conn.create_scalar_function(
+        "pgsqlite_session_user",
+        0,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_INNOCUOUS,
+        move |_ctx| {
+            Ok(crate::session::backend_registry::identity_of(&session_id)
+                .map_or_else(|| "postgres".to_string(), |(user, _)| user))
+        },
+    )?;
+    conn.create_scalar_function(
+        "pgsqlite_application_name",
+        0,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_INNOCUOUS,
+        move |_ctx| {
+            Ok(crate::session::backend_registry::identity_of(&session_id)
+                .map(|(_, application_name)| application_name)
+                .unwrap_or_default())
+        },
+    )?;
+
+    // inet_client_addr(), inet_client_port(), inet_server_addr(), inet_server_port() - The
+    // addresses of the session's connection, NULL over a Unix socket or named pipe
+    let addresses = move || crate::session::backend_registry::addresses_of(&session_id).unwrap_or_default();
+    conn.create_scalar_function(
+        "inet_client_addr",
+        0,
+        FunctionFlags::SQLITE_UTF8,
+        move |_ctx| Ok(addresses().0.map(|addr| addr.ip().to_string())),
+    )?;
+    conn.create_scalar_function(
+        "inet_client_port",
+        0,
+        FunctionFlags::SQLITE_UTF8,
+        move |_ctx| Ok(addresses().0.map(|addr| addr.port())),
+    )?;
+    conn.create_scalar_function(
+        "inet_server_addr",
+        0,
+        FunctionFlags::SQLITE_UTF8,
+        move |_ctx| Ok(addresses().1.map(|addr| addr.ip().to_string())),
+    )?;
+    conn.create_scalar_function(
+        "inet_server_port",
+        0,
+        FunctionFlags::SQLITE_UTF8,
+        move |_ctx| Ok(addresses().1.map(|addr| addr.port())),
+    )?;
+
+    // txid_current() - The id of the session's transaction, the same for each call until it ends
+    for name in ["txid_current", "pg_current_xact_id"] {
+        conn.create_scalar_function(
+            name,
+            0,
+            FunctionFlags::SQLITE_UTF8,
+            move |_ctx| {
+                Ok(crate::session::backend_registry::transaction_id_of(&session_id)
+                    .unwrap_or_else(crate::session::transaction_ids::allocate_transaction_id))
+            },
+        )?;
+    }
+    for name in ["txid_current_if_assigned", "pg_current_xact_id_if_assigned"] {
+        conn.create_scalar_function(
+            name,
+            0,
+            FunctionFlags::SQLITE_UTF8,
+            move |_ctx| Ok(crate::session::backend_registry::assigned_transaction_id(&session_id)),
+        )?;
+    }
+    // txid_current_snapshot() - Taken on the first call of a transaction, the same until it ends
+    for name in ["txid_current_snapshot", "pg_current_snapshot"] {
+        conn.create_scalar_function(
+            name,
+            0,
+            FunctionFlags::SQLITE_UTF8,
+            move |_ctx| Ok(crate::session::backend_registry::transaction_snapshot(Some(&session_id)).to_string()),
+        )?;
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -362,6 +756,26 @@ mod tests {
         assert!(pid > 0);
     }
     
+    #[test]
+    fn test_pg_sleep_functions() {
+        let conn = Connection::open_in_memory().unwrap();
+        register_system_functions(&conn).unwrap();
+        
+        let start = std::time::Instant::now();
+        let result: Option<String> = conn.query_row("SELECT pg_sleep(0.05)", [], |row| row.get(0)).unwrap();
+        assert!(result.is_none());
+        assert!(start.elapsed() >= std::time::Duration::from_millis(50));
+        
+        let result: Option<String> = conn.query_row("SELECT pg_sleep_for('10 milliseconds')", [], |row| row.get(0)).unwrap();
+        assert!(result.is_none());
+        
+        // NULL and past timestamps return immediately
+        let result: Option<String> = conn.query_row("SELECT pg_sleep(NULL)", [], |row| row.get(0)).unwrap();
+        assert!(result.is_none());
+        let result: Option<String> = conn.query_row("SELECT pg_sleep_until('2000-01-01 00:00:00')", [], |row| row.get(0)).unwrap();
+        assert!(result.is_none());
+    }
+    
     #[test]
     fn test_pg_is_in_recovery() {
         let conn = Connection::open_in_memory().unwrap();
@@ -1,9 +1,26 @@
-use rusqlite::{Connection, Result};
+use std::ffi::c_int;
+use std::marker::PhantomData;
+use rusqlite::{ffi, Connection, Error, Result};
+use rusqlite::types::Value;
+use rusqlite::vtab::{eponymous_only_module, Context, Filters, IndexInfo, VTab, VTabConfig, VTabConnection, VTabCursor};
+use serde_json::Value as JsonValue;
+use super::series_vtab::hidden_arguments;
+
+/// The hidden array and at columns follow the element
+const COLUMN_ARRAY: c_int = 1;
+const COLUMN_AT: c_int = 2;
 
 /// Register unnest support functions with SQLite
 /// Note: The main unnest functionality is handled by UnnestTranslator
 /// which converts unnest() calls to json_each() equivalents
+///
+/// `__pgsqlite_unnest(array [, at])` is the table of a JSON array's elements for
+/// set-returning calls in select lists, with a single column whose name can't clash
+/// with the user's. `at` picks the at-th element alone; the last array is kept
+/// parsed, so picking its elements one by one stays linear.
 pub fn register_unnest_vtab(conn: &Connection) -> Result<()> {
+    conn.create_module(c"__pgsqlite_unnest", eponymous_only_module::<UnnestTab>(), None::<()>)?;
+
     // Register a helper function for array validation
     conn.create_scalar_function(
         "validate_array_for_unnest",
@@ -25,6 +42,105 @@ pub fn register_unnest_vtab(conn: &Connection) -> Result<()> {
     Ok(())
 }
 
+#[repr(C)]
+struct UnnestTab {
+    /// Base class, must be first
+    base: ffi::sqlite3_vtab,
+}
+
+unsafe impl<'vtab> VTab<'vtab> for UnnestTab {
+    type Aux = ();
+    type Cursor = UnnestCursor<'vtab>;
+
+    fn connect(db: &mut VTabConnection, _aux: Option<&()>, _args: &[&[u8]]) -> Result<(String, Self)> {
+        db.config(VTabConfig::Innocuous)?;
+        Ok((
+            "CREATE TABLE x(__pgsqlite_value, __pgsqlite_array HIDDEN, __pgsqlite_at HIDDEN)".to_string(),
+            UnnestTab { base: ffi::sqlite3_vtab::default() },
+        ))
+    }
+
+    fn best_index(&self, info: &mut IndexInfo) -> Result<()> {
+        hidden_arguments(info, COLUMN_ARRAY, COLUMN_AT)?;
+        Ok(())
+    }
+
+    fn open(&mut self) -> Result<UnnestCursor<'_>> {
+        Ok(UnnestCursor {
+            base: ffi::sqlite3_vtab_cursor::default(),
+            array: None,
+            elements: Vec::new(),
+            position: 0,
+            end: 0,
+            phantom: PhantomData,
+        })
+    }
+}
+
+#[repr(C)]
+struct UnnestCursor<'vtab> {
+    /// Base class, must be first
+    base: ffi::sqlite3_vtab_cursor,
+    /// The array text `elements` were parsed from
+    array: Option<String>,
+    elements: Vec<JsonValue>,
+    /// Index of the current element, and the one past the last to return
+    position: usize,
+    end: usize,
+    phantom: PhantomData<&'vtab UnnestTab>,
+}
+
+unsafe impl VTabCursor for UnnestCursor<'_> {
+    fn filter(&mut self, idx_num: c_int, _idx_str: Option<&str>, args: &Filters<'_>) -> Result<()> {
+        if idx_num & 0b1 == 0 {
+            return Err(Error::ModuleError("unnest requires an array argument".to_string()));
+        }
+        let array: Option<String> = args.get(0)?;
+        if array != self.array {
+            self.elements = match array.as_deref().map(serde_json::from_str::<JsonValue>) {
+                None => Vec::new(),
+                Some(Ok(JsonValue::Array(elements))) => elements,
+                Some(_) => return Err(Error::ModuleError(format!("malformed array literal: \"{}\"", array.unwrap_or_default()))),
+            };
+            self.array = array;
+        }
+        (self.position, self.end) = (0, self.elements.len());
+        if idx_num & 0b10 != 0 {
+            let at: Option<i64> = args.get(1)?;
+            match at.and_then(|at| usize::try_from(at).ok()).filter(|&at| (1..=self.elements.len()).contains(&at)) {
+                Some(at) => (self.position, self.end) = (at - 1, at),
+                None => self.position = self.end,
+            }
+        }
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.position += 1;
+        Ok(())
+    }
+
+    fn eof(&self) -> bool {
+        self.position >= self.end
+    }
+
+    /// Elements come out as json_each() returns them
+    fn column(&self, ctx: &mut Context, _i: c_int) -> Result<()> {
+        let value = match &self.elements[self.position] {
+            JsonValue::Null => Value::Null,
+            JsonValue::Bool(b) => Value::Integer(*b as i64),
+            JsonValue::Number(n) => n.as_i64().map_or_else(|| Value::Real(n.as_f64().unwrap_or_default()), Value::Integer),
+            JsonValue::String(s) => Value::Text(s.clone()),
+            other => Value::Text(other.to_string()),
+        };
+        ctx.set_result(&value)
+    }
+
+    fn rowid(&self) -> Result<i64> {
+        Ok(self.position as i64 + 1)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -87,4 +203,24 @@ mod tests {
         
         assert_eq!(rows, vec!["a", "b", "c"]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_unnest_table() {
+        let conn = Connection::open_in_memory().unwrap();
+        register_unnest_vtab(&conn).unwrap();
+        let elements = |sql: &str| -> Result<Vec<Value>> {
+            let mut stmt = conn.prepare(sql)?;
+            let values = stmt.query_map([], |row| row.get(0))?.collect();
+            values
+        };
+
+        assert_eq!(
+            elements(r#"SELECT __pgsqlite_value FROM __pgsqlite_unnest('[1, "a", null, true, [2]]')"#).unwrap(),
+            [Value::Integer(1), Value::Text("a".into()), Value::Null, Value::Integer(1), Value::Text("[2]".into())]
+        );
+        assert_eq!(elements("SELECT __pgsqlite_value FROM __pgsqlite_unnest('[1, 2, 3]', 2)").unwrap(), [Value::Integer(2)]);
+        assert!(elements("SELECT __pgsqlite_value FROM __pgsqlite_unnest('[1, 2, 3]', 4)").unwrap().is_empty());
+        assert!(elements("SELECT __pgsqlite_value FROM __pgsqlite_unnest(NULL)").unwrap().is_empty());
+        assert!(elements("SELECT __pgsqlite_value FROM __pgsqlite_unnest('{1,2}')").is_err());
+    }
+}
@@ -0,0 +1,148 @@
+use rusqlite::{Connection, Result, functions::{Context, FunctionFlags}};
+use tracing::debug;
+use crate::types::{ArrayHandler, XmlOption, xml};
+
+/// Register the xml functions: the targets of XMLPARSE, XMLSERIALIZE and `::xml` casts,
+/// the well-formedness checks and xpath()
+pub fn register_xml_functions(conn: &Connection) -> Result<()> {
+    debug!("Registering XML functions");
+
+    // pg_xml_parse(text, option) - XMLPARSE(DOCUMENT|CONTENT text) and '...'::xml
+    conn.create_scalar_function(
+        "pg_xml_parse",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let Some(text) = ctx.get::<Option<String>>(0)? else {
+                return Ok(None);
+            };
+            xml::validate(&text, xml_option(ctx, 1)?).map_err(user_error)?;
+            Ok(Some(text))
+        },
+    )?;
+
+    // pg_xml_serialize(xml, option) - XMLSERIALIZE(DOCUMENT|CONTENT xml AS text)
+    conn.create_scalar_function(
+        "pg_xml_serialize",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let Some(text) = ctx.get::<Option<String>>(0)? else {
+                return Ok(None);
+            };
+            if xml_option(ctx, 1)? == XmlOption::Document && !xml::is_well_formed(&text, XmlOption::Document) {
+                return Err(user_error("not an XML document"));
+            }
+            Ok(Some(text))
+        },
+    )?;
+
+    // xml_is_well_formed() checks content, the default XML OPTION
+    let checks = [
+        ("xml_is_well_formed", XmlOption::Content),
+        ("xml_is_well_formed_content", XmlOption::Content),
+        ("xml_is_well_formed_document", XmlOption::Document),
+    ];
+    for (name, option) in checks {
+        conn.create_scalar_function(
+            name,
+            1,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            move |ctx| Ok(ctx.get::<Option<String>>(0)?.map(|text| xml::is_well_formed(&text, option))),
+        )?;
+    }
+
+    // xpath(path, xml [, nsarray]) returns the matches as an xml array, stored as JSON;
+    // xpath_exists() whether there are any
+    for arity in [2, 3] {
+        conn.create_scalar_function(
+            "xpath",
+            arity,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| {
+                let Some(matches) = evaluate_xpath(ctx)? else {
+                    return Ok(None);
+                };
+                serde_json::to_string(&matches)
+                    .map(Some)
+                    .map_err(|e| user_error(format!("failed to encode xpath result: {e}")))
+            },
+        )?;
+        conn.create_scalar_function(
+            "xpath_exists",
+            arity,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| Ok(evaluate_xpath(ctx)?.map(|matches| !matches.is_empty())),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn xml_option(ctx: &Context, idx: usize) -> Result<XmlOption> {
+    let name = ctx.get::<String>(idx)?;
+    XmlOption::from_name(&name).ok_or_else(|| user_error(format!("unrecognized XML option \"{name}\"")))
+}
+
+/// The matches of an xpath() call, None if an argument is NULL
+fn evaluate_xpath(ctx: &Context) -> Result<Option<Vec<String>>> {
+    let (Some(path), Some(document)) = (ctx.get::<Option<String>>(0)?, ctx.get::<Option<String>>(1)?) else {
+        return Ok(None);
+    };
+    let namespaces = match ctx.len() {
+        3 => namespace_mappings(ctx.get::<Option<String>>(2)?.as_deref())?,
+        _ => Vec::new(),
+    };
+    xml::xpath(&path, &document, &namespaces).map(Some).map_err(user_error)
+}
+
+/// Read the prefix and URI pairs of the two-dimensional namespace array, e.g.
+/// `ARRAY[ARRAY['my', 'http://example.com']]`
+fn namespace_mappings(array: Option<&str>) -> Result<Vec<(String, String)>> {
+    let Some(array) = array else {
+        return Ok(Vec::new());
+    };
+    let json = if array.trim_start().starts_with('{') {
+        ArrayHandler::array_literal_to_json(array, None).map_err(|e| user_error(e.to_string()))?
+    } else {
+        array.to_string()
+    };
+    let invalid = || user_error("invalid array for XML namespace mapping");
+    let pairs: Vec<Vec<String>> = serde_json::from_str(&json).map_err(|_| invalid())?;
+    pairs.into_iter()
+        .map(|pair| match <[String; 2]>::try_from(pair) {
+            Ok([prefix, uri]) => Ok((prefix, uri)),
+            Err(_) => Err(invalid()),
+        })
+        .collect()
+}
+
+fn user_error(message: impl Into<String>) -> rusqlite::Error {
+    rusqlite::Error::UserFunctionError(message.into().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xml_functions() {
+        let conn = Connection::open_in_memory().unwrap();
+        register_xml_functions(&conn).unwrap();
+        let query = |sql: &str| conn.query_row(sql, [], |row| row.get::<_, Option<String>>(0));
+
+        assert_eq!(query("SELECT pg_xml_parse('<a>x</a>', 'document')").unwrap().as_deref(), Some("<a>x</a>"));
+        assert!(query("SELECT pg_xml_parse('x<a/>', 'document')").unwrap_err().to_string().contains("invalid XML document"));
+        assert!(query("SELECT pg_xml_serialize('x<a/>', 'document')").unwrap_err().to_string().contains("not an XML document"));
+        let check = |sql: &str| conn.query_row(sql, [], |row| row.get::<_, bool>(0)).unwrap();
+        assert!(check("SELECT xml_is_well_formed_content('x<a/>')"));
+
+        assert_eq!(query("SELECT xpath('//b/text()', '<a><b>1</b><b>2</b></a>')").unwrap().as_deref(), Some(r#"["1","2"]"#));
+        assert_eq!(
+            query(r#"SELECT xpath('/n:a/@id', '<a xmlns="urn:x" id="5"/>', '[["n","urn:x"]]')"#).unwrap().as_deref(),
+            Some(r#"["5"]"#)
+        );
+        assert_eq!(query("SELECT xpath('/n:a/@id', '<a xmlns=\"urn:x\" id=\"5\"/>', '{{n,urn:x}}')").unwrap().as_deref(), Some(r#"["5"]"#));
+        assert!(!check("SELECT xpath_exists('/a/c', '<a><b/></a>')"));
+    }
+}
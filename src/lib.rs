@@ -10,12 +10,17 @@ pub mod rewriter;
 pub mod cache;
 pub mod config;
 pub mod ssl;
+pub mod platform;
 pub mod ddl;
 pub mod migration;
 pub mod schema_drift;
 pub mod error;
 pub mod validator;
 pub mod optimization;
+pub mod shell;
+pub mod ping;
+pub mod snapshot;
+pub mod service_file;
 #[macro_use]
 pub mod profiling;
 
@@ -57,6 +62,38 @@ pub enum PgSqliteError {
 pub type Result<T> = std::result::Result<T, PgSqliteError>;
 
 impl PgSqliteError {
+    /// Build the ErrorResponse sent to the client. Errors that carry a PostgreSQL
+    /// error keep its SQLSTATE, unsupported features are reported as 0A000 and
+    /// anything else as `code`, with `context` prefixed to the message. Errors for
+    /// features pgsqlite lacks get a HINT at what to use instead.
+    pub fn to_error_response(&self, code: &str, context: &str) -> protocol::ErrorResponse {
+        let pg_error = match self {
+            PgSqliteError::Validation(pg_err) => Some(pg_err.to_error_response()),
+            other => error::PgError::from_message(&other.to_string()).map(|pg_err| pg_err.to_error_response()),
+        };
+        let code = match self {
+            PgSqliteError::NotSupported(_) => "0A000",
+            _ => code,
+        };
+        let mut response = pg_error.unwrap_or_else(|| {
+            protocol::ErrorResponse::new("ERROR".to_string(), code.to_string(), format!("{context}: {self}"))
+        });
+        query::FeatureHints::apply(&mut response);
+        response
+    }
+    
+    /// The error of a statement that statement_timeout interrupted: the interruption
+    /// reads as a cancel request, which PostgreSQL reports with a different message
+    pub fn into_statement_timeout(self) -> Self {
+        if self.to_error_response("XX000", "").code != "57014" {
+            return self;
+        }
+        PgSqliteError::Validation(error::PgError::Generic {
+            code: "57014".to_string(),
+            message: "canceling statement due to statement timeout".to_string(),
+        })
+    }
+
     /// Get the PostgreSQL error code for this error
     pub fn pg_error_code(&self) -> &str {
         match self {
@@ -72,6 +109,8 @@ impl PgSqliteError {
                 error::PgError::NumericValueOutOfRange { .. } => "22003", // numeric_value_out_of_range
                 error::PgError::StringDataRightTruncation { .. } => "22001", // string_data_right_truncation
                 error::PgError::UniqueViolation { .. } => "23505", // unique_violation
+                error::PgError::CheckViolation { .. } => "23514", // check_violation
+                error::PgError::NotNullViolation { .. } => "23502", // not_null_violation
                 error::PgError::ForeignKeyViolation { .. } => "23503", // foreign_key_violation
                 error::PgError::SyntaxError { .. } => "42601", // syntax_error
                 error::PgError::Generic { code, .. } => code,
@@ -94,18 +133,19 @@ pub async fn handle_test_connection(
 #[doc(hidden)]
 pub async fn handle_test_connection_with_pool(
     stream: tokio::net::TcpStream,
-    _addr: std::net::SocketAddr,
-    db_handler: std::sync::Arc<session::DbHandler>,
+    addr: std::net::SocketAddr,
+    db_handler: std::sync::Arc<dyn session::SessionStorage>,
 ) -> anyhow::Result<()> {
     use tokio_util::codec::Framed;
     use futures::{SinkExt, StreamExt};
     use std::sync::Arc;
     use protocol::{PostgresCodec, FrontendMessage, BackendMessage, AuthenticationMessage, TransactionStatus, ErrorResponse};
-    use session::{SessionState, ReadOnlyDbHandler, QueryRouter};
+    use session::{SessionState, ReadOnlyDbHandler, QueryRouter, BackendRegistration};
     use query::{QueryExecutor, ExtendedQueryHandler};
     use tracing::{debug, info};
     use config::Config;
     
+    let server_addr = stream.local_addr().ok();
     let codec = PostgresCodec::new();
     let mut framed = Framed::new(stream, codec);
     
@@ -136,6 +176,13 @@ pub async fn handle_test_connection_with_pool(
     // Create a connection for this session
     session.initialize_connection().await
         .map_err(|e| anyhow::anyhow!("Failed to create session connection: {}", e))?;
+    let registration = BackendRegistration::register(
+        &session,
+        startup.parameters.get("application_name").cloned(),
+        Some(addr),
+        server_addr,
+        db_handler.interrupt_handle(&session_id),
+    );
     
     // Set up connection pooling infrastructure (optional - can be enabled via config)
     let config = Arc::new(Config::load());
@@ -171,7 +218,7 @@ pub async fn handle_test_connection_with_pool(
     
     // Send backend key data
     framed.send(BackendMessage::BackendKeyData {
-        process_id: std::process::id() as i32,
+        process_id: session.backend_pid,
         secret_key: 12345,
     }).await?;
     
@@ -182,7 +229,21 @@ pub async fn handle_test_connection_with_pool(
     
     // Main message loop
     let result = async {
-        while let Some(msg) = framed.next().await {
+        loop {
+            let msg = tokio::select! {
+                biased;
+                _ = registration.terminated() => {
+                    let err = ErrorResponse::new(
+                        "FATAL".to_string(),
+                        "57P01".to_string(),
+                        "terminating connection due to administrator command".to_string(),
+                    );
+                    let _ = framed.send(BackendMessage::ErrorResponse(Box::new(err))).await;
+                    break;
+                }
+                msg = framed.next() => msg,
+            };
+            let Some(msg) = msg else { break };
             let message = msg?;
             debug!("Received message: {:?}", message);
             match message {
@@ -193,21 +254,24 @@ pub async fn handle_test_connection_with_pool(
                         Ok(()) => {
                             // Query executed successfully
                         }
+                        Err(_) if registration.is_terminating() => continue,
                         Err(e) => {
                             // If we're in a transaction, mark it as failed
                             if session.in_transaction().await {
                                 session.set_transaction_status(TransactionStatus::InFailedTransaction).await;
                             }
                             
-                            let err = ErrorResponse::new(
-                                "ERROR".to_string(),
-                                "42000".to_string(),
-                                format!("Query execution failed: {e}"),
-                            );
+                            let err = e.to_error_response("42000", "Query execution failed");
                             framed.send(BackendMessage::ErrorResponse(Box::new(err))).await?;
                         }
                     }
                     
+                    // Commits are acknowledged once they are on disk
+                    if let Err(e) = db_handler.wait_durable(&session_id).await {
+                        let err = e.to_error_response(e.pg_error_code(), "Commit could not be synced");
+                        framed.send(BackendMessage::ErrorResponse(Box::new(err))).await?;
+                    }
+                    
                     // Always send ReadyForQuery after handling the query
                     framed.send(BackendMessage::ReadyForQuery {
                         status: *session.transaction_status.read().await,
@@ -220,11 +284,7 @@ pub async fn handle_test_connection_with_pool(
                     match ExtendedQueryHandler::handle_parse(&mut framed, &db_handler, &session, name, query, param_types).await {
                         Ok(()) => {},
                         Err(e) => {
-                            let err = ErrorResponse::new(
-                                "ERROR".to_string(),
-                                "42000".to_string(),
-                                format!("Parse failed: {e}"),
-                            );
+                            let err = e.to_error_response("42000", "Parse failed");
                             framed.send(BackendMessage::ErrorResponse(Box::new(err))).await?;
                         }
                     }
@@ -233,11 +293,7 @@ pub async fn handle_test_connection_with_pool(
                     match ExtendedQueryHandler::handle_bind(&mut framed, &session, portal, statement, formats, values, result_formats).await {
                         Ok(()) => {},
                         Err(e) => {
-                            let err = ErrorResponse::new(
-                                "ERROR".to_string(),
-                                "42000".to_string(),
-                                format!("Bind failed: {e}"),
-                            );
+                            let err = e.to_error_response("42000", "Bind failed");
                             framed.send(BackendMessage::ErrorResponse(Box::new(err))).await?;
                         }
                     }
@@ -246,11 +302,7 @@ pub async fn handle_test_connection_with_pool(
                     match ExtendedQueryHandler::handle_execute(&mut framed, &db_handler, &session, portal, max_rows).await {
                         Ok(()) => {},
                         Err(e) => {
-                            let err = ErrorResponse::new(
-                                "ERROR".to_string(),
-                                "42000".to_string(),
-                                format!("Execute failed: {e}"),
-                            );
+                            let err = e.to_error_response("42000", "Execute failed");
                             framed.send(BackendMessage::ErrorResponse(Box::new(err))).await?;
                         }
                     }
@@ -259,11 +311,7 @@ pub async fn handle_test_connection_with_pool(
                     match ExtendedQueryHandler::handle_describe(&mut framed, &session, typ, name).await {
                         Ok(()) => {},
                         Err(e) => {
-                            let err = ErrorResponse::new(
-                                "ERROR".to_string(),
-                                "42000".to_string(),
-                                format!("Describe failed: {e}"),
-                            );
+                            let err = e.to_error_response("42000", "Describe failed");
                             framed.send(BackendMessage::ErrorResponse(Box::new(err))).await?;
                         }
                     }
@@ -272,16 +320,16 @@ pub async fn handle_test_connection_with_pool(
                     match ExtendedQueryHandler::handle_close(&mut framed, &session, typ, name).await {
                         Ok(()) => {},
                         Err(e) => {
-                            let err = ErrorResponse::new(
-                                "ERROR".to_string(),
-                                "42000".to_string(),
-                                format!("Close failed: {e}"),
-                            );
+                            let err = e.to_error_response("42000", "Close failed");
                             framed.send(BackendMessage::ErrorResponse(Box::new(err))).await?;
                         }
                     }
                 }
                 FrontendMessage::Sync => {
+                    if let Err(e) = db_handler.wait_durable(&session_id).await {
+                        let err = e.to_error_response(e.pg_error_code(), "Commit could not be synced");
+                        framed.send(BackendMessage::ErrorResponse(Box::new(err))).await?;
+                    }
                     framed.send(BackendMessage::ReadyForQuery {
                         status: *session.transaction_status.read().await,
                     }).await?;
@@ -3,6 +3,7 @@ use bytes::{Buf, BytesMut};
 use futures::SinkExt;
 use futures::StreamExt;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::path::PathBuf;
 use std::pin::Pin;
 use std::task::{Context, Poll};
@@ -11,30 +12,104 @@ use tokio::net::TcpListener;
 #[cfg(unix)]
 use tokio::net::UnixListener;
 use tokio_util::codec::Framed;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
+use tracing_subscriber::{EnvFilter, Registry, reload};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
 use tokio_rustls::TlsAcceptor;
 
-use pgsqlite::config::Config;
+use pgsqlite::config::{Command, Config};
 use pgsqlite::protocol::{
     AuthenticationMessage, BackendMessage, ErrorResponse, FrontendMessage, PostgresCodec,
     TransactionStatus,
 };
-use pgsqlite::query::{ExtendedQueryHandler, QueryExecutor};
-use pgsqlite::session::{DbHandler, SessionState};
-use pgsqlite::ssl::CertificateManager;
+use pgsqlite::protocol::startup::encode_fast_startup;
+use pgsqlite::query::{ExtendedQueryHandler, QueryExecutor, RetentionHandler};
+use pgsqlite::session::{BackendRegistration, Databases, DbHandler, LibsqlBackend, SessionState, SessionStorage};
+use pgsqlite::ssl::{CertificateManager, SharedTlsAcceptor};
 use pgsqlite::migration::MigrationRunner;
 
-#[tokio::main]
-async fn main() -> Result<()> {
+/// Set once a shutdown signal arrived; connections accepted afterwards are refused
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// Open sessions on the admin listener, limited by --admin-max-connections
+static ADMIN_SESSIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Changes the log level when the configuration is reloaded
+static LOG_FILTER: std::sync::OnceLock<reload::Handle<EnvFilter, Registry>> = std::sync::OnceLock::new();
+
+fn main() -> Result<()> {
     let config = Config::load();
 
-    // Initialize logging
-    tracing_subscriber::fmt()
-        .with_env_filter(config.log_level.clone())
+    // The shell prints results on stdout, so it only logs what went wrong
+    if let Some(Command::Shell { database }) = &config.command {
+        tracing_subscriber::fmt()
+            .with_env_filter("warn")
+            .with_writer(std::io::stderr)
+            .init();
+        let database = database.as_deref().unwrap_or(&config.database);
+        return pgsqlite::shell::run(database, &config);
+    }
+
+    // The snapshot is written to stdout unless it goes to a file
+    if let Some(Command::Snapshot { database, normalize, output }) = &config.command {
+        tracing_subscriber::fmt()
+            .with_env_filter("warn")
+            .with_writer(std::io::stderr)
+            .init();
+        let database = database.as_deref().unwrap_or(&config.database);
+        return pgsqlite::snapshot::run(database, &config, *normalize, output.as_deref());
+    }
+
+    // Health checks only print whether the server answered
+    if let Some(Command::Ping { host, port, user, dbname, timeout }) = &config.command {
+        std::process::exit(pgsqlite::ping::run(&pgsqlite::ping::PingTarget {
+            host: host.clone(),
+            port: port.unwrap_or(config.port),
+            user: user.clone(),
+            database: dbname.clone(),
+            timeout: std::time::Duration::from_secs(*timeout),
+        }));
+    }
+
+    // Initialize logging, with a filter a configuration reload can replace
+    let (log_filter, log_filter_handle) = reload::Layer::new(EnvFilter::new(&config.log_level));
+    let json = config.log_format == "json";
+    tracing_subscriber::registry()
+        .with(log_filter)
+        .with((!json).then(tracing_subscriber::fmt::layer))
+        .with(json.then(|| tracing_subscriber::fmt::layer().json().flatten_event(true)))
         .init();
+    let _ = LOG_FILTER.set(log_filter_handle);
+
+    // Under the service control manager the server runs on the dispatcher's thread
+    #[cfg(windows)]
+    if config.windows_service {
+        info!("Starting as Windows service");
+        return pgsqlite::platform::service::run(move || run_server(config))
+            .map_err(|e| anyhow::anyhow!("Failed to start Windows service dispatcher: {}", e));
+    }
 
+    run_server(config)
+}
+
+/// Serve until a shutdown signal arrives, then give in-flight work a moment to finish
+fn run_server(config: Config) -> Result<()> {
+    // Statements run on the runtime threads, so keep one free for pg_cancel_backend() on single-core hosts
+    let worker_threads = std::thread::available_parallelism().map_or(2, |n| n.get().max(2));
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(worker_threads)
+        .enable_all()
+        .build()?;
+    let result = runtime.block_on(serve(config));
+    runtime.shutdown_timeout(std::time::Duration::from_secs(5));
+    result
+}
+
+async fn serve(config: Config) -> Result<()> {
     // Display version
-    info!("pgsqlite v{}", env!("CARGO_PKG_VERSION"));
+    info!("pgsqlite v{} (reporting PostgreSQL {}), started at {}",
+        env!("CARGO_PKG_VERSION"), config.server_version, pgsqlite::config::start_time());
 
     // Determine database path based on --in-memory flag
     let db_path = if config.in_memory {
@@ -48,56 +123,70 @@ async fn main() -> Result<()> {
     if config.migrate {
         info!("Running database migrations...");
         
-        // Open connection directly for migration
-        let conn = rusqlite::Connection::open(&db_path)
-            .map_err(|e| anyhow::anyhow!("Failed to open database: {}", e))?;
-        
-        // Register functions needed for migrations
-        pgsqlite::functions::register_all_functions(&conn)
-            .map_err(|e| anyhow::anyhow!("Failed to register functions: {}", e))?;
-        
-        let mut runner = MigrationRunner::new(conn);
-        match runner.run_pending_migrations() {
-            Ok(applied) => {
-                if applied.is_empty() {
-                    info!("No pending migrations. Database is up to date.");
-                } else {
-                    info!("Successfully applied {} migrations: {:?}", applied.len(), applied);
+        // Every served file gets the same catalog, so migrate the --databases files too
+        let paths = std::iter::once(db_path.as_str())
+            .chain(config.databases.iter().map(|(_, path)| path.as_str()));
+        for path in paths {
+            // Open connection directly for migration
+            let conn = rusqlite::Connection::open(path)
+                .map_err(|e| anyhow::anyhow!("Failed to open database {}: {}", path, e))?;
+
+            // Register functions needed for migrations
+            pgsqlite::functions::register_all_functions(&conn)
+                .map_err(|e| anyhow::anyhow!("Failed to register functions: {}", e))?;
+
+            let mut runner = MigrationRunner::new(conn);
+            match runner.run_pending_migrations() {
+                Ok(applied) => {
+                    if applied.is_empty() {
+                        info!("No pending migrations. Database {} is up to date.", path);
+                    } else {
+                        info!("Successfully applied {} migrations to {}: {:?}", applied.len(), path, applied);
+                    }
+                }
+                Err(e) => {
+                    error!("Migration of {} failed: {}", path, e);
+                    std::process::exit(1);
                 }
-                std::process::exit(0);
-            }
-            Err(e) => {
-                error!("Migration failed: {}", e);
-                std::process::exit(1);
             }
         }
+        std::process::exit(0);
     }
 
     // Initialize database handler with direct executor
-    let db_handler = Arc::new(
-        DbHandler::new_with_config(&db_path, &config)
+    let mut db_handler = DbHandler::new_with_config(&db_path, &config)
+        .map_err(|e| anyhow::anyhow!("Failed to create database handler: {}", e))?;
+
+    // Serve a remote libSQL database, caching its schema in the local one
+    if let Some(url) = &config.libsql_url {
+        let remote = LibsqlBackend::new(url, config.libsql_auth_token.clone())
+            .map_err(|e| anyhow::anyhow!("Failed to configure libSQL backend: {}", e))?;
+        db_handler.attach_remote(Arc::new(remote)).await
+            .map_err(|e| anyhow::anyhow!("Failed to connect to libSQL database {}: {}", url, e))?;
+        info!("Serving libSQL database {}", url);
+    }
+    let databases = Arc::new(
+        Databases::open(Arc::new(db_handler), &config)
             .map_err(|e| anyhow::anyhow!("Failed to create database handler: {}", e))?,
     );
 
     // Unix socket setup (only on Unix platforms)
     #[cfg(unix)]
-    let (socket_path, unix_listener) = {
+    let (socket_path, local_listener) = {
         let socket_path = PathBuf::from(&config.socket_dir).join(format!(".s.PGSQL.{}", config.port));
-        
-        // Remove existing socket file if it exists
-        if socket_path.exists() {
-            std::fs::remove_file(&socket_path)?;
-        }
+        let unix_listener = bind_unix_socket(&socket_path, 0o777)?;
+        (socket_path, Some(unix_listener))
+    };
 
-        // Create Unix socket listener
-        let unix_listener = UnixListener::bind(&socket_path)?;
-        info!("Unix socket created at: {}", socket_path.display());
-        
-        // Set socket permissions to 0777 for compatibility
-        use std::os::unix::fs::PermissionsExt;
-        std::fs::set_permissions(&socket_path, std::fs::Permissions::from_mode(0o777))?;
-        
-        (socket_path, unix_listener)
+    // Named pipe setup, the Windows counterpart of the Unix socket
+    #[cfg(windows)]
+    let local_listener = if config.no_pipe {
+        info!("Named pipe listener disabled");
+        None
+    } else {
+        let listener = pgsqlite::platform::NamedPipeListener::bind(&config.pipe_path())?;
+        info!("Named pipe created at: {}", listener.name());
+        Some(listener)
     };
 
     // Create TCP listener if not disabled
@@ -106,10 +195,41 @@ async fn main() -> Result<()> {
         info!("TCP server listening on port {}", config.port);
         Some(listener)
     } else {
-        info!("TCP listener disabled, using Unix socket only");
+        info!("TCP listener disabled, using local connections only");
         None
     };
 
+    // Reserved admin listener for operators: loopback TCP and a Unix socket named after the
+    // admin port, accepting only admin roles and never refused for --max-connections
+    let admin_tcp_listener = match config.admin_port {
+        Some(port) if !config.no_tcp => {
+            let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+            info!("Admin TCP listener on 127.0.0.1:{}", port);
+            Some(listener)
+        }
+        _ => None,
+    };
+
+    #[cfg(unix)]
+    let (admin_socket_path, admin_local_listener) = match config.admin_port {
+        Some(port) => {
+            let path = PathBuf::from(&config.socket_dir).join(format!(".s.PGSQL.{port}"));
+            // Only the server's own user may reach the admin socket
+            let listener = bind_unix_socket(&path, 0o700)?;
+            (Some(path), Some(listener))
+        }
+        None => (None, None),
+    };
+
+    // On Windows the admin listener is TCP only
+    #[cfg(windows)]
+    let admin_local_listener: Option<pgsqlite::platform::NamedPipeListener> = None;
+
+    #[cfg(windows)]
+    if tcp_listener.is_none() && local_listener.is_none() {
+        return Err(anyhow::anyhow!("Cannot run with both TCP and the named pipe disabled"));
+    }
+
     if config.in_memory {
         info!("Using in-memory database (for testing/benchmarking only)");
     } else {
@@ -123,34 +243,29 @@ async fn main() -> Result<()> {
         }
         let cert_manager = CertificateManager::new(Arc::new(config.clone()));
         let (acceptor, _cert_source) = cert_manager.initialize().await?;
+        let acceptor = SharedTlsAcceptor::new(acceptor);
+        // Renewed certificates are picked up without a restart or a reload
+        tokio::spawn(CertificateManager::watch(acceptor.clone()));
         Some(acceptor)
     } else {
         info!("SSL disabled - using unencrypted connections");
         None
     };
 
-    // Handle cleanup on shutdown
-    #[cfg(unix)]
+    // Reread --config-file on SIGHUP and pg_reload_conf()
+    pgsqlite::config::set_current(config.clone());
+    let mut reload_signal = pgsqlite::platform::ReloadSignal::listen();
     {
-        let socket_path_cleanup = socket_path.clone();
+        let databases = databases.clone();
+        let tls_acceptor = tls_acceptor.clone();
         tokio::spawn(async move {
-            tokio::signal::ctrl_c().await.ok();
-            if socket_path_cleanup.exists() {
-                let _ = std::fs::remove_file(&socket_path_cleanup);
-                info!("Cleaned up Unix socket file");
+            loop {
+                reload_signal.recv().await;
+                reload_config(&databases, tls_acceptor.as_ref()).await;
             }
-            std::process::exit(0);
         });
     }
-    
-    #[cfg(not(unix))]
-    {
-        tokio::spawn(async move {
-            tokio::signal::ctrl_c().await.ok();
-            std::process::exit(0);
-        });
-    }
-    
+
     // Start periodic cache metrics logging
     let cache_metrics_interval = config.cache_metrics_interval_duration();
     tokio::spawn(async move {
@@ -161,80 +276,233 @@ async fn main() -> Result<()> {
         }
     });
 
-    // Accept connections from both TCP and Unix sockets
-    #[cfg(unix)]
-    {
-        loop {
-            let db_handler = db_handler.clone();
-            
-            tokio::select! {
-                // Handle TCP connections
-                result = async {
-                    if let Some(ref listener) = tcp_listener {
-                        listener.accept().await
-                    } else {
-                        std::future::pending::<Result<(tokio::net::TcpStream, std::net::SocketAddr), std::io::Error>>().await
-                    }
-                } => {
-                    if let Ok((stream, addr)) = result {
-                        info!("New TCP connection from {}", addr);
-                        let db_handler = db_handler.clone();
-                        let tls_acceptor = tls_acceptor.clone();
-                        tokio::spawn(async move {
-                            if let Err(e) = handle_tcp_connection(stream, addr, db_handler, tls_acceptor).await {
-                                error!("TCP connection error from {}: {}", addr, e);
-                            }
-                        });
-                    }
-                }
-                
-                // Handle Unix socket connections
-                result = unix_listener.accept() => {
-                    if let Ok((stream, _addr)) = result {
-                        info!("New Unix socket connection");
-                        let db_handler = db_handler.clone();
-                        tokio::spawn(async move {
-                            if let Err(e) = handle_unix_connection(stream, db_handler).await {
-                                error!("Unix socket connection error: {}", e);
-                            }
-                        });
+    // Run every database's retention policies, see pgsqlite.set_retention(); a --read-only
+    // server leaves that to the server writing the database, and a remote one has none
+    if config.retention_interval > 0 && !config.read_only && config.libsql_url.is_none() {
+        let databases = databases.clone();
+        let retention_interval = std::time::Duration::from_secs(config.retention_interval);
+        let batch_size = config.retention_batch_size;
+        tokio::spawn(async move {
+            let scheduler = uuid::Uuid::new_v4();
+            let mut interval = tokio::time::interval(retention_interval);
+            loop {
+                interval.tick().await;
+                for db in databases.handlers() {
+                    if let Err(e) = RetentionHandler::run_scheduled(&db, &scheduler, batch_size).await {
+                        warn!("Retention policies failed: {}", e);
                     }
                 }
             }
+        });
+    }
+
+    // Tell tools how to connect, now that the listeners are up
+    let services = pgsqlite::service_file::services(&config);
+    if config.print_urls {
+        for service in &services {
+            println!("{}", service.url());
         }
     }
-    
-    #[cfg(not(unix))]
-    {
-        // Windows/non-Unix: only handle TCP connections
-        loop {
-            let db_handler = db_handler.clone();
-            
-            if let Some(ref listener) = tcp_listener {
-                if let Ok((stream, addr)) = listener.accept().await {
+    if let Some(path) = &config.service_file {
+        pgsqlite::service_file::write(std::path::Path::new(path), &services)?;
+        info!("Wrote connection services for {} database(s) to {}", services.len(), path);
+    }
+
+    // Accept connections from TCP and the local transport until asked to stop
+    let mut local_listener = local_listener;
+    let mut admin_local_listener = admin_local_listener;
+    let shutdown = pgsqlite::platform::shutdown_signal();
+    tokio::pin!(shutdown);
+    let mut grace_deadline = None;
+    loop {
+        let databases = databases.clone();
+        
+        tokio::select! {
+            _ = &mut shutdown, if grace_deadline.is_none() => {
+                if config.shutdown_grace == 0 {
+                    info!("Shutdown requested, no longer accepting connections");
+                    break;
+                }
+                info!("Shutdown requested, refusing new connections for up to {}s while sessions finish", config.shutdown_grace);
+                SHUTTING_DOWN.store(true, Ordering::Relaxed);
+                grace_deadline = Some(tokio::time::Instant::now() + std::time::Duration::from_secs(config.shutdown_grace));
+            }
+
+            // Stop once the open sessions are gone or the grace period is over
+            _ = tokio::time::sleep(std::time::Duration::from_millis(100)), if grace_deadline.is_some() => {
+                if pgsqlite::session::backend_registry::backends().is_empty()
+                    || grace_deadline.is_some_and(|deadline| tokio::time::Instant::now() >= deadline) {
+                    break;
+                }
+            }
+
+            // Handle TCP connections
+            result = accept_tcp(&tcp_listener) => {
+                if let Ok((stream, addr)) = result {
                     info!("New TCP connection from {}", addr);
-                    let db_handler = db_handler.clone();
-                    let tls_acceptor = tls_acceptor.clone();
+                    let databases = databases.clone();
+                    let tls_acceptor = tls_acceptor.as_ref().map(SharedTlsAcceptor::current);
                     tokio::spawn(async move {
-                        if let Err(e) = handle_tcp_connection(stream, addr, db_handler, tls_acceptor).await {
-                            error!("TCP connection error from {}: {}", addr, e);
+                        if let Err(e) = handle_tcp_connection(stream, addr, databases, tls_acceptor, false).await {
+                            log_connection_error(&format!("TCP connection from {addr}"), &e);
                         }
                     });
                 }
-            } else {
-                // No TCP listener and no Unix sockets on Windows
-                error!("No listeners available on Windows when TCP is disabled");
-                return Err(anyhow::anyhow!("Cannot run without TCP on Windows"));
             }
+            
+            // Handle Unix socket / named pipe connections
+            result = accept_local(&mut local_listener) => {
+                if let Ok(stream) = result {
+                    tokio::spawn(async move {
+                        if let Err(e) = handle_local_connection(stream, databases, false).await {
+                            log_connection_error("Local connection", &e);
+                        }
+                    });
+                }
+            }
+
+            // Handle admin connections
+            result = accept_tcp(&admin_tcp_listener) => {
+                if let Ok((stream, addr)) = result {
+                    info!("New admin TCP connection from {}", addr);
+                    let tls_acceptor = tls_acceptor.as_ref().map(SharedTlsAcceptor::current);
+                    tokio::spawn(async move {
+                        if let Err(e) = handle_tcp_connection(stream, addr, databases, tls_acceptor, true).await {
+                            log_connection_error(&format!("Admin TCP connection from {addr}"), &e);
+                        }
+                    });
+                }
+            }
+
+            result = accept_local(&mut admin_local_listener) => {
+                if let Ok(stream) = result {
+                    tokio::spawn(async move {
+                        if let Err(e) = handle_local_connection(stream, databases, true).await {
+                            log_connection_error("Admin local connection", &e);
+                        }
+                    });
+                }
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    for path in std::iter::once(&socket_path).chain(admin_socket_path.as_ref()) {
+        if path.exists() {
+            let _ = std::fs::remove_file(path);
+            info!("Cleaned up Unix socket file {}", path.display());
+        }
+    }
+    info!("pgsqlite stopped");
+    Ok(())
+}
+
+/// Reread --config-file and apply the settings a reload may change: the log level,
+/// admin roles, TLS certificates and settings, and the cache sizes
+async fn reload_config(databases: &Databases, tls_acceptor: Option<&SharedTlsAcceptor>) {
+    let current = pgsqlite::config::current();
+    let (mut config, needs_restart) = match current.reload() {
+        Ok(reloaded) => reloaded,
+        Err(e) => {
+            error!("Configuration file not reloaded: {}", e);
+            return;
+        }
+    };
+    for name in needs_restart {
+        warn!("parameter \"{}\" cannot be changed without restarting the server", name);
+    }
+
+    if config.log_level != current.log_level {
+        let filter = EnvFilter::try_new(&config.log_level)
+            .map_err(|e| e.to_string())
+            .and_then(|filter| LOG_FILTER.get().map_or(Ok(()), |handle| handle.reload(filter).map_err(|e| e.to_string())));
+        if let Err(e) = filter {
+            warn!("Invalid log level \"{}\", keeping \"{}\": {}", config.log_level, current.log_level, e);
+            config.log_level = current.log_level.clone();
         }
     }
+
+    // Certificate files are read again even when their paths stay, to pick up renewed certificates
+    if let Some(tls_acceptor) = tls_acceptor {
+        match CertificateManager::new(Arc::new(config.clone())).reload().await {
+            Ok(Some(acceptor)) => tls_acceptor.replace(acceptor),
+            Ok(None) => {}
+            Err(e) => error!("TLS certificates not reloaded, keeping the current ones: {:#}", e),
+        }
+    }
+
+    for db in databases.handlers() {
+        db.get_caches().resize(&config);
+    }
+    pgsqlite::session::GLOBAL_QUERY_CACHE.resize(config.query_cache_size);
+
+    info!("Configuration reloaded");
+    pgsqlite::config::set_current(config);
+}
+
+/// Clients that hang up mid-handshake or mid-reply are routine (port checks,
+/// pg_isready, killed processes), so only log real failures as errors
+fn log_connection_error(connection: &str, e: &anyhow::Error) {
+    use std::io::ErrorKind;
+    let disconnected = e.chain()
+        .filter_map(|cause| cause.downcast_ref::<std::io::Error>())
+        .any(|io| matches!(io.kind(), ErrorKind::UnexpectedEof | ErrorKind::BrokenPipe | ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted));
+    if disconnected {
+        debug!("{} closed by the client: {}", connection, e);
+    } else {
+        error!("{} error: {}", connection, e);
+    }
+}
+
+/// Create a Unix socket listener with the given permissions, replacing a stale socket file
+#[cfg(unix)]
+fn bind_unix_socket(socket_path: &std::path::Path, mode: u32) -> Result<UnixListener> {
+    // Remove existing socket file if it exists
+    if socket_path.exists() {
+        std::fs::remove_file(socket_path)?;
+    }
+
+    // Create Unix socket listener
+    let unix_listener = UnixListener::bind(socket_path)?;
+    info!("Unix socket created at: {}", socket_path.display());
+    
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(socket_path, std::fs::Permissions::from_mode(mode))?;
+    
+    Ok(unix_listener)
+}
+
+async fn accept_tcp(listener: &Option<TcpListener>) -> std::io::Result<(tokio::net::TcpStream, std::net::SocketAddr)> {
+    match listener {
+        Some(listener) => listener.accept().await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(unix)]
+async fn accept_local(listener: &mut Option<UnixListener>) -> std::io::Result<tokio::net::UnixStream> {
+    match listener {
+        Some(listener) => listener.accept().await.map(|(stream, _addr)| stream),
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(windows)]
+async fn accept_local(
+    listener: &mut Option<pgsqlite::platform::NamedPipeListener>,
+) -> std::io::Result<tokio::net::windows::named_pipe::NamedPipeServer> {
+    match listener {
+        Some(listener) => listener.accept().await,
+        None => std::future::pending().await,
+    }
 }
 
 async fn handle_tcp_connection(
     stream: tokio::net::TcpStream,
     addr: std::net::SocketAddr,
-    db_handler: Arc<DbHandler>,
+    databases: Arc<Databases>,
     tls_acceptor: Option<TlsAcceptor>,
+    admin: bool,
 ) -> Result<()> {
     info!("Handling TCP connection from {}", addr);
     
@@ -242,24 +510,42 @@ async fn handle_tcp_connection(
     stream.set_nodelay(true)?;
     
     // Always handle potential SSL requests, even if SSL is disabled
-    handle_ssl_negotiation(stream, addr, db_handler, tls_acceptor).await
+    handle_ssl_negotiation(stream, addr, databases, tls_acceptor, admin).await
 }
 
 async fn handle_ssl_negotiation(
     mut stream: tokio::net::TcpStream,
     addr: std::net::SocketAddr,
-    db_handler: Arc<DbHandler>,
+    databases: Arc<Databases>,
     tls_acceptor: Option<TlsAcceptor>,
+    admin: bool,
 ) -> Result<()> {
     use tokio::io::{AsyncReadExt, AsyncWriteExt};
     
-    // Read the first message to check if it's an SSL request
+    // The address the client connected to, for inet_server_addr()
+    let server_addr = stream.local_addr().ok();
+
+    // Read the first message to check if it's an SSL request; port checks connect and leave
     let mut buf = vec![0u8; 8];
-    stream.read_exact(&mut buf).await?;
-    
-    // Check if this is an SSL request
-    let len = i32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]);
-    let code = i32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]);
+    let (len, code) = loop {
+        if let Err(e) = stream.read_exact(&mut buf).await {
+            if e.kind() == std::io::ErrorKind::UnexpectedEof {
+                debug!("{} closed the connection before sending a startup message", addr);
+                return Ok(());
+            }
+            return Err(e.into());
+        }
+        let len = i32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]);
+        let code = i32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]);
+        
+        // GSSAPI encryption isn't offered; libpq then goes on with SSLRequest or the startup packet
+        if len == 8 && code == 80877104 {
+            stream.write_all(b"N").await?;
+            stream.flush().await?;
+            continue;
+        }
+        break (len, code);
+    };
     
     if len == 8 && code == 80877103 {
         // This is an SSL request
@@ -272,8 +558,18 @@ async fn handle_ssl_negotiation(
             let tls_stream = tls_acceptor.accept(stream).await?;
             info!("SSL connection established with {}", addr);
             
+            // With --ssl-sni-domain the server name the client connected to picks the database
+            let connection = tls_stream.get_ref().1;
+            let transport = Transport::Tls {
+                sni_database: connection.server_name()
+                    .and_then(|server_name| pgsqlite::config::CONFIG.sni_database(server_name)),
+                client_certificate: connection.peer_certificates()
+                    .and_then(|certificates| certificates.first())
+                    .map(|certificate| ClientCertificate { common_name: pgsqlite::ssl::common_name(certificate) }),
+            };
+            
             // Handle the connection with TLS
-            handle_connection_generic(tls_stream, &addr.to_string(), db_handler).await
+            handle_connection_generic(tls_stream, &addr.to_string(), server_addr, databases, admin, transport).await
         } else {
             // SSL is disabled, send 'N' to indicate SSL is not available
             stream.write_all(b"N").await?;
@@ -281,7 +577,7 @@ async fn handle_ssl_negotiation(
             info!("Rejected SSL request from {} (SSL disabled)", addr);
             
             // Continue with non-SSL connection
-            handle_connection_generic(stream, &addr.to_string(), db_handler).await
+            handle_connection_generic(stream, &addr.to_string(), server_addr, databases, admin, Transport::Tcp).await
         }
     } else {
         // Not an SSL request, we need to handle this as a regular startup message
@@ -290,23 +586,73 @@ async fn handle_ssl_negotiation(
         
         // Create a custom stream that will first return our buffered data
         let stream_with_buffer = StreamWithBuffer::new(stream, initial_data);
-        handle_connection_generic(stream_with_buffer, &addr.to_string(), db_handler).await
+        handle_connection_generic(stream_with_buffer, &addr.to_string(), server_addr, databases, admin, Transport::Tcp).await
     }
 }
 
 #[cfg(unix)]
-async fn handle_unix_connection(
+async fn handle_local_connection(
     stream: tokio::net::UnixStream,
-    db_handler: Arc<DbHandler>,
+    databases: Arc<Databases>,
+    admin: bool,
 ) -> Result<()> {
     info!("Handling Unix socket connection");
-    handle_connection_generic(stream, "unix-socket", db_handler).await
+    handle_connection_generic(stream, "unix-socket", None, databases, admin, Transport::Local).await
+}
+
+#[cfg(windows)]
+async fn handle_local_connection(
+    stream: tokio::net::windows::named_pipe::NamedPipeServer,
+    databases: Arc<Databases>,
+    admin: bool,
+) -> Result<()> {
+    info!("Handling named pipe connection");
+    handle_connection_generic(stream, "named-pipe", None, databases, admin, Transport::Local).await
+}
+
+/// How a client reached the server
+enum Transport {
+    /// A Unix socket or named pipe, only reachable from this machine
+    Local,
+    Tcp,
+    Tls {
+        /// The database the client's TLS server name routed it to
+        sni_database: Option<String>,
+        /// The certificate the client presented, already verified against --ssl-ca
+        client_certificate: Option<ClientCertificate>,
+    },
 }
 
+struct ClientCertificate {
+    common_name: Option<String>,
+}
+
+impl Transport {
+    /// Why --ssl-client-cert refuses `user` on this transport, if it does.
+    /// Local connections need no certificate, like `local` lines of pg_hba.conf.
+    fn client_certificate_error(&self, config: &pgsqlite::config::Config, user: &str) -> Option<String> {
+        let certificate = match self {
+            _ if config.ssl_client_cert == "off" => return None,
+            Transport::Local => return None,
+            Transport::Tcp | Transport::Tls { client_certificate: None, .. } => {
+                return Some("connection requires a valid client certificate".to_string());
+            }
+            Transport::Tls { client_certificate: Some(certificate), .. } => certificate,
+        };
+        let mapped = certificate.common_name.as_deref().is_some_and(|name| config.is_certificate_user(name, user));
+        (config.ssl_client_cert == "verify-full" && !mapped)
+            .then(|| format!("certificate authentication failed for user \"{user}\""))
+    }
+}
+
+/// Serve one client; `admin` connections come from the admin listener
 async fn handle_connection_generic<S>(
     stream: S,
     connection_info: &str,
-    db_handler: Arc<DbHandler>,
+    server_addr: Option<std::net::SocketAddr>,
+    databases: Arc<Databases>,
+    admin: bool,
+    transport: Transport,
 ) -> Result<()>
 where
     S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send,
@@ -322,11 +668,22 @@ where
             return Err(anyhow::anyhow!("Protocol error: expected startup message"));
         }
         Some(Err(e)) => return Err(e.into()),
-        None => return Err(anyhow::anyhow!("Connection closed unexpectedly")),
+        None => {
+            debug!("{} closed the connection before sending a startup message", connection_info);
+            return Ok(());
+        }
     };
 
     info!("Received startup message from {}: {:?}", connection_info, startup);
 
+    // Like PostgreSQL, answer the startup packet during shutdown so pg_isready reports "rejecting connections"
+    if SHUTTING_DOWN.load(Ordering::Relaxed) {
+        info!("Refused connection from {} during shutdown", connection_info);
+        let err = ErrorResponse::new("FATAL".to_string(), "57P03".to_string(), "the database system is shutting down".to_string());
+        let _ = framed.send(BackendMessage::ErrorResponse(Box::new(err))).await;
+        return Ok(());
+    }
+
     // Extract session parameters
     let mut database = "main".to_string();
     let mut user = "postgres".to_string();
@@ -338,6 +695,58 @@ where
             _ => {}
         }
     }
+    if let Transport::Tls { sni_database: Some(sni_database), .. } = &transport {
+        info!("Routing {} to database {} by its TLS server name", connection_info, sni_database);
+        database = sni_database.clone();
+    }
+
+    if let Some(message) = transport.client_certificate_error(&pgsqlite::config::current(), &user) {
+        error!("Rejected connection from {}: {}", connection_info, message);
+        let err = ErrorResponse::new("FATAL".to_string(), "28000".to_string(), message.clone());
+        framed.send(BackendMessage::ErrorResponse(Box::new(err))).await?;
+        return Err(anyhow::anyhow!(message));
+    }
+
+    if admin && !pgsqlite::config::current().is_admin_user(&user) {
+        let message = format!("role \"{user}\" is not permitted to connect on the admin port");
+        error!("Rejected admin connection from {}: {}", connection_info, message);
+        let err = ErrorResponse::new("FATAL".to_string(), "28000".to_string(), message.clone());
+        framed.send(BackendMessage::ErrorResponse(Box::new(err))).await?;
+        return Err(anyhow::anyhow!(message));
+    }
+
+    let db_handler = match databases.select(&database) {
+        Ok(Some(db_handler)) => db_handler,
+        Ok(None) => {
+            let message = format!("database \"{database}\" does not exist");
+            info!("Refused connection from {}: {}", connection_info, message);
+            let err = ErrorResponse::new("FATAL".to_string(), "3D000".to_string(), message);
+            let _ = framed.send(BackendMessage::ErrorResponse(Box::new(err))).await;
+            return Ok(());
+        }
+        Err(e) => {
+            let message = format!("could not open database \"{database}\": {e}");
+            error!("Refused connection from {}: {}", connection_info, message);
+            let err = ErrorResponse::new("FATAL".to_string(), "58000".to_string(), message);
+            let _ = framed.send(BackendMessage::ErrorResponse(Box::new(err))).await;
+            return Ok(());
+        }
+    };
+
+    // Admin sessions bypass --max-connections, so they get a small limit of their own
+    let _admin_slot = if admin {
+        match AdminSlot::acquire(pgsqlite::config::CONFIG.admin_max_connections) {
+            Some(slot) => Some(slot),
+            None => {
+                warn!("Refused admin connection from {}: too many admin connections", connection_info);
+                let err = ErrorResponse::new("FATAL".to_string(), "53300".to_string(), "sorry, too many clients already".to_string());
+                let _ = framed.send(BackendMessage::ErrorResponse(Box::new(err))).await;
+                return Ok(());
+            }
+        }
+    } else {
+        None
+    };
 
     let session = Arc::new(SessionState::new(database, user));
     let session_id = session.id;
@@ -345,51 +754,108 @@ where
     // Set the database handler for this session for proper lifecycle management
     session.set_db_handler(db_handler.clone()).await;
 
-    // Create a connection for this session
-    if let Err(e) = session.initialize_connection().await {
+    // Create a connection for this session; admin sessions don't take a client slot
+    let initialized = if admin {
+        session.initialize_reserved_connection().await
+    } else {
+        session.initialize_connection().await
+    };
+    if let Err(e) = initialized {
+        let mut err = e.to_error_response("08006", "Failed to create session connection");
+        err.severity = "FATAL".to_string();
+        // A full server is an expected answer, not a failure of this connection
+        if err.code == "53300" {
+            warn!("Refused connection from {}: {}", connection_info, err.message);
+            let _ = framed.send(BackendMessage::ErrorResponse(Box::new(err))).await;
+            return Ok(());
+        }
         error!("Failed to create session connection: {}", e);
+        framed.send(BackendMessage::ErrorResponse(Box::new(err))).await?;
         return Err(anyhow::anyhow!("Failed to create session connection: {}", e));
     }
+
+    // List the session in pg_stat_activity and make it reachable for pg_cancel_backend/pg_terminate_backend
+    let registration = BackendRegistration::register(
+        &session,
+        startup.parameters.get("application_name").cloned(),
+        connection_info.parse().ok(),
+        server_addr,
+        db_handler.interrupt_handle(&session_id),
+    );
     
-    // Note: cleanup is now handled by SessionState Drop implementation
-    // when the session Arc is dropped
-    
-    // We'll handle cleanup at the end of the function
+    // connection_open now, connection_close however this function returns, see EventLog
+    let _events = pgsqlite::query::event_log::EventLog::connection_opened(
+        &session,
+        connection_info,
+        startup.parameters.get("application_name").map(String::as_str),
+    );
+
+    // Give the session's connection back however this function returns, including
+    // when the client hangs up in the middle of the handshake
+    let _connection = SessionConnection { db_handler: db_handler.clone(), session_id };
 
-    // Send authentication OK
-    framed
-        .send(BackendMessage::Authentication(AuthenticationMessage::Ok))
-        .await?;
+    // Unix socket and named pipe clients don't parse as socket addresses
+    let local_client = connection_info
+        .parse::<std::net::SocketAddr>()
+        .map_or(true, |addr| addr.ip().is_loopback());
 
-    // Send parameter status messages
-    for (key, value) in session.parameters.read().await.iter() {
+    if pgsqlite::config::CONFIG.fast_startup && local_client {
+        // Whole handshake in a single write
+        let parameters = session.parameters.read().await;
+        encode_fast_startup(&parameters, session.backend_pid, framed.write_buffer_mut());
+        drop(parameters);
+        framed.flush().await?;
+    } else {
+        // Send authentication OK
         framed
-            .send(BackendMessage::ParameterStatus {
-                name: key.clone(),
-                value: value.clone(),
-            })
+            .send(BackendMessage::Authentication(AuthenticationMessage::Ok))
             .await?;
-    }
 
-    // Send backend key data
-    framed
-        .send(BackendMessage::BackendKeyData {
-            process_id: std::process::id() as i32,
-            secret_key: rand::random::<i32>(),
-        })
-        .await?;
+        // Send parameter status messages
+        for (key, value) in session.parameters.read().await.iter() {
+            framed
+                .send(BackendMessage::ParameterStatus {
+                    name: key.clone(),
+                    value: value.clone(),
+                })
+                .await?;
+        }
 
-    // Send ready for query
-    framed
-        .send(BackendMessage::ReadyForQuery {
-            status: TransactionStatus::Idle,
-        })
-        .await?;
+        // Send backend key data
+        framed
+            .send(BackendMessage::BackendKeyData {
+                process_id: session.backend_pid,
+                secret_key: rand::random::<i32>(),
+            })
+            .await?;
+
+        // Send ready for query
+        framed
+            .send(BackendMessage::ReadyForQuery {
+                status: TransactionStatus::Idle,
+            })
+            .await?;
+    }
 
     info!("Sent authentication and ready response to {}", connection_info);
 
-    // Main message loop
-    while let Some(msg) = framed.next().await {
+    // Main message loop, until the client leaves or pg_terminate_backend() closes the session
+    loop {
+        let msg = tokio::select! {
+            biased;
+            _ = registration.terminated() => {
+                info!("Terminating connection from {} by administrator command", connection_info);
+                let err = ErrorResponse::new(
+                    "FATAL".to_string(),
+                    "57P01".to_string(),
+                    "terminating connection due to administrator command".to_string(),
+                );
+                let _ = framed.send(BackendMessage::ErrorResponse(Box::new(err))).await;
+                break;
+            }
+            msg = framed.next() => msg,
+        };
+        let Some(msg) = msg else { break };
         match msg? {
             FrontendMessage::Query(sql) => {
                 debug!("Received query from {}: {}", connection_info, sql);
@@ -399,6 +865,8 @@ where
                     Ok(()) => {
                         // Query executed successfully
                     }
+                    // The FATAL for pg_terminate_backend() replaces the error of the interrupted query
+                    Err(_) if registration.is_terminating() => continue,
                     Err(e) => {
                         error!("Query execution error: {}", e);
                         
@@ -408,15 +876,17 @@ where
                             session.set_transaction_status(TransactionStatus::InFailedTransaction).await;
                         }
                         
-                        let err = ErrorResponse::new(
-                            "ERROR".to_string(),
-                            "42000".to_string(),
-                            format!("Query execution failed: {e}"),
-                        );
+                        let err = e.to_error_response("42000", "Query execution failed");
                         framed.send(BackendMessage::ErrorResponse(Box::new(err))).await?;
                     }
                 }
 
+                // Commits are acknowledged once they are on disk
+                if let Err(e) = db_handler.wait_durable(&session_id).await {
+                    let err = e.to_error_response(e.pg_error_code(), "Commit could not be synced");
+                    framed.send(BackendMessage::ErrorResponse(Box::new(err))).await?;
+                }
+
                 // Always send ReadyForQuery after handling the query
                 framed
                     .send(BackendMessage::ReadyForQuery {
@@ -445,11 +915,7 @@ where
                     Ok(()) => {}
                     Err(e) => {
                         error!("Parse error: {}", e);
-                        let err = ErrorResponse::new(
-                            "ERROR".to_string(),
-                            "42000".to_string(),
-                            format!("Parse failed: {e}"),
-                        );
+                        let err = e.to_error_response("42000", "Parse failed");
                         framed.send(BackendMessage::ErrorResponse(Box::new(err))).await?;
                         framed
                             .send(BackendMessage::ReadyForQuery {
@@ -480,11 +946,7 @@ where
                     Ok(()) => {}
                     Err(e) => {
                         error!("Bind error: {}", e);
-                        let err = ErrorResponse::new(
-                            "ERROR".to_string(),
-                            "42000".to_string(),
-                            format!("Bind failed: {e}"),
-                        );
+                        let err = e.to_error_response("42000", "Bind failed");
                         framed.send(BackendMessage::ErrorResponse(Box::new(err))).await?;
                         framed
                             .send(BackendMessage::ReadyForQuery {
@@ -506,13 +968,10 @@ where
                 .await
                 {
                     Ok(()) => {}
+                    Err(_) if registration.is_terminating() => continue,
                     Err(e) => {
                         error!("Execute error: {}", e);
-                        let err = ErrorResponse::new(
-                            "ERROR".to_string(),
-                            "42000".to_string(),
-                            format!("Execute failed: {e}"),
-                        );
+                        let err = e.to_error_response("42000", "Execute failed");
                         framed.send(BackendMessage::ErrorResponse(Box::new(err))).await?;
                         framed
                             .send(BackendMessage::ReadyForQuery {
@@ -528,11 +987,7 @@ where
                     Ok(()) => {}
                     Err(e) => {
                         error!("Describe error: {}", e);
-                        let err = ErrorResponse::new(
-                            "ERROR".to_string(),
-                            "42000".to_string(),
-                            format!("Describe failed: {e}"),
-                        );
+                        let err = e.to_error_response("42000", "Describe failed");
                         framed.send(BackendMessage::ErrorResponse(Box::new(err))).await?;
                         framed
                             .send(BackendMessage::ReadyForQuery {
@@ -547,11 +1002,7 @@ where
                     Ok(()) => {}
                     Err(e) => {
                         error!("Close error: {}", e);
-                        let err = ErrorResponse::new(
-                            "ERROR".to_string(),
-                            "42000".to_string(),
-                            format!("Close failed: {e}"),
-                        );
+                        let err = e.to_error_response("42000", "Close failed");
                         framed.send(BackendMessage::ErrorResponse(Box::new(err))).await?;
                         framed
                             .send(BackendMessage::ReadyForQuery {
@@ -562,6 +1013,10 @@ where
                 }
             }
             FrontendMessage::Sync => {
+                if let Err(e) = db_handler.wait_durable(&session_id).await {
+                    let err = e.to_error_response(e.pg_error_code(), "Commit could not be synced");
+                    framed.send(BackendMessage::ErrorResponse(Box::new(err))).await?;
+                }
                 // Send ReadyForQuery to indicate we're ready for more commands
                 framed
                     .send(BackendMessage::ReadyForQuery {
@@ -593,13 +1048,40 @@ where
         }
     }
 
-    // Clean up session connection explicitly
-    session.cleanup_connection().await;
-    
     info!("Connection from {} closed", connection_info);
     Ok(())
 }
 
+/// Removes a session's SQLite connection when dropped, freeing its --max-connections slot
+struct SessionConnection {
+    db_handler: Arc<dyn SessionStorage>,
+    session_id: uuid::Uuid,
+}
+
+impl Drop for SessionConnection {
+    fn drop(&mut self) {
+        self.db_handler.remove_session_connection(&self.session_id);
+    }
+}
+
+/// One of the --admin-max-connections slots, given back when dropped
+struct AdminSlot;
+
+impl AdminSlot {
+    fn acquire(limit: usize) -> Option<Self> {
+        ADMIN_SESSIONS
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |open| (open < limit).then_some(open + 1))
+            .ok()
+            .map(|_| AdminSlot)
+    }
+}
+
+impl Drop for AdminSlot {
+    fn drop(&mut self) {
+        ADMIN_SESSIONS.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
 // Helper struct to handle streams with pre-read data
 struct StreamWithBuffer<S> {
     stream: S,
@@ -0,0 +1,286 @@
+use rusqlite::{Connection, Result, params, OptionalExtension};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use super::oid_allocator::{OidAllocator, ARRAY_TYPE, COMPOSITE_RELATION, TYPE};
+
+/// Offset for generated composite type OIDs, above the ENUM type and value ranges
+const COMPOSITE_TYPE_OID_OFFSET: i32 = 30000;
+/// Offset for the OIDs of the relations backing composite types (pg_type.typrelid)
+const COMPOSITE_RELATION_OID_OFFSET: i32 = 40000;
+
+/// Represents a composite type created with CREATE TYPE name AS (...)
+#[derive(Debug, Clone)]
+pub struct CompositeType {
+    pub type_oid: i32,
+    pub type_name: String,
+    pub relation_oid: i32,
+    pub namespace_oid: i32,
+    pub attributes: Vec<CompositeAttribute>,
+}
+
+/// A field of a composite type
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompositeAttribute {
+    pub attnum: i32,
+    pub name: String,
+    pub pg_type: String,
+}
+
+impl CompositeType {
+    /// Find a field by name, case-insensitively
+    pub fn attribute(&self, name: &str) -> Option<&CompositeAttribute> {
+        self.attributes.iter().find(|attr| attr.name.eq_ignore_ascii_case(name))
+    }
+}
+
+pub struct CompositeTypes;
+
+impl CompositeTypes {
+    /// Initialize the composite type metadata tables
+    pub fn init(conn: &Connection) -> Result<()> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS __pgsqlite_composite_types (
+                type_oid INTEGER PRIMARY KEY,
+                type_name TEXT NOT NULL UNIQUE,
+                relation_oid INTEGER NOT NULL,
+                namespace_oid INTEGER DEFAULT 2200 -- public schema
+            );
+
+            CREATE TABLE IF NOT EXISTS __pgsqlite_composite_attributes (
+                type_oid INTEGER NOT NULL,
+                attnum INTEGER NOT NULL,
+                attname TEXT NOT NULL,
+                atttype TEXT NOT NULL,
+                PRIMARY KEY (type_oid, attnum),
+                FOREIGN KEY (type_oid) REFERENCES __pgsqlite_composite_types(type_oid)
+            );"
+        )
+    }
+
+    /// The OID a composite type's allocation starts from, based on its name
+    pub fn generate_type_oid(type_name: &str) -> i32 {
+        let mut hasher = DefaultHasher::new();
+        type_name.hash(&mut hasher);
+        let hash = hasher.finish() as i32;
+        COMPOSITE_TYPE_OID_OFFSET + (hash.abs() % 1000000)
+    }
+
+    /// The OID allocation starts from for the relation whose pg_attribute rows describe the fields
+    fn generate_relation_oid(type_oid: i32) -> i32 {
+        let mut hasher = DefaultHasher::new();
+        type_oid.hash(&mut hasher);
+        let hash = hasher.finish() as i32;
+        COMPOSITE_RELATION_OID_OFFSET + (hash.abs() % 1000000)
+    }
+
+    /// Create a composite type with the given (name, type) fields
+    pub fn create_type(conn: &Connection, type_name: &str, fields: &[(String, String)]) -> Result<i32> {
+        Self::init(conn)?;
+
+        let tx = conn.unchecked_transaction()?;
+        let type_oid = OidAllocator::assign_from(&tx, TYPE, type_name, Self::generate_type_oid(type_name).into())? as i32;
+        let relation_oid = OidAllocator::assign_from(
+            &tx, COMPOSITE_RELATION, type_name, Self::generate_relation_oid(type_oid).into(),
+        )? as i32;
+        OidAllocator::assign_array_type(&tx, type_name, type_oid.into())?;
+        tx.execute(
+            "INSERT INTO __pgsqlite_composite_types (type_oid, type_name, relation_oid) VALUES (?1, ?2, ?3)",
+            params![type_oid, type_name, relation_oid],
+        )?;
+        for (i, (name, pg_type)) in fields.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO __pgsqlite_composite_attributes (type_oid, attnum, attname, atttype) VALUES (?1, ?2, ?3, ?4)",
+                params![type_oid, i as i32 + 1, name, pg_type],
+            )?;
+        }
+        tx.commit()?;
+
+        Ok(type_oid)
+    }
+
+    /// Get a composite type and its fields by name
+    pub fn get_type(conn: &Connection, type_name: &str) -> Result<Option<CompositeType>> {
+        let row = conn.query_row(
+            "SELECT type_oid, type_name, relation_oid, namespace_oid FROM __pgsqlite_composite_types WHERE type_name = ?1",
+            [type_name.to_lowercase()],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        ).optional();
+
+        let (type_oid, type_name, relation_oid, namespace_oid) = match row {
+            Ok(Some(row)) => row,
+            Ok(None) => return Ok(None),
+            // Databases without any composite types yet
+            Err(rusqlite::Error::SqliteFailure(_, Some(msg))) if msg.contains("no such table") => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        Ok(Some(CompositeType {
+            type_oid,
+            type_name,
+            relation_oid,
+            namespace_oid,
+            attributes: Self::get_attributes(conn, type_oid)?,
+        }))
+    }
+
+    /// Get all composite types, ordered by name
+    pub fn get_all_types(conn: &Connection) -> Result<Vec<CompositeType>> {
+        let names: Vec<String> = match conn.prepare("SELECT type_name FROM __pgsqlite_composite_types ORDER BY type_name") {
+            Ok(mut stmt) => stmt.query_map([], |row| row.get(0))?.collect::<Result<_>>()?,
+            Err(rusqlite::Error::SqliteFailure(_, Some(msg))) if msg.contains("no such table") => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut types = Vec::with_capacity(names.len());
+        for name in names {
+            if let Some(composite) = Self::get_type(conn, &name)? {
+                types.push(composite);
+            }
+        }
+        Ok(types)
+    }
+
+    fn get_attributes(conn: &Connection, type_oid: i32) -> Result<Vec<CompositeAttribute>> {
+        let mut stmt = conn.prepare(
+            "SELECT attnum, attname, atttype FROM __pgsqlite_composite_attributes WHERE type_oid = ?1 ORDER BY attnum"
+        )?;
+        stmt.query_map([type_oid], |row| {
+            Ok(CompositeAttribute {
+                attnum: row.get(0)?,
+                name: row.get(1)?,
+                pg_type: row.get(2)?,
+            })
+        })?.collect()
+    }
+
+    /// Get the (table, column) pairs of existing tables declared with a composite type
+    pub fn get_type_usage(conn: &Connection, type_name: &str) -> Result<Vec<(String, String)>> {
+        // __pgsqlite_schema keeps the rows of dropped tables; array columns use the type too
+        let mut stmt = conn.prepare(
+            "SELECT table_name, column_name FROM __pgsqlite_schema
+             WHERE rtrim(lower(pg_type), '[]') = ?1 AND table_name IN (SELECT name FROM sqlite_master WHERE type = 'table')
+             ORDER BY table_name, column_name"
+        )?;
+        stmt.query_map([type_name.to_lowercase()], |row| Ok((row.get(0)?, row.get(1)?)))?.collect()
+    }
+
+    /// Get every column declared with a composite type, with its type name
+    pub fn get_composite_columns(conn: &Connection) -> Result<Vec<(String, String)>> {
+        let stmt = conn.prepare(
+            "SELECT DISTINCT s.column_name, c.type_name FROM __pgsqlite_schema s
+             JOIN __pgsqlite_composite_types c ON c.type_name = lower(s.pg_type)"
+        );
+        match stmt {
+            Ok(mut stmt) => stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?.collect(),
+            Err(rusqlite::Error::SqliteFailure(_, Some(msg))) if msg.contains("no such table") => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Drop a composite type's metadata
+    pub fn drop_type(conn: &Connection, type_oid: i32) -> Result<()> {
+        let relation: Option<(i32, String)> = conn.query_row(
+            "SELECT relation_oid, type_name FROM __pgsqlite_composite_types WHERE type_oid = ?1", [type_oid],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).optional()?;
+        if let Some((relation_oid, type_name)) = relation {
+            let array_oid = OidAllocator::lookup(conn, ARRAY_TYPE, &type_name)?;
+            for oid in array_oid.into_iter().chain([relation_oid.into()]) {
+                OidAllocator::release(conn, oid)?;
+            }
+        }
+        OidAllocator::release(conn, type_oid.into())?;
+        conn.execute("DELETE FROM __pgsqlite_composite_attributes WHERE type_oid = ?1", [type_oid])?;
+        conn.execute("DELETE FROM __pgsqlite_composite_types WHERE type_oid = ?1", [type_oid])?;
+        Ok(())
+    }
+
+    /// Create BEFORE INSERT/UPDATE triggers rejecting values that are not
+    /// record literals with the type's number of fields (22P02)
+    pub fn create_validation_triggers(
+        conn: &Connection,
+        table_name: &str,
+        column_name: &str,
+        composite: &CompositeType,
+    ) -> Result<()> {
+        let field_count = composite.attributes.len();
+        for (event, action) in [("insert", "INSERT".to_string()), ("update", format!("UPDATE OF \"{column_name}\""))] {
+            let trigger_sql = format!(
+                r#"CREATE TRIGGER IF NOT EXISTS "__pgsqlite_composite_{event}_{table_name}_{column_name}"
+                BEFORE {action} ON "{table_name}"
+                FOR EACH ROW
+                WHEN NEW."{column_name}" IS NOT NULL AND NOT pg_record_is_valid(NEW."{column_name}", {field_count})
+                BEGIN
+                    SELECT RAISE(ABORT, 'malformed record literal: "' || NEW."{column_name}" || '"');
+                END"#
+            );
+            conn.execute(&trigger_sql, [])?;
+        }
+        Ok(())
+    }
+
+    /// Create the validation triggers of a column holding arrays of a composite type,
+    /// which are stored as JSON arrays of record literals; other values are left alone
+    pub fn create_array_validation_triggers(
+        conn: &Connection,
+        table_name: &str,
+        column_name: &str,
+        composite: &CompositeType,
+    ) -> Result<()> {
+        let field_count = composite.attributes.len();
+        let invalid_record = format!(
+            r#"SELECT j.value FROM json_tree(NEW."{column_name}") j
+                WHERE j.type = 'text' AND NOT pg_record_is_valid(j.value, {field_count})"#
+        );
+        for (event, action) in [("insert", "INSERT".to_string()), ("update", format!("UPDATE OF \"{column_name}\""))] {
+            let trigger_sql = format!(
+                r#"CREATE TRIGGER IF NOT EXISTS "__pgsqlite_composite_{event}_{table_name}_{column_name}"
+                BEFORE {action} ON "{table_name}"
+                FOR EACH ROW
+                WHEN json_valid(NEW."{column_name}") AND EXISTS ({invalid_record})
+                BEGIN
+                    SELECT RAISE(ABORT, 'malformed record literal: "' || ({invalid_record} LIMIT 1) || '"');
+                END"#
+            );
+            conn.execute(&trigger_sql, [])?;
+        }
+        Ok(())
+    }
+
+    /// Drop the validation triggers of a composite column
+    pub fn drop_validation_triggers(conn: &Connection, table_name: &str, column_name: &str) -> Result<()> {
+        for event in ["insert", "update"] {
+            conn.execute(&format!("DROP TRIGGER IF EXISTS \"__pgsqlite_composite_{event}_{table_name}_{column_name}\""), [])?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_and_get_composite_type() {
+        let conn = Connection::open_in_memory().unwrap();
+        let fields = vec![
+            ("street".to_string(), "text".to_string()),
+            ("zip".to_string(), "integer".to_string()),
+        ];
+        let type_oid = CompositeTypes::create_type(&conn, "address", &fields).unwrap();
+        assert_eq!(type_oid, CompositeTypes::generate_type_oid("address"));
+
+        let composite = CompositeTypes::get_type(&conn, "Address").unwrap().unwrap();
+        assert_eq!(composite.type_name, "address");
+        assert_ne!(composite.relation_oid, composite.type_oid);
+        assert_eq!(composite.attribute("ZIP"), Some(&CompositeAttribute {
+            attnum: 2,
+            name: "zip".to_string(),
+            pg_type: "integer".to_string(),
+        }));
+        assert_eq!(CompositeTypes::get_all_types(&conn).unwrap().len(), 1);
+
+        CompositeTypes::drop_type(&conn, type_oid).unwrap();
+        assert!(CompositeTypes::get_type(&conn, "address").unwrap().is_none());
+    }
+}
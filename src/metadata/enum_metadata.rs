@@ -1,6 +1,7 @@
 use rusqlite::{Connection, Result, params, OptionalExtension};
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
+use super::oid_allocator::{OidAllocator, ARRAY_TYPE, ENUM_VALUE, TYPE};
 
 /// Offset for generated ENUM type OIDs to avoid conflicts with built-in types
 const ENUM_TYPE_OID_OFFSET: i32 = 10000;
@@ -55,7 +56,7 @@ impl EnumMetadata {
         Ok(())
     }
     
-    /// Generate a stable OID for an ENUM type based on its name
+    /// The OID an ENUM type's allocation starts from, based on its name
     pub fn generate_type_oid(type_name: &str) -> i32 {
         let mut hasher = DefaultHasher::new();
         type_name.hash(&mut hasher);
@@ -63,7 +64,7 @@ impl EnumMetadata {
         ENUM_TYPE_OID_OFFSET + (hash.abs() % 1000000)
     }
     
-    /// Generate a stable OID for an ENUM value based on type OID and label
+    /// The OID an ENUM value's allocation starts from, based on type OID and label
     pub fn generate_value_oid(type_oid: i32, label: &str) -> i32 {
         let mut hasher = DefaultHasher::new();
         type_oid.hash(&mut hasher);
@@ -72,6 +73,11 @@ impl EnumMetadata {
         ENUM_VALUE_OID_OFFSET + (hash.abs() % 1000000)
     }
     
+    fn allocate_value_oid(conn: &Connection, type_name: &str, type_oid: i32, label: &str) -> Result<i32> {
+        let seed = Self::generate_value_oid(type_oid, label).into();
+        Ok(OidAllocator::assign_from(conn, ENUM_VALUE, &format!("{type_name}.{label}"), seed)? as i32)
+    }
+    
     /// Create a new ENUM type with its values
     pub fn create_enum_type(
         conn: &mut Connection,
@@ -84,8 +90,8 @@ impl EnumMetadata {
         
         let tx = conn.transaction()?;
         
-        // Generate type OID
-        let type_oid = Self::generate_type_oid(type_name);
+        let type_oid = OidAllocator::assign_from(&tx, TYPE, type_name, Self::generate_type_oid(type_name).into())? as i32;
+        OidAllocator::assign_array_type(&tx, type_name, type_oid.into())?;
         let ns_oid = namespace_oid.unwrap_or(2200); // default to public schema
         
         // Insert type definition
@@ -97,7 +103,7 @@ impl EnumMetadata {
         
         // Insert values with sort order
         for (i, label) in values.iter().enumerate() {
-            let value_oid = Self::generate_value_oid(type_oid, label);
+            let value_oid = Self::allocate_value_oid(&tx, type_name, type_oid, label)?;
             let sort_order = (i + 1) as f64;
             
             tx.execute(
@@ -185,7 +191,7 @@ impl EnumMetadata {
         };
         
         // Insert new value
-        let value_oid = Self::generate_value_oid(type_oid, new_value);
+        let value_oid = Self::allocate_value_oid(&tx, type_name, type_oid, new_value)?;
         tx.execute(
             "INSERT INTO __pgsqlite_enum_values (value_oid, type_oid, label, sort_order) 
              VALUES (?1, ?2, ?3, ?4)",
@@ -196,6 +202,17 @@ impl EnumMetadata {
         Ok(())
     }
     
+    /// Rename a value of an ENUM type, keeping its OID and position
+    pub fn rename_enum_value(conn: &Connection, type_name: &str, old_label: &str, new_label: &str) -> Result<()> {
+        conn.execute(
+            "UPDATE __pgsqlite_enum_values SET label = ?3
+             WHERE label = ?2 AND type_oid = (SELECT type_oid FROM __pgsqlite_enum_types WHERE type_name = ?1)",
+            params![type_name, old_label, new_label],
+        )?;
+        OidAllocator::rename(conn, ENUM_VALUE, &format!("{type_name}.{old_label}"), &format!("{type_name}.{new_label}"))?;
+        Ok(())
+    }
+    
     /// Get ENUM type information by name
     pub fn get_enum_type(conn: &Connection, type_name: &str) -> Result<Option<EnumType>> {
         // Check if tables exist first
@@ -309,7 +326,8 @@ impl EnumMetadata {
     
     /// Drop an ENUM type and all its values
     pub fn drop_enum_type(conn: &mut Connection, type_name: &str) -> Result<()> {
-        let tx = conn.transaction()?;
+        // A savepoint, as DROP SCHEMA ... CASCADE drops types inside its own
+        let tx = conn.savepoint()?;
         
         // Get type OID
         let type_oid: i32 = tx.query_row(
@@ -318,6 +336,14 @@ impl EnumMetadata {
             |row| row.get(0),
         )?;
         
+        let value_oids: Vec<i32> = tx.prepare("SELECT value_oid FROM __pgsqlite_enum_values WHERE type_oid = ?1")?
+            .query_map([type_oid], |row| row.get(0))?
+            .collect::<Result<_>>()?;
+        let array_oid = OidAllocator::lookup(&tx, ARRAY_TYPE, type_name)?;
+        for oid in value_oids.into_iter().map(i64::from).chain([type_oid.into()]).chain(array_oid) {
+            OidAllocator::release(&tx, oid)?;
+        }
+        
         // Delete values first (foreign key constraint)
         tx.execute(
             "DELETE FROM __pgsqlite_enum_values WHERE type_oid = ?1",
@@ -418,5 +444,22 @@ mod tests {
         assert_eq!(values[0].label, "happy");
         assert_eq!(values[1].label, "sad");
         assert_eq!(values[2].label, "angry");
+    }    
+    #[test]
+    fn test_rename_enum_value() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        EnumMetadata::init(&conn).unwrap();
+        
+        let type_oid = EnumMetadata::create_enum_type(&mut conn, "mood", &["happy", "sad"], None).unwrap();
+        let sad = EnumMetadata::get_enum_value(&conn, type_oid, "sad").unwrap().unwrap();
+        
+        EnumMetadata::rename_enum_value(&conn, "mood", "sad", "blue").unwrap();
+        
+        // The value keeps its OID and position
+        let blue = EnumMetadata::get_enum_value(&conn, type_oid, "blue").unwrap().unwrap();
+        assert_eq!(blue.value_oid, sad.value_oid);
+        assert_eq!(blue.sort_order, sad.sort_order);
+        assert!(EnumMetadata::get_enum_value(&conn, type_oid, "sad").unwrap().is_none());
+        assert_eq!(OidAllocator::lookup(&conn, ENUM_VALUE, "mood.blue").unwrap(), Some(sad.value_oid.into()));
     }
 }
\ No newline at end of file
@@ -87,6 +87,59 @@ impl EnumTriggers {
         Ok(())
     }
     
+    /// Create validation triggers for a column holding arrays of an ENUM type. They
+    /// share the names of the triggers of plain ENUM columns, so they are dropped alike.
+    pub fn create_enum_array_validation_triggers(
+        conn: &Connection,
+        table_name: &str,
+        column_name: &str,
+        enum_type: &str,
+    ) -> Result<(), PgSqliteError> {
+        // Arrays are stored as JSON, so every string leaf is a label. Values that are not
+        // JSON, like array literals UPDATE stores as written, are left alone.
+        let invalid_label = format!(
+            r#"SELECT j.value FROM json_tree(NEW."{column_name}") j
+                WHERE j.type = 'text' AND NOT EXISTS (
+                    SELECT 1 FROM __pgsqlite_enum_values ev
+                    JOIN __pgsqlite_enum_types et ON ev.type_oid = et.type_oid
+                    WHERE et.type_name = '{enum_type}' AND ev.label = j.value
+                )"#
+        );
+        
+        for (event, action) in [("insert", "INSERT".to_string()), ("update", format!(r#"UPDATE OF "{column_name}""#))] {
+            let trigger_name = format!("__pgsqlite_{table_name}_{column_name}_{enum_type}_{event}_check");
+            let trigger_sql = format!(
+                r#"CREATE TRIGGER IF NOT EXISTS "{trigger_name}"
+                BEFORE {action} ON "{table_name}"
+                FOR EACH ROW
+                WHEN json_valid(NEW."{column_name}") AND EXISTS ({invalid_label})
+                BEGIN
+                    SELECT RAISE(ABORT, 'invalid input value for enum {enum_type}: "' || ({invalid_label} LIMIT 1) || '"');
+                END"#
+            );
+            conn.execute(&trigger_sql, [])
+                .map_err(|e| PgSqliteError::Protocol(format!("Failed to create {} trigger: {e}", event.to_uppercase())))?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Record the usage and create the validation triggers of an array column whose
+    /// element type is an ENUM, returning false for other element types
+    pub fn create_array_column_triggers(
+        conn: &Connection,
+        table_name: &str,
+        column_name: &str,
+        element_type: &str,
+    ) -> Result<bool, PgSqliteError> {
+        if !super::EnumMetadata::is_enum_type(conn, element_type)? {
+            return Ok(false);
+        }
+        Self::record_enum_usage(conn, table_name, column_name, element_type)?;
+        Self::create_enum_array_validation_triggers(conn, table_name, column_name, element_type)?;
+        Ok(true)
+    }
+    
     /// Drop validation triggers for an ENUM column
     pub fn drop_enum_validation_triggers(
         conn: &Connection,
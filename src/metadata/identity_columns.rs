@@ -0,0 +1,358 @@
+use rusqlite::{Connection, OptionalExtension, params};
+use crate::error::PgError;
+use crate::PgSqliteError;
+use sqlparser::ast::{Expr, SetExpr, Statement, TableObject};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+
+/// A column whose values are generated by SERIAL or GENERATED ... AS IDENTITY
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdentityColumn {
+    pub column_name: String,
+    /// "serial", "always" or "by default"
+    pub kind: String,
+    /// True when the column became the table's INTEGER PRIMARY KEY AUTOINCREMENT rowid alias.
+    /// Otherwise values are assigned by a backing trigger.
+    pub is_rowid_alias: bool,
+}
+
+/// Manages SERIAL/identity column metadata and the triggers that back non-rowid columns
+pub struct IdentityColumns;
+
+impl IdentityColumns {
+    /// Initialize the identity column metadata table
+    pub fn init(conn: &Connection) -> Result<(), PgSqliteError> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS __pgsqlite_identity_columns (
+                table_name TEXT NOT NULL,
+                column_name TEXT NOT NULL,
+                identity_kind TEXT NOT NULL,
+                is_rowid_alias INTEGER NOT NULL DEFAULT 0,
+                last_value INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (table_name, column_name)
+            )",
+            [],
+        ).map_err(|e| PgSqliteError::Protocol(format!("Failed to create identity columns table: {e}")))?;
+
+        Ok(())
+    }
+
+    /// Record the identity columns of a newly created table and create their triggers
+    pub fn record_identity_columns(
+        conn: &Connection,
+        table_name: &str,
+        columns: &[IdentityColumn],
+    ) -> Result<(), PgSqliteError> {
+        Self::init(conn)?;
+        Self::clean_identity_columns_for_table(conn, table_name)?;
+
+        for column in columns {
+            conn.execute(
+                "INSERT OR REPLACE INTO __pgsqlite_identity_columns (table_name, column_name, identity_kind, is_rowid_alias)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![table_name, column.column_name, column.kind, column.is_rowid_alias],
+            ).map_err(|e| PgSqliteError::Protocol(format!("Failed to record identity column: {e}")))?;
+        }
+
+        Self::create_identity_triggers(conn, table_name, columns)
+    }
+
+    /// Create the triggers that implement the identity columns of a table.
+    ///
+    /// SQLite only auto-assigns values to the INTEGER PRIMARY KEY column, so every other
+    /// identity column gets an AFTER INSERT trigger that fills it from a counter kept in
+    /// the metadata table when omitted. Like a PostgreSQL sequence, the counter never
+    /// goes back, so deleted values are not reused. GENERATED ALWAYS columns also get
+    /// triggers that reject explicit values.
+    pub fn create_identity_triggers(
+        conn: &Connection,
+        table_name: &str,
+        columns: &[IdentityColumn],
+    ) -> Result<(), PgSqliteError> {
+        for column in columns {
+            if !column.is_rowid_alias {
+                Self::create_sequence_trigger(conn, table_name, &column.column_name)?;
+            }
+            if column.kind == "always" {
+                Self::create_always_triggers(conn, table_name, column)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Create the trigger that assigns the next value to a non-rowid identity column
+    fn create_sequence_trigger(
+        conn: &Connection,
+        table_name: &str,
+        column_name: &str,
+    ) -> Result<(), PgSqliteError> {
+        let trigger_name = format!("__pgsqlite_{table_name}_{column_name}_identity");
+        let counter = format!(
+            "table_name = '{}' AND column_name = '{}'",
+            table_name.replace('\'', "''"),
+            column_name.replace('\'', "''"),
+        );
+
+        let trigger_sql = format!(
+            r#"CREATE TRIGGER IF NOT EXISTS "{trigger_name}"
+            AFTER INSERT ON "{table_name}"
+            FOR EACH ROW
+            WHEN NEW."{column_name}" IS NULL
+            BEGIN
+                UPDATE __pgsqlite_identity_columns SET last_value = last_value + 1 WHERE {counter};
+                UPDATE "{table_name}" SET "{column_name}" = (
+                    SELECT last_value FROM __pgsqlite_identity_columns WHERE {counter}
+                ) WHERE rowid = NEW.rowid;
+            END"#
+        );
+
+        conn.execute(&trigger_sql, [])
+            .map_err(|e| PgSqliteError::Protocol(format!("Failed to create identity trigger: {e}")))?;
+
+        Ok(())
+    }
+
+    /// Create the triggers that reject explicit values for a GENERATED ALWAYS column.
+    /// A BEFORE INSERT trigger can't tell an omitted rowid alias from an explicit one, so
+    /// inserts into those are only checked by `check_insert`.
+    fn create_always_triggers(
+        conn: &Connection,
+        table_name: &str,
+        column: &IdentityColumn,
+    ) -> Result<(), PgSqliteError> {
+        let column_name = &column.column_name;
+        let insert_trigger = if column.is_rowid_alias {
+            String::new()
+        } else {
+            format!(
+                r#"CREATE TRIGGER IF NOT EXISTS "__pgsqlite_{table_name}_{column_name}_always_insert"
+            BEFORE INSERT ON "{table_name}"
+            FOR EACH ROW
+            WHEN NEW."{column_name}" IS NOT NULL
+            BEGIN
+                SELECT RAISE(ABORT, 'cannot insert a non-DEFAULT value into column "{column_name}"');
+            END;
+            "#
+            )
+        };
+
+        let trigger_sql = format!(
+            r#"{insert_trigger}CREATE TRIGGER IF NOT EXISTS "__pgsqlite_{table_name}_{column_name}_always_update"
+            BEFORE UPDATE OF "{column_name}" ON "{table_name}"
+            FOR EACH ROW
+            WHEN OLD."{column_name}" IS NOT NULL AND NEW."{column_name}" IS NOT OLD."{column_name}"
+            BEGIN
+                SELECT RAISE(ABORT, 'column "{column_name}" can only be updated to DEFAULT');
+            END;"#
+        );
+
+        conn.execute_batch(&trigger_sql)
+            .map_err(|e| PgSqliteError::Protocol(format!("Failed to create identity trigger: {e}")))?;
+
+        Ok(())
+    }
+
+    /// Reject an INSERT that gives a GENERATED ALWAYS column a value other than DEFAULT,
+    /// as PostgreSQL does. The statement's column list decides, since SQLite hands the
+    /// triggers no way to tell an omitted value from an explicit one.
+    pub fn check_insert(conn: &Connection, query: &str) -> Result<(), PgSqliteError> {
+        let is_insert = query.trim_start().get(..6).is_some_and(|word| word.eq_ignore_ascii_case("INSERT"));
+        if !is_insert {
+            return Ok(());
+        }
+        let Ok(statements) = Parser::parse_sql(&PostgreSqlDialect {}, query) else {
+            return Ok(());
+        };
+        let [Statement::Insert(insert)] = statements.as_slice() else {
+            return Ok(());
+        };
+        let TableObject::TableName(name) = &insert.table else {
+            return Ok(());
+        };
+        let table_name = crate::query::audit_handler::sqlite_name(&name.to_string());
+        let always = Self::always_columns(conn, &table_name)?;
+        if always.is_empty() {
+            return Ok(());
+        }
+
+        // Without a column list, values go to the table's columns in order
+        let columns: Vec<String> = if insert.columns.is_empty() {
+            let mut stmt = conn.prepare(&format!("PRAGMA table_info(\"{}\")", table_name.replace('"', "\"\"")))
+                .map_err(|e| PgSqliteError::Protocol(format!("Failed to read table columns: {e}")))?;
+            stmt.query_map([], |row| row.get(1))
+                .and_then(|rows| rows.collect())
+                .map_err(|e| PgSqliteError::Protocol(format!("Failed to read table columns: {e}")))?
+        } else {
+            insert.columns.iter().map(|column| column.value.clone()).collect()
+        };
+        let rows = match insert.source.as_deref().map(|source| source.body.as_ref()) {
+            // DEFAULT VALUES
+            None => return Ok(()),
+            Some(SetExpr::Values(values)) => Some(&values.rows),
+            Some(_) => None,
+        };
+
+        for column_name in &always {
+            let Some(position) = columns.iter().position(|column| column.eq_ignore_ascii_case(column_name)) else {
+                continue;
+            };
+            // Only DEFAULT, or no value at all in a VALUES row shorter than the column list
+            let defaulted = rows.is_some_and(|rows| rows.iter().all(|row| {
+                row.get(position).is_none_or(|value| {
+                    matches!(value, Expr::Identifier(ident) if ident.quote_style.is_none() && ident.value.eq_ignore_ascii_case("DEFAULT"))
+                })
+            }));
+            if !defaulted {
+                return Err(PgSqliteError::Validation(PgError::Generic {
+                    code: "428C9".to_string(), // generated_always
+                    message: format!("cannot insert a non-DEFAULT value into column \"{column_name}\""),
+                }));
+            }
+        }
+        Ok(())
+    }
+
+    /// The GENERATED ALWAYS columns of a table
+    fn always_columns(conn: &Connection, table_name: &str) -> Result<Vec<String>, PgSqliteError> {
+        let columns = conn.prepare(
+            "SELECT column_name FROM __pgsqlite_identity_columns
+             WHERE table_name = ?1 COLLATE NOCASE AND identity_kind = 'always'",
+        ).and_then(|mut stmt| stmt.query_map([table_name], |row| row.get(0))?.collect());
+        match columns {
+            Ok(columns) => Ok(columns),
+            // Databases created before the identity metadata table existed
+            Err(rusqlite::Error::SqliteFailure(_, Some(msg))) if msg.contains("no such table") => Ok(Vec::new()),
+            Err(e) => Err(PgSqliteError::Protocol(format!("Failed to query identity columns: {e}"))),
+        }
+    }
+
+    /// Get the identity kind ("serial", "always" or "by default") of a column, if any
+    pub fn get_identity_kind(
+        conn: &Connection,
+        table_name: &str,
+        column_name: &str,
+    ) -> Result<Option<String>, PgSqliteError> {
+        let kind = conn.query_row(
+            "SELECT identity_kind FROM __pgsqlite_identity_columns WHERE table_name = ?1 AND column_name = ?2",
+            params![table_name, column_name],
+            |row| row.get(0),
+        ).optional();
+
+        match kind {
+            Ok(kind) => Ok(kind),
+            // Databases created before the identity metadata table existed
+            Err(rusqlite::Error::SqliteFailure(_, Some(msg))) if msg.contains("no such table") => Ok(None),
+            Err(e) => Err(PgSqliteError::Protocol(format!("Failed to query identity column: {e}"))),
+        }
+    }
+
+    /// Clean up identity metadata when a table is dropped
+    pub fn clean_identity_columns_for_table(
+        conn: &Connection,
+        table_name: &str,
+    ) -> Result<(), PgSqliteError> {
+        Self::init(conn)?;
+        conn.execute(
+            "DELETE FROM __pgsqlite_identity_columns WHERE table_name = ?1",
+            params![table_name],
+        ).map_err(|e| PgSqliteError::Protocol(format!("Failed to clean identity columns: {e}")))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_identity_columns() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE items (id INTEGER PRIMARY KEY AUTOINCREMENT, seq INTEGER, name TEXT)", []).unwrap();
+
+        IdentityColumns::record_identity_columns(&conn, "items", &[
+            IdentityColumn { column_name: "id".to_string(), kind: "serial".to_string(), is_rowid_alias: true },
+            IdentityColumn { column_name: "seq".to_string(), kind: "by default".to_string(), is_rowid_alias: false },
+        ]).unwrap();
+
+        assert_eq!(IdentityColumns::get_identity_kind(&conn, "items", "id").unwrap(), Some("serial".to_string()));
+        assert_eq!(IdentityColumns::get_identity_kind(&conn, "items", "seq").unwrap(), Some("by default".to_string()));
+        assert_eq!(IdentityColumns::get_identity_kind(&conn, "items", "name").unwrap(), None);
+
+        // The trigger fills in omitted values and leaves explicit ones alone
+        conn.execute("INSERT INTO items (name) VALUES ('a')", []).unwrap();
+        conn.execute("INSERT INTO items (name) VALUES ('b')", []).unwrap();
+        conn.execute("INSERT INTO items (seq, name) VALUES (10, 'c')", []).unwrap();
+        conn.execute("INSERT INTO items (name) VALUES ('d')", []).unwrap();
+
+        // Values are never handed out twice, even after the rows holding them are gone
+        conn.execute("DELETE FROM items WHERE name = 'd'", []).unwrap();
+        conn.execute("INSERT INTO items (name) VALUES ('e')", []).unwrap();
+
+        let seqs: Vec<i64> = conn.prepare("SELECT seq FROM items ORDER BY id").unwrap()
+            .query_map([], |row| row.get(0)).unwrap()
+            .collect::<Result<_, _>>().unwrap();
+        assert_eq!(seqs, vec![1, 2, 10, 4]);
+    }
+
+    #[test]
+    fn test_generated_always_rejects_explicit_values() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE items (id INTEGER PRIMARY KEY AUTOINCREMENT, seq INTEGER, name TEXT)", []).unwrap();
+
+        IdentityColumns::record_identity_columns(&conn, "items", &[
+            IdentityColumn { column_name: "id".to_string(), kind: "always".to_string(), is_rowid_alias: true },
+            IdentityColumn { column_name: "seq".to_string(), kind: "always".to_string(), is_rowid_alias: false },
+        ]).unwrap();
+
+        conn.execute("INSERT INTO items (name) VALUES ('a')", []).unwrap();
+        let error = conn.execute("INSERT INTO items (seq, name) VALUES (5, 'b')", []).unwrap_err();
+        assert!(error.to_string().contains("cannot insert a non-DEFAULT value into column \"seq\""), "{error}");
+        let error = conn.execute("UPDATE items SET seq = 7", []).unwrap_err();
+        assert!(error.to_string().contains("column \"seq\" can only be updated to DEFAULT"), "{error}");
+
+        conn.execute("UPDATE items SET name = 'z'", []).unwrap();
+        let row: (i64, i64) = conn.query_row("SELECT id, seq FROM items", [], |row| Ok((row.get(0)?, row.get(1)?))).unwrap();
+        assert_eq!(row, (1, 1));
+    }
+
+    #[test]
+    fn test_check_insert() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE items (id INTEGER PRIMARY KEY AUTOINCREMENT, seq INTEGER, name TEXT)", []).unwrap();
+        IdentityColumns::record_identity_columns(&conn, "items", &[
+            IdentityColumn { column_name: "id".to_string(), kind: "always".to_string(), is_rowid_alias: true },
+            IdentityColumn { column_name: "seq".to_string(), kind: "by default".to_string(), is_rowid_alias: false },
+        ]).unwrap();
+
+        for query in [
+            "INSERT INTO items (name) VALUES ('a')",
+            "INSERT INTO items (seq, name) VALUES (5, 'a')",
+            "INSERT INTO items (id, name) VALUES (DEFAULT, 'a'), (DEFAULT, 'b')",
+            "INSERT INTO items DEFAULT VALUES",
+            "insert into ITEMS values (default, 1, 'a')",
+            "INSERT INTO other (id) VALUES (5)",
+            "SELECT * FROM items",
+        ] {
+            assert!(IdentityColumns::check_insert(&conn, query).is_ok(), "{query}");
+        }
+        for query in [
+            "INSERT INTO items (id, name) VALUES (5, 'a')",
+            // -1 is what an omitted rowid alias reads as in a trigger
+            "INSERT INTO items (id, name) VALUES (-1, 'a')",
+            "INSERT INTO items (id, name) VALUES (DEFAULT, 'a'), (7, 'b')",
+            "INSERT INTO items VALUES (-1, 1, 'a')",
+            "INSERT INTO items (\"ID\", name) SELECT 1, 'a'",
+        ] {
+            let error = IdentityColumns::check_insert(&conn, query).unwrap_err();
+            assert!(error.to_string().contains("cannot insert a non-DEFAULT value into column \"id\""), "{query}: {error}");
+        }
+    }
+
+    #[test]
+    fn test_get_identity_kind_without_table() {
+        let conn = Connection::open_in_memory().unwrap();
+        assert_eq!(IdentityColumns::get_identity_kind(&conn, "items", "id").unwrap(), None);
+    }
+}
@@ -1,10 +1,18 @@
 use rusqlite::{Connection, Result};
 use std::collections::HashMap;
 
+pub mod composite_types;
 pub mod enum_metadata;
 pub mod enum_triggers;
+pub mod identity_columns;
+pub mod namespaces;
+pub mod oid_allocator;
+pub use composite_types::{CompositeAttribute, CompositeType, CompositeTypes};
 pub use enum_metadata::{EnumMetadata, EnumType, EnumValue};
 pub use enum_triggers::EnumTriggers;
+pub use identity_columns::{IdentityColumn, IdentityColumns};
+pub use namespaces::Namespaces;
+pub use oid_allocator::OidAllocator;
 
 /// Represents a type mapping between PostgreSQL and SQLite
 #[derive(Debug, Clone)]
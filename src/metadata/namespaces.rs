@@ -0,0 +1,216 @@
+use rusqlite::{Connection, params};
+use super::oid_allocator::{self, OidAllocator};
+
+/// OID of the pg_catalog schema
+pub const PG_CATALOG_OID: i64 = 11;
+
+/// OID of the public schema
+pub const PUBLIC_OID: i64 = 2200;
+
+/// Joins a schema name to the name of an object in it
+const SEPARATOR: &str = "__";
+
+/// Start of the name of a session's temporary schema, `pg_temp_<backend pid>`
+const TEMP_SCHEMA_PREFIX: &str = "pg_temp_";
+
+/// Schemas created with CREATE SCHEMA, kept in `__pgsqlite_oids` under their OIDs.
+///
+/// SQLite has a single namespace, so an object in a schema other than public is
+/// stored under its schema and name joined by `__`: table `items` of schema
+/// `tenant1` is the SQLite table `tenant1__items`. Objects of the public schema
+/// keep their plain names.
+pub struct Namespaces;
+
+impl Namespaces {
+    /// The SQLite name of object `name` in `schema`
+    pub fn qualify(schema: &str, name: &str) -> String {
+        if schema == "public" || schema == "pg_catalog" {
+            name.to_string()
+        } else {
+            format!("{schema}{SEPARATOR}{name}")
+        }
+    }
+
+    /// The schema of the temporary tables of the session with this backend pid.
+    ///
+    /// Its tables live in SQLite's temp database of the session's connection, so
+    /// no other connection sees them and SQLite drops them when the session ends.
+    pub fn temp_schema(backend_pid: i32) -> String {
+        format!("{TEMP_SCHEMA_PREFIX}{backend_pid}")
+    }
+
+    /// Delete what the main database records about the temporary tables of a
+    /// connection that is about to close
+    pub fn forget_temp_tables(conn: &Connection) -> rusqlite::Result<()> {
+        let names: Vec<String> = {
+            let mut stmt = conn.prepare("SELECT name FROM sqlite_temp_master WHERE type = 'table'")?;
+            stmt.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?
+        };
+        if names.is_empty() {
+            return Ok(());
+        }
+        for table in Self::metadata_tables(conn)? {
+            let mut delete = conn.prepare(&format!("DELETE FROM main.\"{table}\" WHERE table_name = ?1"))?;
+            for name in &names {
+                delete.execute([name])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Delete the metadata of temporary tables left behind by sessions of a server
+    /// that did not shut down cleanly
+    pub fn sweep_temp_tables(conn: &Connection) -> rusqlite::Result<usize> {
+        let pattern = format!("{TEMP_SCHEMA_PREFIX}[0-9]*{SEPARATOR}*");
+        let mut swept = 0;
+        for table in Self::metadata_tables(conn)? {
+            swept += conn.execute(&format!("DELETE FROM main.\"{table}\" WHERE table_name GLOB ?1"), [&pattern])?;
+        }
+        Ok(swept)
+    }
+
+    /// The metadata tables that record columns or triggers by table name
+    fn metadata_tables(conn: &Connection) -> rusqlite::Result<Vec<String>> {
+        let mut stmt = conn.prepare(
+            r"SELECT m.name FROM main.sqlite_master m, pragma_table_info(m.name) c
+              WHERE m.type = 'table' AND m.name LIKE '\_\_pgsqlite\_%' ESCAPE '\' AND c.name = 'table_name'",
+        )?;
+        stmt.query_map([], |row| row.get(0))?.collect()
+    }
+
+    /// Every schema created with CREATE SCHEMA, with its OID
+    pub fn list(conn: &Connection) -> rusqlite::Result<Vec<(String, i64)>> {
+        let mut stmt = match conn.prepare("SELECT name, oid FROM __pgsqlite_oids WHERE kind = ?1 ORDER BY name") {
+            Ok(stmt) => stmt,
+            // Databases that predate the OID table have no schemas of their own
+            Err(rusqlite::Error::SqliteFailure(_, Some(message))) if message.contains("no such table") => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        stmt.query_map([oid_allocator::NAMESPACE], |row| Ok((row.get(0)?, row.get(1)?)))?.collect()
+    }
+
+    /// The OID of a schema, None if there is no such schema
+    pub fn oid(conn: &Connection, schema: &str) -> rusqlite::Result<Option<i64>> {
+        Ok(match schema {
+            "public" => Some(PUBLIC_OID),
+            "pg_catalog" => Some(PG_CATALOG_OID),
+            _ => OidAllocator::lookup(conn, oid_allocator::NAMESPACE, schema)?,
+        })
+    }
+
+    /// Record a new schema, returning its OID
+    pub fn create(conn: &Connection, schema: &str) -> rusqlite::Result<i64> {
+        OidAllocator::assign(conn, oid_allocator::NAMESPACE, schema)
+    }
+
+    /// Forget a dropped schema
+    pub fn drop(conn: &Connection, schema: &str) -> rusqlite::Result<()> {
+        conn.execute(
+            "DELETE FROM __pgsqlite_oids WHERE kind = ?1 AND name = ?2",
+            params![oid_allocator::NAMESPACE, schema],
+        )?;
+        Ok(())
+    }
+
+    /// The schema OID and the name within the schema of the object SQLite stores as `name`
+    pub fn split<'a>(schemas: &[(String, i64)], name: &'a str) -> (i64, &'a str) {
+        schemas.iter()
+            .filter_map(|(schema, oid)| {
+                let rest = name.strip_prefix(schema.as_str())?.strip_prefix(SEPARATOR)?;
+                (!rest.is_empty()).then_some((schema.len(), *oid, rest))
+            })
+            // With schemas `a` and `a__b`, `a__b__c` belongs to the longer one
+            .max_by_key(|(len, _, _)| *len)
+            .map(|(_, oid, rest)| (oid, rest))
+            .unwrap_or((PUBLIC_OID, name))
+    }
+
+    /// The `(type, name)` of every table and view SQLite stores for a schema, views first
+    pub fn relations(conn: &Connection, schema: &str) -> rusqlite::Result<Vec<(String, String)>> {
+        let schemas = Self::list(conn)?;
+        let mut stmt = conn.prepare(
+            "SELECT type, name FROM sqlite_master WHERE type IN ('table', 'view') AND name LIKE ?1 ESCAPE '\\'
+             ORDER BY type = 'table', rowid",
+        )?;
+        let pattern = format!("{}%", Self::qualify(schema, "").replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+        let relations: Vec<(String, String)> = stmt.query_map([pattern], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        let oid = Self::oid(conn, schema)?;
+        Ok(relations.into_iter().filter(|(_, name)| Some(Self::split(&schemas, name).0) == oid).collect())
+    }
+
+    /// The names of the enum and composite types SQLite stores for a schema
+    pub fn types(conn: &Connection, schema: &str) -> rusqlite::Result<Vec<String>> {
+        let schemas = Self::list(conn)?;
+        let oid = Self::oid(conn, schema)?;
+        let mut stmt = conn.prepare("SELECT name FROM __pgsqlite_oids WHERE kind = ?1 ORDER BY oid")?;
+        let names: Vec<String> = stmt.query_map([oid_allocator::TYPE], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+        Ok(names.into_iter().filter(|name| Some(Self::split(&schemas, name).0) == oid).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qualify_and_split() {
+        assert_eq!(Namespaces::qualify("public", "items"), "items");
+        assert_eq!(Namespaces::qualify("tenant1", "items"), "tenant1__items");
+
+        let schemas = vec![("a".to_string(), 20000), ("a__b".to_string(), 20001), ("tenant1".to_string(), 20002)];
+        assert_eq!(Namespaces::split(&schemas, "tenant1__items"), (20002, "items"));
+        assert_eq!(Namespaces::split(&schemas, "a__b__c"), (20001, "c"));
+        assert_eq!(Namespaces::split(&schemas, "a__c"), (20000, "c"));
+        assert_eq!(Namespaces::split(&schemas, "tenant2__items"), (PUBLIC_OID, "tenant2__items"));
+        assert_eq!(Namespaces::split(&schemas, "tenant1__"), (PUBLIC_OID, "tenant1__"));
+    }
+
+    #[test]
+    fn test_schema_objects() {
+        let conn = Connection::open_in_memory().unwrap();
+        assert!(Namespaces::list(&conn).unwrap().is_empty());
+        let oid = Namespaces::create(&conn, "tenant1").unwrap();
+        assert_eq!(Namespaces::oid(&conn, "tenant1").unwrap(), Some(oid));
+        assert_eq!(Namespaces::oid(&conn, "public").unwrap(), Some(PUBLIC_OID));
+        assert_eq!(Namespaces::oid(&conn, "missing").unwrap(), None);
+
+        conn.execute_batch(
+            "CREATE TABLE items (id INTEGER);
+             CREATE TABLE tenant1__items (id INTEGER);
+             CREATE VIEW tenant1__recent AS SELECT * FROM tenant1__items;
+             CREATE TABLE tenant1xitems (id INTEGER);"
+        ).unwrap();
+        OidAllocator::assign(&conn, oid_allocator::TYPE, "tenant1__mood").unwrap();
+        OidAllocator::assign(&conn, oid_allocator::TYPE, "mood").unwrap();
+        assert_eq!(Namespaces::relations(&conn, "tenant1").unwrap(), vec![
+            ("view".to_string(), "tenant1__recent".to_string()),
+            ("table".to_string(), "tenant1__items".to_string()),
+        ]);
+        assert_eq!(Namespaces::types(&conn, "tenant1").unwrap(), vec!["tenant1__mood".to_string()]);
+
+        Namespaces::drop(&conn, "tenant1").unwrap();
+        assert!(Namespaces::list(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_temp_table_metadata() {
+        let conn = Connection::open_in_memory().unwrap();
+        let staging = Namespaces::qualify(&Namespaces::temp_schema(7), "staging");
+        assert_eq!(staging, "pg_temp_7__staging");
+        conn.execute_batch(&format!(
+            "CREATE TABLE __pgsqlite_schema (table_name TEXT, column_name TEXT);
+             INSERT INTO __pgsqlite_schema VALUES ('items', 'id'), ('{staging}', 'id'), ('pg_temp_3__old', 'id');
+             CREATE TEMP TABLE {staging} (id INTEGER);"
+        )).unwrap();
+        let remaining = |conn: &Connection| -> Vec<String> {
+            let mut stmt = conn.prepare("SELECT table_name FROM __pgsqlite_schema ORDER BY table_name").unwrap();
+            stmt.query_map([], |row| row.get(0)).unwrap().collect::<rusqlite::Result<_>>().unwrap()
+        };
+
+        Namespaces::forget_temp_tables(&conn).unwrap();
+        assert_eq!(remaining(&conn), vec!["items", "pg_temp_3__old"]);
+        assert_eq!(Namespaces::sweep_temp_tables(&conn).unwrap(), 1);
+        assert_eq!(remaining(&conn), vec!["items"]);
+    }
+}
@@ -0,0 +1,318 @@
+use rusqlite::{Connection, OptionalExtension, params};
+
+/// Object kind of tables, views and indexes, which share pg_class
+pub const RELATION: &str = "r";
+
+/// Object kind of the sequences behind serial and identity columns, named `<table>_<column>_seq`
+pub const SEQUENCE: &str = "S";
+
+/// Object kind of enum and composite types, which share pg_type
+pub const TYPE: &str = "t";
+
+/// Object kind of the array types of enum and composite types, named after their element type
+pub const ARRAY_TYPE: &str = "a";
+
+/// Object kind of enum labels, named `<type>.<label>`
+pub const ENUM_VALUE: &str = "e";
+
+/// Object kind of the relations describing the fields of composite types, named after the type
+pub const COMPOSITE_RELATION: &str = "c";
+
+/// Object kind of user-defined functions, named by their signature
+pub const FUNCTION: &str = "f";
+
+/// Object kind of triggers, named `<table>.<trigger>`
+pub const TRIGGER: &str = "g";
+
+/// Object kind of schemas created with CREATE SCHEMA
+pub const NAMESPACE: &str = "n";
+
+/// First OID after the ones PostgreSQL reserves for built-in objects
+const FIRST_USER_OID: i64 = 16384;
+
+/// Size of the range name-derived OIDs fall in
+const OID_RANGE: i64 = 1_000_000;
+
+/// Hands out the OIDs catalog queries report for user objects and keeps them in
+/// `__pgsqlite_oids`, so they survive restarts and no two objects share one, whatever
+/// their kind. Drivers cache the OIDs of enum and composite types for the life of a
+/// connection pool, so an OID must not change or move to another object while the
+/// object exists.
+///
+/// An object's first candidate is the OID derived from its name before OIDs were
+/// stored, so databases created earlier keep the values clients may have cached.
+/// When that OID is taken the next free one is used.
+pub struct OidAllocator;
+
+impl OidAllocator {
+    /// Create the OID table
+    pub fn init(conn: &Connection) -> rusqlite::Result<()> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS __pgsqlite_oids (
+                oid INTEGER PRIMARY KEY,
+                kind TEXT NOT NULL,
+                name TEXT NOT NULL,
+                UNIQUE (kind, name)
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// The OID the catalog views derive from a name: the code points of its first
+    /// three characters and its length, the formula they used before OIDs were stored
+    pub fn seed(name: &str) -> i64 {
+        let mut chars = name.chars().chain(std::iter::repeat(' ')).map(|c| c as i64);
+        let (first, second, third) = (chars.next().unwrap_or(32), chars.next().unwrap_or(32), chars.next().unwrap_or(32));
+        let hash = first * 1_000_000 + second * 10_000 + third * 100 + name.chars().count() as i64 * 7;
+        hash % OID_RANGE + FIRST_USER_OID
+    }
+
+    /// The stored OID of an object, if it has one
+    pub fn lookup(conn: &Connection, kind: &str, name: &str) -> rusqlite::Result<Option<i64>> {
+        match conn.query_row(
+            "SELECT oid FROM __pgsqlite_oids WHERE kind = ?1 AND name = ?2",
+            params![kind, name],
+            |row| row.get(0),
+        ).optional() {
+            // Databases that predate the OID table report derived OIDs until migrated
+            Err(rusqlite::Error::SqliteFailure(_, Some(message))) if message.contains("no such table") => Ok(None),
+            result => result,
+        }
+    }
+
+    /// The OID of an object, assigning one if it has none yet
+    pub fn assign(conn: &Connection, kind: &str, name: &str) -> rusqlite::Result<i64> {
+        Self::assign_from(conn, kind, name, Self::seed(name))
+    }
+
+    /// The OID of an object, assigning the first free OID from `seed` on if it has none yet
+    pub fn assign_from(conn: &Connection, kind: &str, name: &str, seed: i64) -> rusqlite::Result<i64> {
+        Self::init(conn)?;
+        if let Some(oid) = Self::lookup(conn, kind, name)? {
+            return Ok(oid);
+        }
+        let mut oid = seed;
+        loop {
+            let taken: bool = conn.query_row("SELECT EXISTS(SELECT 1 FROM __pgsqlite_oids WHERE oid = ?1)", [oid], |row| row.get(0))?;
+            if !taken {
+                break;
+            }
+            oid = if oid + 1 >= FIRST_USER_OID + OID_RANGE { FIRST_USER_OID } else { oid + 1 };
+        }
+        conn.execute("INSERT INTO __pgsqlite_oids (oid, kind, name) VALUES (?1, ?2, ?3)", params![oid, kind, name])?;
+        Ok(oid)
+    }
+
+    /// The OID of the array type of an enum or composite type, assigning one after the
+    /// element type's OID if it has none yet, as PostgreSQL tends to
+    pub fn assign_array_type(conn: &Connection, type_name: &str, type_oid: i64) -> rusqlite::Result<i64> {
+        Self::assign_from(conn, ARRAY_TYPE, type_name, type_oid + 1)
+    }
+
+    /// The (element name, element OID, array OID) of every array type of an enum or composite type
+    pub fn array_types(conn: &Connection) -> rusqlite::Result<Vec<(String, i64, i64)>> {
+        let mut stmt = match conn.prepare(
+            "SELECT a.name, t.oid, a.oid FROM __pgsqlite_oids a
+             JOIN __pgsqlite_oids t ON t.kind = ?2 AND t.name = a.name
+             WHERE a.kind = ?1 ORDER BY a.oid",
+        ) {
+            Ok(stmt) => stmt,
+            Err(rusqlite::Error::SqliteFailure(_, Some(message))) if message.contains("no such table") => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        stmt.query_map([ARRAY_TYPE, TYPE], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?.collect()
+    }
+
+    /// Record the OID an object already has, unless the object or the OID is taken
+    pub fn register(conn: &Connection, kind: &str, name: &str, oid: i64) -> rusqlite::Result<()> {
+        Self::init(conn)?;
+        conn.execute("INSERT OR IGNORE INTO __pgsqlite_oids (oid, kind, name) VALUES (?1, ?2, ?3)", params![oid, kind, name])?;
+        Ok(())
+    }
+
+    /// Free the OID of a dropped object
+    pub fn release(conn: &Connection, oid: i64) -> rusqlite::Result<()> {
+        match conn.execute("DELETE FROM __pgsqlite_oids WHERE oid = ?1", [oid]) {
+            Err(rusqlite::Error::SqliteFailure(_, Some(message))) if message.contains("no such table") => Ok(()),
+            result => result.map(|_| ()),
+        }
+    }
+
+    /// The OID of a table, view, index or sequence; one that was never assigned is reported under its seed
+    pub fn relation_oid(conn: &Connection, name: &str) -> rusqlite::Result<i64> {
+        if let Some(oid) = Self::lookup(conn, RELATION, name)? {
+            return Ok(oid);
+        }
+        Ok(Self::lookup(conn, SEQUENCE, name)?.unwrap_or_else(|| Self::seed(name)))
+    }
+
+    /// Keep an object's OID when it is renamed, as PostgreSQL does
+    pub fn rename(conn: &Connection, kind: &str, old_name: &str, new_name: &str) -> rusqlite::Result<()> {
+        conn.execute(
+            "UPDATE OR REPLACE __pgsqlite_oids SET name = ?3 WHERE kind = ?1 AND name = ?2",
+            params![kind, old_name, new_name],
+        )?;
+        Ok(())
+    }
+
+    /// Keep the OIDs of a renamed table and of its columns' sequences. PostgreSQL
+    /// keeps the sequence names too, but here they follow the table's name.
+    pub fn rename_table(conn: &Connection, old_name: &str, new_name: &str) -> rusqlite::Result<()> {
+        Self::rename(conn, RELATION, old_name, new_name)?;
+        for column in Self::sequence_columns(conn, old_name)? {
+            Self::rename(conn, SEQUENCE, &format!("{old_name}_{column}_seq"), &format!("{new_name}_{column}_seq"))?;
+        }
+        Ok(())
+    }
+
+    /// Serial and identity columns of a table
+    fn sequence_columns(conn: &Connection, table: &str) -> rusqlite::Result<Vec<String>> {
+        let mut stmt = match conn.prepare("SELECT column_name FROM __pgsqlite_identity_columns WHERE table_name = ?1") {
+            Ok(stmt) => stmt,
+            Err(rusqlite::Error::SqliteFailure(_, Some(message))) if message.contains("no such table") => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        stmt.query_map([table], |row| row.get(0))?.collect()
+    }
+
+    /// Assign OIDs to relations and sequences created since the last call and
+    /// release those of the ones that no longer exist
+    pub fn sync_relations(conn: &Connection) -> rusqlite::Result<()> {
+        Self::init(conn)?;
+        conn.execute(
+            "DELETE FROM __pgsqlite_oids WHERE kind = ?1 AND name NOT IN (SELECT name FROM sqlite_master)",
+            [RELATION],
+        )?;
+        let missing: Vec<String> = {
+            let mut stmt = conn.prepare(
+                r"SELECT name FROM sqlite_master
+                  WHERE type IN ('table', 'view', 'index')
+                    AND name NOT LIKE 'sqlite\_%' ESCAPE '\' AND name NOT LIKE '\_\_pgsqlite\_%' ESCAPE '\'
+                    AND name NOT IN (SELECT name FROM __pgsqlite_oids WHERE kind = ?1)
+                  ORDER BY rowid",
+            )?;
+            let rows = stmt.query_map([RELATION], |row| row.get(0))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        for name in missing {
+            Self::assign(conn, RELATION, &name)?;
+        }
+        Self::sync_sequences(conn)
+    }
+
+    fn sync_sequences(conn: &Connection) -> rusqlite::Result<()> {
+        let has_identity_columns: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '__pgsqlite_identity_columns')",
+            [],
+            |row| row.get(0),
+        )?;
+        let sequences: Vec<String> = if has_identity_columns {
+            let mut stmt = conn.prepare(
+                "SELECT table_name || '_' || column_name || '_seq' FROM __pgsqlite_identity_columns
+                 WHERE table_name IN (SELECT name FROM sqlite_master WHERE type = 'table')
+                 ORDER BY rowid",
+            )?;
+            stmt.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?
+        } else {
+            Vec::new()
+        };
+        conn.execute(
+            "DELETE FROM __pgsqlite_oids WHERE kind = ?1 AND name NOT IN (SELECT value FROM json_each(?2))",
+            params![SEQUENCE, serde_json::json!(sequences).to_string()],
+        )?;
+        for name in sequences {
+            Self::assign(conn, SEQUENCE, &name)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seed_matches_catalog_views() {
+        let conn = Connection::open_in_memory().unwrap();
+        for name in ["items", "a", "ü_table"] {
+            let derived: i64 = conn.query_row(
+                "SELECT ((unicode(substr(?1, 1, 1)) * 1000000) + (unicode(substr(?1 || ' ', 2, 1)) * 10000) +
+                         (unicode(substr(?1 || '  ', 3, 1)) * 100) + (length(?1) * 7)) % 1000000 + 16384",
+                [name],
+                |row| row.get(0),
+            ).unwrap();
+            assert_eq!(OidAllocator::seed(name), derived, "{name}");
+        }
+    }
+
+    #[test]
+    fn test_assigned_oids_are_unique_and_kept() {
+        let conn = Connection::open_in_memory().unwrap();
+        assert_eq!(OidAllocator::relation_oid(&conn, "items").unwrap(), OidAllocator::seed("items"));
+
+        // Same first three characters and length, so the same seed
+        conn.execute_batch("CREATE TABLE items (id INTEGER); CREATE TABLE itemz (id INTEGER)").unwrap();
+        OidAllocator::sync_relations(&conn).unwrap();
+        let items = OidAllocator::relation_oid(&conn, "items").unwrap();
+        let itemz = OidAllocator::relation_oid(&conn, "itemz").unwrap();
+        assert_eq!(items, OidAllocator::seed("items"));
+        assert_eq!(itemz, items + 1);
+
+        conn.execute_batch("ALTER TABLE itemz RENAME TO goods").unwrap();
+        OidAllocator::rename(&conn, RELATION, "itemz", "goods").unwrap();
+        conn.execute_batch("DROP TABLE items").unwrap();
+        OidAllocator::sync_relations(&conn).unwrap();
+        assert_eq!(OidAllocator::lookup(&conn, RELATION, "goods").unwrap(), Some(itemz));
+        assert_eq!(OidAllocator::lookup(&conn, RELATION, "items").unwrap(), None);
+    }
+
+    #[test]
+    fn test_kinds_share_one_oid_space() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE __pgsqlite_identity_columns (table_name TEXT, column_name TEXT);
+             INSERT INTO __pgsqlite_identity_columns VALUES ('orders', 'id');
+             CREATE TABLE orders (id INTEGER PRIMARY KEY)",
+        ).unwrap();
+        OidAllocator::sync_relations(&conn).unwrap();
+        let orders = OidAllocator::relation_oid(&conn, "orders").unwrap();
+        let sequence = OidAllocator::relation_oid(&conn, "orders_id_seq").unwrap();
+        assert_eq!(OidAllocator::lookup(&conn, SEQUENCE, "orders_id_seq").unwrap(), Some(sequence));
+
+        // A type whose first candidate is taken by the table moves on
+        let status = OidAllocator::assign_from(&conn, TYPE, "status", orders).unwrap();
+        assert_ne!(status, orders);
+        assert_ne!(status, sequence);
+        OidAllocator::release(&conn, status).unwrap();
+        assert_eq!(OidAllocator::lookup(&conn, TYPE, "status").unwrap(), None);
+
+        // Renaming the table keeps the OIDs of the table and its sequence
+        conn.execute_batch("ALTER TABLE orders RENAME TO purchases").unwrap();
+        OidAllocator::rename_table(&conn, "orders", "purchases").unwrap();
+        conn.execute_batch("UPDATE __pgsqlite_identity_columns SET table_name = 'purchases'").unwrap();
+        OidAllocator::sync_relations(&conn).unwrap();
+        assert_eq!(OidAllocator::relation_oid(&conn, "purchases").unwrap(), orders);
+        assert_eq!(OidAllocator::relation_oid(&conn, "purchases_id_seq").unwrap(), sequence);
+
+        conn.execute_batch("DROP TABLE purchases").unwrap();
+        OidAllocator::sync_relations(&conn).unwrap();
+        assert_eq!(OidAllocator::lookup(&conn, SEQUENCE, "purchases_id_seq").unwrap(), None);
+    }
+
+    #[test]
+    fn test_array_types_follow_their_element() {
+        let conn = Connection::open_in_memory().unwrap();
+        let mood = OidAllocator::assign(&conn, TYPE, "mood").unwrap();
+        // The OID after the element's is taken, so the array type moves on
+        let label = OidAllocator::assign_from(&conn, ENUM_VALUE, "mood.sad", mood + 1).unwrap();
+        let array = OidAllocator::assign_array_type(&conn, "mood", mood).unwrap();
+        assert_eq!(array, label + 1);
+        assert_eq!(OidAllocator::assign_array_type(&conn, "mood", mood).unwrap(), array);
+
+        // A type named like the array type keeps an OID of its own
+        let underscored = OidAllocator::assign(&conn, TYPE, "_mood").unwrap();
+        assert_ne!(underscored, array);
+        assert_eq!(OidAllocator::array_types(&conn).unwrap(), [("mood".to_string(), mood, array)]);
+    }
+}
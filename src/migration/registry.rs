@@ -1,5 +1,7 @@
 use super::{Migration, MigrationAction};
 use lazy_static::lazy_static;
+use once_cell::sync::Lazy;
+use regex::Regex;
 use std::collections::BTreeMap;
 
 lazy_static! {
@@ -20,74 +22,2167 @@ lazy_static! {
         register_v11_fix_catalog_views(&mut registry);
         register_v12_pg_stats_minimal(&mut registry);
         register_v13_pg_database_datname_filename(&mut registry);
+        register_v14_identity_columns(&mut registry);
+        register_v15_composite_types(&mut registry);
+        register_v16_pg_stat_progress_copy(&mut registry);
+        register_v17_pg_stat_progress_views(&mut registry);
+        register_v18_pg_stat_statements(&mut registry);
+        register_v19_pg_stat_activity_sessions(&mut registry);
+        register_v20_pg_index_constraint_views(&mut registry);
+        register_v21_pg_proc_description(&mut registry);
+        register_v22_identity_counters(&mut registry);
+        register_v23_relation_oids(&mut registry);
+        register_v24_type_oids(&mut registry);
+        register_v25_triggers(&mut registry);
+        register_v26_sql_functions(&mut registry);
+        register_v27_array_type_oids(&mut registry);
+        register_v28_schemas(&mut registry);
+        register_v29_pg_database_list(&mut registry);
+        register_v30_audited_tables(&mut registry);
+        register_v31_soft_delete_tables(&mut registry);
+        register_v32_retention_policies(&mut registry);
+        register_v33_schema_snapshots(&mut registry);
+        register_v34_pg_stat_progress_create_index(&mut registry);
+        register_v35_clustered_indexes(&mut registry);
+        register_v36_autoanalyze(&mut registry);
+        register_v37_column_masks(&mut registry);
         
         registry
     };
 }
 
-/// Version 13: Make pg_database.datname reflect filename (via function)
-fn register_v13_pg_database_datname_filename(registry: &mut BTreeMap<u32, Migration>) {
-    registry.insert(13, Migration {
-        version: 13,
-        name: "pg_database_datname_filename",
-        description: "pg_database.datname shows database filename",
+/// Version 37: Column masks set with pgsqlite.set_mask(), and a view of them
+fn register_v37_column_masks(registry: &mut BTreeMap<u32, Migration>) {
+    registry.insert(37, Migration {
+        version: 37,
+        name: "column_masks",
+        description: "Record the masks set on columns, with a pg_column_masks view",
         up: MigrationAction::SqlBatch(&[
-            // Recreate pg_database using pgsqlite_datname()
             r#"
-            DROP VIEW IF EXISTS pg_database;
-            CREATE VIEW pg_database AS
+            CREATE TABLE IF NOT EXISTS __pgsqlite_column_masks (
+                table_name TEXT NOT NULL,
+                column_name TEXT NOT NULL,
+                method TEXT NOT NULL,
+                pattern TEXT,
+                PRIMARY KEY (table_name, column_name)
+            );
+            "#,
+            // Not a PostgreSQL view: the masks roles other than --admin-users read columns through
+            r#"
+            CREATE VIEW IF NOT EXISTS pg_column_masks AS
+            SELECT
+                table_name AS relname,
+                column_name AS attname,
+                method,
+                pattern
+            FROM __pgsqlite_column_masks;
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '37', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ]),
+        down: Some(MigrationAction::SqlBatch(&[
+            r#"
+            DROP VIEW IF EXISTS pg_column_masks;
+            DROP TABLE IF EXISTS __pgsqlite_column_masks;
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '36', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ])),
+        dependencies: vec![36],
+    });
+}
+
+/// Version 36: When tables were last analyzed automatically, for pg_stat_user_tables
+fn register_v36_autoanalyze(registry: &mut BTreeMap<u32, Migration>) {
+    registry.insert(36, Migration {
+        version: 36,
+        name: "autoanalyze",
+        description: "Record automatic ANALYZE runs and show them in pg_stat_user_tables",
+        up: MigrationAction::SqlBatch(&[
+            r#"
+            CREATE TABLE IF NOT EXISTS __pgsqlite_autoanalyze (
+                table_name TEXT PRIMARY KEY,
+                last_autoanalyze INTEGER NOT NULL,
+                autoanalyze_count INTEGER NOT NULL DEFAULT 0
+            );
+            "#,
+            // last_autoanalyze is stored in microseconds like TIMESTAMPTZ columns
+            r#"
+            DROP VIEW IF EXISTS pg_stat_user_tables;
+            CREATE VIEW pg_stat_user_tables AS
             SELECT 
-                1            AS oid,
-                pgsqlite_datname() AS datname,
-                10           AS datdba,
-                6            AS encoding,
-                'C'          AS datcollate,
-                'C'          AS datctype,
-                1            AS datallowconn,
-                0            AS datistemplate,
-                -1           AS datconnlimit,
-                0            AS dattablespace,
-                NULL         AS datacl,
-                0            AS datfrozenxid,
-                0            AS datminmxid;
+                CAST( (
+                    (unicode(substr(m.name, 1, 1)) * 1000000) +
+                    (unicode(substr(m.name || ' ', 2, 1)) * 10000) +
+                    (unicode(substr(m.name || '  ', 3, 1)) * 100) +
+                    (length(m.name) * 7)
+                ) % 1000000 + 16384 AS TEXT) AS relid,
+                'public' AS schemaname,
+                m.name   AS relname,
+                0 AS seq_scan,
+                0 AS seq_tup_read,
+                0 AS idx_scan,
+                0 AS idx_tup_fetch,
+                0 AS n_tup_ins,
+                0 AS n_tup_upd,
+                0 AS n_tup_del,
+                0 AS n_tup_hot_upd,
+                0 AS n_live_tup,
+                0 AS n_dead_tup,
+                NULL AS vacuum_count,
+                NULL AS autovacuum_count,
+                NULL AS analyze_count,
+                COALESCE(a.autoanalyze_count, 0) AS autoanalyze_count,
+                NULL AS last_vacuum,
+                NULL AS last_autovacuum,
+                NULL AS last_analyze,
+                CASE WHEN a.last_autoanalyze IS NULL THEN NULL
+                     ELSE strftime('%Y-%m-%d %H:%M:%S', a.last_autoanalyze / 1000000, 'unixepoch') || '+00' END AS last_autoanalyze
+            FROM sqlite_master m
+            LEFT JOIN __pgsqlite_autoanalyze a ON a.table_name = m.name
+            WHERE m.type = 'table'
+              AND m.name NOT LIKE 'sqlite_%'
+              AND m.name NOT LIKE '__pgsqlite_%';
             "#,
-            // Update schema version
             r#"
             UPDATE __pgsqlite_metadata 
-            SET value = '13', updated_at = strftime('%s', 'now')
+            SET value = '36', updated_at = strftime('%s', 'now')
             WHERE key = 'schema_version';
             "#,
         ]),
         down: Some(MigrationAction::SqlBatch(&[
-            // Restore previous view (datname='main')
             r#"
-            DROP VIEW IF EXISTS pg_database;
-            CREATE VIEW pg_database AS
+            DROP VIEW IF EXISTS pg_stat_user_tables;
+            CREATE VIEW pg_stat_user_tables AS
             SELECT 
-                1            AS oid,
-                'main'       AS datname,
-                10           AS datdba,
-                6            AS encoding,
-                'C'          AS datcollate,
-                'C'          AS datctype,
-                1            AS datallowconn,
-                0            AS datistemplate,
-                -1           AS datconnlimit,
-                0            AS dattablespace,
-                NULL         AS datacl,
-                0            AS datfrozenxid,
-                0            AS datminmxid;
+                CAST( (
+                    (unicode(substr(m.name, 1, 1)) * 1000000) +
+                    (unicode(substr(m.name || ' ', 2, 1)) * 10000) +
+                    (unicode(substr(m.name || '  ', 3, 1)) * 100) +
+                    (length(m.name) * 7)
+                ) % 1000000 + 16384 AS TEXT) AS relid,
+                'public' AS schemaname,
+                m.name   AS relname,
+                0 AS seq_scan,
+                0 AS seq_tup_read,
+                0 AS idx_scan,
+                0 AS idx_tup_fetch,
+                0 AS n_tup_ins,
+                0 AS n_tup_upd,
+                0 AS n_tup_del,
+                0 AS n_tup_hot_upd,
+                0 AS n_live_tup,
+                0 AS n_dead_tup,
+                NULL AS vacuum_count,
+                NULL AS autovacuum_count,
+                NULL AS analyze_count,
+                NULL AS autoanalyze_count,
+                NULL AS last_vacuum,
+                NULL AS last_autovacuum,
+                NULL AS last_analyze,
+                NULL AS last_autoanalyze
+            FROM sqlite_master m
+            WHERE m.type = 'table'
+              AND m.name NOT LIKE 'sqlite_%'
+              AND m.name NOT LIKE '__pgsqlite_%';
+            DROP TABLE IF EXISTS __pgsqlite_autoanalyze;
             "#,
             r#"
             UPDATE __pgsqlite_metadata 
-            SET value = '12', updated_at = strftime('%s', 'now')
+            SET value = '35', updated_at = strftime('%s', 'now')
             WHERE key = 'schema_version';
             "#,
         ])),
-        dependencies: vec![12],
+        dependencies: vec![35],
+    });
+}
+
+/// Version 35: The index each table was last clustered on, and CLUSTER progress
+fn register_v35_clustered_indexes(registry: &mut BTreeMap<u32, Migration>) {
+    registry.insert(35, Migration {
+        version: 35,
+        name: "clustered_indexes",
+        description: "Record CLUSTER's index for pg_index.indisclustered and add pg_stat_progress_cluster",
+        up: MigrationAction::SqlBatch(&[
+            r#"
+            CREATE TABLE IF NOT EXISTS __pgsqlite_clustered_indexes (
+                table_name TEXT PRIMARY KEY,
+                index_name TEXT NOT NULL
+            );
+            "#,
+            r#"
+            DROP VIEW IF EXISTS pg_index;
+            CREATE VIEW pg_index AS
+            SELECT
+                indexrelid,
+                indrelid,
+                natts AS indnatts,
+                natts AS indnkeyatts,
+                CASE WHEN is_unique THEN 't' ELSE 'f' END AS indisunique,
+                CASE WHEN origin = 'pk' THEN 't' ELSE 'f' END AS indisprimary,
+                'f' AS indisexclusion,
+                't' AS indimmediate,
+                CASE WHEN EXISTS (
+                    SELECT 1 FROM __pgsqlite_clustered_indexes c
+                    WHERE c.table_name = tablename AND c.index_name = indexname
+                ) THEN 't' ELSE 'f' END AS indisclustered,
+                't' AS indisvalid,
+                'f' AS indcheckxmin,
+                't' AS indisready,
+                't' AS indislive,
+                'f' AS indisreplident,
+                indkey,
+                NULL AS indcollation,
+                NULL AS indclass,
+                NULL AS indoption,
+                NULL AS indexprs,
+                NULL AS indpred
+            FROM __pgsqlite_index_catalog;
+            "#,
+            // CLUSTER copies the rows in one statement; heap blocks aren't counted
+            r#"
+            CREATE VIEW IF NOT EXISTS pg_stat_progress_cluster AS
+            SELECT
+                json_extract(value, '$.pid') AS pid,
+                1 AS datid,
+                pgsqlite_datname() AS datname,
+                pgsqlite_relation_oid(json_extract(value, '$.relname')) AS relid,
+                'CLUSTER' AS command,
+                json_extract(value, '$.phase') AS phase,
+                pgsqlite_relation_oid(json_extract(value, '$.index_relname')) AS cluster_index_relid,
+                json_extract(value, '$.done') AS heap_tuples_scanned,
+                json_extract(value, '$.done') AS heap_tuples_written,
+                0 AS heap_blks_total,
+                0 AS heap_blks_scanned,
+                0 AS index_rebuild_count
+            FROM json_each(pgsqlite_progress('cluster'));
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '35', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ]),
+        down: Some(MigrationAction::SqlBatch(&[
+            r#"
+            DROP VIEW IF EXISTS pg_stat_progress_cluster;
+            DROP VIEW IF EXISTS pg_index;
+            CREATE VIEW pg_index AS
+            SELECT
+                indexrelid,
+                indrelid,
+                natts AS indnatts,
+                natts AS indnkeyatts,
+                CASE WHEN is_unique THEN 't' ELSE 'f' END AS indisunique,
+                CASE WHEN origin = 'pk' THEN 't' ELSE 'f' END AS indisprimary,
+                'f' AS indisexclusion,
+                't' AS indimmediate,
+                'f' AS indisclustered,
+                't' AS indisvalid,
+                'f' AS indcheckxmin,
+                't' AS indisready,
+                't' AS indislive,
+                'f' AS indisreplident,
+                indkey,
+                NULL AS indcollation,
+                NULL AS indclass,
+                NULL AS indoption,
+                NULL AS indexprs,
+                NULL AS indpred
+            FROM __pgsqlite_index_catalog;
+            DROP TABLE IF EXISTS __pgsqlite_clustered_indexes;
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '34', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ])),
+        dependencies: vec![34],
+    });
+}
+
+/// Version 34: Progress view for CREATE INDEX CONCURRENTLY
+fn register_v34_pg_stat_progress_create_index(registry: &mut BTreeMap<u32, Migration>) {
+    registry.insert(34, Migration {
+        version: 34,
+        name: "pg_stat_progress_create_index",
+        description: "Add pg_stat_progress_create_index for concurrent index builds",
+        up: MigrationAction::SqlBatch(&[
+            // Builds count rows as tuples; SQLite has no lockers or partitions to wait for
+            r#"
+            CREATE VIEW IF NOT EXISTS pg_stat_progress_create_index AS
+            SELECT
+                json_extract(value, '$.pid') AS pid,
+                1 AS datid,
+                pgsqlite_datname() AS datname,
+                pgsqlite_relation_oid(json_extract(value, '$.relname')) AS relid,
+                pgsqlite_relation_oid(json_extract(value, '$.index_relname')) AS index_relid,
+                'CREATE INDEX CONCURRENTLY' AS command,
+                json_extract(value, '$.phase') AS phase,
+                0 AS lockers_total,
+                0 AS lockers_done,
+                0 AS current_locker_pid,
+                0 AS blocks_total,
+                0 AS blocks_done,
+                json_extract(value, '$.total') AS tuples_total,
+                json_extract(value, '$.done') AS tuples_done,
+                0 AS partitions_total,
+                0 AS partitions_done
+            FROM json_each(pgsqlite_progress('create_index'));
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '34', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ]),
+        down: Some(MigrationAction::SqlBatch(&[
+            r#"
+            DROP VIEW IF EXISTS pg_stat_progress_create_index;
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '33', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ])),
+        dependencies: vec![33],
+    });
+}
+
+/// Version 33: Schema snapshots saved with pgsqlite.schema_snapshot() for pgsqlite.schema_diff()
+fn register_v33_schema_snapshots(registry: &mut BTreeMap<u32, Migration>) {
+    registry.insert(33, Migration {
+        version: 33,
+        name: "schema_snapshots",
+        description: "Record schema snapshots that pgsqlite.schema_diff() compares",
+        up: MigrationAction::SqlBatch(&[
+            // snapshot is the JSON of the tables' columns, constraints and indexes
+            r#"
+            CREATE TABLE IF NOT EXISTS __pgsqlite_schema_snapshots (
+                snapshot_id INTEGER PRIMARY KEY AUTOINCREMENT,
+                label TEXT UNIQUE,
+                taken_at INTEGER NOT NULL,
+                snapshot TEXT NOT NULL
+            );
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '33', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ]),
+        down: Some(MigrationAction::SqlBatch(&[
+            r#"
+            DROP TABLE IF EXISTS __pgsqlite_schema_snapshots;
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '32', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ])),
+        dependencies: vec![32],
+    });
+}
+
+/// Version 32: Retention policies set with pgsqlite.set_retention(), and views of their runs
+fn register_v32_retention_policies(registry: &mut BTreeMap<u32, Migration>) {
+    registry.insert(32, Migration {
+        version: 32,
+        name: "retention_policies",
+        description: "Record the retention policies that delete expired rows, with pg_stat_retention and pg_stat_progress_retention views",
+        up: MigrationAction::SqlBatch(&[
+            r#"
+            CREATE TABLE IF NOT EXISTS __pgsqlite_retention_policies (
+                table_name TEXT PRIMARY KEY,
+                column_name TEXT NOT NULL,
+                max_age TEXT NOT NULL,
+                last_run INTEGER,
+                last_deleted INTEGER NOT NULL DEFAULT 0,
+                total_deleted INTEGER NOT NULL DEFAULT 0
+            );
+            "#,
+            // last_run is stored in microseconds like TIMESTAMPTZ columns
+            r#"
+            CREATE VIEW IF NOT EXISTS pg_stat_retention AS
+            SELECT
+                table_name AS relname,
+                column_name,
+                max_age,
+                CASE WHEN last_run IS NULL THEN NULL
+                     ELSE strftime('%Y-%m-%d %H:%M:%S', last_run / 1000000, 'unixepoch') || '+00' END AS last_run,
+                last_deleted,
+                total_deleted
+            FROM __pgsqlite_retention_policies;
+            "#,
+            // Not a PostgreSQL view: retention policies deleting expired rows
+            r#"
+            CREATE VIEW IF NOT EXISTS pg_stat_progress_retention AS
+            SELECT
+                json_extract(value, '$.pid') AS pid,
+                1 AS datid,
+                pgsqlite_datname() AS datname,
+                json_extract(value, '$.relname') AS relname,
+                json_extract(value, '$.phase') AS phase,
+                json_extract(value, '$.total') AS rows_total,
+                json_extract(value, '$.done') AS rows_deleted
+            FROM json_each(pgsqlite_progress('retention'));
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '32', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ]),
+        down: Some(MigrationAction::SqlBatch(&[
+            r#"
+            DROP VIEW IF EXISTS pg_stat_progress_retention;
+            "#,
+            r#"
+            DROP VIEW IF EXISTS pg_stat_retention;
+            "#,
+            r#"
+            DROP TABLE IF EXISTS __pgsqlite_retention_policies;
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '31', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ])),
+        dependencies: vec![31],
+    });
+}
+
+/// Version 31: Tables whose deletes pgsqlite.enable_soft_delete() turned into setting deleted_at
+fn register_v31_soft_delete_tables(registry: &mut BTreeMap<u32, Migration>) {
+    registry.insert(31, Migration {
+        version: 31,
+        name: "soft_delete_tables",
+        description: "Record the tables whose statements are rewritten to skip and soft-delete rows",
+        up: MigrationAction::SqlBatch(&[
+            r#"
+            CREATE TABLE IF NOT EXISTS __pgsqlite_soft_delete_tables (
+                table_name TEXT PRIMARY KEY
+            );
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '31', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ]),
+        down: Some(MigrationAction::SqlBatch(&[
+            r#"
+            DROP TABLE IF EXISTS __pgsqlite_soft_delete_tables;
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '30', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ])),
+        dependencies: vec![30],
     });
 }
 
+/// Version 30: Tables audited with pgsqlite.enable_audit() and their audit tables
+fn register_v30_audited_tables(registry: &mut BTreeMap<u32, Migration>) {
+    registry.insert(30, Migration {
+        version: 30,
+        name: "audited_tables",
+        description: "Record the tables whose changes audit triggers write to an audit table",
+        up: MigrationAction::SqlBatch(&[
+            r#"
+            CREATE TABLE IF NOT EXISTS __pgsqlite_audited_tables (
+                table_name TEXT PRIMARY KEY,
+                audit_table TEXT NOT NULL UNIQUE
+            );
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '30', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ]),
+        down: Some(MigrationAction::SqlBatch(&[
+            r#"
+            DROP TABLE IF EXISTS __pgsqlite_audited_tables;
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '29', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ])),
+        dependencies: vec![29],
+    });
+}
+
+/// Version 29: pg_database lists every database clients can connect to, see --databases
+fn register_v29_pg_database_list(registry: &mut BTreeMap<u32, Migration>) {
+    registry.insert(29, Migration {
+        version: 29,
+        name: "pg_database_list",
+        description: "List the databases configured with --databases in pg_database",
+        up: MigrationAction::SqlBatch(&[
+            r#"
+            DROP VIEW IF EXISTS pg_database;
+            CREATE VIEW pg_database AS
+            SELECT 
+                CAST(d.key + 1 AS INTEGER) AS oid,
+                d.value      AS datname,
+                10           AS datdba,
+                6            AS encoding,
+                'C'          AS datcollate,
+                'C'          AS datctype,
+                1            AS datallowconn,
+                0            AS datistemplate,
+                -1           AS datconnlimit,
+                0            AS dattablespace,
+                NULL         AS datacl,
+                0            AS datfrozenxid,
+                0            AS datminmxid
+            FROM json_each(pgsqlite_databases()) d;
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '29', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ]),
+        down: Some(MigrationAction::SqlBatch(&[
+            r#"
+            DROP VIEW IF EXISTS pg_database;
+            CREATE VIEW pg_database AS
+            SELECT 
+                1            AS oid,
+                pgsqlite_datname() AS datname,
+                10           AS datdba,
+                6            AS encoding,
+                'C'          AS datcollate,
+                'C'          AS datctype,
+                1            AS datallowconn,
+                0            AS datistemplate,
+                -1           AS datconnlimit,
+                0            AS dattablespace,
+                NULL         AS datacl,
+                0            AS datfrozenxid,
+                0            AS datminmxid;
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '28', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ])),
+        dependencies: vec![28],
+    });
+}
+
+/// Version 28: Schemas created with CREATE SCHEMA, listed in pg_namespace and owning their relations in pg_class
+fn register_v28_schemas(registry: &mut BTreeMap<u32, Migration>) {
+    registry.insert(28, Migration {
+        version: 28,
+        name: "schemas",
+        description: "List schemas created with CREATE SCHEMA in pg_namespace and report the schema of each relation in pg_class",
+        up: MigrationAction::Combined {
+            pre_sql: None,
+            function: migrate_schemas,
+            post_sql: Some(r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '28', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#),
+        },
+        down: None,
+        dependencies: vec![27],
+    });
+}
+
+/// The relnamespace the catalog views gave every relation
+static PUBLIC_RELNAMESPACE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?m)\b([\w.]+) as relname,\s*2200 as relnamespace,.*$").unwrap()
+});
+
+/// List the schemas in pg_namespace and split the relation names of the catalog
+/// views into schema and name, see Namespaces
+fn migrate_schemas(conn: &rusqlite::Connection) -> anyhow::Result<()> {
+    crate::metadata::OidAllocator::init(conn)?;
+    conn.execute_batch(&format!(
+        r#"
+        DROP VIEW IF EXISTS pg_namespace;
+
+        CREATE VIEW pg_namespace AS
+        SELECT 11 AS oid, 'pg_catalog' AS nspname, 10 AS nspowner, NULL AS nspacl
+        UNION ALL
+        SELECT 2200 AS oid, 'public' AS nspname, 10 AS nspowner, NULL AS nspacl
+        UNION ALL
+        SELECT oid, name AS nspname, 10 AS nspowner, NULL AS nspacl
+        FROM __pgsqlite_oids WHERE kind = '{}';
+        "#,
+        crate::metadata::oid_allocator::NAMESPACE,
+    ))?;
+
+    let views: Vec<(String, String)> = conn.prepare(
+        "SELECT name, sql FROM sqlite_master WHERE type = 'view' AND sql LIKE '%2200 as relnamespace%'"
+    )?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, rusqlite::Error>>()?;
+    for (name, sql) in views {
+        let rewritten = PUBLIC_RELNAMESPACE.replace_all(&sql, |caps: &regex::Captures| {
+            let relation = &caps[1];
+            format!("pgsqlite_relname({relation}) as relname,\n                pgsqlite_relnamespace({relation}) as relnamespace,")
+        });
+        if rewritten != sql {
+            conn.execute_batch(&format!("DROP VIEW IF EXISTS \"{name}\";\n{rewritten};"))?;
+        }
+    }
+    Ok(())
+}
+
+/// Version 27: Enum and composite types get array types, with OIDs from the allocator
+fn register_v27_array_type_oids(registry: &mut BTreeMap<u32, Migration>) {
+    registry.insert(27, Migration {
+        version: 27,
+        name: "array_type_oids",
+        description: "Assign OIDs to the array types of existing enum and composite types",
+        up: MigrationAction::Combined {
+            pre_sql: None,
+            function: migrate_array_type_oids,
+            post_sql: Some(r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '27', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#),
+        },
+        down: None,
+        dependencies: vec![26],
+    });
+}
+
+/// Give the enum and composite types created so far their array types
+fn migrate_array_type_oids(conn: &rusqlite::Connection) -> anyhow::Result<()> {
+    let types: Vec<(String, i64)> = conn
+        .prepare("SELECT type_name, type_oid FROM __pgsqlite_enum_types UNION ALL SELECT type_name, type_oid FROM __pgsqlite_composite_types")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    for (type_name, type_oid) in types {
+        crate::metadata::OidAllocator::assign_array_type(conn, &type_name, type_oid)?;
+    }
+    Ok(())
+}
+
+/// Version 26: SQL-language functions, listed in pg_proc with the trigger functions
+fn register_v26_sql_functions(registry: &mut BTreeMap<u32, Migration>) {
+    registry.insert(26, Migration {
+        version: 26,
+        name: "sql_functions",
+        description: "Store the signatures and translated bodies of SQL functions and list stored functions in pg_proc",
+        up: MigrationAction::SqlBatch(&[
+            r#"
+            -- The existing rows are trigger functions, which take no arguments
+            ALTER TABLE __pgsqlite_functions ADD COLUMN arg_types TEXT NOT NULL DEFAULT '';
+            ALTER TABLE __pgsqlite_functions ADD COLUMN arg_names TEXT;
+            ALTER TABLE __pgsqlite_functions ADD COLUMN nargs INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE __pgsqlite_functions ADD COLUMN return_type_oid INTEGER NOT NULL DEFAULT 2279;
+            ALTER TABLE __pgsqlite_functions ADD COLUMN volatility TEXT NOT NULL DEFAULT 'v';
+            ALTER TABLE __pgsqlite_functions ADD COLUMN is_strict INTEGER NOT NULL DEFAULT 0;
+            -- The body of a SQL function as run by SQLite, with ?N for its arguments
+            ALTER TABLE __pgsqlite_functions ADD COLUMN sqlite_body TEXT;
+            "#,
+            r#"
+            DROP VIEW IF EXISTS pg_proc;
+            
+            CREATE VIEW pg_proc AS
+            SELECT
+                CAST(json_extract(s.value, '$.oid') AS TEXT) AS oid,
+                json_extract(s.value, '$.proname') AS proname,
+                11 AS pronamespace,
+                10 AS proowner,
+                12 AS prolang,
+                1 AS procost,
+                0 AS prorows,
+                json_extract(s.value, '$.provariadic') AS provariadic,
+                0 AS prosupport,
+                CASE WHEN f.type = 's' THEN 'f' ELSE 'a' END AS prokind,
+                'f' AS prosecdef,
+                'f' AS proleakproof,
+                'f' AS proisstrict,
+                'f' AS proretset,
+                CASE WHEN f.flags & 2048 THEN 'i' ELSE 'v' END AS provolatile,
+                's' AS proparallel,
+                json_extract(s.value, '$.pronargs') AS pronargs,
+                0 AS pronargdefaults,
+                json_extract(s.value, '$.prorettype') AS prorettype,
+                json_extract(s.value, '$.proargtypes') AS proargtypes,
+                NULL AS proallargtypes,
+                NULL AS proargmodes,
+                NULL AS proargnames,
+                NULL AS proargdefaults,
+                NULL AS protrftypes,
+                json_extract(s.value, '$.proname') AS prosrc,
+                NULL AS probin,
+                NULL AS prosqlbody,
+                NULL AS proconfig,
+                NULL AS proacl
+            FROM json_each(pgsqlite_functions()) s
+            JOIN (
+                SELECT name, narg, type, max(flags) AS flags
+                FROM pragma_function_list
+                WHERE builtin = 0
+                GROUP BY name, narg
+            ) f ON f.name = json_extract(s.value, '$.proname')
+                AND f.narg IN (json_extract(s.value, '$.pronargs'), -1)
+            UNION ALL
+            SELECT
+                CAST(f.oid AS TEXT) AS oid,
+                f.name AS proname,
+                2200 AS pronamespace,
+                10 AS proowner,
+                CASE WHEN f.language = 'sql' THEN 14 ELSE 0 END AS prolang,
+                100 AS procost,
+                0 AS prorows,
+                0 AS provariadic,
+                0 AS prosupport,
+                'f' AS prokind,
+                'f' AS prosecdef,
+                'f' AS proleakproof,
+                CASE WHEN f.is_strict THEN 't' ELSE 'f' END AS proisstrict,
+                'f' AS proretset,
+                f.volatility AS provolatile,
+                'u' AS proparallel,
+                f.nargs AS pronargs,
+                0 AS pronargdefaults,
+                f.return_type_oid AS prorettype,
+                f.arg_types AS proargtypes,
+                NULL AS proallargtypes,
+                NULL AS proargmodes,
+                f.arg_names AS proargnames,
+                NULL AS proargdefaults,
+                NULL AS protrftypes,
+                f.body AS prosrc,
+                NULL AS probin,
+                NULL AS prosqlbody,
+                NULL AS proconfig,
+                NULL AS proacl
+            FROM __pgsqlite_functions f;
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '26', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ]),
+        down: Some(MigrationAction::SqlBatch(&[
+            r#"
+            DROP VIEW IF EXISTS pg_proc;
+            
+            CREATE VIEW pg_proc AS
+            SELECT
+                CAST(json_extract(s.value, '$.oid') AS TEXT) AS oid,
+                json_extract(s.value, '$.proname') AS proname,
+                11 AS pronamespace,
+                10 AS proowner,
+                12 AS prolang,
+                1 AS procost,
+                0 AS prorows,
+                json_extract(s.value, '$.provariadic') AS provariadic,
+                0 AS prosupport,
+                CASE WHEN f.type = 's' THEN 'f' ELSE 'a' END AS prokind,
+                'f' AS prosecdef,
+                'f' AS proleakproof,
+                'f' AS proisstrict,
+                'f' AS proretset,
+                CASE WHEN f.flags & 2048 THEN 'i' ELSE 'v' END AS provolatile,
+                's' AS proparallel,
+                json_extract(s.value, '$.pronargs') AS pronargs,
+                0 AS pronargdefaults,
+                json_extract(s.value, '$.prorettype') AS prorettype,
+                json_extract(s.value, '$.proargtypes') AS proargtypes,
+                NULL AS proallargtypes,
+                NULL AS proargmodes,
+                NULL AS proargnames,
+                NULL AS proargdefaults,
+                NULL AS protrftypes,
+                json_extract(s.value, '$.proname') AS prosrc,
+                NULL AS probin,
+                NULL AS prosqlbody,
+                NULL AS proconfig,
+                NULL AS proacl
+            FROM json_each(pgsqlite_functions()) s
+            JOIN (
+                SELECT name, narg, type, max(flags) AS flags
+                FROM pragma_function_list
+                WHERE builtin = 0
+                GROUP BY name, narg
+            ) f ON f.name = json_extract(s.value, '$.proname')
+                AND f.narg IN (json_extract(s.value, '$.pronargs'), -1);
+            "#,
+            r#"
+            ALTER TABLE __pgsqlite_functions DROP COLUMN sqlite_body;
+            ALTER TABLE __pgsqlite_functions DROP COLUMN is_strict;
+            ALTER TABLE __pgsqlite_functions DROP COLUMN volatility;
+            ALTER TABLE __pgsqlite_functions DROP COLUMN return_type_oid;
+            ALTER TABLE __pgsqlite_functions DROP COLUMN nargs;
+            ALTER TABLE __pgsqlite_functions DROP COLUMN arg_names;
+            ALTER TABLE __pgsqlite_functions DROP COLUMN arg_types;
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '25', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ])),
+        dependencies: vec![25],
+    });
+}
+
+/// Version 25: PL/pgSQL trigger functions and the triggers using them
+fn register_v25_triggers(registry: &mut BTreeMap<u32, Migration>) {
+    registry.insert(25, Migration {
+        version: 25,
+        name: "triggers",
+        description: "Store trigger functions and triggers as written and add the pg_trigger view",
+        up: MigrationAction::SqlBatch(&[
+            r#"
+            -- Trigger functions as created; the SQLite triggers are generated from them
+            CREATE TABLE IF NOT EXISTS __pgsqlite_functions (
+                oid INTEGER PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                language TEXT NOT NULL,
+                return_type TEXT NOT NULL,
+                body TEXT NOT NULL,
+                definition TEXT NOT NULL
+            );
+            
+            CREATE TABLE IF NOT EXISTS __pgsqlite_triggers (
+                oid INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                table_name TEXT NOT NULL,
+                function_name TEXT NOT NULL,
+                tgtype INTEGER NOT NULL,
+                definition TEXT NOT NULL,
+                UNIQUE (table_name, name)
+            );
+            
+            -- Holds a row while a trigger applies its assignments to NEW
+            CREATE TABLE IF NOT EXISTS __pgsqlite_trigger_writes (depth INTEGER);
+            "#,
+            r#"
+            CREATE VIEW IF NOT EXISTS pg_trigger AS
+            SELECT
+                CAST(t.oid AS TEXT) AS oid,
+                pgsqlite_relation_oid(t.table_name) AS tgrelid,
+                0 AS tgparentid,
+                t.name AS tgname,
+                f.oid AS tgfoid,
+                t.tgtype AS tgtype,
+                'O' AS tgenabled,
+                0 AS tgisinternal,
+                0 AS tgconstrrelid,
+                0 AS tgconstrindid,
+                0 AS tgconstraint,
+                0 AS tgdeferrable,
+                0 AS tginitdeferred,
+                0 AS tgnargs,
+                '' AS tgattr,
+                '' AS tgargs,
+                NULL AS tgqual,
+                NULL AS tgoldtable,
+                NULL AS tgnewtable
+            FROM __pgsqlite_triggers t
+            LEFT JOIN __pgsqlite_functions f ON f.name = t.function_name;
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '25', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ]),
+        down: Some(MigrationAction::SqlBatch(&[
+            r#"
+            DROP VIEW IF EXISTS pg_trigger;
+            DROP TABLE IF EXISTS __pgsqlite_trigger_writes;
+            DROP TABLE IF EXISTS __pgsqlite_triggers;
+            DROP TABLE IF EXISTS __pgsqlite_functions;
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '24', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ])),
+        dependencies: vec![24],
+    });
+}
+
+/// Version 24: Enum and composite types, enum labels and sequences draw from the OID allocator too
+fn register_v24_type_oids(registry: &mut BTreeMap<u32, Migration>) {
+    registry.insert(24, Migration {
+        version: 24,
+        name: "type_oids",
+        description: "Record the OIDs of enum and composite types and enum labels in __pgsqlite_oids, and give sequences OIDs",
+        up: MigrationAction::Combined {
+            pre_sql: None,
+            function: migrate_type_oids,
+            post_sql: Some(r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '24', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#),
+        },
+        down: None,
+        dependencies: vec![23],
+    });
+}
+
+/// Keep the OIDs types and labels were created with, which clients may have cached
+fn migrate_type_oids(conn: &rusqlite::Connection) -> anyhow::Result<()> {
+    use crate::metadata::oid_allocator::{OidAllocator, COMPOSITE_RELATION, ENUM_VALUE, TYPE};
+
+    let mut objects: Vec<(&str, String, i64)> = Vec::new();
+    let mut collect = |kind: &'static str, sql: &str| -> rusqlite::Result<()> {
+        let mut stmt = conn.prepare(sql)?;
+        for row in stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))? {
+            let (name, oid) = row?;
+            objects.push((kind, name, oid));
+        }
+        Ok(())
+    };
+    collect(TYPE, "SELECT type_name, type_oid FROM __pgsqlite_enum_types")?;
+    collect(ENUM_VALUE, "SELECT t.type_name || '.' || v.label, v.value_oid FROM __pgsqlite_enum_values v JOIN __pgsqlite_enum_types t USING (type_oid)")?;
+    collect(TYPE, "SELECT type_name, type_oid FROM __pgsqlite_composite_types")?;
+    collect(COMPOSITE_RELATION, "SELECT type_name, relation_oid FROM __pgsqlite_composite_types")?;
+
+    for (kind, name, oid) in objects {
+        OidAllocator::register(conn, kind, &name, oid)?;
+    }
+    OidAllocator::sync_relations(conn)?;
+    Ok(())
+}
+
+/// Version 23: Relations get OIDs from a persistent allocator instead of a hash of their name, pg_enum shows its oid column
+fn register_v23_relation_oids(registry: &mut BTreeMap<u32, Migration>) {
+    registry.insert(23, Migration {
+        version: 23,
+        name: "relation_oids",
+        description: "Store relation OIDs in __pgsqlite_oids so they stay unique and survive renames",
+        up: MigrationAction::Combined {
+            pre_sql: Some(r#"
+            -- pg_enum rows have OIDs of their own
+            DROP VIEW IF EXISTS pg_enum;
+            CREATE VIEW pg_enum AS
+            SELECT 
+                v.value_oid as oid,
+                v.type_oid as enumtypid,
+                v.sort_order as enumsortorder,
+                v.label as enumlabel
+            FROM __pgsqlite_enum_values v;
+            "#),
+            function: migrate_relation_oids,
+            post_sql: Some(r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '23', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#),
+        },
+        down: None,
+        dependencies: vec![22],
+    });
+}
+
+/// The OID the catalog views used to derive from a relation name
+static DERIVED_OID: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"\(\s*\(unicode\(substr\(([\w.]+), 1, 1\)\) \* 1000000\)\s*\+\s*\(unicode\(substr\(([\w.]+) \|\| ' ', 2, 1\)\) \* 10000\)\s*\+\s*\(unicode\(substr\(([\w.]+) \|\| '  ', 3, 1\)\) \* 100\)\s*\+\s*\(length\(([\w.]+)\) \* 7\)\s*\) % 1000000 \+ 16384"
+    ).unwrap()
+});
+
+/// Assign OIDs to the existing relations, move the column defaults recorded under
+/// the old hashed OIDs to them, and have the catalog views look them up
+fn migrate_relation_oids(conn: &rusqlite::Connection) -> anyhow::Result<()> {
+    crate::metadata::OidAllocator::sync_relations(conn)?;
+
+    // Databases whose catalog predates pg_attrdef have no defaults to move
+    let has_attrdef: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'pg_attrdef')",
+        [],
+        |row| row.get(0),
+    )?;
+    if has_attrdef {
+        let tables: Vec<(String, i64)> = conn.prepare("SELECT name, oid FROM __pgsqlite_oids")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, rusqlite::Error>>()?;
+        for (name, oid) in tables {
+            conn.execute(
+                "UPDATE pg_attrdef SET adrelid = ?2 WHERE adrelid = ?1",
+                [crate::catalog::constraint_populator::generate_table_oid(&name), oid.to_string()],
+            )?;
+        }
+    }
+
+    let views: Vec<(String, String)> = conn.prepare(
+        "SELECT name, sql FROM sqlite_master WHERE type = 'view' AND sql LIKE '%unicode(substr(%'"
+    )?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, rusqlite::Error>>()?;
+    for (name, sql) in views {
+        // Constraint and row type OIDs are derived from other names and stay as they are
+        let rewritten = DERIVED_OID.replace_all(&sql, |caps: &regex::Captures| {
+            let relation = &caps[1];
+            if (2..=4).all(|i| &caps[i] == relation) && !relation.ends_with("conname") {
+                format!("pgsqlite_relation_oid({relation})")
+            } else {
+                caps[0].to_string()
+            }
+        });
+        if rewritten != sql {
+            conn.execute_batch(&format!("DROP VIEW IF EXISTS \"{name}\";\n{rewritten};"))?;
+        }
+    }
+    Ok(())
+}
+
+/// Version 22: Non-rowid identity columns draw from a persisted counter, GENERATED ALWAYS rejects explicit values
+fn register_v22_identity_counters(registry: &mut BTreeMap<u32, Migration>) {
+    registry.insert(22, Migration {
+        version: 22,
+        name: "identity_counters",
+        description: "Back identity columns with a counter that never reuses values and enforce GENERATED ALWAYS",
+        up: MigrationAction::Combined {
+            pre_sql: None,
+            function: migrate_identity_counters,
+            post_sql: Some(r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '22', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#),
+        },
+        down: None,
+        dependencies: vec![21],
+    });
+}
+
+/// Add the counter column, start each counter after the largest value handed out so
+/// far, and replace the MAX + 1 triggers
+fn migrate_identity_counters(conn: &rusqlite::Connection) -> anyhow::Result<()> {
+    let has_counter: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM pragma_table_info('__pgsqlite_identity_columns') WHERE name = 'last_value')",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_counter {
+        conn.execute("ALTER TABLE __pgsqlite_identity_columns ADD COLUMN last_value INTEGER NOT NULL DEFAULT 0", [])?;
+    }
+
+    let mut stmt = conn.prepare("SELECT table_name, column_name, identity_kind, is_rowid_alias FROM __pgsqlite_identity_columns")?;
+    let columns = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, crate::metadata::IdentityColumn {
+            column_name: row.get(1)?,
+            kind: row.get(2)?,
+            is_rowid_alias: row.get(3)?,
+        }))
+    })?.collect::<Result<Vec<_>, rusqlite::Error>>()?;
+
+    for (table_name, column) in columns {
+        if !column.is_rowid_alias {
+            conn.execute(
+                &format!(
+                    r#"UPDATE __pgsqlite_identity_columns SET last_value = (SELECT COALESCE(MAX("{}"), 0) FROM "{}")
+                    WHERE table_name = ?1 AND column_name = ?2"#,
+                    column.column_name, table_name
+                ),
+                [&table_name, &column.column_name],
+            )?;
+            conn.execute(&format!(r#"DROP TRIGGER IF EXISTS "__pgsqlite_{}_{}_identity""#, table_name, column.column_name), [])?;
+        }
+        crate::metadata::IdentityColumns::create_identity_triggers(conn, &table_name, std::slice::from_ref(&column))?;
+    }
+    Ok(())
+}
+
+/// Version 21: pg_proc lists the registered functions, pg_description holds COMMENT ON text
+fn register_v21_pg_proc_description(registry: &mut BTreeMap<u32, Migration>) {
+    registry.insert(21, Migration {
+        version: 21,
+        name: "pg_proc_description",
+        description: "Add pg_proc view backed by pgsqlite_functions() and pg_description table",
+        up: MigrationAction::SqlBatch(&[
+            r#"
+            CREATE TABLE IF NOT EXISTS pg_description (
+                objoid INTEGER NOT NULL,
+                classoid INTEGER NOT NULL,
+                objsubid INTEGER NOT NULL DEFAULT 0,
+                description TEXT NOT NULL,
+                PRIMARY KEY (objoid, classoid, objsubid)
+            );
+            "#,
+            r#"
+            -- Only functions registered on the connection are listed; SQLite knows whether they are deterministic
+            CREATE VIEW IF NOT EXISTS pg_proc AS
+            SELECT
+                CAST(json_extract(s.value, '$.oid') AS TEXT) AS oid,
+                json_extract(s.value, '$.proname') AS proname,
+                11 AS pronamespace,
+                10 AS proowner,
+                12 AS prolang,
+                1 AS procost,
+                0 AS prorows,
+                json_extract(s.value, '$.provariadic') AS provariadic,
+                0 AS prosupport,
+                CASE WHEN f.type = 's' THEN 'f' ELSE 'a' END AS prokind,
+                'f' AS prosecdef,
+                'f' AS proleakproof,
+                'f' AS proisstrict,
+                'f' AS proretset,
+                CASE WHEN f.flags & 2048 THEN 'i' ELSE 'v' END AS provolatile,
+                's' AS proparallel,
+                json_extract(s.value, '$.pronargs') AS pronargs,
+                0 AS pronargdefaults,
+                json_extract(s.value, '$.prorettype') AS prorettype,
+                json_extract(s.value, '$.proargtypes') AS proargtypes,
+                NULL AS proallargtypes,
+                NULL AS proargmodes,
+                NULL AS proargnames,
+                NULL AS proargdefaults,
+                NULL AS protrftypes,
+                json_extract(s.value, '$.proname') AS prosrc,
+                NULL AS probin,
+                NULL AS prosqlbody,
+                NULL AS proconfig,
+                NULL AS proacl
+            FROM json_each(pgsqlite_functions()) s
+            JOIN (
+                SELECT name, narg, type, max(flags) AS flags
+                FROM pragma_function_list
+                WHERE builtin = 0
+                GROUP BY name, narg
+            ) f ON f.name = json_extract(s.value, '$.proname')
+                AND f.narg IN (json_extract(s.value, '$.pronargs'), -1);
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '21', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ]),
+        down: Some(MigrationAction::SqlBatch(&[
+            r#"
+            DROP VIEW IF EXISTS pg_proc;
+            DROP TABLE IF EXISTS pg_description;
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '20', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ])),
+        dependencies: vec![20],
+    });
+}
+
+/// Version 13: Make pg_database.datname reflect filename (via function)
+fn register_v13_pg_database_datname_filename(registry: &mut BTreeMap<u32, Migration>) {
+    registry.insert(13, Migration {
+        version: 13,
+        name: "pg_database_datname_filename",
+        description: "pg_database.datname shows database filename",
+        up: MigrationAction::SqlBatch(&[
+            // Recreate pg_database using pgsqlite_datname()
+            r#"
+            DROP VIEW IF EXISTS pg_database;
+            CREATE VIEW pg_database AS
+            SELECT 
+                1            AS oid,
+                pgsqlite_datname() AS datname,
+                10           AS datdba,
+                6            AS encoding,
+                'C'          AS datcollate,
+                'C'          AS datctype,
+                1            AS datallowconn,
+                0            AS datistemplate,
+                -1           AS datconnlimit,
+                0            AS dattablespace,
+                NULL         AS datacl,
+                0            AS datfrozenxid,
+                0            AS datminmxid;
+            "#,
+            // Update schema version
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '13', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ]),
+        down: Some(MigrationAction::SqlBatch(&[
+            // Restore previous view (datname='main')
+            r#"
+            DROP VIEW IF EXISTS pg_database;
+            CREATE VIEW pg_database AS
+            SELECT 
+                1            AS oid,
+                'main'       AS datname,
+                10           AS datdba,
+                6            AS encoding,
+                'C'          AS datcollate,
+                'C'          AS datctype,
+                1            AS datallowconn,
+                0            AS datistemplate,
+                -1           AS datconnlimit,
+                0            AS dattablespace,
+                NULL         AS datacl,
+                0            AS datfrozenxid,
+                0            AS datminmxid;
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '12', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ])),
+        dependencies: vec![12],
+    });
+}
+
+/// Version 14: Track SERIAL and GENERATED ... AS IDENTITY columns
+fn register_v14_identity_columns(registry: &mut BTreeMap<u32, Migration>) {
+    registry.insert(14, Migration {
+        version: 14,
+        name: "identity_columns",
+        description: "Add metadata for SERIAL and identity columns",
+        up: MigrationAction::SqlBatch(&[
+            r#"
+            CREATE TABLE IF NOT EXISTS __pgsqlite_identity_columns (
+                table_name TEXT NOT NULL,
+                column_name TEXT NOT NULL,
+                identity_kind TEXT NOT NULL,  -- 'serial', 'always' or 'by default'
+                is_rowid_alias INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (table_name, column_name)
+            );
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '14', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ]),
+        down: Some(MigrationAction::SqlBatch(&[
+            r#"
+            DROP TABLE IF EXISTS __pgsqlite_identity_columns;
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '13', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ])),
+        dependencies: vec![13],
+    });
+}
+
+/// Version 15: Composite types created with CREATE TYPE ... AS (...)
+fn register_v15_composite_types(registry: &mut BTreeMap<u32, Migration>) {
+    registry.insert(15, Migration {
+        version: 15,
+        name: "composite_types",
+        description: "Add metadata for composite types and their fields",
+        up: MigrationAction::SqlBatch(&[
+            r#"
+            CREATE TABLE IF NOT EXISTS __pgsqlite_composite_types (
+                type_oid INTEGER PRIMARY KEY,
+                type_name TEXT NOT NULL UNIQUE,
+                relation_oid INTEGER NOT NULL,  -- pg_type.typrelid, the attrelid of the fields
+                namespace_oid INTEGER DEFAULT 2200
+            );
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS __pgsqlite_composite_attributes (
+                type_oid INTEGER NOT NULL,
+                attnum INTEGER NOT NULL,
+                attname TEXT NOT NULL,
+                atttype TEXT NOT NULL,
+                PRIMARY KEY (type_oid, attnum),
+                FOREIGN KEY (type_oid) REFERENCES __pgsqlite_composite_types(type_oid)
+            );
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '15', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ]),
+        down: Some(MigrationAction::SqlBatch(&[
+            r#"
+            DROP TABLE IF EXISTS __pgsqlite_composite_attributes;
+            "#,
+            r#"
+            DROP TABLE IF EXISTS __pgsqlite_composite_types;
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '14', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ])),
+        dependencies: vec![14],
+    });
+}
+
+/// Version 16: pg_stat_progress_copy over the running COPY operations
+fn register_v16_pg_stat_progress_copy(registry: &mut BTreeMap<u32, Migration>) {
+    registry.insert(16, Migration {
+        version: 16,
+        name: "pg_stat_progress_copy",
+        description: "Add pg_stat_progress_copy view backed by pgsqlite_copy_progress()",
+        up: MigrationAction::SqlBatch(&[
+            // relid uses the same name hash as pg_stat_user_tables; COPY (query) has relid 0
+            r#"
+            CREATE VIEW IF NOT EXISTS pg_stat_progress_copy AS
+            SELECT
+                p.pid,
+                1 AS datid,
+                pgsqlite_datname() AS datname,
+                CASE WHEN p.relname IS NULL THEN '0' ELSE CAST( (
+                    (unicode(substr(p.relname, 1, 1)) * 1000000) +
+                    (unicode(substr(p.relname || ' ', 2, 1)) * 10000) +
+                    (unicode(substr(p.relname || '  ', 3, 1)) * 100) +
+                    (length(p.relname) * 7)
+                ) % 1000000 + 16384 AS TEXT) END AS relid,
+                p.command,
+                'PIPE' AS type,
+                p.bytes_processed,
+                0 AS bytes_total,
+                p.tuples_processed,
+                0 AS tuples_excluded
+            FROM (
+                SELECT
+                    json_extract(value, '$.pid') AS pid,
+                    json_extract(value, '$.relname') AS relname,
+                    json_extract(value, '$.command') AS command,
+                    json_extract(value, '$.bytes_processed') AS bytes_processed,
+                    json_extract(value, '$.tuples_processed') AS tuples_processed
+                FROM json_each(pgsqlite_copy_progress())
+            ) p;
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '16', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ]),
+        down: Some(MigrationAction::SqlBatch(&[
+            r#"
+            DROP VIEW IF EXISTS pg_stat_progress_copy;
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '15', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ])),
+        dependencies: vec![15],
+    });
+}
+
+/// Version 17: Progress views for VACUUM and multi-statement imports
+fn register_v17_pg_stat_progress_views(registry: &mut BTreeMap<u32, Migration>) {
+    registry.insert(17, Migration {
+        version: 17,
+        name: "pg_stat_progress_views",
+        description: "Back pg_stat_progress_copy by pgsqlite_progress() and add pg_stat_progress_vacuum and pg_stat_progress_import",
+        up: MigrationAction::SqlBatch(&[
+            r#"
+            DROP VIEW IF EXISTS pg_stat_progress_copy;
+            "#,
+            r#"
+            CREATE VIEW pg_stat_progress_copy AS
+            SELECT
+                p.pid,
+                1 AS datid,
+                pgsqlite_datname() AS datname,
+                CASE WHEN p.relname IS NULL THEN '0' ELSE CAST( (
+                    (unicode(substr(p.relname, 1, 1)) * 1000000) +
+                    (unicode(substr(p.relname || ' ', 2, 1)) * 10000) +
+                    (unicode(substr(p.relname || '  ', 3, 1)) * 100) +
+                    (length(p.relname) * 7)
+                ) % 1000000 + 16384 AS TEXT) END AS relid,
+                p.phase AS command,
+                'PIPE' AS type,
+                p.bytes AS bytes_processed,
+                0 AS bytes_total,
+                p.done AS tuples_processed,
+                0 AS tuples_excluded
+            FROM (
+                SELECT
+                    json_extract(value, '$.pid') AS pid,
+                    json_extract(value, '$.relname') AS relname,
+                    json_extract(value, '$.phase') AS phase,
+                    json_extract(value, '$.bytes') AS bytes,
+                    json_extract(value, '$.done') AS done
+                FROM json_each(pgsqlite_progress('copy'))
+            ) p;
+            "#,
+            // SQLite vacuums the whole file, so heap blocks are database pages
+            r#"
+            CREATE VIEW IF NOT EXISTS pg_stat_progress_vacuum AS
+            SELECT
+                p.pid,
+                1 AS datid,
+                pgsqlite_datname() AS datname,
+                CASE WHEN p.relname IS NULL THEN '0' ELSE CAST( (
+                    (unicode(substr(p.relname, 1, 1)) * 1000000) +
+                    (unicode(substr(p.relname || ' ', 2, 1)) * 10000) +
+                    (unicode(substr(p.relname || '  ', 3, 1)) * 100) +
+                    (length(p.relname) * 7)
+                ) % 1000000 + 16384 AS TEXT) END AS relid,
+                p.phase,
+                p.total AS heap_blks_total,
+                p.done AS heap_blks_scanned,
+                p.done AS heap_blks_vacuumed,
+                0 AS index_vacuum_count,
+                0 AS max_dead_tuples,
+                0 AS num_dead_tuples
+            FROM (
+                SELECT
+                    json_extract(value, '$.pid') AS pid,
+                    json_extract(value, '$.relname') AS relname,
+                    json_extract(value, '$.phase') AS phase,
+                    json_extract(value, '$.total') AS total,
+                    json_extract(value, '$.done') AS done
+                FROM json_each(pgsqlite_progress('vacuum'))
+            ) p;
+            "#,
+            // Not a PostgreSQL view: multi-statement scripts such as schema dumps
+            r#"
+            CREATE VIEW IF NOT EXISTS pg_stat_progress_import AS
+            SELECT
+                json_extract(value, '$.pid') AS pid,
+                1 AS datid,
+                pgsqlite_datname() AS datname,
+                json_extract(value, '$.phase') AS phase,
+                json_extract(value, '$.total') AS statements_total,
+                json_extract(value, '$.done') AS statements_done,
+                json_extract(value, '$.bytes') AS bytes_processed
+            FROM json_each(pgsqlite_progress('import'));
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '17', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ]),
+        down: Some(MigrationAction::SqlBatch(&[
+            r#"
+            DROP VIEW IF EXISTS pg_stat_progress_import;
+            "#,
+            r#"
+            DROP VIEW IF EXISTS pg_stat_progress_vacuum;
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '16', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ])),
+        dependencies: vec![16],
+    });
+}
+
+/// Version 18: pg_stat_statements over the statement statistics collected in memory
+fn register_v18_pg_stat_statements(registry: &mut BTreeMap<u32, Migration>) {
+    registry.insert(18, Migration {
+        version: 18,
+        name: "pg_stat_statements",
+        description: "Add pg_stat_statements view backed by pgsqlite_stat_statements()",
+        up: MigrationAction::SqlBatch(&[
+            r#"
+            CREATE VIEW IF NOT EXISTS pg_stat_statements AS
+            SELECT
+                10 AS userid,
+                1 AS dbid,
+                1 AS toplevel,
+                json_extract(value, '$.queryid') AS queryid,
+                json_extract(value, '$.query') AS query,
+                json_extract(value, '$.calls') AS calls,
+                json_extract(value, '$.total_exec_time') AS total_exec_time,
+                json_extract(value, '$.min_exec_time') AS min_exec_time,
+                json_extract(value, '$.max_exec_time') AS max_exec_time,
+                json_extract(value, '$.mean_exec_time') AS mean_exec_time,
+                json_extract(value, '$.stddev_exec_time') AS stddev_exec_time,
+                json_extract(value, '$.rows') AS rows
+            FROM json_each(pgsqlite_stat_statements());
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '18', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ]),
+        down: Some(MigrationAction::SqlBatch(&[
+            r#"
+            DROP VIEW IF EXISTS pg_stat_statements;
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '17', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ])),
+        dependencies: vec![17],
+    });
+}
+
+/// Version 19: pg_stat_activity lists every connected session
+fn register_v19_pg_stat_activity_sessions(registry: &mut BTreeMap<u32, Migration>) {
+    registry.insert(19, Migration {
+        version: 19,
+        name: "pg_stat_activity_sessions",
+        description: "Back pg_stat_activity by pgsqlite_backends() so every session has a row",
+        up: MigrationAction::SqlBatch(&[
+            r#"
+            DROP VIEW IF EXISTS pg_stat_activity;
+            "#,
+            r#"
+            CREATE VIEW pg_stat_activity AS
+            SELECT
+                1 AS datid,
+                json_extract(value, '$.datname') AS datname,
+                json_extract(value, '$.pid') AS pid,
+                10 AS usesysid,
+                json_extract(value, '$.usename') AS usename,
+                json_extract(value, '$.application_name') AS application_name,
+                json_extract(value, '$.client_addr') AS client_addr,
+                json_extract(value, '$.client_port') AS client_port,
+                json_extract(value, '$.backend_start') AS backend_start,
+                NULL AS xact_start,
+                json_extract(value, '$.query_start') AS query_start,
+                json_extract(value, '$.query_start') AS state_change,
+                NULL AS wait_event_type,
+                NULL AS wait_event,
+                json_extract(value, '$.state') AS state,
+                NULL AS backend_xid,
+                NULL AS backend_xmin,
+                json_extract(value, '$.query') AS query,
+                'client backend' AS backend_type
+            FROM json_each(pgsqlite_backends());
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '19', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ]),
+        down: Some(MigrationAction::SqlBatch(&[
+            r#"
+            DROP VIEW IF EXISTS pg_stat_activity;
+            "#,
+            r#"
+            CREATE VIEW pg_stat_activity AS
+            SELECT
+                1                 AS datid,
+                'main'            AS datname,
+                pg_backend_pid()  AS pid,
+                10                AS usesysid,
+                'postgres'        AS usename,
+                'pgsqlite'        AS application_name,
+                inet_client_addr() AS client_addr,
+                inet_client_port() AS client_port,
+                datetime('now')   AS backend_start,
+                NULL              AS xact_start,
+                NULL              AS query_start,
+                datetime('now')   AS state_change,
+                NULL              AS wait_event_type,
+                NULL              AS wait_event,
+                'active'          AS state,
+                NULL              AS backend_xid,
+                NULL              AS backend_xmin,
+                NULL              AS query,
+                'client backend'  AS backend_type;
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '18', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ])),
+        dependencies: vec![18],
+    });
+}
+
+/// Version 20: pg_index, pg_indexes and pg_constraint synthesized from SQLite's pragmas
+fn register_v20_pg_index_constraint_views(registry: &mut BTreeMap<u32, Migration>) {
+    registry.insert(20, Migration {
+        version: 20,
+        name: "pg_index_constraint_views",
+        description: "Derive pg_index, pg_indexes and pg_constraint from index_list/index_info/foreign_key_list",
+        up: MigrationAction::Combined {
+            pre_sql: Some(r#"
+            -- CHECK constraints are the only ones SQLite has no pragma for
+            CREATE TABLE IF NOT EXISTS __pgsqlite_check_constraints (
+                tablename TEXT NOT NULL,
+                conname TEXT NOT NULL,
+                consrc TEXT NOT NULL,
+                PRIMARY KEY (tablename, conname)
+            );
+            DROP TABLE IF EXISTS pg_constraint;
+            DROP TABLE IF EXISTS pg_index;
+            "#),
+            function: backfill_check_constraints,
+            post_sql: Some(r#"
+            CREATE VIEW __pgsqlite_index_catalog AS
+            WITH sqlite_indexes AS (
+                SELECT
+                    m.name AS tablename,
+                    il.name AS sqlite_name,
+                    il."unique" AS is_unique,
+                    il.origin AS origin,
+                    il.partial AS partial,
+                    (SELECT group_concat(name, ', ') FROM (SELECT name FROM pragma_index_info(il.name) ORDER BY seqno)) AS columns,
+                    (SELECT group_concat(name, '_') FROM (SELECT name FROM pragma_index_info(il.name) ORDER BY seqno)) AS name_columns,
+                    (SELECT group_concat(CASE WHEN cid < 0 THEN 0 ELSE cid + 1 END, ' ') FROM (SELECT cid FROM pragma_index_info(il.name) ORDER BY seqno)) AS indkey,
+                    (SELECT count(*) FROM pragma_index_info(il.name)) AS natts,
+                    s.sql AS sql
+                FROM sqlite_master m
+                JOIN pragma_index_list(m.name) il
+                LEFT JOIN sqlite_master s ON s.type = 'index' AND s.name = il.name
+                WHERE m.type = 'table'
+                  AND m.name NOT LIKE 'sqlite_%'
+                  AND m.name NOT LIKE '__pgsqlite_%'
+                UNION ALL
+                -- An INTEGER PRIMARY KEY is the rowid and has no index of its own
+                SELECT m.name, NULL, 1, 'pk', 0, p.name, p.name, CAST(p.cid + 1 AS TEXT), 1, NULL
+                FROM sqlite_master m
+                JOIN pragma_table_info(m.name) p
+                WHERE m.type = 'table'
+                  AND m.name NOT LIKE 'sqlite_%'
+                  AND m.name NOT LIKE '__pgsqlite_%'
+                  AND p.pk = 1
+                  AND NOT EXISTS (SELECT 1 FROM pragma_index_list(m.name) WHERE origin = 'pk')
+            ),
+            named AS (
+                SELECT
+                    *,
+                    -- Name implicit indexes the way PostgreSQL names constraint indexes
+                    CASE origin
+                        WHEN 'pk' THEN tablename || '_pkey'
+                        WHEN 'u' THEN tablename || '_' || name_columns || '_key'
+                        ELSE sqlite_name
+                    END AS indexname
+                FROM sqlite_indexes
+            )
+            SELECT
+                CAST(
+                    (
+                        (unicode(substr(indexname, 1, 1)) * 1000000) +
+                        (unicode(substr(indexname || ' ', 2, 1)) * 10000) +
+                        (unicode(substr(indexname || '  ', 3, 1)) * 100) +
+                        (length(indexname) * 7)
+                    ) % 1000000 + 16384
+                AS TEXT) AS indexrelid,
+                CAST(
+                    (
+                        (unicode(substr(tablename, 1, 1)) * 1000000) +
+                        (unicode(substr(tablename || ' ', 2, 1)) * 10000) +
+                        (unicode(substr(tablename || '  ', 3, 1)) * 100) +
+                        (length(tablename) * 7)
+                    ) % 1000000 + 16384
+                AS TEXT) AS indrelid,
+                tablename,
+                indexname,
+                origin,
+                is_unique,
+                natts,
+                indkey,
+                columns,
+                CASE
+                    -- Expression and partial indexes keep their original definition
+                    WHEN sql IS NOT NULL AND (partial OR columns IS NULL) THEN sql
+                    ELSE 'CREATE ' || CASE WHEN is_unique THEN 'UNIQUE ' ELSE '' END || 'INDEX ' || indexname ||
+                         ' ON public.' || tablename || ' USING btree (' || columns || ')'
+                END AS indexdef
+            FROM named;
+
+            CREATE VIEW pg_index AS
+            SELECT
+                indexrelid,
+                indrelid,
+                natts AS indnatts,
+                natts AS indnkeyatts,
+                CASE WHEN is_unique THEN 't' ELSE 'f' END AS indisunique,
+                CASE WHEN origin = 'pk' THEN 't' ELSE 'f' END AS indisprimary,
+                'f' AS indisexclusion,
+                't' AS indimmediate,
+                'f' AS indisclustered,
+                't' AS indisvalid,
+                'f' AS indcheckxmin,
+                't' AS indisready,
+                't' AS indislive,
+                'f' AS indisreplident,
+                indkey,
+                NULL AS indcollation,
+                NULL AS indclass,
+                NULL AS indoption,
+                NULL AS indexprs,
+                NULL AS indpred
+            FROM __pgsqlite_index_catalog;
+
+            CREATE VIEW pg_indexes AS
+            SELECT
+                'public' AS schemaname,
+                tablename,
+                indexname,
+                NULL AS tablespace,
+                indexdef
+            FROM __pgsqlite_index_catalog;
+
+            CREATE VIEW pg_constraint AS
+            -- Primary keys and unique constraints, backed by their index
+            SELECT
+                indexrelid AS oid,
+                indexname AS conname,
+                2200 AS connamespace,
+                CASE origin WHEN 'pk' THEN 'p' ELSE 'u' END AS contype,
+                'f' AS condeferrable,
+                'f' AS condeferred,
+                't' AS convalidated,
+                indrelid AS conrelid,
+                0 AS contypid,
+                indexrelid AS conindid,
+                0 AS conparentid,
+                0 AS confrelid,
+                ' ' AS confupdtype,
+                ' ' AS confdeltype,
+                ' ' AS confmatchtype,
+                't' AS conislocal,
+                0 AS coninhcount,
+                'f' AS connoinherit,
+                '{' || replace(indkey, ' ', ',') || '}' AS conkey,
+                NULL AS confkey,
+                NULL AS conpfeqop,
+                NULL AS conppeqop,
+                NULL AS conffeqop,
+                NULL AS conexclop,
+                NULL AS conbin,
+                CASE origin WHEN 'pk' THEN 'PRIMARY KEY' ELSE 'UNIQUE' END || ' (' || columns || ')' AS consrc
+            FROM __pgsqlite_index_catalog
+            WHERE origin IN ('pk', 'u')
+            UNION ALL
+            -- Foreign keys, one row per constraint
+            SELECT
+                CAST(
+                    (
+                        (unicode(substr(conname, 1, 1)) * 1000000) +
+                        (unicode(substr(conname || ' ', 2, 1)) * 10000) +
+                        (unicode(substr(conname || '  ', 3, 1)) * 100) +
+                        (length(conname) * 7)
+                    ) % 1000000 + 16384
+                AS TEXT) AS oid,
+                conname,
+                2200,
+                'f',
+                'f',
+                'f',
+                't',
+                CAST(
+                    (
+                        (unicode(substr(tablename, 1, 1)) * 1000000) +
+                        (unicode(substr(tablename || ' ', 2, 1)) * 10000) +
+                        (unicode(substr(tablename || '  ', 3, 1)) * 100) +
+                        (length(tablename) * 7)
+                    ) % 1000000 + 16384
+                AS TEXT),
+                0,
+                0,
+                0,
+                CAST(
+                    (
+                        (unicode(substr(reftable, 1, 1)) * 1000000) +
+                        (unicode(substr(reftable || ' ', 2, 1)) * 10000) +
+                        (unicode(substr(reftable || '  ', 3, 1)) * 100) +
+                        (length(reftable) * 7)
+                    ) % 1000000 + 16384
+                AS TEXT),
+                CASE on_update
+                    WHEN 'CASCADE' THEN 'c'
+                    WHEN 'SET NULL' THEN 'n'
+                    WHEN 'SET DEFAULT' THEN 'd'
+                    WHEN 'RESTRICT' THEN 'r'
+                    ELSE 'a'
+                END,
+                CASE on_delete
+                    WHEN 'CASCADE' THEN 'c'
+                    WHEN 'SET NULL' THEN 'n'
+                    WHEN 'SET DEFAULT' THEN 'd'
+                    WHEN 'RESTRICT' THEN 'r'
+                    ELSE 'a'
+                END,
+                's',
+                't',
+                0,
+                'f',
+                '{' || conkey || '}',
+                '{' || confkey || '}',
+                NULL,
+                NULL,
+                NULL,
+                NULL,
+                NULL,
+                'FOREIGN KEY (' || columns || ') REFERENCES ' || reftable || '(' || refcolumns || ')' ||
+                    CASE WHEN on_update = 'NO ACTION' THEN '' ELSE ' ON UPDATE ' || on_update END ||
+                    CASE WHEN on_delete = 'NO ACTION' THEN '' ELSE ' ON DELETE ' || on_delete END
+            FROM (
+                SELECT
+                    tablename,
+                    reftable,
+                    tablename || '_' || group_concat(from_column, '_') || '_fkey' AS conname,
+                    group_concat(from_column, ', ') AS columns,
+                    group_concat(to_column, ', ') AS refcolumns,
+                    group_concat(from_attnum, ',') AS conkey,
+                    group_concat(to_attnum, ',') AS confkey,
+                    on_update,
+                    on_delete
+                FROM (
+                    SELECT
+                        m.name AS tablename,
+                        fk.id AS id,
+                        fk."table" AS reftable,
+                        fk."from" AS from_column,
+                        -- A reference without columns targets the parent's primary key
+                        COALESCE(fk."to", (SELECT name FROM pragma_table_info(fk."table") WHERE pk = fk.seq + 1)) AS to_column,
+                        (SELECT cid + 1 FROM pragma_table_info(m.name) WHERE name = fk."from") AS from_attnum,
+                        (SELECT cid + 1 FROM pragma_table_info(fk."table")
+                            WHERE name = COALESCE(fk."to", (SELECT name FROM pragma_table_info(fk."table") WHERE pk = fk.seq + 1))) AS to_attnum,
+                        fk.on_update AS on_update,
+                        fk.on_delete AS on_delete
+                    FROM sqlite_master m
+                    JOIN pragma_foreign_key_list(m.name) fk
+                    WHERE m.type = 'table'
+                      AND m.name NOT LIKE 'sqlite_%'
+                      AND m.name NOT LIKE '__pgsqlite_%'
+                    ORDER BY m.name, fk.id, fk.seq
+                )
+                GROUP BY tablename, id
+            )
+            UNION ALL
+            -- CHECK constraints recorded at CREATE TABLE time
+            SELECT
+                CAST(
+                    (
+                        (unicode(substr(c.conname, 1, 1)) * 1000000) +
+                        (unicode(substr(c.conname || ' ', 2, 1)) * 10000) +
+                        (unicode(substr(c.conname || '  ', 3, 1)) * 100) +
+                        (length(c.conname) * 7)
+                    ) % 1000000 + 16384
+                AS TEXT),
+                c.conname,
+                2200,
+                'c',
+                'f',
+                'f',
+                't',
+                CAST(
+                    (
+                        (unicode(substr(c.tablename, 1, 1)) * 1000000) +
+                        (unicode(substr(c.tablename || ' ', 2, 1)) * 10000) +
+                        (unicode(substr(c.tablename || '  ', 3, 1)) * 100) +
+                        (length(c.tablename) * 7)
+                    ) % 1000000 + 16384
+                AS TEXT),
+                0,
+                0,
+                0,
+                0,
+                ' ',
+                ' ',
+                ' ',
+                't',
+                0,
+                'f',
+                NULL,
+                NULL,
+                NULL,
+                NULL,
+                NULL,
+                NULL,
+                NULL,
+                c.consrc
+            FROM __pgsqlite_check_constraints c
+            JOIN sqlite_master m ON m.type = 'table' AND m.name = c.tablename;
+
+            DROP VIEW IF EXISTS pg_class;
+            CREATE VIEW pg_class AS
+            SELECT 
+                -- Generate stable OID from table name using SQLite's built-in functions
+                -- Use a deterministic formula based on the table name's character codes
+                -- Cast to TEXT to handle both numeric and string comparisons
+                CAST(
+                    (
+                        (unicode(substr(name, 1, 1)) * 1000000) +
+                        (unicode(substr(name || ' ', 2, 1)) * 10000) +
+                        (unicode(substr(name || '  ', 3, 1)) * 100) +
+                        (length(name) * 7)
+                    ) % 1000000 + 16384
+                AS TEXT) as oid,
+                name as relname,
+                2200 as relnamespace,  -- public schema
+                CASE 
+                    WHEN type = 'table' THEN 'r'
+                    WHEN type = 'view' THEN 'v'
+                    WHEN type = 'index' THEN 'i'
+                END as relkind,
+                10 as relowner,
+                CASE WHEN type = 'index' THEN 403 ELSE 0 END as relam,
+                0 as relfilenode,
+                0 as reltablespace,
+                0 as relpages,
+                -1 as reltuples,
+                0 as relallvisible,
+                0 as reltoastrelid,
+                CASE WHEN type = 'table' THEN 't' ELSE 'f' END as relhasindex,
+                'f' as relisshared,
+                'p' as relpersistence,
+                -- Generate type OID using a different formula to avoid collisions
+                CAST(
+                    (
+                        (unicode(substr(name || '_type', 1, 1)) * 1000000) +
+                        (unicode(substr(name || '_type' || ' ', 2, 1)) * 10000) +
+                        (unicode(substr(name || '_type' || '  ', 3, 1)) * 100) +
+                        (length(name || '_type') * 7)
+                    ) % 1000000 + 16384
+                AS TEXT) as reltype,
+                0 as reloftype,
+                0 as relnatts,
+                0 as relchecks,
+                'f' as relhasrules,
+                'f' as relhastriggers,
+                'f' as relhassubclass,
+                'f' as relrowsecurity,
+                'f' as relforcerowsecurity,
+                't' as relispopulated,
+                'p' as relreplident,
+                't' as relispartition,
+                0 as relrewrite,
+                0 as relfrozenxid,
+                '{}' as relminmxid,
+                '' as relacl,
+                '' as reloptions,
+                '' as relpartbound
+            FROM sqlite_master
+            WHERE type IN ('table', 'view', 'index')
+              AND name NOT LIKE 'sqlite_%'
+              AND name NOT LIKE '__pgsqlite_%'
+
+            UNION ALL
+            -- Indexes SQLite keeps implicitly for primary keys and unique constraints
+            SELECT
+                indexrelid,
+                indexname,
+                2200,
+                'i',
+                10,
+                403,
+                0,
+                0,
+                0,
+                -1,
+                0,
+                0,
+                'f',
+                'f',
+                'p',
+                '0',
+                0,
+                natts,
+                0,
+                'f',
+                'f',
+                'f',
+                'f',
+                'f',
+                't',
+                'p',
+                'f',
+                0,
+                0,
+                '{}',
+                '',
+                '',
+                ''
+            FROM __pgsqlite_index_catalog
+            WHERE origin IN ('pk', 'u');
+
+            UPDATE __pgsqlite_metadata 
+            SET value = '20', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#),
+        },
+        down: Some(MigrationAction::SqlBatch(&[
+            r#"
+            DROP VIEW IF EXISTS pg_constraint;
+            DROP VIEW IF EXISTS pg_indexes;
+            DROP VIEW IF EXISTS pg_index;
+            DROP VIEW IF EXISTS pg_class;
+            "#,
+            r#"
+            CREATE VIEW pg_class AS
+            SELECT 
+                -- Generate stable OID from table name using SQLite's built-in functions
+                -- Use a deterministic formula based on the table name's character codes
+                -- Cast to TEXT to handle both numeric and string comparisons
+                CAST(
+                    (
+                        (unicode(substr(name, 1, 1)) * 1000000) +
+                        (unicode(substr(name || ' ', 2, 1)) * 10000) +
+                        (unicode(substr(name || '  ', 3, 1)) * 100) +
+                        (length(name) * 7)
+                    ) % 1000000 + 16384
+                AS TEXT) as oid,
+                name as relname,
+                2200 as relnamespace,  -- public schema
+                CASE 
+                    WHEN type = 'table' THEN 'r'
+                    WHEN type = 'view' THEN 'v'
+                    WHEN type = 'index' THEN 'i'
+                END as relkind,
+                10 as relowner,
+                CASE WHEN type = 'index' THEN 403 ELSE 0 END as relam,
+                0 as relfilenode,
+                0 as reltablespace,
+                0 as relpages,
+                -1 as reltuples,
+                0 as relallvisible,
+                0 as reltoastrelid,
+                CASE WHEN type = 'table' THEN 't' ELSE 'f' END as relhasindex,
+                'f' as relisshared,
+                'p' as relpersistence,
+                -- Generate type OID using a different formula to avoid collisions
+                CAST(
+                    (
+                        (unicode(substr(name || '_type', 1, 1)) * 1000000) +
+                        (unicode(substr(name || '_type' || ' ', 2, 1)) * 10000) +
+                        (unicode(substr(name || '_type' || '  ', 3, 1)) * 100) +
+                        (length(name || '_type') * 7)
+                    ) % 1000000 + 16384
+                AS TEXT) as reltype,
+                0 as reloftype,
+                0 as relnatts,
+                0 as relchecks,
+                'f' as relhasrules,
+                'f' as relhastriggers,
+                'f' as relhassubclass,
+                'f' as relrowsecurity,
+                'f' as relforcerowsecurity,
+                't' as relispopulated,
+                'p' as relreplident,
+                't' as relispartition,
+                0 as relrewrite,
+                0 as relfrozenxid,
+                '{}' as relminmxid,
+                '' as relacl,
+                '' as reloptions,
+                '' as relpartbound
+            FROM sqlite_master
+            WHERE type IN ('table', 'view', 'index')
+              AND name NOT LIKE 'sqlite_%'
+              AND name NOT LIKE '__pgsqlite_%';
+            "#,
+            r#"
+            DROP VIEW IF EXISTS __pgsqlite_index_catalog;
+            "#,
+            r#"
+            CREATE TABLE pg_constraint (
+                oid TEXT PRIMARY KEY,
+                conname TEXT NOT NULL,
+                connamespace INTEGER DEFAULT 2200,
+                contype CHAR(1) NOT NULL,
+                condeferrable BOOLEAN DEFAULT 0,
+                condeferred BOOLEAN DEFAULT 0,
+                convalidated BOOLEAN DEFAULT 1,
+                conrelid TEXT NOT NULL,
+                contypid INTEGER DEFAULT 0,
+                conindid INTEGER DEFAULT 0,
+                conparentid INTEGER DEFAULT 0,
+                confrelid INTEGER DEFAULT 0,
+                confupdtype CHAR(1) DEFAULT ' ',
+                confdeltype CHAR(1) DEFAULT ' ',
+                confmatchtype CHAR(1) DEFAULT ' ',
+                conislocal BOOLEAN DEFAULT 1,
+                coninhcount INTEGER DEFAULT 0,
+                connoinherit BOOLEAN DEFAULT 0,
+                conkey TEXT,
+                confkey TEXT,
+                conpfeqop TEXT,
+                conppeqop TEXT,
+                conffeqop TEXT,
+                conexclop TEXT,
+                conbin TEXT,
+                consrc TEXT
+            );
+            "#,
+            r#"
+            CREATE TABLE pg_index (
+                indexrelid TEXT PRIMARY KEY,
+                indrelid TEXT NOT NULL,
+                indnatts SMALLINT NOT NULL,
+                indnkeyatts SMALLINT NOT NULL,
+                indisunique BOOLEAN DEFAULT 0,
+                indisprimary BOOLEAN DEFAULT 0,
+                indisexclusion BOOLEAN DEFAULT 0,
+                indimmediate BOOLEAN DEFAULT 1,
+                indisclustered BOOLEAN DEFAULT 0,
+                indisvalid BOOLEAN DEFAULT 1,
+                indcheckxmin BOOLEAN DEFAULT 0,
+                indisready BOOLEAN DEFAULT 1,
+                indislive BOOLEAN DEFAULT 1,
+                indisreplident BOOLEAN DEFAULT 0,
+                indkey TEXT,
+                indcollation TEXT,
+                indclass TEXT,
+                indoption TEXT,
+                indexprs TEXT,
+                indpred TEXT
+            );
+            "#,
+            r#"
+            DROP TABLE IF EXISTS __pgsqlite_check_constraints;
+            "#,
+            r#"
+            UPDATE __pgsqlite_metadata 
+            SET value = '19', updated_at = strftime('%s', 'now')
+            WHERE key = 'schema_version';
+            "#,
+        ])),
+        dependencies: vec![19],
+    });
+}
+
+/// Record the CHECK constraints of existing tables for the pg_constraint view
+fn backfill_check_constraints(conn: &rusqlite::Connection) -> anyhow::Result<()> {
+    let mut stmt = conn.prepare("
+        SELECT name, sql FROM sqlite_master 
+        WHERE type = 'table' 
+        AND name NOT LIKE 'sqlite_%'
+        AND name NOT LIKE '__pgsqlite_%'
+    ")?;
+    let tables = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?.collect::<Result<Vec<_>, rusqlite::Error>>()?;
+    
+    for (table_name, create_sql) in tables {
+        crate::catalog::constraint_populator::store_check_constraints(conn, &table_name, &create_sql)?;
+    }
+    Ok(())
+}
+
 /// Version 1: Initial schema
 fn register_v1_initial_schema(registry: &mut BTreeMap<u32, Migration>) {
     registry.insert(1, Migration {
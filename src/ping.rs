@@ -0,0 +1,163 @@
+//! `pgsqlite ping`: a health check for container HEALTHCHECK lines.
+//!
+//! Connects like a client would, waits for the server to finish the startup
+//! handshake, runs `SELECT 1`, and says goodbye. Any failure along the way is an
+//! error, so the process exits non-zero without pg_isready or psql installed.
+
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+
+/// Where and as whom to connect
+#[derive(Debug, Clone)]
+pub struct PingTarget {
+    /// Host name or address, or a socket directory when it starts with `/`
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    pub database: String,
+    /// Limit for connecting and for each read and write
+    pub timeout: Duration,
+}
+
+impl PingTarget {
+    /// Human-readable address, as pg_isready prints it
+    pub fn address(&self) -> String {
+        if self.host.starts_with('/') {
+            format!("{}/.s.PGSQL.{}", self.host.trim_end_matches('/'), self.port)
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+}
+
+/// Print the outcome the way pg_isready does and return the process exit code:
+/// 0 when the server answered, 2 when it did not
+pub fn run(target: &PingTarget) -> i32 {
+    match ping(target) {
+        Ok(()) => {
+            println!("{} - accepting connections", target.address());
+            0
+        }
+        Err(e) => {
+            eprintln!("{} - no response: {e:#}", target.address());
+            2
+        }
+    }
+}
+
+/// Perform startup, `SELECT 1` and terminate against the server
+pub fn ping(target: &PingTarget) -> Result<()> {
+    if target.host.starts_with('/') {
+        #[cfg(unix)]
+        {
+            let stream = std::os::unix::net::UnixStream::connect(target.address())?;
+            stream.set_read_timeout(Some(target.timeout))?;
+            stream.set_write_timeout(Some(target.timeout))?;
+            return handshake(stream, target);
+        }
+        #[cfg(not(unix))]
+        bail!("Unix sockets are not supported on this platform");
+    }
+
+    let address = (target.host.as_str(), target.port).to_socket_addrs()?
+        .next()
+        .ok_or_else(|| anyhow!("could not resolve host {}", target.host))?;
+    let stream = TcpStream::connect_timeout(&address, target.timeout)?;
+    stream.set_read_timeout(Some(target.timeout))?;
+    stream.set_write_timeout(Some(target.timeout))?;
+    stream.set_nodelay(true)?;
+    handshake(stream, target)
+}
+
+fn handshake<S: Read + Write>(mut stream: S, target: &PingTarget) -> Result<()> {
+    stream.write_all(&startup_message(&target.user, &target.database))?;
+    loop {
+        match read_message(&mut stream)? {
+            (b'R', body) => match body.get(..4).map(|code| i32::from_be_bytes(code.try_into().unwrap())) {
+                Some(0) => {}
+                Some(method) => bail!("server requested authentication method {method}, which ping does not support"),
+                None => bail!("malformed authentication message"),
+            },
+            (b'E', body) => bail!("{}", error_message(&body)),
+            (b'Z', _) => break,
+            // ParameterStatus, BackendKeyData and notices
+            _ => {}
+        }
+    }
+
+    stream.write_all(&query_message("SELECT 1"))?;
+    let mut value = None;
+    loop {
+        match read_message(&mut stream)? {
+            (b'D', body) => value = first_column(&body),
+            (b'E', body) => bail!("{}", error_message(&body)),
+            (b'Z', _) => break,
+            _ => {}
+        }
+    }
+    if value.as_deref() != Some("1") {
+        bail!("SELECT 1 returned {value:?}");
+    }
+
+    // The server may already be closing the connection; the check has passed either way
+    let _ = stream.write_all(&[b'X', 0, 0, 0, 4]);
+    Ok(())
+}
+
+fn startup_message(user: &str, database: &str) -> Vec<u8> {
+    let mut body = 196608i32.to_be_bytes().to_vec(); // protocol 3.0
+    for (name, value) in [("user", user), ("database", database), ("application_name", "pgsqlite ping")] {
+        body.extend_from_slice(name.as_bytes());
+        body.push(0);
+        body.extend_from_slice(value.as_bytes());
+        body.push(0);
+    }
+    body.push(0);
+
+    let mut message = ((body.len() + 4) as i32).to_be_bytes().to_vec();
+    message.extend_from_slice(&body);
+    message
+}
+
+fn query_message(sql: &str) -> Vec<u8> {
+    let mut message = vec![b'Q'];
+    message.extend_from_slice(&((sql.len() + 5) as i32).to_be_bytes());
+    message.extend_from_slice(sql.as_bytes());
+    message.push(0);
+    message
+}
+
+/// Read one backend message as its type byte and body
+fn read_message(stream: &mut impl Read) -> io::Result<(u8, Vec<u8>)> {
+    let mut header = [0u8; 5];
+    stream.read_exact(&mut header)?;
+    let length = i32::from_be_bytes([header[1], header[2], header[3], header[4]]);
+    if !(4..=1 << 20).contains(&length) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("invalid message length {length}")));
+    }
+    let mut body = vec![0u8; length as usize - 4];
+    stream.read_exact(&mut body)?;
+    Ok((header[0], body))
+}
+
+/// The message field of an ErrorResponse
+fn error_message(body: &[u8]) -> String {
+    body.split(|&b| b == 0)
+        .find_map(|field| field.strip_prefix(b"M"))
+        .map(|message| String::from_utf8_lossy(message).into_owned())
+        .unwrap_or_else(|| "server reported an error".to_string())
+}
+
+/// The first column of a DataRow, None when it is NULL or missing
+fn first_column(body: &[u8]) -> Option<String> {
+    let count = i16::from_be_bytes(body.get(..2)?.try_into().ok()?);
+    if count < 1 {
+        return None;
+    }
+    let length = i32::from_be_bytes(body.get(2..6)?.try_into().ok()?);
+    let value = body.get(6..6 + usize::try_from(length).ok()?)?;
+    Some(String::from_utf8_lossy(value).into_owned())
+}
@@ -0,0 +1,81 @@
+// Platform-specific listeners and process lifecycle
+#[cfg(windows)]
+pub mod named_pipe;
+#[cfg(windows)]
+pub mod service;
+
+#[cfg(windows)]
+pub use named_pipe::NamedPipeListener;
+
+/// Resolves each time the server is asked to reread its configuration file: SIGHUP
+/// on Unix, and pg_reload_conf() on every platform.
+pub struct ReloadSignal {
+    #[cfg(unix)]
+    hangup: Option<tokio::signal::unix::Signal>,
+}
+
+impl ReloadSignal {
+    /// Start listening; from now on SIGHUP no longer terminates the process
+    pub fn listen() -> Self {
+        Self {
+            #[cfg(unix)]
+            hangup: tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()).ok(),
+        }
+    }
+
+    pub async fn recv(&mut self) {
+        #[cfg(unix)]
+        if let Some(hangup) = &mut self.hangup {
+            tokio::select! {
+                _ = hangup.recv() => {}
+                _ = crate::config::reload_requested() => {}
+            }
+            return;
+        }
+        crate::config::reload_requested().await;
+    }
+}
+
+/// Resolves when the server is asked to stop: Ctrl+C everywhere, SIGTERM on Unix,
+/// and Ctrl+Break, console close, system shutdown or a service stop on Windows.
+pub async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+            }
+            Err(_) => {
+                tokio::signal::ctrl_c().await.ok();
+            }
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        use tokio::signal::windows::{ctrl_break, ctrl_close, ctrl_shutdown};
+        let (Ok(mut brk), Ok(mut close), Ok(mut shutdown)) = (ctrl_break(), ctrl_close(), ctrl_shutdown()) else {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = service::stop_requested() => {}
+            }
+            return;
+        };
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = brk.recv() => {}
+            _ = close.recv() => {}
+            _ = shutdown.recv() => {}
+            _ = service::stop_requested() => {}
+        }
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    {
+        tokio::signal::ctrl_c().await.ok();
+    }
+}
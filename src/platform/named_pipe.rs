@@ -0,0 +1,36 @@
+use std::io;
+use tokio::net::windows::named_pipe::{NamedPipeServer, ServerOptions};
+
+/// Accepts PostgreSQL connections on a Windows named pipe, the local transport
+/// Windows offers in place of a Unix domain socket.
+///
+/// A pipe instance serves exactly one client, so a fresh instance is created
+/// as soon as the pending one is connected and there's always one waiting.
+pub struct NamedPipeListener {
+    name: String,
+    pending: NamedPipeServer,
+}
+
+impl NamedPipeListener {
+    /// Create the first instance of the pipe, failing if another process already owns the name
+    pub fn bind(name: &str) -> io::Result<Self> {
+        let pending = ServerOptions::new()
+            .first_pipe_instance(true)
+            .create(name)?;
+        Ok(Self {
+            name: name.to_string(),
+            pending,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Wait for the next client and hand over its pipe instance
+    pub async fn accept(&mut self) -> io::Result<NamedPipeServer> {
+        self.pending.connect().await?;
+        let next = ServerOptions::new().create(&self.name)?;
+        Ok(std::mem::replace(&mut self.pending, next))
+    }
+}
@@ -0,0 +1,82 @@
+use once_cell::sync::Lazy;
+use std::ffi::OsString;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::error;
+use windows_service::service::{
+    ServiceControl, ServiceControlAccept, ServiceExitCode, ServiceState, ServiceStatus, ServiceType,
+};
+use windows_service::service_control_handler::{self, ServiceControlHandlerResult};
+use windows_service::{define_windows_service, service_dispatcher};
+
+/// Name used when registering with the service control manager. It is ignored
+/// for own-process services, so the service may be installed under any name.
+pub const SERVICE_NAME: &str = "pgsqlite";
+
+/// Signalled when the service control manager asks the service to stop
+static STOP: Lazy<Notify> = Lazy::new(Notify::new);
+
+/// The server started once the service is running
+type Server = Box<dyn FnOnce() -> anyhow::Result<()> + Send>;
+static SERVER: Mutex<Option<Server>> = Mutex::new(None);
+
+define_windows_service!(ffi_service_main, service_main);
+
+/// Run `server` as a Windows service. Blocks until the service stops; the
+/// server should return once `crate::platform::shutdown_signal()` resolves.
+pub fn run(server: impl FnOnce() -> anyhow::Result<()> + Send + 'static) -> windows_service::Result<()> {
+    *SERVER.lock().unwrap() = Some(Box::new(server));
+    service_dispatcher::start(SERVICE_NAME, ffi_service_main)
+}
+
+/// Resolves when a service stop or system shutdown was requested
+pub async fn stop_requested() {
+    STOP.notified().await;
+}
+
+fn service_main(_arguments: Vec<OsString>) {
+    if let Err(e) = run_service() {
+        error!("Windows service failed: {}", e);
+    }
+}
+
+fn run_service() -> windows_service::Result<()> {
+    let status_handle = service_control_handler::register(SERVICE_NAME, |control| match control {
+        ServiceControl::Stop | ServiceControl::Shutdown => {
+            // notify_one keeps the permit if the server isn't waiting yet
+            STOP.notify_one();
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        _ => ServiceControlHandlerResult::NotImplemented,
+    })?;
+
+    let status = |current_state, controls_accepted, exit_code| ServiceStatus {
+        service_type: ServiceType::OWN_PROCESS,
+        current_state,
+        controls_accepted,
+        exit_code: ServiceExitCode::Win32(exit_code),
+        checkpoint: 0,
+        wait_hint: Duration::default(),
+        process_id: None,
+    };
+
+    status_handle.set_service_status(status(
+        ServiceState::Running,
+        ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+        0,
+    ))?;
+
+    let server = SERVER.lock().unwrap().take();
+    let exit_code = match server.map(|server| server()) {
+        Some(Ok(())) => 0,
+        Some(Err(e)) => {
+            error!("pgsqlite service stopped with an error: {}", e);
+            1
+        }
+        None => 1,
+    };
+
+    status_handle.set_service_status(status(ServiceState::Stopped, ServiceControlAccept::empty(), exit_code))
+}
@@ -7,6 +7,11 @@ use super::messages::*;
 #[derive(Clone)]
 pub struct PostgresCodec {
     state: CodecState,
+    /// Row count of the last CommandComplete encoded, read by statement statistics
+    completed_rows: u64,
+    /// Reported in place of the UPDATE command tag, for statements that run as an UPDATE
+    /// on behalf of another command, such as soft deletes
+    update_tag: Option<&'static str>,
 }
 
 #[derive(Debug, Clone)]
@@ -19,8 +24,20 @@ impl PostgresCodec {
     pub fn new() -> Self {
         PostgresCodec {
             state: CodecState::WaitingForStartup,
+            completed_rows: 0,
+            update_tag: None,
         }
     }
+
+    /// Rows reported by the CommandComplete tags since the last call
+    pub fn take_completed_rows(&mut self) -> u64 {
+        std::mem::take(&mut self.completed_rows)
+    }
+
+    /// Report the UPDATE command tags of the running statement as `tag`, until set to None
+    pub fn report_updates_as(&mut self, tag: Option<&'static str>) {
+        self.update_tag = tag;
+    }
 }
 
 impl Default for PostgresCodec {
@@ -59,7 +76,13 @@ impl Encoder<BackendMessage> for PostgresCodec {
             BackendMessage::ReadyForQuery { status } => encode_ready_for_query(status, dst),
             BackendMessage::RowDescription(fields) => encode_row_description(fields, dst),
             BackendMessage::DataRow(values) => encode_data_row(&values, dst),
-            BackendMessage::CommandComplete { tag } => encode_command_complete(&tag, dst),
+            BackendMessage::CommandComplete { tag } => {
+                self.completed_rows += command_tag_rows(&tag);
+                match (self.update_tag, tag.strip_prefix("UPDATE ")) {
+                    (Some(update_tag), Some(rows)) => encode_command_complete(&format!("{update_tag} {rows}"), dst),
+                    _ => encode_command_complete(&tag, dst),
+                }
+            }
             BackendMessage::EmptyQueryResponse => encode_empty_query_response(dst),
             BackendMessage::ErrorResponse(err) => encode_error_response(*err, dst),
             BackendMessage::NoticeResponse(notice) => encode_notice_response(notice, dst),
@@ -69,6 +92,9 @@ impl Encoder<BackendMessage> for PostgresCodec {
             BackendMessage::PortalSuspended => encode_portal_suspended(dst),
             BackendMessage::NoData => encode_no_data(dst),
             BackendMessage::ParameterDescription(oids) => encode_parameter_description(oids, dst),
+            BackendMessage::CopyOutResponse { format, column_formats } => encode_copy_out_response(format, column_formats, dst),
+            BackendMessage::CopyData(data) => encode_copy_data(&data, dst),
+            BackendMessage::CopyDone => encode_copy_done(dst),
         }
         Ok(())
     }
@@ -202,6 +228,16 @@ fn decode_normal_message(src: &mut BytesMut) -> io::Result<Option<FrontendMessag
     }
 }
 
+/// The row count carried by a command tag such as `SELECT 5` or `INSERT 0 3`
+fn command_tag_rows(tag: &str) -> u64 {
+    match tag.split_once(' ') {
+        Some(("SELECT" | "INSERT" | "UPDATE" | "DELETE" | "MERGE" | "COPY" | "FETCH" | "MOVE", rest)) => {
+            rest.rsplit(' ').next().and_then(|n| n.parse().ok()).unwrap_or(0)
+        }
+        _ => 0,
+    }
+}
+
 fn encode_authentication(auth: AuthenticationMessage, dst: &mut BytesMut) {
     dst.put_u8(b'R');
     let len_pos = dst.len();
@@ -329,6 +365,35 @@ fn encode_error_response(err: ErrorResponse, dst: &mut BytesMut) {
         put_cstring(dst, &position.to_string());
     }
     
+    if let Some(internal_position) = err.internal_position {
+        dst.put_u8(b'p');
+        put_cstring(dst, &internal_position.to_string());
+    }
+    
+    // Context and failing object fields, used by clients to map errors
+    let object_fields = [
+        (b'q', &err.internal_query),
+        (b'W', &err.where_),
+        (b's', &err.schema),
+        (b't', &err.table),
+        (b'c', &err.column),
+        (b'd', &err.datatype),
+        (b'n', &err.constraint),
+        (b'F', &err.file),
+        (b'R', &err.routine),
+    ];
+    for (field_type, value) in object_fields {
+        if let Some(value) = value {
+            dst.put_u8(field_type);
+            put_cstring(dst, value);
+        }
+    }
+    
+    if let Some(line) = err.line {
+        dst.put_u8(b'L');
+        put_cstring(dst, &line.to_string());
+    }
+    
     // Null terminator
     dst.put_u8(0);
     
@@ -402,6 +467,31 @@ fn encode_parameter_description(oids: Vec<i32>, dst: &mut BytesMut) {
     update_message_length(dst, len_pos);
 }
 
+fn encode_copy_out_response(format: i8, column_formats: Vec<i16>, dst: &mut BytesMut) {
+    dst.put_u8(b'H');
+    let len_pos = dst.len();
+    dst.put_i32(0); // Placeholder
+    
+    dst.put_i8(format);
+    dst.put_i16(column_formats.len() as i16);
+    for column_format in column_formats {
+        dst.put_i16(column_format);
+    }
+    
+    update_message_length(dst, len_pos);
+}
+
+fn encode_copy_data(data: &[u8], dst: &mut BytesMut) {
+    dst.put_u8(b'd');
+    dst.put_i32(4 + data.len() as i32);
+    dst.put_slice(data);
+}
+
+fn encode_copy_done(dst: &mut BytesMut) {
+    dst.put_u8(b'c');
+    dst.put_i32(4); // Fixed length
+}
+
 // Helper functions
 fn read_cstring(buf: &mut &[u8]) -> io::Result<String> {
     let null_pos = buf.iter().position(|&b| b == 0)
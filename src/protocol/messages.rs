@@ -58,6 +58,9 @@ pub enum BackendMessage {
     PortalSuspended,
     NoData,
     ParameterDescription(Vec<i32>),
+    CopyOutResponse { format: i8, column_formats: Vec<i16> },
+    CopyData(Vec<u8>),
+    CopyDone,
 }
 
 #[derive(Debug, Clone)]
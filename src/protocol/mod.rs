@@ -7,11 +7,12 @@ pub mod value_handler;
 pub mod buffer_pool;
 pub mod memory_monitor;
 pub mod small_value;
+pub mod startup;
 
 
 pub use messages::*;
 pub use codec::PostgresCodec;
-pub use binary::{BinaryEncoder, ZeroCopyBinaryEncoder};
+pub use binary::{BinaryEncoder, BinaryDecoder, ZeroCopyBinaryEncoder};
 pub use memory_mapped::{MappedValue, MappedValueReader, MappedValueFactory, MemoryMappedConfig};
 pub use value_handler::{ValueHandler, ValueHandlerConfig, ValueHandlerStats};
 pub use buffer_pool::{BufferPool, BufferPoolConfig, BufferPoolStats, PooledBytesMut, global_buffer_pool, get_pooled_buffer};
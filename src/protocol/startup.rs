@@ -0,0 +1,66 @@
+use super::{AuthenticationMessage, BackendMessage, PostgresCodec, TransactionStatus};
+use bytes::{Bytes, BytesMut};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use tokio_util::codec::Encoder;
+
+/// ParameterStatus values drivers read during startup; the fast path leaves out the rest
+pub const FAST_STARTUP_PARAMETERS: &[&str] = &[
+    "server_version",
+    "server_encoding",
+    "client_encoding",
+    "DateStyle",
+    "TimeZone",
+    "integer_datetimes",
+    "default_transaction_read_only",
+    "in_hot_standby",
+];
+
+/// AuthenticationOk and the startup ParameterStatus messages, identical for every session
+static FAST_STARTUP_PREFIX: OnceLock<Bytes> = OnceLock::new();
+
+/// Append the whole startup handshake for a trusted local client to `dst`
+///
+/// The AuthenticationOk/ParameterStatus prefix is serialized once and reused;
+/// only BackendKeyData carries per-session data, with a zero secret key.
+pub fn encode_fast_startup(parameters: &HashMap<String, String>, process_id: i32, dst: &mut BytesMut) {
+    let prefix = FAST_STARTUP_PREFIX.get_or_init(|| {
+        let mut buf = BytesMut::new();
+        encode(BackendMessage::Authentication(AuthenticationMessage::Ok), &mut buf);
+        for name in FAST_STARTUP_PARAMETERS {
+            if let Some(value) = parameters.get(*name) {
+                encode(BackendMessage::ParameterStatus { name: name.to_string(), value: value.clone() }, &mut buf);
+            }
+        }
+        buf.freeze()
+    });
+    dst.extend_from_slice(prefix);
+    encode(BackendMessage::BackendKeyData { process_id, secret_key: 0 }, dst);
+    encode(BackendMessage::ReadyForQuery { status: TransactionStatus::Idle }, dst);
+}
+
+fn encode(message: BackendMessage, dst: &mut BytesMut) {
+    PostgresCodec::new().encode(message, dst).expect("startup messages always encode");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fast_startup_matches_codec() {
+        let mut parameters = HashMap::new();
+        parameters.insert("server_version".to_string(), "14.0".to_string());
+        parameters.insert("IntervalStyle".to_string(), "postgres".to_string());
+
+        let mut fast = BytesMut::new();
+        encode_fast_startup(&parameters, 42, &mut fast);
+
+        let mut expected = BytesMut::new();
+        encode(BackendMessage::Authentication(AuthenticationMessage::Ok), &mut expected);
+        encode(BackendMessage::ParameterStatus { name: "server_version".to_string(), value: "14.0".to_string() }, &mut expected);
+        encode(BackendMessage::BackendKeyData { process_id: 42, secret_key: 0 }, &mut expected);
+        encode(BackendMessage::ReadyForQuery { status: TransactionStatus::Idle }, &mut expected);
+        assert_eq!(fast, expected);
+    }
+}
@@ -4,7 +4,6 @@ use crate::protocol::{MappedValue, MappedValueFactory, MemoryMappedConfig};
 use crate::types::PgType;
 // use crate::types::value_converter::ValueConverter; // Reserved for future enhanced type conversion
 use tracing::debug;
-use serde_json;
 
 /// Configuration for value handling strategies
 #[derive(Debug, Clone)]
@@ -142,13 +141,15 @@ impl ValueHandler {
         }
 
         // Check if this is an array type and needs JSON to array conversion
-        let pg_data = if pg_type.is_some() && pg_type.unwrap().is_array() {
-            // Convert JSON array to PostgreSQL array format for text protocol
+        let pg_data = if let Some(array_type) = pg_type.filter(|t| t.is_array()) {
             if !binary_format {
+                // Convert JSON array to PostgreSQL array format for text protocol
                 self.convert_json_to_pg_array(text_data)?
             } else {
-                // Binary format will be handled in a future update
-                text_data.as_bytes().to_vec()
+                let elem_oid = array_type.element_type().map_or(PgType::Text.to_oid(), |t| t.to_oid());
+                // Keep the original data if it isn't a stored JSON array
+                crate::protocol::BinaryEncoder::encode_array(text_data, elem_oid)
+                    .unwrap_or_else(|_| text_data.as_bytes().to_vec())
             }
         } else {
             text_data.as_bytes().to_vec()
@@ -261,52 +262,13 @@ impl ValueHandler {
     
     /// Convert JSON array to PostgreSQL text array format
     fn convert_json_to_pg_array(&self, json_str: &str) -> io::Result<Vec<u8>> {
-        // Try to parse as JSON array
-        match serde_json::from_str::<serde_json::Value>(json_str) {
-            Ok(json_val) => {
-                if let serde_json::Value::Array(arr) = json_val {
-                    // Convert to PostgreSQL array literal format
-                    let pg_array = self.json_array_to_pg_text(&arr);
-                    Ok(pg_array.into_bytes())
-                } else {
-                    // Not an array, return as-is
-                    Ok(json_str.as_bytes().to_vec())
-                }
-            }
-            Err(_) => {
-                // Not valid JSON, return as-is
-                Ok(json_str.as_bytes().to_vec())
-            }
+        // Values that aren't JSON arrays are returned as-is
+        match crate::types::ArrayHandler::json_to_array_literal(json_str) {
+            Some(pg_array) => Ok(pg_array.into_bytes()),
+            None => Ok(json_str.as_bytes().to_vec()),
         }
     }
     
-    /// Convert JSON array elements to PostgreSQL text array format
-    fn json_array_to_pg_text(&self, arr: &[serde_json::Value]) -> String {
-        let elements: Vec<String> = arr.iter().map(|elem| {
-            match elem {
-                serde_json::Value::Null => "NULL".to_string(),
-                serde_json::Value::Bool(b) => b.to_string(),
-                serde_json::Value::Number(n) => n.to_string(),
-                serde_json::Value::String(s) => {
-                    // Escape quotes and backslashes
-                    let escaped = s.replace('\\', "\\\\").replace('"', "\\\"");
-                    format!("\"{escaped}\"")
-                }
-                serde_json::Value::Array(_) => {
-                    // Nested arrays - convert recursively
-                    // For now, just stringify
-                    elem.to_string()
-                }
-                serde_json::Value::Object(_) => {
-                    // Objects - stringify
-                    elem.to_string()
-                }
-            }
-        }).collect();
-        
-        format!("{{{}}}", elements.join(","))
-    }
-    
     /// Convert a row of SQLite values to mapped values
     pub fn convert_row(
         &self,
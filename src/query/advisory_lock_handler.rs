@@ -0,0 +1,109 @@
+use crate::session::advisory_locks::{self, LockKey, LockScope, LockWaitError};
+use crate::session::SessionState;
+use crate::PgSqliteError;
+use crate::query::trigger_handler::pg_error;
+use tokio_util::codec::Framed;
+use regex::Regex;
+use once_cell::sync::Lazy;
+use std::sync::Arc;
+use tracing::debug;
+
+static LOCK_CALL_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^\s*SELECT\s+(?:pg_catalog\.)?pg_advisory(_xact)?_lock\s*\(\s*([^,()]+?)\s*(?:,\s*([^,()]+?)\s*)?\)\s*(?:AS\s+(\w+|"[^"]+"))?\s*;?\s*$"#).unwrap()
+});
+
+/// Handles standalone `SELECT pg_advisory_lock(...)` and `pg_advisory_xact_lock(...)`.
+///
+/// Like SleepHandler, the wait for a lock held by another session happens on the
+/// session's task, so it doesn't hold on to a runtime worker thread the holder may
+/// need to finish its transaction. Calls inside larger statements wait in SQLite, see
+/// advisory_lock_functions.
+pub struct AdvisoryLockHandler;
+
+/// A parsed standalone advisory lock call
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdvisoryLockCall {
+    pub key: LockKey,
+    pub scope: LockScope,
+    pub column_name: String,
+}
+
+impl AdvisoryLockHandler {
+    /// Parse `SELECT pg_advisory_lock(key)` or `SELECT pg_advisory_xact_lock(key1, key2)`
+    pub fn parse_lock_call(query: &str) -> Option<AdvisoryLockCall> {
+        // Every advisory lock function starts with pg_advisory
+        if !query.as_bytes().windows(11).any(|w| w.eq_ignore_ascii_case(b"pg_advisory")) {
+            return None;
+        }
+
+        let caps = LOCK_CALL_PATTERN.captures(query)?;
+        let key = match caps.get(3) {
+            None => LockKey::Single(Self::strip_literal(&caps[2]).parse().ok()?),
+            Some(key2) => LockKey::Pair(
+                Self::strip_literal(&caps[2]).parse().ok()?,
+                Self::strip_literal(key2.as_str()).parse().ok()?,
+            ),
+        };
+        let (scope, function) = match caps.get(1) {
+            Some(_) => (LockScope::Transaction, "pg_advisory_xact_lock"),
+            None => (LockScope::Session, "pg_advisory_lock"),
+        };
+        let column_name = caps.get(4)
+            .map(|m| m.as_str().trim_matches('"').to_string())
+            .unwrap_or_else(|| function.to_string());
+
+        Some(AdvisoryLockCall { key, scope, column_name })
+    }
+
+    /// Wait for the lock without blocking the runtime, then return the single void-valued row
+    ///
+    /// pg_cancel_backend() and statement_timeout end the wait.
+    pub async fn handle_lock<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        session: &Arc<SessionState>,
+        call: &AdvisoryLockCall,
+        skip_row_description: bool,
+    ) -> Result<(), PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        debug!("Waiting for advisory lock {:?}", call.key);
+        let pid = session.backend_pid;
+        advisory_locks::lock(session.id, call.key, call.scope, || crate::session::backend_registry::should_abort(pid)).await
+            .map_err(|error| match error {
+                LockWaitError::Canceled => pg_error("57014", "canceling statement due to user request".to_string()),
+                LockWaitError::Deadlock => pg_error("40P01", "deadlock detected".to_string()),
+            })?;
+
+        let field = crate::query::SleepHandler::void_field(&call.column_name);
+        crate::query::SleepHandler::send_void_row(framed, field, skip_row_description).await
+    }
+
+    /// A key argument without quotes and cast, as bound parameters are substituted
+    fn strip_literal(argument: &str) -> String {
+        let without_cast = match argument.find("::") {
+            Some(pos) => &argument[..pos],
+            None => argument,
+        };
+        without_cast.trim().trim_matches('\'').to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lock_call() {
+        let call = AdvisoryLockHandler::parse_lock_call("SELECT pg_advisory_lock(42)").unwrap();
+        assert_eq!(call, AdvisoryLockCall { key: LockKey::Single(42), scope: LockScope::Session, column_name: "pg_advisory_lock".to_string() });
+
+        let call = AdvisoryLockHandler::parse_lock_call("select pg_catalog.pg_advisory_xact_lock('7'::int4, -3) AS locked;").unwrap();
+        assert_eq!(call, AdvisoryLockCall { key: LockKey::Pair(7, -3), scope: LockScope::Transaction, column_name: "locked".to_string() });
+
+        // Keys out of range, try-locks and calls inside larger statements are left to the functions
+        assert!(AdvisoryLockHandler::parse_lock_call("SELECT pg_advisory_lock(4294967296, 1)").is_none());
+        assert!(AdvisoryLockHandler::parse_lock_call("SELECT pg_try_advisory_lock(42)").is_none());
+        assert!(AdvisoryLockHandler::parse_lock_call("SELECT pg_advisory_lock(id) FROM jobs").is_none());
+    }
+}
@@ -0,0 +1,1596 @@
+use crate::ddl::{CompositeDdlHandler, ForeignKey, ForeignKeys};
+use crate::error::PgError;
+use crate::metadata::{EnumTriggers, IdentityColumn, IdentityColumns};
+use crate::metadata::OidAllocator;
+use crate::protocol::BackendMessage;
+use crate::protocol::messages::NoticeResponse;
+use crate::query::TranslationPipeline;
+use crate::query::sql_utils::{quote_identifier, unqualified, unquote_identifier};
+use crate::session::{SessionState, SessionStorage};
+use crate::translator::{CreateTableResult, CreateTableTranslator};
+use crate::validator::{BitConstraint, ExclusionConstraint, StringConstraint, StringConstraintValidator};
+use crate::PgSqliteError;
+use futures::SinkExt;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rusqlite::{Connection, OptionalExtension};
+use std::sync::Arc;
+use tokio_util::codec::Framed;
+use tracing::debug;
+
+static ALTER_TABLE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^\s*ALTER\s+TABLE\s+(IF\s+EXISTS\s+)?(?:ONLY\s+)?((?:"(?:[^"]|"")+"|[\w$]+)(?:\.(?:"(?:[^"]|"")+"|[\w$]+))?)\s*\*?\s+(.+?)\s*;?\s*$"#).unwrap()
+});
+
+static ADD_COLUMN_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)^ADD\s+(COLUMN\s+)?(IF\s+NOT\s+EXISTS\s+)?(.+)$").unwrap()
+});
+
+static DROP_COLUMN_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^DROP\s+(COLUMN\s+)?(IF\s+EXISTS\s+)?("(?:[^"]|"")+"|[\w$]+)(?:\s+(?:CASCADE|RESTRICT))?$"#).unwrap()
+});
+
+static RENAME_TABLE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^RENAME\s+TO\s+((?:"(?:[^"]|"")+"|[\w$]+)(?:\.(?:"(?:[^"]|"")+"|[\w$]+))?)$"#).unwrap()
+});
+
+static RENAME_COLUMN_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^RENAME\s+(COLUMN\s+)?("(?:[^"]|"")+"|[\w$]+)\s+TO\s+("(?:[^"]|"")+"|[\w$]+)$"#).unwrap()
+});
+
+static ALTER_COLUMN_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^ALTER\s+(COLUMN\s+)?("(?:[^"]|"")+"|[\w$]+)\s+(.+)$"#).unwrap()
+});
+
+static COLUMN_TYPE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^(?:SET\s+DATA\s+)?TYPE\s+(.+?)(?:\s+COLLATE\s+(?:"[^"]+"|\S+))?(?:\s+USING\s+(.+))?$"#).unwrap()
+});
+
+static ADD_FOREIGN_KEY_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^ADD\s+(?:CONSTRAINT\s+("(?:[^"]|"")+"|[\w$]+)\s+)?FOREIGN\s+KEY\s*\(([^)]*)\)\s*REFERENCES\s+((?:"(?:[^"]|"")+"|[\w$]+)(?:\.(?:"(?:[^"]|"")+"|[\w$]+))?)\s*(?:\(([^)]*)\))?(.*?)(\s+NOT\s+VALID)?$"#).unwrap()
+});
+
+static DROP_CONSTRAINT_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^DROP\s+CONSTRAINT\s+(IF\s+EXISTS\s+)?("(?:[^"]|"")+"|[\w$]+)(?:\s+(?:CASCADE|RESTRICT))?$"#).unwrap()
+});
+
+static SET_DEFAULT_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)^SET\s+DEFAULT\s+(.+)$").unwrap()
+});
+
+static NULLABILITY_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)^(SET|DROP)\s+(NOT\s+NULL|DEFAULT)$").unwrap()
+});
+
+/// Literals SQLite accepts as a DEFAULT without parentheses, which ADD COLUMN requires
+static LITERAL_DEFAULT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)^(?:[-+]?\d+(?:\.\d*)?(?:e[-+]?\d+)?|'(?:[^']|'')*'|NULL|TRUE|FALSE)$").unwrap()
+});
+
+/// Placeholder table name for translating a single column definition
+const SCRATCH_TABLE: &str = "__pgsqlite_alter";
+
+/// Tables keyed by (table_name, column_name) that describe a column's PostgreSQL type
+const TYPE_METADATA: &[&str] = &[
+    "__pgsqlite_schema",
+    "__pgsqlite_string_constraints",
+    "__pgsqlite_numeric_constraints",
+    "__pgsqlite_enum_usage",
+    "__pgsqlite_array_types",
+];
+
+/// Every table keyed by (table_name, column_name)
+const COLUMN_METADATA: &[&str] = &[
+    "__pgsqlite_schema",
+    "__pgsqlite_string_constraints",
+    "__pgsqlite_numeric_constraints",
+    "__pgsqlite_enum_usage",
+    "__pgsqlite_array_types",
+    "__pgsqlite_identity_columns",
+    "__pgsqlite_fts_metadata",
+    "__pgsqlite_retention_policies",
+    "__pgsqlite_column_masks",
+    "__pgsqlite_exclusion_constraints",
+];
+
+/// Keywords that start a column constraint clause
+const CLAUSE_KEYWORDS: &[&str] = &[
+    "CONSTRAINT", "PRIMARY", "NOT", "NULL", "UNIQUE", "CHECK", "DEFAULT", "COLLATE", "REFERENCES", "GENERATED", "AS",
+];
+
+/// Keywords that start a table constraint rather than a column definition
+const TABLE_CONSTRAINT_KEYWORDS: &[&str] = &["CONSTRAINT", "PRIMARY", "UNIQUE", "CHECK", "FOREIGN", "EXCLUDE"];
+
+/// Handles `ALTER TABLE` column changes the way PostgreSQL migrations use them.
+///
+/// ADD, DROP and RENAME COLUMN and RENAME TO run natively where SQLite allows it;
+/// type, default and nullability changes, foreign keys added or dropped, and the
+/// additions and drops SQLite refuses, rebuild the table following SQLite's
+/// documented procedure. pgsqlite's per-column metadata, validation triggers and
+/// catalog rows follow every change.
+pub struct AlterTableHandler;
+
+/// A parsed ALTER TABLE statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlterTableStatement {
+    pub table: String,
+    pub if_exists: bool,
+    pub actions: Vec<AlterTableAction>,
+}
+
+/// One comma-separated action of an ALTER TABLE statement
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlterTableAction {
+    AddColumn {
+        column: String,
+        /// The full column definition as written
+        definition: String,
+        if_not_exists: bool,
+    },
+    DropColumn { column: String, if_exists: bool },
+    RenameColumn { column: String, new_name: String },
+    RenameTable { new_name: String },
+    AlterColumnType {
+        column: String,
+        data_type: String,
+        using: Option<String>,
+    },
+    SetDefault { column: String, default: String },
+    DropDefault { column: String },
+    SetNotNull { column: String },
+    DropNotNull { column: String },
+    AddForeignKey {
+        /// The CONSTRAINT name, if one was given
+        name: Option<String>,
+        columns: Vec<String>,
+        parent: String,
+        /// Empty to reference the parent's primary key
+        parent_columns: Vec<String>,
+        /// ON DELETE, ON UPDATE, MATCH and DEFERRABLE clauses as written
+        options: String,
+        /// False for NOT VALID, which leaves existing rows unchecked
+        validate: bool,
+    },
+    DropConstraint { name: String, if_exists: bool },
+}
+
+/// What an ALTER TABLE changed, for the async caller to act on
+#[derive(Debug, Default)]
+struct AlterOutcome {
+    /// Every name the table had, whose cached schema is now stale
+    tables: Vec<String>,
+    notices: Vec<String>,
+}
+
+impl AlterTableHandler {
+    /// Whether a query starts with ALTER; ALTER TABLE and ALTER INDEX both come here
+    pub fn might_be_alter_table(query: &str) -> bool {
+        query.trim_start().get(..5).is_some_and(|prefix| prefix.eq_ignore_ascii_case("ALTER"))
+    }
+
+    /// Parse an ALTER TABLE statement whose actions are all column, rename or foreign key actions.
+    ///
+    /// Returns None for anything else, including other constraint actions, which
+    /// keep going through the generic DDL path.
+    pub fn parse_alter_table(query: &str) -> Option<AlterTableStatement> {
+        if !Self::might_be_alter_table(query) {
+            return None;
+        }
+        let caps = ALTER_TABLE_PATTERN.captures(query)?;
+        let actions = split_top_level(&caps[3], ",").into_iter()
+            .map(parse_action)
+            .collect::<Option<Vec<_>>>()?;
+        Some(AlterTableStatement {
+            table: unqualified(&caps[2]),
+            if_exists: caps.get(1).is_some(),
+            actions,
+        })
+    }
+
+    pub async fn handle_alter_table<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        statement: &AlterTableStatement,
+    ) -> Result<(), PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        db.require_local_data("ALTER TABLE")?;
+        let statement = Self::translate_expressions(db, session, statement).await?;
+        let outcome = db.with_session_connection(&session.id, |conn| {
+            Ok(Self::alter_table(conn, &statement))
+        }).await??;
+
+        for table in &outcome.tables {
+            forget_table(db.as_ref(), table);
+        }
+        for message in outcome.notices {
+            framed.send(BackendMessage::NoticeResponse(NoticeResponse {
+                severity: "NOTICE".to_string(),
+                code: "00000".to_string(),
+                message,
+                detail: None,
+                hint: None,
+                position: None,
+                where_: None,
+            })).await.map_err(PgSqliteError::Io)?;
+        }
+        if let Some(table) = outcome.tables.last() {
+            crate::ddl::ForeignKeyIndexer::index_foreign_keys(framed, db, session, &format!("ALTER TABLE {}", quote_identifier(table))).await?;
+        }
+        db.with_session_connection(&session.id, OidAllocator::sync_relations).await?;
+
+        framed.send(BackendMessage::CommandComplete { tag: "ALTER TABLE".to_string() }).await
+            .map_err(PgSqliteError::Io)
+    }
+
+    /// Run default and USING expressions through the SELECT translators, so casts
+    /// and PostgreSQL functions in them become SQLite expressions
+    async fn translate_expressions(
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        statement: &AlterTableStatement,
+    ) -> Result<AlterTableStatement, PgSqliteError> {
+        let mut translated = statement.clone();
+        for action in &mut translated.actions {
+            match action {
+                AlterTableAction::AddColumn { definition, .. } => {
+                    if let Some(mut column) = ColumnDefinition::parse(definition)
+                        && let Some(default) = column.default() {
+                        let expression = translate_expression(db, session, &statement.table, &default).await?;
+                        column.set_default(Some(&sqlite_default(&expression)));
+                        *definition = column.to_sql();
+                    }
+                }
+                AlterTableAction::AlterColumnType { using: Some(using), .. } => {
+                    *using = translate_expression(db, session, &statement.table, using).await?;
+                }
+                AlterTableAction::SetDefault { default, .. } => {
+                    *default = sqlite_default(&translate_expression(db, session, &statement.table, default).await?);
+                }
+                _ => {}
+            }
+        }
+        Ok(translated)
+    }
+
+    /// Apply every action inside a savepoint, so a failing action leaves the table as it was
+    fn alter_table(conn: &Connection, statement: &AlterTableStatement) -> Result<AlterOutcome, PgSqliteError> {
+        rebuild_safely(conn, || Self::apply_actions(conn, statement))
+    }
+
+    fn apply_actions(conn: &Connection, statement: &AlterTableStatement) -> Result<AlterOutcome, PgSqliteError> {
+        let mut outcome = AlterOutcome::default();
+        let Some(mut table) = table_name(conn, &statement.table)? else {
+            if statement.if_exists {
+                outcome.notices.push(format!("relation \"{}\" does not exist, skipping", statement.table));
+                return Ok(outcome);
+            }
+            return Err(pg_error("42P01", format!("relation \"{}\" does not exist", statement.table)));
+        };
+        outcome.tables.push(table.clone());
+
+        // Validation triggers name the table and column, so they are recreated from the metadata afterwards
+        drop_validation_triggers(conn, &table)?;
+
+        for action in &statement.actions {
+            match action {
+                AlterTableAction::AddColumn { column, definition, if_not_exists } => {
+                    if column_name(conn, &table, column)?.is_some() {
+                        if *if_not_exists {
+                            outcome.notices.push(format!("column \"{column}\" of relation \"{table}\" already exists, skipping"));
+                            continue;
+                        }
+                        return Err(duplicate_column(&table, column));
+                    }
+                    add_column(conn, &table, column, definition)?;
+                }
+                AlterTableAction::DropColumn { column, if_exists } => {
+                    match column_name(conn, &table, column)? {
+                        Some(column) => drop_column(conn, &table, &column)?,
+                        None if *if_exists => {
+                            outcome.notices.push(format!("column \"{column}\" of relation \"{table}\" does not exist, skipping"));
+                        }
+                        None => return Err(undefined_column(&table, column)),
+                    }
+                }
+                AlterTableAction::RenameColumn { column, new_name } => {
+                    let column = existing_column(conn, &table, column)?;
+                    if column_name(conn, &table, new_name)?.is_some_and(|existing| existing != column) {
+                        return Err(duplicate_column(&table, new_name));
+                    }
+                    conn.execute(&format!("ALTER TABLE {} RENAME COLUMN {} TO {}", quote_identifier(&table), quote_identifier(&column), quote_identifier(new_name)), [])?;
+                    for metadata in existing_tables(conn, COLUMN_METADATA)? {
+                        conn.execute(
+                            &format!("UPDATE {metadata} SET column_name = ?3 WHERE table_name = ?1 AND column_name = ?2 COLLATE NOCASE"),
+                            [&table, &column, new_name],
+                        )?;
+                    }
+                }
+                AlterTableAction::RenameTable { new_name } => {
+                    rename_table(conn, &table, new_name)?;
+                    table = new_name.clone();
+                    outcome.tables.push(table.clone());
+                }
+                AlterTableAction::AlterColumnType { column, data_type, using } => {
+                    let column = existing_column(conn, &table, column)?;
+                    alter_column_type(conn, &table, &column, data_type, using.as_deref())?;
+                }
+                AlterTableAction::SetDefault { column, default } => {
+                    let column = existing_column(conn, &table, column)?;
+                    rebuild_column(conn, &table, &column, |definition| definition.set_default(Some(default)))?;
+                }
+                AlterTableAction::DropDefault { column } => {
+                    let column = existing_column(conn, &table, column)?;
+                    rebuild_column(conn, &table, &column, |definition| definition.set_default(None))?;
+                }
+                AlterTableAction::SetNotNull { column } => {
+                    let column = existing_column(conn, &table, column)?;
+                    let has_nulls: bool = conn.query_row(
+                        &format!("SELECT EXISTS(SELECT 1 FROM {} WHERE {} IS NULL)", quote_identifier(&table), quote_identifier(&column)),
+                        [],
+                        |row| row.get(0),
+                    )?;
+                    if has_nulls {
+                        return Err(pg_error("23502", format!("column \"{column}\" of relation \"{table}\" contains null values")));
+                    }
+                    rebuild_column(conn, &table, &column, |definition| definition.set_not_null(true))?;
+                }
+                AlterTableAction::DropNotNull { column } => {
+                    let column = existing_column(conn, &table, column)?;
+                    rebuild_column(conn, &table, &column, |definition| definition.set_not_null(false))?;
+                }
+                AlterTableAction::AddForeignKey { name, columns, parent, parent_columns, options, validate } => {
+                    add_foreign_key(conn, &table, name.as_deref(), columns, parent, parent_columns, options, *validate)?;
+                }
+                AlterTableAction::DropConstraint { name, if_exists } => {
+                    let foreign_key = ForeignKeys::of_table(conn, &table)?.into_iter().find(|foreign_key| foreign_key.name.eq_ignore_ascii_case(name));
+                    match foreign_key {
+                        Some(foreign_key) => drop_foreign_key(conn, &table, &foreign_key)?,
+                        None if *if_exists => {
+                            outcome.notices.push(format!("constraint \"{name}\" of relation \"{table}\" does not exist, skipping"));
+                        }
+                        None if constraint_exists(conn, &table, name) => {
+                            return Err(pg_error("0A000", format!("cannot drop constraint \"{name}\" of relation \"{table}\": only foreign keys can be dropped")));
+                        }
+                        None => return Err(pg_error("42704", format!("constraint \"{name}\" of relation \"{table}\" does not exist"))),
+                    }
+                }
+            }
+        }
+
+        create_validation_triggers(conn, &table)?;
+        crate::catalog::constraint_populator::populate_constraints_for_table(conn, &table)
+            .map_err(|e| PgSqliteError::Protocol(format!("Failed to refresh constraints of {table}: {e}")))?;
+        debug!("Altered table {} ({} actions)", table, statement.actions.len());
+        Ok(outcome)
+    }
+}
+
+fn parse_action(action: &str) -> Option<AlterTableAction> {
+    let first_word = |text: &str| text.split_whitespace().next().unwrap_or("").to_uppercase();
+
+    if let Some(caps) = ADD_FOREIGN_KEY_PATTERN.captures(action) {
+        let names = |list: &str| list.split(',').map(unquote_identifier).filter(|name| !name.is_empty()).collect::<Vec<_>>();
+        return Some(AlterTableAction::AddForeignKey {
+            name: caps.get(1).map(|name| unquote_identifier(name.as_str())),
+            columns: names(&caps[2]),
+            parent: unqualified(&caps[3]),
+            parent_columns: caps.get(4).map(|list| names(list.as_str())).unwrap_or_default(),
+            options: caps[5].trim().to_string(),
+            validate: caps.get(6).is_none(),
+        });
+    }
+    if let Some(caps) = DROP_CONSTRAINT_PATTERN.captures(action) {
+        return Some(AlterTableAction::DropConstraint { name: unquote_identifier(&caps[2]), if_exists: caps.get(1).is_some() });
+    }
+    if let Some(caps) = ADD_COLUMN_PATTERN.captures(action) {
+        let definition = caps[3].trim().to_string();
+        if caps.get(1).is_none() && TABLE_CONSTRAINT_KEYWORDS.contains(&first_word(&definition).as_str()) {
+            return None;
+        }
+        let column = ColumnDefinition::parse(&definition)?.column_name();
+        return Some(AlterTableAction::AddColumn { column, definition, if_not_exists: caps.get(2).is_some() });
+    }
+    if let Some(caps) = DROP_COLUMN_PATTERN.captures(action) {
+        if caps.get(1).is_none() && caps[3].eq_ignore_ascii_case("CONSTRAINT") {
+            return None;
+        }
+        return Some(AlterTableAction::DropColumn { column: unquote_identifier(&caps[3]), if_exists: caps.get(2).is_some() });
+    }
+    if let Some(caps) = RENAME_TABLE_PATTERN.captures(action) {
+        return Some(AlterTableAction::RenameTable { new_name: unqualified(&caps[1]) });
+    }
+    if let Some(caps) = RENAME_COLUMN_PATTERN.captures(action) {
+        if caps.get(1).is_none() && caps[2].eq_ignore_ascii_case("CONSTRAINT") {
+            return None;
+        }
+        return Some(AlterTableAction::RenameColumn { column: unquote_identifier(&caps[2]), new_name: unquote_identifier(&caps[3]) });
+    }
+    let caps = ALTER_COLUMN_PATTERN.captures(action)?;
+    if caps.get(1).is_none() && caps[2].eq_ignore_ascii_case("CONSTRAINT") {
+        return None;
+    }
+    let column = unquote_identifier(&caps[2]);
+    let change = caps[3].trim();
+    if let Some(type_caps) = COLUMN_TYPE_PATTERN.captures(change) {
+        return Some(AlterTableAction::AlterColumnType {
+            column,
+            data_type: type_caps[1].trim().to_string(),
+            using: type_caps.get(2).map(|using| using.as_str().trim().to_string()),
+        });
+    }
+    if let Some(default_caps) = SET_DEFAULT_PATTERN.captures(change) {
+        return Some(AlterTableAction::SetDefault { column, default: default_caps[1].trim().to_string() });
+    }
+    let nullability = NULLABILITY_PATTERN.captures(change)?;
+    let set = nullability[1].eq_ignore_ascii_case("SET");
+    Some(match (set, nullability[2].to_uppercase().starts_with("NOT")) {
+        (true, true) => AlterTableAction::SetNotNull { column },
+        (false, true) => AlterTableAction::DropNotNull { column },
+        (false, false) => AlterTableAction::DropDefault { column },
+        (true, false) => return None,
+    })
+}
+
+/// Translate a PostgreSQL expression as the only item of a SELECT list over the table
+async fn translate_expression(
+    db: &Arc<dyn SessionStorage>,
+    session: &Arc<SessionState>,
+    table: &str,
+    expression: &str,
+) -> Result<String, PgSqliteError> {
+    let from = format!(" FROM {}", quote_identifier(table));
+    let translated = TranslationPipeline::translate(db, session, &format!("SELECT {expression}{from}")).await?;
+    let sql = translated.sql.trim();
+    let select_list = sql.get(..7)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("SELECT "))
+        .and_then(|_| sql.rfind(&from).map(|end| sql[7..end].trim()));
+    Ok(select_list.unwrap_or(expression).to_string())
+}
+
+/// A DEFAULT clause value: literals as they are, anything else parenthesized
+fn sqlite_default(expression: &str) -> String {
+    let expression = expression.trim();
+    if LITERAL_DEFAULT.is_match(expression) || (expression.starts_with('(') && tokens(expression).len() == 1) {
+        expression.to_string()
+    } else {
+        format!("({expression})")
+    }
+}
+
+/// Drop every cached view of a table's columns
+pub(crate) fn forget_table(db: &dyn SessionStorage, table: &str) {
+    db.get_schema_cache().invalidate(table);
+    db.get_string_validator().invalidate_table(table);
+    crate::query::executor::forget_table_schema_info(table);
+    crate::validator::NumericValidator::invalidate_cache(table);
+    crate::types::numeric_utils::invalidate_numeric_cache(table);
+    crate::query::fast_path::clear_decimal_cache();
+    crate::session::GLOBAL_QUERY_CACHE.invalidate_table(table);
+    // The pool keeps result columns by query text, not by table
+    db.get_caches().statements.clear();
+}
+
+/// ADD COLUMN, natively when SQLite allows the definition and by rebuilding the table otherwise
+fn add_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<(), PgSqliteError> {
+    // The default was already translated; the column translator only sees the type and constraints
+    let mut pg_definition = ColumnDefinition::parse(definition)
+        .ok_or_else(|| pg_error("42601", format!("invalid column definition \"{definition}\"")))?;
+    let default = pg_definition.default();
+    pg_definition.set_default(None);
+
+    let result = translate_column(conn, &pg_definition.to_sql())?;
+    let mut sqlite_definition = ColumnDefinition::parse(result.sql.trim())
+        .ok_or_else(|| PgSqliteError::Protocol(format!("Failed to translate column definition \"{definition}\"")))?;
+    sqlite_definition.set_default(default.as_deref());
+
+    if sqlite_definition.is_not_null() && default.is_none() && has_rows(conn, table)? {
+        return Err(pg_error("23502", format!("column \"{column}\" of relation \"{table}\" contains null values")));
+    }
+
+    let sql = sqlite_definition.to_sql();
+    match conn.execute(&format!("ALTER TABLE {} ADD COLUMN {sql}", quote_identifier(table)), []) {
+        Ok(_) => {}
+        // Key columns, non-constant defaults and NOT NULL without a default need a new table
+        Err(rusqlite::Error::SqliteFailure(_, Some(message))) if message.starts_with("Cannot add") => {
+            debug!("Rebuilding {} to add column {}: {}", table, column, message);
+            let mut layout = TableLayout::load(conn, table)?;
+            let position = layout.elements.iter().rposition(|element| !is_table_constraint(element)).map_or(0, |i| i + 1);
+            layout.elements.insert(position, sql);
+            let copy = table_columns(conn, table)?.into_iter().map(|name| (name.clone(), quote_identifier(&name))).collect::<Vec<_>>();
+            rebuild_table(conn, table, &layout, &copy, None)?;
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    record_column_metadata(conn, table, column, &result)?;
+
+    // Existing rows get identity values in insertion order, as PostgreSQL fills them
+    if result.identity_columns.iter().any(|identity| !identity.is_rowid_alias) {
+        conn.execute(
+            &format!(
+                "UPDATE {table} SET {column} = (SELECT n FROM (SELECT rowid AS id, row_number() OVER (ORDER BY rowid) AS n FROM {table}) WHERE id = {table}.rowid)",
+                table = quote_identifier(table),
+                column = quote_identifier(column),
+            ),
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+/// DROP COLUMN along with the indexes and table constraints that use it, as PostgreSQL does
+fn drop_column(conn: &Connection, table: &str, column: &str) -> Result<(), PgSqliteError> {
+    // Views are not rewritten by a rebuild, so one reading the column must be dropped first
+    for (view, sql) in query_pairs(conn, "SELECT name, sql FROM sqlite_master WHERE type = 'view' AND ?1 IS NOT NULL", table)? {
+        if mentions(&sql, table) && mentions(&sql, column) {
+            return Err(pg_error("2BP01", format!(
+                "cannot drop column {column} of table {table} because other objects depend on it (view {view} depends on column {column} of table {table})"
+            )));
+        }
+    }
+
+    let attnum = conn.query_row(
+        "SELECT cid + 1 FROM pragma_table_xinfo(?1) WHERE name = ?2",
+        [table, column],
+        |row| row.get::<_, i64>(0),
+    )?;
+
+    let indexes: Vec<(String, String)> = query_pairs(
+        conn,
+        "SELECT name, sql FROM sqlite_master WHERE type = 'index' AND tbl_name = ?1 AND sql IS NOT NULL",
+        table,
+    )?;
+    for (index, sql) in indexes {
+        if sql.find('(').is_some_and(|open| mentions(&sql[open..], column)) {
+            conn.execute(&format!("DROP INDEX {}", quote_identifier(&index)), [])?;
+        }
+    }
+
+    match conn.execute(&format!("ALTER TABLE {} DROP COLUMN {}", quote_identifier(table), quote_identifier(column)), []) {
+        Ok(_) => {}
+        // Key and UNIQUE columns, and those named by a table constraint or index, need a new table
+        Err(rusqlite::Error::SqliteFailure(_, Some(message)))
+            if (message.starts_with("cannot drop") && !message.contains("no other columns")) || message.contains("after drop column") => {
+            debug!("Rebuilding {} to drop column {}: {}", table, column, message);
+            let mut layout = TableLayout::load(conn, table)?;
+            layout.elements.retain(|element| {
+                if is_table_constraint(element) {
+                    !mentions(element, column)
+                } else {
+                    !ColumnDefinition::parse(element).is_some_and(|definition| definition.column_name().eq_ignore_ascii_case(column))
+                }
+            });
+            let copy = table_columns(conn, table)?.into_iter()
+                .filter(|name| !name.eq_ignore_ascii_case(column))
+                .map(|name| (name.clone(), quote_identifier(&name)))
+                .collect::<Vec<_>>();
+            rebuild_table(conn, table, &layout, &copy, None)?;
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    for metadata in existing_tables(conn, COLUMN_METADATA)? {
+        conn.execute(&format!("DELETE FROM {metadata} WHERE table_name = ?1 AND column_name = ?2 COLLATE NOCASE"), [table, column])?;
+    }
+
+    // Column comments follow the attribute numbers of the remaining columns
+    if let Some(oid) = relation_oid(conn, table)? {
+        conn.execute("DELETE FROM pg_description WHERE objoid = ?1 AND classoid = 1259 AND objsubid = ?2", [oid, attnum])?;
+        conn.execute("UPDATE pg_description SET objsubid = objsubid - 1 WHERE objoid = ?1 AND classoid = 1259 AND objsubid > ?2", [oid, attnum])?;
+    }
+    Ok(())
+}
+
+/// RENAME TO, carrying the metadata, catalog rows and comments over to the new name
+fn rename_table(conn: &Connection, table: &str, new_name: &str) -> Result<(), PgSqliteError> {
+    if table_name(conn, new_name)?.is_some_and(|existing| existing != table) {
+        return Err(pg_error("42P07", format!("relation \"{new_name}\" already exists")));
+    }
+    let old_oid = relation_oid(conn, table)?;
+
+    conn.execute(&format!("ALTER TABLE {} RENAME TO {}", quote_identifier(table), quote_identifier(new_name)), [])?;
+    OidAllocator::rename_table(conn, table, new_name)?;
+
+    for metadata in existing_tables(conn, COLUMN_METADATA)? {
+        conn.execute(&format!("UPDATE {metadata} SET table_name = ?2 WHERE table_name = ?1"), [table, new_name])?;
+    }
+    if !existing_tables(conn, &["__pgsqlite_check_constraints"])?.is_empty() {
+        conn.execute("UPDATE __pgsqlite_check_constraints SET tablename = ?2 WHERE tablename = ?1", [table, new_name])?;
+    }
+    if !existing_tables(conn, &["pg_attrdef"])?.is_empty() {
+        conn.execute("DELETE FROM pg_attrdef WHERE adrelid = ?1", [OidAllocator::relation_oid(conn, new_name)?])?;
+    }
+    if !existing_tables(conn, &["__pgsqlite_triggers"])?.is_empty() {
+        crate::query::TriggerHandler::rename_table(conn, table, new_name)?;
+    }
+    crate::query::AuditHandler::rename_table(conn, table, new_name)?;
+    crate::query::SoftDeleteHandler::rename_table(conn, table, new_name)?;
+    crate::query::ClusterHandler::rename_table(conn, table, new_name)?;
+    crate::query::AutoAnalyze::rename_table(conn, table, new_name)?;
+    if let Some(old_oid) = old_oid
+        && let Some(new_oid) = relation_oid(conn, new_name)? {
+        conn.execute("UPDATE pg_description SET objoid = ?2 WHERE objoid = ?1 AND classoid = 1259", [old_oid, new_oid])?;
+    }
+    Ok(())
+}
+
+/// ALTER COLUMN TYPE: metadata only when the SQLite storage type stays the same, a rebuild otherwise
+fn alter_column_type(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    data_type: &str,
+    using: Option<&str>,
+) -> Result<(), PgSqliteError> {
+    let result = translate_column(conn, &format!("{} {data_type}", quote_identifier(column)))?;
+    let mapping = result.type_mappings.values().next()
+        .ok_or_else(|| PgSqliteError::Protocol(format!("Failed to translate type {data_type}")))?;
+    let (declared_type, old_pg_type): (String, Option<String>) = conn.query_row(
+        "SELECT type, (SELECT pg_type FROM __pgsqlite_schema WHERE table_name = ?1 AND column_name = ?2 COLLATE NOCASE)
+         FROM pragma_table_xinfo(?1) WHERE name = ?2",
+        [table, column],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).or_else(|_| conn.query_row(
+        "SELECT type, NULL FROM pragma_table_xinfo(?1) WHERE name = ?2",
+        [table, column],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ))?;
+    let old_type = old_pg_type.unwrap_or_else(|| declared_type.clone());
+    let same_storage = declared_type.eq_ignore_ascii_case(&mapping.sqlite_type);
+
+    let value = match using {
+        Some(expression) => format!("({expression})"),
+        None if same_storage => quote_identifier(column),
+        None => match (type_category(&old_type), type_category(&mapping.pg_type)) {
+            (TypeCategory::Integer | TypeCategory::Number, TypeCategory::Integer) => {
+                format!("CAST(round({}) AS INTEGER)", quote_identifier(column))
+            }
+            (TypeCategory::Integer | TypeCategory::Number, TypeCategory::Number)
+            | (TypeCategory::Integer | TypeCategory::Number | TypeCategory::Text, TypeCategory::Text) => {
+                format!("CAST({} AS {})", quote_identifier(column), mapping.sqlite_type)
+            }
+            _ => {
+                return Err(pg_error("42804", format!(
+                    "column \"{column}\" cannot be cast automatically to type {}",
+                    mapping.pg_type.to_lowercase()
+                )));
+            }
+        },
+    };
+
+    check_converted_values(conn, table, &value, mapping.type_modifier, &mapping.pg_type, &result)?;
+
+    if !same_storage || using.is_some() {
+        let sqlite_type = mapping.sqlite_type.clone();
+        let copy_value = value.clone();
+        let mut copy = Vec::new();
+        for name in table_columns(conn, table)? {
+            let source = if name.eq_ignore_ascii_case(column) { copy_value.clone() } else { quote_identifier(&name) };
+            copy.push((name, source));
+        }
+        let mut layout = TableLayout::load(conn, table)?;
+        layout.update_column(column, |definition| definition.data_type = sqlite_type)?;
+        rebuild_table(conn, table, &layout, &copy, None)?;
+    }
+
+    for metadata in existing_tables(conn, TYPE_METADATA)? {
+        conn.execute(&format!("DELETE FROM {metadata} WHERE table_name = ?1 AND column_name = ?2 COLLATE NOCASE"), [table, column])?;
+    }
+    record_column_metadata(conn, table, column, &result)
+}
+
+/// Reject a type change whose converted values the new type's triggers would refuse
+fn check_converted_values(
+    conn: &Connection,
+    table: &str,
+    value: &str,
+    type_modifier: Option<i32>,
+    pg_type: &str,
+    result: &CreateTableResult,
+) -> Result<(), PgSqliteError> {
+    let converted = format!("SELECT {value} AS v FROM {}", quote_identifier(table));
+    let base_type = base_type(pg_type);
+    if let Some(max_length) = type_modifier.filter(|_| is_string_type(&base_type)) {
+        let too_long: bool = conn.query_row(
+            &format!("SELECT EXISTS(SELECT 1 FROM ({converted}) WHERE length(v) > {max_length})"),
+            [],
+            |row| row.get(0),
+        )?;
+        if too_long {
+            let type_name = if is_char_type(&base_type) { "character" } else { "character varying" };
+            return Err(pg_error("22001", format!("value too long for type {type_name}({max_length})")));
+        }
+    }
+    for (_, enum_type) in &result.enum_columns {
+        let invalid: Option<String> = conn.query_row(
+            &format!(
+                "SELECT v FROM ({converted}) WHERE v IS NOT NULL AND v NOT IN (
+                    SELECT ev.label FROM __pgsqlite_enum_values ev
+                    JOIN __pgsqlite_enum_types et ON ev.type_oid = et.type_oid
+                    WHERE et.type_name = ?1
+                ) LIMIT 1"
+            ),
+            [enum_type],
+            |row| row.get(0),
+        ).optional()?;
+        if let Some(invalid) = invalid {
+            return Err(pg_error("22P02", format!("invalid input value for enum {enum_type}: \"{invalid}\"")));
+        }
+    }
+    Ok(())
+}
+
+/// Rebuild the table with one column definition changed
+fn rebuild_column(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    change: impl FnOnce(&mut ColumnDefinition),
+) -> Result<(), PgSqliteError> {
+    let mut layout = TableLayout::load(conn, table)?;
+    layout.update_column(column, change)?;
+    let copy = table_columns(conn, table)?.into_iter().map(|name| (name.clone(), quote_identifier(&name))).collect::<Vec<_>>();
+    rebuild_table(conn, table, &layout, &copy, None)
+}
+
+/// Recreate a table with a new definition, following SQLite's procedure for
+/// schema changes ALTER TABLE cannot make: create the new table, copy the rows,
+/// drop the old table, rename the new one into place, and restore the indexes
+/// and triggers. `copy` pairs each target column with the expression filling it,
+/// and `order_by` is the order the rows are copied in.
+fn rebuild_table(
+    conn: &Connection,
+    table: &str,
+    layout: &TableLayout,
+    copy: &[(String, String)],
+    order_by: Option<&str>,
+) -> Result<(), PgSqliteError> {
+    let indexes = query_column(conn, "SELECT sql FROM sqlite_master WHERE type = 'index' AND tbl_name = ?1 AND sql IS NOT NULL", table)?;
+    let triggers = query_column(conn, "SELECT sql FROM sqlite_master WHERE type = 'trigger' AND tbl_name = ?1", table)?;
+    let sequence: Option<i64> = if existing_tables(conn, &["sqlite_sequence"])?.is_empty() {
+        None
+    } else {
+        conn.query_row("SELECT seq FROM sqlite_sequence WHERE name = ?1", [table], |row| row.get(0)).optional()?
+    };
+
+    let new_table = format!("__pgsqlite_rebuild_{table}");
+    conn.execute(&format!("CREATE TABLE {} ({}){}", quote_identifier(&new_table), layout.elements.join(", "), layout.suffix), [])?;
+    let targets: Vec<String> = copy.iter().map(|(column, _)| quote_identifier(column)).collect();
+    let sources: Vec<&str> = copy.iter().map(|(_, source)| source.as_str()).collect();
+    let order_by = order_by.map(|order_by| format!(" ORDER BY {order_by}")).unwrap_or_default();
+    conn.execute(
+        &format!("INSERT INTO {} ({}) SELECT {} FROM {}{order_by}", quote_identifier(&new_table), targets.join(", "), sources.join(", "), quote_identifier(table)),
+        [],
+    )?;
+    if ForeignKeys::enforced(conn)? {
+        refuse_referenced_rebuild(conn, table)?;
+    }
+    conn.execute(&format!("DROP TABLE {}", quote_identifier(table)), [])?;
+
+    // Legacy mode leaves views and triggers elsewhere alone; they already name the final table
+    conn.execute_batch("PRAGMA legacy_alter_table = ON")?;
+    let renamed = conn.execute(&format!("ALTER TABLE {} RENAME TO {}", quote_identifier(&new_table), quote_identifier(table)), []);
+    conn.execute_batch("PRAGMA legacy_alter_table = OFF")?;
+    renamed?;
+
+    for sql in indexes.iter().chain(&triggers) {
+        conn.execute(sql, [])?;
+    }
+    if let Some(sequence) = sequence {
+        conn.execute("UPDATE sqlite_sequence SET seq = MAX(seq, ?2) WHERE name = ?1", rusqlite::params![table, sequence])?;
+    }
+    debug!("Rebuilt table {}", table);
+    Ok(())
+}
+
+/// Rewrite the table with its rows copied in the given order, for CLUSTER.
+///
+/// Rows of a table without an INTEGER PRIMARY KEY get new rowids in that order,
+/// so the table's pages follow the order; a table keyed by its INTEGER PRIMARY KEY
+/// or declared WITHOUT ROWID stays stored in key order and is only compacted.
+pub(crate) fn cluster_table(conn: &Connection, table: &str, order_by: &str) -> Result<(), PgSqliteError> {
+    rebuild_safely(conn, || {
+        let layout = TableLayout::load(conn, table)?;
+        let copy = table_columns(conn, table)?.into_iter().map(|name| (name.clone(), quote_identifier(&name))).collect::<Vec<_>>();
+        rebuild_table(conn, table, &layout, &copy, Some(order_by))
+    })
+}
+
+/// Run a change that may rebuild tables inside a savepoint, so a failure leaves
+/// them as they were.
+///
+/// Dropping the old table during a rebuild would run the ON DELETE actions of the
+/// foreign keys referencing it, so outside a transaction enforcement is paused
+/// until the change is done; SQLite ignores the pragma inside one.
+fn rebuild_safely<R>(conn: &Connection, change: impl FnOnce() -> Result<R, PgSqliteError>) -> Result<R, PgSqliteError> {
+    let pause_foreign_keys = conn.is_autocommit() && ForeignKeys::enforced(conn)?;
+    if pause_foreign_keys {
+        conn.execute_batch("PRAGMA foreign_keys = OFF")?;
+    }
+    conn.execute_batch("SAVEPOINT __pgsqlite_rebuild")?;
+    let outcome = match change() {
+        Ok(outcome) => conn.execute_batch("RELEASE __pgsqlite_rebuild").map(|_| outcome).map_err(PgSqliteError::from),
+        Err(e) => {
+            if let Err(rollback_error) = conn.execute_batch("ROLLBACK TO __pgsqlite_rebuild; RELEASE __pgsqlite_rebuild") {
+                debug!("Failed to roll back the table rebuild: {}", rollback_error);
+            }
+            Err(e)
+        }
+    };
+    if pause_foreign_keys {
+        conn.execute_batch("PRAGMA foreign_keys = ON")?;
+    }
+    outcome
+}
+
+/// Inside a transaction foreign keys stay enforced, and dropping the old table
+/// would delete, nullify or refuse to drop the rows of other tables referencing it
+fn refuse_referenced_rebuild(conn: &Connection, table: &str) -> Result<(), PgSqliteError> {
+    for foreign_key in ForeignKeys::referencing(conn, table)? {
+        if foreign_key.table.eq_ignore_ascii_case(table) {
+            continue;
+        }
+        let referenced: bool = conn.query_row(
+            &format!(
+                "SELECT EXISTS(SELECT 1 FROM {} WHERE {})",
+                quote_identifier(&foreign_key.table),
+                foreign_key.columns.iter().map(|column| format!("{} IS NOT NULL", quote_identifier(column))).collect::<Vec<_>>().join(" AND "),
+            ),
+            [],
+            |row| row.get(0),
+        )?;
+        if referenced {
+            return Err(pg_error("0A000", format!(
+                "cannot rebuild table \"{table}\" inside a transaction block while rows of table \"{}\" reference it; run this statement outside a transaction",
+                foreign_key.table,
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// ADD FOREIGN KEY: validated against the parent and the existing rows, then added by a rebuild
+#[allow(clippy::too_many_arguments)]
+fn add_foreign_key(
+    conn: &Connection,
+    table: &str,
+    name: Option<&str>,
+    columns: &[String],
+    parent: &str,
+    parent_columns: &[String],
+    options: &str,
+    validate: bool,
+) -> Result<(), PgSqliteError> {
+    let columns = columns.iter().map(|column| existing_column(conn, table, column)).collect::<Result<Vec<_>, _>>()?;
+    let parent = table_name(conn, parent)?.unwrap_or_else(|| parent.to_string());
+    let constraint_name = name.map(str::to_string).unwrap_or_else(|| format!("{table}_{}_fkey", columns.join("_")));
+    if ForeignKeys::of_table(conn, table)?.iter().any(|foreign_key| foreign_key.name.eq_ignore_ascii_case(&constraint_name)) {
+        return Err(pg_error("42710", format!("constraint \"{constraint_name}\" for relation \"{table}\" already exists")));
+    }
+
+    // The parent's primary key when no columns were named
+    let parent_columns = if parent_columns.is_empty() {
+        let primary_key = query_column(conn, "SELECT name FROM pragma_table_info(?1) WHERE pk > 0 ORDER BY pk", &parent)?;
+        if primary_key.is_empty() { vec![String::new(); columns.len()] } else { primary_key }
+    } else {
+        parent_columns.to_vec()
+    };
+    ForeignKeys::validate(conn, &ForeignKey {
+        name: constraint_name,
+        table: table.to_string(),
+        columns: columns.clone(),
+        parent: parent.clone(),
+        parent_columns: parent_columns.clone(),
+        id: 0,
+    }, validate)?;
+
+    let quoted = |names: &[String]| names.iter().map(|name| quote_identifier(name)).collect::<Vec<_>>().join(", ");
+    let mut element = format!("FOREIGN KEY ({}) REFERENCES {} ({})", quoted(&columns), quote_identifier(&parent), quoted(&parent_columns));
+    if let Some(name) = name {
+        element = format!("CONSTRAINT {} {element}", quote_identifier(name));
+    }
+    if !options.is_empty() {
+        element = format!("{element} {options}");
+    }
+
+    // Existing rows are not checked again while they are copied
+    let mut layout = TableLayout::load(conn, table)?;
+    layout.elements.push(element);
+    let copy = table_columns(conn, table)?.into_iter().map(|name| (name.clone(), quote_identifier(&name))).collect::<Vec<_>>();
+    conn.execute_batch("PRAGMA defer_foreign_keys = ON")?;
+    let rebuilt = rebuild_table(conn, table, &layout, &copy, None);
+    conn.execute_batch("PRAGMA defer_foreign_keys = OFF")?;
+    rebuilt
+}
+
+/// Drop a column the way ALTER TABLE ... DROP COLUMN does, returning every name the
+/// table had. DROP TYPE ... CASCADE uses this for the columns of the dropped type.
+pub(crate) fn drop_dependent_column(conn: &Connection, table: &str, column: &str) -> Result<Vec<String>, PgSqliteError> {
+    let statement = AlterTableStatement {
+        table: table.to_string(),
+        if_exists: true,
+        actions: vec![AlterTableAction::DropColumn { column: column.to_string(), if_exists: true }],
+    };
+    Ok(AlterTableHandler::alter_table(conn, &statement)?.tables)
+}
+
+/// Rebuild a table without one of its foreign keys, declared either as a table
+/// constraint or as a REFERENCES clause of its column. DROP TABLE ... CASCADE on
+/// the referenced table uses this too.
+pub(crate) fn drop_foreign_key(conn: &Connection, table: &str, foreign_key: &ForeignKey) -> Result<(), PgSqliteError> {
+    let references_parent = |clause: &str| {
+        let words = tokens(clause);
+        words.iter().position(|&(start, end)| clause[start..end].eq_ignore_ascii_case("REFERENCES"))
+            .and_then(|i| words.get(i + 1))
+            .is_some_and(|&(start, end)| unqualified(&clause[start..end]).eq_ignore_ascii_case(&foreign_key.parent))
+    };
+
+    let mut layout = TableLayout::load(conn, table)?;
+    let mut dropped = false;
+    for element in &mut layout.elements {
+        if dropped {
+            break;
+        }
+        if is_table_constraint(element) {
+            if clause_kind(element) != "FOREIGN" || !references_parent(element) {
+                continue;
+            }
+            let columns = tokens(element).into_iter()
+                .map(|(start, end)| &element[start..end])
+                .find(|token| token.starts_with('('))
+                .map(|list| list[1..list.len() - 1].split(',').map(unquote_identifier).collect::<Vec<_>>())
+                .unwrap_or_default();
+            if columns.len() == foreign_key.columns.len()
+                && columns.iter().all(|column| foreign_key.columns.iter().any(|c| c.eq_ignore_ascii_case(column))) {
+                element.clear();
+                dropped = true;
+            }
+        } else if let [column] = foreign_key.columns.as_slice()
+            && let Some(mut definition) = ColumnDefinition::parse(element)
+            && definition.column_name().eq_ignore_ascii_case(column) {
+            let before = definition.clauses.len();
+            definition.clauses.retain(|clause| !(clause_kind(clause) == "REFERENCES" && references_parent(clause)));
+            if definition.clauses.len() < before {
+                *element = definition.to_sql();
+                dropped = true;
+            }
+        }
+    }
+    if !dropped {
+        return Err(PgSqliteError::Protocol(format!("Cannot find the definition of constraint {} of table {table}", foreign_key.name)));
+    }
+    layout.elements.retain(|element| !element.is_empty());
+
+    let copy = table_columns(conn, table)?.into_iter().map(|name| (name.clone(), quote_identifier(&name))).collect::<Vec<_>>();
+    rebuild_table(conn, table, &layout, &copy, None)
+}
+
+/// Whether pg_constraint lists a constraint of the table under this name
+fn constraint_exists(conn: &Connection, table: &str, name: &str) -> bool {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM pg_constraint c JOIN pg_class t ON t.oid = c.conrelid WHERE t.relname = ?1 AND c.conname = ?2 COLLATE NOCASE)",
+        [table, name],
+        |row| row.get(0),
+    ).unwrap_or(false)
+}
+
+/// Translate one PostgreSQL column definition with the CREATE TABLE translator.
+/// The returned SQL is just the SQLite column definition.
+fn translate_column(conn: &Connection, definition: &str) -> Result<CreateTableResult, PgSqliteError> {
+    // A serial or identity column only becomes the rowid alias when it is declared the primary key
+    let is_primary_key = ColumnDefinition::parse(definition)
+        .is_some_and(|column| column.clauses.iter().any(|clause| clause_kind(clause) == "PRIMARY"));
+    let placeholder_key = if is_primary_key { String::new() } else { format!(", PRIMARY KEY ({SCRATCH_TABLE}_key)") };
+    let mut result = CreateTableTranslator::translate_with_connection_full(
+        &format!("CREATE TABLE {SCRATCH_TABLE} ({definition}{placeholder_key})"),
+        Some(conn),
+    ).map_err(|e| PgSqliteError::Protocol(format!("ALTER TABLE translation failed: {e}")))?;
+    let prefix = format!("CREATE TABLE {SCRATCH_TABLE} (");
+    result.sql = result.sql.strip_prefix(&prefix)
+        .and_then(|sql| sql.strip_suffix(')'))
+        .and_then(|sql| split_top_level(sql, ",").first().map(|column| column.to_string()))
+        .ok_or_else(|| PgSqliteError::Protocol(format!("ALTER TABLE translation failed for \"{definition}\"")))?;
+    Ok(result)
+}
+
+/// Store the type metadata the CREATE TABLE path records for a column
+fn record_column_metadata(conn: &Connection, table: &str, column: &str, result: &CreateTableResult) -> Result<(), PgSqliteError> {
+    for mapping in result.type_mappings.values() {
+        conn.execute(
+            "INSERT OR REPLACE INTO __pgsqlite_schema (table_name, column_name, pg_type, sqlite_type) VALUES (?1, ?2, ?3, ?4)",
+            [table, column, &mapping.pg_type, &mapping.sqlite_type],
+        )?;
+        let Some(modifier) = mapping.type_modifier else {
+            continue;
+        };
+        let base_type = base_type(&mapping.pg_type);
+        if is_string_type(&base_type) {
+            conn.execute(
+                "INSERT OR REPLACE INTO __pgsqlite_string_constraints (table_name, column_name, max_length, is_char_type) VALUES (?1, ?2, ?3, ?4)",
+                rusqlite::params![table, column, modifier, is_char_type(&base_type)],
+            )?;
+        } else if base_type == "numeric" || base_type == "decimal" {
+            let typmod = modifier - 4; // Remove VARHDRSZ
+            conn.execute(
+                "INSERT OR REPLACE INTO __pgsqlite_numeric_constraints (table_name, column_name, precision, scale) VALUES (?1, ?2, ?3, ?4)",
+                rusqlite::params![table, column, (typmod >> 16) & 0xFFFF, typmod & 0xFFFF],
+            )?;
+        }
+    }
+    for (_, enum_type) in &result.enum_columns {
+        EnumTriggers::record_enum_usage(conn, table, column, enum_type)?;
+    }
+    for (_, element_type, dimensions) in &result.array_columns {
+        conn.execute(
+            "INSERT OR REPLACE INTO __pgsqlite_array_types (table_name, column_name, element_type, dimensions) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![table, column, element_type, dimensions],
+        )?;
+    }
+    if let Some(identity) = result.identity_columns.first() {
+        IdentityColumns::init(conn)?;
+        conn.execute(
+            "INSERT OR REPLACE INTO __pgsqlite_identity_columns (table_name, column_name, identity_kind, is_rowid_alias) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![table, column, identity.kind, identity.is_rowid_alias],
+        )?;
+    }
+    Ok(())
+}
+
+/// Drop the triggers pgsqlite created to validate the table's columns
+fn drop_validation_triggers(conn: &Connection, table: &str) -> Result<(), PgSqliteError> {
+    let triggers = query_column(
+        conn,
+        r"SELECT name FROM sqlite_master WHERE type = 'trigger' AND tbl_name = ?1 AND name LIKE '\_\_pgsqlite\_%' ESCAPE '\'",
+        table,
+    )?;
+    for trigger in triggers {
+        conn.execute(&format!("DROP TRIGGER {}", quote_identifier(&trigger)), [])?;
+    }
+    Ok(())
+}
+
+/// Recreate the validation and audit triggers of the table's columns from the metadata tables
+fn create_validation_triggers(conn: &Connection, table: &str) -> Result<(), PgSqliteError> {
+    let present = existing_tables(conn, COLUMN_METADATA)?;
+
+    if present.contains(&"__pgsqlite_string_constraints") {
+        let mut stmt = conn.prepare("SELECT column_name, max_length, is_char_type FROM __pgsqlite_string_constraints WHERE table_name = ?1")?;
+        let constraints = stmt.query_map([table], |row| Ok(StringConstraint {
+            table_name: table.to_string(),
+            column_name: row.get(0)?,
+            max_length: row.get(1)?,
+            is_char_type: row.get::<_, i32>(2)? != 0,
+        }))?.collect::<rusqlite::Result<Vec<_>>>()?;
+        for constraint in constraints {
+            StringConstraintValidator::create_length_triggers(conn, &constraint)?;
+        }
+    }
+
+    if present.contains(&"__pgsqlite_schema") {
+        for (column, pg_type) in query_pairs(conn, "SELECT column_name, pg_type FROM __pgsqlite_schema WHERE table_name = ?1", table)? {
+            if let Some(constraint) = BitConstraint::from_column(table, &column, &pg_type, type_modifier(&pg_type)) {
+                constraint.create_triggers(conn)?;
+            }
+            CompositeDdlHandler::create_column_triggers(conn, table, &column, &pg_type)?;
+        }
+    }
+
+    if present.contains(&"__pgsqlite_enum_usage") {
+        let array_columns = if present.contains(&"__pgsqlite_array_types") {
+            query_column(conn, "SELECT column_name FROM __pgsqlite_array_types WHERE table_name = ?1", table)?
+        } else {
+            Vec::new()
+        };
+        for (column, enum_type) in query_pairs(conn, "SELECT column_name, enum_type FROM __pgsqlite_enum_usage WHERE table_name = ?1", table)? {
+            if array_columns.iter().any(|array_column| array_column.eq_ignore_ascii_case(&column)) {
+                EnumTriggers::create_enum_array_validation_triggers(conn, table, &column, &enum_type)?;
+            } else {
+                EnumTriggers::create_enum_validation_triggers(conn, table, &column, &enum_type)?;
+            }
+        }
+    }
+
+    if present.contains(&"__pgsqlite_identity_columns") {
+        let mut stmt = conn.prepare("SELECT column_name, identity_kind, is_rowid_alias FROM __pgsqlite_identity_columns WHERE table_name = ?1")?;
+        let columns = stmt.query_map([table], |row| Ok(IdentityColumn {
+            column_name: row.get(0)?,
+            kind: row.get(1)?,
+            is_rowid_alias: row.get(2)?,
+        }))?.collect::<rusqlite::Result<Vec<_>>>()?;
+        IdentityColumns::create_identity_triggers(conn, table, &columns)?;
+    }
+
+    for constraint in ExclusionConstraint::load(conn, table)? {
+        constraint.create_triggers(conn)?;
+    }
+
+    // Audit triggers record every column, so they are regenerated for the new ones
+    crate::query::AuditHandler::create_triggers(conn, table)?;
+    Ok(())
+}
+
+/// The column definitions and table constraints of a table as SQLite stores them
+#[derive(Debug, Clone, PartialEq)]
+struct TableLayout {
+    elements: Vec<String>,
+    /// Table options after the closing parenthesis, such as WITHOUT ROWID
+    suffix: String,
+}
+
+impl TableLayout {
+    fn load(conn: &Connection, table: &str) -> Result<Self, PgSqliteError> {
+        let sql: String = conn.query_row("SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?1", [table], |row| row.get(0))?;
+        Self::parse(&sql).ok_or_else(|| PgSqliteError::Protocol(format!("Cannot parse the definition of table {table}")))
+    }
+
+    fn parse(sql: &str) -> Option<Self> {
+        let (start, end) = tokens(sql).into_iter().find(|&(start, _)| sql[start..].starts_with('('))?;
+        Some(TableLayout {
+            elements: split_top_level(&sql[start + 1..end - 1], ",").into_iter().map(str::to_string).collect(),
+            suffix: sql[end..].to_string(),
+        })
+    }
+
+    fn update_column(&mut self, column: &str, change: impl FnOnce(&mut ColumnDefinition)) -> Result<(), PgSqliteError> {
+        let element = self.elements.iter_mut()
+            .filter(|element| !is_table_constraint(element))
+            .find(|element| ColumnDefinition::parse(element).is_some_and(|definition| definition.column_name().eq_ignore_ascii_case(column)))
+            .ok_or_else(|| PgSqliteError::Protocol(format!("Cannot find the definition of column {column}")))?;
+        let mut definition = ColumnDefinition::parse(element).expect("column definition parsed above");
+        change(&mut definition);
+        *element = definition.to_sql();
+        Ok(())
+    }
+}
+
+/// A column definition split into its name, type and constraint clauses, each kept as written
+#[derive(Debug, Clone, PartialEq)]
+struct ColumnDefinition {
+    name: String,
+    data_type: String,
+    clauses: Vec<String>,
+}
+
+impl ColumnDefinition {
+    fn parse(definition: &str) -> Option<Self> {
+        let spans = tokens(definition);
+        let (&(name_start, name_end), rest) = spans.split_first()?;
+        let word = |(start, end): (usize, usize)| definition[start..end].to_uppercase();
+
+        let mut type_span: Option<(usize, usize)> = None;
+        let mut clauses: Vec<Vec<(usize, usize)>> = Vec::new();
+        let mut previous = String::new();
+        for &span in rest {
+            let upper = word(span);
+            let starts_clause = CLAUSE_KEYWORDS.contains(&upper.as_str()) && match clauses.last() {
+                None => true,
+                Some(clause) => {
+                    let first = word(clause[0]);
+                    let kind = if first == "CONSTRAINT" { clause.get(2).map(|&s| word(s)).unwrap_or_default() } else { first.clone() };
+                    // NOT NULL, ON DELETE SET NULL, CONSTRAINT <name> <constraint>, DEFAULT NULL, GENERATED BY DEFAULT AS IDENTITY
+                    !(previous == "NOT"
+                        || previous == "SET"
+                        || (first == "CONSTRAINT" && clause.len() < 3)
+                        || (first == "DEFAULT" && clause.len() < 2)
+                        || (kind == "GENERATED" && (upper == "DEFAULT" || upper == "AS")))
+                }
+            };
+            if starts_clause {
+                clauses.push(vec![span]);
+            } else if let Some(clause) = clauses.last_mut() {
+                clause.push(span);
+            } else {
+                type_span = Some((type_span.map_or(span.0, |(start, _)| start), span.1));
+            }
+            previous = upper;
+        }
+
+        let text = |(start, end): (usize, usize)| definition[start..end].to_string();
+        Some(ColumnDefinition {
+            name: text((name_start, name_end)),
+            data_type: type_span.map(text).unwrap_or_default(),
+            clauses: clauses.iter().map(|clause| text((clause[0].0, clause[clause.len() - 1].1))).collect(),
+        })
+    }
+
+    fn column_name(&self) -> String {
+        unquote_identifier(&self.name)
+    }
+
+    fn to_sql(&self) -> String {
+        std::iter::once(self.name.as_str())
+            .chain((!self.data_type.is_empty()).then_some(self.data_type.as_str()))
+            .chain(self.clauses.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The DEFAULT expression, if the column has one
+    fn default(&self) -> Option<String> {
+        self.clauses.iter().filter(|clause| clause_kind(clause) == "DEFAULT").find_map(|clause| {
+            let words = clause_words(clause);
+            let (_, end) = words.iter().find(|(word, _)| word == "DEFAULT")?;
+            Some(clause[*end..].trim().to_string())
+        })
+    }
+
+    fn set_default(&mut self, default: Option<&str>) {
+        self.clauses.retain(|clause| clause_kind(clause) != "DEFAULT");
+        if let Some(default) = default {
+            self.clauses.push(format!("DEFAULT {default}"));
+        }
+    }
+
+    fn is_not_null(&self) -> bool {
+        self.clauses.iter().any(|clause| is_not_null_clause(clause))
+    }
+
+    fn set_not_null(&mut self, not_null: bool) {
+        self.clauses.retain(|clause| !is_not_null_clause(clause) && clause_kind(clause) != "NULL");
+        if not_null {
+            self.clauses.push("NOT NULL".to_string());
+        }
+    }
+}
+
+/// Upper-cased words of a constraint clause with the end offset of each
+fn clause_words(clause: &str) -> Vec<(String, usize)> {
+    tokens(clause).into_iter().map(|(start, end)| (clause[start..end].to_uppercase(), end)).collect()
+}
+
+/// The constraint keyword of a clause, skipping a CONSTRAINT <name> prefix
+fn clause_kind(clause: &str) -> String {
+    let words = clause_words(clause);
+    let index = if words.first().is_some_and(|(word, _)| word == "CONSTRAINT") { 2 } else { 0 };
+    words.get(index).map(|(word, _)| word.clone()).unwrap_or_default()
+}
+
+fn is_not_null_clause(clause: &str) -> bool {
+    let words = clause_words(clause);
+    let index = if words.first().is_some_and(|(word, _)| word == "CONSTRAINT") { 2 } else { 0 };
+    words.get(index).is_some_and(|(word, _)| word == "NOT") && words.get(index + 1).is_some_and(|(word, _)| word == "NULL")
+}
+
+fn is_table_constraint(element: &str) -> bool {
+    element.split_whitespace().next()
+        .is_some_and(|word| TABLE_CONSTRAINT_KEYWORDS.iter().any(|keyword| word.eq_ignore_ascii_case(keyword)))
+}
+
+/// Whether a SQL fragment names the column, looking inside parentheses but not string literals
+fn mentions(sql: &str, column: &str) -> bool {
+    tokens(sql).into_iter().any(|(start, end)| {
+        let token = &sql[start..end];
+        if token.starts_with('(') {
+            mentions(&token[1..token.len() - 1], column)
+        } else if token.starts_with('\'') {
+            false
+        } else {
+            token.rsplit('.').next().is_some_and(|name| unquote_identifier(name).eq_ignore_ascii_case(column))
+        }
+    })
+}
+
+/// Byte ranges of the tokens of a SQL fragment: quoted strings and identifiers,
+/// parenthesized or bracketed groups, words, and single punctuation characters
+fn tokens(sql: &str) -> Vec<(usize, usize)> {
+    let bytes = sql.as_bytes();
+    let is_word = |c: u8| c.is_ascii_alphanumeric() || c == b'_' || c == b'$' || c == b'.' || c >= 0x80;
+    let mut spans = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        match bytes[i] {
+            c if c.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b'\'' | b'"' => i = skip_quoted(bytes, i),
+            b'(' | b'[' => {
+                let mut depth = 0;
+                while i < bytes.len() {
+                    match bytes[i] {
+                        b'\'' | b'"' => {
+                            i = skip_quoted(bytes, i);
+                            continue;
+                        }
+                        b'(' | b'[' => depth += 1,
+                        b')' | b']' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                i += 1;
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                    i += 1;
+                }
+            }
+            c if is_word(c) => {
+                while i < bytes.len() && is_word(bytes[i]) {
+                    i += 1;
+                }
+            }
+            _ => i += 1,
+        }
+        spans.push((start, i));
+    }
+    spans
+}
+
+/// Index just past the closing quote of the quoted text starting at `start`
+fn skip_quoted(bytes: &[u8], start: usize) -> usize {
+    let quote = bytes[start];
+    let mut i = start + 1;
+    while i < bytes.len() {
+        if bytes[i] == quote {
+            if bytes.get(i + 1) == Some(&quote) {
+                i += 2;
+                continue;
+            }
+            return i + 1;
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+/// Split on a separator token outside parentheses and quotes
+fn split_top_level<'a>(sql: &'a str, separator: &str) -> Vec<&'a str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    for (token_start, token_end) in tokens(sql) {
+        if &sql[token_start..token_end] == separator {
+            parts.push(sql[start..token_start].trim());
+            start = token_end;
+        }
+    }
+    parts.push(sql[start..].trim());
+    parts.into_iter().filter(|part| !part.is_empty()).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TypeCategory {
+    Integer,
+    Number,
+    Text,
+    Other,
+}
+
+/// The broad category deciding whether PostgreSQL converts a column's values without USING
+fn type_category(pg_type: &str) -> TypeCategory {
+    if pg_type.contains('[') {
+        return TypeCategory::Other;
+    }
+    match base_type(pg_type).as_str() {
+        "smallint" | "int2" | "integer" | "int" | "int4" | "bigint" | "int8"
+        | "serial" | "serial2" | "serial4" | "serial8" | "smallserial" | "bigserial" => TypeCategory::Integer,
+        "real" | "float4" | "double precision" | "float8" | "float" | "numeric" | "decimal" => TypeCategory::Number,
+        "text" | "varchar" | "character varying" | "char" | "character" | "bpchar" | "name" | "citext" => TypeCategory::Text,
+        _ => TypeCategory::Other,
+    }
+}
+
+/// Lower-cased type name without its modifiers
+fn base_type(pg_type: &str) -> String {
+    pg_type.split('(').next().unwrap_or(pg_type).trim().to_lowercase()
+}
+
+fn is_string_type(base_type: &str) -> bool {
+    matches!(base_type, "varchar" | "char" | "character varying" | "character" | "nvarchar")
+}
+
+fn is_char_type(base_type: &str) -> bool {
+    matches!(base_type, "char" | "character")
+}
+
+/// The first modifier of a type, such as the length of bit(8)
+fn type_modifier(pg_type: &str) -> Option<i32> {
+    let (_, modifiers) = pg_type.split_once('(')?;
+    modifiers.split([',', ')']).next()?.trim().parse().ok()
+}
+
+/// The table's name as stored, matched case-insensitively
+fn table_name(conn: &Connection, name: &str) -> rusqlite::Result<Option<String>> {
+    conn.query_row(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name = ?1 COLLATE NOCASE",
+        [name],
+        |row| row.get(0),
+    ).optional()
+}
+
+/// The column's name as stored, matched case-insensitively
+fn column_name(conn: &Connection, table: &str, column: &str) -> rusqlite::Result<Option<String>> {
+    conn.query_row(
+        "SELECT name FROM pragma_table_xinfo(?1) WHERE name = ?2 COLLATE NOCASE",
+        [table, column],
+        |row| row.get(0),
+    ).optional()
+}
+
+fn existing_column(conn: &Connection, table: &str, column: &str) -> Result<String, PgSqliteError> {
+    column_name(conn, table, column)?.ok_or_else(|| undefined_column(table, column))
+}
+
+/// Columns rows are copied through, leaving out generated columns
+fn table_columns(conn: &Connection, table: &str) -> rusqlite::Result<Vec<String>> {
+    query_column(conn, "SELECT name FROM pragma_table_xinfo(?1) WHERE hidden = 0 ORDER BY cid", table)
+}
+
+fn has_rows(conn: &Connection, table: &str) -> rusqlite::Result<bool> {
+    conn.query_row(&format!("SELECT EXISTS(SELECT 1 FROM {})", quote_identifier(table)), [], |row| row.get(0))
+}
+
+/// The table's OID in pg_class, when the catalog and pg_description are present
+fn relation_oid(conn: &Connection, table: &str) -> rusqlite::Result<Option<i64>> {
+    if existing_tables(conn, &["pg_description"])?.is_empty() {
+        return Ok(None);
+    }
+    conn.query_row("SELECT CAST(oid AS INTEGER) FROM pg_class WHERE relname = ?1", [table], |row| row.get(0)).optional()
+}
+
+/// The subset of the given tables that exist
+fn existing_tables<'a>(conn: &Connection, names: &[&'a str]) -> rusqlite::Result<Vec<&'a str>> {
+    let mut present = Vec::new();
+    for &name in names {
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+            [name],
+            |row| row.get(0),
+        )?;
+        if exists {
+            present.push(name);
+        }
+    }
+    Ok(present)
+}
+
+fn query_column(conn: &Connection, sql: &str, parameter: &str) -> rusqlite::Result<Vec<String>> {
+    let mut stmt = conn.prepare(sql)?;
+    let rows = stmt.query_map([parameter], |row| row.get(0))?;
+    rows.collect()
+}
+
+fn query_pairs(conn: &Connection, sql: &str, parameter: &str) -> rusqlite::Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(sql)?;
+    let rows = stmt.query_map([parameter], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    rows.collect()
+}
+
+fn pg_error(code: &str, message: String) -> PgSqliteError {
+    PgSqliteError::Validation(PgError::Generic { code: code.to_string(), message })
+}
+
+fn duplicate_column(table: &str, column: &str) -> PgSqliteError {
+    pg_error("42701", format!("column \"{column}\" of relation \"{table}\" already exists"))
+}
+
+fn undefined_column(table: &str, column: &str) -> PgSqliteError {
+    pg_error("42703", format!("column \"{column}\" of relation \"{table}\" does not exist"))
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_column_actions() {
+        assert_eq!(AlterTableHandler::parse_alter_table("ALTER TABLE public.orders ADD COLUMN note VARCHAR(20) DEFAULT 'none', DROP COLUMN IF EXISTS legacy CASCADE;"), Some(AlterTableStatement {
+            table: "orders".to_string(),
+            if_exists: false,
+            actions: vec![
+                AlterTableAction::AddColumn {
+                    column: "note".to_string(),
+                    definition: "note VARCHAR(20) DEFAULT 'none'".to_string(),
+                    if_not_exists: false,
+                },
+                AlterTableAction::DropColumn { column: "legacy".to_string(), if_exists: true },
+            ],
+        }));
+        assert_eq!(AlterTableHandler::parse_alter_table("alter table if exists only \"Orders\" alter column total type numeric(12, 2) using total::numeric"), Some(AlterTableStatement {
+            table: "Orders".to_string(),
+            if_exists: true,
+            actions: vec![AlterTableAction::AlterColumnType {
+                column: "total".to_string(),
+                data_type: "numeric(12, 2)".to_string(),
+                using: Some("total::numeric".to_string()),
+            }],
+        }));
+        assert_eq!(AlterTableHandler::parse_alter_table("ALTER TABLE orders ALTER paid SET DEFAULT false, ALTER paid DROP NOT NULL, ALTER COLUMN note DROP DEFAULT, ALTER note SET NOT NULL").unwrap().actions, vec![
+            AlterTableAction::SetDefault { column: "paid".to_string(), default: "false".to_string() },
+            AlterTableAction::DropNotNull { column: "paid".to_string() },
+            AlterTableAction::DropDefault { column: "note".to_string() },
+            AlterTableAction::SetNotNull { column: "note".to_string() },
+        ]);
+        assert_eq!(AlterTableHandler::parse_alter_table("ALTER TABLE orders RENAME COLUMN \"Total\" TO amount").unwrap().actions, vec![
+            AlterTableAction::RenameColumn { column: "Total".to_string(), new_name: "amount".to_string() },
+        ]);
+        assert_eq!(AlterTableHandler::parse_alter_table("ALTER TABLE orders RENAME TO purchases").unwrap().actions, vec![
+            AlterTableAction::RenameTable { new_name: "purchases".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn test_parse_foreign_key_actions() {
+        assert_eq!(AlterTableHandler::parse_alter_table("ALTER TABLE orders ADD CONSTRAINT \"Orders_customer\" FOREIGN KEY (customer_id, region) REFERENCES public.customers (id, region) ON DELETE CASCADE NOT VALID").unwrap().actions, vec![
+            AlterTableAction::AddForeignKey {
+                name: Some("Orders_customer".to_string()),
+                columns: vec!["customer_id".to_string(), "region".to_string()],
+                parent: "customers".to_string(),
+                parent_columns: vec!["id".to_string(), "region".to_string()],
+                options: "ON DELETE CASCADE".to_string(),
+                validate: false,
+            },
+        ]);
+        assert_eq!(AlterTableHandler::parse_alter_table("ALTER TABLE orders ADD FOREIGN KEY (customer_id) REFERENCES customers, DROP CONSTRAINT IF EXISTS orders_old_fkey CASCADE").unwrap().actions, vec![
+            AlterTableAction::AddForeignKey {
+                name: None,
+                columns: vec!["customer_id".to_string()],
+                parent: "customers".to_string(),
+                parent_columns: vec![],
+                options: String::new(),
+                validate: true,
+            },
+            AlterTableAction::DropConstraint { name: "orders_old_fkey".to_string(), if_exists: true },
+        ]);
+    }
+
+    #[test]
+    fn test_parse_leaves_other_statements_alone() {
+        assert_eq!(AlterTableHandler::parse_alter_table("ALTER TABLE orders ADD CONSTRAINT orders_total_check CHECK (total > 0)"), None);
+        assert_eq!(AlterTableHandler::parse_alter_table("ALTER TABLE orders ADD PRIMARY KEY (id)"), None);
+        assert_eq!(AlterTableHandler::parse_alter_table("ALTER TABLE orders ALTER CONSTRAINT orders_customer_fk DEFERRABLE"), None);
+        assert_eq!(AlterTableHandler::parse_alter_table("ALTER TABLE orders ADD COLUMN note TEXT, OWNER TO postgres"), None);
+        assert_eq!(AlterTableHandler::parse_alter_table("ALTER SEQUENCE orders_id_seq RESTART"), None);
+        assert_eq!(AlterTableHandler::parse_alter_table("SELECT 1"), None);
+    }
+
+    #[test]
+    fn test_column_definition_clauses() {
+        let mut column = ColumnDefinition::parse("status VARCHAR(10) CONSTRAINT status_set NOT NULL DEFAULT 'new' CHECK (status IN ('new', 'done'))").unwrap();
+        assert_eq!(column.column_name(), "status");
+        assert_eq!(column.data_type, "VARCHAR(10)");
+        assert_eq!(column.clauses, ["CONSTRAINT status_set NOT NULL", "DEFAULT 'new'", "CHECK (status IN ('new', 'done'))"]);
+        assert!(column.is_not_null());
+        assert_eq!(column.default().as_deref(), Some("'new'"));
+
+        column.set_default(Some("(lower('NEW'))"));
+        column.set_not_null(false);
+        assert_eq!(column.to_sql(), "status VARCHAR(10) CHECK (status IN ('new', 'done')) DEFAULT (lower('NEW'))");
+
+        let column = ColumnDefinition::parse("\"Owner\" INTEGER DEFAULT NULL REFERENCES users(id) ON DELETE SET NULL").unwrap();
+        assert_eq!(column.column_name(), "Owner");
+        assert_eq!(column.clauses, ["DEFAULT NULL", "REFERENCES users(id) ON DELETE SET NULL"]);
+
+        let column = ColumnDefinition::parse("seq BIGINT GENERATED BY DEFAULT AS IDENTITY").unwrap();
+        assert_eq!(column.data_type, "BIGINT");
+        assert_eq!(column.clauses, ["GENERATED BY DEFAULT AS IDENTITY"]);
+        assert_eq!(column.default(), None);
+    }
+
+    #[test]
+    fn test_table_layout_and_mentions() {
+        let layout = TableLayout::parse("CREATE TABLE \"t\" (id INTEGER PRIMARY KEY, note TEXT DEFAULT 'a,b', n INTEGER, UNIQUE (note, n)) WITHOUT ROWID").unwrap();
+        assert_eq!(layout.elements, ["id INTEGER PRIMARY KEY", "note TEXT DEFAULT 'a,b'", "n INTEGER", "UNIQUE (note, n)"]);
+        assert_eq!(layout.suffix, " WITHOUT ROWID");
+
+        assert!(mentions("UNIQUE (note, n)", "NOTE"));
+        assert!(mentions("CHECK (t.n > 0)", "n"));
+        assert!(!mentions("CHECK (kind <> 'note')", "note"));
+    }
+
+    #[test]
+    fn test_sqlite_default() {
+        assert_eq!(sqlite_default("0"), "0");
+        assert_eq!(sqlite_default("'it''s'"), "'it''s'");
+        assert_eq!(sqlite_default("NULL"), "NULL");
+        assert_eq!(sqlite_default("-1.5"), "-1.5");
+        assert_eq!(sqlite_default("(1 + 2)"), "(1 + 2)");
+        assert_eq!(sqlite_default("datetime('now')"), "(datetime('now'))");
+    }
+}
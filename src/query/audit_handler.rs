@@ -0,0 +1,380 @@
+use crate::metadata::{Namespaces, OidAllocator};
+use crate::protocol::{BackendMessage, FieldDescription};
+use crate::query::sql_utils::{fold_identifier, quote_identifier, split_qualified};
+use crate::query::trigger_handler::pg_error;
+use crate::session::{SessionState, SessionStorage};
+use crate::types::{PgType, SchemaTypeMapper};
+use crate::PgSqliteError;
+use futures::SinkExt;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rusqlite::{Connection, OptionalExtension};
+use std::sync::Arc;
+use tokio_util::codec::Framed;
+use tracing::debug;
+
+static ENABLE_AUDIT_CALL_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)^\s*SELECT\s+(?:\*\s+FROM\s+)?pgsqlite\.enable_audit\s*\(\s*'((?:[^']|'')*)'\s*\)\s*;?\s*$").unwrap()
+});
+
+/// Columns of an audit table as (name, PostgreSQL type, SQLite definition)
+const AUDIT_COLUMNS: [(&str, &str, &str); 7] = [
+    ("audit_id", "BIGINT", "INTEGER PRIMARY KEY AUTOINCREMENT"),
+    ("operation", "TEXT", "TEXT NOT NULL"),
+    ("old_row", "JSONB", "TEXT"),
+    ("new_row", "JSONB", "TEXT"),
+    ("actor", "TEXT", "TEXT"),
+    ("application_name", "TEXT", "TEXT"),
+    ("changed_at", "TIMESTAMPTZ", "INTEGER NOT NULL"),
+];
+
+/// Handles `SELECT pgsqlite.enable_audit('<table>')`.
+///
+/// Creates `<table>_audit` and SQLite triggers that add a row to it for every
+/// insert, update and delete on the table: the operation, the old and new row as
+/// JSON, the session's user and application_name, and when it happened. Audited
+/// tables are listed in __pgsqlite_audited_tables; ALTER TABLE regenerates their
+/// triggers for the new columns, and so does calling enable_audit again.
+pub struct AuditHandler;
+
+impl AuditHandler {
+    /// Whether a query names pgsqlite.enable_audit
+    pub fn might_be_enable_audit(query: &str) -> bool {
+        query.as_bytes().windows(21).any(|w| w.eq_ignore_ascii_case(b"pgsqlite.enable_audit"))
+    }
+
+    /// The table passed to a standalone `pgsqlite.enable_audit()` call
+    pub fn parse_enable_audit_call(query: &str) -> Option<String> {
+        if !Self::might_be_enable_audit(query) {
+            return None;
+        }
+        let caps = ENABLE_AUDIT_CALL_PATTERN.captures(query)?;
+        Some(caps[1].replace("''", "'").trim().to_string())
+    }
+
+    /// The row description of the result, also reported by Parse in the extended protocol
+    pub fn field_descriptions() -> Vec<FieldDescription> {
+        vec![FieldDescription {
+            name: "enable_audit".to_string(),
+            table_oid: 0,
+            column_id: 1,
+            type_oid: PgType::Text.to_oid(),
+            type_size: -1,
+            type_modifier: -1,
+            format: 0,
+        }]
+    }
+
+    /// Returns one row holding the name of the audit table
+    pub async fn handle_enable_audit<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        table: &str,
+        skip_row_description: bool,
+    ) -> Result<(), PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        db.require_local_data("pgsqlite.enable_audit()")?;
+        let (table, audit_table) = db.with_session_connection(&session.id, |conn| {
+            Ok(Self::enable_audit(conn, table))
+        }).await??;
+        crate::query::alter_table_handler::forget_table(db.as_ref(), &table);
+        db.with_session_connection(&session.id, OidAllocator::sync_relations).await?;
+        // The statement is a SELECT, so the catalog cache doesn't see it create a table
+        session.record_schema_change();
+        crate::cache::CatalogCache::bump_generation();
+        debug!("Auditing {} into {}", table, audit_table);
+
+        if !skip_row_description {
+            framed.send(BackendMessage::RowDescription(Self::field_descriptions())).await
+                .map_err(PgSqliteError::Io)?;
+        }
+        framed.send(BackendMessage::DataRow(vec![Some(audit_table.into_bytes())])).await
+            .map_err(PgSqliteError::Io)?;
+        framed.send(BackendMessage::CommandComplete {
+            tag: "SELECT 1".to_string(),
+        }).await.map_err(PgSqliteError::Io)
+    }
+
+    /// Create the audit table unless the table already has one and (re)generate its
+    /// triggers, returning the SQLite names of the table and its audit table
+    fn enable_audit(conn: &Connection, name: &str) -> Result<(String, String), PgSqliteError> {
+        let Some(table) = table_name(conn, &sqlite_name(name))? else {
+            return Err(pg_error("42P01", format!("relation \"{name}\" does not exist")));
+        };
+        let is_audit_table: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM __pgsqlite_audited_tables WHERE audit_table = ?1)",
+            [&table],
+            |row| row.get(0),
+        )?;
+        if is_audit_table {
+            return Err(pg_error("42809", format!("\"{table}\" is an audit table")));
+        }
+
+        conn.execute_batch("SAVEPOINT __pgsqlite_enable_audit")?;
+        match Self::create_audit(conn, &table) {
+            Ok(audit_table) => {
+                conn.execute_batch("RELEASE __pgsqlite_enable_audit")?;
+                Ok((table, audit_table))
+            }
+            Err(e) => {
+                if let Err(rollback_error) = conn.execute_batch("ROLLBACK TO __pgsqlite_enable_audit; RELEASE __pgsqlite_enable_audit") {
+                    debug!("Failed to roll back enable_audit: {}", rollback_error);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    fn create_audit(conn: &Connection, table: &str) -> Result<String, PgSqliteError> {
+        let audit_table = match Self::audit_table_of(conn, table)? {
+            Some(audit_table) => audit_table,
+            None => {
+                let audit_table = format!("{table}_audit");
+                if table_name(conn, &audit_table)?.is_some() {
+                    return Err(pg_error("42P07", format!("relation \"{audit_table}\" already exists")));
+                }
+                let columns = AUDIT_COLUMNS.iter()
+                    .map(|(column, _, definition)| format!("{column} {definition}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                conn.execute(&format!("CREATE TABLE {} ({columns})", quote_identifier(&audit_table)), [])?;
+                for (column, pg_type, definition) in AUDIT_COLUMNS {
+                    let sqlite_type = definition.split_whitespace().next().unwrap_or("TEXT");
+                    conn.execute(
+                        "INSERT OR REPLACE INTO __pgsqlite_schema (table_name, column_name, pg_type, sqlite_type) VALUES (?1, ?2, ?3, ?4)",
+                        [&audit_table, column, pg_type, sqlite_type],
+                    )?;
+                }
+                conn.execute(
+                    "INSERT INTO __pgsqlite_audited_tables (table_name, audit_table) VALUES (?1, ?2)",
+                    [table, &audit_table],
+                )?;
+                crate::catalog::constraint_populator::populate_constraints_for_table(conn, &audit_table)
+                    .map_err(|e| PgSqliteError::Protocol(format!("Failed to record constraints of {audit_table}: {e}")))?;
+                audit_table
+            }
+        };
+        Self::create_triggers(conn, table)?;
+        Ok(audit_table)
+    }
+
+    /// The audit table of an audited table
+    fn audit_table_of(conn: &Connection, table: &str) -> rusqlite::Result<Option<String>> {
+        if !has_audited_tables(conn)? {
+            return Ok(None);
+        }
+        conn.query_row(
+            "SELECT audit_table FROM __pgsqlite_audited_tables WHERE table_name = ?1",
+            [table],
+            |row| row.get(0),
+        ).optional()
+    }
+
+    /// Generate the audit triggers of an audited table for its current columns; other tables are left alone
+    pub fn create_triggers(conn: &Connection, table: &str) -> rusqlite::Result<()> {
+        let Some(audit_table) = Self::audit_table_of(conn, table)? else {
+            return Ok(());
+        };
+        drop_audit_triggers(conn, table)?;
+
+        let columns = row_columns(conn, table)?;
+        let row_json = |row: &str| {
+            let fields = columns.iter()
+                .map(|(column, expression)| format!("'{}', {}", column.replace('\'', "''"), expression.replace("{row}", row)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("json_object({fields})")
+        };
+        for (operation, old_row, new_row) in [
+            ("INSERT", "NULL".to_string(), row_json("NEW")),
+            ("UPDATE", row_json("OLD"), row_json("NEW")),
+            ("DELETE", row_json("OLD"), "NULL".to_string()),
+        ] {
+            conn.execute_batch(&format!(
+                "CREATE TRIGGER {trigger} AFTER {operation} ON {table} FOR EACH ROW
+                 BEGIN
+                     INSERT INTO {audit_table} (operation, old_row, new_row, actor, application_name, changed_at)
+                     VALUES ('{operation}', {old_row}, {new_row}, pgsqlite_session_user(), pgsqlite_application_name(), CAST(unixepoch('subsec') * 1000000 AS INTEGER));
+                 END",
+                trigger = quote_identifier(&audit_trigger_name(operation, table)),
+                table = quote_identifier(table),
+                audit_table = quote_identifier(&audit_table),
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Keep auditing a renamed table
+    pub fn rename_table(conn: &Connection, old_name: &str, new_name: &str) -> rusqlite::Result<()> {
+        if !has_audited_tables(conn)? {
+            return Ok(());
+        }
+        conn.execute("UPDATE __pgsqlite_audited_tables SET table_name = ?2 WHERE table_name = ?1", [old_name, new_name])?;
+        conn.execute("UPDATE __pgsqlite_audited_tables SET audit_table = ?2 WHERE audit_table = ?1", [old_name, new_name])?;
+        Ok(())
+    }
+
+    /// Forget audited tables that no longer exist, whose triggers SQLite dropped with
+    /// them, and stop auditing the tables whose audit table was dropped
+    pub fn prune_audits(conn: &Connection) -> rusqlite::Result<()> {
+        if !has_audited_tables(conn)? {
+            return Ok(());
+        }
+        let mut stmt = conn.prepare(
+            "SELECT table_name FROM __pgsqlite_audited_tables
+             WHERE table_name NOT IN (SELECT name FROM sqlite_master WHERE type = 'table')
+                OR audit_table NOT IN (SELECT name FROM sqlite_master WHERE type = 'table')",
+        )?;
+        let orphans: Vec<String> = stmt.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+        for table in orphans {
+            drop_audit_triggers(conn, &table)?;
+            conn.execute("DELETE FROM __pgsqlite_audited_tables WHERE table_name = ?1", [&table])?;
+        }
+        Ok(())
+    }
+}
+
+/// The SQLite name of a possibly schema-qualified table name
+pub(crate) fn sqlite_name(name: &str) -> String {
+    let parts: Vec<String> = split_qualified(name).into_iter().map(fold_identifier).collect();
+    match parts.as_slice() {
+        [schema, table] => Namespaces::qualify(schema, table),
+        _ => parts.last().cloned().unwrap_or_default(),
+    }
+}
+
+fn audit_trigger_name(operation: &str, table: &str) -> String {
+    format!("__pgsqlite_audit_{}_{table}", operation.to_lowercase())
+}
+
+fn drop_audit_triggers(conn: &Connection, table: &str) -> rusqlite::Result<()> {
+    for operation in ["INSERT", "UPDATE", "DELETE"] {
+        conn.execute(&format!("DROP TRIGGER IF EXISTS {}", quote_identifier(&audit_trigger_name(operation, table))), [])?;
+    }
+    Ok(())
+}
+
+/// The table's columns with the JSON value expressions of their `{row}` values,
+/// which present the stored values the way PostgreSQL would show them
+fn row_columns(conn: &Connection, table: &str) -> rusqlite::Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT c.name, c.type, s.pg_type FROM pragma_table_info(?1) c
+         LEFT JOIN __pgsqlite_schema s ON s.table_name = ?1 AND s.column_name = c.name COLLATE NOCASE
+         ORDER BY c.cid",
+    )?;
+    let rows = stmt.query_map([table], |row| {
+        let column: String = row.get(0)?;
+        let declared_type: String = row.get(1)?;
+        let pg_type: Option<String> = row.get(2)?;
+        let expression = json_value(&format!("{{row}}.{}", quote_identifier(&column)), pg_type.as_deref(), &declared_type);
+        Ok((column, expression))
+    })?;
+    rows.collect()
+}
+
+/// JSON value expression of a stored column value
+fn json_value(value: &str, pg_type: Option<&str>, declared_type: &str) -> String {
+    let Some(pg_type) = pg_type else {
+        return if declared_type.eq_ignore_ascii_case("BLOB") { hex(value) } else { value.to_string() };
+    };
+    let pg_type_upper = pg_type.trim().to_uppercase();
+    let oid = SchemaTypeMapper::pg_type_string_to_oid(&pg_type_upper);
+    match PgType::from_oid(oid) {
+        _ if pg_type_upper.ends_with("[]") => json(value),
+        Some(t) if t.is_array() => json(value),
+        Some(PgType::Json | PgType::Jsonb) => json(value),
+        Some(PgType::Bool) => format!("json(CASE {value} WHEN 1 THEN 'true' WHEN 0 THEN 'false' ELSE json_quote({value}) END)"),
+        Some(PgType::Bytea) => hex(value),
+        Some(PgType::Date) => format!("CASE WHEN typeof({value}) = 'integer' THEN date({value} * 86400, 'unixepoch') ELSE {value} END"),
+        Some(PgType::Time) => microseconds(value, "%H:%M:%S", ""),
+        Some(PgType::Timestamp) => microseconds(value, "%Y-%m-%d %H:%M:%S", ""),
+        Some(PgType::Timestamptz) => microseconds(value, "%Y-%m-%d %H:%M:%S", "+00:00"),
+        _ => value.to_string(),
+    }
+}
+
+/// Nest JSON text as JSON rather than as a string
+fn json(value: &str) -> String {
+    format!("json(CASE WHEN json_valid({value}) THEN {value} ELSE json_quote({value}) END)")
+}
+
+/// Bytes in PostgreSQL's hex output format
+fn hex(value: &str) -> String {
+    format!("'\\x' || lower(hex({value}))")
+}
+
+/// Format integer microseconds since the epoch or midnight, with the fraction only when there is one
+fn microseconds(value: &str, format: &str, suffix: &str) -> String {
+    let fraction = format!("(({value} % 1000000) + 1000000) % 1000000");
+    format!(
+        "CASE WHEN typeof({value}) = 'integer' THEN strftime('{format}', ({value} - {fraction}) / 1000000, 'unixepoch') || replace(printf('.%06d', {fraction}), '.000000', '') || '{suffix}' ELSE {value} END"
+    )
+}
+
+fn has_audited_tables(conn: &Connection) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '__pgsqlite_audited_tables')",
+        [],
+        |row| row.get(0),
+    )
+}
+
+/// The table's name as SQLite stores it, if the table exists
+pub(crate) fn table_name(conn: &Connection, name: &str) -> rusqlite::Result<Option<String>> {
+    conn.query_row(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name = ?1 COLLATE NOCASE",
+        [name],
+        |row| row.get(0),
+    ).optional()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_enable_audit_call() {
+        assert_eq!(AuditHandler::parse_enable_audit_call("SELECT pgsqlite.enable_audit('orders');"), Some("orders".to_string()));
+        assert_eq!(
+            AuditHandler::parse_enable_audit_call("select * from PGSQLITE.ENABLE_AUDIT( 'sales.\"Order''s\"' )"),
+            Some("sales.\"Order's\"".to_string())
+        );
+        assert_eq!(AuditHandler::parse_enable_audit_call("SELECT pgsqlite.enable_audit('orders'), 1"), None);
+        assert_eq!(AuditHandler::parse_enable_audit_call("SELECT 'pgsqlite.enable_audit'"), None);
+    }
+
+    #[test]
+    fn test_sqlite_name() {
+        assert_eq!(sqlite_name("Orders"), "orders");
+        assert_eq!(sqlite_name("public.orders"), "orders");
+        assert_eq!(sqlite_name("tenant1.\"Items.v2\""), "tenant1__Items.v2");
+    }
+
+    #[test]
+    fn test_json_values() {
+        use rusqlite::types::Value;
+        let conn = Connection::open_in_memory().unwrap();
+        let value = |expression: &str, stored: Value| -> String {
+            conn.query_row(&format!("SELECT json_object('v', {})", expression.replace("{row}", "?1")), [stored], |row| row.get(0)).unwrap()
+        };
+        assert_eq!(value(&json_value("{row}", Some("BOOLEAN"), "INTEGER"), Value::Integer(1)), r#"{"v":true}"#);
+        assert_eq!(value(&json_value("{row}", Some("JSONB"), "TEXT"), Value::Text(r#"{"a": [1]}"#.to_string())), r#"{"v":{"a":[1]}}"#);
+        assert_eq!(value(&json_value("{row}", Some("INTEGER[]"), "TEXT"), Value::Null), r#"{"v":null}"#);
+        assert_eq!(value(&json_value("{row}", Some("BYTEA"), "BLOB"), Value::Blob(vec![0xde, 0xad])), r#"{"v":"\\xdead"}"#);
+        assert_eq!(value(&json_value("{row}", Some("DATE"), "INTEGER"), Value::Integer(19723)), r#"{"v":"2024-01-01"}"#);
+        assert_eq!(
+            value(&json_value("{row}", Some("TIMESTAMP"), "INTEGER"), Value::Integer(1_704_103_200_500_000)),
+            r#"{"v":"2024-01-01 10:00:00.500000"}"#
+        );
+        assert_eq!(
+            value(&json_value("{row}", Some("TIMESTAMPTZ"), "INTEGER"), Value::Integer(-1)),
+            r#"{"v":"1969-12-31 23:59:59.999999+00:00"}"#
+        );
+        assert_eq!(value(&json_value("{row}", Some("TIME"), "INTEGER"), Value::Integer(45_296_000_000)), r#"{"v":"12:34:56"}"#);
+        assert_eq!(value(&json_value("{row}", Some("NUMERIC(10,2)"), "DECIMAL"), Value::Text("1.50".to_string())), r#"{"v":"1.50"}"#);
+    }
+}
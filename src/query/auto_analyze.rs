@@ -0,0 +1,185 @@
+use crate::query::sql_utils::quote_identifier;
+use crate::session::{SessionState, SessionStorage};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rusqlite::Connection;
+use std::sync::Arc;
+use tracing::{debug, info};
+
+static QUERY_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)^\s*(?:\(\s*)*(?:SELECT|WITH)\b").unwrap()
+});
+
+/// Joins, groupings, subqueries, set operations and comma-separated FROM lists
+static COMPLEX_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)\bJOIN\b|\bGROUP\s+BY\b|\(\s*SELECT\b|\b(?:UNION|INTERSECT|EXCEPT)\b|\bFROM\s+[\w.$]+(?:\s+(?:AS\s+)?\w+)?\s*,").unwrap()
+});
+
+/// Runs ANALYZE on the tables a complex query reads that have never been analyzed, so
+/// SQLite plans ad-hoc joins and aggregations over freshly loaded data with statistics.
+///
+/// A table counts as analyzed once sqlite_stat1 has rows for it, whether from this, from
+/// ANALYZE or from VACUUM ANALYZE. Tables with fewer than --auto-analyze-min-rows rows are
+/// left until they have grown, as their plans hardly depend on statistics. Each automatic
+/// run is recorded in __pgsqlite_autoanalyze for pg_stat_user_tables.last_autoanalyze.
+pub struct AutoAnalyze;
+
+impl AutoAnalyze {
+    /// Whether the query is a SELECT whose plan depends on table statistics
+    pub fn is_complex_query(query: &str) -> bool {
+        QUERY_PATTERN.is_match(query) && COMPLEX_PATTERN.is_match(query)
+    }
+
+    /// Analyze the never-analyzed tables `query` names before it runs. Failures, such as
+    /// a read-only database, are logged and leave the query to run without statistics.
+    pub async fn analyze_before(db: &Arc<dyn SessionStorage>, session: &Arc<SessionState>, query: &str) {
+        if !Self::is_complex_query(query) {
+            return;
+        }
+        let min_rows = crate::config::current().auto_analyze_min_rows;
+        if min_rows == 0 {
+            return;
+        }
+        let query = query.to_lowercase();
+        match db.with_session_connection(&session.id, |conn| analyze_unanalyzed(conn, &query, min_rows)).await {
+            Ok(analyzed) => {
+                for table in analyzed {
+                    info!("Automatically analyzed table {}", table);
+                }
+            }
+            Err(e) => debug!("Automatic ANALYZE skipped: {}", e),
+        }
+    }
+
+    /// Follow an ALTER TABLE ... RENAME TO with the table's statistics, which SQLite
+    /// leaves under the old name, and its record
+    pub fn rename_table(conn: &Connection, old_name: &str, new_name: &str) -> rusqlite::Result<()> {
+        if has_statistics(conn)? {
+            conn.execute("UPDATE sqlite_stat1 SET tbl = ?2 WHERE tbl = ?1", [old_name, new_name])?;
+        }
+        if !has_autoanalyze(conn)? {
+            return Ok(());
+        }
+        conn.execute("UPDATE __pgsqlite_autoanalyze SET table_name = ?2 WHERE table_name = ?1", [old_name, new_name])?;
+        Ok(())
+    }
+
+    /// Forget the records of dropped tables
+    pub fn prune_autoanalyze(conn: &Connection) -> rusqlite::Result<()> {
+        if !has_autoanalyze(conn)? {
+            return Ok(());
+        }
+        conn.execute(
+            "DELETE FROM __pgsqlite_autoanalyze
+             WHERE table_name NOT IN (SELECT name FROM sqlite_master WHERE type = 'table')",
+            [],
+        )?;
+        Ok(())
+    }
+}
+
+/// Analyze the tables named in the lowercased `query` that have no statistics and at
+/// least `min_rows` rows, returning their names
+fn analyze_unanalyzed(conn: &Connection, query: &str, min_rows: u64) -> rusqlite::Result<Vec<String>> {
+    let unanalyzed = format!(
+        "SELECT name FROM sqlite_master m
+         WHERE type = 'table' AND sql NOT LIKE 'CREATE VIRTUAL%'
+           AND name NOT LIKE 'sqlite\\_%' ESCAPE '\\'
+           AND name NOT LIKE '\\_\\_pgsqlite\\_%' ESCAPE '\\'
+           AND name NOT LIKE 'pg\\_%' ESCAPE '\\'{}",
+        if has_statistics(conn)? { " AND NOT EXISTS (SELECT 1 FROM sqlite_stat1 s WHERE s.tbl = m.name)" } else { "" }
+    );
+    let tables: Vec<String> = conn.prepare(&unanalyzed)?
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?
+        .into_iter()
+        .filter(|table| mentions(query, &table.to_lowercase()))
+        .collect();
+
+    let mut analyzed = Vec::new();
+    for table in tables {
+        let rows: i64 = conn.query_row(
+            &format!("SELECT count(*) FROM (SELECT 1 FROM main.{} LIMIT {min_rows})", quote_identifier(&table)),
+            [],
+            |row| row.get(0),
+        )?;
+        if (rows as u64) < min_rows {
+            continue;
+        }
+        conn.execute_batch(&format!("ANALYZE main.{}", quote_identifier(&table)))?;
+        if has_autoanalyze(conn)? {
+            conn.execute(
+                "INSERT INTO __pgsqlite_autoanalyze (table_name, last_autoanalyze, autoanalyze_count) VALUES (?1, ?2, 1)
+                 ON CONFLICT (table_name) DO UPDATE SET
+                     last_autoanalyze = excluded.last_autoanalyze,
+                     autoanalyze_count = autoanalyze_count + 1",
+                rusqlite::params![table, chrono::Utc::now().timestamp_micros()],
+            )?;
+        }
+        analyzed.push(table);
+    }
+    Ok(analyzed)
+}
+
+/// Whether `name` appears in `query` as a whole identifier
+fn mentions(query: &str, name: &str) -> bool {
+    let is_identifier = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    query.match_indices(name).any(|(start, _)| {
+        !query[..start].chars().next_back().is_some_and(is_identifier)
+            && !query[start + name.len()..].chars().next().is_some_and(is_identifier)
+    })
+}
+
+
+/// sqlite_stat1 is created by the first ANALYZE
+fn has_statistics(conn: &Connection) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'sqlite_stat1')",
+        [],
+        |row| row.get(0),
+    )
+}
+
+/// Databases opened before migration 36 have no record of automatic runs
+fn has_autoanalyze(conn: &Connection) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '__pgsqlite_autoanalyze')",
+        [],
+        |row| row.get(0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_complex_query() {
+        for query in [
+            "SELECT * FROM orders o JOIN customers c ON c.id = o.customer_id",
+            "select region, sum(total) from orders group by region",
+            "SELECT * FROM orders WHERE customer_id IN (SELECT id FROM customers)",
+            "WITH recent AS (SELECT * FROM orders) SELECT count(*) FROM recent, customers",
+            "SELECT id FROM orders UNION SELECT id FROM refunds",
+            "SELECT * FROM orders o, customers c WHERE c.id = o.customer_id",
+        ] {
+            assert!(AutoAnalyze::is_complex_query(query), "{query}");
+        }
+        for query in [
+            "SELECT * FROM orders WHERE id = 1",
+            "SELECT substring(name FROM 2), id FROM customers",
+            "INSERT INTO totals SELECT region, sum(total) FROM orders GROUP BY region",
+            "UPDATE orders SET total = 0 FROM customers WHERE customers.id = orders.customer_id",
+        ] {
+            assert!(!AutoAnalyze::is_complex_query(query), "{query}");
+        }
+    }
+
+    #[test]
+    fn test_mentions() {
+        assert!(mentions("select * from orders o join \"line items\" l on true", "line items"));
+        assert!(mentions("select * from public.orders, customers", "orders"));
+        assert!(!mentions("select * from orders_archive join customer_orders using (id)", "orders"));
+        assert!(!mentions("select * from orders", "order"));
+    }
+}
@@ -0,0 +1,386 @@
+use crate::protocol::{BackendMessage, NoticeResponse};
+use crate::query::audit_handler::{sqlite_name, table_name};
+use crate::query::progress::{ProgressCommand, ProgressGuard};
+use crate::query::sql_utils::quote_identifier;
+use crate::query::trigger_handler::pg_error;
+use crate::session::{SessionState, SessionStorage};
+use crate::PgSqliteError;
+use futures::SinkExt;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rusqlite::{Connection, OptionalExtension};
+use sqlparser::ast::Statement;
+use sqlparser::dialect::SQLiteDialect;
+use sqlparser::parser::Parser;
+use std::sync::Arc;
+use tokio_util::codec::Framed;
+use tracing::debug;
+
+static CLUSTER_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)^\s*CLUSTER\b\s*(?:\(([^()]*)\)\s*)?(VERBOSE\b\s*)?(.*?)\s*;?\s*$").unwrap()
+});
+
+/// `table [USING index]`, with the table's schema captured apart
+static CLUSTER_TABLE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^(?:("(?:[^"]|"")+"|[\w$]+)\.)?("(?:[^"]|"")+"|[\w$]+)(?:\s+USING\s+("(?:[^"]|"")+"|[\w$]+))?$"#).unwrap()
+});
+
+/// The pre-8.3 form `index ON table`
+static CLUSTER_INDEX_ON_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^("(?:[^"]|"")+"|[\w$]+)\s+ON\s+(?:("(?:[^"]|"")+"|[\w$]+)\.)?("(?:[^"]|"")+"|[\w$]+)$"#).unwrap()
+});
+
+/// A parsed CLUSTER statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClusterStatement {
+    /// The table's SQLite name; None re-clusters every table clustered before
+    pub table: Option<String>,
+    /// The index's SQLite name; None reuses the index the table was last clustered on
+    pub index: Option<String>,
+    pub verbose: bool,
+}
+
+/// An index a table can be clustered on
+#[derive(Debug, Clone, PartialEq)]
+struct ClusterIndex {
+    /// The name pg_index and pg_indexes show
+    name: String,
+    partial: bool,
+    /// The ORDER BY that reads the rows in index order
+    order_by: String,
+}
+
+/// Handles `CLUSTER [VERBOSE] [table [USING index]]` and `CLUSTER index ON table`.
+///
+/// The table is rebuilt with its rows copied in the order of the index, so rows
+/// next to each other in the index share pages and range scans over it read fewer
+/// of them. Like in PostgreSQL the index is remembered: it shows as indisclustered
+/// in pg_index, a later CLUSTER of the table may leave it out, and CLUSTER without
+/// a table re-clusters every table clustered before. A rebuild in progress shows up
+/// in pg_stat_progress_cluster.
+pub struct ClusterHandler;
+
+impl ClusterHandler {
+    /// Whether a query starts with CLUSTER
+    pub fn might_be_cluster(query: &str) -> bool {
+        query.trim_start().get(..7).is_some_and(|prefix| prefix.eq_ignore_ascii_case("CLUSTER"))
+    }
+
+    pub fn parse_cluster(query: &str) -> Result<Option<ClusterStatement>, PgSqliteError> {
+        if !Self::might_be_cluster(query) {
+            return Ok(None);
+        }
+        let Some(caps) = CLUSTER_PATTERN.captures(query) else {
+            return Ok(None);
+        };
+
+        let mut verbose = caps.get(2).is_some();
+        if let Some(options) = caps.get(1) {
+            for option in options.as_str().split(',').map(str::trim).filter(|o| !o.is_empty()) {
+                let mut words = option.split_whitespace();
+                let name = words.next().unwrap_or_default().to_lowercase();
+                if name != "verbose" {
+                    return Err(pg_error("42601", format!("unrecognized CLUSTER option \"{name}\"")));
+                }
+                verbose = !matches!(
+                    words.next().map(|v| v.trim_matches('\'').to_lowercase()).as_deref(),
+                    Some("false" | "off" | "0")
+                );
+            }
+        }
+
+        let target = &caps[3];
+        let (table, index) = if target.is_empty() {
+            (None, None)
+        } else if let Some(caps) = CLUSTER_TABLE_PATTERN.captures(target) {
+            (Some(qualified(caps.get(1), &caps[2])), caps.get(3).map(|index| qualified(caps.get(1), index.as_str())))
+        } else if let Some(caps) = CLUSTER_INDEX_ON_PATTERN.captures(target) {
+            (Some(qualified(caps.get(2), &caps[3])), Some(qualified(caps.get(2), &caps[1])))
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(ClusterStatement { table, index, verbose }))
+    }
+
+    pub async fn handle_cluster<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        statement: &ClusterStatement,
+    ) -> Result<(), PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        db.require_local_data("CLUSTER")?;
+        let targets = match &statement.table {
+            Some(table) => {
+                let table = table.clone();
+                let index = statement.index.clone();
+                let target = db.with_session_connection(&session.id, move |conn| Ok(Self::resolve(conn, &table, index.as_deref()))).await??;
+                vec![target]
+            }
+            None => {
+                if session.in_transaction().await {
+                    return Err(pg_error("25001", "CLUSTER cannot run inside a transaction block".to_string()));
+                }
+                db.with_session_connection(&session.id, |conn| Ok(Self::clustered_tables(conn))).await??
+            }
+        };
+
+        for (table, index) in targets {
+            if statement.verbose {
+                framed.send(BackendMessage::NoticeResponse(NoticeResponse {
+                    severity: "INFO".to_string(),
+                    code: "00000".to_string(),
+                    message: format!("clustering \"{table}\" using index scan on \"{}\"", index.name),
+                    detail: None,
+                    hint: None,
+                    position: None,
+                    where_: None,
+                })).await.map_err(PgSqliteError::Io)?;
+            }
+            Self::cluster_table(db, session, &table, &index).await?;
+        }
+        db.with_session_connection(&session.id, crate::metadata::OidAllocator::sync_relations).await?;
+
+        framed.send(BackendMessage::CommandComplete { tag: "CLUSTER".to_string() }).await
+            .map_err(PgSqliteError::Io)
+    }
+
+    async fn cluster_table(
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        table: &str,
+        index: &ClusterIndex,
+    ) -> Result<(), PgSqliteError> {
+        let progress = ProgressGuard::start(ProgressCommand::Cluster, Some(table.to_string()), "initializing");
+        progress.set_index_relname(&index.name);
+
+        let rows = db.with_session_connection(&session.id, |conn| {
+            conn.query_row(&format!("SELECT count(*) FROM {}", quote_identifier(table)), [], |row| row.get::<_, i64>(0))
+        }).await?;
+        progress.set_total(rows as u64);
+
+        // Copying, rebuilding the indexes and swapping the tables is one statement batch
+        progress.set_phase("index scanning heap");
+        db.with_session_connection(&session.id, |conn| {
+            Ok(crate::query::alter_table_handler::cluster_table(conn, table, &index.order_by).and_then(|_| {
+                conn.execute(
+                    "INSERT OR REPLACE INTO __pgsqlite_clustered_indexes (table_name, index_name) VALUES (?1, ?2)",
+                    [table, &index.name],
+                )?;
+                Ok(())
+            }))
+        }).await??;
+        progress.advance(rows as u64, 0);
+
+        debug!("Clustered {} rows of {} on {}", rows, table, index.name);
+        Ok(())
+    }
+
+    /// The table and the index to cluster it on, the one it was clustered on before when none is named
+    fn resolve(conn: &Connection, table: &str, index: Option<&str>) -> Result<(String, ClusterIndex), PgSqliteError> {
+        let Some(table) = table_name(conn, table)? else {
+            return Err(pg_error("42P01", format!("relation \"{table}\" does not exist")));
+        };
+        let indexes = table_indexes(conn, &table)?;
+        let index = match index {
+            Some(index) => indexes.into_iter()
+                .find(|candidate| candidate.name.eq_ignore_ascii_case(index))
+                .ok_or_else(|| pg_error("42704", format!("index \"{index}\" for table \"{table}\" does not exist")))?,
+            None => {
+                let recorded = recorded_index(conn, &table)?;
+                recorded.and_then(|name| indexes.into_iter().find(|candidate| candidate.name == name))
+                    .ok_or_else(|| pg_error("42704", format!("there is no previously clustered index for table \"{table}\"")))?
+            }
+        };
+        if index.partial {
+            return Err(pg_error("0A000", format!("cannot cluster on partial index \"{}\"", index.name)));
+        }
+        Ok((table, index))
+    }
+
+    /// The tables clustered before whose index still exists, for CLUSTER without a table
+    fn clustered_tables(conn: &Connection) -> Result<Vec<(String, ClusterIndex)>, PgSqliteError> {
+        if !has_clustered_indexes(conn)? {
+            return Ok(Vec::new());
+        }
+        let mut stmt = conn.prepare(
+            "SELECT table_name FROM __pgsqlite_clustered_indexes
+             WHERE table_name IN (SELECT name FROM sqlite_master WHERE type = 'table')
+             ORDER BY table_name",
+        )?;
+        let tables: Vec<String> = stmt.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+        let mut targets = Vec::new();
+        for table in tables {
+            match Self::resolve(conn, &table, None) {
+                Ok(target) => targets.push(target),
+                Err(e) => debug!("Skipped clustering {}: {}", table, e),
+            }
+        }
+        Ok(targets)
+    }
+
+    /// Carry the clustered index over to the table's new name, and with it the
+    /// names of its primary key and unique constraint indexes
+    pub fn rename_table(conn: &Connection, old_name: &str, new_name: &str) -> rusqlite::Result<()> {
+        if !has_clustered_indexes(conn)? {
+            return Ok(());
+        }
+        conn.execute(
+            "UPDATE __pgsqlite_clustered_indexes SET
+                table_name = ?2,
+                index_name = CASE
+                    WHEN index_name = ?1 || '_pkey' OR (index_name LIKE ?1 || '\\_%\\_key' ESCAPE '\\' AND index_name NOT IN (
+                        SELECT name FROM sqlite_master WHERE type = 'index' AND tbl_name = ?2
+                    )) THEN ?2 || substr(index_name, length(?1) + 1)
+                    ELSE index_name
+                END
+             WHERE table_name = ?1",
+            [old_name, new_name],
+        )?;
+        Ok(())
+    }
+
+    /// Forget clustered tables that no longer exist
+    pub fn prune_clustered_indexes(conn: &Connection) -> rusqlite::Result<()> {
+        if !has_clustered_indexes(conn)? {
+            return Ok(());
+        }
+        conn.execute(
+            "DELETE FROM __pgsqlite_clustered_indexes
+             WHERE table_name NOT IN (SELECT name FROM sqlite_master WHERE type = 'table')",
+            [],
+        )?;
+        Ok(())
+    }
+}
+
+/// The indexes of a table under the names pg_index gives them, the INTEGER PRIMARY KEY included
+fn table_indexes(conn: &Connection, table: &str) -> rusqlite::Result<Vec<ClusterIndex>> {
+    let mut stmt = conn.prepare(
+        "SELECT il.name, il.origin, il.partial, m.sql FROM pragma_index_list(?1) il
+         LEFT JOIN sqlite_master m ON m.type = 'index' AND m.name = il.name",
+    )?;
+    let listed: Vec<(String, String, bool, Option<String>)> = stmt
+        .query_map([table], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut indexes = Vec::new();
+    for (sqlite_index, origin, partial, sql) in listed {
+        let mut stmt = conn.prepare(
+            "SELECT name, desc, coll FROM pragma_index_xinfo(?1) WHERE key = 1 ORDER BY seqno",
+        )?;
+        let columns: Vec<(Option<String>, bool, String)> = stmt
+            .query_map([&sqlite_index], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        let name = match origin.as_str() {
+            "pk" => format!("{table}_pkey"),
+            "u" => format!("{table}_{}_key", columns.iter().filter_map(|(name, _, _)| name.as_deref()).collect::<Vec<_>>().join("_")),
+            _ => sqlite_index,
+        };
+        // Expression keys only appear in the index definition
+        let order_by = sql.as_deref().and_then(index_order_by).unwrap_or_else(|| {
+            columns.iter()
+                .filter_map(|(name, desc, collation)| {
+                    let name = name.as_deref()?;
+                    let collation = if collation.eq_ignore_ascii_case("BINARY") { String::new() } else { format!(" COLLATE {collation}") };
+                    Some(format!("{}{collation}{}", quote_identifier(name), if *desc { " DESC" } else { "" }))
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        });
+        indexes.push(ClusterIndex { name, partial, order_by });
+    }
+
+    // An INTEGER PRIMARY KEY is the rowid, which the rows are stored in the order of
+    if !indexes.iter().any(|index| index.name == format!("{table}_pkey")) {
+        let rowid_key: Option<String> = conn.query_row(
+            "SELECT name FROM pragma_table_info(?1) WHERE pk = 1 AND NOT EXISTS (SELECT 1 FROM pragma_table_info(?1) WHERE pk = 2)",
+            [table],
+            |row| row.get(0),
+        ).optional()?;
+        if let Some(column) = rowid_key {
+            indexes.push(ClusterIndex { name: format!("{table}_pkey"), partial: false, order_by: quote_identifier(&column) });
+        }
+    }
+    Ok(indexes)
+}
+
+/// The keys of a CREATE INDEX as an ORDER BY
+fn index_order_by(sql: &str) -> Option<String> {
+    let mut statements = Parser::parse_sql(&SQLiteDialect {}, sql).ok()?;
+    let (Some(Statement::CreateIndex(create)), None) = (statements.pop(), statements.pop()) else {
+        return None;
+    };
+    Some(create.columns.iter().map(|column| column.column.to_string()).collect::<Vec<_>>().join(", "))
+}
+
+fn recorded_index(conn: &Connection, table: &str) -> rusqlite::Result<Option<String>> {
+    if !has_clustered_indexes(conn)? {
+        return Ok(None);
+    }
+    conn.query_row("SELECT index_name FROM __pgsqlite_clustered_indexes WHERE table_name = ?1", [table], |row| row.get(0))
+        .optional()
+}
+
+fn has_clustered_indexes(conn: &Connection) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '__pgsqlite_clustered_indexes')",
+        [],
+        |row| row.get(0),
+    )
+}
+
+/// The SQLite name of a table or index, in the schema the table was qualified with
+fn qualified(schema: Option<regex::Match<'_>>, name: &str) -> String {
+    match schema {
+        Some(schema) => sqlite_name(&format!("{}.{name}", schema.as_str())),
+        None => sqlite_name(name),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(query: &str) -> Option<ClusterStatement> {
+        ClusterHandler::parse_cluster(query).unwrap()
+    }
+
+    fn statement(table: Option<&str>, index: Option<&str>, verbose: bool) -> Option<ClusterStatement> {
+        Some(ClusterStatement { table: table.map(str::to_string), index: index.map(str::to_string), verbose })
+    }
+
+    #[test]
+    fn test_parse_cluster() {
+        assert_eq!(parse("CLUSTER events USING events_at_idx;"), statement(Some("events"), Some("events_at_idx"), false));
+        assert_eq!(parse("cluster verbose public.\"Events\""), statement(Some("Events"), None, true));
+        assert_eq!(parse("CLUSTER (VERBOSE) events_at_idx ON events"), statement(Some("events"), Some("events_at_idx"), true));
+        assert_eq!(parse("CLUSTER"), statement(None, None, false));
+        assert_eq!(parse("CLUSTER tenant1.events USING events_at_idx"), statement(Some("tenant1__events"), Some("tenant1__events_at_idx"), false));
+        assert_eq!(parse("CLUSTERED"), None);
+        assert_eq!(parse("SELECT 1"), None);
+        assert!(ClusterHandler::parse_cluster("CLUSTER (ANALYZE) events").is_err());
+    }
+
+    #[test]
+    fn test_table_indexes() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE events (id INTEGER PRIMARY KEY, at TEXT, kind TEXT UNIQUE, deleted INTEGER);
+             CREATE INDEX events_at_idx ON events (lower(at) DESC, kind);
+             CREATE INDEX events_live_idx ON events (at) WHERE deleted = 0;",
+        ).unwrap();
+        let mut indexes = table_indexes(&conn, "events").unwrap();
+        indexes.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(indexes, vec![
+            ClusterIndex { name: "events_at_idx".to_string(), partial: false, order_by: "lower(at) DESC, kind".to_string() },
+            ClusterIndex { name: "events_kind_key".to_string(), partial: false, order_by: "\"kind\"".to_string() },
+            ClusterIndex { name: "events_live_idx".to_string(), partial: true, order_by: "at".to_string() },
+            ClusterIndex { name: "events_pkey".to_string(), partial: false, order_by: "\"id\"".to_string() },
+        ]);
+    }
+}
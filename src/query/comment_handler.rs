@@ -0,0 +1,287 @@
+use crate::error::PgError;
+use crate::protocol::BackendMessage;
+use crate::query::sql_utils::{split_qualified, unqualified, unquote_identifier};
+use crate::session::{SessionState, SessionStorage};
+use crate::PgSqliteError;
+use futures::SinkExt;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rusqlite::{Connection, OptionalExtension};
+use std::sync::Arc;
+use tokio_util::codec::Framed;
+use tracing::debug;
+
+static COMMENT_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)^\s*COMMENT\s+ON\s+(TABLE|VIEW|MATERIALIZED\s+VIEW|INDEX|SEQUENCE|COLUMN|FUNCTION|SCHEMA|DATABASE|CONSTRAINT)\s+(.+?)\s+IS\s+(NULL|'(?:[^']|'')*')\s*;?\s*$").unwrap()
+});
+
+static CONSTRAINT_TARGET: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)^(.+?)\s+ON\s+(.+)$").unwrap()
+});
+
+/// Handles `COMMENT ON <object> IS '<text>' | NULL`.
+///
+/// Comments are stored in pg_description under the OIDs the catalog views report,
+/// so col_description(), obj_description() and tools reading pg_description find
+/// them. A NULL or empty comment removes the existing one.
+pub struct CommentHandler;
+
+/// The object a comment is attached to
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommentTarget {
+    /// Table, view, index or sequence
+    Relation(String),
+    Column { table: String, column: String },
+    /// Argument types are only counted; None when the statement names no argument list
+    Function { name: String, arg_count: Option<usize> },
+    Schema(String),
+    Database(String),
+    Constraint { name: String, table: String },
+}
+
+/// A parsed COMMENT ON statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommentStatement {
+    pub target: CommentTarget,
+    pub comment: Option<String>,
+}
+
+impl CommentHandler {
+    /// Whether a query starts with COMMENT
+    pub fn might_be_comment(query: &str) -> bool {
+        query.trim_start().get(..7).is_some_and(|prefix| prefix.eq_ignore_ascii_case("COMMENT"))
+    }
+
+    pub fn parse_comment(query: &str) -> Result<Option<CommentStatement>, PgSqliteError> {
+        if !Self::might_be_comment(query) {
+            return Ok(None);
+        }
+        let Some(caps) = COMMENT_PATTERN.captures(query) else {
+            return Err(syntax_error("syntax error in COMMENT statement"));
+        };
+
+        let object = caps[2].trim();
+        let target = match caps[1].to_uppercase().split_whitespace().collect::<Vec<_>>().join(" ").as_str() {
+            "COLUMN" => {
+                let mut parts: Vec<String> = split_qualified(object).into_iter().map(unquote_identifier).collect();
+                let column = parts.pop().filter(|_| !parts.is_empty())
+                    .ok_or_else(|| syntax_error("column name must be qualified"))?;
+                let table = parts.pop().unwrap_or_default();
+                CommentTarget::Column { table, column }
+            }
+            "FUNCTION" => {
+                let (name, arg_count) = match object.split_once('(') {
+                    Some((name, args)) => {
+                        let args = args.trim_end().strip_suffix(')')
+                            .ok_or_else(|| syntax_error("syntax error in function argument list"))?;
+                        let count = if args.trim().is_empty() { 0 } else { args.split(',').count() };
+                        (name.trim(), Some(count))
+                    }
+                    None => (object, None),
+                };
+                CommentTarget::Function { name: unqualified(name), arg_count }
+            }
+            "SCHEMA" => CommentTarget::Schema(unquote_identifier(object)),
+            "DATABASE" => CommentTarget::Database(unquote_identifier(object)),
+            "CONSTRAINT" => {
+                let parts = CONSTRAINT_TARGET.captures(object)
+                    .ok_or_else(|| syntax_error("syntax error in COMMENT ON CONSTRAINT"))?;
+                CommentTarget::Constraint { name: unquote_identifier(parts[1].trim()), table: unqualified(parts[2].trim()) }
+            }
+            _ => CommentTarget::Relation(unqualified(object)),
+        };
+
+        let comment = match &caps[3] {
+            literal if literal.eq_ignore_ascii_case("NULL") => None,
+            literal => Some(literal[1..literal.len() - 1].replace("''", "'")),
+        };
+        Ok(Some(CommentStatement { target, comment: comment.filter(|c| !c.is_empty()) }))
+    }
+
+    pub async fn handle_comment<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        statement: &CommentStatement,
+    ) -> Result<(), PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        let stored = db.with_session_connection(&session.id, |conn| {
+            let object = match resolve_target(conn, &statement.target)? {
+                Ok(object) => object,
+                Err(error) => return Ok(Err(error)),
+            };
+            let (objoid, classoid, objsubid) = object;
+            match &statement.comment {
+                Some(comment) => conn.execute(
+                    "INSERT OR REPLACE INTO pg_description (objoid, classoid, objsubid, description) VALUES (?1, ?2, ?3, ?4)",
+                    rusqlite::params![objoid, classoid, objsubid, comment],
+                )?,
+                None => conn.execute(
+                    "DELETE FROM pg_description WHERE objoid = ?1 AND classoid = ?2 AND objsubid = ?3",
+                    rusqlite::params![objoid, classoid, objsubid],
+                )?,
+            };
+            Ok(Ok(()))
+        }).await?;
+        stored.map_err(PgSqliteError::Validation)?;
+
+        debug!("Stored comment on {:?}", statement.target);
+        framed.send(BackendMessage::CommandComplete {
+            tag: "COMMENT".to_string(),
+        }).await.map_err(PgSqliteError::Io)?;
+
+        Ok(())
+    }
+
+    /// Drop the comments of relations that no longer exist
+    pub fn prune_relation_comments(conn: &Connection) -> rusqlite::Result<()> {
+        let has_descriptions: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'pg_description')",
+            [],
+            |row| row.get(0),
+        )?;
+        if has_descriptions {
+            conn.execute(
+                "DELETE FROM pg_description WHERE classoid = 1259 AND objoid NOT IN (SELECT CAST(oid AS INTEGER) FROM pg_class)",
+                [],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// `(objoid, classoid, objsubid)` of the commented object, or the error to report
+type ResolvedTarget = Result<(i64, i64, i64), PgError>;
+
+fn resolve_target(conn: &Connection, target: &CommentTarget) -> rusqlite::Result<ResolvedTarget> {
+    let relation_oid = |name: &str| -> rusqlite::Result<Option<i64>> {
+        conn.query_row("SELECT CAST(oid AS INTEGER) FROM pg_class WHERE relname = ?1 COLLATE NOCASE", [name], |row| row.get(0))
+            .optional()
+    };
+    let missing_relation = |name: &str| PgError::Generic {
+        code: "42P01".to_string(),
+        message: format!("relation \"{name}\" does not exist"),
+    };
+
+    Ok(match target {
+        CommentTarget::Relation(name) => match relation_oid(name)? {
+            Some(oid) => Ok((oid, 1259, 0)),
+            None => Err(missing_relation(name)),
+        },
+        CommentTarget::Column { table, column } => {
+            let Some(oid) = relation_oid(table)? else {
+                return Ok(Err(missing_relation(table)));
+            };
+            let attnum: Option<i64> = conn.query_row(
+                "SELECT cid + 1 FROM pragma_table_info(?1) WHERE name = ?2 COLLATE NOCASE",
+                [table, column],
+                |row| row.get(0),
+            ).optional()?;
+            match attnum {
+                Some(attnum) => Ok((oid, 1259, attnum)),
+                None => Err(PgError::Generic {
+                    code: "42703".to_string(),
+                    message: format!("column \"{column}\" of relation \"{table}\" does not exist"),
+                }),
+            }
+        }
+        CommentTarget::Function { name, arg_count } => {
+            let mut stmt = conn.prepare(
+                "SELECT CAST(oid AS INTEGER) FROM pg_proc WHERE proname = ?1 COLLATE NOCASE AND (?2 IS NULL OR pronargs = ?2)",
+            )?;
+            let oids: Vec<i64> = stmt.query_map(rusqlite::params![name, arg_count.map(|n| n as i64)], |row| row.get(0))?
+                .collect::<rusqlite::Result<_>>()?;
+            match oids.as_slice() {
+                [oid] => Ok((*oid, 1255, 0)),
+                [] => Err(PgError::Generic {
+                    code: "42883".to_string(),
+                    message: format!("function {name} does not exist"),
+                }),
+                _ => Err(PgError::Generic {
+                    code: "42725".to_string(),
+                    message: format!("function name \"{name}\" is not unique"),
+                }),
+            }
+        }
+        CommentTarget::Schema(name) => match name.to_lowercase().as_str() {
+            "public" => Ok((2200, 2615, 0)),
+            "pg_catalog" => Ok((11, 2615, 0)),
+            _ => Err(PgError::Generic {
+                code: "3F000".to_string(),
+                message: format!("schema \"{name}\" does not exist"),
+            }),
+        },
+        CommentTarget::Database(name) => {
+            let oid: Option<i64> = conn.query_row(
+                "SELECT CAST(oid AS INTEGER) FROM pg_database WHERE datname = ?1",
+                [name],
+                |row| row.get(0),
+            ).optional()?;
+            match oid {
+                Some(oid) => Ok((oid, 1262, 0)),
+                None => Err(PgError::Generic {
+                    code: "3D000".to_string(),
+                    message: format!("database \"{name}\" does not exist"),
+                }),
+            }
+        }
+        CommentTarget::Constraint { name, table } => {
+            let Some(table_oid) = relation_oid(table)? else {
+                return Ok(Err(missing_relation(table)));
+            };
+            let oid: Option<i64> = conn.query_row(
+                "SELECT CAST(oid AS INTEGER) FROM pg_constraint WHERE conname = ?1 COLLATE NOCASE AND CAST(conrelid AS INTEGER) = ?2",
+                rusqlite::params![name, table_oid],
+                |row| row.get(0),
+            ).optional()?;
+            match oid {
+                Some(oid) => Ok((oid, 2606, 0)),
+                None => Err(PgError::Generic {
+                    code: "42704".to_string(),
+                    message: format!("constraint \"{name}\" for table \"{table}\" does not exist"),
+                }),
+            }
+        }
+    })
+}
+
+fn syntax_error(message: &str) -> PgSqliteError {
+    PgSqliteError::Validation(PgError::Generic {
+        code: "42601".to_string(),
+        message: message.to_string(),
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(query: &str) -> CommentStatement {
+        CommentHandler::parse_comment(query).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_parse_comment() {
+        assert_eq!(parse("COMMENT ON TABLE public.Orders IS 'Customer orders'"), CommentStatement {
+            target: CommentTarget::Relation("Orders".to_string()),
+            comment: Some("Customer orders".to_string()),
+        });
+        assert_eq!(parse("comment on column orders.\"Total\" is 'It''s the sum';"), CommentStatement {
+            target: CommentTarget::Column { table: "orders".to_string(), column: "Total".to_string() },
+            comment: Some("It's the sum".to_string()),
+        });
+        assert_eq!(parse("COMMENT ON FUNCTION split_part(text, text, integer) IS NULL"), CommentStatement {
+            target: CommentTarget::Function { name: "split_part".to_string(), arg_count: Some(3) },
+            comment: None,
+        });
+        assert_eq!(parse("COMMENT ON CONSTRAINT orders_pkey ON public.orders IS ''").target,
+            CommentTarget::Constraint { name: "orders_pkey".to_string(), table: "orders".to_string() });
+        assert_eq!(parse("COMMENT ON MATERIALIZED  VIEW totals IS 'x'").target, CommentTarget::Relation("totals".to_string()));
+
+        assert!(CommentHandler::parse_comment("COMMENT ON COLUMN total IS 'x'").is_err());
+        assert_eq!(CommentHandler::parse_comment("SELECT 'COMMENT'").unwrap(), None);
+    }
+}
@@ -5,15 +5,22 @@
 /// Strip SQL comments from a query
 /// 
 /// Removes both single-line (--) and multi-line (/* */) comments
-/// while preserving string literals and their contents.
+/// while preserving string literals, dollar-quoted bodies and their contents.
 pub fn strip_sql_comments(query: &str) -> String {
     let mut result = String::with_capacity(query.len());
-    let mut chars = query.chars().peekable();
+    let mut chars = query.char_indices().peekable();
     let mut in_string = false;
     let mut string_delimiter = '\0';
     
-    while let Some(ch) = chars.next() {
+    while let Some((pos, ch)) = chars.next() {
         match ch {
+            // Function bodies keep their comments, which belong to the body
+            '$' if !in_string && dollar_quote_len(query, pos).is_some() => {
+                let end = dollar_quote_len(query, pos).map_or(query.len(), |len| pos + len);
+                result.push_str(&query[pos..end]);
+                while chars.next_if(|&(next, _)| next < end).is_some() {}
+            }
+
             // Handle string literals
             '\'' | '"' if !in_string => {
                 in_string = true;
@@ -22,10 +29,10 @@ pub fn strip_sql_comments(query: &str) -> String {
             }
             ch if ch == string_delimiter && in_string => {
                 // Check for escaped quotes
-                if chars.peek() == Some(&ch) {
+                if chars.peek().map(|&(_, next)| next) == Some(ch) {
                     // Escaped quote, consume both
                     result.push(ch);
-                    result.push(chars.next().unwrap());
+                    result.push(chars.next().unwrap().1);
                 } else {
                     // End of string
                     in_string = false;
@@ -35,21 +42,21 @@ pub fn strip_sql_comments(query: &str) -> String {
             }
             
             // Handle comments only outside of strings
-            '-' if !in_string && chars.peek() == Some(&'-') => {
+            '-' if !in_string && chars.peek().map(|&(_, next)| next) == Some('-') => {
                 // Single-line comment, skip to end of line
                 chars.next(); // consume second '-'
-                for c in chars.by_ref() {
+                for (_, c) in chars.by_ref() {
                     if c == '\n' {
                         result.push('\n'); // preserve line break
                         break;
                     }
                 }
             }
-            '/' if !in_string && chars.peek() == Some(&'*') => {
+            '/' if !in_string && chars.peek().map(|&(_, next)| next) == Some('*') => {
                 // Multi-line comment, skip until */
                 chars.next(); // consume '*'
                 let mut prev_char = '\0';
-                for c in chars.by_ref() {
+                for (_, c) in chars.by_ref() {
                     if prev_char == '*' && c == '/' {
                         break;
                     }
@@ -67,6 +74,69 @@ pub fn strip_sql_comments(query: &str) -> String {
     result
 }
 
+/// Split a query string into its statements at the semicolons outside string
+/// literals, quoted identifiers, comments and dollar-quoted bodies. Statements are
+/// trimmed and empty ones dropped.
+pub fn split_statements(query: &str) -> Vec<&str> {
+    let bytes = query.as_bytes();
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            quote @ (b'\'' | b'"') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    i += 1;
+                }
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = query[i + 2..].find("*/").map_or(bytes.len(), |end| i + 2 + end + 1);
+            }
+            b'$' => {
+                if let Some(len) = dollar_quote_len(query, i) {
+                    i += len;
+                    continue;
+                }
+            }
+            b';' => {
+                statements.push(query[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    statements.push(query[start..].trim());
+    statements.retain(|statement| !statement.is_empty());
+    statements
+}
+
+/// Length of the dollar-quoted string (`$$...$$` or `$tag$...$tag$`) starting at
+/// byte `start`, up to the end of the query when it is not closed; None when no
+/// dollar quote starts there, as for the `$1` of a parameter
+fn dollar_quote_len(query: &str, start: usize) -> Option<usize> {
+    let is_tag_byte = |b: &u8| b.is_ascii_alphanumeric() || *b == b'_' || *b >= 0x80;
+    let bytes = query.as_bytes();
+    // `$` inside an identifier such as a$b$ does not open a quote
+    if start > 0 && (is_tag_byte(&bytes[start - 1]) || bytes[start - 1] == b'$') {
+        return None;
+    }
+    let rest = &bytes[start + 1..];
+    let tag_len = rest.iter().position(|b| !is_tag_byte(b))?;
+    if rest[tag_len] != b'$' || rest.first().is_some_and(u8::is_ascii_digit) {
+        return None;
+    }
+    let tag = &query[start..start + tag_len + 2];
+    let body_start = start + tag.len();
+    Some(query[body_start..].find(tag).map_or(query.len() - start, |end| tag.len() + end + tag.len()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -177,4 +247,25 @@ WHERE active = true
             "SELECT * FROM users WHERE count "
         );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_dollar_quoted_bodies_keep_comments() {
+        let query = "CREATE FUNCTION f() RETURNS trigger AS $$ -- don't touch\nBEGIN RETURN NEW; END $$ LANGUAGE plpgsql -- done";
+        assert_eq!(
+            strip_sql_comments(query),
+            "CREATE FUNCTION f() RETURNS trigger AS $$ -- don't touch\nBEGIN RETURN NEW; END $$ LANGUAGE plpgsql "
+        );
+        assert_eq!(strip_sql_comments("SELECT $1, a$b$ -- x"), "SELECT $1, a$b$ ");
+    }
+
+    #[test]
+    fn test_split_statements() {
+        assert_eq!(split_statements("SELECT 1; SELECT ';'; ;"), vec!["SELECT 1", "SELECT ';'"]);
+        assert_eq!(
+            split_statements("CREATE FUNCTION f() RETURNS trigger AS $body$ BEGIN NEW.a := 1; RETURN NEW; END $body$ LANGUAGE plpgsql; SELECT $1"),
+            vec!["CREATE FUNCTION f() RETURNS trigger AS $body$ BEGIN NEW.a := 1; RETURN NEW; END $body$ LANGUAGE plpgsql", "SELECT $1"]
+        );
+        assert_eq!(split_statements("SELECT 1 /* ; */ -- ;\n; \"a;b\""), vec!["SELECT 1 /* ; */ -- ;", "\"a;b\""]);
+        assert!(split_statements(";").is_empty());
+    }
+}
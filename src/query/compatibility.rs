@@ -0,0 +1,169 @@
+use crate::protocol::BackendMessage;
+use crate::protocol::messages::NoticeResponse;
+use crate::session::SessionState;
+use crate::translator::LockingClauseTranslator;
+use crate::PgSqliteError;
+use futures::SinkExt;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::sync::Arc;
+use tokio_util::codec::Framed;
+use tracing::warn;
+
+static COLLATE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)\bCOLLATE\s+("(?:[^"]|"")+"|[\w.]+)"#).unwrap()
+});
+
+/// CREATE TABLE enforces EXCLUDE constraints; ALTER TABLE can't add them
+static ALTER_TABLE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^\s*ALTER\s+TABLE\b").unwrap()
+});
+
+static EXCLUDE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\bEXCLUDE\s+(?:USING\s+\w+\s*)?\(").unwrap()
+});
+
+/// Collations SQLite knows: its built-ins plus the ones pgsqlite registers
+const SQLITE_COLLATIONS: [&str; 4] = ["binary", "nocase", "rtrim", "uuid"];
+
+/// How features that pgsqlite only approximates are reported, set with
+/// `--strict-compatibility` and per session with `SET pgsqlite.strict_compatibility`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StrictCompatibility {
+    /// Approximate silently
+    #[default]
+    Off,
+    /// Approximate, and send a WARNING notice for each approximation
+    Warn,
+    /// Reject the statement with feature_not_supported
+    Error,
+}
+
+impl StrictCompatibility {
+    /// The `--strict-compatibility` setting, used by sessions that haven't set their own
+    pub fn server_default() -> Self {
+        static SERVER_DEFAULT: Lazy<StrictCompatibility> = Lazy::new(|| {
+            StrictCompatibility::from_setting(&crate::config::CONFIG.strict_compatibility).unwrap_or_default()
+        });
+        *SERVER_DEFAULT
+    }
+
+    pub fn from_setting(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "off" | "false" | "no" | "0" => Some(Self::Off),
+            "warn" | "warning" => Some(Self::Warn),
+            "error" | "on" | "true" | "yes" | "1" => Some(Self::Error),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Warn => "warn",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// Detects PostgreSQL features that pgsqlite accepts but does not reproduce:
+/// row locking clauses that are dropped, collations SQLite doesn't have, and
+/// constraints SQLite doesn't support. With strict compatibility off, none of
+/// this is checked.
+pub struct CompatibilityCheck;
+
+impl CompatibilityCheck {
+    /// Describe each approximated feature the statement uses
+    pub fn degradations(query: &str) -> Vec<String> {
+        let mut found = Vec::new();
+
+        if let Some(clause) = LockingClauseTranslator::locking_clause(query) {
+            found.push(format!("{clause} is ignored: SQLite has no row-level locks"));
+        }
+
+        for caps in COLLATE_PATTERN.captures_iter(query) {
+            let collation = caps[1].trim_matches('"').replace("\"\"", "\"");
+            let name = collation.rsplit('.').next().unwrap_or(&collation).to_lowercase();
+            if !SQLITE_COLLATIONS.contains(&name.as_str()) {
+                found.push(format!("COLLATE \"{collation}\" is not supported: SQLite only provides BINARY, NOCASE and RTRIM"));
+            }
+        }
+
+        if ALTER_TABLE_PATTERN.is_match(query) && EXCLUDE_PATTERN.is_match(query) {
+            found.push("EXCLUDE constraints can only be added in CREATE TABLE".to_string());
+        }
+
+        found
+    }
+
+    /// Apply the session's strict compatibility mode to a statement before it runs
+    pub async fn enforce<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        session: &Arc<SessionState>,
+        query: &str,
+    ) -> Result<(), PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        let mode = session.strict_compatibility();
+        if mode == StrictCompatibility::Off {
+            return Ok(());
+        }
+
+        let degradations = Self::degradations(query);
+        if mode == StrictCompatibility::Error
+            && let Some(first) = degradations.first() {
+            return Err(PgSqliteError::Validation(crate::error::PgError::Generic {
+                code: "0A000".to_string(),
+                message: format!("{first} (pgsqlite.strict_compatibility = error)"),
+            }));
+        }
+
+        for degradation in degradations {
+            warn!("strict compatibility [{}]: {} in {}", session.id, degradation, query);
+            framed.send(BackendMessage::NoticeResponse(NoticeResponse {
+                severity: "WARNING".to_string(),
+                code: "01000".to_string(),
+                message: degradation,
+                detail: None,
+                hint: None,
+                position: None,
+                where_: None,
+            })).await.map_err(PgSqliteError::Io)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_degradations() {
+        assert_eq!(
+            CompatibilityCheck::degradations("SELECT * FROM t WHERE id = 1 FOR UPDATE SKIP LOCKED"),
+            vec!["FOR UPDATE is ignored: SQLite has no row-level locks".to_string()]
+        );
+        assert_eq!(
+            CompatibilityCheck::degradations("SELECT name FROM t ORDER BY name COLLATE \"en_US\", code COLLATE nocase"),
+            vec!["COLLATE \"en_US\" is not supported: SQLite only provides BINARY, NOCASE and RTRIM".to_string()]
+        );
+        assert_eq!(
+            CompatibilityCheck::degradations("ALTER TABLE bookings ADD CONSTRAINT no_overlap EXCLUDE USING gist (room WITH =, during WITH &&)"),
+            vec!["EXCLUDE constraints can only be added in CREATE TABLE".to_string()]
+        );
+        assert!(CompatibilityCheck::degradations("CREATE TABLE bookings (room INTEGER, during TSRANGE, EXCLUDE USING gist (room WITH =, during WITH &&))").is_empty());
+        // Foreign keys are enforced
+        assert!(CompatibilityCheck::degradations("CREATE TABLE orders (id INTEGER PRIMARY KEY, user_id INTEGER REFERENCES users(id))").is_empty());
+        assert!(CompatibilityCheck::degradations("SELECT * FROM orders o JOIN users u ON u.id = o.user_id").is_empty());
+    }
+
+    #[test]
+    fn test_strict_compatibility_setting() {
+        assert_eq!(StrictCompatibility::from_setting("WARN"), Some(StrictCompatibility::Warn));
+        assert_eq!(StrictCompatibility::from_setting("on"), Some(StrictCompatibility::Error));
+        assert_eq!(StrictCompatibility::from_setting("off"), Some(StrictCompatibility::Off));
+        assert_eq!(StrictCompatibility::from_setting("sometimes"), None);
+    }
+}
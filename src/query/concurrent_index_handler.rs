@@ -0,0 +1,349 @@
+use crate::protocol::{BackendMessage, NoticeResponse};
+use crate::query::audit_handler::{sqlite_name, table_name};
+use crate::query::progress::{ProgressCommand, ProgressGuard};
+use crate::query::sql_utils::{fold_identifier, last_name_part, quote_identifier};
+use crate::query::trigger_handler::pg_error;
+use crate::session::{SessionState, SessionStorage};
+use crate::PgSqliteError;
+use futures::SinkExt;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OptionalExtension};
+use sqlparser::ast::{Expr, Statement};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use std::sync::Arc;
+use tokio_util::codec::Framed;
+use tracing::debug;
+
+static CONCURRENTLY_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\bINDEX\s+(CONCURRENTLY\s+)").unwrap()
+});
+
+static DROP_INDEX_CONCURRENTLY_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)^\s*DROP\s+INDEX\s+CONCURRENTLY\s+(IF\s+EXISTS\s+)?(.*?)(?:\s+(CASCADE|RESTRICT))?\s*;?\s*$").unwrap()
+});
+
+/// Rows read per batch while the table is scanned ahead of the build
+const SCAN_BATCH_ROWS: i64 = 10_000;
+
+/// A `CREATE INDEX CONCURRENTLY` or `DROP INDEX CONCURRENTLY` statement
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConcurrentIndexStatement {
+    Create(ConcurrentIndex),
+    /// The DROP INDEX SQLite runs
+    Drop { sql: String },
+}
+
+/// The index a `CREATE INDEX CONCURRENTLY` builds
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConcurrentIndex {
+    pub name: String,
+    /// Whether the statement named no index, so the name is PostgreSQL's
+    /// `<table>_<keys>_idx`, numbered when taken
+    pub generated_name: bool,
+    pub table: String,
+    pub unique: bool,
+    pub if_not_exists: bool,
+    /// The indexed columns and expressions, without their sort order
+    pub keys: Vec<String>,
+    pub predicate: Option<String>,
+    /// The CREATE INDEX SQLite runs
+    pub sql: String,
+}
+
+/// Handles `CREATE [UNIQUE] INDEX CONCURRENTLY` and `DROP INDEX CONCURRENTLY`.
+///
+/// SQLite builds an index in one write transaction and can't build one in pieces, so
+/// writers of every table still wait for the whole CREATE INDEX. What a concurrent
+/// build adds comes before it, without the write lock: the table is read in batches of
+/// rowids, yielding between them, which only leaves its pages cached for the build, and
+/// a unique index is checked for duplicate keys rather than failing at the end of the
+/// build. Like in PostgreSQL neither statement runs inside a transaction block, and a
+/// build in progress shows up in pg_stat_progress_create_index.
+pub struct ConcurrentIndexHandler;
+
+impl ConcurrentIndexHandler {
+    /// Whether a query mentions CONCURRENTLY, which CREATE and DROP INDEX need to come here
+    pub fn might_be_concurrent_index(query: &str) -> bool {
+        query.as_bytes().windows(12).any(|w| w.eq_ignore_ascii_case(b"CONCURRENTLY"))
+    }
+
+    /// Parse a CREATE INDEX CONCURRENTLY or a DROP INDEX CONCURRENTLY
+    pub fn parse_concurrent_index(query: &str) -> Result<Option<ConcurrentIndexStatement>, PgSqliteError> {
+        if !Self::might_be_concurrent_index(query) {
+            return Ok(None);
+        }
+        if let Some(caps) = DROP_INDEX_CONCURRENTLY_PATTERN.captures(query) {
+            if caps.get(3).is_some_and(|option| option.as_str().eq_ignore_ascii_case("CASCADE")) {
+                return Err(pg_error("0A000", "DROP INDEX CONCURRENTLY does not support CASCADE".to_string()));
+            }
+            if caps[2].contains(',') {
+                return Err(pg_error("0A000", "DROP INDEX CONCURRENTLY does not support dropping multiple objects".to_string()));
+            }
+            let if_exists = if caps.get(1).is_some() { "IF EXISTS " } else { "" };
+            return Ok(Some(ConcurrentIndexStatement::Drop { sql: format!("DROP INDEX {if_exists}{}", &caps[2]) }));
+        }
+
+        let Ok(mut statements) = Parser::parse_sql(&PostgreSqlDialect {}, query) else {
+            return Ok(None);
+        };
+        let (Some(Statement::CreateIndex(create)), None) = (statements.pop(), statements.pop()) else {
+            return Ok(None);
+        };
+        if !create.concurrently {
+            return Ok(None);
+        }
+        let Some(concurrently) = CONCURRENTLY_PATTERN.captures(query).and_then(|caps| caps.get(1)) else {
+            return Ok(None);
+        };
+        let (name, generated_name) = match &create.name {
+            Some(name) => (sqlite_name(&name.to_string()), false),
+            None => {
+                let table = fold_identifier(last_name_part(&create.table_name.to_string()));
+                let keys: Vec<String> = create.columns.iter().map(|column| key_name(&column.column.expr)).collect();
+                (format!("{table}_{}_idx", keys.join("_")), true)
+            }
+        };
+        // The generated name goes where the statement would have named the index
+        let named = if generated_name { format!("{} ", quote_identifier(&name)) } else { String::new() };
+        Ok(Some(ConcurrentIndexStatement::Create(ConcurrentIndex {
+            name,
+            generated_name,
+            table: sqlite_name(&create.table_name.to_string()),
+            unique: create.unique,
+            if_not_exists: create.if_not_exists,
+            keys: create.columns.iter().map(|column| column.column.expr.to_string()).collect(),
+            predicate: create.predicate.map(|predicate| predicate.to_string()),
+            sql: format!("{}{named}{}", &query[..concurrently.start()], &query[concurrently.end()..]),
+        })))
+    }
+
+    pub async fn handle_concurrent_index<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        statement: &ConcurrentIndexStatement,
+    ) -> Result<(), PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        let (command, tag) = match statement {
+            ConcurrentIndexStatement::Create(_) => ("CREATE INDEX CONCURRENTLY", "CREATE INDEX"),
+            ConcurrentIndexStatement::Drop { .. } => ("DROP INDEX CONCURRENTLY", "DROP INDEX"),
+        };
+        db.require_local_data(command)?;
+        if session.in_transaction().await {
+            return Err(pg_error("25001", format!("{command} cannot run inside a transaction block")));
+        }
+
+        let changed = match statement {
+            ConcurrentIndexStatement::Create(index) => Self::build_index(framed, db, session, index).await?,
+            ConcurrentIndexStatement::Drop { sql } => {
+                db.execute_with_session(sql, &session.id).await?;
+                true
+            }
+        };
+        if changed {
+            db.with_session_connection(&session.id, crate::metadata::OidAllocator::sync_relations).await?;
+        }
+
+        framed.send(BackendMessage::CommandComplete { tag: tag.to_string() }).await
+            .map_err(PgSqliteError::Io)
+    }
+
+    /// Scan, check and build, returning false when IF NOT EXISTS found the name taken
+    async fn build_index<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        index: &ConcurrentIndex,
+    ) -> Result<bool, PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        let progress = ProgressGuard::start(ProgressCommand::CreateIndex, Some(index.table.clone()), "initializing");
+
+        let prepared = db.with_session_connection(&session.id, |conn| Ok(Self::prepare(conn, index))).await??;
+        let Some((index, table, rowid, rows)) = prepared else {
+            framed.send(BackendMessage::NoticeResponse(NoticeResponse {
+                severity: "NOTICE".to_string(),
+                code: "00000".to_string(),
+                message: format!("relation \"{}\" already exists, skipping", index.name),
+                detail: None,
+                hint: None,
+                position: None,
+                where_: None,
+            })).await.map_err(PgSqliteError::Io)?;
+            return Ok(false);
+        };
+        progress.set_index_relname(&index.name);
+        progress.set_total(rows);
+
+        // Each batch is a read of its own, and writers get their turn in between
+        if rowid {
+            progress.set_phase("building index: scanning table");
+            let batch = format!("SELECT count(*), max(rowid) FROM (SELECT rowid FROM {} WHERE rowid > ?1 ORDER BY rowid LIMIT ?2)", quote_identifier(&table));
+            let mut last = i64::MIN;
+            loop {
+                let (count, max) = db.with_session_connection(&session.id, |conn| {
+                    conn.query_row(&batch, [last, SCAN_BATCH_ROWS], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Option<i64>>(1)?)))
+                }).await?;
+                progress.advance(count as u64, 0);
+                match max {
+                    Some(max) if count == SCAN_BATCH_ROWS => last = max,
+                    _ => break,
+                }
+                tokio::task::yield_now().await;
+            }
+        }
+
+        if index.unique {
+            progress.set_phase("building index: checking uniqueness");
+            let duplicate = db.with_session_connection(&session.id, |conn| Ok(Self::find_duplicate(conn, &table, &index))).await?;
+            if let Some(key) = duplicate {
+                return Err(pg_error("23505", format!(
+                    "could not create unique index \"{}\": Key ({})=({}) is duplicated.",
+                    index.name,
+                    index.keys.join(", "),
+                    key.join(", "),
+                )));
+            }
+        }
+
+        progress.set_phase("building index: loading tuples in tree");
+        db.execute_with_session(&index.sql, &session.id).await?;
+        debug!("Built index {} on {} concurrently", index.name, table);
+        Ok(true)
+    }
+
+    /// The index with its name settled, the table's SQLite name, whether it has rowids,
+    /// and its row count; None when IF NOT EXISTS finds the index name taken
+    fn prepare(conn: &Connection, index: &ConcurrentIndex) -> Result<Option<(ConcurrentIndex, String, bool, u64)>, PgSqliteError> {
+        let taken = |name: &str| conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE name = ?1 COLLATE NOCASE)",
+            [name],
+            |row| row.get::<_, bool>(0),
+        );
+        let mut index = index.clone();
+        if index.generated_name {
+            let mut name = index.name.clone();
+            let mut suffix = 0;
+            while taken(&name)? {
+                suffix += 1;
+                name = format!("{}{suffix}", index.name);
+            }
+            if suffix > 0 {
+                index.sql = index.sql.replacen(&quote_identifier(&index.name), &quote_identifier(&name), 1);
+                index.name = name;
+            }
+        } else if taken(&index.name)? {
+            if index.if_not_exists {
+                return Ok(None);
+            }
+            return Err(pg_error("42P07", format!("relation \"{}\" already exists", index.name)));
+        }
+        let Some(table) = table_name(conn, &index.table)? else {
+            return Err(pg_error("42P01", format!("relation \"{}\" does not exist", index.table)));
+        };
+        let without_rowid: bool = conn.query_row(
+            "SELECT wr FROM pragma_table_list WHERE schema = 'main' AND name = ?1",
+            [&table],
+            |row| row.get(0),
+        ).optional()?.unwrap_or(false);
+        let rows: i64 = conn.query_row(&format!("SELECT count(*) FROM {}", quote_identifier(&table)), [], |row| row.get(0))?;
+        Ok(Some((index, table, !without_rowid, rows as u64)))
+    }
+
+    /// A key more than one row has, as text; NULL keys are never duplicates. A check
+    /// SQLite can't run is skipped, the build reports the duplicates then.
+    fn find_duplicate(conn: &Connection, table: &str, index: &ConcurrentIndex) -> Option<Vec<String>> {
+        let keys = index.keys.join(", ");
+        let mut conditions: Vec<String> = index.keys.iter().map(|key| format!("({key}) IS NOT NULL")).collect();
+        conditions.extend(index.predicate.iter().map(|predicate| format!("({predicate})")));
+        let sql = format!(
+            "SELECT {keys} FROM {} WHERE {} GROUP BY {keys} HAVING count(*) > 1 LIMIT 1",
+            quote_identifier(table),
+            conditions.join(" AND "),
+        );
+        let found = conn.query_row(&sql, [], |row| {
+            (0..index.keys.len()).map(|i| row.get_ref(i).map(key_text)).collect::<rusqlite::Result<Vec<_>>>()
+        }).optional();
+        match found {
+            Ok(key) => key,
+            Err(e) => {
+                debug!("Skipped the duplicate check of index {}: {}", index.name, e);
+                None
+            }
+        }
+    }
+}
+
+/// What PostgreSQL names an index key in a generated index name: its column, the
+/// function it calls, or `expr`
+fn key_name(key: &Expr) -> String {
+    match key {
+        Expr::Identifier(ident) => fold_identifier(&ident.to_string()),
+        Expr::CompoundIdentifier(idents) => idents.last().map_or_else(String::new, |ident| fold_identifier(&ident.to_string())),
+        Expr::Function(function) => fold_identifier(last_name_part(&function.name.to_string())),
+        _ => "expr".to_string(),
+    }
+}
+
+fn key_text(value: ValueRef<'_>) -> String {
+    match value {
+        ValueRef::Null => "null".to_string(),
+        ValueRef::Integer(i) => i.to_string(),
+        ValueRef::Real(f) => f.to_string(),
+        ValueRef::Text(text) => String::from_utf8_lossy(text).into_owned(),
+        ValueRef::Blob(blob) => format!("\\x{}", hex::encode(blob)),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(query: &str) -> Option<ConcurrentIndexStatement> {
+        ConcurrentIndexHandler::parse_concurrent_index(query).unwrap()
+    }
+
+    #[test]
+    fn test_parse_concurrent_index() {
+        assert_eq!(
+            parse("CREATE UNIQUE INDEX CONCURRENTLY IF NOT EXISTS \"Orders_Email\" ON public.orders (lower(email), id DESC) WHERE deleted_at IS NULL;"),
+            Some(ConcurrentIndexStatement::Create(ConcurrentIndex {
+                name: "Orders_Email".to_string(),
+                generated_name: false,
+                table: "orders".to_string(),
+                unique: true,
+                if_not_exists: true,
+                keys: vec!["lower(email)".to_string(), "id".to_string()],
+                predicate: Some("deleted_at IS NULL".to_string()),
+                sql: "CREATE UNIQUE INDEX IF NOT EXISTS \"Orders_Email\" ON public.orders (lower(email), id DESC) WHERE deleted_at IS NULL;".to_string(),
+            }))
+        );
+        assert_eq!(
+            parse("drop index concurrently if exists orders_email"),
+            Some(ConcurrentIndexStatement::Drop { sql: "DROP INDEX IF EXISTS orders_email".to_string() })
+        );
+        assert_eq!(parse("CREATE INDEX orders_email ON orders (email)"), None);
+        assert_eq!(
+            parse("create index concurrently on public.Orders (email, lower(\"Name\"), (a + b))"),
+            Some(ConcurrentIndexStatement::Create(ConcurrentIndex {
+                name: "orders_email_lower_expr_idx".to_string(),
+                generated_name: true,
+                table: "orders".to_string(),
+                unique: false,
+                if_not_exists: false,
+                keys: vec!["email".to_string(), "lower(\"Name\")".to_string(), "(a + b)".to_string()],
+                predicate: None,
+                sql: "create index \"orders_email_lower_expr_idx\" on public.Orders (email, lower(\"Name\"), (a + b))".to_string(),
+            }))
+        );
+        assert!(ConcurrentIndexHandler::parse_concurrent_index("DROP INDEX CONCURRENTLY a, b").is_err());
+        assert!(ConcurrentIndexHandler::parse_concurrent_index("DROP INDEX CONCURRENTLY a CASCADE").is_err());
+    }
+}
@@ -0,0 +1,444 @@
+use crate::error::PgError;
+use crate::protocol::BackendMessage;
+use crate::session::{SessionState, SessionStorage};
+use crate::types::datetime_utils::{format_days_to_date, format_microseconds_to_time, format_microseconds_to_timestamp};
+use crate::PgSqliteError;
+use futures::SinkExt;
+use crate::query::progress::{ProgressCommand, ProgressGuard};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rusqlite::types::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio_util::codec::Framed;
+use tracing::debug;
+
+/// Rows read per statement when exporting a table. Every chunk is a separate
+/// read, so a slow client never keeps one snapshot open for the whole export.
+pub const COPY_CHUNK_ROWS: usize = 1000;
+
+static COPY_TO_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^\s*COPY\s+(?:\((.+)\)|([\w."]+)\s*(?:\(([^()]*)\))?)\s+TO\s+STDOUT\b\s*(.*?)\s*;?\s*$"#).unwrap()
+});
+
+static OPTION_TOKEN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"'(?:[^']|'')*'|[^\s(),']+").unwrap()
+});
+
+/// Handles `COPY ... TO STDOUT`.
+///
+/// Tables are streamed in rowid-ordered chunks with a flush and a yield point
+/// between them, and every running export is visible in pg_stat_progress_copy.
+pub struct CopyHandler;
+
+/// What a COPY TO statement reads from
+#[derive(Debug, Clone, PartialEq)]
+pub enum CopySource {
+    Table { name: String, columns: Vec<String> },
+    Query(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CopyFormat {
+    Text,
+    Csv,
+}
+
+/// A parsed `COPY ... TO STDOUT` statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct CopyToStatement {
+    pub source: CopySource,
+    pub format: CopyFormat,
+    pub header: bool,
+    pub delimiter: String,
+    pub null: String,
+    pub quote: String,
+}
+
+impl CopyHandler {
+    /// Whether a query starts with COPY
+    pub fn might_be_copy(query: &str) -> bool {
+        query.trim_start().get(..4).is_some_and(|prefix| prefix.eq_ignore_ascii_case("COPY"))
+    }
+
+    /// Parse `COPY table [(columns)] TO STDOUT [options]` or `COPY (query) TO STDOUT [options]`
+    pub fn parse_copy_to(query: &str) -> Result<Option<CopyToStatement>, PgSqliteError> {
+        if !Self::might_be_copy(query) {
+            return Ok(None);
+        }
+        let Some(caps) = COPY_TO_PATTERN.captures(query) else {
+            return Ok(None);
+        };
+
+        let source = match caps.get(1) {
+            Some(inner) => CopySource::Query(inner.as_str().trim().to_string()),
+            None => {
+                let name = caps[2].trim_matches('"');
+                let name = name.strip_prefix("public.").unwrap_or(name).trim_matches('"').to_string();
+                let columns = caps.get(3)
+                    .map(|list| list.as_str().split(',').map(|c| c.trim().trim_matches('"').to_string()).collect())
+                    .unwrap_or_default();
+                CopySource::Table { name, columns }
+            }
+        };
+
+        let mut statement = CopyToStatement {
+            source,
+            format: CopyFormat::Text,
+            header: false,
+            delimiter: "\t".to_string(),
+            null: "\\N".to_string(),
+            quote: "\"".to_string(),
+        };
+        let mut delimiter = None;
+        let mut null = None;
+
+        // Options are accepted both as WITH (FORMAT csv, HEADER) and in the older CSV HEADER form
+        let tokens: Vec<&str> = OPTION_TOKEN.find_iter(&caps[4]).map(|m| m.as_str()).collect();
+        let mut tokens = tokens.into_iter()
+            .filter(|token| !token.eq_ignore_ascii_case("WITH") && !token.eq_ignore_ascii_case("AS"))
+            .peekable();
+        while let Some(token) = tokens.next() {
+            match token.to_uppercase().as_str() {
+                "FORMAT" => match tokens.next().map(|value| Self::unquote(value).to_lowercase()).as_deref() {
+                    Some("text") => statement.format = CopyFormat::Text,
+                    Some("csv") => statement.format = CopyFormat::Csv,
+                    Some("binary") => return Err(Self::binary_not_supported()),
+                    other => return Err(PgSqliteError::Validation(PgError::Generic {
+                        code: "22023".to_string(),
+                        message: format!("COPY format \"{}\" not recognized", other.unwrap_or_default()),
+                    })),
+                },
+                "CSV" => statement.format = CopyFormat::Csv,
+                "BINARY" => return Err(Self::binary_not_supported()),
+                "HEADER" => {
+                    statement.header = true;
+                    if let Some(value) = tokens.peek() {
+                        match value.to_lowercase().as_str() {
+                            "true" | "on" | "1" => { tokens.next(); }
+                            "false" | "off" | "0" => { tokens.next(); statement.header = false; }
+                            _ => {}
+                        }
+                    }
+                }
+                "DELIMITER" => delimiter = tokens.next().map(Self::unquote),
+                "NULL" => null = tokens.next().map(Self::unquote),
+                "QUOTE" => if let Some(quote) = tokens.next() { statement.quote = Self::unquote(quote) },
+                _ => return Err(PgSqliteError::Validation(PgError::SyntaxError {
+                    message: format!("option \"{}\" not recognized", token.to_lowercase()),
+                    position: None,
+                })),
+            }
+        }
+
+        if statement.format == CopyFormat::Csv {
+            statement.delimiter = ",".to_string();
+            statement.null = String::new();
+        }
+        if let Some(delimiter) = delimiter {
+            if delimiter.chars().count() != 1 {
+                return Err(PgSqliteError::Validation(PgError::Generic {
+                    code: "0A000".to_string(),
+                    message: "COPY delimiter must be a single one-byte character".to_string(),
+                }));
+            }
+            statement.delimiter = delimiter;
+        }
+        if let Some(null) = null {
+            statement.null = null;
+        }
+
+        Ok(Some(statement))
+    }
+
+    /// Stream the rows as CopyData messages and finish with `COPY n`
+    pub async fn handle_copy_to<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        statement: &CopyToStatement,
+    ) -> Result<(), PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        db.require_local_data("COPY TO")?;
+        // Masked columns are exported as roles other than --admin-users read them, see MaskHandler
+        let masked_query;
+        let source = match &statement.source {
+            CopySource::Query(query) => {
+                masked_query = CopySource::Query(crate::query::MaskHandler::rewrite(db, session, query).await?.into_owned());
+                &masked_query
+            }
+            source => source,
+        };
+        let (columns, pg_types) = Self::resolve_columns(db, session, source).await?;
+        let relname = match source {
+            CopySource::Table { name, .. } => Some(name.clone()),
+            CopySource::Query(_) => None,
+        };
+        let progress = ProgressGuard::start(ProgressCommand::Copy, relname, "COPY TO");
+
+        framed.send(BackendMessage::CopyOutResponse {
+            format: 0,
+            column_formats: vec![0; columns.len()],
+        }).await.map_err(PgSqliteError::Io)?;
+
+        if statement.header && statement.format == CopyFormat::Csv {
+            let header: Vec<Option<String>> = columns.iter().map(|c| Some(c.clone())).collect();
+            framed.feed(BackendMessage::CopyData(Self::encode_line(&header, statement))).await
+                .map_err(PgSqliteError::Io)?;
+        }
+
+        let mut total = 0u64;
+        match source {
+            CopySource::Table { name, .. } => {
+                let masks = crate::query::MaskHandler::column_expressions(db, session, name).await?;
+                let select = format!(
+                    "SELECT rowid, {} FROM \"{}\" WHERE rowid > ?1 ORDER BY rowid LIMIT {}",
+                    columns.iter()
+                        .map(|c| masks.get(&c.to_lowercase()).cloned().unwrap_or_else(|| format!("\"{c}\"")))
+                        .collect::<Vec<_>>().join(", "),
+                    name,
+                    COPY_CHUNK_ROWS,
+                );
+                let mut last_rowid = i64::MIN;
+                loop {
+                    let chunk = db.with_session_connection(&session.id, |conn| {
+                        let mut stmt = conn.prepare(&select)?;
+                        let rows = stmt.query_map([last_rowid], |row| {
+                            let rowid: i64 = row.get(0)?;
+                            let values = (1..=columns.len()).map(|i| row.get::<_, Value>(i)).collect::<rusqlite::Result<Vec<_>>>()?;
+                            Ok((rowid, values))
+                        })?;
+                        rows.collect::<rusqlite::Result<Vec<_>>>()
+                    }).await?;
+
+                    let chunk_len = chunk.len();
+                    if let Some((rowid, _)) = chunk.last() {
+                        last_rowid = *rowid;
+                    }
+                    let rows = chunk.into_iter().map(|(_, values)| values).collect();
+                    total += Self::send_rows(framed, rows, &pg_types, statement, &progress).await?;
+
+                    if chunk_len < COPY_CHUNK_ROWS {
+                        break;
+                    }
+                    // Let the client drain what was sent before reading the next chunk
+                    tokio::task::yield_now().await;
+                }
+            }
+            CopySource::Query(query) => {
+                let rows = db.with_session_connection(&session.id, |conn| {
+                    let mut stmt = conn.prepare(query)?;
+                    let column_count = stmt.column_count();
+                    let rows = stmt.query_map([], |row| {
+                        (0..column_count).map(|i| row.get::<_, Value>(i)).collect::<rusqlite::Result<Vec<_>>>()
+                    })?;
+                    rows.collect::<rusqlite::Result<Vec<_>>>()
+                }).await?;
+                total += Self::send_rows(framed, rows, &pg_types, statement, &progress).await?;
+            }
+        }
+
+        debug!("COPY TO STDOUT sent {} rows", total);
+        framed.send(BackendMessage::CopyDone).await.map_err(PgSqliteError::Io)?;
+        framed.send(BackendMessage::CommandComplete {
+            tag: format!("COPY {total}"),
+        }).await.map_err(PgSqliteError::Io)?;
+
+        Ok(())
+    }
+
+    /// Output column names and their PostgreSQL types (empty when unknown)
+    async fn resolve_columns(
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        source: &CopySource,
+    ) -> Result<(Vec<String>, Vec<String>), PgSqliteError> {
+        match source {
+            CopySource::Table { name, columns } => {
+                let (table_columns, schema_types) = db.with_session_connection(&session.id, |conn| {
+                    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?1) ORDER BY cid")?;
+                    let table_columns = stmt.query_map([name], |row| row.get::<_, String>(0))?
+                        .collect::<rusqlite::Result<Vec<_>>>()?;
+                    let mut stmt = conn.prepare("SELECT column_name, pg_type FROM __pgsqlite_schema WHERE table_name = ?1")?;
+                    let schema_types = stmt.query_map([name], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+                        .collect::<rusqlite::Result<HashMap<_, _>>>()?;
+                    Ok((table_columns, schema_types))
+                }).await?;
+
+                if table_columns.is_empty() {
+                    return Err(PgSqliteError::Validation(PgError::Generic {
+                        code: "42P01".to_string(),
+                        message: format!("relation \"{name}\" does not exist"),
+                    }));
+                }
+
+                let columns = if columns.is_empty() { table_columns } else {
+                    for column in columns {
+                        if !table_columns.iter().any(|c| c.eq_ignore_ascii_case(column)) {
+                            return Err(PgSqliteError::Validation(PgError::Generic {
+                                code: "42703".to_string(),
+                                message: format!("column \"{column}\" of relation \"{name}\" does not exist"),
+                            }));
+                        }
+                    }
+                    columns.clone()
+                };
+                let pg_types = columns.iter()
+                    .map(|column| schema_types.iter()
+                        .find(|(name, _)| name.eq_ignore_ascii_case(column))
+                        .map(|(_, pg_type)| pg_type.to_lowercase())
+                        .unwrap_or_default())
+                    .collect();
+                Ok((columns, pg_types))
+            }
+            CopySource::Query(query) => {
+                let columns = db.with_session_connection(&session.id, |conn| {
+                    let stmt = conn.prepare(query)?;
+                    Ok(stmt.column_names().into_iter().map(String::from).collect::<Vec<_>>())
+                }).await?;
+                let pg_types = vec![String::new(); columns.len()];
+                Ok((columns, pg_types))
+            }
+        }
+    }
+
+    /// Queue one CopyData message per row, then flush so the rows leave before the next read
+    async fn send_rows<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        rows: Vec<Vec<Value>>,
+        pg_types: &[String],
+        statement: &CopyToStatement,
+        progress: &ProgressGuard,
+    ) -> Result<u64, PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        let count = rows.len() as u64;
+        let mut bytes = 0u64;
+        for row in rows {
+            let fields: Vec<Option<String>> = row.into_iter().enumerate()
+                .map(|(i, value)| Self::format_value(value, pg_types.get(i).map(String::as_str).unwrap_or_default()))
+                .collect();
+            let line = Self::encode_line(&fields, statement);
+            bytes += line.len() as u64;
+            framed.feed(BackendMessage::CopyData(line)).await.map_err(PgSqliteError::Io)?;
+        }
+        framed.flush().await.map_err(PgSqliteError::Io)?;
+        progress.advance(count, bytes);
+        Ok(count)
+    }
+
+    /// Render a stored value the way PostgreSQL prints its type
+    fn format_value(value: Value, pg_type: &str) -> Option<String> {
+        let base_type = pg_type.split('(').next().unwrap_or(pg_type).trim();
+        match value {
+            Value::Null => None,
+            Value::Integer(i) => Some(match base_type {
+                "boolean" | "bool" => if i != 0 { "t" } else { "f" }.to_string(),
+                "date" => format_days_to_date(i),
+                "time" | "time without time zone" => format_microseconds_to_time(i),
+                "timestamp" | "timestamp without time zone" => format_microseconds_to_timestamp(i),
+                _ => i.to_string(),
+            }),
+            Value::Real(f) => Some(f.to_string()),
+            Value::Text(text) => Some(text),
+            Value::Blob(blob) => Some(format!("\\x{}", hex::encode(blob))),
+        }
+    }
+
+    /// Encode one row in text or CSV format, including the trailing newline
+    pub fn encode_line(fields: &[Option<String>], statement: &CopyToStatement) -> Vec<u8> {
+        let mut line = String::new();
+        for (i, field) in fields.iter().enumerate() {
+            if i > 0 {
+                line.push_str(&statement.delimiter);
+            }
+            match (field, statement.format) {
+                (None, _) => line.push_str(&statement.null),
+                (Some(value), CopyFormat::Text) => {
+                    for c in value.chars() {
+                        match c {
+                            '\\' => line.push_str("\\\\"),
+                            '\n' => line.push_str("\\n"),
+                            '\r' => line.push_str("\\r"),
+                            '\t' => line.push_str("\\t"),
+                            c if statement.delimiter.starts_with(c) => {
+                                line.push('\\');
+                                line.push(c);
+                            }
+                            c => line.push(c),
+                        }
+                    }
+                }
+                (Some(value), CopyFormat::Csv) => {
+                    let needs_quotes = *value == statement.null
+                        || value.contains(statement.delimiter.as_str())
+                        || value.contains(statement.quote.as_str())
+                        || value.contains(['\n', '\r']);
+                    if needs_quotes {
+                        let doubled = format!("{0}{0}", statement.quote);
+                        line.push_str(&statement.quote);
+                        line.push_str(&value.replace(statement.quote.as_str(), &doubled));
+                        line.push_str(&statement.quote);
+                    } else {
+                        line.push_str(value);
+                    }
+                }
+            }
+        }
+        line.push('\n');
+        line.into_bytes()
+    }
+
+    fn unquote(token: &str) -> String {
+        token.strip_prefix('\'')
+            .and_then(|t| t.strip_suffix('\''))
+            .map(|t| t.replace("''", "'"))
+            .unwrap_or_else(|| token.to_string())
+    }
+
+    fn binary_not_supported() -> PgSqliteError {
+        PgSqliteError::NotSupported("COPY TO with FORMAT binary".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(query: &str) -> CopyToStatement {
+        CopyHandler::parse_copy_to(query).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_parse_copy_to() {
+        let statement = parse("COPY public.users (id, \"name\") TO STDOUT");
+        assert_eq!(statement.source, CopySource::Table { name: "users".to_string(), columns: vec!["id".to_string(), "name".to_string()] });
+        assert_eq!((statement.format, statement.header, statement.delimiter.as_str(), statement.null.as_str()), (CopyFormat::Text, false, "\t", "\\N"));
+
+        let statement = parse("COPY users TO STDOUT WITH (FORMAT csv, HEADER true, DELIMITER ';', NULL 'NULL');");
+        assert_eq!((statement.format, statement.header, statement.delimiter.as_str(), statement.null.as_str()), (CopyFormat::Csv, true, ";", "NULL"));
+
+        let statement = parse("copy (SELECT id FROM users WHERE id > 1) to stdout with csv header");
+        assert_eq!(statement.source, CopySource::Query("SELECT id FROM users WHERE id > 1".to_string()));
+        assert_eq!((statement.format, statement.header, statement.delimiter.as_str()), (CopyFormat::Csv, true, ","));
+
+        assert!(CopyHandler::parse_copy_to("COPY users FROM STDIN").unwrap().is_none());
+        assert!(CopyHandler::parse_copy_to("SELECT 1").unwrap().is_none());
+        assert!(CopyHandler::parse_copy_to("COPY users TO STDOUT (FORMAT binary)").is_err());
+        assert!(CopyHandler::parse_copy_to("COPY users TO STDOUT (DELIMITER '||')").is_err());
+    }
+
+    #[test]
+    fn test_encode_line() {
+        let fields = vec![Some("a\tb\\c".to_string()), None, Some("say \"hi\", bye".to_string()), Some(String::new())];
+
+        let text = parse("COPY t TO STDOUT");
+        assert_eq!(CopyHandler::encode_line(&fields, &text), b"a\\tb\\\\c\t\\N\tsay \"hi\", bye\t\n".to_vec());
+
+        let csv = parse("COPY t TO STDOUT (FORMAT csv)");
+        assert_eq!(CopyHandler::encode_line(&fields, &csv), b"a\tb\\c,,\"say \"\"hi\"\", bye\",\"\"\n".to_vec());
+    }
+}
@@ -0,0 +1,125 @@
+use crate::session::SessionState;
+use crate::PgSqliteError;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Structured events for `--log-format json`, for log shippers such as Logstash or the
+/// Datadog agent.
+///
+/// Each connection logs `connection_open` and `connection_close`, and each statement
+/// `query_start` and `query_end`, or `query_error` with its SQLSTATE. They carry the
+/// session's UUID and backend pid, which clients get in BackendKeyData and from
+/// pg_backend_pid(), and statements a per-session `statement_id` that ties a statement's
+/// events together. The events are logged at INFO, errors at WARN, and not at all with
+/// text logs, which keep their usual messages.
+pub struct EventLog;
+
+/// The `query_start` of a statement, for its `query_end`
+pub struct StatementEvent {
+    statement_id: u64,
+}
+
+impl EventLog {
+    pub fn enabled() -> bool {
+        crate::config::CONFIG.log_format == "json"
+    }
+
+    /// Log `connection_open`; the returned guard logs `connection_close` when dropped,
+    /// however the connection ends
+    pub fn connection_opened(session: &SessionState, client: &str, application_name: Option<&str>) -> Option<ConnectionEvents> {
+        if !Self::enabled() {
+            return None;
+        }
+        info!(
+            target: "pgsqlite::events",
+            event = "connection_open",
+            session_id = %session.id,
+            pid = session.backend_pid,
+            user = %session.user,
+            database = %session.database,
+            client = %client,
+            application_name = application_name.unwrap_or(""),
+            "connection opened"
+        );
+        Some(ConnectionEvents { session_id: session.id, pid: session.backend_pid, opened: Instant::now() })
+    }
+
+    /// Log `query_start` for a statement about to run
+    pub fn statement_started(session: &SessionState, query: &str) -> Option<StatementEvent> {
+        if !Self::enabled() {
+            return None;
+        }
+        let statement_id = session.next_statement_id();
+        info!(
+            target: "pgsqlite::events",
+            event = "query_start",
+            session_id = %session.id,
+            pid = session.backend_pid,
+            statement_id,
+            query = %query,
+            "query started"
+        );
+        Some(StatementEvent { statement_id })
+    }
+
+    /// Log `query_end` with the rows the statement's command tags reported, or `query_error`
+    /// with the SQLSTATE and message the client gets
+    pub fn statement_finished(
+        session: &SessionState,
+        statement: Option<StatementEvent>,
+        elapsed: Duration,
+        rows: u64,
+        result: &Result<(), PgSqliteError>,
+    ) {
+        let Some(StatementEvent { statement_id }) = statement else {
+            return;
+        };
+        let duration_ms = elapsed.as_secs_f64() * 1000.0;
+        match result {
+            Ok(()) => info!(
+                target: "pgsqlite::events",
+                event = "query_end",
+                session_id = %session.id,
+                pid = session.backend_pid,
+                statement_id,
+                duration_ms,
+                rows,
+                "query finished"
+            ),
+            Err(e) => {
+                let err = e.to_error_response("42000", "Query execution failed");
+                warn!(
+                    target: "pgsqlite::events",
+                    event = "query_error",
+                    session_id = %session.id,
+                    pid = session.backend_pid,
+                    statement_id,
+                    duration_ms,
+                    sqlstate = %err.code,
+                    error = %err.message,
+                    "query failed"
+                );
+            }
+        }
+    }
+}
+
+/// Logs a session's `connection_close` when dropped
+pub struct ConnectionEvents {
+    session_id: uuid::Uuid,
+    pid: i32,
+    opened: Instant,
+}
+
+impl Drop for ConnectionEvents {
+    fn drop(&mut self) {
+        info!(
+            target: "pgsqlite::events",
+            event = "connection_close",
+            session_id = %self.session_id,
+            pid = self.pid,
+            duration_ms = self.opened.elapsed().as_secs_f64() * 1000.0,
+            "connection closed"
+        );
+    }
+}
@@ -1,18 +1,21 @@
 use crate::protocol::{BackendMessage, FieldDescription};
-use crate::session::{DbHandler, SessionState, QueryRouter};
-use crate::translator::{JsonTranslator, ReturningTranslator, BatchUpdateTranslator, BatchDeleteTranslator, FtsTranslator};
+use crate::session::{SessionState, SessionStorage, QueryRouter};
+use crate::translator::{JsonTranslator, ReturningTranslator};
 use crate::types::PgType;
-use crate::cache::{RowDescriptionKey, GLOBAL_ROW_DESCRIPTION_CACHE};
-use crate::metadata::EnumTriggers;
+use crate::cache::RowDescriptionKey;
+use crate::metadata::{EnumTriggers, IdentityColumns};
 use crate::PgSqliteError;
+use crate::ddl::ForeignKeys;
+use crate::protocol::messages::NoticeResponse;
 use crate::query::join_type_inference::build_column_to_table_mapping;
+use crate::query::progress::{ProgressCommand, ProgressGuard};
+use crate::query::query_trace::QueryTrace;
 use tokio_util::codec::Framed;
 use futures::SinkExt;
 use tokio::io::AsyncWriteExt;
 use tracing::{info, debug};
 use std::sync::Arc;
 use rusqlite::params;
-use serde_json;
 use std::collections::HashMap;
 use parking_lot::RwLock;
 use once_cell::sync::Lazy;
@@ -32,7 +35,7 @@ static TABLE_SCHEMA_CACHE: Lazy<RwLock<HashMap<String, TableSchemaInfo>>> =
     Lazy::new(|| RwLock::new(HashMap::new()));
 
 /// Get all schema information for a table in one query
-async fn get_table_schema_info(table_name: &str, db: &Arc<DbHandler>, session_id: &Uuid) -> TableSchemaInfo {
+async fn get_table_schema_info(table_name: &str, db: &Arc<dyn SessionStorage>, session_id: &Uuid) -> TableSchemaInfo {
     // Check cache first
     {
         let cache = TABLE_SCHEMA_CACHE.read();
@@ -107,6 +110,11 @@ async fn get_table_schema_info(table_name: &str, db: &Arc<DbHandler>, session_id
     schema_info
 }
 
+/// Drop the cached schema information of a table or view whose columns changed
+pub(crate) fn forget_table_schema_info(table_name: &str) {
+    TABLE_SCHEMA_CACHE.write().remove(table_name);
+}
+
 
 /// Create a command complete tag with optimized static strings for common cases
 fn create_command_tag(operation: &str, rows_affected: usize) -> String {
@@ -130,7 +138,7 @@ impl QueryExecutor {
     /// Get cached connection or fetch and cache it
     async fn get_or_cache_connection(
         session: &Arc<SessionState>,
-        db: &Arc<DbHandler>
+        db: &Arc<dyn SessionStorage>
     ) -> Option<Arc<parking_lot::Mutex<rusqlite::Connection>>> {
         // First check if we have a cached connection
         if let Some(cached) = session.get_cached_connection() {
@@ -140,7 +148,7 @@ impl QueryExecutor {
         
         // Try to get connection from manager and cache it
         // debug!("Connection not cached for session {}, fetching from manager", session.id);
-        if let Some(conn_arc) = db.connection_manager().get_connection_arc(&session.id) {
+        if let Some(conn_arc) = db.session_connection(&session.id) {
             session.cache_connection(conn_arc.clone());
             // debug!("Cached connection for session {}", session.id);
             Some(conn_arc)
@@ -151,7 +159,7 @@ impl QueryExecutor {
     }
     pub async fn execute_query<T>(
         framed: &mut Framed<T, crate::protocol::PostgresCodec>,
-        db: &Arc<DbHandler>,
+        db: &Arc<dyn SessionStorage>,
         session: &Arc<SessionState>,
         query: &str,
         query_router: Option<&Arc<QueryRouter>>,
@@ -197,11 +205,8 @@ impl QueryExecutor {
         // Check if query contains multiple statements
         let trimmed = query_to_execute.trim();
         if trimmed.contains(';') {
-            // Split by semicolon and execute each statement
-            let statements: Vec<&str> = trimmed.split(';')
-                .map(|s| s.trim())
-                .filter(|s| !s.is_empty())
-                .collect();
+            // Split at the semicolons between statements, not those inside literals or function bodies
+            let statements = crate::query::split_statements(trimmed);
             
             // Handle empty query case (just semicolon) - SQLAlchemy uses ";" for ping
             if statements.is_empty() {
@@ -214,9 +219,13 @@ impl QueryExecutor {
             
             if statements.len() > 1 {
                 debug!("Query contains {} statements", statements.len());
+                // Scripts such as schema dumps show up in pg_stat_progress_import while they run
+                let progress = ProgressGuard::start(ProgressCommand::Import, None, "executing statements");
+                progress.set_total(statements.len() as u64);
                 for (i, stmt) in statements.iter().enumerate() {
                     debug!("Executing statement {}: {}", i + 1, stmt);
                     Self::execute_single_statement(framed, db, session, stmt, query_router).await?;
+                    progress.advance(1, stmt.len() as u64);
                 }
                 return Ok(());
             }
@@ -228,7 +237,46 @@ impl QueryExecutor {
     
     async fn execute_single_statement<T>(
         framed: &mut Framed<T, crate::protocol::PostgresCodec>,
-        db: &Arc<DbHandler>,
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        query: &str,
+        query_router: Option<&Arc<QueryRouter>>,
+    ) -> Result<(), PgSqliteError> 
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send,
+    {
+        let trace = session.trace_enabled();
+        if trace {
+            QueryTrace::emit(framed, session, format!("statement: {query}")).await?;
+        }
+        framed.codec_mut().take_completed_rows();
+        crate::session::backend_registry::query_started(session.backend_pid, query, session.statement_timeout());
+        let event = crate::query::event_log::EventLog::statement_started(session, query);
+        let started = std::time::Instant::now();
+        let mut result = Self::run_single_statement(framed, db, session, query, query_router).await;
+        framed.codec_mut().report_updates_as(None);
+        if crate::session::backend_registry::query_finished(session.backend_pid) {
+            result = result.map_err(PgSqliteError::into_statement_timeout);
+        }
+        // The statement's transaction ended: txid_current() hands out a new id, and transaction-level advisory locks are released
+        if !session.in_transaction().await {
+            crate::session::backend_registry::transaction_finished(session.backend_pid);
+        }
+        crate::cache::CatalogCache::statement_finished(session, query);
+        let rows = framed.codec_mut().take_completed_rows();
+        if result.is_ok() {
+            crate::query::statement_stats::record(query, started.elapsed(), rows);
+        }
+        crate::query::event_log::EventLog::statement_finished(session, event, started.elapsed(), rows, &result);
+        if trace {
+            QueryTrace::finished(framed, session, started, &result).await?;
+        }
+        result
+    }
+    
+    async fn run_single_statement<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        db: &Arc<dyn SessionStorage>,
         session: &Arc<SessionState>,
         query: &str,
         query_router: Option<&Arc<QueryRouter>>,
@@ -248,10 +296,97 @@ impl QueryExecutor {
                 ));
             }
         }
+        // A --read-only server refuses writes before anything rewrites them
+        crate::session::read_only_handler::reject_writes(query)?;
+        // schema.table names become the SQLite names of the tables, see SchemaHandler
+        let query = &*crate::query::SchemaHandler::qualify_names(db, session, query).await?;
+        // Deleted rows of soft-delete tables are skipped and DELETE marks rows deleted, see SoftDeleteHandler
+        let query = &*crate::query::SoftDeleteHandler::rewrite(db, session, query).await?;
+        if crate::query::SoftDeleteHandler::is_soft_delete(query) {
+            framed.codec_mut().report_updates_as(Some("DELETE"));
+        }
+        // Masked columns read through pgsqlite_mask() for roles other than --admin-users, see MaskHandler
+        let query = &*crate::query::MaskHandler::rewrite(db, session, query).await?;
+        // GENERATED ALWAYS columns refuse explicit values, see IdentityColumns::check_insert
+        if matches!(crate::query::QueryTypeDetector::detect_query_type(query), crate::query::QueryType::Insert) {
+            db.with_session_connection(&session.id, |conn| Ok(crate::metadata::IdentityColumns::check_insert(conn, query))).await??;
+        }
+        // Strict compatibility mode reports what the translators below would approximate
+        crate::query::CompatibilityCheck::enforce(framed, session, query).await?;
+        // Standalone pg_sleep() is awaited here rather than blocking inside SQLite
+        if let Some(sleep_call) = crate::query::SleepHandler::parse_sleep_call(query) {
+            return crate::query::SleepHandler::handle_sleep(framed, session.backend_pid, &sleep_call, false).await;
+        }
+        // So are waits for an advisory lock
+        if let Some(lock_call) = crate::query::AdvisoryLockHandler::parse_lock_call(query) {
+            return crate::query::AdvisoryLockHandler::handle_lock(framed, session, &lock_call, false).await;
+        }
+        // COPY ... TO STDOUT streams its rows as CopyData messages
+        if let Some(copy) = crate::query::CopyHandler::parse_copy_to(query)? {
+            return crate::query::CopyHandler::handle_copy_to(framed, db, session, &copy).await;
+        }
+        if let Some(vacuum) = crate::query::VacuumHandler::parse_vacuum(query)? {
+            return crate::query::VacuumHandler::handle_vacuum(framed, db, session, &vacuum).await;
+        }
+        if let Some(cluster) = crate::query::ClusterHandler::parse_cluster(query)? {
+            return crate::query::ClusterHandler::handle_cluster(framed, db, session, &cluster).await;
+        }
+        if let Some(comment) = crate::query::CommentHandler::parse_comment(query)? {
+            return crate::query::CommentHandler::handle_comment(framed, db, session, &comment).await;
+        }
+        // MERGE runs as an UPDATE, DELETE and INSERT per clause, see MergeHandler
+        if let Some(merge) = crate::query::MergeHandler::parse_merge(query)? {
+            return crate::query::MergeHandler::handle_merge(framed, db, session, &merge).await;
+        }
+        if let Some(schema) = crate::query::SchemaHandler::parse_schema(query) {
+            return crate::query::SchemaHandler::handle_schema(framed, db, session, &schema).await;
+        }
+        if let Some(view) = crate::query::ViewHandler::parse_view(query) {
+            return crate::query::ViewHandler::handle_view(framed, db, session, &view).await;
+        }
+        if let Some(trigger) = crate::query::TriggerHandler::parse_trigger(query)? {
+            return crate::query::TriggerHandler::handle_trigger(framed, db, session, &trigger).await;
+        }
+        if let Some(function) = crate::query::FunctionHandler::parse_function(query)? {
+            return crate::query::FunctionHandler::handle_function(framed, db, session, &function).await;
+        }
+        if let Some(alter) = crate::query::AlterTableHandler::parse_alter_table(query) {
+            return crate::query::AlterTableHandler::handle_alter_table(framed, db, session, &alter).await;
+        }
+        // pgsqlite.translate('...') explains a query instead of running it
+        if let Some(explained) = crate::query::TranslateHandler::parse_translate_call(query) {
+            return crate::query::TranslateHandler::handle_translate(framed, db, session, &explained, false).await;
+        }
+        // pgsqlite.enable_audit('...') creates the table's audit table and triggers
+        if let Some(table) = crate::query::AuditHandler::parse_enable_audit_call(query) {
+            return crate::query::AuditHandler::handle_enable_audit(framed, db, session, &table, false).await;
+        }
+        // pgsqlite.enable_soft_delete('...') and pgsqlite.disable_soft_delete('...') set which tables soft-delete
+        if let Some(call) = crate::query::SoftDeleteHandler::parse_soft_delete_call(query) {
+            return crate::query::SoftDeleteHandler::handle_soft_delete_call(framed, db, session, &call, false, false).await;
+        }
+        // pgsqlite.set_mask('...', '...', '...') and pgsqlite.drop_mask('...', '...') set which columns are masked
+        if let Some(call) = crate::query::MaskHandler::parse_mask_call(query) {
+            return crate::query::MaskHandler::handle_mask_call(framed, db, session, &call, false, false).await;
+        }
+        // pgsqlite.set_retention(...), pgsqlite.drop_retention('...') and pgsqlite.run_retention() manage retention policies
+        if let Some(call) = crate::query::RetentionHandler::parse_retention_call(query) {
+            return crate::query::RetentionHandler::handle_retention_call(framed, db, session, &call, false, false).await;
+        }
+        // pgsqlite.schema_snapshot(['...']) saves the schema, pgsqlite.schema_diff(a, b) compares two saved schemas
+        if let Some(call) = crate::query::SchemaSnapshotHandler::parse_schema_snapshot_call(query) {
+            return crate::query::SchemaSnapshotHandler::handle_schema_snapshot_call(framed, db, session, &call, false, false).await;
+        }
+        // Never-analyzed tables get statistics before a join, grouping or subquery first reads them
+        crate::query::AutoAnalyze::analyze_before(db, session, query).await;
+        
         // Ultra-fast path: Skip all translation if query is simple enough
         let is_ultra_simple = crate::query::simple_query_detector::is_ultra_simple_query(query);
         // Checking if query is ultra-simple
         if is_ultra_simple {
+            if session.trace_enabled() {
+                QueryTrace::emit(framed, session, "strategy: ultra-simple fast path, no translation".to_string()).await?;
+            }
             // Simple query routing without any processing
             match QueryTypeDetector::detect_query_type(query) {
                 QueryType::Select => {
@@ -352,6 +487,15 @@ impl QueryExecutor {
                         })
                         .collect();
                     
+                    // Array columns are stored as JSON and need converting to PostgreSQL array literals
+                    let array_columns: Vec<bool> = fields.iter()
+                        .map(|f| crate::types::ArrayHandler::is_array_oid(f.type_oid))
+                        .collect();
+                    let bytea_columns: Vec<bool> = fields.iter()
+                        .map(|f| f.type_oid == PgType::Bytea.to_oid())
+                        .collect();
+                    let bytea_output = session.bytea_output().await;
+                    
                     framed.send(BackendMessage::RowDescription(fields)).await
                         .map_err(PgSqliteError::Io)?;
                     
@@ -415,8 +559,16 @@ impl QueryExecutor {
                                             eprintln!("  As string: {:?}", std::str::from_utf8(&data));
                                         }
                                         
+                                        // Check for array columns
+                                        if array_columns.get(col_idx).copied().unwrap_or(false) {
+                                            Some(Self::convert_json_to_pg_array(&data).unwrap_or(data))
+                                        }
+                                        // Raw BLOB bytes go out as bytea text per bytea_output
+                                        else if bytea_columns.get(col_idx).copied().unwrap_or(false) {
+                                            Some(bytea_output.encode(&data).into_bytes())
+                                        }
                                         // Check for boolean columns
-                                        if boolean_columns.contains(col_name) {
+                                        else if boolean_columns.contains(col_name) {
                                             // Check if this looks like a boolean value
                                             match std::str::from_utf8(&data) {
                                                 Ok(s) => match s.trim() {
@@ -567,271 +719,15 @@ impl QueryExecutor {
             }
         }
         
-        // Analyze query once to determine which translators are needed
-        let translation_flags = crate::translator::QueryAnalyzer::analyze(query);
-        debug!("Query analysis flags: {:?}", translation_flags);
-        
-        // Translate PostgreSQL cast syntax if present and collect metadata
-        let mut translation_metadata = crate::translator::TranslationMetadata::new();
-        let mut translated_query = if translation_flags.contains(crate::translator::TranslationFlags::CAST) {
-            if crate::profiling::is_profiling_enabled() {
-                crate::time_cast_translation!({
-                    use crate::translator::CastTranslator;
-                    let (translated, metadata) = db.with_session_connection(&session.id, |conn| {
-                        Ok(CastTranslator::translate_with_metadata(query, Some(conn)))
-                    }).await?;
-                    translation_metadata.merge(metadata);
-                    translated
-                })
-            } else {
-                use crate::translator::CastTranslator;
-                let (translated, metadata) = db.with_session_connection(&session.id, |conn| {
-                    Ok(CastTranslator::translate_with_metadata(query, Some(conn)))
-                }).await?;
-                translation_metadata.merge(metadata);
-                translated
-            }
-        } else {
-            query.to_string()
-        };
-        
-        // Translate NUMERIC to TEXT casts with proper formatting
-        if translation_flags.contains(crate::translator::TranslationFlags::NUMERIC_FORMAT) {
-            use crate::translator::NumericFormatTranslator;
-            translated_query = db.with_session_connection(&session.id, |conn| {
-                Ok(NumericFormatTranslator::translate_query(&translated_query, conn))
-            }).await?
-        }
-        
-        // Translate batch UPDATE operations if needed
-        if translation_flags.contains(crate::translator::TranslationFlags::BATCH_UPDATE) {
-            use std::collections::HashMap;
-            use parking_lot::Mutex;
-            let decimal_cache = Arc::new(Mutex::new(HashMap::new()));
-            let batch_translator = BatchUpdateTranslator::new(decimal_cache);
-            translated_query = batch_translator.translate(&translated_query, &[]);
-            debug!("Query after batch UPDATE translation: {}", translated_query);
-        }
-        
-        // Translate batch DELETE operations if needed
-        if translation_flags.contains(crate::translator::TranslationFlags::BATCH_DELETE) {
-            use std::collections::HashMap;
-            use parking_lot::Mutex;
-            let decimal_cache = Arc::new(Mutex::new(HashMap::new()));
-            let batch_translator = BatchDeleteTranslator::new(decimal_cache);
-            translated_query = batch_translator.translate(&translated_query, &[]);
-            debug!("Query after batch DELETE translation: {}", translated_query);
-        }
-        
-        // Translate FTS operations if needed
-        if translation_flags.contains(crate::translator::TranslationFlags::FTS) {
-            debug!("Query contains FTS operations: {}", translated_query);
-            let fts_translator = FtsTranslator::new();
-            
-            // Get connection, do translation, and immediately drop it to avoid Send issues
-            let fts_result = db.with_session_connection(&session.id, |conn| {
-                let result = fts_translator.translate(&translated_query, Some(conn));
-                Ok::<_, rusqlite::Error>(result)
-            }).await;
-            
-            match fts_result {
-                Ok(Ok(fts_queries)) => {
-                    // For multiple queries (like CREATE TABLE with shadow tables), execute them all
-                    if fts_queries.len() > 1 {
-                        debug!("FTS translation produced {} queries", fts_queries.len());
-                        
-                        // Execute all but the last query first
-                        for (i, fts_query) in fts_queries.iter().take(fts_queries.len() - 1).enumerate() {
-                            debug!("Executing FTS query {}: {}", i + 1, fts_query);
-                            let cached_conn = Self::get_or_cache_connection(session, db).await;
-                            db.execute_with_session_cached(fts_query, &session.id, cached_conn.as_ref()).await?;
-                        }
-                        
-                        // Use the last query as the main query
-                        if let Some(main_query) = fts_queries.last() {
-                            translated_query = main_query.clone();
-                            debug!("Using final FTS query: {}", translated_query);
-                        }
-                    } else if fts_queries.len() == 1 {
-                        translated_query = fts_queries[0].clone();
-                        debug!("Query after FTS translation: {}", translated_query);
-                    }
-                }
-                Ok(Err(e)) => {
-                    debug!("FTS translation failed: {}", e);
-                    return Err(PgSqliteError::Protocol(format!("FTS translation error: {e}")));
-                }
-                Err(e) => {
-                    debug!("FTS connection failed: {}", e);
-                    return Err(PgSqliteError::Protocol(format!("Failed to translate FTS: {e}")));
-                }
-            }
-        }
-        
-        // Translate INSERT statements with datetime values if needed
-        if translation_flags.contains(crate::translator::TranslationFlags::INSERT_DATETIME) {
-            use crate::translator::InsertTranslator;
-            debug!("Query needs INSERT datetime translation: {}", translated_query);
-            match InsertTranslator::translate_query(&translated_query, db).await {
-                Ok(translated) => {
-                    debug!("Query after INSERT translation: {}", translated);
-                    translated_query = translated;
-                }
-                Err(e) => {
-                    debug!("INSERT translation failed: {}", e);
-                    // Return the error to the user
-                    return Err(PgSqliteError::Protocol(e));
-                }
-            }
-        }
-        
-        // Translate PostgreSQL datetime functions if present and capture metadata
-        // translation_metadata already initialized above with cast metadata
-        if translation_flags.contains(crate::translator::TranslationFlags::DATETIME) {
-            if crate::profiling::is_profiling_enabled() {
-                crate::time_datetime_translation!({
-                    use crate::translator::DateTimeTranslator;
-                    debug!("Query needs datetime translation: {}", translated_query);
-                    let (translated, metadata) = DateTimeTranslator::translate_with_metadata(&translated_query);
-                    translated_query = translated;
-                    translation_metadata.merge(metadata);
-                    debug!("Query after datetime translation: {}", translated_query);
-                });
-            } else {
-                use crate::translator::DateTimeTranslator;
-                debug!("Query needs datetime translation: {}", translated_query);
-                let (translated, metadata) = DateTimeTranslator::translate_with_metadata(&translated_query);
-                translated_query = translated;
-                translation_metadata.merge(metadata);
-                debug!("Query after datetime translation: {}", translated_query);
-            }
-        }
-        
-        // Translate JSON operators if present
-        if translation_flags.contains(crate::translator::TranslationFlags::JSON) {
-            use crate::translator::JsonTranslator;
-            debug!("Query needs JSON operator translation: {}", translated_query);
-            match JsonTranslator::translate_json_operators(&translated_query) {
-                Ok(translated) => {
-                    debug!("Query after JSON operator translation: {}", translated);
-                    translated_query = translated;
-                }
-                Err(e) => {
-                    debug!("JSON operator translation failed: {}", e);
-                    // Continue with original query - some operators might not be supported yet
-                }
-            }
-            
-            // Note: JSON path $ restoration will happen right before SQLite execution
-            debug!("Query after JSON translation ($ placeholders preserved): {}", translated_query);
-        }
-        
-        // Translate catalog functions (remove pg_catalog prefix)
-        {
-            use crate::translator::{CatalogFunctionTranslator, PgTableIsVisibleTranslator};
-            translated_query = CatalogFunctionTranslator::translate(&translated_query);
-            translated_query = PgTableIsVisibleTranslator::translate(&translated_query);
-        }
-        
-        // Translate array operators with metadata
-        if translation_flags.contains(crate::translator::TranslationFlags::ARRAY) {
-            use crate::translator::ArrayTranslator;
-            match ArrayTranslator::translate_with_metadata(&translated_query) {
-            Ok((translated, metadata)) => {
-                if translated != translated_query {
-                    debug!("Query after array operator translation: {}", translated);
-                    translated_query = translated;
-                }
-                debug!("Array translation metadata: {} hints", metadata.column_mappings.len());
-                for (col, hint) in &metadata.column_mappings {
-                    debug!("  Column '{}': type={:?}", col, hint.suggested_type);
-                }
-                translation_metadata.merge(metadata);
-            }
-            Err(e) => {
-                debug!("Array operator translation failed: {}", e);
-                // Continue with original query
-            }
-            }
-        }
-        
-        // Translate array_agg functions with ORDER BY/DISTINCT support
-        if translation_flags.contains(crate::translator::TranslationFlags::ARRAY_AGG) {
-            use crate::translator::ArrayAggTranslator;
-            match ArrayAggTranslator::translate_with_metadata(&translated_query) {
-            Ok((translated, metadata)) => {
-                if translated != translated_query {
-                    debug!("Query after array_agg translation: {}", translated);
-                    translated_query = translated;
-                }
-                debug!("Array_agg translation metadata: {} hints", metadata.column_mappings.len());
-                translation_metadata.merge(metadata);
-            }
-            Err(e) => {
-                debug!("Array_agg translation failed: {}", e);
-                // Continue with original query
-            }
-            }
-        }
-        
-        // Translate unnest() functions to json_each() equivalents
-        if translation_flags.contains(crate::translator::TranslationFlags::UNNEST) {
-            use crate::translator::UnnestTranslator;
-            match UnnestTranslator::translate_with_metadata(&translated_query) {
-            Ok((translated, metadata)) => {
-                if translated != translated_query {
-                    debug!("Query after unnest translation: {}", translated);
-                    translated_query = translated;
-                }
-                debug!("Unnest translation metadata: {} hints", metadata.column_mappings.len());
-                translation_metadata.merge(metadata);
-            }
-            Err(e) => {
-                debug!("Unnest translation failed: {}", e);
-                // Continue with original query
-            }
-            }
-        }
-        
-        // Translate json_each()/jsonb_each() functions for PostgreSQL compatibility
-        if translation_flags.contains(crate::translator::TranslationFlags::JSON_EACH) {
-            use crate::translator::JsonEachTranslator;
-            match JsonEachTranslator::translate_with_metadata(&translated_query) {
-            Ok((translated, metadata)) => {
-                if translated != translated_query {
-                    debug!("Query after json_each translation: {}", translated);
-                    translated_query = translated;
-                }
-                debug!("JsonEach translation metadata: {} hints", metadata.column_mappings.len());
-                translation_metadata.merge(metadata);
-            }
-            Err(e) => {
-                debug!("JsonEach translation failed: {}", e);
-                // Continue with original query
-            }
-            }
-        }
-        
-        // Translate row_to_json() functions for PostgreSQL compatibility
-        if translation_flags.contains(crate::translator::TranslationFlags::ROW_TO_JSON) {
-            use crate::translator::RowToJsonTranslator;
-            let (translated, metadata) = RowToJsonTranslator::translate_row_to_json(&translated_query);
-            if translated != translated_query {
-            debug!("Query after row_to_json translation: {}", translated);
-            translated_query = translated;
-            }
-            debug!("RowToJson translation metadata: {} hints", metadata.column_mappings.len());
-            translation_metadata.merge(metadata);
-        }
-        
-        // Analyze arithmetic expressions for type metadata
-        if translation_flags.contains(crate::translator::TranslationFlags::ARITHMETIC) {
-            debug!("Analyzing arithmetic expressions in query");
-            let arithmetic_metadata = crate::translator::ArithmeticAnalyzer::analyze_query(&translated_query);
-            debug!("ArithmeticAnalyzer found {} hints", arithmetic_metadata.column_mappings.len());
-            translation_metadata.merge(arithmetic_metadata);
-            debug!("Total translation metadata after merge: {} hints", translation_metadata.column_mappings.len());
+        // Run the translator chain, then any statements it needs executed first (FTS shadow tables)
+        let translated = crate::query::TranslationPipeline::translate(db, session, query).await?;
+        for (i, setup_query) in translated.setup.iter().enumerate() {
+            debug!("Executing FTS query {}: {}", i + 1, setup_query);
+            let cached_conn = Self::get_or_cache_connection(session, db).await;
+            db.execute_with_session_cached(setup_query, &session.id, cached_conn.as_ref()).await?;
         }
+        let translation_metadata = translated.metadata;
+        let translated_query = translated.sql;
         
         let query_to_execute = translated_query.as_str();
         
@@ -840,11 +736,16 @@ impl QueryExecutor {
         
         let query_type = QueryTypeDetector::detect_query_type(query_to_execute);
         debug!("Query type detected: {:?} for query: {}", query_type, query_to_execute);
+        if session.trace_enabled() {
+            QueryTrace::emit(framed, session, format!("translators: {:?}", translated.fired)).await?;
+            QueryTrace::translated(framed, session, query, query_to_execute).await?;
+            QueryTrace::emit(framed, session, format!("strategy: {query_type:?} via full translation path")).await?;
+        }
         match query_type {
             QueryType::Select => {
                 // debug!("Detected SELECT, calling execute_select for query: {}", query_to_execute);
                 debug!("Calling execute_select for query: {}", query_to_execute);
-                Self::execute_select(framed, db, session, query_to_execute, &translation_metadata, query_router).await
+                Self::execute_select(framed, db, session, query, query_to_execute, &translation_metadata, query_router).await
             },
             QueryType::Insert | QueryType::Update | QueryType::Delete => {
                 Self::execute_dml(framed, db, session, query_to_execute, query_router).await
@@ -869,8 +770,9 @@ impl QueryExecutor {
     
     async fn execute_select<T>(
         framed: &mut Framed<T, crate::protocol::PostgresCodec>,
-        db: &Arc<DbHandler>,
+        db: &Arc<dyn SessionStorage>,
         session: &Arc<SessionState>,
+        original_query: &str,
         query: &str,
         translation_metadata: &crate::translator::TranslationMetadata,
         query_router: Option<&Arc<QueryRouter>>,
@@ -886,6 +788,9 @@ impl QueryExecutor {
         if crate::cache::is_cacheable_for_wire_protocol(query)
             && let Some(cached_response) = crate::cache::WIRE_PROTOCOL_CACHE.get(query) {
                 debug!("Wire protocol cache hit for query: {}", query);
+                if session.trace_enabled() {
+                    QueryTrace::emit(framed, session, "cache: wire protocol cache hit".to_string()).await?;
+                }
                 
                 // Send cached row description
                 framed.send(BackendMessage::RowDescription(cached_response.row_description.clone())).await
@@ -952,7 +857,7 @@ impl QueryExecutor {
         };
         
         // Check cache first
-        let fields = if let Some(cached_fields) = GLOBAL_ROW_DESCRIPTION_CACHE.get(&cache_key) {
+        let fields = if let Some(cached_fields) = db.get_caches().row_descriptions.get(&cache_key) {
             cached_fields
         } else {
             // Pre-fetch schema types for all columns if we have a table name
@@ -1080,6 +985,15 @@ impl QueryExecutor {
                 }
             }
             
+            // Type the result columns from the PostgreSQL query's AST, for the expressions
+            // the lookups above can't follow; translations that change the column count
+            // leave the types to the fallbacks
+            let checked_types = db.with_session_connection(&session.id, |conn| {
+                Ok(crate::types::TypeChecker::new(conn).result_column_types(original_query))
+            }).await.ok().flatten()
+                .filter(|types| types.len() == response.columns.len())
+                .unwrap_or_default();
+            
             // Build field descriptions with proper type inference
             let fields: Vec<FieldDescription> = response.columns.iter()
                 .enumerate()
@@ -1094,6 +1008,9 @@ impl QueryExecutor {
                     } else if crate::types::aggregate_type_fixer::fix_aggregate_type_for_decimal(name, Some(query)).is_some() {
                         // Third priority: Check if this is an aliased aggregate on a decimal column
                         crate::types::PgType::Numeric.to_oid()
+                    } else if let Some(pg_type) = checked_types.get(i).copied().flatten() {
+                        // Third priority: The type checker's inference from the PostgreSQL query
+                        pg_type.to_oid()
                     } else if let Some(hint) = translation_metadata.get_hint(name) {
                         // Third priority: Check translation metadata (datetime or arithmetic)
                         debug!("Found translation hint for column '{}': {:?}", name, hint);
@@ -1168,7 +1085,7 @@ impl QueryExecutor {
                 .collect();
             
             // Cache the field descriptions
-            GLOBAL_ROW_DESCRIPTION_CACHE.insert(cache_key, fields.clone());
+            db.get_caches().row_descriptions.insert(cache_key, fields.clone());
             
             fields
         };
@@ -1308,7 +1225,7 @@ impl QueryExecutor {
         // Convert array data before sending rows
         debug!("Converting array data for {} rows", response.rows.len());
         debug!("About to convert array data for {} rows", response.rows.len());
-        let mut converted_rows = Self::convert_array_data_in_rows(response.rows, &fields)?;
+        let mut converted_rows = Self::convert_array_data_in_rows(response.rows, &fields, session.bytea_output().await)?;
         debug!("Completed array data conversion");
         
         // Convert datetime data if needed
@@ -1363,7 +1280,8 @@ impl QueryExecutor {
         
         // Prepare wire protocol cache if this query is cacheable
         let mut encoded_rows = Vec::new();
-        let should_cache = crate::cache::is_cacheable_for_wire_protocol(query) && row_count <= 1000; // Don't cache huge results
+        let should_cache = crate::cache::is_cacheable_for_wire_protocol(query) && row_count <= 1000 && // Don't cache huge results
+            !fields.iter().any(|f| f.type_oid == PgType::Bytea.to_oid()); // bytea text depends on the session's bytea_output
         
         // Optimized data row sending for better SELECT performance
         if converted_rows.len() > 5 {
@@ -1415,7 +1333,7 @@ impl QueryExecutor {
     
     async fn execute_dml<T>(
         framed: &mut Framed<T, crate::protocol::PostgresCodec>,
-        db: &Arc<DbHandler>,
+        db: &Arc<dyn SessionStorage>,
         session: &Arc<SessionState>,
         query: &str,
         query_router: Option<&Arc<QueryRouter>>,
@@ -1570,7 +1488,7 @@ impl QueryExecutor {
     
     async fn execute_dml_with_returning<T>(
         framed: &mut Framed<T, crate::protocol::PostgresCodec>,
-        db: &Arc<DbHandler>,
+        db: &Arc<dyn SessionStorage>,
         session: &Arc<SessionState>,
         query: &str,
         query_router: Option<&Arc<QueryRouter>>,
@@ -1580,21 +1498,57 @@ impl QueryExecutor {
     {
         use crate::query::{QueryTypeDetector, QueryType};
         
+        // Extract table name from query for type lookup
+        let query_type = QueryTypeDetector::detect_query_type(query);
+        let table_name = match query_type {
+            QueryType::Insert => extract_table_name_from_insert(query),
+            QueryType::Update => extract_table_name_from_update(query),
+            QueryType::Delete => extract_table_name_from_delete(query),
+            _ => None,
+        };
+        
+        // SQLite evaluates RETURNING before the triggers that apply the assignments of
+        // BEFORE triggers to NEW, so rows such triggers change are read back afterwards
+        let reread = match (&query_type, &table_name, ReturningTranslator::extract_returning_clause(query)) {
+            (QueryType::Insert | QueryType::Update, Some(table), Some(parts)) => {
+                let assigns = db.with_session_connection(&session.id, |conn| crate::query::TriggerHandler::assigns_new(conn, table)).await?;
+                assigns.then_some((table, parts))
+            }
+            _ => None,
+        };
+        
         // SQLite 3.35.0+ supports native RETURNING clause
         // Execute the query with RETURNING clause directly
-        let returning_response = if let Some(router) = query_router {
+        let returning_response = if let Some((table, (base_query, returning_clause))) = reread {
+            let cached_conn = Self::get_or_cache_connection(session, db).await;
+            let changed = db.query_with_session_cached(&format!("{base_query} RETURNING rowid"), &session.id, cached_conn.as_ref()).await?;
+            let rowids: Vec<String> = changed.rows.iter()
+                .filter_map(|row| row.first().cloned().flatten())
+                .map(|rowid| String::from_utf8_lossy(&rowid).into_owned())
+                .collect();
+            db.query_with_session_cached(
+                &format!("SELECT {returning_clause} FROM {table} WHERE rowid IN ({}) ORDER BY rowid", rowids.join(", ")),
+                &session.id,
+                cached_conn.as_ref(),
+            ).await?
+        } else if let Some(router) = query_router {
             router.execute_query(query, session).await.map_err(|e| PgSqliteError::Protocol(e.to_string()))?
         } else {
             let cached_conn = Self::get_or_cache_connection(session, db).await;
             db.query_with_session_cached(query, &session.id, cached_conn.as_ref()).await?
         };
         
-        // Extract table name from query for type lookup
-        let table_name = match QueryTypeDetector::detect_query_type(query) {
-            QueryType::Insert => extract_table_name_from_insert(query),
-            QueryType::Update => extract_table_name_from_update(query),
-            QueryType::Delete => extract_table_name_from_delete(query),
-            _ => None,
+        // Expressions in the RETURNING list are typed like the select list of a query on the table
+        let checked_types = match (&table_name, ReturningTranslator::extract_returning_clause(query)) {
+            (Some(table), Some((_, returning_clause))) => {
+                let probe_query = format!("SELECT {returning_clause} FROM {table}");
+                db.with_session_connection(&session.id, |conn| {
+                    Ok(crate::types::TypeChecker::new(conn).result_column_types(&probe_query))
+                }).await.ok().flatten()
+                    .filter(|types| types.len() == returning_response.columns.len())
+                    .unwrap_or_default()
+            }
+            _ => Vec::new(),
         };
         
         // Build field descriptions with proper type information
@@ -1610,6 +1564,9 @@ impl QueryExecutor {
                 && let Ok(Some(schema_type)) = db.get_schema_type_with_session(&session.id, table, col_name).await {
                     pg_type = Some(schema_type.clone());
                     type_oid = crate::types::SchemaTypeMapper::pg_type_string_to_oid(&schema_type);
+                } else if let Some(checked_type) = checked_types.get(i).copied().flatten() {
+                    pg_type = Some(checked_type.name().to_string());
+                    type_oid = checked_type.to_oid();
                 }
             
             fields.push(FieldDescription {
@@ -1629,6 +1586,7 @@ impl QueryExecutor {
             .map_err(PgSqliteError::Io)?;
         
         // Send data rows with proper type conversion
+        let bytea_output = session.bytea_output().await;
         let mut row_count = 0;
         for row in returning_response.rows {
             // Convert row values based on column types
@@ -1687,6 +1645,15 @@ impl QueryExecutor {
                                     Some(value_bytes.clone())
                                 }
                             }
+                            "BOOLEAN" | "BOOL" => {
+                                // Convert SQLite's 0/1 storage to PostgreSQL's t/f
+                                match value_bytes.as_slice() {
+                                    b"0" => Some(b"f".to_vec()),
+                                    b"1" => Some(b"t".to_vec()),
+                                    _ => Some(value_bytes.clone()),
+                                }
+                            }
+                            "BYTEA" => Some(bytea_output.encode(value_bytes).into_bytes()),
                             _ => Some(value_bytes.clone()),
                         };
                         converted_row.push(formatted);
@@ -1720,7 +1687,7 @@ impl QueryExecutor {
     
     async fn execute_ddl<T>(
         framed: &mut Framed<T, crate::protocol::PostgresCodec>,
-        db: &Arc<DbHandler>,
+        db: &Arc<dyn SessionStorage>,
         session: &Arc<SessionState>,
         query: &str,
         _query_router: Option<&Arc<QueryRouter>>,
@@ -1730,39 +1697,47 @@ impl QueryExecutor {
     {
         use crate::translator::CreateTableTranslator;
         use crate::query::{QueryTypeDetector, QueryType};
-        use crate::ddl::EnumDdlHandler;
+        use crate::ddl::{CompositeDdlHandler, EnumDdlHandler};
+        
+        // CREATE INDEX CONCURRENTLY reads the table in batches before the build takes the write lock
+        if let Some(statement) = crate::query::ConcurrentIndexHandler::parse_concurrent_index(query)? {
+            return crate::query::ConcurrentIndexHandler::handle_concurrent_index(framed, db, session, &statement).await;
+        }
+        
+        // Check if this is CREATE TYPE ... AS (...)
+        if CompositeDdlHandler::is_composite_ddl(query) {
+            db.with_session_connection_mut(&session.id, |conn| {
+                Ok(CompositeDdlHandler::handle_create_type(conn, query))
+            }).await??;
+            
+            framed.send(BackendMessage::CommandComplete { 
+                tag: "CREATE TYPE".to_string() 
+            }).await
+                .map_err(PgSqliteError::Io)?;
+            
+            return Ok(());
+        }
         
         // Check if this is an ENUM DDL statement
         if EnumDdlHandler::is_enum_ddl(query) {
             // Handle ENUM DDL with session connections
-            db.with_session_connection_mut(&session.id, |conn| {
-                EnumDdlHandler::handle_enum_ddl(conn, query)
-                    .map_err(|e| rusqlite::Error::SqliteFailure(
-                        rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_ERROR),
-                        Some(format!("ENUM DDL failed: {e}"))
-                    ))
-            }).await?;
-            
-            let command_tag = if query.trim().to_uppercase().starts_with("CREATE TYPE") {
-                "CREATE TYPE"
-            } else if query.trim().to_uppercase().starts_with("ALTER TYPE") {
-                "ALTER TYPE"
-            } else if query.trim().to_uppercase().starts_with("DROP TYPE") {
-                "DROP TYPE"  
-            } else {
-                "OK"
-            };
+            let tables = db.with_session_connection_mut(&session.id, |conn| {
+                Ok(EnumDdlHandler::handle_enum_ddl(conn, query))
+            }).await??;
+            for table in &tables {
+                crate::query::alter_table_handler::forget_table(db.as_ref(), table);
+            }
             
             // Send command complete
             framed.send(BackendMessage::CommandComplete { 
-                tag: command_tag.to_string() 
+                tag: EnumDdlHandler::command_tag(query).to_string() 
             }).await
                 .map_err(PgSqliteError::Io)?;
             
             return Ok(());
         }
         
-        let (translated_query, type_mappings, enum_columns, array_columns) = if matches!(QueryTypeDetector::detect_query_type(query), QueryType::Create) && query.trim_start()[6..].trim_start().to_uppercase().starts_with("TABLE") {
+        let (translated_query, type_mappings, enum_columns, array_columns, identity_columns, exclusion_constraints) = if CreateTableTranslator::is_create_table(query) {
             // Use CREATE TABLE translator with connection for ENUM support
             db.with_session_connection(&session.id, |conn| {
                 let result = CreateTableTranslator::translate_with_connection_full(query, Some(conn))
@@ -1771,7 +1746,7 @@ impl QueryExecutor {
                         Some(format!("CREATE TABLE translation failed: {e}"))
                     ))?;
                 
-                Ok((result.sql, result.type_mappings, result.enum_columns, result.array_columns))
+                Ok((result.sql, result.type_mappings, result.enum_columns, result.array_columns, result.identity_columns, result.exclusion_constraints))
             }).await?
         } else {
             // For other DDL, check for JSON/JSONB types
@@ -1780,7 +1755,7 @@ impl QueryExecutor {
             } else {
                 query.to_string()
             };
-            (translated, std::collections::HashMap::new(), Vec::new(), Vec::new())
+            (translated, std::collections::HashMap::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new())
         };
         
         // Check if this is a DROP TABLE command and extract table name
@@ -1797,10 +1772,50 @@ impl QueryExecutor {
             None
         };
         
+        // PostgreSQL refuses to drop a referenced table unless told to CASCADE
+        let mut translated_query = translated_query;
+        if is_drop_table
+            && let Some(drop) = db.with_session_connection(&session.id, |conn| Ok(ForeignKeys::prepare_drop_table(conn, query))).await?? {
+            for message in drop.notices {
+                framed.send(BackendMessage::NoticeResponse(NoticeResponse {
+                    severity: "NOTICE".to_string(),
+                    code: "00000".to_string(),
+                    message,
+                    detail: None,
+                    hint: None,
+                    position: None,
+                    where_: None,
+                })).await.map_err(PgSqliteError::Io)?;
+            }
+            translated_query = drop.sql;
+        }
+        
+        // SQLite accepts references to missing tables and columns; PostgreSQL checks them up front,
+        // so a table this statement creates is checked afterwards (and dropped again if wrong)
+        let created_table = match CreateTableTranslator::created_table_name(query) {
+            Some(table_name) if matches!(QueryTypeDetector::detect_query_type(query), QueryType::Create) => {
+                let exists = db.with_session_connection(&session.id, |conn| {
+                    conn.query_row(
+                        "SELECT COUNT(*) FROM (SELECT name, type FROM sqlite_master UNION ALL SELECT name, type FROM sqlite_temp_master) WHERE type = 'table' AND name = ?1",
+                        [&table_name],
+                        |row| row.get::<_, i64>(0),
+                    )
+                }).await? > 0;
+                (!exists).then_some(table_name)
+            }
+            _ => None,
+        };
+        
         // Execute the translated query
         let cached_conn = Self::get_or_cache_connection(session, db).await;
         db.execute_with_session_cached(&translated_query, &session.id, cached_conn.as_ref()).await?;
         
+        if let Some(table_name) = created_table
+            && let Err(e) = db.with_session_connection(&session.id, |conn| Ok(ForeignKeys::validate_table(conn, &table_name))).await? {
+            db.execute_with_session_cached(&format!("DROP TABLE \"{table_name}\""), &session.id, cached_conn.as_ref()).await?;
+            return Err(e);
+        }
+        
         // If this was a DROP TABLE, clean up enum usage records
         if let Some(table_name) = table_name_to_clean {
             db.with_session_connection_mut(&session.id, |conn| {
@@ -1812,13 +1827,31 @@ impl QueryExecutor {
                     ))
             }).await?;
             debug!("Cleaned up enum usage records for dropped table: {}", table_name);
+            
+            db.with_session_connection(&session.id, |conn| {
+                IdentityColumns::clean_identity_columns_for_table(conn, &table_name)
+                    .map_err(|e| rusqlite::Error::SqliteFailure(
+                        rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_ERROR),
+                        Some(format!("Failed to clean identity columns for table {table_name}: {e}"))
+                    ))
+            }).await?;
+            
+            db.with_session_connection(&session.id, crate::query::CommentHandler::prune_relation_comments).await?;
+            db.with_session_connection(&session.id, crate::query::TriggerHandler::prune_triggers).await?;
+            db.with_session_connection(&session.id, crate::query::AuditHandler::prune_audits).await?;
+            db.with_session_connection(&session.id, crate::query::SoftDeleteHandler::prune_soft_deletes).await?;
+            db.with_session_connection(&session.id, crate::query::RetentionHandler::prune_retention_policies).await?;
+            db.with_session_connection(&session.id, crate::query::ClusterHandler::prune_clustered_indexes).await?;
+            db.with_session_connection(&session.id, crate::query::AutoAnalyze::prune_autoanalyze).await?;
+            db.with_session_connection(&session.id, crate::query::MaskHandler::prune_column_masks).await?;
+            db.with_session_connection(&session.id, crate::validator::ExclusionConstraint::prune).await?;
         }
         
         // If we have type mappings, store them in the metadata table
         debug!("Type mappings count: {}", type_mappings.len());
         if !type_mappings.is_empty() {
             // Extract table name from the original query
-            if let Some(table_name) = extract_table_name_from_create(query) {
+            if let Some(table_name) = CreateTableTranslator::created_table_name(query) {
                 // Initialize the metadata table if it doesn't exist
                 let init_query = "CREATE TABLE IF NOT EXISTS __pgsqlite_schema (
                     table_name TEXT NOT NULL,
@@ -1850,6 +1883,23 @@ impl QueryExecutor {
                             Err(e) => debug!("Failed to store metadata for {}.{}: {}", table_name, parts[1], e),
                         }
                         
+                        // Validate bit strings and their length on every write
+                        if let Some(constraint) = crate::validator::BitConstraint::from_column(&table_name, parts[1], &type_mapping.pg_type, type_mapping.type_modifier) {
+                            match db.with_session_connection(&session.id, |conn| constraint.create_triggers(conn)).await {
+                                Ok(()) => debug!("Created bit string triggers: {}.{}", table_name, parts[1]),
+                                Err(e) => debug!("Failed to create bit string triggers for {}.{}: {}", table_name, parts[1], e),
+                            }
+                        }
+                        
+                        // Reject values that are not records of a composite column's type
+                        match db.with_session_connection(&session.id, |conn| {
+                            crate::ddl::CompositeDdlHandler::create_column_triggers(conn, &table_name, parts[1], &type_mapping.pg_type)
+                        }).await {
+                            Ok(true) => debug!("Created composite type triggers: {}.{}", table_name, parts[1]),
+                            Ok(false) => {}
+                            Err(e) => debug!("Failed to create composite type triggers for {}.{}: {}", table_name, parts[1], e),
+                        }
+                        
                         // Store string constraints if present
                         if let Some(modifier) = type_mapping.type_modifier {
                             // Extract base type without parameters
@@ -1863,16 +1913,18 @@ impl QueryExecutor {
                             if pg_type_lower == "varchar" || pg_type_lower == "char" || 
                                pg_type_lower == "character varying" || pg_type_lower == "character" ||
                                pg_type_lower == "nvarchar" {
-                                let is_char = pg_type_lower == "char" || pg_type_lower == "character";
-                                let constraint_query = format!(
-                                    "INSERT OR REPLACE INTO __pgsqlite_string_constraints (table_name, column_name, max_length, is_char_type) 
-                                     VALUES ('{}', '{}', {}, {})",
-                                    table_name, parts[1], modifier, if is_char { 1 } else { 0 }
-                                );
+                                let constraint = crate::validator::StringConstraint {
+                                    table_name: table_name.to_string(),
+                                    column_name: parts[1].to_string(),
+                                    max_length: modifier,
+                                    is_char_type: pg_type_lower == "char" || pg_type_lower == "character",
+                                };
                                 
-                                let cached_conn = Self::get_or_cache_connection(session, db).await;
-                                match db.execute_with_session_cached(&constraint_query, &session.id, cached_conn.as_ref()).await {
-                                    Ok(_) => debug!("Stored string constraint: {}.{} max_length={}", table_name, parts[1], modifier),
+                                // Store the constraint and create the triggers enforcing it
+                                match db.with_session_connection(&session.id, |conn| {
+                                    crate::validator::StringConstraintValidator::record_constraint(conn, &constraint)
+                                }).await {
+                                    Ok(()) => debug!("Stored string constraint: {}.{} max_length={}", table_name, parts[1], modifier),
                                     Err(e) => debug!("Failed to store string constraint for {}.{}: {}", table_name, parts[1], e),
                                 }
                             } else if pg_type_lower == "numeric" || pg_type_lower == "decimal" {
@@ -1903,6 +1955,8 @@ impl QueryExecutor {
                 }
                 
                 debug!("Stored type mappings for table {} (simple query protocol)", table_name);
+                // A schema cached while the table was being created predates its column types
+                db.get_schema_cache().invalidate(&table_name);
                 
                 // Create triggers for ENUM columns
                 if !enum_columns.is_empty() {
@@ -1928,6 +1982,27 @@ impl QueryExecutor {
                     }).await?;
                 }
                 
+                // Record SERIAL/identity columns and create their backing triggers
+                if !identity_columns.is_empty() {
+                    db.with_session_connection(&session.id, |conn| {
+                        IdentityColumns::record_identity_columns(conn, &table_name, &identity_columns)
+                            .map_err(|e| rusqlite::Error::SqliteFailure(
+                                rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_ERROR),
+                                Some(format!("Failed to record identity columns: {e}"))
+                            ))
+                    }).await?;
+                    debug!("Recorded {} identity columns for {}", identity_columns.len(), table_name);
+                }
+                
+                // Enforce EXCLUDE constraints with an index and triggers
+                for constraint in &exclusion_constraints {
+                    db.with_session_connection(&session.id, |conn| {
+                        constraint.create(conn)?;
+                        constraint.record(conn)
+                    }).await?;
+                    debug!("Created exclusion constraint {} on {}", constraint.constraint_name, table_name);
+                }
+                
                 // Store array column metadata
                 if !array_columns.is_empty() {
                     db.with_session_connection(&session.id, |conn| {
@@ -1959,6 +2034,13 @@ impl QueryExecutor {
                             
                             debug!("Stored array column metadata for {}.{} (element_type: {}, dimensions: {})", 
                                   table_name, column_name, element_type, dimensions);
+                            
+                            // Reject labels that are not values of an ENUM element type
+                            EnumTriggers::create_array_column_triggers(conn, &table_name, column_name, element_type)
+                                .map_err(|e| rusqlite::Error::SqliteFailure(
+                                    rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_ERROR),
+                                    Some(format!("Failed to create enum triggers: {e}"))
+                                ))?;
                         }
                         Ok(())
                     }).await?;
@@ -1971,7 +2053,7 @@ impl QueryExecutor {
                 // No need for triggers anymore
                 
                 // Populate PostgreSQL catalog tables with constraint information
-                if let Some(table_name) = extract_table_name_from_create(query) {
+                if let Some(table_name) = CreateTableTranslator::created_table_name(query) {
                     db.with_session_connection(&session.id, |conn| {
                         // Populate pg_constraint, pg_attrdef, and pg_index tables
                         if let Err(e) = crate::catalog::constraint_populator::populate_constraints_for_table(conn, &table_name) {
@@ -1986,10 +2068,13 @@ impl QueryExecutor {
             }
         }
         
+        crate::ddl::ForeignKeyIndexer::index_foreign_keys(framed, db, session, query).await?;
+        db.with_session_connection(&session.id, crate::metadata::OidAllocator::sync_relations).await?;
+        
         let tag = match QueryTypeDetector::detect_query_type(query) {
             QueryType::Create => {
                 let after_create = query.trim_start()[6..].trim_start();
-                if after_create.to_uppercase().starts_with("TABLE") {
+                if CreateTableTranslator::is_create_table(query) {
                     "CREATE TABLE".to_string()
                 } else if after_create.to_uppercase().starts_with("INDEX") {
                     "CREATE INDEX".to_string()
@@ -2016,7 +2101,7 @@ impl QueryExecutor {
     
     async fn execute_transaction<T>(
         framed: &mut Framed<T, crate::protocol::PostgresCodec>,
-        db: &Arc<DbHandler>,
+        db: &Arc<dyn SessionStorage>,
         session: &Arc<SessionState>,
         query: &str,
         _query_router: Option<&Arc<QueryRouter>>,
@@ -2082,6 +2167,7 @@ impl QueryExecutor {
                 
                 // Update transaction status to Idle
                 *session.transaction_status.write().await = TransactionStatus::Idle;
+                session.end_transaction(true).await;
                 tracing::debug!("Transaction status updated to Idle");
                 framed.send(BackendMessage::CommandComplete { tag: "COMMIT".to_string() }).await
                     .map_err(PgSqliteError::Io)?;
@@ -2092,6 +2178,7 @@ impl QueryExecutor {
                 
                 // Update transaction status to Idle (regardless of previous state)
                 *session.transaction_status.write().await = TransactionStatus::Idle;
+                session.end_transaction(false).await;
                 framed.send(BackendMessage::CommandComplete { tag: "ROLLBACK".to_string() }).await
                     .map_err(PgSqliteError::Io)?;
             }
@@ -2103,7 +2190,7 @@ impl QueryExecutor {
     
     async fn execute_generic<T>(
         framed: &mut Framed<T, crate::protocol::PostgresCodec>,
-        db: &Arc<DbHandler>,
+        db: &Arc<dyn SessionStorage>,
         session: &Arc<SessionState>,
         query: &str,
         query_router: Option<&Arc<QueryRouter>>,
@@ -2198,6 +2285,7 @@ impl QueryExecutor {
     fn convert_array_data_in_rows(
         rows: Vec<Vec<Option<Vec<u8>>>>,
         fields: &[FieldDescription],
+        bytea_output: crate::types::ByteaFormat,
     ) -> Result<Vec<Vec<Option<Vec<u8>>>>, PgSqliteError> {
         // Extract type OIDs from field descriptions
         let type_oids: Vec<i32> = fields.iter().map(|f| f.type_oid).collect();
@@ -2211,6 +2299,8 @@ impl QueryExecutor {
         let timetz_oid = PgType::Timetz.to_oid();
         let timestamp_oid = PgType::Timestamp.to_oid();
         let timestamptz_oid = PgType::Timestamptz.to_oid();
+        let interval_oid = PgType::Interval.to_oid();
+        let bytea_oid = PgType::Bytea.to_oid();
         
         let needs_conversion = type_oids.iter().any(|&oid| {
             oid == bool_oid || 
@@ -2219,6 +2309,8 @@ impl QueryExecutor {
             oid == timetz_oid ||
             oid == timestamp_oid ||
             oid == timestamptz_oid ||
+            oid == interval_oid ||
+            oid == bytea_oid ||
             PgType::from_oid(oid).is_some_and(|t| t.is_array())
         });
         
@@ -2298,6 +2390,15 @@ impl QueryExecutor {
                         } else {
                             Some(data) // Keep original if not valid UTF-8
                         }
+                    } else if type_oid == interval_oid {
+                        // Convert stored interval text (or INTEGER microseconds) to PostgreSQL interval format
+                        match std::str::from_utf8(&data).ok().and_then(|s| crate::types::Interval::from_storage(s).ok()) {
+                            Some(interval) => Some(interval.to_string().into_bytes()),
+                            None => Some(data), // Keep original if not an interval
+                        }
+                    } else if type_oid == bytea_oid {
+                        // Raw BLOB bytes go out as bytea text per bytea_output
+                        Some(bytea_output.encode(&data).into_bytes())
                     } else {
                         Some(data)
                     }
@@ -2316,54 +2417,14 @@ impl QueryExecutor {
     
     /// Convert JSON array string to PostgreSQL array format
     pub fn convert_json_to_pg_array(json_data: &[u8]) -> Result<Vec<u8>, String> {
-        // Convert bytes to string
         let s = std::str::from_utf8(json_data).map_err(|_| "Invalid UTF-8")?;
         
-        // Try to parse as JSON array
-        match serde_json::from_str::<serde_json::Value>(s) {
-            Ok(json_val) => {
-                if let serde_json::Value::Array(arr) = json_val {
-                    // Convert to PostgreSQL array literal format
-                    let pg_array = Self::json_array_to_pg_text(&arr);
-                    Ok(pg_array.into_bytes())
-                } else {
-                    // Not an array, return as-is
-                    Ok(json_data.to_vec())
-                }
-            }
-            Err(_) => {
-                // Not valid JSON, return as-is
-                Ok(json_data.to_vec())
-            }
+        // Values that aren't JSON arrays are returned as-is
+        match crate::types::ArrayHandler::json_to_array_literal(s) {
+            Some(pg_array) => Ok(pg_array.into_bytes()),
+            None => Ok(json_data.to_vec()),
         }
     }
-    
-    /// Convert JSON array elements to PostgreSQL text array format
-    fn json_array_to_pg_text(arr: &[serde_json::Value]) -> String {
-        let elements: Vec<String> = arr.iter().map(|elem| {
-            match elem {
-                serde_json::Value::Null => "NULL".to_string(),
-                serde_json::Value::Bool(b) => b.to_string(),
-                serde_json::Value::Number(n) => n.to_string(),
-                serde_json::Value::String(s) => {
-                    // Escape quotes and backslashes
-                    let escaped = s.replace('\\', "\\\\").replace('"', "\\\"");
-                    format!("\"{escaped}\"")
-                }
-                serde_json::Value::Array(_) => {
-                    // Nested arrays - convert recursively
-                    // For now, just stringify
-                    elem.to_string()
-                }
-                serde_json::Value::Object(_) => {
-                    // Objects - stringify
-                    elem.to_string()
-                }
-            }
-        }).collect();
-        
-        format!("{{{}}}", elements.join(","))
-    }
 }
 
 fn extract_table_name_from_select(query: &str) -> Option<String> {
@@ -2473,38 +2534,6 @@ fn extract_column_mappings_from_query(query: &str, table: &str) -> std::collecti
     mappings
 }
 
-/// Extract table name from CREATE TABLE statement
-fn extract_table_name_from_create(query: &str) -> Option<String> {
-    // Look for CREATE TABLE pattern with case-insensitive search
-    let create_table_pos = query.as_bytes().windows(12)
-        .position(|window| window.eq_ignore_ascii_case(b"CREATE TABLE"))?;
-    
-    let after_create = &query[create_table_pos + 12..].trim();
-    
-    // Skip IF NOT EXISTS if present
-    let after_create = if after_create.len() >= 13 && after_create[..13].eq_ignore_ascii_case("IF NOT EXISTS") {
-        &after_create[13..].trim()
-    } else {
-        after_create
-    };
-    
-    // Find the end of table name
-    let table_end = after_create.find(|c: char| {
-        c.is_whitespace() || c == '('
-    }).unwrap_or(after_create.len());
-    
-    let table_name = after_create[..table_end].trim();
-    
-    // Remove quotes if present
-    let table_name = table_name.trim_matches('"').trim_matches('\'');
-    
-    if !table_name.is_empty() {
-        Some(table_name.to_string())
-    } else {
-        None
-    }
-}
-
 /// Extract table name from INSERT statement
 fn extract_table_name_from_insert(query: &str) -> Option<String> {
     // Look for INSERT INTO pattern with case-insensitive search
@@ -2642,7 +2671,7 @@ mod tests {
         ];
         
         let rows = vec![vec![Some(b"[\"a\", \"b\", \"c\"]".to_vec())]];
-        let converted = QueryExecutor::convert_array_data_in_rows(rows, &fields).unwrap();
+        let converted = QueryExecutor::convert_array_data_in_rows(rows, &fields, crate::types::ByteaFormat::Hex).unwrap();
         let result_data = &converted[0][0].as_ref().unwrap();
         let result_str = String::from_utf8_lossy(result_data);
         assert_eq!(result_str, r#"{"a","b","c"}"#);
@@ -1,12 +1,12 @@
 use crate::protocol::{BackendMessage, FieldDescription};
-use crate::session::{DbHandler, SessionState, PreparedStatement, Portal, GLOBAL_QUERY_CACHE};
+use crate::session::{DbHandler, SessionState, SessionStorage, PreparedStatement, Portal, ResultShape, GLOBAL_QUERY_CACHE};
 use crate::catalog::CatalogInterceptor;
 use crate::translator::{JsonTranslator, ReturningTranslator, CastTranslator};
-use crate::types::{DecimalHandler, PgType};
-use std::str::FromStr;
-use crate::cache::{RowDescriptionKey, GLOBAL_ROW_DESCRIPTION_CACHE, GLOBAL_PARAMETER_CACHE, CachedParameterInfo};
+use crate::types::{ArrayHandler, DecimalHandler, PgType};
+use crate::cache::{RowDescriptionKey, GLOBAL_PARAMETER_CACHE, CachedParameterInfo};
 use crate::validator::NumericValidator;
 use crate::query::ParameterParser;
+use crate::query::query_trace::QueryTrace;
 use crate::PgSqliteError;
 use tokio_util::codec::Framed;
 use futures::SinkExt;
@@ -64,7 +64,7 @@ impl ExtendedQueryHandler {
     /// Get cached connection or fetch and cache it
     async fn get_or_cache_connection(
         session: &Arc<SessionState>,
-        db: &Arc<DbHandler>
+        db: &Arc<dyn SessionStorage>
     ) -> Option<Arc<parking_lot::Mutex<rusqlite::Connection>>> {
         // First check if we have a cached connection
         if let Some(cached) = session.get_cached_connection() {
@@ -72,7 +72,7 @@ impl ExtendedQueryHandler {
         }
         
         // Try to get connection from manager and cache it
-        if let Some(conn_arc) = db.connection_manager().get_connection_arc(&session.id) {
+        if let Some(conn_arc) = db.session_connection(&session.id) {
             session.cache_connection(conn_arc.clone());
             Some(conn_arc)
         } else {
@@ -81,7 +81,7 @@ impl ExtendedQueryHandler {
     }
     pub async fn handle_parse<T>(
         framed: &mut Framed<T, crate::protocol::PostgresCodec>,
-        db: &Arc<DbHandler>,
+        db: &Arc<dyn SessionStorage>,
         session: &Arc<SessionState>,
         name: String,
         query: String,
@@ -90,6 +90,15 @@ impl ExtendedQueryHandler {
     where
         T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
     {
+        // schema.table names become the SQLite names of the tables, see SchemaHandler
+        let query = crate::query::SchemaHandler::qualify_names(db, session, &query).await?.into_owned();
+        // Deleted rows of soft-delete tables are skipped and DELETE marks rows deleted, see SoftDeleteHandler
+        let query = crate::query::SoftDeleteHandler::rewrite(db, session, &query).await?.into_owned();
+        // Masked columns read through pgsqlite_mask() for roles other than --admin-users, see MaskHandler
+        let query = crate::query::MaskHandler::rewrite(db, session, &query).await?.into_owned();
+        // Strict compatibility mode reports what the translators would approximate
+        crate::query::CompatibilityCheck::enforce(framed, session, &query).await?;
+
         // Fast path: Check if we already have this prepared statement
         // This avoids re-parsing the same query multiple times
         if !name.is_empty() {
@@ -99,6 +108,9 @@ impl ExtendedQueryHandler {
                 if existing.query == query && existing.param_types == param_types {
                     // Already parsed, just send ParseComplete
                     drop(statements);
+                    if session.trace_enabled() {
+                        QueryTrace::emit(framed, session, format!("parse: prepared statement \"{name}\" reused")).await?;
+                    }
                     framed.send(BackendMessage::ParseComplete).await
                         .map_err(PgSqliteError::Io)?;
                     return Ok(());
@@ -108,6 +120,9 @@ impl ExtendedQueryHandler {
             // For unnamed statements, check if we have cached info about this query
             // This is important for benchmarks that use parameterized queries
             if let Some(cached_info) = GLOBAL_PARAMETER_CACHE.get(&query) {
+                if session.trace_enabled() {
+                    QueryTrace::emit(framed, session, format!("parse: parameter cache hit for {query}")).await?;
+                }
                 // Translate the query for cached statements too
                 // In per-session mode, we can't get a connection during parse,
                 // so we'll translate without connection (which handles most cases)
@@ -125,6 +140,7 @@ impl ExtendedQueryHandler {
                     param_formats: vec![0; cached_info.param_types.len()],
                     field_descriptions: Vec::new(), // Will be populated during bind/execute
                     translation_metadata: None,
+                    result_shape: None,
                 };
                 
                 // Store as unnamed statement
@@ -144,6 +160,61 @@ impl ExtendedQueryHandler {
             return Err(PgSqliteError::Protocol("Empty query".to_string()));
         }
         
+        // COPY ... TO STDOUT, VACUUM, CLUSTER, COMMENT, schema, view, trigger and function DDL and ALTER TABLE have no parameters or row description; they run on Execute
+        if crate::query::CopyHandler::parse_copy_to(&cleaned_query)?.is_some()
+            || crate::query::SchemaHandler::parse_schema(&cleaned_query).is_some()
+            || crate::query::VacuumHandler::parse_vacuum(&cleaned_query)?.is_some()
+            || crate::query::ClusterHandler::parse_cluster(&cleaned_query)?.is_some()
+            || crate::query::CommentHandler::parse_comment(&cleaned_query)?.is_some()
+            || crate::query::ViewHandler::parse_view(&cleaned_query).is_some()
+            || crate::query::TriggerHandler::parse_trigger(&cleaned_query)?.is_some()
+            || crate::query::FunctionHandler::parse_function(&cleaned_query)?.is_some()
+            || crate::query::AlterTableHandler::parse_alter_table(&cleaned_query).is_some() {
+            session.prepared_statements.write().await.insert(name, PreparedStatement {
+                query: cleaned_query,
+                translated_query: None,
+                param_types: Vec::new(),
+                param_formats: Vec::new(),
+                field_descriptions: Vec::new(),
+                translation_metadata: None,
+                result_shape: None,
+            });
+            framed.send(BackendMessage::ParseComplete).await
+                .map_err(PgSqliteError::Io)?;
+            return Ok(());
+        }
+        
+        // pgsqlite.translate('...'), pgsqlite.enable_audit('...'), the soft delete switches, the
+        // retention functions, mask functions and the schema snapshot functions always return the same columns
+        let helper_columns = if crate::query::TranslateHandler::parse_translate_call(&cleaned_query).is_some() {
+            Some(crate::query::TranslateHandler::field_descriptions())
+        } else if crate::query::AuditHandler::parse_enable_audit_call(&cleaned_query).is_some() {
+            Some(crate::query::AuditHandler::field_descriptions())
+        } else if let Some(call) = crate::query::SoftDeleteHandler::parse_soft_delete_call(&cleaned_query) {
+            Some(crate::query::SoftDeleteHandler::field_descriptions(&call))
+        } else if let Some(call) = crate::query::MaskHandler::parse_mask_call(&cleaned_query) {
+            Some(crate::query::MaskHandler::field_descriptions(&call))
+        } else if let Some(call) = crate::query::RetentionHandler::parse_retention_call(&cleaned_query) {
+            Some(crate::query::RetentionHandler::field_descriptions(&call))
+        } else {
+            crate::query::SchemaSnapshotHandler::parse_schema_snapshot_call(&cleaned_query)
+                .map(|call| crate::query::SchemaSnapshotHandler::field_descriptions(&call))
+        };
+        if let Some(field_descriptions) = helper_columns {
+            session.prepared_statements.write().await.insert(name, PreparedStatement {
+                query: cleaned_query,
+                translated_query: None,
+                param_types: Vec::new(),
+                param_formats: Vec::new(),
+                field_descriptions,
+                translation_metadata: None,
+                result_shape: None,
+            });
+            framed.send(BackendMessage::ParseComplete).await
+                .map_err(PgSqliteError::Io)?;
+            return Ok(());
+        }
+        
         // Removed verbose debug logging for parsing
         
         // Extract cast type information BEFORE any query translation
@@ -250,6 +321,43 @@ impl ExtendedQueryHandler {
             }
         }
         
+        // SQLAlchemy's insertmanyvalues INSERT ... SELECT ... FROM (VALUES ...) AS imp_sen(...)
+        // becomes a plain multi-row INSERT, so parameter types come from the target columns
+        let query = match crate::translator::InsertManyValuesTranslator::translate_query(&cleaned_query) {
+            Some(rewritten) => {
+                cleaned_query = rewritten;
+                cleaned_query.clone()
+            }
+            None => query,
+        };
+        
+        // Interval offsets in RANGE window frames become the integer unit of the ORDER BY column
+        if crate::translator::WindowTranslator::needs_translation(&cleaned_query) {
+            cleaned_query = db.with_session_connection(&session.id, |conn| {
+                Ok(crate::translator::WindowTranslator::translate_query(&cleaned_query, Some(conn)))
+            }).await?;
+        }
+        
+        // Interval operators become the interval functions, before the datetime
+        // translator turns the interval literals into text
+        cleaned_query = db.with_session_connection(&session.id, |conn| {
+            Ok(if crate::translator::IntervalTranslator::needs_translation(&cleaned_query, conn) {
+                crate::translator::IntervalTranslator::translate_query(&cleaned_query, conn)
+            } else {
+                cleaned_query.clone()
+            })
+        }).await?;
+
+        // Row locking clauses are dropped, SQLite locks the whole database on write
+        if crate::translator::LockingClauseTranslator::needs_translation(&cleaned_query) {
+            cleaned_query = crate::translator::LockingClauseTranslator::translate_query(&cleaned_query);
+        }
+        
+        // MATERIALIZED hints are dropped from WITH queries
+        if crate::translator::CteTranslator::needs_translation(&cleaned_query) {
+            cleaned_query = crate::translator::CteTranslator::translate_query(&cleaned_query);
+        }
+        
         // Check if this is a SET command - handle it specially
         if crate::query::SetHandler::is_set_command(&cleaned_query) {
             // For SET commands, we need to create a special prepared statement
@@ -274,6 +382,7 @@ impl ExtendedQueryHandler {
                     vec![]
                 },
                 translation_metadata: None, // SET commands don't need translation metadata
+                result_shape: None,
             };
             
             session.prepared_statements.write().await.insert(name.clone(), stmt);
@@ -285,6 +394,26 @@ impl ExtendedQueryHandler {
             return Ok(());
         }
         
+        // Standalone pg_sleep() must not be probed, the function would sleep during Parse
+        if let Some(sleep_call) = crate::query::SleepHandler::parse_sleep_call(&cleaned_query) {
+            let stmt = PreparedStatement {
+                query: cleaned_query.clone(),
+                translated_query: None,
+                param_types: vec![],
+                param_formats: vec![],
+                field_descriptions: vec![crate::query::SleepHandler::field_description(&sleep_call)],
+                translation_metadata: None,
+                result_shape: None,
+            };
+
+            session.prepared_statements.write().await.insert(name.clone(), stmt);
+
+            framed.send(BackendMessage::ParseComplete).await
+                .map_err(PgSqliteError::Io)?;
+
+            return Ok(());
+        }
+
         // Check if this is a simple parameter SELECT (e.g., SELECT $1, $2)
         let is_simple_param_select = query_starts_with_ignore_case(&query, "SELECT") && 
             !query.to_uppercase().contains("FROM") && 
@@ -328,40 +457,7 @@ impl ExtendedQueryHandler {
                     });
                 } else {
                     // Need to analyze the query
-                    let (analyzed_types, original_types_opt, table_name, column_names) = if query_starts_with_ignore_case(&query, "INSERT") {
-                        match Self::analyze_insert_params(&query, db).await {
-                            Ok((types, orig_types)) => {
-                                debug!("Analyzed INSERT parameter types: {:?} (original: {:?})", types, orig_types);
-                                
-                                // Extract table and columns for caching
-                                let (table, cols) = crate::types::QueryContextAnalyzer::get_insert_column_info(&query)
-                                    .unwrap_or_else(|| (String::new(), Vec::new()));
-                                
-                                (types, Some(orig_types), Some(table), cols)
-                            }
-                            Err(_) => {
-                                // If we can't determine types, default to text
-                                let param_count = ParameterParser::count_parameters(&query);
-                                let types = vec![PgType::Text.to_oid(); param_count];
-                                (types.clone(), Some(types), None, Vec::new())
-                            }
-                        }
-                    } else if query_starts_with_ignore_case(&query, "SELECT") {
-                        let types = Self::analyze_select_params(&query, db, session).await.unwrap_or_else(|_| {
-                            // If we can't determine types, default to text
-                            let param_count = ParameterParser::count_parameters(&query);
-                            vec![PgType::Text.to_oid(); param_count]
-                        });
-                        debug!("Analyzed SELECT parameter types: {:?}", types);
-                        
-                        let table = extract_table_name_from_select(&query);
-                        (types.clone(), Some(types), table, Vec::new())
-                    } else {
-                        // Other query types - just count parameters
-                        let param_count = ParameterParser::count_parameters(&query);
-                        let types = vec![PgType::Text.to_oid(); param_count];
-                        (types.clone(), Some(types), None, Vec::new())
-                    };
+                    let (analyzed_types, original_types_opt, table_name, column_names) = Self::infer_param_types(&query, db, session).await;
                     
                     actual_param_types = analyzed_types.clone();
                     
@@ -431,6 +527,11 @@ impl ExtendedQueryHandler {
             translation_metadata.merge(metadata);
         }
         
+        // JSON operators are translated at execute time, but aliased -> / ->>
+        // expressions need their result types for the field descriptions
+        #[cfg(not(feature = "unified_processor"))] // Skip when using unified processor
+        translation_metadata.merge(crate::translator::JsonTranslator::extract_operator_metadata(&translated_for_analysis));
+        
         // Translate catalog functions (remove pg_catalog prefix)
         #[cfg(not(feature = "unified_processor"))] // Skip when using unified processor
         {
@@ -439,6 +540,54 @@ impl ExtendedQueryHandler {
             translated_for_analysis = PgTableIsVisibleTranslator::translate(&translated_for_analysis);
         }
         
+        // Translate FETCH FIRST / OFFSET n ROWS pagination to LIMIT/OFFSET
+        #[cfg(not(feature = "unified_processor"))] // Skip when using unified processor
+        if crate::translator::PaginationTranslator::needs_translation(&translated_for_analysis) {
+            translated_for_analysis = crate::translator::PaginationTranslator::translate_query(&translated_for_analysis);
+        }
+        
+        // Move the ORDER BY of WITHIN GROUP into percentile_cont/percentile_disc/mode calls
+        #[cfg(not(feature = "unified_processor"))] // Skip when using unified processor
+        if crate::translator::WithinGroupTranslator::needs_translation(&translated_for_analysis) {
+            translated_for_analysis = crate::translator::WithinGroupTranslator::translate_query(&translated_for_analysis);
+        }
+        
+        // Spell out PostgreSQL's default NULL ordering (NULLS LAST for ASC, NULLS FIRST for DESC)
+        #[cfg(not(feature = "unified_processor"))] // Skip when using unified processor
+        if crate::translator::NullOrderingTranslator::needs_translation(&translated_for_analysis) {
+            translated_for_analysis = crate::translator::NullOrderingTranslator::translate_query(&translated_for_analysis);
+        }
+        
+        // Expand regexp_split_to_table() and string_to_table() in FROM to json_each()
+        #[cfg(not(feature = "unified_processor"))] // Skip when using unified processor
+        if crate::translator::UnnestTranslator::contains_split_function(&translated_for_analysis) {
+            translated_for_analysis = crate::translator::UnnestTranslator::translate_split_functions(&translated_for_analysis);
+        }
+        
+        // Rewrite bit string literals and bitwise operators
+        #[cfg(not(feature = "unified_processor"))] // Skip when using unified processor
+        if crate::translator::BitTranslator::needs_translation(&translated_for_analysis) {
+            translated_for_analysis = crate::translator::BitTranslator::translate_query(&translated_for_analysis);
+        }
+        
+        // Rewrite inet/cidr containment operators, which SQLite reads as bit shifts
+        #[cfg(not(feature = "unified_processor"))] // Skip when using unified processor
+        if crate::translator::NetworkTranslator::needs_translation(&translated_for_analysis) {
+            translated_for_analysis = crate::translator::NetworkTranslator::translate_query(&translated_for_analysis);
+        }
+        
+        // Rewrite XMLPARSE and XMLSERIALIZE as function calls
+        #[cfg(not(feature = "unified_processor"))] // Skip when using unified processor
+        if crate::translator::XmlTranslator::needs_translation(&translated_for_analysis) {
+            translated_for_analysis = crate::translator::XmlTranslator::translate_query(&translated_for_analysis);
+        }
+        
+        // Translate standalone VALUES and (VALUES ...) AS t(cols) to SELECT ... UNION ALL
+        #[cfg(not(feature = "unified_processor"))] // Skip when using unified processor
+        if crate::translator::ValuesTranslator::needs_translation(&translated_for_analysis) {
+            translated_for_analysis = crate::translator::ValuesTranslator::translate_query(&translated_for_analysis);
+        }
+        
         // Translate array operators with metadata
         #[cfg(not(feature = "unified_processor"))] // Skip when using unified processor
         {
@@ -458,7 +607,15 @@ impl ExtendedQueryHandler {
                 }
             }
         }
-        
+
+        // Expand set-returning functions in select lists into lateral joins
+        #[cfg(not(feature = "unified_processor"))] // Skip when using unified processor
+        if crate::translator::SetReturningTranslator::needs_translation(&translated_for_analysis) {
+            let (translated, metadata) = crate::translator::SetReturningTranslator::translate_query(&translated_for_analysis);
+            translated_for_analysis = translated;
+            translation_metadata.merge(metadata);
+        }
+
         // Translate json_each()/jsonb_each() functions for PostgreSQL compatibility
         #[cfg(not(feature = "unified_processor"))] // Skip when using unified processor
         {
@@ -493,15 +650,29 @@ impl ExtendedQueryHandler {
         translation_metadata.merge(metadata);
         }
         
+        // Expand crosstab() in FROM to json_each() over its pivoted rows
+        #[cfg(not(feature = "unified_processor"))] // Skip when using unified processor
+        if crate::translator::CrosstabTranslator::needs_translation(&translated_for_analysis) {
+            translated_for_analysis = crate::translator::CrosstabTranslator::translate_query(&translated_for_analysis);
+        }
+        
+        // Translate ROW(...) constructors, (col).field access and composite row_to_json() members
+        #[cfg(not(feature = "unified_processor"))] // Skip when using unified processor
+        if crate::translator::CompositeTranslator::needs_translation(&translated_for_analysis) {
+            translated_for_analysis = db.with_session_connection(&session.id, |conn| {
+                Ok(crate::translator::CompositeTranslator::translate_query(&translated_for_analysis, conn))
+            }).await?;
+        }
+        
         // Note: System function processing (like to_regtype) is handled during Execute phase
         // after parameter substitution, not during Parse phase
         
-        // Analyze arithmetic expressions for type metadata
-        #[cfg(not(feature = "unified_processor"))] // Skip when using unified processor
-        if crate::translator::ArithmeticAnalyzer::needs_analysis(&translated_for_analysis) {
-            let arithmetic_metadata = crate::translator::ArithmeticAnalyzer::analyze_query(&translated_for_analysis);
-            translation_metadata.merge(arithmetic_metadata);
-            debug!("Found {} arithmetic type hints", translation_metadata.column_mappings.len());
+        // Check recursive CTEs and type the columns projected out of CTEs
+        if crate::translator::CteTranslator::needs_translation(&cleaned_query) {
+            let cte_metadata = db.with_session_connection(&session.id, |conn| {
+                Ok(crate::translator::CteTranslator::analyze(&cleaned_query, conn))
+            }).await??;
+            translation_metadata.merge(cte_metadata);
         }
         
         // For now, we'll just analyze the query to get field descriptions
@@ -509,7 +680,10 @@ impl ExtendedQueryHandler {
         info!("Analyzing query '{}' for field descriptions", translated_for_analysis);
         info!("Original query: {}", cleaned_query);
         info!("Is simple param select: {}", is_simple_param_select);
-        let field_descriptions = if query_starts_with_ignore_case(&cleaned_query, "SELECT") {
+        // Standalone VALUES statements are translated to SELECT above
+        let field_descriptions = if query_starts_with_ignore_case(&cleaned_query, "SELECT")
+            || query_starts_with_ignore_case(&cleaned_query, "VALUES")
+            || crate::translator::CteTranslator::is_select(&cleaned_query) {
             // Don't try to get field descriptions if this is a catalog query
             // These queries are handled specially and don't need real field info
             if cleaned_query.contains("pg_catalog") || cleaned_query.contains("pg_type") || 
@@ -524,9 +698,20 @@ impl ExtendedQueryHandler {
                 let mut test_query = translated_for_analysis.to_string();
                 let param_count = ParameterParser::count_parameters(&translated_for_analysis);
                 
+                // JSON operators are only translated at execute time; SQLite understands
+                // -> and ->> natively but not the remaining ones
+                if crate::translator::JsonTranslator::contains_json_operations(&test_query)
+                    && let Ok(translated) = crate::translator::JsonTranslator::translate_json_operators(&test_query) {
+                    test_query = translated;
+                }
+                
                 if param_count > 0 {
                     // Replace parameters with dummy values using proper parser
-                    let dummy_values = vec!["NULL".to_string(); param_count];
+                    // SQLite rejects NULL as a LIMIT/OFFSET count, so use 0 for those
+                    let pagination_params = crate::translator::PaginationTranslator::pagination_parameters(&translated_for_analysis);
+                    let dummy_values: Vec<String> = (1..=param_count)
+                        .map(|i| if pagination_params.contains(&i) { "0" } else { "NULL" }.to_string())
+                        .collect();
                     test_query = ParameterParser::substitute_parameters(&test_query, &dummy_values)
                         .unwrap_or(test_query); // Fall back to original if substitution fails
                 }
@@ -540,116 +725,36 @@ impl ExtendedQueryHandler {
                 let cast_regex = regex::Regex::new(r"::[a-zA-Z]\w*").unwrap();
                 test_query = cast_regex.replace_all(&test_query, "").to_string();
                 
-                // Add LIMIT 1 to avoid processing too much data, but only if there's no existing LIMIT
-                if !test_query.to_uppercase().contains(" LIMIT ") {
-                    test_query = format!("{test_query} LIMIT 1");
-                }
-                let cached_conn = Self::get_or_cache_connection(session, db).await;
-                let test_response = db.query_with_session_cached(&test_query, &session.id, cached_conn.as_ref()).await;
+                // Prepare the statement without stepping it: describing runs nothing
+                let described = db.describe_with_session(&test_query, &session.id).await;
                 
-                match test_response {
-                    Ok(response) => {
-                        info!("Test query returned {} columns: {:?}", response.columns.len(), response.columns);
-                        // Extract table name from query to look up schema
-                        let table_name = extract_table_name_from_select(&query);
+                match described {
+                    Ok(described) => {
+                        let (columns, column_types): (Vec<String>, Vec<Option<String>>) = described.into_iter().unzip();
+                        info!("Described {} columns: {:?}", columns.len(), columns);
+                        // Type the result columns from the query's AST; translations that
+                        // change the column count leave the types to the fallbacks below
+                        let checked_types = db.with_session_connection(&session.id, |conn| {
+                            Ok(crate::types::TypeChecker::new(conn).result_column_types(&cleaned_query))
+                        }).await.ok().flatten()
+                            .filter(|types| types.len() == columns.len())
+                            .unwrap_or_default();
+                        info!("Type checker inferred column types: {:?}", checked_types);
                         
-                        // Pre-fetch schema types for all columns if we have a table name
-                        let mut schema_types = std::collections::HashMap::new();
-                        if let Some(ref table) = table_name {
-                            // For aliased columns, try to find the source column
-                            for col_name in &response.columns {
-                                // First try direct lookup
-                                if let Ok(Some(pg_type)) = db.get_schema_type_with_session(&session.id, table, col_name).await {
-                                    schema_types.insert(col_name.clone(), pg_type);
-                                } else {
-                                    // Parse the query to find the source column for this alias
-                                    // Look for pattern like "table.column AS alias" in the SELECT clause
-                                    let pattern = format!(r"(?i)(\w+)\.(\w+)\s+AS\s+{}", regex::escape(col_name));
-                                    // Checking alias pattern
-                                    if let Ok(re) = regex::Regex::new(&pattern)
-                                        && let Some(captures) = re.captures(&query)
-                                            && let Some(src_table) = captures.get(1)
-                                                && let Some(src_col) = captures.get(2) {
-                                                    let src_table_name = src_table.as_str();
-                                                    let src_col_name = src_col.as_str();
-                                                    // Only use if it's the same table we identified
-                                                    if src_table_name == table
-                                                        && let Ok(Some(pg_type)) = db.get_schema_type_with_session(&session.id, table, src_col_name).await {
-                                                            info!("Found type for aliased column '{}' from query pattern '{}.{}' in table '{}': {}", col_name, src_table_name, src_col_name, table, pg_type);
-                                                            schema_types.insert(col_name.clone(), pg_type);
-                                                            continue;
-                                                        }
-                                                }
-                                        // First check translation metadata
-                                    if let Some(hint) = translation_metadata.get_hint(col_name) {
-                                        // For datetime expressions, check if we have a source column and prefer its type
-                                        if let Some(ref source_col) = hint.source_column {
-                                            if let Ok(Some(source_type)) = db.get_schema_type_with_session(&session.id, table, source_col).await {
-                                                info!("Found source column type for datetime expression '{}' -> '{}': {}", col_name, source_col, source_type);
-                                                schema_types.insert(col_name.clone(), source_type);
-                                            } else if let Some(suggested_type) = &hint.suggested_type {
-                                                info!("Using suggested type for datetime expression '{}': {:?}", col_name, suggested_type);
-                                                // Convert PgType to the string format used in schema
-                                                let type_string = match suggested_type {
-                                                    crate::types::PgType::Float8 => "DOUBLE PRECISION",
-                                                    crate::types::PgType::Float4 => "REAL",
-                                                    crate::types::PgType::Int4 => "INTEGER",
-                                                    crate::types::PgType::Int8 => "BIGINT",
-                                                    crate::types::PgType::Text => "TEXT",
-                                                    crate::types::PgType::Date => "DATE",
-                                                    crate::types::PgType::Time => "TIME",
-                                                    crate::types::PgType::Timestamp => "TIMESTAMP",
-                                                    crate::types::PgType::Timestamptz => "TIMESTAMPTZ",
-                                                    crate::types::PgType::TextArray => "TEXT[]",
-                                                    _ => "TEXT", // Default to TEXT for unknown types
-                                                };
-                                                schema_types.insert(col_name.clone(), type_string.to_string());
-                                            }
-                                        } else if let Some(suggested_type) = &hint.suggested_type {
-                                            info!("Found type hint from translation for '{}': {:?}", col_name, suggested_type);
-                                            // Convert PgType to the string format used in schema
-                                            let type_string = match suggested_type {
-                                                crate::types::PgType::Float8 => "DOUBLE PRECISION",
-                                                crate::types::PgType::Float4 => "REAL",
-                                                crate::types::PgType::Int4 => "INTEGER",
-                                                crate::types::PgType::Int8 => "BIGINT",
-                                                crate::types::PgType::Text => "TEXT",
-                                                crate::types::PgType::Date => "DATE",
-                                                crate::types::PgType::Time => "TIME",
-                                                crate::types::PgType::Timestamp => "TIMESTAMP",
-                                                crate::types::PgType::Timestamptz => "TIMESTAMPTZ",
-                                                _ => "TEXT", // Default to TEXT for unknown types
-                                            };
-                                            schema_types.insert(col_name.clone(), type_string.to_string());
-                                        }
-                                    } else {
-                                        // Try to find source table and column if this is an alias
-                                        info!("Attempting to extract source for alias: '{}' from query: {}", col_name, cleaned_query);
-                                        if let Some((source_table, source_col)) = Self::extract_source_table_column_for_alias(&cleaned_query, col_name) {
-                                            info!("Successfully resolved alias '{}' -> table '{}', column '{}'", col_name, source_table, source_col);
-                                            if let Ok(Some(pg_type)) = db.get_schema_type_with_session(&session.id, &source_table, &source_col).await {
-                                                info!("Found schema type for alias '{}' -> source column '{}.{}': {}", col_name, source_table, source_col, pg_type);
-                                                schema_types.insert(col_name.clone(), pg_type);
-                                            } else {
-                                                info!("No schema type found for '{}.{}'", source_table, source_col);
-                                            }
-                                        } else {
-                                            info!("Could not extract source table/column for alias '{}' from query", col_name);
-                                        }
-                                    }
-                                }
-                            }
-                        }
+                        // Extract table name from query to look up columns the type checker can't follow
+                        let table_name = extract_table_name_from_select(&query);
                         
-                        // Try to infer types from the first row if available
-                        // We need to handle this asynchronously for schema lookup
                         let mut inferred_types = Vec::new();
+                        // Run only if some column's type is left to its value
+                        let mut first_row = None;
                         
-                        for (i, col_name) in response.columns.iter().enumerate() {
+                        for (i, col_name) in columns.iter().enumerate() {
                             let inferred_type = {
-                                // First priority: Check if this column has an explicit cast
+                                // First priority: Check if this column has an explicit cast; a cast
+                                // inside a larger expression leaves the type to the type checker
                                 if let Some(cast_type) = cast_info.get(&i) {
-                                    Self::cast_type_to_oid(cast_type)
+                                    checked_types.get(i).copied().flatten()
+                                        .map_or_else(|| Self::cast_type_to_oid(cast_type), |pg_type| pg_type.to_oid())
                                 }
                                 // For parameter columns (NULL from SELECT $1), try to match with parameters
                                 else if col_name == "NULL" || col_name == "?column?" {
@@ -692,63 +797,16 @@ impl ExtendedQueryHandler {
                                             PgType::Text.to_oid()
                                         }
                                     } else {
-                                        // For other queries with NULL columns, default to TEXT
-                                        PgType::Text.to_oid()
+                                        // Other NULL columns take the type checker's type, or TEXT
+                                        checked_types.get(i).copied().flatten().unwrap_or(PgType::Text).to_oid()
                                     }
                                 }
                                 // Second priority: Check translation metadata for type hints
-                                else if let Some(hint) = translation_metadata.get_hint(col_name) {
-                                    // FIRST: Check for arithmetic expressions on float columns
-                                    if hint.expression_type == Some(crate::translator::ExpressionType::ArithmeticOnFloat) {
-                                        debug!("Arithmetic expression '{}' detected with ArithmeticOnFloat hint, returning FLOAT8", col_name);
-                                        // For arithmetic on REAL/FLOAT columns, always return FLOAT8
-                                        PgType::Float8.to_oid()
-                                    } else if let Some(suggested_type) = &hint.suggested_type {
-                                        debug!("Using type hint from translation metadata for '{}': {:?}", col_name, suggested_type);
-                                        suggested_type.to_oid()
-                                    } else if hint.is_expression {
-                                        // For other arithmetic expressions without suggested type, infer from source column
-                                        if let Some(source_col) = &hint.source_column {
-                                            // Extract table.column if present, or use the table from context
-                                            let (source_table, source_column) = if source_col.contains('.') {
-                                                let parts: Vec<&str> = source_col.split('.').collect();
-                                                (parts[0], parts[1])
-                                            } else if let Some(ref table) = table_name {
-                                                (table.as_str(), source_col.as_str())
-                                            } else {
-                                                info!("Arithmetic expression '{}' has no table context and source column '{}' has no table prefix", col_name, source_col);
-                                                ("", source_col.as_str()) // Will likely fail, but try anyway
-                                            };
-                                            
-                                            if let Ok(Some(source_type_str)) = db.get_schema_type_with_session(&session.id, source_table, source_column).await {
-                                                // Convert type string to OID for comparison
-                                                let source_type_oid = crate::types::SchemaTypeMapper::pg_type_string_to_oid(&source_type_str);
-                                                
-                                                // Determine arithmetic result type based on source column type
-                                                let arithmetic_result_type = match source_type_oid {
-                                                    1700 => PgType::Numeric, // NUMERIC arithmetic = NUMERIC
-                                                    23 | 21 | 20 => PgType::Numeric, // INTEGER arithmetic often = NUMERIC for safety
-                                                    700 | 701 => PgType::Float8, // FLOAT arithmetic = FLOAT
-                                                    _ => PgType::Float8, // Default to FLOAT8 for unknown types
-                                                };
-                                                info!("Resolved arithmetic expression '{}' from source '{}' (type {} -> OID {}) -> {:?}", 
-                                                      col_name, source_col, source_type_str, source_type_oid, arithmetic_result_type);
-                                                arithmetic_result_type.to_oid()
-                                            } else {
-                                                info!("Could not resolve source column type for arithmetic expression '{}'", col_name);
-                                                0 // Will be handled below
-                                            }
-                                        } else {
-                                            info!("Arithmetic expression '{}' has no source column", col_name);
-                                            0 // Will be handled below  
-                                        }
-                                    } else {
-                                        info!("No type hint found in translation metadata for '{}'", col_name);
-                                        // Continue to next priority
-                                        0 // Will be handled below
-                                    }
+                                else if let Some(suggested_type) = translation_metadata.get_hint(col_name)
+                                    .and_then(|hint| hint.suggested_type) {
+                                    debug!("Using type hint from translation metadata for '{}': {:?}", col_name, suggested_type);
+                                    suggested_type.to_oid()
                                 } else {
-                                    info!("No type hint found in translation metadata for '{}'", col_name);
                                     0 // Will be handled below
                                 }
                             };
@@ -759,136 +817,54 @@ impl ExtendedQueryHandler {
                                 continue;
                             }
                             
-                            // Third priority: Check schema table for stored type mappings
-                            if let Some(pg_type) = schema_types.get(col_name) {
-                                // Use basic type OID mapping (enum checking would require async which isn't allowed in closure)
-                                let type_oid = crate::types::SchemaTypeMapper::pg_type_string_to_oid(pg_type);
-                                inferred_types.push(type_oid);
+                            // Third priority: the type checker's type for the column
+                            if let Some(Some(pg_type)) = checked_types.get(i) {
+                                inferred_types.push(pg_type.to_oid());
                                 continue;
                             }
                             
-                            // Third priority: Check for aggregate functions
-                            let col_lower = col_name.to_lowercase();
-                            if let Some(oid) = crate::types::SchemaTypeMapper::get_aggregate_return_type_with_query(&col_lower, None, None, Some(&cleaned_query)) {
-                                info!("Column '{}' identified with type OID {} from aggregate detection", col_name, oid);
-                                inferred_types.push(oid);
-                                continue;  // Important: continue here to prevent value-based inference from overriding
+                            // Fourth priority: the table column SQLite says the result column comes from
+                            if let Some(pg_type) = column_types.get(i).cloned().flatten() {
+                                inferred_types.push(crate::types::SchemaTypeMapper::pg_type_string_to_oid(&pg_type));
+                                continue;
                             }
                             
-                            // Check if this looks like a numeric result column based on the translated query
-                            // For arithmetic operations that result in decimal functions, the cleaned_query
-                            // might contain patterns like "decimal_mul(...) AS col_name"
-                            if cleaned_query.contains("decimal_mul") || cleaned_query.contains("decimal_add") || 
-                               cleaned_query.contains("decimal_sub") || cleaned_query.contains("decimal_div") {
-                                // This query uses decimal arithmetic functions
-                                // Check if this column might be the result
-                                if col_name.contains("total") || col_name.contains("sum") || 
-                                   col_name.contains("price") || col_name.contains("amount") ||
-                                   col_name == "?column?" {
-                                    info!("Column '{}' appears to be result of decimal arithmetic", col_name);
-                                    inferred_types.push(PgType::Numeric.to_oid());
+                            // Fifth priority: a column of the FROM table by that name
+                            if let Some(ref table) = table_name {
+                                // lag/lead/first_value/last_value/nth_value have the type of the column they read
+                                let source_col = crate::translator::WindowTranslator::value_function_column(col_name, &cleaned_query)
+                                    .unwrap_or_else(|| col_name.clone());
+                                if let Ok(Some(pg_type)) = db.get_schema_type_with_session(&session.id, table, &source_col).await {
+                                    inferred_types.push(crate::types::SchemaTypeMapper::pg_type_string_to_oid(&pg_type));
                                     continue;
                                 }
                             }
                             
-                            // Fourth priority: For expressions, try to infer from SQLite's type affinity
-                            // SQLite will tell us the actual type of the expression result
+                            // Sixth priority: Check for aggregate functions
+                            let col_lower = col_name.to_lowercase();
+                            if let Some(oid) = crate::types::SchemaTypeMapper::get_aggregate_return_type_with_query(&col_lower, None, None, Some(&cleaned_query)) {
+                                info!("Column '{}' identified with type OID {} from aggregate detection", col_name, oid);
+                                inferred_types.push(oid);
+                                continue;
+                            }
                             
-                            // Last resort: Try to infer from value if we have data
-                            if !response.rows.is_empty() {
-                                if let Some(value) = response.rows[0].get(i) {
-                                    let value_str = value.as_ref().and_then(|v| std::str::from_utf8(v).ok()).unwrap_or("<non-utf8>");
-                                    let inferred_type = crate::types::SchemaTypeMapper::infer_type_from_value(value.as_deref());
-                                    info!("Column '{}': inferring type from value '{}' -> type OID {}", col_name, value_str, inferred_type);
-                                    inferred_types.push(inferred_type);
-                                } else {
-                                    info!("Column '{}': NULL value, defaulting to text", col_name);
-                                    inferred_types.push(PgType::Text.to_oid()); // text for NULL
-                                }
-                            } else {
-                                // **THIS IS THE KEY FIX**: Instead of defaulting to TEXT, try schema lookup
-                                info!("Column '{}': no data rows, attempting schema-based type inference", col_name);
-                                
-                                // Try to extract source table.column from the query
-                                if let Some((source_table, source_col)) = Self::extract_source_table_column_for_alias(&cleaned_query, col_name) {
-                                    info!("Resolved alias '{}' -> table '{}', column '{}'", col_name, source_table, source_col);
-                                    
-                                    // Use session-aware schema lookup to see uncommitted data
-                                    match db.get_schema_type_with_session(&session.id, &source_table, &source_col).await {
-                                        Ok(Some(pg_type_str)) => {
-                                            let type_oid = crate::types::SchemaTypeMapper::pg_type_string_to_oid(&pg_type_str);
-                                            info!("Column '{}': resolved type from schema '{}.{}' -> {} (OID {})", 
-                                                  col_name, source_table, source_col, pg_type_str, type_oid);
-                                            inferred_types.push(type_oid);
-                                        }
-                                        Ok(None) => {
-                                            info!("Column '{}': no schema type found for '{}.{}', defaulting to text", 
-                                                  col_name, source_table, source_col);
-                                            inferred_types.push(PgType::Text.to_oid());
-                                        }
-                                        Err(_) => {
-                                            // Schema lookup error, defaulting to text
-                                            inferred_types.push(PgType::Text.to_oid());
-                                        }
-                                    }
-                                } else {
-                                    // Could not extract source table.column, try to infer from query structure
-                                    info!("Column '{}': could not extract source table.column, analyzing query structure", col_name);
-                                    
-                                    // First, try to handle table_column pattern like "orders_total_amount"
-                                    let mut type_found = false;
-                                    if col_name.contains('_')
-                                        && let Some(underscore_pos) = col_name.find('_') {
-                                            let potential_table = &col_name[..underscore_pos];
-                                            let potential_column = &col_name[underscore_pos + 1..];
-                                            
-                                            // Try to look up the type from schema
-                                            match db.get_schema_type_with_session(&session.id, potential_table, potential_column).await {
-                                                Ok(Some(pg_type_str)) => {
-                                                    let type_oid = crate::types::SchemaTypeMapper::pg_type_string_to_oid(&pg_type_str);
-                                                    info!("Column '{}': resolved type from table_column pattern '{}_{}'-> {} (OID {})", 
-                                                          col_name, potential_table, potential_column, pg_type_str, type_oid);
-                                                    inferred_types.push(type_oid);
-                                                    type_found = true;
-                                                }
-                                                _ => {
-                                                    // Pattern didn't match a real table.column
-                                                }
-                                            }
-                                        }
-                                    
-                                    if !type_found {
-                                        // For simple SELECT queries, try to extract table from FROM clause and assume column exists
-                                        if let Some(table_name) = extract_table_name_from_select(&cleaned_query) {
-                                            info!("Column '{}': extracted table '{}' from FROM clause, assuming column exists", col_name, table_name);
-                                            
-                                            match db.get_schema_type_with_session(&session.id, &table_name, col_name).await {
-                                                Ok(Some(pg_type_str)) => {
-                                                    let type_oid = crate::types::SchemaTypeMapper::pg_type_string_to_oid(&pg_type_str);
-                                                    info!("Column '{}': resolved type from schema '{}.{}' -> {} (OID {})", 
-                                                          col_name, table_name, col_name, pg_type_str, type_oid);
-                                                    inferred_types.push(type_oid);
-                                                }
-                                                Ok(None) => {
-                                                    info!("Column '{}': no schema type found for '{}.{}', defaulting to text", 
-                                                          col_name, table_name, col_name);
-                                                    inferred_types.push(PgType::Text.to_oid());
-                                                }
-                                                Err(_) => {
-                                                    // Schema lookup error, defaulting to text
-                                                    inferred_types.push(PgType::Text.to_oid());
-                                                }
-                                            }
-                                        } else {
-                                            info!("Column '{}': could not extract table name from query, defaulting to text", col_name);
-                                            inferred_types.push(PgType::Text.to_oid());
-                                        }
-                                    }
-                                }
+                            // Last resort: the storage class of the value in the first row,
+                            // for read-only statements
+                            if first_row.is_none() {
+                                first_row = Some(db.first_row_if_read_only(&test_query, &session.id).await.ok().flatten());
                             }
+                            let pg_type = match first_row.as_ref().and_then(|row| row.as_ref()).and_then(|row| row.get(i)) {
+                                Some(rusqlite::types::Value::Integer(value)) if i32::try_from(*value).is_ok() => PgType::Int4,
+                                Some(rusqlite::types::Value::Integer(_)) => PgType::Int8,
+                                Some(rusqlite::types::Value::Real(_)) => PgType::Float8,
+                                Some(rusqlite::types::Value::Blob(_)) => PgType::Bytea,
+                                _ => PgType::Text,
+                            };
+                            info!("Column '{}': typed {:?} from its value", col_name, pg_type);
+                            inferred_types.push(pg_type.to_oid());
                         }
                         
-                        let fields = response.columns.iter()
+                        let fields = columns.iter()
                             .enumerate()
                             .map(|(i, col_name)| FieldDescription {
                                 name: col_name.clone(),
@@ -901,19 +877,6 @@ impl ExtendedQueryHandler {
                             })
                             .collect::<Vec<_>>();
                         
-                        // Special logging for orders queries
-                        if cleaned_query.contains("orders") {
-                            info!("PARSE: Orders query detected!");
-                            info!("PARSE: Query: {}", cleaned_query);
-                            info!("PARSE: Field descriptions created:");
-                            for field in &fields {
-                                info!("PARSE:   {} -> type OID {}", field.name, field.type_oid);
-                                if field.name.contains("total_amount") && field.type_oid == 25 {
-                                    info!("PARSE:   ^^^ BUG: total_amount has TEXT type!");
-                                }
-                            }
-                        }
-                        
                         info!("Parsed {} field descriptions from query with inferred types", fields.len());
                         fields
                     }
@@ -923,6 +886,9 @@ impl ExtendedQueryHandler {
                     }
                 }
             }
+        } else if ReturningTranslator::has_returning_clause(&cleaned_query) {
+            // DML with RETURNING produces rows, so Describe must report them up front
+            Self::describe_returning_fields(db, session, &cleaned_query).await
         } else {
             Vec::new()
         };
@@ -946,6 +912,14 @@ impl ExtendedQueryHandler {
         
         info!("Final param_types for statement: {:?}", actual_param_types);
         
+        // Remember the result columns the client is told about, so Execute can tell
+        // whether DDL changed them in the meantime
+        let result_shape = if field_descriptions.is_empty() {
+            None
+        } else {
+            Self::result_shape(db, session, &translated_for_analysis).await
+        };
+        
         // Store the prepared statement
         // We already translated the query above for analysis, so just use that
         let translated_query = Some(translated_for_analysis);
@@ -961,8 +935,20 @@ impl ExtendedQueryHandler {
             } else {
                 Some(translation_metadata)
             },
+            result_shape,
         };
         
+        if session.trace_enabled() {
+            QueryTrace::emit(framed, session, format!("parse: {cleaned_query}")).await?;
+            QueryTrace::translated(framed, session, &cleaned_query, stmt.translated_query.as_deref().unwrap_or(&cleaned_query)).await?;
+            let columns: Vec<String> = stmt.field_descriptions.iter()
+                .map(|f| format!("{} {}", f.name, f.type_oid))
+                .collect();
+            QueryTrace::emit(framed, session, format!(
+                "types: parameters {:?}, columns [{}]", stmt.param_types, columns.join(", ")
+            )).await?;
+        }
+        
         session.prepared_statements.write().await.insert(name.clone(), stmt);
         
         // Send ParseComplete
@@ -972,83 +958,6 @@ impl ExtendedQueryHandler {
         Ok(())
     }
     
-    /// Try to extract the source table and column for an alias in a simple SELECT
-    /// e.g., "SELECT test_users.id AS users_id" -> returns ("test_users", "id")
-    /// e.g., "SELECT id AS event_id FROM test_events" -> returns ("test_events", "id")
-    fn extract_source_table_column_for_alias(query: &str, alias: &str) -> Option<(String, String)> {
-        // This is a simple heuristic for the common case
-        // Look for "SELECT <expr> as <alias>" pattern
-        info!("extract_source_table_column_for_alias: Looking for alias '{}' in query", alias);
-        let query_upper = query.to_uppercase();
-        let alias_upper = alias.to_uppercase();
-        
-        // Find "AS <alias>" in the query
-        let as_pattern = format!(" AS {alias_upper}");
-        info!("Looking for pattern: '{}'", as_pattern);
-        if let Some(as_pos) = query_upper.find(&as_pattern) {
-            info!("Found AS pattern at position {}", as_pos);
-            // Work backwards to find the start of the expression
-            let before_as = &query[..as_pos];
-            
-            // Find the column expression before AS
-            // Handle both "table.column" and "column" patterns
-            
-            // Get the last token/word before AS (handling commas and spaces)
-            let trimmed = before_as.trim_end();
-            info!("Text before AS (trimmed): '{}'", trimmed);
-            
-            // Find where this expression starts (after SELECT or comma)
-            let mut expr_start = 0;
-            let select_upper = "SELECT ";
-            
-            // Look for the last comma or SELECT keyword
-            if let Some(comma_pos) = trimmed.rfind(',') {
-                expr_start = comma_pos + 1;
-                info!("Found comma at position {}, expr_start = {}", comma_pos, expr_start);
-            } else if let Some(select_pos) = query_upper.find(select_upper) {
-                expr_start = select_pos + select_upper.len();
-                info!("Found SELECT at position {}, expr_start = {}", select_pos, expr_start);
-            }
-            
-            // Make sure expr_start is within bounds
-            if expr_start >= trimmed.len() {
-                info!("expr_start {} >= trimmed.len() {}, using 0", expr_start, trimmed.len());
-                expr_start = 0;
-            }
-            
-            let expr = trimmed[expr_start..].trim();
-            info!("Extracted expression: '{}'", expr);
-            
-            // Check if it contains a dot (table.column)
-            if let Some(dot_pos) = expr.rfind('.') {
-                let table_part = expr[..dot_pos].trim();
-                let column_part = expr[dot_pos + 1..].trim();
-                info!("Found table.column pattern: '{}' . '{}'", table_part, column_part);
-                
-                // Validate both parts are simple identifiers
-                if table_part.chars().all(|c| c.is_alphanumeric() || c == '_') &&
-                   column_part.chars().all(|c| c.is_alphanumeric() || c == '_') {
-                    info!("Successfully parsed alias '{}' -> table '{}', column '{}'", alias, table_part, column_part);
-                    return Some((table_part.to_string(), column_part.to_string()));
-                }
-            }
-            // For simple column references without table prefix
-            else if expr.chars().all(|c| c.is_alphanumeric() || c == '_') {
-                // Try to extract table from FROM clause
-                if let Some(table_name) = extract_table_name_from_select(query) {
-                    info!("Successfully parsed alias '{}' -> table '{}' (from FROM clause), column '{}'", alias, table_name, expr);
-                    return Some((table_name, expr.to_string()));
-                }
-            }
-            
-            info!("Failed to parse expression '{}' - not a valid table.column or simple column", expr);
-        } else {
-            info!("AS pattern not found for alias '{}'", alias);
-        }
-        
-        None
-    }
-    
     pub async fn handle_bind<T>(
         framed: &mut Framed<T, crate::protocol::PostgresCodec>,
         session: &Arc<SessionState>,
@@ -1203,7 +1112,93 @@ impl ExtendedQueryHandler {
     
     pub async fn handle_execute<T>(
         framed: &mut Framed<T, crate::protocol::PostgresCodec>,
-        db: &Arc<DbHandler>,
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        portal: String,
+        max_rows: i32,
+    ) -> Result<(), PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        let trace = session.trace_enabled();
+        let (query, translated) = match session.portals.read().await.get(&portal) {
+            Some(p) => (Some(p.query.clone()), p.translated_query.clone().filter(|_| trace)),
+            None => (None, None),
+        };
+        if trace && let Some(query) = &query {
+            QueryTrace::emit(framed, session, format!("execute: {}", translated.as_deref().unwrap_or(query))).await?;
+        }
+        framed.codec_mut().take_completed_rows();
+        let event = query.as_deref().and_then(|query| crate::query::event_log::EventLog::statement_started(session, query));
+        if let Some(query) = &query {
+            crate::session::backend_registry::query_started(session.backend_pid, query, session.statement_timeout());
+            // A DELETE on a soft-delete table runs as an UPDATE, see SoftDeleteHandler
+            if crate::query::SoftDeleteHandler::is_soft_delete(query) {
+                framed.codec_mut().report_updates_as(Some("DELETE"));
+            }
+        }
+        let started = std::time::Instant::now();
+        let mut result = Self::execute_portal(framed, db, session, portal, max_rows).await;
+        framed.codec_mut().report_updates_as(None);
+        if crate::session::backend_registry::query_finished(session.backend_pid) {
+            result = result.map_err(PgSqliteError::into_statement_timeout);
+        }
+        // The statement's transaction ended: txid_current() hands out a new id, and transaction-level advisory locks are released
+        if !session.in_transaction().await {
+            crate::session::backend_registry::transaction_finished(session.backend_pid);
+        }
+        if let Some(query) = &query {
+            crate::cache::CatalogCache::statement_finished(session, query);
+        }
+        let rows = framed.codec_mut().take_completed_rows();
+        if result.is_ok() && let Some(query) = &query {
+            crate::query::statement_stats::record(query, started.elapsed(), rows);
+        }
+        crate::query::event_log::EventLog::statement_finished(session, event, started.elapsed(), rows, &result);
+        if trace {
+            QueryTrace::finished(framed, session, started, &result).await?;
+        }
+        result
+    }
+    
+    /// The result columns SQLite reports for a query, or None if it can't be prepared as it is
+    async fn result_shape(db: &Arc<dyn SessionStorage>, session: &Arc<SessionState>, query: &str) -> Option<ResultShape> {
+        let generation = crate::cache::CatalogCache::generation();
+        let columns = db.with_session_connection(&session.id, |conn| {
+            Ok(DbHandler::prepared_columns(conn, query).ok())
+        }).await.ok()??;
+        Some(ResultShape { generation, columns })
+    }
+    
+    /// Check a described statement's result columns again after DDL. Like PostgreSQL,
+    /// the statement fails if they changed and otherwise runs against the new schema.
+    async fn check_result_shape(db: &Arc<dyn SessionStorage>, session: &Arc<SessionState>, statement_name: &str) -> Result<(), PgSqliteError> {
+        let generation = crate::cache::CatalogCache::generation();
+        let (query, described) = match session.prepared_statements.read().await.get(statement_name) {
+            Some(PreparedStatement { translated_query: Some(query), result_shape: Some(shape), .. }) if shape.generation != generation => {
+                (query.clone(), shape.columns.clone())
+            }
+            _ => return Ok(()),
+        };
+        // A statement that no longer prepares, as when its table was dropped, fails on its own
+        let Some(current) = Self::result_shape(db, session, &query).await else {
+            return Ok(());
+        };
+        if current.columns != described {
+            return Err(PgSqliteError::Validation(crate::error::PgError::Generic {
+                code: "0A000".to_string(), // feature_not_supported
+                message: "cached plan must not change result type".to_string(),
+            }));
+        }
+        if let Some(stmt) = session.prepared_statements.write().await.get_mut(statement_name) {
+            stmt.result_shape = Some(current);
+        }
+        Ok(())
+    }
+    
+    async fn execute_portal<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        db: &Arc<dyn SessionStorage>,
         session: &Arc<SessionState>,
         portal: String,
         max_rows: i32,
@@ -1227,36 +1222,63 @@ impl ExtendedQueryHandler {
              portal_obj.inferred_param_types.clone())
         };
         
-        // Special logging for orders queries
-        if query.contains("orders") && query.contains("customer_id") {
-            info!("EXECUTE: Orders query detected!");
-            info!("EXECUTE: Query: {}", query);
-            info!("EXECUTE: Statement name: {}", statement_name);
-            
-            // Check what field_descriptions are stored for this statement
-            let statements = session.prepared_statements.read().await;
-            
-            // For unnamed statements, try both empty string and the actual value
-            let stmt_opt = if statement_name.is_empty() {
-                statements.get("")
-                    .or_else(|| statements.get(&statement_name))
-            } else {
-                statements.get(&statement_name)
-            };
-            
-            if let Some(stmt) = stmt_opt {
-                info!("EXECUTE: Statement has {} field_descriptions", stmt.field_descriptions.len());
-                for fd in &stmt.field_descriptions {
-                    info!("EXECUTE:   {} -> type OID {}", fd.name, fd.type_oid);
-                    if fd.name.contains("total_amount") && fd.type_oid == 25 {
-                        info!("EXECUTE:   ^^^ BUG DETECTED: total_amount has TEXT type!");
-                    }
-                }
-            } else {
-                info!("EXECUTE: Statement not found! Available keys: {:?}", 
-                      statements.keys().collect::<Vec<_>>());
-            }
+        // A --read-only server refuses writes when they would run, like a standby
+        crate::session::read_only_handler::reject_writes(&query)?;
+        // GENERATED ALWAYS columns refuse explicit values, see IdentityColumns::check_insert
+        if matches!(crate::query::QueryTypeDetector::detect_query_type(&query), crate::query::QueryType::Insert) {
+            db.with_session_connection(&session.id, |conn| Ok(crate::metadata::IdentityColumns::check_insert(conn, &query))).await??;
+        }
+        
+        // DDL since Parse may have changed the columns the client was described
+        Self::check_result_shape(db, session, &statement_name).await?;
+        
+        if let Some(copy) = crate::query::CopyHandler::parse_copy_to(&query)? {
+            return crate::query::CopyHandler::handle_copy_to(framed, db, session, &copy).await;
+        }
+        if let Some(vacuum) = crate::query::VacuumHandler::parse_vacuum(&query)? {
+            return crate::query::VacuumHandler::handle_vacuum(framed, db, session, &vacuum).await;
+        }
+        if let Some(cluster) = crate::query::ClusterHandler::parse_cluster(&query)? {
+            return crate::query::ClusterHandler::handle_cluster(framed, db, session, &cluster).await;
+        }
+        if let Some(comment) = crate::query::CommentHandler::parse_comment(&query)? {
+            return crate::query::CommentHandler::handle_comment(framed, db, session, &comment).await;
+        }
+        if let Some(schema) = crate::query::SchemaHandler::parse_schema(&query) {
+            return crate::query::SchemaHandler::handle_schema(framed, db, session, &schema).await;
+        }
+        if let Some(view) = crate::query::ViewHandler::parse_view(&query) {
+            return crate::query::ViewHandler::handle_view(framed, db, session, &view).await;
+        }
+        if let Some(trigger) = crate::query::TriggerHandler::parse_trigger(&query)? {
+            return crate::query::TriggerHandler::handle_trigger(framed, db, session, &trigger).await;
+        }
+        if let Some(function) = crate::query::FunctionHandler::parse_function(&query)? {
+            return crate::query::FunctionHandler::handle_function(framed, db, session, &function).await;
+        }
+        if let Some(alter) = crate::query::AlterTableHandler::parse_alter_table(&query) {
+            return crate::query::AlterTableHandler::handle_alter_table(framed, db, session, &alter).await;
+        }
+        // Parse reported the result columns, so Describe has already sent them
+        if let Some(explained) = crate::query::TranslateHandler::parse_translate_call(&query) {
+            return crate::query::TranslateHandler::handle_translate(framed, db, session, &explained, true).await;
+        }
+        if let Some(table) = crate::query::AuditHandler::parse_enable_audit_call(&query) {
+            return crate::query::AuditHandler::handle_enable_audit(framed, db, session, &table, true).await;
+        }
+        if let Some(call) = crate::query::SoftDeleteHandler::parse_soft_delete_call(&query) {
+            return crate::query::SoftDeleteHandler::handle_soft_delete_call(framed, db, session, &call, true, result_formats.first() == Some(&1)).await;
+        }
+        if let Some(call) = crate::query::MaskHandler::parse_mask_call(&query) {
+            return crate::query::MaskHandler::handle_mask_call(framed, db, session, &call, true, result_formats.first() == Some(&1)).await;
+        }
+        if let Some(call) = crate::query::RetentionHandler::parse_retention_call(&query) {
+            return crate::query::RetentionHandler::handle_retention_call(framed, db, session, &call, true, result_formats.first() == Some(&1)).await;
+        }
+        if let Some(call) = crate::query::SchemaSnapshotHandler::parse_schema_snapshot_call(&query) {
+            return crate::query::SchemaSnapshotHandler::handle_schema_snapshot_call(framed, db, session, &call, true, result_formats.first() == Some(&1)).await;
         }
+        crate::query::AutoAnalyze::analyze_before(db, session, &query).await;
         
         // Use translated query if available, otherwise use original query
         let effective_query = translated_query.as_ref().unwrap_or(&query);
@@ -1331,78 +1353,14 @@ impl ExtendedQueryHandler {
                         // Need to infer types from the query structure
                         drop(statements);
                         
-                        // Parse the query to get column names and their aliases
-                        let mut inferred_types = Vec::new();
-                        let table_name = extract_table_name_from_select(&query);
+                        // Type the result columns from the query's AST
                         info!("Ultra-fast path: Inferring types for query: {}", query);
-                        info!("Ultra-fast path: Extracted table name: {:?}", table_name);
-                        
-                        // Extract column names from SELECT clause
-                        // Handle both " FROM " and "\nFROM " (queries might have newlines)
-                        let query_upper = query.to_uppercase();
-                        let select_end = query_upper.find(" FROM ")
-                            .or_else(|| query_upper.find("\nFROM "));
-                        
-                        if let Some(select_end) = select_end {
-                            let select_part = &query[6..select_end]; // Skip "SELECT"
-                            let columns: Vec<&str> = select_part.split(',').map(|s| s.trim()).collect();
-                            
-                            info!("Ultra-fast path: Parsing {} columns from SELECT clause", columns.len());
-                            for col_expr in columns {
-                                info!("Ultra-fast path: Processing column expression: '{}'", col_expr);
-                                let mut found_type = false;
-                                
-                                // Extract the alias name (after AS) if present
-                                let col_name = if let Some(as_pos) = col_expr.to_uppercase().rfind(" AS ") {
-                                    col_expr[as_pos + 4..].trim()
-                                } else {
-                                    // No alias, use the expression itself
-                                    col_expr
-                                };
-                                
-                                // Try to extract source table.column from the expression
-                                if let Some((source_table, source_col)) = Self::extract_source_table_column_for_alias(&query, col_name) {
-                                    if let Ok(Some(pg_type_str)) = db.get_schema_type_with_session(&session.id, &source_table, &source_col).await {
-                                        let type_oid = crate::types::SchemaTypeMapper::pg_type_string_to_oid(&pg_type_str);
-                                        info!("Ultra-fast path: Pre-execution type inference for '{}' from '{}.{}' -> {} (OID {})", 
-                                              col_name, source_table, source_col, pg_type_str, type_oid);
-                                        inferred_types.push(type_oid);
-                                        found_type = true;
-                                    }
-                                } else if col_expr.contains('.') {
-                                    // Handle table.column format directly
-                                    let parts: Vec<&str> = col_expr.split('.').collect();
-                                    if parts.len() == 2 {
-                                        let table_part = parts[0].trim();
-                                        let column_part = parts[1].trim();
-                                        if let Ok(Some(pg_type_str)) = db.get_schema_type_with_session(&session.id, table_part, column_part).await {
-                                            let type_oid = crate::types::SchemaTypeMapper::pg_type_string_to_oid(&pg_type_str);
-                                            info!("Ultra-fast path: Pre-execution type inference for '{}' from '{}.{}' -> {} (OID {})", 
-                                                  col_name, table_part, column_part, pg_type_str, type_oid);
-                                            inferred_types.push(type_oid);
-                                            found_type = true;
-                                        }
-                                    }
-                                } else if let Some(ref table) = table_name {
-                                    // Try direct column name lookup in the main table
-                                    if let Ok(Some(pg_type_str)) = db.get_schema_type_with_session(&session.id, table, col_expr).await {
-                                        let type_oid = crate::types::SchemaTypeMapper::pg_type_string_to_oid(&pg_type_str);
-                                        info!("Ultra-fast path: Pre-execution type inference for '{}' from table '{}' -> {} (OID {})", 
-                                              col_name, table, pg_type_str, type_oid);
-                                        inferred_types.push(type_oid);
-                                        found_type = true;
-                                    }
-                                }
-                                
-                                if !found_type {
-                                    // Default to TEXT if we can't resolve
-                                    info!("Ultra-fast path: Could not infer type for '{}', defaulting to TEXT", col_name);
-                                    inferred_types.push(PgType::Text.to_oid());
-                                }
-                            }
-                        } else {
-                            info!("Ultra-fast path: Could not find FROM clause in query, cannot parse columns");
-                        }
+                        let inferred_types: Vec<i32> = db.with_session_connection(&session.id, |conn| {
+                            Ok(crate::types::TypeChecker::new(conn).result_column_types(&query))
+                        }).await.ok().flatten().unwrap_or_default()
+                            .into_iter()
+                            .map(|pg_type| pg_type.unwrap_or(PgType::Text).to_oid())
+                            .collect();
                         
                         info!("Ultra-fast path: Inferred {} types", inferred_types.len());
                         inferred_types
@@ -1570,6 +1528,7 @@ impl ExtendedQueryHandler {
                         
                         // Default to text format for ultra-fast path
                         let result_formats = vec![0i16; response.columns.len()];
+                        let bytea_output = session.bytea_output().await;
                         
                         for row in response.rows {
                             // Convert row data to handle datetime types properly
@@ -1584,7 +1543,7 @@ impl ExtendedQueryHandler {
                                         }
                                     }
                             }
-                            let encoded_row = Self::encode_row(&row, &result_formats, &field_types)?;
+                            let encoded_row = Self::encode_row(&row, &result_formats, &field_types, bytea_output)?;
                             framed.send(BackendMessage::DataRow(encoded_row)).await
                                 .map_err(PgSqliteError::Io)?;
                         }
@@ -1685,6 +1644,9 @@ impl ExtendedQueryHandler {
         // Use translated query if available, otherwise use original
         let query_to_use = translated_query.as_ref().unwrap_or(&query);
         
+        // Network and xml parameters are described to clients as TEXT; substitute them by column type
+        let param_types = Self::validated_param_types(effective_query, param_types);
+        
         // Validate numeric constraints before parameter substitution
         let validation_error = if query_starts_with_ignore_case(query_to_use, "INSERT") {
             if let Some(table_name) = Self::extract_table_name_from_insert(query_to_use) {
@@ -1825,7 +1787,28 @@ impl ExtendedQueryHandler {
         }
         
         // Execute based on query type
-        if query_starts_with_ignore_case(&final_query, "SELECT") {
+        if let Some(sleep_call) = crate::query::SleepHandler::parse_sleep_call(&final_query) {
+            // Describe already reported the result column if the statement has field descriptions
+            let skip_row_desc = {
+                let portals = session.portals.read().await;
+                let statements = session.prepared_statements.read().await;
+                portals.get(&portal)
+                    .and_then(|portal| statements.get(&portal.statement_name))
+                    .is_some_and(|stmt| !stmt.field_descriptions.is_empty())
+            };
+            crate::query::SleepHandler::handle_sleep(framed, session.backend_pid, &sleep_call, skip_row_desc).await?;
+        } else if let Some(lock_call) = crate::query::AdvisoryLockHandler::parse_lock_call(&final_query) {
+            let skip_row_desc = {
+                let portals = session.portals.read().await;
+                let statements = session.prepared_statements.read().await;
+                portals.get(&portal)
+                    .and_then(|portal| statements.get(&portal.statement_name))
+                    .is_some_and(|stmt| !stmt.field_descriptions.is_empty())
+            };
+            crate::query::AdvisoryLockHandler::handle_lock(framed, session, &lock_call, skip_row_desc).await?;
+        } else if let Some(merge) = crate::query::MergeHandler::parse_merge(&final_query)? {
+            crate::query::MergeHandler::handle_merge(framed, db, session, &merge).await?;
+        } else if query_starts_with_ignore_case(&final_query, "SELECT") || crate::translator::CteTranslator::is_select(&final_query) {
             Self::execute_select(framed, db, session, &portal, &final_query, max_rows).await?;
         } else if query_starts_with_ignore_case(&final_query, "INSERT") 
             || query_starts_with_ignore_case(&final_query, "UPDATE") 
@@ -2215,7 +2198,7 @@ impl ExtendedQueryHandler {
     
     async fn try_execute_fast_path_with_params<T>(
         framed: &mut Framed<T, crate::protocol::PostgresCodec>,
-        db: &Arc<DbHandler>,
+        db: &Arc<dyn SessionStorage>,
         session: &Arc<SessionState>,
         portal: &str,
         query: &str,
@@ -2253,14 +2236,12 @@ impl ExtendedQueryHandler {
         };
         
         // Get field descriptions from prepared statement if available
-        // Also check if we've already sent RowDescription with binary format
-        let (field_types, has_binary_row_desc): (Option<Vec<i32>>, bool) = {
+        // Like the normal path, a statement with field descriptions had them sent by Describe
+        let (field_types, has_row_desc): (Option<Vec<i32>>, bool) = {
             let statements = session.prepared_statements.read().await;
             if let Some(stmt) = statements.get(&statement_name) {
                 if !stmt.field_descriptions.is_empty() {
-                    // Check if any field has binary format, which means Describe(Portal) sent RowDescription
-                    let has_binary = stmt.field_descriptions.iter().any(|fd| fd.format == 1);
-                    (Some(stmt.field_descriptions.iter().map(|fd| fd.type_oid).collect()), has_binary)
+                    (Some(stmt.field_descriptions.iter().map(|fd| fd.type_oid).collect()), true)
                 } else {
                     (None, false)
                 }
@@ -2269,6 +2250,8 @@ impl ExtendedQueryHandler {
             }
         };
         
+        let bytea_output = session.bytea_output().await;
+        
         // Try fast path execution first
         if let Ok(Some(response)) = db.try_execute_fast_path_with_params(query, &rusqlite_params, &session.id).await {
             if response.columns.is_empty() {
@@ -2282,13 +2265,13 @@ impl ExtendedQueryHandler {
                 framed.send(BackendMessage::CommandComplete { tag }).await?;
             } else {
                 // SELECT operation - check if we need to send RowDescription
-                if has_binary_row_desc {
-                    // Describe(Portal) already sent RowDescription with binary format
+                if has_row_desc {
+                    // Describe already sent RowDescription
                     // Just send the data rows without RowDescription
-                    Self::send_data_rows_only(framed, response, &result_formats, field_types.as_deref()).await?;
+                    Self::send_data_rows_only(framed, response, &result_formats, field_types.as_deref(), bytea_output).await?;
                 } else {
                     // Send full response with RowDescription
-                    Self::send_select_response(framed, response, max_rows, &result_formats, field_types.as_deref()).await?;
+                    Self::send_select_response(framed, response, max_rows, &result_formats, field_types.as_deref(), bytea_output).await?;
                 }
             }
             return Ok(Some(Ok(())));
@@ -2307,13 +2290,13 @@ impl ExtendedQueryHandler {
                 framed.send(BackendMessage::CommandComplete { tag }).await?;
             } else {
                 // SELECT operation - check if we need to send RowDescription
-                if has_binary_row_desc {
-                    // Describe(Portal) already sent RowDescription with binary format
+                if has_row_desc {
+                    // Describe already sent RowDescription
                     // Just send the data rows without RowDescription
-                    Self::send_data_rows_only(framed, response, &result_formats, field_types.as_deref()).await?;
+                    Self::send_data_rows_only(framed, response, &result_formats, field_types.as_deref(), bytea_output).await?;
                 } else {
                     // Send full response with RowDescription
-                    Self::send_select_response(framed, response, max_rows, &result_formats, field_types.as_deref()).await?;
+                    Self::send_select_response(framed, response, max_rows, &result_formats, field_types.as_deref(), bytea_output).await?;
                 }
             }
             return Ok(Some(Ok(())));
@@ -2323,7 +2306,7 @@ impl ExtendedQueryHandler {
     }
     
     async fn try_statement_pool_execution(
-        db: &Arc<DbHandler>,
+        db: &Arc<dyn SessionStorage>,
         session: &Arc<SessionState>,
         query: &str,
         params: &[rusqlite::types::Value],
@@ -2344,12 +2327,12 @@ impl ExtendedQueryHandler {
         // (decimal queries need rewriting which complicates caching)
         match fast_query.operation {
             crate::query::FastPathOperation::Select => {
-                db.query_with_statement_pool_params(query, &byte_params, &session.id)
+                db.execute_with_params(query, &byte_params, &session.id)
                     .await
                     .map_err(|e| PgSqliteError::Protocol(e.to_string()))
             }
             _ => {
-                db.execute_with_statement_pool_params(query, &byte_params, &session.id)
+                db.execute_with_params(query, &byte_params, &session.id)
                     .await
                     .map_err(|e| PgSqliteError::Protocol(e.to_string()))
             }
@@ -2395,11 +2378,41 @@ impl ExtendedQueryHandler {
                         Err(e) => Err(PgSqliteError::Protocol(format!("Invalid timestamp: {e}")))
                     }
                 }
+                t if ArrayHandler::is_array_oid(t) && text.trim_start().starts_with('{') => {
+                    // Array literal - stored as JSON
+                    ArrayHandler::array_literal_to_json(text, ArrayHandler::element_type(t)).map(rusqlite::types::Value::Text)
+                }
+                t if t == PgType::Bytea.to_oid() => {
+                    // BYTEA - hex or escape text stored as BLOB
+                    crate::types::ByteaFormat::decode(text)
+                        .map(rusqlite::types::Value::Blob)
+                        .map_err(PgSqliteError::InvalidParameter)
+                }
                 _ => Ok(rusqlite::types::Value::Text(text.to_string())), // Default to TEXT
             }
         } else {
-            // Binary format - simplified for now, fall back to normal path
-            Err(PgSqliteError::Protocol("Binary format not supported in fast path".to_string()))
+            // Binary format - decode the types drivers send in binary by default, fall back to normal path for the rest
+            match param_type {
+                t if t == PgType::Uuid.to_oid() => {
+                    // UUID - 16 bytes, stored in its text form
+                    crate::protocol::BinaryDecoder::decode_uuid(bytes)
+                        .map(rusqlite::types::Value::Text)
+                        .map_err(PgSqliteError::Protocol)
+                }
+                t if t == PgType::Json.to_oid() || t == PgType::Jsonb.to_oid() => {
+                    // JSON - UTF-8 text, JSONB - a version byte followed by the text
+                    crate::protocol::BinaryDecoder::decode_json(bytes, t)
+                        .map(|json| rusqlite::types::Value::Text(json.to_string()))
+                        .map_err(PgSqliteError::Protocol)
+                }
+                t if ArrayHandler::is_array_oid(t) => {
+                    // Binary array - stored as JSON
+                    crate::protocol::BinaryDecoder::decode_array(bytes)
+                        .map(|array| rusqlite::types::Value::Text(array.to_string()))
+                        .map_err(|e| PgSqliteError::Protocol(format!("Invalid binary array: {e}")))
+                }
+                _ => Err(PgSqliteError::Protocol("Binary format not supported in fast path".to_string())),
+            }
         }
     }
     
@@ -2408,6 +2421,7 @@ impl ExtendedQueryHandler {
         response: crate::session::db_handler::DbResponse,
         result_formats: &[i16],
         field_types: Option<&[i32]>,  // Optional field types
+        bytea_output: crate::types::ByteaFormat,
     ) -> Result<(), PgSqliteError>
     where
         T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
@@ -2419,11 +2433,12 @@ impl ExtendedQueryHandler {
         // Check if we need binary encoding
         let needs_binary_encoding = !result_formats.is_empty() && 
             result_formats.contains(&1);
+        let has_bytea = field_types.is_some_and(|types| types.contains(&PgType::Bytea.to_oid()));
         
-        if needs_binary_encoding && field_types.is_some() {
+        if (needs_binary_encoding || has_bytea) && field_types.is_some() {
             let types = field_types.unwrap();
             for row in response.rows {
-                let encoded_row = Self::encode_row(&row, result_formats, types)?;
+                let encoded_row = Self::encode_row(&row, result_formats, types, bytea_output)?;
                 framed.send(BackendMessage::DataRow(encoded_row)).await?;
             }
         } else {
@@ -2447,6 +2462,7 @@ impl ExtendedQueryHandler {
         _max_rows: i32,
         result_formats: &[i16],
         field_types: Option<&[i32]>,  // Optional field types
+        bytea_output: crate::types::ByteaFormat,
     ) -> Result<(), PgSqliteError>
     where
         T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
@@ -2482,6 +2498,7 @@ impl ExtendedQueryHandler {
                 format,
             });
         }
+        let column_formats: Vec<i16> = field_descriptions.iter().map(|fd| fd.format).collect();
         framed.send(BackendMessage::RowDescription(field_descriptions)).await?;
         
         // Check if we need conversion for timestamps OR TEXT columns that might contain timestamps
@@ -2554,6 +2571,10 @@ impl ExtendedQueryHandler {
                         } else {
                             converted_row.push(None);
                         }
+                    }
+                    // Bytea columns requested as text are rendered per bytea_output
+                    else if type_oid == PgType::Bytea.to_oid() && column_formats.get(i).copied().unwrap_or(0) == 0 {
+                        converted_row.push(cell.as_ref().map(|bytes| bytea_output.encode(bytes).into_bytes()));
                     } else {
                         converted_row.push(cell.clone());
                     }
@@ -2566,12 +2587,13 @@ impl ExtendedQueryHandler {
             let needs_binary_encoding = !result_formats.is_empty() && 
                 (result_formats.len() == 1 && result_formats[0] == 1 || 
                  result_formats.contains(&1));
+            let has_bytea = field_types.is_some_and(|types| types.contains(&PgType::Bytea.to_oid()));
             
-            if needs_binary_encoding && field_types.is_some() {
+            if (needs_binary_encoding || has_bytea) && field_types.is_some() {
                 // Apply binary encoding to results
                 let types = field_types.unwrap();
                 for row in response.rows {
-                    let encoded_row = Self::encode_row(&row, result_formats, types)?;
+                    let encoded_row = Self::encode_row(&row, result_formats, types, bytea_output)?;
                     framed.send(BackendMessage::DataRow(encoded_row)).await?;
                 }
             } else {
@@ -2644,6 +2666,16 @@ impl ExtendedQueryHandler {
                                     format!("X'{}'", hex::encode(bytes))
                                 }
                             }
+                            26 => {
+                                // oid - unsigned int4
+                                if bytes.len() == 4 {
+                                    let value = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                                    info!("Decoded binary oid parameter {}: {}", i + 1, value);
+                                    value.to_string()
+                                } else {
+                                    format!("X'{}'", hex::encode(bytes))
+                                }
+                            }
                             t if t == PgType::Money.to_oid() => {
                                 // money - binary format is int8 cents
                                 if bytes.len() == 8 {
@@ -2687,6 +2719,13 @@ impl ExtendedQueryHandler {
                                     }
                                 }
                             }
+                            t if t == PgType::Interval.to_oid() => {
+                                // interval - int8 microseconds, int4 days, int4 months
+                                match crate::types::Interval::from_binary(bytes) {
+                                    Some(interval) => interval.to_sql_literal(),
+                                    None => format!("X'{}'", hex::encode(bytes)),
+                                }
+                            }
                             t if t == PgType::Timestamp.to_oid() || t == PgType::Timestamptz.to_oid() => {
                                 // timestamp/timestamptz - int8 microseconds since PostgreSQL epoch (2000-01-01)
                                 if bytes.len() == 8 {
@@ -2702,26 +2741,7 @@ impl ExtendedQueryHandler {
                                     info!("Decoded binary timestamp parameter {}: {} PG microseconds = {} Unix microseconds", 
                                           i + 1, pg_micros, unix_micros);
                                     
-                                    // Check if this is a VALUES clause that will be rewritten
-                                    if query.contains("FROM (VALUES") && query.contains("SELECT CAST(p0") {
-                                        // SQLAlchemy VALUES pattern - convert to formatted timestamp string
-                                        // Convert microseconds to NaiveDateTime
-                                        let seconds = unix_micros / 1_000_000;
-                                        let nanos = ((unix_micros % 1_000_000) * 1000) as u32;
-                                        
-                                        if let Some(dt) = chrono::DateTime::from_timestamp(seconds, nanos).map(|dt| dt.naive_utc()) {
-                                            // Format as ISO timestamp string for VALUES clause
-                                            let formatted = dt.format("%Y-%m-%d %H:%M:%S%.6f").to_string();
-                                            info!("VALUES clause detected - formatting timestamp as: {}", formatted);
-                                            format!("'{formatted}'")
-                                        } else {
-                                            // Fallback to raw microseconds if conversion fails
-                                            unix_micros.to_string()
-                                        }
-                                    } else {
-                                        // Normal query - use raw microseconds
-                                        unix_micros.to_string()
-                                    }
+                                    unix_micros.to_string()
                                 } else {
                                     format!("X'{}'", hex::encode(bytes))
                                 }
@@ -2749,6 +2769,20 @@ impl ExtendedQueryHandler {
                                     format!("X'{}'", hex::encode(bytes))
                                 }
                             }
+                            t if t == PgType::Inet.to_oid() || t == PgType::Cidr.to_oid() || t == PgType::Macaddr.to_oid() => {
+                                // Network types - binary encoding or UTF-8 text
+                                match PgType::from_oid(t).and_then(crate::types::NetworkKind::from_pg_type) {
+                                    Some(kind) => format!("'{}'", kind.decode_param(bytes).map_err(PgSqliteError::InvalidParameter)?),
+                                    None => format!("X'{}'", hex::encode(bytes)),
+                                }
+                            }
+                            t if t == PgType::Xml.to_oid() => {
+                                // xml - UTF-8 text, checked for being well-formed content
+                                let text = String::from_utf8_lossy(bytes);
+                                crate::types::xml::validate(&text, crate::types::XmlOption::Content)
+                                    .map_err(PgSqliteError::InvalidParameter)?;
+                                format!("'{}'", text.replace('\'', "''"))
+                            }
                             0 => {
                                 // No type specified - try to infer from byte pattern
                                 if bytes.len() == 1 && (bytes[0] == 0 || bytes[0] == 1) {
@@ -2780,6 +2814,30 @@ impl ExtendedQueryHandler {
                                     format!("X'{}'", hex::encode(bytes))
                                 }
                             }
+                            t if t == PgType::Uuid.to_oid() => {
+                                // uuid - 16 bytes, stored in its text form
+                                crate::protocol::BinaryDecoder::decode_uuid(bytes)
+                                    .map(|uuid| format!("'{uuid}'"))
+                                    .map_err(PgSqliteError::InvalidParameter)?
+                            }
+                            t if t == PgType::Json.to_oid() || t == PgType::Jsonb.to_oid() => {
+                                // json - UTF-8 text, jsonb - a version byte followed by the text
+                                let json = crate::protocol::BinaryDecoder::decode_json(bytes, t)
+                                    .map_err(PgSqliteError::InvalidParameter)?;
+                                format!("'{}'", json.replace('\'', "''"))
+                            }
+                            t if ArrayHandler::is_array_oid(t) => {
+                                // Arrays are stored as JSON
+                                let array = crate::protocol::BinaryDecoder::decode_array(bytes)
+                                    .map_err(|e| PgSqliteError::InvalidParameter(format!("Invalid binary array: {e}")))?;
+                                format!("'{}'", array.to_string().replace('\'', "''"))
+                            }
+                            t if crate::cache::global_enum_cache().is_enum_oid(t) => {
+                                // ENUM - the binary format is the label's text
+                                let label = String::from_utf8(bytes.clone())
+                                    .map_err(|_| PgSqliteError::InvalidParameter(format!("Invalid UTF-8 in ENUM parameter {}", i + 1)))?;
+                                format!("'{}'", label.replace('\'', "''"))
+                            }
                             _ => {
                                 // Other binary data - treat as blob
                                 info!("Unknown binary parameter type OID {} for parameter {}, bytes: {}", param_type, i + 1, hex::encode(bytes));
@@ -2792,6 +2850,15 @@ impl ExtendedQueryHandler {
                             Ok(s) => {
                                 // Check parameter type to determine handling
                                 match param_type {
+                                    t if ArrayHandler::is_array_oid(t) && s.trim_start().starts_with(['{', '[']) => {
+                                        // PostgreSQL array literal - store as JSON
+                                        let json = if s.trim_start().starts_with('[') && serde_json::from_str::<serde_json::Value>(&s).is_ok_and(|v| v.is_array()) {
+                                            s
+                                        } else {
+                                            ArrayHandler::array_literal_to_json(&s, ArrayHandler::element_type(t))?
+                                        };
+                                        format!("'{}'", json.replace('\'', "''"))
+                                    }
                                     t if t == PgType::Int4.to_oid() || t == PgType::Int8.to_oid() || t == PgType::Int2.to_oid() || 
                                          t == PgType::Float4.to_oid() || t == PgType::Float8.to_oid() => {
                                         // Integer and float types - use as-is if valid number
@@ -2801,10 +2868,28 @@ impl ExtendedQueryHandler {
                                             format!("'{}'", s.replace('\'', "''"))
                                         }
                                     }
+                                    t if t == PgType::Inet.to_oid() || t == PgType::Cidr.to_oid() || t == PgType::Macaddr.to_oid() => {
+                                        // Network types - validate and store in canonical form
+                                        match PgType::from_oid(t).and_then(crate::types::NetworkKind::from_pg_type) {
+                                            Some(kind) => format!("'{}'", kind.canonicalize(&s).map_err(PgSqliteError::InvalidParameter)?),
+                                            None => format!("'{}'", s.replace('\'', "''")),
+                                        }
+                                    }
+                                    t if t == PgType::Xml.to_oid() => {
+                                        // xml - stored as written once it's known to be well-formed content
+                                        crate::types::xml::validate(&s, crate::types::XmlOption::Content)
+                                            .map_err(PgSqliteError::InvalidParameter)?;
+                                        format!("'{}'", s.replace('\'', "''"))
+                                    }
                                     t if t == PgType::Money.to_oid() => {
                                         // MONEY type - always quote
                                         format!("'{}'", s.replace('\'', "''"))
                                     }
+                                    t if t == PgType::Bytea.to_oid() => {
+                                        // BYTEA - hex or escape text stored as a BLOB literal
+                                        let bytes = crate::types::ByteaFormat::decode(&s).map_err(PgSqliteError::InvalidParameter)?;
+                                        format!("X'{}'", hex::encode(bytes))
+                                    }
                                     t if t == PgType::Numeric.to_oid() => {
                                         // NUMERIC type - validate and quote
                                         match DecimalHandler::validate_numeric_string(&s) {
@@ -2819,19 +2904,12 @@ impl ExtendedQueryHandler {
                                         }
                                     }
                                     t if t == PgType::Timestamp.to_oid() || t == PgType::Timestamptz.to_oid() => {
-                                        // TIMESTAMP types - check if this is a VALUES clause that will be rewritten
-                                        // If so, keep as formatted string to avoid double conversion
-                                        if query.contains("FROM (VALUES") && query.contains("SELECT CAST(p0") {
-                                            // SQLAlchemy VALUES pattern - keep as formatted string
-                                            format!("'{}'", s.replace('\'', "''"))
-                                        } else {
-                                            // Normal query - convert to Unix timestamp
-                                            match crate::types::ValueConverter::convert_timestamp_to_unix(&s) {
-                                                Ok(unix_timestamp) => unix_timestamp,
-                                                Err(e) => {
-                                                    // Invalid TIMESTAMP parameter
-                                                    return Err(PgSqliteError::InvalidParameter(format!("Invalid TIMESTAMP value: {e}")));
-                                                }
+                                        // TIMESTAMP types - convert to Unix timestamp
+                                        match crate::types::ValueConverter::convert_timestamp_to_unix(&s) {
+                                            Ok(unix_timestamp) => unix_timestamp,
+                                            Err(e) => {
+                                                // Invalid TIMESTAMP parameter
+                                                return Err(PgSqliteError::InvalidParameter(format!("Invalid TIMESTAMP value: {e}")));
                                             }
                                         }
                                     }
@@ -2845,6 +2923,13 @@ impl ExtendedQueryHandler {
                                             }
                                         }
                                     }
+                                    t if t == PgType::Interval.to_oid() => {
+                                        // INTERVAL - quoted interval text
+                                        match crate::types::Interval::parse(&s) {
+                                            Ok(interval) => interval.to_sql_literal(),
+                                            Err(e) => return Err(PgSqliteError::InvalidParameter(e)),
+                                        }
+                                    }
                                     t if t == PgType::Time.to_oid() || t == PgType::Timetz.to_oid() => {
                                         // TIME types - convert to seconds since midnight
                                         match crate::types::ValueConverter::convert_time_to_seconds(&s) {
@@ -2887,94 +2972,6 @@ impl ExtendedQueryHandler {
         let cast_regex = regex::Regex::new(r"::[a-zA-Z][a-zA-Z0-9_]*(?:\s+(?:WITHOUT|WITH)\s+TIME\s+ZONE|\s+PRECISION|\s+VARYING)?").unwrap();
         let result = cast_regex.replace_all(&result, "").to_string();
         
-        // SQLite doesn't support VALUES with column aliases like "AS table_alias(col1, col2, ...)"
-        // Replace ") AS imp_sen(p0, p1, p2, p3, p4, p5, p6, p7, sen_counter)" with just ")"
-        let alias_regex = regex::Regex::new(r"\)\s+AS\s+\w+\s*\([^)]+\)").unwrap();
-        let result = alias_regex.replace_all(&result, ")").to_string();
-        
-        // Handle SQLAlchemy's VALUES pattern which uses p0, p1, etc. column references
-        // This pattern needs to be completely rewritten for SQLite
-        if result.contains("SELECT CAST(p0") && result.contains("FROM (VALUES") {
-            info!("Detected SQLAlchemy VALUES pattern, attempting to rewrite");
-            
-            // Find the positions of key parts
-            if let (Some(table_start), Some(values_start), Some(values_end)) = (
-                result.find("INSERT INTO "),
-                result.find("FROM (VALUES "),
-                result.rfind(")")
-            ) {
-                // Extract table and columns
-                let table_part = &result[table_start + 12..values_start];
-                if let Some(paren_pos) = table_part.find('(') {
-                    let table_name = table_part[..paren_pos].trim();
-                    let columns_end = table_part.find(')').unwrap_or(table_part.len());
-                    let columns = &table_part[paren_pos..=columns_end];
-                    
-                    // Extract VALUES content
-                    let values_content = &result[values_start + 13..values_end];
-                    
-                    // Extract RETURNING clause if present
-                    let returning_clause = if let Some(ret_pos) = result.find(" RETURNING ") {
-                        &result[ret_pos..]
-                    } else {
-                        ""
-                    };
-                    
-                    // Parse values rows - need to handle nested parentheses properly
-                    let mut all_values = Vec::new();
-                    let mut current_value = String::new();
-                    let mut paren_depth = 0;
-                    let mut in_row = false;
-                    
-                    for ch in values_content.chars() {
-                        match ch {
-                            '(' => {
-                                paren_depth += 1;
-                                if paren_depth == 1 {
-                                    in_row = true;
-                                    current_value.clear();
-                                } else {
-                                    current_value.push(ch);
-                                }
-                            }
-                            ')' => {
-                                paren_depth -= 1;
-                                if paren_depth == 0 && in_row {
-                                    // End of row - remove the trailing counter value
-                                    let values: Vec<&str> = current_value.split(", ").collect();
-                                    if values.len() > 1 {
-                                        // Skip the last value (sen_counter)
-                                        let actual_values = &values[..values.len() - 1];
-                                        all_values.push(format!("({})", actual_values.join(", ")));
-                                    }
-                                    in_row = false;
-                                } else {
-                                    current_value.push(ch);
-                                }
-                            }
-                            _ => {
-                                if in_row {
-                                    current_value.push(ch);
-                                }
-                            }
-                        }
-                    }
-                    
-                    // Build the new query
-                    if !all_values.is_empty() {
-                        // Join all values and remove any remaining casts
-                        let values_str = all_values.join(", ");
-                        // Apply cast removal to the VALUES content
-                        let values_str = cast_regex.replace_all(&values_str, "").to_string();
-                        
-                        let new_query = format!("INSERT INTO {table_name} {columns} VALUES {values_str}{returning_clause}");
-                        info!("Rewrote SQLAlchemy VALUES pattern to: {}", new_query);
-                        return Ok(new_query);
-                    }
-                }
-            }
-        }
-        
         Ok(result)
     }
     
@@ -2989,6 +2986,11 @@ impl ExtendedQueryHandler {
         const ACLITEM_ARRAY_TYPE: i32 = 1034;
         const TEXT_ARRAY_TYPE: i32 = 1009;
         const PG_NODE_TREE_TYPE: i32 = 194;
+        // The single-byte "char" type, not bpchar
+        const CHAR_TYPE: i32 = 18;
+        
+        // The type lookup of drivers joins pg_range; tokio-postgres reads its typtype as "char"
+        let is_type_lookup = query.contains("pg_range");
         
         // Determine which catalog table based on query
         if query.contains("pg_class") {
@@ -3024,13 +3026,21 @@ impl ExtendedQueryHandler {
                 "oid" | "typnamespace" | "typowner" | "typrelid" | "typelem" | "typarray" | 
                 "typinput" | "typoutput" | "typreceive" | "typsend" | "typmodin" | 
                 "typmodout" | "typanalyze" | "typbasetype" | "typcollation" => OID_TYPE,
+                // pg_range, which drivers join when looking up a type
+                "rngtypid" | "rngsubtype" => OID_TYPE,
                 "typname" | "typdefault" | "typacl" => PgType::Text.to_oid(),
                 "typlen" => PgType::Int2.to_oid(),
                 "typmod" | "typndims" => PgType::Int4.to_oid(),
                 "typbyval" | "typisdefined" | "typnotnull" => PgType::Bool.to_oid(),
+                "typtype" if is_type_lookup => CHAR_TYPE,
                 "typtype" | "typcategory" | "typalign" | "typstorage" | "typdelim" => PgType::Char.to_oid(),
                 _ => PgType::Text.to_oid(),
             }
+        } else if query.contains("pg_enum") {
+            match column_name {
+                "oid" | "enumtypid" => OID_TYPE,
+                _ => PgType::Text.to_oid(),
+            }
         } else if query.contains("pg_namespace") {
             match column_name {
                 "oid" | "nspowner" => OID_TYPE,
@@ -3128,47 +3138,6 @@ impl ExtendedQueryHandler {
         }
     }
     
-    // Parse IPv4/IPv6 address for CIDR/INET types
-    fn parse_inet(addr_str: &str) -> Option<Vec<u8>> {
-        use std::net::IpAddr;
-        
-        // Split address and netmask if present
-        let parts: Vec<&str> = addr_str.split('/').collect();
-        let ip_str = parts[0];
-        let bits = if parts.len() > 1 {
-            parts[1].parse::<u8>().ok()?
-        } else {
-            // Default netmask
-            match ip_str.parse::<IpAddr>().ok()? {
-                IpAddr::V4(_) => 32,
-                IpAddr::V6(_) => 128,
-            }
-        };
-        
-        // Parse IP address
-        match ip_str.parse::<IpAddr>().ok()? {
-            IpAddr::V4(addr) => {
-                let mut result = Vec::with_capacity(8);
-                result.push(2); // AF_INET
-                result.push(bits); // bits
-                result.push(0); // is_cidr (0 for INET, 1 for CIDR)
-                result.push(4); // nb (number of bytes)
-                result.extend_from_slice(&addr.octets());
-                Some(result)
-            }
-            IpAddr::V6(addr) => {
-                let mut result = Vec::with_capacity(20);
-                result.push(3); // AF_INET6
-                result.push(bits); // bits
-                result.push(0); // is_cidr
-                result.push(16); // nb
-                result.extend_from_slice(&addr.octets());
-                Some(result)
-            }
-        }
-    }
-    
-    
     // Parse bit string
     fn parse_bit_string(bit_str: &str) -> Option<Vec<u8>> {
         // Remove B prefix if present (e.g., B'101010')
@@ -3358,6 +3327,7 @@ impl ExtendedQueryHandler {
         row: &[Option<Vec<u8>>],
         result_formats: &[i16],
         field_types: &[i32],
+        bytea_output: crate::types::ByteaFormat,
     ) -> Result<Vec<Option<Vec<u8>>>, PgSqliteError> {
         
         // Log the first few values for debugging
@@ -3462,6 +3432,13 @@ impl ExtendedQueryHandler {
                                     Some(bytes.clone())
                                 }
                             }
+                            26 => {
+                                // oid - unsigned 4-byte integer
+                                match std::str::from_utf8(bytes).ok().and_then(|s| s.trim().parse::<u32>().ok()) {
+                                    Some(val) => Some(val.to_be_bytes().to_vec()),
+                                    None => Some(bytes.clone()),
+                                }
+                            }
                             t if t == PgType::Float4.to_oid() => {
                                 // float4 - convert text to binary
                                 if let Ok(s) = String::from_utf8(bytes.clone()) {
@@ -3490,10 +3467,12 @@ impl ExtendedQueryHandler {
                                     Some(bytes.clone())
                                 }
                             }
-                            // NOTE: Array type handling removed because:
-                            // 1. Arrays are stored as JSON strings in SQLite
-                            // 2. We return them as TEXT type to clients
-                            // 3. Binary array encoding is not implemented
+                            t if ArrayHandler::is_array_oid(t) => {
+                                // Arrays are stored as JSON - encode in array_send format
+                                std::str::from_utf8(bytes).ok()
+                                    .and_then(|s| crate::protocol::BinaryEncoder::encode_array(s, ArrayHandler::element_oid(t)).ok())
+                                    .or_else(|| Some(bytes.clone()))
+                            }
                             t if t == PgType::Uuid.to_oid() => {
                                 // uuid - convert text to binary (16 bytes)
                                 if let Ok(s) = String::from_utf8(bytes.clone()) {
@@ -3551,6 +3530,13 @@ impl ExtendedQueryHandler {
                                     Some(bytes.clone())
                                 }
                             }
+                            t if t == PgType::Interval.to_oid() => {
+                                // interval - int8 microseconds, int4 days, int4 months
+                                match std::str::from_utf8(bytes).ok().and_then(|s| crate::types::Interval::from_storage(s).ok()) {
+                                    Some(interval) => Some(interval.to_binary()),
+                                    None => Some(bytes.clone()),
+                                }
+                            }
                             t if t == PgType::Timestamp.to_oid() || t == PgType::Timestamptz.to_oid() => {
                                 // timestamp/timestamptz - microseconds since 2000-01-01 as int8
                                 if let Ok(s) = String::from_utf8(bytes.clone()) {
@@ -3578,7 +3564,7 @@ impl ExtendedQueryHandler {
                             t if t == PgType::Numeric.to_oid() => {
                                 if let Ok(s) = String::from_utf8(bytes.clone()) {
                                     // Try to parse and encode as PostgreSQL numeric binary format
-                                    if let Ok(decimal) = rust_decimal::Decimal::from_str(&s) {
+                                    if let Ok(decimal) = DecimalHandler::parse_decimal(&s) {
                                         debug!("Encoding NUMERIC value '{}' as binary", s);
                                         Some(crate::protocol::binary::BinaryEncoder::encode_numeric(&decimal))
                                     } else {
@@ -3612,8 +3598,8 @@ impl ExtendedQueryHandler {
                             t if t == PgType::Cidr.to_oid() || t == PgType::Inet.to_oid() => {
                                 // cidr/inet - family(1) + bits(1) + is_cidr(1) + nb(1) + address bytes
                                 if let Ok(s) = String::from_utf8(bytes.clone()) {
-                                    if let Some(inet_bytes) = Self::parse_inet(&s) {
-                                        Some(inet_bytes)
+                                    if let Ok(value) = crate::types::InetValue::parse_inet(&s) {
+                                        Some(crate::types::InetValue { is_cidr: t == PgType::Cidr.to_oid(), ..value }.to_binary())
                                     } else {
                                         // If parsing fails, keep as text
                                         Some(bytes.clone())
@@ -3702,6 +3688,16 @@ impl ExtendedQueryHandler {
                                     Some(bytes.clone())
                                 }
                             }
+                            t if t == PgType::Tsrange.to_oid() || t == PgType::Tstzrange.to_oid() || t == PgType::Daterange.to_oid() => {
+                                // tsrange, tstzrange, daterange
+                                let kind = PgType::from_oid(t).and_then(crate::types::RangeKind::from_pg_type);
+                                match (std::str::from_utf8(bytes), kind) {
+                                    (Ok(s), Some(kind)) => crate::protocol::binary::BinaryEncoder::encode_datetime_range(s, kind)
+                                        .ok()
+                                        .or_else(|| Some(bytes.clone())),
+                                    _ => Some(bytes.clone()),
+                                }
+                            }
                             // Text types - these are fine as-is in binary format
                             t if t == PgType::Text.to_oid() || t == PgType::Varchar.to_oid() || t == PgType::Char.to_oid() => {
                                 // text/varchar/char - UTF-8 encoded text
@@ -3750,10 +3746,8 @@ impl ExtendedQueryHandler {
                                 // bool - convert SQLite's 0/1 to PostgreSQL's f/t format
                                 if let Ok(s) = String::from_utf8(bytes.clone()) {
                                     let pg_bool_str = match s.trim() {
-                                        "0" => "f",
-                                        "1" => "t",
-                                        // Already in PostgreSQL format or other values
-                                        "f" | "t" | "false" | "true" => &s,
+                                        "0" | "f" | "false" | "FALSE" | "F" => "f",
+                                        "1" | "t" | "true" | "TRUE" | "T" => "t",
                                         _ => &s, // Keep unknown values as-is
                                     };
                                     Some(pg_bool_str.as_bytes().to_vec())
@@ -3795,6 +3789,13 @@ impl ExtendedQueryHandler {
                                     Some(bytes.clone())
                                 }
                             }
+                            // Interval - convert stored text (or INTEGER microseconds) to interval format
+                            t if t == PgType::Interval.to_oid() => {
+                                match std::str::from_utf8(bytes).ok().and_then(|s| crate::types::Interval::from_storage(s).ok()) {
+                                    Some(interval) => Some(interval.to_string().into_bytes()),
+                                    None => Some(bytes.clone()),
+                                }
+                            }
                             // Timestamp types - convert from INTEGER microseconds to formatted string
                             t if t == PgType::Timestamp.to_oid() || t == PgType::Timestamptz.to_oid() => {
                                 if let Ok(s) = String::from_utf8(bytes.clone()) {
@@ -3816,8 +3817,13 @@ impl ExtendedQueryHandler {
                                     Some(bytes.clone())
                                 }
                             }
-                            // NOTE: Array type handling removed for text format too
-                            // Arrays are returned as JSON strings with TEXT type
+                            t if ArrayHandler::is_array_oid(t) => {
+                                // Arrays are stored as JSON - convert to PostgreSQL array literal
+                                std::str::from_utf8(bytes).ok()
+                                    .and_then(ArrayHandler::json_to_array_literal)
+                                    .map(String::into_bytes)
+                                    .or_else(|| Some(bytes.clone()))
+                            }
                             t if t == PgType::Text.to_oid() => {
                                 // Enhanced datetime detection for TEXT columns
                                 if let Ok(s) = String::from_utf8(bytes.clone()) {
@@ -3845,6 +3851,8 @@ impl ExtendedQueryHandler {
                                     Some(bytes.clone())
                                 }
                             }
+                            // Bytea - raw BLOB bytes rendered per bytea_output
+                            t if t == PgType::Bytea.to_oid() => Some(bytea_output.encode(bytes).into_bytes()),
                             _ => {
                                 // For other types, keep as-is
                                 Some(bytes.clone())
@@ -3862,7 +3870,7 @@ impl ExtendedQueryHandler {
     
     async fn execute_select<T>(
         framed: &mut Framed<T, crate::protocol::PostgresCodec>,
-        db: &Arc<DbHandler>,
+        db: &Arc<dyn SessionStorage>,
         session: &Arc<SessionState>,
         portal_name: &str,
         query: &str,
@@ -3939,7 +3947,7 @@ impl ExtendedQueryHandler {
             };
             
             // Check cache first
-            let fields = if let Some(cached_fields) = GLOBAL_ROW_DESCRIPTION_CACHE.get(&cache_key) {
+            let fields = if let Some(cached_fields) = db.get_caches().row_descriptions.get(&cache_key) {
                 // Update formats from portal
                 let portals = session.portals.read().await;
                 let portal = portals.get(portal_name).unwrap();
@@ -4179,7 +4187,7 @@ impl ExtendedQueryHandler {
                     type_modifier: f.type_modifier,
                     format: 0, // Default format for cache
                 }).collect::<Vec<_>>();
-                GLOBAL_ROW_DESCRIPTION_CACHE.insert(cache_key, cache_fields);
+                db.get_caches().row_descriptions.insert(cache_key, cache_fields);
                 
                 fields
             };
@@ -4201,9 +4209,20 @@ impl ExtendedQueryHandler {
                 
                 // Get table name for schema lookup
                 let table_name = extract_table_name_from_select(&portal.query);
+                let checked_types = db.with_session_connection(&session.id, |conn| {
+                    Ok(crate::types::TypeChecker::new(conn).result_column_types(&portal.query))
+                }).await.ok().flatten()
+                    .filter(|types| types.len() == response.columns.len())
+                    .unwrap_or_default();
                 
                 for (i, col_name) in response.columns.iter().enumerate() {
-                    // Check for aggregate functions first
+                    // The type checker's type for the column comes first
+                    if let Some(Some(pg_type)) = checked_types.get(i) {
+                        field_types.push(pg_type.to_oid());
+                        continue;
+                    }
+                    
+                    // Check for aggregate functions
                     let col_lower = col_name.to_lowercase();
                     
                     if let Some(oid) = crate::types::SchemaTypeMapper::get_aggregate_return_type_with_query(&col_lower, None, None, Some(&portal.query)) {
@@ -4216,16 +4235,7 @@ impl ExtendedQueryHandler {
                     // This is crucial for datetime types which are stored as INTEGER in SQLite
                     let mut found_type = false;
                     if let Some(ref table) = table_name {
-                        // Try to extract source table.column from alias
-                        if let Some((source_table, source_col)) = Self::extract_source_table_column_for_alias(&portal.query, col_name) {
-                            if let Ok(Some(pg_type_str)) = db.get_schema_type_with_session(&session.id, &source_table, &source_col).await {
-                                let type_oid = crate::types::SchemaTypeMapper::pg_type_string_to_oid(&pg_type_str);
-                                info!("Column '{}': resolved type from schema '{}.{}' -> {} (OID {}) in execute_select", 
-                                      col_name, source_table, source_col, pg_type_str, type_oid);
-                                field_types.push(type_oid);
-                                found_type = true;
-                            }
-                        } else if col_name.contains('.') {
+                        if col_name.contains('.') {
                             // Handle columns with table prefix like "users.id"
                             let parts: Vec<&str> = col_name.split('.').collect();
                             if parts.len() == 2 {
@@ -4355,9 +4365,10 @@ impl ExtendedQueryHandler {
             }
         }
         
+        let bytea_output = session.bytea_output().await;
         for row in rows_to_send {
             // Convert row data based on result formats
-            let encoded_row = Self::encode_row(&row, &result_formats, &field_types)?;
+            let encoded_row = Self::encode_row(&row, &result_formats, &field_types, bytea_output)?;
             framed.send(BackendMessage::DataRow(encoded_row)).await
                 .map_err(PgSqliteError::Io)?;
         }
@@ -4397,7 +4408,7 @@ impl ExtendedQueryHandler {
     
     async fn execute_dml<T>(
         framed: &mut Framed<T, crate::protocol::PostgresCodec>,
-        db: &Arc<DbHandler>,
+        db: &Arc<dyn SessionStorage>,
         query: &str,
         portal_name: &str,
         session: &Arc<SessionState>,
@@ -4414,7 +4425,15 @@ impl ExtendedQueryHandler {
                 let portal = portals.get(portal_name).unwrap();
                 portal.result_formats.clone()
             };
-            return Self::execute_dml_with_returning(framed, db, session, query, &result_formats).await;
+            // Skip RowDescription if Describe already reported the RETURNING columns
+            let send_row_description = {
+                let portals = session.portals.read().await;
+                let statements = session.prepared_statements.read().await;
+                portals.get(portal_name)
+                    .and_then(|portal| statements.get(&portal.statement_name))
+                    .is_none_or(|stmt| stmt.field_descriptions.is_empty())
+            };
+            return Self::execute_dml_with_returning(framed, db, session, query, &result_formats, send_row_description).await;
         }
         
         // Validation is now done in handle_execute before parameter substitution
@@ -4439,9 +4458,53 @@ impl ExtendedQueryHandler {
         Ok(())
     }
     
+    /// Describe the columns of a DML statement's RETURNING clause without executing it
+    async fn describe_returning_fields(
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        query: &str,
+    ) -> Vec<FieldDescription> {
+        let Some((base_query, returning_clause)) = ReturningTranslator::extract_returning_clause(query) else {
+            return Vec::new();
+        };
+        
+        let table_name = if query_starts_with_ignore_case(&base_query, "INSERT") {
+            ReturningTranslator::extract_table_from_insert(&base_query)
+        } else if query_starts_with_ignore_case(&base_query, "UPDATE") {
+            ReturningTranslator::extract_table_from_update(&base_query)
+        } else if query_starts_with_ignore_case(&base_query, "DELETE") {
+            ReturningTranslator::extract_table_from_delete(&base_query)
+        } else {
+            None
+        };
+        let Some(table_name) = table_name else {
+            return Vec::new();
+        };
+        
+        // Read the column names off the equivalent SELECT, prepared but never run
+        let probe_query = format!("SELECT {returning_clause} FROM {table_name}");
+        match db.describe_with_session(&probe_query, &session.id).await {
+            Ok(described) => {
+                let columns: Vec<String> = described.into_iter().map(|(name, _)| name).collect();
+                Self::build_returning_field_descriptions(
+                    db,
+                    session,
+                    &table_name,
+                    &columns,
+                    &[],
+                    &returning_clause,
+                ).await
+            }
+            Err(e) => {
+                debug!("Could not describe RETURNING columns for '{}': {}", query, e);
+                Vec::new()
+            }
+        }
+    }
+    
     /// Helper function to build field descriptions for RETURNING clause with proper type detection
     async fn build_returning_field_descriptions(
-        db: &Arc<DbHandler>,
+        db: &Arc<dyn SessionStorage>,
         session: &Arc<SessionState>,
         table_name: &str,
         columns: &[String],
@@ -4450,6 +4513,14 @@ impl ExtendedQueryHandler {
     ) -> Vec<FieldDescription> {
         let mut fields = Vec::new();
         
+        // Expressions in the RETURNING list are typed like the select list of a query on the table
+        let probe_query = format!("SELECT {returning_clause} FROM {table_name}");
+        let checked_types = db.with_session_connection(&session.id, |conn| {
+            Ok(crate::types::TypeChecker::new(conn).result_column_types(&probe_query))
+        }).await.ok().flatten()
+            .filter(|types| types.len() == columns.len())
+            .unwrap_or_default();
+        
         for (i, col_name) in columns.iter().enumerate() {
             let format = if result_formats.is_empty() {
                 0 // Default to text if no formats specified
@@ -4461,16 +4532,20 @@ impl ExtendedQueryHandler {
                 0 // Default to text if not enough formats
             };
             
+            // Aliased column references (RETURNING t.id AS id__1) use the source column's type
+            let source_col = Self::returning_alias_source(returning_clause, col_name)
+                .unwrap_or_else(|| col_name.clone());
+            
             // Try to get the actual type from schema
             let type_oid = if returning_clause == "*" || col_name == &col_name.to_lowercase() {
                 // Direct column reference or wildcard - look up in schema
-                if let Ok(Some(pg_type_str)) = db.get_schema_type_with_session(&session.id, table_name, col_name).await {
+                if let Ok(Some(pg_type_str)) = db.get_schema_type_with_session(&session.id, table_name, &source_col).await {
                     // Convert PostgreSQL type name to OID
                     match pg_type_str.to_uppercase().as_str() {
                         "BOOL" | "BOOLEAN" => 16,
-                        "INT2" | "SMALLINT" => 21,
-                        "INT4" | "INTEGER" | "INT" => 23,
-                        "INT8" | "BIGINT" => 20,
+                        "INT2" | "SMALLINT" | "SMALLSERIAL" | "SERIAL2" => 21,
+                        "INT4" | "INTEGER" | "INT" | "SERIAL" | "SERIAL4" => 23,
+                        "INT8" | "BIGINT" | "BIGSERIAL" | "SERIAL8" => 20,
                         "FLOAT4" | "REAL" => 700,
                         "FLOAT8" | "DOUBLE PRECISION" => 701,
                         "TEXT" => 25,
@@ -4491,11 +4566,12 @@ impl ExtendedQueryHandler {
                         _ => 25, // Default to TEXT for unknown types
                     }
                 } else {
-                    25 // Default to TEXT if not found
+                    // Not a column: typed by the type checker, or TEXT
+                    checked_types.get(i).copied().flatten().map_or(25, |pg_type| pg_type.to_oid())
                 }
             } else {
-                // Could be an expression, default to TEXT
-                25
+                // An expression, typed by the type checker or sent as TEXT
+                checked_types.get(i).copied().flatten().map_or(25, |pg_type| pg_type.to_oid())
             };
             
             fields.push(FieldDescription {
@@ -4511,10 +4587,18 @@ impl ExtendedQueryHandler {
         
         fields
     }
+    
+    /// The column behind `[table.]column AS alias` in a RETURNING clause
+    fn returning_alias_source(returning_clause: &str, alias: &str) -> Option<String> {
+        let pattern = format!(r#"(?i)(?:^|,)\s*(?:\w+\.)?(\w+)\s+AS\s+"?{}"?\s*(?:,|$)"#, regex::escape(alias));
+        regex::Regex::new(&pattern).ok()?
+            .captures(returning_clause)
+            .map(|caps| caps[1].to_string())
+    }
 
     /// Helper function to convert timestamp columns in RETURNING results
     async fn convert_returning_timestamps(
-        db: &Arc<DbHandler>,
+        db: &Arc<dyn SessionStorage>,
         session: &Arc<SessionState>,
         table_name: &str,
         columns: &[String],
@@ -4555,16 +4639,18 @@ impl ExtendedQueryHandler {
 
     async fn execute_dml_with_returning<T>(
         framed: &mut Framed<T, crate::protocol::PostgresCodec>,
-        db: &Arc<DbHandler>,
+        db: &Arc<dyn SessionStorage>,
         session: &Arc<SessionState>,
         query: &str,
         result_formats: &[i16],
+        send_row_description: bool,
     ) -> Result<(), PgSqliteError>
     where
         T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
     {
         let (base_query, returning_clause) = ReturningTranslator::extract_returning_clause(query)
             .ok_or_else(|| PgSqliteError::Protocol("Failed to parse RETURNING clause".to_string()))?;
+        let bytea_output = session.bytea_output().await;
         
         if query_starts_with_ignore_case(&base_query, "INSERT") {
             // For INSERT, execute the insert and then query by last_insert_rowid
@@ -4627,9 +4713,12 @@ impl ExtendedQueryHandler {
                 result_formats,
                 &returning_clause,
             ).await;
+            let field_types: Vec<i32> = fields.iter().map(|f| f.type_oid).collect();
             
-            framed.send(BackendMessage::RowDescription(fields)).await
-                .map_err(PgSqliteError::Io)?;
+            if send_row_description {
+                framed.send(BackendMessage::RowDescription(fields)).await
+                    .map_err(PgSqliteError::Io)?;
+            }
             
             // Convert timestamps and send data rows
             let converted_rows = Self::convert_returning_timestamps(
@@ -4641,7 +4730,8 @@ impl ExtendedQueryHandler {
             ).await?;
             
             for row in converted_rows {
-                framed.send(BackendMessage::DataRow(row)).await
+                let encoded_row = Self::encode_row(&row, result_formats, &field_types, bytea_output)?;
+                framed.send(BackendMessage::DataRow(encoded_row)).await
                     .map_err(PgSqliteError::Io)?;
             }
             
@@ -4689,9 +4779,12 @@ impl ExtendedQueryHandler {
                     result_formats,
                     &returning_clause,
                 ).await;
+                let field_types: Vec<i32> = fields.iter().map(|f| f.type_oid).collect();
                 
-                framed.send(BackendMessage::RowDescription(fields)).await
-                    .map_err(PgSqliteError::Io)?;
+                if send_row_description {
+                    framed.send(BackendMessage::RowDescription(fields)).await
+                        .map_err(PgSqliteError::Io)?;
+                }
                 
                 // Convert timestamps and send data rows
                 let converted_rows = Self::convert_returning_timestamps(
@@ -4703,7 +4796,8 @@ impl ExtendedQueryHandler {
                 ).await?;
                 
                 for row in converted_rows {
-                    framed.send(BackendMessage::DataRow(row)).await
+                    let encoded_row = Self::encode_row(&row, result_formats, &field_types, bytea_output)?;
+                    framed.send(BackendMessage::DataRow(encoded_row)).await
                         .map_err(PgSqliteError::Io)?;
                 }
             }
@@ -4746,8 +4840,12 @@ impl ExtendedQueryHandler {
                 &returning_clause,
             ).await;
             
-            framed.send(BackendMessage::RowDescription(fields)).await
-                .map_err(PgSqliteError::Io)?;
+            let field_types: Vec<i32> = fields.iter().map(|f| f.type_oid).collect();
+            
+            if send_row_description {
+                framed.send(BackendMessage::RowDescription(fields)).await
+                    .map_err(PgSqliteError::Io)?;
+            }
             
             // Convert timestamps in captured rows (skip rowid column)
             let rows_without_rowid: Vec<Vec<Option<Vec<u8>>>> = captured_rows.rows.into_iter()
@@ -4764,7 +4862,8 @@ impl ExtendedQueryHandler {
             
             // Send converted rows
             for row in converted_rows {
-                framed.send(BackendMessage::DataRow(row)).await
+                let encoded_row = Self::encode_row(&row, result_formats, &field_types, bytea_output)?;
+                framed.send(BackendMessage::DataRow(encoded_row)).await
                     .map_err(PgSqliteError::Io)?;
             }
             
@@ -4779,49 +4878,91 @@ impl ExtendedQueryHandler {
     
     async fn execute_ddl<T>(
         framed: &mut Framed<T, crate::protocol::PostgresCodec>,
-        db: &Arc<DbHandler>,
+        db: &Arc<dyn SessionStorage>,
         session: &Arc<SessionState>,
         query: &str,
     ) -> Result<(), PgSqliteError>
     where
         T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
     {
-        use crate::ddl::EnumDdlHandler;
+        use crate::ddl::{CompositeDdlHandler, EnumDdlHandler, ForeignKeys};
+        
+        // CREATE INDEX CONCURRENTLY reads the table in batches before the build takes the write lock
+        if let Some(statement) = crate::query::ConcurrentIndexHandler::parse_concurrent_index(query)? {
+            return crate::query::ConcurrentIndexHandler::handle_concurrent_index(framed, db, session, &statement).await;
+        }
+        
+        // CREATE TYPE ... AS (...) only touches pgsqlite's metadata tables
+        if CompositeDdlHandler::is_composite_ddl(query) {
+            db.with_session_connection_mut(&session.id, |conn| {
+                Ok(CompositeDdlHandler::handle_create_type(conn, query))
+            }).await??;
+            
+            framed.send(BackendMessage::CommandComplete { tag: "CREATE TYPE".to_string() }).await
+                .map_err(PgSqliteError::Io)?;
+            
+            return Ok(());
+        }
         
-        // Check if this is an ENUM DDL statement first
+        // ENUM DDL changes pgsqlite's metadata tables, and the columns of the type on DROP TYPE ... CASCADE
         if EnumDdlHandler::is_enum_ddl(query) {
-            // ENUM DDL needs special handling through direct SQL execution
-            // Parse and execute the ENUM DDL as SQL statements
-            let enum_error = PgSqliteError::Protocol(
-                "ENUM DDL is not supported in the current per-session connection mode. \
-                Please create ENUMs before establishing connections.".to_string()
-            );
-            return Err(enum_error);
+            let tables = db.with_session_connection_mut(&session.id, |conn| {
+                Ok(EnumDdlHandler::handle_enum_ddl(conn, query))
+            }).await??;
+            for table in &tables {
+                crate::query::alter_table_handler::forget_table(db.as_ref(), table);
+            }
+            
+            framed.send(BackendMessage::CommandComplete { tag: EnumDdlHandler::command_tag(query).to_string() }).await
+                .map_err(PgSqliteError::Io)?;
+            
+            return Ok(());
         }
         
         // Handle CREATE TABLE translation
-        if query_starts_with_ignore_case(query, "CREATE TABLE") {
+        if crate::translator::CreateTableTranslator::is_create_table(query) {
             // Use translator with connection for ENUM support
-            let (sqlite_sql, type_mappings, enum_columns, array_columns) = db.with_session_connection(&session.id, |conn| {
+            let (sqlite_sql, type_mappings, enum_columns, array_columns, identity_columns, exclusion_constraints) = db.with_session_connection(&session.id, |conn| {
                 let result = crate::translator::CreateTableTranslator::translate_with_connection_full(query, Some(conn))
                     .map_err(|e| rusqlite::Error::SqliteFailure(
                         rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_ERROR),
                         Some(format!("CREATE TABLE translation failed: {e}"))
                     ))?;
                 
-                Ok((result.sql, result.type_mappings, result.enum_columns, result.array_columns))
+                Ok((result.sql, result.type_mappings, result.enum_columns, result.array_columns, result.identity_columns, result.exclusion_constraints))
             }).await
             .map_err(|e| PgSqliteError::Protocol(format!("Failed to translate CREATE TABLE: {e}")))?;
             
+            // A table this statement creates gets its foreign keys checked afterwards
+            let created_table = match crate::translator::CreateTableTranslator::created_table_name(query) {
+                Some(table_name) => {
+                    let exists = db.with_session_connection(&session.id, |conn| {
+                        conn.query_row(
+                            "SELECT COUNT(*) FROM (SELECT name, type FROM sqlite_master UNION ALL SELECT name, type FROM sqlite_temp_master) WHERE type = 'table' AND name = ?1",
+                            [&table_name],
+                            |row| row.get::<_, i64>(0),
+                        )
+                    }).await? > 0;
+                    (!exists).then_some(table_name)
+                }
+                None => None,
+            };
+            
             // Execute the translated CREATE TABLE
             let cached_conn = Self::get_or_cache_connection(session, db).await;
             db.execute_with_session_cached(&sqlite_sql, &session.id, cached_conn.as_ref()).await?;
             
+            if let Some(table_name) = created_table
+                && let Err(e) = db.with_session_connection(&session.id, |conn| Ok(ForeignKeys::validate_table(conn, &table_name))).await? {
+                db.execute_with_session_cached(&format!("DROP TABLE \"{table_name}\""), &session.id, cached_conn.as_ref()).await?;
+                return Err(e);
+            }
+            
             // Store the type mappings if we have any
             debug!("Type mappings count: {}", type_mappings.len());
             if !type_mappings.is_empty() {
                 // Extract table name from query
-                if let Some(table_name) = extract_table_name_from_create(query) {
+                if let Some(table_name) = crate::translator::CreateTableTranslator::created_table_name(query) {
                     // Initialize the metadata table if it doesn't exist
                     let init_query = "CREATE TABLE IF NOT EXISTS __pgsqlite_schema (
                         table_name TEXT NOT NULL,
@@ -4845,7 +4986,24 @@ impl ExtendedQueryHandler {
                             let cached_conn = Self::get_or_cache_connection(session, db).await;
                             let _ = db.execute_with_session_cached(&insert_query, &session.id, cached_conn.as_ref()).await;
                             
-                            // Store numeric constraints if applicable
+                            // Validate bit strings and their length on every write
+                            if let Some(constraint) = crate::validator::BitConstraint::from_column(&table_name, parts[1], &type_mapping.pg_type, type_mapping.type_modifier) {
+                                match db.with_session_connection(&session.id, |conn| constraint.create_triggers(conn)).await {
+                                    Ok(()) => info!("Created bit string triggers: {}.{}", table_name, parts[1]),
+                                    Err(e) => debug!("Failed to create bit string triggers for {}.{}: {}", table_name, parts[1], e),
+                                }
+                            }
+                            
+                            // Reject values that are not records of a composite column's type
+                            match db.with_session_connection(&session.id, |conn| {
+                                crate::ddl::CompositeDdlHandler::create_column_triggers(conn, &table_name, parts[1], &type_mapping.pg_type)
+                            }).await {
+                                Ok(true) => info!("Created composite type triggers: {}.{}", table_name, parts[1]),
+                                Ok(false) => {}
+                                Err(e) => debug!("Failed to create composite type triggers for {}.{}: {}", table_name, parts[1], e),
+                            }
+                            
+                            // Store string and numeric constraints if applicable
                             if let Some(modifier) = type_mapping.type_modifier {
                                 // Extract base type without parameters
                                 let base_type = if let Some(paren_pos) = type_mapping.pg_type.find('(') {
@@ -4855,7 +5013,24 @@ impl ExtendedQueryHandler {
                                 };
                                 let pg_type_lower = base_type.to_lowercase();
                                 
-                                if pg_type_lower == "numeric" || pg_type_lower == "decimal" {
+                                if pg_type_lower == "varchar" || pg_type_lower == "char" || 
+                                   pg_type_lower == "character varying" || pg_type_lower == "character" ||
+                                   pg_type_lower == "nvarchar" {
+                                    let constraint = crate::validator::StringConstraint {
+                                        table_name: table_name.to_string(),
+                                        column_name: parts[1].to_string(),
+                                        max_length: modifier,
+                                        is_char_type: pg_type_lower == "char" || pg_type_lower == "character",
+                                    };
+                                    
+                                    // Store the constraint and create the triggers enforcing it
+                                    match db.with_session_connection(&session.id, |conn| {
+                                        crate::validator::StringConstraintValidator::record_constraint(conn, &constraint)
+                                    }).await {
+                                        Ok(()) => info!("Stored string constraint: {}.{} max_length={}", table_name, parts[1], modifier),
+                                        Err(e) => debug!("Failed to store string constraint for {}.{}: {}", table_name, parts[1], e),
+                                    }
+                                } else if pg_type_lower == "numeric" || pg_type_lower == "decimal" {
                                     // Decode precision and scale from modifier
                                     let tmp_typmod = modifier - 4; // Remove VARHDRSZ
                                     let precision = (tmp_typmod >> 16) & 0xFFFF;
@@ -4882,6 +5057,8 @@ impl ExtendedQueryHandler {
                     }
                     
                     debug!("Stored type mappings for table {} (extended query protocol)", table_name);
+                    // A schema cached while the table was being created predates its column types
+                    db.get_schema_cache().invalidate(&table_name);
                     
                     // Create triggers for ENUM columns
                     if !enum_columns.is_empty() {
@@ -4908,6 +5085,26 @@ impl ExtendedQueryHandler {
                         .map_err(|e| PgSqliteError::Protocol(format!("Failed to create ENUM triggers: {e}")))?;
                     }
                     
+                    // Record SERIAL/identity columns and create their backing triggers
+                    if !identity_columns.is_empty() {
+                        db.with_session_connection(&session.id, |conn| {
+                            crate::metadata::IdentityColumns::record_identity_columns(conn, &table_name, &identity_columns)
+                                .map_err(|e| rusqlite::Error::SqliteFailure(
+                                    rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_ERROR),
+                                    Some(format!("Failed to record identity columns: {e}"))
+                                ))
+                        }).await
+                        .map_err(|e| PgSqliteError::Protocol(format!("Failed to record identity columns: {e}")))?;
+                    }
+                    
+                    // Enforce EXCLUDE constraints with an index and triggers
+                    for constraint in &exclusion_constraints {
+                        db.with_session_connection(&session.id, |conn| {
+                            constraint.create(conn)?;
+                            constraint.record(conn)
+                        }).await?;
+                    }
+                    
                     // Store array column metadata
                     if !array_columns.is_empty() {
                         db.with_session_connection(&session.id, |conn| {
@@ -4933,6 +5130,13 @@ impl ExtendedQueryHandler {
                                 
                                 info!("Stored array column metadata for {}.{} (element_type: {}, dimensions: {})", 
                                       table_name, column_name, element_type, dimensions);
+                                
+                                // Reject labels that are not values of an ENUM element type
+                                crate::metadata::EnumTriggers::create_array_column_triggers(conn, &table_name, column_name, element_type)
+                                    .map_err(|e| rusqlite::Error::SqliteFailure(
+                                        rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_ERROR),
+                                        Some(format!("Failed to create enum triggers: {e}"))
+                                    ))?;
                             }
                             Ok::<(), rusqlite::Error>(())
                         }).await
@@ -4941,6 +5145,9 @@ impl ExtendedQueryHandler {
                 }
             }
             
+            crate::ddl::ForeignKeyIndexer::index_foreign_keys(framed, db, session, query).await?;
+            db.with_session_connection(&session.id, crate::metadata::OidAllocator::sync_relations).await?;
+            
             // Send CommandComplete and return
             framed.send(BackendMessage::CommandComplete { tag: "CREATE TABLE".to_string() }).await
                 .map_err(PgSqliteError::Io)?;
@@ -4949,16 +5156,35 @@ impl ExtendedQueryHandler {
         };
         
         // Handle other DDL with potential JSON translation
-        let translated_query = if query.to_lowercase().contains("json") || query.to_lowercase().contains("jsonb") {
+        let mut translated_query = if query.to_lowercase().contains("json") || query.to_lowercase().contains("jsonb") {
             JsonTranslator::translate_statement(query)?
         } else {
             query.to_string()
         };
         
+        if query_starts_with_ignore_case(query, "DROP TABLE")
+            && let Some(drop) = db.with_session_connection(&session.id, |conn| Ok(ForeignKeys::prepare_drop_table(conn, query))).await?? {
+            for message in drop.notices {
+                framed.send(BackendMessage::NoticeResponse(crate::protocol::messages::NoticeResponse {
+                    severity: "NOTICE".to_string(),
+                    code: "00000".to_string(),
+                    message,
+                    detail: None,
+                    hint: None,
+                    position: None,
+                    where_: None,
+                })).await.map_err(PgSqliteError::Io)?;
+            }
+            translated_query = drop.sql;
+        }
+        
         let cached_conn = Self::get_or_cache_connection(session, db).await;
         db.execute_with_session_cached(&translated_query, &session.id, cached_conn.as_ref()).await?;
         
-        let tag = if query_starts_with_ignore_case(query, "CREATE TABLE") {
+        crate::ddl::ForeignKeyIndexer::index_foreign_keys(framed, db, session, query).await?;
+        db.with_session_connection(&session.id, crate::metadata::OidAllocator::sync_relations).await?;
+        
+        let tag = if crate::translator::CreateTableTranslator::is_create_table(query) {
             "CREATE TABLE".to_string()
         } else if query_starts_with_ignore_case(query, "DROP TABLE") {
             "DROP TABLE".to_string()
@@ -4976,23 +5202,29 @@ impl ExtendedQueryHandler {
     
     async fn execute_transaction<T>(
         framed: &mut Framed<T, crate::protocol::PostgresCodec>,
-        db: &Arc<DbHandler>,
+        db: &Arc<dyn SessionStorage>,
         session: &Arc<SessionState>,
         query: &str,
     ) -> Result<(), PgSqliteError>
     where
         T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
     {
+        use crate::protocol::TransactionStatus;
         if query_starts_with_ignore_case(query, "BEGIN") {
             db.begin_with_session(&session.id).await?;
+            session.set_transaction_status(TransactionStatus::InTransaction).await;
             framed.send(BackendMessage::CommandComplete { tag: "BEGIN".to_string() }).await
                 .map_err(PgSqliteError::Io)?;
         } else if query_starts_with_ignore_case(query, "COMMIT") {
             db.commit_with_session(&session.id).await?;
+            session.set_transaction_status(TransactionStatus::Idle).await;
+            session.end_transaction(true).await;
             framed.send(BackendMessage::CommandComplete { tag: "COMMIT".to_string() }).await
                 .map_err(PgSqliteError::Io)?;
         } else if query_starts_with_ignore_case(query, "ROLLBACK") {
             db.rollback_with_session(&session.id).await?;
+            session.set_transaction_status(TransactionStatus::Idle).await;
+            session.end_transaction(false).await;
             framed.send(BackendMessage::CommandComplete { tag: "ROLLBACK".to_string() }).await
                 .map_err(PgSqliteError::Io)?;
         }
@@ -5002,7 +5234,7 @@ impl ExtendedQueryHandler {
     
     async fn execute_generic<T>(
         framed: &mut Framed<T, crate::protocol::PostgresCodec>,
-        db: &Arc<DbHandler>,
+        db: &Arc<dyn SessionStorage>,
         session: &Arc<SessionState>,
         query: &str,
     ) -> Result<(), PgSqliteError>
@@ -5018,8 +5250,62 @@ impl ExtendedQueryHandler {
         Ok(())
     }
     
+    /// Parameter types for a query parsed without them, from the columns each parameter is
+    /// inserted into, assigned to or compared against. Also returns the original types used
+    /// for value conversion and the table and columns the parameters belong to.
+    pub(crate) async fn infer_param_types(
+        query: &str,
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+    ) -> (Vec<i32>, Option<Vec<i32>>, Option<String>, Vec<String>) {
+        let (mut analyzed_types, mut original_types_opt, table_name, column_names) = if let Some((types, orig_types)) = Self::analyze_dml_params(query, db).await {
+            debug!("Analyzed INSERT ... SELECT / UPDATE parameter types: {:?} (original: {:?})", types, orig_types);
+            (types, Some(orig_types), None, Vec::new())
+        } else if query_starts_with_ignore_case(query, "INSERT") {
+            match Self::analyze_insert_params(query, db).await {
+                Ok((types, orig_types)) => {
+                    debug!("Analyzed INSERT parameter types: {:?} (original: {:?})", types, orig_types);
+                    
+                    // Extract table and columns for caching
+                    let (table, cols) = crate::types::QueryContextAnalyzer::get_insert_column_info(query)
+                        .unwrap_or_else(|| (String::new(), Vec::new()));
+                    
+                    (types, Some(orig_types), Some(table), cols)
+                }
+                Err(_) => {
+                    // If we can't determine types, default to text
+                    let param_count = ParameterParser::count_parameters(query);
+                    let types = vec![PgType::Text.to_oid(); param_count];
+                    (types.clone(), Some(types), None, Vec::new())
+                }
+            }
+        } else if query_starts_with_ignore_case(query, "SELECT") || crate::translator::CteTranslator::is_select(query) {
+            let types = Self::analyze_select_params(query, db, session).await.unwrap_or_else(|_| {
+                // If we can't determine types, default to text
+                let param_count = ParameterParser::count_parameters(query);
+                vec![PgType::Text.to_oid(); param_count]
+            });
+            debug!("Analyzed SELECT parameter types: {:?}", types);
+            
+            let table = extract_table_name_from_select(query);
+            (types.clone(), Some(types), table, Vec::new())
+        } else {
+            // Other query types - just count parameters
+            let param_count = ParameterParser::count_parameters(query);
+            let types = vec![PgType::Text.to_oid(); param_count];
+            (types.clone(), Some(types), None, Vec::new())
+        };
+        
+        Self::apply_subscript_param_types(query, db, &mut analyzed_types).await;
+        if let Some(original_types) = original_types_opt.as_mut() {
+            Self::apply_subscript_param_types(query, db, original_types).await;
+        }
+        
+        (analyzed_types, original_types_opt, table_name, column_names)
+    }
+    
     /// Analyze INSERT query to determine parameter types from schema
-    async fn analyze_insert_params(query: &str, db: &Arc<DbHandler>) -> Result<(Vec<i32>, Vec<i32>), PgSqliteError> {
+    async fn analyze_insert_params(query: &str, db: &Arc<dyn SessionStorage>) -> Result<(Vec<i32>, Vec<i32>), PgSqliteError> {
         // Use QueryContextAnalyzer to extract table and column info
         let (table_name, columns) = crate::types::QueryContextAnalyzer::get_insert_column_info(query)
             .ok_or_else(|| PgSqliteError::Protocol("Failed to parse INSERT query".to_string()))?;
@@ -5046,21 +5332,7 @@ impl ExtendedQueryHandler {
             if let Some(col_info) = table_schema.column_map.get(&column.to_lowercase()) {
                 original_types.push(col_info.pg_oid);
                 
-                // For certain PostgreSQL types that tokio-postgres doesn't support in binary format,
-                // use TEXT as the parameter type to allow string representation
-                let param_oid = match col_info.pg_oid {
-                    t if t == PgType::Macaddr8.to_oid() => PgType::Text.to_oid(), // MACADDR8 -> TEXT
-                    t if t == PgType::Macaddr.to_oid() => PgType::Text.to_oid(), // MACADDR -> TEXT  
-                    t if t == PgType::Inet.to_oid() => PgType::Text.to_oid(), // INET -> TEXT
-                    t if t == PgType::Cidr.to_oid() => PgType::Text.to_oid(), // CIDR -> TEXT
-                    t if t == PgType::Money.to_oid() => PgType::Text.to_oid(), // MONEY -> TEXT
-                    t if t == PgType::Int4range.to_oid() => PgType::Text.to_oid(), // INT4RANGE -> TEXT
-                    t if t == PgType::Int8range.to_oid() => PgType::Text.to_oid(), // INT8RANGE -> TEXT
-                    t if t == PgType::Numrange.to_oid() => PgType::Text.to_oid(), // NUMRANGE -> TEXT
-                    t if t == PgType::Bit.to_oid() => PgType::Text.to_oid(), // BIT -> TEXT
-                    t if t == PgType::Varbit.to_oid() => PgType::Text.to_oid(), // VARBIT -> TEXT
-                    _ => col_info.pg_oid, // Use original OID for supported types
-                };
+                let param_oid = Self::binary_compatible_param_oid(col_info.pg_oid);
                 
                 param_types.push(param_oid);
                 if param_oid != col_info.pg_oid {
@@ -5078,9 +5350,143 @@ impl ExtendedQueryHandler {
             }
         }
         
+        // Multi-row VALUES lists bind more parameters than there are columns, so map
+        // each placeholder to the column it is inserted into
+        if let Some(placeholder_columns) = Self::insert_placeholder_columns(query)
+            && placeholder_columns.iter().flatten().all(|&column| column < param_types.len())
+        {
+            let by_column = |types: &[i32]| placeholder_columns.iter()
+                .map(|column| column.map_or(PgType::Text.to_oid(), |c| types[c]))
+                .collect::<Vec<_>>();
+            return Ok((by_column(&param_types), by_column(&original_types)));
+        }
+        
         Ok((param_types, original_types))
     }
     
+    /// For certain PostgreSQL types that tokio-postgres doesn't support in binary format,
+    /// use TEXT as the parameter type to allow string representation
+    fn binary_compatible_param_oid(oid: i32) -> i32 {
+        match oid {
+            t if t == PgType::Macaddr8.to_oid() => PgType::Text.to_oid(), // MACADDR8 -> TEXT
+            t if t == PgType::Macaddr.to_oid() => PgType::Text.to_oid(), // MACADDR -> TEXT
+            t if t == PgType::Inet.to_oid() => PgType::Text.to_oid(), // INET -> TEXT
+            t if t == PgType::Cidr.to_oid() => PgType::Text.to_oid(), // CIDR -> TEXT
+            t if t == PgType::Money.to_oid() => PgType::Text.to_oid(), // MONEY -> TEXT
+            t if t == PgType::Int4range.to_oid() => PgType::Text.to_oid(), // INT4RANGE -> TEXT
+            t if t == PgType::Int8range.to_oid() => PgType::Text.to_oid(), // INT8RANGE -> TEXT
+            t if t == PgType::Numrange.to_oid() => PgType::Text.to_oid(), // NUMRANGE -> TEXT
+            t if t == PgType::Tsrange.to_oid() => PgType::Text.to_oid(), // TSRANGE -> TEXT
+            t if t == PgType::Tstzrange.to_oid() => PgType::Text.to_oid(), // TSTZRANGE -> TEXT
+            t if t == PgType::Daterange.to_oid() => PgType::Text.to_oid(), // DATERANGE -> TEXT
+            t if t == PgType::Bit.to_oid() => PgType::Text.to_oid(), // BIT -> TEXT
+            t if t == PgType::Varbit.to_oid() => PgType::Text.to_oid(), // VARBIT -> TEXT
+            t if t == PgType::Xml.to_oid() => PgType::Text.to_oid(), // XML -> TEXT
+            _ => oid, // Use original OID for supported types
+        }
+    }
+    
+    /// Analyze `INSERT ... SELECT` and `UPDATE ... [FROM]` parameters, typing each one from the
+    /// column it is inserted into, assigned to or compared against. Returns None for other shapes.
+    async fn analyze_dml_params(query: &str, db: &Arc<dyn SessionStorage>) -> Option<(Vec<i32>, Vec<i32>)> {
+        let param_count = ParameterParser::count_parameters(query);
+        let columns = crate::query::param_type_inference::dml_parameter_columns(query, param_count)?;
+        
+        let mut param_types = Vec::with_capacity(columns.len());
+        let mut original_types = Vec::with_capacity(columns.len());
+        for (i, param_column) in columns.iter().enumerate() {
+            let mut oid = PgType::Text.to_oid();
+            if let Some(param_column) = param_column {
+                for table in &param_column.tables {
+                    if let Ok(schema) = db.get_table_schema(table).await
+                        && let Some(col_info) = schema.column_map.get(&param_column.column)
+                    {
+                        info!("Parameter ${} takes the type of {}.{}: {} (OID {})",
+                              i + 1, table, param_column.column, col_info.pg_type, col_info.pg_oid);
+                        oid = col_info.pg_oid;
+                        break;
+                    }
+                }
+            }
+            original_types.push(oid);
+            param_types.push(Self::binary_compatible_param_oid(oid));
+        }
+        
+        Some((param_types, original_types))
+    }
+    
+    /// For `INSERT ... VALUES (...), (...)`, the column index each `$n` is inserted into
+    /// (indexed by parameter number - 1). Parameters used elsewhere map to None.
+    fn insert_placeholder_columns(query: &str) -> Option<Vec<Option<usize>>> {
+        use sqlparser::ast::{Expr, SetExpr, Statement, Value};
+        
+        let statements = sqlparser::parser::Parser::parse_sql(&sqlparser::dialect::PostgreSqlDialect {}, query).ok()?;
+        let Some(Statement::Insert(insert)) = statements.first() else {
+            return None;
+        };
+        let SetExpr::Values(values) = insert.source.as_ref()?.body.as_ref() else {
+            return None;
+        };
+        
+        let mut columns = vec![None; ParameterParser::count_parameters(query)];
+        for row in &values.rows {
+            for (column, expr) in row.iter().enumerate() {
+                if let Expr::Value(value) = expr
+                    && let Value::Placeholder(placeholder) = &value.value
+                    && let Some(slot) = placeholder.strip_prefix('$')
+                        .and_then(|n| n.parse::<usize>().ok())
+                        .and_then(|n| columns.get_mut(n.checked_sub(1)?))
+                {
+                    *slot = Some(column);
+                }
+            }
+        }
+        Some(columns)
+    }
+    
+    /// Restore inet/cidr/macaddr and xml parameter types, which binary_compatible_param_oid reports
+    /// as TEXT, so their values are validated and network addresses stored in canonical form
+    fn validated_param_types(query: &str, mut param_types: Vec<i32>) -> Vec<i32> {
+        if let Some(cached_info) = GLOBAL_PARAMETER_CACHE.get(query) {
+            for (param_type, &original) in param_types.iter_mut().zip(cached_info.original_types.iter()) {
+                if PgType::from_oid(original).and_then(crate::types::NetworkKind::from_pg_type).is_some()
+                    || original == PgType::Xml.to_oid() {
+                    *param_type = original;
+                }
+            }
+        }
+        param_types
+    }
+    
+    /// Array subscripts and slice bounds bound as `$n` are integers, and values assigned
+    /// through `SET col[i] = $n` take the array's element type
+    async fn apply_subscript_param_types(query: &str, db: &Arc<dyn SessionStorage>, param_types: &mut [i32]) {
+        for index in crate::translator::ArrayTranslator::subscript_parameters(query) {
+            if let Some(oid) = index.checked_sub(1).and_then(|i| param_types.get_mut(i)) {
+                *oid = PgType::Int4.to_oid();
+            }
+        }
+        
+        let assignments = crate::translator::ArrayTranslator::subscript_assignment_parameters(query);
+        if assignments.is_empty() {
+            return;
+        }
+        let Some(table) = Self::extract_table_name_from_update(query) else {
+            return;
+        };
+        let Ok(schema) = db.get_table_schema(&table).await else {
+            return;
+        };
+        for (column, index) in assignments {
+            if let Some(col_info) = schema.column_map.get(&column)
+                && let Some(element_type) = crate::types::ArrayHandler::element_type(col_info.pg_oid)
+                && let Some(oid) = index.checked_sub(1).and_then(|i| param_types.get_mut(i))
+            {
+                *oid = element_type.to_oid();
+            }
+        }
+    }
+    
     /// Convert PostgreSQL type name to OID
     fn pg_type_name_to_oid(type_name: &str) -> i32 {
         match type_name.to_lowercase().as_str() {
@@ -5109,12 +5515,16 @@ impl ExtendedQueryHandler {
             "int4range" => PgType::Int4range.to_oid(),
             "int8range" => PgType::Int8range.to_oid(),
             "numrange" => PgType::Numrange.to_oid(),
+            "tsrange" => PgType::Tsrange.to_oid(),
+            "tstzrange" => PgType::Tstzrange.to_oid(),
+            "daterange" => PgType::Daterange.to_oid(),
             "cidr" => PgType::Cidr.to_oid(),
             "inet" => PgType::Inet.to_oid(),
             "macaddr" => PgType::Macaddr.to_oid(),
             "macaddr8" => PgType::Macaddr8.to_oid(),
             "bit" => PgType::Bit.to_oid(),
             "varbit" | "bit varying" => PgType::Varbit.to_oid(),
+            "xml" => PgType::Xml.to_oid(),
             _ => {
                 info!("Unknown PostgreSQL type '{}', defaulting to text", type_name);
                 PgType::Text.to_oid() // Default to text
@@ -5123,7 +5533,7 @@ impl ExtendedQueryHandler {
     }
 
     /// Analyze SELECT query to determine parameter types from WHERE clause
-    async fn analyze_select_params(query: &str, db: &Arc<DbHandler>, session: &Arc<SessionState>) -> Result<Vec<i32>, PgSqliteError> {
+    async fn analyze_select_params(query: &str, db: &Arc<dyn SessionStorage>, session: &Arc<SessionState>) -> Result<Vec<i32>, PgSqliteError> {
         // First, check for explicit parameter casts like $1::int4
         let mut param_types = Vec::new();
         
@@ -5151,7 +5561,51 @@ impl ExtendedQueryHandler {
             if found_type {
                 continue;
             }
-            
+
+            // Pagination counts (LIMIT $n, OFFSET $n [ROWS], FETCH FIRST $n ROWS ONLY) are bigint
+            if crate::translator::PaginationTranslator::pagination_parameters(query).contains(&i) {
+                param_types.push(PgType::Int8.to_oid());
+                info!("Parameter {} is a pagination count, using int8", i);
+                continue;
+            }
+
+            // Bounds and steps of generate_series() are integers
+            if crate::translator::SetReturningTranslator::series_parameters(query).contains(&i) {
+                param_types.push(PgType::Int4.to_oid());
+                info!("Parameter {} is a generate_series argument, using int4", i);
+                continue;
+            }
+
+            // A composite field compared with the parameter, e.g. (home).zip > $1
+            if let Ok(Some(pg_type)) = db.with_session_connection(&session.id, |conn| {
+                Ok(crate::translator::CompositeTranslator::param_field_type(query, i, conn))
+            }).await {
+                let oid = crate::types::SchemaTypeMapper::pg_type_string_to_oid(&pg_type);
+                param_types.push(oid);
+                info!("Found type for parameter {} from composite field: {} (OID {})", i, pg_type, oid);
+                continue;
+            }
+
+            // An argument of a SQL function takes the argument's declared type
+            if let Ok(Some(oid)) = db.with_session_connection(&session.id, |conn| {
+                Ok(crate::query::FunctionHandler::param_argument_type(query, i, conn))
+            }).await {
+                param_types.push(oid);
+                info!("Parameter {} is a SQL function argument of type OID {}", i, oid);
+                continue;
+            }
+
+            // Catalog OID columns such as pg_type.oid and pg_enum.enumtypid take oid
+            // parameters, which drivers bind when they look up a type by its OID
+            if query.contains("pg_")
+                && let Ok(column_regex) = regex::Regex::new(&format!(r"(\w+)\s*=\s*{}\b", regex::escape(&param)))
+                && let Some(column) = column_regex.captures(query).map(|caps| caps[1].to_lowercase())
+                && Self::get_catalog_column_type(&column, query) == 26 {
+                param_types.push(26);
+                info!("Parameter {} is compared with catalog column {}, using oid", i, column);
+                continue;
+            }
+
             // If no explicit cast, try to infer from column comparisons
             // Extract table name from SELECT query (only if needed)
             let table_name = if let Some(name) = extract_table_name_from_select(query) {
@@ -5172,17 +5626,27 @@ impl ExtendedQueryHandler {
             // Look for the parameter in the query and find the column it's compared to
             // Use simpler string matching instead of complex regex
             let param_escaped = regex::escape(&param);
+            // The flag marks patterns where the parameter is an array of the column's type
             let patterns = vec![
-                format!(r"(\w+)\s*=\s*{}", param_escaped),
-                format!(r"(\w+)\s*<\s*{}", param_escaped),
-                format!(r"(\w+)\s*>\s*{}", param_escaped),
-                format!(r"(\w+)\s*<=\s*{}", param_escaped),
-                format!(r"(\w+)\s*>=\s*{}", param_escaped),
-                format!(r"(\w+)\s*!=\s*{}", param_escaped),
-                format!(r"(\w+)\s*<>\s*{}", param_escaped),
+                (format!(r"(\w+)\s*(?:=|<>|!=|<=|>=|<|>)\s*(?:any|all)\s*\(\s*{}\s*\)", param_escaped), true),
+                (format!(r"(\w+)\s*(?:@>|<@|&&)\s*{}", param_escaped), false),
+                (format!(r"(\w+)\s*=\s*{}", param_escaped), false),
+                (format!(r"(\w+)\s*<\s*{}", param_escaped), false),
+                (format!(r"(\w+)\s*>\s*{}", param_escaped), false),
+                (format!(r"(\w+)\s*<=\s*{}", param_escaped), false),
+                (format!(r"(\w+)\s*>=\s*{}", param_escaped), false),
+                (format!(r"(\w+)\s*!=\s*{}", param_escaped), false),
+                (format!(r"(\w+)\s*<>\s*{}", param_escaped), false),
             ];
+            let to_param_oid = |oid: i32, is_array_element: bool| {
+                if is_array_element {
+                    PgType::from_oid(oid).and_then(|t| t.array_type()).map_or(PgType::TextArray.to_oid(), |t| t.to_oid())
+                } else {
+                    oid
+                }
+            };
             
-            for pattern in &patterns {
+            for (pattern, is_array_element) in &patterns {
                 let regex = regex::Regex::new(pattern).unwrap();
                 if let Some(captures) = regex.captures(&query_lower)
                     && let Some(column_match) = captures.get(1) {
@@ -5190,7 +5654,7 @@ impl ExtendedQueryHandler {
                         
                         // Look up the type for this column
                         if let Ok(Some(pg_type)) = db.get_schema_type_with_session(&session.id, &table_name, column).await {
-                            let oid = crate::types::SchemaTypeMapper::pg_type_string_to_oid(&pg_type);
+                            let oid = to_param_oid(crate::types::SchemaTypeMapper::pg_type_string_to_oid(&pg_type), *is_array_element);
                             param_types.push(oid);
                             info!("Found type for parameter {} from column {}: {} (OID {})", 
                                   i, column, pg_type, oid);
@@ -5207,7 +5671,7 @@ impl ExtendedQueryHandler {
                                             String::from_utf8(type_bytes.clone())
                                         )
                                             && col_name.to_lowercase() == column {
-                                                let pg_type = crate::types::SchemaTypeMapper::sqlite_type_to_pg_oid(&sqlite_type);
+                                                let pg_type = to_param_oid(crate::types::SchemaTypeMapper::sqlite_type_to_pg_oid(&sqlite_type), *is_array_element);
                                                 param_types.push(pg_type);
                                                 info!("Mapped SQLite type for parameter {} from column {}: {} -> PG OID {}", 
                                                       i, column, sqlite_type, pg_type);
@@ -5218,6 +5682,17 @@ impl ExtendedQueryHandler {
                             }
                         }
                         
+                        // A column of a CTE takes the type of what the CTE selects
+                        if !found_type
+                            && crate::translator::CteTranslator::needs_translation(query)
+                            && let Ok(Some(pg_type)) = db.with_session_connection(&session.id, |conn| {
+                                Ok(crate::translator::CteTranslator::column_type(query, column, conn))
+                            }).await {
+                            param_types.push(to_param_oid(pg_type.to_oid(), *is_array_element));
+                            info!("Found type for parameter {} from CTE column {}: {:?}", i, column, pg_type);
+                            found_type = true;
+                        }
+                        
                         if found_type {
                             break;
                         }
@@ -5367,12 +5842,16 @@ impl ExtendedQueryHandler {
             "int4range" => PgType::Int4range.to_oid(),
             "int8range" => PgType::Int8range.to_oid(),
             "numrange" => PgType::Numrange.to_oid(),
+            "tsrange" => PgType::Tsrange.to_oid(),
+            "tstzrange" => PgType::Tstzrange.to_oid(),
+            "daterange" => PgType::Daterange.to_oid(),
             "cidr" => PgType::Cidr.to_oid(),
             "inet" => PgType::Inet.to_oid(),
             "macaddr" => PgType::Macaddr.to_oid(),
             "macaddr8" => PgType::Macaddr8.to_oid(),
             "bit" => PgType::Bit.to_oid(),
             "varbit" | "bit varying" => PgType::Varbit.to_oid(),
+            "xml" => PgType::Xml.to_oid(),
             _ => PgType::Text.to_oid(), // Default to text for unknown types
         }
     }
@@ -5548,36 +6027,3 @@ fn extract_table_name_from_select(query: &str) -> Option<String> {
         None
     }
 }
-
-/// Extract table name from CREATE TABLE statement
-fn extract_table_name_from_create(query: &str) -> Option<String> {
-    // Look for CREATE TABLE pattern
-    if let Some(table_pos) = find_keyword_position(query, "CREATE TABLE") {
-        let after_create = &query[table_pos + 12..].trim();
-        
-        // Skip IF NOT EXISTS if present
-        let after_create = if query_starts_with_ignore_case(after_create, "IF NOT EXISTS") {
-            &after_create[13..].trim()
-        } else {
-            after_create
-        };
-        
-        // Find the end of table name
-        let table_end = after_create.find(|c: char| {
-            c.is_whitespace() || c == '('
-        }).unwrap_or(after_create.len());
-        
-        let table_name = after_create[..table_end].trim();
-        
-        // Remove quotes if present
-        let table_name = table_name.trim_matches('"').trim_matches('\'');
-        
-        if !table_name.is_empty() {
-            Some(table_name.to_string())
-        } else {
-            None
-        }
-    } else {
-        None
-    }
-}
\ No newline at end of file
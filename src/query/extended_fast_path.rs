@@ -1,6 +1,6 @@
 use crate::protocol::BackendMessage;
-use crate::session::{DbHandler, SessionState};
-use crate::types::{DecimalHandler, PgType};
+use crate::session::{SessionState, SessionStorage};
+use crate::types::{ArrayHandler, ByteaFormat, DecimalHandler, NetworkKind, PgType, XmlOption, xml};
 use crate::cache::GLOBAL_PARAM_VALUE_CACHE;
 use crate::PgSqliteError;
 use tokio_util::codec::Framed;
@@ -24,7 +24,7 @@ impl ExtendedFastPath {
     /// Execute a parameterized query using prepared statements directly
     pub async fn execute_with_params<T>(
         framed: &mut Framed<T, crate::protocol::PostgresCodec>,
-        db: &Arc<DbHandler>,
+        db: &Arc<dyn SessionStorage>,
         session: &Arc<SessionState>,
         portal_name: &str,
         query: &str,
@@ -74,6 +74,11 @@ impl ExtendedFastPath {
                 }
             }
             QueryType::Insert | QueryType::Update | QueryType::Delete => {
+                // RETURNING needs typed field descriptions that match Describe, which the
+                // normal path builds from the table schema
+                if crate::translator::ReturningTranslator::has_returning_clause(query) {
+                    return Ok(false);
+                }
                 match Self::execute_dml_with_params(framed, db, session, query, rusqlite_params, query_type).await {
                     Ok(()) => {
                         Ok(true)
@@ -275,13 +280,37 @@ impl ExtendedFastPath {
                     // TODO: Implement proper conversion for TIMESTAMPTZ, TIMETZ, INTERVAL
                     Ok(rusqlite::types::Value::Text(text.to_string()))
                 }
-                t if t == PgType::Money.to_oid() || t == PgType::Macaddr.to_oid() || t == PgType::Macaddr8.to_oid() ||
-                     t == PgType::Inet.to_oid() || t == PgType::Cidr.to_oid() || t == PgType::Int4range.to_oid() ||
-                     t == PgType::Int8range.to_oid() || t == PgType::Numrange.to_oid() || t == PgType::Bit.to_oid() ||
+                t if t == PgType::Inet.to_oid() || t == PgType::Cidr.to_oid() || t == PgType::Macaddr.to_oid() => {
+                    // INET, CIDR, MACADDR - validate and store in canonical form
+                    PgType::from_oid(t).and_then(NetworkKind::from_pg_type)
+                        .map_or_else(|| Ok(text.to_string()), |kind| kind.canonicalize(text))
+                        .map(rusqlite::types::Value::Text)
+                        .map_err(PgSqliteError::Protocol)
+                }
+                t if t == PgType::Xml.to_oid() => {
+                    // XML - stored as written once it's known to be well-formed content
+                    xml::validate(text, XmlOption::Content)
+                        .map(|_| rusqlite::types::Value::Text(text.to_string()))
+                        .map_err(PgSqliteError::Protocol)
+                }
+                t if t == PgType::Money.to_oid() || t == PgType::Macaddr8.to_oid() || t == PgType::Int4range.to_oid() ||
+                     t == PgType::Int8range.to_oid() || t == PgType::Numrange.to_oid() || t == PgType::Tsrange.to_oid() ||
+                     t == PgType::Tstzrange.to_oid() || t == PgType::Daterange.to_oid() || t == PgType::Bit.to_oid() ||
                      t == PgType::Varbit.to_oid() => {
                     // Special types that are mapped to TEXT
                     Ok(rusqlite::types::Value::Text(text.to_string()))
                 }
+                t if ArrayHandler::is_array_oid(t) && text.trim_start().starts_with('{') => {
+                    // Array literal - stored as JSON
+                    ArrayHandler::array_literal_to_json(text, ArrayHandler::element_type(t))
+                        .map(rusqlite::types::Value::Text)
+                }
+                t if t == PgType::Bytea.to_oid() => {
+                    // BYTEA - hex or escape text stored as BLOB
+                    ByteaFormat::decode(text)
+                        .map(rusqlite::types::Value::Blob)
+                        .map_err(PgSqliteError::Protocol)
+                }
                 _ => {
                     // Default to TEXT
                     Ok(rusqlite::types::Value::Text(text.to_string()))
@@ -497,9 +526,37 @@ impl ExtendedFastPath {
                         Err(PgSqliteError::Protocol("Invalid INTERVAL binary format".to_string()))
                     }
                 }
-                t if t == PgType::Macaddr.to_oid() || t == PgType::Macaddr8.to_oid() || t == PgType::Inet.to_oid() ||
-                     t == PgType::Cidr.to_oid() || t == PgType::Int4range.to_oid() || t == PgType::Int8range.to_oid() ||
-                     t == PgType::Numrange.to_oid() || t == PgType::Bit.to_oid() || t == PgType::Varbit.to_oid() => {
+                t if t == PgType::Inet.to_oid() || t == PgType::Cidr.to_oid() || t == PgType::Macaddr.to_oid() => {
+                    // INET, CIDR, MACADDR - binary encoding or UTF-8 text
+                    PgType::from_oid(t).and_then(NetworkKind::from_pg_type)
+                        .ok_or_else(|| PgSqliteError::Protocol(format!("Unsupported network type OID {t}")))?
+                        .decode_param(bytes)
+                        .map(rusqlite::types::Value::Text)
+                        .map_err(PgSqliteError::Protocol)
+                }
+                t if t == PgType::Xml.to_oid() => {
+                    // XML - UTF-8 text, checked for being well-formed content
+                    let text = std::str::from_utf8(bytes)
+                        .map_err(|_| PgSqliteError::Protocol("Invalid UTF-8 in XML parameter".to_string()))?;
+                    xml::validate(text, XmlOption::Content)
+                        .map(|_| rusqlite::types::Value::Text(text.to_string()))
+                        .map_err(PgSqliteError::Protocol)
+                }
+                t if t == PgType::Uuid.to_oid() => {
+                    // UUID - 16 bytes, stored in its text form
+                    crate::protocol::BinaryDecoder::decode_uuid(bytes)
+                        .map(rusqlite::types::Value::Text)
+                        .map_err(PgSqliteError::Protocol)
+                }
+                t if t == PgType::Json.to_oid() || t == PgType::Jsonb.to_oid() => {
+                    // JSON - UTF-8 text, JSONB - a version byte followed by the text
+                    crate::protocol::BinaryDecoder::decode_json(bytes, t)
+                        .map(|json| rusqlite::types::Value::Text(json.to_string()))
+                        .map_err(PgSqliteError::Protocol)
+                }
+                t if t == PgType::Macaddr8.to_oid() || t == PgType::Int4range.to_oid() || t == PgType::Int8range.to_oid() ||
+                     t == PgType::Numrange.to_oid() || t == PgType::Tsrange.to_oid() || t == PgType::Tstzrange.to_oid() ||
+                     t == PgType::Daterange.to_oid() || t == PgType::Bit.to_oid() || t == PgType::Varbit.to_oid() => {
                     // Other special types - for now, error out so we can implement them properly
                     Err(PgSqliteError::Protocol(format!("Binary format not implemented for type {param_type}")))
                 }
@@ -542,6 +599,12 @@ impl ExtendedFastPath {
                         Ok(rusqlite::types::Value::Blob(bytes.to_vec()))
                     }
                 }
+                t if ArrayHandler::is_array_oid(t) => {
+                    // Binary array - stored as JSON
+                    crate::protocol::BinaryDecoder::decode_array(bytes)
+                        .map(|array| rusqlite::types::Value::Text(array.to_string()))
+                        .map_err(|e| PgSqliteError::Protocol(format!("Invalid binary array: {e}")))
+                }
                 _ => {
                     // Store as BLOB for unsupported binary types
                     // Unknown binary type, storing as blob
@@ -553,7 +616,7 @@ impl ExtendedFastPath {
     
     async fn execute_select_with_params<T>(
         framed: &mut Framed<T, crate::protocol::PostgresCodec>,
-        db: &Arc<DbHandler>,
+        db: &Arc<dyn SessionStorage>,
         session: &Arc<SessionState>,
         portal_name: &str,
         query: &str,
@@ -740,7 +803,7 @@ impl ExtendedFastPath {
     
     async fn execute_dml_with_params<T>(
         framed: &mut Framed<T, crate::protocol::PostgresCodec>,
-        db: &Arc<DbHandler>,
+        db: &Arc<dyn SessionStorage>,
         session: &Arc<SessionState>,
         query: &str,
         params: Vec<rusqlite::types::Value>,
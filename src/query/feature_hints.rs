@@ -0,0 +1,123 @@
+use crate::protocol::ErrorResponse;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Errors for features pgsqlite lacks, by a pattern of their message, and the HINT
+/// pointing at what it offers instead. The first matching entry wins.
+const UNSUPPORTED_FEATURES: &[(&str, &str)] = &[
+    // Functions and triggers
+    (r"^trigger functions in language", "Write the trigger function in LANGUAGE plpgsql."),
+    (r"^constraint triggers are not supported", "Use a regular AFTER ... FOR EACH ROW trigger; it runs as each row changes instead of at commit."),
+    (r"^INSTEAD OF triggers are not supported", "Write to the tables under the view instead."),
+    (r"^(statement-level triggers|transition tables in triggers) are not supported", "Use a FOR EACH ROW trigger, which sees each changed row as NEW and OLD."),
+    (r"^trigger function arguments are not supported", "Use a trigger function of its own for each set of arguments."),
+    (r"^DECLARE sections in trigger functions", "Use the expressions where the variables would be used."),
+    (r"^EXCEPTION blocks in trigger functions", "Check the failing condition with IF beforehand, or leave it to the statement's error."),
+    (r"^RETURN OLD in BEFORE UPDATE triggers", "Assign OLD's values to the columns of NEW and RETURN NEW, or RETURN NULL to skip the row."),
+    (r"^unsupported PL/pgSQL statement in trigger function", "Trigger functions can assign to NEW, branch with IF, RAISE, RETURN, and run INSERT, UPDATE and DELETE statements."),
+    (r"^SQL functions must have exactly one statement", "Put each statement in a function of its own."),
+    (r"^set-returning SQL functions are not supported", "Use a view, and filter it where the function would take arguments."),
+    (r"^(PL/pgSQL functions are only supported as trigger functions|functions in language)", "Write the function in LANGUAGE sql, with a single SELECT in its body."),
+    (r"overloading functions is not supported", "Give each variant a name of its own, or use CREATE OR REPLACE FUNCTION to replace the function."),
+    (r"^\w+ arguments are not supported in SQL functions", "Return the value through RETURNS instead."),
+    (r"^argument defaults are not supported in SQL functions", "Pass every argument in the calls."),
+    // Tables and indexes
+    (r"^cannot cluster on partial index", "Cluster on an index without a WHERE clause."),
+    (r"only foreign keys can be dropped", "Create the table again without the constraint and copy the rows over."),
+    (r"^DROP INDEX CONCURRENTLY does not support CASCADE", "Drop the dependent objects first, or use DROP INDEX without CONCURRENTLY."),
+    (r"^DROP INDEX CONCURRENTLY does not support dropping multiple objects", "Drop one index per statement."),
+    (r"COPY TO with FORMAT binary", "Use FORMAT text or FORMAT csv."),
+    (r"^cached plan must not change result type", "Prepare the statement again to get the table's current columns."),
+    // pgsqlite.strict_compatibility = error
+    (r"^COLLATE .* is not supported", "Use COLLATE NOCASE for case-insensitive comparisons."),
+    (r"\(pgsqlite\.strict_compatibility = error\)$", "SET pgsqlite.strict_compatibility = warn runs the statement anyway, with a warning."),
+];
+
+/// PostgreSQL statements SQLite rejects as syntax errors, by the keyword SQLite stops
+/// at, and the HINT; these are reported as feature_not_supported
+const UNSUPPORTED_STATEMENTS: &[(&str, &str)] = &[
+    ("LISTEN", "pgsqlite has no asynchronous notifications; poll a table for changes instead."),
+    ("UNLISTEN", "pgsqlite has no asynchronous notifications; poll a table for changes instead."),
+    ("NOTIFY", "pgsqlite has no asynchronous notifications; poll a table for changes instead."),
+    ("EXTENSION", "Extensions can't be loaded; common functions such as gen_random_uuid() are built in."),
+    ("MATERIALIZED", "Use CREATE TABLE ... AS SELECT, and create it again to refresh it."),
+    ("REFRESH", "Materialized views are not supported; drop and create the table built from the query again."),
+    ("SEQUENCE", "Use a SERIAL or GENERATED ... AS IDENTITY column."),
+];
+
+static FEATURE_PATTERNS: Lazy<Vec<(Regex, &'static str)>> = Lazy::new(|| {
+    UNSUPPORTED_FEATURES.iter().map(|(pattern, hint)| (Regex::new(pattern).unwrap(), *hint)).collect()
+});
+
+/// Matches SQLite's syntax error message, capturing the token it stopped at
+static SYNTAX_ERROR_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"near "(\w+)": syntax error"#).unwrap()
+});
+
+/// Turns dead ends into guidance: errors for features pgsqlite doesn't have get a
+/// HINT with the closest thing it supports, from the tables above
+pub struct FeatureHints;
+
+impl FeatureHints {
+    /// Add the HINT to an error for an unsupported feature, reporting statements SQLite
+    /// can't parse as feature_not_supported; other errors are left as they are
+    pub fn apply(response: &mut ErrorResponse) {
+        if response.hint.is_some() {
+            return;
+        }
+        if response.code == "0A000" {
+            // Messages of errors made from a PgSqliteError carry a context prefix
+            let message = response.message.rsplit("Feature not supported: ").next().unwrap_or(&response.message);
+            response.hint = FEATURE_PATTERNS.iter()
+                .find(|(pattern, _)| pattern.is_match(message))
+                .map(|(_, hint)| hint.to_string());
+            return;
+        }
+        let statement = SYNTAX_ERROR_REGEX.captures(&response.message).and_then(|caps| {
+            UNSUPPORTED_STATEMENTS.iter().find(|(keyword, _)| caps[1].eq_ignore_ascii_case(keyword))
+        });
+        if let Some((_, hint)) = statement {
+            response.code = "0A000".to_string();
+            response.hint = Some(hint.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(code: &str, message: &str) -> (String, Option<String>) {
+        let mut response = ErrorResponse::new("ERROR".to_string(), code.to_string(), message.to_string());
+        FeatureHints::apply(&mut response);
+        (response.code, response.hint)
+    }
+
+    #[test]
+    fn test_feature_hints() {
+        assert_eq!(
+            apply("0A000", "statement-level triggers are not supported, only FOR EACH ROW").1.as_deref(),
+            Some("Use a FOR EACH ROW trigger, which sees each changed row as NEW and OLD.")
+        );
+        assert_eq!(
+            apply("0A000", "Query execution failed: Feature not supported: COPY TO with FORMAT binary").1.as_deref(),
+            Some("Use FORMAT text or FORMAT csv.")
+        );
+        // The more specific entry comes first
+        assert_eq!(
+            apply("0A000", "COLLATE \"de_DE\" is not supported: SQLite only provides BINARY, NOCASE and RTRIM (pgsqlite.strict_compatibility = error)").1.as_deref(),
+            Some("Use COLLATE NOCASE for case-insensitive comparisons.")
+        );
+        assert_eq!(apply("0A000", "something else entirely").1, None);
+    }
+
+    #[test]
+    fn test_unsupported_statements() {
+        let (code, hint) = apply("42000", "Query execution failed: SQLite error: near \"LISTEN\": syntax error in LISTEN changes at offset 0");
+        assert_eq!(code, "0A000");
+        assert!(hint.unwrap().contains("poll a table"));
+        // Other syntax errors stay syntax errors
+        assert_eq!(apply("42000", "Query execution failed: SQLite error: near \"FORM\": syntax error in SELECT 1 FORM t").1, None);
+        assert_eq!(apply("42000", "SQLite error: near \"FORM\": syntax error").0, "42000");
+    }
+}
@@ -0,0 +1,450 @@
+use crate::functions::sql_functions::{TempFunction, register_sql_function, register_temp_function};
+use crate::metadata::OidAllocator;
+use crate::metadata::oid_allocator::FUNCTION;
+use crate::protocol::BackendMessage;
+use crate::query::TranslationPipeline;
+use crate::query::sql_utils::{fold_identifier, last_name_part};
+use crate::query::trigger_handler::{LANGUAGE_PATTERN, function_body, pg_error};
+use crate::session::{SessionState, SessionStorage};
+use crate::types::SchemaTypeMapper;
+use crate::PgSqliteError;
+use futures::SinkExt;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rusqlite::OptionalExtension;
+use std::sync::Arc;
+use tokio_util::codec::Framed;
+use tracing::debug;
+
+static CREATE_FUNCTION_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^\s*CREATE\s+(OR\s+REPLACE\s+)?(TEMP\s+|TEMPORARY\s+)?FUNCTION\s+((?:"(?:[^"]|"")+"|[\w$]+)(?:\.(?:"(?:[^"]|"")+"|[\w$]+))?)\s*\((.*?)\)\s*RETURNS\s+(.*)$"#).unwrap()
+});
+
+/// The result type, up to the first option
+static RETURN_TYPE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)^\s*(.+?)\s*(?:\b(?:LANGUAGE|IMMUTABLE|STABLE|VOLATILE|STRICT|CALLED|RETURNS|RETURN|SECURITY|EXTERNAL|PARALLEL|COST|ROWS|SUPPORT|SET|WINDOW|LEAKPROOF|NOT|TRANSFORM)\b.*)?$").unwrap()
+});
+
+/// A SQL-standard body, `RETURN expression`, which follows the options
+static RETURN_BODY_PATTERN: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)\bRETURN\b(.*)$").unwrap());
+
+static VOLATILITY_PATTERN: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\b(IMMUTABLE|STABLE|VOLATILE)\b").unwrap());
+
+static STRICT_PATTERN: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\bSTRICT\b|\bRETURNS\s+NULL\s+ON\s+NULL\s+INPUT\b").unwrap());
+
+/// First words of the multi-word type names, so `double precision` isn't read as an argument named `double`
+const MULTI_WORD_TYPES: &[&str] = &["double", "character", "char", "national", "bit", "time", "timestamp", "interval"];
+
+/// Handles `CREATE FUNCTION ... LANGUAGE sql`.
+///
+/// The body is translated once, with the arguments as numbered parameters, and
+/// stored in __pgsqlite_functions next to the statement as written. Every
+/// connection registers a scalar function that runs it; see
+/// `functions::sql_functions`. A temporary function is registered on the session's
+/// connection only, shadowing a function of the same name there until DROP
+/// FUNCTION or the end of the session. Trigger functions and `DROP FUNCTION`
+/// belong to `TriggerHandler`.
+pub struct FunctionHandler;
+
+/// A parsed `CREATE FUNCTION` of a SQL function
+#[derive(Debug, Clone, PartialEq)]
+pub struct SqlFunction {
+    pub name: String,
+    pub arguments: Vec<FunctionArgument>,
+    /// The result type as written
+    pub return_type: String,
+    /// The body as written, or the expression of a `RETURN` body
+    pub body: String,
+    /// The single statement of the body, with `$1`, `$2`, ... for the arguments
+    pub statement: String,
+    /// 'i', 's' or 'v', as in pg_proc.provolatile
+    pub volatility: char,
+    pub strict: bool,
+    pub or_replace: bool,
+    /// `CREATE TEMP FUNCTION`, visible to the creating session only
+    pub temporary: bool,
+    /// The statement as written
+    pub definition: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionArgument {
+    pub name: Option<String>,
+    pub type_name: String,
+}
+
+impl FunctionHandler {
+    /// Parse a `CREATE FUNCTION` that isn't a trigger function, which `TriggerHandler` claims first
+    pub fn parse_function(query: &str) -> Result<Option<SqlFunction>, PgSqliteError> {
+        let Some(caps) = CREATE_FUNCTION_PATTERN.captures(query) else {
+            return Ok(None);
+        };
+        let definition = query.trim().trim_end_matches(';').trim_end().to_string();
+        let arguments = parse_arguments(&caps[4])?;
+
+        let (body, statement, options) = match function_body(&caps[5]) {
+            Some((body, options)) => {
+                let statements = crate::query::split_statements(&body);
+                let [statement] = statements.as_slice() else {
+                    return Err(pg_error("0A000", "SQL functions must have exactly one statement in their body".to_string()));
+                };
+                let statement = statement.to_string();
+                (body, statement, options)
+            }
+            None => {
+                let body = RETURN_BODY_PATTERN.captures(&caps[5])
+                    .ok_or_else(|| pg_error("42P13", "no function body specified".to_string()))?;
+                let expression = body[1].trim().trim_end_matches(';').trim_end().to_string();
+                let statement = format!("SELECT {expression}");
+                (expression, statement, caps[5][..body.get(0).map_or(0, |m| m.start())].to_string())
+            }
+        };
+        let return_type = RETURN_TYPE_PATTERN.captures(&options)
+            .map(|return_type| return_type[1].to_string())
+            .ok_or_else(|| pg_error("42601", "syntax error at or near \"RETURNS\"".to_string()))?;
+        let upper_return_type = return_type.to_uppercase();
+        if upper_return_type.starts_with("SETOF") || upper_return_type.starts_with("TABLE") {
+            return Err(pg_error("0A000", "set-returning SQL functions are not supported".to_string()));
+        }
+
+        let language = LANGUAGE_PATTERN.captures(&options)
+            .map(|language| language[1].to_lowercase())
+            .ok_or_else(|| pg_error("42P13", "no language specified".to_string()))?;
+        match language.as_str() {
+            "sql" => {}
+            "plpgsql" => return Err(pg_error("0A000", "PL/pgSQL functions are only supported as trigger functions".to_string())),
+            other => return Err(pg_error("0A000", format!("functions in language \"{other}\" are not supported, only sql"))),
+        }
+
+        let statement = replace_argument_names(&statement, &arguments);
+
+        let volatility = VOLATILITY_PATTERN.captures(&options)
+            .map_or('v', |volatility| volatility[1].to_lowercase().chars().next().unwrap_or('v'));
+
+        // PostgreSQL spells a session's own function pg_temp.name
+        let temporary = caps.get(2).is_some()
+            || caps[3].split_once('.').is_some_and(|(schema, _)| fold_identifier(schema).eq_ignore_ascii_case("pg_temp"));
+
+        Ok(Some(SqlFunction {
+            name: fold_identifier(last_name_part(&caps[3])),
+            arguments,
+            return_type,
+            body,
+            statement,
+            volatility,
+            strict: STRICT_PATTERN.is_match(&options),
+            or_replace: caps.get(1).is_some(),
+            temporary,
+            definition,
+        }))
+    }
+
+    pub async fn handle_function<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        function: &SqlFunction,
+    ) -> Result<(), PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        db.require_local_data("CREATE FUNCTION")?;
+        let translated = TranslationPipeline::translate(db, session, &function.statement).await?.sql;
+        let sqlite_body = numbered_parameters(&translated);
+        let return_type_oid = if function.return_type.eq_ignore_ascii_case("void") {
+            2278
+        } else {
+            SchemaTypeMapper::pg_type_string_to_oid(&function.return_type)
+        };
+        let arg_types: Vec<String> = function.arguments.iter()
+            .map(|argument| SchemaTypeMapper::pg_type_string_to_oid(&argument.type_name).to_string())
+            .collect();
+        let arg_types = arg_types.join(" ");
+        let arg_names = function.arguments.iter().any(|argument| argument.name.is_some()).then(|| {
+            let names: Vec<&str> = function.arguments.iter().map(|argument| argument.name.as_deref().unwrap_or("\"\"")).collect();
+            format!("{{{}}}", names.join(","))
+        });
+        let nargs = function.arguments.len() as i32;
+
+        if function.temporary {
+            return Self::create_temp_function(framed, db, session, function, sqlite_body).await;
+        }
+
+        db.with_session_connection(&session.id, |conn| {
+            let existing: Option<(i64, String, String)> = conn.query_row(
+                "SELECT oid, return_type, arg_types FROM __pgsqlite_functions WHERE name = ?1",
+                [&function.name],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            ).optional()?;
+            if let Some((_, existing_return_type, existing_arg_types)) = &existing {
+                if *existing_arg_types != arg_types {
+                    return Ok(Err(pg_error("0A000", format!("function \"{}\" already exists, overloading functions is not supported", function.name))));
+                }
+                if !function.or_replace {
+                    return Ok(Err(pg_error("42723", format!("function \"{}\" already exists with same argument types", function.name))));
+                }
+                if !existing_return_type.eq_ignore_ascii_case(&function.return_type) {
+                    return Ok(Err(pg_error("42P13", "cannot change return type of existing function".to_string())));
+                }
+            }
+            // Like PostgreSQL, refuse bodies referring to tables or functions that don't exist
+            conn.prepare(&sqlite_body)?;
+
+            let oid = match existing {
+                Some((oid, ..)) => oid,
+                None => {
+                    let types: Vec<&str> = function.arguments.iter().map(|argument| argument.type_name.as_str()).collect();
+                    OidAllocator::assign(conn, FUNCTION, &format!("{}({})", function.name, types.join(",")))?
+                }
+            };
+            conn.execute(
+                "INSERT OR REPLACE INTO __pgsqlite_functions
+                     (oid, name, language, return_type, body, definition, arg_types, arg_names, nargs, return_type_oid, volatility, is_strict, sqlite_body)
+                 VALUES (?1, ?2, 'sql', ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                rusqlite::params![
+                    oid, function.name, function.return_type, function.body, function.definition, arg_types, arg_names,
+                    nargs, return_type_oid, function.volatility.to_string(), function.strict, sqlite_body,
+                ],
+            )?;
+            Ok(Ok(()))
+        }).await??;
+
+        db.with_all_connections(&|conn| register_sql_function(conn, &function.name, nargs, function.volatility == 'i'))?;
+        debug!("Created SQL function {} running {}", function.name, sqlite_body);
+
+        framed.send(BackendMessage::CommandComplete { tag: "CREATE FUNCTION".to_string() }).await
+            .map_err(PgSqliteError::Io)
+    }
+}
+
+impl FunctionHandler {
+    async fn create_temp_function<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        function: &SqlFunction,
+        sqlite_body: String,
+    ) -> Result<(), PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        let temp = TempFunction {
+            nargs: function.arguments.len() as i32,
+            immutable: function.volatility == 'i',
+            strict: function.strict,
+            body: sqlite_body,
+        };
+        if let Some(existing) = session.temp_function(&function.name) {
+            if existing.nargs != temp.nargs {
+                return Err(pg_error("0A000", format!("function \"{}\" already exists, overloading functions is not supported", function.name)));
+            }
+            if !function.or_replace {
+                return Err(pg_error("42723", format!("function \"{}\" already exists with same argument types", function.name)));
+            }
+        }
+
+        db.with_session_connection(&session.id, |conn| {
+            conn.prepare(&temp.body)?;
+            register_temp_function(conn, &function.name, &temp)
+        }).await?;
+        debug!("Created temporary function {} for session {} running {}", function.name, session.id, temp.body);
+        session.set_temp_function(function.name.clone(), temp);
+
+        framed.send(BackendMessage::CommandComplete { tag: "CREATE FUNCTION".to_string() }).await
+            .map_err(PgSqliteError::Io)
+    }
+}
+
+/// Calls whose arguments contain no nested parentheses
+static CALL_PATTERN: Lazy<Regex> = Lazy::new(|| Regex::new(r"(\w+)\s*\(([^()]*)\)").unwrap());
+
+impl FunctionHandler {
+    /// The OID of the argument type of a SQL function the parameter `$index` is passed to, e.g. add_tax($1, 0.2)
+    pub fn param_argument_type(query: &str, index: usize, conn: &rusqlite::Connection) -> Option<i32> {
+        let param = format!("${index}");
+        CALL_PATTERN.captures_iter(query).find_map(|call| {
+            let position = call[2].split(',').position(|argument| argument.trim() == param)?;
+            let arg_types: String = conn.query_row(
+                "SELECT arg_types FROM __pgsqlite_functions WHERE name = ?1 AND language = 'sql'",
+                [call[1].to_lowercase()],
+                |row| row.get(0),
+            ).ok()?;
+            arg_types.split_whitespace().nth(position)?.parse().ok()
+        })
+    }
+}
+
+/// The arguments between the parentheses of `CREATE FUNCTION`
+fn parse_arguments(arguments: &str) -> Result<Vec<FunctionArgument>, PgSqliteError> {
+    let mut parsed = Vec::new();
+    for argument in split_top_level(arguments) {
+        let mut words: Vec<&str> = argument.split_whitespace().collect();
+        if words.is_empty() {
+            continue;
+        }
+        let mode = words[0].to_uppercase();
+        if matches!(mode.as_str(), "OUT" | "INOUT" | "VARIADIC") {
+            return Err(pg_error("0A000", format!("{mode} arguments are not supported in SQL functions")));
+        }
+        if mode == "IN" {
+            words.remove(0);
+        }
+        if words.iter().any(|word| word.eq_ignore_ascii_case("DEFAULT")) || argument.contains('=') {
+            return Err(pg_error("0A000", "argument defaults are not supported in SQL functions".to_string()));
+        }
+        let named = words.len() > 1 && !MULTI_WORD_TYPES.contains(&words[0].to_lowercase().as_str());
+        parsed.push(if named {
+            FunctionArgument { name: Some(fold_identifier(words[0])), type_name: words[1..].join(" ") }
+        } else {
+            FunctionArgument { name: None, type_name: words.join(" ") }
+        });
+    }
+    Ok(parsed)
+}
+
+/// Split at the commas outside parentheses, as in `a numeric(10,2), b text`
+fn split_top_level(list: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in list.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&list[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&list[start..]);
+    parts
+}
+
+/// Replace references to named arguments with `$1`, `$2`, ..., leaving string
+/// literals, quoted identifiers, qualified names and function calls alone
+fn replace_argument_names(body: &str, arguments: &[FunctionArgument]) -> String {
+    let position = |word: &str| arguments.iter()
+        .position(|argument| argument.name.as_deref().is_some_and(|name| name.eq_ignore_ascii_case(word)));
+    map_outside_quotes(body, |word, before, after| {
+        let qualified = before.ends_with('.') || after.starts_with('.');
+        // Names after these keywords are tables, columns being assigned or aliases
+        let previous = before.trim_end().rsplit(|c: char| !(c.is_alphanumeric() || c == '_')).next().unwrap_or_default();
+        let names_relation = ["FROM", "JOIN", "INTO", "UPDATE", "AS"].iter().any(|keyword| previous.eq_ignore_ascii_case(keyword));
+        match position(word) {
+            Some(i) if !qualified && !names_relation && !after.trim_start().starts_with('(') => format!("${}", i + 1),
+            _ => word.to_string(),
+        }
+    })
+}
+
+/// Turn `$1`, `$2`, ... into SQLite's numbered parameters `?1`, `?2`, ...
+fn numbered_parameters(sql: &str) -> String {
+    let mut result = String::with_capacity(sql.len());
+    let mut quote = None;
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match quote {
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if c == '$' && chars.peek().is_some_and(char::is_ascii_digit) => {
+                result.push('?');
+                continue;
+            }
+            None => {}
+        }
+        result.push(c);
+    }
+    result
+}
+
+/// Rebuild `sql` with each word outside quotes replaced by `replace(word, text before, text after)`
+fn map_outside_quotes(sql: &str, replace: impl Fn(&str, &str, &str) -> String) -> String {
+    let mut result = String::with_capacity(sql.len());
+    let mut chars = sql.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == '\'' || c == '"' {
+            result.push(c);
+            for (_, next) in chars.by_ref() {
+                result.push(next);
+                if next == c {
+                    break;
+                }
+            }
+        } else if c.is_alphanumeric() || c == '_' {
+            let mut end = i + c.len_utf8();
+            while let Some(&(j, next)) = chars.peek() {
+                if !(next.is_alphanumeric() || next == '_') {
+                    break;
+                }
+                end = j + next.len_utf8();
+                chars.next();
+            }
+            result.push_str(&replace(&sql[i..end], &sql[..i], &sql[end..]));
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sql_function() {
+        let parsed = FunctionHandler::parse_function(
+            "CREATE OR REPLACE FUNCTION public.add_tax(price numeric(10,2), rate numeric) RETURNS numeric AS $$ SELECT price * (1 + rate) $$ LANGUAGE sql IMMUTABLE STRICT;"
+        ).unwrap().unwrap();
+        assert_eq!(parsed.name, "add_tax");
+        assert_eq!(parsed.arguments, [
+            FunctionArgument { name: Some("price".to_string()), type_name: "numeric(10,2)".to_string() },
+            FunctionArgument { name: Some("rate".to_string()), type_name: "numeric".to_string() },
+        ]);
+        assert_eq!(parsed.return_type, "numeric");
+        assert_eq!(parsed.body, " SELECT price * (1 + rate) ");
+        assert_eq!(parsed.statement, "SELECT $1 * (1 + $2)");
+        assert_eq!((parsed.volatility, parsed.strict, parsed.or_replace), ('i', true, true));
+
+        let parsed = FunctionHandler::parse_function(
+            "create function full_name(text, double precision) returns text language sql return $1 || ' ' || $2::text"
+        ).unwrap().unwrap();
+        assert_eq!(parsed.arguments.iter().map(|argument| argument.type_name.as_str()).collect::<Vec<_>>(), ["text", "double precision"]);
+        assert_eq!(parsed.body, "$1 || ' ' || $2::text");
+        assert_eq!(parsed.statement, "SELECT $1 || ' ' || $2::text");
+        assert_eq!(parsed.volatility, 'v');
+
+        assert!(FunctionHandler::parse_function("CREATE FUNCTION f() RETURNS SETOF int AS 'SELECT 1' LANGUAGE sql").is_err());
+        assert!(FunctionHandler::parse_function("CREATE FUNCTION f() RETURNS int AS 'SELECT 1; SELECT 2' LANGUAGE sql").is_err());
+        assert!(FunctionHandler::parse_function("CREATE FUNCTION f() RETURNS int AS 'BEGIN RETURN 1; END' LANGUAGE plpgsql").is_err());
+        assert_eq!(FunctionHandler::parse_function("CREATE TABLE f (id int)").unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_temp_function() {
+        let parsed = FunctionHandler::parse_function(
+            "CREATE OR REPLACE TEMP FUNCTION now() RETURNS timestamptz AS $$ SELECT '2024-01-01 00:00:00+00' $$ LANGUAGE sql"
+        ).unwrap().unwrap();
+        assert_eq!((parsed.name.as_str(), parsed.temporary, parsed.or_replace), ("now", true, true));
+
+        let parsed = FunctionHandler::parse_function("CREATE FUNCTION pg_temp.fetch_rate(code text) RETURNS numeric LANGUAGE sql RETURN 1.5").unwrap().unwrap();
+        assert_eq!((parsed.name.as_str(), parsed.temporary), ("fetch_rate", true));
+        assert_eq!(parsed.statement, "SELECT 1.5");
+
+        let parsed = FunctionHandler::parse_function("CREATE FUNCTION public.f() RETURNS int LANGUAGE sql RETURN 1").unwrap().unwrap();
+        assert!(!parsed.temporary);
+    }
+
+    #[test]
+    fn test_argument_references() {
+        let arguments = parse_arguments("name text, t text").unwrap();
+        assert_eq!(
+            replace_argument_names("SELECT upper(name) || 'name' || t.name || \"name\" FROM t WHERE t.id = length(name)", &arguments),
+            "SELECT upper($1) || 'name' || t.name || \"name\" FROM t WHERE t.id = length($1)",
+        );
+        assert_eq!(numbered_parameters("SELECT $1 + $12, '$2'"), "SELECT ?1 + ?12, '$2'");
+    }
+}
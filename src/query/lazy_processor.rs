@@ -117,9 +117,9 @@ impl<'a> LazyQueryProcessor<'a> {
             return needs_decimal;
         }
         
-        // For INSERT queries, check if table has decimal columns
-        if matches!(QueryTypeDetector::detect_query_type(self.original_query), QueryType::Insert)
-            && let Some(table_name) = extract_insert_table_name(self.original_query) {
+        // For writes, check if their table has decimal columns
+        if matches!(QueryTypeDetector::detect_query_type(self.original_query), QueryType::Insert | QueryType::Update | QueryType::Delete)
+            && let Some(table_name) = crate::query::simple_query_detector::extract_simple_table_name(self.original_query) {
                 return schema_cache.has_decimal_columns(&table_name);
             }
         
@@ -148,15 +148,16 @@ impl<'a> LazyQueryProcessor<'a> {
            !self.needs_array_translation && !self.needs_delete_using_translation &&
            !self.needs_batch_update_translation && !self.needs_datetime_translation &&
            !self.needs_pg_table_is_visible_translation {
-            // Check if this is an INSERT that might need decimal rewrite
-            if matches!(QueryTypeDetector::detect_query_type(self.original_query), QueryType::Insert) {
-                if let Some(table_name) = extract_insert_table_name(self.original_query)
-                    && !_schema_cache.has_decimal_columns(&table_name) {
+            // Check if this is a write that might need decimal rewrite
+            match QueryTypeDetector::detect_query_type(self.original_query) {
+                QueryType::Select => {}
+                QueryType::Insert | QueryType::Update | QueryType::Delete => {
+                    if !writes_decimal_table(self.original_query, conn, _schema_cache) {
                         return Ok(self.original_query);
                     }
-            } else if !matches!(QueryTypeDetector::detect_query_type(self.original_query), QueryType::Select) {
-                // Not a SELECT or INSERT that needs decimal handling
-                return Ok(self.original_query);
+                }
+                // Not a statement that needs decimal handling
+                _ => return Ok(self.original_query),
             }
         }
         
@@ -293,35 +294,19 @@ impl<'a> LazyQueryProcessor<'a> {
         let query_type = QueryTypeDetector::detect_query_type(&current_query);
         
         // For performance, only rewrite when necessary
-        if matches!(query_type, QueryType::Insert | QueryType::Select) {
-            if let Some(table_name) = extract_insert_table_name(&current_query) {
-                if _schema_cache.has_decimal_columns(&table_name) {
-                    tracing::debug!("Before decimal rewriting: {}", current_query);
-                    match rewrite_query_for_decimal(&current_query, conn) {
-                        Ok(rewritten) => {
-                            if rewritten != current_query {
-                                tracing::debug!("After decimal rewriting: {}", rewritten);
-                                current_query = Cow::Owned(rewritten);
-                            }
-                        }
-                        Err(e) => {
-                            tracing::warn!("Failed to rewrite query for decimal: {}", e);
-                            // Continue with original query
-                        }
-                    }
-                }
-            } else if matches!(query_type, QueryType::Select) {
-                // For SELECT queries, be conservative and always try decimal rewriting
-                tracing::debug!("Before decimal rewriting (SELECT): {}", current_query);
+        if matches!(query_type, QueryType::Insert | QueryType::Select | QueryType::Update | QueryType::Delete) {
+            // SELECTs are always tried; writes only when their table has NUMERIC columns
+            if matches!(query_type, QueryType::Select) || writes_decimal_table(&current_query, conn, _schema_cache) {
+                tracing::debug!("Before decimal rewriting: {}", current_query);
                 match rewrite_query_for_decimal(&current_query, conn) {
                     Ok(rewritten) => {
                         if rewritten != current_query {
-                            tracing::debug!("After decimal rewriting (SELECT): {}", rewritten);
+                            tracing::debug!("After decimal rewriting: {}", rewritten);
                             current_query = Cow::Owned(rewritten);
                         }
                     }
                     Err(e) => {
-                        tracing::warn!("Failed to rewrite SELECT query for decimal: {}", e);
+                        tracing::warn!("Failed to rewrite query for decimal: {}", e);
                         // Continue with original query
                     }
                 }
@@ -344,9 +329,11 @@ impl<'a> LazyQueryProcessor<'a> {
     }
 }
 
-// Helper functions (these should be imported from the appropriate modules)
-fn extract_insert_table_name(query: &str) -> Option<String> {
-    crate::session::db_handler::extract_insert_table_name(query)
+/// Whether the table an INSERT, UPDATE or DELETE writes has NUMERIC columns, whose
+/// values the decimal rewriter keeps as canonical decimal text
+fn writes_decimal_table(query: &str, conn: &Connection, schema_cache: &SchemaCache) -> bool {
+    crate::query::simple_query_detector::extract_simple_table_name(query)
+        .is_some_and(|table| schema_cache.has_decimal_columns_or_load(conn, &table))
 }
 
 fn rewrite_query_for_decimal(query: &str, conn: &Connection) -> Result<String, rusqlite::Error> {
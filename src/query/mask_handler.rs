@@ -0,0 +1,776 @@
+use crate::protocol::{BackendMessage, FieldDescription};
+use crate::query::audit_handler::{sqlite_name, table_name};
+use crate::query::soft_delete_handler::{parse_expr, parse_query, TableRewriter};
+use crate::query::sql_utils::{quote_identifier, quote_literal};
+use crate::query::trigger_handler::pg_error;
+use crate::session::{SessionState, SessionStorage};
+use crate::types::{PgType, SchemaTypeMapper};
+use crate::PgSqliteError;
+use futures::SinkExt;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use regex::Regex;
+use rusqlite::{Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+use sqlparser::ast::{
+    visit_expressions_mut, visit_relations, visit_statements, Assignment, Expr, FromTable, Ident, ObjectName, OnConflict,
+    OnConflictAction, OnInsert, SelectItem, SelectItemQualifiedWildcardKind, SetExpr, Statement, TableFactor, TableObject,
+    TableWithJoins, UpdateTableFromKind,
+};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use tokio_util::codec::Framed;
+use tracing::debug;
+
+static MASK_CALL_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)^\s*SELECT\s+(?:\*\s+FROM\s+)?pgsqlite\.(set|drop)_mask\s*\(\s*'((?:[^']|'')*)'\s*,\s*'((?:[^']|'')*)'(?:\s*,\s*'((?:[^']|'')*)')?(?:\s*,\s*'((?:[^']|'')*)')?\s*\)\s*;?\s*$").unwrap()
+});
+
+/// What a partial mask hides without a pattern: all but the last four characters
+const DEFAULT_PARTIAL_PATTERN: &str = "(?s)^(.*?).{0,4}$";
+
+/// The table the masks are listed in
+const MASKS_TABLE: &str = "__pgsqlite_column_masks";
+
+/// What a full mask shows instead of the value, whatever its length
+const FULL_MASK: &str = "********";
+
+/// Compiled partial mask patterns, shared by the connections' pgsqlite_mask()
+static PATTERNS: Lazy<Mutex<HashMap<String, Regex>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// How a masked column's values are shown
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MaskMethod {
+    /// A fixed string, hiding the length too
+    Full,
+    /// The characters a regular expression matches replaced with `*`
+    Partial,
+    /// The hex SHA-256 digest, so equal values still join and group together
+    Hash,
+}
+
+impl MaskMethod {
+    pub fn parse(method: &str) -> Option<Self> {
+        match method.trim().to_lowercase().as_str() {
+            "full" => Some(Self::Full),
+            "partial" => Some(Self::Partial),
+            "hash" => Some(Self::Hash),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Full => "full",
+            Self::Partial => "partial",
+            Self::Hash => "hash",
+        }
+    }
+}
+
+/// A `pgsqlite.set_mask()` or `pgsqlite.drop_mask()` call
+#[derive(Debug, Clone, PartialEq)]
+pub enum MaskCall {
+    Set { table: String, column: String, method: String, pattern: Option<String> },
+    Drop { table: String, column: String },
+}
+
+/// The masked columns of the database as of a catalog generation, see CatalogCache
+pub struct ColumnMasks {
+    generation: u64,
+    /// Lowercase SQLite table names to the subquery that stands in for the table
+    subqueries: HashMap<String, String>,
+    /// Lowercase SQLite table names to their masked columns' lowercase names and the
+    /// arguments pgsqlite_mask() takes after the value
+    arguments: HashMap<String, HashMap<String, String>>,
+}
+
+impl ColumnMasks {
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// The masked table a name refers to, by lowercase name, whatever it's qualified with
+    fn table(&self, name: &ObjectName) -> Option<&str> {
+        let table = name.0.last()?.as_ident()?.value.to_lowercase();
+        self.arguments.get_key_value(&table).map(|(table, _)| table.as_str())
+    }
+
+    /// The masked table an INSERT writes
+    fn target(&self, name: &ObjectName, alias: Option<&Ident>) -> Option<Target<'_>> {
+        let [part] = name.0.as_slice() else {
+            return None;
+        };
+        let ident = part.as_ident()?;
+        let (table, arguments) = self.arguments.get_key_value(&ident.value.to_lowercase())?;
+        Some(Target {
+            table,
+            qualifier: alias.unwrap_or(ident).clone(),
+            arguments,
+            subquery: &self.subqueries[table],
+        })
+    }
+
+    /// The masked table an UPDATE or DELETE writes
+    fn table_target(&self, table: &TableWithJoins) -> Option<Target<'_>> {
+        match &table.relation {
+            TableFactor::Table { name, alias, args: None, .. } if table.joins.is_empty() => {
+                self.target(name, alias.as_ref().map(|alias| &alias.name))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A masked table an INSERT, UPDATE or DELETE writes, whose columns its SET clauses and
+/// RETURNING read by name rather than through the table's subquery
+struct Target<'m> {
+    table: &'m str,
+    /// The name the statement qualifies the table's columns with
+    qualifier: Ident,
+    arguments: &'m HashMap<String, String>,
+    subquery: &'m str,
+}
+
+impl Target<'_> {
+    /// Pass the expression's references to masked columns through pgsqlite_mask(),
+    /// returning the columns. Those in subqueries count too, as a column the subquery's
+    /// tables don't have is the target's.
+    fn mask(&self, expr: &mut Expr) -> Result<Vec<String>, PgSqliteError> {
+        let mut masked = Vec::new();
+        let flow = visit_expressions_mut(expr, |expr| {
+            let column = match &*expr {
+                Expr::Identifier(column) => column,
+                Expr::CompoundIdentifier(idents) => match idents.as_slice() {
+                    [qualifier, column] if qualifier.value.eq_ignore_ascii_case(&self.qualifier.value) => column,
+                    _ => return ControlFlow::Continue(()),
+                },
+                _ => return ControlFlow::Continue(()),
+            };
+            let column = column.value.to_lowercase();
+            if let Some(arguments) = self.arguments.get(&column) {
+                let Some(masked_expr) = parse_expr(&mask_expression(&expr.to_string(), arguments)) else {
+                    return ControlFlow::Break(());
+                };
+                *expr = masked_expr;
+                masked.push(column);
+            }
+            ControlFlow::Continue(())
+        });
+        if flow.is_break() {
+            return Err(unmaskable(self.table));
+        }
+        Ok(masked)
+    }
+
+    /// Refuse a SET clause that reads a masked column, which would copy its value
+    /// elsewhere or write the masked value back
+    fn check_assignment(&self, assignment: &Assignment) -> Result<(), PgSqliteError> {
+        match self.mask(&mut assignment.value.clone())?.first() {
+            Some(column) => Err(pg_error(
+                "42501",
+                format!("permission denied: column \"{column}\" of relation \"{}\" is masked and can't be assigned from", self.table),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Mask the masked columns RETURNING reads, keeping the names of the columns it
+    /// returns, and return whether it did
+    fn mask_returning(&self, returning: &mut Option<Vec<SelectItem>>) -> Result<bool, PgSqliteError> {
+        let Some(items) = returning else {
+            return Ok(false);
+        };
+        let mut changed = false;
+        for item in std::mem::take(items) {
+            match item {
+                SelectItem::Wildcard(_) => {
+                    items.extend(self.columns()?);
+                    changed = true;
+                }
+                SelectItem::QualifiedWildcard(SelectItemQualifiedWildcardKind::ObjectName(name), _)
+                    if matches!(name.0.as_slice(), [part] if part.as_ident().is_some_and(|ident| ident.value.eq_ignore_ascii_case(&self.qualifier.value))) =>
+                {
+                    items.extend(self.columns()?);
+                    changed = true;
+                }
+                SelectItem::UnnamedExpr(mut expr) => {
+                    let name = match &expr {
+                        Expr::Identifier(ident) => Some(ident.clone()),
+                        Expr::CompoundIdentifier(idents) => idents.last().cloned(),
+                        _ => None,
+                    };
+                    if self.mask(&mut expr)?.is_empty() {
+                        items.push(SelectItem::UnnamedExpr(expr));
+                        continue;
+                    }
+                    changed = true;
+                    items.push(match name {
+                        Some(alias) => SelectItem::ExprWithAlias { expr, alias },
+                        None => SelectItem::UnnamedExpr(expr),
+                    });
+                }
+                SelectItem::ExprWithAlias { mut expr, alias } => {
+                    changed |= !self.mask(&mut expr)?.is_empty();
+                    items.push(SelectItem::ExprWithAlias { expr, alias });
+                }
+                item => items.push(item),
+            }
+        }
+        Ok(changed)
+    }
+
+    /// The table's columns as its subquery reads them, for `RETURNING *`
+    fn columns(&self) -> Result<Vec<SelectItem>, PgSqliteError> {
+        match parse_query(self.subquery).map(|query| *query.body) {
+            Some(SetExpr::Select(select)) => Ok(select.projection),
+            _ => Err(unmaskable(self.table)),
+        }
+    }
+}
+
+/// Handles `SELECT pgsqlite.set_mask('<table>', '<column>', '<method>' [, '<pattern>'])` and
+/// `pgsqlite.drop_mask('<table>', '<column>')`, which only --admin-users may call, and
+/// masks the columns they set for all other roles.
+///
+/// Statements of those roles read a masked table through a subquery that passes its
+/// masked columns through pgsqlite_mask(), so the values are masked wherever the
+/// statement uses them: in results, expressions, filters, subqueries, and tables or views
+/// it creates from a query. COPY TO masks the same way, and so does RETURNING. Statements
+/// that would read a masked table some other way are refused rather than run unmasked.
+/// The masks are listed in __pgsqlite_column_masks, which only --admin-users may change,
+/// and shown by the pg_column_masks view.
+pub struct MaskHandler;
+
+impl MaskHandler {
+    /// Whether a query mentions one of the `*_mask` functions anywhere
+    pub fn might_be_mask_call(query: &str) -> bool {
+        query.as_bytes().windows(5).any(|w| w.eq_ignore_ascii_case(b"_mask"))
+    }
+
+    /// A standalone `pgsqlite.set_mask()` or `pgsqlite.drop_mask()` call
+    pub fn parse_mask_call(query: &str) -> Option<MaskCall> {
+        if !Self::might_be_mask_call(query) {
+            return None;
+        }
+        let caps = MASK_CALL_PATTERN.captures(query)?;
+        let argument = |i: usize| caps.get(i).map(|m| m.as_str().replace("''", "'"));
+        let table = argument(2)?.trim().to_string();
+        let column = argument(3)?.trim().to_string();
+        if caps[1].eq_ignore_ascii_case("drop") {
+            return argument(4).is_none().then_some(MaskCall::Drop { table, column });
+        }
+        Some(MaskCall::Set { table, column, method: argument(4)?, pattern: argument(5) })
+    }
+
+    /// The row description of the result, also reported by Parse in the extended protocol
+    pub fn field_descriptions(call: &MaskCall) -> Vec<FieldDescription> {
+        vec![FieldDescription {
+            name: match call {
+                MaskCall::Set { .. } => "set_mask",
+                MaskCall::Drop { .. } => "drop_mask",
+            }.to_string(),
+            table_oid: 0,
+            column_id: 1,
+            type_oid: PgType::Bool.to_oid(),
+            type_size: 1,
+            type_modifier: -1,
+            format: 0,
+        }]
+    }
+
+    /// Returns one row telling whether the call changed the column's mask, in binary
+    /// when the portal asked for it
+    pub async fn handle_mask_call<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        call: &MaskCall,
+        skip_row_description: bool,
+        binary: bool,
+    ) -> Result<(), PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        db.require_local_data("column masks")?;
+        // Roles that read masked values mustn't be able to lift the masks
+        if !crate::config::current().is_admin_user(&session.user) {
+            return Err(pg_error("42501", "permission denied: only --admin-users may set or drop column masks".to_string()));
+        }
+        let changed = match call.clone() {
+            MaskCall::Set { table, column, method, pattern } => {
+                let Some(method) = MaskMethod::parse(&method) else {
+                    return Err(pg_error("22023", format!("unknown mask method \"{method}\", expected full, partial or hash")));
+                };
+                if let Some(pattern) = &pattern {
+                    if method != MaskMethod::Partial {
+                        return Err(pg_error("22023", format!("{} masks take no pattern", method.as_str())));
+                    }
+                    if let Err(e) = Regex::new(pattern) {
+                        return Err(pg_error("2201B", format!("invalid regular expression: {e}")));
+                    }
+                }
+                db.with_session_connection(&session.id, move |conn| {
+                    Ok(Self::set_mask(conn, &table, &column, method, pattern.as_deref()))
+                }).await??
+            }
+            MaskCall::Drop { table, column } => db.with_session_connection(&session.id, move |conn| {
+                Ok(Self::drop_mask(conn, &table, &column))
+            }).await??,
+        };
+        if changed {
+            // The statement is a SELECT, so the catalog cache doesn't see it change how tables are read
+            crate::cache::CatalogCache::bump_generation();
+        }
+        debug!("Mask call {:?} changed: {}", call, changed);
+
+        if !skip_row_description {
+            framed.send(BackendMessage::RowDescription(Self::field_descriptions(call))).await
+                .map_err(PgSqliteError::Io)?;
+        }
+        let value = match (binary, changed) {
+            (true, changed) => vec![u8::from(changed)],
+            (false, true) => b"t".to_vec(),
+            (false, false) => b"f".to_vec(),
+        };
+        framed.send(BackendMessage::DataRow(vec![Some(value)])).await
+            .map_err(PgSqliteError::Io)?;
+        framed.send(BackendMessage::CommandComplete {
+            tag: "SELECT 1".to_string(),
+        }).await.map_err(PgSqliteError::Io)
+    }
+
+    /// Record the column's mask, returning whether it differs from the one it had
+    fn set_mask(conn: &Connection, name: &str, column: &str, method: MaskMethod, pattern: Option<&str>) -> Result<bool, PgSqliteError> {
+        let (table, column) = Self::resolve_column(conn, name, column)?;
+        let pg_type: Option<String> = conn.query_row(
+            "SELECT pg_type FROM __pgsqlite_schema WHERE table_name = ?1 AND column_name = ?2",
+            [&table, &column],
+            |row| row.get(0),
+        ).optional()?;
+        let oid = pg_type.as_deref().map(|t| SchemaTypeMapper::pg_type_string_to_oid(&t.trim().to_uppercase()));
+        if !matches!(oid.and_then(PgType::from_oid), Some(PgType::Text | PgType::Varchar | PgType::Char)) {
+            return Err(pg_error(
+                "42804",
+                format!("column \"{column}\" of relation \"{table}\" must be of type text, varchar or char to be masked"),
+            ));
+        }
+        let changed = conn.execute(
+            "INSERT INTO __pgsqlite_column_masks (table_name, column_name, method, pattern) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT (table_name, column_name) DO UPDATE SET method = excluded.method, pattern = excluded.pattern
+             WHERE method IS NOT excluded.method OR pattern IS NOT excluded.pattern",
+            rusqlite::params![table, column, method.as_str(), pattern],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Remove the column's mask, returning whether it had one
+    fn drop_mask(conn: &Connection, name: &str, column: &str) -> Result<bool, PgSqliteError> {
+        let (table, column) = Self::resolve_column(conn, name, column)?;
+        let removed = conn.execute(
+            "DELETE FROM __pgsqlite_column_masks WHERE table_name = ?1 AND column_name = ?2",
+            [&table, &column],
+        )?;
+        Ok(removed > 0)
+    }
+
+    /// The table's and column's names as SQLite has them
+    fn resolve_column(conn: &Connection, name: &str, column: &str) -> Result<(String, String), PgSqliteError> {
+        let Some(table) = table_name(conn, &sqlite_name(name))? else {
+            return Err(pg_error("42P01", format!("relation \"{name}\" does not exist")));
+        };
+        let existing: Option<String> = conn.query_row(
+            "SELECT name FROM pragma_table_info(?1) WHERE name = ?2 COLLATE NOCASE",
+            [&table, column],
+            |row| row.get(0),
+        ).optional()?;
+        match existing {
+            Some(existing) => Ok((table, existing)),
+            None => Err(pg_error("42703", format!("column \"{column}\" of relation \"{table}\" does not exist"))),
+        }
+    }
+
+    /// Forget the masks of dropped tables
+    pub fn prune_column_masks(conn: &Connection) -> rusqlite::Result<()> {
+        if !has_column_masks(conn)? {
+            return Ok(());
+        }
+        conn.execute(
+            "DELETE FROM __pgsqlite_column_masks
+             WHERE table_name NOT IN (SELECT name FROM sqlite_master WHERE type = 'table')",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Rewrite a statement of a role other than --admin-users so it reads masked tables
+    /// through their masked columns. Statements that would read masked values some other
+    /// way or change the masks are refused, and so are those that don't parse.
+    pub async fn rewrite<'q>(
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        query: &'q str,
+    ) -> Result<Cow<'q, str>, PgSqliteError> {
+        if crate::config::current().is_admin_user(&session.user) {
+            return Ok(Cow::Borrowed(query));
+        }
+        let masks = Self::masks(db, session).await?;
+        Ok(match Self::rewrite_statements(query, &masks)? {
+            Some(rewritten) => {
+                debug!("Mask rewrite: {} -> {}", query, rewritten);
+                Cow::Owned(rewritten)
+            }
+            None => Cow::Borrowed(query),
+        })
+    }
+
+    /// The expressions COPY TO reads the table's masked columns with, by lowercase column
+    /// name; empty for --admin-users and tables without masks
+    pub async fn column_expressions(
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        table: &str,
+    ) -> Result<HashMap<String, String>, PgSqliteError> {
+        if crate::config::current().is_admin_user(&session.user) {
+            return Ok(HashMap::new());
+        }
+        let masks = Self::masks(db, session).await?;
+        Ok(masks.arguments.get(&table.to_lowercase())
+            .map(|columns| columns.iter()
+                .map(|(column, arguments)| (column.clone(), mask_expression(&quote_identifier(column), arguments)))
+                .collect())
+            .unwrap_or_default())
+    }
+
+    /// The column masks, reloaded after catalog changes
+    async fn masks(db: &Arc<dyn SessionStorage>, session: &Arc<SessionState>) -> Result<Arc<ColumnMasks>, PgSqliteError> {
+        let generation = crate::cache::CatalogCache::generation();
+        if let Some(masks) = session.column_masks(generation) {
+            return Ok(masks);
+        }
+        let masks = Arc::new(db.with_session_connection(&session.id, |conn| {
+            let mut subqueries = HashMap::new();
+            let mut arguments = HashMap::new();
+            if has_column_masks(conn)? {
+                let mut stmt = conn.prepare(
+                    "SELECT m.table_name, c.name, m.method, m.pattern
+                     FROM (SELECT DISTINCT table_name FROM __pgsqlite_column_masks) t
+                     JOIN __pgsqlite_column_masks m ON m.table_name = t.table_name
+                     JOIN pragma_table_info(t.table_name) c ON c.name = m.column_name COLLATE NOCASE",
+                )?;
+                let masked = stmt.query_map([], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, Option<String>>(3)?))
+                })?.collect::<rusqlite::Result<Vec<_>>>()?;
+                for (table, column, method, pattern) in masked {
+                    let mask = format!(
+                        "{}, {}",
+                        quote_literal(&method),
+                        pattern.as_deref().map_or_else(|| "NULL".to_string(), quote_literal),
+                    );
+                    arguments.entry(table.to_lowercase()).or_insert_with(HashMap::new).insert(column.to_lowercase(), mask);
+                }
+                for (table, masked) in &arguments {
+                    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?1) ORDER BY cid")?;
+                    let columns = stmt.query_map([table], |row| row.get::<_, String>(0))?
+                        .map(|column| column.map(|column| match masked.get(&column.to_lowercase()) {
+                            Some(mask) => format!("{} AS {}", mask_expression(&quote_identifier(&column), mask), quote_identifier(&column)),
+                            None => quote_identifier(&column),
+                        }))
+                        .collect::<rusqlite::Result<Vec<_>>>()?;
+                    subqueries.insert(table.clone(), format!("SELECT {} FROM {}", columns.join(", "), quote_identifier(table)));
+                }
+            }
+            Ok(ColumnMasks { generation, subqueries, arguments })
+        }).await?);
+        session.set_column_masks(masks.clone());
+        Ok(masks)
+    }
+
+    /// The statements rewritten for the masks, or None when they don't read a masked table
+    fn rewrite_statements(query: &str, masks: &ColumnMasks) -> Result<Option<String>, PgSqliteError> {
+        let mentions = |table: &str| query.as_bytes().windows(table.len()).any(|w| w.eq_ignore_ascii_case(table.as_bytes()));
+        let mentioned = masks.subqueries.keys().find(|table| mentions(table));
+        if mentioned.is_none() && !mentions(MASKS_TABLE) {
+            return Ok(None);
+        }
+        let Ok(mut statements) = Parser::parse_sql(&PostgreSqlDialect {}, query) else {
+            return Err(mentioned.map_or_else(masks_table_denied, |table| unmaskable(table)));
+        };
+        let mut changed = false;
+        for statement in &mut statements {
+            changed |= Self::rewrite_statement(statement, masks)?;
+        }
+        Ok(changed.then(|| statements.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")))
+    }
+
+    /// Rewrite a statement, returning whether it changed. Each masked table it names must
+    /// be one it reads through the table's subquery or the table an INSERT, UPDATE or
+    /// DELETE writes; the rows those change are matched on their values.
+    fn rewrite_statement(statement: &mut Statement, masks: &ColumnMasks) -> Result<bool, PgSqliteError> {
+        let mut named = Vec::new();
+        let mut names_masks_table = false;
+        let _ = visit_relations(&*statement, |name| {
+            names_masks_table |= name.0.last()
+                .and_then(|part| part.as_ident())
+                .is_some_and(|ident| ident.value.eq_ignore_ascii_case(MASKS_TABLE));
+            named.extend(masks.table(name).map(str::to_string));
+            ControlFlow::<()>::Continue(())
+        });
+        if names_masks_table {
+            let writes = visit_statements(&*statement, |statement| match statement {
+                Statement::Query(_) => ControlFlow::Continue(()),
+                _ => ControlFlow::Break(()),
+            });
+            if writes.is_break() {
+                return Err(masks_table_denied());
+            }
+        }
+        let Some(first) = named.first() else {
+            return Ok(false);
+        };
+        let replacement = |table: &str, _: &ObjectName| masks.subqueries.get(table).cloned();
+        let mut rewriter = TableRewriter::new(&replacement);
+        let mut written = 0;
+        let mut changed = false;
+        match statement {
+            Statement::Query(query) => rewriter.query(query),
+            Statement::Insert(insert) => {
+                let target = match &insert.table {
+                    TableObject::TableName(name) => masks.target(name, insert.table_alias.as_ref()),
+                    TableObject::TableFunction(_) => None,
+                };
+                if let Some(source) = &mut insert.source {
+                    rewriter.query(source);
+                }
+                if let Some(OnInsert::OnConflict(OnConflict { action: OnConflictAction::DoUpdate(update), .. })) = &mut insert.on {
+                    for assignment in &mut update.assignments {
+                        if let Some(target) = &target {
+                            target.check_assignment(assignment)?;
+                        }
+                        rewriter.expr(&mut assignment.value);
+                    }
+                    if let Some(selection) = &mut update.selection {
+                        rewriter.expr(selection);
+                    }
+                }
+                if let Some(target) = &target {
+                    written += 1;
+                    changed |= target.mask_returning(&mut insert.returning)?;
+                }
+                rewrite_returning(&mut rewriter, &mut insert.returning);
+            }
+            Statement::Update { table, assignments, from, selection, returning, .. } => {
+                if let Some(target) = masks.table_target(table) {
+                    written += 1;
+                    for assignment in assignments.iter() {
+                        target.check_assignment(assignment)?;
+                    }
+                    changed |= target.mask_returning(returning)?;
+                }
+                rewrite_returning(&mut rewriter, returning);
+                for assignment in assignments.iter_mut() {
+                    rewriter.expr(&mut assignment.value);
+                }
+                if let Some(UpdateTableFromKind::AfterSet(from) | UpdateTableFromKind::BeforeSet(from)) = from {
+                    from.iter_mut().for_each(|table| rewriter.table_with_joins(table));
+                }
+                if let Some(selection) = selection {
+                    rewriter.expr(selection);
+                }
+            }
+            Statement::Delete(delete) => {
+                let (FromTable::WithFromKeyword(from) | FromTable::WithoutKeyword(from)) = &delete.from;
+                if let ([table], true) = (from.as_slice(), delete.tables.is_empty())
+                    && let Some(target) = masks.table_target(table) {
+                    written += 1;
+                    changed |= target.mask_returning(&mut delete.returning)?;
+                }
+                rewrite_returning(&mut rewriter, &mut delete.returning);
+                if let Some(using) = &mut delete.using {
+                    using.iter_mut().for_each(|table| rewriter.table_with_joins(table));
+                }
+                if let Some(selection) = &mut delete.selection {
+                    rewriter.expr(selection);
+                }
+            }
+            Statement::CreateTable(create) => {
+                if let Some(query) = &mut create.query {
+                    rewriter.query(query);
+                }
+            }
+            Statement::CreateView { query, .. } => rewriter.query(query),
+            Statement::Explain { statement, .. } => return Self::rewrite_statement(statement, masks),
+            // Dropping, emptying or indexing a masked table reads none of its values
+            Statement::Drop { .. } | Statement::Truncate { .. } | Statement::CreateIndex(_) => return Ok(false),
+            // COPY TO masks what it exports itself, see CopyHandler::handle_copy_to
+            Statement::Copy { .. } => return Ok(false),
+            _ => {}
+        }
+        if rewriter.replaced + written != named.len() {
+            return Err(unmaskable(first));
+        }
+        Ok(changed || rewriter.changed)
+    }
+
+    /// pgsqlite_mask(value, method, pattern): the value as the mask shows it; NULL stays NULL
+    pub fn mask_value(value: &str, method: &str, pattern: Option<&str>) -> Result<String, String> {
+        match MaskMethod::parse(method) {
+            Some(MaskMethod::Full) => Ok(FULL_MASK.to_string()),
+            Some(MaskMethod::Hash) => Ok(hex::encode(Sha256::digest(value.as_bytes()))),
+            Some(MaskMethod::Partial) => {
+                let pattern = pattern.unwrap_or(DEFAULT_PARTIAL_PATTERN);
+                let mut patterns = PATTERNS.lock();
+                let regex = match patterns.get(pattern) {
+                    Some(regex) => regex,
+                    None => {
+                        let regex = Regex::new(pattern).map_err(|e| format!("invalid regular expression: {e}"))?;
+                        patterns.entry(pattern.to_string()).or_insert(regex)
+                    }
+                };
+                Ok(mask_matches(value, regex))
+            }
+            None => Err(format!("unknown mask method \"{method}\"")),
+        }
+    }
+}
+
+/// Replace the characters of each match with `*`, or only those of its capture groups
+/// when the pattern has some
+fn mask_matches(value: &str, regex: &Regex) -> String {
+    let mut hidden = vec![false; value.len()];
+    for caps in regex.captures_iter(value) {
+        let ranges: Vec<_> = if caps.len() > 1 {
+            caps.iter().skip(1).flatten().map(|m| m.range()).collect()
+        } else {
+            caps.get(0).map(|m| m.range()).into_iter().collect()
+        };
+        for range in ranges {
+            hidden[range].fill(true);
+        }
+    }
+    value.char_indices()
+        .map(|(i, c)| if hidden[i] { '*' } else { c })
+        .collect()
+}
+
+
+
+/// Rewrite the subqueries of a RETURNING clause
+fn rewrite_returning(rewriter: &mut TableRewriter, returning: &mut Option<Vec<SelectItem>>) {
+    for item in returning.iter_mut().flatten() {
+        if let SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } = item {
+            rewriter.expr(expr);
+        }
+    }
+}
+
+/// pgsqlite_mask() of a value, given the arguments of its column's mask
+fn mask_expression(value: &str, arguments: &str) -> String {
+    format!("pgsqlite_mask({value}, {arguments})")
+}
+
+/// The error for a statement that reads a masked table in a way its masks can't be applied to
+fn unmaskable(table: &str) -> PgSqliteError {
+    pg_error("42501", format!("permission denied: the column masks of relation \"{table}\" can't be applied to this statement"))
+}
+
+/// The error for a statement of another role that changes the masks table
+fn masks_table_denied() -> PgSqliteError {
+    pg_error("42501", format!("permission denied: only --admin-users may change {MASKS_TABLE}"))
+}
+
+/// Databases opened before migration 37 have no masks table
+fn has_column_masks(conn: &Connection) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '__pgsqlite_column_masks')",
+        [],
+        |row| row.get(0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mask_call() {
+        assert_eq!(
+            MaskHandler::parse_mask_call("SELECT pgsqlite.set_mask('users', 'email', 'partial', '^([^@]*)@');"),
+            Some(MaskCall::Set {
+                table: "users".to_string(),
+                column: "email".to_string(),
+                method: "partial".to_string(),
+                pattern: Some("^([^@]*)@".to_string()),
+            })
+        );
+        assert_eq!(
+            MaskHandler::parse_mask_call("select * from PGSQLITE.DROP_MASK( 'o''s', 'ssn' )"),
+            Some(MaskCall::Drop { table: "o's".to_string(), column: "ssn".to_string() })
+        );
+        assert_eq!(MaskHandler::parse_mask_call("SELECT pgsqlite.set_mask('users', 'email')"), None);
+        assert_eq!(MaskHandler::parse_mask_call("SELECT pgsqlite.drop_mask('users', 'email', 'full')"), None);
+    }
+
+    #[test]
+    fn test_mask_value() {
+        assert_eq!(MaskHandler::mask_value("4111-1111-1111-1234", "partial", None).unwrap(), "***************1234");
+        assert_eq!(MaskHandler::mask_value("abc", "partial", None).unwrap(), "abc");
+        assert_eq!(MaskHandler::mask_value("jane.doe@example.com", "partial", Some("^([^@]*)@")).unwrap(), "********@example.com");
+        assert_eq!(MaskHandler::mask_value("555-12-3456", "partial", Some(r"\d")).unwrap(), "***-**-****");
+        assert_eq!(MaskHandler::mask_value("é@ü", "partial", Some("é")).unwrap(), "*@ü");
+        assert_eq!(MaskHandler::mask_value("secret", "FULL", None).unwrap(), FULL_MASK);
+        assert_eq!(
+            MaskHandler::mask_value("abc", "hash", None).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert!(MaskHandler::mask_value("abc", "redact", None).is_err());
+    }
+
+    #[test]
+    fn test_rewrite_statements() {
+        let masks = ColumnMasks {
+            generation: 0,
+            subqueries: HashMap::from([(
+                "users".to_string(),
+                "SELECT \"id\", pgsqlite_mask(\"email\", 'full', NULL) AS \"email\" FROM \"users\"".to_string(),
+            )]),
+            arguments: HashMap::from([(
+                "users".to_string(),
+                HashMap::from([("email".to_string(), "'full', NULL".to_string())]),
+            )]),
+        };
+        let rewrite = |query: &str| MaskHandler::rewrite_statements(query, &masks);
+        assert_eq!(
+            rewrite("SELECT email AS e FROM users u WHERE id = 1").unwrap().as_deref(),
+            Some("SELECT email AS e FROM (SELECT \"id\", pgsqlite_mask(\"email\", 'full', NULL) AS \"email\" FROM \"users\") AS u WHERE id = 1")
+        );
+        assert_eq!(
+            rewrite("DELETE FROM users WHERE email IN (SELECT email FROM users)").unwrap().as_deref(),
+            Some("DELETE FROM users WHERE email IN (SELECT email FROM (SELECT \"id\", pgsqlite_mask(\"email\", 'full', NULL) AS \"email\" FROM \"users\") AS users)")
+        );
+        assert_eq!(
+            rewrite("UPDATE users u SET id = 2 RETURNING u.email, *").unwrap().as_deref(),
+            Some("UPDATE users AS u SET id = 2 RETURNING pgsqlite_mask(u.email, 'full', NULL) AS email, \"id\", pgsqlite_mask(\"email\", 'full', NULL) AS \"email\"")
+        );
+        assert_eq!(rewrite("SELECT * FROM orders").unwrap(), None);
+        assert_eq!(rewrite("UPDATE users SET email = 'x' WHERE email = 'y'").unwrap(), None);
+
+        // What the masks can't be proven to cover is refused
+        for query in [
+            "SELECT email FROM users; SELECT",
+            "SELECT 1; SELECT * FROM main.users",
+            "UPDATE users SET id = length(email)",
+            "INSERT INTO users (id) VALUES (1) ON CONFLICT (id) DO UPDATE SET id = length(users.email)",
+            "WITH changed AS (UPDATE users SET id = 2 RETURNING email) SELECT * FROM changed",
+            "CREATE TRIGGER copy AFTER UPDATE ON users BEGIN SELECT 1; END",
+            "DELETE FROM __pgsqlite_column_masks",
+        ] {
+            assert!(rewrite(query).is_err(), "{query}");
+        }
+        assert_eq!(rewrite("SELECT * FROM __pgsqlite_column_masks").unwrap(), None);
+    }
+}
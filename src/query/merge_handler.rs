@@ -0,0 +1,458 @@
+use crate::protocol::BackendMessage;
+use crate::query::sql_utils::last_name_part;
+use crate::query::trigger_handler::pg_error;
+use crate::query::TranslationPipeline;
+use crate::session::{SessionState, SessionStorage};
+use crate::PgSqliteError;
+use futures::SinkExt;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use sqlparser::ast::{
+    AssignmentTarget, MergeAction, MergeClauseKind, MergeInsertKind, Statement, TableFactor,
+};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use std::sync::Arc;
+use tokio_util::codec::Framed;
+use tracing::debug;
+
+/// The WHEN of a merge clause, or a THEN DO NOTHING, which sqlparser doesn't know
+static DO_NOTHING_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\bWHEN\s+(?:(NOT\s+MATCHED)(\s+BY\s+SOURCE)?|MATCHED)\b|\bTHEN\s+DO\s+NOTHING\b").unwrap()
+});
+
+/// Column a DO NOTHING is parsed as setting or inserting
+const DO_NOTHING_COLUMN: &str = "__pgsqlite_do_nothing";
+
+/// The temp table holding the joined rows and the clause each one takes
+const WORK_TABLE: &str = "__pgsqlite_merge";
+
+/// Which rows a merge clause applies to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MergeMatch {
+    /// Target rows the source joins
+    Matched,
+    /// Source rows that join no target row
+    NotMatched,
+    /// Target rows that no source row joins
+    NotMatchedBySource,
+}
+
+/// What a merge clause does to its rows
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeClauseAction {
+    /// The (column, expression) assignments of UPDATE SET
+    Update(Vec<(String, String)>),
+    Delete,
+    Insert { columns: Vec<String>, values: Vec<String> },
+    Nothing,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeClause {
+    pub kind: MergeMatch,
+    pub predicate: Option<String>,
+    pub action: MergeClauseAction,
+}
+
+/// A parsed MERGE statement, its expressions as PostgreSQL SQL
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeStatement {
+    pub target: String,
+    /// The name the other clauses refer to the target by
+    pub target_alias: String,
+    /// The SELECT producing the source rows
+    pub source: String,
+    pub source_alias: String,
+    /// Column aliases of the source, if given
+    pub source_columns: Vec<String>,
+    pub on: String,
+    pub clauses: Vec<MergeClause>,
+}
+
+/// Handles `MERGE INTO target USING source ON ... WHEN ... THEN ...`.
+///
+/// SQLite has no MERGE, so the statement runs as a sequence inside a savepoint:
+/// the source is outer-joined to the target into a temp table that records the
+/// first clause each row satisfies, then one UPDATE, DELETE or INSERT per clause
+/// applies it to the rows that took that clause. Deciding every row's clause up
+/// front keeps a row a clause inserts or updates from being matched again by a
+/// later one, as in PostgreSQL.
+pub struct MergeHandler;
+
+impl MergeHandler {
+    /// Whether a query starts with MERGE, before it is handed to sqlparser
+    pub fn might_be_merge(query: &str) -> bool {
+        query.trim_start().get(..5).is_some_and(|prefix| prefix.eq_ignore_ascii_case("MERGE"))
+    }
+
+    pub fn parse_merge(query: &str) -> Result<Option<MergeStatement>, PgSqliteError> {
+        if !Self::might_be_merge(query) {
+            return Ok(None);
+        }
+        let query = Self::mark_do_nothing(query);
+        let statements = Parser::parse_sql(&PostgreSqlDialect {}, &query)?;
+        let [Statement::Merge { table, source, on, clauses, output, .. }] = statements.as_slice() else {
+            return Ok(None);
+        };
+        if output.is_some() {
+            return Err(pg_error("0A000", "MERGE ... OUTPUT is not supported".to_string()));
+        }
+
+        let TableFactor::Table { name, alias, args: None, .. } = table else {
+            return Err(pg_error("0A000", format!("MERGE into {table} is not supported, only into a table")));
+        };
+        let target_alias = alias.as_ref().map(|alias| alias.name.to_string()).unwrap_or_else(|| last_name_part(&name.to_string()).to_string());
+        let (source_query, source_alias) = match source {
+            TableFactor::Table { name, alias, args: None, .. } => (
+                format!("SELECT * FROM {name}"),
+                alias.as_ref().map(|alias| alias.name.to_string()).unwrap_or_else(|| last_name_part(&name.to_string()).to_string()),
+            ),
+            TableFactor::Derived { lateral: false, subquery, alias } => (
+                subquery.to_string(),
+                alias.as_ref().map(|alias| alias.name.to_string()).unwrap_or_else(|| "__pgsqlite_merge_source".to_string()),
+            ),
+            _ => return Err(pg_error("0A000", format!("MERGE using {source} is not supported, only a table or subquery"))),
+        };
+        let source_columns = match source {
+            TableFactor::Table { alias: Some(alias), .. } | TableFactor::Derived { alias: Some(alias), .. } => {
+                alias.columns.iter().map(|column| column.name.to_string()).collect()
+            }
+            _ => Vec::new(),
+        };
+
+        let clauses = clauses.iter()
+            .map(|clause| {
+                let kind = match clause.clause_kind {
+                    MergeClauseKind::Matched => MergeMatch::Matched,
+                    MergeClauseKind::NotMatched | MergeClauseKind::NotMatchedByTarget => MergeMatch::NotMatched,
+                    MergeClauseKind::NotMatchedBySource => MergeMatch::NotMatchedBySource,
+                };
+                let action = match &clause.action {
+                    MergeAction::Update { assignments } => {
+                        let assignments = assignments.iter()
+                            .map(|assignment| match &assignment.target {
+                                AssignmentTarget::ColumnName(column) => Ok((last_name_part(&column.to_string()).to_string(), assignment.value.to_string())),
+                                AssignmentTarget::Tuple(_) => Err(pg_error(
+                                    "0A000",
+                                    "UPDATE SET (...) = ... is not supported in MERGE".to_string(),
+                                )),
+                            })
+                            .collect::<Result<Vec<_>, _>>()?;
+                        if assignments.iter().any(|(column, _)| column == DO_NOTHING_COLUMN) {
+                            MergeClauseAction::Nothing
+                        } else {
+                            MergeClauseAction::Update(assignments)
+                        }
+                    }
+                    MergeAction::Delete => MergeClauseAction::Delete,
+                    MergeAction::Insert(insert) if insert.columns.iter().any(|column| column.value == DO_NOTHING_COLUMN) => {
+                        MergeClauseAction::Nothing
+                    }
+                    MergeAction::Insert(insert) => match &insert.kind {
+                        MergeInsertKind::Row => {
+                            return Err(pg_error("0A000", "MERGE INSERT ROW is not supported".to_string()));
+                        }
+                        MergeInsertKind::Values(values) => {
+                            let [row] = values.rows.as_slice() else {
+                                return Err(pg_error("42601", "MERGE INSERT takes a single VALUES row".to_string()));
+                            };
+                            let mut columns = Vec::new();
+                            let mut exprs = Vec::new();
+                            for (i, expr) in row.iter().enumerate() {
+                                let value = expr.to_string();
+                                // DEFAULT leaves the column out, so it gets its default
+                                if value.eq_ignore_ascii_case("DEFAULT") {
+                                    if insert.columns.is_empty() {
+                                        return Err(pg_error(
+                                            "0A000",
+                                            "DEFAULT in MERGE INSERT needs a column list".to_string(),
+                                        ));
+                                    }
+                                    continue;
+                                }
+                                if let Some(column) = insert.columns.get(i) {
+                                    columns.push(column.to_string());
+                                }
+                                exprs.push(value);
+                            }
+                            MergeClauseAction::Insert { columns, values: exprs }
+                        }
+                    },
+                };
+                Ok(MergeClause {
+                    kind,
+                    predicate: clause.predicate.as_ref().map(|predicate| predicate.to_string()),
+                    action,
+                })
+            })
+            .collect::<Result<Vec<_>, PgSqliteError>>()?;
+
+        Ok(Some(MergeStatement {
+            target: name.to_string(),
+            target_alias,
+            source: source_query,
+            source_alias,
+            source_columns,
+            on: on.to_string(),
+            clauses,
+        }))
+    }
+
+    /// Replace each THEN DO NOTHING with an action sqlparser allows in its clause:
+    /// an INSERT of a marker column for NOT MATCHED, an UPDATE of it otherwise
+    fn mark_do_nothing(query: &str) -> String {
+        let mut not_matched = false;
+        DO_NOTHING_PATTERN.replace_all(query, |caps: &Captures| {
+            if caps[0].len() >= 4 && caps[0][..4].eq_ignore_ascii_case("WHEN") {
+                not_matched = caps.get(1).is_some() && caps.get(2).is_none();
+                caps[0].to_string()
+            } else if not_matched {
+                format!("THEN INSERT ({DO_NOTHING_COLUMN}) VALUES (0)")
+            } else {
+                format!("THEN UPDATE SET {DO_NOTHING_COLUMN} = 0")
+            }
+        }).into_owned()
+    }
+
+    pub async fn handle_merge<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        merge: &MergeStatement,
+    ) -> Result<(), PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        db.execute_with_session("SAVEPOINT __pgsqlite_merge", &session.id).await?;
+        let result = Self::run(db, session, merge).await;
+        if let Err(drop_error) = db.execute_with_session(&format!("DROP TABLE IF EXISTS temp.{WORK_TABLE}"), &session.id).await {
+            debug!("Failed to drop the MERGE work table: {}", drop_error);
+        }
+        let rows = match result {
+            Ok(rows) => {
+                db.execute_with_session("RELEASE __pgsqlite_merge", &session.id).await?;
+                rows
+            }
+            Err(e) => {
+                for rollback in ["ROLLBACK TO __pgsqlite_merge", "RELEASE __pgsqlite_merge"] {
+                    if let Err(rollback_error) = db.execute_with_session(rollback, &session.id).await {
+                        debug!("Failed to roll back MERGE: {}", rollback_error);
+                    }
+                }
+                return Err(e);
+            }
+        };
+        debug!("MERGE into {} affected {} rows", merge.target, rows);
+
+        framed.send(BackendMessage::CommandComplete { tag: format!("MERGE {rows}") }).await
+            .map_err(PgSqliteError::Io)
+    }
+
+    /// Run the statements of the merge, returning the number of rows it affected
+    async fn run(db: &Arc<dyn SessionStorage>, session: &Arc<SessionState>, merge: &MergeStatement) -> Result<usize, PgSqliteError> {
+        db.execute_with_session(&format!("DROP TABLE IF EXISTS temp.{WORK_TABLE}"), &session.id).await?;
+        let work = TranslationPipeline::translate(db, session, &merge.work_query()).await?;
+        for setup in &work.setup {
+            db.execute_with_session(setup, &session.id).await?;
+        }
+        db.execute_with_session(&format!("CREATE TEMP TABLE {WORK_TABLE} AS {}", work.sql), &session.id).await?;
+
+        if let Some(check) = merge.duplicate_check() {
+            let duplicates = db.query_with_session(&check, &session.id).await?;
+            if !duplicates.rows.is_empty() {
+                return Err(pg_error("21000", "MERGE command cannot affect row a second time".to_string()));
+            }
+        }
+
+        let mut rows = 0;
+        for statement in (0..merge.clauses.len()).filter_map(|i| merge.clause_statement(i)) {
+            let translated = TranslationPipeline::translate(db, session, &statement).await?;
+            for setup in &translated.setup {
+                db.execute_with_session(setup, &session.id).await?;
+            }
+            rows += db.execute_with_session(&translated.sql, &session.id).await?.rows_affected;
+        }
+        Ok(rows)
+    }
+}
+
+impl MergeStatement {
+    /// Whether any clause needs the target rows no source row joins
+    fn by_source(&self) -> bool {
+        self.clauses.iter().any(|clause| clause.kind == MergeMatch::NotMatchedBySource)
+    }
+
+    /// The SELECT of the work table: the source outer-joined to the target, with the
+    /// target row's rowid and the 1-based number of the clause each row takes, or 0
+    fn work_query(&self) -> String {
+        let source_columns = if self.source_columns.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", self.source_columns.join(", "))
+        };
+        let (s, t) = (&self.source_alias, &self.target_alias);
+        let whens: String = self.clauses.iter().enumerate()
+            .map(|(i, clause)| {
+                let kind = match clause.kind {
+                    MergeMatch::Matched => format!("{t}.rowid IS NOT NULL AND {s}.__pgsqlite_source_row IS NOT NULL"),
+                    MergeMatch::NotMatched => format!("{t}.rowid IS NULL"),
+                    MergeMatch::NotMatchedBySource => format!("{s}.__pgsqlite_source_row IS NULL"),
+                };
+                match &clause.predicate {
+                    Some(predicate) => format!(" WHEN {kind} AND ({predicate}) THEN {}", i + 1),
+                    None => format!(" WHEN {kind} THEN {}", i + 1),
+                }
+            })
+            .collect();
+        format!(
+            "WITH __pgsqlite_merge_source{source_columns} AS ({}) \
+             SELECT {s}.*, {t}.rowid AS __pgsqlite_target_rowid, CASE{whens} ELSE 0 END AS __pgsqlite_merge_action \
+             FROM (SELECT *, 1 AS __pgsqlite_source_row FROM __pgsqlite_merge_source) AS {s} \
+             {} JOIN {} AS {t} ON {}",
+            self.source,
+            if self.by_source() { "FULL" } else { "LEFT" },
+            self.target,
+            self.on,
+        )
+    }
+
+    /// A query returning a row if a target row would be updated or deleted more than once
+    fn duplicate_check(&self) -> Option<String> {
+        let actions: Vec<String> = self.clauses.iter().enumerate()
+            .filter(|(_, clause)| {
+                clause.kind == MergeMatch::Matched
+                    && matches!(clause.action, MergeClauseAction::Update(_) | MergeClauseAction::Delete)
+            })
+            .map(|(i, _)| (i + 1).to_string())
+            .collect();
+        if actions.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "SELECT __pgsqlite_target_rowid FROM {WORK_TABLE} WHERE __pgsqlite_merge_action IN ({}) \
+             GROUP BY __pgsqlite_target_rowid HAVING count(*) > 1 LIMIT 1",
+            actions.join(", "),
+        ))
+    }
+
+    /// The statement applying the i-th clause to the rows of the work table that took it
+    fn clause_statement(&self, i: usize) -> Option<String> {
+        let (s, t) = (&self.source_alias, &self.target_alias);
+        let action = i + 1;
+        match &self.clauses[i].action {
+            MergeClauseAction::Update(assignments) => {
+                let assignments = assignments.iter()
+                    .map(|(column, value)| format!("{column} = {value}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                Some(format!(
+                    "UPDATE {} AS {t} SET {assignments} FROM {WORK_TABLE} AS {s} \
+                     WHERE {s}.__pgsqlite_merge_action = {action} AND {t}.rowid = {s}.__pgsqlite_target_rowid",
+                    self.target,
+                ))
+            }
+            MergeClauseAction::Delete => Some(format!(
+                "DELETE FROM {} WHERE rowid IN \
+                 (SELECT __pgsqlite_target_rowid FROM {WORK_TABLE} WHERE __pgsqlite_merge_action = {action})",
+                self.target,
+            )),
+            MergeClauseAction::Insert { columns, values } => {
+                let columns = if columns.is_empty() {
+                    String::new()
+                } else {
+                    format!(" ({})", columns.join(", "))
+                };
+                let values = if values.is_empty() { "NULL".to_string() } else { values.join(", ") };
+                Some(format!(
+                    "INSERT INTO {}{columns} SELECT {values} FROM {WORK_TABLE} AS {s} \
+                     WHERE {s}.__pgsqlite_merge_action = {action}",
+                    self.target,
+                ))
+            }
+            MergeClauseAction::Nothing => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UPSERT: &str = "MERGE INTO items t USING incoming s ON t.id = s.id \
+        WHEN MATCHED AND s.qty = 0 THEN DELETE \
+        WHEN MATCHED THEN UPDATE SET qty = t.qty + s.qty \
+        WHEN NOT MATCHED THEN INSERT (id, qty) VALUES (s.id, s.qty)";
+
+    #[test]
+    fn test_parse_merge() {
+        let merge = MergeHandler::parse_merge(UPSERT).unwrap().unwrap();
+        assert_eq!(merge.target, "items");
+        assert_eq!(merge.target_alias, "t");
+        assert_eq!(merge.source, "SELECT * FROM incoming");
+        assert_eq!(merge.source_alias, "s");
+        assert_eq!(merge.on, "t.id = s.id");
+        assert_eq!(merge.clauses, vec![
+            MergeClause { kind: MergeMatch::Matched, predicate: Some("s.qty = 0".to_string()), action: MergeClauseAction::Delete },
+            MergeClause {
+                kind: MergeMatch::Matched,
+                predicate: None,
+                action: MergeClauseAction::Update(vec![("qty".to_string(), "t.qty + s.qty".to_string())]),
+            },
+            MergeClause {
+                kind: MergeMatch::NotMatched,
+                predicate: None,
+                action: MergeClauseAction::Insert {
+                    columns: vec!["id".to_string(), "qty".to_string()],
+                    values: vec!["s.id".to_string(), "s.qty".to_string()],
+                },
+            },
+        ]);
+        assert_eq!(MergeHandler::parse_merge("SELECT 1").unwrap(), None);
+        assert!(MergeHandler::parse_merge("MERGE INTO items USING").is_err());
+    }
+
+    #[test]
+    fn test_parse_do_nothing() {
+        let merge = MergeHandler::parse_merge(
+            "merge into items using (values (1, 'a')) as v (id, name) on items.id = v.id \
+             when matched then do nothing when not matched by source then do nothing \
+             when not matched and v.name <> 'b' then do nothing",
+        ).unwrap().unwrap();
+        assert_eq!(merge.target_alias, "items");
+        assert_eq!(merge.source_alias, "v");
+        assert_eq!(merge.source_columns, vec!["id", "name"]);
+        let kinds: Vec<_> = merge.clauses.iter().map(|clause| (clause.kind, clause.action.clone())).collect();
+        assert_eq!(kinds, vec![
+            (MergeMatch::Matched, MergeClauseAction::Nothing),
+            (MergeMatch::NotMatchedBySource, MergeClauseAction::Nothing),
+            (MergeMatch::NotMatched, MergeClauseAction::Nothing),
+        ]);
+        assert_eq!(merge.clauses[2].predicate.as_deref(), Some("v.name <> 'b'"));
+    }
+
+    #[test]
+    fn test_clause_statements() {
+        let merge = MergeHandler::parse_merge(UPSERT).unwrap().unwrap();
+        assert_eq!(
+            merge.work_query(),
+            "WITH __pgsqlite_merge_source AS (SELECT * FROM incoming) \
+             SELECT s.*, t.rowid AS __pgsqlite_target_rowid, CASE \
+             WHEN t.rowid IS NOT NULL AND s.__pgsqlite_source_row IS NOT NULL AND (s.qty = 0) THEN 1 \
+             WHEN t.rowid IS NOT NULL AND s.__pgsqlite_source_row IS NOT NULL THEN 2 \
+             WHEN t.rowid IS NULL THEN 3 ELSE 0 END AS __pgsqlite_merge_action \
+             FROM (SELECT *, 1 AS __pgsqlite_source_row FROM __pgsqlite_merge_source) AS s \
+             LEFT JOIN items AS t ON t.id = s.id"
+        );
+        assert_eq!(
+            merge.clause_statement(1).unwrap(),
+            "UPDATE items AS t SET qty = t.qty + s.qty FROM __pgsqlite_merge AS s \
+             WHERE s.__pgsqlite_merge_action = 2 AND t.rowid = s.__pgsqlite_target_rowid"
+        );
+        assert_eq!(
+            merge.clause_statement(2).unwrap(),
+            "INSERT INTO items (id, qty) SELECT s.id, s.qty FROM __pgsqlite_merge AS s WHERE s.__pgsqlite_merge_action = 3"
+        );
+        assert!(merge.duplicate_check().unwrap().contains("IN (1, 2)"));
+    }
+}
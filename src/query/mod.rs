@@ -6,8 +6,36 @@ pub mod fast_path;
 pub mod extended_fast_path;
 pub mod query_type_detection;
 pub mod comment_stripper;
+pub mod sql_utils;
 pub mod lazy_processor;
 pub mod set_handler;
+pub mod sleep_handler;
+pub mod advisory_lock_handler;
+pub mod copy_handler;
+pub mod vacuum_handler;
+pub mod comment_handler;
+pub mod view_handler;
+pub mod trigger_handler;
+pub mod function_handler;
+pub mod alter_table_handler;
+pub mod schema_handler;
+pub mod progress;
+pub mod statement_stats;
+pub mod query_trace;
+pub mod event_log;
+pub mod compatibility;
+pub mod feature_hints;
+pub mod translation_pipeline;
+pub mod translate_handler;
+pub mod audit_handler;
+pub mod soft_delete_handler;
+pub mod mask_handler;
+pub mod retention_handler;
+pub mod schema_snapshot_handler;
+pub mod concurrent_index_handler;
+pub mod cluster_handler;
+pub mod merge_handler;
+pub mod auto_analyze;
 pub mod simple_query_detector;
 pub mod parameter_parser;
 pub mod query_processor;
@@ -15,6 +43,7 @@ pub mod unified_processor;
 pub mod pattern_optimizer;
 pub mod query_handler;
 pub mod join_type_inference;
+pub mod param_type_inference;
 
 pub use executor::QueryExecutor;
 pub use query_handler::{QueryHandler, QueryHandlerImpl};
@@ -26,9 +55,32 @@ pub use fast_path::{
     clear_decimal_cache, FastPathQuery, FastPathOperation, WhereClause
 };
 pub use query_type_detection::{QueryTypeDetector, QueryType};
-pub use comment_stripper::strip_sql_comments;
+pub use comment_stripper::{split_statements, strip_sql_comments};
 pub use lazy_processor::LazyQueryProcessor;
 pub use set_handler::SetHandler;
+pub use sleep_handler::SleepHandler;
+pub use advisory_lock_handler::AdvisoryLockHandler;
+pub use copy_handler::CopyHandler;
+pub use vacuum_handler::VacuumHandler;
+pub use comment_handler::CommentHandler;
+pub use view_handler::{ViewHandler, ViewStatement};
+pub use trigger_handler::{TriggerHandler, TriggerStatement};
+pub use function_handler::{FunctionHandler, SqlFunction, FunctionArgument};
+pub use alter_table_handler::{AlterTableHandler, AlterTableStatement, AlterTableAction};
+pub use schema_handler::{SchemaHandler, SchemaStatement};
+pub use translation_pipeline::{TranslationPipeline, TranslatedQuery};
+pub use translate_handler::TranslateHandler;
+pub use audit_handler::AuditHandler;
+pub use soft_delete_handler::{SoftDeleteHandler, SoftDeleteCall};
+pub use mask_handler::{MaskHandler, MaskCall};
+pub use retention_handler::{RetentionHandler, RetentionCall};
+pub use schema_snapshot_handler::{SchemaSnapshotHandler, SchemaSnapshotCall};
+pub use concurrent_index_handler::{ConcurrentIndexHandler, ConcurrentIndexStatement, ConcurrentIndex};
+pub use cluster_handler::{ClusterHandler, ClusterStatement};
+pub use merge_handler::{MergeHandler, MergeStatement};
+pub use auto_analyze::AutoAnalyze;
+pub use compatibility::{CompatibilityCheck, StrictCompatibility};
+pub use feature_hints::FeatureHints;
 pub use query_processor::process_query;
 pub use parameter_parser::ParameterParser;
 pub use pattern_optimizer::{QueryPatternOptimizer, QueryPattern, OptimizationHints, QueryComplexity, ResultSize};
\ No newline at end of file
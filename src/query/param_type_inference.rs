@@ -0,0 +1,269 @@
+use sqlparser::ast::{
+    AssignmentTarget, BinaryOperator, Expr, JoinConstraint, JoinOperator, Query, SelectItem, SetExpr,
+    Statement, TableFactor, TableObject, TableWithJoins, UpdateTableFromKind, Value,
+};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+
+/// The column a parameter is bound to, with the tables it may belong to
+/// (the qualifying table, or every table in scope for an unqualified column)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParamColumn {
+    pub tables: Vec<String>,
+    pub column: String,
+}
+
+/// Map the parameters of `INSERT ... SELECT` and `UPDATE ... [FROM ...]` statements to
+/// the columns that determine their types.
+///
+/// Parameters selected into an INSERT column or assigned in SET take the target column's
+/// type; parameters compared against a column in WHERE, JOIN ... ON or HAVING (`=`, `<`,
+/// `IN (...)`, `BETWEEN`, ...) take that column's type, with table aliases resolved.
+/// Returns None for other statements. Entries are indexed by parameter number - 1 and
+/// are None when nothing determines the type.
+pub fn dml_parameter_columns(query: &str, param_count: usize) -> Option<Vec<Option<ParamColumn>>> {
+    let statements = Parser::parse_sql(&PostgreSqlDialect {}, query).ok()?;
+    let mut scope = Scope { params: vec![None; param_count], ..Default::default() };
+
+    match statements.first()? {
+        Statement::Insert(insert) => {
+            let TableObject::TableName(name) = &insert.table else {
+                return None;
+            };
+            let target = table_name(&name.to_string());
+            let source = insert.source.as_ref()?;
+            let SetExpr::Select(select) = source.body.as_ref() else {
+                // INSERT ... VALUES maps parameters by position elsewhere
+                return None;
+            };
+
+            for (item, column) in select.projection.iter().zip(&insert.columns) {
+                if let SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } = item
+                    && let Some(index) = placeholder_index(expr)
+                {
+                    scope.bind(index, ParamColumn { tables: vec![target.clone()], column: column.value.clone() });
+                }
+            }
+            scope.visit_query(source);
+        }
+        Statement::Update { table, assignments, from, selection, .. } => {
+            scope.add_tables(table);
+            let target = scope.tables.first().map(|(name, _)| name.clone())?;
+            if let Some(UpdateTableFromKind::BeforeSet(tables) | UpdateTableFromKind::AfterSet(tables)) = from {
+                tables.iter().for_each(|t| scope.add_tables(t));
+            }
+
+            for assignment in assignments {
+                if let AssignmentTarget::ColumnName(name) = &assignment.target
+                    && let Some(index) = placeholder_index(&assignment.value)
+                {
+                    let column = table_name(&name.to_string());
+                    scope.bind(index, ParamColumn { tables: vec![target.clone()], column });
+                } else {
+                    scope.visit_expr(&assignment.value);
+                }
+            }
+            if let Some(selection) = selection {
+                scope.visit_expr(selection);
+            }
+        }
+        _ => return None,
+    }
+
+    Some(scope.params)
+}
+
+/// Tables visible to the statement, as (table, alias)
+#[derive(Default)]
+struct Scope {
+    tables: Vec<(String, Option<String>)>,
+    params: Vec<Option<ParamColumn>>,
+}
+
+impl Scope {
+    fn bind(&mut self, index: usize, column: ParamColumn) {
+        if let Some(slot) = self.params.get_mut(index)
+            && slot.is_none()
+        {
+            *slot = Some(column);
+        }
+    }
+
+    fn add_tables(&mut self, table: &TableWithJoins) {
+        self.add_table_factor(&table.relation);
+        for join in &table.joins {
+            self.add_table_factor(&join.relation);
+        }
+    }
+
+    fn add_table_factor(&mut self, factor: &TableFactor) {
+        if let TableFactor::Table { name, alias, .. } = factor {
+            self.tables.push((table_name(&name.to_string()), alias.as_ref().map(|a| a.name.value.to_lowercase())));
+        }
+    }
+
+    /// Join conditions and filters of a (sub)query; its tables join the scope
+    fn visit_query(&mut self, query: &Query) {
+        self.visit_set_expr(&query.body);
+    }
+
+    fn visit_set_expr(&mut self, body: &SetExpr) {
+        let SetExpr::Select(select) = body else {
+            return;
+        };
+        select.from.iter().for_each(|t| self.add_tables(t));
+        for table in &select.from {
+            for join in &table.joins {
+                if let Some(condition) = join_condition(&join.join_operator) {
+                    self.visit_expr(condition);
+                }
+            }
+        }
+        if let Some(selection) = &select.selection {
+            self.visit_expr(selection);
+        }
+        if let Some(having) = &select.having {
+            self.visit_expr(having);
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::BinaryOp { left, op, right } => {
+                if is_comparison(op) {
+                    self.bind_comparison(left, right);
+                    self.bind_comparison(right, left);
+                }
+                self.visit_expr(left);
+                self.visit_expr(right);
+            }
+            Expr::InList { expr, list, .. } => {
+                for item in list {
+                    self.bind_comparison(expr, item);
+                }
+            }
+            Expr::Between { expr, low, high, .. } => {
+                self.bind_comparison(expr, low);
+                self.bind_comparison(expr, high);
+            }
+            Expr::InSubquery { subquery, .. } => self.visit_set_expr(subquery),
+            Expr::Exists { subquery, .. } | Expr::Subquery(subquery) => self.visit_query(subquery),
+            Expr::Nested(inner) | Expr::UnaryOp { expr: inner, .. } => self.visit_expr(inner),
+            _ => {}
+        }
+    }
+
+    /// Record `column <op> $n`
+    fn bind_comparison(&mut self, column: &Expr, value: &Expr) {
+        let (Some(index), Some((qualifier, column))) = (placeholder_index(value), column_reference(column)) else {
+            return;
+        };
+        let tables = match qualifier {
+            Some(qualifier) => self.tables.iter()
+                .filter(|(name, alias)| alias.as_deref() == Some(qualifier.as_str()) || *name == qualifier)
+                .map(|(name, _)| name.clone())
+                .collect(),
+            None => self.tables.iter().map(|(name, _)| name.clone()).collect(),
+        };
+        self.bind(index, ParamColumn { tables, column });
+    }
+}
+
+fn is_comparison(op: &BinaryOperator) -> bool {
+    matches!(op, BinaryOperator::Eq | BinaryOperator::NotEq | BinaryOperator::Lt
+        | BinaryOperator::LtEq | BinaryOperator::Gt | BinaryOperator::GtEq)
+}
+
+fn join_condition(operator: &JoinOperator) -> Option<&Expr> {
+    match operator {
+        JoinOperator::Join(JoinConstraint::On(expr))
+        | JoinOperator::Inner(JoinConstraint::On(expr))
+        | JoinOperator::Left(JoinConstraint::On(expr))
+        | JoinOperator::LeftOuter(JoinConstraint::On(expr))
+        | JoinOperator::Right(JoinConstraint::On(expr))
+        | JoinOperator::RightOuter(JoinConstraint::On(expr))
+        | JoinOperator::FullOuter(JoinConstraint::On(expr)) => Some(expr),
+        _ => None,
+    }
+}
+
+/// Zero-based index of a `$n` placeholder
+fn placeholder_index(expr: &Expr) -> Option<usize> {
+    match expr {
+        Expr::Value(value) => match &value.value {
+            Value::Placeholder(placeholder) => placeholder.strip_prefix('$')?.parse::<usize>().ok()?.checked_sub(1),
+            _ => None,
+        },
+        Expr::Nested(inner) => placeholder_index(inner),
+        _ => None,
+    }
+}
+
+/// `column` or `qualifier.column`, lowercased
+fn column_reference(expr: &Expr) -> Option<(Option<String>, String)> {
+    match expr {
+        Expr::Identifier(ident) => Some((None, ident.value.to_lowercase())),
+        Expr::CompoundIdentifier(parts) if parts.len() >= 2 => Some((
+            Some(parts[parts.len() - 2].value.to_lowercase()),
+            parts[parts.len() - 1].value.to_lowercase(),
+        )),
+        Expr::Nested(inner) => column_reference(inner),
+        _ => None,
+    }
+}
+
+/// Unqualified, unquoted, lowercased table or column name
+fn table_name(name: &str) -> String {
+    name.rsplit('.').next().unwrap_or(name).trim_matches('"').to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(tables: &[&str], column: &str) -> Option<ParamColumn> {
+        Some(ParamColumn { tables: tables.iter().map(|t| t.to_string()).collect(), column: column.to_string() })
+    }
+
+    #[test]
+    fn test_insert_select_parameters() {
+        let columns = dml_parameter_columns(
+            "INSERT INTO archive (id, note, archived_at) SELECT o.id, $1, $2 FROM orders o JOIN customers c ON c.id = o.customer_id \
+             WHERE o.total > $3 AND c.region IN ($4, $5)",
+            5,
+        ).unwrap();
+        assert_eq!(columns, vec![
+            column(&["archive"], "note"),
+            column(&["archive"], "archived_at"),
+            column(&["orders"], "total"),
+            column(&["customers"], "region"),
+            column(&["customers"], "region"),
+        ]);
+    }
+
+    #[test]
+    fn test_update_parameters() {
+        let columns = dml_parameter_columns(
+            "UPDATE accounts SET balance = $1, note = UPPER($2) WHERE id = $3",
+            3,
+        ).unwrap();
+        assert_eq!(columns, vec![column(&["accounts"], "balance"), None, column(&["accounts"], "id")]);
+
+        let columns = dml_parameter_columns(
+            "UPDATE items AS i SET price = $1 FROM suppliers s WHERE s.id = i.supplier_id AND s.rating BETWEEN $2 AND $3 AND qty < $4",
+            4,
+        ).unwrap();
+        assert_eq!(columns, vec![
+            column(&["items"], "price"),
+            column(&["suppliers"], "rating"),
+            column(&["suppliers"], "rating"),
+            column(&["items", "suppliers"], "qty"),
+        ]);
+    }
+
+    #[test]
+    fn test_other_statements_are_not_analyzed() {
+        assert!(dml_parameter_columns("INSERT INTO t (a) VALUES ($1)", 1).is_none());
+        assert!(dml_parameter_columns("SELECT * FROM t WHERE a = $1", 1).is_none());
+    }
+}
@@ -0,0 +1,155 @@
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Long operations currently running, keyed by an id that is unique per operation
+static PROGRESS: Lazy<Mutex<HashMap<u64, Progress>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_PROGRESS_ID: AtomicU64 = AtomicU64::new(1);
+
+/// The kind of long operation, one pg_stat_progress_* view each
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressCommand {
+    /// COPY ... TO STDOUT, shown in pg_stat_progress_copy
+    Copy,
+    /// VACUUM [ANALYZE], shown in pg_stat_progress_vacuum
+    Vacuum,
+    /// A multi-statement script such as a schema dump, shown in pg_stat_progress_import
+    Import,
+    /// A retention policy deleting expired rows, shown in pg_stat_progress_retention
+    Retention,
+    /// CREATE INDEX CONCURRENTLY, shown in pg_stat_progress_create_index
+    CreateIndex,
+    /// CLUSTER, shown in pg_stat_progress_cluster
+    Cluster,
+}
+
+impl ProgressCommand {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "copy" => Some(ProgressCommand::Copy),
+            "vacuum" => Some(ProgressCommand::Vacuum),
+            "import" => Some(ProgressCommand::Import),
+            "retention" => Some(ProgressCommand::Retention),
+            "create_index" => Some(ProgressCommand::CreateIndex),
+            "cluster" => Some(ProgressCommand::Cluster),
+            _ => None,
+        }
+    }
+}
+
+/// A row of one of the pg_stat_progress_* views
+///
+/// The counters mean what the view says: tuples and bytes for COPY, heap pages
+/// for VACUUM, statements for an import and rows for a retention policy, an index
+/// build or a CLUSTER.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progress {
+    pub pid: u32,
+    pub command: ProgressCommand,
+    pub relname: Option<String>,
+    /// The index being built, for CREATE INDEX, or clustered on, for CLUSTER
+    pub index_relname: Option<String>,
+    pub phase: String,
+    pub total: u64,
+    pub done: u64,
+    pub bytes: u64,
+}
+
+/// Registers an operation in the progress registry and removes it when dropped,
+/// so operations that fail or lose their client don't linger in the views
+pub struct ProgressGuard(u64);
+
+impl ProgressGuard {
+    pub fn start(command: ProgressCommand, relname: Option<String>, phase: &str) -> Self {
+        let id = NEXT_PROGRESS_ID.fetch_add(1, Ordering::Relaxed);
+        PROGRESS.lock().insert(id, Progress {
+            pid: std::process::id(),
+            command,
+            relname,
+            index_relname: None,
+            phase: phase.to_string(),
+            total: 0,
+            done: 0,
+            bytes: 0,
+        });
+        ProgressGuard(id)
+    }
+
+    /// Count `done` more units of work and `bytes` more bytes
+    pub fn advance(&self, done: u64, bytes: u64) {
+        self.update(|progress| {
+            progress.done += done;
+            progress.bytes += bytes;
+        });
+    }
+
+    pub fn set_total(&self, total: u64) {
+        self.update(|progress| progress.total = total);
+    }
+
+    pub fn set_index_relname(&self, index: &str) {
+        self.update(|progress| progress.index_relname = Some(index.to_string()));
+    }
+
+    pub fn set_phase(&self, phase: &str) {
+        self.update(|progress| progress.phase = phase.to_string());
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Progress)) {
+        if let Some(progress) = PROGRESS.lock().get_mut(&self.0) {
+            f(progress);
+        }
+    }
+}
+
+impl Drop for ProgressGuard {
+    fn drop(&mut self) {
+        PROGRESS.lock().remove(&self.0);
+    }
+}
+
+/// Snapshot of the running operations of one kind
+pub fn progress(command: ProgressCommand) -> Vec<Progress> {
+    PROGRESS.lock().values().filter(|progress| progress.command == command).cloned().collect()
+}
+
+/// The running operations of one kind as a JSON array, the source of the pg_stat_progress_* views
+pub fn progress_json(command: ProgressCommand) -> String {
+    let rows: Vec<serde_json::Value> = progress(command).into_iter()
+        .map(|progress| serde_json::json!({
+            "pid": progress.pid,
+            "relname": progress.relname,
+            "index_relname": progress.index_relname,
+            "phase": progress.phase,
+            "total": progress.total,
+            "done": progress.done,
+            "bytes": progress.bytes,
+        }))
+        .collect();
+    serde_json::Value::Array(rows).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_registry() {
+        let guard = ProgressGuard::start(ProgressCommand::Vacuum, Some("progress_test_table".to_string()), "scanning heap");
+        guard.set_total(40);
+        guard.advance(10, 0);
+        guard.advance(30, 0);
+        guard.set_phase("performing final cleanup");
+
+        let find = || progress(ProgressCommand::Vacuum).into_iter()
+            .find(|p| p.relname.as_deref() == Some("progress_test_table"));
+        let entry = find().unwrap();
+        assert_eq!((entry.total, entry.done, entry.phase.as_str()), (40, 40, "performing final cleanup"));
+        assert!(progress(ProgressCommand::Copy).iter().all(|p| p.relname.as_deref() != Some("progress_test_table")));
+        assert!(progress_json(ProgressCommand::Vacuum).contains("\"relname\":\"progress_test_table\""));
+
+        drop(guard);
+        assert!(find().is_none());
+    }
+}
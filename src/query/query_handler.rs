@@ -1,9 +1,9 @@
-use crate::session::{DbHandler, DbResponse, QueryRouter, SessionState};
+use crate::session::{DbResponse, QueryRouter, SessionState, SessionStorage};
 use crate::PgSqliteError;
 use std::sync::Arc;
 use async_trait::async_trait;
 
-/// Trait for handling database queries - can be implemented by DbHandler or QueryRouter
+/// Trait for handling database queries - can be implemented by SessionStorage or QueryRouter
 #[async_trait]
 pub trait QueryHandler: Send + Sync {
     /// Execute a SELECT query
@@ -21,9 +21,9 @@ pub trait QueryHandler: Send + Sync {
     }
 }
 
-/// Wrapper enum to hold either a DbHandler or QueryRouter
+/// Wrapper enum to hold either a SessionStorage or QueryRouter
 pub enum QueryHandlerImpl {
-    Direct(Arc<DbHandler>),
+    Direct(Arc<dyn SessionStorage>),
     Routed(Arc<QueryRouter>),
 }
 
@@ -76,21 +76,21 @@ impl QueryHandler for QueryHandlerImpl {
     }
 }
 
-/// Implementation for Arc<DbHandler> to maintain backward compatibility
+/// Implementation for Arc<dyn SessionStorage> to maintain backward compatibility
 #[async_trait]
-impl QueryHandler for Arc<DbHandler> {
+impl QueryHandler for Arc<dyn SessionStorage> {
     async fn query(&self, sql: &str) -> Result<DbResponse, PgSqliteError> {
-        DbHandler::query(self, sql).await
+        SessionStorage::query(self.as_ref(), sql).await
             .map_err(|e| e.into())
     }
     
     async fn execute(&self, sql: &str) -> Result<DbResponse, PgSqliteError> {
-        DbHandler::execute(self, sql).await
+        SessionStorage::execute(self.as_ref(), sql).await
             .map_err(|e| e.into())
     }
     
     async fn get_schema_type(&self, table: &str, column: &str) -> Result<Option<String>, PgSqliteError> {
-        DbHandler::get_schema_type(self, table, column).await
+        SessionStorage::get_schema_type(self.as_ref(), table, column).await
             .map_err(|e| e.into())
     }
 }
\ No newline at end of file
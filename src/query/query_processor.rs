@@ -8,7 +8,7 @@ use super::unified_processor;
 #[cfg(not(feature = "unified_processor"))]
 use super::lazy_processor::LazyQueryProcessor;
 #[cfg(not(feature = "unified_processor"))]
-use super::simple_query_detector::is_fast_path_simple_query;
+use super::simple_query_detector::{extract_simple_table_name, is_fast_path_simple_query};
 
 use tracing::debug;
 
@@ -39,7 +39,9 @@ pub fn process_query(
     #[cfg(not(feature = "unified_processor"))]
     {
         // Old implementation - kept for A/B testing
-        if is_fast_path_simple_query(query) {
+        // NUMERIC columns hold decimal text, which only the decimal rewriter computes on
+        if is_fast_path_simple_query(query)
+            && !extract_simple_table_name(query).is_some_and(|table| schema_cache.has_decimal_columns_or_load(conn, &table)) {
             debug!("Using OLD FAST PATH for query: {}", query);
             return Ok(query.to_string());
         }
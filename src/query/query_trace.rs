@@ -0,0 +1,75 @@
+use crate::protocol::BackendMessage;
+use crate::protocol::messages::NoticeResponse;
+use crate::session::SessionState;
+use crate::PgSqliteError;
+use futures::SinkExt;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio_util::codec::Framed;
+use tracing::info;
+
+/// Per-session statement tracing, switched on with `SET pgsqlite.trace = on`.
+///
+/// Every line goes to the client as a DEBUG notice and to the server log, so a
+/// single misbehaving query can be followed without raising the log level of the
+/// whole server. Callers check `SessionState::trace_enabled()` before building a
+/// message so untraced sessions pay nothing.
+pub struct QueryTrace;
+
+impl QueryTrace {
+    /// Send one trace line to the client
+    pub async fn emit<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        session: &Arc<SessionState>,
+        message: String,
+    ) -> Result<(), PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        info!("pgsqlite.trace [{}] {}", session.id, message);
+        framed.send(BackendMessage::NoticeResponse(NoticeResponse {
+            severity: "DEBUG".to_string(),
+            code: "00000".to_string(),
+            message: format!("pgsqlite.trace: {message}"),
+            detail: None,
+            hint: None,
+            position: None,
+            where_: None,
+        })).await.map_err(PgSqliteError::Io)
+    }
+
+    /// Report the rewritten SQL when the translators changed the query
+    pub async fn translated<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        session: &Arc<SessionState>,
+        original: &str,
+        translated: &str,
+    ) -> Result<(), PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        if original.trim() == translated.trim() {
+            Self::emit(framed, session, "translated: unchanged".to_string()).await
+        } else {
+            Self::emit(framed, session, format!("translated: {translated}")).await
+        }
+    }
+
+    /// Report how long a statement took and whether it failed
+    pub async fn finished<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        session: &Arc<SessionState>,
+        started: Instant,
+        result: &Result<(), PgSqliteError>,
+    ) -> Result<(), PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        let elapsed = started.elapsed().as_secs_f64() * 1000.0;
+        let message = match result {
+            Ok(()) => format!("finished in {elapsed:.3} ms"),
+            Err(e) => format!("failed after {elapsed:.3} ms: {e}"),
+        };
+        Self::emit(framed, session, message).await
+    }
+}
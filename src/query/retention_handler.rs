@@ -0,0 +1,347 @@
+use crate::protocol::{BackendMessage, FieldDescription};
+use crate::query::audit_handler::{sqlite_name, table_name};
+use crate::query::progress::{ProgressCommand, ProgressGuard};
+use crate::query::sql_utils::quote_identifier;
+use crate::query::trigger_handler::pg_error;
+use crate::session::{SessionState, SessionStorage};
+use crate::types::{Interval, PgType, SchemaTypeMapper};
+use crate::PgSqliteError;
+use futures::SinkExt;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rusqlite::{Connection, OptionalExtension};
+use std::sync::Arc;
+use tokio_util::codec::Framed;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+static SET_RETENTION_CALL_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)^\s*SELECT\s+(?:\*\s+FROM\s+)?pgsqlite\.set_retention\s*\(\s*'((?:[^']|'')*)'\s*,\s*'((?:[^']|'')*)'\s*,\s*'((?:[^']|'')*)'\s*\)\s*;?\s*$").unwrap()
+});
+
+static DROP_RETENTION_CALL_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)^\s*SELECT\s+(?:\*\s+FROM\s+)?pgsqlite\.drop_retention\s*\(\s*'((?:[^']|'')*)'\s*\)\s*;?\s*$").unwrap()
+});
+
+static RUN_RETENTION_CALL_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)^\s*SELECT\s+(?:\*\s+FROM\s+)?pgsqlite\.run_retention\s*\(\s*\)\s*;?\s*$").unwrap()
+});
+
+const MICROS_PER_DAY: i64 = 86_400_000_000;
+
+/// A `pgsqlite.set_retention()`, `pgsqlite.drop_retention()` or `pgsqlite.run_retention()` call
+#[derive(Debug, Clone, PartialEq)]
+pub enum RetentionCall {
+    Set { table: String, column: String, max_age: String },
+    Drop { table: String },
+    Run,
+}
+
+/// A row of __pgsqlite_retention_policies
+#[derive(Debug, Clone)]
+struct RetentionPolicy {
+    table: String,
+    column: String,
+    max_age: String,
+}
+
+/// Handles `SELECT pgsqlite.set_retention('<table>', '<column>', '<max age>')`,
+/// `pgsqlite.drop_retention('<table>')` and `pgsqlite.run_retention()`.
+///
+/// A retention policy deletes the rows of a table whose timestamp or date column is
+/// older than the max age, an interval such as `'30 days'`. Policies are listed in
+/// __pgsqlite_retention_policies and run every --retention-interval seconds, or when
+/// run_retention() is called, deleting --retention-batch-size rows per statement so
+/// sessions writing the table aren't locked out for long. Running policies show up in
+/// pg_stat_progress_retention, and pg_stat_retention shows when each last ran and how
+/// many rows it deleted.
+pub struct RetentionHandler;
+
+impl RetentionHandler {
+    /// Whether a query mentions one of the `*_retention` functions anywhere
+    pub fn might_be_retention_call(query: &str) -> bool {
+        query.as_bytes().windows(10).any(|w| w.eq_ignore_ascii_case(b"_retention"))
+    }
+
+    /// A standalone retention function call
+    pub fn parse_retention_call(query: &str) -> Option<RetentionCall> {
+        if !Self::might_be_retention_call(query) {
+            return None;
+        }
+        let unquote = |s: &str| s.replace("''", "'").trim().to_string();
+        if let Some(caps) = SET_RETENTION_CALL_PATTERN.captures(query) {
+            return Some(RetentionCall::Set { table: unquote(&caps[1]), column: unquote(&caps[2]), max_age: unquote(&caps[3]) });
+        }
+        if let Some(caps) = DROP_RETENTION_CALL_PATTERN.captures(query) {
+            return Some(RetentionCall::Drop { table: unquote(&caps[1]) });
+        }
+        RUN_RETENTION_CALL_PATTERN.is_match(query).then_some(RetentionCall::Run)
+    }
+
+    /// The row description of the result, also reported by Parse in the extended protocol
+    pub fn field_descriptions(call: &RetentionCall) -> Vec<FieldDescription> {
+        let (name, pg_type, type_size) = match call {
+            RetentionCall::Set { .. } => ("set_retention", PgType::Bool, 1),
+            RetentionCall::Drop { .. } => ("drop_retention", PgType::Bool, 1),
+            RetentionCall::Run => ("run_retention", PgType::Int8, 8),
+        };
+        vec![FieldDescription {
+            name: name.to_string(),
+            table_oid: 0,
+            column_id: 1,
+            type_oid: pg_type.to_oid(),
+            type_size,
+            type_modifier: -1,
+            format: 0,
+        }]
+    }
+
+    /// Returns one row: whether set_retention() or drop_retention() changed the
+    /// table's policy, or how many rows run_retention() deleted, in binary when the
+    /// portal asked for it
+    pub async fn handle_retention_call<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        call: &RetentionCall,
+        skip_row_description: bool,
+        binary: bool,
+    ) -> Result<(), PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        db.require_local_data("retention policies")?;
+        let value = match call {
+            RetentionCall::Set { table, column, max_age } => {
+                let (table, column, max_age) = (table.clone(), column.clone(), max_age.clone());
+                let changed = db.with_session_connection(&session.id, move |conn| {
+                    Ok(Self::set_retention(conn, &table, &column, &max_age))
+                }).await??;
+                bool_value(changed, binary)
+            }
+            RetentionCall::Drop { table } => {
+                let table = table.clone();
+                let dropped = db.with_session_connection(&session.id, move |conn| {
+                    Ok(Self::drop_retention(conn, &table))
+                }).await??;
+                bool_value(dropped, binary)
+            }
+            RetentionCall::Run => {
+                let deleted = Self::run_policies(db, &session.id, crate::config::CONFIG.retention_batch_size).await? as i64;
+                if binary { deleted.to_be_bytes().to_vec() } else { deleted.to_string().into_bytes() }
+            }
+        };
+        debug!("Retention call {:?}", call);
+
+        if !skip_row_description {
+            framed.send(BackendMessage::RowDescription(Self::field_descriptions(call))).await
+                .map_err(PgSqliteError::Io)?;
+        }
+        framed.send(BackendMessage::DataRow(vec![Some(value)])).await
+            .map_err(PgSqliteError::Io)?;
+        framed.send(BackendMessage::CommandComplete {
+            tag: "SELECT 1".to_string(),
+        }).await.map_err(PgSqliteError::Io)
+    }
+
+    /// Add or replace the table's policy, returning whether it changed
+    fn set_retention(conn: &Connection, name: &str, column: &str, max_age: &str) -> Result<bool, PgSqliteError> {
+        let Some(table) = table_name(conn, &sqlite_name(name))? else {
+            return Err(pg_error("42P01", format!("relation \"{name}\" does not exist")));
+        };
+        let Some((column, _)) = Self::time_column(conn, &table, column)? else {
+            return Err(pg_error("42703", format!("column \"{column}\" of relation \"{table}\" does not exist")));
+        };
+        let interval = Interval::parse(max_age).map_err(|message| pg_error("22007", message))?;
+        let now = chrono::Utc::now().timestamp_micros();
+        if interval.negate().add_to_timestamp(now).is_none_or(|cutoff| cutoff >= now) {
+            return Err(pg_error("22023", format!("retention max age must be a positive interval, not \"{max_age}\"")));
+        }
+        let changed = conn.execute(
+            "INSERT INTO __pgsqlite_retention_policies (table_name, column_name, max_age) VALUES (?1, ?2, ?3)
+             ON CONFLICT (table_name) DO UPDATE SET column_name = excluded.column_name, max_age = excluded.max_age
+             WHERE column_name IS NOT excluded.column_name OR max_age IS NOT excluded.max_age",
+            [&table, &column, &interval.to_string()],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Remove the table's policy, returning whether it had one
+    fn drop_retention(conn: &Connection, name: &str) -> Result<bool, PgSqliteError> {
+        let Some(table) = table_name(conn, &sqlite_name(name))? else {
+            return Err(pg_error("42P01", format!("relation \"{name}\" does not exist")));
+        };
+        Ok(conn.execute("DELETE FROM __pgsqlite_retention_policies WHERE table_name = ?1", [&table])? > 0)
+    }
+
+    /// The column's name as the table spells it and whether it holds dates rather
+    /// than timestamps; a column of another type is an error
+    fn time_column(conn: &Connection, table: &str, column: &str) -> Result<Option<(String, bool)>, PgSqliteError> {
+        let found: Option<(String, Option<String>)> = conn.query_row(
+            "SELECT c.name, s.pg_type FROM pragma_table_info(?1) c
+             LEFT JOIN __pgsqlite_schema s ON s.table_name = ?1 AND s.column_name = c.name COLLATE NOCASE
+             WHERE c.name = ?2 COLLATE NOCASE",
+            [table, column],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).optional()?;
+        let Some((column, pg_type)) = found else {
+            return Ok(None);
+        };
+        let oid = pg_type.as_deref().map(|t| SchemaTypeMapper::pg_type_string_to_oid(&t.trim().to_uppercase()));
+        match oid.and_then(PgType::from_oid) {
+            Some(PgType::Timestamp | PgType::Timestamptz) => Ok(Some((column, false))),
+            Some(PgType::Date) => Ok(Some((column, true))),
+            _ => Err(pg_error(
+                "42804",
+                format!("column \"{column}\" of relation \"{table}\" must be of type timestamp, timestamptz or date"),
+            )),
+        }
+    }
+
+    /// Forget the policies of tables that no longer exist
+    pub fn prune_retention_policies(conn: &Connection) -> rusqlite::Result<()> {
+        if !has_retention_policies(conn)? {
+            return Ok(());
+        }
+        conn.execute(
+            "DELETE FROM __pgsqlite_retention_policies
+             WHERE table_name NOT IN (SELECT name FROM sqlite_master WHERE type = 'table')",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Run every policy of the database for the scheduler, on a connection of its own
+    /// that is opened the first time and kept for the next runs
+    pub async fn run_scheduled(db: &Arc<dyn SessionStorage>, scheduler: &Uuid, batch_size: usize) -> Result<u64, PgSqliteError> {
+        if db.session_connection(scheduler).is_none() {
+            db.create_reserved_session_connection(*scheduler).await?;
+        }
+        Self::run_policies(db, scheduler, batch_size).await
+    }
+
+    /// Delete the expired rows of every policy on the session's connection, `batch_size`
+    /// rows per statement, returning how many were deleted. A failing policy doesn't
+    /// stop the others; the first error is returned once they have run.
+    pub async fn run_policies(db: &Arc<dyn SessionStorage>, session_id: &Uuid, batch_size: usize) -> Result<u64, PgSqliteError> {
+        let policies = db.with_session_connection(session_id, |conn| {
+            if !has_retention_policies(conn)? {
+                return Ok(Vec::new());
+            }
+            let mut stmt = conn.prepare("SELECT table_name, column_name, max_age FROM __pgsqlite_retention_policies ORDER BY table_name")?;
+            stmt.query_map([], |row| Ok(RetentionPolicy {
+                table: row.get(0)?,
+                column: row.get(1)?,
+                max_age: row.get(2)?,
+            }))?.collect::<rusqlite::Result<Vec<_>>>()
+        }).await?;
+
+        let mut deleted = 0;
+        let mut first_error = None;
+        for policy in policies {
+            match Self::run_policy(db, session_id, &policy, batch_size.max(1)).await {
+                Ok(count) => deleted += count,
+                Err(e) => {
+                    warn!("Retention policy of {} failed: {}", policy.table, e);
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(deleted),
+        }
+    }
+
+    async fn run_policy(db: &Arc<dyn SessionStorage>, session_id: &Uuid, policy: &RetentionPolicy, batch_size: usize) -> Result<u64, PgSqliteError> {
+        let started = chrono::Utc::now().timestamp_micros();
+        let interval = Interval::parse(&policy.max_age).map_err(|message| pg_error("22007", message))?;
+        let Some(cutoff) = interval.negate().add_to_timestamp(started) else {
+            return Err(pg_error("22008", format!("retention max age \"{}\" is out of range", policy.max_age)));
+        };
+        let (table, column) = (policy.table.clone(), policy.column.clone());
+        let Some((_, is_date)) = db.with_session_connection(session_id, move |conn| {
+            Ok(Self::time_column(conn, &table, &column))
+        }).await?? else {
+            return Err(pg_error("42703", format!("column \"{}\" of relation \"{}\" does not exist", policy.column, policy.table)));
+        };
+        // Dates are stored as days since the epoch, timestamps as microseconds
+        let cutoff = if is_date { cutoff.div_euclid(MICROS_PER_DAY) } else { cutoff };
+        let expired = format!("{} WHERE {} < {cutoff}", quote_identifier(&policy.table), quote_identifier(&policy.column));
+
+        let progress = ProgressGuard::start(ProgressCommand::Retention, Some(policy.table.clone()), "counting expired rows");
+        let counted = db.query_with_session(&format!("SELECT count(*) FROM {expired}"), session_id).await?;
+        let total = counted.rows.first()
+            .and_then(|row| row.first().cloned().flatten())
+            .and_then(|count| String::from_utf8(count).ok()?.parse::<u64>().ok())
+            .unwrap_or(0);
+        progress.set_total(total);
+        progress.set_phase("deleting expired rows");
+
+        // Each batch commits on its own (outside a transaction block), so writers of the
+        // table wait for one batch at most
+        let delete = format!("DELETE FROM {} WHERE rowid IN (SELECT rowid FROM {expired} LIMIT {batch_size})", quote_identifier(&policy.table));
+        let mut deleted = 0;
+        loop {
+            let count = db.execute_with_session(&delete, session_id).await?.rows_affected as u64;
+            progress.advance(count, 0);
+            deleted += count;
+            if count < batch_size as u64 {
+                break;
+            }
+            tokio::task::yield_now().await;
+        }
+
+        let table = policy.table.clone();
+        db.with_session_connection(session_id, move |conn| {
+            conn.execute(
+                "UPDATE __pgsqlite_retention_policies
+                 SET last_run = ?2, last_deleted = ?3, total_deleted = total_deleted + ?3
+                 WHERE table_name = ?1",
+                rusqlite::params![table, started, deleted as i64],
+            )
+        }).await?;
+        if deleted > 0 {
+            info!("Retention policy of {} deleted {} rows older than {}", policy.table, deleted, policy.max_age);
+        }
+        Ok(deleted)
+    }
+}
+
+fn bool_value(value: bool, binary: bool) -> Vec<u8> {
+    match (binary, value) {
+        (true, value) => vec![u8::from(value)],
+        (false, true) => b"t".to_vec(),
+        (false, false) => b"f".to_vec(),
+    }
+}
+
+
+/// Databases opened before migration 32 have no policies table
+fn has_retention_policies(conn: &Connection) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '__pgsqlite_retention_policies')",
+        [],
+        |row| row.get(0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_retention_call() {
+        assert_eq!(
+            RetentionHandler::parse_retention_call("SELECT pgsqlite.set_retention('events', 'created_at', '30 days');"),
+            Some(RetentionCall::Set { table: "events".to_string(), column: "created_at".to_string(), max_age: "30 days".to_string() })
+        );
+        assert_eq!(
+            RetentionHandler::parse_retention_call("select * from PGSQLITE.DROP_RETENTION( 'o''s' )"),
+            Some(RetentionCall::Drop { table: "o's".to_string() })
+        );
+        assert_eq!(RetentionHandler::parse_retention_call("SELECT pgsqlite.run_retention()"), Some(RetentionCall::Run));
+        assert_eq!(RetentionHandler::parse_retention_call("SELECT pgsqlite.run_retention(), 1"), None);
+        assert_eq!(RetentionHandler::parse_retention_call("SELECT pgsqlite.set_retention('events', 'created_at')"), None);
+    }
+}
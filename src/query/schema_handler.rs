@@ -0,0 +1,977 @@
+use crate::cache::CatalogCache;
+use crate::ddl::EnumDdlHandler;
+use crate::error::PgError;
+use crate::metadata::{EnumTriggers, IdentityColumns, Namespaces};
+use crate::protocol::BackendMessage;
+use crate::protocol::messages::NoticeResponse;
+use crate::query::sql_utils::quote_identifier;
+use crate::session::{SessionState, SessionStorage};
+use crate::PgSqliteError;
+use futures::SinkExt;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rusqlite::Connection;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio_util::codec::Framed;
+use tracing::debug;
+
+static CREATE_SCHEMA_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^\s*CREATE\s+SCHEMA\s+(IF\s+NOT\s+EXISTS\s+)?(?:("(?:[^"]|"")+"|[\w$]+)\s*)?(?:AUTHORIZATION\s+("(?:[^"]|"")+"|[\w$]+)\s*)?;?\s*$"#).unwrap()
+});
+
+static DROP_SCHEMA_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)^\s*DROP\s+SCHEMA\s+(IF\s+EXISTS\s+)?(.+?)(?:\s+(CASCADE|RESTRICT))?\s*;?\s*$").unwrap()
+});
+
+/// The search_path a session starts with
+pub const DEFAULT_SEARCH_PATH: &str = "\"$user\", public";
+
+/// Handles `CREATE SCHEMA` and `DROP SCHEMA`, and maps schema-qualified names to
+/// the SQLite names of the objects, as described in [`Namespaces`].
+///
+/// Before a statement runs, `schema.table` becomes the table's SQLite name and
+/// unqualified table names are looked up along the session's search_path. A table
+/// reference that is renamed keeps its visible name as an alias, so
+/// `SELECT items.id FROM tenant1.items` reads `FROM tenant1__items AS items`.
+///
+/// Temporary tables belong to the session's own schema, `pg_temp_<backend pid>`,
+/// which is searched before the search_path as in PostgreSQL and can be written
+/// `pg_temp`. SQLite keeps them in the temp database of the session's connection.
+pub struct SchemaHandler;
+
+/// A parsed schema statement
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaStatement {
+    Create {
+        name: String,
+        if_not_exists: bool,
+    },
+    Drop {
+        names: Vec<String>,
+        if_exists: bool,
+        cascade: bool,
+    },
+}
+
+/// What name resolution needs to know about the catalog, reloaded when its generation moves on
+#[derive(Debug)]
+pub struct NamespaceSnapshot {
+    generation: u64,
+    /// Schemas created with CREATE SCHEMA
+    schemas: Vec<(String, i64)>,
+    /// Lowercased SQLite names of the tables, views and indexes, when there are schemas to look them up in
+    relations: HashSet<String>,
+    /// The session's temporary schema
+    temp_schema: String,
+    /// Whether the session has temporary relations, which are then among `relations`
+    has_temp: bool,
+}
+
+impl NamespaceSnapshot {
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+}
+
+impl SchemaHandler {
+    /// Whether a query is a CREATE or DROP that mentions SCHEMA
+    fn might_be_schema_ddl(query: &str) -> bool {
+        let trimmed = query.trim_start();
+        let is_ddl = trimmed.get(..6).is_some_and(|prefix| prefix.eq_ignore_ascii_case("CREATE"))
+            || trimmed.get(..4).is_some_and(|prefix| prefix.eq_ignore_ascii_case("DROP"));
+        is_ddl && query.as_bytes().windows(6).any(|w| w.eq_ignore_ascii_case(b"SCHEMA"))
+    }
+
+    pub fn parse_schema(query: &str) -> Option<SchemaStatement> {
+        if !Self::might_be_schema_ddl(query) {
+            return None;
+        }
+        if let Some(caps) = CREATE_SCHEMA_PATTERN.captures(query) {
+            // CREATE SCHEMA AUTHORIZATION joe names the schema after its owner
+            let name = caps.get(2).or(caps.get(3))?;
+            return Some(SchemaStatement::Create {
+                name: identifier(name.as_str()),
+                if_not_exists: caps.get(1).is_some(),
+            });
+        }
+        let caps = DROP_SCHEMA_PATTERN.captures(query)?;
+        Some(SchemaStatement::Drop {
+            names: caps[2].split(',').map(identifier).collect(),
+            if_exists: caps.get(1).is_some(),
+            cascade: caps.get(3).is_some_and(|c| c.as_str().eq_ignore_ascii_case("CASCADE")),
+        })
+    }
+
+    pub async fn handle_schema<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        statement: &SchemaStatement,
+    ) -> Result<(), PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        let tag = match statement {
+            SchemaStatement::Create { name, if_not_exists } => {
+                if name.starts_with("pg_") {
+                    return Err(pg_error("42939", format!("unacceptable schema name \"{name}\"")));
+                }
+                let exists = name == "information_schema"
+                    || db.with_session_connection(&session.id, |conn| Namespaces::oid(conn, name)).await?.is_some();
+                match exists {
+                    true if *if_not_exists => debug!("Schema {} already exists, skipping", name),
+                    true => return Err(pg_error("42P06", format!("schema \"{name}\" already exists"))),
+                    false => {
+                        let oid = db.with_session_connection(&session.id, |conn| Namespaces::create(conn, name)).await?;
+                        debug!("Created schema {} with OID {}", name, oid);
+                    }
+                }
+                "CREATE SCHEMA"
+            }
+            SchemaStatement::Drop { names, if_exists, cascade } => {
+                db.require_local_data("DROP SCHEMA")?;
+                let mut dropped = Vec::new();
+                for name in names {
+                    match name.as_str() {
+                        "pg_catalog" | "information_schema" => {
+                            return Err(pg_error("2BP01", format!("cannot drop schema {name} because it is required by the database system")));
+                        }
+                        "public" => {
+                            return Err(pg_error("0A000", "dropping schema public is not supported".to_string()));
+                        }
+                        _ => {}
+                    }
+                    let exists = db.with_session_connection(&session.id, |conn| Namespaces::oid(conn, name)).await?.is_some();
+                    if !exists {
+                        if *if_exists {
+                            continue;
+                        }
+                        return Err(pg_error("3F000", format!("schema \"{name}\" does not exist")));
+                    }
+                    let objects = db.with_session_connection_mut(&session.id, |conn| Ok(Self::drop_schema(conn, name, *cascade))).await??;
+                    dropped.extend(objects);
+                }
+                if let Some(notice) = cascade_notice(&dropped) {
+                    framed.send(BackendMessage::NoticeResponse(notice)).await.map_err(PgSqliteError::Io)?;
+                }
+                "DROP SCHEMA"
+            }
+        };
+
+        framed.send(BackendMessage::CommandComplete { tag: tag.to_string() }).await
+            .map_err(PgSqliteError::Io)
+    }
+
+    /// Drop a schema, and with `cascade` its views, tables and types, returning how
+    /// PostgreSQL would describe each object dropped along with it
+    fn drop_schema(conn: &mut Connection, schema: &str, cascade: bool) -> Result<Vec<String>, PgSqliteError> {
+        let relations = Namespaces::relations(conn, schema)?;
+        let types = Namespaces::types(conn, schema)?;
+        if !cascade && (!relations.is_empty() || !types.is_empty()) {
+            return Err(pg_error("2BP01", format!("cannot drop schema {schema} because other objects depend on it")));
+        }
+
+        conn.execute_batch("SAVEPOINT __pgsqlite_drop_schema")?;
+        match Self::drop_schema_objects(conn, schema, &relations, &types) {
+            Ok(dropped) => {
+                conn.execute_batch("RELEASE __pgsqlite_drop_schema")?;
+                Ok(dropped)
+            }
+            Err(e) => {
+                if let Err(rollback_error) = conn.execute_batch("ROLLBACK TO __pgsqlite_drop_schema; RELEASE __pgsqlite_drop_schema") {
+                    debug!("Failed to roll back DROP SCHEMA: {}", rollback_error);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    fn drop_schema_objects(conn: &mut Connection, schema: &str, relations: &[(String, String)], types: &[String]) -> Result<Vec<String>, PgSqliteError> {
+        let mut dropped = Vec::new();
+        let visible = |name: &str| format!("{schema}.{}", &name[Namespaces::qualify(schema, "").len()..]);
+        for (kind, name) in relations {
+            if kind == "view" {
+                conn.execute_batch(&format!("DROP VIEW {}", quote_identifier(name)))?;
+                crate::query::ViewHandler::forget_column_types(conn, name)?;
+            } else {
+                conn.execute_batch(&format!("DROP TABLE {}", quote_identifier(name)))?;
+                EnumTriggers::clean_enum_usage_for_table(conn, name)?;
+                IdentityColumns::clean_identity_columns_for_table(conn, name)?;
+                crate::query::executor::forget_table_schema_info(name);
+            }
+            dropped.push(format!("{kind} {}", visible(name)));
+        }
+        crate::query::CommentHandler::prune_relation_comments(conn)?;
+        crate::query::TriggerHandler::prune_triggers(conn)?;
+        crate::query::AuditHandler::prune_audits(conn)?;
+        crate::query::SoftDeleteHandler::prune_soft_deletes(conn)?;
+        crate::query::RetentionHandler::prune_retention_policies(conn)?;
+        crate::query::ClusterHandler::prune_clustered_indexes(conn)?;
+        crate::query::AutoAnalyze::prune_autoanalyze(conn)?;
+        crate::query::MaskHandler::prune_column_masks(conn)?;
+        crate::validator::ExclusionConstraint::prune(conn)?;
+        for name in types {
+            EnumDdlHandler::handle_enum_ddl(conn, &format!("DROP TYPE {name} CASCADE"))?;
+            dropped.push(format!("type {}", visible(name)));
+        }
+        Namespaces::drop(conn, schema)?;
+        Ok(dropped)
+    }
+
+    /// Map the schema-qualified names in a query to SQLite names and resolve the
+    /// unqualified table names along the session's search_path
+    pub async fn qualify_names<'q>(
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        query: &'q str,
+    ) -> Result<Cow<'q, str>, PgSqliteError> {
+        let snapshot = Self::snapshot(db, session).await?;
+        // Without schemas or temporary tables the database only has public names to unqualify,
+        // unless the statement makes a temporary relation
+        let mentions = |word: &[u8]| query.as_bytes().windows(word.len()).any(|w| w.eq_ignore_ascii_case(word));
+        if snapshot.schemas.is_empty() && !snapshot.has_temp && !mentions(b"public") && !mentions(b"temp") {
+            return Ok(Cow::Borrowed(query));
+        }
+        let search_path = session.search_path().await;
+        let resolver = NameResolver::new(&snapshot, &search_path, &session.user);
+        Ok(match resolver.rewrite(query)? {
+            Some(rewritten) => {
+                debug!("Qualified names: {} -> {}", query, rewritten);
+                Cow::Owned(rewritten)
+            }
+            None => Cow::Borrowed(query),
+        })
+    }
+
+    /// The session's view of the schemas and relations, reloaded after catalog changes
+    async fn snapshot(db: &Arc<dyn SessionStorage>, session: &Arc<SessionState>) -> Result<Arc<NamespaceSnapshot>, PgSqliteError> {
+        let generation = CatalogCache::generation();
+        if let Some(snapshot) = session.namespace_snapshot(generation) {
+            return Ok(snapshot);
+        }
+        let temp_schema = Namespaces::temp_schema(session.backend_pid);
+        let snapshot = Arc::new(db.with_session_connection(&session.id, |conn| {
+            let schemas = Namespaces::list(conn)?;
+            let has_temp: bool = conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM sqlite_temp_master WHERE name GLOB ?1)",
+                [Namespaces::qualify(&temp_schema, "*")],
+                |row| row.get(0),
+            )?;
+            let relations = if schemas.is_empty() && !has_temp {
+                HashSet::new()
+            } else {
+                let mut stmt = conn.prepare(
+                    "SELECT lower(name) FROM sqlite_master WHERE type IN ('table', 'view', 'index')
+                     UNION ALL SELECT lower(name) FROM sqlite_temp_master WHERE type IN ('table', 'view', 'index')",
+                )?;
+                stmt.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?
+            };
+            Ok(NamespaceSnapshot { generation, schemas, relations, temp_schema, has_temp })
+        }).await?);
+        session.set_namespace_snapshot(snapshot.clone());
+        Ok(snapshot)
+    }
+
+    /// The value `SET search_path` stores: the schema names, quoted where needed
+    pub fn normalize_search_path(value: &str) -> String {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("DEFAULT") {
+            return DEFAULT_SEARCH_PATH.to_string();
+        }
+        split_list(value).iter()
+            .map(|schema| {
+                let name = match schema.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')) {
+                    Some(literal) => literal.replace("''", "'"),
+                    None => identifier(schema),
+                };
+                let plain = name.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+                    && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '$');
+                if plain { name } else { quote_identifier(&name) }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// The schema names of a stored search_path
+    pub fn parse_search_path(value: &str) -> Vec<String> {
+        split_list(value).iter().map(|schema| identifier(schema)).filter(|s| !s.is_empty()).collect()
+    }
+}
+
+/// The notice PostgreSQL sends when DROP ... CASCADE takes other objects with it
+fn cascade_notice(dropped: &[String]) -> Option<NoticeResponse> {
+    let (message, detail) = match dropped {
+        [] => return None,
+        [object] => (format!("drop cascades to {object}"), None),
+        objects => (
+            format!("drop cascades to {} other objects", objects.len()),
+            Some(objects.iter().map(|o| format!("drop cascades to {o}")).collect::<Vec<_>>().join("\n")),
+        ),
+    };
+    Some(NoticeResponse {
+        severity: "NOTICE".to_string(),
+        code: "00000".to_string(),
+        message,
+        detail,
+        hint: None,
+        position: None,
+        where_: None,
+    })
+}
+
+/// Keywords after which a name refers to a relation
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Position {
+    /// A relation being created, placed in the first schema of the search_path
+    Create,
+    /// An existing relation; `alias` when it may be followed by an alias
+    Reference { alias: bool },
+    /// The name of an index being created, which goes to the schema of its table
+    IndexName,
+    /// The new name of a relation being renamed, which stays in its schema
+    RenameTarget,
+}
+
+/// Words that end a table reference, so a renamed table needs an alias before them
+const ALIAS_STOPS: [&str; 29] = [
+    "WHERE", "ON", "USING", "JOIN", "INNER", "LEFT", "RIGHT", "FULL", "CROSS", "NATURAL", "OUTER",
+    "GROUP", "ORDER", "LIMIT", "OFFSET", "HAVING", "WINDOW", "UNION", "INTERSECT", "EXCEPT",
+    "SET", "RETURNING", "FOR", "FETCH", "DEFAULT", "VALUES", "SELECT", "NOT", "INDEXED",
+];
+
+/// Words that start a clause; a comma continues the clause before it
+const CLAUSES: [&str; 26] = [
+    "SELECT", "FROM", "JOIN", "WHERE", "GROUP", "ORDER", "HAVING", "LIMIT", "OFFSET", "UNION",
+    "INTERSECT", "EXCEPT", "SET", "VALUES", "RETURNING", "ON", "USING", "TABLE", "INTO", "UPDATE",
+    "TRUNCATE", "WINDOW", "WITH", "ADD", "INDEX", "VIEW",
+];
+
+/// Words before an opening parenthesis that make it more than a function call
+const NOT_CALLS: [&str; 20] = [
+    "FROM", "JOIN", "IN", "EXISTS", "AS", "ON", "LATERAL", "ANY", "ALL", "SOME", "WHERE", "AND",
+    "OR", "NOT", "SELECT", "VALUES", "USING", "UNION", "RETURNING", "THEN",
+];
+
+/// One part of a possibly qualified name
+#[derive(Debug)]
+struct Part<'q> {
+    /// The part as written
+    text: &'q str,
+    /// The name it stands for: quotes removed, lowercased if it was not quoted
+    name: String,
+    quoted: bool,
+}
+
+impl Part<'_> {
+    /// The keyword this part could be, if it was not quoted
+    fn keyword(&self) -> Option<String> {
+        (!self.quoted).then(|| self.text.to_ascii_uppercase())
+    }
+
+    /// The part as SQLite should store it: its spelling if unquoted, its contents if quoted
+    fn spelling(&self) -> String {
+        if self.quoted { self.name.clone() } else { self.text.to_string() }
+    }
+}
+
+#[derive(Debug)]
+enum Token<'q> {
+    Name { start: usize, end: usize, parts: Vec<Part<'q>>, star: bool },
+    Punct { pos: usize, ch: u8 },
+}
+
+impl Token<'_> {
+    fn keyword(&self) -> Option<String> {
+        match self {
+            Token::Name { parts, .. } if parts.len() == 1 => parts[0].keyword(),
+            _ => None,
+        }
+    }
+
+    fn is_punct(&self, c: u8) -> bool {
+        matches!(self, Token::Punct { ch, .. } if *ch == c)
+    }
+}
+
+/// A parenthesized group, or the statement itself
+#[derive(Default)]
+struct Group {
+    /// Opened by a function call, where FROM is part of the arguments
+    call: bool,
+    /// The last clause keyword seen in the group
+    clause: Option<String>,
+}
+
+/// Rewrites the names of one statement
+struct NameResolver<'a> {
+    snapshot: &'a NamespaceSnapshot,
+    /// Existing schemas of the search_path, in order
+    search_path: Vec<String>,
+}
+
+impl<'a> NameResolver<'a> {
+    fn new(snapshot: &'a NamespaceSnapshot, search_path: &[String], user: &str) -> Self {
+        let search_path = search_path.iter()
+            .map(|schema| if schema == "$user" { user.to_string() } else { schema.clone() })
+            .filter(|schema| schema == "public" || snapshot.schemas.iter().any(|(s, _)| s == schema))
+            .fold(Vec::new(), |mut path, schema| {
+                if !path.contains(&schema) {
+                    path.push(schema);
+                }
+                path
+            });
+        Self { snapshot, search_path }
+    }
+
+    fn is_schema(&self, name: &str) -> bool {
+        name == "public" || self.is_temp_schema(name) || self.snapshot.schemas.iter().any(|(s, _)| s == name)
+    }
+
+    /// `pg_temp` stands for the session's own temporary schema
+    fn is_temp_schema(&self, name: &str) -> bool {
+        name == "pg_temp" || name == self.snapshot.temp_schema
+    }
+
+    fn exists(&self, name: &str) -> bool {
+        self.snapshot.relations.contains(&name.to_lowercase())
+    }
+
+    /// The statement with its names rewritten, None if nothing changed
+    fn rewrite(&self, query: &str) -> Result<Option<String>, PgSqliteError> {
+        let tokens = tokenize(query);
+        let keywords: Vec<Option<String>> = tokens.iter().map(Token::keyword).collect();
+        let head: Vec<&str> = keywords.iter().take(4).map(|k| k.as_deref().unwrap_or("")).collect();
+        let create = head.first() == Some(&"CREATE");
+        let alter = head.first() == Some(&"ALTER");
+        let create_index = create && head.contains(&"INDEX");
+        // CREATE [GLOBAL | LOCAL] TEMP[ORARY] TABLE or VIEW
+        let temporary = create && head.iter().skip(1).take(2).any(|k| matches!(*k, "TEMP" | "TEMPORARY"))
+            && head.iter().any(|k| matches!(*k, "TABLE" | "VIEW"));
+        let on_table = create_index || head.contains(&"TRIGGER");
+        let ctes = common_table_names(&tokens);
+
+        let mut edits: Vec<(usize, usize, String)> = Vec::new();
+        let mut groups = vec![Group::default()];
+        // The relation a CREATE makes, so references to it in the same statement resolve
+        let mut created: Option<(String, String)> = None;
+        // The schema of the relation an ALTER or CREATE INDEX works on
+        let mut target_schema: Option<String> = None;
+        let mut index_name: Option<(usize, usize, String)> = None;
+        let mut rename_target: Option<(usize, usize, String)> = None;
+
+        for (k, token) in tokens.iter().enumerate() {
+            let prev = k.checked_sub(1).map(|p| &tokens[p]);
+            let prev_keyword = k.checked_sub(1).and_then(|p| keywords[p].as_deref());
+            let (start, end, parts, star) = match token {
+                Token::Punct { ch: b'(', .. } => {
+                    let call = matches!(prev, Some(Token::Name { .. })) && !prev_keyword.is_some_and(|kw| NOT_CALLS.contains(&kw));
+                    groups.push(Group { call, clause: None });
+                    continue;
+                }
+                Token::Punct { ch: b')', .. } => {
+                    if groups.len() > 1 {
+                        groups.pop();
+                    }
+                    continue;
+                }
+                Token::Punct { .. } => continue,
+                Token::Name { start, end, parts, star } => (*start, *end, parts, *star),
+            };
+            let group = groups.last_mut().expect("the statement group is never popped");
+            if let Some(keyword) = &keywords[k] {
+                if CLAUSES.contains(&keyword.as_str()) {
+                    group.clause = Some(keyword.clone());
+                    continue;
+                }
+                // The IF [NOT] EXISTS between a keyword and the name it introduces
+                if matches!(keyword.as_str(), "IF" | "NOT" | "EXISTS") {
+                    continue;
+                }
+            }
+
+            // schema.table.column and schema.table.* lose the schema, leaving the alias the table gets
+            if parts.len() >= 3 || (parts.len() == 2 && star) {
+                if self.is_schema(&parts[0].name) {
+                    let rest = parts[1..].iter().map(|p| p.text).collect::<Vec<_>>().join(".");
+                    edits.push((start, end, rest));
+                }
+                continue;
+            }
+
+            if parts.len() == 1 && keywords[k].as_deref() == Some("CURRENT_SCHEMA")
+                && tokens.get(k + 1).is_some_and(|t| t.is_punct(b'('))
+                && let Some(Token::Punct { pos, ch: b')' }) = tokens.get(k + 2) {
+                let current = self.search_path.first().filter(|s| *s != "public");
+                if let Some(schema) = current {
+                    edits.push((start, pos + 1, format!("'{}'", schema.replace('\'', "''"))));
+                } else if self.search_path.is_empty() {
+                    edits.push((start, pos + 1, "NULL".to_string()));
+                }
+                continue;
+            }
+
+            let position = match prev_keyword {
+                Some("FROM") if !group.call => Some(Position::Reference { alias: true }),
+                Some("JOIN") | Some("UPDATE") => Some(Position::Reference { alias: true }),
+                Some("TABLE") | Some("VIEW") | Some("SEQUENCE") if create => Some(Position::Create),
+                Some("EXISTS") if create && matches!(group.clause.as_deref(), Some("TABLE") | Some("VIEW")) => Some(Position::Create),
+                Some("INDEX") | Some("CONCURRENTLY") if create_index => Some(Position::IndexName),
+                Some("EXISTS") if create_index => Some(Position::IndexName),
+                Some("ON") if on_table => Some(Position::Reference { alias: false }),
+                Some("TO") if alter && k >= 2 && keywords[k - 2].as_deref() == Some("RENAME") => Some(Position::RenameTarget),
+                Some("INTO") | Some("TABLE") | Some("VIEW") | Some("INDEX") | Some("SEQUENCE") | Some("TRUNCATE")
+                | Some("REFERENCES") | Some("ONLY") | Some("COPY") | Some("EXISTS") | Some("CONCURRENTLY") => Some(Position::Reference { alias: false }),
+                None if prev.is_some_and(|p| p.is_punct(b',')) => match group.clause.as_deref() {
+                    Some("FROM") if !group.call => Some(Position::Reference { alias: true }),
+                    Some("TABLE") | Some("TRUNCATE") | Some("VIEW") | Some("INDEX") => Some(Position::Reference { alias: false }),
+                    _ => None,
+                },
+                _ => None,
+            };
+
+            let resolved = match (parts.len(), position) {
+                // pg_temp also qualifies temporary functions, which keep their name
+                (2, _) if self.is_schema(&parts[0].name) && (position.is_some() || !self.is_temp_schema(&parts[0].name)) => {
+                    let schema = if self.is_temp_schema(&parts[0].name) {
+                        self.snapshot.temp_schema.clone()
+                    } else {
+                        parts[0].name.clone()
+                    };
+                    if temporary && position == Some(Position::Create) && schema != self.snapshot.temp_schema {
+                        return Err(pg_error("42P16", "cannot create temporary relation in non-temporary schema".to_string()));
+                    }
+                    Some((Namespaces::qualify(&schema, &parts[1].spelling()), schema))
+                }
+                (1, Some(Position::Create)) if temporary => {
+                    let schema = &self.snapshot.temp_schema;
+                    Some((Namespaces::qualify(schema, &parts[0].spelling()), schema.clone()))
+                }
+                (1, Some(Position::Create)) => {
+                    let Some(schema) = self.search_path.first() else {
+                        return Err(pg_error("3F000", "no schema has been selected to create in".to_string()));
+                    };
+                    Some((Namespaces::qualify(schema, &parts[0].spelling()), schema.clone()))
+                }
+                (1, Some(Position::Reference { alias })) => {
+                    // FROM generate_series(...) is a function, REFERENCES t (id) is not
+                    let called = alias && tokens.get(k + 1).is_some_and(|t| t.is_punct(b'('));
+                    if called || ctes.contains(&parts[0].name) {
+                        None
+                    } else {
+                        let name = parts[0].spelling();
+                        let temp = (self.snapshot.has_temp || temporary).then_some(&self.snapshot.temp_schema);
+                        temp.into_iter().chain(&self.search_path).find_map(|schema| {
+                            let qualified = Namespaces::qualify(schema, &name);
+                            let is_created = created.as_ref().is_some_and(|(visible, _)| visible.eq_ignore_ascii_case(&name));
+                            (self.exists(&qualified) || (is_created && created.as_ref().is_some_and(|(_, q)| *q == qualified)))
+                                .then(|| (qualified, schema.clone()))
+                        })
+                    }
+                }
+                (1, Some(Position::IndexName)) => {
+                    index_name = Some((start, end, parts[0].spelling()));
+                    None
+                }
+                (1, Some(Position::RenameTarget)) => {
+                    rename_target = Some((start, end, parts[0].spelling()));
+                    None
+                }
+                _ => None,
+            };
+            let Some((qualified, schema)) = resolved else {
+                continue;
+            };
+
+            if position == Some(Position::Create) && created.is_none() {
+                created = Some((parts.last().map(Part::spelling).unwrap_or_default(), qualified.clone()));
+                // CREATE TABLE pg_temp.name makes a temporary table too
+                if !temporary && schema == self.snapshot.temp_schema
+                    && let Some(Token::Name { start: kind, .. }) = tokens.iter().take(4).find(|t| matches!(t.keyword().as_deref(), Some("TABLE" | "VIEW"))) {
+                    edits.push((*kind, *kind, "TEMP ".to_string()));
+                }
+            }
+            if (alter || (on_table && position == Some(Position::Reference { alias: false }))) && target_schema.is_none() {
+                target_schema = Some(schema.clone());
+            }
+
+            let visible = parts.last().expect("names have at least one part");
+            if qualified == visible.spelling() && !visible.quoted && parts.len() == 1 {
+                continue;
+            }
+            let mut replacement = quote_if_needed(&qualified, parts.iter().any(|p| p.quoted));
+            if position == Some(Position::Reference { alias: true }) && schema != "public" && !has_alias(&tokens, &keywords, k) {
+                replacement = format!("{replacement} AS {}", visible.text);
+            }
+            edits.push((start, end, replacement));
+        }
+
+        // Indexes and renamed relations stay in the schema of their table
+        if let Some(schema) = target_schema.filter(|s| s != "public") {
+            for (start, end, name) in index_name.into_iter().chain(rename_target) {
+                edits.push((start, end, quote_if_needed(&Namespaces::qualify(&schema, &name), false)));
+            }
+        }
+
+        if edits.is_empty() {
+            return Ok(None);
+        }
+        edits.sort_by_key(|(start, _, _)| *start);
+        let mut rewritten = String::with_capacity(query.len() + 16 * edits.len());
+        let mut copied = 0;
+        for (start, end, replacement) in edits {
+            rewritten.push_str(&query[copied..start]);
+            rewritten.push_str(&replacement);
+            copied = end;
+        }
+        rewritten.push_str(&query[copied..]);
+        Ok(Some(rewritten))
+    }
+}
+
+/// Whether the table reference at `k` is already followed by an alias
+fn has_alias(tokens: &[Token], keywords: &[Option<String>], k: usize) -> bool {
+    match tokens.get(k + 1) {
+        Some(Token::Name { parts, .. }) if parts.len() == 1 => {
+            keywords[k + 1].as_deref().is_none_or(|kw| kw == "AS" || !ALIAS_STOPS.contains(&kw))
+        }
+        _ => false,
+    }
+}
+
+/// Names the statement's WITH clause defines, which hide tables of the same name
+fn common_table_names(tokens: &[Token]) -> HashSet<String> {
+    let mut names = HashSet::new();
+    for (k, token) in tokens.iter().enumerate() {
+        let Token::Name { parts, .. } = token else { continue };
+        if parts.len() != 1 {
+            continue;
+        }
+        // name AS (...) or name (columns) AS (...)
+        let mut next = k + 1;
+        if tokens.get(next).is_some_and(|t| t.is_punct(b'(')) {
+            match tokens[next..].iter().position(|t| t.is_punct(b')')) {
+                Some(close) => next += close + 1,
+                None => continue,
+            }
+        }
+        let is_as = tokens.get(next).and_then(Token::keyword).as_deref() == Some("AS");
+        if is_as && tokens.get(next + 1).is_some_and(|t| t.is_punct(b'(')) {
+            names.insert(parts[0].name.clone());
+        }
+    }
+    names
+}
+
+/// Split a statement into names and punctuation, skipping literals, comments and dollar-quoted bodies
+fn tokenize(query: &str) -> Vec<Token<'_>> {
+    let bytes = query.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        match b {
+            b'\'' => i = skip_string(bytes, i, false),
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                i = bytes[i..].iter().position(|&c| c == b'\n').map_or(bytes.len(), |n| i + n);
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = query[i + 2..].find("*/").map_or(bytes.len(), |n| i + 2 + n + 2);
+            }
+            b'$' => {
+                let tag_len = bytes[i + 1..].iter().position(|&c| !(c.is_ascii_alphanumeric() || c == b'_'));
+                match tag_len {
+                    Some(len) if bytes[i + 1 + len] == b'$' && !bytes.get(i + 1).is_some_and(u8::is_ascii_digit) => {
+                        let tag = &query[i..i + len + 2];
+                        i = query[i + tag.len()..].find(tag).map_or(bytes.len(), |n| i + tag.len() + n + tag.len());
+                    }
+                    _ => i += 1,
+                }
+            }
+            b'0'..=b'9' => {
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'.' || bytes[i] == b'_') {
+                    i += 1;
+                }
+            }
+            b'"' => i = read_name(query, i, &mut tokens),
+            _ if is_identifier_start(b) => {
+                // E'...', B'...' and the like are literals with a prefix
+                let word_end = i + bytes[i..].iter().position(|&c| !is_identifier_char(c)).unwrap_or(bytes.len() - i);
+                if bytes.get(word_end) == Some(&b'\'') && word_end - i <= 2 {
+                    let escapes = query[i..word_end].eq_ignore_ascii_case("e");
+                    i = skip_string(bytes, word_end, escapes);
+                } else {
+                    i = read_name(query, i, &mut tokens);
+                }
+            }
+            _ if b.is_ascii_whitespace() => i += 1,
+            _ => {
+                tokens.push(Token::Punct { pos: i, ch: b });
+                i += 1;
+            }
+        }
+    }
+    tokens
+}
+
+/// Read a possibly qualified name starting at `start`, returning where it ends
+fn read_name<'q>(query: &'q str, start: usize, tokens: &mut Vec<Token<'q>>) -> usize {
+    let bytes = query.as_bytes();
+    let mut parts = Vec::new();
+    let mut i = start;
+    let mut star = false;
+    loop {
+        let part_start = i;
+        if bytes[i] == b'"' {
+            i += 1;
+            while i < bytes.len() {
+                if bytes[i] == b'"' {
+                    if bytes.get(i + 1) == Some(&b'"') {
+                        i += 2;
+                        continue;
+                    }
+                    break;
+                }
+                i += 1;
+            }
+            i = (i + 1).min(bytes.len());
+            let text = &query[part_start..i];
+            parts.push(Part { text, name: identifier(text), quoted: true });
+        } else {
+            while i < bytes.len() && is_identifier_char(bytes[i]) {
+                i += 1;
+            }
+            let text = &query[part_start..i];
+            parts.push(Part { text, name: text.to_lowercase(), quoted: false });
+        }
+        match (bytes.get(i), bytes.get(i + 1)) {
+            (Some(b'.'), Some(&next)) if next == b'"' || is_identifier_start(next) => i += 1,
+            (Some(b'.'), Some(b'*')) => {
+                star = true;
+                break;
+            }
+            _ => break,
+        }
+    }
+    tokens.push(Token::Name { start, end: i, parts, star });
+    i
+}
+
+/// Skip a string literal starting at the quote at `start`
+fn skip_string(bytes: &[u8], start: usize, escapes: bool) -> usize {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if escapes => i += 2,
+            b'\'' if bytes.get(i + 1) == Some(&b'\'') => i += 2,
+            b'\'' => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+fn is_identifier_start(b: u8) -> bool {
+    b.is_ascii_alphabetic() || b == b'_' || b >= 0x80
+}
+
+fn is_identifier_char(b: u8) -> bool {
+    is_identifier_start(b) || b.is_ascii_digit() || b == b'$'
+}
+
+/// Split a comma-separated list of names, leaving commas inside quotes alone
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    for c in value.chars() {
+        match (c, quote) {
+            ('"' | '\'', None) => {
+                quote = Some(c);
+                current.push(c);
+            }
+            (c, Some(q)) if c == q => {
+                quote = None;
+                current.push(c);
+            }
+            (',', None) => items.push(std::mem::take(&mut current).trim().to_string()),
+            _ => current.push(c),
+        }
+    }
+    items.push(current.trim().to_string());
+    items.retain(|item| !item.is_empty());
+    items
+}
+
+/// The name an identifier stands for: quotes removed, lowercased if it was not quoted
+fn identifier(name: &str) -> String {
+    let name = name.trim();
+    match name.strip_prefix('"').and_then(|n| n.strip_suffix('"')) {
+        Some(quoted) => quoted.replace("\"\"", "\""),
+        None => name.to_lowercase(),
+    }
+}
+
+
+/// A SQLite name as it goes into a statement, quoted when it was or has to be
+fn quote_if_needed(name: &str, quoted: bool) -> String {
+    let plain = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if quoted || !plain { quote_identifier(name) } else { name.to_string() }
+}
+
+fn pg_error(code: &str, message: String) -> PgSqliteError {
+    PgSqliteError::Validation(PgError::Generic { code: code.to_string(), message })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(relations: &[&str]) -> NamespaceSnapshot {
+        NamespaceSnapshot {
+            generation: 0,
+            schemas: vec![("tenant1".to_string(), 20000), ("Tenant2".to_string(), 20001)],
+            relations: relations.iter().map(|r| r.to_lowercase()).collect(),
+            temp_schema: "pg_temp_7".to_string(),
+            has_temp: relations.iter().any(|r| r.starts_with("pg_temp_7__")),
+        }
+    }
+
+    fn rewrite(query: &str, search_path: &str, relations: &[&str]) -> String {
+        let snapshot = snapshot(relations);
+        let path = SchemaHandler::parse_search_path(search_path);
+        let resolver = NameResolver::new(&snapshot, &path, "postgres");
+        resolver.rewrite(query).unwrap().unwrap_or_else(|| query.to_string())
+    }
+
+    #[test]
+    fn test_parse_schema() {
+        assert_eq!(SchemaHandler::parse_schema("CREATE SCHEMA IF NOT EXISTS Tenant1;"), Some(SchemaStatement::Create {
+            name: "tenant1".to_string(),
+            if_not_exists: true,
+        }));
+        assert_eq!(SchemaHandler::parse_schema("create schema \"Tenant2\" authorization postgres"), Some(SchemaStatement::Create {
+            name: "Tenant2".to_string(),
+            if_not_exists: false,
+        }));
+        assert_eq!(SchemaHandler::parse_schema("CREATE SCHEMA AUTHORIZATION joe").unwrap(), SchemaStatement::Create {
+            name: "joe".to_string(),
+            if_not_exists: false,
+        });
+        assert_eq!(SchemaHandler::parse_schema("DROP SCHEMA IF EXISTS a, \"B\" CASCADE;"), Some(SchemaStatement::Drop {
+            names: vec!["a".to_string(), "B".to_string()],
+            if_exists: true,
+            cascade: true,
+        }));
+        assert_eq!(SchemaHandler::parse_schema("DROP SCHEMA a RESTRICT").unwrap(), SchemaStatement::Drop {
+            names: vec!["a".to_string()],
+            if_exists: false,
+            cascade: false,
+        });
+        assert_eq!(SchemaHandler::parse_schema("CREATE TABLE schema_versions (id INT)"), None);
+        assert_eq!(SchemaHandler::parse_schema("SELECT * FROM information_schema.schemata"), None);
+    }
+
+    #[test]
+    fn test_search_path_values() {
+        assert_eq!(SchemaHandler::normalize_search_path("Tenant1, public"), "tenant1, public");
+        assert_eq!(SchemaHandler::normalize_search_path("'Tenant2', \"$user\",public"), "\"Tenant2\", \"$user\", public");
+        assert_eq!(SchemaHandler::normalize_search_path("default"), DEFAULT_SEARCH_PATH);
+        assert_eq!(SchemaHandler::parse_search_path(DEFAULT_SEARCH_PATH), vec!["$user", "public"]);
+        assert_eq!(SchemaHandler::parse_search_path("\"Tenant2\", public"), vec!["Tenant2", "public"]);
+    }
+
+    #[test]
+    fn test_qualified_names() {
+        let path = DEFAULT_SEARCH_PATH;
+        assert_eq!(rewrite("SELECT * FROM public.items WHERE public.items.id = 1", path, &[]), "SELECT * FROM items WHERE items.id = 1");
+        assert_eq!(
+            rewrite("SELECT tenant1.items.name, i.* FROM tenant1.items JOIN \"Tenant2\".orders o ON o.item = tenant1.items.id", path, &[]),
+            "SELECT items.name, i.* FROM tenant1__items AS items JOIN \"Tenant2__orders\" o ON o.item = items.id",
+        );
+        assert_eq!(
+            rewrite("SELECT tenant1.items.* FROM tenant1.items, tenant1.orders AS o WHERE true", path, &[]),
+            "SELECT items.* FROM tenant1__items AS items, tenant1__orders AS o WHERE true",
+        );
+        assert_eq!(
+            rewrite("INSERT INTO tenant1.items (id) VALUES (1) RETURNING id", path, &[]),
+            "INSERT INTO tenant1__items (id) VALUES (1) RETURNING id",
+        );
+        assert_eq!(rewrite("UPDATE tenant1.items SET n = 1", path, &[]), "UPDATE tenant1__items AS items SET n = 1");
+        assert_eq!(
+            rewrite("CREATE TABLE tenant1.items (id INT, kind tenant1.mood, owner INT REFERENCES tenant1.users (id))", path, &[]),
+            "CREATE TABLE tenant1__items (id INT, kind tenant1__mood, owner INT REFERENCES tenant1__users (id))",
+        );
+        // Literals, comments, function bodies and unknown schemas are left alone
+        let untouched = "SELECT 'tenant1.items', $$tenant1.items$$, E'\\'tenant1.x', other.items, pg_catalog.pg_class.oid -- tenant1.items\n FROM t";
+        assert_eq!(rewrite(untouched, path, &[]), untouched);
+    }
+
+    #[test]
+    fn test_search_path_resolution() {
+        let relations = ["tenant1__items", "items", "orders", "tenant1__items_name_idx"];
+        let path = "tenant1, public";
+        assert_eq!(
+            rewrite("SELECT items.id FROM items JOIN orders ON orders.item = items.id WHERE EXTRACT(YEAR FROM created) > 1", path, &relations),
+            "SELECT items.id FROM tenant1__items AS items JOIN orders ON orders.item = items.id WHERE EXTRACT(YEAR FROM created) > 1",
+        );
+        assert_eq!(rewrite("SELECT * FROM items", DEFAULT_SEARCH_PATH, &relations), "SELECT * FROM items");
+        assert_eq!(
+            rewrite("WITH items AS (SELECT 1) SELECT * FROM items", path, &relations),
+            "WITH items AS (SELECT 1) SELECT * FROM items",
+        );
+        assert_eq!(
+            rewrite("CREATE TABLE IF NOT EXISTS nodes (id INT PRIMARY KEY, parent INT REFERENCES nodes (id), item INT REFERENCES items)", path, &relations),
+            "CREATE TABLE IF NOT EXISTS tenant1__nodes (id INT PRIMARY KEY, parent INT REFERENCES tenant1__nodes (id), item INT REFERENCES tenant1__items)",
+        );
+        assert_eq!(
+            rewrite("CREATE UNIQUE INDEX IF NOT EXISTS items_code ON items (code)", path, &relations),
+            "CREATE UNIQUE INDEX IF NOT EXISTS tenant1__items_code ON tenant1__items (code)",
+        );
+        assert_eq!(rewrite("ALTER TABLE items RENAME TO goods", path, &relations), "ALTER TABLE tenant1__items RENAME TO tenant1__goods");
+        assert_eq!(rewrite("DROP TABLE IF EXISTS items, orders", path, &relations), "DROP TABLE IF EXISTS tenant1__items, orders");
+        assert_eq!(rewrite("DROP INDEX items_name_idx", path, &relations), "DROP INDEX tenant1__items_name_idx");
+        assert_eq!(rewrite("DROP INDEX CONCURRENTLY items_name_idx", path, &relations), "DROP INDEX CONCURRENTLY tenant1__items_name_idx");
+        assert_eq!(rewrite("SELECT current_schema()", path, &relations), "SELECT 'tenant1'");
+        assert_eq!(rewrite("SELECT current_schema()", DEFAULT_SEARCH_PATH, &relations), "SELECT current_schema()");
+
+        // With no schema of the search_path existing there is nowhere to create a table
+        let snapshot = snapshot(&relations);
+        let resolver = NameResolver::new(&snapshot, &["missing".to_string()], "postgres");
+        assert!(resolver.rewrite("CREATE TABLE t (id INT)").is_err());
+        assert_eq!(resolver.rewrite("SELECT current_schema()").unwrap().as_deref(), Some("SELECT NULL"));
+    }
+
+    #[test]
+    fn test_temp_relations() {
+        let path = DEFAULT_SEARCH_PATH;
+        assert_eq!(
+            rewrite("CREATE TEMP TABLE staging (id INT PRIMARY KEY, parent INT REFERENCES staging (id))", path, &[]),
+            "CREATE TEMP TABLE pg_temp_7__staging (id INT PRIMARY KEY, parent INT REFERENCES pg_temp_7__staging (id))",
+        );
+        assert_eq!(rewrite("CREATE TABLE pg_temp.staging (id INT)", path, &[]), "CREATE TEMP TABLE pg_temp_7__staging (id INT)");
+        assert_eq!(
+            rewrite("CREATE LOCAL TEMPORARY TABLE IF NOT EXISTS staging (id INT)", "tenant1, public", &[]),
+            "CREATE LOCAL TEMPORARY TABLE IF NOT EXISTS pg_temp_7__staging (id INT)",
+        );
+        let snapshot = snapshot(&[]);
+        let resolver = NameResolver::new(&snapshot, &["public".to_string()], "postgres");
+        assert!(resolver.rewrite("CREATE TEMP TABLE tenant1.staging (id INT)").is_err());
+
+        // Temporary tables hide permanent ones of the same name
+        let relations = ["items", "pg_temp_7__items", "orders"];
+        assert_eq!(
+            rewrite("SELECT * FROM items JOIN orders ON orders.item = items.id", path, &relations),
+            "SELECT * FROM pg_temp_7__items AS items JOIN orders ON orders.item = items.id",
+        );
+        assert_eq!(rewrite("SELECT * FROM public.items", path, &relations), "SELECT * FROM items");
+        assert_eq!(rewrite("DELETE FROM pg_temp.items", path, &relations), "DELETE FROM pg_temp_7__items AS items");
+        assert_eq!(rewrite("CREATE INDEX items_id ON items (id)", path, &relations), "CREATE INDEX pg_temp_7__items_id ON pg_temp_7__items (id)");
+        assert_eq!(rewrite("DROP TABLE items", path, &relations), "DROP TABLE pg_temp_7__items");
+        let function = "CREATE FUNCTION pg_temp.rate(code text) RETURNS numeric LANGUAGE sql RETURN 1";
+        assert_eq!(rewrite(function, path, &relations), function);
+        assert_eq!(rewrite("SELECT pg_temp.rate('EUR')", path, &relations), "SELECT pg_temp.rate('EUR')");
+    }
+}
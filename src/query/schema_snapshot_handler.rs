@@ -0,0 +1,431 @@
+use crate::metadata::Namespaces;
+use crate::metadata::namespaces::PUBLIC_OID;
+use crate::protocol::{BackendMessage, FieldDescription};
+use crate::query::trigger_handler::pg_error;
+use crate::session::{SessionState, SessionStorage};
+use crate::types::PgType;
+use crate::PgSqliteError;
+use futures::SinkExt;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio_util::codec::Framed;
+use tracing::debug;
+
+static SCHEMA_SNAPSHOT_CALL_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)^\s*SELECT\s+(?:\*\s+FROM\s+)?pgsqlite\.schema_snapshot\s*\(\s*(?:'((?:[^']|'')*)')?\s*\)\s*;?\s*$").unwrap()
+});
+
+static SCHEMA_DIFF_CALL_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)^\s*SELECT\s+(?:\*\s+FROM\s+)?pgsqlite\.schema_diff\s*\(\s*(\d+|'(?:[^']|'')*')\s*,\s*(\d+|'(?:[^']|'')*')\s*\)\s*;?\s*$").unwrap()
+});
+
+/// Columns of a schema_diff() row
+const DIFF_COLUMNS: [&str; 6] = ["object_type", "table_name", "object_name", "change", "old_definition", "new_definition"];
+
+/// A `pgsqlite.schema_snapshot()` or `pgsqlite.schema_diff()` call
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaSnapshotCall {
+    /// Save the current schema, under an optional label
+    Snapshot { label: Option<String> },
+    /// Compare two saved snapshots, each named by its id or label
+    Diff { old: String, new: String },
+}
+
+/// The tables of a database as a snapshot stores them, keyed by visible name
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+struct Snapshot {
+    tables: BTreeMap<String, TableSnapshot>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+struct TableSnapshot {
+    /// (name, definition) in column order
+    columns: Vec<(String, String)>,
+    constraints: BTreeMap<String, String>,
+    indexes: BTreeMap<String, String>,
+}
+
+/// A row of schema_diff(): something added, removed or changed between two snapshots
+#[derive(Debug, Clone, PartialEq)]
+struct SchemaChange {
+    object_type: &'static str,
+    table: String,
+    name: String,
+    change: &'static str,
+    old: Option<String>,
+    new: Option<String>,
+}
+
+/// Handles `SELECT pgsqlite.schema_snapshot(['<label>'])` and
+/// `SELECT * FROM pgsqlite.schema_diff(<a>, <b>)`.
+///
+/// A snapshot records the columns, constraints and indexes of every table in
+/// __pgsqlite_schema_snapshots and returns its id. schema_diff() compares two
+/// snapshots, named by id or label, and returns a row for every table, column,
+/// constraint and index that was added, removed or changed from the first to the
+/// second, so a migration can be checked against what it was meant to do.
+pub struct SchemaSnapshotHandler;
+
+impl SchemaSnapshotHandler {
+    /// Whether a query names one of the pgsqlite.schema_* functions
+    pub fn might_be_schema_snapshot_call(query: &str) -> bool {
+        query.as_bytes().windows(16).any(|w| w.eq_ignore_ascii_case(b"pgsqlite.schema_"))
+    }
+
+    /// A standalone `pgsqlite.schema_snapshot()` or `pgsqlite.schema_diff()` call
+    pub fn parse_schema_snapshot_call(query: &str) -> Option<SchemaSnapshotCall> {
+        if !Self::might_be_schema_snapshot_call(query) {
+            return None;
+        }
+        if let Some(caps) = SCHEMA_SNAPSHOT_CALL_PATTERN.captures(query) {
+            let label = caps.get(1).map(|label| label.as_str().replace("''", "'"));
+            return Some(SchemaSnapshotCall::Snapshot { label });
+        }
+        let caps = SCHEMA_DIFF_CALL_PATTERN.captures(query)?;
+        let argument = |i: usize| {
+            let argument = &caps[i];
+            argument.strip_prefix('\'')
+                .and_then(|quoted| quoted.strip_suffix('\''))
+                .map_or_else(|| argument.to_string(), |label| label.replace("''", "'"))
+        };
+        Some(SchemaSnapshotCall::Diff { old: argument(1), new: argument(2) })
+    }
+
+    /// The row description of the result, also reported by Parse in the extended protocol
+    pub fn field_descriptions(call: &SchemaSnapshotCall) -> Vec<FieldDescription> {
+        let column = |(i, name): (usize, &str), pg_type: PgType, type_size: i16| FieldDescription {
+            name: name.to_string(),
+            table_oid: 0,
+            column_id: i as i16 + 1,
+            type_oid: pg_type.to_oid(),
+            type_size,
+            type_modifier: -1,
+            format: 0,
+        };
+        match call {
+            SchemaSnapshotCall::Snapshot { .. } => vec![column((0, "schema_snapshot"), PgType::Int8, 8)],
+            SchemaSnapshotCall::Diff { .. } => DIFF_COLUMNS.iter().copied().enumerate()
+                .map(|column_name| column(column_name, PgType::Text, -1))
+                .collect(),
+        }
+    }
+
+    /// Returns the id of the new snapshot, or a row per change between two snapshots,
+    /// the id in binary when the portal asked for it
+    pub async fn handle_schema_snapshot_call<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        call: &SchemaSnapshotCall,
+        skip_row_description: bool,
+        binary: bool,
+    ) -> Result<(), PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        let rows: Vec<Vec<Option<Vec<u8>>>> = match call {
+            SchemaSnapshotCall::Snapshot { label } => {
+                let label = label.clone();
+                let id = db.with_session_connection(&session.id, move |conn| {
+                    Ok(Self::save_snapshot(conn, label.as_deref()))
+                }).await??;
+                debug!("Saved schema snapshot {}", id);
+                let value = if binary { id.to_be_bytes().to_vec() } else { id.to_string().into_bytes() };
+                vec![vec![Some(value)]]
+            }
+            SchemaSnapshotCall::Diff { old, new } => {
+                let (old, new) = (old.clone(), new.clone());
+                let changes = db.with_session_connection(&session.id, move |conn| {
+                    Ok(Self::load_snapshot(conn, &old).and_then(|old| Ok(diff(&old, &Self::load_snapshot(conn, &new)?))))
+                }).await??;
+                changes.into_iter()
+                    .map(|change| vec![
+                        Some(change.object_type.as_bytes().to_vec()),
+                        Some(change.table.into_bytes()),
+                        Some(change.name.into_bytes()),
+                        Some(change.change.as_bytes().to_vec()),
+                        change.old.map(String::into_bytes),
+                        change.new.map(String::into_bytes),
+                    ])
+                    .collect()
+            }
+        };
+
+        if !skip_row_description {
+            framed.send(BackendMessage::RowDescription(Self::field_descriptions(call))).await
+                .map_err(PgSqliteError::Io)?;
+        }
+        let count = rows.len();
+        for row in rows {
+            framed.send(BackendMessage::DataRow(row)).await
+                .map_err(PgSqliteError::Io)?;
+        }
+        framed.send(BackendMessage::CommandComplete {
+            tag: format!("SELECT {count}"),
+        }).await.map_err(PgSqliteError::Io)
+    }
+
+    /// Record the current schema, returning the id of the snapshot
+    fn save_snapshot(conn: &Connection, label: Option<&str>) -> Result<i64, PgSqliteError> {
+        if let Some(label) = label {
+            if label.is_empty() || label.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(pg_error("22023", format!("invalid schema snapshot label \"{label}\"")));
+            }
+            let exists: bool = conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM __pgsqlite_schema_snapshots WHERE label = ?1)",
+                [label],
+                |row| row.get(0),
+            )?;
+            if exists {
+                return Err(pg_error("42710", format!("schema snapshot \"{label}\" already exists")));
+            }
+        }
+        let snapshot = serde_json::to_string(&Self::snapshot(conn)?)
+            .map_err(|e| PgSqliteError::Protocol(format!("Failed to encode schema snapshot: {e}")))?;
+        conn.execute(
+            "INSERT INTO __pgsqlite_schema_snapshots (label, taken_at, snapshot) VALUES (?1, ?2, ?3)",
+            rusqlite::params![label, chrono::Utc::now().timestamp_micros(), snapshot],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// A saved snapshot, named by its id or label
+    fn load_snapshot(conn: &Connection, name: &str) -> Result<Snapshot, PgSqliteError> {
+        let saved: Option<String> = conn.query_row(
+            "SELECT snapshot FROM __pgsqlite_schema_snapshots WHERE label = ?1 OR CAST(snapshot_id AS TEXT) = ?1",
+            [name],
+            |row| row.get(0),
+        ).optional()?;
+        let Some(saved) = saved else {
+            return Err(pg_error("42704", format!("schema snapshot \"{name}\" does not exist")));
+        };
+        serde_json::from_str(&saved)
+            .map_err(|e| PgSqliteError::Protocol(format!("Failed to decode schema snapshot {name}: {e}")))
+    }
+
+    /// The columns, constraints and indexes of every table
+    fn snapshot(conn: &Connection) -> rusqlite::Result<Snapshot> {
+        let schemas = Namespaces::list(conn)?;
+        let visible = |name: &str| match Namespaces::split(&schemas, name) {
+            (PUBLIC_OID, name) => name.to_string(),
+            (oid, rest) => schemas.iter()
+                .find(|(_, schema_oid)| *schema_oid == oid)
+                .map_or_else(|| name.to_string(), |(schema, _)| format!("{schema}.{rest}")),
+        };
+
+        let tables: Vec<String> = conn.prepare(
+            "SELECT name FROM sqlite_master WHERE type = 'table'
+               AND name NOT LIKE 'sqlite_%' AND name NOT LIKE '__pgsqlite_%' AND name NOT LIKE 'pg\\_%' ESCAPE '\\'
+             ORDER BY name",
+        )?.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+
+        let mut snapshot = Snapshot::default();
+        for table in tables {
+            let mut columns_stmt = conn.prepare(
+                "SELECT c.name, lower(COALESCE(s.pg_type, c.type)), c.\"notnull\", c.dflt_value FROM pragma_table_info(?1) c
+                 LEFT JOIN __pgsqlite_schema s ON s.table_name = ?1 AND s.column_name = c.name COLLATE NOCASE
+                 ORDER BY c.cid",
+            )?;
+            let columns = columns_stmt.query_map([&table], |row| {
+                let mut definition: String = row.get(1)?;
+                if row.get::<_, bool>(2)? {
+                    definition.push_str(" NOT NULL");
+                }
+                if let Some(default) = row.get::<_, Option<String>>(3)? {
+                    definition.push_str(&format!(" DEFAULT {default}"));
+                }
+                Ok((row.get(0)?, definition))
+            })?.collect::<rusqlite::Result<Vec<_>>>()?;
+
+            let mut constraints = BTreeMap::new();
+            let mut indexes = BTreeMap::new();
+            let mut index_stmt = conn.prepare(
+                "SELECT indexname, origin, columns, indexdef FROM __pgsqlite_index_catalog WHERE tablename = ?1",
+            )?;
+            let catalog = index_stmt.query_map([&table], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<String>>(2)?, row.get::<_, String>(3)?))
+            })?.collect::<rusqlite::Result<Vec<_>>>()?;
+            for (name, origin, columns, definition) in catalog {
+                match origin.as_str() {
+                    "pk" => constraints.insert(name, format!("PRIMARY KEY ({})", columns.unwrap_or_default())),
+                    "u" => constraints.insert(name, format!("UNIQUE ({})", columns.unwrap_or_default())),
+                    _ => indexes.insert(name, definition),
+                };
+            }
+            constraints.extend(foreign_keys(conn, &table)?);
+            if has_check_constraints(conn)? {
+                let mut check_stmt = conn.prepare("SELECT conname, consrc FROM __pgsqlite_check_constraints WHERE tablename = ?1")?;
+                let checks = check_stmt.query_map([&table], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+                for check in checks {
+                    let (name, source) = check?;
+                    constraints.insert(name, format!("CHECK ({source})"));
+                }
+            }
+
+            snapshot.tables.insert(visible(&table), TableSnapshot { columns, constraints, indexes });
+        }
+        Ok(snapshot)
+    }
+}
+
+/// The foreign keys of a table, named and written the way pg_constraint shows them
+fn foreign_keys(conn: &Connection, table: &str) -> rusqlite::Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT fk.id, fk.\"table\", fk.\"from\",
+                COALESCE(fk.\"to\", (SELECT name FROM pragma_table_info(fk.\"table\") WHERE pk = fk.seq + 1)),
+                fk.on_update, fk.on_delete
+         FROM pragma_foreign_key_list(?1) fk ORDER BY fk.id, fk.seq",
+    )?;
+    let rows = stmt.query_map([table], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+            row.get::<_, String>(4)?,
+            row.get::<_, String>(5)?,
+        ))
+    })?.collect::<rusqlite::Result<Vec<_>>>()?;
+
+    // A key over several columns is a row per column
+    let mut keys = BTreeMap::new();
+    for (id, parent, from, to, on_update, on_delete) in rows {
+        let key = keys.entry(id).or_insert_with(|| (parent, Vec::new(), Vec::new(), on_update, on_delete));
+        key.1.push(from);
+        key.2.push(to);
+    }
+    Ok(keys.into_values()
+        .map(|(parent, from, to, on_update, on_delete)| {
+            let mut definition = format!("FOREIGN KEY ({}) REFERENCES {parent}({})", from.join(", "), to.join(", "));
+            if on_update != "NO ACTION" {
+                definition.push_str(&format!(" ON UPDATE {on_update}"));
+            }
+            if on_delete != "NO ACTION" {
+                definition.push_str(&format!(" ON DELETE {on_delete}"));
+            }
+            (format!("{table}_{}_fkey", from.join("_")), definition)
+        })
+        .collect())
+}
+
+/// What changed from `old` to `new`, table by table. A table that was added or removed
+/// is one row listing its columns, rather than a row for each of its parts.
+fn diff(old: &Snapshot, new: &Snapshot) -> Vec<SchemaChange> {
+    let mut changes = Vec::new();
+    let table_names: std::collections::BTreeSet<&String> = old.tables.keys().chain(new.tables.keys()).collect();
+    for table in table_names {
+        let (old_table, new_table) = (old.tables.get(table), new.tables.get(table));
+        let (old_table, new_table) = match (old_table, new_table) {
+            (Some(old_table), Some(new_table)) => (old_table, new_table),
+            (old_table, new_table) => {
+                let definition = |t: &TableSnapshot| t.columns.iter()
+                    .map(|(name, definition)| format!("{name} {definition}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                changes.push(SchemaChange {
+                    object_type: "table",
+                    table: table.clone(),
+                    name: table.clone(),
+                    change: if new_table.is_some() { "added" } else { "removed" },
+                    old: old_table.map(definition),
+                    new: new_table.map(definition),
+                });
+                continue;
+            }
+        };
+        let columns = |t: &TableSnapshot| t.columns.iter().cloned().collect::<BTreeMap<_, _>>();
+        for (object_type, old_parts, new_parts) in [
+            ("column", columns(old_table), columns(new_table)),
+            ("constraint", old_table.constraints.clone(), new_table.constraints.clone()),
+            ("index", old_table.indexes.clone(), new_table.indexes.clone()),
+        ] {
+            let names: std::collections::BTreeSet<&String> = old_parts.keys().chain(new_parts.keys()).collect();
+            for name in names {
+                let (old_definition, new_definition) = (old_parts.get(name), new_parts.get(name));
+                let change = match (old_definition, new_definition) {
+                    (None, Some(_)) => "added",
+                    (Some(_), None) => "removed",
+                    (old_definition, new_definition) if old_definition != new_definition => "changed",
+                    _ => continue,
+                };
+                changes.push(SchemaChange {
+                    object_type,
+                    table: table.clone(),
+                    name: name.clone(),
+                    change,
+                    old: old_definition.cloned(),
+                    new: new_definition.cloned(),
+                });
+            }
+        }
+    }
+    changes
+}
+
+fn has_check_constraints(conn: &Connection) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '__pgsqlite_check_constraints')",
+        [],
+        |row| row.get(0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_schema_snapshot_call() {
+        assert_eq!(
+            SchemaSnapshotHandler::parse_schema_snapshot_call("SELECT pgsqlite.schema_snapshot();"),
+            Some(SchemaSnapshotCall::Snapshot { label: None })
+        );
+        assert_eq!(
+            SchemaSnapshotHandler::parse_schema_snapshot_call("select pgsqlite.SCHEMA_SNAPSHOT('before v2')"),
+            Some(SchemaSnapshotCall::Snapshot { label: Some("before v2".to_string()) })
+        );
+        assert_eq!(
+            SchemaSnapshotHandler::parse_schema_snapshot_call("SELECT * FROM pgsqlite.schema_diff(1, 'it''s')"),
+            Some(SchemaSnapshotCall::Diff { old: "1".to_string(), new: "it's".to_string() })
+        );
+        assert_eq!(SchemaSnapshotHandler::parse_schema_snapshot_call("SELECT * FROM pgsqlite.schema_diff(1)"), None);
+    }
+
+    #[test]
+    fn test_diff() {
+        let table = |columns: &[(&str, &str)], indexes: &[(&str, &str)]| TableSnapshot {
+            columns: columns.iter().map(|(name, definition)| (name.to_string(), definition.to_string())).collect(),
+            constraints: BTreeMap::new(),
+            indexes: indexes.iter().map(|(name, definition)| (name.to_string(), definition.to_string())).collect(),
+        };
+        let old = Snapshot {
+            tables: BTreeMap::from([
+                ("a".to_string(), table(&[("id", "integer"), ("name", "text")], &[("a_name", "CREATE INDEX a_name ON public.a USING btree (name)")])),
+                ("b".to_string(), table(&[("id", "integer")], &[])),
+            ]),
+        };
+        let new = Snapshot {
+            tables: BTreeMap::from([
+                ("a".to_string(), table(&[("id", "bigint"), ("email", "text NOT NULL")], &[])),
+                ("c".to_string(), table(&[("id", "integer")], &[])),
+            ]),
+        };
+        let changes = diff(&old, &new);
+        let changes: Vec<(&str, &str, &str, &str)> = changes.iter()
+            .map(|c| (c.object_type, c.table.as_str(), c.name.as_str(), c.change))
+            .collect();
+        assert_eq!(changes, vec![
+            ("column", "a", "email", "added"),
+            ("column", "a", "id", "changed"),
+            ("column", "a", "name", "removed"),
+            ("index", "a", "a_name", "removed"),
+            ("table", "b", "b", "removed"),
+            ("table", "c", "c", "added"),
+        ]);
+        assert!(diff(&new, &new).is_empty());
+    }
+}
@@ -9,15 +9,15 @@ use once_cell::sync::Lazy;
 use tracing::{debug, info};
 
 static SET_TIMEZONE_PATTERN: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"(?i)^\s*SET\s+TIME\s*ZONE\s+(.+)$").unwrap()
+    Regex::new(r"(?i)^\s*SET\s+(?:(SESSION|LOCAL)\s+)?TIME\s*ZONE\s+(.+)$").unwrap()
 });
 
 static SET_PARAMETER_PATTERN: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"(?i)^\s*SET\s+(\w+)\s+(?:TO|=)\s+(.+)$").unwrap()
+    Regex::new(r"(?i)^\s*SET\s+(?:(SESSION|LOCAL)\s+)?(\w+(?:\.\w+)?)\s*(?:\s+TO\s+|=)\s*(.+)$").unwrap()
 });
 
 static SHOW_PARAMETER_PATTERN: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"(?i)^\s*SHOW\s+(.+?)\s*$").unwrap()
+    Regex::new(r"(?i)^\s*SHOW\s+(.+?)\s*;?\s*$").unwrap()
 });
 
 pub struct SetHandler;
@@ -67,11 +67,31 @@ impl SetHandler {
         let trimmed = query.trim();
         debug!("Handling SET command: {}", trimmed);
         
+        // SET LOCAL only lasts until the end of the transaction block, and outside one it does nothing
+        let local = SET_TIMEZONE_PATTERN.captures(trimmed).or_else(|| SET_PARAMETER_PATTERN.captures(trimmed))
+            .and_then(|caps| caps.get(1))
+            .is_some_and(|scope| scope.as_str().eq_ignore_ascii_case("LOCAL"));
+        if local && !session.in_transaction().await {
+            framed.send(BackendMessage::NoticeResponse(crate::protocol::messages::NoticeResponse {
+                severity: "WARNING".to_string(),
+                code: "25P01".to_string(), // no_active_sql_transaction
+                message: "SET LOCAL can only be used in transaction blocks".to_string(),
+                detail: None,
+                hint: None,
+                position: None,
+                where_: None,
+            })).await.map_err(PgSqliteError::Io)?;
+            framed.send(BackendMessage::CommandComplete {
+                tag: "SET".to_string()
+            }).await.map_err(PgSqliteError::Io)?;
+            return Ok(());
+        }
+
         // Handle SET TIME ZONE
         if let Some(caps) = SET_TIMEZONE_PATTERN.captures(trimmed) {
-            let timezone = caps[1].trim().trim_matches('\'').trim_matches('"');
+            let timezone = caps[2].trim().trim_matches('\'').trim_matches('"');
             info!("Setting timezone to: {}", timezone);
-            Self::set_timezone(session, timezone).await?;
+            Self::set_timezone(session, timezone, local).await?;
             
             framed.send(BackendMessage::CommandComplete { 
                 tag: "SET".to_string() 
@@ -82,13 +102,109 @@ impl SetHandler {
         
         // Handle general SET parameter
         if let Some(caps) = SET_PARAMETER_PATTERN.captures(trimmed) {
-            let param_name = caps[1].to_uppercase();
-            let param_value = caps[2].trim().trim_matches('\'').trim_matches('"');
-            
+            let param_name = caps[2].to_uppercase();
+            let mut param_value = caps[3].trim().trim_end_matches(';').trim_end().trim_matches('\'').trim_matches('"');
+
+            if param_name == "BYTEA_OUTPUT" && crate::types::ByteaFormat::from_setting(param_value).is_none() {
+                return Err(PgSqliteError::Validation(crate::error::PgError::Generic {
+                    code: "22023".to_string(),
+                    message: format!("invalid value for parameter \"bytea_output\": \"{param_value}\""),
+                }));
+            }
+
+            if param_name == "PGSQLITE.TRACE" {
+                let enabled = match param_value.to_lowercase().as_str() {
+                    "on" | "true" | "yes" | "1" => true,
+                    "off" | "false" | "no" | "0" => false,
+                    _ => return Err(PgSqliteError::Validation(crate::error::PgError::Generic {
+                        code: "22023".to_string(),
+                        message: "parameter \"pgsqlite.trace\" requires a Boolean value".to_string(),
+                    })),
+                };
+                param_value = if enabled { "on" } else { "off" };
+            }
+
+            if param_name == "PGSQLITE.AUTO_INDEX_FOREIGN_KEYS" {
+                param_value = match param_value.to_lowercase().as_str() {
+                    "on" | "true" | "yes" | "1" => "on",
+                    "off" | "false" | "no" | "0" => "off",
+                    _ => return Err(PgSqliteError::Validation(crate::error::PgError::Generic {
+                        code: "22023".to_string(),
+                        message: "parameter \"pgsqlite.auto_index_foreign_keys\" requires a Boolean value".to_string(),
+                    })),
+                };
+            }
+
+            if param_name == "PGSQLITE.SOFT_DELETE" {
+                param_value = match param_value.to_lowercase().as_str() {
+                    "on" | "true" | "yes" | "1" => "on",
+                    "off" | "false" | "no" | "0" => "off",
+                    _ => return Err(PgSqliteError::Validation(crate::error::PgError::Generic {
+                        code: "22023".to_string(),
+                        message: "parameter \"pgsqlite.soft_delete\" requires a Boolean value".to_string(),
+                    })),
+                };
+            }
+
+            if param_name == "SYNCHRONOUS_COMMIT" {
+                param_value = match param_value.to_lowercase().as_str() {
+                    "on" | "true" | "yes" | "1" => "on",
+                    "off" | "false" | "no" | "0" => "off",
+                    "local" => "local",
+                    "remote_write" => "remote_write",
+                    "remote_apply" => "remote_apply",
+                    _ => return Err(PgSqliteError::Validation(crate::error::PgError::Generic {
+                        code: "22023".to_string(),
+                        message: format!("invalid value for parameter \"synchronous_commit\": \"{param_value}\""),
+                    })),
+                };
+                // Accepted as in PostgreSQL, but say so when commits aren't synced to begin with
+                if param_value == "off"
+                    && let Some(db_handler) = session.get_db_handler().await
+                    && !db_handler.syncs_commits() {
+                    framed.send(BackendMessage::NoticeResponse(crate::protocol::messages::NoticeResponse {
+                        severity: "NOTICE".to_string(),
+                        code: "00000".to_string(),
+                        message: "synchronous_commit = off has no effect: commits are not synced before they are acknowledged".to_string(),
+                        detail: None,
+                        hint: Some("Commits are synced under --group-commit-delay-ms or with --pragma-synchronous FULL.".to_string()),
+                        position: None,
+                        where_: None,
+                    })).await.map_err(PgSqliteError::Io)?;
+                }
+            }
+
+            let statement_timeout;
+            if param_name == "STATEMENT_TIMEOUT" {
+                let Some(millis) = Self::parse_statement_timeout(param_value) else {
+                    return Err(PgSqliteError::Validation(crate::error::PgError::Generic {
+                        code: "22023".to_string(),
+                        message: format!("invalid value for parameter \"statement_timeout\": \"{param_value}\""),
+                    }));
+                };
+                statement_timeout = Self::format_statement_timeout(millis);
+                param_value = &statement_timeout;
+            }
+
+            if param_name == "PGSQLITE.STRICT_COMPATIBILITY" {
+                let Some(mode) = crate::query::StrictCompatibility::from_setting(param_value) else {
+                    return Err(PgSqliteError::Validation(crate::error::PgError::Generic {
+                        code: "22023".to_string(),
+                        message: format!("invalid value for parameter \"pgsqlite.strict_compatibility\": \"{param_value}\""),
+                    }));
+                };
+                param_value = mode.as_str();
+            }
+
+            // Kept as a list of schema names, like SHOW search_path reports it
+            let search_path;
+            if param_name == "SEARCH_PATH" {
+                search_path = crate::query::SchemaHandler::normalize_search_path(caps[3].trim().trim_end_matches(';'));
+                param_value = &search_path;
+            }
+
             // Update session parameter
-            let mut params = session.parameters.write().await;
-            params.insert(param_name.clone(), param_value.to_string());
-            drop(params);
+            session.set_parameter(&param_name, param_value.to_string(), local).await;
             
             framed.send(BackendMessage::CommandComplete { 
                 tag: "SET".to_string() 
@@ -107,13 +223,45 @@ impl SetHandler {
                 "TRANSACTION ISOLATION LEVEL" => "read committed".to_string(),
                 "DEFAULT_TRANSACTION_ISOLATION" => "read committed".to_string(), 
                 "TRANSACTION_ISOLATION" => "read committed".to_string(),
-                "SERVER_VERSION" => "15.0".to_string(),
-                "SERVER_VERSION_NUM" => "150000".to_string(),
+                "SERVER_VERSION" => crate::config::CONFIG.server_version.clone(),
+                "SERVER_VERSION_NUM" => crate::config::CONFIG.server_version_num().to_string(),
+                "TRANSACTION_READ_ONLY" | "IN_HOT_STANDBY" => if crate::config::CONFIG.read_only { "on" } else { "off" }.to_string(),
+                "DEFAULT_TRANSACTION_READ_ONLY" => {
+                    let params = session.parameters.read().await;
+                    params.get(&param_name)
+                        .or_else(|| params.get("default_transaction_read_only"))
+                        .cloned()
+                        .unwrap_or_else(|| "off".to_string())
+                }
                 "IS_SUPERUSER" => "on".to_string(),
                 "SESSION_AUTHORIZATION" => "postgres".to_string(),
                 "STANDARD_CONFORMING_STRINGS" => "on".to_string(),
                 "CLIENT_ENCODING" => "UTF8".to_string(),
                 "SERVER_ENCODING" => "UTF8".to_string(),
+                "BYTEA_OUTPUT" => {
+                    let params = session.parameters.read().await;
+                    params.get(&param_name)
+                        .map(|v| v.to_lowercase())
+                        .unwrap_or_else(|| "hex".to_string())
+                }
+                "PGSQLITE.TRACE" => if session.trace_enabled() { "on" } else { "off" }.to_string(),
+                "PGSQLITE.STRICT_COMPATIBILITY" => session.strict_compatibility().as_str().to_string(),
+                "PGSQLITE.AUTO_INDEX_FOREIGN_KEYS" => if session.auto_index_foreign_keys().await { "on" } else { "off" }.to_string(),
+                "PGSQLITE.SOFT_DELETE" => if session.soft_delete_enabled().await { "on" } else { "off" }.to_string(),
+                "SYNCHRONOUS_COMMIT" => {
+                    let params = session.parameters.read().await;
+                    params.get(&param_name).cloned().unwrap_or_else(|| "on".to_string())
+                }
+                "STATEMENT_TIMEOUT" => {
+                    let params = session.parameters.read().await;
+                    params.get(&param_name).cloned().unwrap_or_else(|| "0".to_string())
+                }
+                "SEARCH_PATH" => {
+                    let params = session.parameters.read().await;
+                    params.get(&param_name)
+                        .cloned()
+                        .unwrap_or_else(|| crate::query::schema_handler::DEFAULT_SEARCH_PATH.to_string())
+                }
                 _ => {
                     // Fall back to session parameters
                     let params = session.parameters.read().await;
@@ -156,7 +304,7 @@ impl SetHandler {
     }
     
     /// Set the session timezone
-    async fn set_timezone(session: &Arc<SessionState>, timezone: &str) -> Result<(), PgSqliteError> {
+    async fn set_timezone(session: &Arc<SessionState>, timezone: &str, local: bool) -> Result<(), PgSqliteError> {
         // Validate timezone (basic validation)
         let valid_timezone = match timezone.to_uppercase().as_str() {
             "UTC" | "GMT" => "UTC",
@@ -176,12 +324,43 @@ impl SetHandler {
             }
         };
         
-        let mut params = session.parameters.write().await;
-        params.insert("TIMEZONE".to_string(), valid_timezone.to_string());
+        session.set_parameter("TIMEZONE", valid_timezone.to_string(), local).await;
         
         Ok(())
     }
     
+    /// Milliseconds of a statement_timeout value: a number of milliseconds, or a number
+    /// with one of the units us, ms, s, min, h and d. 0 (and DEFAULT) turns the timeout off.
+    pub fn parse_statement_timeout(value: &str) -> Option<u64> {
+        let value = value.trim().to_lowercase();
+        if value == "default" {
+            return Some(0);
+        }
+        let split = value.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(value.len());
+        let number: f64 = value[..split].parse().ok()?;
+        let unit_millis = match value[split..].trim() {
+            "" | "ms" => 1.0,
+            "us" => 0.001,
+            "s" => 1_000.0,
+            "min" => 60_000.0,
+            "h" => 3_600_000.0,
+            "d" => 86_400_000.0,
+            _ => return None,
+        };
+        let millis = (number * unit_millis).round();
+        (millis <= f64::from(i32::MAX)).then_some(millis as u64)
+    }
+
+    /// A statement_timeout in the largest unit that divides it evenly, like SHOW reports it
+    fn format_statement_timeout(millis: u64) -> String {
+        if millis == 0 {
+            return "0".to_string();
+        }
+        [(86_400_000, "d"), (3_600_000, "h"), (60_000, "min"), (1_000, "s")].into_iter()
+            .find(|(unit, _)| millis.is_multiple_of(*unit))
+            .map_or_else(|| format!("{millis}ms"), |(unit, name)| format!("{}{name}", millis / unit))
+    }
+
     /// Check if a string is a valid timezone offset
     fn is_valid_offset(offset: &str) -> bool {
         let offset_pattern = Regex::new(r"^[+-]\d{2}:\d{2}$").unwrap();
@@ -215,8 +394,41 @@ mod tests {
         
         let query = "SET TIME ZONE '+05:30'";
         assert!(SET_TIMEZONE_PATTERN.is_match(query));
+
+        let caps = SET_TIMEZONE_PATTERN.captures("SET LOCAL TIME ZONE 'UTC'").unwrap();
+        assert_eq!((&caps[1], &caps[2]), ("LOCAL", "'UTC'"));
+    }
+
+    #[test]
+    fn test_set_parameter_pattern() {
+        let caps = SET_PARAMETER_PATTERN.captures("set local statement_timeout = '5s'").unwrap();
+        assert_eq!((caps.get(1).map(|m| m.as_str()), &caps[2], &caps[3]), (Some("local"), "statement_timeout", "'5s'"));
+
+        let caps = SET_PARAMETER_PATTERN.captures("SET SESSION app.tenant TO 42").unwrap();
+        assert_eq!((caps.get(1).map(|m| m.as_str()), &caps[2], &caps[3]), (Some("SESSION"), "app.tenant", "42"));
+
+        // A parameter that happens to be called local
+        let caps = SET_PARAMETER_PATTERN.captures("SET local = 1").unwrap();
+        assert_eq!((caps.get(1).map(|m| m.as_str()), &caps[2]), (None, "local"));
     }
     
+    #[test]
+    fn test_statement_timeout_values() {
+        assert_eq!(SetHandler::parse_statement_timeout("250"), Some(250));
+        assert_eq!(SetHandler::parse_statement_timeout("1.5s"), Some(1_500));
+        assert_eq!(SetHandler::parse_statement_timeout("2 min"), Some(120_000));
+        assert_eq!(SetHandler::parse_statement_timeout("1500us"), Some(2));
+        assert_eq!(SetHandler::parse_statement_timeout("DEFAULT"), Some(0));
+        assert_eq!(SetHandler::parse_statement_timeout("-1"), None);
+        assert_eq!(SetHandler::parse_statement_timeout("5 weeks"), None);
+        assert_eq!(SetHandler::parse_statement_timeout("30d"), None);
+
+        assert_eq!(SetHandler::format_statement_timeout(0), "0");
+        assert_eq!(SetHandler::format_statement_timeout(90_000), "90s");
+        assert_eq!(SetHandler::format_statement_timeout(7_200_000), "2h");
+        assert_eq!(SetHandler::format_statement_timeout(1_500), "1500ms");
+    }
+
     #[test]
     fn test_show_parameter_pattern() {
         let query = "SHOW TimeZone";
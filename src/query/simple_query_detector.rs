@@ -37,6 +37,11 @@ static SIMPLE_DELETE_REGEX: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r"(?i)^\s*DELETE\s+FROM\s+\w+\s+(WHERE\s+\w+\s*=\s*('[^']*'|\d+))?\s*;?\s*$").unwrap()
 });
 
+/// SUM and AVG calls, which the decimal rewriter makes exact for NUMERIC columns
+static SUM_AVG_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(?:sum|avg)\s*\(").unwrap()
+});
+
 /// Detects if a query is simple enough to bypass all translation and processing
 pub fn is_ultra_simple_query(query: &str) -> bool {
     debug!("Checking if ultra-simple: {}", query);
@@ -62,7 +67,13 @@ pub fn is_ultra_simple_query(query: &str) -> bool {
        query.contains("DECIMAL") || // May need rewriting
        query.contains("NUMERIC") ||
        query.contains("unnest") || // unnest function calls need translation
-       query.contains("UNNEST") {
+       query.contains("UNNEST") ||
+       crate::translator::SetReturningTranslator::needs_translation(query) || // generate_series and friends
+       crate::translator::BitTranslator::needs_translation(query) || // Bit strings and bitwise operators
+       crate::translator::XmlTranslator::needs_translation(query) || // XMLPARSE and XMLSERIALIZE
+       crate::translator::CompositeTranslator::needs_translation(query) || // ROW(...) and (col).field
+       crate::translator::WindowTranslator::needs_translation(query) || // Interval offsets in RANGE frames
+       crate::translator::LockingClauseTranslator::needs_translation(query) { // FOR UPDATE / FOR SHARE
         return false;
     }
     
@@ -77,7 +88,11 @@ pub fn is_ultra_simple_query(query: &str) -> bool {
         if (query.contains("'") && query.contains('-')) || // Date patterns like '2024-01-01'
            (query.contains("'") && query.contains(':')) ||  // Time patterns like '14:30:00'
            query.contains('{') ||                           // Array patterns like '{1,2,3}'
-           query.contains("ARRAY[") {                       // Array constructor like ARRAY[1,2,3]
+           query.contains("ARRAY[") ||                      // Array constructor like ARRAY[1,2,3]
+           crate::translator::InsertTranslator::contains_range_literal(query) || // Range literals like '[1,10]'
+           crate::translator::InsertTranslator::contains_network_literal(query) || // Addresses like '10.0.0.1/8'
+           crate::translator::InsertTranslator::contains_bytea_literal(query) || // Bytea input like '\x0102'
+           crate::translator::InsertTranslator::contains_xml_literal(query) { // xml input like '<a>x</a>'
             debug!("INSERT query detected with special patterns - NOT ultra-simple: {}", query);
             return false;
         }
@@ -229,6 +244,42 @@ pub fn is_fast_path_simple_query(query: &str) -> bool {
         return false;
     }
     
+    // Check for network containment operators (<<, >>, <<=, >>=)
+    if memchr::memmem::find(query_bytes, b"<<").is_some() ||
+       memchr::memmem::find(query_bytes, b">>").is_some() {
+        return false;
+    }
+    
+    // Check for bit string literals and bitwise operators
+    if crate::translator::BitTranslator::needs_translation(query) {
+        return false;
+    }
+    
+    // Check for ROW(...) constructors and composite field access
+    if crate::translator::CompositeTranslator::needs_translation(query) {
+        return false;
+    }
+    
+    // Check for XMLPARSE and XMLSERIALIZE
+    if crate::translator::XmlTranslator::needs_translation(query) {
+        return false;
+    }
+    
+    // Check for WITHIN GROUP ordered-set aggregates
+    if crate::translator::WithinGroupTranslator::needs_translation(query) {
+        return false;
+    }
+    
+    // Check for crosstab() pivots
+    if crate::translator::CrosstabTranslator::needs_translation(query) {
+        return false;
+    }
+    
+    // Check for interval offsets in RANGE window frames
+    if crate::translator::WindowTranslator::needs_translation(query) {
+        return false;
+    }
+    
     // Check for special SQL features
     if memchr::memmem::find(query_bytes, b"USING").is_some() ||
        memchr::memmem::find(query_bytes, b"AT TIME ZONE").is_some() ||
@@ -270,6 +321,18 @@ pub fn is_fast_path_simple_query(query: &str) -> bool {
                memchr::memchr(b':', query_bytes).is_some() {
                 return false;
             }
+            // IPv4 and dotted MAC addresses are validated and canonicalized
+            if crate::translator::InsertTranslator::contains_network_literal(query) {
+                return false;
+            }
+            // Bytea hex and escape input is decoded into BLOBs
+            if memchr::memchr(b'\\', query_bytes).is_some() {
+                return false;
+            }
+            // xml input is checked for being well-formed
+            if crate::translator::InsertTranslator::contains_xml_literal(query) {
+                return false;
+            }
         }
         // Check for array literals
         if memchr::memchr(b'{', query_bytes).is_some() ||
@@ -287,7 +350,13 @@ pub fn is_fast_path_simple_query(query: &str) -> bool {
        memchr::memmem::find(query_bytes, b"HAVING").is_some() ||
        memchr::memmem::find(query_bytes, b"EXTRACT").is_some() ||
        memchr::memmem::find(query_bytes, b"unnest").is_some() ||
-       memchr::memmem::find(query_bytes, b"UNNEST").is_some() {
+       memchr::memmem::find(query_bytes, b"UNNEST").is_some() ||
+       crate::translator::SetReturningTranslator::needs_translation(query) {
+        return false;
+    }
+    
+    // SUM and AVG, plain or windowed, go through the decimal rewriter
+    if SUM_AVG_REGEX.is_match(query) {
         return false;
     }
     
@@ -307,7 +376,10 @@ pub fn is_simple_batch_insert(query: &str) -> bool {
        query.contains("NOW()") ||
        query.contains("||") || // String concatenation
        query.contains("DECIMAL") || // May need rewriting
-       query.contains("NUMERIC") {
+       query.contains("NUMERIC") ||
+       crate::translator::BitTranslator::contains_bit_literal(query) || // B'0101' literals
+       crate::translator::XmlTranslator::needs_translation(query) || // XMLPARSE and XMLSERIALIZE
+       crate::translator::CompositeTranslator::needs_translation(query) { // ROW(...) constructors
         return false;
     }
     
@@ -322,22 +394,18 @@ pub fn is_simple_batch_insert(query: &str) -> bool {
     true
 }
 
+static FROM_TABLE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)FROM\s+(\w+)").unwrap());
+static INTO_TABLE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)INTO\s+(\w+)").unwrap());
+static INSERT_TABLE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)^\s*INSERT\s+INTO\s+(\w+)").unwrap());
+static UPDATE_TABLE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)^\s*UPDATE\s+(\w+)").unwrap());
+
 /// Extract table name from a simple query
 pub fn extract_simple_table_name(query: &str) -> Option<String> {
-    // Try to extract table name using simple regex
-    if let Some(caps) = Regex::new(r"(?i)FROM\s+(\w+)").unwrap().captures(query) {
-        return Some(caps.get(1).unwrap().as_str().to_string());
-    }
-    
-    if let Some(caps) = Regex::new(r"(?i)INTO\s+(\w+)").unwrap().captures(query) {
-        return Some(caps.get(1).unwrap().as_str().to_string());
-    }
-    
-    if let Some(caps) = Regex::new(r"(?i)UPDATE\s+(\w+)").unwrap().captures(query) {
-        return Some(caps.get(1).unwrap().as_str().to_string());
-    }
-    
-    None
+    // The table an INSERT or UPDATE writes comes before any FROM in its subqueries
+    [&*INSERT_TABLE_REGEX, &*UPDATE_TABLE_REGEX, &*FROM_TABLE_REGEX, &*INTO_TABLE_REGEX]
+        .iter()
+        .find_map(|regex| regex.captures(query))
+        .map(|caps| caps[1].to_string())
 }
 
 #[cfg(test)]
@@ -381,6 +449,8 @@ mod tests {
         assert_eq!(extract_simple_table_name("INSERT INTO products (name) VALUES ('test')"), Some("products".to_string()));
         assert_eq!(extract_simple_table_name("UPDATE customers SET name = 'test'"), Some("customers".to_string()));
         assert_eq!(extract_simple_table_name("DELETE FROM orders"), Some("orders".to_string()));
+        assert_eq!(extract_simple_table_name("INSERT INTO totals SELECT sum(amount) FROM orders"), Some("totals".to_string()));
+        assert_eq!(extract_simple_table_name("UPDATE orders SET amount = (SELECT max(price) FROM prices)"), Some("orders".to_string()));
     }
     
     #[test]
@@ -444,6 +514,8 @@ mod tests {
         assert!(!is_fast_path_simple_query("INSERT INTO logs (created) VALUES ('2024-01-01')"));
         assert!(!is_fast_path_simple_query("INSERT INTO logs (time) VALUES ('14:30:00')"));
         assert!(!is_fast_path_simple_query("SELECT * FROM unnest(ARRAY[1,2,3])"));
+        assert!(!is_fast_path_simple_query("SELECT sum(amount) FROM payments WHERE id > 0"));
+        assert!(!is_fast_path_simple_query("SELECT id, AVG (amount) OVER (ORDER BY id) FROM payments"));
         
         // Complex RETURNING clauses should NOT use fast path
         assert!(!is_fast_path_simple_query("INSERT INTO users (name) VALUES ('test') RETURNING id::text"));
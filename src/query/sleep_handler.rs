@@ -0,0 +1,253 @@
+use crate::protocol::BackendMessage;
+use crate::PgSqliteError;
+use tokio_util::codec::Framed;
+use futures::SinkExt;
+use regex::Regex;
+use once_cell::sync::Lazy;
+use std::time::Duration;
+use tracing::debug;
+
+static SLEEP_CALL_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^\s*SELECT\s+(?:pg_catalog\.)?pg_sleep(_for|_until)?\s*\(\s*(.+?)\s*\)\s*(?:AS\s+(\w+|"[^"]+"))?\s*;?\s*$"#).unwrap()
+});
+
+/// OID of the void pseudo-type
+const VOID_OID: i32 = 2278;
+
+/// How often a sleep checks whether its statement was canceled or timed out
+pub const CANCEL_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Handles standalone `SELECT pg_sleep(...)` statements.
+///
+/// The sleep is awaited on the session's task instead of inside SQLite, so a
+/// sleeping client never ties up a runtime worker thread that other
+/// connections are being served from.
+pub struct SleepHandler;
+
+/// A parsed standalone pg_sleep call
+#[derive(Debug, Clone, PartialEq)]
+pub struct SleepCall {
+    pub duration: Duration,
+    pub column_name: String,
+}
+
+impl SleepHandler {
+    /// Whether a query calls something named like pg_sleep, in any position
+    pub fn might_be_sleep(query: &str) -> bool {
+        query.as_bytes().windows(8).any(|w| w.eq_ignore_ascii_case(b"pg_sleep"))
+    }
+
+    /// Parse `SELECT pg_sleep(seconds)`, `pg_sleep_for(interval)` or `pg_sleep_until(timestamp)`
+    pub fn parse_sleep_call(query: &str) -> Option<SleepCall> {
+        if !Self::might_be_sleep(query) {
+            return None;
+        }
+
+        let caps = SLEEP_CALL_PATTERN.captures(query)?;
+        let variant = caps.get(1).map(|m| m.as_str().to_lowercase());
+        let argument = Self::strip_literal(&caps[2]);
+
+        let duration = match variant.as_deref() {
+            None => Self::seconds_to_duration(argument.parse::<f64>().ok()?),
+            Some("_for") => Self::parse_interval(&argument)?,
+            Some(_) => Self::duration_until(&argument)?,
+        };
+
+        let column_name = caps.get(3)
+            .map(|m| m.as_str().trim_matches('"').to_string())
+            .unwrap_or_else(|| format!("pg_sleep{}", variant.unwrap_or_default()));
+
+        Some(SleepCall { duration, column_name })
+    }
+
+    /// The single result column of a sleep call, typed void like in PostgreSQL
+    pub fn field_description(call: &SleepCall) -> crate::protocol::FieldDescription {
+        Self::void_field(&call.column_name)
+    }
+
+    /// A result column typed void
+    pub(crate) fn void_field(name: &str) -> crate::protocol::FieldDescription {
+        crate::protocol::FieldDescription {
+            name: name.to_string(),
+            table_oid: 0,
+            column_id: 1,
+            type_oid: VOID_OID,
+            type_size: 4,
+            type_modifier: -1,
+            format: 0,
+        }
+    }
+
+    /// Sleep without blocking the runtime, then return the single void-valued row
+    ///
+    /// pg_cancel_backend() and the statement_timeout of backend `pid` end the sleep early.
+    pub async fn handle_sleep<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        pid: i32,
+        call: &SleepCall,
+        skip_row_description: bool,
+    ) -> Result<(), PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        debug!("Sleeping for {:?}", call.duration);
+        let deadline = tokio::time::Instant::now() + call.duration;
+        loop {
+            if crate::session::backend_registry::should_abort(pid) {
+                return Err(crate::query::trigger_handler::pg_error("57014", "canceling statement due to user request".to_string()));
+            }
+            let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            tokio::time::sleep(remaining.min(CANCEL_CHECK_INTERVAL)).await;
+        }
+
+        Self::send_void_row(framed, Self::field_description(call), skip_row_description).await
+    }
+
+    /// Send the single row of a standalone call of a function returning void
+    pub(crate) async fn send_void_row<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        field: crate::protocol::FieldDescription,
+        skip_row_description: bool,
+    ) -> Result<(), PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        if !skip_row_description {
+            framed.send(BackendMessage::RowDescription(vec![field])).await
+                .map_err(PgSqliteError::Io)?;
+        }
+
+        // void is sent as an empty value
+        framed.send(BackendMessage::DataRow(vec![Some(Vec::new())])).await
+            .map_err(PgSqliteError::Io)?;
+
+        framed.send(BackendMessage::CommandComplete {
+            tag: "SELECT 1".to_string()
+        }).await.map_err(PgSqliteError::Io)?;
+
+        Ok(())
+    }
+
+    /// Convert fractional seconds to a Duration; negative or invalid values don't sleep
+    pub fn seconds_to_duration(seconds: f64) -> Duration {
+        if seconds.is_finite() && seconds > 0.0 {
+            Duration::from_secs_f64(seconds)
+        } else {
+            Duration::ZERO
+        }
+    }
+
+    /// Parse an interval such as '1.5 seconds', '2 minutes 10 seconds' or '00:00:05'
+    pub fn parse_interval(interval: &str) -> Option<Duration> {
+        let interval = interval.trim();
+
+        if interval.contains(':') {
+            let mut seconds = 0.0;
+            for part in interval.split(':') {
+                seconds = seconds * 60.0 + part.trim().parse::<f64>().ok()?;
+            }
+            return Some(Self::seconds_to_duration(seconds));
+        }
+
+        let parts: Vec<&str> = interval.split_whitespace().collect();
+        if parts.is_empty() {
+            return None;
+        }
+
+        // A bare number is interpreted as seconds, like PostgreSQL does
+        if parts.len() == 1 {
+            return parts[0].parse::<f64>().ok().map(Self::seconds_to_duration);
+        }
+
+        if !parts.len().is_multiple_of(2) {
+            return None;
+        }
+
+        let mut seconds = 0.0;
+        for pair in parts.chunks(2) {
+            let value: f64 = pair[0].parse().ok()?;
+            let unit_seconds = match pair[1].to_lowercase().as_str() {
+                "microsecond" | "microseconds" | "us" => 0.000_001,
+                "millisecond" | "milliseconds" | "ms" => 0.001,
+                "second" | "seconds" | "sec" | "secs" | "s" => 1.0,
+                "minute" | "minutes" | "min" | "mins" => 60.0,
+                "hour" | "hours" | "hr" | "hrs" | "h" => 3600.0,
+                "day" | "days" | "d" => 86400.0,
+                "week" | "weeks" => 604800.0,
+                _ => return None,
+            };
+            seconds += value * unit_seconds;
+        }
+
+        Some(Self::seconds_to_duration(seconds))
+    }
+
+    /// Duration from now until the given timestamp (zero if it's in the past)
+    pub fn duration_until(timestamp: &str) -> Option<Duration> {
+        let timestamp = timestamp.trim();
+        let target = if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(timestamp) {
+            dt.with_timezone(&chrono::Utc)
+        } else if let Ok(dt) = chrono::DateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S%.f%#z") {
+            dt.with_timezone(&chrono::Utc)
+        } else {
+            chrono::NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S%.f").ok()?.and_utc()
+        };
+
+        let remaining = target.signed_duration_since(chrono::Utc::now());
+        Some(remaining.to_std().unwrap_or(Duration::ZERO))
+    }
+
+    /// Remove quotes and a trailing ::type cast from a literal argument
+    fn strip_literal(argument: &str) -> String {
+        let without_cast = match argument.find("::") {
+            Some(pos) => &argument[..pos],
+            None => argument,
+        };
+        without_cast.trim().trim_matches('\'').to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sleep_call() {
+        let call = SleepHandler::parse_sleep_call("SELECT pg_sleep(1.5)").unwrap();
+        assert_eq!(call.duration, Duration::from_millis(1500));
+        assert_eq!(call.column_name, "pg_sleep");
+
+        let call = SleepHandler::parse_sleep_call("select pg_catalog.pg_sleep('0.25'::float8) AS nap;").unwrap();
+        assert_eq!(call.duration, Duration::from_millis(250));
+        assert_eq!(call.column_name, "nap");
+
+        let call = SleepHandler::parse_sleep_call("SELECT pg_sleep_for('2 minutes 3 seconds')").unwrap();
+        assert_eq!(call.duration, Duration::from_secs(123));
+        assert_eq!(call.column_name, "pg_sleep_for");
+
+        let call = SleepHandler::parse_sleep_call("SELECT pg_sleep(-1)").unwrap();
+        assert_eq!(call.duration, Duration::ZERO);
+
+        // Only standalone calls are intercepted
+        assert!(SleepHandler::parse_sleep_call("SELECT pg_sleep(1), name FROM users").is_none());
+        assert!(SleepHandler::parse_sleep_call("SELECT * FROM users").is_none());
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(SleepHandler::parse_interval("100 milliseconds"), Some(Duration::from_millis(100)));
+        assert_eq!(SleepHandler::parse_interval("00:01:05"), Some(Duration::from_secs(65)));
+        assert_eq!(SleepHandler::parse_interval("3"), Some(Duration::from_secs(3)));
+        assert_eq!(SleepHandler::parse_interval("1 fortnight"), None);
+    }
+
+    #[test]
+    fn test_duration_until() {
+        assert_eq!(SleepHandler::duration_until("2000-01-01 00:00:00"), Some(Duration::ZERO));
+        assert_eq!(SleepHandler::duration_until("2000-01-01T00:00:00+00:00"), Some(Duration::ZERO));
+        assert!(SleepHandler::duration_until("not a timestamp").is_none());
+    }
+}
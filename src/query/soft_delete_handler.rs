@@ -0,0 +1,536 @@
+use crate::protocol::{BackendMessage, FieldDescription};
+use crate::query::audit_handler::{sqlite_name, table_name};
+use crate::query::trigger_handler::pg_error;
+use crate::session::{SessionState, SessionStorage};
+use crate::types::{PgType, SchemaTypeMapper};
+use crate::PgSqliteError;
+use futures::SinkExt;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rusqlite::{Connection, OptionalExtension};
+use sqlparser::ast::{
+    Assignment, AssignmentTarget, BinaryOperator, Expr, FromTable, FunctionArg, FunctionArgExpr, FunctionArguments,
+    Ident, ObjectName, Query, Select, SelectItem, SetExpr, Statement, TableAlias, TableFactor, TableWithJoins,
+    UpdateTableFromKind,
+};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio_util::codec::Framed;
+use tracing::debug;
+
+static SOFT_DELETE_CALL_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)^\s*SELECT\s+(?:\*\s+FROM\s+)?pgsqlite\.(enable|disable)_soft_delete\s*\(\s*'((?:[^']|'')*)'\s*\)\s*;?\s*$").unwrap()
+});
+
+/// The column whose timestamp marks a row of a soft-delete table as deleted
+const DELETED_AT: &str = "deleted_at";
+
+/// What a rewritten DELETE stores in deleted_at. The function only appears in
+/// rewritten DELETEs, so it also tells Execute to report them with a DELETE tag.
+const DELETED_AT_NOW: &str = "pgsqlite_soft_delete_now()";
+
+/// A `pgsqlite.enable_soft_delete()` or `pgsqlite.disable_soft_delete()` call
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoftDeleteCall {
+    pub enable: bool,
+    pub table: String,
+}
+
+/// The soft-delete tables of the database as of a catalog generation, see CatalogCache
+pub struct SoftDeleteTables {
+    generation: u64,
+    /// Lowercase SQLite names
+    tables: HashSet<String>,
+}
+
+impl SoftDeleteTables {
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+}
+
+/// Handles `SELECT pgsqlite.enable_soft_delete('<table>')` and
+/// `pgsqlite.disable_soft_delete('<table>')`, and rewrites the statements on the
+/// tables they enable.
+///
+/// SELECT, UPDATE and DELETE skip the rows of a soft-delete table whose `deleted_at`
+/// is set, and DELETE becomes an UPDATE that sets `deleted_at` to the current time.
+/// The tables are listed in __pgsqlite_soft_delete_tables. `SET pgsqlite.soft_delete = off`
+/// turns the rewriting off for a session, to see, restore or purge deleted rows.
+pub struct SoftDeleteHandler;
+
+impl SoftDeleteHandler {
+    /// Whether a query mentions one of the `*_soft_delete` functions anywhere
+    pub fn might_be_soft_delete_call(query: &str) -> bool {
+        query.as_bytes().windows(12).any(|w| w.eq_ignore_ascii_case(b"_soft_delete"))
+    }
+
+    /// A standalone `pgsqlite.enable_soft_delete()` or `pgsqlite.disable_soft_delete()` call
+    pub fn parse_soft_delete_call(query: &str) -> Option<SoftDeleteCall> {
+        if !Self::might_be_soft_delete_call(query) {
+            return None;
+        }
+        let caps = SOFT_DELETE_CALL_PATTERN.captures(query)?;
+        Some(SoftDeleteCall {
+            enable: caps[1].eq_ignore_ascii_case("enable"),
+            table: caps[2].replace("''", "'").trim().to_string(),
+        })
+    }
+
+    /// The row description of the result, also reported by Parse in the extended protocol
+    pub fn field_descriptions(call: &SoftDeleteCall) -> Vec<FieldDescription> {
+        vec![FieldDescription {
+            name: if call.enable { "enable_soft_delete" } else { "disable_soft_delete" }.to_string(),
+            table_oid: 0,
+            column_id: 1,
+            type_oid: PgType::Bool.to_oid(),
+            type_size: 1,
+            type_modifier: -1,
+            format: 0,
+        }]
+    }
+
+    /// Returns one row telling whether the call changed the table's setting, in binary
+    /// when the portal asked for it
+    pub async fn handle_soft_delete_call<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        call: &SoftDeleteCall,
+        skip_row_description: bool,
+        binary: bool,
+    ) -> Result<(), PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        let enable = call.enable;
+        let name = call.table.clone();
+        let changed = db.with_session_connection(&session.id, move |conn| {
+            Ok(Self::set_soft_delete(conn, &name, enable))
+        }).await??;
+        if changed {
+            // The statement is a SELECT, so the catalog cache doesn't see it change how tables are read
+            crate::cache::CatalogCache::bump_generation();
+        }
+        debug!("Soft delete {} for {}", if enable { "enabled" } else { "disabled" }, call.table);
+
+        if !skip_row_description {
+            framed.send(BackendMessage::RowDescription(Self::field_descriptions(call))).await
+                .map_err(PgSqliteError::Io)?;
+        }
+        let value = match (binary, changed) {
+            (true, changed) => vec![u8::from(changed)],
+            (false, true) => b"t".to_vec(),
+            (false, false) => b"f".to_vec(),
+        };
+        framed.send(BackendMessage::DataRow(vec![Some(value)])).await
+            .map_err(PgSqliteError::Io)?;
+        framed.send(BackendMessage::CommandComplete {
+            tag: "SELECT 1".to_string(),
+        }).await.map_err(PgSqliteError::Io)
+    }
+
+    /// Add the table to or remove it from __pgsqlite_soft_delete_tables, returning
+    /// whether it wasn't already (or was) listed
+    fn set_soft_delete(conn: &Connection, name: &str, enable: bool) -> Result<bool, PgSqliteError> {
+        let Some(table) = table_name(conn, &sqlite_name(name))? else {
+            return Err(pg_error("42P01", format!("relation \"{name}\" does not exist")));
+        };
+        if !enable {
+            let removed = conn.execute("DELETE FROM __pgsqlite_soft_delete_tables WHERE table_name = ?1", [&table])?;
+            return Ok(removed > 0);
+        }
+
+        let column: Option<Option<String>> = conn.query_row(
+            "SELECT s.pg_type FROM pragma_table_info(?1) c
+             LEFT JOIN __pgsqlite_schema s ON s.table_name = ?1 AND s.column_name = c.name COLLATE NOCASE
+             WHERE c.name = ?2 COLLATE NOCASE",
+            [table.as_str(), DELETED_AT],
+            |row| row.get(0),
+        ).optional()?;
+        let Some(pg_type) = column else {
+            return Err(pg_error("42703", format!("column \"{DELETED_AT}\" of relation \"{table}\" does not exist")));
+        };
+        let oid = pg_type.as_deref().map(|t| SchemaTypeMapper::pg_type_string_to_oid(&t.trim().to_uppercase()));
+        if !matches!(oid.and_then(PgType::from_oid), Some(PgType::Timestamp | PgType::Timestamptz)) {
+            return Err(pg_error(
+                "42804",
+                format!("column \"{DELETED_AT}\" of relation \"{table}\" must be of type timestamp or timestamptz"),
+            ));
+        }
+        let added = conn.execute("INSERT OR IGNORE INTO __pgsqlite_soft_delete_tables (table_name) VALUES (?1)", [&table])?;
+        Ok(added > 0)
+    }
+
+    /// Keep a renamed table's soft deletes
+    pub fn rename_table(conn: &Connection, old_name: &str, new_name: &str) -> rusqlite::Result<()> {
+        if !has_soft_delete_tables(conn)? {
+            return Ok(());
+        }
+        conn.execute("UPDATE __pgsqlite_soft_delete_tables SET table_name = ?2 WHERE table_name = ?1", [old_name, new_name])?;
+        Ok(())
+    }
+
+    /// Forget soft-delete tables that no longer exist
+    pub fn prune_soft_deletes(conn: &Connection) -> rusqlite::Result<()> {
+        if !has_soft_delete_tables(conn)? {
+            return Ok(());
+        }
+        conn.execute(
+            "DELETE FROM __pgsqlite_soft_delete_tables
+             WHERE table_name NOT IN (SELECT name FROM sqlite_master WHERE type = 'table')",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Whether `query` is a DELETE that `rewrite` turned into an UPDATE, which reports
+    /// the DELETE command tag
+    pub fn is_soft_delete(query: &str) -> bool {
+        query.contains(DELETED_AT_NOW) && query.trim_start().get(..6).is_some_and(|w| w.eq_ignore_ascii_case("UPDATE"))
+    }
+
+    /// Rewrite a statement on soft-delete tables so it doesn't see their deleted rows,
+    /// and so a DELETE marks its rows deleted. Other statements, those that don't
+    /// parse, and all statements of sessions that turned rewriting off come back unchanged.
+    pub async fn rewrite<'q>(
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        query: &'q str,
+    ) -> Result<Cow<'q, str>, PgSqliteError> {
+        let tables = Self::tables(db, session).await?;
+        let mentions = |table: &String| query.as_bytes().windows(table.len()).any(|w| w.eq_ignore_ascii_case(table.as_bytes()));
+        if !tables.tables.iter().any(mentions) || !session.soft_delete_enabled().await {
+            return Ok(Cow::Borrowed(query));
+        }
+        Ok(match Self::rewrite_statement(query, &tables.tables) {
+            Some(rewritten) => {
+                debug!("Soft delete rewrite: {} -> {}", query, rewritten);
+                Cow::Owned(rewritten)
+            }
+            None => Cow::Borrowed(query),
+        })
+    }
+
+    /// The soft-delete tables, reloaded after catalog changes
+    async fn tables(db: &Arc<dyn SessionStorage>, session: &Arc<SessionState>) -> Result<Arc<SoftDeleteTables>, PgSqliteError> {
+        let generation = crate::cache::CatalogCache::generation();
+        if let Some(tables) = session.soft_delete_tables(generation) {
+            return Ok(tables);
+        }
+        let tables = Arc::new(db.with_session_connection(&session.id, |conn| {
+            let tables = if has_soft_delete_tables(conn)? {
+                let mut stmt = conn.prepare("SELECT lower(table_name) FROM __pgsqlite_soft_delete_tables")?;
+                stmt.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?
+            } else {
+                HashSet::new()
+            };
+            Ok(SoftDeleteTables { generation, tables })
+        }).await?);
+        session.set_soft_delete_tables(tables.clone());
+        Ok(tables)
+    }
+
+    fn rewrite_statement(query: &str, tables: &HashSet<String>) -> Option<String> {
+        let mut statements = Parser::parse_sql(&PostgreSqlDialect {}, query).ok()?;
+        let [statement] = statements.as_mut_slice() else {
+            return None;
+        };
+        let replacement = |table: &str, name: &ObjectName| {
+            tables.contains(table).then(|| format!("SELECT * FROM {name} WHERE {DELETED_AT} IS NULL"))
+        };
+        let mut rewriter = TableRewriter::new(&replacement);
+        match statement {
+            Statement::Query(query) => rewriter.query(query),
+            Statement::Insert(insert) => {
+                if let Some(source) = &mut insert.source {
+                    rewriter.query(source);
+                }
+            }
+            Statement::Update { table, assignments, from, selection, .. } => {
+                for assignment in assignments.iter_mut() {
+                    rewriter.expr(&mut assignment.value);
+                }
+                if let Some(UpdateTableFromKind::AfterSet(from) | UpdateTableFromKind::BeforeSet(from)) = from {
+                    from.iter_mut().for_each(|table| rewriter.table_with_joins(table));
+                }
+                if let Some(selection) = selection {
+                    rewriter.expr(selection);
+                }
+                if let Some(target) = rewriter.target(table) {
+                    *selection = Some(not_deleted(selection.take(), target));
+                    rewriter.changed = true;
+                }
+            }
+            Statement::Delete(delete) => {
+                if let Some(using) = &mut delete.using {
+                    using.iter_mut().for_each(|table| rewriter.table_with_joins(table));
+                }
+                if let Some(selection) = &mut delete.selection {
+                    rewriter.expr(selection);
+                }
+                let from = match &delete.from {
+                    FromTable::WithFromKeyword(from) | FromTable::WithoutKeyword(from) => from,
+                };
+                if let ([table], true) = (from.as_slice(), delete.tables.is_empty())
+                    && let Some(target) = rewriter.target(table) {
+                    *statement = Statement::Update {
+                        table: table.clone(),
+                        assignments: vec![Assignment {
+                            target: AssignmentTarget::ColumnName(ObjectName::from(vec![Ident::new(DELETED_AT)])),
+                            value: parse_expr(DELETED_AT_NOW)?,
+                        }],
+                        from: delete.using.take().map(UpdateTableFromKind::AfterSet),
+                        selection: Some(not_deleted(delete.selection.take(), target)),
+                        returning: delete.returning.take(),
+                        or: None,
+                    };
+                    rewriter.changed = true;
+                }
+            }
+            _ => return None,
+        }
+        rewriter.changed.then(|| statement.to_string())
+    }
+}
+
+/// Replaces the tables a statement reads with subqueries, for the soft-delete tables
+/// their rows that aren't deleted and for masked tables their masked columns
+pub(crate) struct TableRewriter<'a> {
+    /// The subquery standing in for a table, given its lowercase name and its name as written
+    replacement: &'a dyn Fn(&str, &ObjectName) -> Option<String>,
+    /// Names of the CTEs in scope, which hide tables of the same name
+    ctes: Vec<String>,
+    pub(crate) changed: bool,
+    /// How many tables it replaced
+    pub(crate) replaced: usize,
+}
+
+impl<'a> TableRewriter<'a> {
+    pub(crate) fn new(replacement: &'a dyn Fn(&str, &ObjectName) -> Option<String>) -> Self {
+        Self { replacement, ctes: Vec::new(), changed: false, replaced: 0 }
+    }
+
+    fn replacement(&self, name: &ObjectName) -> Option<String> {
+        let [part] = name.0.as_slice() else {
+            return None;
+        };
+        let table = part.as_ident()?.value.to_lowercase();
+        if self.ctes.contains(&table) {
+            return None;
+        }
+        (self.replacement)(&table, name)
+    }
+
+    /// The name the columns of an UPDATE or DELETE target are qualified with, if
+    /// it's a table the rewriter replaces
+    fn target(&self, table: &TableWithJoins) -> Option<Ident> {
+        match &table.relation {
+            TableFactor::Table { name, alias, args: None, .. } if table.joins.is_empty() && self.replacement(name).is_some() => {
+                Some(alias.as_ref().map_or_else(|| name.0[0].as_ident().cloned(), |alias| Some(alias.name.clone()))?)
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn query(&mut self, query: &mut Query) {
+        let scope = self.ctes.len();
+        if let Some(with) = &mut query.with {
+            for cte in &mut with.cte_tables {
+                // A recursive CTE refers to itself
+                self.ctes.push(cte.alias.name.value.to_lowercase());
+                self.query(&mut cte.query);
+            }
+        }
+        self.set_expr(&mut query.body);
+        self.ctes.truncate(scope);
+    }
+
+    fn set_expr(&mut self, body: &mut SetExpr) {
+        match body {
+            SetExpr::Select(select) => self.select(select),
+            SetExpr::Query(query) => self.query(query),
+            SetExpr::SetOperation { left, right, .. } => {
+                self.set_expr(left);
+                self.set_expr(right);
+            }
+            SetExpr::Values(values) => values.rows.iter_mut().flatten().for_each(|expr| self.expr(expr)),
+            _ => {}
+        }
+    }
+
+    fn select(&mut self, select: &mut Select) {
+        for table in &mut select.from {
+            self.table_with_joins(table);
+        }
+        for item in &mut select.projection {
+            if let SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } = item {
+                self.expr(expr);
+            }
+        }
+        for expr in select.selection.iter_mut().chain(select.having.iter_mut()) {
+            self.expr(expr);
+        }
+    }
+
+    pub(crate) fn table_with_joins(&mut self, table: &mut TableWithJoins) {
+        self.table_factor(&mut table.relation);
+        for join in &mut table.joins {
+            self.table_factor(&mut join.relation);
+        }
+    }
+
+    fn table_factor(&mut self, factor: &mut TableFactor) {
+        match factor {
+            TableFactor::Table { name, alias, args: None, .. } => {
+                let Some(subquery) = self.replacement(name).and_then(|sql| parse_query(&sql)) else {
+                    return;
+                };
+                // Named after the table, so the statement's references to it still resolve
+                let alias = alias.take().or_else(|| {
+                    name.0[0].as_ident().map(|ident| TableAlias { name: ident.clone(), columns: Vec::new() })
+                });
+                *factor = TableFactor::Derived { lateral: false, subquery: Box::new(subquery), alias };
+                self.changed = true;
+                self.replaced += 1;
+            }
+            TableFactor::Derived { subquery, .. } => self.query(subquery),
+            TableFactor::NestedJoin { table_with_joins, .. } => self.table_with_joins(table_with_joins),
+            _ => {}
+        }
+    }
+
+    /// Rewrite the subqueries of an expression
+    pub(crate) fn expr(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Subquery(query) => self.query(query),
+            Expr::Exists { subquery, .. } => self.query(subquery),
+            Expr::InSubquery { expr, subquery, .. } => {
+                self.expr(expr);
+                self.set_expr(subquery);
+            }
+            Expr::BinaryOp { left, right, .. } => {
+                self.expr(left);
+                self.expr(right);
+            }
+            Expr::AnyOp { left, right, .. } | Expr::AllOp { left, right, .. } => {
+                self.expr(left);
+                self.expr(right);
+            }
+            Expr::UnaryOp { expr, .. } | Expr::Nested(expr) | Expr::Cast { expr, .. } => self.expr(expr),
+            Expr::IsNull(expr) | Expr::IsNotNull(expr) | Expr::IsTrue(expr) | Expr::IsFalse(expr) => self.expr(expr),
+            Expr::Between { expr, low, high, .. } => {
+                self.expr(expr);
+                self.expr(low);
+                self.expr(high);
+            }
+            Expr::InList { expr, list, .. } => {
+                self.expr(expr);
+                list.iter_mut().for_each(|item| self.expr(item));
+            }
+            Expr::Case { operand, conditions, else_result, .. } => {
+                if let Some(operand) = operand {
+                    self.expr(operand);
+                }
+                for when in conditions {
+                    self.expr(&mut when.condition);
+                    self.expr(&mut when.result);
+                }
+                if let Some(else_result) = else_result {
+                    self.expr(else_result);
+                }
+            }
+            Expr::Function(function) => match &mut function.args {
+                FunctionArguments::Subquery(query) => self.query(query),
+                FunctionArguments::List(list) => {
+                    for arg in &mut list.args {
+                        if let FunctionArg::Unnamed(FunctionArgExpr::Expr(expr))
+                            | FunctionArg::Named { arg: FunctionArgExpr::Expr(expr), .. } = arg {
+                            self.expr(expr);
+                        }
+                    }
+                }
+                FunctionArguments::None => {}
+            },
+            _ => {}
+        }
+    }
+}
+
+/// `selection AND target.deleted_at IS NULL`
+fn not_deleted(selection: Option<Expr>, target: Ident) -> Expr {
+    let filter = Expr::IsNull(Box::new(Expr::CompoundIdentifier(vec![target, Ident::new(DELETED_AT)])));
+    match selection {
+        Some(selection) => Expr::BinaryOp {
+            left: Box::new(Expr::Nested(Box::new(selection))),
+            op: BinaryOperator::And,
+            right: Box::new(filter),
+        },
+        None => filter,
+    }
+}
+
+pub(crate) fn parse_query(sql: &str) -> Option<Query> {
+    match Parser::parse_sql(&PostgreSqlDialect {}, sql).ok()?.pop()? {
+        Statement::Query(query) => Some(*query),
+        _ => None,
+    }
+}
+
+pub(crate) fn parse_expr(sql: &str) -> Option<Expr> {
+    Parser::new(&PostgreSqlDialect {}).try_with_sql(sql).ok()?.parse_expr().ok()
+}
+
+fn has_soft_delete_tables(conn: &Connection) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '__pgsqlite_soft_delete_tables')",
+        [],
+        |row| row.get(0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewrite(query: &str) -> Option<String> {
+        let tables = HashSet::from(["orders".to_string()]);
+        SoftDeleteHandler::rewrite_statement(query, &tables)
+    }
+
+    #[test]
+    fn test_parse_soft_delete_call() {
+        assert_eq!(
+            SoftDeleteHandler::parse_soft_delete_call("SELECT pgsqlite.enable_soft_delete('orders');"),
+            Some(SoftDeleteCall { enable: true, table: "orders".to_string() })
+        );
+        assert_eq!(
+            SoftDeleteHandler::parse_soft_delete_call("select * from PGSQLITE.DISABLE_SOFT_DELETE( 'o''s' )"),
+            Some(SoftDeleteCall { enable: false, table: "o's".to_string() })
+        );
+        assert_eq!(SoftDeleteHandler::parse_soft_delete_call("SELECT pgsqlite.enable_soft_delete('orders'), 1"), None);
+    }
+
+    #[test]
+    fn test_rewrite_statement() {
+        assert_eq!(
+            rewrite("SELECT id FROM orders o WHERE total > 1").as_deref(),
+            Some("SELECT id FROM (SELECT * FROM orders WHERE deleted_at IS NULL) AS o WHERE total > 1")
+        );
+        assert_eq!(
+            rewrite("DELETE FROM orders WHERE id = 1 RETURNING id").as_deref(),
+            Some("UPDATE orders SET deleted_at = pgsqlite_soft_delete_now() WHERE (id = 1) AND orders.deleted_at IS NULL RETURNING id")
+        );
+        assert!(SoftDeleteHandler::is_soft_delete(&rewrite("DELETE FROM orders").unwrap()));
+        assert_eq!(
+            rewrite("UPDATE orders SET total = 0").as_deref(),
+            Some("UPDATE orders SET total = 0 WHERE orders.deleted_at IS NULL")
+        );
+        // CTEs shadow tables of the same name, and other tables are left alone
+        assert_eq!(rewrite("WITH orders AS (SELECT 1) SELECT * FROM orders"), None);
+        assert_eq!(rewrite("DELETE FROM customers"), None);
+    }
+}
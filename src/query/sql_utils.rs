@@ -0,0 +1,78 @@
+//! Quoting and splitting of SQL names and values, shared by the handlers that generate
+//! or parse SQL
+
+/// A name as a double-quoted SQL identifier
+pub fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// A value as a single-quoted SQL string literal
+pub fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// An identifier with its double quotes removed, otherwise as written
+pub fn unquote_identifier(name: &str) -> String {
+    let name = name.trim();
+    match name.strip_prefix('"').and_then(|n| n.strip_suffix('"')) {
+        Some(quoted) => quoted.replace("\"\"", "\""),
+        None => name.to_string(),
+    }
+}
+
+/// An identifier as PostgreSQL resolves it: case-folded unless quoted
+pub fn fold_identifier(name: &str) -> String {
+    let name = name.trim();
+    if name.starts_with('"') { unquote_identifier(name) } else { name.to_lowercase() }
+}
+
+/// Split a name at the dots outside double quotes
+pub fn split_qualified(name: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, c) in name.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '.' if !quoted => {
+                parts.push(&name[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&name[start..]);
+    parts
+}
+
+/// Last part of a possibly schema-qualified name, still quoted if it was
+pub fn last_name_part(name: &str) -> &str {
+    split_qualified(name).pop().unwrap_or_default()
+}
+
+/// Last part of a possibly schema-qualified name, with quotes removed
+pub fn unqualified(name: &str) -> String {
+    unquote_identifier(last_name_part(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quoting() {
+        assert_eq!(quote_identifier("orders"), "\"orders\"");
+        assert_eq!(quote_identifier("odd\"name"), "\"odd\"\"name\"");
+        assert_eq!(quote_literal("it's"), "'it''s'");
+    }
+
+    #[test]
+    fn test_name_parts() {
+        assert_eq!(split_qualified("app.\"odd.name\""), vec!["app", "\"odd.name\""]);
+        assert_eq!(last_name_part("app.\"Orders\""), "\"Orders\"");
+        assert_eq!(unqualified("app.\"odd.name\""), "odd.name");
+        assert_eq!(unqualified("Orders"), "Orders");
+        assert_eq!(fold_identifier("Orders"), "orders");
+        assert_eq!(fold_identifier("\"Orders\""), "Orders");
+    }
+}
@@ -0,0 +1,132 @@
+use crate::cache::QueryFingerprint;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Statistics of every statement executed, keyed by query fingerprint
+static STATEMENT_STATS: Lazy<Mutex<HashMap<u64, StatementStats>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Accumulated execution statistics of one normalized statement, a row of pg_stat_statements
+///
+/// Statements that differ only in literals, whitespace or keyword case share a
+/// fingerprint and therefore a row; `query` keeps the text of the first one seen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatementStats {
+    pub queryid: u64,
+    pub query: String,
+    pub calls: u64,
+    pub rows: u64,
+    /// Execution times in milliseconds
+    pub total_exec_time: f64,
+    pub min_exec_time: f64,
+    pub max_exec_time: f64,
+    sum_of_squares: f64,
+}
+
+impl StatementStats {
+    pub fn mean_exec_time(&self) -> f64 {
+        if self.calls == 0 { 0.0 } else { self.total_exec_time / self.calls as f64 }
+    }
+
+    /// Population standard deviation, as pg_stat_statements reports it
+    pub fn stddev_exec_time(&self) -> f64 {
+        if self.calls == 0 {
+            return 0.0;
+        }
+        let mean = self.mean_exec_time();
+        (self.sum_of_squares / self.calls as f64 - mean * mean).max(0.0).sqrt()
+    }
+}
+
+/// Record one successful execution of `query`
+///
+/// Does nothing when `--stat-statements-max` is 0. Once the limit is reached the
+/// least-called statement makes room for a new one.
+pub fn record(query: &str, elapsed: Duration, rows: u64) {
+    record_limited(query, elapsed, rows, crate::config::CONFIG.stat_statements_max);
+}
+
+fn record_limited(query: &str, elapsed: Duration, rows: u64, max: usize) {
+    if max == 0 {
+        return;
+    }
+    let query = query.trim().trim_end_matches(';').trim_end();
+    if query.is_empty() {
+        return;
+    }
+
+    let queryid = QueryFingerprint::generate(query);
+    let millis = elapsed.as_secs_f64() * 1000.0;
+    let mut stats = STATEMENT_STATS.lock();
+    if !stats.contains_key(&queryid) && stats.len() >= max
+        && let Some(evicted) = stats.values().min_by_key(|entry| entry.calls).map(|entry| entry.queryid) {
+            stats.remove(&evicted);
+        }
+    let entry = stats.entry(queryid).or_insert_with(|| StatementStats {
+        queryid,
+        query: query.to_string(),
+        calls: 0,
+        rows: 0,
+        total_exec_time: 0.0,
+        min_exec_time: millis,
+        max_exec_time: millis,
+        sum_of_squares: 0.0,
+    });
+    entry.calls += 1;
+    entry.rows += rows;
+    entry.total_exec_time += millis;
+    entry.min_exec_time = entry.min_exec_time.min(millis);
+    entry.max_exec_time = entry.max_exec_time.max(millis);
+    entry.sum_of_squares += millis * millis;
+}
+
+/// Snapshot of all tracked statements
+pub fn statement_stats() -> Vec<StatementStats> {
+    STATEMENT_STATS.lock().values().cloned().collect()
+}
+
+/// Discard all statistics, what `pg_stat_statements_reset()` does
+pub fn reset() {
+    STATEMENT_STATS.lock().clear();
+}
+
+/// The tracked statements as a JSON array, the source of the pg_stat_statements view
+pub fn statement_stats_json() -> String {
+    let rows: Vec<serde_json::Value> = statement_stats().into_iter()
+        .map(|stats| serde_json::json!({
+            // queryid is a bigint in PostgreSQL
+            "queryid": stats.queryid as i64,
+            "query": stats.query,
+            "calls": stats.calls,
+            "rows": stats.rows,
+            "total_exec_time": stats.total_exec_time,
+            "min_exec_time": stats.min_exec_time,
+            "max_exec_time": stats.max_exec_time,
+            "mean_exec_time": stats.mean_exec_time(),
+            "stddev_exec_time": stats.stddev_exec_time(),
+        }))
+        .collect();
+    serde_json::Value::Array(rows).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statements_grouped_by_fingerprint() {
+        record_limited("SELECT * FROM stats_unit_test WHERE id = 1", Duration::from_millis(2), 1, 5000);
+        record_limited("select *  from stats_unit_test where id = 42;", Duration::from_millis(4), 3, 5000);
+
+        let entry = statement_stats().into_iter()
+            .find(|s| s.query.contains("stats_unit_test"))
+            .unwrap();
+        assert_eq!(entry.query, "SELECT * FROM stats_unit_test WHERE id = 1");
+        assert_eq!((entry.calls, entry.rows), (2, 4));
+        assert!((entry.mean_exec_time() - 3.0).abs() < 1e-9);
+        assert!((entry.stddev_exec_time() - 1.0).abs() < 1e-9);
+        assert!((entry.min_exec_time - 2.0).abs() < 1e-9 && (entry.max_exec_time - 4.0).abs() < 1e-9);
+        assert!(statement_stats_json().contains("stats_unit_test"));
+    }
+}
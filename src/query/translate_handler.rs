@@ -0,0 +1,196 @@
+use crate::protocol::{BackendMessage, FieldDescription};
+use crate::query::{ExtendedQueryHandler, ParameterParser, TranslationPipeline};
+use crate::session::{SessionState, SessionStorage};
+use crate::translator::TranslationMetadata;
+use crate::types::{PgType, SchemaTypeMapper};
+use crate::PgSqliteError;
+use futures::SinkExt;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rusqlite::Connection;
+use std::sync::Arc;
+use tokio_util::codec::Framed;
+use tracing::debug;
+
+static TRANSLATE_CALL_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)^\s*SELECT\s+(?:\*\s+FROM\s+)?pgsqlite\.translate\s*\(\s*'((?:[^']|'')*)'\s*\)\s*;?\s*$").unwrap()
+});
+
+static PARAMETER_CAST_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\$(\d+)\s*::\s*([A-Za-z_]\w*(?:\[\])?)").unwrap()
+});
+
+/// Result columns of `pgsqlite.translate()`
+const RESULT_COLUMNS: [&str; 4] = ["translated_query", "translators", "columns", "parameters"];
+
+/// Handles `SELECT pgsqlite.translate('<query>')`.
+///
+/// Runs the query through the same translator chain as the simple query
+/// protocol without executing it, and returns one row with the SQL SQLite would
+/// see, the translators that changed it, and the inferred result column and
+/// parameter types. Columns are NULL when SQLite can't prepare the translated SQL.
+pub struct TranslateHandler;
+
+/// What pgsqlite does with a query
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranslationExplanation {
+    pub translated_query: String,
+    pub translators: Vec<&'static str>,
+    /// `name type` for each result column
+    pub columns: Option<Vec<String>>,
+    /// `$n type` for each parameter
+    pub parameters: Vec<String>,
+}
+
+impl TranslateHandler {
+    /// Whether a query names pgsqlite.translate
+    pub fn might_be_translate(query: &str) -> bool {
+        query.as_bytes().windows(18).any(|w| w.eq_ignore_ascii_case(b"pgsqlite.translate"))
+    }
+
+    /// The query passed to a standalone `pgsqlite.translate()` call
+    pub fn parse_translate_call(query: &str) -> Option<String> {
+        if !Self::might_be_translate(query) {
+            return None;
+        }
+        let caps = TRANSLATE_CALL_PATTERN.captures(query)?;
+        let inner = caps[1].replace("''", "'");
+        let inner = crate::query::strip_sql_comments(&inner);
+        Some(inner.trim().trim_end_matches(';').trim_end().to_string())
+    }
+
+    /// The row description of the result, also reported by Parse in the extended protocol
+    pub fn field_descriptions() -> Vec<FieldDescription> {
+        RESULT_COLUMNS.iter().enumerate()
+            .map(|(i, name)| FieldDescription {
+                name: name.to_string(),
+                table_oid: 0,
+                column_id: (i + 1) as i16,
+                type_oid: PgType::Text.to_oid(),
+                type_size: -1,
+                type_modifier: -1,
+                format: 0,
+            })
+            .collect()
+    }
+
+    pub async fn explain(
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        query: &str,
+    ) -> Result<TranslationExplanation, PgSqliteError> {
+        let translated = TranslationPipeline::translate(db, session, query).await?;
+
+        let mut param_types = if ParameterParser::count_parameters(query) > 0 {
+            ExtendedQueryHandler::infer_param_types(query, db, session).await.0
+        } else {
+            Vec::new()
+        };
+        for caps in PARAMETER_CAST_PATTERN.captures_iter(query) {
+            if let Some(slot) = caps[1].parse::<usize>().ok().and_then(|n| param_types.get_mut(n.wrapping_sub(1))) {
+                *slot = SchemaTypeMapper::pg_type_string_to_oid(&caps[2]);
+            }
+        }
+        let parameters = param_types.iter().enumerate()
+            .map(|(i, oid)| format!("${} {}", i + 1, SchemaTypeMapper::pg_oid_to_type_name(*oid)))
+            .collect();
+
+        let columns = db.with_session_connection(&session.id, |conn| {
+            Ok(Self::column_types(conn, query, &translated.sql, &translated.metadata))
+        }).await?;
+
+        let translated_query = translated.setup.iter()
+            .chain(std::iter::once(&translated.sql))
+            .map(|sql| sql.trim().trim_end_matches(';'))
+            .collect::<Vec<_>>()
+            .join(";\n");
+        debug!("pgsqlite.translate: {} -> {}", query, translated_query);
+
+        Ok(TranslationExplanation {
+            translated_query,
+            translators: translated.fired,
+            columns,
+            parameters,
+        })
+    }
+
+    /// Prepare the translated SQL and type each result column the way the row description would
+    fn column_types(conn: &Connection, query: &str, sql: &str, metadata: &TranslationMetadata) -> Option<Vec<String>> {
+        let stmt = conn.prepare(sql).ok()?;
+        let tables: Vec<String> = crate::query::join_type_inference::extract_all_tables_from_query(query)
+            .into_iter()
+            .map(|(table, _)| table.strip_prefix("public.").unwrap_or(&table).to_string())
+            .collect();
+
+        let columns = stmt.columns().iter()
+            .map(|column| {
+                let name = column.name();
+                let hint = metadata.get_hint(name);
+                let source_column = hint.and_then(|h| h.source_column.clone())
+                    .or_else(|| crate::translator::WindowTranslator::value_function_column(name, query))
+                    .unwrap_or_else(|| name.to_string());
+
+                let oid = hint.and_then(|h| h.suggested_type.as_ref().map(PgType::to_oid))
+                    .or_else(|| tables.iter().find_map(|table| SchemaTypeMapper::get_type_from_schema(conn, table, &source_column)))
+                    .or_else(|| SchemaTypeMapper::get_aggregate_return_type_with_query(name, Some(conn), tables.first().map(String::as_str), Some(query)))
+                    .or_else(|| column.decl_type().map(SchemaTypeMapper::sqlite_type_to_pg_oid))
+                    .unwrap_or_else(|| PgType::Text.to_oid());
+                format!("{} {}", name, SchemaTypeMapper::pg_oid_to_type_name(oid))
+            })
+            .collect();
+        Some(columns)
+    }
+
+    pub async fn handle_translate<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        query: &str,
+        skip_row_description: bool,
+    ) -> Result<(), PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        db.require_local_data("pgsqlite.translate()")?;
+        let explanation = Self::explain(db, session, query).await?;
+
+        if !skip_row_description {
+            framed.send(BackendMessage::RowDescription(Self::field_descriptions())).await
+                .map_err(PgSqliteError::Io)?;
+        }
+
+        let row = vec![
+            Some(explanation.translated_query.into_bytes()),
+            Some(explanation.translators.join(", ").into_bytes()),
+            explanation.columns.map(|columns| columns.join(", ").into_bytes()),
+            Some(explanation.parameters.join(", ").into_bytes()),
+        ];
+        framed.send(BackendMessage::DataRow(row)).await
+            .map_err(PgSqliteError::Io)?;
+
+        framed.send(BackendMessage::CommandComplete {
+            tag: "SELECT 1".to_string(),
+        }).await.map_err(PgSqliteError::Io)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_translate_call() {
+        assert_eq!(
+            TranslateHandler::parse_translate_call("SELECT pgsqlite.translate('SELECT * FROM t WHERE name = ''x'';')"),
+            Some("SELECT * FROM t WHERE name = 'x'".to_string())
+        );
+        assert_eq!(
+            TranslateHandler::parse_translate_call("select * from PGSQLITE.TRANSLATE( 'SELECT now()' );"),
+            Some("SELECT now()".to_string())
+        );
+        assert_eq!(TranslateHandler::parse_translate_call("SELECT pgsqlite.translate('SELECT 1'), 2"), None);
+        assert_eq!(TranslateHandler::parse_translate_call("SELECT 'pgsqlite.translate'"), None);
+    }
+}
@@ -0,0 +1,443 @@
+use crate::session::{SessionState, SessionStorage};
+use crate::translator::{
+    BatchDeleteTranslator, BatchUpdateTranslator, FtsTranslator, QueryAnalyzer, TranslationFlags, TranslationMetadata,
+};
+use crate::PgSqliteError;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::debug;
+
+/// A query after the simple query protocol's translators have run
+#[derive(Debug)]
+pub struct TranslatedQuery {
+    /// The SQL handed to SQLite
+    pub sql: String,
+    /// Statements that must run before `sql`, such as the shadow tables of an FTS table
+    pub setup: Vec<String>,
+    /// Result column type hints collected along the way
+    pub metadata: TranslationMetadata,
+    /// Translator selection from QueryAnalyzer
+    pub flags: TranslationFlags,
+    /// Translators that changed the SQL or contributed type hints, in the order they ran
+    pub fired: Vec<&'static str>,
+}
+
+/// The ordered chain of PostgreSQL-to-SQLite translators used by the simple query protocol.
+///
+/// Shared by the executor and `pgsqlite.translate()`, so the explanation shows
+/// exactly what would run.
+pub struct TranslationPipeline;
+
+impl TranslationPipeline {
+    pub async fn translate(
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        query: &str,
+    ) -> Result<TranslatedQuery, PgSqliteError> {
+        let mut fired = Vec::new();
+
+        // Rewrite SQLAlchemy's insertmanyvalues INSERT ... SELECT ... FROM (VALUES ...) to a plain multi-row INSERT
+        let rewritten_insert = crate::translator::InsertManyValuesTranslator::translate_query(query);
+        if rewritten_insert.is_some() {
+            fired.push("insert_many_values");
+        }
+        let query = rewritten_insert.as_deref().unwrap_or(query);
+
+        // Interval offsets in RANGE window frames become the integer unit of the ORDER BY column,
+        // before the cast and datetime translators rewrite the interval literals
+        let rewritten_window = if crate::translator::WindowTranslator::needs_translation(query) {
+            Some(db.with_session_connection(&session.id, |conn| {
+                Ok(crate::translator::WindowTranslator::translate_query(query, Some(conn)))
+            }).await?)
+        } else {
+            None
+        };
+        let query = match rewritten_window.as_deref() {
+            Some(rewritten) => {
+                Self::record(&mut fired, "window", query, rewritten);
+                rewritten
+            }
+            None => query,
+        };
+
+        // Interval operators become the interval functions, before the datetime
+        // translator turns the interval literals into text
+        let rewritten_interval = db.with_session_connection(&session.id, |conn| {
+            Ok(crate::translator::IntervalTranslator::needs_translation(query, conn)
+                .then(|| crate::translator::IntervalTranslator::translate_query(query, conn)))
+        }).await?;
+        let query = match rewritten_interval.as_deref() {
+            Some(rewritten) => {
+                Self::record(&mut fired, "interval", query, rewritten);
+                rewritten
+            }
+            None => query,
+        };
+
+        // Drop MATERIALIZED hints, check recursive CTEs and type the columns projected out of CTEs
+        let (rewritten_cte, cte_metadata) = if crate::translator::CteTranslator::needs_translation(query) {
+            let rewritten = crate::translator::CteTranslator::translate_query(query);
+            let metadata = db.with_session_connection(&session.id, |conn| {
+                Ok(crate::translator::CteTranslator::analyze(&rewritten, conn))
+            }).await??;
+            (Some(rewritten), metadata)
+        } else {
+            (None, TranslationMetadata::new())
+        };
+        let query = match rewritten_cte.as_deref() {
+            Some(rewritten) => {
+                Self::record(&mut fired, "cte", query, rewritten);
+                rewritten
+            }
+            None => query,
+        };
+
+        // Analyze query once to determine which translators are needed
+        let translation_flags = QueryAnalyzer::analyze(query);
+        debug!("Query analysis flags: {:?}", translation_flags);
+
+        // Translate PostgreSQL cast syntax if present and collect metadata
+        let mut translation_metadata = TranslationMetadata::new();
+        let mut translated_query = if translation_flags.contains(TranslationFlags::CAST) {
+            if crate::profiling::is_profiling_enabled() {
+                crate::time_cast_translation!({
+                    use crate::translator::CastTranslator;
+                    let (translated, metadata) = db.with_session_connection(&session.id, |conn| {
+                        Ok(CastTranslator::translate_with_metadata(query, Some(conn)))
+                    }).await?;
+                    translation_metadata.merge(metadata);
+                    translated
+                })
+            } else {
+                use crate::translator::CastTranslator;
+                let (translated, metadata) = db.with_session_connection(&session.id, |conn| {
+                    Ok(CastTranslator::translate_with_metadata(query, Some(conn)))
+                }).await?;
+                translation_metadata.merge(metadata);
+                translated
+            }
+        } else {
+            query.to_string()
+        };
+        Self::record(&mut fired, "cast", query, &translated_query);
+
+        // Translate NUMERIC to TEXT casts with proper formatting
+        if translation_flags.contains(TranslationFlags::NUMERIC_FORMAT) {
+            use crate::translator::NumericFormatTranslator;
+            let translated = db.with_session_connection(&session.id, |conn| {
+                Ok(NumericFormatTranslator::translate_query(&translated_query, conn))
+            }).await?;
+            Self::apply(&mut fired, "numeric_format", &mut translated_query, translated);
+        }
+
+        // Translate batch UPDATE operations if needed
+        if translation_flags.contains(TranslationFlags::BATCH_UPDATE) {
+            let decimal_cache = Arc::new(Mutex::new(HashMap::new()));
+            let batch_translator = BatchUpdateTranslator::new(decimal_cache);
+            let translated = batch_translator.translate(&translated_query, &[]);
+            Self::apply(&mut fired, "batch_update", &mut translated_query, translated);
+            debug!("Query after batch UPDATE translation: {}", translated_query);
+        }
+
+        // Translate batch DELETE operations if needed
+        if translation_flags.contains(TranslationFlags::BATCH_DELETE) {
+            let decimal_cache = Arc::new(Mutex::new(HashMap::new()));
+            let batch_translator = BatchDeleteTranslator::new(decimal_cache);
+            let translated = batch_translator.translate(&translated_query, &[]);
+            Self::apply(&mut fired, "batch_delete", &mut translated_query, translated);
+            debug!("Query after batch DELETE translation: {}", translated_query);
+        }
+
+        // Translate FTS operations if needed
+        let mut setup = Vec::new();
+        if translation_flags.contains(TranslationFlags::FTS) {
+            debug!("Query contains FTS operations: {}", translated_query);
+            let fts_translator = FtsTranslator::new();
+
+            // Get connection, do translation, and immediately drop it to avoid Send issues
+            let fts_result = db.with_session_connection(&session.id, |conn| {
+                let result = fts_translator.translate(&translated_query, Some(conn));
+                Ok::<_, rusqlite::Error>(result)
+            }).await;
+
+            match fts_result {
+                Ok(Ok(mut fts_queries)) => {
+                    // For multiple queries (like CREATE TABLE with shadow tables), the last one is the main query
+                    if let Some(main_query) = fts_queries.pop() {
+                        debug!("FTS translation produced {} setup queries", fts_queries.len());
+                        if !fts_queries.is_empty() {
+                            fired.push("fts");
+                        }
+                        setup = fts_queries;
+                        Self::apply(&mut fired, "fts", &mut translated_query, main_query);
+                        debug!("Query after FTS translation: {}", translated_query);
+                    }
+                }
+                Ok(Err(e)) => {
+                    debug!("FTS translation failed: {}", e);
+                    return Err(PgSqliteError::Protocol(format!("FTS translation error: {e}")));
+                }
+                Err(e) => {
+                    debug!("FTS connection failed: {}", e);
+                    return Err(PgSqliteError::Protocol(format!("Failed to translate FTS: {e}")));
+                }
+            }
+        }
+
+        // Translate INSERT statements with datetime values if needed
+        if translation_flags.contains(TranslationFlags::INSERT_DATETIME) {
+            use crate::translator::InsertTranslator;
+            debug!("Query needs INSERT datetime translation: {}", translated_query);
+            match InsertTranslator::translate_query(&translated_query, db.as_ref()).await {
+                Ok(translated) => {
+                    debug!("Query after INSERT translation: {}", translated);
+                    Self::apply(&mut fired, "insert_values", &mut translated_query, translated);
+                }
+                Err(e) => {
+                    debug!("INSERT translation failed: {}", e);
+                    // Return the error to the user
+                    return Err(PgSqliteError::Protocol(e));
+                }
+            }
+        }
+
+        // Translate PostgreSQL datetime functions if present and capture metadata
+        if translation_flags.contains(TranslationFlags::DATETIME) {
+            use crate::translator::DateTimeTranslator;
+            debug!("Query needs datetime translation: {}", translated_query);
+            let (translated, metadata) = if crate::profiling::is_profiling_enabled() {
+                crate::time_datetime_translation!({
+                    DateTimeTranslator::translate_with_metadata(&translated_query)
+                })
+            } else {
+                DateTimeTranslator::translate_with_metadata(&translated_query)
+            };
+            Self::apply_with_metadata(&mut fired, "datetime", &mut translated_query, translated, &mut translation_metadata, metadata);
+            debug!("Query after datetime translation: {}", translated_query);
+        }
+
+        // Translate JSON operators if present
+        if translation_flags.contains(TranslationFlags::JSON) {
+            use crate::translator::JsonTranslator;
+            debug!("Query needs JSON operator translation: {}", translated_query);
+            match JsonTranslator::translate_json_operators(&translated_query) {
+                Ok(translated) => {
+                    debug!("Query after JSON operator translation: {}", translated);
+                    Self::apply(&mut fired, "json", &mut translated_query, translated);
+                }
+                Err(e) => {
+                    debug!("JSON operator translation failed: {}", e);
+                    // Continue with original query - some operators might not be supported yet
+                }
+            }
+
+            // Note: JSON path $ restoration will happen right before SQLite execution
+            debug!("Query after JSON translation ($ placeholders preserved): {}", translated_query);
+        }
+
+        // Translate catalog functions (remove pg_catalog prefix)
+        {
+            use crate::translator::{CatalogFunctionTranslator, PgTableIsVisibleTranslator};
+            let translated = CatalogFunctionTranslator::translate(&translated_query);
+            Self::apply(&mut fired, "catalog_function", &mut translated_query, translated);
+            let translated = PgTableIsVisibleTranslator::translate(&translated_query);
+            Self::apply(&mut fired, "pg_table_is_visible", &mut translated_query, translated);
+        }
+
+        // Drop FOR UPDATE / FOR SHARE, SQLite locks the whole database on write
+        if crate::translator::LockingClauseTranslator::needs_translation(&translated_query) {
+            let translated = crate::translator::LockingClauseTranslator::translate_query(&translated_query);
+            Self::apply(&mut fired, "locking_clause", &mut translated_query, translated);
+        }
+
+        // Translate FETCH FIRST / OFFSET n ROWS pagination to LIMIT/OFFSET
+        if crate::translator::PaginationTranslator::needs_translation(&translated_query) {
+            let translated = crate::translator::PaginationTranslator::translate_query(&translated_query);
+            Self::apply(&mut fired, "pagination", &mut translated_query, translated);
+        }
+
+        // Move the ORDER BY of WITHIN GROUP into percentile_cont/percentile_disc/mode calls
+        if crate::translator::WithinGroupTranslator::needs_translation(&translated_query) {
+            let translated = crate::translator::WithinGroupTranslator::translate_query(&translated_query);
+            Self::apply(&mut fired, "within_group", &mut translated_query, translated);
+        }
+
+        // Spell out PostgreSQL's default NULL ordering (NULLS LAST for ASC, NULLS FIRST for DESC)
+        if crate::translator::NullOrderingTranslator::needs_translation(&translated_query) {
+            let translated = crate::translator::NullOrderingTranslator::translate_query(&translated_query);
+            Self::apply(&mut fired, "null_ordering", &mut translated_query, translated);
+        }
+
+        // Rewrite bit string literals and bitwise operators
+        if crate::translator::BitTranslator::needs_translation(&translated_query) {
+            let translated = crate::translator::BitTranslator::translate_query(&translated_query);
+            Self::apply(&mut fired, "bit", &mut translated_query, translated);
+        }
+
+        // Rewrite inet/cidr containment operators, which SQLite reads as bit shifts
+        if crate::translator::NetworkTranslator::needs_translation(&translated_query) {
+            let translated = crate::translator::NetworkTranslator::translate_query(&translated_query);
+            Self::apply(&mut fired, "network", &mut translated_query, translated);
+        }
+
+        // Rewrite XMLPARSE and XMLSERIALIZE as function calls
+        if crate::translator::XmlTranslator::needs_translation(&translated_query) {
+            let translated = crate::translator::XmlTranslator::translate_query(&translated_query);
+            Self::apply(&mut fired, "xml", &mut translated_query, translated);
+        }
+
+        // Translate standalone VALUES and (VALUES ...) AS t(cols) to SELECT ... UNION ALL
+        if crate::translator::ValuesTranslator::needs_translation(&translated_query) {
+            let translated = crate::translator::ValuesTranslator::translate_query(&translated_query);
+            Self::apply(&mut fired, "values", &mut translated_query, translated);
+        }
+
+        // Translate array operators with metadata
+        if translation_flags.contains(TranslationFlags::ARRAY) {
+            use crate::translator::ArrayTranslator;
+            match ArrayTranslator::translate_with_metadata(&translated_query) {
+                Ok((translated, metadata)) => {
+                    debug!("Array translation metadata: {} hints", metadata.column_mappings.len());
+                    for (col, hint) in &metadata.column_mappings {
+                        debug!("  Column '{}': type={:?}", col, hint.suggested_type);
+                    }
+                    Self::apply_with_metadata(&mut fired, "array", &mut translated_query, translated, &mut translation_metadata, metadata);
+                }
+                Err(e) => {
+                    debug!("Array operator translation failed: {}", e);
+                    // Continue with original query
+                }
+            }
+        }
+
+        // Translate array_agg functions with ORDER BY/DISTINCT support
+        if translation_flags.contains(TranslationFlags::ARRAY_AGG) {
+            use crate::translator::ArrayAggTranslator;
+            match ArrayAggTranslator::translate_with_metadata(&translated_query) {
+                Ok((translated, metadata)) => {
+                    debug!("Array_agg translation metadata: {} hints", metadata.column_mappings.len());
+                    Self::apply_with_metadata(&mut fired, "array_agg", &mut translated_query, translated, &mut translation_metadata, metadata);
+                }
+                Err(e) => {
+                    debug!("Array_agg translation failed: {}", e);
+                    // Continue with original query
+                }
+            }
+        }
+
+        // Expand set-returning functions in select lists into lateral joins
+        if translation_flags.intersects(TranslationFlags::UNNEST | TranslationFlags::JSON_EACH) && crate::translator::SetReturningTranslator::needs_translation(&translated_query) {
+            let (translated, metadata) = crate::translator::SetReturningTranslator::translate_query(&translated_query);
+            Self::apply_with_metadata(&mut fired, "set_returning", &mut translated_query, translated, &mut translation_metadata, metadata);
+        }
+
+        // Translate unnest() functions to json_each() equivalents
+        if translation_flags.contains(TranslationFlags::UNNEST) {
+            use crate::translator::UnnestTranslator;
+            match UnnestTranslator::translate_with_metadata(&translated_query) {
+                Ok((translated, metadata)) => {
+                    debug!("Unnest translation metadata: {} hints", metadata.column_mappings.len());
+                    Self::apply_with_metadata(&mut fired, "unnest", &mut translated_query, translated, &mut translation_metadata, metadata);
+                }
+                Err(e) => {
+                    debug!("Unnest translation failed: {}", e);
+                    // Continue with original query
+                }
+            }
+        }
+
+        // Translate json_each()/jsonb_each() functions for PostgreSQL compatibility
+        if translation_flags.contains(TranslationFlags::JSON_EACH) {
+            use crate::translator::JsonEachTranslator;
+            match JsonEachTranslator::translate_with_metadata(&translated_query) {
+                Ok((translated, metadata)) => {
+                    debug!("JsonEach translation metadata: {} hints", metadata.column_mappings.len());
+                    Self::apply_with_metadata(&mut fired, "json_each", &mut translated_query, translated, &mut translation_metadata, metadata);
+                }
+                Err(e) => {
+                    debug!("JsonEach translation failed: {}", e);
+                    // Continue with original query
+                }
+            }
+        }
+
+        // Translate row_to_json() functions for PostgreSQL compatibility
+        if translation_flags.contains(TranslationFlags::ROW_TO_JSON) {
+            use crate::translator::RowToJsonTranslator;
+            let (translated, metadata) = RowToJsonTranslator::translate_row_to_json(&translated_query);
+            debug!("RowToJson translation metadata: {} hints", metadata.column_mappings.len());
+            Self::apply_with_metadata(&mut fired, "row_to_json", &mut translated_query, translated, &mut translation_metadata, metadata);
+        }
+
+        // Expand crosstab() in FROM to json_each() over its pivoted rows
+        if crate::translator::CrosstabTranslator::needs_translation(&translated_query) {
+            let translated = crate::translator::CrosstabTranslator::translate_query(&translated_query);
+            Self::apply(&mut fired, "crosstab", &mut translated_query, translated);
+            debug!("Query after crosstab translation: {}", translated_query);
+        }
+
+        // Translate ROW(...) constructors, (col).field access and composite row_to_json() members
+        if crate::translator::CompositeTranslator::needs_translation(&translated_query) {
+            let translated = db.with_session_connection(&session.id, |conn| {
+                Ok(crate::translator::CompositeTranslator::translate_query(&translated_query, conn))
+            }).await?;
+            Self::apply(&mut fired, "composite", &mut translated_query, translated);
+            debug!("Query after composite translation: {}", translated_query);
+        }
+
+        // Analyze arithmetic expressions for type metadata
+        if translation_flags.contains(TranslationFlags::ARITHMETIC) {
+            debug!("Analyzing arithmetic expressions in query");
+            let arithmetic_metadata = crate::translator::ArithmeticAnalyzer::analyze_query(&translated_query);
+            debug!("ArithmeticAnalyzer found {} hints", arithmetic_metadata.column_mappings.len());
+            if !arithmetic_metadata.column_mappings.is_empty() {
+                fired.push("arithmetic");
+            }
+            translation_metadata.merge(arithmetic_metadata);
+            debug!("Total translation metadata after merge: {} hints", translation_metadata.column_mappings.len());
+        }
+
+        // Types resolved through CTEs are more precise than the guesses above
+        if !cte_metadata.column_mappings.is_empty() {
+            if !fired.contains(&"cte") {
+                fired.push("cte");
+            }
+            translation_metadata.merge(cte_metadata);
+        }
+
+        Ok(TranslatedQuery {
+            sql: translated_query,
+            setup,
+            metadata: translation_metadata,
+            flags: translation_flags,
+            fired,
+        })
+    }
+
+    fn record(fired: &mut Vec<&'static str>, name: &'static str, before: &str, after: &str) {
+        if before != after && !fired.contains(&name) {
+            fired.push(name);
+        }
+    }
+
+    fn apply(fired: &mut Vec<&'static str>, name: &'static str, query: &mut String, translated: String) {
+        Self::record(fired, name, query, &translated);
+        *query = translated;
+    }
+
+    fn apply_with_metadata(
+        fired: &mut Vec<&'static str>,
+        name: &'static str,
+        query: &mut String,
+        translated: String,
+        translation_metadata: &mut TranslationMetadata,
+        metadata: TranslationMetadata,
+    ) {
+        if !metadata.column_mappings.is_empty() && !fired.contains(&name) {
+            fired.push(name);
+        }
+        Self::apply(fired, name, query, translated);
+        translation_metadata.merge(metadata);
+    }
+}
@@ -0,0 +1,707 @@
+use crate::error::PgError;
+use crate::functions::sql_functions::{register_temp_function, unregister_temp_function};
+use crate::metadata::OidAllocator;
+use crate::metadata::oid_allocator::{FUNCTION, TRIGGER};
+use crate::protocol::BackendMessage;
+use crate::protocol::messages::NoticeResponse;
+use crate::query::TranslationPipeline;
+use crate::query::sql_utils::{fold_identifier, last_name_part, quote_identifier};
+use crate::rewriter::DecimalQueryRewriter;
+use crate::session::{SessionState, SessionStorage};
+use crate::translator::{SqlFragment, TriggerDefinition, TriggerEvent, TriggerTiming, TriggerTranslator};
+use crate::PgSqliteError;
+use futures::SinkExt;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rusqlite::{Connection, OptionalExtension};
+use sqlparser::ast::{SelectItem, SetExpr, Statement};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use std::sync::Arc;
+use tokio_util::codec::Framed;
+use tracing::debug;
+
+static CREATE_FUNCTION_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^\s*CREATE\s+(OR\s+REPLACE\s+)?FUNCTION\s+((?:"(?:[^"]|"")+"|[\w$]+)(?:\.(?:"(?:[^"]|"")+"|[\w$]+))?)\s*\(([^)]*)\)\s*RETURNS\s+TRIGGER\b(.*)$"#).unwrap()
+});
+
+static FUNCTION_BODY_START: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)\bAS\s+(\$(?:[A-Za-z_]\w*)?\$|')").unwrap()
+});
+
+pub(crate) static LANGUAGE_PATTERN: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\bLANGUAGE\s+'?(\w+)'?").unwrap());
+
+static CREATE_TRIGGER_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^\s*CREATE\s+(OR\s+REPLACE\s+)?TRIGGER\s+("(?:[^"]|"")+"|\w+)\s+(BEFORE|AFTER|INSTEAD\s+OF)\s+(.+?)\s+ON\s+((?:"(?:[^"]|"")+"|\w+)(?:\.(?:"(?:[^"]|"")+"|\w+))?)(.*?)\s*EXECUTE\s+(?:FUNCTION|PROCEDURE)\s+((?:"(?:[^"]|"")+"|\w+)(?:\.(?:"(?:[^"]|"")+"|\w+))?)\s*\(\s*(.*?)\s*\)\s*;?\s*$"#).unwrap()
+});
+
+static FOR_EACH_PATTERN: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)^\s*FOR\s+(?:EACH\s+)?(ROW|STATEMENT)\b\s*(.*)$").unwrap());
+
+static WHEN_PATTERN: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)^WHEN\s*\((.*)\)$").unwrap());
+
+static UPDATE_OF_PATTERN: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)^UPDATE\s+OF\s+(.+)$").unwrap());
+
+static EVENT_SEPARATOR: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\s+OR\s+").unwrap());
+
+static DROP_TRIGGER_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^\s*DROP\s+TRIGGER\s+(IF\s+EXISTS\s+)?("(?:[^"]|"")+"|\w+)\s+ON\s+((?:"(?:[^"]|"")+"|\w+)(?:\.(?:"(?:[^"]|"")+"|\w+))?)(?:\s+(?:CASCADE|RESTRICT))?\s*;?\s*$"#).unwrap()
+});
+
+static DROP_FUNCTION_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^\s*DROP\s+FUNCTION\s+(IF\s+EXISTS\s+)?((?:"(?:[^"]|"")+"|[\w$]+)(?:\.(?:"(?:[^"]|"")+"|[\w$]+))?)\s*(?:\([^)]*\))?(?:\s+(CASCADE|RESTRICT))?\s*;?\s*$"#).unwrap()
+});
+
+static ROW_QUALIFIER: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\b(new|old)\s*\.").unwrap());
+
+/// Handles `CREATE FUNCTION ... RETURNS trigger`, `CREATE TRIGGER`, `DROP TRIGGER`
+/// and `DROP FUNCTION`.
+///
+/// Trigger functions are stored as written in __pgsqlite_functions and triggers in
+/// __pgsqlite_triggers, which back pg_trigger and pg_get_triggerdef(). Creating a
+/// trigger translates its function into SQLite triggers named after the trigger's
+/// OID; replacing the function regenerates them.
+pub struct TriggerHandler;
+
+/// A parsed trigger or trigger function statement
+#[derive(Debug, Clone, PartialEq)]
+pub enum TriggerStatement {
+    CreateFunction {
+        name: String,
+        body: String,
+        or_replace: bool,
+        /// The statement as written
+        definition: String,
+    },
+    CreateTrigger {
+        trigger: TriggerDefinition,
+        function: String,
+        or_replace: bool,
+        /// The statement as written
+        definition: String,
+    },
+    DropTrigger {
+        name: String,
+        table: String,
+        if_exists: bool,
+    },
+    DropFunction {
+        name: String,
+        if_exists: bool,
+        cascade: bool,
+    },
+}
+
+/// A stored trigger
+struct StoredTrigger {
+    oid: i64,
+    name: String,
+    table: String,
+    definition: String,
+}
+
+impl TriggerHandler {
+    /// Whether a query is a CREATE or DROP mentioning TRIGGER or FUNCTION, which every
+    /// trigger statement is; most queries fail on the first keyword
+    pub fn might_be_trigger_ddl(query: &str) -> bool {
+        let trimmed = query.trim_start();
+        let is_ddl = trimmed.get(..6).is_some_and(|prefix| prefix.eq_ignore_ascii_case("CREATE"))
+            || trimmed.get(..4).is_some_and(|prefix| prefix.eq_ignore_ascii_case("DROP"));
+        is_ddl && query.as_bytes().windows(7).any(|w| w.eq_ignore_ascii_case(b"TRIGGER") || w.eq_ignore_ascii_case(b"FUNCTIO"))
+    }
+
+    /// Parse a trigger statement. Functions returning anything but `trigger` are left alone.
+    pub fn parse_trigger(query: &str) -> Result<Option<TriggerStatement>, PgSqliteError> {
+        if !Self::might_be_trigger_ddl(query) {
+            return Ok(None);
+        }
+        let definition = query.trim().trim_end_matches(';').trim_end().to_string();
+
+        if let Some(caps) = CREATE_FUNCTION_PATTERN.captures(query) {
+            if !caps[3].trim().is_empty() {
+                return Err(pg_error("42P13", "trigger functions cannot have declared arguments".to_string()));
+            }
+            let (body, options) = function_body(&caps[4])
+                .ok_or_else(|| pg_error("42P13", "no function body specified".to_string()))?;
+            let language = LANGUAGE_PATTERN.captures(&options)
+                .map(|language| language[1].to_lowercase())
+                .ok_or_else(|| pg_error("42P13", "no language specified".to_string()))?;
+            if language != "plpgsql" {
+                return Err(pg_error("0A000", format!("trigger functions in language \"{language}\" are not supported, only plpgsql")));
+            }
+            return Ok(Some(TriggerStatement::CreateFunction {
+                name: fold_identifier(last_name_part(&caps[2])),
+                body,
+                or_replace: caps.get(1).is_some(),
+                definition,
+            }));
+        }
+
+        if let Some(caps) = CREATE_TRIGGER_PATTERN.captures(query) {
+            return Ok(Some(TriggerStatement::CreateTrigger {
+                trigger: parse_trigger_definition(&caps)?,
+                function: fold_identifier(last_name_part(&caps[7])),
+                or_replace: caps.get(1).is_some(),
+                definition,
+            }));
+        }
+
+        if let Some(caps) = DROP_TRIGGER_PATTERN.captures(query) {
+            return Ok(Some(TriggerStatement::DropTrigger {
+                name: fold_identifier(&caps[2]),
+                table: fold_identifier(last_name_part(&caps[3])),
+                if_exists: caps.get(1).is_some(),
+            }));
+        }
+
+        if let Some(caps) = DROP_FUNCTION_PATTERN.captures(query) {
+            return Ok(Some(TriggerStatement::DropFunction {
+                name: fold_identifier(last_name_part(&caps[2])),
+                if_exists: caps.get(1).is_some(),
+                cascade: caps.get(3).is_some_and(|option| option.as_str().eq_ignore_ascii_case("CASCADE")),
+            }));
+        }
+
+        let upper = query.trim_start().to_uppercase();
+        if upper.starts_with("CREATE CONSTRAINT TRIGGER") {
+            return Err(pg_error("0A000", "constraint triggers are not supported".to_string()));
+        }
+        if upper.split_whitespace().take(4).any(|word| word == "TRIGGER") {
+            return Err(pg_error("42601", "syntax error in trigger statement".to_string()));
+        }
+        Ok(None)
+    }
+
+    pub async fn handle_trigger<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        statement: &TriggerStatement,
+    ) -> Result<(), PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        db.require_local_data("triggers")?;
+        let mut notices = Vec::new();
+        let tag = match statement {
+            TriggerStatement::CreateFunction { name, body, or_replace, definition } => {
+                // Refuse what the translator cannot handle now rather than when a trigger uses it
+                TriggerTranslator::parse_body(body).map_err(PgSqliteError::Validation)?;
+                let dependents = db.with_session_connection(&session.id, |conn| {
+                    Ok(Self::store_function(conn, name, body, *or_replace, definition))
+                }).await??;
+                for trigger in dependents {
+                    let Some(TriggerStatement::CreateTrigger { trigger: parsed, .. }) = Self::parse_trigger(&trigger.definition)? else {
+                        continue;
+                    };
+                    let parsed = TriggerDefinition { table: trigger.table, ..parsed };
+                    Self::install(db, session, trigger.oid, &parsed, body).await?;
+                }
+                "CREATE FUNCTION"
+            }
+            TriggerStatement::CreateTrigger { trigger, function, or_replace, definition } => {
+                let (table, body, existing) = db.with_session_connection(&session.id, |conn| {
+                    Ok(Self::prepare_trigger(conn, trigger, function, *or_replace))
+                }).await??;
+                let trigger = TriggerDefinition { table, ..trigger.clone() };
+                let key = format!("{}.{}", trigger.table, trigger.name);
+                let oid = match existing {
+                    Some(oid) => oid,
+                    None => db.with_session_connection(&session.id, |conn| OidAllocator::assign(conn, TRIGGER, &key)).await?,
+                };
+                if let Err(e) = Self::install(db, session, oid, &trigger, &body).await {
+                    if existing.is_none() {
+                        db.with_session_connection(&session.id, |conn| OidAllocator::release(conn, oid)).await?;
+                    }
+                    return Err(e);
+                }
+                db.with_session_connection(&session.id, |conn| {
+                    conn.execute(
+                        "INSERT OR REPLACE INTO __pgsqlite_triggers (oid, name, table_name, function_name, tgtype, definition) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                        rusqlite::params![oid, trigger.name, trigger.table, function, trigger.tgtype(), definition],
+                    )
+                }).await?;
+                debug!("Created trigger {} on {} with OID {}", trigger.name, trigger.table, oid);
+                "CREATE TRIGGER"
+            }
+            TriggerStatement::DropTrigger { name, table, if_exists } => {
+                let dropped = db.with_session_connection(&session.id, |conn| {
+                    let Some(trigger) = Self::find_trigger(conn, table, name)? else {
+                        return Ok(false);
+                    };
+                    Self::drop_trigger(conn, trigger.oid)?;
+                    Ok(true)
+                }).await?;
+                if !dropped {
+                    let message = format!("trigger \"{name}\" for relation \"{table}\" does not exist");
+                    if !*if_exists {
+                        return Err(pg_error("42704", message));
+                    }
+                    notices.push(format!("{message}, skipping"));
+                }
+                "DROP TRIGGER"
+            }
+            TriggerStatement::DropFunction { name, if_exists, cascade } => {
+                // A session's temporary function shadows a stored one of the same name
+                let dropped = match session.remove_temp_function(name) {
+                    Some(temp) => {
+                        let remaining = session.temp_functions();
+                        db.with_session_connection(&session.id, |conn| {
+                            unregister_temp_function(conn, name, temp.nargs)?;
+                            remaining.iter().try_for_each(|(name, function)| register_temp_function(conn, name, function))
+                        }).await?;
+                        true
+                    }
+                    None => db.with_session_connection(&session.id, |conn| {
+                        Ok(Self::drop_function(conn, name, *cascade))
+                    }).await??,
+                };
+                if !dropped {
+                    let message = format!("function {name}() does not exist");
+                    if !*if_exists {
+                        return Err(pg_error("42883", message));
+                    }
+                    notices.push(format!("{message}, skipping"));
+                }
+                "DROP FUNCTION"
+            }
+        };
+
+        for message in notices {
+            framed.send(BackendMessage::NoticeResponse(NoticeResponse {
+                severity: "NOTICE".to_string(),
+                code: "00000".to_string(),
+                message,
+                detail: None,
+                hint: None,
+                position: None,
+                where_: None,
+            })).await.map_err(PgSqliteError::Io)?;
+        }
+        framed.send(BackendMessage::CommandComplete { tag: tag.to_string() }).await
+            .map_err(PgSqliteError::Io)
+    }
+
+    /// Keep the triggers of a renamed table, whose SQLite triggers SQLite itself updates
+    pub fn rename_table(conn: &Connection, old_name: &str, new_name: &str) -> rusqlite::Result<()> {
+        for trigger in Self::table_triggers(conn, old_name)? {
+            OidAllocator::rename(conn, TRIGGER, &format!("{old_name}.{}", trigger.name), &format!("{new_name}.{}", trigger.name))?;
+            let definition = match CREATE_TRIGGER_PATTERN.captures(&trigger.definition).and_then(|caps| caps.get(5)) {
+                Some(table) => format!("{}{}{}", &trigger.definition[..table.start()], quote_if_needed(new_name), &trigger.definition[table.end()..]),
+                None => trigger.definition.clone(),
+            };
+            conn.execute(
+                "UPDATE __pgsqlite_triggers SET table_name = ?2, definition = ?3 WHERE oid = ?1",
+                rusqlite::params![trigger.oid, new_name, definition],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Whether a BEFORE trigger of the table assigns to NEW, which SQLite applies after
+    /// evaluating RETURNING
+    pub fn assigns_new(conn: &Connection, table: &str) -> rusqlite::Result<bool> {
+        conn.query_row(
+            r"SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'trigger' AND tbl_name = ?1 COLLATE NOCASE AND name LIKE 'pgsqlite\_trigger\_%\_assign' ESCAPE '\')",
+            [table],
+            |row| row.get(0),
+        )
+    }
+
+    /// Forget the triggers of tables that no longer exist; SQLite dropped their triggers with them
+    pub fn prune_triggers(conn: &Connection) -> rusqlite::Result<()> {
+        let has_triggers: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '__pgsqlite_triggers')",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_triggers {
+            return Ok(());
+        }
+        let mut stmt = conn.prepare(
+            "SELECT oid FROM __pgsqlite_triggers WHERE table_name NOT IN (SELECT name FROM sqlite_master WHERE type = 'table')",
+        )?;
+        let orphans: Vec<i64> = stmt.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+        for oid in orphans {
+            conn.execute("DELETE FROM __pgsqlite_triggers WHERE oid = ?1", [oid])?;
+            OidAllocator::release(conn, oid)?;
+        }
+        Ok(())
+    }
+
+    /// Store a trigger function, returning the triggers that use a function it replaced
+    fn store_function(conn: &Connection, name: &str, body: &str, or_replace: bool, definition: &str) -> Result<Vec<StoredTrigger>, PgSqliteError> {
+        let existing: Option<i64> = conn.query_row("SELECT oid FROM __pgsqlite_functions WHERE name = ?1", [name], |row| row.get(0))
+            .optional()?;
+        if existing.is_some() && !or_replace {
+            return Err(pg_error("42723", format!("function \"{name}\" already exists with same argument types")));
+        }
+        let oid = match existing {
+            Some(oid) => oid,
+            None => OidAllocator::assign(conn, FUNCTION, &format!("{name}()"))?,
+        };
+        conn.execute(
+            "INSERT OR REPLACE INTO __pgsqlite_functions (oid, name, language, return_type, body, definition) VALUES (?1, ?2, 'plpgsql', 'trigger', ?3, ?4)",
+            rusqlite::params![oid, name, body, definition],
+        )?;
+        if existing.is_none() {
+            return Ok(Vec::new());
+        }
+        Ok(Self::function_triggers(conn, name)?)
+    }
+
+    /// Check a new trigger against the catalog, returning the table's stored name, the
+    /// function body and the OID of the trigger it replaces
+    fn prepare_trigger(conn: &Connection, trigger: &TriggerDefinition, function: &str, or_replace: bool) -> Result<(String, String, Option<i64>), PgSqliteError> {
+        let table: String = conn.query_row(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name = ?1 COLLATE NOCASE",
+            [&trigger.table],
+            |row| row.get(0),
+        ).optional()?
+            .ok_or_else(|| pg_error("42P01", format!("relation \"{}\" does not exist", trigger.table)))?;
+        let body: String = conn.query_row("SELECT body FROM __pgsqlite_functions WHERE name = ?1", [function], |row| row.get(0))
+            .optional()?
+            .ok_or_else(|| pg_error("42883", format!("function {function}() does not exist")))?;
+        let existing = Self::find_trigger(conn, &table, &trigger.name)?;
+        if existing.is_some() && !or_replace {
+            return Err(pg_error("42710", format!("trigger \"{}\" for relation \"{table}\" already exists", trigger.name)));
+        }
+        Ok((table, body, existing.map(|trigger| trigger.oid)))
+    }
+
+    /// Translate the function body for a trigger and replace the trigger's SQLite triggers
+    async fn install(
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        oid: i64,
+        trigger: &TriggerDefinition,
+        body: &str,
+    ) -> Result<(), PgSqliteError> {
+        let mut statements = TriggerTranslator::parse_body(body).map_err(PgSqliteError::Validation)?;
+        let mut trigger = trigger.clone();
+        let mut fragments = TriggerTranslator::fragments(&mut statements);
+        if let Some(when) = trigger.when.as_mut() {
+            fragments.push(SqlFragment::Expression(when));
+        }
+        for fragment in fragments {
+            match fragment {
+                SqlFragment::Expression(expression) => {
+                    let translated = TranslationPipeline::translate(db, session, &format!("SELECT {expression}")).await?;
+                    if let Some(sql) = translated.sql.strip_prefix("SELECT ") {
+                        let table = trigger.table.clone();
+                        let sql = sql.to_string();
+                        *expression = db.with_session_connection(&session.id, move |conn| {
+                            Ok(decimal_expression(conn, &table, &sql).unwrap_or(sql))
+                        }).await?;
+                    }
+                }
+                SqlFragment::Statement(sql) => *sql = TranslationPipeline::translate(db, session, sql).await?.sql,
+            }
+        }
+
+        let triggers = TriggerTranslator::translate(&trigger, &statements, &format!("pgsqlite_trigger_{oid}"))
+            .map_err(PgSqliteError::Validation)?;
+        db.with_session_connection(&session.id, |conn| {
+            Ok(in_savepoint(conn, |conn| {
+                drop_sqlite_triggers(conn, oid)?;
+                for sql in &triggers {
+                    conn.execute_batch(sql)?;
+                }
+                reorder_assignment_triggers(conn, &trigger.table)
+            }))
+        }).await??;
+        Ok(())
+    }
+
+    fn drop_trigger(conn: &Connection, oid: i64) -> rusqlite::Result<()> {
+        drop_sqlite_triggers(conn, oid)?;
+        conn.execute("DELETE FROM __pgsqlite_triggers WHERE oid = ?1", [oid])?;
+        OidAllocator::release(conn, oid)
+    }
+
+    /// Drop a trigger function, returning false if there is none with this name
+    fn drop_function(conn: &Connection, name: &str, cascade: bool) -> Result<bool, PgSqliteError> {
+        let Some(oid) = conn.query_row("SELECT oid FROM __pgsqlite_functions WHERE name = ?1", [name], |row| row.get::<_, i64>(0))
+            .optional()? else {
+            return Ok(false);
+        };
+        let dependents = Self::function_triggers(conn, name)?;
+        if !dependents.is_empty() && !cascade {
+            return Err(pg_error("2BP01", format!("cannot drop function {name}() because other objects depend on it")));
+        }
+        for trigger in dependents {
+            Self::drop_trigger(conn, trigger.oid)?;
+        }
+        conn.execute("DELETE FROM __pgsqlite_functions WHERE oid = ?1", [oid])?;
+        OidAllocator::release(conn, oid)?;
+        Ok(true)
+    }
+
+    fn find_trigger(conn: &Connection, table: &str, name: &str) -> rusqlite::Result<Option<StoredTrigger>> {
+        conn.query_row(
+            "SELECT oid, name, table_name, definition FROM __pgsqlite_triggers WHERE table_name = ?1 COLLATE NOCASE AND name = ?2",
+            [table, name],
+            stored_trigger,
+        ).optional()
+    }
+
+    fn table_triggers(conn: &Connection, table: &str) -> rusqlite::Result<Vec<StoredTrigger>> {
+        let mut stmt = conn.prepare("SELECT oid, name, table_name, definition FROM __pgsqlite_triggers WHERE table_name = ?1")?;
+        stmt.query_map([table], stored_trigger)?.collect()
+    }
+
+    fn function_triggers(conn: &Connection, function: &str) -> rusqlite::Result<Vec<StoredTrigger>> {
+        let mut stmt = conn.prepare("SELECT oid, name, table_name, definition FROM __pgsqlite_triggers WHERE function_name = ?1 ORDER BY oid")?;
+        stmt.query_map([function], stored_trigger)?.collect()
+    }
+}
+
+fn stored_trigger(row: &rusqlite::Row<'_>) -> rusqlite::Result<StoredTrigger> {
+    Ok(StoredTrigger { oid: row.get(0)?, name: row.get(1)?, table: row.get(2)?, definition: row.get(3)? })
+}
+
+/// The firing conditions of a CREATE TRIGGER statement
+fn parse_trigger_definition(caps: &regex::Captures<'_>) -> Result<TriggerDefinition, PgSqliteError> {
+    let timing = match caps[3].to_uppercase().as_str() {
+        "BEFORE" => TriggerTiming::Before,
+        "AFTER" => TriggerTiming::After,
+        _ => return Err(pg_error("0A000", "INSTEAD OF triggers are not supported".to_string())),
+    };
+
+    let mut events = Vec::new();
+    let mut update_columns = Vec::new();
+    for event in EVENT_SEPARATOR.split(caps[4].trim()) {
+        let event = event.trim();
+        let parsed = if event.eq_ignore_ascii_case("INSERT") {
+            TriggerEvent::Insert
+        } else if event.eq_ignore_ascii_case("DELETE") {
+            TriggerEvent::Delete
+        } else if event.eq_ignore_ascii_case("UPDATE") {
+            TriggerEvent::Update
+        } else if let Some(columns) = UPDATE_OF_PATTERN.captures(event) {
+            update_columns = columns[1].split(',').map(fold_identifier).collect();
+            TriggerEvent::Update
+        } else if event.eq_ignore_ascii_case("TRUNCATE") {
+            return Err(pg_error("0A000", "TRUNCATE triggers are not supported".to_string()));
+        } else {
+            return Err(pg_error("42601", format!("syntax error at or near \"{event}\"")));
+        };
+        if !events.contains(&parsed) {
+            events.push(parsed);
+        }
+    }
+
+    // Without FOR EACH ROW a trigger fires once per statement
+    let clauses = caps[6].trim();
+    let for_each = FOR_EACH_PATTERN.captures(clauses)
+        .filter(|for_each| for_each[1].eq_ignore_ascii_case("ROW"))
+        .ok_or_else(|| {
+            if clauses.to_uppercase().starts_with("REFERENCING") {
+                pg_error("0A000", "transition tables in triggers are not supported".to_string())
+            } else {
+                pg_error("0A000", "statement-level triggers are not supported, only FOR EACH ROW".to_string())
+            }
+        })?;
+    let when = match for_each[2].trim() {
+        "" => None,
+        condition => Some(WHEN_PATTERN.captures(condition)
+            .ok_or_else(|| pg_error("42601", format!("syntax error at or near \"{condition}\"")))?[1]
+            .trim()
+            .to_string()),
+    };
+    if !caps[8].is_empty() {
+        return Err(pg_error("0A000", "trigger function arguments are not supported".to_string()));
+    }
+
+    Ok(TriggerDefinition {
+        name: fold_identifier(&caps[2]),
+        table: fold_identifier(last_name_part(&caps[5])),
+        timing,
+        events,
+        update_columns,
+        when,
+    })
+}
+
+/// The body of a function after its `AS`, in dollar quotes or a string literal, and
+/// the rest of the definition with the body taken out
+pub(crate) fn function_body(definition: &str) -> Option<(String, String)> {
+    let start = FUNCTION_BODY_START.captures(definition)?;
+    let opener = start.get(1)?;
+    let rest = &definition[opener.end()..];
+    let (body, length) = if opener.as_str() == "'" {
+        let mut body = String::new();
+        let mut chars = rest.char_indices().peekable();
+        loop {
+            let (i, c) = chars.next()?;
+            if c == '\'' && chars.next_if(|&(_, next)| next == '\'').is_none() {
+                break (body, i + 1);
+            }
+            body.push(c);
+        }
+    } else {
+        let end = rest.find(opener.as_str())?;
+        (rest[..end].to_string(), end + opener.len())
+    };
+    let options = format!("{} {}", &definition[..start.get(0)?.start()], &rest[length..]);
+    Some((body, options))
+}
+
+/// Run `apply` so that either all or none of its changes are kept
+fn in_savepoint(conn: &Connection, apply: impl FnOnce(&Connection) -> rusqlite::Result<()>) -> Result<(), PgSqliteError> {
+    conn.execute_batch("SAVEPOINT __pgsqlite_trigger_ddl")?;
+    match apply(conn) {
+        Ok(()) => {
+            conn.execute_batch("RELEASE __pgsqlite_trigger_ddl")?;
+            Ok(())
+        }
+        Err(e) => {
+            if let Err(rollback_error) = conn.execute_batch("ROLLBACK TO __pgsqlite_trigger_ddl; RELEASE __pgsqlite_trigger_ddl") {
+                debug!("Failed to roll back trigger DDL: {}", rollback_error);
+            }
+            Err(pg_error("42601", format!("could not create trigger: {e}")))
+        }
+    }
+}
+
+/// Drop the SQLite triggers generated for the trigger with this OID
+fn drop_sqlite_triggers(conn: &Connection, oid: i64) -> rusqlite::Result<()> {
+    let mut stmt = conn.prepare(r"SELECT name FROM sqlite_master WHERE type = 'trigger' AND name LIKE ?1 ESCAPE '\'")?;
+    let names: Vec<String> = stmt.query_map([format!(r"pgsqlite\_trigger\_{oid}\_%")], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    for name in names {
+        conn.execute(&format!("DROP TRIGGER {}", quote_identifier(&name)), [])?;
+    }
+    Ok(())
+}
+
+/// Recreate the triggers applying assignments to NEW so they are the table's newest.
+/// SQLite runs the newest trigger first, and AFTER triggers read the row once the
+/// assignments of the BEFORE triggers are in it.
+fn reorder_assignment_triggers(conn: &Connection, table: &str) -> rusqlite::Result<()> {
+    let mut stmt = conn.prepare(
+        r"SELECT name, sql FROM sqlite_master WHERE type = 'trigger' AND tbl_name = ?1 AND name LIKE 'pgsqlite\_trigger\_%\_assign' ESCAPE '\' ORDER BY rowid",
+    )?;
+    let triggers: Vec<(String, String)> = stmt.query_map([table], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    for (name, sql) in triggers {
+        conn.execute(&format!("DROP TRIGGER {}", quote_identifier(&name)), [])?;
+        conn.execute_batch(&sql)?;
+    }
+    Ok(())
+}
+
+/// A body expression with the arithmetic and comparisons of NUMERIC columns of NEW and
+/// OLD going through the decimal functions, as those columns hold decimal text
+fn decimal_expression(conn: &Connection, table: &str, expression: &str) -> Option<String> {
+    // The rewriter matches qualifiers to aliases as spelled
+    let mut rows: Vec<&str> = ROW_QUALIFIER.captures_iter(expression).filter_map(|caps| caps.get(1)).map(|row| row.as_str()).collect();
+    if rows.is_empty() {
+        return None;
+    }
+    rows.sort_unstable();
+    rows.dedup();
+    let table = quote_if_needed(table);
+    let from = rows.iter().map(|row| format!("{table} AS {row}")).collect::<Vec<_>>().join(", ");
+    let mut statements = Parser::parse_sql(&PostgreSqlDialect {}, &format!("SELECT {expression} FROM {from}")).ok()?;
+    let [statement] = statements.as_mut_slice() else {
+        return None;
+    };
+    let unchanged = statement.to_string();
+    DecimalQueryRewriter::new(conn).rewrite_statement(statement).ok()?;
+    if statement.to_string() == unchanged {
+        return None;
+    }
+    let Statement::Query(query) = statement else {
+        return None;
+    };
+    let SetExpr::Select(select) = query.body.as_ref() else {
+        return None;
+    };
+    match select.projection.as_slice() {
+        [SelectItem::UnnamedExpr(expr)] => Some(expr.to_string()),
+        _ => None,
+    }
+}
+
+pub(crate) fn pg_error(code: &str, message: String) -> PgSqliteError {
+    PgSqliteError::Validation(PgError::Generic { code: code.to_string(), message })
+}
+
+
+fn quote_if_needed(name: &str) -> String {
+    if name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_') {
+        name.to_string()
+    } else {
+        quote_identifier(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_create_function() {
+        let parsed = TriggerHandler::parse_trigger(
+            "CREATE OR REPLACE FUNCTION public.touch() RETURNS trigger AS $body$ BEGIN NEW.updated_at := now(); RETURN NEW; END; $body$ LANGUAGE plpgsql;"
+        ).unwrap();
+        assert_eq!(parsed, Some(TriggerStatement::CreateFunction {
+            name: "touch".to_string(),
+            body: " BEGIN NEW.updated_at := now(); RETURN NEW; END; ".to_string(),
+            or_replace: true,
+            definition: "CREATE OR REPLACE FUNCTION public.touch() RETURNS trigger AS $body$ BEGIN NEW.updated_at := now(); RETURN NEW; END; $body$ LANGUAGE plpgsql".to_string(),
+        }));
+        assert!(matches!(
+            TriggerHandler::parse_trigger("create function f() returns trigger language 'plpgsql' as 'begin raise exception ''no''; end'").unwrap(),
+            Some(TriggerStatement::CreateFunction { body, .. }) if body == "begin raise exception 'no'; end"
+        ));
+
+        assert!(TriggerHandler::parse_trigger("CREATE FUNCTION f() RETURNS trigger AS $$ BEGIN RETURN NEW; END $$ LANGUAGE sql").is_err());
+        assert_eq!(TriggerHandler::parse_trigger("CREATE FUNCTION add(a int) RETURNS int AS $$ SELECT a $$ LANGUAGE sql").unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_create_trigger() {
+        let parsed = TriggerHandler::parse_trigger(
+            "CREATE TRIGGER \"Touch\" BEFORE INSERT OR UPDATE OF price, \"Name\" ON public.items FOR EACH ROW WHEN (NEW.price > 0) EXECUTE FUNCTION touch()"
+        ).unwrap();
+        let Some(TriggerStatement::CreateTrigger { trigger, function, or_replace, .. }) = parsed else {
+            panic!("{parsed:?}");
+        };
+        assert_eq!(trigger, TriggerDefinition {
+            name: "Touch".to_string(),
+            table: "items".to_string(),
+            timing: TriggerTiming::Before,
+            events: vec![TriggerEvent::Insert, TriggerEvent::Update],
+            update_columns: vec!["price".to_string(), "Name".to_string()],
+            when: Some("NEW.price > 0".to_string()),
+        });
+        assert_eq!(function, "touch");
+        assert!(!or_replace);
+
+        let error = |query: &str| match TriggerHandler::parse_trigger(query) {
+            Err(PgSqliteError::Validation(PgError::Generic { code, .. })) => code,
+            other => panic!("{other:?}"),
+        };
+        assert_eq!(error("CREATE TRIGGER t AFTER INSERT ON items EXECUTE FUNCTION f()"), "0A000");
+        assert_eq!(error("CREATE TRIGGER t AFTER TRUNCATE ON items FOR EACH STATEMENT EXECUTE FUNCTION f()"), "0A000");
+        assert_eq!(error("CREATE TRIGGER t INSTEAD OF INSERT ON v FOR EACH ROW EXECUTE PROCEDURE f()"), "0A000");
+        assert_eq!(error("CREATE TRIGGER t AFTER INSERT ON items FOR EACH ROW EXECUTE FUNCTION f('x')"), "0A000");
+        assert_eq!(error("CREATE TRIGGER t ON items"), "42601");
+    }
+
+    #[test]
+    fn test_parse_drop_statements() {
+        assert_eq!(TriggerHandler::parse_trigger("DROP TRIGGER IF EXISTS touch ON public.items CASCADE;").unwrap(), Some(TriggerStatement::DropTrigger {
+            name: "touch".to_string(),
+            table: "items".to_string(),
+            if_exists: true,
+        }));
+        assert_eq!(TriggerHandler::parse_trigger("drop function touch() cascade").unwrap(), Some(TriggerStatement::DropFunction {
+            name: "touch".to_string(),
+            if_exists: false,
+            cascade: true,
+        }));
+        assert_eq!(TriggerHandler::parse_trigger("DROP TABLE triggers").unwrap(), None);
+    }
+}
@@ -254,7 +254,9 @@ fn has_any_special_pattern_fast(bytes: &[u8]) -> bool {
     memchr::memmem::find(bytes, b"GROUP BY").is_some() ||
     memchr::memmem::find(bytes, b"HAVING").is_some() ||
     memchr::memmem::find(bytes, b"unnest").is_some() ||
-    memchr::memmem::find(bytes, b"UNNEST").is_some()
+    memchr::memmem::find(bytes, b"UNNEST").is_some() ||
+    memchr::memmem::find(bytes, b"generate_series").is_some() ||
+    memchr::memmem::find(bytes, b"GENERATE_SERIES").is_some()
 }
 
 /// Check INSERT-specific patterns
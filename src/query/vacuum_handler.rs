@@ -0,0 +1,172 @@
+use crate::error::PgError;
+use crate::protocol::BackendMessage;
+use crate::query::progress::{ProgressCommand, ProgressGuard};
+use crate::session::{SessionState, SessionStorage};
+use crate::PgSqliteError;
+use futures::SinkExt;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::sync::Arc;
+use tokio_util::codec::Framed;
+use tracing::debug;
+
+static VACUUM_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)^\s*VACUUM\b\s*(?:\(([^()]*)\))?\s*((?:(?:FULL|FREEZE|VERBOSE|ANALYZE|ANALYSE)\b\s*)*)(.*?)\s*;?\s*$").unwrap()
+});
+
+/// Options PostgreSQL accepts in `VACUUM (...)`. SQLite always rewrites the whole
+/// file, so everything except ANALYZE is accepted and has no further effect.
+const VACUUM_OPTIONS: &[&str] = &[
+    "full", "freeze", "verbose", "analyze", "analyse", "disable_page_skipping", "skip_locked",
+    "index_cleanup", "process_main", "process_toast", "truncate", "parallel",
+    "skip_database_stats", "only_database_stats", "buffer_usage_limit",
+];
+
+/// Handles `VACUUM [ (options) ] [ FULL ] [ FREEZE ] [ VERBOSE ] [ ANALYZE ] [ table [, ...] ]`.
+///
+/// SQLite's VACUUM rebuilds the whole database file, so it runs once whatever
+/// tables are named; ANALYZE is then run for the named tables. The operation is
+/// visible in pg_stat_progress_vacuum while it runs.
+pub struct VacuumHandler;
+
+/// A parsed VACUUM statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct VacuumStatement {
+    pub analyze: bool,
+    pub tables: Vec<String>,
+}
+
+impl VacuumHandler {
+    /// Whether a query starts with VACUUM
+    pub fn might_be_vacuum(query: &str) -> bool {
+        query.trim_start().get(..6).is_some_and(|prefix| prefix.eq_ignore_ascii_case("VACUUM"))
+    }
+
+    pub fn parse_vacuum(query: &str) -> Result<Option<VacuumStatement>, PgSqliteError> {
+        if !Self::might_be_vacuum(query) {
+            return Ok(None);
+        }
+        let Some(caps) = VACUUM_PATTERN.captures(query) else {
+            return Ok(None);
+        };
+
+        let mut analyze = caps[2].to_lowercase().contains("analy");
+        if let Some(options) = caps.get(1) {
+            for option in options.as_str().split(',').map(str::trim).filter(|o| !o.is_empty()) {
+                let mut words = option.split_whitespace();
+                let name = words.next().unwrap_or_default().to_lowercase();
+                if !VACUUM_OPTIONS.contains(&name.as_str()) {
+                    return Err(PgSqliteError::Validation(PgError::Generic {
+                        code: "42601".to_string(),
+                        message: format!("unrecognized VACUUM option \"{name}\""),
+                    }));
+                }
+                if name.starts_with("analy") {
+                    analyze = !matches!(
+                        words.next().map(|v| v.trim_matches('\'').to_lowercase()).as_deref(),
+                        Some("false" | "off" | "0")
+                    );
+                }
+            }
+        }
+
+        // Column lists (ANALYZE t (a, b)) are dropped; SQLite analyzes whole tables
+        let tables = caps[3].split(',')
+            .map(|table| {
+                let name = table.split('(').next().unwrap_or_default().trim();
+                let name = name.strip_prefix("public.").unwrap_or(name);
+                name.trim_matches('"').to_string()
+            })
+            .filter(|name| !name.is_empty())
+            .collect();
+
+        Ok(Some(VacuumStatement { analyze, tables }))
+    }
+
+    pub async fn handle_vacuum<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        statement: &VacuumStatement,
+    ) -> Result<(), PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        db.require_local_data("VACUUM")?;
+        if session.in_transaction().await {
+            return Err(PgSqliteError::Validation(PgError::Generic {
+                code: "25001".to_string(),
+                message: "VACUUM cannot run inside a transaction block".to_string(),
+            }));
+        }
+
+        let relname = match statement.tables.as_slice() {
+            [table] => Some(table.clone()),
+            _ => None,
+        };
+        let progress = ProgressGuard::start(ProgressCommand::Vacuum, relname, "initializing");
+
+        let pages = db.with_session_connection(&session.id, |conn| {
+            for table in &statement.tables {
+                let exists: bool = conn.query_row(
+                    "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+                    [table],
+                    |row| row.get(0),
+                )?;
+                if !exists {
+                    return Ok(Err(table.clone()));
+                }
+            }
+            conn.query_row("PRAGMA page_count", [], |row| row.get::<_, i64>(0)).map(Ok)
+        }).await?;
+        let pages = pages.map_err(|table| PgSqliteError::Validation(PgError::Generic {
+            code: "42P01".to_string(),
+            message: format!("relation \"{table}\" does not exist"),
+        }))? as u64;
+
+        progress.set_total(pages);
+        progress.set_phase("vacuuming heap");
+        db.with_session_connection(&session.id, |conn| conn.execute_batch("VACUUM")).await?;
+        progress.advance(pages, 0);
+
+        if statement.analyze {
+            progress.set_phase("performing final cleanup");
+            let analyze = if statement.tables.is_empty() {
+                "ANALYZE".to_string()
+            } else {
+                statement.tables.iter().map(|table| format!("ANALYZE \"{table}\";")).collect::<String>()
+            };
+            db.with_session_connection(&session.id, |conn| conn.execute_batch(&analyze)).await?;
+        }
+
+        debug!("VACUUM processed {} pages", pages);
+        framed.send(BackendMessage::CommandComplete {
+            tag: "VACUUM".to_string(),
+        }).await.map_err(PgSqliteError::Io)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(query: &str) -> VacuumStatement {
+        VacuumHandler::parse_vacuum(query).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_parse_vacuum() {
+        assert_eq!(parse("VACUUM"), VacuumStatement { analyze: false, tables: vec![] });
+        assert_eq!(parse("vacuum full verbose analyze public.orders, \"Line Items\" (qty);"), VacuumStatement {
+            analyze: true,
+            tables: vec!["orders".to_string(), "Line Items".to_string()],
+        });
+        assert_eq!(parse("VACUUM (VERBOSE, ANALYZE) orders"), VacuumStatement { analyze: true, tables: vec!["orders".to_string()] });
+        assert_eq!(parse("VACUUM (ANALYZE false, PARALLEL 4)"), VacuumStatement { analyze: false, tables: vec![] });
+
+        assert!(VacuumHandler::parse_vacuum("VACUUM (SPEED 11)").is_err());
+        assert_eq!(VacuumHandler::parse_vacuum("SELECT 'VACUUM'").unwrap(), None);
+    }
+}
@@ -0,0 +1,329 @@
+use crate::error::PgError;
+use crate::metadata::TypeMetadata;
+use crate::protocol::BackendMessage;
+use crate::query::TranslationPipeline;
+use crate::query::sql_utils::{quote_identifier, unqualified, unquote_identifier};
+use crate::session::{SessionState, SessionStorage};
+use crate::translator::TranslationMetadata;
+use crate::types::{PgType, SchemaTypeMapper};
+use crate::PgSqliteError;
+use futures::SinkExt;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rusqlite::Connection;
+use std::sync::Arc;
+use tokio_util::codec::Framed;
+use tracing::debug;
+
+static CREATE_VIEW_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^\s*CREATE\s+(OR\s+REPLACE\s+)?(TEMP\s+|TEMPORARY\s+)?VIEW\s+(IF\s+NOT\s+EXISTS\s+)?((?:"(?:[^"]|"")+"|[\w$]+)(?:\.(?:"(?:[^"]|"")+"|[\w$]+))?)\s*(?:\(([^)]*)\)\s*)?AS\s+(.+?)\s*;?\s*$"#).unwrap()
+});
+
+static DROP_VIEW_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)^\s*DROP\s+VIEW\s+(IF\s+EXISTS\s+)?(.+?)(?:\s+(?:CASCADE|RESTRICT))?\s*;?\s*$").unwrap()
+});
+
+static CHECK_OPTION: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)\s+WITH\s+(?:CASCADED\s+|LOCAL\s+)?CHECK\s+OPTION$").unwrap()
+});
+
+/// Handles `CREATE [OR REPLACE] VIEW` and `DROP VIEW`.
+///
+/// The view body goes through the same translator chain as a SELECT, and the
+/// PostgreSQL type of each view column is recorded in __pgsqlite_schema under the
+/// view's name, so selecting from the view converts values exactly like selecting
+/// from its tables and the catalogs report the declared types.
+pub struct ViewHandler;
+
+/// A parsed view statement
+#[derive(Debug, Clone, PartialEq)]
+pub enum ViewStatement {
+    Create {
+        name: String,
+        /// Explicit column names, empty when the view takes them from its query
+        columns: Vec<String>,
+        query: String,
+        or_replace: bool,
+        if_not_exists: bool,
+        temporary: bool,
+    },
+    Drop {
+        names: Vec<String>,
+        if_exists: bool,
+    },
+}
+
+impl ViewHandler {
+    /// Whether a query is a CREATE or DROP that mentions VIEW
+    pub fn might_be_view_ddl(query: &str) -> bool {
+        let trimmed = query.trim_start();
+        let is_ddl = trimmed.get(..6).is_some_and(|prefix| prefix.eq_ignore_ascii_case("CREATE"))
+            || trimmed.get(..4).is_some_and(|prefix| prefix.eq_ignore_ascii_case("DROP"));
+        is_ddl && query.as_bytes().windows(4).any(|w| w.eq_ignore_ascii_case(b"VIEW"))
+    }
+
+    pub fn parse_view(query: &str) -> Option<ViewStatement> {
+        if !Self::might_be_view_ddl(query) {
+            return None;
+        }
+        if let Some(caps) = CREATE_VIEW_PATTERN.captures(query) {
+            let columns = caps.get(5)
+                .map(|list| list.as_str().split(',').map(unquote_identifier).filter(|c| !c.is_empty()).collect())
+                .unwrap_or_default();
+            return Some(ViewStatement::Create {
+                name: unqualified(&caps[4]),
+                columns,
+                query: CHECK_OPTION.replace(&caps[6], "").to_string(),
+                or_replace: caps.get(1).is_some(),
+                if_not_exists: caps.get(3).is_some(),
+                temporary: caps.get(2).is_some(),
+            });
+        }
+        let caps = DROP_VIEW_PATTERN.captures(query)?;
+        Some(ViewStatement::Drop {
+            names: caps[2].split(',').map(unqualified).collect(),
+            if_exists: caps.get(1).is_some(),
+        })
+    }
+
+    pub async fn handle_view<T>(
+        framed: &mut Framed<T, crate::protocol::PostgresCodec>,
+        db: &Arc<dyn SessionStorage>,
+        session: &Arc<SessionState>,
+        statement: &ViewStatement,
+    ) -> Result<(), PgSqliteError>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        db.require_local_data("views")?;
+        let tag = match statement {
+            ViewStatement::Create { name, columns, query, or_replace, if_not_exists, temporary } => {
+                let translated = TranslationPipeline::translate(db, session, query).await?;
+                let exists = db.with_session_connection(&session.id, |conn| relation_type(conn, name)).await?;
+                match exists.as_deref() {
+                    Some("view") if *if_not_exists => {
+                        debug!("View {} already exists, skipping", name);
+                        return send_complete(framed, "CREATE VIEW").await;
+                    }
+                    Some("view") if *or_replace => {
+                        db.execute_with_session(&format!("DROP VIEW {}", quote_identifier(name)), &session.id).await?;
+                    }
+                    Some(_) => {
+                        return Err(PgSqliteError::Validation(PgError::Generic {
+                            code: "42P07".to_string(),
+                            message: format!("relation \"{name}\" already exists"),
+                        }));
+                    }
+                    None => {}
+                }
+
+                let column_list = if columns.is_empty() {
+                    String::new()
+                } else {
+                    format!(" ({})", columns.iter().map(|c| quote_identifier(c)).collect::<Vec<_>>().join(", "))
+                };
+                let sql = format!(
+                    "CREATE {}VIEW {}{} AS {}",
+                    if *temporary { "TEMP " } else { "" },
+                    quote_identifier(name),
+                    column_list,
+                    translated.sql,
+                );
+                db.execute_with_session(&sql, &session.id).await?;
+
+                let recorded = db.with_session_connection(&session.id, |conn| {
+                    Self::record_column_types(conn, name, query, &translated.sql, &translated.metadata)
+                }).await?;
+                debug!("Recorded {} column types for view {}", recorded, name);
+                db.get_schema_cache().invalidate(name);
+                "CREATE VIEW"
+            }
+            ViewStatement::Drop { names, if_exists } => {
+                for name in names {
+                    let sql = format!("DROP VIEW {}{}", if *if_exists { "IF EXISTS " } else { "" }, quote_identifier(name));
+                    db.execute_with_session(&sql, &session.id).await?;
+                    db.with_session_connection(&session.id, |conn| Self::forget_column_types(conn, name)).await?;
+                    db.get_schema_cache().invalidate(name);
+                }
+                db.with_session_connection(&session.id, crate::query::CommentHandler::prune_relation_comments).await?;
+                "DROP VIEW"
+            }
+        };
+
+        send_complete(framed, tag).await
+    }
+
+    /// Store the PostgreSQL type of each column of a just-created view, returning how many were stored.
+    ///
+    /// Columns taken directly from a table keep that column's declared type, so
+    /// modifiers such as varchar(50) or numeric(10,2) survive; expressions use the
+    /// type the translators hinted, then the aggregate and declared-type fallbacks
+    /// the row description would use.
+    pub fn record_column_types(
+        conn: &Connection,
+        view: &str,
+        query: &str,
+        sql: &str,
+        metadata: &TranslationMetadata,
+    ) -> rusqlite::Result<usize> {
+        let types = Self::column_types(conn, query, sql, metadata)?;
+
+        // Final column names and SQLite types come from the view itself, which applies any column list
+        let mut stmt = conn.prepare("SELECT name, type FROM pragma_table_info(?1) ORDER BY cid")?;
+        let columns: Vec<(String, String)> = stmt.query_map([view], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Self::forget_column_types(conn, view)?;
+        for ((column, sqlite_type), pg_type) in columns.iter().zip(&types) {
+            conn.execute(
+                "INSERT OR REPLACE INTO __pgsqlite_schema (table_name, column_name, pg_type, sqlite_type) VALUES (?1, ?2, ?3, ?4)",
+                [view, column.as_str(), pg_type.as_str(), sqlite_type.as_str()],
+            )?;
+        }
+        Ok(columns.len().min(types.len()))
+    }
+
+    /// Remove the recorded column types of a dropped view
+    pub fn forget_column_types(conn: &Connection, view: &str) -> rusqlite::Result<()> {
+        conn.execute("DELETE FROM __pgsqlite_schema WHERE table_name = ?1", [view])?;
+        crate::query::executor::forget_table_schema_info(view);
+        Ok(())
+    }
+
+    /// The PostgreSQL type of each result column of the view's translated query
+    fn column_types(conn: &Connection, query: &str, sql: &str, metadata: &TranslationMetadata) -> rusqlite::Result<Vec<String>> {
+        let stmt = conn.prepare(sql)?;
+        let first_table = crate::query::join_type_inference::extract_all_tables_from_query(query)
+            .into_iter()
+            .next()
+            .map(|(table, _)| table.strip_prefix("public.").unwrap_or(&table).to_string());
+
+        let types = stmt.columns_with_metadata().iter().zip(stmt.columns())
+            .map(|(origin, column)| {
+                // SQLite resolves aliases, joins and other views down to the table column
+                if let (Some(table), Some(source_column)) = (origin.table_name(), origin.origin_name())
+                    && let Ok(Some(pg_type)) = TypeMetadata::get_pg_type(conn, table, source_column) {
+                    return column_type(&pg_type);
+                }
+                let name = column.name();
+                if let Some(suggested) = metadata.get_hint(name).and_then(|h| h.suggested_type.as_ref()) {
+                    return suggested.name().to_string();
+                }
+                let oid = SchemaTypeMapper::get_aggregate_return_type_with_query(name, Some(conn), first_table.as_deref(), Some(query))
+                    .or_else(|| column.decl_type().map(SchemaTypeMapper::sqlite_type_to_pg_oid))
+                    .unwrap_or_else(|| PgType::Text.to_oid());
+                SchemaTypeMapper::pg_oid_to_type_name(oid).to_string()
+            })
+            .collect();
+        Ok(types)
+    }
+}
+
+/// The type a view column gets from a table column; serial columns are plain integers
+fn column_type(pg_type: &str) -> String {
+    match pg_type.to_uppercase().as_str() {
+        "SERIAL" | "SERIAL4" => "INTEGER".to_string(),
+        "BIGSERIAL" | "SERIAL8" => "BIGINT".to_string(),
+        "SMALLSERIAL" | "SERIAL2" => "SMALLINT".to_string(),
+        _ => pg_type.to_string(),
+    }
+}
+
+/// `table` or `view` for an existing relation, None when the name is free
+fn relation_type(conn: &Connection, name: &str) -> rusqlite::Result<Option<String>> {
+    use rusqlite::OptionalExtension;
+    conn.query_row(
+        "SELECT type FROM sqlite_master WHERE type IN ('table', 'view') AND name = ?1 COLLATE NOCASE
+         UNION ALL SELECT type FROM sqlite_temp_master WHERE type IN ('table', 'view') AND name = ?1 COLLATE NOCASE",
+        [name],
+        |row| row.get(0),
+    ).optional()
+}
+
+async fn send_complete<T>(framed: &mut Framed<T, crate::protocol::PostgresCodec>, tag: &str) -> Result<(), PgSqliteError>
+where
+    T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    framed.send(BackendMessage::CommandComplete { tag: tag.to_string() }).await
+        .map_err(PgSqliteError::Io)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_create_view() {
+        assert_eq!(ViewHandler::parse_view("CREATE VIEW public.big_orders AS SELECT id, total FROM orders WHERE total > 100;"), Some(ViewStatement::Create {
+            name: "big_orders".to_string(),
+            columns: Vec::new(),
+            query: "SELECT id, total FROM orders WHERE total > 100".to_string(),
+            or_replace: false,
+            if_not_exists: false,
+            temporary: false,
+        }));
+        assert_eq!(ViewHandler::parse_view("create or replace temp view \"Totals\" (id, \"Sum\") as\n  select id, total::text from orders with local check option"), Some(ViewStatement::Create {
+            name: "Totals".to_string(),
+            columns: vec!["id".to_string(), "Sum".to_string()],
+            query: "select id, total::text from orders".to_string(),
+            or_replace: true,
+            if_not_exists: false,
+            temporary: true,
+        }));
+        assert!(matches!(ViewHandler::parse_view("CREATE VIEW IF NOT EXISTS v AS SELECT 1"), Some(ViewStatement::Create { if_not_exists: true, .. })));
+
+        assert_eq!(ViewHandler::parse_view("CREATE MATERIALIZED VIEW totals AS SELECT 1"), None);
+        assert_eq!(ViewHandler::parse_view("CREATE TABLE reviews (id INTEGER)"), None);
+        assert_eq!(ViewHandler::parse_view("SELECT * FROM big_orders"), None);
+    }
+
+    #[test]
+    fn test_parse_drop_view() {
+        assert_eq!(ViewHandler::parse_view("DROP VIEW IF EXISTS public.a, \"B\" CASCADE;"), Some(ViewStatement::Drop {
+            names: vec!["a".to_string(), "B".to_string()],
+            if_exists: true,
+        }));
+        assert_eq!(ViewHandler::parse_view("drop view totals"), Some(ViewStatement::Drop {
+            names: vec!["totals".to_string()],
+            if_exists: false,
+        }));
+        assert_eq!(ViewHandler::parse_view("DROP TABLE reviews"), None);
+    }
+
+    #[test]
+    fn test_record_column_types() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE __pgsqlite_schema (table_name TEXT, column_name TEXT, pg_type TEXT, sqlite_type TEXT, PRIMARY KEY (table_name, column_name));
+             CREATE TABLE orders (id INTEGER PRIMARY KEY AUTOINCREMENT, total DECIMAL, placed_at INTEGER, note TEXT);
+             INSERT INTO __pgsqlite_schema VALUES
+                ('orders', 'id', 'SERIAL', 'INTEGER PRIMARY KEY AUTOINCREMENT'),
+                ('orders', 'total', 'NUMERIC(10,2)', 'DECIMAL'),
+                ('orders', 'placed_at', 'TIMESTAMP', 'INTEGER'),
+                ('orders', 'note', 'VARCHAR(20)', 'TEXT');"
+        ).unwrap();
+        let sql = "SELECT id, total, placed_at AS placed, note, count(*) AS n FROM orders GROUP BY id";
+        conn.execute(&format!("CREATE VIEW summary (order_id, total, placed, note, n) AS {sql}"), []).unwrap();
+
+        let recorded = ViewHandler::record_column_types(&conn, "summary", sql, sql, &TranslationMetadata::new()).unwrap();
+        assert_eq!(recorded, 5);
+
+        let mut stmt = conn.prepare("SELECT column_name, pg_type FROM __pgsqlite_schema WHERE table_name = 'summary' ORDER BY rowid").unwrap();
+        let types: Vec<(String, String)> = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(types, [
+            ("order_id".to_string(), "INTEGER".to_string()),
+            ("total".to_string(), "NUMERIC(10,2)".to_string()),
+            ("placed".to_string(), "TIMESTAMP".to_string()),
+            ("note".to_string(), "VARCHAR(20)".to_string()),
+            ("n".to_string(), "int8".to_string()),
+        ]);
+
+        ViewHandler::forget_column_types(&conn, "summary").unwrap();
+        let remaining: i64 = conn.query_row("SELECT COUNT(*) FROM __pgsqlite_schema WHERE table_name = 'summary'", [], |row| row.get(0)).unwrap();
+        assert_eq!(remaining, 0);
+    }
+}
@@ -1,14 +1,21 @@
 use sqlparser::ast::{
     Expr, BinaryOperator, Function, FunctionArg, FunctionArgExpr, FunctionArguments, FunctionArgumentList,
     ObjectName, ObjectNamePart, Ident, SelectItem, Query, SetExpr, Statement, DataType, Cte, TableFactor,
-    GroupByExpr, OrderBy, OrderByKind
+    GroupByExpr, OrderBy, OrderByKind, UnaryOperator, Value, ValueWithSpan, AssignmentTarget
 };
 use rusqlite::Connection;
+use rust_decimal::Decimal;
 use std::collections::HashMap;
+use std::str::FromStr;
 use crate::types::PgType;
+use crate::functions::decimal_functions::format_numeric;
+use crate::validator::NumericValidator;
 use super::expression_type_resolver::{ExpressionTypeResolver, QueryContext};
 use super::implicit_cast_detector::{ImplicitCastDetector, ImplicitCast};
 
+/// The precision and scale of a NUMERIC column, `None` when its type has no modifier
+type NumericTypmod = Option<(i32, i32)>;
+
 /// Cache for schema type lookups to avoid repeated database queries
 #[derive(Debug, Clone)]
 struct SchemaCache {
@@ -120,7 +127,7 @@ impl<'a> DecimalQueryRewriter<'a> {
             // Check if this table has any decimal columns in the schema
             let query = "SELECT 1 FROM __pgsqlite_schema 
                          WHERE table_name = ?1 
-                         AND sqlite_type = 'DECIMAL' 
+                         AND (sqlite_type = 'DECIMAL' OR ((pg_type LIKE 'NUMERIC%' OR pg_type LIKE 'DECIMAL%') AND pg_type NOT LIKE '%]'))
                          LIMIT 1";
             
             // Check using exists query
@@ -152,12 +159,35 @@ impl<'a> DecimalQueryRewriter<'a> {
                         _ => return Ok(()),
                     };
                     let tables = self.extract_table_names_from_query(source);
-                    let mut all_tables = vec![table_name];
+                    let mut all_tables = vec![table_name.clone()];
                     all_tables.extend(tables);
                     
                     if !self.any_table_has_decimal_columns(&all_tables) {
                         return Ok(()); // Skip rewriting
                     }
+                    
+                    // Values written to NUMERIC columns are stored as canonical decimal text
+                    if let SetExpr::Values(values) = source.body.as_mut() {
+                        let table_columns = self.numeric_columns(&table_name);
+                        let columns: Vec<String> = if insert.columns.is_empty() {
+                            table_columns.iter().map(|(name, _)| name.clone()).collect()
+                        } else {
+                            insert.columns.iter().map(|ident| ident.value.clone()).collect()
+                        };
+                        let numeric_columns: HashMap<String, NumericTypmod> = table_columns
+                            .into_iter()
+                            .filter_map(|(name, typmod)| typmod.map(|typmod| (name.to_lowercase(), typmod)))
+                            .collect();
+                        let context = QueryContext::default();
+                        for row in &mut values.rows {
+                            for (value, column) in row.iter_mut().zip(&columns) {
+                                if let Some(typmod) = numeric_columns.get(&column.to_lowercase()) {
+                                    self.store_as_decimal_text(value, *typmod, &context)?;
+                                }
+                            }
+                        }
+                        return Ok(());
+                    }
                     self.rewrite_query(source)
                 } else {
                     Ok(())
@@ -180,10 +210,22 @@ impl<'a> DecimalQueryRewriter<'a> {
                     
                     // Rewrite assignment expressions only if table has decimal columns
                     if has_decimal_columns {
-                        // For UPDATE assignments, we don't want to wrap simple numeric literals
-                        // because rust_decimal can't handle very large numbers (>28 digits)
+                        let numeric_columns: HashMap<String, NumericTypmod> = self.numeric_columns(&context.default_table.clone().unwrap_or_default())
+                            .into_iter()
+                            .filter_map(|(name, typmod)| typmod.map(|typmod| (name.to_lowercase(), typmod)))
+                            .collect();
                         for assignment in assignments {
-                            self.rewrite_update_assignment(&mut assignment.value, &context)?;
+                            let column = match &assignment.target {
+                                AssignmentTarget::ColumnName(name) => name.0.last().map(|part| part.to_string().trim_matches('"').to_lowercase()),
+                                AssignmentTarget::Tuple(_) => None,
+                            };
+                            match column.and_then(|column| numeric_columns.get(&column)) {
+                                // Values written to NUMERIC columns are stored as canonical decimal text
+                                Some(typmod) => self.store_as_decimal_text(&mut assignment.value, *typmod, &context)?,
+                                // For UPDATE assignments, we don't want to wrap simple numeric literals
+                                // because rust_decimal can't handle very large numbers (>28 digits)
+                                None => self.rewrite_update_assignment(&mut assignment.value, &context)?,
+                            }
                         }
                     }
                 }
@@ -283,7 +325,8 @@ impl<'a> DecimalQueryRewriter<'a> {
         
         // Rewrite ORDER BY
         if let Some(order_by) = &mut query.order_by {
-            self.rewrite_order_by(order_by, &context)?;
+            let decimal_aliases = Self::decimal_result_aliases(&query.body);
+            self.rewrite_order_by(order_by, &context, &decimal_aliases)?;
         }
         
         Ok(())
@@ -570,21 +613,29 @@ impl<'a> DecimalQueryRewriter<'a> {
                     self.rewrite_aggregate_to_decimal(func, context)?;
                 } else if is_math && (has_decimal_arg || has_implicit_cast) {
                     self.rewrite_math_function_to_decimal(func)?;
+                } else if func_name.eq_ignore_ascii_case("json_object") {
+                    self.rewrite_json_object_members(func, context);
                 }
             }
             Expr::Nested(inner) => {
                 self.rewrite_expression(inner, context)?;
             }
-            Expr::InList { expr, list, .. } => {
-                self.rewrite_expression(expr, context)?;
-                for item in list {
+            Expr::InList { expr: tested, list, .. } => {
+                self.rewrite_expression(tested, context)?;
+                for item in list.iter_mut() {
                     self.rewrite_expression(item, context)?;
                 }
+                if let Some(compared) = self.compare_as_decimal(expr, context)? {
+                    *expr = compared;
+                }
             }
-            Expr::Between { expr, low, high, .. } => {
-                self.rewrite_expression(expr, context)?;
+            Expr::Between { expr: tested, low, high, .. } => {
+                self.rewrite_expression(tested, context)?;
                 self.rewrite_expression(low, context)?;
                 self.rewrite_expression(high, context)?;
+                if let Some(compared) = self.compare_as_decimal(expr, context)? {
+                    *expr = compared;
+                }
             }
             Expr::Subquery(subquery) => {
                 self.rewrite_query_with_context(subquery, Some(context))?;
@@ -664,6 +715,9 @@ impl<'a> DecimalQueryRewriter<'a> {
             Eq => "decimal_eq",
             Lt => "decimal_lt",
             Gt => "decimal_gt",
+            LtEq => "decimal_le",
+            GtEq => "decimal_ge",
+            NotEq => "decimal_ne",
             _ => return Ok(Expr::BinaryOp { 
                 left: Box::new(left), 
                 op, 
@@ -777,6 +831,40 @@ impl<'a> DecimalQueryRewriter<'a> {
         Ok(Expr::Function(func))
     }
     
+    /// IN and BETWEEN over a NUMERIC value as decimal comparisons, as NUMERIC text
+    /// compares by value only through the decimal functions
+    fn compare_as_decimal(&mut self, expr: &Expr, context: &QueryContext) -> Result<Option<Expr>, String> {
+        let (tested, bounds, negated) = match expr {
+            Expr::InList { expr: tested, list, negated } if !list.is_empty() => {
+                (tested, list.iter().map(|item| (BinaryOperator::Eq, item)).collect::<Vec<_>>(), *negated)
+            }
+            Expr::Between { expr: tested, low, high, negated } => {
+                (tested, vec![(BinaryOperator::GtEq, low.as_ref()), (BinaryOperator::LtEq, high.as_ref())], *negated)
+            }
+            _ => return Ok(None),
+        };
+        let tested_type = self.resolver.resolve_expr_type(tested, context);
+        if tested_type != PgType::Numeric {
+            return Ok(None);
+        }
+        
+        // Any of the list's values, or both of the bounds
+        let joiner = if matches!(expr, Expr::InList { .. }) { BinaryOperator::Or } else { BinaryOperator::And };
+        let mut compared: Option<Expr> = None;
+        for (op, bound) in bounds {
+            let bound_type = self.resolver.resolve_expr_type(bound, context);
+            let comparison = self.create_decimal_function_expr(op, tested.as_ref().clone(), bound.clone(), tested_type, bound_type, context)?;
+            compared = Some(match compared {
+                Some(left) => Expr::BinaryOp { left: Box::new(left), op: joiner.clone(), right: Box::new(comparison) },
+                None => comparison,
+            });
+        }
+        Ok(compared.map(|compared| {
+            let compared = Expr::Nested(Box::new(compared));
+            if negated { Expr::UnaryOp { op: UnaryOperator::Not, expr: Box::new(compared) } } else { compared }
+        }))
+    }
+    
     /// Wrap expression in decimal_from_text function
     fn wrap_in_decimal_from_text(&mut self, expr: Expr, context: &QueryContext) -> Expr {
         // Check if this is a float-returning math function - don't wrap these
@@ -809,20 +897,10 @@ impl<'a> DecimalQueryRewriter<'a> {
             return arithmetic_expr;
         }
         
-        // First cast to text if needed
+        // Number literals are passed as written, as going through REAL would round them
         let text_expr = match &expr {
-            Expr::Value(val) => {
-                match &val.value {
-                    sqlparser::ast::Value::Number(_, _) => {
-                        Expr::Cast {
-                            expr: Box::new(expr),
-                            data_type: DataType::Text,
-                            format: None,
-                            kind: sqlparser::ast::CastKind::Cast,
-                        }
-                    }
-                    _ => expr,
-                }
+            Expr::Value(ValueWithSpan { value: Value::Number(digits, _), .. }) => {
+                Expr::value(Value::SingleQuotedString(digits.clone()))
             }
             _ => expr,
         };
@@ -888,19 +966,133 @@ impl<'a> DecimalQueryRewriter<'a> {
         }
     }
     
+    /// The columns of a table in declaration order, with the typmod of those that are NUMERIC
+    fn numeric_columns(&self, table: &str) -> Vec<(String, Option<NumericTypmod>)> {
+        let query = "SELECT c.name, s.pg_type FROM pragma_table_info(?1) c
+                     LEFT JOIN __pgsqlite_schema s ON s.table_name = ?1 AND s.column_name = c.name
+                     ORDER BY c.cid";
+        let Ok(mut stmt) = self.resolver.conn().prepare(query) else {
+            return Vec::new();
+        };
+        stmt.query_map([table], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)))
+            .map(|rows| rows.flatten().map(|(name, pg_type)| (name, pg_type.as_deref().and_then(Self::numeric_typmod))).collect())
+            .unwrap_or_default()
+    }
+    
+    /// `Some` with the precision and scale of a NUMERIC(p,s) type name, `None` for other types
+    fn numeric_typmod(pg_type: &str) -> Option<NumericTypmod> {
+        let (base, modifier) = pg_type.split_once('(').unwrap_or((pg_type, ""));
+        let base = base.trim();
+        if !(base.eq_ignore_ascii_case("numeric") || base.eq_ignore_ascii_case("decimal")) || pg_type.trim_end().ends_with(']') {
+            return None;
+        }
+        let mut parts = modifier.trim_end_matches(')').split(',').map(|part| part.trim().parse::<i32>());
+        Some(match (parts.next(), parts.next()) {
+            (Some(Ok(precision)), Some(Ok(scale))) => Some((precision, scale)),
+            (Some(Ok(precision)), None) => Some((precision, 0)),
+            _ => None,
+        })
+    }
+    
+    /// Rewrite a value written to a NUMERIC column so it is stored as canonical decimal text:
+    /// literals keep all their digits instead of going through REAL, input is checked
+    /// against the column's precision and scale, and computed values are rounded to its scale
+    fn store_as_decimal_text(&mut self, expr: &mut Expr, typmod: NumericTypmod, context: &QueryContext) -> Result<(), String> {
+        let literal = match expr {
+            Expr::Value(ValueWithSpan { value: Value::Null, .. }) => return Ok(()),
+            Expr::Identifier(ident) if ident.quote_style.is_none() && ident.value.eq_ignore_ascii_case("DEFAULT") => return Ok(()),
+            Expr::Value(ValueWithSpan { value: Value::Number(digits, _), .. }) => Some(digits.clone()),
+            Expr::UnaryOp { op: op @ (UnaryOperator::Minus | UnaryOperator::Plus), expr: inner } => match inner.as_ref() {
+                Expr::Value(ValueWithSpan { value: Value::Number(digits, _), .. }) => {
+                    Some(if *op == UnaryOperator::Minus { format!("-{digits}") } else { digits.clone() })
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(digits) = literal {
+            // 1.23e2 and 00123.45 are stored the way PostgreSQL prints them, numbers too
+            // long for Decimal as written
+            let digits = Decimal::from_str(&digits)
+                .or_else(|_| Decimal::from_scientific(&digits))
+                .map(|decimal| decimal.to_string())
+                .unwrap_or(digits);
+            // Literals that fit the column are formatted here, the rest fail in numeric_cast
+            let digits = match typmod {
+                Some((precision, scale)) if NumericValidator::validate_value(&digits, precision, scale).is_ok() => {
+                    *expr = Expr::value(Value::SingleQuotedString(format_numeric(&digits, scale)));
+                    return Ok(());
+                }
+                _ => digits,
+            };
+            *expr = Expr::value(Value::SingleQuotedString(digits));
+        }
+        
+        let is_input = matches!(expr, Expr::Value(ValueWithSpan { value: Value::SingleQuotedString(_) | Value::Placeholder(_), .. }));
+        if !is_input {
+            self.rewrite_expression(expr, context)?;
+        }
+        if let Some((precision, scale)) = typmod {
+            let name = if is_input { "numeric_cast" } else { "numeric_format" };
+            *expr = Expr::Function(Function {
+                name: ObjectName(vec![ObjectNamePart::Identifier(Ident::new(name))]),
+                args: FunctionArguments::List(FunctionArgumentList {
+                    duplicate_treatment: None,
+                    args: [expr.clone(), Expr::value(Value::Number(precision.to_string(), false)), Expr::value(Value::Number(scale.to_string(), false))]
+                        .into_iter()
+                        .map(|arg| FunctionArg::Unnamed(FunctionArgExpr::Expr(arg)))
+                        .collect(),
+                    clauses: vec![],
+                }),
+                over: None,
+                uses_odbc_syntax: false,
+                parameters: FunctionArguments::None,
+                filter: None,
+                null_treatment: None,
+                within_group: vec![],
+            });
+        }
+        Ok(())
+    }
+    
     /// Rewrite ORDER BY clause
-    fn rewrite_order_by(&mut self, order_by: &mut OrderBy, context: &QueryContext) -> Result<(), String> {
+    /// Aliases of result columns computed by decimal functions, e.g. `total` in
+    /// `decimal_sum(amount) AS total`
+    fn decimal_result_aliases(body: &SetExpr) -> Vec<String> {
+        let SetExpr::Select(select) = body else {
+            return Vec::new();
+        };
+        select.projection.iter().filter_map(|item| match item {
+            SelectItem::ExprWithAlias { expr: Expr::Function(func), alias }
+                if func.name.to_string().to_lowercase().starts_with("decimal_") => Some(alias.value.clone()),
+            _ => None,
+        }).collect()
+    }
+    
+    fn rewrite_order_by(&mut self, order_by: &mut OrderBy, context: &QueryContext, decimal_aliases: &[String]) -> Result<(), String> {
         match &mut order_by.kind {
             OrderByKind::Expressions(order_exprs) => {
                 for order_expr in order_exprs {
                     // First rewrite the expression normally
                     self.rewrite_expression(&mut order_expr.expr, context)?;
                     
+                    // Decimal functions return text, so sort their results numerically
+                    if let Expr::Function(func) = &order_expr.expr
+                        && func.name.to_string().to_lowercase().starts_with("decimal_")
+                    {
+                        order_expr.expr = Self::sorted_as_decimal(order_expr.expr.clone());
+                    }
+                    
                     // If the expression is a simple column reference to a decimal type,
                     // we need to ensure it sorts correctly
                     if let Expr::Identifier(_) | Expr::CompoundIdentifier(_) = &order_expr.expr {
                         let expr_type = self.resolver.resolve_expr_type(&order_expr.expr, context);
-                        if expr_type == PgType::Numeric || expr_type == PgType::Float4 || expr_type == PgType::Float8 {
+                        // Aliases of decimal function results hold text as well
+                        let is_decimal_alias = matches!(&order_expr.expr, Expr::Identifier(ident)
+                            if decimal_aliases.iter().any(|alias| alias.eq_ignore_ascii_case(&ident.value)));
+                        if is_decimal_alias || expr_type == PgType::Numeric {
+                            order_expr.expr = Self::sorted_as_decimal(order_expr.expr.clone());
+                        } else if expr_type == PgType::Float4 || expr_type == PgType::Float8 {
                             // Wrap in CAST to REAL for proper numeric ordering
                             order_expr.expr = Expr::Cast {
                                 expr: Box::new(order_expr.expr.clone()),
@@ -919,6 +1111,20 @@ impl<'a> DecimalQueryRewriter<'a> {
         Ok(())
     }
     
+    /// `CAST(expr AS TEXT) COLLATE decimal`, which sorts numbers and the text decimal
+    /// functions return by value without rounding them to REAL
+    fn sorted_as_decimal(expr: Expr) -> Expr {
+        Expr::Collate {
+            expr: Box::new(Expr::Cast {
+                expr: Box::new(expr),
+                data_type: DataType::Text,
+                format: None,
+                kind: sqlparser::ast::CastKind::Cast,
+            }),
+            collation: ObjectName(vec![ObjectNamePart::Identifier(Ident::new("decimal"))]),
+        }
+    }
+    
     /// Check if function is an aggregate
     fn is_aggregate_function(&self, name: &ObjectName) -> bool {
         let func_name = name.to_string().to_uppercase();
@@ -972,76 +1178,6 @@ impl<'a> DecimalQueryRewriter<'a> {
         Ok(())
     }
     
-    /// Check if a column needs decimal wrapping based on its storage type
-    fn column_needs_decimal_wrapping(&mut self, expr: &Expr, context: &QueryContext) -> bool {
-        match expr {
-            Expr::Identifier(ident) => {
-                self.check_column_decimal_storage(None, &ident.value, context)
-            }
-            Expr::CompoundIdentifier(parts) => {
-                if parts.len() >= 2 {
-                    let table = &parts[parts.len() - 2].value;
-                    let column = &parts[parts.len() - 1].value;
-                    self.check_column_decimal_storage(Some(table), column, context)
-                } else {
-                    false
-                }
-            }
-            _ => false,
-        }
-    }
-    
-    /// Check if a column is stored as DECIMAL in SQLite
-    fn check_column_decimal_storage(&mut self, table: Option<&str>, column: &str, context: &QueryContext) -> bool {
-        // Determine actual table name
-        let table_name = if let Some(t) = table {
-            // Check if it's an alias
-            context.table_aliases.get(t)
-                .cloned()
-                .unwrap_or_else(|| t.to_string())
-        } else {
-            // Use default table
-            context.default_table.clone().unwrap_or_default()
-        };
-        
-        // Check if this is a derived table or CTE (these don't have SQLite storage info)
-        if table_name == "__derived__" || context.cte_columns.contains_key(&table_name) || 
-           (table.is_none() && context.derived_table_columns.contains_key(&table_name)) {
-            // For derived tables/CTEs, check if the column type suggests decimal storage
-            let pg_type = self.resolver.resolve_expr_type(&if table.is_some() {
-                Expr::CompoundIdentifier(vec![
-                    sqlparser::ast::Ident::new(table.unwrap()),
-                    sqlparser::ast::Ident::new(column)
-                ])
-            } else {
-                Expr::Identifier(sqlparser::ast::Ident::new(column))
-            }, context);
-            return pg_type == PgType::Numeric; // Only NUMERIC types need wrapping from derived tables
-        }
-        
-        // Query SQLite schema to check storage type
-        let query = "SELECT sqlite_type FROM __pgsqlite_schema 
-                     WHERE table_name = ?1 AND column_name = ?2 AND sqlite_type = 'DECIMAL'";
-        
-        match self.resolver.conn().query_row(query, [&table_name, column], |_| Ok(())) {
-            Ok(_) => {
-                // Found DECIMAL storage, but need to check if it's a NUMERIC type specifically
-                // (vs FLOAT types which should not be wrapped)
-                let pg_type_query = "SELECT pg_type FROM __pgsqlite_schema 
-                                     WHERE table_name = ?1 AND column_name = ?2";
-                if let Ok(pg_type_str) = self.resolver.conn().query_row(pg_type_query, [&table_name, column], |row| {
-                    row.get::<_, String>(0)
-                }) {
-                    // Only wrap NUMERIC types, not FLOAT types
-                    pg_type_str == "NUMERIC"
-                } else {
-                    false
-                }
-            }
-            Err(_) => false,
-        }
-    }
-    
     /// Rewrite expression specifically for implicit casts (always processes)
     fn rewrite_expression_for_implicit_casts(&mut self, expr: &mut Expr, context: &QueryContext) -> Result<(), String> {
         match expr {
@@ -1094,16 +1230,22 @@ impl<'a> DecimalQueryRewriter<'a> {
             Expr::Nested(inner) => {
                 self.rewrite_expression_for_implicit_casts(inner, context)?;
             }
-            Expr::InList { expr, list, .. } => {
-                self.rewrite_expression_for_implicit_casts(expr, context)?;
-                for item in list {
+            Expr::InList { expr: tested, list, .. } => {
+                self.rewrite_expression_for_implicit_casts(tested, context)?;
+                for item in list.iter_mut() {
                     self.rewrite_expression_for_implicit_casts(item, context)?;
                 }
+                if let Some(compared) = self.compare_as_decimal(expr, context)? {
+                    *expr = compared;
+                }
             }
-            Expr::Between { expr, low, high, .. } => {
-                self.rewrite_expression_for_implicit_casts(expr, context)?;
+            Expr::Between { expr: tested, low, high, .. } => {
+                self.rewrite_expression_for_implicit_casts(tested, context)?;
                 self.rewrite_expression_for_implicit_casts(low, context)?;
                 self.rewrite_expression_for_implicit_casts(high, context)?;
+                if let Some(compared) = self.compare_as_decimal(expr, context)? {
+                    *expr = compared;
+                }
             }
             Expr::Subquery(subquery) => {
                 self.rewrite_query_with_context(subquery, Some(context))?;
@@ -1139,32 +1281,63 @@ impl<'a> DecimalQueryRewriter<'a> {
         Ok(())
     }
 
+    /// NUMERIC members of json_object() are JSON numbers, not the decimal text they are stored as
+    fn rewrite_json_object_members(&mut self, func: &mut Function, context: &QueryContext) {
+        let FunctionArguments::List(list) = &mut func.args else {
+            return;
+        };
+        for arg in list.args.iter_mut().skip(1).step_by(2) {
+            if let FunctionArg::Unnamed(FunctionArgExpr::Expr(value)) = arg
+                && matches!(value, Expr::Identifier(_) | Expr::CompoundIdentifier(_))
+                && self.resolver.resolve_expr_type(value, context) == PgType::Numeric {
+                    *value = Expr::Function(Function {
+                        name: ObjectName(vec![ObjectNamePart::Identifier(Ident::new("json"))]),
+                        args: FunctionArguments::List(FunctionArgumentList {
+                            duplicate_treatment: None,
+                            args: vec![FunctionArg::Unnamed(FunctionArgExpr::Expr(value.clone()))],
+                            clauses: vec![],
+                        }),
+                        over: None,
+                        uses_odbc_syntax: false,
+                        parameters: FunctionArguments::None,
+                        filter: None,
+                        null_treatment: None,
+                        within_group: vec![],
+                    });
+                }
+        }
+    }
+    
     /// Rewrite aggregate function to use decimal version
     fn rewrite_aggregate_to_decimal(&mut self, func: &mut Function, context: &QueryContext) -> Result<(), String> {
         let func_name = func.name.to_string().to_uppercase();
             
-        // For now, we'll use SQLite's built-in aggregates with proper wrapping
-        // In a real implementation, we would register custom aggregate functions
+        // MIN/MAX use SQLite's built-ins, SUM/AVG switch to the exact
+        // decimal_sum/decimal_avg aggregates
         match func_name.as_str() {
             "SUM" | "AVG" | "MIN" | "MAX" => {
-                // Ensure argument is wrapped in decimal conversion if needed
                 if let FunctionArguments::List(list) = &mut func.args
-                    && !list.args.is_empty()
-                        && let FunctionArg::Unnamed(FunctionArgExpr::Expr(arg)) = &mut list.args[0] {
-                            // Check if argument is already a decimal function
-                            let is_decimal_func = matches!(
-                                arg, 
-                                Expr::Function(f) if f.name.to_string().to_uppercase().starts_with("DECIMAL_")
-                            );
-                            
-                            // Check if this column is stored as DECIMAL in SQLite (needs wrapping)
-                            let needs_decimal_wrapping = self.column_needs_decimal_wrapping(arg, context);
-                            
-                            // Wrap if not already a decimal function AND needs decimal wrapping
-                            if !is_decimal_func && needs_decimal_wrapping {
-                                *arg = self.wrap_in_decimal_from_text(arg.clone(), context);
-                            }
+                    && let Some(FunctionArg::Unnamed(FunctionArgExpr::Expr(arg))) = list.args.first_mut() {
+                        if func_name == "MIN" || func_name == "MAX" {
+                            // MIN/MAX compare with their argument's collation, so compare decimal text by value
+                            *arg = Self::sorted_as_decimal(arg.clone());
+                        } else if !matches!(arg, Expr::Function(f) if f.name.to_string().to_uppercase().starts_with("DECIMAL_")) {
+                            *arg = self.wrap_in_decimal_from_text(arg.clone(), context);
                         }
+                    }
+                
+                // SQLite's SUM/AVG accumulate in floating point, use the exact versions,
+                // which are window functions too
+                if func_name == "SUM" || func_name == "AVG" {
+                    // Keep the caller's spelling so SUM becomes DECIMAL_SUM and sum becomes decimal_sum
+                    let original_name = func.name.to_string();
+                    let decimal_name = if original_name.chars().any(|c| c.is_ascii_lowercase()) {
+                        format!("decimal_{original_name}")
+                    } else {
+                        format!("DECIMAL_{original_name}")
+                    };
+                    func.name = ObjectName(vec![ObjectNamePart::Identifier(Ident::new(decimal_name))]);
+                }
             }
             _ => {}
         }
@@ -1,12 +1,29 @@
 use sqlparser::ast::{
     Expr, BinaryOperator, UnaryOperator, Value, Function, FunctionArg, FunctionArgExpr, 
-    FunctionArguments, Query, TableFactor, Join, SetExpr, ValueWithSpan, Cte, SelectItem,
+    FunctionArguments, Query, TableFactor, Join, Select, SetExpr, ValueWithSpan, Cte, SelectItem,
     SelectItemQualifiedWildcardKind, ObjectNamePart
 };
 use std::collections::HashMap;
 use rusqlite::Connection;
 use crate::types::{PgType, SchemaTypeMapper};
 
+/// Columns of the pragma table-valued functions
+const PRAGMA_COLUMNS: [(&str, &str, PgType); 13] = [
+    ("pragma_table_info", "cid", PgType::Int4),
+    ("pragma_table_info", "name", PgType::Text),
+    ("pragma_table_info", "type", PgType::Text),
+    ("pragma_table_info", "notnull", PgType::Int4),
+    ("pragma_table_info", "dflt_value", PgType::Text),
+    ("pragma_table_info", "pk", PgType::Int4),
+    ("pragma_table_xinfo", "cid", PgType::Int4),
+    ("pragma_table_xinfo", "name", PgType::Text),
+    ("pragma_table_xinfo", "type", PgType::Text),
+    ("pragma_table_xinfo", "notnull", PgType::Int4),
+    ("pragma_table_xinfo", "dflt_value", PgType::Text),
+    ("pragma_table_xinfo", "pk", PgType::Int4),
+    ("pragma_table_xinfo", "hidden", PgType::Int4),
+];
+
 /// Context for resolving types within a query
 #[derive(Debug, Clone, Default)]
 pub struct QueryContext {
@@ -18,6 +35,8 @@ pub struct QueryContext {
     pub cte_columns: HashMap<String, Vec<(String, PgType)>>,
     /// Maps derived table aliases to their column types
     pub derived_table_columns: HashMap<String, Vec<(String, PgType)>>,
+    /// Tables of the FROM clause and its joins, in order
+    pub tables: Vec<String>,
 }
 
 impl QueryContext {
@@ -62,7 +81,7 @@ impl<'a> ExpressionTypeResolver<'a> {
         }
         
         // Extract table information from query body
-        if let SetExpr::Select(select) = &*query.body {
+        if let Some(select) = Self::leftmost_select(&query.body) {
             for table in &select.from {
                 self.process_table_with_joins(&table.relation, &table.joins, &mut context);
             }
@@ -76,10 +95,14 @@ impl<'a> ExpressionTypeResolver<'a> {
         let cte_name = cte.alias.name.value.clone();
         let mut column_types = Vec::new();
         
-        // Analyze the CTE query to determine column types
-        if let SetExpr::Select(select) = &*cte.query.body {
-            // Build a temporary context for the CTE
-            let mut cte_context = QueryContext::default();
+        // Analyze the CTE query to determine column types. A recursive CTE takes its
+        // types from the non-recursive term, the leftmost one of its UNION.
+        if let Some(select) = Self::leftmost_select(&cte.query.body) {
+            // Build a temporary context for the CTE, which can read the CTEs before it
+            let mut cte_context = QueryContext {
+                cte_columns: context.cte_columns.clone(),
+                ..QueryContext::default()
+            };
             
             // Process tables in the CTE
             for table in &select.from {
@@ -105,10 +128,9 @@ impl<'a> ExpressionTypeResolver<'a> {
                     }
                     SelectItem::Wildcard(_) => {
                         // For wildcards, we need to get all columns from the referenced tables
-                        if let Some(table) = &cte_context.default_table
-                            && let Ok(cols) = self.get_table_columns(table) {
-                                column_types.extend(cols);
-                            }
+                        if let Some(table) = cte_context.default_table.clone() {
+                            column_types.extend(self.relation_columns(&table, &cte_context));
+                        }
                     }
                     SelectItem::QualifiedWildcard(name, _) => {
                         let table_name = match name {
@@ -122,28 +144,56 @@ impl<'a> ExpressionTypeResolver<'a> {
                         let actual_table = cte_context.table_aliases.get(&table_name)
                             .cloned()
                             .unwrap_or(table_name);
-                        if let Ok(cols) = self.get_table_columns(&actual_table) {
-                            column_types.extend(cols);
-                        }
+                        column_types.extend(self.relation_columns(&actual_table, &cte_context));
                     }
                 }
             }
         }
         
+        // A column list renames the columns whatever the query calls them
+        for ((name, _), column) in column_types.iter_mut().zip(&cte.alias.columns) {
+            *name = column.name.value.clone();
+        }
+        
         context.cte_columns.insert(cte_name, column_types);
     }
     
+    /// The first SELECT of a query body, which names and types the columns of a set operation
+    pub fn leftmost_select(body: &SetExpr) -> Option<&Select> {
+        match body {
+            SetExpr::Select(select) => Some(select),
+            SetExpr::Query(query) => Self::leftmost_select(&query.body),
+            SetExpr::SetOperation { left, .. } => Self::leftmost_select(left),
+            _ => None,
+        }
+    }
+    
     /// Process a table and its joins to build context
     pub fn process_table_with_joins(&mut self, table: &TableFactor, joins: &[Join], context: &mut QueryContext) {
         match table {
             TableFactor::Table { name, alias, .. } => {
                 let table_name = name.to_string();
+                context.tables.push(table_name.clone());
                 
                 if let Some(alias) = alias {
                     let alias_name = alias.name.value.clone();
-                    
+
+                    // A column definition list, as in crosstab(...) AS ct(region text, q1 int), types the function's columns
+                    let defined: Vec<(String, PgType)> = alias.columns.iter()
+                        .filter_map(|column| {
+                            let oid = SchemaTypeMapper::pg_type_string_to_oid(&column.data_type.as_ref()?.to_string());
+                            Some((column.name.value.clone(), PgType::from_oid(oid)?))
+                        })
+                        .collect();
+                    if !defined.is_empty() && defined.len() == alias.columns.len() {
+                        context.derived_table_columns.insert(alias_name.clone(), defined);
+                        context.table_aliases.insert(alias_name.clone(), "__derived__".to_string());
+                        if context.default_table.is_none() {
+                            context.default_table = Some(alias_name);
+                        }
+                    }
                     // Check if this table name refers to a CTE
-                    if let Some(cte_columns) = context.cte_columns.get(&table_name) {
+                    else if let Some(cte_columns) = context.cte_columns.get(&table_name) {
                         // Map the CTE columns to the alias
                         context.derived_table_columns.insert(alias_name.clone(), cte_columns.clone());
                         context.table_aliases.insert(alias_name, table_name.clone());
@@ -223,6 +273,14 @@ impl<'a> ExpressionTypeResolver<'a> {
         }
     }
     
+    /// Columns of a CTE or table
+    pub fn relation_columns(&mut self, name: &str, context: &QueryContext) -> Vec<(String, PgType)> {
+        match context.cte_columns.get(name) {
+            Some(columns) => columns.clone(),
+            None => self.get_table_columns(name).unwrap_or_default(),
+        }
+    }
+    
     /// Get all columns from a table
     fn get_table_columns(&mut self, table_name: &str) -> Result<Vec<(String, PgType)>, String> {
         let mut columns = Vec::new();
@@ -299,6 +357,13 @@ impl<'a> ExpressionTypeResolver<'a> {
                 }
             }
             Expr::Nested(expr) => self.resolve_expr_type(expr, context),
+            // Predicates always produce booleans
+            Expr::IsNull(_) | Expr::IsNotNull(_) | Expr::IsTrue(_) | Expr::IsNotTrue(_) |
+            Expr::IsFalse(_) | Expr::IsNotFalse(_) | Expr::IsUnknown(_) | Expr::IsNotUnknown(_) |
+            Expr::IsDistinctFrom(..) | Expr::IsNotDistinctFrom(..) |
+            Expr::InList { .. } | Expr::InSubquery { .. } | Expr::Between { .. } |
+            Expr::Like { .. } | Expr::ILike { .. } | Expr::SimilarTo { .. } |
+            Expr::AnyOp { .. } | Expr::AllOp { .. } | Expr::Exists { .. } => PgType::Bool,
             Expr::Subquery(subquery) => {
                 // For scalar subqueries, analyze the projection
                 let subquery_context = self.build_context(subquery);
@@ -325,8 +390,55 @@ impl<'a> ExpressionTypeResolver<'a> {
         }
     }
     
+    /// Check whether an expression yields a boolean regardless of its operand types
+    ///
+    /// SQLite evaluates comparisons and predicates to the integers 0 and 1, so the
+    /// result column carries no type of its own. This only looks at the shape of the
+    /// expression and needs no schema access.
+    pub fn is_boolean_expr(expr: &Expr) -> bool {
+        use BinaryOperator::*;
+
+        match expr {
+            Expr::Value(ValueWithSpan { value: Value::Boolean(_), .. }) => true,
+            Expr::BinaryOp { op, .. } => matches!(op, Eq | NotEq | Lt | LtEq | Gt | GtEq | And | Or | Xor),
+            Expr::UnaryOp { op: UnaryOperator::Not, .. } => true,
+            Expr::IsNull(_) | Expr::IsNotNull(_) | Expr::IsTrue(_) | Expr::IsNotTrue(_) |
+            Expr::IsFalse(_) | Expr::IsNotFalse(_) | Expr::IsUnknown(_) | Expr::IsNotUnknown(_) |
+            Expr::IsDistinctFrom(..) | Expr::IsNotDistinctFrom(..) |
+            Expr::InList { .. } | Expr::InSubquery { .. } | Expr::Between { .. } |
+            Expr::Like { .. } | Expr::ILike { .. } | Expr::SimilarTo { .. } |
+            Expr::AnyOp { .. } | Expr::AllOp { .. } | Expr::Exists { .. } => true,
+            Expr::Cast { data_type, .. } => {
+                matches!(data_type.to_string().to_uppercase().as_str(), "BOOLEAN" | "BOOL")
+            }
+            Expr::Nested(inner) => Self::is_boolean_expr(inner),
+            // COALESCE and the like have the type of their arguments, so one boolean makes them all boolean
+            Expr::Function(func) if matches!(
+                func.name.to_string().to_lowercase().as_str(),
+                "coalesce" | "nullif" | "greatest" | "least"
+            ) => match &func.args {
+                FunctionArguments::List(list) => list.args.iter().any(|arg| matches!(
+                    arg, FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) if Self::is_boolean_expr(expr)
+                )),
+                _ => false,
+            },
+            // A CASE is boolean when every branch it can return is
+            Expr::Case { conditions, else_result, .. } => {
+                !conditions.is_empty()
+                    && conditions.iter().all(|when| Self::is_boolean_expr(&when.result))
+                    && else_result.as_deref().is_none_or(Self::is_boolean_expr)
+            }
+            _ => false,
+        }
+    }
+
     /// Resolve column type from schema
     fn resolve_column_type(&mut self, table: Option<&str>, column: &str, context: &QueryContext) -> PgType {
+        self.column_type(table, column, context).unwrap_or(PgType::Text)
+    }
+    
+    /// Type of a column reference, None when no table in the context has the column
+    pub fn column_type(&mut self, table: Option<&str>, column: &str, context: &QueryContext) -> Option<PgType> {
         // Determine actual table name
         let table_name = if let Some(t) = table {
             // Check if it's an alias
@@ -341,8 +453,7 @@ impl<'a> ExpressionTypeResolver<'a> {
             // This handles unqualified columns in JOIN queries
             for actual_table in context.table_aliases.values() {
                 if let Some(type_oid) = SchemaTypeMapper::get_type_from_schema(self.conn, actual_table, column) {
-                    let pg_type = PgType::from_oid(type_oid).unwrap_or(PgType::Text);
-                    return pg_type;
+                    return PgType::from_oid(type_oid);
                 }
             }
             // If not found in any aliased table, return empty string to continue normal flow
@@ -356,11 +467,11 @@ impl<'a> ExpressionTypeResolver<'a> {
                 && let Some(columns) = context.derived_table_columns.get(alias) {
                     for (col_name, col_type) in columns {
                         if col_name == column {
-                            return *col_type;
+                            return Some(*col_type);
                         }
                     }
                 }
-            return PgType::Text;
+            return None;
         }
         
         // Also check if the table_name itself is a derived table (when column is unqualified)
@@ -368,35 +479,51 @@ impl<'a> ExpressionTypeResolver<'a> {
             && let Some(columns) = context.derived_table_columns.get(&table_name) {
                 for (col_name, col_type) in columns {
                     if col_name == column {
-                        return *col_type;
+                        return Some(*col_type);
                     }
                 }
             }
         
         // Check if this is a CTE
         if let Some(cte_columns) = context.cte_columns.get(&table_name) {
-            for (col_name, col_type) in cte_columns {
-                if col_name == column {
-                    return *col_type;
-                }
+            if let Some((_, col_type)) = cte_columns.iter().find(|(col_name, _)| col_name == column) {
+                return Some(*col_type);
+            }
+            // An unqualified column can also come from another CTE joined in
+            if table.is_none() {
+                return context.cte_columns.values().flatten()
+                    .find(|(col_name, _)| col_name == column)
+                    .map(|(_, col_type)| *col_type);
             }
-            return PgType::Text;
+            return None;
         }
-        
+
         // Check cache first
         let cache_key = format!("{table_name}.{column}");
         if let Some(&pg_type) = self.type_cache.get(&cache_key) {
-            return pg_type;
+            return Some(pg_type);
         }
         
         // Look up in schema
+        // Types without a PgType (ENUMs and their arrays) are left to the caller
         if let Some(type_oid) = SchemaTypeMapper::get_type_from_schema(self.conn, &table_name, column) {
-            let pg_type = PgType::from_oid(type_oid).unwrap_or(PgType::Text);
+            let pg_type = PgType::from_oid(type_oid)?;
             self.type_cache.insert(cache_key, pg_type);
-            pg_type
-        } else {
-            PgType::Text // Default if not found
+            return Some(pg_type);
+        }
+        
+        // An unqualified column of a table joined in without an alias
+        if table.is_none() {
+            for other in context.tables.iter().filter(|other| **other != table_name) {
+                if let Some(type_oid) = SchemaTypeMapper::get_type_from_schema(self.conn, other, column) {
+                    return PgType::from_oid(type_oid);
+                }
+            }
         }
+        // SQLite's pragma table-valued functions don't declare their column types
+        PRAGMA_COLUMNS.iter()
+            .find(|(function, name, _)| table_name.eq_ignore_ascii_case(function) && *name == column)
+            .map(|(_, _, pg_type)| *pg_type)
     }
     
     /// Resolve type of a literal value
@@ -534,7 +661,7 @@ impl<'a> ExpressionTypeResolver<'a> {
             "LOWER" | "UPPER" | "TRIM" | "SUBSTR" => PgType::Text,
             // Our decimal functions
             "DECIMAL_ADD" | "DECIMAL_SUB" | "DECIMAL_MUL" | "DECIMAL_DIV" => PgType::Numeric,
-            "DECIMAL_FROM_TEXT" => PgType::Numeric,
+            "DECIMAL_FROM_TEXT" | "DECIMAL_SUM" | "DECIMAL_AVG" => PgType::Numeric,
             "DECIMAL_TO_TEXT" => PgType::Text,
             // Date/Time functions (SQLite built-ins)
             "DATE" => PgType::Date,
@@ -216,7 +216,12 @@ impl SchemaDriftDetector {
     }
     
     fn normalize_sqlite_type(type_str: &str) -> String {
-        let upper = type_str.to_uppercase();
+        let mut upper = type_str.to_uppercase();
+        
+        // Serial primary keys record their column constraints along with the type
+        if let Some(constraint_pos) = upper.find(" PRIMARY KEY") {
+            upper.truncate(constraint_pos);
+        }
         
         // Remove any size/precision specifications
         let base_type = if let Some(paren_pos) = upper.find('(') {
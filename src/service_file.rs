@@ -0,0 +1,177 @@
+//! Connection details for the databases a server serves, for wiring tools to it.
+//!
+//! With --print-urls the server prints a `postgresql://` URL per database once it
+//! listens, and with --service-file it writes a section per database to a libpq
+//! connection service file, so psql and other libpq clients connect with
+//! `service=<database>`. Sections of other services in the file are kept.
+//!
+//! pgsqlite has no password authentication, so there is nothing for a `.pgpass`
+//! file to hold: the sections and URLs carry the `sslmode` the server's TLS
+//! settings call for instead.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::config::Config;
+
+/// The role the URLs and sections connect as; pgsqlite accepts any
+const USER: &str = "postgres";
+
+/// How to reach one database
+#[derive(Debug, Clone, PartialEq)]
+pub struct Service {
+    /// The service name, the database's name
+    pub name: String,
+    /// `localhost`, or the Unix socket directory when TCP is disabled
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    /// `require` with --ssl, `disable` without
+    pub sslmode: &'static str,
+    /// Whether the server asks for a client certificate, see --ssl-client-cert
+    pub client_cert: bool,
+}
+
+impl Service {
+    /// The libpq connection URL
+    pub fn url(&self) -> String {
+        let database = encode(&self.name);
+        if self.host.starts_with('/') {
+            format!("postgresql://{}@/{}?host={}&port={}&sslmode={}", encode(&self.user), database, encode(&self.host), self.port, self.sslmode)
+        } else {
+            format!("postgresql://{}@{}:{}/{}?sslmode={}", encode(&self.user), self.host, self.port, database, self.sslmode)
+        }
+    }
+
+    /// The `[name]` section of a connection service file
+    pub fn section(&self) -> String {
+        let mut section = format!(
+            "[{}]\nhost={}\nport={}\ndbname={}\nuser={}\nsslmode={}\n",
+            self.name, self.host, self.port, self.name, self.user, self.sslmode
+        );
+        if self.client_cert {
+            section.push_str("# The server requires a client certificate: set sslcert and sslkey to it\n");
+        }
+        section
+    }
+}
+
+/// A service for every database the server serves, the --database one first
+pub fn services(config: &Config) -> Vec<Service> {
+    let host = if config.no_tcp { config.socket_dir.clone() } else { "localhost".to_string() };
+    config.database_names().into_iter()
+        .map(|name| Service {
+            name,
+            host: host.clone(),
+            port: config.port,
+            user: USER.to_string(),
+            sslmode: if config.ssl { "require" } else { "disable" },
+            client_cert: config.ssl && config.ssl_client_cert != "off",
+        })
+        .collect()
+}
+
+/// Write the services' sections to the service file at `path`, replacing the sections
+/// of the same name and keeping the others
+pub fn write(path: &Path, services: &[Service]) -> Result<()> {
+    let existing = match std::fs::read_to_string(path) {
+        Ok(existing) => existing,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read service file {}", path.display())),
+    };
+    std::fs::write(path, merge(&existing, services))
+        .with_context(|| format!("Failed to write service file {}", path.display()))
+}
+
+/// The service file `existing` with the services' sections in place of any it has
+/// under their names, appended after the sections of other services
+pub fn merge(existing: &str, services: &[Service]) -> String {
+    let mut merged = String::new();
+    let mut replaced = false;
+    for line in existing.lines() {
+        if let Some(name) = line.trim().strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+            replaced = services.iter().any(|service| service.name == name.trim());
+        }
+        if !replaced {
+            merged.push_str(line);
+            merged.push('\n');
+        }
+    }
+    let kept = merged.trim_end().len();
+    merged.truncate(kept);
+    for service in services {
+        if !merged.is_empty() {
+            merged.push_str("\n\n");
+        }
+        merged.push_str(service.section().trim_end());
+    }
+    merged.push('\n');
+    merged
+}
+
+/// Percent-encode all but the characters URLs leave as they are
+fn encode(value: &str) -> String {
+    value.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(name: &str, host: &str) -> Service {
+        Service {
+            name: name.to_string(),
+            host: host.to_string(),
+            port: 5432,
+            user: USER.to_string(),
+            sslmode: "disable",
+            client_cert: false,
+        }
+    }
+
+    #[test]
+    fn test_url() {
+        assert_eq!(service("main", "localhost").url(), "postgresql://postgres@localhost:5432/main?sslmode=disable");
+        assert_eq!(
+            service("my db", "/tmp/pg sockets").url(),
+            "postgresql://postgres@/my%20db?host=%2Ftmp%2Fpg%20sockets&port=5432&sslmode=disable"
+        );
+    }
+
+    #[test]
+    fn test_merge_replaces_own_sections() {
+        let existing = "# my services\n[prod]\nhost=db.example.com\n\n[main]\nhost=old\nport=1\n\n[other]\nhost=x\n";
+        let merged = merge(existing, &[service("main", "localhost"), service("logs", "localhost")]);
+        assert_eq!(merged, "\
+# my services
+[prod]
+host=db.example.com
+
+[other]
+host=x
+
+[main]
+host=localhost
+port=5432
+dbname=main
+user=postgres
+sslmode=disable
+
+[logs]
+host=localhost
+port=5432
+dbname=logs
+user=postgres
+sslmode=disable
+");
+        // Writing again leaves the file as it is
+        assert_eq!(merge(&merged, &[service("main", "localhost"), service("logs", "localhost")]), merged);
+        assert_eq!(merge("", &[service("main", "localhost")]), service("main", "localhost").section());
+    }
+}
@@ -0,0 +1,265 @@
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// How often a session waiting for an advisory lock checks whether it was freed or the
+/// wait was canceled
+pub const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// An advisory lock key. As in PostgreSQL, the one-bigint and two-integer forms of the
+/// functions lock separate key spaces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LockKey {
+    Single(i64),
+    Pair(i32, i32),
+}
+
+/// Session locks are held until unlocked or the session ends, transaction locks until
+/// the transaction ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockScope {
+    Session,
+    Transaction,
+}
+
+/// Why waiting for a lock failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockWaitError {
+    /// The statement was canceled or passed its statement_timeout
+    Canceled,
+    /// The lock is held by a session that waits for a lock this session holds
+    Deadlock,
+}
+
+/// An exclusive advisory lock. The holder can take it again; it is released once every
+/// acquisition has been undone.
+#[derive(Default)]
+struct Lock {
+    holder: Option<Uuid>,
+    session_count: u32,
+    transaction_count: u32,
+    /// Sessions waiting for the lock, which is granted to them in this order
+    waiters: VecDeque<Uuid>,
+}
+
+#[derive(Default)]
+struct LockTable {
+    locks: HashMap<LockKey, Lock>,
+    /// The lock each waiting session waits for, to find deadlocks
+    waiting: HashMap<Uuid, LockKey>,
+}
+
+static LOCKS: Lazy<Mutex<LockTable>> = Lazy::new(|| Mutex::new(LockTable::default()));
+
+impl LockTable {
+    /// Take the lock if it's free and no earlier waiter is queued for it; otherwise queue
+    /// `owner` for it when `queue` is set
+    fn attempt(&mut self, owner: Uuid, key: LockKey, scope: LockScope, queue: bool) -> Result<bool, LockWaitError> {
+        let lock = self.locks.entry(key).or_default();
+        let grantable = match lock.holder {
+            Some(holder) => holder == owner,
+            None => lock.waiters.front().is_none_or(|first| *first == owner),
+        };
+        if grantable {
+            lock.holder = Some(owner);
+            match scope {
+                LockScope::Session => lock.session_count += 1,
+                LockScope::Transaction => lock.transaction_count += 1,
+            }
+            lock.waiters.retain(|waiter| *waiter != owner);
+            self.waiting.remove(&owner);
+            return Ok(true);
+        }
+        if !queue {
+            self.forget_if_unused(key);
+            return Ok(false);
+        }
+        if !lock.waiters.contains(&owner) {
+            lock.waiters.push_back(owner);
+            self.waiting.insert(owner, key);
+        }
+        if self.waits_for(lock_holder(&self.locks, key), owner) {
+            self.abandon(owner, key);
+            return Err(LockWaitError::Deadlock);
+        }
+        Ok(false)
+    }
+
+    /// Whether `session`, or the session holding the lock it waits for and so on, is `owner`
+    fn waits_for(&self, mut session: Option<Uuid>, owner: Uuid) -> bool {
+        // Each session waits for one lock, so the chain can't be longer than the waiters
+        for _ in 0..=self.waiting.len() {
+            match session {
+                Some(current) if current == owner => return true,
+                Some(current) => session = self.waiting.get(&current).and_then(|key| lock_holder(&self.locks, *key)),
+                None => return false,
+            }
+        }
+        false
+    }
+
+    /// Leave the queue of a lock the session stopped waiting for
+    fn abandon(&mut self, owner: Uuid, key: LockKey) {
+        if let Some(lock) = self.locks.get_mut(&key) {
+            lock.waiters.retain(|waiter| *waiter != owner);
+        }
+        self.waiting.remove(&owner);
+        self.forget_if_unused(key);
+    }
+
+    /// Undo one acquisition in `scope`, or all of them with `all`; false if the session
+    /// held the lock in no such scope
+    fn release(&mut self, owner: Uuid, key: LockKey, scope: LockScope, all: bool) -> bool {
+        let Some(lock) = self.locks.get_mut(&key).filter(|lock| lock.holder == Some(owner)) else {
+            return false;
+        };
+        let count = match scope {
+            LockScope::Session => &mut lock.session_count,
+            LockScope::Transaction => &mut lock.transaction_count,
+        };
+        if *count == 0 {
+            return false;
+        }
+        *count = if all { 0 } else { *count - 1 };
+        if lock.session_count == 0 && lock.transaction_count == 0 {
+            lock.holder = None;
+        }
+        self.forget_if_unused(key);
+        true
+    }
+
+    fn forget_if_unused(&mut self, key: LockKey) {
+        if self.locks.get(&key).is_some_and(|lock| lock.holder.is_none() && lock.waiters.is_empty()) {
+            self.locks.remove(&key);
+        }
+    }
+
+    /// Release what `owner` holds in `scope`
+    fn release_scope(&mut self, owner: Uuid, scope: LockScope) {
+        let held: Vec<LockKey> = self.locks.iter()
+            .filter(|(_, lock)| lock.holder == Some(owner))
+            .map(|(key, _)| *key)
+            .collect();
+        for key in held {
+            self.release(owner, key, scope, true);
+        }
+    }
+}
+
+fn lock_holder(locks: &HashMap<LockKey, Lock>, key: LockKey) -> Option<Uuid> {
+    locks.get(&key).and_then(|lock| lock.holder)
+}
+
+/// Take the lock if it's available right away, the pg_try_advisory_lock() family
+pub fn try_lock(owner: Uuid, key: LockKey, scope: LockScope) -> bool {
+    LOCKS.lock().attempt(owner, key, scope, false).unwrap_or(false)
+}
+
+/// Wait for the lock on the runtime, checking `should_abort` while waiting
+pub async fn lock(owner: Uuid, key: LockKey, scope: LockScope, should_abort: impl Fn() -> bool) -> Result<(), LockWaitError> {
+    while !poll_lock(owner, key, scope, &should_abort)? {
+        tokio::time::sleep(LOCK_POLL_INTERVAL).await;
+    }
+    Ok(())
+}
+
+/// Wait for the lock on the calling thread, for calls inside a statement SQLite runs
+pub fn lock_blocking(owner: Uuid, key: LockKey, scope: LockScope, should_abort: impl Fn() -> bool) -> Result<(), LockWaitError> {
+    while !poll_lock(owner, key, scope, &should_abort)? {
+        std::thread::sleep(LOCK_POLL_INTERVAL);
+    }
+    Ok(())
+}
+
+fn poll_lock(owner: Uuid, key: LockKey, scope: LockScope, should_abort: &impl Fn() -> bool) -> Result<bool, LockWaitError> {
+    let mut table = LOCKS.lock();
+    if should_abort() {
+        table.abandon(owner, key);
+        return Err(LockWaitError::Canceled);
+    }
+    table.attempt(owner, key, scope, true)
+}
+
+/// Release one session-level acquisition of the lock; false if the session doesn't hold it
+pub fn unlock(owner: Uuid, key: LockKey) -> bool {
+    LOCKS.lock().release(owner, key, LockScope::Session, false)
+}
+
+/// Release the session-level locks of the session, for pg_advisory_unlock_all()
+pub fn unlock_all(owner: Uuid) {
+    LOCKS.lock().release_scope(owner, LockScope::Session);
+}
+
+/// The session's transaction ended: release its transaction-level locks
+pub fn transaction_finished(owner: Uuid) {
+    LOCKS.lock().release_scope(owner, LockScope::Transaction);
+}
+
+/// The session ended: release all its locks and leave any queue it waits in
+pub fn session_finished(owner: Uuid) {
+    let mut table = LOCKS.lock();
+    table.release_scope(owner, LockScope::Session);
+    table.release_scope(owner, LockScope::Transaction);
+    if let Some(key) = table.waiting.get(&owner).copied() {
+        table.abandon(owner, key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_reentry_and_release() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let key = LockKey::Single(i64::MAX);
+
+        assert!(try_lock(a, key, LockScope::Session));
+        assert!(try_lock(a, key, LockScope::Session));
+        assert!(try_lock(a, key, LockScope::Transaction));
+        assert!(!try_lock(b, key, LockScope::Session));
+        // The two-integer form locks a key of its own
+        assert!(try_lock(b, LockKey::Pair(-1, -1), LockScope::Session));
+
+        // Released once each acquisition is undone
+        assert!(unlock(a, key));
+        transaction_finished(a);
+        assert!(!try_lock(b, key, LockScope::Session));
+        assert!(unlock(a, key));
+        assert!(!unlock(a, key));
+        assert!(try_lock(b, key, LockScope::Session));
+
+        session_finished(b);
+        assert!(try_lock(a, LockKey::Pair(-1, -1), LockScope::Session));
+        session_finished(a);
+    }
+
+    #[test]
+    fn test_waiters_are_queued_and_deadlocks_detected() {
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let (first, second) = (LockKey::Pair(i32::MIN, 1), LockKey::Pair(i32::MIN, 2));
+
+        assert!(try_lock(a, first, LockScope::Session));
+        assert!(try_lock(b, second, LockScope::Session));
+        assert_eq!(poll_lock(c, first, LockScope::Session, &|| false), Ok(false));
+        assert_eq!(poll_lock(b, first, LockScope::Session, &|| false), Ok(false));
+        // a waiting for second, held by b, which waits for first, held by a
+        assert_eq!(poll_lock(a, second, LockScope::Session, &|| false), Err(LockWaitError::Deadlock));
+
+        // The first waiter gets the lock, even when a later one asks first
+        unlock(a, first);
+        assert_eq!(poll_lock(b, first, LockScope::Session, &|| false), Ok(false));
+        assert!(!try_lock(a, first, LockScope::Session));
+        assert_eq!(poll_lock(c, first, LockScope::Session, &|| false), Ok(true));
+
+        // A canceled waiter leaves the queue
+        assert_eq!(poll_lock(b, first, LockScope::Session, &|| true), Err(LockWaitError::Canceled));
+        session_finished(c);
+        assert!(try_lock(a, first, LockScope::Session));
+
+        session_finished(a);
+        session_finished(b);
+    }
+}
@@ -0,0 +1,317 @@
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use super::SessionInterrupt;
+use super::transaction_ids::{TransactionSnapshot, allocate_transaction_id};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+/// Connected client sessions keyed by backend pid, the source of pg_stat_activity
+static BACKENDS: Lazy<Mutex<HashMap<i32, Backend>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Backend pids are handed out per session, starting at the server's process id
+static NEXT_BACKEND_PID: Lazy<AtomicI32> = Lazy::new(|| AtomicI32::new(std::process::id() as i32));
+
+/// Allocate the backend pid of a new session
+pub fn allocate_backend_pid() -> i32 {
+    NEXT_BACKEND_PID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// A row of pg_stat_activity
+#[derive(Clone)]
+pub struct Backend {
+    pub pid: i32,
+    pub session_id: Uuid,
+    pub database: String,
+    pub user: String,
+    pub application_name: String,
+    /// None for Unix socket and named pipe clients
+    pub client_addr: Option<SocketAddr>,
+    /// The server address the client connected to, None like client_addr
+    pub server_addr: Option<SocketAddr>,
+    pub backend_start: chrono::DateTime<chrono::Utc>,
+    /// The running statement, or the last one once the session is idle
+    pub query: Option<String>,
+    pub query_start: Option<chrono::DateTime<chrono::Utc>>,
+    pub active: bool,
+    /// The id txid_current() gave the running transaction, None until it's asked for
+    pub backend_xid: Option<i64>,
+    /// The snapshot txid_current_snapshot() gave the running transaction, kept until it ends
+    /// as SQLite reads from the same snapshot throughout a transaction
+    snapshot: Option<TransactionSnapshot>,
+    interrupt: Option<Arc<SessionInterrupt>>,
+    signals: Arc<BackendSignals>,
+}
+
+struct BackendSignals {
+    terminating: AtomicBool,
+    terminate: Notify,
+}
+
+/// Registers a session in the backend registry and removes it when dropped
+pub struct BackendRegistration {
+    pid: i32,
+    signals: Arc<BackendSignals>,
+}
+
+impl BackendRegistration {
+    /// `interrupt` aborts the statement running on the session's SQLite connection
+    pub fn register(
+        session: &super::SessionState,
+        application_name: Option<String>,
+        client_addr: Option<SocketAddr>,
+        server_addr: Option<SocketAddr>,
+        interrupt: Option<SessionInterrupt>,
+    ) -> Self {
+        let signals = Arc::new(BackendSignals {
+            terminating: AtomicBool::new(false),
+            terminate: Notify::new(),
+        });
+        BACKENDS.lock().insert(session.backend_pid, Backend {
+            pid: session.backend_pid,
+            session_id: session.id,
+            database: session.database.clone(),
+            user: session.user.clone(),
+            application_name: application_name.unwrap_or_default(),
+            client_addr,
+            server_addr,
+            backend_start: chrono::Utc::now(),
+            query: None,
+            query_start: None,
+            active: false,
+            backend_xid: None,
+            snapshot: None,
+            interrupt: interrupt.map(Arc::new),
+            signals: signals.clone(),
+        });
+        BackendRegistration { pid: session.backend_pid, signals }
+    }
+
+    /// Resolves once pg_terminate_backend() targets this session
+    pub async fn terminated(&self) {
+        if !self.is_terminating() {
+            self.signals.terminate.notified().await;
+        }
+    }
+
+    pub fn is_terminating(&self) -> bool {
+        self.signals.terminating.load(Ordering::Acquire)
+    }
+}
+
+impl Drop for BackendRegistration {
+    fn drop(&mut self) {
+        let backend = BACKENDS.lock().remove(&self.pid);
+        if let Some(backend) = backend {
+            super::advisory_locks::session_finished(backend.session_id);
+        }
+    }
+}
+
+/// Mark the session as running `query`, interrupting it if it is still running after `timeout`
+pub fn query_started(pid: i32, query: &str, timeout: Option<Duration>) {
+    if let Some(backend) = BACKENDS.lock().get_mut(&pid) {
+        // A cancel request only applies to the statement that was running when it arrived
+        if let Some(interrupt) = &backend.interrupt {
+            interrupt.reset();
+            interrupt.start_deadline(timeout);
+        }
+        backend.query = Some(query.to_string());
+        backend.query_start = Some(chrono::Utc::now());
+        backend.active = true;
+    }
+}
+
+/// Mark the session as idle again; true if the statement_timeout interrupted the statement
+pub fn query_finished(pid: i32) -> bool {
+    let mut backends = BACKENDS.lock();
+    let Some(backend) = backends.get_mut(&pid) else {
+        return false;
+    };
+    backend.active = false;
+    backend.interrupt.as_ref().is_some_and(|interrupt| {
+        interrupt.reset();
+        interrupt.finish_deadline()
+    })
+}
+
+/// Backend pid of the session, if it's registered
+pub fn backend_pid_of(session_id: &Uuid) -> Option<i32> {
+    BACKENDS.lock().values().find(|backend| backend.session_id == *session_id).map(|backend| backend.pid)
+}
+
+/// User and application_name of the session, if it's registered
+pub fn identity_of(session_id: &Uuid) -> Option<(String, String)> {
+    BACKENDS.lock().values()
+        .find(|backend| backend.session_id == *session_id)
+        .map(|backend| (backend.user.clone(), backend.application_name.clone()))
+}
+
+/// Whether the backend's statement was canceled or ran past its statement_timeout, for
+/// statements that wait outside SQLite, where the progress handler can't abort them
+pub fn should_abort(pid: i32) -> bool {
+    BACKENDS.lock().get(&pid)
+        .and_then(|backend| backend.interrupt.clone())
+        .is_some_and(|interrupt| interrupt.should_abort())
+}
+
+/// Client and server address of the session's connection, if it's registered
+pub fn addresses_of(session_id: &Uuid) -> Option<(Option<SocketAddr>, Option<SocketAddr>)> {
+    BACKENDS.lock().values()
+        .find(|backend| backend.session_id == *session_id)
+        .map(|backend| (backend.client_addr, backend.server_addr))
+}
+
+/// The id of the session's running transaction, allocating one on the first call
+pub fn transaction_id_of(session_id: &Uuid) -> Option<i64> {
+    BACKENDS.lock().values_mut()
+        .find(|backend| backend.session_id == *session_id)
+        .map(|backend| *backend.backend_xid.get_or_insert_with(allocate_transaction_id))
+}
+
+/// The id of the session's running transaction, None if it hasn't asked for one
+pub fn assigned_transaction_id(session_id: &Uuid) -> Option<i64> {
+    BACKENDS.lock().values()
+        .find(|backend| backend.session_id == *session_id)
+        .and_then(|backend| backend.backend_xid)
+}
+
+/// The transactions visible to the session's running transaction: the first call takes a
+/// snapshot of the transactions other sessions are running, later ones return it again.
+/// Without a registered session, a new snapshot.
+pub fn transaction_snapshot(session_id: Option<&Uuid>) -> TransactionSnapshot {
+    let mut backends = BACKENDS.lock();
+    let pid = session_id.and_then(|session_id| {
+        backends.values().find(|backend| backend.session_id == *session_id).map(|backend| backend.pid)
+    });
+    if let Some(snapshot) = pid.and_then(|pid| backends.get(&pid)?.snapshot.clone()) {
+        return snapshot;
+    }
+    let own = pid.and_then(|pid| backends.get(&pid)?.backend_xid);
+    let running = backends.values()
+        .filter(|backend| Some(backend.pid) != pid)
+        .filter_map(|backend| backend.backend_xid)
+        .collect();
+    let snapshot = TransactionSnapshot::new(running, own);
+    if let Some(backend) = pid.and_then(|pid| backends.get_mut(&pid)) {
+        backend.snapshot = Some(snapshot.clone());
+    }
+    snapshot
+}
+
+/// The backend's transaction ended, so the next one gets an id and snapshot of its own
+/// and its transaction-level advisory locks are released
+pub fn transaction_finished(pid: i32) {
+    let session_id = BACKENDS.lock().get_mut(&pid).map(|backend| {
+        backend.backend_xid = None;
+        backend.snapshot = None;
+        backend.session_id
+    });
+    if let Some(session_id) = session_id {
+        super::advisory_locks::transaction_finished(session_id);
+    }
+}
+
+/// Interrupt the statement the backend is running; false when no such backend exists
+///
+/// The request stays pending until the statement ends, so it also stops a statement
+/// that had not started stepping yet. An idle backend has nothing to cancel.
+pub fn cancel_backend(pid: i32) -> bool {
+    let backends = BACKENDS.lock();
+    let Some(backend) = backends.get(&pid) else {
+        return false;
+    };
+    if backend.active && let Some(interrupt) = &backend.interrupt {
+        interrupt.cancel();
+    }
+    true
+}
+
+/// Interrupt the backend's statement and close its connection
+///
+/// With a positive `timeout` waits that long for the session to end and returns
+/// false if it is still connected afterwards.
+pub fn terminate_backend(pid: i32, timeout: Duration) -> bool {
+    let (interrupt, signals) = match BACKENDS.lock().get(&pid) {
+        Some(backend) => (backend.interrupt.clone(), backend.signals.clone()),
+        None => return false,
+    };
+    signals.terminating.store(true, Ordering::Release);
+    signals.terminate.notify_one();
+    if let Some(interrupt) = interrupt {
+        interrupt.cancel();
+    }
+
+    if timeout.is_zero() {
+        return true;
+    }
+    let started = Instant::now();
+    while BACKENDS.lock().contains_key(&pid) {
+        if started.elapsed() >= timeout {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    true
+}
+
+/// Snapshot of the connected sessions
+pub fn backends() -> Vec<Backend> {
+    let mut backends: Vec<Backend> = BACKENDS.lock().values().cloned().collect();
+    backends.sort_by_key(|backend| backend.pid);
+    backends
+}
+
+/// The connected sessions as a JSON array, the source of the pg_stat_activity view
+pub fn backends_json() -> String {
+    let timestamp = |t: chrono::DateTime<chrono::Utc>| t.format("%Y-%m-%d %H:%M:%S%.6f+00").to_string();
+    let rows: Vec<serde_json::Value> = backends().into_iter()
+        .map(|backend| serde_json::json!({
+            "pid": backend.pid,
+            "datname": backend.database,
+            "usename": backend.user,
+            "application_name": backend.application_name,
+            "client_addr": backend.client_addr.map(|addr| addr.ip().to_string()),
+            "client_port": backend.client_addr.map_or(-1, |addr| i32::from(addr.port())),
+            "backend_start": timestamp(backend.backend_start),
+            "query_start": backend.query_start.map(timestamp),
+            "state": if backend.active { "active" } else { "idle" },
+            "query": backend.query,
+        }))
+        .collect();
+    serde_json::Value::Array(rows).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_backend_registry() {
+        let session = super::super::SessionState::new("main".to_string(), "registry_test".to_string());
+        let registration = BackendRegistration::register(&session, Some("psql".to_string()), None, None, None);
+        assert_eq!(backend_pid_of(&session.id), Some(session.backend_pid));
+
+        query_started(session.backend_pid, "SELECT 1", None);
+        let backend = backends().into_iter().find(|b| b.pid == session.backend_pid).unwrap();
+        assert!(backend.active);
+        assert_eq!(backend.query.as_deref(), Some("SELECT 1"));
+        assert!(!query_finished(session.backend_pid));
+        assert!(backends_json().contains("\"usename\":\"registry_test\""));
+
+        assert!(cancel_backend(session.backend_pid));
+        assert!(!registration.is_terminating());
+        assert!(terminate_backend(session.backend_pid, Duration::ZERO));
+        assert!(registration.is_terminating());
+        registration.terminated().await;
+
+        drop(registration);
+        assert!(backend_pid_of(&session.id).is_none());
+        assert!(!cancel_backend(session.backend_pid));
+        assert!(!terminate_backend(session.backend_pid, Duration::ZERO));
+    }
+}
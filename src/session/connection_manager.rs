@@ -1,13 +1,130 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 use parking_lot::{RwLock, Mutex};
 use rusqlite::{Connection, OpenFlags};
 use uuid::Uuid;
 use crate::config::Config;
 use crate::PgSqliteError;
-use crate::session::ThreadLocalConnectionCache;
+use crate::session::{GroupCommit, ThreadLocalConnectionCache};
 use tracing::{warn, debug, info};
 
+/// Number of SQLite VM instructions between checks for a cancel request
+const CANCEL_CHECK_INTERVAL: i32 = 1000;
+
+/// Run a statement on a runtime worker thread. On the multi-threaded runtime the worker
+/// hands its other tasks to another thread first, so a long statement doesn't stall the
+/// sessions queued behind it, including the one sending pg_cancel_backend.
+pub(crate) fn run_statement<R>(f: impl FnOnce() -> R) -> R {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(f)
+        }
+        _ => f(),
+    }
+}
+
+/// How connections to the database open. Under --read-only the file has to exist and
+/// SQLite refuses any write to it.
+pub(crate) fn open_flags(config: &Config) -> OpenFlags {
+    let access = if config.read_only {
+        OpenFlags::SQLITE_OPEN_READ_ONLY
+    } else {
+        OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE
+    };
+    access | OpenFlags::SQLITE_OPEN_FULL_MUTEX | OpenFlags::SQLITE_OPEN_URI
+}
+
+/// The pragmas a connection starts with. The journal mode and durability of a
+/// --read-only server's database belong to the server that writes it, and query_only
+/// keeps its sessions from writing even to temporary tables.
+pub(crate) fn connection_pragmas(config: &Config, synchronous: &str) -> String {
+    let mut pragmas = String::new();
+    if config.read_only {
+        pragmas.push_str("PRAGMA query_only = ON;\n");
+    } else {
+        pragmas.push_str(&format!(
+            "PRAGMA journal_mode = {};
+             PRAGMA synchronous = {};
+             ",
+            config.pragma_journal_mode,
+            synchronous
+        ));
+    }
+    pragmas.push_str(&format!(
+        "PRAGMA cache_size = {};
+         PRAGMA temp_store = MEMORY;
+         PRAGMA mmap_size = {};
+         PRAGMA foreign_keys = ON;",
+        config.pragma_cache_size,
+        config.pragma_mmap_size
+    ));
+    pragmas
+}
+
+/// A session's pending cancel request and statement_timeout deadline, checked by its
+/// connection's progress handler
+#[derive(Default)]
+struct StatementCancel {
+    requested: AtomicBool,
+    deadline: Mutex<Option<Instant>>,
+    timed_out: AtomicBool,
+}
+
+impl StatementCancel {
+    /// Whether the running statement should be aborted
+    fn should_abort(&self) -> bool {
+        if self.requested.load(Ordering::Acquire) {
+            return true;
+        }
+        let expired = self.deadline.lock().is_some_and(|deadline| Instant::now() >= deadline);
+        if expired {
+            self.timed_out.store(true, Ordering::Release);
+        }
+        expired
+    }
+}
+
+/// Cancels the statements running on one session's connection
+pub struct SessionInterrupt {
+    handle: rusqlite::InterruptHandle,
+    cancel: Arc<StatementCancel>,
+}
+
+impl SessionInterrupt {
+    /// Abort the running statement, or the next one if it hasn't started stepping yet
+    pub fn cancel(&self) {
+        self.cancel.requested.store(true, Ordering::Release);
+        self.handle.interrupt();
+    }
+    
+    /// Whether the running statement was canceled or passed its deadline
+    pub fn should_abort(&self) -> bool {
+        self.cancel.should_abort()
+    }
+
+    /// Drop a pending cancel request, called when the session starts or finishes a statement
+    pub fn reset(&self) {
+        self.cancel.requested.store(false, Ordering::Release);
+    }
+
+    /// Abort the statement the session starts next once it has run for `timeout`
+    ///
+    /// The progress handler checks the deadline as the statement steps, which unlike a
+    /// timer task doesn't depend on the runtime thread the statement blocks.
+    pub fn start_deadline(&self, timeout: Option<Duration>) {
+        self.cancel.timed_out.store(false, Ordering::Release);
+        *self.cancel.deadline.lock() = timeout.map(|timeout| Instant::now() + timeout);
+    }
+
+    /// Clear the statement's deadline; true if the statement was aborted for passing it
+    pub fn finish_deadline(&self) -> bool {
+        *self.cancel.deadline.lock() = None;
+        self.cancel.timed_out.swap(false, Ordering::AcqRel)
+    }
+}
+
 /// Manages per-session SQLite connections for true isolation
 pub struct ConnectionManager {
     /// Map of session_id to SQLite connection (each wrapped in its own Mutex for thread safety)
@@ -16,8 +133,16 @@ pub struct ConnectionManager {
     db_path: String,
     /// Configuration
     config: Arc<Config>,
+    /// Sessions that don't count against `max_connections`: admin connections and the default session
+    reserved: Mutex<HashSet<Uuid>>,
+    /// Per-session cancel requests and statement deadlines, checked by each connection's progress handler
+    cancel_requests: RwLock<HashMap<Uuid, Arc<StatementCancel>>>,
+    /// Per-session synchronous_commit, read by each connection's commit hook under group commit
+    synchronous_commits: RwLock<HashMap<Uuid, Arc<AtomicBool>>>,
     /// Maximum number of connections allowed
     max_connections: usize,
+    /// Syncs the commits of concurrent sessions together, with --group-commit-delay-ms
+    group_commit: Option<Arc<GroupCommit>>,
 }
 
 impl ConnectionManager {
@@ -25,20 +150,40 @@ impl ConnectionManager {
         Self {
             connections: Arc::new(RwLock::new(HashMap::new())),
             db_path,
+            max_connections: config.max_connections,
             config,
-            max_connections: 100, // TODO: Make configurable
+            reserved: Mutex::new(HashSet::new()),
+            cancel_requests: RwLock::new(HashMap::new()),
+            synchronous_commits: RwLock::new(HashMap::new()),
+            group_commit: None,
         }
     }
     
+    /// Leave syncing commits to `group_commit` instead of each connection
+    pub fn with_group_commit(mut self, group_commit: Option<Arc<GroupCommit>>) -> Self {
+        self.group_commit = group_commit;
+        self
+    }
+    
     /// Create a new connection for a session
     pub fn create_connection(&self, session_id: Uuid) -> Result<(), PgSqliteError> {
+        self.open_connection(session_id, false)
+    }
+    
+    /// Create a connection that bypasses the connection limit and doesn't count against it
+    pub fn create_reserved_connection(&self, session_id: Uuid) -> Result<(), PgSqliteError> {
+        self.open_connection(session_id, true)
+    }
+    
+    fn open_connection(&self, session_id: Uuid, reserved: bool) -> Result<(), PgSqliteError> {
         let mut connections = self.connections.write();
         
         // Check connection limit
-        if connections.len() >= self.max_connections {
-            return Err(PgSqliteError::Protocol(
-                format!("Maximum connection limit ({}) reached", self.max_connections)
-            ));
+        if !reserved && connections.len() - self.reserved.lock().len() >= self.max_connections {
+            return Err(PgSqliteError::Validation(crate::error::PgError::Generic {
+                code: "53300".to_string(),
+                message: "sorry, too many clients already".to_string(),
+            }));
         }
         
         // Check if connection already exists
@@ -48,41 +193,69 @@ impl ConnectionManager {
         }
         
         // Create new connection
-        let flags = OpenFlags::SQLITE_OPEN_READ_WRITE 
-            | OpenFlags::SQLITE_OPEN_CREATE 
-            | OpenFlags::SQLITE_OPEN_FULL_MUTEX
-            | OpenFlags::SQLITE_OPEN_URI;
-        
         debug!("Creating connection for session {} with path: {}", session_id, self.db_path);
             
-        let conn = Connection::open_with_flags(&self.db_path, flags)
+        let conn = Connection::open_with_flags(&self.db_path, open_flags(&self.config))
             .map_err(PgSqliteError::Sqlite)?;
         
+        // Under group commit a commit only reaches the WAL, the group syncs it
+        let synchronous = if self.group_commit.is_some() { "NORMAL" } else { self.config.pragma_synchronous.as_str() };
+        
         // Set pragmas
-        let pragma_sql = format!(
-            "PRAGMA journal_mode = {};
-             PRAGMA synchronous = {};
-             PRAGMA cache_size = {};
-             PRAGMA temp_store = MEMORY;
-             PRAGMA mmap_size = {};",
-            self.config.pragma_journal_mode,
-            self.config.pragma_synchronous,
-            self.config.pragma_cache_size,
-            self.config.pragma_mmap_size
-        );
-        conn.execute_batch(&pragma_sql)
+        conn.execute_batch(&connection_pragmas(&self.config, synchronous))
             .map_err(PgSqliteError::Sqlite)?;
         
         // Register functions
         crate::functions::register_all_functions(&conn)
             .map_err(PgSqliteError::Sqlite)?;
+        crate::functions::system_functions::register_session_functions(&conn, session_id)
+            .map_err(PgSqliteError::Sqlite)?;
         
         // Initialize metadata
         crate::metadata::TypeMetadata::init(&conn)
             .map_err(PgSqliteError::Sqlite)?;
+        crate::functions::sql_functions::register_stored_functions(&conn)
+            .map_err(PgSqliteError::Sqlite)?;
+        
+        // sqlite3_interrupt() is lost when it arrives before a statement starts stepping,
+        // so a cancel request also stays set until the session's next statement begins
+        let cancel = Arc::new(StatementCancel::default());
+        let handler_cancel = cancel.clone();
+        conn.progress_handler(CANCEL_CHECK_INTERVAL, Some(move || handler_cancel.should_abort()));
+        // pg_sleep() and advisory lock waits inside a statement don't step it, so they check for the cancel themselves
+        let sleep_cancel = cancel.clone();
+        crate::functions::system_functions::register_sleep_functions(&conn, move || sleep_cancel.should_abort())
+            .map_err(PgSqliteError::Sqlite)?;
+        let lock_cancel = cancel.clone();
+        crate::functions::advisory_lock_functions::register_advisory_lock_functions(&conn, session_id, move || lock_cancel.should_abort())
+            .map_err(PgSqliteError::Sqlite)?;
+        self.cancel_requests.write().insert(session_id, cancel);
+        
+        let group_commit = self.group_commit.clone();
+        let synchronous = Arc::new(AtomicBool::new(true));
+        let hook_synchronous = synchronous.clone();
+        conn.commit_hook(Some(move || {
+            // Every commit advances the position pg_current_wal_lsn() reports
+            crate::session::transaction_ids::record_commit();
+            if let Some(group_commit) = &group_commit {
+                if hook_synchronous.load(Ordering::Acquire) {
+                    group_commit.committed(session_id);
+                } else {
+                    // synchronous_commit = off: the next flush syncs it, nobody waits for it
+                    group_commit.schedule_flush();
+                }
+            }
+            false
+        }));
+        if self.group_commit.is_some() {
+            self.synchronous_commits.write().insert(session_id, synchronous);
+        }
         
         let conn_arc = Arc::new(Mutex::new(conn));
         connections.insert(session_id, conn_arc.clone());
+        if reserved {
+            self.reserved.lock().insert(session_id);
+        }
         
         // Cache in thread-local storage for fast access
         ThreadLocalConnectionCache::insert(session_id, conn_arc);
@@ -103,8 +276,7 @@ impl ConnectionManager {
     {
         // First try thread-local cache (fast path)
         if let Some(conn_arc) = ThreadLocalConnectionCache::get(session_id) {
-            let conn = conn_arc.lock();
-            return f(&conn).map_err(PgSqliteError::Sqlite);
+            return run_statement(|| f(&conn_arc.lock())).map_err(PgSqliteError::Sqlite);
         }
         
         // Fall back to global map (slow path)
@@ -126,8 +298,7 @@ impl ConnectionManager {
         ThreadLocalConnectionCache::insert(*session_id, conn_arc.clone());
         
         // Now lock the individual connection
-        let conn = conn_arc.lock();
-        f(&conn).map_err(PgSqliteError::Sqlite)
+        run_statement(|| f(&conn_arc.lock())).map_err(PgSqliteError::Sqlite)
     }
     
     /// Execute a query with a cached connection Arc (avoids HashMap lookup)
@@ -139,8 +310,7 @@ impl ConnectionManager {
     where
         F: FnOnce(&Connection) -> Result<R, rusqlite::Error>
     {
-        let conn = conn_arc.lock();
-        f(&conn).map_err(PgSqliteError::Sqlite)
+        run_statement(|| f(&conn_arc.lock())).map_err(PgSqliteError::Sqlite)
     }
     
     /// Remove a connection when session ends
@@ -149,9 +319,78 @@ impl ConnectionManager {
         ThreadLocalConnectionCache::remove(session_id);
         
         let mut connections = self.connections.write();
-        if connections.remove(session_id).is_some() {
+        self.reserved.lock().remove(session_id);
+        self.cancel_requests.write().remove(session_id);
+        self.synchronous_commits.write().remove(session_id);
+        if let Some(group_commit) = &self.group_commit {
+            group_commit.forget(session_id);
+        }
+        if let Some(conn) = connections.remove(session_id) {
             info!("Removed connection for session {} (remaining connections: {})", session_id, connections.len());
+            drop(connections);
+            // SQLite drops the temporary tables with the connection, the main database keeps their metadata
+            let conn = conn.lock();
+            if !conn.is_autocommit() && let Err(e) = conn.execute_batch("ROLLBACK") {
+                warn!("Failed to roll back the transaction of session {}: {}", session_id, e);
+            }
+            if let Err(e) = crate::metadata::Namespaces::forget_temp_tables(&conn) {
+                warn!("Failed to forget the temporary tables of session {}: {}", session_id, e);
+            }
+        }
+    }
+    
+    /// Whether commits are synced before they are acknowledged, which is what
+    /// `synchronous_commit = off` gives up: under group commit or at FULL or EXTRA. A
+    /// --read-only server has no commits.
+    pub fn syncs_commits(&self) -> bool {
+        !self.config.read_only && (self.group_commit.is_some()
+            || matches!(self.config.pragma_synchronous.to_ascii_uppercase().as_str(), "FULL" | "EXTRA" | "2" | "3"))
+    }
+    
+    /// Whether the session's commits are synced before they are acknowledged. Under group
+    /// commit this holds from the session's next commit on, otherwise the connection syncs
+    /// at `--pragma-synchronous` or at most at NORMAL, which leaves commits in the WAL until
+    /// a checkpoint. SQLite can't change that inside a transaction, so there it takes effect
+    /// when the session calls this again at the end of the transaction block.
+    pub fn set_synchronous_commit(&self, session_id: &Uuid, synchronous: bool) -> Result<(), PgSqliteError> {
+        if self.group_commit.is_some() {
+            if let Some(flag) = self.synchronous_commits.read().get(session_id) {
+                flag.store(synchronous, Ordering::Release);
+            }
+            return Ok(());
+        }
+        let configured = self.config.pragma_synchronous.as_str();
+        let level = match configured.to_ascii_uppercase().as_str() {
+            "FULL" | "EXTRA" | "2" | "3" if !synchronous => "NORMAL",
+            _ => configured,
+        };
+        self.execute_with_session(session_id, |conn| {
+            if !conn.is_autocommit() {
+                return Ok(());
+            }
+            conn.execute_batch(&format!("PRAGMA synchronous = {level}"))
+        })
+    }
+    
+    /// Handle that interrupts the statement running on a session's connection
+    pub fn interrupt_handle(&self, session_id: &Uuid) -> Option<SessionInterrupt> {
+        let cancel = self.cancel_requests.read().get(session_id)?.clone();
+        let conn_arc = self.connections.read().get(session_id)?.clone();
+        let conn = conn_arc.lock();
+        Some(SessionInterrupt { handle: conn.get_interrupt_handle(), cancel })
+    }
+    
+    /// Run `f` on every open connection, such as to register a function one session created
+    pub fn for_each_connection<F>(&self, f: F) -> Result<(), PgSqliteError>
+    where
+        F: Fn(&Connection) -> Result<(), rusqlite::Error>
+    {
+        // Connections are locked one at a time, without holding the map
+        let connections: Vec<_> = self.connections.read().values().cloned().collect();
+        for conn_arc in connections {
+            f(&conn_arc.lock()).map_err(PgSqliteError::Sqlite)?;
         }
+        Ok(())
     }
     
     /// Get the number of active connections
@@ -167,8 +406,9 @@ impl ConnectionManager {
     /// Force WAL checkpoint on all connections except the specified one
     /// This ensures all connections see committed data from other connections
     pub fn refresh_all_other_connections(&self, excluding_session: &Uuid) -> Result<(), PgSqliteError> {
-        // Only do this in WAL mode
-        if self.config.pragma_journal_mode != "WAL" {
+        // Only do this in WAL mode, and not under group commit, where the TRUNCATE
+        // checkpoint would sync the database on every commit
+        if self.config.pragma_journal_mode != "WAL" || self.group_commit.is_some() {
             return Ok(());
         }
         
@@ -234,8 +474,7 @@ impl ConnectionManager {
     {
         // First try thread-local cache (fast path)
         if let Some(conn_arc) = ThreadLocalConnectionCache::get(session_id) {
-            let mut conn = conn_arc.lock();
-            return f(&mut conn).map_err(PgSqliteError::Sqlite);
+            return run_statement(|| f(&mut conn_arc.lock())).map_err(PgSqliteError::Sqlite);
         }
         
         // Fall back to global map (slow path)
@@ -255,8 +494,7 @@ impl ConnectionManager {
         ThreadLocalConnectionCache::insert(*session_id, conn_arc.clone());
         
         // Now lock the individual connection for mutable access
-        let mut conn = conn_arc.lock();
-        f(&mut conn).map_err(PgSqliteError::Sqlite)
+        run_statement(|| f(&mut conn_arc.lock())).map_err(PgSqliteError::Sqlite)
     }
     
     /// Get the connection Arc for a session (for caching)
@@ -286,7 +524,6 @@ impl ConnectionManager {
     where
         F: FnOnce(&mut Connection) -> Result<R, rusqlite::Error>
     {
-        let mut conn = conn_arc.lock();
-        f(&mut conn).map_err(PgSqliteError::Sqlite)
+        run_statement(|| f(&mut conn_arc.lock())).map_err(PgSqliteError::Sqlite)
     }
 }
\ No newline at end of file
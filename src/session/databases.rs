@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use parking_lot::Mutex;
+use rusqlite::{Connection, OpenFlags};
+use tracing::info;
+use crate::config::Config;
+use super::{DbHandler, SessionStorage};
+
+/// The databases a server serves, one [`SessionStorage`] per SQLite file.
+///
+/// Clients pick one with the `database` startup parameter. Without --databases
+/// every client gets the --database file, whatever name it asks for.
+///
+/// With --in-memory each session normally gets a private database. A client that
+/// asks for `file:name?mode=memory&cache=shared` instead shares the in-memory
+/// database `name` with every other session asking for it, so parallel tests can
+/// each work on their own ephemeral database. These live until the server exits.
+pub struct Databases {
+    default_name: String,
+    default: Arc<dyn SessionStorage>,
+    named: HashMap<String, Arc<dyn SessionStorage>>,
+    /// Set with --in-memory, for opening shared in-memory databases on demand
+    config: Option<Config>,
+    shared_memory: Mutex<HashMap<String, Arc<dyn SessionStorage>>>,
+}
+
+impl Databases {
+    /// Serve the --database file alone
+    pub fn single(default: Arc<dyn SessionStorage>, config: &Config) -> Self {
+        Self {
+            default_name: config.default_database_name(),
+            default,
+            named: HashMap::new(),
+            config: config.in_memory.then(|| config.clone()),
+            shared_memory: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Serve the --database file and open each file of --databases
+    pub fn open(default: Arc<dyn SessionStorage>, config: &Config) -> Result<Self, rusqlite::Error> {
+        let mut databases = Self::single(default, config);
+        for (name, path) in &config.databases {
+            info!("Serving database {} from {}", name, path);
+            databases.named.insert(name.clone(), Arc::new(DbHandler::new_with_config(path, config)?));
+        }
+        Ok(databases)
+    }
+
+    /// The handler of the database a client asked for, None if no database has that name
+    pub fn select(&self, name: &str) -> Result<Option<Arc<dyn SessionStorage>>, rusqlite::Error> {
+        if let Some(config) = &self.config
+            && let Some(memory_name) = shared_memory_name(name) {
+            return self.shared_memory(memory_name, config).map(Some);
+        }
+        if self.named.is_empty() || name == self.default_name {
+            return Ok(Some(self.default.clone()));
+        }
+        Ok(self.named.get(name).cloned())
+    }
+
+    /// Every database served so far: the --database and --databases files and the
+    /// shared in-memory databases clients have asked for
+    pub fn handlers(&self) -> Vec<Arc<dyn SessionStorage>> {
+        std::iter::once(self.default.clone())
+            .chain(self.named.values().cloned())
+            .chain(self.shared_memory.lock().values().cloned())
+            .collect()
+    }
+
+    /// The shared in-memory database called `name`, created the first time it is asked for
+    fn shared_memory(&self, name: &str, config: &Config) -> Result<Arc<dyn SessionStorage>, rusqlite::Error> {
+        let mut shared = self.shared_memory.lock();
+        if let Some(handler) = shared.get(name) {
+            return Ok(handler.clone());
+        }
+        // SQLite's memdb VFS shares a database between the connections of a process and,
+        // unlike a shared cache, lets them wait for each other's locks like on a file
+        let path = format!("file:/pgsqlite-{name}?vfs=memdb");
+        // The database disappears with its last connection, and the handler runs its
+        // migrations on a connection of its own before opening the sessions' ones
+        let keep_alive = Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE | OpenFlags::SQLITE_OPEN_URI)?;
+        let handler: Arc<dyn SessionStorage> = Arc::new(DbHandler::new_with_config(&path, config)?);
+        drop(keep_alive);
+        info!("Created shared in-memory database {}", name);
+        shared.insert(name.to_string(), handler.clone());
+        Ok(handler)
+    }
+}
+
+/// The name of the shared in-memory database a startup database parameter like
+/// `file:name?mode=memory&cache=shared` asks for
+fn shared_memory_name(database: &str) -> Option<&str> {
+    let (name, query) = database.strip_prefix("file:")?.split_once('?')?;
+    let memory = query.split('&').any(|param| param == "mode=memory");
+    (memory && !name.is_empty()).then_some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_memory_name() {
+        assert_eq!(shared_memory_name("file:suite1?mode=memory&cache=shared"), Some("suite1"));
+        assert_eq!(shared_memory_name("file:suite1?cache=shared&mode=memory"), Some("suite1"));
+        assert_eq!(shared_memory_name("file:suite1?mode=ro"), None);
+        assert_eq!(shared_memory_name("file:?mode=memory"), None);
+        assert_eq!(shared_memory_name("main"), None);
+    }
+}
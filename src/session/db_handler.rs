@@ -1,15 +1,36 @@
 use std::sync::Arc;
 use uuid::Uuid;
 use rusqlite::OptionalExtension;
-use crate::cache::SchemaCache;
+use crate::cache::{DatabaseCaches, SchemaCache};
 use crate::optimization::{OptimizationManager, statement_cache_optimizer::StatementCacheOptimizer};
 use crate::query::{QueryTypeDetector, QueryType, process_query};
 use crate::config::Config;
 use crate::migration::MigrationRunner;
 use crate::validator::StringConstraintValidator;
-use crate::session::ConnectionManager;
+use crate::session::{ConnectionManager, GroupCommit, StorageBackend};
 use crate::PgSqliteError;
-use crate::cache::StatementPool;
+use crate::ddl::{Constraints, ForeignKeys};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Catalog relation names; `pg_` function calls are told apart by the parenthesis that follows
+static CATALOG_RELATION_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\bpg_[a-z0-9_]+\b").unwrap());
+
+/// Remote objects cached in the local database when a remote engine is attached
+const REMOTE_SCHEMA_QUERY: &str = "SELECT type, name, sql FROM sqlite_master \
+    WHERE type IN ('table', 'index', 'view') AND sql IS NOT NULL \
+    AND name NOT LIKE 'sqlite_%' AND name NOT LIKE '__pgsqlite%' \
+    ORDER BY CASE type WHEN 'table' THEN 0 WHEN 'index' THEN 1 ELSE 2 END, rowid";
+
+/// Statements on pgsqlite's metadata or the catalogs, which only exist in the local database
+fn is_local_metadata_query(query: &str) -> bool {
+    let lower = query.to_lowercase();
+    if lower.contains("__pgsqlite") || lower.contains("sqlite_master") || lower.contains("sqlite_schema")
+        || lower.contains("pragma") || lower.contains("information_schema") {
+        return true;
+    }
+    CATALOG_RELATION_REGEX.find_iter(&lower).any(|m| !lower[m.end()..].trim_start().starts_with('('))
+}
 
 #[inline(always)]
 fn is_select_query_fast(sql: &str) -> bool {
@@ -26,10 +47,10 @@ fn is_select_query_fast(sql: &str) -> bool {
     }
     true
 }
-use tracing::debug;
+use tracing::{debug, info};
 
 /// Database response structure
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DbResponse {
     pub columns: Vec<String>,
     pub rows: Vec<Vec<Option<Vec<u8>>>>,
@@ -44,11 +65,17 @@ pub struct DbResponse {
 pub struct DbHandler {
     connection_manager: Arc<ConnectionManager>,
     schema_cache: Arc<SchemaCache>,
+    // Row descriptions, results and statements of this database, apart from other databases'
+    caches: Arc<DatabaseCaches>,
     string_validator: Arc<StringConstraintValidator>,
     statement_cache_optimizer: Arc<StatementCacheOptimizer>,
     db_path: String,
     // Default session for compatibility methods like query()/execute()
     default_session_id: Uuid,
+    // Engine for user statements when the data lives elsewhere, e.g. on a libSQL server
+    remote: Option<Arc<dyn StorageBackend>>,
+    // Syncs the commits of concurrent sessions together, with --group-commit-delay-ms
+    group_commit: Option<Arc<GroupCommit>>,
 }
 
 impl DbHandler {
@@ -64,9 +91,14 @@ impl DbHandler {
         
         // Create a temporary connection for migrations
         let temp_conn = Self::create_initial_connection(db_path, config)?;
+        let group_commit = GroupCommit::for_database(&temp_conn, config);
         
-        // Run migrations if needed
-        Self::run_migrations_if_needed(temp_conn, db_path)?;
+        // Run migrations if needed; a read-only server can only check there are none
+        if config.read_only {
+            Self::check_read_only_schema(temp_conn, db_path)?;
+        } else {
+            Self::run_migrations_if_needed(temp_conn, db_path)?;
+        }
         
         // Initialize optimization components
         let optimization_manager = Arc::new(OptimizationManager::new(true));
@@ -76,36 +108,54 @@ impl DbHandler {
         let connection_manager = Arc::new(ConnectionManager::new(
             db_path.to_string(),
             Arc::new(config.clone())
-        ));
+        ).with_group_commit(group_commit.clone()));
         // Create a default session connection for non-session APIs
         let default_session_id = Uuid::new_v4();
-        // Use connection manager to create and initialize the connection
+        // Use connection manager to create and initialize the connection; it doesn't take a client slot
         connection_manager
-            .create_connection(default_session_id)
+            .create_reserved_connection(default_session_id)
             .map_err(|e| rusqlite::Error::SqliteFailure(
                 rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_ERROR),
                 Some(format!("Failed to create default session connection: {e}"))
             ))?;
         
+        // Type descriptions resolve ENUM columns to their OIDs through the ENUM cache
+        if let Err(e) = connection_manager.execute_with_session(&default_session_id, |conn| {
+            crate::cache::global_enum_cache().load_types(conn)
+        }) {
+            debug!("Failed to load ENUM types: {}", e);
+        }
+        
+        // Sessions of a server that stopped without closing them left their temporary tables' metadata behind,
+        // which is for the server writing the database to remove
+        if !config.read_only {
+            match connection_manager.execute_with_session(&default_session_id, crate::metadata::Namespaces::sweep_temp_tables) {
+                Ok(0) => {}
+                Ok(swept) => debug!("Removed {} metadata rows of temporary tables from earlier sessions", swept),
+                Err(e) => debug!("Failed to sweep temporary table metadata: {}", e),
+            }
+        }
+        
         // DbHandler initialized
         
         Ok(Self {
             connection_manager,
             schema_cache: Arc::new(SchemaCache::new(config.schema_cache_ttl)),
+            caches: Arc::new(DatabaseCaches::new(config)),
             string_validator: Arc::new(StringConstraintValidator::new()),
             statement_cache_optimizer,
             db_path: db_path.to_string(),
             default_session_id,
+            remote: None,
+            group_commit,
         })
     }
     
     fn create_initial_connection(db_path: &str, config: &Config) -> Result<rusqlite::Connection, rusqlite::Error> {
-        use rusqlite::{Connection, OpenFlags};
+        use rusqlite::Connection;
+        use crate::session::connection_manager::{connection_pragmas, open_flags};
         
-        let flags = OpenFlags::SQLITE_OPEN_READ_WRITE 
-            | OpenFlags::SQLITE_OPEN_CREATE 
-            | OpenFlags::SQLITE_OPEN_FULL_MUTEX
-            | OpenFlags::SQLITE_OPEN_URI;
+        let flags = open_flags(config);
             
         let conn = if db_path == ":memory:" {
             // For memory databases, each connection gets its own database
@@ -116,22 +166,31 @@ impl DbHandler {
         };
         
         // Set pragmas
-        let pragma_sql = format!(
-            "PRAGMA journal_mode = {};
-             PRAGMA synchronous = {};
-             PRAGMA cache_size = {};
-             PRAGMA temp_store = MEMORY;
-             PRAGMA mmap_size = {};",
-            config.pragma_journal_mode,
-            config.pragma_synchronous,
-            config.pragma_cache_size,
-            config.pragma_mmap_size
-        );
-        conn.execute_batch(&pragma_sql)?;
+        conn.execute_batch(&connection_pragmas(config, &config.pragma_synchronous))?;
         
         Ok(conn)
     }
     
+    /// A --read-only server serves a database some other server created and keeps
+    /// migrated, so it only checks the catalog is there and current
+    fn check_read_only_schema(conn: rusqlite::Connection, db_path: &str) -> Result<(), rusqlite::Error> {
+        let migrated = conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='__pgsqlite_migrations'",
+            [],
+            |row| row.get::<_, i64>(0)
+        )? > 0;
+        
+        crate::functions::register_all_functions(&conn)?;
+        let current = migrated && MigrationRunner::new(conn).check_schema_version().is_ok();
+        if !current {
+            return Err(rusqlite::Error::SqliteFailure(
+                rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_ERROR),
+                Some(format!("Database {db_path} needs migrations, which --read-only cannot run; run pgsqlite --migrate on it first"))
+            ));
+        }
+        Ok(())
+    }
+    
     fn run_migrations_if_needed(conn: rusqlite::Connection, db_path: &str) -> Result<(), rusqlite::Error> {
         // Skip all checks for in-memory databases
         if db_path.contains(":memory:") {
@@ -246,14 +305,124 @@ impl DbHandler {
         Ok(())
     }
     
+    /// Run user statements on a remote engine. This database stays the local cache of the
+    /// schema and of pgsqlite's type metadata, which translation and the catalogs read.
+    /// Returns the number of remote tables, indexes and views newly cached.
+    pub async fn attach_remote(&mut self, remote: Arc<dyn StorageBackend>) -> Result<usize, PgSqliteError> {
+        let session_id = Uuid::new_v4();
+        remote.create_session_connection(session_id).await?;
+        let objects = remote.query_with_session(REMOTE_SCHEMA_QUERY, &session_id).await;
+        remote.remove_session_connection(&session_id);
+        let objects = objects?;
+
+        let cached = self.connection_manager.execute_with_session(&self.default_session_id, |conn| {
+            let mut cached = 0;
+            for row in &objects.rows {
+                let [Some(kind), Some(name), Some(sql)] = &row[..] else { continue };
+                let (kind, name) = (String::from_utf8_lossy(kind), String::from_utf8_lossy(name));
+                let exists: bool = conn.query_row(
+                    "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = ?1 AND name = ?2)",
+                    [kind.as_ref(), name.as_ref()],
+                    |row| row.get(0),
+                )?;
+                if !exists {
+                    conn.execute_batch(&String::from_utf8_lossy(sql))?;
+                    cached += 1;
+                }
+            }
+            Ok(cached)
+        })?;
+
+        info!("Attached {} storage; {} schema objects cached locally", remote.name(), cached);
+        self.remote = Some(remote);
+        Ok(cached)
+    }
+
+    /// Name of the attached remote engine, if any
+    pub fn remote_engine(&self) -> Option<&'static str> {
+        self.remote.as_ref().map(|remote| remote.name())
+    }
+
+    /// The remote engine, when one is attached and the statement is on user data
+    fn remote_for(&self, query: &str) -> Option<&Arc<dyn StorageBackend>> {
+        self.remote.as_ref().filter(|_| !is_local_metadata_query(query))
+    }
+
+    /// Run a statement remotely. DDL and transaction control are replayed locally, keeping the
+    /// schema cache current and pgsqlite's metadata writes in step with the remote transaction.
+    async fn execute_remote(&self, remote: &Arc<dyn StorageBackend>, query: &str, session_id: &Uuid) -> Result<DbResponse, PgSqliteError> {
+        let processed_query = self.connection_manager.execute_with_session(session_id, |conn| {
+            process_query(query, conn, &self.schema_cache)
+        })?;
+        let response = remote.execute_with_session(&processed_query, session_id).await?;
+
+        if matches!(
+            QueryTypeDetector::detect_query_type(query),
+            QueryType::Create | QueryType::Drop | QueryType::Alter | QueryType::Begin | QueryType::Commit | QueryType::Rollback
+        ) && let Err(e) = self.connection_manager.execute_with_session(session_id, |conn| conn.execute_batch(&processed_query)) {
+            // The remote database is authoritative; a stale local cache must not fail the statement
+            debug!("Local replay of remote statement failed: {}", e);
+        }
+        Ok(response)
+    }
+
     /// Create a connection for a new session
     pub async fn create_session_connection(&self, session_id: Uuid) -> Result<(), PgSqliteError> {
-        self.connection_manager.create_connection(session_id)
+        self.connection_manager.create_connection(session_id)?;
+        if let Some(remote) = &self.remote {
+            remote.create_session_connection(session_id).await?;
+        }
+        Ok(())
+    }
+    
+    /// Create a connection for an admin session, which bypasses the connection limit
+    pub async fn create_reserved_session_connection(&self, session_id: Uuid) -> Result<(), PgSqliteError> {
+        self.connection_manager.create_reserved_connection(session_id)?;
+        if let Some(remote) = &self.remote {
+            remote.create_reserved_session_connection(session_id).await?;
+        }
+        Ok(())
+    }
+    
+    /// Wait until the session's commits are on disk, before they are acknowledged with
+    /// ReadyForQuery; returns at once unless group commit holds some of them back
+    pub async fn wait_durable(&self, session_id: &Uuid) -> Result<(), PgSqliteError> {
+        match &self.group_commit {
+            Some(group_commit) => group_commit.wait_durable(session_id).await.map_err(PgSqliteError::Io),
+            None => Ok(()),
+        }
+    }
+    
+    /// `ConnectionManager::syncs_commits`
+    pub fn syncs_commits(&self) -> bool {
+        self.connection_manager.syncs_commits()
+    }
+    
+    /// Whether the session's commits are synced before they are acknowledged, see
+    /// `ConnectionManager::set_synchronous_commit`
+    pub fn set_synchronous_commit(&self, session_id: &Uuid, synchronous: bool) -> Result<(), PgSqliteError> {
+        self.connection_manager.set_synchronous_commit(session_id, synchronous)
+    }
+    
+    /// Handle that interrupts the statement running on a session's connection
+    pub fn interrupt_handle(&self, session_id: &Uuid) -> Option<super::SessionInterrupt> {
+        self.connection_manager.interrupt_handle(session_id)
+    }
+    
+    /// Run `f` on the connection of every session
+    pub fn with_all_connections<F>(&self, f: F) -> Result<(), PgSqliteError>
+    where
+        F: Fn(&rusqlite::Connection) -> Result<(), rusqlite::Error>
+    {
+        self.connection_manager.for_each_connection(f)
     }
     
     /// Remove a session's connection
     pub fn remove_session_connection(&self, session_id: &Uuid) {
         self.connection_manager.remove_connection(session_id);
+        if let Some(remote) = &self.remote {
+            remote.remove_session_connection(session_id);
+        }
     }
     
     
@@ -271,6 +440,45 @@ impl DbHandler {
         if lq.trim_start().starts_with("create database ") || lq.trim_start().starts_with("drop database ") {
             return Ok(DbResponse { columns: vec![], rows: vec![], rows_affected: 0 });
         }
+        if let Some(remote) = self.remote_for(query) {
+            let processed_query = self.connection_manager.execute_with_session(session_id, |conn| {
+                process_query(query, conn, &self.schema_cache)
+            })?;
+            return remote.execute_with_params(&processed_query, params, session_id).await;
+        }
+        // Convert params to rusqlite values
+        // For now, be more aggressive about converting to text since most PostgreSQL
+        // parameters in text mode should be text-compatible
+        let values: Vec<rusqlite::types::Value> = params.iter()
+            .map(|p| match p {
+                Some(data) => {
+                    match String::from_utf8(data.clone()) {
+                        Ok(s) => {
+                            // Parameter converted to text
+                            rusqlite::types::Value::Text(s)
+                        },
+                        Err(_e) => {
+                            // For psycopg3 in text mode, all parameters should be UTF-8 text
+                            // If UTF-8 conversion fails, try to recover by using lossy conversion
+                            // UTF-8 conversion failed, trying lossy
+                            let lossy_string = String::from_utf8_lossy(data);
+                            if !lossy_string.is_empty() {
+                                // Lossy conversion successful
+                                rusqlite::types::Value::Text(lossy_string.into_owned())
+                            } else {
+                                // Storing as blob
+                                rusqlite::types::Value::Blob(data.clone())
+                            }
+                        },
+                    }
+                }
+                None => {
+                    // Null parameter
+                    rusqlite::types::Value::Null
+                },
+            })
+            .collect();
+        
         let result = self.connection_manager.execute_with_session(session_id, |conn| {
             // Process query with fast path optimization
             let processed_query = process_query(query, conn, &self.schema_cache)?;
@@ -278,38 +486,6 @@ impl DbHandler {
             
             let mut stmt = conn.prepare(&processed_query)?;
             
-            // Convert params to rusqlite values
-            // For now, be more aggressive about converting to text since most PostgreSQL
-            // parameters in text mode should be text-compatible
-            let values: Vec<rusqlite::types::Value> = params.iter()
-                .map(|p| match p {
-                    Some(data) => {
-                        match String::from_utf8(data.clone()) {
-                            Ok(s) => {
-                                // Parameter converted to text
-                                rusqlite::types::Value::Text(s)
-                            },
-                            Err(_e) => {
-                                // For psycopg3 in text mode, all parameters should be UTF-8 text
-                                // If UTF-8 conversion fails, try to recover by using lossy conversion
-                                // UTF-8 conversion failed, trying lossy
-                                let lossy_string = String::from_utf8_lossy(data);
-                                if !lossy_string.is_empty() {
-                                    // Lossy conversion successful
-                                    rusqlite::types::Value::Text(lossy_string.into_owned())
-                                } else {
-                                    // Storing as blob
-                                    rusqlite::types::Value::Blob(data.clone())
-                                }
-                            },
-                        }
-                    }
-                    None => {
-                        // Null parameter
-                        rusqlite::types::Value::Null
-                    },
-                })
-                .collect();
             
             let query_type = QueryTypeDetector::detect_query_type(query);
             
@@ -366,7 +542,8 @@ impl DbHandler {
             }
             
             Ok(result)
-        })?;
+        });
+        let result = self.explain_constraint_failure(result, session_id, query, &values, true)?;
         
         // After the closure completes, check if we need WAL refresh
         let query_type = QueryTypeDetector::detect_query_type(query);
@@ -416,7 +593,7 @@ impl DbHandler {
                 // Process query with fast path optimization
                 let processed_query = process_query(query, conn, &self.schema_cache)?;
                 if is_select_query_fast(&processed_query) {
-                    let (columns, rows) = StatementPool::global().query_cached(conn, &processed_query, [])?;
+                    let (columns, rows) = self.caches.statements.query_cached(conn, &processed_query, [])?;
                     Ok(DbResponse { columns, rows, rows_affected: 0 })
                 } else {
                     let mut stmt = conn.prepare(&processed_query)?;
@@ -499,15 +676,22 @@ impl DbHandler {
             // which will strip the schema prefix and allow them to query the views
         }
         
+        if let Some(remote) = self.remote_for(query) {
+            let processed_query = self.connection_manager.execute_with_session(session_id, |conn| {
+                process_query(query, conn, &self.schema_cache)
+            })?;
+            return remote.query_with_session(&processed_query, session_id).await;
+        }
+        
         // Use cached connection if available, otherwise fall back to lookup
         match cached_conn {
             Some(conn) => {
-                self.connection_manager.execute_with_cached_connection(conn, |conn| {
+                let result = self.connection_manager.execute_with_cached_connection(conn, |conn| {
                     // Process query with fast path optimization
                     let processed_query = process_query(query, conn, &self.schema_cache)?;
                     
                     if is_select_query_fast(&processed_query) {
-                        let (columns, rows) = StatementPool::global().query_cached(conn, &processed_query, [])?;
+                        let (columns, rows) = self.caches.statements.query_cached(conn, &processed_query, [])?;
                         Ok(DbResponse { columns, rows, rows_affected: 0 })
                     } else {
                         let mut stmt = conn.prepare(&processed_query)?;
@@ -532,7 +716,8 @@ impl DbHandler {
                         })?.collect();
                         Ok(DbResponse { columns, rows: rows?, rows_affected: 0 })
                     }
-                })
+                });
+                self.explain_constraint_failure(result, session_id, query, &[], true)
             }
             None => {
                 // Fall back to regular lookup
@@ -541,6 +726,51 @@ impl DbHandler {
         }
     }
     
+    /// The result columns `query` would produce, read from the prepared statement
+    /// without running it, so describing a statement never has side effects
+    pub async fn describe_with_session(&self, query: &str, session_id: &Uuid) -> Result<Vec<(String, Option<String>)>, PgSqliteError> {
+        self.connection_manager.execute_with_session(session_id, |conn| {
+            let processed_query = process_query(query, conn, &self.schema_cache)?;
+            Self::prepared_columns(conn, &processed_query)
+        })
+    }
+
+    /// The first row `query` returns, for typing columns whose type depends on the value,
+    /// like json_extract()'s. Only a statement SQLite reports as read-only is run; None
+    /// for the others and when there are no rows.
+    pub async fn first_row_if_read_only(&self, query: &str, session_id: &Uuid) -> Result<Option<Vec<rusqlite::types::Value>>, PgSqliteError> {
+        self.connection_manager.execute_with_session(session_id, |conn| {
+            let processed_query = process_query(query, conn, &self.schema_cache)?;
+            let mut stmt = conn.prepare(&processed_query)?;
+            if !stmt.readonly() {
+                return Ok(None);
+            }
+            let column_count = stmt.column_count();
+            let mut rows = stmt.query([])?;
+            let Some(row) = rows.next()? else {
+                return Ok(None);
+            };
+            (0..column_count).map(|i| row.get(i)).collect::<Result<Vec<_>, _>>().map(Some)
+        })
+    }
+
+    /// Each result column of `query` with the PostgreSQL type of the table column it
+    /// comes from, or SQLite's declared type when pgsqlite has no metadata for it
+    pub fn prepared_columns(conn: &rusqlite::Connection, query: &str) -> Result<Vec<(String, Option<String>)>, rusqlite::Error> {
+        let stmt = conn.prepare(query)?;
+        let columns = stmt.columns().into_iter().zip(stmt.columns_with_metadata()).map(|(column, origin)| {
+            let pg_type = origin.table_name().zip(origin.origin_name()).and_then(|(table, column_name)| {
+                conn.query_row(
+                    "SELECT pg_type FROM __pgsqlite_schema WHERE table_name = ?1 AND column_name = ?2",
+                    [table, column_name],
+                    |row| row.get(0),
+                ).ok()
+            });
+            (column.name().to_string(), pg_type.or_else(|| column.decl_type().map(str::to_string)))
+        }).collect();
+        Ok(columns)
+    }
+
     /// Query with session-specific connection
     pub async fn query_with_session(&self, query: &str, session_id: &Uuid) -> Result<DbResponse, PgSqliteError> {
         // Check if this is a catalog query that should be intercepted
@@ -584,12 +814,19 @@ impl DbHandler {
             // which will strip the schema prefix and allow them to query the views
         }
         
-        self.connection_manager.execute_with_session(session_id, |conn| {
+        if let Some(remote) = self.remote_for(query) {
+            let processed_query = self.connection_manager.execute_with_session(session_id, |conn| {
+                process_query(query, conn, &self.schema_cache)
+            })?;
+            return remote.query_with_session(&processed_query, session_id).await;
+        }
+        
+        let result = self.connection_manager.execute_with_session(session_id, |conn| {
             // Process query with fast path optimization
             let processed_query = process_query(query, conn, &self.schema_cache)?;
             
             if is_select_query_fast(&processed_query) {
-                let (columns, rows) = StatementPool::global().query_cached(conn, &processed_query, [])?;
+                let (columns, rows) = self.caches.statements.query_cached(conn, &processed_query, [])?;
                 Ok(DbResponse { columns, rows, rows_affected: 0 })
             } else {
                 let mut stmt = conn.prepare(&processed_query)?;
@@ -614,7 +851,8 @@ impl DbHandler {
                 })?.collect();
                 Ok(DbResponse { columns, rows: rows?, rows_affected: 0 })
             }
-        })
+        });
+        self.explain_constraint_failure(result, session_id, query, &[], true)
     }
     
     /// Execute without session (compatibility - creates temporary connection)
@@ -678,9 +916,12 @@ impl DbHandler {
         if lq.trim_start().starts_with("create database ") || lq.trim_start().starts_with("drop database ") {
             return Ok(DbResponse { columns: vec![], rows: vec![], rows_affected: 0 });
         }
+        if let Some(remote) = self.remote_for(query) {
+            return self.execute_remote(remote, query, session_id).await;
+        }
         match cached_conn {
             Some(conn) => {
-                self.connection_manager.execute_with_cached_connection(conn, |conn| {
+                let result = self.connection_manager.execute_with_cached_connection(conn, |conn| {
                     // Process query with fast path optimization
                     let processed_query = process_query(query, conn, &self.schema_cache)?;
                     
@@ -690,7 +931,8 @@ impl DbHandler {
                         rows: vec![],
                         rows_affected,
                     })
-                })
+                });
+                self.explain_constraint_failure(result, session_id, query, &[], true)
             }
             None => {
                 // Fall back to regular lookup
@@ -706,7 +948,10 @@ impl DbHandler {
         if lq.trim_start().starts_with("create database ") || lq.trim_start().starts_with("drop database ") {
             return Ok(DbResponse { columns: vec![], rows: vec![], rows_affected: 0 });
         }
-        self.connection_manager.execute_with_session(session_id, |conn| {
+        if let Some(remote) = self.remote_for(query) {
+            return self.execute_remote(remote, query, session_id).await;
+        }
+        let result = self.connection_manager.execute_with_session(session_id, |conn| {
             // Process query with fast path optimization
             let processed_query = process_query(query, conn, &self.schema_cache)?;
             
@@ -716,7 +961,42 @@ impl DbHandler {
                 rows: vec![],
                 rows_affected,
             })
-        })
+        });
+        self.explain_constraint_failure(result, session_id, query, &[], true)
+    }
+    
+    /// SQLite's constraint errors don't name the constraint; look it up on the session's
+    /// connection (rerunning the statement for foreign keys) to report the error PostgreSQL
+    /// would. `translate` is false when `query` has already been through `process_query`.
+    fn explain_constraint_failure<R>(
+        &self,
+        result: Result<R, PgSqliteError>,
+        session_id: &Uuid,
+        query: &str,
+        params: &[rusqlite::types::Value],
+        translate: bool,
+    ) -> Result<R, PgSqliteError> {
+        match result {
+            Err(PgSqliteError::Sqlite(e)) if ForeignKeys::is_violation(&e) || Constraints::is_violation(&e) => {
+                let explained = self.connection_manager.execute_with_session(session_id, |conn| {
+                    let processed_query = if translate {
+                        process_query(query, conn, &self.schema_cache)?
+                    } else {
+                        query.to_string()
+                    };
+                    Ok(if ForeignKeys::is_violation(&e) {
+                        ForeignKeys::explain_violation(conn, &processed_query, rusqlite::params_from_iter(params.iter()))
+                    } else {
+                        Constraints::explain_violation(conn, &processed_query, &e)
+                    })
+                });
+                match explained {
+                    Ok(Some(violation)) => Err(PgSqliteError::Validation(violation)),
+                    _ => Err(PgSqliteError::Sqlite(e)),
+                }
+            }
+            other => other,
+        }
     }
     
     /// Transaction control methods
@@ -724,15 +1004,35 @@ impl DbHandler {
         self.connection_manager.execute_with_session(session_id, |conn| {
             conn.execute("BEGIN", [])?;
             Ok(())
-        })
+        })?;
+        if let Some(remote) = &self.remote
+            && let Err(e) = remote.begin_with_session(session_id).await {
+            self.rollback(session_id).await?;
+            return Err(e);
+        }
+        Ok(())
     }
     
     pub async fn commit(&self, session_id: &Uuid) -> Result<(), PgSqliteError> {
+        // The remote transaction decides; local metadata writes follow its outcome
+        if let Some(remote) = &self.remote
+            && let Err(e) = remote.commit_with_session(session_id).await {
+            self.connection_manager.execute_with_session(session_id, |conn| conn.execute_batch("ROLLBACK"))?;
+            return Err(e);
+        }
+        
         // Execute the commit on the current session
-        self.connection_manager.execute_with_session(session_id, |conn| {
+        let result = self.connection_manager.execute_with_session(session_id, |conn| {
             conn.execute("COMMIT", [])?;
             Ok(())
-        })?;
+        });
+        if let Err(e) = self.explain_constraint_failure(result, session_id, "COMMIT", &[], false) {
+            // A deferred foreign key failure leaves the transaction open; PostgreSQL rolls it back
+            if !self.connection_manager.execute_with_session(session_id, |conn| Ok(conn.is_autocommit()))? {
+                self.connection_manager.execute_with_session(session_id, |conn| conn.execute_batch("ROLLBACK"))?;
+            }
+            return Err(e);
+        }
         
         // Force all other connections to refresh their WAL view (WAL mode only)
         // This ensures committed data is visible to all other sessions
@@ -758,7 +1058,11 @@ impl DbHandler {
                 Err(e) => Err(e),
             }?;
             Ok(())
-        })
+        })?;
+        if let Some(remote) = &self.remote {
+            remote.rollback_with_session(session_id).await?;
+        }
+        Ok(())
     }
     
     pub async fn rollback_with_session(&self, session_id: &Uuid) -> Result<(), PgSqliteError> {
@@ -856,12 +1160,21 @@ impl DbHandler {
         params: &[rusqlite::types::Value],
         session_id: &Uuid,
     ) -> Result<Option<DbResponse>, PgSqliteError> {
+        if self.remote_for(query).is_some() {
+            return Ok(None);
+        }
         
         // Detect query type before the closure
         let query_type = QueryTypeDetector::detect_query_type(query);
         
         // Use the connection manager to get the session connection
         let result = self.connection_manager.execute_with_session(session_id, |conn| {
+            // NUMERIC columns hold decimal text, which only the decimal rewriter computes on
+            if crate::query::simple_query_detector::extract_simple_table_name(query)
+                .is_some_and(|table| self.schema_cache.has_decimal_columns_or_load(conn, &table)) {
+                return Ok(None);
+            }
+            
             // Execute the query directly with rusqlite parameters
             let mut stmt = conn.prepare(query)?;
             
@@ -1067,7 +1380,8 @@ impl DbHandler {
             };
             
             Ok(Some(response?))
-        })?;
+        });
+        let result = self.explain_constraint_failure(result, session_id, query, params, false)?;
         
         // After a successful DML operation, check if we need to trigger WAL refresh
         // This is needed for autocommit mode where no explicit COMMIT is sent
@@ -1166,6 +1480,10 @@ impl DbHandler {
     pub fn get_schema_cache(&self) -> &Arc<SchemaCache> {
         &self.schema_cache
     }
+
+    pub fn get_caches(&self) -> &Arc<DatabaseCaches> {
+        &self.caches
+    }
     
     pub fn get_string_validator(&self) -> &Arc<StringConstraintValidator> {
         &self.string_validator
@@ -0,0 +1,182 @@
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use parking_lot::Mutex;
+use rusqlite::Connection;
+use tokio::sync::oneshot;
+use uuid::Uuid;
+use crate::config::Config;
+use tracing::{debug, warn};
+
+type FlushWaiter = oneshot::Sender<Result<(), Arc<io::Error>>>;
+
+/// Makes the commits of concurrent sessions durable with one fsync of the WAL.
+///
+/// Connections commit with `synchronous = NORMAL`, which appends to the WAL without
+/// syncing it, and their commit hook marks the session. Before a marked session
+/// acknowledges its commit with ReadyForQuery it waits for a flush: the first waiter
+/// schedules one `max_delay` later, and every session that committed by then shares it.
+pub struct GroupCommit {
+    wal_path: PathBuf,
+    max_delay: Duration,
+    /// Sessions whose commits may not be on disk yet
+    unsynced: Mutex<HashSet<Uuid>>,
+    /// Waiters of the scheduled flush, `None` while no flush is scheduled
+    waiters: Mutex<Option<Vec<FlushWaiter>>>,
+    flushes: AtomicU64,
+}
+
+impl GroupCommit {
+    pub fn new(wal_path: impl Into<PathBuf>, max_delay: Duration) -> Self {
+        Self {
+            wal_path: wal_path.into(),
+            max_delay,
+            unsynced: Mutex::new(HashSet::new()),
+            waiters: Mutex::new(None),
+            flushes: AtomicU64::new(0),
+        }
+    }
+
+    /// Group commit for the database `conn` is open on, when `--group-commit-delay-ms`
+    /// asks for it and the database keeps a WAL file to sync
+    pub fn for_database(conn: &Connection, config: &Config) -> Option<Arc<Self>> {
+        // A --read-only server has no commits to sync
+        if config.group_commit_delay_ms == 0 || config.read_only {
+            return None;
+        }
+        let journal_mode: String = conn.query_row("PRAGMA journal_mode", [], |row| row.get(0)).ok()?;
+        match conn.path().filter(|path| !path.is_empty()) {
+            Some(path) if journal_mode.eq_ignore_ascii_case("wal") => Some(Arc::new(Self::new(
+                format!("{path}-wal"),
+                Duration::from_millis(config.group_commit_delay_ms),
+            ))),
+            _ => {
+                warn!("Group commit needs a database file in WAL journal mode; each commit syncs on its own");
+                None
+            }
+        }
+    }
+
+    /// Record a commit of the session, called from its connection's commit hook
+    pub fn committed(&self, session_id: Uuid) {
+        self.unsynced.lock().insert(session_id);
+    }
+
+    /// Forget a session that is going away
+    pub fn forget(&self, session_id: &Uuid) {
+        self.unsynced.lock().remove(session_id);
+    }
+
+    /// Wait until the session's commits are on disk; returns at once when it has none
+    pub async fn wait_durable(self: &Arc<Self>, session_id: &Uuid) -> io::Result<()> {
+        if !self.unsynced.lock().remove(session_id) {
+            return Ok(());
+        }
+        let (sender, receiver) = oneshot::channel();
+        self.join_flush(Some(sender));
+        match receiver.await {
+            Ok(result) => result.map_err(|e| io::Error::new(e.kind(), e.to_string())),
+            Err(_) => Err(io::Error::other("group commit flush was dropped")),
+        }
+    }
+
+    /// Make sure a flush is coming, for a commit no session waits for
+    pub fn schedule_flush(self: &Arc<Self>) {
+        self.join_flush(None);
+    }
+
+    fn join_flush(self: &Arc<Self>, waiter: Option<FlushWaiter>) {
+        let mut waiters = self.waiters.lock();
+        match waiters.as_mut() {
+            Some(batch) => batch.extend(waiter),
+            None => {
+                // Outside the runtime the commit waits for the next flush, or a checkpoint
+                let Ok(runtime) = tokio::runtime::Handle::try_current() else { return };
+                *waiters = Some(waiter.into_iter().collect());
+                runtime.spawn(self.clone().flush());
+            }
+        }
+    }
+
+    /// Number of fsyncs so far
+    pub fn flushes(&self) -> u64 {
+        self.flushes.load(Ordering::Relaxed)
+    }
+
+    async fn flush(self: Arc<Self>) {
+        tokio::time::sleep(self.max_delay).await;
+        // Commits that finish from here on wait for the next flush
+        let waiters = self.waiters.lock().take().unwrap_or_default();
+        let wal_path = self.wal_path.clone();
+        let result = tokio::task::spawn_blocking(move || sync_file(&wal_path)).await
+            .unwrap_or_else(|e| Err(io::Error::other(e)))
+            .map_err(Arc::new);
+        self.flushes.fetch_add(1, Ordering::Relaxed);
+        match &result {
+            Ok(()) => debug!("Group commit synced the commits of {} sessions", waiters.len()),
+            Err(e) => warn!("Group commit failed to sync {}: {}", self.wal_path.display(), e),
+        }
+        for waiter in waiters {
+            let _ = waiter.send(result.clone());
+        }
+    }
+}
+
+fn sync_file(path: &Path) -> io::Result<()> {
+    match std::fs::File::open(path) {
+        // A checkpoint that removed the WAL synced the database file before
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        file => file?.sync_data(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_concurrent_commits_share_a_flush() {
+        let dir = tempfile::tempdir().unwrap();
+        let wal_path = dir.path().join("test.db-wal");
+        std::fs::write(&wal_path, b"frames").unwrap();
+        let group = Arc::new(GroupCommit::new(&wal_path, Duration::from_millis(20)));
+
+        let sessions: Vec<Uuid> = (0..8).map(|_| Uuid::new_v4()).collect();
+        for session in &sessions {
+            group.committed(*session);
+        }
+        let waits = sessions.iter().map(|session| {
+            let group = group.clone();
+            let session = *session;
+            tokio::spawn(async move { group.wait_durable(&session).await })
+        });
+        for wait in waits.collect::<Vec<_>>() {
+            wait.await.unwrap().unwrap();
+        }
+        assert_eq!(group.flushes(), 1);
+
+        // Sessions without new commits don't wait, and a removed WAL has nothing to sync
+        group.wait_durable(&sessions[0]).await.unwrap();
+        assert_eq!(group.flushes(), 1);
+        std::fs::remove_file(&wal_path).unwrap();
+        group.committed(sessions[0]);
+        group.wait_durable(&sessions[0]).await.unwrap();
+        assert_eq!(group.flushes(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_unwaited_commit_is_flushed() {
+        let dir = tempfile::tempdir().unwrap();
+        let wal_path = dir.path().join("test.db-wal");
+        std::fs::write(&wal_path, b"frames").unwrap();
+        let group = Arc::new(GroupCommit::new(&wal_path, Duration::from_millis(10)));
+
+        group.schedule_flush();
+        group.schedule_flush();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(group.flushes(), 1);
+    }
+}
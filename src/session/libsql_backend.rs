@@ -0,0 +1,386 @@
+use crate::session::{DbResponse, StorageBackend};
+use crate::PgSqliteError;
+use async_trait::async_trait;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD_NO_PAD;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::debug;
+use uuid::Uuid;
+
+/// Remote libSQL/Turso database reached over the Hrana HTTP protocol (`/v2/pipeline`)
+///
+/// Outside a transaction every statement runs on a short-lived stream that is closed in the
+/// same request, so idle sessions never hold a server stream that could expire. BEGIN opens
+/// a stream that the session keeps, through the baton, until COMMIT or ROLLBACK.
+pub struct LibsqlBackend {
+    client: reqwest::Client,
+    base_url: String,
+    auth_token: Option<String>,
+    streams: Mutex<HashMap<Uuid, Stream>>,
+    /// PostgreSQL column types from the remote `__pgsqlite_schema`, by table; dropped on DDL
+    schema_types: RwLock<HashMap<String, HashMap<String, String>>>,
+}
+
+/// A session's open Hrana stream
+#[derive(Default)]
+struct Stream {
+    baton: Option<String>,
+    base_url: Option<String>,
+}
+
+#[derive(Serialize)]
+struct PipelineRequest<'a> {
+    baton: Option<&'a str>,
+    requests: Vec<StreamRequest<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamRequest<'a> {
+    Execute { stmt: Stmt<'a> },
+    Close,
+}
+
+#[derive(Serialize)]
+struct Stmt<'a> {
+    sql: &'a str,
+    args: Vec<Value>,
+    want_rows: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Value {
+    Null,
+    Integer { value: String },
+    Float { value: f64 },
+    Text { value: String },
+    Blob { base64: String },
+}
+
+#[derive(Deserialize)]
+struct PipelineResponse {
+    baton: Option<String>,
+    base_url: Option<String>,
+    results: Vec<StreamResult>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamResult {
+    Ok { response: StreamResponse },
+    Error { error: HranaError },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamResponse {
+    Execute { result: StmtResult },
+    Close,
+}
+
+#[derive(Deserialize)]
+struct StmtResult {
+    cols: Vec<Col>,
+    rows: Vec<Vec<Value>>,
+    affected_row_count: u64,
+}
+
+#[derive(Deserialize)]
+struct Col {
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct HranaError {
+    message: String,
+    code: Option<String>,
+}
+
+/// How a statement changes the session's transaction state
+#[derive(Debug, PartialEq)]
+enum TransactionEffect {
+    Begin,
+    End,
+    None,
+}
+
+impl LibsqlBackend {
+    /// Backend for a database URL (`libsql://`, `https://` or `http://`) and optional auth token
+    pub fn new(url: &str, auth_token: Option<String>) -> Result<Self, PgSqliteError> {
+        let base_url = if let Some(host) = url.strip_prefix("libsql://").or_else(|| url.strip_prefix("wss://")) {
+            format!("https://{host}")
+        } else if let Some(host) = url.strip_prefix("ws://") {
+            format!("http://{host}")
+        } else if url.starts_with("https://") || url.starts_with("http://") {
+            url.to_string()
+        } else {
+            return Err(PgSqliteError::InvalidParameter(format!(
+                "unsupported libSQL URL '{url}': expected libsql://, https:// or http://"
+            )));
+        };
+
+        // Same crypto provider as the TLS server, with the Mozilla root certificates
+        let roots = rustls::RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        let tls = rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::aws_lc_rs::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| PgSqliteError::Protocol(format!("Failed to configure libSQL TLS: {e}")))?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let client = reqwest::Client::builder()
+            .use_preconfigured_tls(tls)
+            .build()
+            .map_err(|e| PgSqliteError::Protocol(format!("Failed to create libSQL client: {e}")))?;
+
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            auth_token: auth_token.filter(|token| !token.is_empty()),
+            streams: Mutex::new(HashMap::new()),
+            schema_types: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// Run one statement on the session's stream
+    async fn execute_stmt(&self, query: &str, args: Vec<Value>, session_id: &Uuid) -> Result<StmtResult, PgSqliteError> {
+        let (baton, base_url) = {
+            let streams = self.streams.lock();
+            let stream = streams.get(session_id)
+                .ok_or_else(|| PgSqliteError::Protocol(format!("No libSQL stream for session {session_id}")))?;
+            (stream.baton.clone(), stream.base_url.clone())
+        };
+
+        // Keep the stream only while a transaction is open on it
+        let keep_open = match transaction_effect(query) {
+            TransactionEffect::Begin => true,
+            TransactionEffect::End => false,
+            TransactionEffect::None => baton.is_some(),
+        };
+
+        let mut requests = vec![StreamRequest::Execute { stmt: Stmt { sql: query, args, want_rows: true } }];
+        if !keep_open {
+            requests.push(StreamRequest::Close);
+        }
+        let request = PipelineRequest { baton: baton.as_deref(), requests };
+
+        let response = self.send_pipeline(base_url.as_deref(), &request).await;
+        let mut streams = self.streams.lock();
+        let response = match response {
+            Ok(response) => response,
+            Err(e) => {
+                // A failed request leaves no usable stream behind
+                if let Some(stream) = streams.get_mut(session_id) {
+                    *stream = Stream::default();
+                }
+                return Err(e);
+            }
+        };
+        if let Some(stream) = streams.get_mut(session_id) {
+            *stream = if keep_open {
+                Stream { baton: response.baton, base_url: response.base_url.or(base_url) }
+            } else {
+                Stream::default()
+            };
+        }
+        drop(streams);
+
+        match response.results.into_iter().next() {
+            Some(StreamResult::Ok { response: StreamResponse::Execute { result } }) => {
+                if is_ddl(query) {
+                    self.schema_types.write().clear();
+                }
+                Ok(result)
+            }
+            Some(StreamResult::Error { error }) => Err(sqlite_error(error)),
+            _ => Err(PgSqliteError::Protocol("libSQL server returned no statement result".to_string())),
+        }
+    }
+
+    async fn send_pipeline(&self, base_url: Option<&str>, request: &PipelineRequest<'_>) -> Result<PipelineResponse, PgSqliteError> {
+        let url = format!("{}/v2/pipeline", base_url.unwrap_or(&self.base_url));
+        debug!("libSQL pipeline request to {}", url);
+
+        let mut builder = self.client.post(&url).json(request);
+        if let Some(token) = &self.auth_token {
+            builder = builder.bearer_auth(token);
+        }
+        let response = builder.send().await
+            .map_err(|e| PgSqliteError::Protocol(format!("libSQL request failed: {e}")))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(PgSqliteError::Protocol(format!("libSQL server returned {status}: {body}")));
+        }
+        response.json().await
+            .map_err(|e| PgSqliteError::Protocol(format!("Invalid libSQL response: {e}")))
+    }
+}
+
+#[async_trait]
+impl StorageBackend for LibsqlBackend {
+    fn name(&self) -> &'static str {
+        "libsql"
+    }
+
+    async fn create_session_connection(&self, session_id: Uuid) -> Result<(), PgSqliteError> {
+        // Streams are opened lazily by the first statement
+        self.streams.lock().insert(session_id, Stream::default());
+        Ok(())
+    }
+
+    fn remove_session_connection(&self, session_id: &Uuid) {
+        let Some(stream) = self.streams.lock().remove(session_id) else { return };
+        let Some(baton) = stream.baton else { return };
+
+        // Close the abandoned transaction's stream so the server rolls it back right away
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            let client = self.client.clone();
+            let url = format!("{}/v2/pipeline", stream.base_url.as_deref().unwrap_or(&self.base_url));
+            let auth_token = self.auth_token.clone();
+            handle.spawn(async move {
+                let request = PipelineRequest { baton: Some(&baton), requests: vec![StreamRequest::Close] };
+                let mut builder = client.post(&url).json(&request);
+                if let Some(token) = &auth_token {
+                    builder = builder.bearer_auth(token);
+                }
+                if let Err(e) = builder.send().await {
+                    debug!("Failed to close libSQL stream: {}", e);
+                }
+            });
+        }
+    }
+
+    async fn query_with_session(&self, query: &str, session_id: &Uuid) -> Result<DbResponse, PgSqliteError> {
+        let result = self.execute_stmt(query, Vec::new(), session_id).await?;
+        Ok(into_response(result))
+    }
+
+    async fn execute_with_session(&self, query: &str, session_id: &Uuid) -> Result<DbResponse, PgSqliteError> {
+        let result = self.execute_stmt(query, Vec::new(), session_id).await?;
+        Ok(DbResponse { columns: vec![], rows: vec![], rows_affected: result.affected_row_count as usize })
+    }
+
+    async fn execute_with_params(
+        &self,
+        query: &str,
+        params: &[Option<Vec<u8>>],
+        session_id: &Uuid,
+    ) -> Result<DbResponse, PgSqliteError> {
+        // Text-format parameters bind as TEXT like on the local engine; anything else as a BLOB
+        let args = params.iter()
+            .map(|param| match param {
+                Some(data) => match std::str::from_utf8(data) {
+                    Ok(text) => Value::Text { value: text.to_string() },
+                    Err(_) => Value::Blob { base64: STANDARD_NO_PAD.encode(data) },
+                },
+                None => Value::Null,
+            })
+            .collect();
+        let result = self.execute_stmt(query, args, session_id).await?;
+        Ok(into_response(result))
+    }
+
+    async fn begin_with_session(&self, session_id: &Uuid) -> Result<(), PgSqliteError> {
+        self.execute_stmt("BEGIN", Vec::new(), session_id).await.map(|_| ())
+    }
+
+    async fn commit_with_session(&self, session_id: &Uuid) -> Result<(), PgSqliteError> {
+        self.execute_stmt("COMMIT", Vec::new(), session_id).await.map(|_| ())
+    }
+
+    async fn rollback_with_session(&self, session_id: &Uuid) -> Result<(), PgSqliteError> {
+        let in_transaction = self.streams.lock().get(session_id).is_some_and(|stream| stream.baton.is_some());
+        if !in_transaction {
+            return Ok(());
+        }
+        self.execute_stmt("ROLLBACK", Vec::new(), session_id).await.map(|_| ())
+    }
+
+    async fn get_schema_type_with_session(
+        &self,
+        session_id: &Uuid,
+        table_name: &str,
+        column_name: &str,
+    ) -> Result<Option<String>, PgSqliteError> {
+        if let Some(columns) = self.schema_types.read().get(table_name) {
+            return Ok(columns.get(column_name).cloned());
+        }
+
+        // Databases that pgsqlite never wrote to have no metadata table; cache that too
+        let columns: HashMap<String, String> = match self.execute_stmt(
+            "SELECT column_name, pg_type FROM __pgsqlite_schema WHERE table_name = ?",
+            vec![Value::Text { value: table_name.to_string() }],
+            session_id,
+        ).await {
+            Ok(result) => result.rows.into_iter()
+                .filter_map(|row| match <[Value; 2]>::try_from(row) {
+                    Ok([Value::Text { value: column }, Value::Text { value: pg_type }]) => Some((column, pg_type)),
+                    _ => None,
+                })
+                .collect(),
+            Err(PgSqliteError::Sqlite(_)) => HashMap::new(),
+            Err(e) => return Err(e),
+        };
+
+        let pg_type = columns.get(column_name).cloned();
+        self.schema_types.write().insert(table_name.to_string(), columns);
+        Ok(pg_type)
+    }
+}
+
+fn transaction_effect(query: &str) -> TransactionEffect {
+    let upper = query.trim_start().to_uppercase();
+    if upper.starts_with("BEGIN") || upper.starts_with("START TRANSACTION") || upper.starts_with("SAVEPOINT") {
+        TransactionEffect::Begin
+    } else if upper.starts_with("COMMIT") || upper.starts_with("END")
+        || (upper.starts_with("ROLLBACK") && !upper.contains(" TO ")) {
+        TransactionEffect::End
+    } else {
+        TransactionEffect::None
+    }
+}
+
+fn is_ddl(query: &str) -> bool {
+    let upper = query.trim_start().to_uppercase();
+    upper.starts_with("CREATE") || upper.starts_with("ALTER") || upper.starts_with("DROP")
+}
+
+/// Rows in the byte format the local engine produces
+fn into_response(result: StmtResult) -> DbResponse {
+    let columns = result.cols.into_iter().map(|col| col.name.unwrap_or_default()).collect();
+    let rows = result.rows.into_iter()
+        .map(|row| row.into_iter().map(value_bytes).collect())
+        .collect();
+    DbResponse { columns, rows, rows_affected: result.affected_row_count as usize }
+}
+
+fn value_bytes(value: Value) -> Option<Vec<u8>> {
+    match value {
+        Value::Null => None,
+        Value::Integer { value } | Value::Text { value } => Some(value.into_bytes()),
+        Value::Float { value } => Some(value.to_string().into_bytes()),
+        Value::Blob { base64 } => STANDARD_NO_PAD.decode(base64.trim_end_matches('=')).ok(),
+    }
+}
+
+/// Remote statement errors as SQLite errors, so they map to the same SQLSTATEs as local ones
+fn sqlite_error(error: HranaError) -> PgSqliteError {
+    use rusqlite::ffi;
+    let code = match error.code.as_deref().unwrap_or("") {
+        "SQLITE_CONSTRAINT_UNIQUE" => ffi::SQLITE_CONSTRAINT_UNIQUE,
+        "SQLITE_CONSTRAINT_PRIMARYKEY" => ffi::SQLITE_CONSTRAINT_PRIMARYKEY,
+        "SQLITE_CONSTRAINT_NOTNULL" => ffi::SQLITE_CONSTRAINT_NOTNULL,
+        "SQLITE_CONSTRAINT_FOREIGNKEY" => ffi::SQLITE_CONSTRAINT_FOREIGNKEY,
+        "SQLITE_CONSTRAINT_CHECK" => ffi::SQLITE_CONSTRAINT_CHECK,
+        code if code.starts_with("SQLITE_CONSTRAINT") => ffi::SQLITE_CONSTRAINT,
+        "SQLITE_BUSY" => ffi::SQLITE_BUSY,
+        _ => ffi::SQLITE_ERROR,
+    };
+    // sqld reports SQL errors as "SQLite error: <message>"; the local engine reports the message alone
+    let message = error.message.strip_prefix("SQLite error: ").unwrap_or(&error.message).to_string();
+    PgSqliteError::Sqlite(rusqlite::Error::SqliteFailure(ffi::Error::new(code), Some(message)))
+}
@@ -7,13 +7,25 @@ pub mod query_router;
 pub mod portal_manager;
 pub mod connection_manager;
 pub mod thread_local_cache;
+pub mod backend_registry;
+pub mod transaction_ids;
+pub mod advisory_locks;
+pub mod storage;
+pub mod libsql_backend;
+pub mod databases;
+pub mod group_commit;
 
-pub use state::{SessionState, PreparedStatement, Portal, GLOBAL_QUERY_CACHE};
+pub use state::{SessionState, PreparedStatement, Portal, ResultShape, GLOBAL_QUERY_CACHE};
 pub use pool::{SqlitePool, PooledConnection};
 pub use db_handler::{DbHandler, DbResponse};
 pub use read_only_handler::{ReadOnlyDbHandler, ReadOnlyError};
 pub use pool::PoolStats;
 pub use query_router::{QueryRouter, QueryRoute, QueryType, RouterError, RouterStats};
 pub use portal_manager::{PortalManager, PortalExecutor, ManagedPortal, PortalExecutionState, CachedQueryResult};
-pub use connection_manager::ConnectionManager;
-pub use thread_local_cache::ThreadLocalConnectionCache;
\ No newline at end of file
+pub use connection_manager::{ConnectionManager, SessionInterrupt};
+pub use thread_local_cache::ThreadLocalConnectionCache;
+pub use backend_registry::BackendRegistration;
+pub use storage::{SessionStorage, StorageBackend};
+pub use libsql_backend::LibsqlBackend;
+pub use databases::Databases;
+pub use group_commit::GroupCommit;
@@ -1,7 +1,8 @@
-use crate::session::{DbHandler, ReadOnlyDbHandler, DbResponse, ReadOnlyError};
+use crate::session::{ReadOnlyDbHandler, DbResponse, ReadOnlyError, SessionStorage};
 use crate::session::state::SessionState;
 use crate::config::Config;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use thiserror::Error;
 use tracing::{debug, info};
 
@@ -23,6 +24,9 @@ pub enum QueryRoute {
     Write,
     /// Use write handler due to active transaction
     WriteTransaction,
+    /// Use read-only handler once it has applied the session's last commit, the write
+    /// handler until then
+    ReadYourWrites,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -44,18 +48,24 @@ pub enum QueryType {
 
 /// Query router that determines whether to use read-only pool or write connection
 pub struct QueryRouter {
-    write_handler: Arc<DbHandler>,
+    write_handler: Arc<dyn SessionStorage>,
     read_handler: Arc<ReadOnlyDbHandler>,
     #[allow(dead_code)]
     config: Arc<Config>,
     /// Track if connection pooling is enabled
     pooling_enabled: bool,
+    /// Whether the commit position table exists on the writer
+    commit_position_ready: AtomicBool,
 }
 
+/// A single row counting the writes made through routers, so a reader can tell whether
+/// it has applied a given write. Readers that lack the table have applied none.
+const COMMIT_POSITION_TABLE: &str = "__pgsqlite_commit_position";
+
 impl QueryRouter {
     /// Create a new query router
     pub fn new(
-        write_handler: Arc<DbHandler>,
+        write_handler: Arc<dyn SessionStorage>,
         read_handler: Arc<ReadOnlyDbHandler>,
         config: Arc<Config>,
     ) -> Self {
@@ -64,6 +74,7 @@ impl QueryRouter {
             read_handler,
             config,
             pooling_enabled: true, // TODO: Make this configurable
+            commit_position_ready: AtomicBool::new(false),
         }
     }
 
@@ -82,9 +93,21 @@ impl QueryRouter {
                 let result = self.read_handler.query(sql).await?;
                 Ok(result)
             }
+            QueryRoute::ReadYourWrites => {
+                let position = session_state.last_commit_position();
+                if let Some(result) = self.read_handler.query_if(sql, (), |conn| Ok(applied_commit_position(conn)? >= position)).await? {
+                    info!("Executing query via read-only pool, which has applied commit position {}", position);
+                    return Ok(result);
+                }
+                info!("Executing query via write handler until the read-only pool applies commit position {}", position);
+                Ok(self.write_handler.query(sql).await?)
+            }
             QueryRoute::Write | QueryRoute::WriteTransaction => {
                 info!("Executing query via write handler");
                 let result = self.write_handler.query(sql).await?;
+                if !self.is_read_query(sql) {
+                    session_state.record_write(self.advance_commit_position().await?);
+                }
                 Ok(result)
             }
         }
@@ -105,10 +128,21 @@ impl QueryRouter {
                 let result = self.read_handler.query_with_params(sql, params).await?;
                 Ok(result)
             }
+            QueryRoute::ReadYourWrites => {
+                let position = session_state.last_commit_position();
+                if let Some(result) = self.read_handler.query_if(sql, params, |conn| Ok(applied_commit_position(conn)? >= position)).await? {
+                    return Ok(result);
+                }
+                // Like writes, until the pool applies the session's commit
+                Ok(self.write_handler.query(sql).await?)
+            }
             QueryRoute::Write | QueryRoute::WriteTransaction => {
                 // For now, use the write handler for parameterized queries
                 // TODO: Implement parameterized queries in write handler
                 let result = self.write_handler.query(sql).await?;
+                if !self.is_read_query(sql) {
+                    session_state.record_write(self.advance_commit_position().await?);
+                }
                 Ok(result)
             }
         }
@@ -126,26 +160,46 @@ impl QueryRouter {
             return QueryRoute::WriteTransaction;
         }
 
-        let query_type = self.classify_query(sql);
-        
-        match query_type {
-            QueryType::Select | QueryType::Explain => {
-                // Use read-only pool for SELECT and EXPLAIN queries
-                QueryRoute::ReadOnly
-            }
-            QueryType::Pragma => {
-                // Most pragma queries are read-only, but some modify state
-                if self.is_read_only_pragma(sql) {
-                    QueryRoute::ReadOnly
-                } else {
-                    QueryRoute::Write
-                }
-            }
-            _ => {
-                // All other operations use write handler
-                QueryRoute::Write
-            }
+        // All write operations use write handler
+        if !self.is_read_query(sql) {
+            return QueryRoute::Write;
+        }
+
+        // A pooled reader may not have applied the session's latest commit yet
+        if session_state.last_commit_position() > 0 {
+            return QueryRoute::ReadYourWrites;
+        }
+
+        QueryRoute::ReadOnly
+    }
+
+    /// Whether a query can run on the read-only pool
+    fn is_read_query(&self, sql: &str) -> bool {
+        match self.classify_query(sql) {
+            QueryType::Select | QueryType::Explain => true,
+            // Most pragma queries are read-only, but some modify state
+            QueryType::Pragma => self.is_read_only_pragma(sql),
+            _ => false,
+        }
+    }
+
+    /// Count a write in the commit position table and return the new position. Within
+    /// a transaction the count commits with the write.
+    async fn advance_commit_position(&self) -> Result<i64, RouterError> {
+        if !self.commit_position_ready.load(Ordering::Acquire) {
+            self.write_handler.query(&format!(
+                "CREATE TABLE IF NOT EXISTS {COMMIT_POSITION_TABLE} (id INTEGER PRIMARY KEY CHECK (id = 1), position INTEGER NOT NULL)"
+            )).await?;
+            self.commit_position_ready.store(true, Ordering::Release);
         }
+        let response = self.write_handler.query(&format!(
+            "INSERT INTO {COMMIT_POSITION_TABLE} (id, position) VALUES (1, 1)
+             ON CONFLICT (id) DO UPDATE SET position = position + 1 RETURNING position"
+        )).await?;
+        response.rows.first()
+            .and_then(|row| row.first().cloned().flatten())
+            .and_then(|position| String::from_utf8(position).ok()?.parse().ok())
+            .ok_or_else(|| RouterError::Other("commit position was not returned".to_string()))
     }
 
     /// Classify the type of SQL query
@@ -220,6 +274,17 @@ impl QueryRouter {
     }
 }
 
+/// The commit position a connection's view of the database has reached
+fn applied_commit_position(conn: &rusqlite::Connection) -> rusqlite::Result<i64> {
+    let position = conn.query_row(&format!("SELECT position FROM {COMMIT_POSITION_TABLE}"), [], |row| row.get(0));
+    match position {
+        Ok(position) => Ok(position),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(0),
+        Err(rusqlite::Error::SqliteFailure(_, Some(msg))) if msg.contains("no such table") => Ok(0),
+        Err(e) => Err(e),
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RouterStats {
     pub pooling_enabled: bool,
@@ -229,6 +294,7 @@ pub struct RouterStats {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::session::DbHandler;
 
     #[test]
     fn test_query_classification() {
@@ -346,4 +412,76 @@ mod tests {
             QueryRoute::ReadOnly
         );
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_read_your_writes() {
+        use std::time::{SystemTime, UNIX_EPOCH};
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let config = Arc::new(Config::load());
+
+        // The read pool is on a copy that stands for a replica lagging behind the writer
+        let write_db = format!("/tmp/test_affinity_write_{timestamp}.db");
+        let read_db = format!("/tmp/test_affinity_read_{timestamp}.db");
+
+        let write_handler = match DbHandler::new(&write_db) {
+            Ok(h) => Arc::new(h),
+            Err(_) => {
+                eprintln!("Skipping test_read_your_writes due to DB creation failure");
+                return;
+            }
+        };
+        let read_handler = match ReadOnlyDbHandler::new(&read_db, config.clone()) {
+            Ok(h) => Arc::new(h),
+            Err(_) => {
+                eprintln!("Skipping test_read_your_writes due to ReadOnly DB creation failure");
+                return;
+            }
+        };
+        write_handler.execute("CREATE TABLE affinity_items (id INTEGER PRIMARY KEY)").await.unwrap();
+        let router = QueryRouter::new(write_handler, read_handler, config);
+
+        let writer_session = SessionState::new_test();
+        let other_session = SessionState::new_test();
+
+        // Reads go to the pool until the session writes
+        assert_eq!(
+            router.determine_route("SELECT * FROM affinity_items", &writer_session).await,
+            QueryRoute::ReadOnly
+        );
+        router.execute_query("INSERT INTO affinity_items (id) VALUES (1)", &writer_session).await.unwrap();
+        router.execute_query("INSERT INTO affinity_items (id) VALUES (2)", &writer_session).await.unwrap();
+        assert_eq!(writer_session.last_commit_position(), 2);
+
+        // However long the replica lags, the writing session reads its rows from the writer
+        assert_eq!(
+            router.determine_route("SELECT * FROM affinity_items", &writer_session).await,
+            QueryRoute::ReadYourWrites
+        );
+        let response = router.execute_query("SELECT id FROM affinity_items ORDER BY id", &writer_session).await.unwrap();
+        assert_eq!(response.rows, vec![vec![Some(b"1".to_vec())], vec![Some(b"2".to_vec())]]);
+
+        // Other sessions keep using the pool
+        assert_eq!(
+            router.determine_route("SELECT * FROM affinity_items", &other_session).await,
+            QueryRoute::ReadOnly
+        );
+
+        // Once the replica has applied the session's last commit, its reads go there
+        let replica = rusqlite::Connection::open(&read_db).unwrap();
+        replica.execute_batch(
+            "CREATE TABLE affinity_items (id INTEGER PRIMARY KEY);
+             INSERT INTO affinity_items (id) VALUES (1);
+             CREATE TABLE __pgsqlite_commit_position (id INTEGER PRIMARY KEY, position INTEGER NOT NULL);
+             INSERT INTO __pgsqlite_commit_position VALUES (1, 1);"
+        ).unwrap();
+        let response = router.execute_query("SELECT id FROM affinity_items ORDER BY id", &writer_session).await.unwrap();
+        assert_eq!(response.rows.len(), 2, "position 1 is behind the session's");
+        replica.execute_batch("INSERT INTO affinity_items (id) VALUES (2); UPDATE __pgsqlite_commit_position SET position = 2").unwrap();
+        replica.execute_batch("INSERT INTO affinity_items (id) VALUES (3)").unwrap();
+        let response = router.execute_query("SELECT id FROM affinity_items ORDER BY id", &writer_session).await.unwrap();
+        assert_eq!(response.rows.len(), 3, "read from the replica");
+
+        std::fs::remove_file(&write_db).ok();
+        std::fs::remove_file(&read_db).ok();
+    }
+}
@@ -1,10 +1,44 @@
 use crate::session::pool::{SqlitePool, PoolStats};
 use crate::session::db_handler::DbResponse;
 use crate::config::Config;
+use crate::error::PgError;
+use crate::PgSqliteError;
+use once_cell::sync::Lazy;
+use regex::Regex;
 use std::sync::Arc;
 use std::time::Duration;
 use thiserror::Error;
 
+/// COPY table [(columns)] FROM ..., as opposed to COPY ... TO
+static COPY_FROM_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^COPY\s+[^\s(]+(?:\s*\([^)]*\))?\s+FROM\b"#).unwrap()
+});
+
+/// SELECT ... INTO new_table FROM ..., which creates the table
+static SELECT_INTO_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^SELECT\s.*?\sINTO\s+(?:(?:TEMP|TEMPORARY|UNLOGGED|TABLE)\s+)*[\w."]+\s+FROM\b"#).unwrap()
+});
+
+/// The data-modifying statement of a WITH query, in one of its CTEs or after them
+static DATA_MODIFYING_WITH_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)(?:\bAS\s*(?:NOT\s+)?(?:MATERIALIZED\s*)?\(|\))\s*(INSERT|UPDATE|DELETE|MERGE)\b").unwrap()
+});
+
+/// BEGIN, START TRANSACTION and SET TRANSACTION asking for a read-write transaction,
+/// and SET transaction_read_only turning it off
+static READ_WRITE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)^(?:(?:BEGIN|START\s+TRANSACTION|SET\s+(?:SESSION\s+CHARACTERISTICS\s+AS\s+)?TRANSACTION)\b.*\bREAD\s+WRITE\b|SET\s+(?:SESSION\s+|LOCAL\s+)?TRANSACTION_READ_ONLY\s*(?:=|\s+TO\s+)\s*'?(?:OFF|FALSE|NO|0)\b)").unwrap()
+});
+
+/// Words between CREATE, DROP or ALTER and the kind of object, left out of the command name
+const OBJECT_MODIFIERS: [&str; 12] = [
+    "OR", "REPLACE", "UNIQUE", "TEMP", "TEMPORARY", "UNLOGGED", "GLOBAL", "LOCAL",
+    "RECURSIVE", "TRUSTED", "PROCEDURAL", "CONSTRAINT",
+];
+
+/// Kinds of object named with two words, like MATERIALIZED VIEW
+const TWO_WORD_OBJECTS: [&str; 6] = ["MATERIALIZED", "FOREIGN", "EVENT", "ACCESS", "DEFAULT", "TEXT"];
+
 #[derive(Error, Debug)]
 pub enum ReadOnlyError {
     #[error("SQLite error: {0}")]
@@ -66,41 +100,7 @@ impl ReadOnlyDbHandler {
         }
 
         let conn = self.pool.acquire().await?;
-        
-        // Execute query using rusqlite
-        let mut stmt = conn.prepare(sql)?;
-        let column_names: Vec<String> = stmt.column_names()
-            .iter()
-            .map(|s| s.to_string())
-            .collect();
-
-        let rows = stmt.query_map([], |row| {
-            let mut values = Vec::new();
-            for i in 0..column_names.len() {
-                // Convert SQLite values to bytes for DbResponse compatibility
-                let value = match row.get::<_, rusqlite::types::Value>(i)? {
-                    rusqlite::types::Value::Null => None,
-                    rusqlite::types::Value::Integer(i) => Some(i.to_string().into_bytes()),
-                    rusqlite::types::Value::Real(f) => Some(f.to_string().into_bytes()),
-                    rusqlite::types::Value::Text(s) => Some(s.into_bytes()),
-                    rusqlite::types::Value::Blob(b) => Some(b),
-                };
-                values.push(value);
-            }
-            Ok(values)
-        })?;
-
-        let mut result_rows = Vec::new();
-        for row_result in rows {
-            result_rows.push(row_result?);
-        }
-
-        let rows_affected = result_rows.len();
-        Ok(DbResponse {
-            columns: column_names,
-            rows: result_rows,
-            rows_affected,
-        })
+        Ok(Self::collect_rows(&conn, sql, [])?)
     }
 
     /// Execute a prepared statement with parameters
@@ -115,7 +115,36 @@ impl ReadOnlyDbHandler {
         }
 
         let conn = self.pool.acquire().await?;
-        
+        Ok(Self::collect_rows(&conn, sql, params)?)
+    }
+
+    /// Execute a SELECT query if `ready` accepts the pooled connection's view of the
+    /// database, None otherwise. Both run in one read transaction, so the query sees
+    /// the data `ready` checked.
+    pub async fn query_if<P, F>(&self, sql: &str, params: P, ready: F) -> Result<Option<DbResponse>, ReadOnlyError>
+    where
+        P: rusqlite::Params,
+        F: FnOnce(&rusqlite::Connection) -> rusqlite::Result<bool>,
+    {
+        if !is_read_only_query(sql) {
+            return Err(ReadOnlyError::WriteNotAllowed);
+        }
+
+        let conn = self.pool.acquire().await?;
+        conn.execute_batch("BEGIN")?;
+        let result = ready(&conn).and_then(|ready| {
+            if ready { Self::collect_rows(&conn, sql, params).map(Some) } else { Ok(None) }
+        });
+        conn.execute_batch("COMMIT")?;
+        Ok(result?)
+    }
+
+    /// Run a query and convert its rows to bytes for DbResponse compatibility
+    fn collect_rows<P: rusqlite::Params>(
+        conn: &rusqlite::Connection,
+        sql: &str,
+        params: P,
+    ) -> rusqlite::Result<DbResponse> {
         let mut stmt = conn.prepare(sql)?;
         let column_names: Vec<String> = stmt.column_names()
             .iter()
@@ -161,6 +190,62 @@ impl ReadOnlyDbHandler {
     }
 }
 
+/// Refuse statements that write to the database when the server runs with --read-only,
+/// with the error a PostgreSQL standby gives. SQLite enforces read-only on its own, since
+/// the connection is opened read-only with query_only set, so a write this misses still
+/// fails, only with a less specific message.
+pub fn reject_writes(sql: &str) -> Result<(), PgSqliteError> {
+    if !crate::config::CONFIG.read_only {
+        return Ok(());
+    }
+    let sql = crate::query::strip_sql_comments(sql);
+    let message = if READ_WRITE_PATTERN.is_match(sql.trim()) {
+        "cannot set transaction read-write mode on a read-only server".to_string()
+    } else if let Some(command) = write_command(&sql) {
+        format!("cannot execute {command} in a read-only transaction")
+    } else {
+        return Ok(());
+    };
+    Err(PgSqliteError::Validation(PgError::Generic {
+        code: "25006".to_string(), // read_only_sql_transaction
+        message,
+    }))
+}
+
+/// The command of a statement that writes to the database, named the way PostgreSQL's
+/// "cannot execute ... in a read-only transaction" names it, e.g. `CREATE TABLE`.
+/// None for statements that only read.
+pub fn write_command(sql: &str) -> Option<String> {
+    let sql = sql.trim_start_matches(|c: char| c.is_whitespace() || c == '(');
+    let words: Vec<String> = sql
+        .split(|c: char| c.is_whitespace() || c == '(' || c == ';')
+        .filter(|word| !word.is_empty())
+        .take(8)
+        .map(|word| word.to_uppercase())
+        .collect();
+    let first = words.first()?;
+    match first.as_str() {
+        "INSERT" | "UPDATE" | "DELETE" | "MERGE" | "TRUNCATE" | "COMMENT" | "GRANT" | "REVOKE"
+        | "REINDEX" | "CLUSTER" | "VACUUM" | "ANALYZE" | "ANALYSE" => Some(first.clone()),
+        "REFRESH" => Some("REFRESH MATERIALIZED VIEW".to_string()),
+        "CREATE" | "DROP" | "ALTER" => {
+            let mut object = words[1..].iter().skip_while(|word| OBJECT_MODIFIERS.contains(&word.as_str()));
+            let mut command = first.clone();
+            if let Some(kind) = object.next() {
+                command = format!("{command} {kind}");
+                if TWO_WORD_OBJECTS.contains(&kind.as_str()) && let Some(kind) = object.next() {
+                    command = format!("{command} {kind}");
+                }
+            }
+            Some(command)
+        }
+        "COPY" => COPY_FROM_PATTERN.is_match(sql).then(|| "COPY FROM".to_string()),
+        "SELECT" => SELECT_INTO_PATTERN.is_match(sql).then(|| "SELECT INTO".to_string()),
+        "WITH" => DATA_MODIFYING_WITH_PATTERN.captures(sql).map(|caps| caps[1].to_uppercase()),
+        _ => None,
+    }
+}
+
 /// Check if a SQL query is read-only
 fn is_read_only_query(sql: &str) -> bool {
     let sql_upper = sql.trim().to_uppercase();
@@ -194,6 +279,43 @@ mod tests {
         assert!(!is_read_only_query("DROP TABLE test"));
     }
 
+    #[test]
+    fn test_write_command() {
+        assert_eq!(write_command("INSERT INTO users VALUES (1)").as_deref(), Some("INSERT"));
+        assert_eq!(write_command("  update users set name = 'x'").as_deref(), Some("UPDATE"));
+        assert_eq!(write_command("CREATE TABLE t (id int)").as_deref(), Some("CREATE TABLE"));
+        assert_eq!(write_command("create unique index t_idx on t (id)").as_deref(), Some("CREATE INDEX"));
+        assert_eq!(write_command("CREATE OR REPLACE VIEW v AS SELECT 1").as_deref(), Some("CREATE VIEW"));
+        assert_eq!(write_command("CREATE TEMP TABLE t (id int)").as_deref(), Some("CREATE TABLE"));
+        assert_eq!(write_command("DROP MATERIALIZED VIEW IF EXISTS mv").as_deref(), Some("DROP MATERIALIZED VIEW"));
+        assert_eq!(write_command("ALTER TABLE t ADD COLUMN c int").as_deref(), Some("ALTER TABLE"));
+        assert_eq!(write_command("COPY t (a, b) FROM STDIN").as_deref(), Some("COPY FROM"));
+        assert_eq!(write_command("SELECT * INTO archive FROM t").as_deref(), Some("SELECT INTO"));
+        assert_eq!(write_command("WITH gone AS (DELETE FROM t RETURNING *) SELECT count(*) FROM gone").as_deref(), Some("DELETE"));
+        assert_eq!(write_command("WITH src AS (SELECT 1 AS id) INSERT INTO t SELECT id FROM src").as_deref(), Some("INSERT"));
+
+        assert!(write_command("SELECT * FROM t").is_none());
+        assert!(write_command("(SELECT 1) UNION (SELECT 2)").is_none());
+        assert!(write_command("WITH x AS (SELECT 1) SELECT * FROM x").is_none());
+        assert!(write_command("COPY t TO STDOUT").is_none());
+        assert!(write_command("COPY (SELECT * FROM t WHERE a > 1) TO STDOUT").is_none());
+        assert!(write_command("BEGIN").is_none());
+        assert!(write_command("SHOW transaction_read_only").is_none());
+        assert!(write_command("EXPLAIN SELECT 1").is_none());
+    }
+
+    #[test]
+    fn test_read_write_pattern() {
+        assert!(READ_WRITE_PATTERN.is_match("BEGIN READ WRITE"));
+        assert!(READ_WRITE_PATTERN.is_match("START TRANSACTION ISOLATION LEVEL SERIALIZABLE, READ WRITE"));
+        assert!(READ_WRITE_PATTERN.is_match("SET SESSION CHARACTERISTICS AS TRANSACTION READ WRITE"));
+        assert!(READ_WRITE_PATTERN.is_match("SET transaction_read_only = off"));
+        assert!(READ_WRITE_PATTERN.is_match("set local transaction_read_only to 'false'"));
+        assert!(!READ_WRITE_PATTERN.is_match("BEGIN READ ONLY"));
+        assert!(!READ_WRITE_PATTERN.is_match("SET transaction_read_only = on"));
+        assert!(!READ_WRITE_PATTERN.is_match("SET default_transaction_read_only = off"));
+    }
+
     #[tokio::test]
     async fn test_read_only_handler_creation() {
         let config = Arc::new(Config::load());
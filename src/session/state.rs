@@ -4,11 +4,13 @@ use crate::protocol::TransactionStatus;
 use crate::cache::QueryCache;
 use crate::config::CONFIG;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU8, AtomicU64, AtomicUsize, Ordering};
 use once_cell::sync::Lazy;
-use crate::session::DbHandler;
+use crate::session::StorageBackend;
 use parking_lot::Mutex as ParkingMutex;
 use rusqlite::Connection;
+use crate::functions::sql_functions::TempFunction;
+use std::time::Duration;
 
 // Global query cache shared across all sessions
 pub static GLOBAL_QUERY_CACHE: Lazy<Arc<QueryCache>> = Lazy::new(|| {
@@ -20,6 +22,7 @@ static ACTIVE_SESSION_COUNT: AtomicUsize = AtomicUsize::new(0);
 
 pub struct SessionState {
     pub id: uuid::Uuid,
+    pub backend_pid: i32, // Reported in BackendKeyData and pg_backend_pid(), targeted by pg_terminate_backend()
     pub database: String,
     pub user: String,
     pub parameters: RwLock<HashMap<String, String>>,
@@ -28,10 +31,32 @@ pub struct SessionState {
     pub transaction_status: RwLock<TransactionStatus>,
     pub portal_manager: Arc<super::PortalManager>,
     pub python_param_mapping: RwLock<HashMap<String, Vec<String>>>, // Maps statement name to Python parameter names
-    pub db_handler: Mutex<Option<Arc<DbHandler>>>, // Reference to the database handler for session lifecycle management
+    pub db_handler: Mutex<Option<Arc<dyn StorageBackend>>>, // Storage engine that owns the session's connection, for lifecycle management
     pub cached_connection: ParkingMutex<Option<Arc<ParkingMutex<Connection>>>>, // Cached connection for fast access
+    trace: AtomicBool, // SET pgsqlite.trace, read on every statement so kept outside the parameter map
+    strict_compatibility: AtomicU8, // SET pgsqlite.strict_compatibility, STRICT_COMPATIBILITY_UNSET until set
+    statement_timeout: AtomicU64, // SET statement_timeout in milliseconds, 0 for none; read on every statement
+    statement_count: AtomicU64, // Statements started, numbering them in the structured log, see EventLog
+    last_commit_position: AtomicI64, // Commit position of the session's last write, 0 before it writes, for read-your-writes routing
+    schema_changed: AtomicBool, // Catalog-changing statement ran since the last transaction end, see CatalogCache
+    transaction_parameters: ParkingMutex<HashMap<String, TransactionParameter>>, // Parameters SET in the open transaction block
+    namespace_snapshot: ParkingMutex<Option<Arc<crate::query::schema_handler::NamespaceSnapshot>>>, // Schemas and relations for name resolution, see SchemaHandler
+    soft_delete_tables: ParkingMutex<Option<Arc<crate::query::soft_delete_handler::SoftDeleteTables>>>, // Tables whose statements SoftDeleteHandler rewrites
+    column_masks: ParkingMutex<Option<Arc<crate::query::mask_handler::ColumnMasks>>>, // Masked columns MaskHandler rewrites reads of
+    temp_functions: ParkingMutex<HashMap<String, TempFunction>>, // CREATE TEMP FUNCTION, registered on the session's connection only
 }
 
+/// A parameter the open transaction block has set, with the values it goes back to when the block ends
+struct TransactionParameter {
+    /// The value before the block, restored by ROLLBACK
+    before: Option<String>,
+    /// The value COMMIT keeps: that of the last plain SET in the block, `before` if there was only SET LOCAL
+    on_commit: Option<String>,
+}
+
+// The session follows --strict-compatibility until it sets its own mode
+const STRICT_COMPATIBILITY_UNSET: u8 = u8::MAX;
+
 pub struct PreparedStatement {
     pub query: String,
     pub translated_query: Option<String>, // Cached translation of the query
@@ -39,6 +64,16 @@ pub struct PreparedStatement {
     pub param_formats: Vec<i16>,
     pub field_descriptions: Vec<crate::protocol::FieldDescription>,
     pub translation_metadata: Option<crate::translator::TranslationMetadata>, // Type hints from query translation
+    pub result_shape: Option<ResultShape>, // Result columns as described, checked again on Execute after DDL
+}
+
+/// The result columns a prepared statement was described with
+#[derive(Clone, Debug)]
+pub struct ResultShape {
+    /// The CatalogCache generation the columns were read at
+    pub generation: u64,
+    /// Each column's name, with the PostgreSQL type of the table column it comes from
+    pub columns: Vec<(String, Option<String>)>,
 }
 
 #[derive(Clone)]
@@ -55,19 +90,24 @@ pub struct Portal {
 impl SessionState {
     pub fn new(database: String, user: String) -> Self {
         let mut parameters = HashMap::new();
-        parameters.insert("server_version".to_string(), "14.0 (SQLite wrapper)".to_string());
+        parameters.insert("server_version".to_string(), CONFIG.server_version.clone());
         parameters.insert("server_encoding".to_string(), "UTF8".to_string());
         parameters.insert("client_encoding".to_string(), "UTF8".to_string());
         parameters.insert("DateStyle".to_string(), "ISO, MDY".to_string());
         parameters.insert("TimeZone".to_string(), "UTC".to_string());
         parameters.insert("IntervalStyle".to_string(), "postgres".to_string());
         parameters.insert("integer_datetimes".to_string(), "on".to_string());
+        // Reported like a hot standby's, so drivers asked for a read-write server skip a --read-only one
+        let read_only = if CONFIG.read_only { "on" } else { "off" };
+        parameters.insert("default_transaction_read_only".to_string(), read_only.to_string());
+        parameters.insert("in_hot_standby".to_string(), read_only.to_string());
         
         // Increment active session count
         ACTIVE_SESSION_COUNT.fetch_add(1, Ordering::Relaxed);
         
         SessionState {
             id: uuid::Uuid::new_v4(),
+            backend_pid: super::backend_registry::allocate_backend_pid(),
             database,
             user,
             parameters: RwLock::new(parameters),
@@ -78,6 +118,17 @@ impl SessionState {
             python_param_mapping: RwLock::new(HashMap::new()),
             db_handler: Mutex::new(None), // Will be set after session is created
             cached_connection: ParkingMutex::new(None), // Initialize as None
+            trace: AtomicBool::new(false),
+            strict_compatibility: AtomicU8::new(STRICT_COMPATIBILITY_UNSET),
+            statement_timeout: AtomicU64::new(0),
+            statement_count: AtomicU64::new(0),
+            last_commit_position: AtomicI64::new(0),
+            schema_changed: AtomicBool::new(false),
+            transaction_parameters: ParkingMutex::new(HashMap::new()),
+            namespace_snapshot: ParkingMutex::new(None),
+            soft_delete_tables: ParkingMutex::new(None),
+            column_masks: ParkingMutex::new(None),
+            temp_functions: ParkingMutex::new(HashMap::new()),
         }
     }
 
@@ -104,7 +155,218 @@ impl SessionState {
     pub async fn get_transaction_status(&self) -> TransactionStatus {
         *self.transaction_status.read().await
     }
-    
+
+    /// Get the text format for bytea results, as selected by `SET bytea_output`
+    pub async fn bytea_output(&self) -> crate::types::ByteaFormat {
+        self.parameters.read().await.get("BYTEA_OUTPUT")
+            .and_then(|value| crate::types::ByteaFormat::from_setting(value))
+            .unwrap_or_default()
+    }
+
+    /// Whether statements on soft-delete tables are rewritten, per `SET pgsqlite.soft_delete`
+    pub async fn soft_delete_enabled(&self) -> bool {
+        self.parameters.read().await.get("PGSQLITE.SOFT_DELETE").is_none_or(|value| value != "off")
+    }
+
+    /// Whether DDL indexes uncovered foreign key columns, per `SET pgsqlite.auto_index_foreign_keys`
+    /// or `--auto-index-foreign-keys` when the session has not set it
+    pub async fn auto_index_foreign_keys(&self) -> bool {
+        match self.parameters.read().await.get("PGSQLITE.AUTO_INDEX_FOREIGN_KEYS") {
+            Some(value) => value == "on",
+            None => CONFIG.auto_index_foreign_keys,
+        }
+    }
+
+    /// Whether `SET pgsqlite.trace = on` is in effect for this session
+    pub fn trace_enabled(&self) -> bool {
+        self.trace.load(Ordering::Relaxed)
+    }
+
+    pub fn set_trace(&self, enabled: bool) {
+        self.trace.store(enabled, Ordering::Relaxed);
+    }
+
+    /// The next number of the session's statements, starting at 1
+    pub fn next_statement_id(&self) -> u64 {
+        self.statement_count.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// How long a statement may run before it's canceled, per `SET statement_timeout`
+    pub fn statement_timeout(&self) -> Option<Duration> {
+        match self.statement_timeout.load(Ordering::Relaxed) {
+            0 => None,
+            millis => Some(Duration::from_millis(millis)),
+        }
+    }
+
+    /// How this session reports features pgsqlite only approximates
+    pub fn strict_compatibility(&self) -> crate::query::StrictCompatibility {
+        use crate::query::StrictCompatibility;
+        match self.strict_compatibility.load(Ordering::Relaxed) {
+            0 => StrictCompatibility::Off,
+            1 => StrictCompatibility::Warn,
+            2 => StrictCompatibility::Error,
+            _ => StrictCompatibility::server_default(),
+        }
+    }
+
+    pub fn set_strict_compatibility(&self, mode: crate::query::StrictCompatibility) {
+        self.strict_compatibility.store(strict_compatibility_value(Some(mode)), Ordering::Relaxed);
+    }
+
+    /// Set a parameter. Inside a transaction block ROLLBACK undoes the change, and
+    /// for a `local` one (SET LOCAL) so does COMMIT.
+    pub async fn set_parameter(&self, name: &str, value: String, local: bool) {
+        let in_transaction = self.in_transaction().await;
+        let mut params = self.parameters.write().await;
+        if in_transaction {
+            let mut saved = self.transaction_parameters.lock();
+            let parameter = saved.entry(name.to_string()).or_insert_with(|| TransactionParameter {
+                before: params.get(name).cloned(),
+                on_commit: params.get(name).cloned(),
+            });
+            if !local {
+                parameter.on_commit = Some(value.clone());
+            }
+        }
+        self.apply_parameter(&mut params, name, Some(value));
+        drop(params);
+        if name == "SYNCHRONOUS_COMMIT" {
+            self.apply_synchronous_commit().await;
+        }
+    }
+
+    /// Put back the parameters the transaction block that just ended changed: the
+    /// SET LOCAL ones when it committed, all of them when it rolled back
+    pub async fn end_transaction(&self, committed: bool) {
+        let saved = std::mem::take(&mut *self.transaction_parameters.lock());
+        if saved.is_empty() {
+            return;
+        }
+        let synchronous_commit_changed = saved.contains_key("SYNCHRONOUS_COMMIT");
+        let mut params = self.parameters.write().await;
+        for (name, parameter) in saved {
+            let value = if committed { parameter.on_commit } else { parameter.before };
+            self.apply_parameter(&mut params, &name, value);
+        }
+        drop(params);
+        if synchronous_commit_changed {
+            self.apply_synchronous_commit().await;
+        }
+    }
+
+    /// Whether the session's commits are on disk before they are acknowledged, per
+    /// `SET synchronous_commit`; only `off` acknowledges them earlier
+    pub async fn synchronous_commit(&self) -> bool {
+        self.parameters.read().await.get("SYNCHRONOUS_COMMIT").is_none_or(|value| value != "off")
+    }
+
+    /// Hand the session's synchronous_commit to the storage engine, which applies it to
+    /// the commits of the session's connection
+    async fn apply_synchronous_commit(&self) {
+        let synchronous = self.synchronous_commit().await;
+        if let Some(db_handler) = self.get_db_handler().await
+            && let Err(e) = db_handler.set_synchronous_commit(&self.id, synchronous) {
+            tracing::warn!("Failed to apply synchronous_commit to session {}: {}", self.id, e);
+        }
+    }
+
+    /// Store a parameter's value, keeping the settings read on every statement in step
+    fn apply_parameter(&self, params: &mut HashMap<String, String>, name: &str, value: Option<String>) {
+        match name {
+            "PGSQLITE.TRACE" => self.set_trace(value.as_deref() == Some("on")),
+            "PGSQLITE.STRICT_COMPATIBILITY" => {
+                let mode = value.as_deref().and_then(crate::query::StrictCompatibility::from_setting);
+                self.strict_compatibility.store(strict_compatibility_value(mode), Ordering::Relaxed);
+            }
+            "STATEMENT_TIMEOUT" => {
+                let millis = value.as_deref().and_then(crate::query::SetHandler::parse_statement_timeout);
+                self.statement_timeout.store(millis.unwrap_or(0), Ordering::Relaxed);
+            }
+            _ => {}
+        }
+        match value {
+            Some(value) => params.insert(name.to_string(), value),
+            None => params.remove(name),
+        };
+    }
+
+    /// Record the commit position of a write the session made through the writer
+    pub fn record_write(&self, position: i64) {
+        self.last_commit_position.fetch_max(position, Ordering::Relaxed);
+    }
+
+    /// Commit position a reader must have applied to see the session's writes, 0 if
+    /// it hasn't written
+    pub fn last_commit_position(&self) -> i64 {
+        self.last_commit_position.load(Ordering::Relaxed)
+    }
+
+    /// Record that the session ran a statement that may change the catalog
+    pub fn record_schema_change(&self) {
+        self.schema_changed.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the session changed the catalog since this was last asked
+    pub fn take_schema_change(&self) -> bool {
+        self.schema_changed.swap(false, Ordering::Relaxed)
+    }
+
+    /// The schema names of the session's search_path
+    pub async fn search_path(&self) -> Vec<String> {
+        let params = self.parameters.read().await;
+        let value = params.get("SEARCH_PATH").map(String::as_str).unwrap_or(crate::query::schema_handler::DEFAULT_SEARCH_PATH);
+        crate::query::SchemaHandler::parse_search_path(value)
+    }
+
+    /// The namespace snapshot last loaded, if it was loaded at catalog `generation`
+    pub fn namespace_snapshot(&self, generation: u64) -> Option<Arc<crate::query::schema_handler::NamespaceSnapshot>> {
+        self.namespace_snapshot.lock().clone().filter(|snapshot| snapshot.generation() == generation)
+    }
+
+    /// Keep a freshly loaded namespace snapshot
+    pub fn set_namespace_snapshot(&self, snapshot: Arc<crate::query::schema_handler::NamespaceSnapshot>) {
+        *self.namespace_snapshot.lock() = Some(snapshot);
+    }
+
+    /// The soft-delete tables last loaded, if they were loaded at catalog `generation`
+    pub fn soft_delete_tables(&self, generation: u64) -> Option<Arc<crate::query::soft_delete_handler::SoftDeleteTables>> {
+        self.soft_delete_tables.lock().clone().filter(|tables| tables.generation() == generation)
+    }
+
+    /// Keep freshly loaded soft-delete tables
+    pub fn set_soft_delete_tables(&self, tables: Arc<crate::query::soft_delete_handler::SoftDeleteTables>) {
+        *self.soft_delete_tables.lock() = Some(tables);
+    }
+
+    /// The column masks last loaded, if they were loaded at catalog `generation`
+    pub fn column_masks(&self, generation: u64) -> Option<Arc<crate::query::mask_handler::ColumnMasks>> {
+        self.column_masks.lock().clone().filter(|masks| masks.generation() == generation)
+    }
+
+    /// Keep freshly loaded column masks
+    pub fn set_column_masks(&self, masks: Arc<crate::query::mask_handler::ColumnMasks>) {
+        *self.column_masks.lock() = Some(masks);
+    }
+
+    /// The temporary function the session created with this name
+    pub fn temp_function(&self, name: &str) -> Option<TempFunction> {
+        self.temp_functions.lock().get(name).cloned()
+    }
+
+    /// The session's temporary functions, by name
+    pub fn temp_functions(&self) -> Vec<(String, TempFunction)> {
+        self.temp_functions.lock().iter().map(|(name, function)| (name.clone(), function.clone())).collect()
+    }
+
+    pub fn set_temp_function(&self, name: String, function: TempFunction) {
+        self.temp_functions.lock().insert(name, function);
+    }
+
+    pub fn remove_temp_function(&self, name: &str) -> Option<TempFunction> {
+        self.temp_functions.lock().remove(name)
+    }
+
     /// Get the current number of active sessions
     pub async fn get_session_count(&self) -> usize {
         ACTIVE_SESSION_COUNT.load(Ordering::Relaxed)
@@ -112,12 +374,12 @@ impl SessionState {
     
     /// Set the database handler for this session
     /// This should be called after the session is created and a connection is established
-    pub async fn set_db_handler(&self, db_handler: Arc<DbHandler>) {
+    pub async fn set_db_handler(&self, db_handler: Arc<dyn StorageBackend>) {
         *self.db_handler.lock().await = Some(db_handler);
     }
     
     /// Get the database handler for this session
-    pub async fn get_db_handler(&self) -> Option<Arc<DbHandler>> {
+    pub async fn get_db_handler(&self) -> Option<Arc<dyn StorageBackend>> {
         self.db_handler.lock().await.clone()
     }
     
@@ -130,6 +392,14 @@ impl SessionState {
         Ok(())
     }
     
+    /// Initialize the connection of an admin session, which bypasses the connection limit
+    pub async fn initialize_reserved_connection(&self) -> Result<(), crate::PgSqliteError> {
+        if let Some(ref db_handler) = *self.db_handler.lock().await {
+            db_handler.create_reserved_session_connection(self.id).await?;
+        }
+        Ok(())
+    }
+    
     /// Clean up the session connection
     /// This should be called when the session is being terminated
     pub async fn cleanup_connection(&self) {
@@ -152,6 +422,16 @@ impl SessionState {
     }
 }
 
+fn strict_compatibility_value(mode: Option<crate::query::StrictCompatibility>) -> u8 {
+    use crate::query::StrictCompatibility;
+    match mode {
+        Some(StrictCompatibility::Off) => 0,
+        Some(StrictCompatibility::Warn) => 1,
+        Some(StrictCompatibility::Error) => 2,
+        None => STRICT_COMPATIBILITY_UNSET,
+    }
+}
+
 impl Drop for SessionState {
     fn drop(&mut self) {
         // Note: We can't do async operations in Drop, so cleanup is handled
@@ -0,0 +1,371 @@
+use crate::cache::{DatabaseCaches, SchemaCache, schema::TableSchema};
+use crate::session::connection_manager::run_statement;
+use crate::session::{DbHandler, DbResponse, SessionInterrupt};
+use crate::validator::StringConstraintValidator;
+use crate::PgSqliteError;
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use rusqlite::Connection;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Storage engine the protocol and translator layers run statements against
+///
+/// Every PostgreSQL session owns one engine connection, opened at startup and released
+/// when the client disconnects. Statements reach the engine already translated to
+/// SQLite's dialect. `DbHandler`, backed by rusqlite, is the default implementation.
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    /// Engine name used in logs
+    fn name(&self) -> &'static str;
+
+    /// Open the connection of a new session
+    async fn create_session_connection(&self, session_id: Uuid) -> Result<(), PgSqliteError>;
+
+    /// Open the connection of an admin session, which bypasses the connection limit
+    async fn create_reserved_session_connection(&self, session_id: Uuid) -> Result<(), PgSqliteError> {
+        self.create_session_connection(session_id).await
+    }
+
+    /// Release a session's connection
+    fn remove_session_connection(&self, session_id: &Uuid);
+
+    /// Whether the session's commits are synced before they are acknowledged, per its
+    /// `synchronous_commit`; engines that don't sync commits themselves ignore it
+    fn set_synchronous_commit(&self, _session_id: &Uuid, _synchronous: bool) -> Result<(), PgSqliteError> {
+        Ok(())
+    }
+
+    /// Whether commits are synced before they are acknowledged, so that a session's
+    /// `synchronous_commit = off` acknowledges them sooner
+    fn syncs_commits(&self) -> bool {
+        false
+    }
+
+    /// Run a statement that returns rows
+    async fn query_with_session(&self, query: &str, session_id: &Uuid) -> Result<DbResponse, PgSqliteError>;
+
+    /// Run a statement that returns a row count
+    async fn execute_with_session(&self, query: &str, session_id: &Uuid) -> Result<DbResponse, PgSqliteError>;
+
+    /// Run a statement with bound parameters, NULLs as None
+    async fn execute_with_params(
+        &self,
+        query: &str,
+        params: &[Option<Vec<u8>>],
+        session_id: &Uuid,
+    ) -> Result<DbResponse, PgSqliteError>;
+
+    async fn begin_with_session(&self, session_id: &Uuid) -> Result<(), PgSqliteError>;
+
+    async fn commit_with_session(&self, session_id: &Uuid) -> Result<(), PgSqliteError>;
+
+    /// Roll back the session's transaction; succeeds when none is open
+    async fn rollback_with_session(&self, session_id: &Uuid) -> Result<(), PgSqliteError>;
+
+    /// PostgreSQL type recorded for a column, as seen by the session
+    async fn get_schema_type_with_session(
+        &self,
+        session_id: &Uuid,
+        table_name: &str,
+        column_name: &str,
+    ) -> Result<Option<String>, PgSqliteError>;
+}
+
+/// Storage the protocol, executor and handler layers run against
+///
+/// Besides running statements, every session has a SQLite connection holding the schema
+/// and pgsqlite's metadata, which translation, the catalogs and the handlers read and
+/// write, and each database has the caches kept over it. `DbHandler` implements it, also
+/// when user statements go to a remote engine.
+#[async_trait]
+pub trait SessionStorage: StorageBackend {
+    /// Engine user statements go to instead of the SQLite connection, when one is attached
+    fn remote_engine(&self) -> Option<&'static str>;
+
+    /// The session's SQLite connection; see `with_session_connection`
+    fn session_connection(&self, session_id: &Uuid) -> Option<Arc<Mutex<Connection>>>;
+
+    /// Run `f` on the SQLite connection of every session
+    fn with_all_connections(&self, f: &dyn Fn(&Connection) -> Result<(), rusqlite::Error>) -> Result<(), PgSqliteError>;
+
+    /// Handle that interrupts the statement running on a session's connection
+    fn interrupt_handle(&self, session_id: &Uuid) -> Option<SessionInterrupt>;
+
+    /// Wait until the session's commits are on disk, before they are acknowledged
+    async fn wait_durable(&self, session_id: &Uuid) -> Result<(), PgSqliteError>;
+
+    /// Run a statement that returns rows outside any client session
+    async fn query(&self, query: &str) -> Result<DbResponse, rusqlite::Error>;
+
+    /// Run a statement that returns a row count outside any client session
+    async fn execute(&self, query: &str) -> Result<DbResponse, rusqlite::Error>;
+
+    /// `query_with_session` on the connection the session cached, when it has one
+    async fn query_with_session_cached(
+        &self,
+        query: &str,
+        session_id: &Uuid,
+        cached_conn: Option<&Arc<Mutex<Connection>>>,
+    ) -> Result<DbResponse, PgSqliteError>;
+
+    /// `execute_with_session` on the connection the session cached, when it has one
+    async fn execute_with_session_cached(
+        &self,
+        query: &str,
+        session_id: &Uuid,
+        cached_conn: Option<&Arc<Mutex<Connection>>>,
+    ) -> Result<DbResponse, PgSqliteError>;
+
+    /// Run a simple parameterized statement without translating it; None when it isn't one
+    async fn try_execute_fast_path_with_params(
+        &self,
+        query: &str,
+        params: &[rusqlite::types::Value],
+        session_id: &Uuid,
+    ) -> Result<Option<DbResponse>, PgSqliteError>;
+
+    /// Names and declared types of the columns a statement returns
+    async fn describe_with_session(&self, query: &str, session_id: &Uuid) -> Result<Vec<(String, Option<String>)>, PgSqliteError>;
+
+    /// The first row of a read-only statement; None for statements that write
+    async fn first_row_if_read_only(&self, query: &str, session_id: &Uuid) -> Result<Option<Vec<rusqlite::types::Value>>, PgSqliteError>;
+
+    /// Cached schema of a table
+    async fn get_table_schema(&self, table_name: &str) -> Result<TableSchema, rusqlite::Error>;
+
+    /// PostgreSQL type recorded for a column, outside any client session
+    async fn get_schema_type(&self, table_name: &str, column_name: &str) -> Result<Option<String>, rusqlite::Error>;
+
+    /// Connection outside any client session, for the catalogs that still ask for one
+    fn get_mut_connection(&self) -> Result<std::sync::MutexGuard<'_, Connection>, rusqlite::Error>;
+
+    fn get_schema_cache(&self) -> &Arc<SchemaCache>;
+
+    /// Row descriptions, results and statements of this database
+    fn get_caches(&self) -> &Arc<DatabaseCaches>;
+
+    fn get_string_validator(&self) -> &Arc<StringConstraintValidator>;
+}
+
+impl dyn SessionStorage + '_ {
+    /// Refuse a feature that reads or writes table data on the SQLite connection, which
+    /// only holds the schema cache when a remote engine is attached
+    pub fn require_local_data(&self, feature: &str) -> Result<(), PgSqliteError> {
+        match self.remote_engine() {
+            Some(engine) => Err(PgSqliteError::NotSupported(format!("{feature} on {engine} storage"))),
+            None => Ok(()),
+        }
+    }
+
+    /// Run `f` on the session's SQLite connection
+    pub async fn with_session_connection<F, R>(&self, session_id: &Uuid, f: F) -> Result<R, PgSqliteError>
+    where
+        F: FnOnce(&Connection) -> Result<R, rusqlite::Error>,
+    {
+        let conn = self.connection_for(session_id)?;
+        run_statement(|| f(&conn.lock())).map_err(PgSqliteError::Sqlite)
+    }
+
+    /// Run `f` on the session's SQLite connection, for transactions and savepoints
+    pub async fn with_session_connection_mut<F, R>(&self, session_id: &Uuid, f: F) -> Result<R, PgSqliteError>
+    where
+        F: FnOnce(&mut Connection) -> Result<R, rusqlite::Error>,
+    {
+        let conn = self.connection_for(session_id)?;
+        run_statement(|| f(&mut conn.lock())).map_err(PgSqliteError::Sqlite)
+    }
+
+    fn connection_for(&self, session_id: &Uuid) -> Result<Arc<Mutex<Connection>>, PgSqliteError> {
+        self.session_connection(session_id)
+            .ok_or_else(|| PgSqliteError::Protocol(format!("No connection found for session {session_id}")))
+    }
+}
+
+#[async_trait]
+impl StorageBackend for DbHandler {
+    fn name(&self) -> &'static str {
+        "sqlite"
+    }
+
+    async fn create_session_connection(&self, session_id: Uuid) -> Result<(), PgSqliteError> {
+        DbHandler::create_session_connection(self, session_id).await
+    }
+
+    async fn create_reserved_session_connection(&self, session_id: Uuid) -> Result<(), PgSqliteError> {
+        DbHandler::create_reserved_session_connection(self, session_id).await
+    }
+
+    fn remove_session_connection(&self, session_id: &Uuid) {
+        DbHandler::remove_session_connection(self, session_id)
+    }
+
+    fn set_synchronous_commit(&self, session_id: &Uuid, synchronous: bool) -> Result<(), PgSqliteError> {
+        DbHandler::set_synchronous_commit(self, session_id, synchronous)
+    }
+
+    fn syncs_commits(&self) -> bool {
+        DbHandler::syncs_commits(self)
+    }
+
+    async fn query_with_session(&self, query: &str, session_id: &Uuid) -> Result<DbResponse, PgSqliteError> {
+        DbHandler::query_with_session(self, query, session_id).await
+    }
+
+    async fn execute_with_session(&self, query: &str, session_id: &Uuid) -> Result<DbResponse, PgSqliteError> {
+        DbHandler::execute_with_session(self, query, session_id).await
+    }
+
+    async fn execute_with_params(
+        &self,
+        query: &str,
+        params: &[Option<Vec<u8>>],
+        session_id: &Uuid,
+    ) -> Result<DbResponse, PgSqliteError> {
+        DbHandler::execute_with_params(self, query, params, session_id).await
+    }
+
+    async fn begin_with_session(&self, session_id: &Uuid) -> Result<(), PgSqliteError> {
+        DbHandler::begin_with_session(self, session_id).await
+    }
+
+    async fn commit_with_session(&self, session_id: &Uuid) -> Result<(), PgSqliteError> {
+        DbHandler::commit_with_session(self, session_id).await
+    }
+
+    async fn rollback_with_session(&self, session_id: &Uuid) -> Result<(), PgSqliteError> {
+        DbHandler::rollback_with_session(self, session_id).await
+    }
+
+    async fn get_schema_type_with_session(
+        &self,
+        session_id: &Uuid,
+        table_name: &str,
+        column_name: &str,
+    ) -> Result<Option<String>, PgSqliteError> {
+        DbHandler::get_schema_type_with_session(self, session_id, table_name, column_name).await
+    }
+}
+
+#[async_trait]
+impl SessionStorage for DbHandler {
+    fn remote_engine(&self) -> Option<&'static str> {
+        DbHandler::remote_engine(self)
+    }
+
+    fn session_connection(&self, session_id: &Uuid) -> Option<Arc<Mutex<Connection>>> {
+        self.connection_manager().get_connection_arc(session_id)
+    }
+
+    fn with_all_connections(&self, f: &dyn Fn(&Connection) -> Result<(), rusqlite::Error>) -> Result<(), PgSqliteError> {
+        DbHandler::with_all_connections(self, f)
+    }
+
+    fn interrupt_handle(&self, session_id: &Uuid) -> Option<SessionInterrupt> {
+        DbHandler::interrupt_handle(self, session_id)
+    }
+
+    async fn wait_durable(&self, session_id: &Uuid) -> Result<(), PgSqliteError> {
+        DbHandler::wait_durable(self, session_id).await
+    }
+
+    async fn query(&self, query: &str) -> Result<DbResponse, rusqlite::Error> {
+        DbHandler::query(self, query).await
+    }
+
+    async fn execute(&self, query: &str) -> Result<DbResponse, rusqlite::Error> {
+        DbHandler::execute(self, query).await
+    }
+
+    async fn query_with_session_cached(
+        &self,
+        query: &str,
+        session_id: &Uuid,
+        cached_conn: Option<&Arc<Mutex<Connection>>>,
+    ) -> Result<DbResponse, PgSqliteError> {
+        DbHandler::query_with_session_cached(self, query, session_id, cached_conn).await
+    }
+
+    async fn execute_with_session_cached(
+        &self,
+        query: &str,
+        session_id: &Uuid,
+        cached_conn: Option<&Arc<Mutex<Connection>>>,
+    ) -> Result<DbResponse, PgSqliteError> {
+        DbHandler::execute_with_session_cached(self, query, session_id, cached_conn).await
+    }
+
+    async fn try_execute_fast_path_with_params(
+        &self,
+        query: &str,
+        params: &[rusqlite::types::Value],
+        session_id: &Uuid,
+    ) -> Result<Option<DbResponse>, PgSqliteError> {
+        DbHandler::try_execute_fast_path_with_params(self, query, params, session_id).await
+    }
+
+    async fn describe_with_session(&self, query: &str, session_id: &Uuid) -> Result<Vec<(String, Option<String>)>, PgSqliteError> {
+        DbHandler::describe_with_session(self, query, session_id).await
+    }
+
+    async fn first_row_if_read_only(&self, query: &str, session_id: &Uuid) -> Result<Option<Vec<rusqlite::types::Value>>, PgSqliteError> {
+        DbHandler::first_row_if_read_only(self, query, session_id).await
+    }
+
+    async fn get_table_schema(&self, table_name: &str) -> Result<TableSchema, rusqlite::Error> {
+        DbHandler::get_table_schema(self, table_name).await
+    }
+
+    async fn get_schema_type(&self, table_name: &str, column_name: &str) -> Result<Option<String>, rusqlite::Error> {
+        DbHandler::get_schema_type(self, table_name, column_name).await
+    }
+
+    fn get_mut_connection(&self) -> Result<std::sync::MutexGuard<'_, Connection>, rusqlite::Error> {
+        DbHandler::get_mut_connection(self)
+    }
+
+    fn get_schema_cache(&self) -> &Arc<SchemaCache> {
+        DbHandler::get_schema_cache(self)
+    }
+
+    fn get_caches(&self) -> &Arc<DatabaseCaches> {
+        DbHandler::get_caches(self)
+    }
+
+    fn get_string_validator(&self) -> &Arc<StringConstraintValidator> {
+        DbHandler::get_string_validator(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_db_handler_as_storage_backend() {
+        let storage: Arc<dyn StorageBackend> = Arc::new(DbHandler::new(":memory:").unwrap());
+        assert_eq!(storage.name(), "sqlite");
+
+        let session_id = Uuid::new_v4();
+        storage.create_session_connection(session_id).await.unwrap();
+        storage.execute_with_session("CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)", &session_id).await.unwrap();
+
+        storage.begin_with_session(&session_id).await.unwrap();
+        let inserted = storage.execute_with_params(
+            "INSERT INTO items (id, name) VALUES ($1, $2)",
+            &[Some(b"1".to_vec()), Some(b"widget".to_vec())],
+            &session_id,
+        ).await.unwrap();
+        assert_eq!(inserted.rows_affected, 1);
+        storage.commit_with_session(&session_id).await.unwrap();
+
+        let response = storage.query_with_session("SELECT name FROM items", &session_id).await.unwrap();
+        assert_eq!(response.rows, vec![vec![Some(b"widget".to_vec())]]);
+
+        // Rolling back without a transaction is not an error
+        storage.rollback_with_session(&session_id).await.unwrap();
+        storage.remove_session_connection(&session_id);
+        assert!(storage.query_with_session("SELECT 1", &session_id).await.is_err());
+    }
+}
@@ -0,0 +1,134 @@
+use once_cell::sync::Lazy;
+use std::fmt;
+use std::sync::atomic::{AtomicI64, Ordering};
+
+/// Transaction ids and WAL positions start from the server's start time in microseconds,
+/// so they keep increasing across restarts without being stored, as long as there are
+/// fewer than a million transactions a second
+fn startup_seed() -> i64 {
+    chrono::Utc::now().timestamp_micros().max(1)
+}
+
+/// Transaction ids for txid_current(), handed out in the order transactions ask for one
+static NEXT_TRANSACTION_ID: Lazy<AtomicI64> = Lazy::new(|| AtomicI64::new(startup_seed()));
+
+/// The position pg_current_wal_lsn() reports, advanced by every commit
+static WAL_POSITION: Lazy<AtomicI64> = Lazy::new(|| AtomicI64::new(startup_seed()));
+
+/// Allocate a transaction id
+pub fn allocate_transaction_id() -> i64 {
+    NEXT_TRANSACTION_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// The id the next transaction to ask for one will get
+pub fn next_transaction_id() -> i64 {
+    NEXT_TRANSACTION_ID.load(Ordering::Relaxed)
+}
+
+/// A SQLite transaction that wrote something committed
+pub fn record_commit() {
+    WAL_POSITION.fetch_add(1, Ordering::Relaxed);
+}
+
+/// The current WAL position as a pg_lsn, e.g. `6230/4F1A2C10`
+pub fn current_wal_lsn() -> String {
+    format_lsn(WAL_POSITION.load(Ordering::Relaxed))
+}
+
+pub fn format_lsn(position: i64) -> String {
+    format!("{:X}/{:X}", (position as u64) >> 32, position as u32)
+}
+
+/// Parse a pg_lsn written as two hexadecimal halves
+pub fn parse_lsn(text: &str) -> Option<i64> {
+    let (high, low) = text.trim().split_once('/')?;
+    let high = u32::from_str_radix(high, 16).ok()?;
+    let low = u32::from_str_radix(low, 16).ok()?;
+    Some((((high as u64) << 32) | low as u64) as i64)
+}
+
+/// The transactions a session can see, as txid_current_snapshot() reports it: every id
+/// below `xmin` has finished, none from `xmax` on has started, and `xip` lists those in
+/// between that were still running
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionSnapshot {
+    pub xmin: i64,
+    pub xmax: i64,
+    pub xip: Vec<i64>,
+}
+
+impl TransactionSnapshot {
+    /// A snapshot taken by the transaction `own`, if it has an id, when the other
+    /// transactions in `running` are in progress. Like in PostgreSQL the own transaction
+    /// holds xmin back but isn't listed in xip, since it sees its own changes.
+    pub fn new(mut running: Vec<i64>, own: Option<i64>) -> Self {
+        let xmax = next_transaction_id();
+        running.retain(|&xid| xid < xmax && Some(xid) != own);
+        running.sort_unstable();
+        running.dedup();
+        let xmin = running.first().copied().into_iter().chain(own).min().unwrap_or(xmax);
+        TransactionSnapshot { xmin, xmax, xip: running }
+    }
+
+    /// Parse the `xmin:xmax:xip,...` text form
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts = text.trim().splitn(3, ':');
+        let xmin = parts.next()?.parse().ok()?;
+        let xmax = parts.next()?.parse().ok()?;
+        let xip = match parts.next()? {
+            "" => Vec::new(),
+            list => list.split(',').map(|xid| xid.parse().ok()).collect::<Option<Vec<i64>>>()?,
+        };
+        (xmin <= xmax).then_some(TransactionSnapshot { xmin, xmax, xip })
+    }
+
+    /// Whether the transaction had committed when the snapshot was taken, like
+    /// txid_visible_in_snapshot()
+    pub fn is_visible(&self, xid: i64) -> bool {
+        xid < self.xmin || (xid < self.xmax && !self.xip.contains(&xid))
+    }
+}
+
+impl fmt::Display for TransactionSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let xip: Vec<String> = self.xip.iter().map(|xid| xid.to_string()).collect();
+        write!(f, "{}:{}:{}", self.xmin, self.xmax, xip.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot() {
+        let xmax = next_transaction_id();
+        let snapshot = TransactionSnapshot::new(vec![xmax - 3, xmax - 5, xmax + 10], None);
+        assert_eq!(snapshot.to_string(), format!("{}:{xmax}:{},{}", xmax - 5, xmax - 5, xmax - 3));
+        assert_eq!(TransactionSnapshot::parse(&snapshot.to_string()), Some(snapshot.clone()));
+        assert!(snapshot.is_visible(xmax - 6));
+        assert!(!snapshot.is_visible(xmax - 5));
+        assert!(snapshot.is_visible(xmax - 4));
+        assert!(!snapshot.is_visible(xmax));
+
+        // The own transaction holds xmin back and is visible
+        let own = TransactionSnapshot::new(vec![xmax - 3], Some(xmax - 4));
+        assert_eq!(own.to_string(), format!("{}:{xmax}:{}", xmax - 4, xmax - 3));
+        assert!(own.is_visible(xmax - 4));
+        assert_eq!(TransactionSnapshot::new(vec![xmax - 3], Some(xmax - 1)).xmin, xmax - 3);
+
+        assert_eq!(TransactionSnapshot::parse("10:20:"), Some(TransactionSnapshot { xmin: 10, xmax: 20, xip: Vec::new() }));
+        assert!(TransactionSnapshot::parse("20:10:").is_none());
+    }
+
+    #[test]
+    fn test_lsn() {
+        assert_eq!(format_lsn(0x1_0000_00A0), "1/A0");
+        assert_eq!(parse_lsn("1/A0"), Some(0x1_0000_00A0));
+        assert_eq!(parse_lsn("16/B374D848").map(format_lsn).as_deref(), Some("16/B374D848"));
+        assert!(parse_lsn("nonsense").is_none());
+        let before = parse_lsn(&current_wal_lsn()).unwrap();
+        record_commit();
+        assert!(parse_lsn(&current_wal_lsn()).unwrap() > before);
+    }
+}
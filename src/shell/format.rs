@@ -0,0 +1,176 @@
+use super::{Column, Feedback, Output};
+
+/// Type OIDs psql right-aligns: the integer, floating point, numeric, oid and money types
+const NUMERIC_TYPES: [u32; 8] = [20, 21, 23, 26, 700, 701, 790, 1700];
+
+/// Render a statement's output the way psql prints it
+pub(crate) fn render_output(output: &Output) -> String {
+    match output {
+        Output::Rows { columns, rows, tag } => {
+            let mut text = render_table(None, columns, rows, &[row_count(rows.len())]);
+            // psql only echoes the tag of statements that return rows as a side effect
+            if !tag.starts_with("SELECT") && !tag.starts_with("SHOW") {
+                text.push_str(tag);
+                text.push('\n');
+            }
+            text
+        }
+        Output::Complete(tag) => format!("{tag}\n"),
+        Output::Message(feedback) => render_feedback(feedback),
+    }
+}
+
+/// "(1 row)" or "(N rows)"
+pub(crate) fn row_count(rows: usize) -> String {
+    if rows == 1 {
+        "(1 row)".to_string()
+    } else {
+        format!("({rows} rows)")
+    }
+}
+
+/// `ERROR:  message` plus DETAIL and HINT lines
+pub(crate) fn render_feedback(feedback: &Feedback) -> String {
+    let mut text = format!("{}:  {}\n", feedback.severity, feedback.message);
+    if let Some(detail) = &feedback.detail {
+        text.push_str(&format!("DETAIL:  {detail}\n"));
+    }
+    if let Some(hint) = &feedback.hint {
+        text.push_str(&format!("HINT:  {hint}\n"));
+    }
+    text
+}
+
+/// psql's aligned format: centered headers, `+` marking continued multi-line values,
+/// numbers right-aligned, then the footer lines and a blank line
+pub(crate) fn render_table(
+    title: Option<&str>,
+    columns: &[Column],
+    rows: &[Vec<Option<String>>],
+    footer: &[String],
+) -> String {
+    let cells: Vec<Vec<Vec<&str>>> = rows.iter()
+        .map(|row| row.iter().map(|value| value.as_deref().unwrap_or("").split('\n').collect()).collect())
+        .collect();
+    let widths: Vec<usize> = columns.iter().enumerate()
+        .map(|(i, column)| {
+            cells.iter()
+                .filter_map(|row| row.get(i))
+                .flat_map(|lines| lines.iter().map(|line| width(line)))
+                .chain(std::iter::once(width(&column.name)))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let total: usize = widths.iter().map(|w| w + 2).sum::<usize>() + widths.len().saturating_sub(1);
+
+    let mut text = String::new();
+    if let Some(title) = title {
+        let padding = total.saturating_sub(width(title)) / 2;
+        text.push_str(&format!("{}{}\n", " ".repeat(padding), title));
+    }
+
+    let header: Vec<String> = columns.iter().zip(&widths)
+        .map(|(column, &w)| {
+            let left = (w - width(&column.name)) / 2;
+            let right = w - width(&column.name) - left;
+            format!(" {}{}{} ", " ".repeat(left), column.name, " ".repeat(right))
+        })
+        .collect();
+    text.push_str(&header.join("|"));
+    text.push('\n');
+    let rule: Vec<String> = widths.iter().map(|w| "-".repeat(w + 2)).collect();
+    text.push_str(&rule.join("+"));
+    text.push('\n');
+
+    for row in &cells {
+        let height = row.iter().map(Vec::len).max().unwrap_or(1);
+        for line in 0..height {
+            let mut out = String::new();
+            for (i, (lines, (column, &w))) in row.iter().zip(columns.iter().zip(&widths)).enumerate() {
+                let value = lines.get(line).copied().unwrap_or("");
+                let continued = line + 1 < lines.len();
+                let last = i + 1 == columns.len();
+                if i > 0 {
+                    out.push('|');
+                }
+                out.push(' ');
+                let pad = " ".repeat(w - width(value));
+                if NUMERIC_TYPES.contains(&column.type_oid) {
+                    out.push_str(&pad);
+                    out.push_str(value);
+                } else {
+                    out.push_str(value);
+                    if !last || continued {
+                        out.push_str(&pad);
+                    }
+                }
+                if continued {
+                    out.push('+');
+                } else if !last {
+                    out.push(' ');
+                }
+            }
+            text.push_str(&out);
+            text.push('\n');
+        }
+    }
+
+    for line in footer {
+        text.push_str(line);
+        text.push('\n');
+    }
+    text.push('\n');
+    text
+}
+
+fn width(text: &str) -> usize {
+    text.chars().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, type_oid: u32) -> Column {
+        Column { name: name.to_string(), type_oid }
+    }
+
+    #[test]
+    fn test_render_table() {
+        let columns = [column("id", 23), column("name", 25)];
+        let rows = vec![
+            vec![Some("1".to_string()), Some("alice".to_string())],
+            vec![Some("10".to_string()), None],
+        ];
+        let text = render_table(None, &columns, &rows, &[row_count(rows.len())]);
+        assert_eq!(text, " id | name  \n----+-------\n  1 | alice\n 10 | \n(2 rows)\n\n");
+    }
+
+    #[test]
+    fn test_render_title_and_multiline_values() {
+        let columns = [column("Name", 25), column("Definition", 25)];
+        let rows = vec![vec![Some("v".to_string()), Some("SELECT 1\nFROM t".to_string())]];
+        let text = render_table(Some("Views"), &columns, &rows, &[]);
+        assert_eq!(text, "       Views\n Name | Definition \n------+------------\n v    | SELECT 1  +\n      | FROM t\n\n");
+    }
+
+    #[test]
+    fn test_render_output() {
+        let inserted = Output::Rows {
+            columns: vec![column("id", 23)],
+            rows: vec![vec![Some("7".to_string())]],
+            tag: "INSERT 0 1".to_string(),
+        };
+        assert_eq!(render_output(&inserted), " id \n----\n  7\n(1 row)\n\nINSERT 0 1\n");
+        assert_eq!(render_output(&Output::Complete("CREATE TABLE".to_string())), "CREATE TABLE\n");
+
+        let error = Output::Message(Feedback {
+            severity: "ERROR".to_string(),
+            message: "relation \"t\" does not exist".to_string(),
+            detail: None,
+            hint: Some("Create it first".to_string()),
+        });
+        assert_eq!(render_output(&error), "ERROR:  relation \"t\" does not exist\nHINT:  Create it first\n");
+    }
+}
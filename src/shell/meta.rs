@@ -0,0 +1,535 @@
+use super::format::{render_feedback, render_table, row_count};
+use super::{Column, Output, Shell};
+
+const HELP: &str = r#"General
+  \q                     quit pgsqlite shell
+  \?                     show this help
+  \timing [on|off]       toggle timing of statements
+
+Informational
+  (options: S = show system objects, + = additional detail)
+  \d[S+]                 list tables, views and indexes
+  \d[S+]  NAME           describe table, view or index
+  \df[S]  [PATTERN]      list functions
+  \di[S]  [PATTERN]      list indexes
+  \dn     [PATTERN]      list schemas
+  \dt[S]  [PATTERN]      list tables
+  \du     [PATTERN]      list roles
+  \dv[S]  [PATTERN]      list views
+  \l                     list databases
+
+"#;
+
+// Catalog queries below follow the ones psql 15 sends for the same commands
+
+const LIST_RELATIONS: &str = r#"SELECT n.nspname as "Schema",
+  c.relname as "Name",
+  CASE c.relkind WHEN 'r' THEN 'table' WHEN 'v' THEN 'view' WHEN 'm' THEN 'materialized view' WHEN 'i' THEN 'index' WHEN 'S' THEN 'sequence' WHEN 't' THEN 'TOAST table' WHEN 'f' THEN 'foreign table' WHEN 'p' THEN 'partitioned table' WHEN 'I' THEN 'partitioned index' END as "Type",
+  pg_catalog.pg_get_userbyid(c.relowner) as "Owner""#;
+
+const LIST_FUNCTIONS: &str = r#"SELECT n.nspname as "Schema",
+  p.proname as "Name",
+  pg_catalog.pg_get_function_result(p.oid) as "Result data type",
+  pg_catalog.pg_get_function_arguments(p.oid) as "Argument data types",
+ CASE p.prokind
+  WHEN 'a' THEN 'agg'
+  WHEN 'w' THEN 'window'
+  WHEN 'p' THEN 'proc'
+  ELSE 'func'
+ END as "Type"
+FROM pg_catalog.pg_proc p
+     LEFT JOIN pg_catalog.pg_namespace n ON n.oid = p.pronamespace
+WHERE pg_catalog.pg_function_is_visible(p.oid)
+"#;
+
+const LIST_SCHEMAS: &str = r#"SELECT n.nspname AS "Name",
+  pg_catalog.pg_get_userbyid(n.nspowner) AS "Owner"
+FROM pg_catalog.pg_namespace n
+"#;
+
+const LIST_ROLES: &str = r#"SELECT r.rolname, r.rolsuper, r.rolinherit,
+  r.rolcreaterole, r.rolcreatedb, r.rolcanlogin,
+  r.rolconnlimit, r.rolvaliduntil,
+  ARRAY(SELECT b.rolname
+        FROM pg_catalog.pg_auth_members m
+        JOIN pg_catalog.pg_roles b ON (m.roleid = b.oid)
+        WHERE m.member = r.oid) as memberof
+, r.rolreplication
+, r.rolbypassrls
+FROM pg_catalog.pg_roles r
+"#;
+
+const LIST_DATABASES: &str = r#"SELECT d.datname as "Name",
+       pg_catalog.pg_get_userbyid(d.datdba) as "Owner",
+       pg_catalog.pg_encoding_to_char(d.encoding) as "Encoding",
+       d.datcollate as "Collate",
+       d.datctype as "Ctype",
+       NULL as "ICU Locale",
+       'libc' AS "Locale Provider",
+       pg_catalog.array_to_string(d.datacl, E'\n') AS "Access privileges"
+FROM pg_catalog.pg_database d
+ORDER BY 1;"#;
+
+const LOOKUP_RELATION: &str = r#"SELECT c.oid,
+  n.nspname,
+  c.relname
+FROM pg_catalog.pg_class c
+     LEFT JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+WHERE "#;
+
+const RELATION_INFO: &str = r#"SELECT c.relchecks, c.relkind, c.relhasindex, c.relhasrules, c.relhastriggers, c.relrowsecurity, c.relforcerowsecurity, false AS relhasoids, c.relispartition, '', c.reltablespace, CASE WHEN c.reloftype = 0 THEN '' ELSE c.reloftype::pg_catalog.regtype::pg_catalog.text END, c.relpersistence, c.relreplident, am.amname
+FROM pg_catalog.pg_class c
+ LEFT JOIN pg_catalog.pg_class tc ON (c.reltoastrelid = tc.oid)
+LEFT JOIN pg_catalog.pg_am am ON (c.relam = am.oid)
+WHERE c.oid = '{oid}';"#;
+
+const COLUMNS: &str = r#"SELECT a.attname,
+  pg_catalog.format_type(a.atttypid, a.atttypmod),
+  (SELECT pg_catalog.pg_get_expr(d.adbin, d.adrelid, true)
+   FROM pg_catalog.pg_attrdef d
+   WHERE d.adrelid = a.attrelid AND d.adnum = a.attnum AND a.atthasdef),
+  a.attnotnull,
+  (SELECT c.collname FROM pg_catalog.pg_collation c, pg_catalog.pg_type t
+   WHERE c.oid = a.attcollation AND t.oid = a.atttypid AND a.attcollation <> t.typcollation) AS attcollation,
+  a.attidentity,
+  a.attgenerated
+FROM pg_catalog.pg_attribute a
+WHERE a.attrelid = '{oid}' AND a.attnum > 0 AND NOT a.attisdropped
+ORDER BY a.attnum;"#;
+
+const INDEX_COLUMNS: &str = r#"SELECT a.attname,
+  pg_catalog.format_type(a.atttypid, a.atttypmod),
+  CASE WHEN a.attnum <= (SELECT i.indnkeyatts FROM pg_catalog.pg_index i WHERE i.indexrelid = '{oid}') THEN 'yes' ELSE 'no' END AS is_key,
+  pg_catalog.pg_get_indexdef(a.attrelid, a.attnum, TRUE) AS indexdef
+FROM pg_catalog.pg_attribute a
+WHERE a.attrelid = '{oid}' AND a.attnum > 0 AND NOT a.attisdropped
+ORDER BY a.attnum;"#;
+
+const INDEX_INFO: &str = r#"SELECT i.indisunique, i.indisprimary, a.amname, c2.relname
+FROM pg_catalog.pg_index i, pg_catalog.pg_class c, pg_catalog.pg_class c2, pg_catalog.pg_am a
+WHERE i.indexrelid = c.oid AND c.oid = '{oid}' AND c.relam = a.oid
+AND i.indrelid = c2.oid;"#;
+
+const TABLE_INDEXES: &str = r#"SELECT c2.relname, i.indisprimary, i.indisunique, i.indisclustered, i.indisvalid, pg_catalog.pg_get_indexdef(i.indexrelid, 0, true),
+  pg_catalog.pg_get_constraintdef(con.oid, true), contype, condeferrable, condeferred, i.indisreplident, c2.reltablespace
+FROM pg_catalog.pg_class c, pg_catalog.pg_class c2, pg_catalog.pg_index i
+  LEFT JOIN pg_catalog.pg_constraint con ON (conrelid = i.indrelid AND conindid = i.indexrelid AND contype IN ('p','u','x'))
+WHERE c.oid = '{oid}' AND c.oid = i.indrelid AND i.indexrelid = c2.oid
+ORDER BY i.indisprimary DESC, c2.relname;"#;
+
+const CHECK_CONSTRAINTS: &str = r#"SELECT r.conname, pg_catalog.pg_get_constraintdef(r.oid, true)
+FROM pg_catalog.pg_constraint r
+WHERE r.conrelid = '{oid}' AND r.contype = 'c'
+ORDER BY 1;"#;
+
+const FOREIGN_KEYS: &str = r#"SELECT true as sametable, conname,
+  pg_catalog.pg_get_constraintdef(r.oid, true) as condef,
+  conrelid::pg_catalog.regclass AS ontable
+FROM pg_catalog.pg_constraint r
+WHERE r.conrelid = '{oid}' AND r.contype = 'f'
+     AND conparentid = 0
+ORDER BY conname"#;
+
+const REFERENCED_BY: &str = r#"SELECT conname, conrelid::pg_catalog.regclass AS ontable,
+       pg_catalog.pg_get_constraintdef(oid, true) AS condef
+  FROM pg_catalog.pg_constraint c
+ WHERE confrelid IN (SELECT pg_catalog.pg_partition_ancestors('{oid}')
+                     UNION ALL VALUES ('{oid}'::pg_catalog.regclass))
+       AND contype = 'f' AND conparentid = 0
+ORDER BY conname;"#;
+
+const VIEW_DEFINITION: &str = "SELECT pg_catalog.pg_get_viewdef('{oid}'::pg_catalog.oid, true);";
+
+/// A backslash command of the shell
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum MetaCommand {
+    Quit,
+    Help,
+    /// `\timing`, optionally forced on or off
+    Timing(Option<bool>),
+    ListDatabases,
+    /// `\d` without a name, `\dt`, `\di` and `\dv`
+    ListRelations { kinds: &'static [char], pattern: Option<String>, system: bool },
+    Describe { pattern: String, verbose: bool },
+    ListFunctions { pattern: Option<String>, system: bool },
+    ListSchemas(Option<String>),
+    ListRoles(Option<String>),
+    Unknown(String),
+}
+
+impl MetaCommand {
+    pub(crate) fn parse(line: &str) -> Self {
+        let line = line.trim().trim_start_matches('\\');
+        let mut words = line.split_whitespace();
+        let name = words.next().unwrap_or_default();
+        let pattern = words.next().map(str::to_string);
+
+        let (base, verbose) = match name.strip_suffix('+') {
+            Some(base) => (base, true),
+            None => (name, false),
+        };
+        let (base, system) = match base.strip_suffix('S') {
+            Some(base) if base.starts_with('d') => (base, true),
+            _ => (base, false),
+        };
+
+        match base {
+            "q" | "quit" => MetaCommand::Quit,
+            "?" => MetaCommand::Help,
+            "timing" => MetaCommand::Timing(pattern.map(|value| value.eq_ignore_ascii_case("on"))),
+            "l" | "list" => MetaCommand::ListDatabases,
+            "d" => match pattern {
+                Some(pattern) => MetaCommand::Describe { pattern, verbose },
+                None => MetaCommand::ListRelations { kinds: &['r', 'p', 'v', 'm', 'S', 'f'], pattern: None, system },
+            },
+            "dt" => MetaCommand::ListRelations { kinds: &['r', 'p'], pattern, system },
+            "di" => MetaCommand::ListRelations { kinds: &['i', 'I'], pattern, system },
+            "dv" => MetaCommand::ListRelations { kinds: &['v'], pattern, system },
+            "df" => MetaCommand::ListFunctions { pattern, system },
+            "dn" => MetaCommand::ListSchemas(pattern),
+            "du" | "dg" => MetaCommand::ListRoles(pattern),
+            _ => MetaCommand::Unknown(name.to_string()),
+        }
+    }
+}
+
+/// A psql name pattern as anchored regexes for the schema and the name.
+///
+/// Unquoted letters fold to lower case, `*` and `?` are wildcards, and a `.` outside
+/// double quotes separates the schema from the name.
+pub(crate) fn pattern_regexes(pattern: &str) -> (Option<String>, String) {
+    let mut schema = None;
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                current.push('"');
+            }
+            '"' => quoted = !quoted,
+            '.' if !quoted => schema = Some(std::mem::take(&mut current)),
+            '*' if !quoted => current.push_str(".*"),
+            '?' if !quoted => current.push('.'),
+            '\'' => current.push_str("''"),
+            c if "\\^$.|()[]{}+*?".contains(c) => {
+                current.push('\\');
+                current.push(c);
+            }
+            c if quoted => current.push(c),
+            c => current.extend(c.to_lowercase()),
+        }
+    }
+    (schema.map(|schema| format!("^({schema})$")), format!("^({current})$"))
+}
+
+/// `AND column OPERATOR(pg_catalog.~) 'regex'` conditions for a pattern, plus the
+/// visibility check psql adds when no schema is named
+fn pattern_filter(pattern: &str, schema_column: &str, name_column: &str, visibility: Option<&str>) -> String {
+    let (schema, name) = pattern_regexes(pattern);
+    let mut filter = format!("  AND {name_column} OPERATOR(pg_catalog.~) '{name}' COLLATE pg_catalog.default\n");
+    match (schema, visibility) {
+        (Some(schema), _) => {
+            filter.push_str(&format!("  AND {schema_column} OPERATOR(pg_catalog.~) '{schema}' COLLATE pg_catalog.default\n"));
+        }
+        (None, Some(visibility)) => filter.push_str(&format!("  AND {visibility}\n")),
+        (None, None) => {}
+    }
+    filter
+}
+
+/// A query result with rows
+struct ResultSet {
+    columns: Vec<Column>,
+    rows: Vec<Vec<Option<String>>>,
+}
+
+impl ResultSet {
+    fn value(&self, row: usize, column: usize) -> &str {
+        self.rows.get(row).and_then(|row| row.get(column)).and_then(|value| value.as_deref()).unwrap_or("")
+    }
+}
+
+fn text_columns(names: &[&str]) -> Vec<Column> {
+    names.iter().map(|name| Column { name: name.to_string(), type_oid: 25 }).collect()
+}
+
+impl Shell {
+    /// Output of a backslash command, or None for `\q`
+    pub async fn meta(&mut self, line: &str) -> Option<String> {
+        let output = match MetaCommand::parse(line) {
+            MetaCommand::Quit => return None,
+            MetaCommand::Help => Ok(HELP.to_string()),
+            MetaCommand::Timing(setting) => {
+                self.timing = setting.unwrap_or(!self.timing);
+                Ok(format!("Timing is {}.\n", if self.timing { "on" } else { "off" }))
+            }
+            MetaCommand::ListDatabases => self.list("List of databases", LIST_DATABASES, None).await,
+            MetaCommand::ListRelations { kinds, pattern, system } => {
+                self.list_relations(kinds, pattern.as_deref(), system).await
+            }
+            MetaCommand::Describe { pattern, verbose } => self.describe(&pattern, verbose).await,
+            MetaCommand::ListFunctions { pattern, system } => {
+                let mut sql = LIST_FUNCTIONS.to_string();
+                if !system && pattern.is_none() {
+                    sql.push_str("      AND n.nspname <> 'pg_catalog'\n      AND n.nspname <> 'information_schema'\n");
+                }
+                if let Some(pattern) = &pattern {
+                    sql.push_str(&pattern_filter(pattern, "n.nspname", "p.proname", None));
+                }
+                sql.push_str("ORDER BY 1, 2, 4;");
+                self.list("List of functions", &sql, pattern.as_deref()).await
+            }
+            MetaCommand::ListSchemas(pattern) => {
+                let mut sql = LIST_SCHEMAS.to_string();
+                match &pattern {
+                    Some(pattern) => {
+                        let (_, name) = pattern_regexes(pattern);
+                        sql.push_str(&format!("WHERE n.nspname OPERATOR(pg_catalog.~) '{name}' COLLATE pg_catalog.default\n"));
+                    }
+                    None => sql.push_str("WHERE n.nspname !~ '^pg_' AND n.nspname <> 'information_schema'\n"),
+                }
+                sql.push_str("ORDER BY 1;");
+                self.list("List of schemas", &sql, pattern.as_deref()).await
+            }
+            MetaCommand::ListRoles(pattern) => self.list_roles(pattern.as_deref()).await,
+            MetaCommand::Unknown(name) => Ok(format!("invalid command \\{name}\nTry \\? for help.\n")),
+        };
+        Some(output.unwrap_or_else(|error| error))
+    }
+
+    /// Run a catalog query, or the rendered error it failed with
+    async fn query(&mut self, sql: &str) -> Result<ResultSet, String> {
+        let mut result = ResultSet { columns: Vec::new(), rows: Vec::new() };
+        for output in self.execute(sql).await {
+            match output {
+                Output::Rows { columns, rows, .. } => result = ResultSet { columns, rows },
+                Output::Message(feedback) if feedback.severity == "ERROR" => return Err(render_feedback(&feedback)),
+                _ => {}
+            }
+        }
+        Ok(result)
+    }
+
+    async fn list(&mut self, title: &str, sql: &str, pattern: Option<&str>) -> Result<String, String> {
+        let result = self.query(sql).await?;
+        if result.rows.is_empty() && pattern.is_some() {
+            let noun = title.trim_start_matches("List of ").trim_end_matches('s');
+            return Ok(format!("Did not find any {noun} named \"{}\".\n", pattern.unwrap_or_default()));
+        }
+        Ok(render_table(Some(title), &result.columns, &result.rows, &[row_count(result.rows.len())]))
+    }
+
+    async fn list_relations(&mut self, kinds: &[char], pattern: Option<&str>, system: bool) -> Result<String, String> {
+        let indexes = kinds.contains(&'i');
+        let mut sql = LIST_RELATIONS.to_string();
+        if indexes {
+            sql.push_str(",\n  c2.relname as \"Table\"");
+        }
+        sql.push_str("\nFROM pg_catalog.pg_class c\n     LEFT JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace\n");
+        sql.push_str("     LEFT JOIN pg_catalog.pg_am am ON am.oid = c.relam\n");
+        if indexes {
+            sql.push_str("     LEFT JOIN pg_catalog.pg_index i ON i.indexrelid = c.oid\n");
+            sql.push_str("     LEFT JOIN pg_catalog.pg_class c2 ON i.indrelid = c2.oid\n");
+        }
+        let kinds: Vec<String> = kinds.iter().map(|kind| format!("'{kind}'")).collect();
+        sql.push_str(&format!("WHERE c.relkind IN ({},'')\n", kinds.join(",")));
+        if !system && pattern.is_none() {
+            sql.push_str("      AND n.nspname <> 'pg_catalog'\n      AND n.nspname !~ '^pg_toast'\n      AND n.nspname <> 'information_schema'\n");
+        }
+        match pattern {
+            Some(pattern) => sql.push_str(&pattern_filter(pattern, "n.nspname", "c.relname", Some("pg_catalog.pg_table_is_visible(c.oid)"))),
+            None => sql.push_str("  AND pg_catalog.pg_table_is_visible(c.oid)\n"),
+        }
+        sql.push_str("ORDER BY 1,2;");
+
+        let result = self.query(&sql).await?;
+        if result.rows.is_empty() {
+            return Ok(match pattern {
+                Some(pattern) => format!("Did not find any relation named \"{pattern}\".\n"),
+                None => "Did not find any relations.\n".to_string(),
+            });
+        }
+        Ok(render_table(Some("List of relations"), &result.columns, &result.rows, &[row_count(result.rows.len())]))
+    }
+
+    async fn list_roles(&mut self, pattern: Option<&str>) -> Result<String, String> {
+        let mut sql = LIST_ROLES.to_string();
+        match pattern {
+            Some(pattern) => {
+                let (_, name) = pattern_regexes(pattern);
+                sql.push_str(&format!("WHERE r.rolname OPERATOR(pg_catalog.~) '{name}' COLLATE pg_catalog.default\n"));
+            }
+            None => sql.push_str("WHERE r.rolname !~ '^pg_'\n"),
+        }
+        sql.push_str("ORDER BY 1;");
+        let result = self.query(&sql).await?;
+
+        // psql turns the role flags into a list of attributes
+        let rows: Vec<Vec<Option<String>>> = (0..result.rows.len())
+            .map(|row| {
+                let flag = |column: usize| result.value(row, column) == "t";
+                let attributes = [
+                    (flag(1), "Superuser"),
+                    (!flag(2), "No inheritance"),
+                    (flag(3), "Create role"),
+                    (flag(4), "Create DB"),
+                    (!flag(5), "Cannot login"),
+                    (flag(9), "Replication"),
+                    (flag(10), "Bypass RLS"),
+                ];
+                let attributes: Vec<&str> = attributes.iter().filter(|(set, _)| *set).map(|(_, name)| *name).collect();
+                vec![
+                    Some(result.value(row, 0).to_string()),
+                    Some(attributes.join(", ")),
+                    Some(result.value(row, 8).to_string()),
+                ]
+            })
+            .collect();
+        Ok(render_table(Some("List of roles"), &text_columns(&["Role name", "Attributes", "Member of"]), &rows, &[]))
+    }
+
+    /// `\d NAME`: columns and footers of every matching table, view and index
+    async fn describe(&mut self, pattern: &str, verbose: bool) -> Result<String, String> {
+        let mut sql = LOOKUP_RELATION.to_string();
+        sql.push_str(pattern_filter(pattern, "n.nspname", "c.relname", Some("pg_catalog.pg_table_is_visible(c.oid)")).trim_start().trim_start_matches("AND "));
+        sql.push_str("ORDER BY 2, 3;");
+        let relations = self.query(&sql).await?;
+        if relations.rows.is_empty() {
+            return Ok(format!("Did not find any relation named \"{pattern}\".\n"));
+        }
+
+        let mut text = String::new();
+        for row in 0..relations.rows.len() {
+            let oid = relations.value(row, 0).to_string();
+            let qualified = format!("{}.{}", relations.value(row, 1), relations.value(row, 2));
+            let info = self.query(&RELATION_INFO.replace("{oid}", &oid)).await?;
+            text.push_str(&match info.value(0, 1) {
+                "i" => self.describe_index(&oid, &qualified).await?,
+                "v" => self.describe_table(&oid, &format!("View \"{qualified}\""), verbose, false).await?,
+                _ => self.describe_table(&oid, &format!("Table \"{qualified}\""), verbose, true).await?,
+            });
+        }
+        Ok(text)
+    }
+
+    async fn describe_table(&mut self, oid: &str, title: &str, verbose: bool, table: bool) -> Result<String, String> {
+        let query = |template: &str| template.replace("{oid}", oid);
+        let columns = self.query(&query(COLUMNS)).await?;
+        let rows: Vec<Vec<Option<String>>> = (0..columns.rows.len())
+            .map(|row| {
+                let default = match columns.value(row, 5) {
+                    "a" => "generated always as identity",
+                    "d" => "generated by default as identity",
+                    _ => columns.value(row, 2),
+                };
+                vec![
+                    Some(columns.value(row, 0).to_string()),
+                    Some(columns.value(row, 1).to_string()),
+                    Some(columns.value(row, 4).to_string()),
+                    Some(if columns.value(row, 3) == "t" { "not null" } else { "" }.to_string()),
+                    Some(default.to_string()),
+                ]
+            })
+            .collect();
+
+        let mut footer = Vec::new();
+        if table {
+            let indexes = self.query(&query(TABLE_INDEXES)).await?;
+            section(&mut footer, "Indexes:", (0..indexes.rows.len()).map(|row| {
+                let kind = match (indexes.value(row, 7), indexes.value(row, 2)) {
+                    ("p", _) => " PRIMARY KEY,",
+                    ("u", _) => " UNIQUE CONSTRAINT,",
+                    (_, "t") => " UNIQUE,",
+                    _ => "",
+                };
+                let definition = indexes.value(row, 5);
+                let method = definition.split_once(" USING ").map_or(definition, |(_, method)| method);
+                format!("\"{}\"{} {}", indexes.value(row, 0), kind, method)
+            }));
+
+            let checks = self.query(&query(CHECK_CONSTRAINTS)).await?;
+            section(&mut footer, "Check constraints:", (0..checks.rows.len())
+                .map(|row| format!("\"{}\" {}", checks.value(row, 0), checks.value(row, 1))));
+
+            let foreign_keys = self.query(&query(FOREIGN_KEYS)).await?;
+            section(&mut footer, "Foreign-key constraints:", (0..foreign_keys.rows.len())
+                .map(|row| format!("\"{}\" {}", foreign_keys.value(row, 1), foreign_keys.value(row, 2))));
+
+            let referenced_by = self.query(&query(REFERENCED_BY)).await?;
+            section(&mut footer, "Referenced by:", (0..referenced_by.rows.len()).map(|row| {
+                format!(
+                    "TABLE \"{}\" CONSTRAINT \"{}\" {}",
+                    referenced_by.value(row, 1),
+                    referenced_by.value(row, 0),
+                    referenced_by.value(row, 2)
+                )
+            }));
+        } else if verbose {
+            let definition = self.query(&query(VIEW_DEFINITION)).await?;
+            footer.push("View definition:".to_string());
+            footer.extend(definition.value(0, 0).lines().map(str::to_string));
+        }
+
+        let headers = text_columns(&["Column", "Type", "Collation", "Nullable", "Default"]);
+        Ok(render_table(Some(title), &headers, &rows, &footer))
+    }
+
+    async fn describe_index(&mut self, oid: &str, qualified: &str) -> Result<String, String> {
+        let query = |template: &str| template.replace("{oid}", oid);
+        let columns = self.query(&query(INDEX_COLUMNS)).await?;
+        let info = self.query(&query(INDEX_INFO)).await?;
+
+        let mut properties = String::new();
+        if info.value(0, 1) == "t" {
+            properties.push_str("primary key, ");
+        } else if info.value(0, 0) == "t" {
+            properties.push_str("unique, ");
+        }
+        let table = info.value(0, 3);
+        let schema = if table.starts_with("pg_") { "pg_catalog" } else { "public" };
+        let footer = format!("{}{}, for table \"{schema}.{table}\"", properties, info.value(0, 2));
+
+        let headers = text_columns(&["Column", "Type", "Key?", "Definition"]);
+        Ok(render_table(Some(&format!("Index \"{qualified}\"")), &headers, &columns.rows, &[footer]))
+    }
+}
+
+/// A footer heading followed by its indented entries, when there are any
+fn section(footer: &mut Vec<String>, heading: &str, entries: impl Iterator<Item = String>) {
+    let entries: Vec<String> = entries.map(|entry| format!("    {entry}")).collect();
+    if !entries.is_empty() {
+        footer.push(heading.to_string());
+        footer.extend(entries);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_meta_commands() {
+        assert_eq!(MetaCommand::parse("\\q"), MetaCommand::Quit);
+        assert_eq!(MetaCommand::parse("\\timing on"), MetaCommand::Timing(Some(true)));
+        assert_eq!(
+            MetaCommand::parse("\\dtS+ ord*"),
+            MetaCommand::ListRelations { kinds: &['r', 'p'], pattern: Some("ord*".to_string()), system: true }
+        );
+        assert_eq!(
+            MetaCommand::parse("\\d+ orders"),
+            MetaCommand::Describe { pattern: "orders".to_string(), verbose: true }
+        );
+        assert!(matches!(MetaCommand::parse("\\d"), MetaCommand::ListRelations { pattern: None, .. }));
+        assert_eq!(MetaCommand::parse("\\dx"), MetaCommand::Unknown("dx".to_string()));
+    }
+
+    #[test]
+    fn test_pattern_regexes() {
+        assert_eq!(pattern_regexes("Orders"), (None, "^(orders)$".to_string()));
+        assert_eq!(pattern_regexes("public.ord*"), (Some("^(public)$".to_string()), "^(ord.*)$".to_string()));
+        assert_eq!(pattern_regexes("\"Mixed.Case\""), (None, "^(Mixed\\.Case)$".to_string()));
+        assert_eq!(pattern_regexes("it's?"), (None, "^(it''s.)$".to_string()));
+    }
+}
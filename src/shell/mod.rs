@@ -0,0 +1,429 @@
+//! `pgsqlite shell`: an interactive prompt on the embedded engine.
+//!
+//! Statements run through the same executor client connections use, so the shell
+//! speaks the PostgreSQL dialect and sees the same catalog. The backend messages are
+//! written to an in-memory transport, decoded, and printed the way psql prints them.
+
+mod format;
+mod meta;
+
+use std::io::{self, IsTerminal};
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use bytes::{Buf, BytesMut};
+use futures::SinkExt;
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_util::codec::Framed;
+
+use crate::config::Config;
+use crate::protocol::{PostgresCodec, TransactionStatus};
+use crate::query::QueryExecutor;
+use crate::session::{DbHandler, SessionState, SessionStorage};
+use crate::PgSqliteError;
+
+/// History file in the home directory, shared by every database
+const HISTORY_FILE: &str = ".pgsqlite_history";
+
+/// A result column: its name and PostgreSQL type OID
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub type_oid: u32,
+}
+
+/// An error or notice as the backend reported it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Feedback {
+    pub severity: String,
+    pub message: String,
+    pub detail: Option<String>,
+    pub hint: Option<String>,
+}
+
+/// What one statement sent back
+#[derive(Debug, Clone, PartialEq)]
+pub enum Output {
+    /// A result set, with the command tag that ended it
+    Rows {
+        columns: Vec<Column>,
+        rows: Vec<Vec<Option<String>>>,
+        tag: String,
+    },
+    /// The command tag of a statement without a result set
+    Complete(String),
+    /// An error or notice
+    Message(Feedback),
+}
+
+/// In-memory transport: there is nothing to read, and everything written is kept
+#[derive(Default)]
+struct Capture {
+    written: BytesMut,
+}
+
+impl AsyncRead for Capture {
+    fn poll_read(self: Pin<&mut Self>, _cx: &mut Context<'_>, _buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for Capture {
+    fn poll_write(mut self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.written.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// A session on the embedded engine that hands back decoded results
+pub struct Shell {
+    db: Arc<dyn SessionStorage>,
+    session: Arc<SessionState>,
+    framed: Framed<Capture, PostgresCodec>,
+    timing: bool,
+}
+
+impl Shell {
+    /// Open a session on `db`, as a client connecting to `database` would get
+    pub async fn open(db: Arc<dyn SessionStorage>, database: &str) -> Result<Self, PgSqliteError> {
+        let session = Arc::new(SessionState::new(database.to_string(), "postgres".to_string()));
+        session.set_db_handler(db.clone()).await;
+        session.initialize_connection().await?;
+        Ok(Self {
+            db,
+            session,
+            framed: Framed::new(Capture::default(), PostgresCodec::new()),
+            timing: false,
+        })
+    }
+
+    /// Run one or more statements and collect what each sent back
+    pub async fn execute(&mut self, sql: &str) -> Vec<Output> {
+        let result = QueryExecutor::execute_query(&mut self.framed, &self.db, &self.session, sql, None).await;
+        let _ = self.framed.flush().await;
+        let mut outputs = decode(std::mem::take(&mut self.framed.get_mut().written));
+        if let Err(e) = result {
+            // Same as for a client: the open transaction can only be rolled back now
+            if self.session.in_transaction().await {
+                self.session.set_transaction_status(TransactionStatus::InFailedTransaction).await;
+            }
+            let err = e.to_error_response("42000", "Query execution failed");
+            outputs.push(Output::Message(Feedback {
+                severity: err.severity,
+                message: err.message,
+                detail: err.detail,
+                hint: err.hint,
+            }));
+        }
+        outputs
+    }
+
+    /// Run `sql` and print its results, with the elapsed time under \timing
+    pub async fn run_statements(&mut self, sql: &str) {
+        let started = Instant::now();
+        let outputs = self.execute(sql).await;
+        let elapsed = started.elapsed();
+        for output in &outputs {
+            print!("{}", format::render_output(output));
+        }
+        if self.timing {
+            println!("Time: {:.3} ms", elapsed.as_secs_f64() * 1000.0);
+        }
+    }
+
+    /// The marker psql adds to the prompt inside a transaction
+    async fn transaction_marker(&self) -> &'static str {
+        match *self.session.transaction_status.read().await {
+            TransactionStatus::Idle => "",
+            TransactionStatus::InTransaction => "*",
+            TransactionStatus::InFailedTransaction => "!",
+        }
+    }
+}
+
+/// Run the shell on `database` until `\q` or end of input
+pub fn run(database: &str, config: &Config) -> anyhow::Result<()> {
+    let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build()?;
+    let db = DbHandler::new_with_config(database, config)
+        .map_err(|e| anyhow::anyhow!("Failed to open database {}: {}", database, e))?;
+    let name = Path::new(database).file_stem()
+        .and_then(|stem| stem.to_str())
+        .filter(|stem| !stem.starts_with(':'))
+        .unwrap_or("pgsqlite")
+        .to_string();
+    let mut shell = runtime.block_on(Shell::open(Arc::new(db), &name))
+        .map_err(|e| anyhow::anyhow!("Failed to open session: {}", e))?;
+
+    let mut editor = DefaultEditor::new()?;
+    let history = std::env::var_os("HOME").map(|home| Path::new(&home).join(HISTORY_FILE));
+    if let Some(history) = &history {
+        let _ = editor.load_history(history);
+    }
+    if io::stdin().is_terminal() {
+        println!("pgsqlite v{} shell on {}", env!("CARGO_PKG_VERSION"), database);
+        println!("Type \"\\?\" for help.\n");
+    }
+
+    let mut buffer = String::new();
+    loop {
+        // psql's default prompts for a superuser: `db=# `, `db-# ` while continuing a statement
+        let marker = runtime.block_on(shell.transaction_marker());
+        let continuation = if buffer.is_empty() { '=' } else { '-' };
+        let prompt = format!("{name}{continuation}{marker}# ");
+        let line = match editor.readline(&prompt) {
+            Ok(line) => line,
+            // Ctrl-C discards the statement being typed, like psql
+            Err(ReadlineError::Interrupted) => {
+                buffer.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+
+        if buffer.is_empty() && line.trim_start().starts_with('\\') {
+            let _ = editor.add_history_entry(line.as_str());
+            match runtime.block_on(shell.meta(&line)) {
+                Some(output) => print!("{output}"),
+                None => break,
+            }
+            continue;
+        }
+
+        if !buffer.is_empty() {
+            buffer.push('\n');
+        }
+        buffer.push_str(&line);
+        if buffer.trim().is_empty() {
+            buffer.clear();
+        } else if statement_complete(&buffer) {
+            let _ = editor.add_history_entry(buffer.as_str());
+            runtime.block_on(shell.run_statements(&buffer));
+            buffer.clear();
+        }
+    }
+
+    if let Some(history) = &history {
+        let _ = editor.save_history(history);
+    }
+    Ok(())
+}
+
+/// Whether the input ends with a `;` outside quotes, comments and dollar-quoted bodies
+fn statement_complete(sql: &str) -> bool {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut ends_with_semicolon = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\'' | '"' => {
+                i += 1;
+                while i < chars.len() && chars[i] != c {
+                    i += 1;
+                }
+                if i == chars.len() {
+                    return false;
+                }
+            }
+            '-' if chars.get(i + 1) == Some(&'-') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                let Some(end) = find(&chars[i + 2..], &['*', '/']) else {
+                    return false;
+                };
+                i += end + 4;
+                continue;
+            }
+            '$' => {
+                let tag_len = chars[i + 1..].iter().position(|&ch| !(ch.is_alphanumeric() || ch == '_'));
+                if let Some(tag_len) = tag_len.filter(|&len| chars[i + 1 + len] == '$') {
+                    let tag = &chars[i..i + tag_len + 2];
+                    let Some(end) = find(&chars[i + tag.len()..], tag) else {
+                        return false;
+                    };
+                    i += tag.len() + end + tag.len();
+                    ends_with_semicolon = false;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        if !c.is_whitespace() {
+            ends_with_semicolon = c == ';';
+        }
+        i += 1;
+    }
+    ends_with_semicolon
+}
+
+fn find(haystack: &[char], needle: &[char]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// Decode the backend messages of a simple-query exchange
+fn decode(mut buf: BytesMut) -> Vec<Output> {
+    let mut outputs = Vec::new();
+    // Columns of the result set being read, until its CommandComplete
+    let mut columns: Option<Vec<Column>> = None;
+    let mut rows = Vec::new();
+    while buf.len() >= 5 {
+        let len = i32::from_be_bytes([buf[1], buf[2], buf[3], buf[4]]) as usize;
+        if buf.len() < len + 1 {
+            break;
+        }
+        let kind = buf[0];
+        buf.advance(5);
+        let mut body = buf.split_to(len - 4);
+        match kind {
+            b'T' => {
+                let count = body.get_i16();
+                let described = (0..count)
+                    .map(|_| {
+                        let name = cstring(&mut body);
+                        body.advance(6); // table OID, column number
+                        let type_oid = body.get_u32();
+                        body.advance(8); // type size, modifier, format
+                        Column { name, type_oid }
+                    })
+                    .collect();
+                columns = Some(described);
+                rows.clear();
+            }
+            b'D' => {
+                let count = body.get_i16();
+                let row = (0..count)
+                    .map(|_| {
+                        let len = body.get_i32();
+                        (len >= 0).then(|| String::from_utf8_lossy(&body.split_to(len as usize)).into_owned())
+                    })
+                    .collect();
+                if columns.is_some() {
+                    rows.push(row);
+                }
+            }
+            b'C' => {
+                let tag = cstring(&mut body);
+                outputs.push(match columns.take() {
+                    Some(columns) => Output::Rows { columns, rows: std::mem::take(&mut rows), tag },
+                    None => Output::Complete(tag),
+                });
+            }
+            b'E' | b'N' => {
+                let mut feedback = Feedback {
+                    severity: String::new(),
+                    message: String::new(),
+                    detail: None,
+                    hint: None,
+                };
+                while body.has_remaining() {
+                    let field = body.get_u8();
+                    if field == 0 {
+                        break;
+                    }
+                    let value = cstring(&mut body);
+                    match field {
+                        b'S' => feedback.severity = value,
+                        b'M' => feedback.message = value,
+                        b'D' => feedback.detail = Some(value),
+                        b'H' => feedback.hint = Some(value),
+                        _ => {}
+                    }
+                }
+                outputs.push(Output::Message(feedback));
+            }
+            _ => {}
+        }
+    }
+    outputs
+}
+
+fn cstring(body: &mut BytesMut) -> String {
+    let end = body.iter().position(|&b| b == 0).unwrap_or(body.len());
+    let value = String::from_utf8_lossy(&body[..end]).into_owned();
+    body.advance((end + 1).min(body.len()));
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{BackendMessage, ErrorResponse, FieldDescription};
+    use tokio_util::codec::Encoder;
+
+    #[test]
+    fn test_statement_complete() {
+        assert!(statement_complete("SELECT 1;"));
+        assert!(statement_complete("SELECT 1; -- done"));
+        assert!(statement_complete("SELECT 'a;b'\n  FROM t;"));
+        assert!(!statement_complete("SELECT 1"));
+        assert!(!statement_complete("SELECT 'a;"));
+        assert!(!statement_complete("SELECT 1 /* ; */"));
+        assert!(!statement_complete("CREATE FUNCTION f() RETURNS int AS $$ SELECT 1;"));
+        assert!(statement_complete("CREATE FUNCTION f() RETURNS int AS $body$ SELECT 1; $body$ LANGUAGE sql;"));
+        assert!(statement_complete("SELECT \"a;\" FROM t;"));
+    }
+
+    #[test]
+    fn test_decode() {
+        let mut codec = PostgresCodec::new();
+        let mut buf = BytesMut::new();
+        let messages = vec![
+            BackendMessage::RowDescription(vec![FieldDescription {
+                name: "id".to_string(),
+                table_oid: 0,
+                column_id: 0,
+                type_oid: 23,
+                type_size: 4,
+                type_modifier: -1,
+                format: 0,
+            }]),
+            BackendMessage::DataRow(vec![Some(b"1".to_vec())]),
+            BackendMessage::DataRow(vec![None]),
+            BackendMessage::CommandComplete { tag: "SELECT 2".to_string() },
+            BackendMessage::CommandComplete { tag: "CREATE TABLE".to_string() },
+            BackendMessage::ErrorResponse(Box::new(ErrorResponse::new(
+                "ERROR".to_string(),
+                "42P01".to_string(),
+                "relation \"t\" does not exist".to_string(),
+            ))),
+            BackendMessage::ReadyForQuery { status: TransactionStatus::Idle },
+        ];
+        for message in messages {
+            codec.encode(message, &mut buf).unwrap();
+        }
+
+        let outputs = decode(buf);
+        assert_eq!(outputs.len(), 3);
+        assert_eq!(outputs[0], Output::Rows {
+            columns: vec![Column { name: "id".to_string(), type_oid: 23 }],
+            rows: vec![vec![Some("1".to_string())], vec![None]],
+            tag: "SELECT 2".to_string(),
+        });
+        assert_eq!(outputs[1], Output::Complete("CREATE TABLE".to_string()));
+        match &outputs[2] {
+            Output::Message(feedback) => {
+                assert_eq!(feedback.severity, "ERROR");
+                assert_eq!(feedback.message, "relation \"t\" does not exist");
+            }
+            other => panic!("expected an error, got {other:?}"),
+        }
+    }
+}
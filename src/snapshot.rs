@@ -0,0 +1,149 @@
+//! `pgsqlite snapshot`: the rows of every table, for asserting database state in tests.
+//!
+//! The tables are read through the same executor client connections use, so values
+//! are formatted as clients see them, and written in the format of
+//! `pg_dump --data-only`: a `COPY ... FROM stdin;` block per table. With `--normalize`
+//! the dump is canonical: tables in name order, rows sorted by their values, and the
+//! columns matching --snapshot-volatile-columns left out, so two runs of a test
+//! produce the same file and a change shows up as a small diff.
+
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Result};
+use regex::Regex;
+
+use crate::config::Config;
+use crate::query::copy_handler::{CopyFormat, CopyHandler, CopySource, CopyToStatement};
+use crate::query::sql_utils::{quote_identifier, quote_literal};
+use crate::session::DbHandler;
+use crate::shell::{Output, Shell};
+
+/// User tables: not SQLite's, not pgsqlite's catalog, and not full-text index storage
+const TABLES: &str = "SELECT name FROM sqlite_master m
+    WHERE type = 'table' AND sql NOT LIKE 'CREATE VIRTUAL%'
+      AND name NOT LIKE 'sqlite\\_%' ESCAPE '\\'
+      AND name NOT LIKE '\\_\\_pgsqlite\\_%' ESCAPE '\\'
+      AND name NOT LIKE 'pg\\_%' ESCAPE '\\'
+      AND NOT EXISTS (
+          SELECT 1 FROM sqlite_master v
+          WHERE v.type = 'table' AND v.sql LIKE 'CREATE VIRTUAL%' AND m.name LIKE v.name || '\\_%' ESCAPE '\\'
+      )
+    ORDER BY name";
+
+/// What goes into a snapshot
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotOptions {
+    /// Sort the rows and leave out the volatile columns
+    pub normalize: bool,
+    /// `column` or `table.column` patterns, where `*` matches any characters
+    pub volatile_columns: Vec<String>,
+}
+
+impl SnapshotOptions {
+    /// Whether the column is left out of a normalized snapshot
+    pub fn is_volatile(&self, table: &str, column: &str) -> bool {
+        self.normalize && self.volatile_columns.iter().any(|pattern| {
+            let (table_pattern, column_pattern) = pattern.rsplit_once('.').unwrap_or(("*", pattern));
+            matches_glob(table_pattern, table) && matches_glob(column_pattern, column)
+        })
+    }
+}
+
+/// Write the snapshot of `database` to `output`, or to stdout without one
+pub fn run(database: &str, config: &Config, normalize: bool, output: Option<&Path>) -> Result<()> {
+    let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build()?;
+    let db = DbHandler::new_with_config(database, config)
+        .map_err(|e| anyhow!("Failed to open database {}: {}", database, e))?;
+    let mut shell = runtime.block_on(Shell::open(Arc::new(db), "snapshot"))
+        .map_err(|e| anyhow!("Failed to open session: {}", e))?;
+    let options = SnapshotOptions {
+        normalize,
+        volatile_columns: config.snapshot_volatile_columns.clone(),
+    };
+    let dump = runtime.block_on(snapshot(&mut shell, &options))?;
+    match output {
+        Some(path) => std::fs::write(path, dump)?,
+        None => std::io::stdout().write_all(dump.as_bytes())?,
+    }
+    Ok(())
+}
+
+/// The snapshot of the database the shell is open on
+pub async fn snapshot(shell: &mut Shell, options: &SnapshotOptions) -> Result<String> {
+    let format = CopyToStatement {
+        source: CopySource::Query(String::new()),
+        format: CopyFormat::Text,
+        header: false,
+        delimiter: "\t".to_string(),
+        null: "\\N".to_string(),
+        quote: "\"".to_string(),
+    };
+    let mut dump = String::new();
+    for table in column(query(shell, TABLES).await?) {
+        let columns: Vec<String> = column(query(shell, &format!("SELECT name FROM pragma_table_info({}) ORDER BY cid", quote_literal(&table))).await?)
+            .into_iter()
+            .filter(|column| !options.is_volatile(&table, column))
+            .collect();
+        let names: Vec<String> = columns.iter().map(|column| quote_identifier(column)).collect();
+        dump.push_str(&format!("COPY {} ({}) FROM stdin;\n", quote_identifier(&table), names.join(", ")));
+        if !columns.is_empty() {
+            let mut select = format!("SELECT {} FROM {}", names.join(", "), quote_identifier(&table));
+            if options.normalize {
+                let positions: Vec<String> = (1..=columns.len()).map(|i| i.to_string()).collect();
+                select.push_str(&format!(" ORDER BY {}", positions.join(", ")));
+            }
+            for row in query(shell, &select).await? {
+                dump.push_str(&String::from_utf8_lossy(&CopyHandler::encode_line(&row, &format)));
+            }
+        }
+        dump.push_str("\\.\n\n");
+    }
+    Ok(dump)
+}
+
+/// The rows of a query, or its error
+async fn query(shell: &mut Shell, sql: &str) -> Result<Vec<Vec<Option<String>>>> {
+    for output in shell.execute(sql).await {
+        match output {
+            Output::Rows { rows, .. } => return Ok(rows),
+            Output::Message(feedback) if feedback.severity == "ERROR" => bail!("{}: {}", sql, feedback.message),
+            _ => {}
+        }
+    }
+    bail!("{}: no rows returned", sql)
+}
+
+/// The non-NULL values of the first column
+fn column(rows: Vec<Vec<Option<String>>>) -> Vec<String> {
+    rows.into_iter().filter_map(|row| row.into_iter().next().flatten()).collect()
+}
+
+
+
+/// Case-insensitive match of a pattern where `*` stands for any characters
+fn matches_glob(pattern: &str, name: &str) -> bool {
+    let pattern = regex::escape(pattern).replace("\\*", ".*");
+    Regex::new(&format!("(?i)^{pattern}$")).is_ok_and(|regex| regex.is_match(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_volatile() {
+        let options = SnapshotOptions {
+            normalize: true,
+            volatile_columns: vec!["created_at".to_string(), "sessions.*".to_string(), "*_audit.changed_*".to_string()],
+        };
+        assert!(options.is_volatile("orders", "created_at"));
+        assert!(options.is_volatile("orders", "CREATED_AT"));
+        assert!(options.is_volatile("sessions", "token"));
+        assert!(options.is_volatile("orders_audit", "changed_by"));
+        assert!(!options.is_volatile("orders", "updated_at"));
+        assert!(!options.is_volatile("orders", "changed_by"));
+        assert!(!SnapshotOptions { normalize: false, ..options }.is_volatile("orders", "created_at"));
+    }
+}
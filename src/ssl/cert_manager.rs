@@ -1,12 +1,16 @@
 use anyhow::{Context, Result};
 use rcgen::{CertificateParams, DistinguishedName};
 use rustls::pki_types::{CertificateDer, PrivateKeyDer};
-use rustls::ServerConfig;
+use rustls::crypto::CryptoProvider;
+use rustls::server::danger::ClientCertVerifier;
+use rustls::server::{NoServerSessionStorage, ServerSessionMemoryCache, WebPkiClientVerifier};
+use rustls::{RootCertStore, ServerConfig, SupportedProtocolVersion};
 use rustls_pemfile;
 use std::fs;
 use std::io::BufReader;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 use tokio_rustls::TlsAcceptor;
 use tracing::{debug, info, warn};
 
@@ -23,6 +27,29 @@ pub struct CertificateManager {
     config: Arc<Config>,
 }
 
+/// The OID of the X.509 commonName attribute, 2.5.4.3
+const COMMON_NAME_OID: &[u8] = &[0x55, 0x04, 0x03];
+
+/// The TLS acceptor for new connections, replaced when a configuration reload
+/// or a change to the certificate files reads the certificates again; established
+/// connections keep theirs
+#[derive(Clone)]
+pub struct SharedTlsAcceptor(Arc<parking_lot::RwLock<TlsAcceptor>>);
+
+impl SharedTlsAcceptor {
+    pub fn new(acceptor: TlsAcceptor) -> Self {
+        Self(Arc::new(parking_lot::RwLock::new(acceptor)))
+    }
+
+    pub fn current(&self) -> TlsAcceptor {
+        self.0.read().clone()
+    }
+
+    pub fn replace(&self, acceptor: TlsAcceptor) {
+        *self.0.write() = acceptor;
+    }
+}
+
 impl CertificateManager {
     pub fn new(config: Arc<Config>) -> Self {
         Self { config }
@@ -54,6 +81,78 @@ impl CertificateManager {
         Ok((tls_acceptor, cert_source))
     }
 
+    /// An acceptor with the certificate files read again and the current TLS settings,
+    /// for a configuration reload. None when the certificates are generated in memory,
+    /// which only a restart replaces.
+    pub async fn reload(&self) -> Result<Option<TlsAcceptor>> {
+        let cert_source = match (&self.config.ssl_cert, &self.config.ssl_key) {
+            (Some(cert_path), Some(key_path)) => CertificateSource::Provided {
+                cert_path: cert_path.clone(),
+                key_path: key_path.clone(),
+            },
+            _ if self.config.in_memory || self.config.database == ":memory:" || self.config.ssl_ephemeral => return Ok(None),
+            _ => match self.check_filesystem_certificates()? {
+                Some(cert_source) => cert_source,
+                None => return Ok(None),
+            },
+        };
+        self.create_tls_acceptor(&cert_source).await.map(Some)
+    }
+
+    /// Read the certificates again whenever the certificate, key or CA files change,
+    /// checking every --ssl-cert-check-interval seconds of the current configuration
+    pub async fn watch(acceptor: SharedTlsAcceptor) {
+        let mut seen = Self::new(crate::config::current()).file_versions();
+        loop {
+            let config = crate::config::current();
+            let interval = match config.ssl_cert_check_interval {
+                0 => 60,
+                seconds => seconds,
+            };
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+            if config.ssl_cert_check_interval == 0 {
+                continue;
+            }
+
+            let manager = Self::new(crate::config::current());
+            let versions = manager.file_versions();
+            if versions == seen {
+                continue;
+            }
+            match manager.reload().await {
+                Ok(Some(new_acceptor)) => {
+                    acceptor.replace(new_acceptor);
+                    info!("TLS certificate files changed, new connections use the new certificates");
+                }
+                Ok(None) => {}
+                // A renewal tool may be halfway through writing the files; try again at the next check
+                Err(e) => {
+                    warn!("TLS certificate files changed but could not be loaded, keeping the current ones: {:#}", e);
+                    continue;
+                }
+            }
+            seen = versions;
+        }
+    }
+
+    /// The files the TLS configuration is read from, with when each was last modified
+    fn file_versions(&self) -> Vec<(PathBuf, Option<SystemTime>)> {
+        let mut files: Vec<PathBuf> = match (&self.config.ssl_cert, &self.config.ssl_key) {
+            (Some(cert_path), Some(key_path)) => vec![cert_path.into(), key_path.into()],
+            _ => match self.check_filesystem_certificates() {
+                Ok(Some(CertificateSource::FileSystem { cert_path, key_path })) => vec![cert_path.into(), key_path.into()],
+                _ => Vec::new(),
+            },
+        };
+        files.extend(self.config.ssl_ca.iter().map(PathBuf::from));
+        files.into_iter()
+            .map(|path| {
+                let modified = fs::metadata(&path).and_then(|metadata| metadata.modified()).ok();
+                (path, modified)
+            })
+            .collect()
+    }
+
     async fn discover_certificates(&self) -> Result<CertificateSource> {
         // Priority 1: Check provided paths from config
         if let (Some(cert_path), Some(key_path)) = (&self.config.ssl_cert, &self.config.ssl_key) {
@@ -170,14 +269,108 @@ impl CertificateManager {
             }
         };
 
-        let config = ServerConfig::builder()
-            .with_no_client_auth()
+        let provider = Arc::new(self.crypto_provider()?);
+        let builder = ServerConfig::builder_with_provider(provider.clone())
+            .with_protocol_versions(self.protocol_versions())
+            .context("No cipher suite is usable with the configured TLS versions")?;
+        let builder = match self.client_cert_verifier(provider)? {
+            Some(verifier) => builder.with_client_cert_verifier(verifier),
+            None => builder.with_no_client_auth(),
+        };
+        let mut config = builder
             .with_single_cert(certs, key)
             .context("Failed to create TLS configuration")?;
 
+        // Resumption lets clients that reconnect often skip the full handshake
+        config.session_storage = if self.config.ssl_session_cache_size > 0 {
+            ServerSessionMemoryCache::new(self.config.ssl_session_cache_size)
+        } else {
+            Arc::new(NoServerSessionStorage {})
+        };
+        if self.config.ssl_session_tickets {
+            config.ticketer = rustls::crypto::aws_lc_rs::Ticketer::new()
+                .context("Failed to create TLS session ticketer")?;
+        }
+        config.alpn_protocols = Self::split_list(self.config.ssl_alpn.as_deref())
+            .map(|protocol| protocol.as_bytes().to_vec())
+            .collect();
+
+        debug!(
+            "TLS configured: min version {}, {} cipher suites, session cache {}, tickets {}, ALPN {:?}",
+            self.config.ssl_min_version,
+            config.crypto_provider().cipher_suites.len(),
+            self.config.ssl_session_cache_size,
+            self.config.ssl_session_tickets,
+            self.config.ssl_alpn
+        );
         Ok(TlsAcceptor::from(Arc::new(config)))
     }
 
+    /// With --ssl-client-cert, a verifier accepting client certificates signed by --ssl-ca.
+    /// Clients without one still complete the handshake, so the startup can refuse
+    /// them with an error message rather than a failed handshake.
+    fn client_cert_verifier(&self, provider: Arc<CryptoProvider>) -> Result<Option<Arc<dyn ClientCertVerifier>>> {
+        if self.config.ssl_client_cert == "off" {
+            return Ok(None);
+        }
+        let ca_path = self.config.ssl_ca.as_deref().context("--ssl-client-cert needs --ssl-ca")?;
+        let ca_file = fs::File::open(ca_path)
+            .with_context(|| format!("Failed to open CA file: {ca_path}"))?;
+        let mut roots = RootCertStore::empty();
+        for cert in rustls_pemfile::certs(&mut BufReader::new(ca_file)) {
+            roots.add(cert.context("Failed to parse CA file")?)
+                .with_context(|| format!("Invalid CA certificate in {ca_path}"))?;
+        }
+        if roots.is_empty() {
+            anyhow::bail!("No CA certificates found in {}", ca_path);
+        }
+        let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+            .allow_unauthenticated()
+            .build()
+            .context("Failed to create the client certificate verifier")?;
+        Ok(Some(verifier))
+    }
+
+    /// The default provider, narrowed to `--ssl-ciphers` when given
+    fn crypto_provider(&self) -> Result<CryptoProvider> {
+        let mut provider = rustls::crypto::aws_lc_rs::default_provider();
+        let wanted: Vec<&str> = Self::split_list(self.config.ssl_ciphers.as_deref()).collect();
+        if wanted.is_empty() {
+            return Ok(provider);
+        }
+
+        for name in &wanted {
+            if !provider.cipher_suites.iter().any(|suite| Self::suite_name(suite).eq_ignore_ascii_case(name)) {
+                let supported: Vec<String> = provider.cipher_suites.iter().map(Self::suite_name).collect();
+                anyhow::bail!("Unknown TLS cipher suite {}, supported: {}", name, supported.join(", "));
+            }
+        }
+        provider.cipher_suites.retain(|suite| {
+            let suite_name = Self::suite_name(suite);
+            wanted.iter().any(|name| suite_name.eq_ignore_ascii_case(name))
+        });
+        Ok(provider)
+    }
+
+    fn suite_name(suite: &rustls::SupportedCipherSuite) -> String {
+        format!("{:?}", suite.suite())
+    }
+
+    fn protocol_versions(&self) -> &'static [&'static SupportedProtocolVersion] {
+        static TLS13_ONLY: &[&SupportedProtocolVersion] = &[&rustls::version::TLS13];
+        match self.config.ssl_min_version.as_str() {
+            "1.3" => TLS13_ONLY,
+            _ => rustls::ALL_VERSIONS,
+        }
+    }
+
+    fn split_list(list: Option<&str>) -> impl Iterator<Item = &str> {
+        list.unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+    }
+
     fn load_certificates_from_files(&self, cert_path: &str, key_path: &str) -> Result<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)> {
         // Load certificate
         let cert_file = fs::File::open(cert_path)
@@ -225,4 +418,54 @@ impl CertificateManager {
 
         Ok((certs, private_key))
     }
-}
\ No newline at end of file
+}
+
+/// The common name in the subject of a DER-encoded X.509 certificate
+pub fn common_name(certificate: &[u8]) -> Option<String> {
+    let (_, certificate, _) = der_element(certificate)?;
+    let (_, mut fields, _) = der_element(certificate)?;
+    // The version is an explicit [0] field that v1 certificates leave out
+    if fields.first() == Some(&0xa0) {
+        fields = der_element(fields)?.2;
+    }
+    // The serial number, signature algorithm, issuer and validity come before the subject
+    for _ in 0..4 {
+        fields = der_element(fields)?.2;
+    }
+    let (_, mut names, _) = der_element(fields)?;
+    while !names.is_empty() {
+        let (_, mut attributes, rest) = der_element(names)?;
+        names = rest;
+        while !attributes.is_empty() {
+            let (_, attribute, rest) = der_element(attributes)?;
+            attributes = rest;
+            let (tag, oid, value) = der_element(attribute)?;
+            if tag == 0x06 && oid == COMMON_NAME_OID {
+                let (_, name, _) = der_element(value)?;
+                return String::from_utf8(name.to_vec()).ok();
+            }
+        }
+    }
+    None
+}
+
+/// Split one DER element off `input`: its tag, its contents and what follows it
+fn der_element(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    let (&length, rest) = rest.split_first()?;
+    let (length, rest) = if length < 0x80 {
+        (length as usize, rest)
+    } else {
+        let octets = (length & 0x7f) as usize;
+        if octets == 0 || octets > 4 || rest.len() < octets {
+            return None;
+        }
+        let (octets, rest) = rest.split_at(octets);
+        (octets.iter().fold(0usize, |length, &octet| (length << 8) | octet as usize), rest)
+    };
+    if rest.len() < length {
+        return None;
+    }
+    let (contents, rest) = rest.split_at(length);
+    Some((tag, contents, rest))
+}
@@ -1,3 +1,3 @@
 pub mod cert_manager;
 
-pub use cert_manager::{CertificateManager, CertificateSource};
\ No newline at end of file
+pub use cert_manager::{common_name, CertificateManager, CertificateSource, SharedTlsAcceptor};
\ No newline at end of file
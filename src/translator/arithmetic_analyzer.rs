@@ -59,6 +59,11 @@ impl ArithmeticAnalyzer {
                 let expression = &caps[1];
                 let alias = &caps[2];
                 
+                // The * of count(*) is not arithmetic, e.g. count(*) OVER (ORDER BY x) AS n
+                if !expression.replace("(*)", "").contains(['+', '-', '*', '/']) {
+                    continue;
+                }
+                
                 debug!("Found arithmetic expression '{}' aliased as '{}'", expression, alias);
                 
                 // Extract all columns from the expression
@@ -190,6 +195,15 @@ mod tests {
         assert_eq!(hint.source_column.as_ref().unwrap(), "quantity");
     }
     
+    #[test]
+    fn test_count_star_is_not_arithmetic() {
+        let query = "SELECT id, count(*) OVER (ORDER BY id ROWS BETWEEN 1 PRECEDING AND CURRENT ROW) AS n FROM t";
+        assert!(ArithmeticAnalyzer::analyze_query(query).get_hint("n").is_none());
+        
+        let query = "SELECT count(*) * 2 AS doubled FROM t";
+        assert!(ArithmeticAnalyzer::analyze_query(query).get_hint("doubled").is_some());
+    }
+    
     #[test]
     fn test_no_false_positives_for_keywords() {
         let query = "SELECT col1 + 5 FROM table WHERE col2 > 10";
@@ -1,21 +1,21 @@
 use crate::PgSqliteError;
 use crate::translator::{TranslationMetadata, ColumnTypeHint, ExpressionType};
-use crate::types::PgType;
+use crate::types::{ArrayHandler, PgType};
 use regex::Regex;
 use once_cell::sync::Lazy;
 use tracing::debug;
 
 /// Regex patterns for array operators
 static ARRAY_CONTAINS_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r#"(\b\w+(?:\.\w+)*)\s*@>\s*('[^']+'|"[^"]+"|'\[[^\]]+\]')"#).unwrap()
+    Regex::new(r#"(\w+\([^()]*\)|\b\w+(?:\.\w+)*)\s*@>\s*(\w+\([^()]*\)|'[^']+'|"[^"]+"|'\[[^\]]+\]'|\$\d+|-?\d+(?:\.\d+)?\b)"#).unwrap()
 });
 
 static ARRAY_CONTAINED_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r#"(\b\w+(?:\.\w+)*|'[^']+'|"[^"]+"|'\[[^\]]+\]')\s*<@\s*(\b\w+(?:\.\w+)*|'[^']+'|"[^"]+"|'\[[^\]]+\]')"#).unwrap()
+    Regex::new(r#"(\w+\([^()]*\)|\b\w+(?:\.\w+)*|'[^']+'|"[^"]+"|'\[[^\]]+\]'|\$\d+)\s*<@\s*(\w+\([^()]*\)|\b\w+(?:\.\w+)*|'[^']+'|"[^"]+"|'\[[^\]]+\]'|\$\d+)"#).unwrap()
 });
 
 static ARRAY_OVERLAP_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r#"(\b\w+(?:\.\w+)*)\s*&&\s*(\b\w+(?:\.\w+)*|'[^']+'|"[^"]+"|'\[[^\]]+\]')"#).unwrap()
+    Regex::new(r#"(\w+\([^()]*\)|\b\w+(?:\.\w+)*)\s*&&\s*(\w+\([^()]*\)|\b\w+(?:\.\w+)*|'[^']+'|"[^"]+"|'\[[^\]]+\]'|\$\d+)"#).unwrap()
 });
 
 static ARRAY_LITERAL_REGEX: Lazy<Regex> = Lazy::new(|| {
@@ -23,15 +23,31 @@ static ARRAY_LITERAL_REGEX: Lazy<Regex> = Lazy::new(|| {
 });
 
 static ARRAY_SUBSCRIPT_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"(\b\w+(?:\.\w+)*)\[(\d+)\]").unwrap()
+    Regex::new(r"(\b\w+(?:\.\w+)*)((?:\[[^\[\]:']+\])+)").unwrap()
 });
 
 static ARRAY_SLICE_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"(\b\w+(?:\.\w+)*)\[(\d+):(\d+)\]").unwrap()
+    Regex::new(r"(\b\w+(?:\.\w+)*)\[([^\[\]:']*):([^\[\]:']*)\]").unwrap()
+});
+
+static SUBSCRIPT_PARAM_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\w\[([^\[\]']+)\]").unwrap()
+});
+
+static SUBSCRIPT_ASSIGNMENT_PARAM_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)(?:\bSET\s+|,\s*)(\w+)\[[^\[\]:']+\]\s*=\s*\$(\d+)\b").unwrap()
+});
+
+static SUBSCRIPT_ASSIGNMENT_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)(\bSET\s+|,\s*)(\w+)\[([^\[\]:']+)\]\s*=\s*").unwrap()
 });
 
 static ANY_OPERATOR_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r#"('[^']+'|"[^"]+"|[^\s=]+)\s*=\s*ANY\s*\(('[^']+'|"[^"]+"|[\w\.]+)\)"#).unwrap()
+    Regex::new(r#"('[^']+'|"[^"]+"|[^\s=<>!(]+)\s*(=|<>|!=|<=|>=|<|>)\s*ANY\s*\(\s*('[^']*'|"[^"]+"|\$\d+|[\w\.]+)\s*\)"#).unwrap()
+});
+
+static ANY_ALL_DETECT_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(?:ANY|ALL)\s*\(").unwrap()
 });
 
 static ALL_OPERATOR_REGEX: Lazy<Regex> = Lazy::new(|| {
@@ -58,6 +74,7 @@ static ARRAY_FUNCTION_ALIAS_REGEXES: Lazy<Vec<(&'static str, Regex)>> = Lazy::ne
         ("array_ndims", Regex::new(r"(?i)array_ndims\s*\([^)]+\)\s+(?:AS\s+)?(\w+)").unwrap()),
         ("array_position", Regex::new(r"(?i)array_position\s*\([^)]+\)\s+(?:AS\s+)?(\w+)").unwrap()),
         ("json_array_length", Regex::new(r"(?i)json_array_length\s*\([^)]+\)\s+(?:AS\s+)?(\w+)").unwrap()),
+        ("cardinality", Regex::new(r"(?i)cardinality\s*\([^)]+\)\s+(?:AS\s+)?(\w+)").unwrap()),
         // Array functions that return booleans
         ("array_contains", Regex::new(r"(?i)array_contains\s*\([^)]+\)\s+(?:AS\s+)?(\w+)").unwrap()),
         ("array_contained", Regex::new(r"(?i)array_contained\s*\([^)]+\)\s+(?:AS\s+)?(\w+)").unwrap()),
@@ -91,9 +108,8 @@ impl ArrayTranslator {
             return true;
         }
         
-        // ANY/ALL operators - check common cases
-        if sql.contains(" ANY(") || sql.contains(" any(") || 
-           sql.contains(" ALL(") || sql.contains(" all(") {
+        // ANY/ALL operators, with or without whitespace before the parenthesis
+        if ANY_ALL_DETECT_REGEX.is_match(sql) {
             return true;
         }
         
@@ -111,7 +127,8 @@ impl ArrayTranslator {
             "array_append", "array_prepend", "array_cat", "array_remove",
             "array_replace", "array_slice", "string_to_array", "array_positions",
             "array_upper", "array_lower", "array_ndims", "array_position",
-            "array_contains", "array_contained", "array_overlap", "json_array_length"
+            "array_contains", "array_contained", "array_overlap", "json_array_length",
+            "cardinality"
         ];
         
         // For less common functions, do a case-insensitive check on smaller string segments
@@ -138,9 +155,10 @@ impl ArrayTranslator {
         // Translate ARRAY[...] literals first (most specific)
         result = Self::translate_array_literals(&result)?;
         
-        // Translate array subscript access
-        result = Self::translate_array_subscript(&result)?;
+        // Translate array subscript access, assignments first
+        result = Self::translate_subscript_assignment(&result)?;
         result = Self::translate_array_slice(&result)?;
+        result = Self::translate_array_subscript(&result)?;
         
         // Translate ANY/ALL operators
         result = Self::translate_any_operator(&result)?;
@@ -168,9 +186,10 @@ impl ArrayTranslator {
         // Translate ARRAY[...] literals first (most specific)
         result = Self::translate_array_literals(&result)?;
         
-        // Translate array subscript access
-        result = Self::translate_array_subscript(&result)?;
+        // Translate array subscript access, assignments first
+        result = Self::translate_subscript_assignment(&result)?;
         result = Self::translate_array_slice(&result)?;
+        result = Self::translate_array_subscript(&result)?;
         
         // Translate ANY/ALL operators
         result = Self::translate_any_operator(&result)?;
@@ -250,37 +269,115 @@ impl ArrayTranslator {
         Ok(format!("'[{}]'", json_elements.join(",")))
     }
     
-    /// Translate array subscript access: array[1] -> json_extract(array, '$[0]')
-    fn translate_array_subscript(sql: &str) -> Result<String, PgSqliteError> {
-        let mut result = sql.to_string();
+    /// Check whether a query uses subscript or slice notation on a column
+    pub fn contains_subscript(sql: &str) -> bool {
+        sql.contains('[') && (ARRAY_SUBSCRIPT_REGEX.is_match(sql) || ARRAY_SLICE_REGEX.is_match(sql))
+    }
+    
+    /// Parameters used directly as a subscript or slice bound (`col[$1]`, `col[$1:$2]`)
+    pub fn subscript_parameters(sql: &str) -> Vec<usize> {
+        SUBSCRIPT_PARAM_REGEX.captures_iter(sql)
+            .flat_map(|caps| {
+                caps[1].split(':')
+                    .filter_map(|bound| bound.trim().strip_prefix('$')?.parse().ok())
+                    .collect::<Vec<usize>>()
+            })
+            .collect()
+    }
+    
+    /// Parameters assigned to an array element in UPDATE (`SET col[i] = $n`), as (column, n)
+    pub fn subscript_assignment_parameters(sql: &str) -> Vec<(String, usize)> {
+        SUBSCRIPT_ASSIGNMENT_PARAM_REGEX.captures_iter(sql)
+            .filter_map(|caps| Some((caps[1].to_lowercase(), caps[2].parse().ok()?)))
+            .collect()
+    }
+    
+    /// Translate subscripted assignments in UPDATE:
+    /// SET array[2] = value -> SET array = array_set(array, 2, value)
+    fn translate_subscript_assignment(sql: &str) -> Result<String, PgSqliteError> {
+        if !sql.trim_start().get(..6).is_some_and(|prefix| prefix.eq_ignore_ascii_case("UPDATE")) {
+            return Ok(sql.to_string());
+        }
         
-        while let Some(captures) = ARRAY_SUBSCRIPT_REGEX.captures(&result) {
-            let array_col = &captures[1];
-            let index: usize = captures[2].parse().unwrap_or(1);
-            // PostgreSQL arrays are 1-based, JSON arrays are 0-based
-            let json_index = if index > 0 { index - 1 } else { 0 };
+        let mut result = sql.to_string();
+        let mut search_from = 0;
+        while let Some(captures) = SUBSCRIPT_ASSIGNMENT_REGEX.captures_at(&result, search_from) {
+            let whole = captures.get(0).unwrap();
+            if is_inside_string_literal(&result, whole.start()) {
+                search_from = whole.end();
+                continue;
+            }
             
-            let replacement = format!("json_extract({array_col}, '$[{json_index}]')");
-            result = result.replace(&captures[0], &replacement);
+            let value_end = find_expression_end(&result, whole.end());
+            let value = result[whole.end()..value_end].trim_end();
+            let replacement = format!(
+                "{}{column} = array_set({column}, {}, {value})",
+                &captures[1], captures[3].trim(), column = &captures[2]
+            );
+            let value_end = whole.end() + value.len();
+            
+            search_from = whole.start() + replacement.len();
+            result.replace_range(whole.start()..value_end, &replacement);
         }
         
         Ok(result)
     }
     
-    /// Translate array slice access: array[1:3] -> array_slice(array, 1, 3)
-    fn translate_array_slice(sql: &str) -> Result<String, PgSqliteError> {
-        let mut result = sql.to_string();
+    /// Translate array subscript access: array[1] -> json_extract(array, '$[0]'),
+    /// array[1][2] -> json_extract(array, '$[0][1]') and array[i] -> array_subscript(array, i)
+    fn translate_array_subscript(sql: &str) -> Result<String, PgSqliteError> {
+        let result = ARRAY_SUBSCRIPT_REGEX.replace_all(sql, |captures: &regex::Captures| {
+            let array_col = &captures[1];
+            if array_col.eq_ignore_ascii_case("ARRAY") || is_inside_string_literal(sql, captures.get(0).unwrap().start()) {
+                return captures[0].to_string();
+            }
+            
+            let indexes: Vec<&str> = captures[2]
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .split("][")
+                .map(str::trim)
+                .collect();
+            
+            if indexes.iter().all(|index| index.bytes().all(|b| b.is_ascii_digit())) {
+                // PostgreSQL arrays are 1-based, JSON arrays are 0-based; there is no element 0
+                let mut path = String::from("$");
+                for index in &indexes {
+                    match index.parse::<usize>() {
+                        Ok(index) if index > 0 => path.push_str(&format!("[{}]", index - 1)),
+                        _ => return "NULL".to_string(),
+                    }
+                }
+                format!("json_extract({array_col}, '{path}')")
+            } else {
+                indexes.iter().fold(array_col.to_string(), |array, index| format!("array_subscript({array}, {index})"))
+            }
+        });
         
-        while let Some(captures) = ARRAY_SLICE_REGEX.captures(&result) {
+        Ok(result.into_owned())
+    }
+    
+    /// Translate array slice access: array[1:3] -> array_slice(array, 1, 3). Omitted
+    /// bounds default to the start and end of the array.
+    fn translate_array_slice(sql: &str) -> Result<String, PgSqliteError> {
+        let result = ARRAY_SLICE_REGEX.replace_all(sql, |captures: &regex::Captures| {
             let array_col = &captures[1];
-            let start = &captures[2];
-            let end = &captures[3];
+            if array_col.eq_ignore_ascii_case("ARRAY") || is_inside_string_literal(sql, captures.get(0).unwrap().start()) {
+                return captures[0].to_string();
+            }
             
-            let replacement = format!("array_slice({array_col}, {start}, {end})");
-            result = result.replace(&captures[0], &replacement);
-        }
+            let start = match captures[2].trim() {
+                "" => "1",
+                start => start,
+            };
+            let end = match captures[3].trim() {
+                "" => "2147483647",
+                end => end,
+            };
+            format!("array_slice({array_col}, {start}, {end})")
+        });
         
-        Ok(result)
+        Ok(result.into_owned())
     }
     
     /// Translate ANY operator: value = ANY(array) -> EXISTS(SELECT 1 FROM json_each(array) WHERE value = ?)
@@ -301,20 +398,46 @@ impl ArrayTranslator {
             result = result.replace(&captures[0], &replacement);
         }
         
-        // Then handle regular ANY(column) patterns
+        // Then handle ANY(column), ANY($1) and ANY('{...}') patterns
         while let Some(captures) = ANY_OPERATOR_REGEX.captures(&result) {
             let value = &captures[1];
-            let array_col = &captures[2];
+            let operator = &captures[2];
+            let array = Self::array_operand_to_json(&captures[3]);
             
-            let replacement = format!(
-                "EXISTS (SELECT 1 FROM json_each({array_col}) WHERE value = {value})"
-            );
-            result = result.replace(&captures[0], &replacement);
+            // The compared value stays outside the json_each scope so that columns named
+            // like json_each's own columns (id, key, value, ...) resolve to the outer table
+            let replacement = if operator == "=" {
+                format!("{value} IN (SELECT value FROM json_each({array}))")
+            } else {
+                // value > ANY(array) means some element is < value
+                let flipped = match operator {
+                    ">" => "<",
+                    ">=" => "<=",
+                    "<" => ">",
+                    "<=" => ">=",
+                    _ => operator,
+                };
+                format!("EXISTS (SELECT 1 FROM json_each({array}) WHERE value {flipped} {value})")
+            };
+            let range = captures.get(0).unwrap().range();
+            result.replace_range(range, &replacement);
         }
         
         Ok(result)
     }
     
+    /// Convert a quoted PostgreSQL array literal ('{1,2}') used as an ANY/ALL operand to JSON.
+    /// Columns, parameters and JSON literals are returned unchanged.
+    fn array_operand_to_json(operand: &str) -> String {
+        if operand.len() >= 2 && operand.starts_with("'{") && operand.ends_with('\'') {
+            let literal = operand[1..operand.len() - 1].replace("''", "'");
+            if let Ok(json) = ArrayHandler::array_literal_to_json(&literal, None) {
+                return format!("'{}'", json.replace('\'', "''"));
+            }
+        }
+        operand.to_string()
+    }
+    
     /// Translate ALL operator: value > ALL(array) -> NOT EXISTS(SELECT 1 FROM json_each(array) WHERE value <= ?)
     fn translate_all_operator(sql: &str) -> Result<String, PgSqliteError> {
         let mut result = sql.to_string();
@@ -354,15 +477,22 @@ impl ArrayTranslator {
                         "NOT EXISTS ({subquery_or_array} WHERE {select_expr} {inverted_op} {value})"
                     )
                 }
+            } else if operator == "!=" || operator == "<>" {
+                // value <> ALL(array) -> value NOT IN (...), keeping value outside the json_each scope
+                let array = Self::array_operand_to_json(subquery_or_array.trim());
+                format!("{value} NOT IN (SELECT value FROM json_each({array}))")
             } else {
                 // Handle array column case: ALL(array_col) -> NOT EXISTS(SELECT 1 FROM json_each(array_col) WHERE value <= ?)
+                let array = Self::array_operand_to_json(subquery_or_array.trim());
                 format!(
-                    "NOT EXISTS (SELECT 1 FROM json_each({subquery_or_array}) WHERE value {inverted_op} {value})"
+                    "NOT EXISTS (SELECT 1 FROM json_each({array}) WHERE value {inverted_op} {value})"
                 )
             };
             
-            let full_match = format!("{value} {operator} ALL({subquery_or_array})");
-            result = result.replace(&full_match, &replacement);
+            // Replace from the start of the match through the closing parenthesis
+            let match_start = captures.get(0).unwrap().start();
+            let match_end = start_pos + subquery_or_array.len() + 1;
+            result.replace_range(match_start..match_end, &replacement);
         }
         
         Ok(result)
@@ -756,14 +886,14 @@ impl ArrayTranslator {
                 
                 // Determine return type based on function name
                 let suggested_type = match *func_name {
-                    // Functions that return arrays (stored as JSON TEXT)
+                    // Functions that return arrays take the type checker's array type
                     "array_agg" | "array_append" | "array_prepend" | "array_cat" |
                     "array_remove" | "array_replace" | "array_slice" | "string_to_array" |
-                    "array_positions" => PgType::Text,
+                    "array_positions" => continue,
                     
                     // Functions that return integers
                     "array_length" | "array_upper" | "array_lower" | "array_ndims" |
-                    "array_position" | "json_array_length" => PgType::Int4,
+                    "array_position" | "json_array_length" | "cardinality" => PgType::Int4,
                     
                     // Functions that return booleans
                     "array_contains" | "array_contained" | "array_overlap" => PgType::Bool,
@@ -802,6 +932,48 @@ fn extract_select_expression(select_query: &str) -> Option<&str> {
     }
 }
 
+/// Whether a byte offset falls inside a single-quoted string literal
+fn is_inside_string_literal(sql: &str, pos: usize) -> bool {
+    sql[..pos].bytes().filter(|&b| b == b'\'').count() % 2 == 1
+}
+
+/// Find where the expression starting at `start` ends: at a top-level comma or
+/// closing parenthesis, a WHERE/FROM/RETURNING keyword, a semicolon or the end
+fn find_expression_end(sql: &str, start: usize) -> usize {
+    let bytes = sql.as_bytes();
+    let mut depth = 0;
+    let mut in_quotes = false;
+    let mut i = start;
+    
+    while i < bytes.len() {
+        let b = bytes[i];
+        if in_quotes {
+            if b == b'\'' {
+                in_quotes = false;
+            }
+        } else {
+            match b {
+                b'\'' => in_quotes = true,
+                b'(' => depth += 1,
+                b')' if depth == 0 => return i,
+                b')' => depth -= 1,
+                b',' | b';' if depth == 0 => return i,
+                _ if depth == 0 && b.is_ascii_whitespace() => {
+                    let rest = sql[i..].trim_start();
+                    let keyword_end = rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len());
+                    if ["WHERE", "FROM", "RETURNING"].iter().any(|kw| rest[..keyword_end].eq_ignore_ascii_case(kw)) {
+                        return i;
+                    }
+                }
+                _ => {}
+            }
+        }
+        i += 1;
+    }
+    
+    bytes.len()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -814,7 +986,38 @@ mod tests {
         
         let sql2 = "SELECT matrix[2][3] FROM data";
         let result2 = ArrayTranslator::translate_array_operators(sql2).unwrap();
-        assert!(result2.contains("json_extract(matrix, '$[1]')"));
+        assert_eq!(result2, "SELECT json_extract(matrix, '$[1][2]') FROM data");
+        
+        let sql3 = "SELECT tags[i + 1], tags[0], '{a}[1]' FROM products";
+        let result3 = ArrayTranslator::translate_array_operators(sql3).unwrap();
+        assert_eq!(result3, "SELECT array_subscript(tags, i + 1), NULL, '{a}[1]' FROM products");
+    }
+    
+    #[test]
+    fn test_array_slice() {
+        let sql = "SELECT scores[2:3], scores[:2], scores[2:] FROM results";
+        let result = ArrayTranslator::translate_array_operators(sql).unwrap();
+        assert_eq!(result, "SELECT array_slice(scores, 2, 3), array_slice(scores, 1, 2), array_slice(scores, 2, 2147483647) FROM results");
+    }
+    
+    #[test]
+    fn test_subscript_parameters() {
+        let sql = "UPDATE t SET vals[$1] = $2, tags[2] = $3 WHERE vals[$4:$5] = '[]' AND id = $6";
+        assert_eq!(ArrayTranslator::subscript_parameters(sql), vec![1, 4, 5]);
+        assert_eq!(
+            ArrayTranslator::subscript_assignment_parameters(sql),
+            vec![("vals".to_string(), 2), ("tags".to_string(), 3)]
+        );
+    }
+    
+    #[test]
+    fn test_subscript_assignment() {
+        let sql = "UPDATE products SET tags[2] = 'sale', name = 'x', scores[$1] = lower('A,B') WHERE tags[1] = 'new'";
+        let result = ArrayTranslator::translate_array_operators(sql).unwrap();
+        assert_eq!(
+            result,
+            "UPDATE products SET tags = array_set(tags, 2, 'sale'), name = 'x', scores = array_set(scores, $1, lower('A,B')) WHERE json_extract(tags, '$[0]') = 'new'"
+        );
     }
     
     #[test]
@@ -822,7 +1025,20 @@ mod tests {
         let sql = "SELECT * FROM products WHERE 'electronics' = ANY(tags)";
         let result = ArrayTranslator::translate_array_operators(sql).unwrap();
         println!("ANY operator result: {result}");
-        assert!(result.contains("EXISTS (SELECT 1 FROM json_each(tags) WHERE value = 'electronics')"));
+        assert!(result.contains("'electronics' IN (SELECT value FROM json_each(tags))"));
+        
+        // Parameters, spacing, PostgreSQL array literals and other comparison operators
+        let sql = "SELECT * FROM products WHERE id = ANY ($1)";
+        let result = ArrayTranslator::translate_array_operators(sql).unwrap();
+        assert_eq!(result, "SELECT * FROM products WHERE id IN (SELECT value FROM json_each($1))");
+        
+        let sql = "SELECT * FROM products WHERE id = ANY('{1,2}')";
+        let result = ArrayTranslator::translate_array_operators(sql).unwrap();
+        assert_eq!(result, "SELECT * FROM products WHERE id IN (SELECT value FROM json_each('[1,2]'))");
+        
+        let sql = "SELECT * FROM scores WHERE 4 > ANY(grades)";
+        let result = ArrayTranslator::translate_array_operators(sql).unwrap();
+        assert_eq!(result, "SELECT * FROM scores WHERE EXISTS (SELECT 1 FROM json_each(grades) WHERE value < 4)");
     }
     
     #[test]
@@ -831,6 +1047,10 @@ mod tests {
         let result = ArrayTranslator::translate_array_operators(sql).unwrap();
         assert!(result.contains("NOT EXISTS (SELECT 1 FROM json_each(grades) WHERE value <= 90)"));
         
+        let sql = "SELECT * FROM products WHERE id <> ALL ($1)";
+        let result = ArrayTranslator::translate_array_operators(sql).unwrap();
+        assert_eq!(result, "SELECT * FROM products WHERE id NOT IN (SELECT value FROM json_each($1))");
+        
         // Test ALL with subquery
         let sql2 = "SELECT id, name FROM products WHERE 5 < ALL(SELECT length(value) FROM json_each(tags))";
         let result2 = ArrayTranslator::translate_array_operators(sql2).unwrap();
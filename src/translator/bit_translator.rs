@@ -0,0 +1,122 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use super::network_translator::OPERAND;
+
+/// Translator for bit string literals and the bitwise operators
+///
+/// `B'0101'` literals become plain text literals, since bit strings are stored
+/// as text. `&`, `|` and `#` are rewritten to bit_and(), bit_or() and bit_xor(),
+/// and unary `~` to bit_not(); the functions work digit by digit on bit strings
+/// and keep the integer meaning for integers. SQLite has no `#` operator at all.
+pub struct BitTranslator;
+
+static BIT_LITERAL_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\b[bB]'([^']*)'").unwrap()
+});
+
+static BIT_OPERATOR_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(r"({OPERAND})\s*([&|#])\s*({OPERAND})")).unwrap()
+});
+
+/// Unary `~` only follows the start of an expression; after an operand it is
+/// the regex match operator
+static BIT_NOT_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(
+        r"(?i)(^|[(,=<>+\-*/]|\b(?:SELECT|WHERE|AND|OR|NOT|WHEN|THEN|ELSE|RETURNING))(\s*)~\s*({OPERAND})"
+    )).unwrap()
+});
+
+impl BitTranslator {
+    /// Check if translation might be needed
+    pub fn needs_translation(query: &str) -> bool {
+        query.bytes().any(|b| matches!(b, b'&' | b'|' | b'#' | b'~')) || Self::contains_bit_literal(query)
+    }
+
+    /// Check for a `B'...'` bit string literal
+    pub fn contains_bit_literal(query: &str) -> bool {
+        BIT_LITERAL_REGEX.find_iter(query).any(|m| !Self::inside_literal(query, m.start()))
+    }
+
+    /// Rewrite bit string literals and operators
+    pub fn translate_query(query: &str) -> String {
+        if !Self::needs_translation(query) {
+            return query.to_string();
+        }
+
+        let mut result = BIT_LITERAL_REGEX.replace_all(query, |caps: &regex::Captures| {
+            if Self::inside_literal(query, caps.get(0).unwrap().start()) {
+                return caps[0].to_string();
+            }
+            if crate::functions::bit_functions::validate_bit_digits(&caps[1]).is_ok() {
+                format!("'{}'", &caps[1])
+            } else {
+                // Let the cast report the invalid digit when the query runs
+                format!("pg_bit_cast('{}', NULL, 1)", &caps[1])
+            }
+        }).into_owned();
+
+        // Operators are left associative, so rewrite until chains like a & b & c are nested
+        loop {
+            let current = result.clone();
+            let rewritten = BIT_OPERATOR_REGEX.replace_all(&current, |caps: &regex::Captures| {
+                if Self::inside_literal(&current, caps.get(0).unwrap().start()) {
+                    return caps[0].to_string();
+                }
+                let function = match &caps[2] {
+                    "&" => "bit_and",
+                    "|" => "bit_or",
+                    _ => "bit_xor",
+                };
+                format!("{function}({}, {})", &caps[1], &caps[3])
+            });
+            if rewritten == current {
+                break;
+            }
+            result = rewritten.into_owned();
+        }
+
+        if result.contains('~') {
+            let current = result.clone();
+            result = BIT_NOT_REGEX.replace_all(&current, |caps: &regex::Captures| {
+                if Self::inside_literal(&current, caps.get(0).unwrap().start()) {
+                    return caps[0].to_string();
+                }
+                format!("{}{}bit_not({})", &caps[1], &caps[2], &caps[3])
+            }).into_owned();
+        }
+
+        result
+    }
+
+    fn inside_literal(query: &str, pos: usize) -> bool {
+        query[..pos].bytes().filter(|&b| b == b'\'').count() % 2 == 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bit_literals_and_operators() {
+        assert_eq!(
+            BitTranslator::translate_query("SELECT B'1100' & B'1010', b'1' | flags, a # b # c FROM t"),
+            "SELECT bit_and('1100', '1010'), bit_or('1', flags), bit_xor(bit_xor(a, b), c) FROM t"
+        );
+        assert_eq!(
+            BitTranslator::translate_query("SELECT ~flags, id FROM t WHERE ~ B'01' = flags"),
+            "SELECT bit_not(flags), id FROM t WHERE bit_not('01') = flags"
+        );
+        assert_eq!(
+            BitTranslator::translate_query("INSERT INTO t VALUES (B'012')"),
+            "INSERT INTO t VALUES (pg_bit_cast('012', NULL, 1))"
+        );
+    }
+
+    #[test]
+    fn test_other_operators_unchanged() {
+        let query = "SELECT a || b, x && y, data #> '{a}', data #>> '{b}', name ~ 'B''x', 'a & b | c' FROM t WHERE s !~ 'q'";
+        assert_eq!(BitTranslator::translate_query(query), query);
+        assert!(!BitTranslator::contains_bit_literal("SELECT 'B' FROM t WHERE code = 'AB'"));
+    }
+}
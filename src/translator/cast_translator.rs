@@ -186,7 +186,15 @@ impl CastTranslator {
             
             // Find the end of the type after ::
             let after = &result[cast_pos + 2..];
-            let type_end = Self::find_type_end(after);
+            let mut type_end = Self::find_type_end(after);
+            // Array types: int[], text[][]
+            while after[type_end..].starts_with("[]") {
+                type_end += 2;
+            }
+            // A regclass cast on to oid or an integer type is looked up as a whole
+            if after[..type_end].eq_ignore_ascii_case("regclass") {
+                type_end += Self::oid_cast_len(&after[type_end..]);
+            }
             
             // Extract expression and type
             let mut expr = &result[expr_start..cast_pos];
@@ -205,7 +213,19 @@ impl CastTranslator {
             }
             
             // Check if this is an ENUM type cast
-            let translated_cast = if let Some(conn) = conn {
+            let translated_cast = if type_name.eq_ignore_ascii_case("regclass") {
+                // A regclass value reads as the relation name
+                expr.to_string()
+            } else if type_name.get(..10).is_some_and(|t| t.eq_ignore_ascii_case("regclass::")) {
+                // The OID pg_class reports for the relation
+                format!("regclass({expr})")
+            } else if type_name.eq_ignore_ascii_case("regnamespace") {
+                // A regnamespace value reads as the schema name
+                format!("regnamespace({expr})")
+            } else if type_name.ends_with("[]") {
+                // Array literals are checked against the element type and stored as JSON
+                format!("pg_array_from_text({expr}, '{}')", type_name.to_lowercase())
+            } else if let Some(conn) = conn {
                 if Self::is_enum_type(conn, type_name) {
                     // For ENUM types, we validate the value
                     Self::translate_enum_cast(expr, type_name, conn)
@@ -244,6 +264,26 @@ impl CastTranslator {
                             // Use pgsqlite's date conversion function
                             format!("pg_date_from_text({expr})")
                         }
+                        "INT4RANGE" | "INT8RANGE" | "NUMRANGE" | "TSRANGE" | "TSTZRANGE" | "DATERANGE" => {
+                            // Ranges are stored as canonical text
+                            format!("pg_range_from_text({expr}, '{}')", type_name.to_lowercase())
+                        }
+                        "INET" | "CIDR" | "MACADDR" => {
+                            // Network addresses are validated and stored as canonical text
+                            format!("pg_network_from_text({expr}, '{}')", type_name.to_lowercase())
+                        }
+                        "XML" => {
+                            // xml is stored as text once it's known to be well-formed content
+                            format!("pg_xml_parse({expr}, 'content')")
+                        }
+                        "BYTEA" => {
+                            // Hex and escape format text is decoded into a BLOB
+                            format!("pg_bytea_from_text({expr})")
+                        }
+                        bit_type if Self::is_bit_type(bit_type) => {
+                            // Bit strings are checked and sized to the target length
+                            Self::translate_bit_cast(expr, bit_type)
+                        }
                         "TIME" | "TIME WITHOUT TIME ZONE" | "TIME WITH TIME ZONE" | "TIMETZ" => {
                             // Use pgsqlite's time conversion function
                             format!("pg_time_from_text({expr})")
@@ -305,6 +345,21 @@ impl CastTranslator {
                         "DATE" => {
                             format!("pg_date_from_text({expr})")
                         }
+                        "INT4RANGE" | "INT8RANGE" | "NUMRANGE" | "TSRANGE" | "TSTZRANGE" | "DATERANGE" => {
+                            format!("pg_range_from_text({expr}, '{}')", type_name.to_lowercase())
+                        }
+                        "INET" | "CIDR" | "MACADDR" => {
+                            format!("pg_network_from_text({expr}, '{}')", type_name.to_lowercase())
+                        }
+                        "XML" => {
+                            format!("pg_xml_parse({expr}, 'content')")
+                        }
+                        "BYTEA" => {
+                            format!("pg_bytea_from_text({expr})")
+                        }
+                        bit_type if Self::is_bit_type(bit_type) => {
+                            Self::translate_bit_cast(expr, bit_type)
+                        }
                         "TIME" | "TIME WITHOUT TIME ZONE" | "TIME WITH TIME ZONE" | "TIMETZ" => {
                             format!("pg_time_from_text({expr})")
                         }
@@ -373,6 +428,19 @@ impl CastTranslator {
         result
     }
     
+    /// Length of a `::oid` or `::integer`-like cast at the start of the text, or 0
+    fn oid_cast_len(rest: &str) -> usize {
+        let Some(next) = rest.strip_prefix("::") else {
+            return 0;
+        };
+        let next_type = next.split(|c: char| !c.is_ascii_alphanumeric()).next().unwrap_or("");
+        if ["OID", "INT", "INT4", "INT8", "INTEGER", "BIGINT"].iter().any(|t| next_type.eq_ignore_ascii_case(t)) {
+            2 + next_type.len()
+        } else {
+            0
+        }
+    }
+    
     /// Check if a position is inside a string literal
     fn is_inside_string(query: &str, pos: usize) -> bool {
         let mut in_single_quote = false;
@@ -445,6 +513,30 @@ impl CastTranslator {
         0
     }
     
+    /// Check for BIT, BIT VARYING or VARBIT, with or without a length
+    fn is_bit_type(upper_type: &str) -> bool {
+        let base = upper_type.split('(').next().unwrap_or(upper_type).trim();
+        matches!(base, "BIT" | "BIT VARYING" | "VARBIT")
+    }
+    
+    /// Translate a cast to a bit string type into pg_bit_cast(value, length, varying)
+    fn translate_bit_cast(expr: &str, upper_type: &str) -> String {
+        let varying = !upper_type.split('(').next().unwrap_or(upper_type).trim().eq("BIT");
+        let length = upper_type.split_once('(')
+            .and_then(|(_, rest)| rest.trim_end_matches(')').trim().parse::<i32>().ok())
+            .map_or("NULL".to_string(), |length| length.to_string());
+        format!("pg_bit_cast({expr}, {length}, {})", varying as i32)
+    }
+    
+    /// Extend a bit string type name over its (n) length
+    fn include_bit_length(after: &str, type_name: &str, len: usize) -> usize {
+        if Self::is_bit_type(type_name) && after[len..].starts_with('(')
+            && let Some(close) = after[len..].find(')') {
+            return len + close + 1;
+        }
+        len
+    }
+    
     /// Find the end of a type name after ::
     fn find_type_end(after: &str) -> usize {
         let bytes = after.as_bytes();
@@ -476,7 +568,7 @@ impl CastTranslator {
                 } else if let Some(next_char) = after.as_bytes().get(len) {
                     // There's a character after the pattern - make sure it's a word boundary
                     if !next_char.is_ascii_alphanumeric() && *next_char != b'_' {
-                        return len;
+                        return Self::include_bit_length(after, pattern, len);
                     }
                 }
             }
@@ -508,7 +600,7 @@ impl CastTranslator {
                         if after.contains("RETURNING") {
                             eprintln!("DEBUG: Type '{}' followed by non-alphanumeric '{}', returning {}", type_name, *next_char as char, len);
                         }
-                        return len;
+                        return Self::include_bit_length(after, type_name, len);
                     }
                 }
             }
@@ -622,6 +714,7 @@ impl CastTranslator {
             "text" | "TEXT" => return "TEXT",
             "integer" | "INTEGER" => return "INTEGER",
             "int" | "INT" => return "INTEGER",
+            "oid" | "OID" => return "INTEGER",
             "bool" | "BOOL" | "boolean" | "BOOLEAN" => return "INTEGER",
             "real" | "REAL" | "float" | "FLOAT" => return "REAL",
             "bytea" | "BYTEA" => return "BLOB",
@@ -639,7 +732,7 @@ impl CastTranslator {
         };
         
         match base_type {
-            "INTEGER" | "INT" | "INT4" | "INT8" | "BIGINT" | "SMALLINT" | "INT2" => "INTEGER",
+            "INTEGER" | "INT" | "INT4" | "INT8" | "BIGINT" | "SMALLINT" | "INT2" | "OID" => "INTEGER",
             "REAL" | "FLOAT" | "FLOAT4" | "FLOAT8" | "DOUBLE" | "DOUBLE PRECISION" => "REAL",
             "TEXT" | "VARCHAR" | "CHAR" | "CHARACTER VARYING" => "TEXT",
             "BYTEA" => "BLOB",
@@ -650,7 +743,7 @@ impl CastTranslator {
             "TIMESTAMP" | "TIMESTAMP WITHOUT TIME ZONE" | "TIMESTAMP WITH TIME ZONE" | "TIMESTAMPTZ" => "INTEGER",
             "DATE" => "INTEGER",
             "TIME" | "TIME WITHOUT TIME ZONE" | "TIME WITH TIME ZONE" | "TIMETZ" => "INTEGER",
-            "INTERVAL" => "INTEGER",
+            "INTERVAL" => "TEXT",
             _ => "TEXT", // Default to TEXT for unknown types
         }
     }
@@ -744,6 +837,26 @@ impl CastTranslator {
                             // Use pgsqlite's date conversion function
                             format!("pg_date_from_text({expr})")
                         }
+                        "INT4RANGE" | "INT8RANGE" | "NUMRANGE" | "TSRANGE" | "TSTZRANGE" | "DATERANGE" => {
+                            // Ranges are stored as canonical text
+                            format!("pg_range_from_text({expr}, '{}')", type_name.to_lowercase())
+                        }
+                        "INET" | "CIDR" | "MACADDR" => {
+                            // Network addresses are validated and stored as canonical text
+                            format!("pg_network_from_text({expr}, '{}')", type_name.to_lowercase())
+                        }
+                        "XML" => {
+                            // xml is stored as text once it's known to be well-formed content
+                            format!("pg_xml_parse({expr}, 'content')")
+                        }
+                        "BYTEA" => {
+                            // Hex and escape format text is decoded into a BLOB
+                            format!("pg_bytea_from_text({expr})")
+                        }
+                        bit_type if Self::is_bit_type(bit_type) => {
+                            // Bit strings are checked and sized to the target length
+                            Self::translate_bit_cast(expr, bit_type)
+                        }
                         "TIME" | "TIME WITHOUT TIME ZONE" | "TIME WITH TIME ZONE" | "TIMETZ" => {
                             // Use pgsqlite's time conversion function
                             format!("pg_time_from_text({expr})")
@@ -0,0 +1,221 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rusqlite::Connection;
+use crate::metadata::{CompositeType, CompositeTypes};
+
+/// Translator for composite type expressions
+///
+/// Composite values are stored as record text. `ROW(a, b)` becomes pg_row(a, b),
+/// `(col).field` becomes pg_record_field(col, n) for the field's position in the
+/// column's type, and composite columns passed to row_to_json() or inside the
+/// json_object() built for it are nested as JSON objects instead of record text.
+pub struct CompositeTranslator;
+
+static ROW_CONSTRUCTOR_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\bROW\s*\(").unwrap()
+});
+
+/// `(col).field` or `(t.col).field`; the leading group keeps function calls like `f(x).y` out
+static FIELD_ACCESS_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(^|[^\w.])\(\s*((?:\w+\.)?(\w+))\s*\)\s*\.\s*(\w+)").unwrap()
+});
+
+/// A `'name', name` member of the json_object() generated for row_to_json()
+static JSON_OBJECT_MEMBER_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"'(\w+)', (\w+)(\s*[,)])").unwrap()
+});
+
+/// `row_to_json(col)` or `row_to_json(t.col)`
+static ROW_TO_JSON_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\brow_to_json\s*\(\s*((?:\w+\.)?(\w+))\s*\)").unwrap()
+});
+
+/// Nested composite fields are expanded this many levels deep in row_to_json()
+const MAX_DESCRIPTOR_DEPTH: usize = 8;
+
+impl CompositeTranslator {
+    /// Check if the query may contain composite type expressions
+    pub fn needs_translation(query: &str) -> bool {
+        ROW_CONSTRUCTOR_REGEX.find_iter(query).any(|m| !Self::inside_literal(query, m.start()))
+            || FIELD_ACCESS_REGEX.find_iter(query).any(|m| !Self::inside_literal(query, m.start()))
+            || query.contains("json_object(")
+            || ROW_TO_JSON_REGEX.is_match(query)
+    }
+
+    /// Rewrite ROW constructors, field access and row_to_json() members
+    pub fn translate_query(query: &str, conn: &Connection) -> String {
+        if !Self::needs_translation(query) {
+            return query.to_string();
+        }
+
+        let mut result = ROW_CONSTRUCTOR_REGEX.replace_all(query, |caps: &regex::Captures| {
+            if Self::inside_literal(query, caps.get(0).unwrap().start()) {
+                caps[0].to_string()
+            } else {
+                "pg_row(".to_string()
+            }
+        }).into_owned();
+
+        let has_field_access = FIELD_ACCESS_REGEX.is_match(&result);
+        let has_json_object = result.contains("json_object(");
+        let has_row_to_json = ROW_TO_JSON_REGEX.is_match(&result);
+        if !has_field_access && !has_json_object && !has_row_to_json {
+            return result;
+        }
+
+        let composite_columns = Self::composite_columns(conn);
+        if composite_columns.is_empty() {
+            return result;
+        }
+
+        if has_field_access {
+            let current = result.clone();
+            result = FIELD_ACCESS_REGEX.replace_all(&current, |caps: &regex::Captures| {
+                if Self::inside_literal(&current, caps.get(2).unwrap().start()) {
+                    return caps[0].to_string();
+                }
+                let field = composite_columns.iter()
+                    .filter(|(column, _)| column.eq_ignore_ascii_case(&caps[3]))
+                    .find_map(|(_, composite)| composite.attribute(&caps[4]));
+                let Some(field) = field else {
+                    return caps[0].to_string();
+                };
+                let access = format!("pg_record_field({}, {})", &caps[2], field.attnum);
+                let base_type = field.pg_type.split('(').next().unwrap_or(&field.pg_type).trim();
+                let typed = match base_type {
+                    "smallint" | "int2" | "integer" | "int" | "int4" | "bigint" | "int8" => format!("CAST({access} AS INTEGER)"),
+                    "real" | "float4" | "double precision" | "float8" => format!("CAST({access} AS REAL)"),
+                    _ => access,
+                };
+                format!("{}{typed}", &caps[1])
+            }).into_owned();
+        }
+
+        if has_json_object {
+            let current = result.clone();
+            result = JSON_OBJECT_MEMBER_REGEX.replace_all(&current, |caps: &regex::Captures| {
+                let composite = composite_columns.iter()
+                    .find(|(column, _)| caps[1] == caps[2] && column.eq_ignore_ascii_case(&caps[2]))
+                    .map(|(_, composite)| composite);
+                match composite {
+                    Some(composite) if !Self::inside_literal(&current, caps.get(2).unwrap().start()) => {
+                        let descriptor = Self::descriptor(conn, composite, 0).to_string().replace('\'', "''");
+                        format!("'{}', json(pg_record_to_json({}, '{descriptor}')){}", &caps[1], &caps[2], &caps[3])
+                    }
+                    _ => caps[0].to_string(),
+                }
+            }).into_owned();
+        }
+
+        if has_row_to_json {
+            let current = result.clone();
+            result = ROW_TO_JSON_REGEX.replace_all(&current, |caps: &regex::Captures| {
+                let composite = composite_columns.iter()
+                    .find(|(column, _)| column.eq_ignore_ascii_case(&caps[2]))
+                    .map(|(_, composite)| composite);
+                match composite {
+                    Some(composite) if !Self::inside_literal(&current, caps.get(0).unwrap().start()) => {
+                        let descriptor = Self::descriptor(conn, composite, 0).to_string().replace('\'', "''");
+                        format!("json(pg_record_to_json({}, '{descriptor}'))", &caps[1])
+                    }
+                    _ => caps[0].to_string(),
+                }
+            }).into_owned();
+        }
+
+        result
+    }
+
+    /// The type of the composite field compared with parameter `$n`, as in `(col).field > $1`
+    pub fn param_field_type(query: &str, param_index: usize, conn: &Connection) -> Option<String> {
+        let pattern = format!(r"\(\s*(?:\w+\.)?(\w+)\s*\)\s*\.\s*(\w+)\s*(?:=|<>|!=|<=|>=|<|>)\s*\${param_index}\b");
+        let captures = Regex::new(&pattern).ok()?.captures(query)?;
+        Self::composite_columns(conn).into_iter()
+            .filter(|(column, _)| column.eq_ignore_ascii_case(&captures[1]))
+            .find_map(|(_, composite)| composite.attribute(&captures[2]).map(|field| field.pg_type.clone()))
+    }
+
+    /// All columns declared with a composite type, with the type's definition
+    fn composite_columns(conn: &Connection) -> Vec<(String, CompositeType)> {
+        CompositeTypes::get_composite_columns(conn).unwrap_or_default()
+            .into_iter()
+            .filter_map(|(column, type_name)| {
+                CompositeTypes::get_type(conn, &type_name).ok().flatten().map(|composite| (column, composite))
+            })
+            .collect()
+    }
+
+    /// Describe a composite type for pg_record_to_json(): an array of [name, type]
+    /// pairs, with nested composite types described in place of their name
+    fn descriptor(conn: &Connection, composite: &CompositeType, depth: usize) -> serde_json::Value {
+        let attributes = composite.attributes.iter().map(|attr| {
+            let nested = (depth < MAX_DESCRIPTOR_DEPTH)
+                .then(|| CompositeTypes::get_type(conn, &attr.pg_type).ok().flatten())
+                .flatten();
+            let field_type = match nested {
+                Some(nested) => Self::descriptor(conn, &nested, depth + 1),
+                None => serde_json::Value::String(attr.pg_type.clone()),
+            };
+            serde_json::json!([attr.name, field_type])
+        }).collect();
+        serde_json::Value::Array(attributes)
+    }
+
+    fn inside_literal(query: &str, pos: usize) -> bool {
+        query[..pos].bytes().filter(|&b| b == b'\'').count() % 2 == 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::metadata::TypeMetadata::init(&conn).unwrap();
+        CompositeTypes::create_type(&conn, "address", &[
+            ("street".to_string(), "text".to_string()),
+            ("zip".to_string(), "integer".to_string()),
+        ]).unwrap();
+        conn.execute(
+            "INSERT INTO __pgsqlite_schema (table_name, column_name, pg_type, sqlite_type) VALUES ('people', 'home', 'address', 'TEXT')",
+            [],
+        ).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_row_constructor_and_field_access() {
+        let conn = setup();
+        assert_eq!(
+            CompositeTranslator::translate_query("INSERT INTO people (id, home) VALUES (1, ROW('Main St', 12345))", &conn),
+            "INSERT INTO people (id, home) VALUES (1, pg_row('Main St', 12345))"
+        );
+        assert_eq!(
+            CompositeTranslator::translate_query("SELECT (home).street, (p.home).zip FROM people p WHERE (home).zip > 100", &conn),
+            "SELECT pg_record_field(home, 1), CAST(pg_record_field(p.home, 2) AS INTEGER) FROM people p WHERE CAST(pg_record_field(home, 2) AS INTEGER) > 100"
+        );
+
+        // Function calls, unknown columns and string literals are left alone
+        let query = "SELECT lower(home).street, (other).x, '(home).zip', row_number() OVER () FROM people";
+        assert_eq!(CompositeTranslator::translate_query(query, &conn), query);
+
+        let query = "SELECT * FROM people WHERE (home).zip > $1 AND (home).street = $2";
+        assert_eq!(CompositeTranslator::param_field_type(query, 1, &conn).as_deref(), Some("integer"));
+        assert_eq!(CompositeTranslator::param_field_type(query, 2, &conn).as_deref(), Some("text"));
+        assert_eq!(CompositeTranslator::param_field_type(query, 3, &conn), None);
+    }
+
+    #[test]
+    fn test_row_to_json_nests_composites() {
+        let conn = setup();
+        assert_eq!(
+            CompositeTranslator::translate_query("SELECT json_object('id', id, 'home', home) FROM (SELECT id, home FROM people) t", &conn),
+            r#"SELECT json_object('id', id, 'home', json(pg_record_to_json(home, '[["street","text"],["zip","integer"]]'))) FROM (SELECT id, home FROM people) t"#
+        );
+        assert_eq!(
+            CompositeTranslator::translate_query("SELECT row_to_json(p.home), row_to_json(t) FROM people p", &conn),
+            r#"SELECT json(pg_record_to_json(p.home, '[["street","text"],["zip","integer"]]')), row_to_json(t) FROM people p"#
+        );
+    }
+}
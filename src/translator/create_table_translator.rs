@@ -1,33 +1,78 @@
 use regex::Regex;
 use std::collections::HashMap;
-use crate::metadata::{TypeMapping, EnumMetadata};
+use crate::metadata::{TypeMapping, EnumMetadata, IdentityColumn};
 use crate::types::TypeMapper;
+use crate::validator::ExclusionConstraint;
 use rusqlite::Connection;
 use std::cell::RefCell;
 use once_cell::sync::Lazy;
 
 // Pre-compiled regex patterns
 static CREATE_TABLE_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"(?is)CREATE\s+TABLE\s+(?:IF\s+NOT\s+EXISTS\s+)?(\w+)\s*\((.*)\)").unwrap()
+    Regex::new(r"(?is)CREATE\s+(?:(?:GLOBAL|LOCAL)\s+)?(TEMP\s+|TEMPORARY\s+)?TABLE\s+(?:IF\s+NOT\s+EXISTS\s+)?(\w+)\s*\((.*)\)").unwrap()
 });
 
+/// The head of CREATE TABLE and CREATE TEMP TABLE, up to the table name
+static CREATE_TABLE_HEAD_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^\s*CREATE\s+(?:(?:GLOBAL|LOCAL)\s+)?(?:(?:TEMP|TEMPORARY)\s+)?TABLE\s+(?:IF\s+NOT\s+EXISTS\s+)?("(?:[^"]|"")+"|[^\s(]+)"#).unwrap()
+});
+
+/// Legacy OID clauses after the column list; every SQLite row has a rowid anyway
+static OIDS_CLAUSE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\)\s*(?:WITH\s+OIDS|WITHOUT\s+OIDS|WITH\s*\(\s*OIDS\s*(?:=\s*\w+\s*)?\))\s*(;?)\s*$").unwrap()
+});
+
+static IDENTITY_CLAUSE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)\s+GENERATED\s+(ALWAYS|BY\s+DEFAULT)\s+AS\s+IDENTITY(?:\s*\([^)]*\))?").unwrap()
+});
+
+/// The schema qualifier of a referenced table, which SQLite's REFERENCES doesn't accept
+static REFERENCES_SCHEMA_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)\bREFERENCES\s+(?:"[^"]+"|\w+)\s*\.\s*"#).unwrap()
+});
+
+/// PostgreSQL 15's column list for ON DELETE SET NULL/SET DEFAULT, which SQLite lacks
+static REFERENTIAL_ACTION_COLUMNS_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)(\bON\s+DELETE\s+SET\s+(?:NULL|DEFAULT))\s*\([^)]*\)").unwrap()
+});
+
+/// Serial pseudo-types, including their int2/int4/int8 aliases
+const SERIAL_TYPES: &[&str] = &[
+    "SMALLSERIAL", "SERIAL2", "SERIAL", "SERIAL4", "BIGSERIAL", "SERIAL8",
+];
+
 #[derive(Debug)]
 pub struct CreateTableResult {
     pub sql: String,
     pub type_mappings: HashMap<String, TypeMapping>,
     pub enum_columns: Vec<(String, String)>, // (column_name, enum_type)
     pub array_columns: Vec<(String, String, i32)>, // (column_name, element_type, dimensions)
+    pub identity_columns: Vec<IdentityColumn>,
+    pub exclusion_constraints: Vec<ExclusionConstraint>,
 }
 
 thread_local! {
     static ENUM_COLUMNS: RefCell<Vec<(String, String)>> = const { RefCell::new(Vec::new()) };
     static ARRAY_COLUMNS: RefCell<Vec<(String, String, i32)>> = const { RefCell::new(Vec::new()) };
+    static IDENTITY_COLUMNS: RefCell<Vec<IdentityColumn>> = const { RefCell::new(Vec::new()) };
+    static EXCLUSION_CONSTRAINTS: RefCell<Vec<ExclusionConstraint>> = const { RefCell::new(Vec::new()) };
 }
 
 pub struct CreateTableTranslator;
 
 #[allow(unused_variables)]
 impl CreateTableTranslator {
+    /// Whether a statement is CREATE TABLE, temporary or not
+    pub fn is_create_table(query: &str) -> bool {
+        CREATE_TABLE_HEAD_REGEX.is_match(query)
+    }
+
+    /// The name of the table a CREATE TABLE statement makes, without its quotes
+    pub fn created_table_name(query: &str) -> Option<String> {
+        let name = CREATE_TABLE_HEAD_REGEX.captures(query)?.get(1)?.as_str();
+        Some(name.trim_matches('"').trim_matches('\'').to_string())
+    }
+
     /// Translate PostgreSQL CREATE TABLE statement to SQLite
     pub fn translate(pg_sql: &str) -> Result<(String, HashMap<String, TypeMapping>), String> {
         Self::translate_with_connection(pg_sql, None)
@@ -52,11 +97,16 @@ impl CreateTableTranslator {
         // Clear enum and array columns trackers
         ENUM_COLUMNS.with(|ec| ec.borrow_mut().clear());
         ARRAY_COLUMNS.with(|ac| ac.borrow_mut().clear());
+        IDENTITY_COLUMNS.with(|ic| ic.borrow_mut().clear());
+        EXCLUSION_CONSTRAINTS.with(|xc| xc.borrow_mut().clear());
+        
+        let pg_sql = OIDS_CLAUSE_REGEX.replace(pg_sql, ")$1");
         
         // Basic regex to match CREATE TABLE - use DOTALL flag to match newlines
-        if let Some(captures) = CREATE_TABLE_REGEX.captures(pg_sql) {
-            let table_name = captures.get(1).unwrap().as_str();
-            let columns_str = captures.get(2).unwrap().as_str();
+        if let Some(captures) = CREATE_TABLE_REGEX.captures(&pg_sql) {
+            let temporary = if captures.get(1).is_some() { "TEMP " } else { "" };
+            let table_name = captures.get(2).unwrap().as_str();
+            let columns_str = captures.get(3).unwrap().as_str();
             
             // Parse columns
             let sqlite_columns = Self::parse_and_translate_columns(
@@ -70,17 +120,21 @@ impl CreateTableTranslator {
             let final_columns = sqlite_columns;
             
             // Reconstruct CREATE TABLE
-            let sqlite_sql = format!("CREATE TABLE {table_name} ({final_columns})");
+            let sqlite_sql = format!("CREATE {temporary}TABLE {table_name} ({final_columns})");
             
             // Collect enum and array columns
             let enum_columns = ENUM_COLUMNS.with(|ec| ec.borrow().clone());
             let array_columns = ARRAY_COLUMNS.with(|ac| ac.borrow().clone());
+            let identity_columns = IDENTITY_COLUMNS.with(|ic| ic.borrow().clone());
+            let exclusion_constraints = EXCLUSION_CONSTRAINTS.with(|xc| xc.borrow().clone());
             
             Ok(CreateTableResult {
                 sql: sqlite_sql,
                 type_mappings: type_mapping,
                 enum_columns,
                 array_columns,
+                identity_columns,
+                exclusion_constraints,
             })
         } else {
             // Not a CREATE TABLE statement, return as-is
@@ -89,6 +143,8 @@ impl CreateTableTranslator {
                 type_mappings: type_mapping,
                 enum_columns: Vec::new(),
                 array_columns: Vec::new(),
+                identity_columns: Vec::new(),
+                exclusion_constraints: Vec::new(),
             })
         }
     }
@@ -103,7 +159,7 @@ impl CreateTableTranslator {
         let mut paren_depth = 0;
         let mut current_column = String::new();
         let mut column_definitions = Vec::new();
-        let mut serial_columns = std::collections::HashSet::new();
+        let mut serial_columns = Vec::new();
         
         // First pass: collect all column definitions
         for ch in columns_str.chars() {
@@ -132,25 +188,35 @@ impl CreateTableTranslator {
             column_definitions.push(current_column.trim().to_string());
         }
         
-        // Identify SERIAL columns
+        // Identify SERIAL and identity columns
         for column_def in &column_definitions {
             if let Some(column_name) = Self::extract_serial_column_name(column_def) {
-                serial_columns.insert(column_name);
+                serial_columns.push(column_name);
             }
         }
         
+        // Only one of them can become the INTEGER PRIMARY KEY rowid alias
+        let rowid_alias = Self::choose_rowid_alias(&column_definitions, &serial_columns);
+        
         // Second pass: translate columns, filtering out redundant PRIMARY KEY constraints
         for column_def in column_definitions {
-            if Self::is_redundant_primary_key(&column_def, &serial_columns) {
+            if Self::is_redundant_primary_key(&column_def, rowid_alias.as_deref()) {
                 // Skip this PRIMARY KEY constraint as it's already handled by SERIAL
                 continue;
             }
             
+            // SQLite has no EXCLUDE constraints; they become triggers once the table exists
+            if let Some(constraint) = ExclusionConstraint::from_definition(table_name, &column_def)? {
+                EXCLUSION_CONSTRAINTS.with(|xc| xc.borrow_mut().push(constraint));
+                continue;
+            }
+            
             let translated = Self::translate_column_definition(
                 &column_def,
                 table_name,
                 type_mapping,
-                conn
+                conn,
+                rowid_alias.as_deref()
             )?;
             sqlite_columns.push(translated);
         }
@@ -158,20 +224,67 @@ impl CreateTableTranslator {
         Ok(sqlite_columns.join(", "))
     }
     
-    /// Extract column name if this is a SERIAL column definition
+    /// Extract column name if this is a SERIAL or GENERATED ... AS IDENTITY column definition
     fn extract_serial_column_name(column_def: &str) -> Option<String> {
         let parts: Vec<&str> = column_def.split_whitespace().collect();
         if parts.len() >= 2 {
+            if Self::is_constraint_keyword(parts[0]) {
+                return None;
+            }
             let pg_type = parts[1].to_uppercase();
-            if pg_type == "SERIAL" || pg_type == "BIGSERIAL" {
+            if Self::is_serial_type(&pg_type) || IDENTITY_CLAUSE_REGEX.is_match(column_def) {
                 return Some(parts[0].to_string());
             }
         }
         None
     }
     
-    /// Check if this is a PRIMARY KEY constraint that references a SERIAL column
-    fn is_redundant_primary_key(column_def: &str, serial_columns: &std::collections::HashSet<String>) -> bool {
+    fn is_serial_type(pg_type: &str) -> bool {
+        SERIAL_TYPES.iter().any(|serial| serial.eq_ignore_ascii_case(pg_type))
+    }
+    
+    /// Pick the serial/identity column that becomes INTEGER PRIMARY KEY AUTOINCREMENT.
+    /// Returns None when the table's primary key is something else or there is none.
+    fn choose_rowid_alias(column_definitions: &[String], serial_columns: &[String]) -> Option<String> {
+        let is_serial = |name: &str| serial_columns.iter().any(|c| c.eq_ignore_ascii_case(name));
+        
+        for column_def in column_definitions {
+            let upper_def = column_def.trim().to_uppercase();
+            if upper_def.starts_with("PRIMARY KEY") || 
+               (upper_def.starts_with("CONSTRAINT") && upper_def.contains("PRIMARY KEY")) {
+                let start = column_def.find('(')?;
+                let end = column_def[start..].find(')')? + start;
+                let columns: Vec<&str> = column_def[start + 1..end].split(',').map(|c| c.trim()).collect();
+                return match columns.as_slice() {
+                    [single] if is_serial(single) => Some(single.to_string()),
+                    _ => None,
+                };
+            }
+        }
+        
+        for column_def in column_definitions {
+            let upper_def = column_def.to_uppercase();
+            if !Self::is_table_constraint(&upper_def) && upper_def.contains("PRIMARY KEY") {
+                let name = column_def.split_whitespace().next()?;
+                return if is_serial(name) { Some(name.to_string()) } else { None };
+            }
+        }
+        
+        // Without a declared primary key the table gets none, as in PostgreSQL
+        None
+    }
+    
+    fn is_table_constraint(upper_def: &str) -> bool {
+        let trimmed = upper_def.trim_start();
+        trimmed.starts_with("PRIMARY KEY")
+            || trimmed.starts_with("FOREIGN KEY")
+            || trimmed.starts_with("UNIQUE")
+            || trimmed.starts_with("CHECK")
+            || trimmed.starts_with("CONSTRAINT")
+    }
+    
+    /// Check if this is a PRIMARY KEY constraint that references the rowid alias column
+    fn is_redundant_primary_key(column_def: &str, rowid_alias: Option<&str>) -> bool {
         let upper_def = column_def.to_uppercase();
         if upper_def.trim().starts_with("PRIMARY KEY") {
             // Parse PRIMARY KEY (column_name) format
@@ -180,7 +293,7 @@ impl CreateTableTranslator {
                     let column_list = &column_def[start + 1..end];
                     let column_name = column_list.trim();
                     // Check if this references a SERIAL column (case-insensitive)
-                    return serial_columns.iter().any(|serial_col| serial_col.eq_ignore_ascii_case(column_name));
+                    return rowid_alias.is_some_and(|serial_col| serial_col.eq_ignore_ascii_case(column_name));
                 }
         }
         false
@@ -190,19 +303,24 @@ impl CreateTableTranslator {
         column_def: &str,
         table_name: &str,
         type_mapping: &mut HashMap<String, TypeMapping>,
-        conn: Option<&Connection>
+        conn: Option<&Connection>,
+        rowid_alias: Option<&str>
     ) -> Result<String, String> {
+        let column_def = &Self::translate_references(column_def);
+        
         // Handle constraints (PRIMARY KEY, FOREIGN KEY, etc.)
-        if column_def.to_uppercase().starts_with("PRIMARY KEY") 
-            || column_def.to_uppercase().starts_with("FOREIGN KEY")
-            || column_def.to_uppercase().starts_with("UNIQUE")
-            || column_def.to_uppercase().starts_with("CHECK")
-            || column_def.to_uppercase().starts_with("CONSTRAINT") {
+        if Self::is_table_constraint(&column_def.to_uppercase()) {
             return Ok(column_def.to_string());
         }
         
+        // Strip GENERATED ... AS IDENTITY, remembering which flavour it was
+        let identity_kind = IDENTITY_CLAUSE_REGEX.captures(column_def).map(|caps| {
+            caps.get(1).unwrap().as_str().split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+        });
+        let stripped_def = IDENTITY_CLAUSE_REGEX.replace(column_def, "");
+        
         // Parse column name and type
-        let parts: Vec<&str> = column_def.split_whitespace().collect();
+        let parts: Vec<&str> = stripped_def.split_whitespace().collect();
         if parts.is_empty() {
             return Ok(column_def.to_string());
         }
@@ -284,8 +402,27 @@ impl CreateTableTranslator {
             }
         }
         
+        let is_identity = identity_kind.is_some() || Self::is_serial_type(&pg_type);
+        let is_rowid_alias = is_identity && rowid_alias.is_some_and(|c| c.eq_ignore_ascii_case(column_name));
+        
         // Check if this is an array type first
-        let (sqlite_type, normalized_pg_type) = if is_array {
+        let (sqlite_type, normalized_pg_type) = if is_identity {
+            IDENTITY_COLUMNS.with(|ic| {
+                ic.borrow_mut().push(IdentityColumn {
+                    column_name: column_name.to_string(),
+                    kind: identity_kind.clone().unwrap_or_else(|| "serial".to_string()),
+                    is_rowid_alias,
+                });
+            });
+            
+            // Non-rowid identity columns are filled in by a trigger after the row is inserted
+            let sqlite_type = if is_rowid_alias {
+                "INTEGER PRIMARY KEY AUTOINCREMENT".to_string()
+            } else {
+                "INTEGER".to_string()
+            };
+            (sqlite_type, pg_type.clone())
+        } else if is_array {
             // Array types are stored as JSON TEXT
             let sqlite_type = "TEXT".to_string();
             
@@ -361,8 +498,7 @@ impl CreateTableTranslator {
             }
             
             // Special handling for SERIAL - skip PRIMARY KEY as it's included in the type translation
-            if (pg_type.to_uppercase() == "SERIAL" || pg_type.to_uppercase() == "BIGSERIAL")
-                && part.to_uppercase() == "PRIMARY" {
+            if is_rowid_alias && part.to_uppercase() == "PRIMARY" {
                     // Skip "PRIMARY" and check if next is "KEY"
                     if let Some(next_part) = parts.get(type_end_idx + i + 1)
                         && next_part.to_uppercase() == "KEY" {
@@ -371,6 +507,14 @@ impl CreateTableTranslator {
                     continue;
                 }
             
+            // Trigger-backed identity columns are NULL until the trigger assigns them
+            if is_identity && !is_rowid_alias && part.to_uppercase() == "NOT"
+                && let Some(next_part) = parts.get(type_end_idx + i + 1)
+                && next_part.to_uppercase() == "NULL" {
+                    skip_next = true;
+                    continue;
+                }
+            
             remaining_parts.push(*part);
         }
         
@@ -404,6 +548,16 @@ impl CreateTableTranslator {
         Ok(result)
     }
     
+    /// Make a REFERENCES clause one SQLite accepts. SQLite enforces the foreign key
+    /// and its ON DELETE/ON UPDATE actions itself, with the same meaning as PostgreSQL.
+    fn translate_references(definition: &str) -> String {
+        if !definition.to_uppercase().contains("REFERENCES") {
+            return definition.to_string();
+        }
+        let unqualified = REFERENCES_SCHEMA_REGEX.replace_all(definition, "REFERENCES ");
+        REFERENTIAL_ACTION_COLUMNS_REGEX.replace_all(&unqualified, "$1").into_owned()
+    }
+    
     fn is_multiword_type_start(type_str: &str) -> bool {
         let start_patterns = [
             "TIMESTAMP WITH", "TIMESTAMP WITHOUT", "TIME WITH", "TIME WITHOUT",
@@ -592,6 +746,14 @@ mod tests {
         assert_eq!(mappings["test.data"].pg_type, "VARCHAR");
     }
     
+    #[test]
+    fn test_translate_oids_clauses() {
+        for clause in ["WITH OIDS", "without oids", "WITH (oids=false)", "WITH (OIDS)"] {
+            let (sql, _) = CreateTableTranslator::translate(&format!("CREATE TABLE legacy (id INTEGER) {clause}")).unwrap();
+            assert_eq!(sql, "CREATE TABLE legacy (id INTEGER)", "{clause}");
+        }
+    }
+    
     #[test]
     fn test_mixed_case_types() {
         let sql = "CREATE TABLE test (
@@ -607,6 +769,16 @@ mod tests {
         assert_eq!(mappings["test.col3"].type_modifier, Some(5));
     }
     
+    #[test]
+    fn test_translate_references() {
+        let (sql, _) = CreateTableTranslator::translate(
+            "CREATE TABLE orders (id SERIAL PRIMARY KEY, customer_id INTEGER NOT NULL REFERENCES public.customers(id) ON DELETE CASCADE, \
+             region TEXT, CONSTRAINT orders_region_fk FOREIGN KEY (region) REFERENCES \"public\".regions (code) ON DELETE SET NULL (region) DEFERRABLE INITIALLY DEFERRED)"
+        ).unwrap();
+        assert_eq!(sql, "CREATE TABLE orders (id INTEGER PRIMARY KEY AUTOINCREMENT, customer_id INTEGER NOT NULL REFERENCES customers(id) ON DELETE CASCADE, \
+            region TEXT, CONSTRAINT orders_region_fk FOREIGN KEY (region) REFERENCES regions (code) ON DELETE SET NULL DEFERRABLE INITIALLY DEFERRED)");
+    }
+    
     #[test]
     fn test_parse_array_type() {
         // Test simple array types
@@ -688,4 +860,81 @@ mod tests {
         assert!(!result.sql.contains("DEFAULT now()"), 
                 "Found 'DEFAULT now()' which should have been translated: {}", result.sql);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_translate_serial_variants() {
+        let sql = "CREATE TABLE counters (
+            id SMALLSERIAL PRIMARY KEY,
+            name TEXT
+        )";
+        
+        let result = CreateTableTranslator::translate_with_connection_full(sql, None).unwrap();
+        
+        assert!(result.sql.contains("id INTEGER PRIMARY KEY AUTOINCREMENT"), "got: {}", result.sql);
+        assert!(!result.sql.contains("PRIMARY KEY AUTOINCREMENT PRIMARY KEY"));
+        assert_eq!(result.type_mappings["counters.id"].pg_type, "SMALLSERIAL");
+        assert_eq!(result.identity_columns, vec![IdentityColumn {
+            column_name: "id".to_string(),
+            kind: "serial".to_string(),
+            is_rowid_alias: true,
+        }]);
+    }
+    
+    #[test]
+    fn test_translate_identity_columns() {
+        let sql = "CREATE TABLE events (
+            id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+            payload TEXT
+        )";
+        
+        let result = CreateTableTranslator::translate_with_connection_full(sql, None).unwrap();
+        
+        assert!(result.sql.contains("id INTEGER PRIMARY KEY AUTOINCREMENT"), "got: {}", result.sql);
+        assert!(!result.sql.to_uppercase().contains("GENERATED"));
+        assert_eq!(result.type_mappings["events.id"].pg_type, "BIGINT");
+        assert_eq!(result.identity_columns[0].kind, "always");
+        assert!(result.identity_columns[0].is_rowid_alias);
+        
+        let sql = "CREATE TABLE events (
+            id INTEGER GENERATED BY DEFAULT AS IDENTITY (START WITH 10 INCREMENT BY 1),
+            payload TEXT,
+            PRIMARY KEY (id)
+        )";
+        
+        let result = CreateTableTranslator::translate_with_connection_full(sql, None).unwrap();
+        
+        assert!(result.sql.contains("id INTEGER PRIMARY KEY AUTOINCREMENT"), "got: {}", result.sql);
+        assert!(!result.sql.contains("PRIMARY KEY (id)"));
+        assert_eq!(result.identity_columns[0].kind, "by default");
+    }
+    
+    #[test]
+    fn test_translate_identity_without_primary_key() {
+        // Only the primary key can be SQLite's rowid alias, other identity columns are trigger-backed
+        let sql = "CREATE TABLE tickets (
+            code TEXT PRIMARY KEY,
+            seq SERIAL NOT NULL,
+            other_seq BIGSERIAL
+        )";
+        
+        let result = CreateTableTranslator::translate_with_connection_full(sql, None).unwrap();
+        
+        assert!(!result.sql.contains("AUTOINCREMENT"), "got: {}", result.sql);
+        assert!(result.sql.contains("seq INTEGER,"), "got: {}", result.sql);
+        assert!(result.sql.contains("code TEXT PRIMARY KEY"));
+        assert_eq!(result.identity_columns.len(), 2);
+        assert!(result.identity_columns.iter().all(|c| !c.is_rowid_alias));
+    }
+
+    #[test]
+    fn test_translate_temp_table() {
+        let sql = "CREATE TEMPORARY TABLE staging (id SERIAL PRIMARY KEY, amount NUMERIC(10,2))";
+        let result = CreateTableTranslator::translate_with_connection_full(sql, None).unwrap();
+        assert!(result.sql.starts_with("CREATE TEMP TABLE staging ("), "got: {}", result.sql);
+        assert_eq!(result.type_mappings.len(), 2);
+
+        assert!(CreateTableTranslator::is_create_table("create local temp table t (a int)"));
+        assert!(!CreateTableTranslator::is_create_table("CREATE TEMP VIEW v AS SELECT 1"));
+        assert_eq!(CreateTableTranslator::created_table_name("CREATE TEMP TABLE IF NOT EXISTS \"Staging\"(a int)").as_deref(), Some("Staging"));
+    }
+}
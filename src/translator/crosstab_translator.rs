@@ -0,0 +1,111 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Translator for tablefunc's crosstab()
+///
+/// `crosstab('source sql' [, 'category sql']) AS ct(row_name text, a int, b int)` becomes a
+/// subquery over json_each(pg_crosstab(...)) that extracts one column per entry of the
+/// column definition list. The source and category queries run as SQLite SQL.
+pub struct CrosstabTranslator;
+
+/// crosstab('...' [, '...']) [AS] alias(column definitions); arguments may be parameters
+static CROSSTAB_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)\bcrosstab\s*\(\s*('(?:[^']|'')*'|\$\d+)\s*(?:,\s*('(?:[^']|'')*'|\$\d+)\s*)?\)\s+(?:AS\s+)?(\w+)\s*\(((?:[^()]|\([^()]*\))*)\)").unwrap()
+});
+
+impl CrosstabTranslator {
+    /// Check if the query calls crosstab()
+    pub fn needs_translation(query: &str) -> bool {
+        query.as_bytes().windows(8).any(|w| w.eq_ignore_ascii_case(b"crosstab")) && CROSSTAB_REGEX.is_match(query)
+    }
+
+    /// Replace each crosstab() call in FROM with a json_each() subquery
+    pub fn translate_query(query: &str) -> String {
+        if !Self::needs_translation(query) {
+            return query.to_string();
+        }
+
+        CROSSTAB_REGEX.replace_all(query, |caps: &regex::Captures| {
+            let columns = Self::split_columns(&caps[4]);
+            let arguments = match caps.get(2) {
+                Some(category_sql) => format!("{}, {}, {}", &caps[1], category_sql.as_str(), columns.len()),
+                None => format!("{}, {}", &caps[1], columns.len()),
+            };
+
+            let select_list = columns.iter().enumerate()
+                .map(|(i, (name, pg_type))| {
+                    let extract = format!("json_extract(value, '$[{i}]')");
+                    match Self::storage_class(pg_type) {
+                        Some(class) => format!("CAST({extract} AS {class}) AS {name}"),
+                        None => format!("{extract} AS {name}"),
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            format!("(SELECT {select_list} FROM json_each(pg_crosstab({arguments})) ORDER BY key) AS {}", &caps[3])
+        }).into_owned()
+    }
+
+    /// Split the column definition list into (name, type) pairs, keeping NUMERIC(10, 2) whole
+    fn split_columns(definitions: &str) -> Vec<(String, String)> {
+        let mut columns = Vec::new();
+        let mut depth = 0;
+        let mut start = 0;
+        let mut push = |definition: &str| {
+            let definition = definition.trim();
+            if !definition.is_empty() {
+                let (name, pg_type) = definition.split_once(char::is_whitespace).unwrap_or((definition, ""));
+                columns.push((name.to_string(), pg_type.trim().to_lowercase()));
+            }
+        };
+        for (i, c) in definitions.char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                ',' if depth == 0 => {
+                    push(&definitions[start..i]);
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        push(&definitions[start..]);
+        columns
+    }
+
+    /// The SQLite type the pivoted values are cast to, so text values land in integer columns
+    fn storage_class(pg_type: &str) -> Option<&'static str> {
+        let base_type = pg_type.split('(').next().unwrap_or(pg_type).trim();
+        match base_type {
+            "smallint" | "int2" | "integer" | "int" | "int4" | "bigint" | "int8" => Some("INTEGER"),
+            "real" | "float4" | "double precision" | "float8" => Some("REAL"),
+            "text" | "varchar" | "character varying" | "char" | "character" | "name" => Some("TEXT"),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crosstab_translation() {
+        assert_eq!(
+            CrosstabTranslator::translate_query(
+                "SELECT * FROM crosstab('SELECT region, quarter, amount FROM sales ORDER BY 1, 2') AS ct(region text, q1 int, q2 numeric(10, 2))"
+            ),
+            "SELECT * FROM (SELECT CAST(json_extract(value, '$[0]') AS TEXT) AS region, CAST(json_extract(value, '$[1]') AS INTEGER) AS q1, \
+             json_extract(value, '$[2]') AS q2 FROM json_each(pg_crosstab('SELECT region, quarter, amount FROM sales ORDER BY 1, 2', 3)) ORDER BY key) AS ct"
+        );
+        assert_eq!(
+            CrosstabTranslator::translate_query("SELECT * FROM crosstab($1, 'SELECT ''q1''') ct(r text, q1 int)"),
+            "SELECT * FROM (SELECT CAST(json_extract(value, '$[0]') AS TEXT) AS r, CAST(json_extract(value, '$[1]') AS INTEGER) AS q1 \
+             FROM json_each(pg_crosstab($1, 'SELECT ''q1''', 2)) ORDER BY key) AS ct"
+        );
+
+        let query = "SELECT crosstab FROM reports";
+        assert_eq!(CrosstabTranslator::translate_query(query), query);
+    }
+}
@@ -0,0 +1,288 @@
+use rusqlite::{Connection, OptionalExtension};
+use sqlparser::ast::{Expr, ObjectNamePart, SelectItem, SelectItemQualifiedWildcardKind, SetExpr, SetOperator, Statement, TableFactor};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use crate::error::PgError;
+use crate::rewriter::ExpressionTypeResolver;
+use crate::types::PgType;
+use crate::PgSqliteError;
+use super::values_translator::{find_closing_paren, identifier_end, keyword_at, skip_whitespace};
+use super::{ColumnTypeHint, ExpressionType, TranslationMetadata};
+
+/// Translator for `WITH` queries
+///
+/// SQLite runs common table expressions natively, but differs from PostgreSQL in a
+/// few places:
+///
+/// - `AS [NOT] MATERIALIZED` is a planner hint, dropped so older SQLite parsers accept it.
+/// - SQLite treats any CTE reading itself as recursive, with or without `RECURSIVE`, and
+///   accepts recursive forms PostgreSQL rejects. These are reported with PostgreSQL's
+///   errors instead of silently returning different rows.
+/// - SQLite result columns carry no type, so the types of columns projected out of a
+///   CTE are resolved through its query and handed on as translation metadata.
+pub struct CteTranslator;
+
+/// Where the parts of a `WITH` clause are in the query text
+struct WithClause {
+    /// `MATERIALIZED ` / `NOT MATERIALIZED ` after each CTE's `AS`
+    hints: Vec<std::ops::Range<usize>>,
+    /// Start of the statement the CTEs belong to
+    main: usize,
+}
+
+impl CteTranslator {
+    /// Check if the query starts with a WITH clause
+    pub fn needs_translation(query: &str) -> bool {
+        keyword_at(query, skip_whitespace(query, 0), "WITH").is_some()
+    }
+
+    /// Whether the statement after the WITH clause returns rows like a SELECT does
+    pub fn is_select(query: &str) -> bool {
+        parse_with(query).is_some_and(|with| {
+            keyword_at(query, with.main, "SELECT").is_some() || keyword_at(query, with.main, "VALUES").is_some()
+        })
+    }
+
+    /// Remove MATERIALIZED hints
+    pub fn translate_query(query: &str) -> String {
+        let Some(with) = parse_with(query) else {
+            return query.to_string();
+        };
+        let mut result = query.to_string();
+        for hint in with.hints.into_iter().rev() {
+            result.replace_range(hint, "");
+        }
+        result
+    }
+
+    /// Check that recursive CTEs have a form PostgreSQL accepts, and collect the
+    /// types of the result columns that come out of CTEs
+    pub fn analyze(query: &str, conn: &Connection) -> Result<TranslationMetadata, PgSqliteError> {
+        let mut metadata = TranslationMetadata::new();
+        // Queries sqlparser cannot read are left to SQLite
+        let Ok(statements) = Parser::parse_sql(&PostgreSqlDialect {}, query) else {
+            return Ok(metadata);
+        };
+        let Some(Statement::Query(query)) = statements.first() else {
+            return Ok(metadata);
+        };
+        let Some(with) = &query.with else {
+            return Ok(metadata);
+        };
+
+        for cte in &with.cte_tables {
+            let name = &cte.alias.name.value;
+            if !references(&cte.query.body, name) {
+                continue;
+            }
+            if !with.recursive {
+                // Without RECURSIVE PostgreSQL reads a table of that name, where SQLite
+                // would recurse or report a circular reference
+                return Err(if table_exists(conn, name)? {
+                    pg_error("0A000", format!("WITH query \"{name}\" reading the table of the same name is not supported, rename the WITH query"))
+                } else {
+                    pg_error("42P01", format!("relation \"{name}\" does not exist"))
+                });
+            }
+            match &*cte.query.body {
+                SetExpr::SetOperation { op: SetOperator::Union, left, .. } => {
+                    // UNION's left operand is the non-recursive term. SQLite would also take
+                    // further UNIONed recursive terms, which PostgreSQL groups into the left one.
+                    if references(left, name) {
+                        return Err(pg_error("42P19", format!("recursive reference to query \"{name}\" must not appear within its non-recursive term")));
+                    }
+                }
+                _ => {
+                    return Err(pg_error("42P19", format!("recursive query \"{name}\" does not have the form non-recursive-term UNION [ALL] recursive-term")));
+                }
+            }
+        }
+
+        let mut resolver = ExpressionTypeResolver::new(conn);
+        let context = resolver.build_context(query);
+        let Some(select) = ExpressionTypeResolver::leftmost_select(&query.body) else {
+            return Ok(metadata);
+        };
+        for item in &select.projection {
+            let columns = match item {
+                // Only plain column references keep their name, SQLite names other
+                // expressions after their text
+                SelectItem::UnnamedExpr(expr @ (Expr::Identifier(_) | Expr::CompoundIdentifier(_))) => {
+                    let pg_type = resolver.resolve_expr_type(expr, &context);
+                    resolver.extract_column_name(expr).map(|name| (name, pg_type)).into_iter().collect()
+                }
+                SelectItem::ExprWithAlias { expr, alias } => {
+                    vec![(alias.value.clone(), resolver.resolve_expr_type(expr, &context))]
+                }
+                SelectItem::Wildcard(_) => context.default_table.as_ref()
+                    .and_then(|table| context.cte_columns.get(table))
+                    .cloned()
+                    .unwrap_or_default(),
+                SelectItem::QualifiedWildcard(SelectItemQualifiedWildcardKind::ObjectName(name), _) => {
+                    let alias = name.0.last().map(|ObjectNamePart::Identifier(ident)| ident.value.clone()).unwrap_or_default();
+                    let table = context.table_aliases.get(&alias).unwrap_or(&alias);
+                    context.cte_columns.get(table).cloned().unwrap_or_default()
+                }
+                _ => Vec::new(),
+            };
+            // Text is what the column would be described as anyway
+            for (name, pg_type) in columns {
+                if pg_type != PgType::Text {
+                    metadata.add_hint(name, ColumnTypeHint::expression(None, pg_type, ExpressionType::Other));
+                }
+            }
+        }
+        Ok(metadata)
+    }
+
+    /// The type of a column of one of the query's CTEs
+    pub fn column_type(query: &str, column: &str, conn: &Connection) -> Option<PgType> {
+        let statements = Parser::parse_sql(&PostgreSqlDialect {}, query).ok()?;
+        let Some(Statement::Query(query)) = statements.first() else {
+            return None;
+        };
+        let context = ExpressionTypeResolver::new(conn).build_context(query);
+        context.cte_columns.values().flatten()
+            .find(|(name, _)| name.eq_ignore_ascii_case(column))
+            .map(|(_, pg_type)| *pg_type)
+    }
+}
+
+/// Find the CTEs of a leading WITH clause
+fn parse_with(query: &str) -> Option<WithClause> {
+    let bytes = query.as_bytes();
+    let mut pos = skip_whitespace(query, keyword_at(query, skip_whitespace(query, 0), "WITH")?);
+    if let Some(end) = keyword_at(query, pos, "RECURSIVE") {
+        pos = skip_whitespace(query, end);
+    }
+
+    let mut hints = Vec::new();
+    loop {
+        // name [(columns)] AS [[NOT] MATERIALIZED] (query)
+        pos = skip_whitespace(query, identifier_end(query, pos)?);
+        if bytes.get(pos) == Some(&b'(') {
+            pos = skip_whitespace(query, find_closing_paren(bytes, pos)? + 1);
+        }
+        pos = skip_whitespace(query, keyword_at(query, pos, "AS")?);
+        let hint_start = pos;
+        let after_not = keyword_at(query, pos, "NOT").map_or(pos, |end| skip_whitespace(query, end));
+        if let Some(end) = keyword_at(query, after_not, "MATERIALIZED") {
+            pos = skip_whitespace(query, end);
+            hints.push(hint_start..pos);
+        }
+        if bytes.get(pos) != Some(&b'(') {
+            return None;
+        }
+        pos = skip_whitespace(query, find_closing_paren(bytes, pos)? + 1);
+        if bytes.get(pos) == Some(&b',') {
+            pos = skip_whitespace(query, pos + 1);
+        } else {
+            return Some(WithClause { hints, main: pos });
+        }
+    }
+}
+
+/// Whether a query body reads the relation `name` in a FROM clause
+fn references(body: &SetExpr, name: &str) -> bool {
+    match body {
+        SetExpr::Select(select) => select.from.iter().any(|table| {
+            relation_references(&table.relation, name)
+                || table.joins.iter().any(|join| relation_references(&join.relation, name))
+        }),
+        SetExpr::Query(query) => references(&query.body, name),
+        SetExpr::SetOperation { left, right, .. } => references(left, name) || references(right, name),
+        _ => false,
+    }
+}
+
+fn relation_references(relation: &TableFactor, name: &str) -> bool {
+    match relation {
+        // A schema-qualified name is never a CTE
+        TableFactor::Table { name: table, .. } => {
+            matches!(table.0.as_slice(), [ObjectNamePart::Identifier(ident)] if ident.value.eq_ignore_ascii_case(name))
+        }
+        TableFactor::Derived { subquery, .. } => references(&subquery.body, name),
+        TableFactor::NestedJoin { table_with_joins, .. } => {
+            relation_references(&table_with_joins.relation, name)
+                || table_with_joins.joins.iter().any(|join| relation_references(&join.relation, name))
+        }
+        _ => false,
+    }
+}
+
+fn table_exists(conn: &Connection, name: &str) -> Result<bool, PgSqliteError> {
+    let found = conn.query_row(
+        "SELECT 1 FROM (SELECT name, type FROM sqlite_master UNION ALL SELECT name, type FROM sqlite_temp_master) WHERE type IN ('table', 'view') AND name = ?1 COLLATE NOCASE",
+        [name],
+        |_| Ok(()),
+    ).optional()?;
+    Ok(found.is_some())
+}
+
+fn pg_error(code: &str, message: String) -> PgSqliteError {
+    PgSqliteError::Validation(PgError::Generic { code: code.to_string(), message })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code(result: Result<TranslationMetadata, PgSqliteError>) -> String {
+        match result {
+            Err(PgSqliteError::Validation(PgError::Generic { code, .. })) => code,
+            other => panic!("expected a validation error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_strip_materialized_hints() {
+        assert_eq!(
+            CteTranslator::translate_query("WITH a AS MATERIALIZED (SELECT 1), b(x) AS NOT  MATERIALIZED (SELECT 2) SELECT * FROM a, b"),
+            "WITH a AS (SELECT 1), b(x) AS (SELECT 2) SELECT * FROM a, b"
+        );
+        assert_eq!(
+            CteTranslator::translate_query("WITH a AS (SELECT 'AS MATERIALIZED (') SELECT * FROM a"),
+            "WITH a AS (SELECT 'AS MATERIALIZED (') SELECT * FROM a"
+        );
+    }
+
+    #[test]
+    fn test_main_statement() {
+        assert!(CteTranslator::is_select("WITH RECURSIVE t(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM t) SELECT n FROM t"));
+        assert!(CteTranslator::is_select("with a as materialized (select 1) values (2)"));
+        assert!(!CteTranslator::is_select("WITH a AS (SELECT 1) INSERT INTO t SELECT * FROM a"));
+        assert!(!CteTranslator::is_select("SELECT 1"));
+    }
+
+    #[test]
+    fn test_recursive_forms() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE items (id INTEGER)", []).unwrap();
+        let analyze = |query: &str| CteTranslator::analyze(query, &conn);
+
+        assert!(analyze("WITH RECURSIVE t(n) AS (SELECT 1 UNION SELECT n + 1 FROM t WHERE n < 5) SELECT n FROM t").is_ok());
+        assert!(analyze("WITH RECURSIVE t(n) AS (SELECT 1 UNION ALL SELECT 2 UNION ALL SELECT n + 1 FROM t WHERE n < 5) SELECT n FROM t").is_ok());
+        assert_eq!(code(analyze("WITH RECURSIVE t(n) AS (SELECT n FROM t) SELECT n FROM t")), "42P19");
+        assert_eq!(code(analyze("WITH RECURSIVE t(n) AS (SELECT 1 INTERSECT SELECT n FROM t) SELECT n FROM t")), "42P19");
+        assert_eq!(code(analyze("WITH RECURSIVE t(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM t UNION ALL SELECT n + 2 FROM t) SELECT n FROM t")), "42P19");
+        assert_eq!(code(analyze("WITH t(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM t WHERE n < 5) SELECT n FROM t")), "42P01");
+        assert_eq!(code(analyze("WITH items AS (SELECT id FROM items) SELECT id FROM items")), "0A000");
+        assert!(analyze("WITH items AS (SELECT id FROM public.items) SELECT id FROM items").is_ok());
+    }
+
+    #[test]
+    fn test_column_types() {
+        let conn = Connection::open_in_memory().unwrap();
+        let metadata = CteTranslator::analyze(
+            "WITH RECURSIVE t(n, flag) AS (SELECT 1, true UNION ALL SELECT n + 1, flag FROM t WHERE n < 5),
+                  u AS (SELECT n * 1.5 AS half, n FROM t)
+             SELECT t.n, flag, half AS h, u.* FROM t JOIN u ON t.n = u.n",
+            &conn,
+        ).unwrap();
+        let hinted = |name: &str| metadata.get_hint(name).and_then(|hint| hint.suggested_type);
+        assert_eq!(hinted("n"), Some(PgType::Int4));
+        assert_eq!(hinted("flag"), Some(PgType::Bool));
+        assert_eq!(hinted("h"), Some(PgType::Numeric));
+        assert_eq!(hinted("half"), Some(PgType::Numeric));
+    }
+}
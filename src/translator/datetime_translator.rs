@@ -1,5 +1,6 @@
 use regex::Regex;
 use once_cell::sync::Lazy;
+use crate::types::Interval;
 
 /// Translates PostgreSQL datetime functions to our custom SQLite functions
 pub struct DateTimeTranslator;
@@ -43,6 +44,20 @@ static AGE_PATTERN: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r"(?i)\bAGE\s*\(").unwrap()
 });
 
+static INTERVAL_LITERAL_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\bINTERVAL\s+'([^']+)'").unwrap()
+});
+
+static INTERVAL_ARITHMETIC_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    // Captures: (1) left operand, (2) operator, (3) interval text
+    Regex::new(r"(?i)(\bINTERVAL\s+'[^']*'|'[^']*'|[\w.]+(?:\s*\([^()]*\))?)\s*([+-])\s*INTERVAL\s+'([^']+)'").unwrap()
+});
+
+// Sources that EXTRACT reads as an interval rather than a timestamp
+static INTERVAL_SOURCE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^(INTERVAL\s+'|AGE\s*\(|JUSTIFY_(HOURS|DAYS|INTERVAL)\s*\()").unwrap()
+});
+
 static AT_TIME_ZONE_PATTERN: Lazy<Regex> = Lazy::new(|| {
     // Match: expression AT TIME ZONE 'timezone' [as alias]
     // Captures: (1) expression, (2) timezone, (3) optional alias
@@ -137,10 +152,15 @@ impl DateTimeTranslator {
         }).to_string();
         
         // Handle EXTRACT(field FROM timestamp) -> extract(field, timestamp)
+        // and EXTRACT(field FROM interval) -> interval_part(field, interval)
         result = EXTRACT_PATTERN.replace_all(&result, |caps: &regex::Captures| {
             let field = &caps[1];
-            let timestamp = &caps[2];
-            format!("extract('{}', {})", field.to_lowercase(), timestamp.trim())
+            let source = caps[2].trim();
+            if INTERVAL_SOURCE_PATTERN.is_match(source) {
+                format!("interval_part('{}', {})", field.to_lowercase(), source)
+            } else {
+                format!("extract('{}', {})", field.to_lowercase(), source)
+            }
         }).to_string();
         
         // Handle DATE_TRUNC('field', timestamp) -> date_trunc('field', timestamp)
@@ -150,78 +170,51 @@ impl DateTimeTranslator {
             format!("date_trunc('{}', {})", field.to_lowercase(), timestamp.trim())
         }).to_string();
         
-        // Handle INTERVAL literals (basic support)
+        // Handle timestamp arithmetic with intervals that needs calendar months
+        result = Self::translate_interval_arithmetic(&result);
+        
+        // Handle INTERVAL literals
         result = Self::translate_interval_literals(&result);
         
         // Handle AT TIME ZONE operator
         let at_time_zone_metadata = Self::translate_at_time_zone_with_metadata(&mut result);
         metadata.merge(at_time_zone_metadata);
         
-        // Handle timestamp arithmetic with intervals
-        result = Self::translate_interval_arithmetic(&result);
-        
         (result, metadata)
     }
     
-    /// Translate INTERVAL literals to microseconds
+    /// Translate INTERVAL literals to their storage form, the quoted interval text
     fn translate_interval_literals(query: &str) -> String {
-        let interval_pattern = Regex::new(r"(?i)INTERVAL\s+'([^']+)'").unwrap();
-        
-        interval_pattern.replace_all(query, |caps: &regex::Captures| {
-            let interval_str = &caps[1];
-            if let Some(microseconds) = Self::parse_interval_to_seconds(interval_str) {
-                format!("{microseconds:.0}")
-            } else {
+        INTERVAL_LITERAL_PATTERN.replace_all(query, |caps: &regex::Captures| {
+            match Interval::parse(&caps[1]) {
+                Ok(interval) => interval.to_sql_literal(),
                 // If we can't parse it, leave it as is
-                caps[0].to_string()
+                Err(_) => caps[0].to_string(),
             }
         }).to_string()
     }
     
-    /// Parse common interval formats to microseconds
-    fn parse_interval_to_seconds(interval: &str) -> Option<f64> {
-        let parts: Vec<&str> = interval.split_whitespace().collect();
-        
-        if parts.len() >= 2 {
-            if let Ok(value) = parts[0].parse::<f64>() {
-                match parts[1].to_lowercase().as_str() {
-                    "second" | "seconds" | "sec" | "secs" => Some(value * 1_000_000.0),
-                    "minute" | "minutes" | "min" | "mins" => Some(value * 60.0 * 1_000_000.0),
-                    "hour" | "hours" | "hr" | "hrs" => Some(value * 3600.0 * 1_000_000.0),
-                    "day" | "days" => Some(value * 86400.0 * 1_000_000.0),
-                    "week" | "weeks" => Some(value * 604800.0 * 1_000_000.0),
-                    "month" | "months" | "mon" | "mons" => Some(value * 2592000.0 * 1_000_000.0), // 30 days
-                    "year" | "years" | "yr" | "yrs" => Some(value * 31536000.0 * 1_000_000.0), // 365 days
-                    _ => None
-                }
-            } else {
-                None
-            }
-        } else {
-            None
-        }
-    }
-    
     /// Translate interval arithmetic (timestamp + interval, etc.)
+    ///
+    /// `x ± INTERVAL '...'` goes through interval_add(), which keeps the representation
+    /// of x: INTEGER microseconds, timestamp text such as now(), or interval text.
+    /// interval + interval literals are folded into a single literal.
     fn translate_interval_arithmetic(query: &str) -> String {
-        // This is a simplified version - full interval arithmetic is complex
-        let mut result = query.to_string();
-        
-        // Handle simple cases like "timestamp + INTERVAL '1 day'"
-        let arithmetic_pattern = Regex::new(r"(?i)(\w+)\s*([+-])\s*INTERVAL\s+'([^']+)'").unwrap();
-        result = arithmetic_pattern.replace_all(&result, |caps: &regex::Captures| {
-            let column = &caps[1];
-            let operator = &caps[2];
-            let interval_str = &caps[3];
+        INTERVAL_ARITHMETIC_PATTERN.replace_all(query, |caps: &regex::Captures| {
+            let operand = &caps[1];
+            let Ok(interval) = Interval::parse(&caps[3]) else {
+                return caps[0].to_string();
+            };
+            let interval = if &caps[2] == "-" { interval.negate() } else { interval };
             
-            if let Some(microseconds) = Self::parse_interval_to_seconds(interval_str) {
-                format!("({column} {operator} {microseconds:.0})")
-            } else {
-                caps[0].to_string()
+            if let Some(left) = INTERVAL_LITERAL_PATTERN.captures(operand) {
+                return match Interval::parse(&left[1]) {
+                    Ok(left) => left.add(&interval).to_sql_literal(),
+                    Err(_) => caps[0].to_string(),
+                };
             }
-        }).to_string();
-        
-        result
+            format!("interval_add({operand}, '{interval}')")
+        }).to_string()
     }
     
     
@@ -343,7 +336,7 @@ mod tests {
         // Test INTERVAL translation
         assert_eq!(
             DateTimeTranslator::translate_query("SELECT created_at + INTERVAL '1 day'"),
-            "SELECT created_at + 86400000000"
+            "SELECT interval_add(created_at, '1 day')"
         );
         
         // Test AT TIME ZONE removal
@@ -355,12 +348,41 @@ mod tests {
     
     #[test]
     fn test_interval_parsing() {
-        assert_eq!(DateTimeTranslator::parse_interval_to_seconds("1 second"), Some(1_000_000.0));
-        assert_eq!(DateTimeTranslator::parse_interval_to_seconds("2 minutes"), Some(120_000_000.0));
-        assert_eq!(DateTimeTranslator::parse_interval_to_seconds("3 hours"), Some(10_800_000_000.0));
-        assert_eq!(DateTimeTranslator::parse_interval_to_seconds("1 day"), Some(86_400_000_000.0));
-        assert_eq!(DateTimeTranslator::parse_interval_to_seconds("1 week"), Some(604_800_000_000.0));
-        assert_eq!(DateTimeTranslator::parse_interval_to_seconds("1 month"), Some(2_592_000_000_000.0));
-        assert_eq!(DateTimeTranslator::parse_interval_to_seconds("1 year"), Some(31_536_000_000_000.0));
+        assert_eq!(DateTimeTranslator::translate_query("SELECT INTERVAL '1 second'"), "SELECT '00:00:01'");
+        assert_eq!(DateTimeTranslator::translate_query("SELECT INTERVAL '2 minutes'"), "SELECT '00:02:00'");
+        assert_eq!(DateTimeTranslator::translate_query("SELECT INTERVAL '3 hours'"), "SELECT '03:00:00'");
+        assert_eq!(DateTimeTranslator::translate_query("SELECT INTERVAL '1 day'"), "SELECT '1 day'");
+        assert_eq!(DateTimeTranslator::translate_query("SELECT INTERVAL '1 week'"), "SELECT '7 days'");
+        // Months are calendar months, kept apart from days
+        assert_eq!(DateTimeTranslator::translate_query("SELECT INTERVAL '1 month'"), "SELECT '1 mon'");
+        assert_eq!(DateTimeTranslator::translate_query("SELECT INTERVAL '1 year 2 months 3 days'"), "SELECT '1 year 2 mons 3 days'");
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_interval_arithmetic() {
+        assert_eq!(
+            DateTimeTranslator::translate_query("SELECT created_at - INTERVAL '1 month' FROM t"),
+            "SELECT interval_add(created_at, '-1 mons') FROM t"
+        );
+        assert_eq!(
+            DateTimeTranslator::translate_query("SELECT NOW() - INTERVAL '2 hours'"),
+            "SELECT interval_add(now(), '-02:00:00')"
+        );
+        assert_eq!(
+            DateTimeTranslator::translate_query("SELECT id FROM t WHERE created_at > now() - INTERVAL '1 day'"),
+            "SELECT id FROM t WHERE created_at > interval_add(now(), '-1 days')"
+        );
+        assert_eq!(
+            DateTimeTranslator::translate_query("SELECT INTERVAL '1 year' + INTERVAL '6 months'"),
+            "SELECT '1 year 6 mons'"
+        );
+        assert_eq!(
+            DateTimeTranslator::translate_query("SELECT EXTRACT(DAY FROM INTERVAL '3 days 4 hours')"),
+            "SELECT interval_part('day', '3 days 04:00:00')"
+        );
+        assert_eq!(
+            DateTimeTranslator::translate_query("SELECT EXTRACT(MONTH FROM age(a, b)) FROM t"),
+            "SELECT interval_part('month', age(a, b)) FROM t"
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use sqlparser::ast::{Expr, Ident, Query, SelectItem, SetExpr, Statement, TableFactor, Values};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use tracing::debug;
+
+/// Translator for SQLAlchemy's "insertmanyvalues" INSERT form
+///
+/// When RETURNING rows must come back in parameter order, SQLAlchemy batches
+/// inserts as
+///
+/// ```sql
+/// INSERT INTO t (a, b) SELECT p0::VARCHAR, CAST(p1 AS TIMESTAMP WITHOUT TIME ZONE)
+/// FROM (VALUES ($1, $2, 0), ($3, $4, 1)) AS imp_sen(p0, p1, sen_counter)
+/// ORDER BY sen_counter RETURNING t.id
+/// ```
+///
+/// SQLite cannot run the casts and SQLite's own VALUES already preserves row order,
+/// so the statement is rewritten on the AST to the equivalent plain multi-row INSERT
+/// `INSERT INTO t (a, b) VALUES ($1, $2), ($3, $4) RETURNING t.id`. Parameter numbers
+/// are left untouched, so the rewrite is valid before or after parameter binding and
+/// the regular INSERT handling (parameter types, datetime conversion) applies.
+pub struct InsertManyValuesTranslator;
+
+impl InsertManyValuesTranslator {
+    /// Cheap textual check before parsing
+    pub fn needs_translation(query: &str) -> bool {
+        let trimmed = query.trim_start();
+        trimmed.get(..6).is_some_and(|s| s.eq_ignore_ascii_case("INSERT"))
+            && query.contains("(VALUES")
+            && query.as_bytes().windows(6).any(|w| w.eq_ignore_ascii_case(b"SELECT"))
+    }
+
+    /// Rewrite the statement, or return None if it is not the insertmanyvalues shape
+    pub fn translate_query(query: &str) -> Option<String> {
+        if !Self::needs_translation(query) {
+            return None;
+        }
+
+        let mut statements = Parser::parse_sql(&PostgreSqlDialect {}, query).ok()?;
+        if statements.len() != 1 {
+            return None;
+        }
+        let Statement::Insert(insert) = &mut statements[0] else {
+            return None;
+        };
+        let source = insert.source.as_mut()?;
+        let rows = Self::rewrite_rows(source)?;
+        if !insert.columns.is_empty() && rows.first().is_some_and(|row| row.len() != insert.columns.len()) {
+            return None;
+        }
+
+        *source.body = SetExpr::Values(Values { explicit_row: false, rows });
+        source.order_by = None;
+
+        let rewritten = statements[0].to_string();
+        debug!("Rewrote insertmanyvalues INSERT: {} -> {}", query, rewritten);
+        Some(rewritten)
+    }
+
+    /// Build the VALUES rows of the plain INSERT from `SELECT <projection> FROM (VALUES ...) AS alias(cols)`
+    fn rewrite_rows(source: &Query) -> Option<Vec<Vec<Expr>>> {
+        if source.with.is_some() || source.limit_clause.is_some() || source.fetch.is_some() {
+            return None;
+        }
+        let SetExpr::Select(select) = source.body.as_ref() else {
+            return None;
+        };
+        if select.from.len() != 1
+            || !select.from[0].joins.is_empty()
+            || select.selection.is_some()
+            || select.having.is_some()
+            || select.distinct.is_some()
+        {
+            return None;
+        }
+
+        let TableFactor::Derived { subquery, alias: Some(alias), .. } = &select.from[0].relation else {
+            return None;
+        };
+        let SetExpr::Values(values) = subquery.body.as_ref() else {
+            return None;
+        };
+        let alias_columns: Vec<&Ident> = alias.columns.iter().map(|column| &column.name).collect();
+        if alias_columns.is_empty() || values.rows.is_empty() {
+            return None;
+        }
+
+        // Each projection item must be one of the derived columns, optionally cast
+        let mut positions = Vec::with_capacity(select.projection.len());
+        for item in &select.projection {
+            let expr = match item {
+                SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => expr,
+                _ => return None,
+            };
+            let column = Self::projected_column(expr)?;
+            positions.push(alias_columns.iter().position(|name| name.value.eq_ignore_ascii_case(&column.value))?);
+        }
+
+        let mut rows = Vec::with_capacity(values.rows.len());
+        for row in &values.rows {
+            if row.len() != alias_columns.len() {
+                return None;
+            }
+            // Dropping a bound parameter (e.g. the sentinel counter) would break the
+            // parameter numbering, so only literal leftovers are allowed
+            let dropped_placeholder = row.iter().enumerate()
+                .any(|(i, expr)| !positions.contains(&i) && matches!(expr, Expr::Value(v) if matches!(v.value, sqlparser::ast::Value::Placeholder(_))));
+            if dropped_placeholder {
+                return None;
+            }
+            rows.push(positions.iter().map(|&i| Self::strip_casts(&row[i]).clone()).collect());
+        }
+
+        Some(rows)
+    }
+
+    /// The derived-table column an insertmanyvalues projection refers to: `p0`, `p0::TYPE` or `CAST(p0 AS TYPE)`
+    fn projected_column(expr: &Expr) -> Option<&Ident> {
+        match expr {
+            Expr::Identifier(ident) => Some(ident),
+            Expr::Cast { expr, .. } | Expr::Nested(expr) => Self::projected_column(expr),
+            _ => None,
+        }
+    }
+
+    /// Drop casts around a VALUES entry; the target column type drives the conversion instead
+    fn strip_casts(expr: &Expr) -> &Expr {
+        match expr {
+            Expr::Cast { expr, .. } | Expr::Nested(expr) => Self::strip_casts(expr),
+            _ => expr,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrites_double_colon_and_cast_forms() {
+        let query = "INSERT INTO events (name, created_at) SELECT p0::VARCHAR, p1::TIMESTAMP WITHOUT TIME ZONE \
+                     FROM (VALUES ($1, $2, 0), ($3, $4, 1)) AS imp_sen(p0, p1, sen_counter) \
+                     ORDER BY sen_counter RETURNING events.id, events.id AS id__1";
+        assert_eq!(
+            InsertManyValuesTranslator::translate_query(query).unwrap(),
+            "INSERT INTO events (name, created_at) VALUES ($1, $2), ($3, $4) RETURNING events.id, events.id AS id__1"
+        );
+
+        let query = "INSERT INTO items (a, b, c) SELECT CAST(p0 AS INTEGER), CAST(p1 AS NUMERIC(10, 2)), p2 \
+                     FROM (VALUES ($1, $2, 'x, y', 0)) AS imp_sen(p0, p1, p2, sen_counter) ORDER BY sen_counter";
+        assert_eq!(
+            InsertManyValuesTranslator::translate_query(query).unwrap(),
+            "INSERT INTO items (a, b, c) VALUES ($1, $2, 'x, y')"
+        );
+    }
+
+    #[test]
+    fn test_projection_order_follows_select_list() {
+        let query = "INSERT INTO t (b, a) SELECT p1, p0 FROM (VALUES (1, 'one', 0), (2, 'two', 1)) AS imp_sen(p0, p1, sen_counter) ORDER BY sen_counter";
+        assert_eq!(
+            InsertManyValuesTranslator::translate_query(query).unwrap(),
+            "INSERT INTO t (b, a) VALUES ('one', 1), ('two', 2)"
+        );
+    }
+
+    #[test]
+    fn test_leaves_other_inserts_alone() {
+        assert!(InsertManyValuesTranslator::translate_query("INSERT INTO t (a) VALUES ($1)").is_none());
+        assert!(InsertManyValuesTranslator::translate_query("INSERT INTO t (a) SELECT x FROM other").is_none());
+        // An expression over the derived columns is not a plain column mapping
+        assert!(InsertManyValuesTranslator::translate_query(
+            "INSERT INTO t (a) SELECT p0 + 1 FROM (VALUES ($1, 0)) AS imp_sen(p0, sen_counter) ORDER BY sen_counter"
+        ).is_none());
+        // A bound sentinel cannot be dropped without renumbering parameters
+        assert!(InsertManyValuesTranslator::translate_query(
+            "INSERT INTO t (a) SELECT p0 FROM (VALUES ($1, $2)) AS imp_sen(p0, sen_counter) ORDER BY sen_counter"
+        ).is_none());
+    }
+}
@@ -1,7 +1,7 @@
 use regex::Regex;
 use once_cell::sync::Lazy;
-use crate::session::DbHandler;
-use crate::types::ValueConverter;
+use crate::session::SessionStorage;
+use crate::types::{ByteaFormat, Interval, NetworkKind, Range, RangeKind, ValueConverter, XmlOption, xml};
 use serde_json;
 use tracing::debug;
 
@@ -28,6 +28,32 @@ static INSERT_SELECT_NO_COLUMNS_PATTERN: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r"(?si)INSERT\s+INTO\s+(\w+)\s+SELECT\s+(.+)").unwrap()
 });
 
+// Interval literals like '36 hours' or '1 year 2 mons', which have no '-' or ':'
+static INTERVAL_VALUE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)'\s*@?\s*[-+]?\d[^']*\b(years?|mons?|months?|weeks?|days?|hours?|minutes?|mins?|seconds?|secs?)\b[^']*'").unwrap()
+});
+
+// Range literals like '[1,10)' or 'empty'
+static RANGE_VALUE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)'\s*(?:empty|[\[(][^',]*,[^',]*[\])])\s*'").unwrap()
+});
+
+// IPv4 addresses like '10.0.0.1/8' and dotted MAC addresses like '0800.2b01.0203';
+// IPv6 and other MAC formats contain ':' or '-'
+static NETWORK_VALUE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"'\s*(?:\d{1,3}(?:\.\d{1,3}){3}(?:/\d{1,2})?|[0-9a-fA-F]{4}\.[0-9a-fA-F]{4}\.[0-9a-fA-F]{4})\s*'").unwrap()
+});
+
+// String literals containing a backslash, like '\x0102' or '\001', may be bytea input
+static BYTEA_VALUE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"'[^']*\\[^']*'").unwrap()
+});
+
+// String literals containing markup or an entity reference, like '<a>x</a>', may be xml input
+static XML_VALUE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"'[^']*[<&][^']*'").unwrap()
+});
+
 impl InsertTranslator {
     /// Check if the query is an INSERT that might need datetime, array, or VALUES translation
     pub fn needs_translation(query: &str) -> bool {
@@ -50,7 +76,12 @@ impl InsertTranslator {
                                    query.contains("NOW()") ||  // PostgreSQL datetime functions
                                    query.contains("CURRENT_DATE") ||
                                    query.contains("CURRENT_TIME") ||
-                                   query.contains("CURRENT_TIMESTAMP");
+                                   query.contains("CURRENT_TIMESTAMP") ||
+                                   Self::contains_interval_literal(query) ||
+                                   Self::contains_range_literal(query) ||
+                                   Self::contains_network_literal(query) ||
+                                   Self::contains_bytea_literal(query) ||
+                                   Self::contains_xml_literal(query);
         
         // Also check for SQLAlchemy VALUES pattern
         let has_sqlalchemy_values = query.contains("FROM (VALUES") && query.contains(") AS ") && 
@@ -59,8 +90,33 @@ impl InsertTranslator {
         is_insert && (has_datetime_or_array || has_sqlalchemy_values)
     }
     
+    /// Check for interval literals that the datetime patterns above don't catch
+    pub fn contains_interval_literal(query: &str) -> bool {
+        INTERVAL_VALUE_PATTERN.is_match(query)
+    }
+    
+    /// Check for range literals, which are stored in canonical form
+    pub fn contains_range_literal(query: &str) -> bool {
+        RANGE_VALUE_PATTERN.is_match(query)
+    }
+    
+    /// Check for IPv4 or dotted MAC address literals, which are validated and canonicalized
+    pub fn contains_network_literal(query: &str) -> bool {
+        NETWORK_VALUE_PATTERN.is_match(query)
+    }
+    
+    /// Check for hex or escape format literals, which are decoded when inserted into bytea columns
+    pub fn contains_bytea_literal(query: &str) -> bool {
+        BYTEA_VALUE_PATTERN.is_match(query)
+    }
+    
+    /// Check for literals with markup, which are validated when inserted into xml columns
+    pub fn contains_xml_literal(query: &str) -> bool {
+        XML_VALUE_PATTERN.is_match(query)
+    }
+    
     /// Translate INSERT statement to convert datetime values to INTEGER format
-    pub async fn translate_query(query: &str, db: &DbHandler) -> Result<String, String> {
+    pub async fn translate_query(query: &str, db: &dyn SessionStorage) -> Result<String, String> {
         // Try matching with explicit columns first
         if let Some(caps) = INSERT_PATTERN.captures(query) {
             let table_name = &caps[1];
@@ -85,7 +141,9 @@ impl InsertTranslator {
                         "timestamptz" | "TIMESTAMPTZ" |
                         "timetz" | "TIMETZ" |
                         "interval" | "INTERVAL"
-                    ) || pg_type.ends_with("[]") || pg_type.starts_with("_")
+                    ) || pg_type.ends_with("[]") || pg_type.starts_with("_") || RangeKind::from_name(pg_type).is_some() ||
+                    NetworkKind::from_name(pg_type).is_some() ||
+                    pg_type.eq_ignore_ascii_case("bytea") || pg_type.eq_ignore_ascii_case("xml")
                 } else {
                     false
                 }
@@ -132,7 +190,9 @@ impl InsertTranslator {
                     "timestamptz" | "TIMESTAMPTZ" |
                     "timetz" | "TIMETZ" |
                     "interval" | "INTERVAL"
-                ) || pg_type.ends_with("[]") || pg_type.starts_with("_")
+                ) || pg_type.ends_with("[]") || pg_type.starts_with("_") || RangeKind::from_name(pg_type).is_some() ||
+                    NetworkKind::from_name(pg_type).is_some() ||
+                    pg_type.eq_ignore_ascii_case("bytea") || pg_type.eq_ignore_ascii_case("xml")
             });
             
             if !needs_conversion {
@@ -228,7 +288,7 @@ impl InsertTranslator {
     }
     
     /// Get column types from __pgsqlite_schema
-    async fn get_column_types(db: &DbHandler, table_name: &str) -> Result<std::collections::HashMap<String, String>, String> {
+    async fn get_column_types(db: &dyn SessionStorage, table_name: &str) -> Result<std::collections::HashMap<String, String>, String> {
         let query = format!(
             "SELECT column_name, pg_type FROM __pgsqlite_schema WHERE table_name = '{table_name}'"
         );
@@ -254,7 +314,7 @@ impl InsertTranslator {
     }
     
     /// Get all columns and their types from __pgsqlite_schema, ordered by column position
-    async fn get_all_columns_and_types(db: &DbHandler, table_name: &str) -> Result<(Vec<String>, std::collections::HashMap<String, String>), String> {
+    async fn get_all_columns_and_types(db: &dyn SessionStorage, table_name: &str) -> Result<(Vec<String>, std::collections::HashMap<String, String>), String> {
         // First get columns from PRAGMA table_info to ensure correct order
         let pragma_query = format!("PRAGMA table_info({table_name})");
         let column_order = match db.query(&pragma_query).await {
@@ -399,6 +459,8 @@ impl InsertTranslator {
         let mut current = String::new();
         let mut in_quotes = false;
         let mut escape_next = false;
+        // Commas inside ARRAY[...] and function calls do not separate values
+        let mut depth = 0;
         let mut chars = values_str.chars().peekable();
         
         while let Some(ch) = chars.next() {
@@ -423,7 +485,15 @@ impl InsertTranslator {
                     current.push(ch);
                     escape_next = true;
                 }
-                ',' if !in_quotes => {
+                '(' | '[' if !in_quotes => {
+                    depth += 1;
+                    current.push(ch);
+                }
+                ')' | ']' if !in_quotes => {
+                    depth -= 1;
+                    current.push(ch);
+                }
+                ',' if !in_quotes && depth == 0 => {
                     values.push(current.trim().to_string());
                     current.clear();
                 }
@@ -468,6 +538,13 @@ impl InsertTranslator {
             }
         }
         
+        // INTERVAL '1 day' is the quoted interval with its type keyword
+        let value = match value.get(..8).zip(value.get(8..)) {
+            Some((keyword, rest)) if keyword.eq_ignore_ascii_case("interval") && pg_type.eq_ignore_ascii_case("interval")
+                && rest.trim_start().starts_with('\'') => rest.trim_start(),
+            _ => value,
+        };
+        
         // Remove quotes if present
         let unquoted = if value.starts_with('\'') && value.ends_with('\'') && value.len() > 1 {
             &value[1..value.len()-1]
@@ -494,7 +571,13 @@ impl InsertTranslator {
                     Err(e) => Err(format!("Invalid timestamp value '{unquoted}': {e}. Expected format: YYYY-MM-DD HH:MM:SS[.ffffff]"))
                 }
             }
-            "timestamptz" | "timetz" | "interval" => {
+            "interval" => {
+                // Stored as the quoted interval text
+                Interval::from_storage(unquoted)
+                    .map(|interval| interval.to_sql_literal())
+                    .map_err(|e| format!("Invalid interval value '{unquoted}': {e}"))
+            }
+            "timestamptz" | "timetz" => {
                 // TODO: Implement these conversions
                 // For now, keep as quoted strings
                 Ok(value.to_string())
@@ -504,6 +587,21 @@ impl InsertTranslator {
                 if pg_type.ends_with("[]") || pg_type.starts_with("_") {
                     // Convert PostgreSQL array literal to JSON
                     Self::convert_array_value(value)
+                } else if let Some(kind) = RangeKind::from_name(pg_type) {
+                    // Store ranges in canonical form, e.g. [1,10] -> [1,11)
+                    Range::parse(kind, unquoted)
+                        .map(|range| format!("'{range}'"))
+                        .map_err(|e| format!("Invalid {kind} value '{unquoted}': {e}"))
+                } else if let Some(kind) = NetworkKind::from_name(pg_type).filter(|_| value.starts_with('\'')) {
+                    // Validate network addresses and store them in canonical form
+                    kind.canonicalize(unquoted).map(|canonical| format!("'{canonical}'"))
+                } else if pg_type.eq_ignore_ascii_case("bytea") && value.starts_with('\'') {
+                    // Decode hex or escape format text into a BLOB literal
+                    ByteaFormat::decode(&unquoted.replace("''", "'"))
+                        .map(|bytes| format!("X'{}'", hex::encode(bytes)))
+                } else if pg_type.eq_ignore_ascii_case("xml") && value.starts_with('\'') {
+                    // xml is stored as written once it's known to be well-formed content
+                    xml::validate(&unquoted.replace("''", "'"), XmlOption::Content).map(|_| value.to_string())
                 } else {
                     // Not a datetime or array type, keep original value
                     Ok(value.to_string())
@@ -532,11 +630,10 @@ impl InsertTranslator {
         
         // Handle '{...}' literal
         if value.starts_with("'{") && value.ends_with("}'") {
-            let inner = &value[2..value.len()-2];
-            let elements = Self::parse_pg_array_literal(inner)?;
-            let json_array = serde_json::to_string(&elements)
-                .map_err(|e| format!("Failed to convert array to JSON: {e}"))?;
-            return Ok(format!("'{json_array}'"));
+            let literal = value[1..value.len()-1].replace("''", "'");
+            let json_array = crate::types::ArrayHandler::array_literal_to_json(&literal, None)
+                .map_err(|e| e.to_string())?;
+            return Ok(format!("'{}'", json_array.replace('\'', "''")));
         }
         
         // If it's already a quoted value that doesn't look like an array, keep it
@@ -581,37 +678,6 @@ impl InsertTranslator {
         Ok(elements)
     }
     
-    /// Parse elements from PostgreSQL array literal format {1,2,3}
-    fn parse_pg_array_literal(inner: &str) -> Result<Vec<serde_json::Value>, String> {
-        let mut elements = Vec::new();
-        let mut current = String::new();
-        let mut in_quotes = false;
-        
-        for ch in inner.chars() {
-            match ch {
-                '"' => {
-                    // PostgreSQL uses double quotes for string elements
-                    in_quotes = !in_quotes;
-                }
-                ',' if !in_quotes => {
-                    let elem = current.trim();
-                    if !elem.is_empty() {
-                        elements.push(Self::parse_pg_array_element(elem)?);
-                    }
-                    current.clear();
-                }
-                _ => current.push(ch),
-            }
-        }
-        
-        // Don't forget the last element
-        if !current.trim().is_empty() {
-            elements.push(Self::parse_pg_array_element(current.trim())?);
-        }
-        
-        Ok(elements)
-    }
-    
     /// Parse a single array element
     fn parse_array_element(elem: &str) -> Result<serde_json::Value, String> {
         let elem = elem.trim();
@@ -644,38 +710,6 @@ impl InsertTranslator {
         Ok(serde_json::Value::String(elem.to_string()))
     }
     
-    /// Parse a single PostgreSQL array element (from {} format)
-    fn parse_pg_array_element(elem: &str) -> Result<serde_json::Value, String> {
-        let elem = elem.trim();
-        
-        // NULL
-        if elem.eq_ignore_ascii_case("NULL") {
-            return Ok(serde_json::Value::Null);
-        }
-        
-        // Quoted string (PostgreSQL uses double quotes in array literals)
-        if elem.starts_with('"') && elem.ends_with('"') && elem.len() > 1 {
-            let unquoted = &elem[1..elem.len()-1];
-            return Ok(serde_json::Value::String(unquoted.to_string()));
-        }
-        
-        // Number
-        if let Ok(num) = elem.parse::<i64>() {
-            return Ok(serde_json::json!(num));
-        }
-        if let Ok(num) = elem.parse::<f64>() {
-            return Ok(serde_json::json!(num));
-        }
-        
-        // Boolean
-        if elem.eq_ignore_ascii_case("true") || elem.eq_ignore_ascii_case("false") {
-            return Ok(serde_json::json!(elem.eq_ignore_ascii_case("true")));
-        }
-        
-        // Default to string
-        Ok(serde_json::Value::String(elem.to_string()))
-    }
-    
     /// Translate SELECT clause expressions to convert datetime literals and functions
     fn translate_select_clause(
         select_clause: &str,
@@ -1046,8 +1080,14 @@ impl InsertTranslator {
                     Err(e) => Err(format!("Invalid timestamp value '{literal}': {e}"))
                 }
             }
+            "interval" => {
+                // Stored as the quoted interval text
+                Interval::from_storage(literal)
+                    .map(|interval| interval.to_sql_literal())
+                    .map_err(|e| format!("Invalid interval value '{literal}': {e}"))
+            }
             _ => {
-                // For other types (timestamptz, timetz, interval), keep as quoted string for now
+                // For other types (timestamptz, timetz), keep as quoted string for now
                 Ok(format!("'{literal}'"))
             }
         }
@@ -1062,6 +1102,9 @@ mod tests {
     fn test_parse_values() {
         let values = InsertTranslator::parse_values("1, 'hello', '2024-01-15', 'it''s fine'").unwrap();
         assert_eq!(values, vec!["1", "'hello'", "'2024-01-15'", "'it''s fine'"]);
+        
+        let values = InsertTranslator::parse_values("ARRAY['a', 'b'], coalesce(NULL, ')'), 2").unwrap();
+        assert_eq!(values, vec!["ARRAY['a', 'b']", "coalesce(NULL, ')')", "2"]);
     }
     
     #[test]
@@ -0,0 +1,426 @@
+use std::ops::ControlFlow;
+use rusqlite::Connection;
+use sqlparser::ast::{
+    BinaryOperator, DataType, Expr, FromTable, Function, FunctionArg, FunctionArgExpr, FunctionArgumentList,
+    FunctionArguments, Ident, ObjectName, ObjectNamePart, OrderByKind, Query, SelectItem, SetExpr, Statement,
+    UnaryOperator, Value, ValueWithSpan, VisitMut, VisitorMut,
+};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use crate::rewriter::{ExpressionTypeResolver, QueryContext};
+use crate::types::{Interval, PgType, TypeChecker};
+
+/// Translator for interval arithmetic, comparisons and aggregates
+///
+/// Intervals are stored as interval text (`1 mon 2 days 03:00:00`), which SQLite can
+/// neither add nor compare, so every operator that takes an interval is rewritten to
+/// the interval functions, using the schema to tell which operands are intervals:
+///
+/// - `ts ± iv` becomes `interval_add(ts, iv)` / `interval_sub(ts, iv)`, which returns
+///   the timestamp in the representation it came in, and `iv ± iv` adds the intervals.
+/// - `iv * n`, `iv / n` and `-iv` become `interval_mul()` and `interval_div()`.
+/// - Comparisons and ORDER BY on intervals go through `interval_key()`, their length.
+/// - `sum()`, `avg()`, `min()` and `max()` of intervals use the interval aggregates.
+///
+/// now() returns timestamp text while stored timestamps are INTEGER microseconds, so
+/// comparisons involving now() compare both sides as microseconds.
+pub struct IntervalTranslator;
+
+impl IntervalTranslator {
+    /// Check if the query may use intervals: it names them, calls now(), or a table
+    /// has an INTERVAL column
+    pub fn needs_translation(query: &str, conn: &Connection) -> bool {
+        let lower = query.to_lowercase();
+        if lower.contains("interval") || lower.contains("now()") || lower.contains("current_timestamp") {
+            return true;
+        }
+        conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM __pgsqlite_schema WHERE pg_type LIKE 'INTERVAL%')",
+            [],
+            |row| row.get::<_, bool>(0),
+        ).unwrap_or(false)
+    }
+
+    /// Rewrite the interval operators of a query. Queries sqlparser cannot read, and
+    /// queries without interval operators, are returned unchanged.
+    pub fn translate_query(query: &str, conn: &Connection) -> String {
+        let Ok(mut statements) = Parser::parse_sql(&PostgreSqlDialect {}, query) else {
+            return query.to_string();
+        };
+        let mut rewriter = IntervalRewriter {
+            resolver: ExpressionTypeResolver::new(conn),
+            checker: TypeChecker::new(conn),
+            contexts: Vec::new(),
+            changed: false,
+        };
+        let _ = statements.visit(&mut rewriter);
+        if !rewriter.changed {
+            return query.to_string();
+        }
+        statements.iter().map(|statement| statement.to_string()).collect::<Vec<_>>().join("; ")
+    }
+}
+
+/// Rewrites expressions bottom-up, keeping the context of the query each one is in
+struct IntervalRewriter<'a> {
+    resolver: ExpressionTypeResolver<'a>,
+    checker: TypeChecker<'a>,
+    /// Contexts of the enclosing queries and statements, innermost last
+    contexts: Vec<QueryContext>,
+    changed: bool,
+}
+
+impl VisitorMut for IntervalRewriter<'_> {
+    type Break = ();
+
+    fn pre_visit_statement(&mut self, statement: &mut Statement) -> ControlFlow<()> {
+        let mut context = QueryContext::default();
+        match statement {
+            Statement::Update { table, from, .. } => {
+                self.resolver.process_table_with_joins(&table.relation, &table.joins, &mut context);
+                if let Some(from) = from {
+                    let (sqlparser::ast::UpdateTableFromKind::BeforeSet(tables)
+                        | sqlparser::ast::UpdateTableFromKind::AfterSet(tables)) = from;
+                    for table in tables.iter() {
+                        self.resolver.process_table_with_joins(&table.relation, &table.joins, &mut context);
+                    }
+                }
+            }
+            Statement::Delete(delete) => {
+                let (FromTable::WithFromKeyword(tables) | FromTable::WithoutKeyword(tables)) = &delete.from;
+                for table in tables {
+                    self.resolver.process_table_with_joins(&table.relation, &table.joins, &mut context);
+                }
+            }
+            _ => {}
+        }
+        self.contexts.push(context);
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_statement(&mut self, _statement: &mut Statement) -> ControlFlow<()> {
+        self.contexts.pop();
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_query(&mut self, query: &mut Query) -> ControlFlow<()> {
+        let mut context = self.resolver.build_context(query);
+        // The other branches of a set operation read their own tables
+        let mut selects = Vec::new();
+        collect_selects(&query.body, &mut selects);
+        for select in selects.into_iter().skip(1) {
+            for table in &select.from {
+                self.resolver.process_table_with_joins(&table.relation, &table.joins, &mut context);
+            }
+        }
+        if let Some(outer) = self.contexts.last() {
+            for (name, columns) in &outer.cte_columns {
+                context.cte_columns.entry(name.clone()).or_insert_with(|| columns.clone());
+            }
+        }
+        self.contexts.push(context);
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_query(&mut self, query: &mut Query) -> ControlFlow<()> {
+        // Intervals sort by length, not by their text
+        let interval_aliases = self.interval_aliases(&query.body);
+        if let Some(order_by) = &mut query.order_by
+            && let OrderByKind::Expressions(exprs) = &mut order_by.kind
+        {
+            for order in exprs {
+                let is_alias = matches!(&order.expr, Expr::Identifier(ident) if interval_aliases.contains(&ident.value));
+                if is_alias || self.type_of(&order.expr) == Some(PgType::Interval) {
+                    order.expr = self.call("interval_key", vec![order.expr.clone()]);
+                }
+            }
+        }
+        self.contexts.pop();
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<()> {
+        if let Some(rewritten) = self.rewrite(expr) {
+            *expr = rewritten;
+            self.changed = true;
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+impl IntervalRewriter<'_> {
+    fn type_of(&mut self, expr: &Expr) -> Option<PgType> {
+        // The type checker leaves now() untyped, as clients read it as text
+        if Self::is_timestamp_text(expr) {
+            return Some(PgType::Timestamptz);
+        }
+        match self.contexts.last() {
+            Some(context) => self.checker.infer(expr, context),
+            None => self.checker.infer(expr, &QueryContext::default()),
+        }
+    }
+
+    /// The rewritten form of an expression whose operands have been rewritten already,
+    /// or None when it stays as it is
+    fn rewrite(&mut self, expr: &Expr) -> Option<Expr> {
+        match expr {
+            // '90 minutes'::interval becomes INTERVAL '01:30:00', which the datetime
+            // translator stores as its text
+            Expr::Cast { expr: inner, data_type: DataType::Interval, .. } => match inner.as_ref() {
+                Expr::Value(ValueWithSpan { value: Value::SingleQuotedString(text), .. }) => {
+                    let interval = Interval::parse(text).ok()?;
+                    Some(Expr::Interval(sqlparser::ast::Interval {
+                        value: Box::new(Expr::value(Value::SingleQuotedString(interval.to_string()))),
+                        leading_field: None,
+                        leading_precision: None,
+                        last_field: None,
+                        fractional_seconds_precision: None,
+                    }))
+                }
+                _ => None,
+            },
+            Expr::BinaryOp { left, op, right } => {
+                let (left_type, right_type) = (self.type_of(left), self.type_of(right));
+                let interval = Some(PgType::Interval);
+                match op {
+                    BinaryOperator::Plus | BinaryOperator::Minus if left_type == interval || right_type == interval => {
+                        let name = if *op == BinaryOperator::Plus { "interval_add" } else { "interval_sub" };
+                        // The interval goes second, interval + timestamp adds to the timestamp
+                        let (base, base_type, offset) = if right_type == interval || *op == BinaryOperator::Minus {
+                            (left, left_type, right)
+                        } else {
+                            (right, right_type, left)
+                        };
+                        Some(self.call(name, vec![Self::as_micros(base, base_type), offset.as_ref().clone()]))
+                    }
+                    BinaryOperator::Multiply if left_type == interval && right_type != interval => {
+                        Some(self.call("interval_mul", vec![left.as_ref().clone(), right.as_ref().clone()]))
+                    }
+                    BinaryOperator::Multiply if right_type == interval && left_type != interval => {
+                        Some(self.call("interval_mul", vec![right.as_ref().clone(), left.as_ref().clone()]))
+                    }
+                    BinaryOperator::Divide if left_type == interval => {
+                        Some(self.call("interval_div", vec![left.as_ref().clone(), right.as_ref().clone()]))
+                    }
+                    op if Self::is_comparison(op) => {
+                        let (left, right) = self.comparison_operands(left, left_type, right, right_type)?;
+                        Some(Expr::BinaryOp { left: Box::new(left), op: op.clone(), right: Box::new(right) })
+                    }
+                    _ => None,
+                }
+            }
+            Expr::UnaryOp { op: UnaryOperator::Minus, expr: operand } if self.type_of(operand) == Some(PgType::Interval) => {
+                Some(self.call("interval_mul", vec![operand.as_ref().clone(), Expr::value(Value::Number("-1".to_string(), false))]))
+            }
+            Expr::Between { expr: operand, negated, low, high } => {
+                let (operand_type, low_type, high_type) = (self.type_of(operand), self.type_of(low), self.type_of(high));
+                let (rewritten, low) = self.comparison_operands(operand, operand_type, low, low_type)?;
+                let (_, high) = self.comparison_operands(operand, operand_type, high, high_type)?;
+                Some(Expr::Between { expr: Box::new(rewritten), negated: *negated, low: Box::new(low), high: Box::new(high) })
+            }
+            Expr::Function(func) if func.over.is_none() => {
+                // Window functions have no interval versions
+                let name = match func.name.0.last()? {
+                    ObjectNamePart::Identifier(ident) => ident.value.to_lowercase(),
+                };
+                let aggregate = match name.as_str() {
+                    "sum" => "interval_sum",
+                    "avg" => "interval_avg",
+                    "min" => "interval_min",
+                    "max" => "interval_max",
+                    _ => return None,
+                };
+                let FunctionArguments::List(list) = &func.args else {
+                    return None;
+                };
+                let [FunctionArg::Unnamed(FunctionArgExpr::Expr(arg))] = list.args.as_slice() else {
+                    return None;
+                };
+                if self.type_of(arg) != Some(PgType::Interval) {
+                    return None;
+                }
+                let mut func = func.clone();
+                func.name = ObjectName(vec![ObjectNamePart::Identifier(Ident::new(aggregate))]);
+                Some(Expr::Function(func))
+            }
+            _ => None,
+        }
+    }
+
+    /// Operands of a comparison that need to be compared as numbers: intervals by their
+    /// length, and timestamps as microseconds when one side is timestamp text
+    fn comparison_operands(&mut self, left: &Expr, left_type: Option<PgType>, right: &Expr, right_type: Option<PgType>) -> Option<(Expr, Expr)> {
+        if left_type == Some(PgType::Interval) || right_type == Some(PgType::Interval) {
+            return Some((
+                self.call("interval_key", vec![left.clone()]),
+                self.call("interval_key", vec![right.clone()]),
+            ));
+        }
+        if Self::is_timestamp_text(left) || Self::is_timestamp_text(right) {
+            let is_timestamp = |pg_type| matches!(pg_type, Some(PgType::Timestamp | PgType::Timestamptz | PgType::Date));
+            if !is_timestamp(left_type) || !is_timestamp(right_type) {
+                return None;
+            }
+            return Some((self.timestamp_micros(left, left_type), self.timestamp_micros(right, right_type)));
+        }
+        None
+    }
+
+    /// A timestamp or date as INTEGER microseconds
+    fn timestamp_micros(&mut self, expr: &Expr, pg_type: Option<PgType>) -> Expr {
+        match pg_type {
+            Some(PgType::Date) => Self::as_micros(expr, pg_type),
+            _ => self.call("pg_timestamp_from_text", vec![expr.clone()]),
+        }
+    }
+
+    /// Dates are stored as days, which interval_add() would take for microseconds
+    fn as_micros(expr: &Expr, pg_type: Option<PgType>) -> Expr {
+        match pg_type {
+            Some(PgType::Date) => Expr::BinaryOp {
+                left: Box::new(Expr::Nested(Box::new(expr.clone()))),
+                op: BinaryOperator::Multiply,
+                right: Box::new(Expr::value(Value::Number("86400000000".to_string(), false))),
+            },
+            _ => expr.clone(),
+        }
+    }
+
+    /// Whether an expression produces timestamp text rather than microseconds: now(),
+    /// CURRENT_TIMESTAMP, and intervals added to them
+    fn is_timestamp_text(expr: &Expr) -> bool {
+        match expr {
+            Expr::Nested(inner) => Self::is_timestamp_text(inner),
+            Expr::Function(func) => {
+                let Some(ObjectNamePart::Identifier(ident)) = func.name.0.last() else {
+                    return false;
+                };
+                match ident.value.to_lowercase().as_str() {
+                    "now" | "current_timestamp" => true,
+                    "interval_add" | "interval_sub" => match &func.args {
+                        FunctionArguments::List(list) => matches!(
+                            list.args.first(),
+                            Some(FunctionArg::Unnamed(FunctionArgExpr::Expr(first))) if Self::is_timestamp_text(first)
+                        ),
+                        _ => false,
+                    },
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    fn is_comparison(op: &BinaryOperator) -> bool {
+        use BinaryOperator::*;
+        matches!(op, Eq | NotEq | Lt | LtEq | Gt | GtEq)
+    }
+
+    /// Aliases of the interval columns of the first SELECT, which ORDER BY can name
+    fn interval_aliases(&mut self, body: &SetExpr) -> Vec<String> {
+        let Some(select) = ExpressionTypeResolver::leftmost_select(body) else {
+            return Vec::new();
+        };
+        let mut aliases = Vec::new();
+        for item in &select.projection {
+            if let SelectItem::ExprWithAlias { expr, alias } = item
+                && self.type_of(expr) == Some(PgType::Interval)
+            {
+                aliases.push(alias.value.clone());
+            }
+        }
+        aliases
+    }
+
+    fn call(&mut self, name: &str, args: Vec<Expr>) -> Expr {
+        self.changed = true;
+        Expr::Function(Function {
+            name: ObjectName(vec![ObjectNamePart::Identifier(Ident::new(name))]),
+            uses_odbc_syntax: false,
+            parameters: FunctionArguments::None,
+            args: FunctionArguments::List(FunctionArgumentList {
+                duplicate_treatment: None,
+                args: args.into_iter().map(|arg| FunctionArg::Unnamed(FunctionArgExpr::Expr(arg))).collect(),
+                clauses: vec![],
+            }),
+            filter: None,
+            null_treatment: None,
+            over: None,
+            within_group: vec![],
+        })
+    }
+}
+
+/// The SELECTs of a query body, leftmost first
+fn collect_selects<'b>(body: &'b SetExpr, selects: &mut Vec<&'b sqlparser::ast::Select>) {
+    match body {
+        SetExpr::Select(select) => selects.push(select),
+        SetExpr::Query(query) => collect_selects(&query.body, selects),
+        SetExpr::SetOperation { left, right, .. } => {
+            collect_selects(left, selects);
+            collect_selects(right, selects);
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::TypeMetadata;
+
+    fn connection() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        TypeMetadata::init(&conn).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE ev (id INTEGER, ts INTEGER, d INTEGER, dur TEXT);
+             INSERT INTO __pgsqlite_schema VALUES
+                 ('ev', 'id', 'INTEGER', 'INTEGER'), ('ev', 'ts', 'TIMESTAMP', 'INTEGER'),
+                 ('ev', 'd', 'DATE', 'INTEGER'), ('ev', 'dur', 'INTERVAL', 'TEXT');"
+        ).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_interval_arithmetic() {
+        let conn = connection();
+        let translate = |sql| IntervalTranslator::translate_query(sql, &conn);
+        assert_eq!(
+            translate("SELECT ts + dur, ts - INTERVAL '1 hour', dur + ts, dur - dur FROM ev"),
+            "SELECT interval_add(ts, dur), interval_sub(ts, INTERVAL '1 hour'), interval_add(ts, dur), interval_sub(dur, dur) FROM ev"
+        );
+        assert_eq!(
+            translate("SELECT dur * 2, 3 * INTERVAL '1 day', dur / 4, -dur FROM ev"),
+            "SELECT interval_mul(dur, 2), interval_mul(INTERVAL '1 day', 3), interval_div(dur, 4), interval_mul(dur, -1) FROM ev"
+        );
+        assert_eq!(
+            translate("SELECT d + INTERVAL '1 day' FROM ev"),
+            "SELECT interval_add((d) * 86400000000, INTERVAL '1 day') FROM ev"
+        );
+        assert_eq!(translate("SELECT '90 minutes'::interval"), "SELECT INTERVAL '01:30:00'");
+    }
+
+    #[test]
+    fn test_interval_comparisons_and_aggregates() {
+        let conn = connection();
+        let translate = |sql| IntervalTranslator::translate_query(sql, &conn);
+        assert_eq!(
+            translate("SELECT id FROM ev WHERE dur > INTERVAL '2 hours' ORDER BY dur DESC"),
+            "SELECT id FROM ev WHERE interval_key(dur) > interval_key(INTERVAL '2 hours') ORDER BY interval_key(dur) DESC"
+        );
+        assert_eq!(
+            translate("SELECT sum(dur) AS total, avg(dur), max(id) FROM ev GROUP BY id ORDER BY total"),
+            "SELECT interval_sum(dur) AS total, interval_avg(dur), max(id) FROM ev GROUP BY id ORDER BY interval_key(total)"
+        );
+        assert_eq!(
+            translate("SELECT id FROM ev WHERE ts > now() - INTERVAL '1 day'"),
+            "SELECT id FROM ev WHERE pg_timestamp_from_text(ts) > pg_timestamp_from_text(interval_sub(now(), INTERVAL '1 day'))"
+        );
+        assert_eq!(
+            translate("SELECT now() + INTERVAL '1 day' > now()"),
+            "SELECT pg_timestamp_from_text(interval_add(now(), INTERVAL '1 day')) > pg_timestamp_from_text(now())"
+        );
+        // Nothing to rewrite
+        assert_eq!(translate("SELECT id, ts FROM ev WHERE id > 1"), "SELECT id, ts FROM ev WHERE id > 1");
+    }
+}
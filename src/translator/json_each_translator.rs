@@ -1,9 +1,39 @@
 use crate::PgSqliteError;
 use crate::translator::{TranslationMetadata, ColumnTypeHint, ExpressionType};
 use crate::types::PgType;
+use super::unnest_translator::UnnestTranslator;
+use once_cell::sync::Lazy;
 use regex::Regex;
 use tracing::debug;
 
+/// A set-returning JSON function call in a FROM list, `FROM jsonb_array_elements(`, after
+/// JOIN or a comma, with an optional LATERAL
+static JSON_SET_FUNCTION_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)(\bFROM\s|\bJOIN\s|,)\s*(?:LATERAL\s+)?(jsonb?_array_elements_text|jsonb?_array_elements|jsonb?_object_keys|jsonb_path_query)\s*\(").unwrap()
+});
+
+/// LATERAL before a json_each() call, which SQLite doesn't accept and doesn't need
+static LATERAL_JSON_EACH_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\bLATERAL\s+(jsonb?_each(?:_text)?\s*\()").unwrap()
+});
+
+/// Clauses that end a FROM list, looking back from one of its items
+const NON_FROM_CLAUSES: &[&str] = &[
+    "SELECT", "WHERE", "GROUP", "ORDER", "HAVING", "LIMIT", "OFFSET", "WINDOW", "VALUES", "SET", "RETURNING",
+    "UNION", "INTERSECT", "EXCEPT",
+];
+
+/// The optional `[AS] alias [(column)]` after a set-returning function call
+static TABLE_ALIAS_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\s+(?:(?i:AS)\s+)?([A-Za-z_]\w*)(?:\s*\(\s*([A-Za-z_]\w*)\s*\))?").unwrap()
+});
+
+/// Words that can follow a FROM item and so are never its alias
+const NON_ALIAS_KEYWORDS: &[&str] = &[
+    "WHERE", "GROUP", "ORDER", "LIMIT", "OFFSET", "HAVING", "WINDOW", "UNION", "INTERSECT", "EXCEPT",
+    "JOIN", "INNER", "LEFT", "RIGHT", "FULL", "CROSS", "NATURAL", "ON", "USING", "FETCH", "FOR", "RETURNING",
+];
+
 
 /// Translates PostgreSQL json_each()/jsonb_each() and json_each_text()/jsonb_each_text() function calls 
 /// to SQLite json_each() equivalents with proper column selection for PostgreSQL compatibility
@@ -11,7 +41,12 @@ pub struct JsonEachTranslator;
 
 impl JsonEachTranslator {
     /// Check if SQL contains json_each or jsonb_each function calls (including _text variants)
+    /// or another set-returning JSON function that is expanded over json_each()
     pub fn contains_json_each(sql: &str) -> bool {
+        if Self::contains_set_function(sql) {
+            return true;
+        }
+
         // Fast path: check for json_each before any expensive operations
         if !sql.contains("json_each") && !sql.contains("jsonb_each") {
             return false;
@@ -32,6 +67,7 @@ impl JsonEachTranslator {
         let mut result = sql.to_string();
         
         // Step 1: Replace jsonb_each variants with json_each
+        result = LATERAL_JSON_EACH_REGEX.replace_all(&result, "$1").to_string();
         result = result.replace("jsonb_each_text(", "json_each_text(");
         result = result.replace("jsonb_each(", "json_each(");
         
@@ -65,9 +101,162 @@ impl JsonEachTranslator {
             replacement
         }).to_string();
         
+        // Step 4: Expand the other set-returning JSON functions over json_each()
+        if Self::contains_set_function(&result) {
+            result = Self::translate_set_functions(&result);
+        }
+        
         Ok(result)
     }
     
+    fn contains_set_function(sql: &str) -> bool {
+        let sql_lower = sql.to_lowercase();
+        (sql_lower.contains("_array_elements") || sql_lower.contains("_object_keys") || sql_lower.contains("jsonb_path_query"))
+            && JSON_SET_FUNCTION_REGEX.is_match(sql)
+    }
+    
+    /// Rewrite `FROM jsonb_array_elements(x) [AS] alias` and the other set-returning JSON
+    /// functions to a single-column subquery over SQLite's json_each(). Like PostgreSQL, the
+    /// column of the *_array_elements functions is `value`, and that of jsonb_object_keys and
+    /// jsonb_path_query is the column alias, else the table alias, else the function name.
+    /// After JOIN or a comma the call may refer to earlier tables, which a subquery cannot do
+    /// in SQLite, so there *_array_elements of a column is mapped straight onto json_each()
+    /// (its `value` column).
+    fn translate_set_functions(sql: &str) -> String {
+        let mut result = String::with_capacity(sql.len());
+        let mut rest = sql;
+        
+        while let Some(caps) = JSON_SET_FUNCTION_REGEX.captures(rest) {
+            let whole = caps.get(0).unwrap();
+            let keyword = caps[1].trim().to_string();
+            let function = caps[2].to_lowercase();
+            
+            // A comma only separates FROM items when the enclosing clause is FROM
+            if keyword == "," && !Self::in_from_list(&format!("{result}{}", &rest[..whole.start()])) {
+                result.push_str(&rest[..whole.end()]);
+                rest = &rest[whole.end()..];
+                continue;
+            }
+            let Some(args_len) = Self::argument_list_length(&rest[whole.end()..]) else {
+                break;
+            };
+            let args = &rest[whole.end()..whole.end() + args_len];
+            let mut after = &rest[whole.end() + args_len + 1..];
+            
+            let (alias, column_alias) = match TABLE_ALIAS_REGEX.captures(after) {
+                Some(alias_caps) if !NON_ALIAS_KEYWORDS.iter().any(|k| alias_caps[1].eq_ignore_ascii_case(k)) => {
+                    after = &after[alias_caps.get(0).unwrap().end()..];
+                    (Some(alias_caps[1].to_string()), alias_caps.get(2).map(|c| c.as_str().to_string()))
+                }
+                _ => (None, None),
+            };
+            
+            result.push_str(&rest[..whole.start()]);
+            let is_array_elements = function.contains("_array_elements");
+            if !keyword.eq_ignore_ascii_case("FROM") && UnnestTranslator::references_columns(args) {
+                if !is_array_elements || column_alias.is_some() {
+                    // Not expressible without a subquery; leave the call as written
+                    result.push_str(&rest[whole.start()..whole.end() + args_len + 1]);
+                    rest = &rest[whole.end() + args_len + 1..];
+                    continue;
+                }
+                result.push_str(&format!("{keyword} json_each({args})"));
+                if let Some(alias) = &alias {
+                    result.push_str(&format!(" AS {alias}"));
+                }
+            } else {
+                let column = column_alias.clone().unwrap_or_else(|| match (is_array_elements, &alias) {
+                    (true, _) => "value".to_string(),
+                    (false, Some(alias)) => alias.clone(),
+                    (false, None) => function.clone(),
+                });
+                let source = if function == "jsonb_path_query" {
+                    format!("json_each(jsonb_path_query_array({args}))")
+                } else {
+                    format!("json_each({args})")
+                };
+                let expr = if function.ends_with("_object_keys") {
+                    "key"
+                } else if function.ends_with("_text") {
+                    "CASE type WHEN 'true' THEN 'true' WHEN 'false' THEN 'false' WHEN 'null' THEN NULL ELSE value END"
+                } else {
+                    // jsonb results keep JSON syntax: strings stay quoted, objects and arrays as-is
+                    "CASE type WHEN 'true' THEN 'true' WHEN 'false' THEN 'false' ELSE json_quote(value) END"
+                };
+                let replacement = format!(
+                    "{keyword} (SELECT {expr} AS {column} FROM {source}) AS {}",
+                    alias.as_deref().unwrap_or(&function)
+                );
+                debug!("JSON set-returning function translation: {}({}) -> {}", function, args, replacement);
+                result.push_str(&replacement);
+            }
+            rest = after;
+        }
+        
+        result.push_str(rest);
+        result
+    }
+    
+    /// Whether the end of `sql` is inside a FROM list, looking back past the
+    /// parenthesized parts for the clause it belongs to
+    fn in_from_list(sql: &str) -> bool {
+        let bytes = sql.as_bytes();
+        let mut depth = 0;
+        let mut i = bytes.len();
+        while i > 0 {
+            i -= 1;
+            match bytes[i] {
+                b')' => depth += 1,
+                b'(' if depth == 0 => return false,
+                b'(' => depth -= 1,
+                b'\'' => {
+                    while i > 0 {
+                        i -= 1;
+                        if bytes[i] == b'\'' {
+                            break;
+                        }
+                    }
+                }
+                b if depth == 0 && b.is_ascii_alphabetic() => {
+                    let end = i + 1;
+                    while i > 0 && (bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'_') {
+                        i -= 1;
+                    }
+                    let word = &sql[i..end];
+                    if word.eq_ignore_ascii_case("FROM") {
+                        return true;
+                    }
+                    if NON_FROM_CLAUSES.iter().any(|clause| word.eq_ignore_ascii_case(clause)) {
+                        return false;
+                    }
+                }
+                _ => {}
+            }
+        }
+        false
+    }
+    
+    /// Length of a function's argument list up to (not including) its closing parenthesis,
+    /// skipping parentheses inside string literals
+    fn argument_list_length(text: &str) -> Option<usize> {
+        let mut depth = 1;
+        let mut in_string = false;
+        for (i, c) in text.char_indices() {
+            match c {
+                '\'' => in_string = !in_string,
+                '(' if !in_string => depth += 1,
+                ')' if !in_string => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(i);
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+    
     /// Translate json_each with metadata
     pub fn translate_with_metadata(sql: &str) -> Result<(String, TranslationMetadata), PgSqliteError> {
         if !Self::contains_json_each(sql) {
@@ -224,4 +413,75 @@ mod tests {
         assert!(result.contains("json_each(t.data) AS e"));
         assert!(!result.contains("json_each_text(t.data)"));
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_set_returning_functions_in_from() {
+        let sql = "SELECT value FROM jsonb_array_elements('[1, \"a\", {\"b\": 2}]')";
+        assert_eq!(
+            JsonEachTranslator::translate_json_each(sql).unwrap(),
+            "SELECT value FROM (SELECT CASE type WHEN 'true' THEN 'true' WHEN 'false' THEN 'false' ELSE json_quote(value) END AS value \
+             FROM json_each('[1, \"a\", {\"b\": 2}]')) AS jsonb_array_elements"
+        );
+        
+        let sql = "SELECT k FROM jsonb_object_keys(data) AS k WHERE k <> 'id'";
+        assert_eq!(
+            JsonEachTranslator::translate_json_each(sql).unwrap(),
+            "SELECT k FROM (SELECT key AS k FROM json_each(data)) AS k WHERE k <> 'id'"
+        );
+        
+        let sql = "SELECT name FROM jsonb_path_query($1, '$.items[*] ? (@.qty > 1).name') AS t(name) ORDER BY 1";
+        assert_eq!(
+            JsonEachTranslator::translate_json_each(sql).unwrap(),
+            "SELECT name FROM (SELECT CASE type WHEN 'true' THEN 'true' WHEN 'false' THEN 'false' ELSE json_quote(value) END AS name \
+             FROM json_each(jsonb_path_query_array($1, '$.items[*] ? (@.qty > 1).name'))) AS t ORDER BY 1"
+        );
+    }
+    
+    #[test]
+    fn test_array_elements_after_join() {
+        let sql = "SELECT o.id, e.value FROM orders o CROSS JOIN jsonb_array_elements_text(o.tags) AS e";
+        assert_eq!(
+            JsonEachTranslator::translate_json_each(sql).unwrap(),
+            "SELECT o.id, e.value FROM orders o CROSS JOIN json_each(o.tags) AS e"
+        );
+        assert!(JsonEachTranslator::contains_json_each(sql));
+        assert!(!JsonEachTranslator::contains_json_each("SELECT jsonb_object_keys(data) FROM t"));
+    }
+    
+    #[test]
+    fn test_functions_after_comma_and_lateral() {
+        let sql = "SELECT t.id, e.value FROM t, jsonb_array_elements(t.data) e WHERE t.id > 1";
+        assert_eq!(
+            JsonEachTranslator::translate_json_each(sql).unwrap(),
+            "SELECT t.id, e.value FROM t, json_each(t.data) AS e WHERE t.id > 1"
+        );
+        
+        let sql = "SELECT t.id, e.value FROM t CROSS JOIN LATERAL jsonb_array_elements(t.data) AS e";
+        assert_eq!(
+            JsonEachTranslator::translate_json_each(sql).unwrap(),
+            "SELECT t.id, e.value FROM t CROSS JOIN json_each(t.data) AS e"
+        );
+        
+        let sql = "SELECT t.id, e.key FROM t, LATERAL jsonb_each(t.data) AS e";
+        assert_eq!(JsonEachTranslator::translate_json_each(sql).unwrap(), "SELECT t.id, e.key FROM t, json_each(t.data) AS e");
+        
+        // Constant arguments keep the function's own column names
+        let sql = "SELECT t.id, k FROM t, LATERAL jsonb_object_keys('{\"a\": 1}') AS k";
+        assert_eq!(
+            JsonEachTranslator::translate_json_each(sql).unwrap(),
+            "SELECT t.id, k FROM t, (SELECT key AS k FROM json_each('{\"a\": 1}')) AS k"
+        );
+        
+        // Calls that can't be expanded are left as written
+        let sql = "SELECT k FROM t JOIN jsonb_object_keys(t.data) AS k ON true";
+        assert_eq!(JsonEachTranslator::translate_json_each(sql).unwrap(), sql);
+        
+        // Commas outside a FROM list don't start a FROM item
+        for sql in [
+            "SELECT id, jsonb_array_elements(data) FROM t",
+            "SELECT id FROM t WHERE x IN (1, jsonb_array_length(data)) ORDER BY id, jsonb_object_keys(data)",
+        ] {
+            assert_eq!(JsonEachTranslator::translate_json_each(sql).unwrap(), sql);
+        }
+    }
+}
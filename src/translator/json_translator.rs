@@ -1,10 +1,22 @@
 use crate::PgSqliteError;
+use crate::translator::{ColumnTypeHint, TranslationMetadata};
+use crate::types::PgType;
 use regex::Regex;
 use once_cell::sync::Lazy;
 
+/// Left operand of the path, containment and existence operators: a column or
+/// the result of an already translated JSON operator (up to two levels of
+/// nested parentheses inside the call)
+const JSON_OPERAND: &str = r"(pgsqlite_json_\w+\((?:[^()]|\((?:[^()]|\([^()]*\))*\))*\)|\b\w+(?:\.\w+)?)";
+
 /// Translates PostgreSQL JSON/JSONB types to SQLite-compatible types
 pub struct JsonTranslator;
 
+/// Right operand of ?| / ?&: a '{a,b}' array literal (with or without quotes),
+/// an ARRAY['a','b'] constructor, a parameter or any other string literal such as a
+/// JSON array
+const KEY_LIST: &str = r"(?:'?\{([^}]*)\}'?|ARRAY\s*\[([^\]]*)\]|('[^']*'|\$\d+))";
+
 impl JsonTranslator {
     /// Translate SQL statement, converting JSON/JSONB types to TEXT
     pub fn translate_statement(sql: &str) -> Result<String, PgSqliteError> {
@@ -69,8 +81,7 @@ impl JsonTranslator {
         let mut result = sql.to_string();
         
         // Translate operators in order of precedence (longer operators first)
-        result = Self::translate_text_extract_operator(&result)?;
-        result = Self::translate_json_extract_operator(&result)?;
+        result = Self::translate_arrow_operators(&result)?;
         result = Self::translate_path_text_operator(&result)?;
         result = Self::translate_path_json_operator(&result)?;
         result = Self::translate_contains_operators(&result)?;
@@ -79,6 +90,54 @@ impl JsonTranslator {
         Ok(result)
     }
     
+    /// Extract type hints for aliased JSON operator expressions.
+    ///
+    /// `->` and `#>` keep the type of their source column (json or jsonb), so the
+    /// hint only records the source column; `->>` and `#>>` always yield text.
+    pub fn extract_operator_metadata(sql: &str) -> TranslationMetadata {
+        static RE_ALIASED: Lazy<Regex> = Lazy::new(|| {
+            Regex::new(r"(?i)\b(?:\w+\.)?(\w+)((?:\s*(?:->>|->|#>>|#>)\s*(?:'[^']*'|\d+|\$\d+))+)\s+AS\s+(\w+)")
+                .expect("Invalid regex")
+        });
+        static RE_LAST_OPERATOR: Lazy<Regex> = Lazy::new(|| {
+            Regex::new(r"(->>|->|#>>|#>)\s*(?:'[^']*'|\d+|\$\d+)\s*$")
+                .expect("Invalid regex")
+        });
+        
+        let mut metadata = TranslationMetadata::new();
+        if !Self::contains_json_operators(sql) {
+            return metadata;
+        }
+        
+        for caps in RE_ALIASED.captures_iter(sql) {
+            let source_column = caps[1].to_string();
+            let alias = caps[3].to_string();
+            let returns_text = RE_LAST_OPERATOR.captures(&caps[2])
+                .is_some_and(|last| last[1].ends_with(">>"));
+            
+            let hint = if returns_text {
+                ColumnTypeHint {
+                    source_column: None,
+                    suggested_type: Some(PgType::Text),
+                    datetime_subtype: None,
+                    is_expression: true,
+                    expression_type: None,
+                }
+            } else {
+                ColumnTypeHint {
+                    source_column: Some(source_column),
+                    suggested_type: None,
+                    datetime_subtype: None,
+                    is_expression: false,
+                    expression_type: None,
+                }
+            };
+            metadata.add_hint(alias, hint);
+        }
+        
+        metadata
+    }
+    
     /// No longer needed - we use custom functions instead of $ paths
     pub fn restore_json_path_root(sql: &str) -> String {
         sql.to_string()
@@ -97,56 +156,173 @@ impl JsonTranslator {
         sql.contains("?&")
     }
     
-    /// Translate ->> operator (extract JSON field as text)
-    fn translate_text_extract_operator(sql: &str) -> Result<String, PgSqliteError> {
-        static RE: Lazy<Regex> = Lazy::new(|| {
-            Regex::new(r"(\b\w+(?:\.\w+)?|pgsqlite_json_get_(?:json|array_json)\([^)]+\))\s*->>\s*'([^']+)'")
-                .expect("Invalid regex")
-        });
-        
-        static RE_INT: Lazy<Regex> = Lazy::new(|| {
-            Regex::new(r"(\b\w+(?:\.\w+)?|pgsqlite_json_get_(?:json|array_json)\([^)]+\))\s*->>\s*(\d+)")
-                .expect("Invalid regex")
-        });
-        
-        let mut result = sql.to_string();
-        
-        // Handle string keys
-        result = RE.replace_all(&result, r"pgsqlite_json_get_text($1, '$2')").to_string();
-        
-        // Handle integer indices
-        result = RE_INT.replace_all(&result, r"pgsqlite_json_get_array_text($1, $2)").to_string();
+    /// Translate -> and ->> operators (extract a JSON field or array element).
+    ///
+    /// Operators are folded left to right, so in a chain such as
+    /// `data->'items'->0->>'name'` each operator takes the call built for the
+    /// previous one as its operand.
+    fn translate_arrow_operators(sql: &str) -> Result<String, PgSqliteError> {
+        let chars: Vec<char> = sql.chars().collect();
+        let mut result = String::with_capacity(sql.len());
+        let mut i = 0;
+        
+        while i < chars.len() {
+            let c = chars[i];
+            
+            // Copy string literals and quoted identifiers through unchanged
+            if c == '\'' || c == '"' {
+                let end = Self::quoted_end(&chars, i);
+                result.extend(&chars[i..end]);
+                i = end;
+                continue;
+            }
+            
+            if c == '-' && chars.get(i + 1) == Some(&'>') {
+                let as_text = chars.get(i + 2) == Some(&'>');
+                let mut key_start = i + if as_text { 3 } else { 2 };
+                while chars.get(key_start).is_some_and(|c| c.is_whitespace()) {
+                    key_start += 1;
+                }
+                
+                // The key is a string literal (object field) or an integer (array index)
+                let key = match chars.get(key_start) {
+                    Some('\'') => Some((Self::quoted_end(&chars, key_start), false)),
+                    Some(d) if d.is_ascii_digit() => {
+                        let mut end = key_start;
+                        while chars.get(end).is_some_and(|c| c.is_ascii_digit()) {
+                            end += 1;
+                        }
+                        Some((end, true))
+                    }
+                    _ => None,
+                };
+                
+                if let Some((key_end, is_index)) = key {
+                    let operand_end = result.trim_end().len();
+                    let operand_start = Self::operand_start(&result[..operand_end]);
+                    if operand_start < operand_end {
+                        let operand = result[operand_start..operand_end].to_string();
+                        let key: String = chars[key_start..key_end].iter().collect();
+                        let function = match (is_index, as_text) {
+                            (false, false) => "pgsqlite_json_get_json",
+                            (false, true) => "pgsqlite_json_get_text",
+                            (true, false) => "pgsqlite_json_get_array_json",
+                            (true, true) => "pgsqlite_json_get_array_text",
+                        };
+                        result.truncate(operand_start);
+                        result.push_str(&format!("{function}({operand}, {key})"));
+                        i = key_end;
+                        continue;
+                    }
+                }
+            }
+            
+            result.push(c);
+            i += 1;
+        }
         
         Ok(result)
     }
     
-    /// Translate -> operator (extract JSON field as JSON)
-    fn translate_json_extract_operator(sql: &str) -> Result<String, PgSqliteError> {
-        static RE: Lazy<Regex> = Lazy::new(|| {
-            Regex::new(r"(\b\w+(?:\.\w+)?|pgsqlite_json_get_(?:json|array_json)\([^)]+\))\s*->\s*'([^']+)'")
-                .expect("Invalid regex")
-        });
-        
-        static RE_INT: Lazy<Regex> = Lazy::new(|| {
-            Regex::new(r"(\b\w+(?:\.\w+)?|pgsqlite_json_get_(?:json|array_json)\([^)]+\))\s*->\s*(\d+)")
-                .expect("Invalid regex")
-        });
-        
-        let mut result = sql.to_string();
-        
-        // Handle string keys
-        result = RE.replace_all(&result, r"pgsqlite_json_get_json($1, '$2')").to_string();
-        
-        // Handle integer indices  
-        result = RE_INT.replace_all(&result, r"pgsqlite_json_get_array_json($1, $2)").to_string();
-        
-        Ok(result)
+    /// Index just past the quoted literal or identifier starting at `start`
+    /// (a doubled quote character is an escaped quote)
+    fn quoted_end(chars: &[char], start: usize) -> usize {
+        let quote = chars[start];
+        let mut i = start + 1;
+        while i < chars.len() {
+            if chars[i] == quote {
+                if chars.get(i + 1) == Some(&quote) {
+                    i += 2;
+                    continue;
+                }
+                return i + 1;
+            }
+            i += 1;
+        }
+        chars.len()
+    }
+    
+    /// Start of the operand that ends `sql`: a column reference, a string
+    /// literal, a parenthesized expression or function call, each optionally
+    /// followed by `::type` casts. Returns `sql.len()` when there is none.
+    fn operand_start(sql: &str) -> usize {
+        let bytes = sql.as_bytes();
+        let is_ident = |b: u8| b.is_ascii_alphanumeric() || b == b'_' || b == b'.' || b == b'"' || b >= 0x80;
+        let mut end = bytes.len();
+        
+        loop {
+            let mut start = end;
+            match bytes[..end].last() {
+                Some(b')') => {
+                    let mut depth = 0;
+                    let mut k = end;
+                    while k > 0 {
+                        k -= 1;
+                        match bytes[k] {
+                            b')' => depth += 1,
+                            b'(' => {
+                                depth -= 1;
+                                if depth == 0 {
+                                    break;
+                                }
+                            }
+                            b'\'' => {
+                                // Skip back over the string literal
+                                while k > 0 {
+                                    k -= 1;
+                                    if bytes[k] == b'\'' {
+                                        break;
+                                    }
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                    if depth != 0 {
+                        return bytes.len();
+                    }
+                    start = k;
+                    // Include the name of a function call
+                    while start > 0 && is_ident(bytes[start - 1]) {
+                        start -= 1;
+                    }
+                }
+                Some(b'\'') => {
+                    let mut k = end - 1;
+                    loop {
+                        match bytes[..k].iter().rposition(|&b| b == b'\'') {
+                            // A doubled quote is an escape inside the literal
+                            Some(q) if q > 0 && bytes[q - 1] == b'\'' => k = q - 1,
+                            Some(q) => {
+                                start = q;
+                                break;
+                            }
+                            None => return bytes.len(),
+                        }
+                    }
+                }
+                _ => {
+                    while start > 0 && is_ident(bytes[start - 1]) {
+                        start -= 1;
+                    }
+                }
+            }
+            
+            if start == end {
+                return bytes.len();
+            }
+            if start >= 2 && &bytes[start - 2..start] == b"::" {
+                end = start - 2;
+                continue;
+            }
+            return start;
+        }
     }
     
     /// Translate #>> operator (extract JSON path as text)
     fn translate_path_text_operator(sql: &str) -> Result<String, PgSqliteError> {
         static RE: Lazy<Regex> = Lazy::new(|| {
-            Regex::new(r"(\b\w+(?:\.\w+)?)\s*#>>\s*'\{([^}]+)\}'")
+            Regex::new(&format!(r"{JSON_OPERAND}\s*#>>\s*'\{{([^}}]+)\}}'"))
                 .expect("Invalid regex")
         });
         
@@ -162,7 +338,7 @@ impl JsonTranslator {
     /// Translate #> operator (extract JSON path as JSON)
     fn translate_path_json_operator(sql: &str) -> Result<String, PgSqliteError> {
         static RE: Lazy<Regex> = Lazy::new(|| {
-            Regex::new(r"(\b\w+(?:\.\w+)?)\s*#>\s*'\{([^}]+)\}'")
+            Regex::new(&format!(r"{JSON_OPERAND}\s*#>\s*'\{{([^}}]+)\}}'"))
                 .expect("Invalid regex")
         });
         
@@ -178,18 +354,18 @@ impl JsonTranslator {
     /// Translate @> and <@ operators (containment)
     fn translate_contains_operators(sql: &str) -> Result<String, PgSqliteError> {
         static RE_CONTAINS: Lazy<Regex> = Lazy::new(|| {
-            Regex::new(r"(\b\w+(?:\.\w+)?)\s*@>\s*'([^']+)'")
+            Regex::new(&format!(r"{JSON_OPERAND}\s*@>\s*'([^']+)'"))
                 .expect("Invalid regex")
         });
         
         static RE_CONTAINED: Lazy<Regex> = Lazy::new(|| {
-            Regex::new(r"(\b\w+(?:\.\w+)?)\s*<@\s*'([^']+)'")
+            Regex::new(&format!(r"{JSON_OPERAND}\s*<@\s*'([^']+)'"))
                 .expect("Invalid regex")
         });
         
         // Also handle reversed format: 'json' <@ column
         static RE_CONTAINED_REV: Lazy<Regex> = Lazy::new(|| {
-            Regex::new(r"'([^']+)'\s*<@\s*(\b\w+(?:\.\w+)?)")
+            Regex::new(&format!(r"'([^']+)'\s*<@\s*{JSON_OPERAND}"))
                 .expect("Invalid regex")
         });
         
@@ -207,33 +383,55 @@ impl JsonTranslator {
         Ok(result)
     }
     
+    /// Build the key list argument of ?| / ?& from the KEY_LIST captures:
+    /// '{a,b}' keeps the historical comma list, ARRAY['a','b'] becomes a JSON
+    /// array and parameters or other string literals are passed through
+    fn key_list_argument(caps: &regex::Captures) -> String {
+        if let Some(keys) = caps.get(2) {
+            format!("'{}'", keys.as_str())
+        } else if let Some(elements) = caps.get(3) {
+            let keys: Vec<String> = elements.as_str()
+                .split(',')
+                .map(|key| key.trim().trim_matches('\'').replace("''", "'"))
+                .filter(|key| !key.is_empty())
+                .collect();
+            format!("'{}'", serde_json::to_string(&keys).unwrap_or_default().replace('\'', "''"))
+        } else {
+            caps.get(4).map(|m| m.as_str().to_string()).unwrap_or_default()
+        }
+    }
+    
     /// Translate ?, ?|, ?& operators (existence checks)
     fn translate_existence_operators(sql: &str) -> Result<String, PgSqliteError> {
         static RE_HAS_KEY: Lazy<Regex> = Lazy::new(|| {
-            Regex::new(r"(\b\w+(?:\.\w+)?)\s*\?\s*'([^']+)'")
+            Regex::new(&format!(r"{JSON_OPERAND}\s*\?\s*('[^']*'|\$\d+)"))
                 .expect("Invalid regex")
         });
         
         static RE_HAS_ANY_KEY: Lazy<Regex> = Lazy::new(|| {
-            Regex::new(r"(\b\w+(?:\.\w+)?)\s*\?\|\s*'?\{([^}]+)\}'?")
+            Regex::new(&format!(r"(?i){JSON_OPERAND}\s*\?\|\s*{KEY_LIST}"))
                 .expect("Invalid regex")
         });
         
         static RE_HAS_ALL_KEYS: Lazy<Regex> = Lazy::new(|| {
-            Regex::new(r"(\b\w+(?:\.\w+)?)\s*\?\&\s*'?\{([^}]+)\}'?")
+            Regex::new(&format!(r"(?i){JSON_OPERAND}\s*\?\&\s*{KEY_LIST}"))
                 .expect("Invalid regex")
         });
         
         let mut result = sql.to_string();
         
         // Translate ? operator (has key)
-        result = RE_HAS_KEY.replace_all(&result, r"pgsqlite_json_has_key($1, '$2')").to_string();
+        result = RE_HAS_KEY.replace_all(&result, r"pgsqlite_json_has_key($1, $2)").to_string();
         
         // Translate ?| operator (has any key)
-        result = RE_HAS_ANY_KEY.replace_all(&result, r"pgsqlite_json_has_any_key($1, '$2')").to_string();
+        result = RE_HAS_ANY_KEY.replace_all(&result, |caps: &regex::Captures| {
+            format!("pgsqlite_json_has_any_key({}, {})", &caps[1], Self::key_list_argument(caps))
+        }).to_string();
         
         // Translate ?& operator (has all keys)
-        result = RE_HAS_ALL_KEYS.replace_all(&result, r"pgsqlite_json_has_all_keys($1, '$2')").to_string();
+        result = RE_HAS_ALL_KEYS.replace_all(&result, |caps: &regex::Captures| {
+            format!("pgsqlite_json_has_all_keys({}, {})", &caps[1], Self::key_list_argument(caps))
+        }).to_string();
         
         Ok(result)
     }
@@ -346,15 +544,19 @@ mod tests {
     
     #[test]
     fn test_chained_operators() {
-        // Test chained JSON operations like data->'items'->1->>'name'
+        // Each operator takes the result of the previous one, keys and indexes alike
         let sql = "SELECT id, data->'items'->1->>'name' AS item_name FROM test";
         let translated = JsonTranslator::translate_json_operators(sql).unwrap();
+        assert_eq!(translated, "SELECT id, pgsqlite_json_get_text(pgsqlite_json_get_array_json(pgsqlite_json_get_json(data, 'items'), 1), 'name') AS item_name FROM test");
         
-        // The translation should at least start replacing the operators with our custom functions
-        assert!(translated.contains("pgsqlite_json_get_json(data, 'items')"));
-        assert!(translated.contains("pgsqlite_json_get"));
-        // The key improvement is that our custom functions can handle any input type
-        // which solves the original "Invalid function parameter type" error
+        let sql = "SELECT t.data->'a'->'b'->>0 FROM t WHERE t.data->0->>'k' = 'x->>y'";
+        let translated = JsonTranslator::translate_json_operators(sql).unwrap();
+        assert_eq!(translated, "SELECT pgsqlite_json_get_array_text(pgsqlite_json_get_json(pgsqlite_json_get_json(t.data, 'a'), 'b'), 0) FROM t WHERE pgsqlite_json_get_text(pgsqlite_json_get_array_json(t.data, 0), 'k') = 'x->>y'");
+        
+        // Parenthesized, cast and function call operands
+        let sql = "SELECT (data->'a')->>'b', '{\"a\": [1]}'::jsonb->'a'->>0, jsonb_build_object('k', 1)->>'k' FROM t";
+        let translated = JsonTranslator::translate_json_operators(sql).unwrap();
+        assert_eq!(translated, "SELECT pgsqlite_json_get_text((pgsqlite_json_get_json(data, 'a')), 'b'), pgsqlite_json_get_array_text(pgsqlite_json_get_json('{\"a\": [1]}'::jsonb, 'a'), 0), pgsqlite_json_get_text(jsonb_build_object('k', 1), 'k') FROM t");
     }
     
     #[test]
@@ -379,4 +581,38 @@ mod tests {
         let translated = JsonTranslator::translate_json_operators(sql).unwrap();
         assert_eq!(translated, "SELECT u.id FROM users u WHERE pgsqlite_json_has_key(u.profile, 'email')");
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_existence_operators_on_expressions_and_arrays() {
+        let sql = "SELECT id FROM docs WHERE data->'a' ? 'b'";
+        let translated = JsonTranslator::translate_json_operators(sql).unwrap();
+        assert_eq!(translated, "SELECT id FROM docs WHERE pgsqlite_json_has_key(pgsqlite_json_get_json(data, 'a'), 'b')");
+        
+        let sql = "SELECT id FROM docs WHERE data ?| ARRAY['k', 'zz']";
+        let translated = JsonTranslator::translate_json_operators(sql).unwrap();
+        assert_eq!(translated, r#"SELECT id FROM docs WHERE pgsqlite_json_has_any_key(data, '["k","zz"]')"#);
+        
+        let sql = r#"SELECT id FROM docs WHERE data ?& '["a","b"]' AND data ? $1"#;
+        let translated = JsonTranslator::translate_json_operators(sql).unwrap();
+        assert_eq!(translated, r#"SELECT id FROM docs WHERE pgsqlite_json_has_all_keys(data, '["a","b"]') AND pgsqlite_json_has_key(data, $1)"#);
+        
+        let sql = r#"SELECT id FROM docs WHERE data->'tags' @> '["x"]'"#;
+        let translated = JsonTranslator::translate_json_operators(sql).unwrap();
+        assert_eq!(translated, r#"SELECT id FROM docs WHERE jsonb_contains(pgsqlite_json_get_json(data, 'tags'), '["x"]')"#);
+    }
+    
+    #[test]
+    fn test_operator_metadata() {
+        let sql = "SELECT d.data->'a'->'b' AS ab, data #>> '{a,b}' AS c, data->>'k' AS k FROM docs d";
+        let metadata = JsonTranslator::extract_operator_metadata(sql);
+        
+        let ab = metadata.get_hint("ab").unwrap();
+        assert_eq!(ab.source_column.as_deref(), Some("data"));
+        assert!(ab.suggested_type.is_none());
+        
+        for alias in ["c", "k"] {
+            let hint = metadata.get_hint(alias).unwrap();
+            assert_eq!(hint.suggested_type, Some(PgType::Text));
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// A trailing `FOR UPDATE | FOR NO KEY UPDATE | FOR SHARE | FOR KEY SHARE` clause with
+/// its optional `OF tables` and `NOWAIT | SKIP LOCKED`, possibly followed by pagination
+static LOCKING_CLAUSE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?is)\s+FOR\s+(NO\s+KEY\s+UPDATE|UPDATE|KEY\s+SHARE|SHARE)(?:\s+OF\s+[\w."]+(?:\s*,\s*[\w."]+)*)?(?:\s+NOWAIT|\s+SKIP\s+LOCKED)?(\s+(?:LIMIT|OFFSET|FETCH)\b[^;']*)?(\s*;?\s*)$"#
+    ).unwrap()
+});
+
+/// Translator for PostgreSQL row locking clauses
+///
+/// SQLite has no row locks: a write transaction locks the whole database, so
+/// `SELECT ... FOR UPDATE` is already serialized against other writers once the
+/// transaction writes. The clause is dropped rather than rejected by SQLite's parser.
+pub struct LockingClauseTranslator;
+
+impl LockingClauseTranslator {
+    /// Check if translation might be needed
+    pub fn needs_translation(query: &str) -> bool {
+        let bytes = query.as_bytes();
+        (bytes.windows(6).any(|w| w.eq_ignore_ascii_case(b"update"))
+            || bytes.windows(5).any(|w| w.eq_ignore_ascii_case(b"share")))
+            && Self::locking_clause(query).is_some()
+    }
+
+    /// The locking strength of the clause, e.g. `FOR UPDATE`
+    pub fn locking_clause(query: &str) -> Option<String> {
+        let caps = LOCKING_CLAUSE_REGEX.captures(query)?;
+        let strength = caps[1].split_whitespace().collect::<Vec<_>>().join(" ").to_uppercase();
+        Some(format!("FOR {strength}"))
+    }
+
+    /// Remove the locking clause, keeping any pagination that followed it
+    pub fn translate_query(query: &str) -> String {
+        LOCKING_CLAUSE_REGEX.replace(query, "$2$3").into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_locking_clauses() {
+        assert_eq!(
+            LockingClauseTranslator::translate_query("SELECT * FROM accounts WHERE id = 1 FOR UPDATE"),
+            "SELECT * FROM accounts WHERE id = 1"
+        );
+        assert_eq!(
+            LockingClauseTranslator::translate_query("SELECT * FROM jobs ORDER BY id LIMIT 1 FOR NO KEY UPDATE OF jobs SKIP LOCKED;"),
+            "SELECT * FROM jobs ORDER BY id LIMIT 1;"
+        );
+        assert_eq!(
+            LockingClauseTranslator::translate_query("SELECT * FROM jobs FOR SHARE NOWAIT LIMIT 5"),
+            "SELECT * FROM jobs LIMIT 5"
+        );
+        assert_eq!(
+            LockingClauseTranslator::locking_clause("select * from t for  key share"),
+            Some("FOR KEY SHARE".to_string())
+        );
+    }
+
+    #[test]
+    fn test_leaves_other_queries_alone() {
+        assert!(!LockingClauseTranslator::needs_translation("UPDATE t SET a = 1"));
+        assert!(!LockingClauseTranslator::needs_translation("SELECT 'x FOR UPDATE' FROM t"));
+        assert!(!LockingClauseTranslator::needs_translation("SELECT * FROM t WHERE note = 'wait FOR UPDATE'"));
+    }
+}
@@ -17,6 +17,7 @@ mod numeric_cast_translator;
 mod array_translator;
 mod array_agg_translator;
 mod unnest_translator;
+mod set_returning_translator;
 mod json_each_translator;
 mod row_to_json_translator;
 mod batch_update_translator;
@@ -26,6 +27,21 @@ mod query_analyzer;
 mod function_parentheses_translator;
 mod catalog_function_translator;
 mod pg_table_is_visible_translator;
+mod pagination_translator;
+mod null_ordering_translator;
+mod network_translator;
+mod xml_translator;
+mod bit_translator;
+mod composite_translator;
+mod within_group_translator;
+mod crosstab_translator;
+mod window_translator;
+mod values_translator;
+mod insert_many_values_translator;
+mod locking_clause_translator;
+mod cte_translator;
+mod trigger_translator;
+mod interval_translator;
 
 pub use json_translator::JsonTranslator;
 pub use returning_translator::ReturningTranslator;
@@ -44,6 +60,7 @@ pub use numeric_cast_translator::NumericCastTranslator;
 pub use array_translator::ArrayTranslator;
 pub use array_agg_translator::ArrayAggTranslator;
 pub use unnest_translator::UnnestTranslator;
+pub use set_returning_translator::SetReturningTranslator;
 pub use json_each_translator::JsonEachTranslator;
 pub use row_to_json_translator::RowToJsonTranslator;
 pub use batch_update_translator::BatchUpdateTranslator;
@@ -52,4 +69,19 @@ pub use fts_translator::FtsTranslator;
 pub use query_analyzer::{QueryAnalyzer, TranslationFlags};
 pub use function_parentheses_translator::FunctionParenthesesTranslator;
 pub use catalog_function_translator::CatalogFunctionTranslator;
-pub use pg_table_is_visible_translator::PgTableIsVisibleTranslator;
\ No newline at end of file
+pub use pg_table_is_visible_translator::PgTableIsVisibleTranslator;
+pub use pagination_translator::PaginationTranslator;
+pub use null_ordering_translator::NullOrderingTranslator;
+pub use network_translator::NetworkTranslator;
+pub use xml_translator::XmlTranslator;
+pub use bit_translator::BitTranslator;
+pub use composite_translator::CompositeTranslator;
+pub use within_group_translator::WithinGroupTranslator;
+pub use crosstab_translator::CrosstabTranslator;
+pub use window_translator::WindowTranslator;
+pub use values_translator::ValuesTranslator;
+pub use insert_many_values_translator::InsertManyValuesTranslator;
+pub use locking_clause_translator::LockingClauseTranslator;
+pub use cte_translator::CteTranslator;
+pub use trigger_translator::{PlStatement, ReturnValue, SqlFragment, TriggerDefinition, TriggerEvent, TriggerTiming, TriggerTranslator, TRIGGER_WRITES_TABLE};
+pub use interval_translator::IntervalTranslator;
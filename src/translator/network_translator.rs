@@ -0,0 +1,71 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Translator for the inet/cidr containment operators
+///
+/// `<<`, `<<=`, `>>` and `>>=` test whether one network is contained in another.
+/// SQLite only knows `<<` and `>>` as integer bit shifts, so the operators are
+/// rewritten to network_sub(), network_subeq(), network_sup() and network_supeq().
+/// network_sub() and network_sup() still shift integers and bit strings.
+pub struct NetworkTranslator;
+
+/// An operand: a string literal, a function call without nested parentheses,
+/// a column reference, a number or a parameter, optionally followed by a cast
+pub(super) const OPERAND: &str = r#"(?:'(?:[^']|'')*'|\w+\([^()]*\)|[\w.$"]+)(?:::\w+)?"#;
+
+static NETWORK_OPERATOR_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(r"({OPERAND})\s*(<<=|>>=|<<|>>)\s*({OPERAND})")).unwrap()
+});
+
+impl NetworkTranslator {
+    /// Check if translation might be needed
+    pub fn needs_translation(query: &str) -> bool {
+        query.contains("<<") || query.contains(">>")
+    }
+
+    /// Rewrite network containment operators as function calls
+    pub fn translate_query(query: &str) -> String {
+        if !Self::needs_translation(query) {
+            return query.to_string();
+        }
+
+        NETWORK_OPERATOR_REGEX.replace_all(query, |caps: &regex::Captures| {
+            let whole = caps.get(0).unwrap();
+            // Leave operators inside string literals alone
+            if query[..whole.start()].bytes().filter(|&b| b == b'\'').count() % 2 == 1 {
+                return caps[0].to_string();
+            }
+
+            let function = match &caps[2] {
+                "<<" => "network_sub",
+                "<<=" => "network_subeq",
+                ">>" => "network_sup",
+                _ => "network_supeq",
+            };
+            format!("{function}({}, {})", &caps[1], &caps[3])
+        }).into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_network_operators() {
+        assert_eq!(
+            NetworkTranslator::translate_query("SELECT * FROM hosts WHERE addr << '10.0.0.0/8'"),
+            "SELECT * FROM hosts WHERE network_sub(addr, '10.0.0.0/8')"
+        );
+        assert_eq!(
+            NetworkTranslator::translate_query("SELECT net >>= pg_network_from_text('10.1.0.0/16', 'cidr'), 1 << 4"),
+            "SELECT network_supeq(net, pg_network_from_text('10.1.0.0/16', 'cidr')), network_sub(1, 4)"
+        );
+    }
+
+    #[test]
+    fn test_json_operators_and_literals_unchanged() {
+        let query = "SELECT data->>'name', data#>>'{a}', 'a << b' FROM t";
+        assert_eq!(NetworkTranslator::translate_query(query), query);
+    }
+}
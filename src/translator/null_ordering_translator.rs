@@ -0,0 +1,136 @@
+use super::pagination_translator::{tokenize, Token};
+
+/// Translator for PostgreSQL NULL ordering
+///
+/// PostgreSQL sorts NULLs as larger than any other value, so they come last in
+/// ascending order and first in descending order. SQLite sorts them as smaller.
+/// Every ORDER BY item without an explicit `NULLS FIRST` / `NULLS LAST` gets the
+/// PostgreSQL default spelled out, at every query level and in window and aggregate
+/// ORDER BY lists. Explicit NULLS clauses are left as written; SQLite supports them.
+pub struct NullOrderingTranslator;
+
+/// Keywords that end an ORDER BY list at the same parenthesis level
+const ORDER_BY_TERMINATORS: &[&str] = &[
+    "LIMIT", "OFFSET", "FETCH", "FOR", "ROWS", "RANGE", "GROUPS", "UNION", "INTERSECT", "EXCEPT", "RETURNING",
+];
+
+/// The ORDER BY item being scanned
+#[derive(Default)]
+struct OrderItem {
+    /// End of the item's last non-whitespace token in the rebuilt text
+    end: Option<usize>,
+    descending: bool,
+    has_nulls: bool,
+}
+
+impl NullOrderingTranslator {
+    /// Check if translation might be needed
+    pub fn needs_translation(query: &str) -> bool {
+        query.as_bytes().windows(5).any(|w| w.eq_ignore_ascii_case(b"order"))
+    }
+
+    /// Add PostgreSQL's default NULLS FIRST / NULLS LAST to ORDER BY items
+    pub fn translate_query(query: &str) -> String {
+        if !Self::needs_translation(query) {
+            return query.to_string();
+        }
+        Self::translate_scope(query)
+    }
+
+    /// Translate one parenthesis level, recursing into nested groups
+    fn translate_scope(scope: &str) -> String {
+        let mut rebuilt = String::with_capacity(scope.len() + 16);
+        let mut after_order = false;
+        let mut item: Option<OrderItem> = None;
+
+        for token in tokenize(scope) {
+            if let Some(current) = item.as_mut() {
+                let ends_list = ORDER_BY_TERMINATORS.iter().any(|keyword| token.is_keyword(keyword))
+                    || matches!(token, Token::Other(text) if text.contains(';'));
+                let ends_item = matches!(token, Token::Other(","));
+                if ends_list || ends_item {
+                    Self::finish_item(&mut rebuilt, current);
+                    item = if ends_item { Some(OrderItem::default()) } else { None };
+                }
+            }
+
+            match token {
+                Token::Group(inner) => {
+                    rebuilt.push('(');
+                    rebuilt.push_str(&Self::translate_scope(inner));
+                    rebuilt.push(')');
+                }
+                Token::Word(text) | Token::Other(text) => rebuilt.push_str(text),
+            }
+
+            if token.is_whitespace() {
+                continue;
+            }
+            if let Some(current) = item.as_mut()
+                && !matches!(token, Token::Other(","))
+            {
+                current.end = Some(rebuilt.len());
+                current.descending = token.is_keyword("DESC");
+                current.has_nulls |= token.is_keyword("NULLS");
+            } else if after_order && token.is_keyword("BY") {
+                item = Some(OrderItem::default());
+            }
+            after_order = token.is_keyword("ORDER");
+        }
+
+        if let Some(current) = item.as_mut() {
+            Self::finish_item(&mut rebuilt, current);
+        }
+        rebuilt
+    }
+
+    fn finish_item(rebuilt: &mut String, item: &OrderItem) {
+        if let Some(end) = item.end
+            && !item.has_nulls
+        {
+            let nulls = if item.descending { " NULLS FIRST" } else { " NULLS LAST" };
+            rebuilt.insert_str(end, nulls);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_null_ordering() {
+        assert_eq!(
+            NullOrderingTranslator::translate_query("SELECT * FROM t ORDER BY a, b DESC, c ASC"),
+            "SELECT * FROM t ORDER BY a NULLS LAST, b DESC NULLS FIRST, c ASC NULLS LAST"
+        );
+        assert_eq!(
+            NullOrderingTranslator::translate_query("SELECT * FROM t ORDER BY lower(name) DESC LIMIT 10 OFFSET 5;"),
+            "SELECT * FROM t ORDER BY lower(name) DESC NULLS FIRST LIMIT 10 OFFSET 5;"
+        );
+    }
+
+    #[test]
+    fn test_explicit_nulls_kept() {
+        let query = "SELECT * FROM t ORDER BY a NULLS FIRST, b DESC NULLS LAST";
+        assert_eq!(NullOrderingTranslator::translate_query(query), query);
+    }
+
+    #[test]
+    fn test_nested_and_window_order_by() {
+        assert_eq!(
+            NullOrderingTranslator::translate_query(
+                "SELECT id, row_number() OVER (PARTITION BY g ORDER BY score DESC ROWS BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW) \
+                 FROM (SELECT * FROM t ORDER BY id) s WHERE note <> 'order by x' ORDER BY 1"
+            ),
+            "SELECT id, row_number() OVER (PARTITION BY g ORDER BY score DESC NULLS FIRST ROWS BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW) \
+             FROM (SELECT * FROM t ORDER BY id NULLS LAST) s WHERE note <> 'order by x' ORDER BY 1 NULLS LAST"
+        );
+    }
+
+    #[test]
+    fn test_queries_without_order_by() {
+        let query = "SELECT * FROM orders WHERE id = 1";
+        assert_eq!(NullOrderingTranslator::translate_query(query), query);
+    }
+}
@@ -0,0 +1,361 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Parameters used as LIMIT / OFFSET / FETCH counts
+static PAGINATION_PARAM_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(?:LIMIT|OFFSET|FETCH\s+(?:FIRST|NEXT))\s+\$(\d+)\b").unwrap()
+});
+
+/// Translator for PostgreSQL pagination clauses
+///
+/// SQLite only understands `LIMIT n [OFFSET m]`. PostgreSQL additionally accepts the
+/// SQL-standard `OFFSET m ROWS FETCH FIRST n ROWS ONLY`, `OFFSET` before `LIMIT`,
+/// `OFFSET` without `LIMIT` and `LIMIT ALL`. Each query level (including subqueries)
+/// is rewritten to the SQLite form.
+pub struct PaginationTranslator;
+
+/// A parsed pagination tail of a single query level
+#[derive(Debug, Default, PartialEq)]
+struct Pagination {
+    limit: Option<String>,
+    offset: Option<String>,
+}
+
+impl PaginationTranslator {
+    /// Check if translation might be needed
+    pub fn needs_translation(query: &str) -> bool {
+        let bytes = query.as_bytes();
+        Self::contains_ignore_case(bytes, b"offset")
+            || Self::contains_ignore_case(bytes, b"fetch")
+            || Self::contains_ignore_case(bytes, b"limit all")
+    }
+
+    /// Rewrite pagination clauses into LIMIT/OFFSET form
+    pub fn translate_query(query: &str) -> String {
+        if !Self::needs_translation(query) {
+            return query.to_string();
+        }
+        Self::translate_scope(query)
+    }
+
+    /// Parameter numbers (1-based) that are used as pagination counts.
+    /// These are bigint in PostgreSQL and must not be NULL when probing the query in SQLite.
+    pub fn pagination_parameters(query: &str) -> Vec<usize> {
+        PAGINATION_PARAM_REGEX.captures_iter(query)
+            .filter_map(|caps| caps[1].parse().ok())
+            .collect()
+    }
+
+    fn contains_ignore_case(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|w| w.eq_ignore_ascii_case(needle))
+    }
+
+    /// Translate one parenthesis level, recursing into nested groups first
+    fn translate_scope(scope: &str) -> String {
+        let tokens = tokenize(scope);
+
+        // Rebuild the scope with nested groups translated
+        let mut rebuilt = String::with_capacity(scope.len());
+        let mut top_level = Vec::with_capacity(tokens.len());
+        for token in &tokens {
+            let start = rebuilt.len();
+            match token {
+                Token::Group(inner) => {
+                    rebuilt.push('(');
+                    rebuilt.push_str(&Self::translate_scope(inner));
+                    rebuilt.push(')');
+                }
+                Token::Word(text) | Token::Other(text) => rebuilt.push_str(text),
+            }
+            top_level.push((start, rebuilt.len(), token));
+        }
+
+        // Find where the pagination tail starts at this level
+        let Some(tail_start) = top_level.iter().position(|(_, _, token)| {
+            token.is_keyword("LIMIT") || token.is_keyword("OFFSET") || token.is_keyword("FETCH")
+        }) else {
+            return rebuilt;
+        };
+
+        // The tail ends at a locking clause, a statement terminator or the end of the scope
+        let tail_end = top_level[tail_start..].iter()
+            .position(|(_, _, token)| token.is_keyword("FOR") || matches!(token, Token::Other(t) if t.contains(';')))
+            .map_or(top_level.len(), |pos| tail_start + pos);
+
+        let tail: Vec<&Token> = top_level[tail_start..tail_end].iter()
+            .map(|(_, _, token)| *token)
+            .filter(|token| !token.is_whitespace())
+            .collect();
+
+        let Some((pagination, needs_rewrite)) = Self::parse_tail(&tail, &rebuilt, &top_level[tail_start..tail_end]) else {
+            return rebuilt;
+        };
+        if !needs_rewrite {
+            return rebuilt;
+        }
+
+        let mut clause = format!("LIMIT {}", pagination.limit.as_deref().unwrap_or("-1"));
+        if let Some(offset) = &pagination.offset {
+            clause.push_str(" OFFSET ");
+            clause.push_str(offset);
+        }
+
+        let replace_start = top_level[tail_start].0;
+        let replace_end = top_level[tail_end - 1].1;
+        // Keep trailing whitespace before a following clause
+        let trailing = &rebuilt[replace_start..replace_end];
+        let trailing_ws = &trailing[trailing.trim_end().len()..];
+
+        format!("{}{}{}{}", &rebuilt[..replace_start], clause, trailing_ws, &rebuilt[replace_end..])
+    }
+
+    /// Parse the pagination tokens. Returns the clause values and whether the
+    /// SQLite form differs from the original.
+    fn parse_tail(tail: &[&Token], rebuilt: &str, spans: &[(usize, usize, &Token)]) -> Option<(Pagination, bool)> {
+        // Map non-whitespace tokens back to their text in the rebuilt scope
+        let texts: Vec<&str> = spans.iter()
+            .filter(|(_, _, token)| !token.is_whitespace())
+            .map(|(start, end, _)| &rebuilt[*start..*end])
+            .collect();
+
+        let mut pagination = Pagination::default();
+        let mut needs_rewrite = false;
+        let mut i = 0;
+
+        while i < tail.len() {
+            if tail[i].is_keyword("LIMIT") {
+                let value = texts.get(i + 1)?;
+                if tail[i + 1].is_keyword("ALL") {
+                    needs_rewrite = true;
+                    pagination.limit = Some("-1".to_string());
+                } else {
+                    pagination.limit = Some(value.to_string());
+                }
+                if pagination.offset.is_some() {
+                    // OFFSET before LIMIT
+                    needs_rewrite = true;
+                }
+                i += 2;
+            } else if tail[i].is_keyword("OFFSET") {
+                pagination.offset = Some(texts.get(i + 1)?.to_string());
+                i += 2;
+                if tail.get(i).is_some_and(|t| t.is_keyword("ROW") || t.is_keyword("ROWS")) {
+                    needs_rewrite = true;
+                    i += 1;
+                }
+            } else if tail[i].is_keyword("FETCH") {
+                // FETCH { FIRST | NEXT } [ count ] { ROW | ROWS } ONLY
+                needs_rewrite = true;
+                if !tail.get(i + 1).is_some_and(|t| t.is_keyword("FIRST") || t.is_keyword("NEXT")) {
+                    return None;
+                }
+                i += 2;
+                let count = if tail.get(i).is_some_and(|t| t.is_keyword("ROW") || t.is_keyword("ROWS")) {
+                    "1".to_string()
+                } else {
+                    let count = texts.get(i)?.to_string();
+                    i += 1;
+                    count
+                };
+                if !tail.get(i).is_some_and(|t| t.is_keyword("ROW") || t.is_keyword("ROWS")) {
+                    return None;
+                }
+                i += 1;
+                // WITH TIES has no SQLite equivalent
+                if !tail.get(i).is_some_and(|t| t.is_keyword("ONLY")) {
+                    return None;
+                }
+                i += 1;
+                pagination.limit = Some(count);
+            } else {
+                return None;
+            }
+        }
+
+        if pagination.limit.is_none() && pagination.offset.is_some() {
+            // SQLite requires LIMIT before OFFSET
+            needs_rewrite = true;
+        }
+
+        Some((pagination, needs_rewrite))
+    }
+}
+
+#[derive(Debug)]
+pub(super) enum Token<'a> {
+    Word(&'a str),
+    Group(&'a str),
+    Other(&'a str),
+}
+
+impl Token<'_> {
+    pub(super) fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, Token::Word(word) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    pub(super) fn is_whitespace(&self) -> bool {
+        matches!(self, Token::Other(text) if text.trim().is_empty())
+    }
+}
+
+/// Split a scope into words, parenthesized groups and other text (whitespace,
+/// literals, operators). Quoted literals and identifiers are kept intact.
+pub(super) fn tokenize(scope: &str) -> Vec<Token<'_>> {
+    let bytes = scope.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let start = i;
+        let b = bytes[i];
+
+        if b.is_ascii_alphabetic() || b == b'_' {
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_' || bytes[i] == b'$') {
+                i += 1;
+            }
+            tokens.push(Token::Word(&scope[start..i]));
+        } else if b == b'(' {
+            let mut depth = 0;
+            while i < bytes.len() {
+                match bytes[i] {
+                    b'(' => depth += 1,
+                    b')' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    b'\'' | b'"' => i = skip_quoted(bytes, i) - 1,
+                    _ => {}
+                }
+                i += 1;
+            }
+            if i >= bytes.len() {
+                // Unbalanced parentheses - treat the rest as opaque text
+                tokens.push(Token::Other(&scope[start..]));
+                break;
+            }
+            tokens.push(Token::Group(&scope[start + 1..i]));
+            i += 1;
+        } else if b == b'\'' || b == b'"' {
+            i = skip_quoted(bytes, i);
+            tokens.push(Token::Other(&scope[start..i]));
+        } else if b.is_ascii_whitespace() {
+            while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+                i += 1;
+            }
+            tokens.push(Token::Other(&scope[start..i]));
+        } else if b == b'$' || b.is_ascii_digit() {
+            // Numbers and $n parameters
+            i += 1;
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'.') {
+                i += 1;
+            }
+            tokens.push(Token::Other(&scope[start..i]));
+        } else {
+            // Advance by a full UTF-8 character
+            i += scope[i..].chars().next().map_or(1, char::len_utf8);
+            tokens.push(Token::Other(&scope[start..i]));
+        }
+    }
+
+    tokens
+}
+
+/// Return the index just past a quoted literal or identifier starting at `start`
+fn skip_quoted(bytes: &[u8], start: usize) -> usize {
+    let quote = bytes[start];
+    let mut i = start + 1;
+    while i < bytes.len() {
+        if bytes[i] == quote {
+            // Doubled quote is an escaped quote
+            if bytes.get(i + 1) == Some(&quote) {
+                i += 2;
+                continue;
+            }
+            return i + 1;
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fetch_first() {
+        assert_eq!(
+            PaginationTranslator::translate_query("SELECT * FROM t ORDER BY id FETCH FIRST 10 ROWS ONLY"),
+            "SELECT * FROM t ORDER BY id LIMIT 10"
+        );
+        assert_eq!(
+            PaginationTranslator::translate_query("SELECT * FROM t ORDER BY id OFFSET 20 ROWS FETCH NEXT 10 ROWS ONLY"),
+            "SELECT * FROM t ORDER BY id LIMIT 10 OFFSET 20"
+        );
+        assert_eq!(
+            PaginationTranslator::translate_query("SELECT * FROM t FETCH FIRST ROW ONLY"),
+            "SELECT * FROM t LIMIT 1"
+        );
+        assert_eq!(
+            PaginationTranslator::translate_query("select * from t offset $1 rows fetch first $2 rows only"),
+            "select * from t LIMIT $2 OFFSET $1"
+        );
+    }
+
+    #[test]
+    fn test_offset_and_limit_forms() {
+        // OFFSET without LIMIT
+        assert_eq!(
+            PaginationTranslator::translate_query("SELECT * FROM t ORDER BY id OFFSET 5"),
+            "SELECT * FROM t ORDER BY id LIMIT -1 OFFSET 5"
+        );
+        // OFFSET before LIMIT
+        assert_eq!(
+            PaginationTranslator::translate_query("SELECT * FROM t ORDER BY id OFFSET 5 LIMIT 10;"),
+            "SELECT * FROM t ORDER BY id LIMIT 10 OFFSET 5;"
+        );
+        // LIMIT ALL
+        assert_eq!(
+            PaginationTranslator::translate_query("SELECT * FROM t LIMIT ALL OFFSET 2"),
+            "SELECT * FROM t LIMIT -1 OFFSET 2"
+        );
+        // Already in SQLite form
+        let query = "SELECT * FROM t ORDER BY id LIMIT 10 OFFSET 5";
+        assert_eq!(PaginationTranslator::translate_query(query), query);
+    }
+
+    #[test]
+    fn test_subqueries_and_literals() {
+        assert_eq!(
+            PaginationTranslator::translate_query(
+                "SELECT * FROM (SELECT id FROM t ORDER BY id FETCH FIRST 3 ROWS ONLY) AS s WHERE name <> 'offset 1 rows' OFFSET 1 ROWS"
+            ),
+            "SELECT * FROM (SELECT id FROM t ORDER BY id LIMIT 3) AS s WHERE name <> 'offset 1 rows' LIMIT -1 OFFSET 1"
+        );
+        assert_eq!(
+            PaginationTranslator::translate_query("SELECT * FROM t OFFSET 1 ROWS FOR UPDATE"),
+            "SELECT * FROM t LIMIT -1 OFFSET 1 FOR UPDATE"
+        );
+    }
+
+    #[test]
+    fn test_pagination_parameters() {
+        assert_eq!(
+            PaginationTranslator::pagination_parameters("SELECT * FROM t WHERE id > $1 ORDER BY id OFFSET $2 ROWS FETCH FIRST $3 ROWS ONLY"),
+            vec![2, 3]
+        );
+        assert_eq!(PaginationTranslator::pagination_parameters("SELECT * FROM t LIMIT $1"), vec![1]);
+        assert!(PaginationTranslator::pagination_parameters("SELECT * FROM t WHERE id = $1").is_empty());
+    }
+
+    #[test]
+    fn test_unsupported_left_alone() {
+        let query = "SELECT * FROM t ORDER BY score FETCH FIRST 3 ROWS WITH TIES";
+        assert_eq!(PaginationTranslator::translate_query(query), query);
+
+        let query = "SELECT offset_value FROM t";
+        assert_eq!(PaginationTranslator::translate_query(query), query);
+    }
+}
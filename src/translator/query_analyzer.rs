@@ -66,7 +66,12 @@ impl QueryAnalyzer {
                query.contains("NOW()") || query.contains("now()") ||
                query.contains("CURRENT_DATE") || query.contains("current_date") ||
                query.contains("CURRENT_TIME") || query.contains("current_time") ||
-               query.contains("CURRENT_TIMESTAMP") || query.contains("current_timestamp") {
+               query.contains("CURRENT_TIMESTAMP") || query.contains("current_timestamp") ||
+               super::InsertTranslator::contains_interval_literal(query) ||
+               super::InsertTranslator::contains_range_literal(query) ||
+               super::InsertTranslator::contains_network_literal(query) ||
+               super::InsertTranslator::contains_bytea_literal(query) ||
+               super::InsertTranslator::contains_xml_literal(query) {
                 flags |= TranslationFlags::INSERT_DATETIME;
             }
             
@@ -76,14 +81,16 @@ impl QueryAnalyzer {
             }
         }
         
-        // Check for datetime functions (not in INSERT)
-        if !flags.contains(TranslationFlags::INSERT_DATETIME)
+        // Check for datetime functions (not in INSERT, apart from interval literals
+        // inside expressions such as now() + INTERVAL '1 day')
+        if (flags.contains(TranslationFlags::INSERT_DATETIME) && query_lower.contains("interval"))
+            || (!flags.contains(TranslationFlags::INSERT_DATETIME)
             && (query_lower.contains("date(") || query_lower.contains("time(") ||
                 query_lower.contains("timestamp") || query_lower.contains("interval") ||
                 query_lower.contains("now()") || query_lower.contains("current_date") ||
                 query_lower.contains("current_time") || query_lower.contains("extract(") ||
                 query_lower.contains("date_trunc(") || query_lower.contains("age(") ||
-                query_lower.contains("at time zone")) {
+                query_lower.contains("at time zone"))) {
             flags |= TranslationFlags::DATETIME;
         }
         
@@ -98,13 +105,13 @@ impl QueryAnalyzer {
         // Check for array operations (more expensive check)
         if query.contains("@>") || query.contains("<@") || query.contains("&&") || 
            query.contains("||") || query.contains("[") || query.contains("ARRAY[") || 
-           query.contains("array[") || query.contains(" ANY(") || query.contains(" any(") ||
-           query.contains(" ALL(") || query.contains(" all(") {
+           query.contains("array[") || query_lower.contains(" any") || query_lower.contains(" all") {
             // Additional checks for array functions
             if query_lower.contains("array_") || query_lower.contains("unnest") ||
-               query.contains("ARRAY[") || query.contains("array[") ||
-               query.contains(" ANY(") || query.contains(" any(") ||
-               query.contains(" ALL(") || query.contains(" all(") {
+               query.contains("ARRAY[") || query.contains("array[") || query.contains("&&") ||
+               query_lower.contains(" any(") || query_lower.contains(" any (") ||
+               query_lower.contains(" all(") || query_lower.contains(" all (") ||
+               crate::translator::ArrayTranslator::contains_subscript(query) {
                 flags |= TranslationFlags::ARRAY;
             }
         }
@@ -114,13 +121,15 @@ impl QueryAnalyzer {
             flags |= TranslationFlags::ARRAY_AGG;
         }
         
-        // Check for unnest
-        if query_lower.contains("unnest") {
+        // Check for unnest, generate_series and the split functions expanded the same way
+        if query_lower.contains("unnest") || query_lower.contains("_to_table") || query_lower.contains("generate_series") {
             flags |= TranslationFlags::UNNEST;
         }
         
-        // Check for json_each/jsonb_each
-        if query_lower.contains("json_each") || query_lower.contains("jsonb_each") {
+        // Check for json_each/jsonb_each and the set-returning JSON functions expanded over it
+        if query_lower.contains("json_each") || query_lower.contains("jsonb_each")
+            || query_lower.contains("_array_elements") || query_lower.contains("_object_keys")
+            || query_lower.contains("jsonb_path_query") {
             flags |= TranslationFlags::JSON_EACH;
         }
         
@@ -215,6 +224,9 @@ mod tests {
     fn test_array_detection() {
         let flags = QueryAnalyzer::analyze("SELECT ARRAY[1,2,3] FROM users");
         assert!(flags.contains(TranslationFlags::ARRAY));
+        
+        let flags = QueryAnalyzer::analyze("SELECT tags[1], scores[2:3] FROM users");
+        assert!(flags.contains(TranslationFlags::ARRAY));
     }
     
     #[test]
@@ -15,12 +15,12 @@ impl SchemaPrefixTranslator {
         // List of known pg_catalog tables that we have views for
         let catalog_tables = [
             "pg_class", "pg_namespace", "pg_attribute", "pg_type", 
-            "pg_constraint", "pg_index", "pg_attrdef", "pg_am",
+            "pg_constraint", "pg_indexes", "pg_index", "pg_attrdef", "pg_am",
             "pg_enum", "pg_range",
             // Newly supported minimal views
             "pg_database", "pg_stat_database", "pg_stat_activity",
             "pg_stat_user_tables", "pg_statio_user_tables",
-            "pg_foreign_data_wrapper"
+            "pg_foreign_data_wrapper", "pg_proc", "pg_description", "pg_trigger"
         ];
         
         for table in &catalog_tables {
@@ -35,7 +35,8 @@ impl SchemaPrefixTranslator {
             "pg_table_is_visible", "pg_get_userbyid", "pg_get_constraintdef",
             "format_type", "pg_get_expr", "pg_get_indexdef", "version",
             "current_database", "current_schema", "current_user", "session_user",
-            "pg_backend_pid", "pg_is_in_recovery", "current_schemas"
+            "pg_backend_pid", "pg_is_in_recovery", "current_schemas",
+            "col_description", "obj_description", "shobj_description"
         ];
 
         // Also normalize pg_size_pretty
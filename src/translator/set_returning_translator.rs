@@ -0,0 +1,454 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sqlparser::ast::{Expr, Ident, Query, Select, SelectItem, SetExpr, Statement, TableFactor, TableWithJoins};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use tracing::debug;
+use crate::types::PgType;
+use super::unnest_translator::UnnestTranslator;
+use super::values_translator::{find_closing_paren, split_top_level};
+use super::{ColumnTypeHint, ExpressionType, TranslationMetadata};
+
+/// A set-returning function call opening its argument list
+static SRF_CALL_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(unnest|generate_series|regexp_split_to_table|string_to_table|jsonb?_array_elements(?:_text)?|jsonb?_object_keys|jsonb_path_query)\s*\(").unwrap()
+});
+
+static SUBQUERY_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\bSELECT\b").unwrap());
+
+/// A json_each() member as JSON text: strings stay quoted, objects and arrays as-is
+const JSON_ELEMENT: &str = "CASE type WHEN 'true' THEN 'true' WHEN 'false' THEN 'false' ELSE json_quote(value) END";
+
+/// A json_each() member as text, with JSON null as NULL
+const JSON_TEXT_ELEMENT: &str = "CASE type WHEN 'true' THEN 'true' WHEN 'false' THEN 'false' ELSE value END";
+
+/// Prefix of the aliases given to the tables the functions are expanded into
+const ALIAS_PREFIX: &str = "__pgsqlite_srf";
+
+/// Translator for set-returning functions in a select list
+///
+/// PostgreSQL runs `SELECT id, unnest(tags) FROM t` once per element of `tags`.
+/// SQLite has no set-returning scalars, so each call becomes a table-valued function
+/// joined laterally after the FROM items (`__pgsqlite_unnest()` for arrays,
+/// `__pgsqlite_series()` for series) and the call is replaced by that table's single
+/// column, named so it can't make the user's column names ambiguous. The
+/// set-returning JSON functions are unnested too, from a JSON array of their
+/// elements. Several calls in one select list advance in lockstep: the row count
+/// is that of the longest and the shorter ones are NULL-padded, as in PostgreSQL
+/// 10 and later.
+///
+/// `FROM generate_series(...)` on constants also becomes a derived table, so its
+/// column gets PostgreSQL's name instead of `value`.
+pub struct SetReturningTranslator;
+
+/// One call taken out of a select list
+struct SrfCall {
+    function: String,
+    args: Vec<String>,
+    alias: String,
+}
+
+impl SrfCall {
+    /// The JSON array the call's elements come from, for all but generate_series
+    fn array(&self) -> String {
+        let args = self.args.join(", ");
+        match self.function.as_str() {
+            "regexp_split_to_table" => format!("regexp_split_to_array({args})"),
+            "string_to_table" => format!("string_to_array({args})"),
+            "jsonb_path_query" => Self::json_elements(&format!("jsonb_path_query_array({args})"), JSON_ELEMENT),
+            function if function.ends_with("_object_keys") => Self::json_elements(&args, "key"),
+            function if function.ends_with("_array_elements_text") => Self::json_elements(&args, JSON_TEXT_ELEMENT),
+            function if function.ends_with("_array_elements") => Self::json_elements(&args, JSON_ELEMENT),
+            _ => args,
+        }
+    }
+
+    /// A JSON array of `element` (an expression over json_each()'s columns) for
+    /// each member of `json`, as text, so JSON results keep their JSON syntax
+    fn json_elements(json: &str, element: &str) -> String {
+        format!("(SELECT json_group_array(({element}) || '') FROM json_each({json}))")
+    }
+
+    /// The table the call expands into
+    fn source(&self) -> String {
+        match self.function.as_str() {
+            "generate_series" => format!("__pgsqlite_series({})", self.args.join(", ")),
+            _ => format!("__pgsqlite_unnest({})", self.array()),
+        }
+    }
+
+    /// The table of the call's row at the 1-based position `at`
+    fn source_at(&self, at: &str) -> String {
+        match self.function.as_str() {
+            "generate_series" if self.args.len() == 2 => format!("__pgsqlite_series({}, 1, {at})", self.args.join(", ")),
+            "generate_series" => format!("__pgsqlite_series({}, {at})", self.args.join(", ")),
+            _ => format!("__pgsqlite_unnest({}, {at})", self.array()),
+        }
+    }
+
+    /// The number of rows the call returns
+    fn count(&self) -> String {
+        match self.function.as_str() {
+            "generate_series" => format!("(SELECT count(*) FROM {})", self.source()),
+            _ => format!("coalesce(json_array_length({}), 0)", self.array()),
+        }
+    }
+
+    /// The output type, where the function alone decides it
+    fn pg_type(&self) -> Option<PgType> {
+        match self.function.as_str() {
+            "generate_series" if self.args.iter().any(|arg| arg.parse::<i64>().is_ok_and(|n| i32::try_from(n).is_err())) => Some(PgType::Int8),
+            "generate_series" => Some(PgType::Int4),
+            "regexp_split_to_table" | "string_to_table" => Some(PgType::Text),
+            function if function.ends_with("_text") || function.ends_with("_object_keys") => Some(PgType::Text),
+            function if function.starts_with("jsonb") => Some(PgType::Jsonb),
+            function if function.starts_with("json_") => Some(PgType::Json),
+            _ => None,
+        }
+    }
+}
+
+impl SetReturningTranslator {
+    /// Check if the query calls a set-returning function
+    pub fn needs_translation(query: &str) -> bool {
+        SRF_CALL_REGEX.is_match(query)
+    }
+
+    /// Parameter numbers (1-based) passed straight to generate_series(), which
+    /// takes integer bounds and step
+    pub fn series_parameters(query: &str) -> Vec<usize> {
+        SRF_CALL_REGEX.captures_iter(query)
+            .filter(|caps| caps[1].eq_ignore_ascii_case("generate_series"))
+            .filter_map(|caps| {
+                let open = caps.get(0).unwrap().end() - 1;
+                find_closing_paren(query.as_bytes(), open).map(|close| split_top_level(&query[open + 1..close]))
+            })
+            .flatten()
+            .filter_map(|arg| arg.strip_prefix('$')?.parse().ok())
+            .collect()
+    }
+
+    /// Expand set-returning functions in select lists into lateral joins. Queries
+    /// that don't parse, or have nothing to expand, come back unchanged.
+    pub fn translate_query(query: &str) -> (String, TranslationMetadata) {
+        let mut rewriter = Rewriter { next_alias: 0, changed: false, metadata: TranslationMetadata::new() };
+        let Ok(mut statements) = Parser::parse_sql(&PostgreSqlDialect {}, query) else {
+            return (query.to_string(), rewriter.metadata);
+        };
+        let [Statement::Query(parsed)] = statements.as_mut_slice() else {
+            return (query.to_string(), rewriter.metadata);
+        };
+        rewriter.query(parsed);
+        if !rewriter.changed {
+            return (query.to_string(), rewriter.metadata);
+        }
+        let translated = parsed.to_string();
+        debug!("Expanded set-returning functions: {} -> {}", query, translated);
+        (translated, rewriter.metadata)
+    }
+}
+
+struct Rewriter {
+    next_alias: usize,
+    changed: bool,
+    metadata: TranslationMetadata,
+}
+
+impl Rewriter {
+    fn alias(&mut self) -> String {
+        self.next_alias += 1;
+        format!("{ALIAS_PREFIX}{}", self.next_alias)
+    }
+
+    fn query(&mut self, query: &mut Query) {
+        if let Some(with) = &mut query.with {
+            for cte in &mut with.cte_tables {
+                self.query(&mut cte.query);
+            }
+        }
+        self.set_expr(&mut query.body);
+    }
+
+    fn set_expr(&mut self, body: &mut SetExpr) {
+        match body {
+            SetExpr::Select(select) => self.select(select),
+            SetExpr::Query(query) => self.query(query),
+            SetExpr::SetOperation { left, right, .. } => {
+                self.set_expr(left);
+                self.set_expr(right);
+            }
+            _ => {}
+        }
+    }
+
+    fn select(&mut self, select: &mut Select) {
+        for table in &mut select.from {
+            self.table_with_joins(table);
+        }
+        for expr in select.selection.iter_mut().chain(select.having.iter_mut()) {
+            self.subqueries(expr);
+        }
+
+        let mut calls = Vec::new();
+        let mut items = Vec::new();
+        for (i, item) in select.projection.iter().enumerate() {
+            let (expr, alias) = match item {
+                SelectItem::UnnamedExpr(expr) => (expr, None),
+                SelectItem::ExprWithAlias { expr, alias } => (expr, Some(alias.clone())),
+                _ => continue,
+            };
+            let text = expr.to_string();
+            // Calls inside a subquery belong to that subquery
+            if !SRF_CALL_REGEX.is_match(&text) || SUBQUERY_REGEX.is_match(&text) {
+                continue;
+            }
+            let first_call = calls.len();
+            let replaced = self.take_calls(&text, &mut calls);
+            if calls.len() == first_call {
+                continue;
+            }
+            let Some(expr) = parse_expr(&replaced) else {
+                calls.truncate(first_call);
+                continue;
+            };
+
+            // A bare call is named after the function, and typed by it
+            let bare_call = calls.len() == first_call + 1 && replaced == format!("{}.__pgsqlite_value", calls[first_call].alias);
+            let alias = match alias {
+                None if bare_call => Some(Ident::new(&calls[first_call].function)),
+                alias => alias,
+            };
+            if let (true, Some(alias), Some(pg_type)) = (bare_call, &alias, calls[first_call].pg_type()) {
+                self.metadata.add_hint(alias.value.clone(), ColumnTypeHint::expression(None, pg_type, ExpressionType::Other));
+            }
+            items.push((i, match alias {
+                Some(alias) => SelectItem::ExprWithAlias { expr, alias },
+                None => SelectItem::UnnamedExpr(expr),
+            }));
+        }
+        if calls.is_empty() {
+            return;
+        }
+
+        let joined = match calls.as_slice() {
+            [call] => format!("{} AS {}", call.source(), call.alias),
+            _ => {
+                // Number the rows up to the longest set and pick each set's row by position
+                let index = self.alias();
+                let counts: Vec<String> = calls.iter().map(SrfCall::count).collect();
+                let mut joined = format!("__pgsqlite_series(1, max({})) AS {index}", counts.join(", "));
+                for call in &calls {
+                    joined.push_str(&format!(" LEFT JOIN {} AS {} ON true", call.source_at(&format!("{index}.__pgsqlite_value")), call.alias));
+                }
+                joined
+            }
+        };
+        if let Some(table) = parse_from_item(&joined) {
+            for (i, item) in items {
+                select.projection[i] = item;
+            }
+            select.from.push(table);
+            self.changed = true;
+        }
+    }
+
+    /// Replace the set-returning calls in a select item with the value column of
+    /// the table each is expanded into
+    fn take_calls(&mut self, text: &str, calls: &mut Vec<SrfCall>) -> String {
+        let mut result = String::new();
+        let mut pos = 0;
+        while let Some(caps) = SRF_CALL_REGEX.captures_at(text, pos) {
+            let whole = caps.get(0).unwrap();
+            let in_literal = text[..whole.start()].bytes().filter(|&b| b == b'\'').count() % 2 == 1;
+            let Some(close) = find_closing_paren(text.as_bytes(), whole.end() - 1) else {
+                break;
+            };
+            if in_literal || text[..whole.start()].ends_with('.') {
+                result.push_str(&text[pos..whole.end()]);
+                pos = whole.end();
+                continue;
+            }
+            let call = SrfCall {
+                function: caps[1].to_lowercase(),
+                args: split_top_level(&text[whole.end()..close]),
+                alias: self.alias(),
+            };
+            result.push_str(&text[pos..whole.start()]);
+            result.push_str(&format!("{}.__pgsqlite_value", call.alias));
+            calls.push(call);
+            pos = close + 1;
+        }
+        result.push_str(&text[pos..]);
+        result
+    }
+
+    fn table_with_joins(&mut self, table: &mut TableWithJoins) {
+        self.table_factor(&mut table.relation);
+        for join in &mut table.joins {
+            self.table_factor(&mut join.relation);
+        }
+    }
+
+    fn table_factor(&mut self, relation: &mut TableFactor) {
+        match relation {
+            TableFactor::Derived { subquery, .. } => self.query(subquery),
+            TableFactor::NestedJoin { table_with_joins, .. } => self.table_with_joins(table_with_joins),
+            TableFactor::Table { name, alias, args: Some(args), with_ordinality, .. }
+                if name.to_string().eq_ignore_ascii_case("generate_series") =>
+            {
+                // Arguments from other FROM items need the table-valued form to correlate
+                let args = args.args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>().join(", ");
+                if UnnestTranslator::references_columns(&args) {
+                    return;
+                }
+                let table_alias = alias.as_ref().map_or_else(|| Ident::new("generate_series"), |alias| alias.name.clone());
+                let mut columns = alias.iter().flat_map(|alias| alias.columns.iter().map(|column| column.name.clone()));
+                let value = columns.next().unwrap_or_else(|| table_alias.clone());
+                let ordinality = if *with_ordinality {
+                    format!(", rowid AS {}", columns.next().unwrap_or_else(|| Ident::new("ordinality")))
+                } else {
+                    String::new()
+                };
+                let derived = format!("(SELECT value AS {value}{ordinality} FROM generate_series({args})) AS {table_alias}");
+                if let Some(table) = parse_from_item(&derived) {
+                    *relation = table.relation;
+                    self.changed = true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Rewrite the subqueries of a WHERE or HAVING condition
+    fn subqueries(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Subquery(query) | Expr::Exists { subquery: query, .. } => self.query(query),
+            Expr::InSubquery { expr, subquery, .. } => {
+                self.subqueries(expr);
+                self.set_expr(subquery);
+            }
+            Expr::BinaryOp { left, right, .. } => {
+                self.subqueries(left);
+                self.subqueries(right);
+            }
+            Expr::UnaryOp { expr, .. } | Expr::Nested(expr) => self.subqueries(expr),
+            _ => {}
+        }
+    }
+}
+
+fn parse_expr(text: &str) -> Option<Expr> {
+    Parser::new(&PostgreSqlDialect {}).try_with_sql(text).ok()?.parse_expr().ok()
+}
+
+fn parse_from_item(text: &str) -> Option<TableWithJoins> {
+    match Parser::parse_sql(&PostgreSqlDialect {}, &format!("SELECT * FROM {text}")).ok()?.pop()? {
+        Statement::Query(query) => match *query.body {
+            SetExpr::Select(mut select) if select.from.len() == 1 => select.from.pop(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translate(sql: &str) -> String {
+        SetReturningTranslator::translate_query(sql).0
+    }
+
+    #[test]
+    fn test_single_call() {
+        assert_eq!(
+            translate("SELECT id, unnest(tags) FROM articles"),
+            "SELECT id, __pgsqlite_srf1.__pgsqlite_value AS unnest FROM articles, __pgsqlite_unnest(tags) AS __pgsqlite_srf1"
+        );
+        assert_eq!(
+            translate("SELECT generate_series(1, 3) * 2 AS n"),
+            "SELECT __pgsqlite_srf1.__pgsqlite_value * 2 AS n FROM __pgsqlite_series(1, 3) AS __pgsqlite_srf1"
+        );
+        let (sql, metadata) = SetReturningTranslator::translate_query("SELECT generate_series(1, 3) AS n");
+        assert_eq!(sql, "SELECT __pgsqlite_srf1.__pgsqlite_value AS n FROM __pgsqlite_series(1, 3) AS __pgsqlite_srf1");
+        assert_eq!(metadata.get_hint("n").and_then(|hint| hint.suggested_type.clone()), Some(PgType::Int4));
+    }
+
+    #[test]
+    fn test_lockstep_calls() {
+        assert_eq!(
+            translate("SELECT unnest(a), generate_series(1, 3) FROM t"),
+            "SELECT __pgsqlite_srf1.__pgsqlite_value AS unnest, __pgsqlite_srf2.__pgsqlite_value AS generate_series \
+             FROM t, __pgsqlite_series(1, max(coalesce(json_array_length(a), 0), (SELECT count(*) FROM __pgsqlite_series(1, 3)))) AS __pgsqlite_srf3 \
+             LEFT JOIN __pgsqlite_unnest(a, __pgsqlite_srf3.__pgsqlite_value) AS __pgsqlite_srf1 ON true \
+             LEFT JOIN __pgsqlite_series(1, 3, 1, __pgsqlite_srf3.__pgsqlite_value) AS __pgsqlite_srf2 ON true"
+        );
+    }
+
+    #[test]
+    fn test_nested_queries() {
+        assert_eq!(
+            translate("SELECT x FROM t WHERE x IN (SELECT unnest(a) FROM u)"),
+            "SELECT x FROM t WHERE x IN (SELECT __pgsqlite_srf1.__pgsqlite_value AS unnest FROM u, __pgsqlite_unnest(a) AS __pgsqlite_srf1)"
+        );
+        assert_eq!(
+            translate("WITH s AS (SELECT string_to_table(v, ',') AS part FROM t) SELECT part FROM s"),
+            "WITH s AS (SELECT __pgsqlite_srf1.__pgsqlite_value AS part FROM t, __pgsqlite_unnest(string_to_array(v, ',')) AS __pgsqlite_srf1) SELECT part FROM s"
+        );
+    }
+
+    #[test]
+    fn test_json_functions() {
+        let (sql, metadata) = SetReturningTranslator::translate_query("SELECT id, jsonb_object_keys(doc) FROM t");
+        assert_eq!(
+            sql,
+            "SELECT id, __pgsqlite_srf1.__pgsqlite_value AS jsonb_object_keys \
+             FROM t, __pgsqlite_unnest((SELECT json_group_array((key) || '') FROM json_each(doc))) AS __pgsqlite_srf1"
+        );
+        assert_eq!(metadata.get_hint("jsonb_object_keys").and_then(|hint| hint.suggested_type.clone()), Some(PgType::Text));
+
+        let (sql, metadata) = SetReturningTranslator::translate_query("SELECT jsonb_path_query(doc, '$.a[*]') AS v FROM t");
+        assert_eq!(
+            sql,
+            format!("SELECT __pgsqlite_srf1.__pgsqlite_value AS v \
+                     FROM t, __pgsqlite_unnest((SELECT json_group_array(({JSON_ELEMENT}) || '') FROM json_each(jsonb_path_query_array(doc, '$.a[*]')))) AS __pgsqlite_srf1")
+        );
+        assert_eq!(metadata.get_hint("v").and_then(|hint| hint.suggested_type.clone()), Some(PgType::Jsonb));
+
+        let (_, metadata) = SetReturningTranslator::translate_query("SELECT json_array_elements(doc) AS e, jsonb_array_elements_text(doc) AS t FROM t");
+        assert_eq!(metadata.get_hint("e").and_then(|hint| hint.suggested_type.clone()), Some(PgType::Json));
+        assert_eq!(metadata.get_hint("t").and_then(|hint| hint.suggested_type.clone()), Some(PgType::Text));
+    }
+
+    #[test]
+    fn test_generate_series_in_from() {
+        assert_eq!(
+            translate("SELECT n FROM generate_series(1, 3) AS n"),
+            "SELECT n FROM (SELECT value AS n FROM generate_series(1, 3)) AS n"
+        );
+        assert_eq!(
+            translate("SELECT * FROM generate_series(5, 1, -2) WITH ORDINALITY AS s(v, i)"),
+            "SELECT * FROM (SELECT value AS v, rowid AS i FROM generate_series(5, 1, -2)) AS s"
+        );
+        // Correlated arguments stay table-valued
+        let sql = "SELECT t.n, s.value FROM t, generate_series(1, t.n) AS s";
+        assert_eq!(translate(sql), sql);
+    }
+
+    #[test]
+    fn test_series_parameters() {
+        assert_eq!(SetReturningTranslator::series_parameters("SELECT generate_series($1, $2 + 1, $3), $4 FROM generate_series(1, $5)"), [1, 3, 5]);
+        assert!(SetReturningTranslator::series_parameters("SELECT unnest($1)").is_empty());
+    }
+
+    #[test]
+    fn test_left_alone() {
+        for sql in [
+            "SELECT name FROM users",
+            "SELECT 'unnest(x)' FROM t",
+            "SELECT (SELECT max(v) FROM unnest(a) AS v) FROM t",
+        ] {
+            assert_eq!(translate(sql), sql);
+        }
+    }
+}
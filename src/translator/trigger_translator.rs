@@ -0,0 +1,844 @@
+use crate::error::PgError;
+use crate::query::sql_utils::{quote_identifier, quote_literal};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+static ASSIGNMENT_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^NEW\s*\.\s*("(?:[^"]|"")+"|\w+)\s*:?=\s*(.+)$"#).unwrap()
+});
+
+static RETURN_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)^RETURN\s+(NEW|OLD|NULL)$").unwrap());
+
+static RAISE_LEVEL_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)^RAISE(?:\s+(EXCEPTION|WARNING|NOTICE|INFO|LOG|DEBUG)\b)?\s*(.*)$").unwrap()
+});
+
+static RAISE_OPTION_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)^(\w+)\s*:?=\s*(.+)$").unwrap());
+
+/// `NEW.column` and `OLD.column` references
+static ROW_REFERENCE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)\b(NEW|OLD)\s*\.\s*("(?:[^"]|"")+"|\w+)"#).unwrap()
+});
+
+static TRIGGER_VARIABLE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\bTG_(OP|WHEN|LEVEL|NAME|TABLE_NAME|RELNAME|TABLE_SCHEMA)\b").unwrap()
+});
+
+static IS_NOT_DISTINCT_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\bIS\s+NOT\s+DISTINCT\s+FROM\b").unwrap());
+
+static IS_DISTINCT_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\bIS\s+DISTINCT\s+FROM\b").unwrap());
+
+/// Table the generated triggers write a row to while they apply assignments to NEW,
+/// so the UPDATE doing it does not fire the PostgreSQL triggers of the table again
+pub const TRIGGER_WRITES_TABLE: &str = "__pgsqlite_trigger_writes";
+
+/// When a trigger fires relative to the row change
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerTiming {
+    Before,
+    After,
+}
+
+/// A row change a trigger fires on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerEvent {
+    Insert,
+    Update,
+    Delete,
+}
+
+impl TriggerEvent {
+    pub fn keyword(self) -> &'static str {
+        match self {
+            TriggerEvent::Insert => "INSERT",
+            TriggerEvent::Update => "UPDATE",
+            TriggerEvent::Delete => "DELETE",
+        }
+    }
+}
+
+/// A row-level trigger as CREATE TRIGGER defines it
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriggerDefinition {
+    pub name: String,
+    pub table: String,
+    pub timing: TriggerTiming,
+    pub events: Vec<TriggerEvent>,
+    /// The columns of `UPDATE OF`, empty when any update fires the trigger
+    pub update_columns: Vec<String>,
+    pub when: Option<String>,
+}
+
+impl TriggerDefinition {
+    /// pg_trigger.tgtype: row-level, BEFORE and the event bits
+    pub fn tgtype(&self) -> i64 {
+        let mut tgtype = 1;
+        if self.timing == TriggerTiming::Before {
+            tgtype |= 2;
+        }
+        for event in &self.events {
+            tgtype |= match event {
+                TriggerEvent::Insert => 4,
+                TriggerEvent::Delete => 8,
+                TriggerEvent::Update => 16,
+            };
+        }
+        tgtype
+    }
+}
+
+/// What a trigger function returns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReturnValue {
+    New,
+    Old,
+    Null,
+}
+
+/// A statement of a PL/pgSQL trigger function body
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlStatement {
+    /// `NEW.column := expression`
+    Assign { column: String, expression: String },
+    /// `IF ... THEN ... [ELSIF ... THEN ...] [ELSE ...] END IF`
+    If { branches: Vec<(String, Vec<PlStatement>)>, otherwise: Vec<PlStatement> },
+    Return(ReturnValue),
+    /// `RAISE EXCEPTION 'format', args [USING ERRCODE = ...]`
+    Raise { code: String, format: String, args: Vec<String> },
+    /// An INSERT, UPDATE or DELETE run as part of the trigger
+    Sql(String),
+}
+
+/// A piece of SQL in a trigger function that goes through the query translators
+pub enum SqlFragment<'a> {
+    Expression(&'a mut String),
+    Statement(&'a mut String),
+}
+
+/// Translator for PL/pgSQL trigger functions
+///
+/// SQLite triggers run plain SQL statements and cannot change the row being
+/// written, so a function body is limited to what maps onto them: assignments to
+/// NEW columns, IF/ELSIF/ELSE, RETURN, RAISE EXCEPTION and INSERT/UPDATE/DELETE
+/// statements. Each PostgreSQL trigger becomes one SQLite trigger per event, with
+/// the conditions of the IF branches a statement sits in as its WHERE clause.
+///
+/// A BEFORE trigger's assignments to NEW are applied by a second, AFTER trigger
+/// that updates the written row; RETURN NULL skips the row with RAISE(IGNORE).
+/// Other languages, DECLARE sections, loops and exception blocks are refused.
+pub struct TriggerTranslator;
+
+impl TriggerTranslator {
+    /// Parse a PL/pgSQL trigger function body
+    pub fn parse_body(body: &str) -> Result<Vec<PlStatement>, PgError> {
+        let body = crate::query::strip_sql_comments(body);
+        let mut parser = BodyParser { text: &body, pos: 0 };
+        parser.skip_label();
+        if parser.peek_keyword("DECLARE") {
+            return Err(unsupported("DECLARE sections in trigger functions are not supported"));
+        }
+        if !parser.eat_keyword("BEGIN") {
+            return Err(syntax_error("trigger function body must start with BEGIN"));
+        }
+        let statements = parser.block_body()?;
+        parser.eat(";");
+        parser.skip_whitespace();
+        if parser.pos < parser.text.len() {
+            return Err(syntax_error(&format!("syntax error at or near \"{}\"", parser.next_word())));
+        }
+        Ok(statements)
+    }
+
+    /// The expressions and statements of a parsed body, to run through the query translators
+    pub fn fragments(statements: &mut [PlStatement]) -> Vec<SqlFragment<'_>> {
+        let mut fragments = Vec::new();
+        for statement in statements {
+            match statement {
+                PlStatement::Assign { expression, .. } => fragments.push(SqlFragment::Expression(expression)),
+                PlStatement::If { branches, otherwise } => {
+                    for (condition, body) in branches {
+                        fragments.push(SqlFragment::Expression(condition));
+                        fragments.extend(Self::fragments(body));
+                    }
+                    fragments.extend(Self::fragments(otherwise));
+                }
+                PlStatement::Raise { args, .. } => fragments.extend(args.iter_mut().map(SqlFragment::Expression)),
+                PlStatement::Sql(sql) => fragments.push(SqlFragment::Statement(sql)),
+                PlStatement::Return(_) => {}
+            }
+        }
+        fragments
+    }
+
+    /// The CREATE TRIGGER statements running `body` for `definition`, named `name`
+    /// followed by the event
+    pub fn translate(definition: &TriggerDefinition, body: &[PlStatement], name: &str) -> Result<Vec<String>, PgError> {
+        let mut triggers = Vec::new();
+        for &event in &definition.events {
+            let mut lowering = Lowering::new(definition, event);
+            lowering.block(body, &[])?;
+
+            let mut when = format!("NOT EXISTS (SELECT 1 FROM {TRIGGER_WRITES_TABLE})");
+            if let Some(condition) = &definition.when {
+                when.push_str(&format!(" AND COALESCE(({}), 0)", Lowering::new(definition, event).specialize(condition)));
+            }
+            let columns = if event == TriggerEvent::Update && !definition.update_columns.is_empty() {
+                format!(" OF {}", definition.update_columns.iter().map(|c| quote_identifier(c)).collect::<Vec<_>>().join(", "))
+            } else {
+                String::new()
+            };
+            let event_name = event.keyword().to_lowercase();
+            let table = quote_identifier(&definition.table);
+            let timing = match definition.timing {
+                TriggerTiming::Before => "BEFORE",
+                TriggerTiming::After => "AFTER",
+            };
+
+            if !lowering.statements.is_empty() {
+                triggers.push(format!(
+                    "CREATE TRIGGER {} {timing} {}{columns} ON {table} FOR EACH ROW WHEN {when}\nBEGIN\n{};\nEND",
+                    quote_identifier(&format!("{name}_{event_name}")),
+                    event.keyword(),
+                    lowering.statements.join(";\n"),
+                ));
+            }
+            if !lowering.assignments.is_empty() {
+                let set = lowering.assignments.iter()
+                    .map(|(column, value)| format!("{} = {value}", quote_identifier(column)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                triggers.push(format!(
+                    "CREATE TRIGGER {} AFTER {}{columns} ON {table} FOR EACH ROW WHEN {when}\nBEGIN\n\
+                     INSERT INTO {TRIGGER_WRITES_TABLE} VALUES (1);\n\
+                     UPDATE {table} SET {set} WHERE rowid = NEW.rowid;\n\
+                     DELETE FROM {TRIGGER_WRITES_TABLE};\nEND",
+                    quote_identifier(&format!("{name}_{event_name}_assign")),
+                    event.keyword(),
+                ));
+            }
+        }
+        Ok(triggers)
+    }
+}
+
+/// Lowers a function body to the statements of the SQLite triggers for one event
+struct Lowering<'a> {
+    definition: &'a TriggerDefinition,
+    event: TriggerEvent,
+    /// Statements of the trigger firing with the PostgreSQL one
+    statements: Vec<String>,
+    /// Columns assigned through NEW and their final values, in order of first assignment
+    assignments: Vec<(String, String)>,
+    /// Conditions under which an earlier RETURN ended the function
+    returned: Vec<String>,
+    /// Set once an unconditional RETURN is reached
+    finished: bool,
+}
+
+impl<'a> Lowering<'a> {
+    fn new(definition: &'a TriggerDefinition, event: TriggerEvent) -> Self {
+        Lowering { definition, event, statements: Vec::new(), assignments: Vec::new(), returned: Vec::new(), finished: false }
+    }
+
+    fn block(&mut self, statements: &[PlStatement], path: &[String]) -> Result<(), PgError> {
+        for statement in statements {
+            if self.finished {
+                break;
+            }
+            let guard = self.guard(path);
+            match statement {
+                PlStatement::Assign { column, expression } => {
+                    // NEW cannot change once the row is written, and is NULL in DELETE triggers
+                    if self.definition.timing == TriggerTiming::After || self.event == TriggerEvent::Delete {
+                        continue;
+                    }
+                    let value = self.specialize(expression);
+                    let value = match &guard {
+                        Some(guard) => format!("CASE WHEN {guard} THEN ({value}) ELSE {} END", self.current_value(column)),
+                        None => format!("({value})"),
+                    };
+                    match self.assignments.iter_mut().find(|(assigned, _)| assigned == column) {
+                        Some((_, current)) => *current = value,
+                        None => self.assignments.push((column.clone(), value)),
+                    }
+                }
+                PlStatement::If { branches, otherwise } => {
+                    let mut not_taken: Vec<String> = Vec::new();
+                    for (condition, body) in branches {
+                        let condition = format!("COALESCE(({}), 0)", self.specialize(condition));
+                        let branch_path: Vec<String> = path.iter().chain(&not_taken).cloned().chain([condition.clone()]).collect();
+                        self.block(body, &branch_path)?;
+                        not_taken.push(format!("NOT {condition}"));
+                    }
+                    let else_path: Vec<String> = path.iter().chain(&not_taken).cloned().collect();
+                    self.block(otherwise, &else_path)?;
+                }
+                PlStatement::Return(value) => {
+                    if self.definition.timing == TriggerTiming::Before && self.skips_row(*value)? {
+                        self.statements.push(with_where("SELECT RAISE(IGNORE)", guard.as_deref()));
+                    }
+                    match guard {
+                        Some(guard) => self.returned.push(guard),
+                        None => self.finished = true,
+                    }
+                }
+                PlStatement::Raise { code, format, args } => {
+                    let message = self.raise_message(code, format, args)?;
+                    self.statements.push(with_where(&format!("SELECT RAISE(ABORT, {message})"), guard.as_deref()));
+                }
+                PlStatement::Sql(sql) => {
+                    let sql = self.specialize(sql);
+                    self.statements.push(match guard {
+                        Some(guard) => guarded_statement(&sql, &guard),
+                        None => sql,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a BEFORE trigger returning `value` leaves the row unchanged; NEW is
+    /// NULL when deleting and OLD when inserting, and returning NULL skips the row
+    fn skips_row(&self, value: ReturnValue) -> Result<bool, PgError> {
+        match (value, self.event) {
+            (ReturnValue::Null, _) | (ReturnValue::New, TriggerEvent::Delete) | (ReturnValue::Old, TriggerEvent::Insert) => Ok(true),
+            (ReturnValue::Old, TriggerEvent::Update) => Err(unsupported("RETURN OLD in BEFORE UPDATE triggers is not supported")),
+            _ => Ok(false),
+        }
+    }
+
+    /// The condition a statement runs under: its IF branches, and no RETURN before it
+    fn guard(&self, path: &[String]) -> Option<String> {
+        let conditions: Vec<String> = path.iter().cloned()
+            .chain(self.returned.iter().map(|returned| format!("NOT ({returned})")))
+            .collect();
+        (!conditions.is_empty()).then(|| conditions.join(" AND "))
+    }
+
+    /// The value NEW.column has at this point of the function
+    fn current_value(&self, column: &str) -> String {
+        match self.assignments.iter().find(|(assigned, _)| assigned == column) {
+            Some((_, value)) => value.clone(),
+            None => self.row_column("NEW", column),
+        }
+    }
+
+    /// A column of the written row. AFTER triggers read it back from the table, since
+    /// SQLite's NEW holds the values from before the assignments of BEFORE triggers.
+    fn row_column(&self, row: &str, column: &str) -> String {
+        if row == "NEW" && self.definition.timing == TriggerTiming::After {
+            format!(
+                "(SELECT {} FROM {} WHERE rowid = NEW.rowid)",
+                quote_identifier(column),
+                quote_identifier(&self.definition.table),
+            )
+        } else {
+            format!("{row}.{}", quote_identifier(column))
+        }
+    }
+
+    /// An expression or statement of the body as it runs for this event: trigger
+    /// variables become literals, a row the event has not becomes NULL, and NEW
+    /// columns take the values assigned so far
+    fn specialize(&self, sql: &str) -> String {
+        let sql = TRIGGER_VARIABLE_REGEX.replace_all(sql, |caps: &Captures| {
+            let value = match caps[1].to_uppercase().as_str() {
+                "OP" => self.event.keyword().to_string(),
+                "WHEN" => match self.definition.timing {
+                    TriggerTiming::Before => "BEFORE".to_string(),
+                    TriggerTiming::After => "AFTER".to_string(),
+                },
+                "LEVEL" => "ROW".to_string(),
+                "NAME" => self.definition.name.clone(),
+                "TABLE_SCHEMA" => "public".to_string(),
+                _ => self.definition.table.clone(),
+            };
+            quote_literal(&value)
+        });
+        let sql = ROW_REFERENCE_REGEX.replace_all(&sql, |caps: &Captures| {
+            let row = caps[1].to_uppercase();
+            let column = column_name(&caps[2]);
+            match (row.as_str(), self.event) {
+                ("NEW", TriggerEvent::Delete) | ("OLD", TriggerEvent::Insert) => "NULL".to_string(),
+                ("NEW", _) if self.assignments.iter().any(|(assigned, _)| *assigned == column) => {
+                    format!("({})", self.current_value(&column))
+                }
+                _ => self.row_column(&row, &column),
+            }
+        });
+        let sql = IS_NOT_DISTINCT_REGEX.replace_all(&sql, "IS");
+        IS_DISTINCT_REGEX.replace_all(&sql, "IS NOT").into_owned()
+    }
+
+    /// The error message of a RAISE as an SQL expression, with the SQLSTATE in front
+    /// for PgError::from_message to pick up
+    fn raise_message(&self, code: &str, format: &str, args: &[String]) -> Result<String, PgError> {
+        let mut parts = Vec::new();
+        let mut literal = format!("[raise {code}] ");
+        let mut args = args.iter();
+        let mut chars = format.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                literal.push(c);
+            } else if chars.next_if_eq(&'%').is_some() {
+                literal.push('%');
+            } else {
+                let arg = args.next().ok_or_else(|| syntax_error("too few parameters specified for RAISE"))?;
+                parts.push(quote_literal(&std::mem::take(&mut literal)));
+                parts.push(format!("COALESCE(CAST(({}) AS TEXT), '<NULL>')", self.specialize(arg)));
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(quote_literal(&literal));
+        }
+        Ok(parts.join(" || "))
+    }
+}
+
+/// Run an INSERT, UPDATE or DELETE only when `guard` holds
+fn guarded_statement(sql: &str, guard: &str) -> String {
+    if starts_with_keyword(sql, "INSERT") {
+        let source = top_level_position(sql, |rest, at_word| {
+            at_word && (starts_with_keyword(rest, "VALUES") || starts_with_keyword(rest, "SELECT"))
+        });
+        return match source {
+            Some(pos) => format!("{}SELECT * FROM ({}) WHERE {guard}", &sql[..pos], &sql[pos..]),
+            None => sql.to_string(),
+        };
+    }
+    match top_level_position(sql, |rest, at_word| at_word && starts_with_keyword(rest, "WHERE")) {
+        Some(pos) => format!("{} WHERE ({guard}) AND ({})", sql[..pos].trim_end(), sql[pos + "WHERE".len()..].trim()),
+        None => format!("{sql} WHERE {guard}"),
+    }
+}
+
+fn with_where(sql: &str, guard: Option<&str>) -> String {
+    match guard {
+        Some(guard) => format!("{sql} WHERE {guard}"),
+        None => sql.to_string(),
+    }
+}
+
+/// Walks a function body, statement by statement
+struct BodyParser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl BodyParser<'_> {
+    /// Statements up to the END of a BEGIN block, which is consumed with its label
+    fn block_body(&mut self) -> Result<Vec<PlStatement>, PgError> {
+        let statements = self.statements(&["END", "EXCEPTION"])?;
+        if self.peek_keyword("EXCEPTION") {
+            return Err(unsupported("EXCEPTION blocks in trigger functions are not supported"));
+        }
+        self.eat_keyword("END");
+        self.skip_whitespace();
+        if self.text[self.pos..].starts_with(|c: char| c.is_alphabetic() || c == '_') {
+            self.pos += self.next_word().len();
+        }
+        Ok(statements)
+    }
+
+    /// Statements up to, not including, one of the `terminators`
+    fn statements(&mut self, terminators: &[&str]) -> Result<Vec<PlStatement>, PgError> {
+        let mut statements = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.pos >= self.text.len() {
+                return Err(syntax_error("missing \"END\" at end of trigger function body"));
+            }
+            if terminators.iter().any(|terminator| self.peek_keyword(terminator)) {
+                return Ok(statements);
+            }
+            if self.eat_keyword("IF") {
+                statements.push(self.if_statement()?);
+            } else if self.eat_keyword("BEGIN") {
+                statements.extend(self.block_body()?);
+                self.expect(";")?;
+            } else {
+                let rest = &self.text[self.pos..];
+                let end = top_level_position(rest, |rest, _| rest.starts_with(';'))
+                    .ok_or_else(|| syntax_error(&format!("syntax error at end of statement \"{}\"", rest.trim())))?;
+                let statement = rest[..end].trim();
+                self.pos += end + 1;
+                statements.extend(parse_statement(statement)?);
+            }
+        }
+    }
+
+    /// IF, once its keyword is consumed, through END IF
+    fn if_statement(&mut self) -> Result<PlStatement, PgError> {
+        let mut branches = Vec::new();
+        let mut otherwise = Vec::new();
+        loop {
+            let condition = self.condition()?;
+            let body = self.statements(&["ELSIF", "ELSEIF", "ELSE", "END"])?;
+            branches.push((condition, body));
+            if self.eat_keyword("ELSIF") || self.eat_keyword("ELSEIF") {
+                continue;
+            }
+            if self.eat_keyword("ELSE") {
+                otherwise = self.statements(&["END"])?;
+            }
+            break;
+        }
+        self.eat_keyword("END");
+        if !self.eat_keyword("IF") {
+            return Err(syntax_error("missing \"IF\" after \"END\""));
+        }
+        self.expect(";")?;
+        Ok(PlStatement::If { branches, otherwise })
+    }
+
+    /// The condition of IF or ELSIF, through its THEN
+    fn condition(&mut self) -> Result<String, PgError> {
+        let rest = &self.text[self.pos..];
+        let then = top_level_position(rest, |rest, at_word| at_word && starts_with_keyword(rest, "THEN"))
+            .ok_or_else(|| syntax_error("missing \"THEN\" after IF condition"))?;
+        self.pos += then + "THEN".len();
+        Ok(rest[..then].trim().to_string())
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Skip the `<<label>>` of a block
+    fn skip_label(&mut self) {
+        self.skip_whitespace();
+        let rest = &self.text[self.pos..];
+        if rest.starts_with("<<")
+            && let Some(end) = rest.find(">>") {
+            self.pos += end + 2;
+        }
+    }
+
+    fn next_word(&self) -> &str {
+        let rest = self.text[self.pos..].trim_start();
+        let end = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+        &rest[..end.max(rest.chars().next().map_or(0, char::len_utf8))]
+    }
+
+    fn peek_keyword(&mut self, keyword: &str) -> bool {
+        self.skip_whitespace();
+        starts_with_keyword(&self.text[self.pos..], keyword)
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek_keyword(keyword);
+        if found {
+            self.pos += keyword.len();
+        }
+        found
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        let found = self.text[self.pos..].starts_with(token);
+        if found {
+            self.pos += token.len();
+        }
+        found
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), PgError> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(syntax_error(&format!("syntax error at or near \"{}\"", self.next_word())))
+        }
+    }
+}
+
+/// One simple statement, without its semicolon; None for statements with no effect here
+fn parse_statement(statement: &str) -> Result<Option<PlStatement>, PgError> {
+    if let Some(caps) = ASSIGNMENT_REGEX.captures(statement) {
+        return Ok(Some(PlStatement::Assign { column: column_name(&caps[1]), expression: caps[2].trim().to_string() }));
+    }
+    if let Some(caps) = RETURN_REGEX.captures(statement) {
+        let value = match caps[1].to_uppercase().as_str() {
+            "NEW" => ReturnValue::New,
+            "OLD" => ReturnValue::Old,
+            _ => ReturnValue::Null,
+        };
+        return Ok(Some(PlStatement::Return(value)));
+    }
+    if starts_with_keyword(statement, "RAISE") {
+        return parse_raise(statement);
+    }
+    if statement.eq_ignore_ascii_case("NULL") {
+        return Ok(None);
+    }
+    if ["INSERT", "UPDATE", "DELETE"].iter().any(|keyword| starts_with_keyword(statement, keyword)) {
+        return Ok(Some(PlStatement::Sql(statement.to_string())));
+    }
+    let words: Vec<&str> = statement.split_whitespace().take(4).collect();
+    Err(unsupported(&format!("unsupported PL/pgSQL statement in trigger function: {}", words.join(" "))))
+}
+
+/// RAISE EXCEPTION becomes an error; the other levels have no effect inside SQLite
+fn parse_raise(statement: &str) -> Result<Option<PlStatement>, PgError> {
+    let caps = RAISE_LEVEL_REGEX.captures(statement).ok_or_else(|| syntax_error("syntax error in RAISE"))?;
+    if caps.get(1).is_some_and(|level| !level.as_str().eq_ignore_ascii_case("EXCEPTION")) {
+        return Ok(None);
+    }
+    let rest = caps[2].trim();
+    let (arguments, options) = match top_level_position(rest, |rest, at_word| at_word && starts_with_keyword(rest, "USING")) {
+        Some(pos) => (rest[..pos].trim(), Some(rest[pos + "USING".len()..].trim())),
+        None => (rest, None),
+    };
+
+    let mut code = "P0001".to_string();
+    let mut message = None;
+    for option in options.map(split_top_level_commas).unwrap_or_default() {
+        let option_caps = RAISE_OPTION_REGEX.captures(option)
+            .ok_or_else(|| syntax_error(&format!("syntax error in RAISE option \"{option}\"")))?;
+        match option_caps[1].to_uppercase().as_str() {
+            "ERRCODE" => code = error_code(option_caps[2].trim())?,
+            "MESSAGE" => message = Some(string_literal(option_caps[2].trim())
+                .ok_or_else(|| unsupported("RAISE MESSAGE must be a string literal"))?),
+            _ => {}
+        }
+    }
+
+    let mut args = split_top_level_commas(arguments).into_iter();
+    let format = match args.next() {
+        Some(format) => string_literal(format)
+            .ok_or_else(|| unsupported("RAISE with a condition name instead of a message is not supported"))?,
+        None => message.clone().ok_or_else(|| syntax_error("RAISE EXCEPTION needs a message"))?,
+    };
+    let args: Vec<String> = args.map(str::to_string).collect();
+    let placeholders = format.replace("%%", "").matches('%').count();
+    if placeholders > args.len() {
+        return Err(syntax_error("too few parameters specified for RAISE"));
+    }
+    if placeholders < args.len() {
+        return Err(syntax_error("too many parameters specified for RAISE"));
+    }
+    Ok(Some(PlStatement::Raise { code, format, args }))
+}
+
+/// The SQLSTATE of an ERRCODE option, given as a code or a condition name
+fn error_code(value: &str) -> Result<String, PgError> {
+    let value = string_literal(value).ok_or_else(|| unsupported("RAISE ERRCODE must be a string literal"))?;
+    if value.len() == 5 && value.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Ok(value.to_uppercase());
+    }
+    let code = match value.to_lowercase().as_str() {
+        "raise_exception" => "P0001",
+        "no_data_found" => "P0002",
+        "data_exception" => "22000",
+        "invalid_parameter_value" => "22023",
+        "integrity_constraint_violation" => "23000",
+        "restrict_violation" => "23001",
+        "not_null_violation" => "23502",
+        "foreign_key_violation" => "23503",
+        "unique_violation" => "23505",
+        "check_violation" => "23514",
+        "insufficient_privilege" => "42501",
+        _ => return Err(unsupported(&format!("unrecognized exception condition \"{value}\""))),
+    };
+    Ok(code.to_string())
+}
+
+/// Byte offset of the first position in `text` outside string literals, quoted
+/// identifiers, parentheses and CASE expressions where `matches` holds. It is given
+/// the rest of the text and whether a word starts there.
+fn top_level_position(text: &str, matches: impl Fn(&str, bool) -> bool) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let (mut depth, mut cases, mut i) = (0usize, 0usize, 0usize);
+    while i < bytes.len() {
+        match bytes[i] {
+            quote @ (b'\'' | b'"') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    i += 1;
+                }
+            }
+            b'(' => depth += 1,
+            b')' => depth = depth.saturating_sub(1),
+            _ if depth == 0 && text.is_char_boundary(i) => {
+                let rest = &text[i..];
+                let at_word = i == 0 || !is_word_byte(bytes[i - 1]);
+                if at_word && starts_with_keyword(rest, "CASE") {
+                    cases += 1;
+                } else if at_word && cases > 0 && starts_with_keyword(rest, "END") {
+                    cases -= 1;
+                } else if cases == 0 && matches(rest, at_word) {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+fn split_top_level_commas(text: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while let Some(pos) = top_level_position(rest, |rest, _| rest.starts_with(',')) {
+        parts.push(rest[..pos].trim());
+        rest = &rest[pos + 1..];
+    }
+    if !rest.trim().is_empty() {
+        parts.push(rest.trim());
+    }
+    parts
+}
+
+fn is_word_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b == b'$' || b >= 0x80
+}
+
+fn starts_with_keyword(text: &str, keyword: &str) -> bool {
+    text.get(..keyword.len()).is_some_and(|prefix| prefix.eq_ignore_ascii_case(keyword))
+        && !text.as_bytes().get(keyword.len()).is_some_and(|&b| is_word_byte(b))
+}
+
+/// The value of a '...' literal
+fn string_literal(text: &str) -> Option<String> {
+    let inner = text.strip_prefix('\'')?.strip_suffix('\'')?;
+    Some(inner.replace("''", "'"))
+}
+
+/// A column name as written after `NEW.`, case-folded unless quoted
+fn column_name(identifier: &str) -> String {
+    match identifier.strip_prefix('"').and_then(|name| name.strip_suffix('"')) {
+        Some(quoted) => quoted.replace("\"\"", "\""),
+        None => identifier.to_lowercase(),
+    }
+}
+
+
+fn syntax_error(message: &str) -> PgError {
+    PgError::Generic { code: "42601".to_string(), message: message.to_string() }
+}
+
+fn unsupported(message: &str) -> PgError {
+    PgError::Generic { code: "0A000".to_string(), message: message.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition(timing: TriggerTiming, events: &[TriggerEvent]) -> TriggerDefinition {
+        TriggerDefinition {
+            name: "audit".to_string(),
+            table: "items".to_string(),
+            timing,
+            events: events.to_vec(),
+            update_columns: Vec::new(),
+            when: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_body() {
+        let body = TriggerTranslator::parse_body(r#"
+            BEGIN
+                -- keep the timestamps current
+                NEW.updated_at := now();
+                IF NEW.price < 0 THEN
+                    RAISE EXCEPTION 'price % is negative', NEW.price USING ERRCODE = 'check_violation';
+                ELSIF TG_OP = 'INSERT' THEN
+                    NEW."Created" = now();
+                ELSE
+                    RAISE NOTICE 'updated';
+                    NULL;
+                END IF;
+                INSERT INTO log (op) VALUES (TG_OP);
+                RETURN NEW;
+            END;
+        "#).unwrap();
+        assert_eq!(body, vec![
+            PlStatement::Assign { column: "updated_at".to_string(), expression: "now()".to_string() },
+            PlStatement::If {
+                branches: vec![
+                    ("NEW.price < 0".to_string(), vec![PlStatement::Raise {
+                        code: "23514".to_string(),
+                        format: "price % is negative".to_string(),
+                        args: vec!["NEW.price".to_string()],
+                    }]),
+                    ("TG_OP = 'INSERT'".to_string(), vec![
+                        PlStatement::Assign { column: "Created".to_string(), expression: "now()".to_string() },
+                    ]),
+                ],
+                otherwise: Vec::new(),
+            },
+            PlStatement::Sql("INSERT INTO log (op) VALUES (TG_OP)".to_string()),
+            PlStatement::Return(ReturnValue::New),
+        ]);
+    }
+
+    #[test]
+    fn test_parse_body_rejects_unsupported_statements() {
+        let error = |body: &str| match TriggerTranslator::parse_body(body) {
+            Err(PgError::Generic { code, .. }) => code,
+            other => panic!("{other:?}"),
+        };
+        assert_eq!(error("DECLARE n int; BEGIN RETURN NEW; END"), "0A000");
+        assert_eq!(error("BEGIN FOR r IN SELECT 1 LOOP NULL; END LOOP; RETURN NEW; END"), "0A000");
+        assert_eq!(error("BEGIN RETURN NEW; EXCEPTION WHEN others THEN RETURN NULL; END"), "0A000");
+        assert_eq!(error("BEGIN RAISE EXCEPTION 'a % b'; END"), "42601");
+        assert_eq!(error("BEGIN IF true RETURN NEW; END"), "42601");
+    }
+
+    #[test]
+    fn test_translate_before_assignments() {
+        let body = TriggerTranslator::parse_body(
+            "BEGIN NEW.updated_at := now(); NEW.version := NEW.version + 1; IF NEW.name IS DISTINCT FROM OLD.name THEN NEW.renamed := NEW.version; END IF; RETURN NEW; END"
+        ).unwrap();
+        let triggers = TriggerTranslator::translate(&definition(TriggerTiming::Before, &[TriggerEvent::Insert]), &body, "t1").unwrap();
+        assert_eq!(triggers, vec![
+            "CREATE TRIGGER \"t1_insert_assign\" AFTER INSERT ON \"items\" FOR EACH ROW WHEN NOT EXISTS (SELECT 1 FROM __pgsqlite_trigger_writes)\nBEGIN\n\
+             INSERT INTO __pgsqlite_trigger_writes VALUES (1);\n\
+             UPDATE \"items\" SET \"updated_at\" = (now()), \"version\" = (NEW.\"version\" + 1), \
+             \"renamed\" = CASE WHEN COALESCE((NEW.\"name\" IS NOT NULL), 0) THEN (((NEW.\"version\" + 1))) ELSE NEW.\"renamed\" END \
+             WHERE rowid = NEW.rowid;\n\
+             DELETE FROM __pgsqlite_trigger_writes;\nEND".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn test_translate_checks_and_statements() {
+        let body = TriggerTranslator::parse_body(
+            "BEGIN IF NEW.qty < 0 THEN RAISE EXCEPTION 'bad qty % for %', NEW.qty, NEW.id; END IF; \
+             IF TG_OP = 'DELETE' THEN INSERT INTO log (id, op) VALUES (OLD.id, TG_OP); RETURN OLD; END IF; \
+             UPDATE stock SET total = total + NEW.qty WHERE id = NEW.id; RETURN NULL; END"
+        ).unwrap();
+        let mut definition = definition(TriggerTiming::Before, &[TriggerEvent::Insert, TriggerEvent::Delete]);
+        definition.when = Some("NEW.qty <> 0".to_string());
+        let triggers = TriggerTranslator::translate(&definition, &body, "t2").unwrap();
+        assert_eq!(triggers.len(), 2);
+        assert_eq!(triggers[0],
+            "CREATE TRIGGER \"t2_insert\" BEFORE INSERT ON \"items\" FOR EACH ROW WHEN NOT EXISTS (SELECT 1 FROM __pgsqlite_trigger_writes) AND COALESCE((NEW.\"qty\" <> 0), 0)\nBEGIN\n\
+             SELECT RAISE(ABORT, '[raise P0001] bad qty ' || COALESCE(CAST((NEW.\"qty\") AS TEXT), '<NULL>') || ' for ' || COALESCE(CAST((NEW.\"id\") AS TEXT), '<NULL>')) WHERE COALESCE((NEW.\"qty\" < 0), 0);\n\
+             INSERT INTO log (id, op) SELECT * FROM (VALUES (NULL, 'INSERT')) WHERE COALESCE(('INSERT' = 'DELETE'), 0);\n\
+             SELECT RAISE(IGNORE) WHERE COALESCE(('INSERT' = 'DELETE'), 0);\n\
+             UPDATE stock SET total = total + NEW.\"qty\" WHERE (NOT (COALESCE(('INSERT' = 'DELETE'), 0))) AND (id = NEW.\"id\");\n\
+             SELECT RAISE(IGNORE) WHERE NOT (COALESCE(('INSERT' = 'DELETE'), 0));\nEND"
+        );
+        // OLD is the row being deleted, so RETURN OLD lets the delete go ahead
+        assert!(triggers[1].contains("VALUES (OLD.\"id\", 'DELETE')"), "{}", triggers[1]);
+        assert!(!triggers[1].contains("'DELETE' = 'DELETE'), 0);\nSELECT RAISE(IGNORE)"), "{}", triggers[1]);
+    }
+
+    #[test]
+    fn test_translate_after_trigger_reads_stored_row() {
+        let body = TriggerTranslator::parse_body("BEGIN INSERT INTO log VALUES (NEW.id, NEW.updated_at); NEW.ignored := 1; RETURN NULL; END").unwrap();
+        let mut definition = definition(TriggerTiming::After, &[TriggerEvent::Update]);
+        definition.update_columns = vec!["price".to_string()];
+        let triggers = TriggerTranslator::translate(&definition, &body, "t3").unwrap();
+        assert_eq!(triggers, vec![
+            "CREATE TRIGGER \"t3_update\" AFTER UPDATE OF \"price\" ON \"items\" FOR EACH ROW WHEN NOT EXISTS (SELECT 1 FROM __pgsqlite_trigger_writes)\nBEGIN\n\
+             INSERT INTO log VALUES ((SELECT \"id\" FROM \"items\" WHERE rowid = NEW.rowid), (SELECT \"updated_at\" FROM \"items\" WHERE rowid = NEW.rowid));\nEND".to_string(),
+        ]);
+        assert_eq!(definition.tgtype(), 17);
+    }
+}
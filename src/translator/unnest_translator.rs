@@ -19,6 +19,30 @@ static UNNEST_WITH_ORDINALITY_REGEX: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r"(?i)\bFROM\s+unnest\s*\(\s*([^)]+)\s*\)\s+WITH\s+ORDINALITY(?:\s+(?:AS\s+)?(\w+))?").unwrap()
 });
 
+/// regexp_split_to_table( / string_to_table( starting a FROM item
+static SPLIT_TABLE_FUNCTION_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)(\bFROM\s+|\bJOIN\s+|,\s*)(LATERAL\s+)?\b(regexp_split_to_table|string_to_table)\s*\(").unwrap()
+});
+
+/// WITH ORDINALITY and an alias with optional column names, following the call
+static SPLIT_TABLE_ALIAS_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^(\s+WITH\s+ORDINALITY)?(?:\s+(?:AS\s+)?(\w+)(?:\s*\(\s*(\w+)\s*(?:,\s*(\w+)\s*)?\))?)?").unwrap()
+});
+
+static IDENTIFIER_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\b[A-Za-z_]\w*\b").unwrap()
+});
+
+static COLUMN_REF_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\b(?:(\w+)\.)?([A-Za-z_]\w*)\b").unwrap()
+});
+
+/// Keywords that can follow a FROM item and are not its alias
+const RESERVED_AFTER_FROM_ITEM: &[&str] = &[
+    "WHERE", "ON", "USING", "JOIN", "LEFT", "RIGHT", "INNER", "OUTER", "FULL", "CROSS", "NATURAL",
+    "GROUP", "ORDER", "HAVING", "LIMIT", "OFFSET", "UNION", "EXCEPT", "INTERSECT", "WINDOW", "FETCH", "FOR",
+];
+
 /// Translates PostgreSQL unnest() function calls to SQLite json_each() equivalents
 pub struct UnnestTranslator;
 
@@ -37,11 +61,11 @@ impl UnnestTranslator {
     
     /// Translate unnest() function calls to json_each() equivalents
     pub fn translate_unnest(sql: &str) -> Result<String, PgSqliteError> {
-        if !Self::contains_unnest(sql) {
+        if !Self::contains_unnest(sql) && !Self::contains_split_function(sql) {
             return Ok(sql.to_string());
         }
         
-        let mut result = sql.to_string();
+        let mut result = Self::translate_split_functions(sql);
         
         // Handle different patterns:
         // 1. FROM unnest(array) WITH ORDINALITY AS alias
@@ -57,11 +81,11 @@ impl UnnestTranslator {
     
     /// Translate unnest with metadata
     pub fn translate_with_metadata(sql: &str) -> Result<(String, TranslationMetadata), PgSqliteError> {
-        if !Self::contains_unnest(sql) {
+        if !Self::contains_unnest(sql) && !Self::contains_split_function(sql) {
             return Ok((sql.to_string(), TranslationMetadata::new()));
         }
         
-        let mut result = sql.to_string();
+        let mut result = Self::translate_split_functions(sql);
         let mut metadata = TranslationMetadata::new();
         
         // Translate unnest calls
@@ -154,6 +178,183 @@ impl UnnestTranslator {
         Ok(result)
     }
     
+    /// Translate the set-returning split functions in FROM to json_each() over the
+    /// matching array function: regexp_split_to_table() splits with
+    /// regexp_split_to_array() and string_to_table() with string_to_array().
+    ///
+    /// Calls on constants become a derived table, so the output column keeps its
+    /// PostgreSQL name. Calls that reference other FROM items (LATERAL) must stay
+    /// table-valued for SQLite to correlate them, so references to their column
+    /// are rewritten to json_each's value column instead.
+    pub fn translate_split_functions(sql: &str) -> String {
+        if !Self::contains_split_function(sql) {
+            return sql.to_string();
+        }
+        
+        let mut result = sql.to_string();
+        let mut search_from = 0;
+        while let Some(caps) = SPLIT_TABLE_FUNCTION_REGEX.captures_at(&result, search_from) {
+            let whole = caps.get(0).unwrap();
+            let prefix = caps.get(1).unwrap();
+            search_from = whole.end();
+            
+            // A comma only starts a FROM item after FROM; otherwise it is a select list
+            let before_upper = result[..prefix.start()].to_uppercase();
+            if result[..whole.start()].bytes().filter(|&b| b == b'\'').count() % 2 == 1
+                || (prefix.as_str().starts_with(',') && before_upper.rfind("FROM") < before_upper.rfind("SELECT")) {
+                continue;
+            }
+            let Some(args_end) = Self::find_closing_paren(&result, whole.end()) else {
+                break;
+            };
+            let args = result[whole.end()..args_end].trim().to_string();
+            let function = caps[3].to_lowercase();
+            let array_function = if function == "string_to_table" { "string_to_array" } else { "regexp_split_to_array" };
+            
+            // Optional WITH ORDINALITY and alias, with column names in parentheses
+            let tail = &result[args_end + 1..];
+            let alias_caps = SPLIT_TABLE_ALIAS_REGEX.captures(tail).unwrap();
+            let with_ordinality = alias_caps.get(1).is_some();
+            let alias = alias_caps.get(2).map(|m| m.as_str())
+                .filter(|alias| !RESERVED_AFTER_FROM_ITEM.contains(&alias.to_uppercase().as_str()));
+            let consumed = match alias {
+                Some(_) => alias_caps.get(0).unwrap().end(),
+                None => alias_caps.get(1).map_or(0, |m| m.end()),
+            };
+            let table_alias = alias.unwrap_or(&function).to_string();
+            let column = alias.and(alias_caps.get(3)).map_or(table_alias.clone(), |m| m.as_str().to_string());
+            let ordinality = alias.and(alias_caps.get(4)).map_or("ordinality".to_string(), |m| m.as_str().to_string());
+            
+            let lateral = caps.get(2).is_some();
+            let source = format!("json_each({array_function}({args}))");
+            let (before, after) = (&result[..prefix.end()], &result[args_end + 1 + consumed..]);
+            let replacement = if lateral || Self::references_columns(&args) {
+                let before = Self::rewrite_select_list(before, &table_alias, &column, with_ordinality.then_some(ordinality.as_str()));
+                format!(
+                    "{}{source} AS {table_alias}{}",
+                    Self::rewrite_column_refs(&before, &table_alias, &column, with_ordinality.then_some(ordinality.as_str())),
+                    Self::rewrite_column_refs(after, &table_alias, &column, with_ordinality.then_some(ordinality.as_str())),
+                )
+            } else {
+                let ordinality_column = if with_ordinality { format!(", key + 1 AS {ordinality}") } else { String::new() };
+                format!("{before}(SELECT value AS {column}{ordinality_column} FROM {source}) AS {table_alias}{after}")
+            };
+            debug!("Translated {}: {}", function, replacement);
+            search_from = 0;
+            result = replacement;
+        }
+        
+        result
+    }
+    
+    /// Check for regexp_split_to_table() or string_to_table()
+    pub fn contains_split_function(sql: &str) -> bool {
+        let sql_lower = sql.to_lowercase();
+        sql_lower.contains("regexp_split_to_table") || sql_lower.contains("string_to_table")
+    }
+    
+    /// Find the parenthesis closing the one opened just before `start`
+    fn find_closing_paren(sql: &str, start: usize) -> Option<usize> {
+        let mut depth = 1;
+        let mut in_string = false;
+        for (i, b) in sql.bytes().enumerate().skip(start) {
+            match b {
+                b'\'' => in_string = !in_string,
+                b'(' if !in_string => depth += 1,
+                b')' if !in_string => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(i);
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+    
+    /// Check whether function arguments name a column rather than only constants
+    pub(super) fn references_columns(args: &str) -> bool {
+        IDENTIFIER_REGEX.find_iter(args).any(|m| {
+            let outside_literal = args[..m.start()].bytes().filter(|&b| b == b'\'').count() % 2 == 0;
+            let is_call = args[m.end()..].trim_start().starts_with('(');
+            let is_string_prefix = args[m.end()..].starts_with('\'');
+            outside_literal && !is_call && !is_string_prefix
+                && !matches!(m.as_str().to_uppercase().as_str(), "NULL" | "TRUE" | "FALSE")
+        })
+    }
+    
+    /// Keep the PostgreSQL column names for select list items that are just the
+    /// function's columns, which otherwise come back named "value"
+    fn rewrite_select_list(before: &str, alias: &str, column: &str, ordinality: Option<&str>) -> String {
+        let upper = before.to_uppercase();
+        let (Some(select_pos), Some(from_pos)) = (upper.find("SELECT"), upper.rfind("FROM")) else {
+            return before.to_string();
+        };
+        if from_pos < select_pos {
+            return before.to_string();
+        }
+        let list_start = select_pos + "SELECT".len();
+        let items: Vec<String> = Self::split_top_level(&before[list_start..from_pos]).into_iter()
+            .map(|item| {
+                let trimmed = item.trim();
+                let name = trimmed.rsplit_once('.')
+                    .filter(|(qualifier, _)| qualifier.eq_ignore_ascii_case(alias))
+                    .map_or(trimmed, |(_, name)| name);
+                if name.eq_ignore_ascii_case(column) || ordinality.is_some_and(|ord| name.eq_ignore_ascii_case(ord)) {
+                    format!(" {trimmed} AS {name}")
+                } else {
+                    item
+                }
+            })
+            .collect();
+        format!("{}{} {}", &before[..list_start], items.join(",").trim_end(), &before[from_pos..])
+    }
+    
+    fn split_top_level(list: &str) -> Vec<String> {
+        let mut items = Vec::new();
+        let mut current = String::new();
+        let mut depth = 0;
+        let mut in_string = false;
+        for c in list.chars() {
+            match c {
+                '\'' => in_string = !in_string,
+                '(' if !in_string => depth += 1,
+                ')' if !in_string => depth -= 1,
+                ',' if !in_string && depth == 0 => {
+                    items.push(std::mem::take(&mut current));
+                    continue;
+                }
+                _ => {}
+            }
+            current.push(c);
+        }
+        items.push(current);
+        items
+    }
+    
+    /// Point references to a table-valued split function's columns at json_each
+    fn rewrite_column_refs(sql: &str, alias: &str, column: &str, ordinality: Option<&str>) -> String {
+        COLUMN_REF_REGEX.replace_all(sql, |caps: &regex::Captures| {
+            let whole = caps.get(0).unwrap();
+            let qualifier = caps.get(1).map(|m| m.as_str());
+            let name = &caps[2];
+            let outside_literal = sql[..whole.start()].bytes().filter(|&b| b == b'\'').count() % 2 == 0;
+            let is_call = sql[whole.end()..].trim_start().starts_with('(');
+            let is_output_name = sql[..whole.start()].trim_end().to_uppercase().ends_with(" AS");
+            if !outside_literal || is_call || is_output_name || qualifier.is_some_and(|q| !q.eq_ignore_ascii_case(alias)) {
+                return whole.as_str().to_string();
+            }
+            if name.eq_ignore_ascii_case(column) {
+                format!("{alias}.value")
+            } else if ordinality.is_some_and(|ord| name.eq_ignore_ascii_case(ord)) {
+                format!("({alias}.key + 1)")
+            } else {
+                whole.as_str().to_string()
+            }
+        }).into_owned()
+    }
+    
     /// Extract metadata for aliased unnest functions
     fn extract_unnest_metadata(sql: &str, metadata: &mut TranslationMetadata) {
         // Look for aliased unnest functions (now converted to json_each)
@@ -269,4 +470,38 @@ mod tests {
         assert!(result.contains("json_each"));
         assert!(!result.contains("unnest"));
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_split_functions_on_constants() {
+        let sql = r"SELECT word FROM regexp_split_to_table('the quick  fox', '\s+') AS word";
+        assert_eq!(
+            UnnestTranslator::translate_split_functions(sql),
+            r"SELECT word FROM (SELECT value AS word FROM json_each(regexp_split_to_array('the quick  fox', '\s+'))) AS word"
+        );
+        
+        let sql = "SELECT * FROM string_to_table('a,b', ',') WITH ORDINALITY AS t(part, n)";
+        assert_eq!(
+            UnnestTranslator::translate_split_functions(sql),
+            "SELECT * FROM (SELECT value AS part, key + 1 AS n FROM json_each(string_to_array('a,b', ','))) AS t"
+        );
+        
+        let sql = "SELECT * FROM string_to_table('a,b', ',') WHERE string_to_table <> 'a'";
+        assert_eq!(
+            UnnestTranslator::translate_split_functions(sql),
+            "SELECT * FROM (SELECT value AS string_to_table FROM json_each(string_to_array('a,b', ','))) AS string_to_table WHERE string_to_table <> 'a'"
+        );
+    }
+    
+    #[test]
+    fn test_split_functions_lateral() {
+        let sql = r"SELECT d.id, w.word FROM docs d, LATERAL regexp_split_to_table(d.body, '\s+') AS w(word) WHERE word <> 'a' ORDER BY d.id";
+        assert_eq!(
+            UnnestTranslator::translate_split_functions(sql),
+            r"SELECT d.id, w.value AS word FROM docs d, json_each(regexp_split_to_array(d.body, '\s+')) AS w WHERE w.value <> 'a' ORDER BY d.id"
+        );
+        
+        // Not a FROM item, and not inside string literals
+        let sql = "SELECT a, string_to_table FROM t WHERE note = 'FROM string_to_table(x)'";
+        assert_eq!(UnnestTranslator::translate_split_functions(sql), sql);
+    }
+}
@@ -0,0 +1,314 @@
+/// Translator for VALUES lists
+///
+/// SQLite supports `VALUES` but names the columns `column1`, `column2`, ... and has no
+/// syntax for renaming them in a derived table. PostgreSQL queries such as
+/// `SELECT t.id FROM (VALUES (1, 'a'), (2, 'b')) AS t(id, name)` are rewritten to
+/// `SELECT column1 AS id, column2 AS name FROM (VALUES ...)`, and standalone
+/// `VALUES (...), (...)` statements become `SELECT * FROM (VALUES ...)`. The rows stay
+/// a native VALUES list, which unlike a chain of `UNION ALL` has no term limit.
+pub struct ValuesTranslator;
+
+impl ValuesTranslator {
+    /// Check if translation might be needed
+    pub fn needs_translation(query: &str) -> bool {
+        // INSERT ... VALUES is native SQLite, and VALUES lists in INSERT ... SELECT,
+        // UPDATE ... FROM and DELETE ... USING are handled by the Insert/BatchUpdate/BatchDelete
+        // translators
+        let trimmed = query.trim_start();
+        if ["INSERT", "UPDATE", "DELETE"].iter().any(|dml| trimmed.get(..6).is_some_and(|s| s.eq_ignore_ascii_case(dml))) {
+            return false;
+        }
+        query.as_bytes().windows(6).any(|w| w.eq_ignore_ascii_case(b"VALUES"))
+    }
+
+    /// Rewrite standalone VALUES statements and VALUES derived tables
+    pub fn translate_query(query: &str) -> String {
+        if !Self::needs_translation(query) {
+            return query.to_string();
+        }
+
+        let mut result = query.to_string();
+
+        // Standalone statement: VALUES (...), (...) [ORDER BY ...] [LIMIT ...]
+        let leading = result.len() - result.trim_start().len();
+        if keyword_at(&result, leading, "VALUES").is_some()
+            && let Some((_, rows_end)) = parse_rows(&result, leading + "VALUES".len())
+        {
+            let select = format!("SELECT * FROM ({})", &result[leading..rows_end]);
+            result = format!("{}{}{}", &result[..leading], select, &result[rows_end..]);
+        }
+
+        // Derived tables: (VALUES (...), (...)) [AS] alias(col, ...)
+        let mut search_from = 0;
+        while let Some((open, keyword_end)) = find_values_subquery(&result, search_from) {
+            let Some((rows, rows_end)) = parse_rows(&result, keyword_end) else {
+                search_from = open + 1;
+                continue;
+            };
+            let close = skip_whitespace(&result, rows_end);
+            if result.as_bytes().get(close) != Some(&b')') {
+                search_from = open + 1;
+                continue;
+            }
+
+            // Without a column list the native column1, column2, ... names already match PostgreSQL
+            let (alias, columns, alias_end) = parse_alias(&result, close + 1);
+            let Some(columns) = columns.filter(|columns| columns.len() == rows[0].len()) else {
+                search_from = close;
+                continue;
+            };
+
+            let values = &result[skip_whitespace(&result, open + 1)..rows_end];
+            let mut replacement = format!("({})", build_select(values, &columns));
+            if let Some(alias) = alias {
+                replacement.push_str(" AS ");
+                replacement.push_str(&alias);
+            }
+
+            result = format!("{}{}{}", &result[..open], replacement, &result[alias_end..]);
+            search_from = open + replacement.len();
+        }
+
+        result
+    }
+}
+
+/// Build `SELECT column1 AS a, column2 AS b FROM (VALUES ...)` from a VALUES list
+fn build_select(values: &str, columns: &[String]) -> String {
+    let exprs: Vec<String> = columns.iter().enumerate()
+        .map(|(i, column)| format!("column{} AS {column}", i + 1))
+        .collect();
+    format!("SELECT {} FROM ({values})", exprs.join(", "))
+}
+
+/// Find the next `(` that opens a `(VALUES ...)` derived table, skipping literals.
+/// Returns the position of the parenthesis and the end of the VALUES keyword.
+fn find_values_subquery(sql: &str, from: usize) -> Option<(usize, usize)> {
+    let bytes = sql.as_bytes();
+    let mut i = from;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\'' | b'"' => i = skip_quoted(bytes, i),
+            b'(' => {
+                if let Some(keyword_end) = keyword_at(sql, skip_whitespace(sql, i + 1), "VALUES") {
+                    return Some((i, keyword_end));
+                }
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// Parse `(expr, ...), (expr, ...)` starting at `pos`. Returns the rows and the
+/// position after the last row. All rows must have the same number of columns.
+fn parse_rows(sql: &str, pos: usize) -> Option<(Vec<Vec<String>>, usize)> {
+    let bytes = sql.as_bytes();
+    let mut rows = Vec::new();
+    let mut pos = skip_whitespace(sql, pos);
+
+    loop {
+        if bytes.get(pos) != Some(&b'(') {
+            return None;
+        }
+        let close = find_closing_paren(bytes, pos)?;
+        let row = split_top_level(&sql[pos + 1..close]);
+        if rows.first().is_some_and(|first: &Vec<String>| first.len() != row.len()) {
+            return None;
+        }
+        rows.push(row);
+
+        let next = skip_whitespace(sql, close + 1);
+        if bytes.get(next) == Some(&b',') {
+            pos = skip_whitespace(sql, next + 1);
+        } else {
+            return Some((rows, close + 1));
+        }
+    }
+}
+
+/// Parse `[AS] alias [(col, ...)]` after a derived table
+fn parse_alias(sql: &str, pos: usize) -> (Option<String>, Option<Vec<String>>, usize) {
+    let bytes = sql.as_bytes();
+    let start = pos;
+    let mut pos = skip_whitespace(sql, pos);
+    if let Some(end) = keyword_at(sql, pos, "AS") {
+        pos = skip_whitespace(sql, end);
+    }
+
+    let Some(alias_end) = identifier_end(sql, pos) else {
+        return (None, None, start);
+    };
+    let alias = &sql[pos..alias_end];
+    // A keyword following the derived table is not an alias
+    const CLAUSES: &[&str] = &["SELECT", "WHERE", "ORDER", "GROUP", "LIMIT", "JOIN", "INNER", "LEFT", "RIGHT",
+        "CROSS", "FULL", "NATURAL", "ON", "UNION", "HAVING", "OFFSET", "FETCH", "EXCEPT", "INTERSECT",
+        "WINDOW", "FOR", "RETURNING", "INSERT", "UPDATE", "DELETE"];
+    if CLAUSES.iter().any(|c| alias.eq_ignore_ascii_case(c)) {
+        return (None, None, start);
+    }
+
+    let open = skip_whitespace(sql, alias_end);
+    if bytes.get(open) == Some(&b'(')
+        && let Some(close) = find_closing_paren(bytes, open)
+    {
+        let columns = split_top_level(&sql[open + 1..close]);
+        return (Some(alias.to_string()), Some(columns), close + 1);
+    }
+
+    (Some(alias.to_string()), None, alias_end)
+}
+
+/// End of the (possibly quoted) identifier starting at `pos`
+pub(super) fn identifier_end(sql: &str, pos: usize) -> Option<usize> {
+    let bytes = sql.as_bytes();
+    match bytes.get(pos)? {
+        b'"' => Some(skip_quoted(bytes, pos)),
+        b if b.is_ascii_alphabetic() || *b == b'_' => {
+            let mut end = pos;
+            while end < bytes.len() && (bytes[end].is_ascii_alphanumeric() || bytes[end] == b'_') {
+                end += 1;
+            }
+            Some(end)
+        }
+        _ => None,
+    }
+}
+
+/// If `keyword` starts at `pos` as a whole word, return the position after it
+pub(super) fn keyword_at(sql: &str, pos: usize, keyword: &str) -> Option<usize> {
+    let end = pos + keyword.len();
+    let word = sql.get(pos..end)?;
+    let boundary = sql.as_bytes().get(end).is_none_or(|b| !b.is_ascii_alphanumeric() && *b != b'_');
+    (word.eq_ignore_ascii_case(keyword) && boundary).then_some(end)
+}
+
+pub(super) fn skip_whitespace(sql: &str, pos: usize) -> usize {
+    let bytes = sql.as_bytes();
+    let mut pos = pos;
+    while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+        pos += 1;
+    }
+    pos
+}
+
+/// Split a row body on commas that are not nested in parentheses or literals
+pub(super) fn split_top_level(body: &str) -> Vec<String> {
+    let bytes = body.as_bytes();
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\'' | b'"' => {
+                i = skip_quoted(bytes, i);
+                continue;
+            }
+            b'(' | b'[' => depth += 1,
+            b')' | b']' => depth -= 1,
+            b',' if depth == 0 => {
+                parts.push(body[start..i].trim().to_string());
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    parts.push(body[start..].trim().to_string());
+    parts
+}
+
+/// Index of the parenthesis closing the one at `open`
+pub(super) fn find_closing_paren(bytes: &[u8], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = open;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\'' | b'"' => {
+                i = skip_quoted(bytes, i);
+                continue;
+            }
+            b'(' => depth += 1,
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Return the index just past a quoted literal or identifier starting at `start`
+fn skip_quoted(bytes: &[u8], start: usize) -> usize {
+    let quote = bytes[start];
+    let mut i = start + 1;
+    while i < bytes.len() {
+        if bytes[i] == quote {
+            // Doubled quote is an escaped quote
+            if bytes.get(i + 1) == Some(&quote) {
+                i += 2;
+                continue;
+            }
+            return i + 1;
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_standalone_values() {
+        assert_eq!(
+            ValuesTranslator::translate_query("VALUES (1, 'a'), (2, 'b')"),
+            "SELECT * FROM (VALUES (1, 'a'), (2, 'b'))"
+        );
+        assert_eq!(
+            ValuesTranslator::translate_query("values (1), (2) ORDER BY 1 DESC;"),
+            "SELECT * FROM (values (1), (2)) ORDER BY 1 DESC;"
+        );
+    }
+
+    #[test]
+    fn test_values_derived_table() {
+        assert_eq!(
+            ValuesTranslator::translate_query("SELECT t.name FROM (VALUES (1, 'a, b'), (2, lower('B'))) AS t(id, name) WHERE t.id = 2"),
+            "SELECT t.name FROM (SELECT column1 AS id, column2 AS name FROM (VALUES (1, 'a, b'), (2, lower('B')))) AS t WHERE t.id = 2"
+        );
+        assert_eq!(
+            ValuesTranslator::translate_query("SELECT * FROM ( VALUES ($1, $2) ) v (\"Id\", n) JOIN x ON x.id = v.\"Id\""),
+            "SELECT * FROM (SELECT column1 AS \"Id\", column2 AS n FROM (VALUES ($1, $2))) AS v JOIN x ON x.id = v.\"Id\""
+        );
+        // Common table expressions keep their own column list
+        let query = "WITH v(x) AS (VALUES (1), (2)) SELECT x FROM v";
+        assert_eq!(ValuesTranslator::translate_query(query), query);
+        // Without a column list PostgreSQL names the columns column1, column2, ... like SQLite
+        let query = "SELECT * FROM (VALUES (1, 2)) AS t";
+        assert_eq!(ValuesTranslator::translate_query(query), query);
+    }
+
+    #[test]
+    fn test_values_left_alone() {
+        let query = "INSERT INTO t (a, b) VALUES (1, 2), (3, 4)";
+        assert_eq!(ValuesTranslator::translate_query(query), query);
+
+        let query = "UPDATE t SET a = v.a FROM (VALUES (1, 2)) AS v(id, a) WHERE t.id = v.id";
+        assert_eq!(ValuesTranslator::translate_query(query), query);
+
+        let query = "SELECT 'VALUES (1)' FROM t";
+        assert_eq!(ValuesTranslator::translate_query(query), query);
+
+        // Rows with different lengths are invalid and passed through for SQLite to report
+        let query = "SELECT * FROM (VALUES (1, 2), (3)) AS t(a, b)";
+        assert_eq!(ValuesTranslator::translate_query(query), query);
+    }
+}
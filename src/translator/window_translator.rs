@@ -0,0 +1,190 @@
+use crate::types::Interval;
+use crate::types::interval::MICROS_PER_DAY;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rusqlite::Connection;
+
+/// Translator for PostgreSQL window definitions SQLite can't run as written
+///
+/// SQLite accepts FILTER, named windows and RANGE frames, but a RANGE offset must be a
+/// number in the unit of the ORDER BY column. Dates are stored as INTEGER days and
+/// timestamps and times as INTEGER microseconds, so `RANGE BETWEEN INTERVAL '7 days'
+/// PRECEDING AND CURRENT ROW` is rewritten to the equivalent number of days or
+/// microseconds. A month counts as 30 days, as in justify_days().
+pub struct WindowTranslator;
+
+/// A RANGE keyword, the start of a frame clause
+static RANGE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\bRANGE\b").unwrap());
+
+/// INTERVAL '7 days' PRECEDING or '1 hour'::interval FOLLOWING
+static INTERVAL_OFFSET_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)(?:\bINTERVAL\s*'([^']*)'|'([^']*)'\s*::\s*interval)\s+(PRECEDING|FOLLOWING)\b").unwrap()
+});
+
+/// The first ORDER BY column of a window definition
+static ORDER_COLUMN_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)\bORDER\s+BY\s+(?:"?\w+"?\.)?"?(\w+)"?"#).unwrap()
+});
+
+/// Window functions whose result has the type of their first argument
+static VALUE_FUNCTION_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)^\s*(?:lag|lead|first_value|last_value|nth_value)\s*\(\s*(?:"?\w+"?\.)?"?(\w+)"?\s*[,)]"#).unwrap()
+});
+
+/// A lag/lead/first_value/last_value/nth_value call, its OVER clause and alias
+static VALUE_FUNCTION_ALIAS_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)\b((?:lag|lead|first_value|last_value|nth_value)\s*\([^()]*\))\s*OVER\s*(?:\w+|\((?:[^()]|\([^()]*\))*\))\s+(?:AS\s+)?(?:"([^"]+)"|(\w+))(?:\s|,|$)"#).unwrap()
+});
+
+/// The OVER keyword of a window function call
+static OVER_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\bOVER\b").unwrap());
+
+impl WindowTranslator {
+    /// Check if the query has a RANGE frame with an interval offset
+    pub fn needs_translation(query: &str) -> bool {
+        RANGE_REGEX.is_match(query) && INTERVAL_OFFSET_REGEX.is_match(query)
+    }
+
+    /// Rewrite interval offsets in RANGE frames to the storage unit of the ORDER BY column
+    pub fn translate_query(query: &str, conn: Option<&Connection>) -> String {
+        if !Self::needs_translation(query) {
+            return query.to_string();
+        }
+
+        // The window definitions (OVER (...) or WINDOW w AS (...)) holding a RANGE frame
+        let mut definitions: Vec<(usize, usize)> = RANGE_REGEX.find_iter(query)
+            .filter_map(|range| Self::enclosing_parens(query, range.start()))
+            .collect();
+        definitions.dedup();
+
+        let mut result = query.to_string();
+        for (open, close) in definitions.into_iter().rev() {
+            let definition = &query[open + 1..close];
+            let Some(order_column) = ORDER_COLUMN_REGEX.captures(definition) else {
+                continue;
+            };
+            let micros_per_unit = match Self::column_type(&order_column[1], query, conn).as_deref() {
+                Some("date") => MICROS_PER_DAY,
+                _ => 1,
+            };
+
+            let rewritten = INTERVAL_OFFSET_REGEX.replace_all(definition, |caps: &regex::Captures| {
+                let text = caps.get(1).or_else(|| caps.get(2)).map_or("", |m| m.as_str());
+                match Interval::parse(text) {
+                    Ok(interval) => {
+                        let micros = (interval.months as i64 * 30 + interval.days as i64) * MICROS_PER_DAY + interval.micros;
+                        let offset = if micros % micros_per_unit == 0 {
+                            (micros / micros_per_unit).to_string()
+                        } else {
+                            (micros as f64 / micros_per_unit as f64).to_string()
+                        };
+                        format!("{offset} {}", &caps[3])
+                    }
+                    Err(_) => caps[0].to_string(),
+                }
+            });
+            result.replace_range(open + 1..close, &rewritten);
+        }
+        result
+    }
+
+    /// The column a lag/lead/first_value/last_value/nth_value result comes from, for
+    /// a result column named either by the expression or by its alias
+    pub fn value_function_column(column_name: &str, query: &str) -> Option<String> {
+        if let Some(caps) = VALUE_FUNCTION_REGEX.captures(column_name) {
+            return Some(caps[1].to_string());
+        }
+        // Field descriptions ask for every column of every query, so skip the scan unless a window is present
+        if !OVER_REGEX.is_match(query) {
+            return None;
+        }
+        let expression = VALUE_FUNCTION_ALIAS_REGEX.captures_iter(query)
+            .find(|caps| caps.get(2).or(caps.get(3)).is_some_and(|alias| alias.as_str().eq_ignore_ascii_case(column_name)))?;
+        VALUE_FUNCTION_REGEX.captures(&expression[1]).map(|caps| caps[1].to_string())
+    }
+
+    /// Positions of the parentheses around `pos`
+    fn enclosing_parens(query: &str, pos: usize) -> Option<(usize, usize)> {
+        let bytes = query.as_bytes();
+        let mut depth = 0;
+        let open = (0..pos).rev().find(|&i| {
+            match bytes[i] {
+                b')' => depth += 1,
+                b'(' if depth == 0 => return true,
+                b'(' => depth -= 1,
+                _ => {}
+            }
+            false
+        })?;
+
+        let mut depth = 0;
+        let close = (open..bytes.len()).find(|&i| {
+            match bytes[i] {
+                b'(' => depth += 1,
+                b')' => depth -= 1,
+                _ => {}
+            }
+            depth == 0
+        })?;
+        Some((open, close))
+    }
+
+    /// The declared type of a column, preferring tables the query mentions
+    fn column_type(column: &str, query: &str, conn: Option<&Connection>) -> Option<String> {
+        let mut stmt = conn?.prepare("SELECT table_name, pg_type FROM __pgsqlite_schema WHERE column_name = ?1 COLLATE NOCASE").ok()?;
+        let candidates: Vec<(String, String)> = stmt.query_map([column], |row| Ok((row.get(0)?, row.get(1)?)))
+            .ok()?
+            .filter_map(|row| row.ok())
+            .collect();
+        let lower_query = query.to_lowercase();
+        candidates.iter()
+            .find(|(table, _)| lower_query.contains(&table.to_lowercase()))
+            .or_else(|| candidates.first())
+            .map(|(_, pg_type)| pg_type.to_lowercase())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_range_interval_translation() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE __pgsqlite_schema (table_name TEXT, column_name TEXT, pg_type TEXT, sqlite_type TEXT);
+             INSERT INTO __pgsqlite_schema VALUES ('sales', 'sold_at', 'TIMESTAMP', 'INTEGER'), ('sales', 'sold_on', 'DATE', 'INTEGER');"
+        ).unwrap();
+
+        assert_eq!(
+            WindowTranslator::translate_query(
+                "SELECT sum(amount) OVER (ORDER BY sold_at RANGE BETWEEN INTERVAL '1 day' PRECEDING AND '2 hours'::interval FOLLOWING) FROM sales",
+                Some(&conn)
+            ),
+            "SELECT sum(amount) OVER (ORDER BY sold_at RANGE BETWEEN 86400000000 PRECEDING AND 7200000000 FOLLOWING) FROM sales"
+        );
+        assert_eq!(
+            WindowTranslator::translate_query(
+                "SELECT sum(amount) OVER w FROM sales WINDOW w AS (PARTITION BY region ORDER BY s.sold_on RANGE INTERVAL '1 week' PRECEDING)",
+                Some(&conn)
+            ),
+            "SELECT sum(amount) OVER w FROM sales WINDOW w AS (PARTITION BY region ORDER BY s.sold_on RANGE 7 PRECEDING)"
+        );
+        assert_eq!(
+            WindowTranslator::translate_query("SELECT sum(x) OVER (ORDER BY d RANGE INTERVAL '12 hours' PRECEDING) FROM t", None),
+            "SELECT sum(x) OVER (ORDER BY d RANGE 43200000000 PRECEDING) FROM t"
+        );
+
+        let query = "SELECT sum(x) OVER (ORDER BY id RANGE BETWEEN 1 PRECEDING AND CURRENT ROW) FROM t";
+        assert_eq!(WindowTranslator::translate_query(query, Some(&conn)), query);
+    }
+
+    #[test]
+    fn test_value_function_column() {
+        let query = "SELECT id, lag(amount, 1) OVER (ORDER BY id) AS prev, first_value(t.region) OVER w first FROM t WINDOW w AS (ORDER BY id)";
+        assert_eq!(WindowTranslator::value_function_column("prev", query).as_deref(), Some("amount"));
+        assert_eq!(WindowTranslator::value_function_column("first", query).as_deref(), Some("region"));
+        assert_eq!(WindowTranslator::value_function_column("lead(price) OVER (ORDER BY id)", query).as_deref(), Some("price"));
+        assert_eq!(WindowTranslator::value_function_column("id", query), None);
+    }
+}
@@ -0,0 +1,130 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Translator for ordered-set aggregates
+///
+/// SQLite has no WITHIN GROUP clause, so the sort expression becomes an extra
+/// argument of the aggregate, followed by 1 when the order is descending:
+/// `percentile_cont(0.5) WITHIN GROUP (ORDER BY price DESC)` becomes
+/// `percentile_cont(0.5, price, 1)` and `mode() WITHIN GROUP (ORDER BY x)` becomes `mode(x)`.
+pub struct WithinGroupTranslator;
+
+static ORDERED_SET_AGGREGATE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(percentile_cont|percentile_disc|mode)\s*\(").unwrap()
+});
+
+static WITHIN_GROUP_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^\s*WITHIN\s+GROUP\s*\(\s*ORDER\s+BY\s+").unwrap()
+});
+
+static SORT_DIRECTION_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)(?:\s+(ASC|DESC))?(?:\s+NULLS\s+(?:FIRST|LAST))?\s*$").unwrap()
+});
+
+impl WithinGroupTranslator {
+    /// Check if the query contains a WITHIN GROUP clause
+    pub fn needs_translation(query: &str) -> bool {
+        query.as_bytes().windows(6).any(|w| w.eq_ignore_ascii_case(b"within"))
+            && ORDERED_SET_AGGREGATE_REGEX.is_match(query)
+    }
+
+    /// Move the ORDER BY expression of each WITHIN GROUP clause into the aggregate call
+    pub fn translate_query(query: &str) -> String {
+        if !Self::needs_translation(query) {
+            return query.to_string();
+        }
+
+        let mut result = String::with_capacity(query.len());
+        let mut rest = query;
+        while let Some(m) = ORDERED_SET_AGGREGATE_REGEX.find(rest) {
+            let args_start = m.end();
+            let translated = Self::closing_paren(rest, args_start).and_then(|args_end| {
+                let within = WITHIN_GROUP_REGEX.find(&rest[args_end + 1..])?;
+                let order_start = args_end + 1 + within.end();
+                let order_end = Self::closing_paren(rest, order_start)?;
+                Some((args_end, order_start, order_end))
+            });
+
+            match translated {
+                Some((args_end, order_start, order_end)) if !Self::inside_literal(query, query.len() - rest.len() + m.start()) => {
+                    let direct_args = rest[args_start..args_end].trim();
+                    let order_by = &rest[order_start..order_end];
+                    let (sort_expr, descending) = match SORT_DIRECTION_REGEX.captures(order_by) {
+                        Some(caps) => (
+                            &order_by[..caps.get(0).unwrap().start()],
+                            caps.get(1).is_some_and(|d| d.as_str().eq_ignore_ascii_case("DESC")),
+                        ),
+                        None => (order_by, false),
+                    };
+
+                    result.push_str(&rest[..args_start]);
+                    if !direct_args.is_empty() {
+                        result.push_str(direct_args);
+                        result.push_str(", ");
+                    }
+                    result.push_str(sort_expr.trim());
+                    if descending {
+                        result.push_str(", 1");
+                    }
+                    result.push(')');
+                    rest = &rest[order_end + 1..];
+                }
+                _ => {
+                    result.push_str(&rest[..args_start]);
+                    rest = &rest[args_start..];
+                }
+            }
+        }
+        result.push_str(rest);
+        result
+    }
+
+    /// Position of the parenthesis closing the one opened just before `start`
+    fn closing_paren(query: &str, start: usize) -> Option<usize> {
+        let mut depth = 0;
+        let mut in_string = false;
+        for (i, c) in query[start..].char_indices() {
+            match c {
+                '\'' => in_string = !in_string,
+                '(' if !in_string => depth += 1,
+                ')' if !in_string => {
+                    if depth == 0 {
+                        return Some(start + i);
+                    }
+                    depth -= 1;
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn inside_literal(query: &str, pos: usize) -> bool {
+        query[..pos].bytes().filter(|&b| b == b'\'').count() % 2 == 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_within_group() {
+        assert_eq!(
+            WithinGroupTranslator::translate_query(
+                "SELECT percentile_cont(0.5) WITHIN GROUP (ORDER BY price), mode() within group (order by lower(name) DESC) FROM items"
+            ),
+            "SELECT percentile_cont(0.5, price), mode(lower(name), 1) FROM items"
+        );
+        assert_eq!(
+            WithinGroupTranslator::translate_query(
+                "SELECT category, percentile_disc(ARRAY[0.25, 0.75]) WITHIN GROUP (ORDER BY (price * 2) ASC NULLS LAST) FROM items GROUP BY category"
+            ),
+            "SELECT category, percentile_disc(ARRAY[0.25, 0.75], (price * 2)) FROM items GROUP BY category"
+        );
+
+        // Calls without WITHIN GROUP and string literals are left alone
+        let query = "SELECT mode(x), 'percentile_cont(0.5) WITHIN GROUP (ORDER BY x)' FROM t";
+        assert_eq!(WithinGroupTranslator::translate_query(query), query);
+    }
+}
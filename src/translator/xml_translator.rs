@@ -0,0 +1,131 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Translator for XMLPARSE and XMLSERIALIZE
+///
+/// Both take a DOCUMENT or CONTENT keyword SQLite can't parse. xml values are stored as
+/// the text they were written as, so `XMLPARSE(DOCUMENT expr)` becomes
+/// `pg_xml_parse(expr, 'document')`, which checks the text is well-formed, and
+/// `XMLSERIALIZE(CONTENT expr AS text)` becomes `pg_xml_serialize(expr, 'content')`,
+/// which passes it through.
+pub struct XmlTranslator;
+
+static XML_FUNCTION_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(xmlparse|xmlserialize)\s*\(\s*(document|content)\s+").unwrap()
+});
+
+/// The optional trailing clauses, which have nothing to do for text
+static TRAILING_OPTION_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\s+(?:(?:preserve|strip)\s+whitespace|(?:no\s+)?indent)\s*$").unwrap()
+});
+
+static AS_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\s+AS\s+").unwrap());
+
+impl XmlTranslator {
+    /// Check if translation might be needed
+    pub fn needs_translation(query: &str) -> bool {
+        XML_FUNCTION_REGEX.is_match(query)
+    }
+
+    /// Rewrite XMLPARSE and XMLSERIALIZE as function calls
+    pub fn translate_query(query: &str) -> String {
+        let mut result = String::with_capacity(query.len());
+        let mut rest = query;
+        while let Some(caps) = XML_FUNCTION_REGEX.captures(rest) {
+            let whole = caps.get(0).unwrap();
+            let open = rest[..whole.end()].rfind('(').unwrap();
+            let Some(close) = Self::closing_paren(rest, open) else {
+                break;
+            };
+            // Leave the keywords inside string literals alone
+            if rest[..whole.start()].bytes().filter(|&b| b == b'\'').count() % 2 == 1 {
+                result.push_str(&rest[..whole.end()]);
+                rest = &rest[whole.end()..];
+                continue;
+            }
+
+            let option = caps[2].to_lowercase();
+            let argument = TRAILING_OPTION_REGEX.replace(&rest[whole.end()..close], "");
+            result.push_str(&rest[..whole.start()]);
+            if caps[1].eq_ignore_ascii_case("xmlparse") {
+                result.push_str(&format!("pg_xml_parse({}, '{option}')", Self::translate_query(argument.trim())));
+            } else {
+                // XMLSERIALIZE(... AS type): the value is text already, so only the expression is kept
+                let expr = Self::split_target_type(&argument).unwrap_or(&argument);
+                result.push_str(&format!("pg_xml_serialize({}, '{option}')", Self::translate_query(expr.trim())));
+            }
+            rest = &rest[close + 1..];
+        }
+        result.push_str(rest);
+        result
+    }
+
+    /// The expression before the last top-level `AS type` of an XMLSERIALIZE argument
+    fn split_target_type(argument: &str) -> Option<&str> {
+        let last_as = AS_REGEX.find_iter(argument)
+            .filter(|found| Self::depth_at(argument, found.start()) == 0)
+            .last()?;
+        Some(&argument[..last_as.start()])
+    }
+
+    /// The parenthesis nesting outside string literals at `pos`
+    fn depth_at(text: &str, pos: usize) -> i32 {
+        let mut depth = 0;
+        let mut in_string = false;
+        for b in text[..pos].bytes() {
+            match b {
+                b'\'' => in_string = !in_string,
+                b'(' if !in_string => depth += 1,
+                b')' if !in_string => depth -= 1,
+                _ => {}
+            }
+        }
+        depth
+    }
+
+    /// The position of the parenthesis closing the one at `open`
+    fn closing_paren(text: &str, open: usize) -> Option<usize> {
+        let mut depth = 0;
+        let mut in_string = false;
+        for (pos, b) in text.bytes().enumerate().skip(open) {
+            match b {
+                b'\'' => in_string = !in_string,
+                b'(' if !in_string => depth += 1,
+                b')' if !in_string => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(pos);
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xmlparse_and_xmlserialize() {
+        assert_eq!(
+            XmlTranslator::translate_query("SELECT XMLPARSE(DOCUMENT '<a>(x)</a>')"),
+            "SELECT pg_xml_parse('<a>(x)</a>', 'document')"
+        );
+        assert_eq!(
+            XmlTranslator::translate_query("SELECT xmlserialize(content xmlparse(content body preserve whitespace) as varchar(100)) FROM docs"),
+            "SELECT pg_xml_serialize(pg_xml_parse(body, 'content'), 'content') FROM docs"
+        );
+        assert_eq!(
+            XmlTranslator::translate_query("SELECT XMLSERIALIZE(DOCUMENT coalesce(a, 'x AS y') AS text)"),
+            "SELECT pg_xml_serialize(coalesce(a, 'x AS y'), 'document')"
+        );
+        assert!(!XmlTranslator::needs_translation("SELECT 'xmlparse' FROM t"));
+        assert_eq!(
+            XmlTranslator::translate_query("SELECT 'XMLPARSE(DOCUMENT x)'"),
+            "SELECT 'XMLPARSE(DOCUMENT x)'"
+        );
+    }
+}
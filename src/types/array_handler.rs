@@ -0,0 +1,258 @@
+use crate::types::PgType;
+use crate::PgSqliteError;
+use serde_json::Value as JsonValue;
+
+/// PostgreSQL array utilities
+///
+/// Arrays are stored in SQLite as JSON arrays. This handler converts between the
+/// PostgreSQL text representation (`{1,2,"a b",NULL}`) and the stored JSON form.
+pub struct ArrayHandler;
+
+impl ArrayHandler {
+    /// Check whether a type OID is one of the supported array types, including arrays of ENUM types
+    pub fn is_array_oid(type_oid: i32) -> bool {
+        PgType::from_oid(type_oid).is_some_and(|t| t.is_array())
+            || crate::cache::global_enum_cache().array_element_oid(type_oid).is_some()
+    }
+
+    /// Element type for an array type OID; ENUM elements have none and are handled as text
+    pub fn element_type(array_oid: i32) -> Option<PgType> {
+        PgType::from_oid(array_oid).and_then(|t| t.element_type())
+    }
+
+    /// Element type OID for an array type OID, text when it is unknown
+    pub fn element_oid(array_oid: i32) -> i32 {
+        Self::element_type(array_oid).map(|t| t.to_oid())
+            .or_else(|| crate::cache::global_enum_cache().array_element_oid(array_oid))
+            .unwrap_or(PgType::Text.to_oid())
+    }
+
+    /// Parse a PostgreSQL array literal into JSON.
+    ///
+    /// When the element type is known, elements are typed accordingly (numbers for
+    /// numeric types, booleans for bool, strings otherwise). Without an element type,
+    /// unquoted elements that look like numbers or booleans are stored as such.
+    pub fn parse_array_literal(literal: &str, element_type: Option<PgType>) -> Result<JsonValue, PgSqliteError> {
+        let mut chars = literal.trim().chars().peekable();
+
+        // Skip optional dimension decoration, e.g. '[1:3]={1,2,3}'
+        if chars.peek() == Some(&'[') {
+            for ch in chars.by_ref() {
+                if ch == '=' {
+                    break;
+                }
+            }
+        }
+
+        if chars.next() != Some('{') {
+            return Err(PgSqliteError::TypeConversion(format!("malformed array literal: \"{literal}\"")));
+        }
+
+        let value = Self::parse_array_body(&mut chars, element_type, literal)?;
+
+        if chars.any(|c| !c.is_whitespace()) {
+            return Err(PgSqliteError::TypeConversion(format!("malformed array literal: \"{literal}\"")));
+        }
+
+        Ok(value)
+    }
+
+    /// Parse a PostgreSQL array literal and return the JSON string to store
+    pub fn array_literal_to_json(literal: &str, element_type: Option<PgType>) -> Result<String, PgSqliteError> {
+        Ok(Self::parse_array_literal(literal, element_type)?.to_string())
+    }
+
+    fn parse_array_body<I>(
+        chars: &mut std::iter::Peekable<I>,
+        element_type: Option<PgType>,
+        literal: &str,
+    ) -> Result<JsonValue, PgSqliteError>
+    where
+        I: Iterator<Item = char>,
+    {
+        let malformed = || PgSqliteError::TypeConversion(format!("malformed array literal: \"{literal}\""));
+        let mut elements = Vec::new();
+
+        loop {
+            while chars.peek().is_some_and(|c| c.is_whitespace()) {
+                chars.next();
+            }
+
+            match chars.peek() {
+                Some('}') if elements.is_empty() => {
+                    chars.next();
+                    return Ok(JsonValue::Array(elements));
+                }
+                Some('{') => {
+                    chars.next();
+                    elements.push(Self::parse_array_body(chars, element_type, literal)?);
+                }
+                Some('"') => {
+                    chars.next();
+                    let mut element = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('\\') => element.push(chars.next().ok_or_else(malformed)?),
+                            Some('"') => break,
+                            Some(c) => element.push(c),
+                            None => return Err(malformed()),
+                        }
+                    }
+                    elements.push(Self::typed_element(&element, element_type, true)?);
+                }
+                Some(_) => {
+                    let mut element = String::new();
+                    while let Some(&c) = chars.peek() {
+                        if c == ',' || c == '}' {
+                            break;
+                        }
+                        chars.next();
+                        if c == '\\' {
+                            element.push(chars.next().ok_or_else(malformed)?);
+                        } else {
+                            element.push(c);
+                        }
+                    }
+                    let element = element.trim();
+                    if element.is_empty() {
+                        return Err(malformed());
+                    }
+                    if element.eq_ignore_ascii_case("NULL") {
+                        elements.push(JsonValue::Null);
+                    } else {
+                        elements.push(Self::typed_element(element, element_type, false)?);
+                    }
+                }
+                None => return Err(malformed()),
+            }
+
+            while chars.peek().is_some_and(|c| c.is_whitespace()) {
+                chars.next();
+            }
+
+            match chars.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(JsonValue::Array(elements)),
+                _ => return Err(malformed()),
+            }
+        }
+    }
+
+    /// Convert a single array element to its stored JSON representation
+    fn typed_element(element: &str, element_type: Option<PgType>, quoted: bool) -> Result<JsonValue, PgSqliteError> {
+        let invalid = |type_name: &str| {
+            PgSqliteError::TypeConversion(format!("invalid input syntax for type {type_name}: \"{element}\""))
+        };
+
+        match element_type {
+            Some(PgType::Int2 | PgType::Int4 | PgType::Int8) => element.trim().parse::<i64>()
+                .map(JsonValue::from)
+                .map_err(|_| invalid("integer")),
+            Some(PgType::Float4 | PgType::Float8) => {
+                let value = element.trim().parse::<f64>().map_err(|_| invalid("double precision"))?;
+                // JSON has no representation for NaN/Infinity, keep them as text
+                Ok(serde_json::Number::from_f64(value)
+                    .map(JsonValue::Number)
+                    .unwrap_or_else(|| JsonValue::String(element.trim().to_string())))
+            }
+            Some(PgType::Bool) => match element.trim().to_lowercase().as_str() {
+                "t" | "true" | "y" | "yes" | "on" | "1" => Ok(JsonValue::Bool(true)),
+                "f" | "false" | "n" | "no" | "off" | "0" => Ok(JsonValue::Bool(false)),
+                _ => Err(invalid("boolean")),
+            },
+            Some(_) => Ok(JsonValue::String(element.to_string())),
+            None => {
+                if !quoted {
+                    if let Ok(num) = element.parse::<i64>() {
+                        return Ok(JsonValue::from(num));
+                    }
+                    if let Some(num) = element.parse::<f64>().ok().and_then(serde_json::Number::from_f64) {
+                        return Ok(JsonValue::Number(num));
+                    }
+                    if element.eq_ignore_ascii_case("true") || element.eq_ignore_ascii_case("false") {
+                        return Ok(JsonValue::Bool(element.eq_ignore_ascii_case("true")));
+                    }
+                }
+                Ok(JsonValue::String(element.to_string()))
+            }
+        }
+    }
+
+    /// Format a stored JSON array as a PostgreSQL array literal.
+    /// Returns None if the value isn't a JSON array.
+    pub fn json_to_array_literal(json_str: &str) -> Option<String> {
+        match serde_json::from_str::<JsonValue>(json_str) {
+            Ok(JsonValue::Array(elements)) => Some(Self::format_elements(&elements)),
+            _ => None,
+        }
+    }
+
+    /// Format JSON array elements in PostgreSQL text array format
+    pub fn format_elements(elements: &[JsonValue]) -> String {
+        let formatted: Vec<String> = elements.iter().map(|elem| {
+            match elem {
+                JsonValue::Null => "NULL".to_string(),
+                JsonValue::Bool(b) => if *b { "t" } else { "f" }.to_string(),
+                JsonValue::Number(n) => n.to_string(),
+                JsonValue::String(s) => Self::quote_element(s),
+                JsonValue::Array(nested) => Self::format_elements(nested),
+                JsonValue::Object(_) => Self::quote_element(&elem.to_string()),
+            }
+        }).collect();
+
+        format!("{{{}}}", formatted.join(","))
+    }
+
+    /// Quote a string element, escaping quotes and backslashes
+    fn quote_element(s: &str) -> String {
+        let escaped = s.replace('\\', "\\\\").replace('"', "\\\"");
+        format!("\"{escaped}\"")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_array_literal() {
+        let value = ArrayHandler::parse_array_literal("{1,2,3}", Some(PgType::Int4)).unwrap();
+        assert_eq!(value, serde_json::json!([1, 2, 3]));
+
+        let value = ArrayHandler::parse_array_literal(r#"{"a b",c,NULL,"NULL","say \"hi\""}"#, Some(PgType::Text)).unwrap();
+        assert_eq!(value, serde_json::json!(["a b", "c", null, "NULL", "say \"hi\""]));
+
+        let value = ArrayHandler::parse_array_literal("{{1,2},{3,4}}", Some(PgType::Int8)).unwrap();
+        assert_eq!(value, serde_json::json!([[1, 2], [3, 4]]));
+
+        let value = ArrayHandler::parse_array_literal("{t,false}", Some(PgType::Bool)).unwrap();
+        assert_eq!(value, serde_json::json!([true, false]));
+
+        let value = ArrayHandler::parse_array_literal("{}", Some(PgType::Int4)).unwrap();
+        assert_eq!(value, serde_json::json!([]));
+
+        let value = ArrayHandler::parse_array_literal(r#"{1.5,abc,true,"2"}"#, None).unwrap();
+        assert_eq!(value, serde_json::json!([1.5, "abc", true, "2"]));
+
+        assert!(ArrayHandler::parse_array_literal("{1,x}", Some(PgType::Int4)).is_err());
+        assert!(ArrayHandler::parse_array_literal("{1,2", None).is_err());
+        assert!(ArrayHandler::parse_array_literal("1,2", None).is_err());
+    }
+
+    #[test]
+    fn test_json_to_array_literal() {
+        assert_eq!(ArrayHandler::json_to_array_literal("[1,2,3]").unwrap(), "{1,2,3}");
+        assert_eq!(ArrayHandler::json_to_array_literal(r#"["a",null]"#).unwrap(), r#"{"a",NULL}"#);
+        assert_eq!(ArrayHandler::json_to_array_literal("[[1,2],[3,4]]").unwrap(), "{{1,2},{3,4}}");
+        assert_eq!(ArrayHandler::json_to_array_literal("[true,false]").unwrap(), "{t,f}");
+        assert_eq!(ArrayHandler::json_to_array_literal("[]").unwrap(), "{}");
+        assert!(ArrayHandler::json_to_array_literal("{1,2}").is_none());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let literal = r#"{"a b","c,d",NULL,"back\\slash"}"#;
+        let json = ArrayHandler::array_literal_to_json(literal, Some(PgType::Text)).unwrap();
+        assert_eq!(ArrayHandler::json_to_array_literal(&json).unwrap(), literal);
+    }
+}
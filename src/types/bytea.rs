@@ -0,0 +1,121 @@
+use std::fmt::Write;
+
+/// Text representations of bytea values, selected by the `bytea_output` setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ByteaFormat {
+    /// `\x` followed by two hex digits per byte (PostgreSQL's default)
+    #[default]
+    Hex,
+    /// Printable ASCII as-is, backslashes doubled and other bytes as `\ooo` octal
+    Escape,
+}
+
+impl ByteaFormat {
+    /// Parse a `bytea_output` setting value
+    pub fn from_setting(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "hex" => Some(ByteaFormat::Hex),
+            "escape" => Some(ByteaFormat::Escape),
+            _ => None,
+        }
+    }
+
+    /// Render raw bytes as bytea text output
+    pub fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            ByteaFormat::Hex => format!("\\x{}", hex::encode(bytes)),
+            ByteaFormat::Escape => {
+                let mut out = String::with_capacity(bytes.len());
+                for &byte in bytes {
+                    match byte {
+                        b'\\' => out.push_str("\\\\"),
+                        0x20..=0x7e => out.push(byte as char),
+                        _ => {
+                            let _ = write!(out, "\\{byte:03o}");
+                        }
+                    }
+                }
+                out
+            }
+        }
+    }
+
+    /// Decode bytea text input. Values starting with `\x` are read as hex, anything else
+    /// in the escape format, where `\\` is a backslash and `\ooo` an octal byte.
+    pub fn decode(text: &str) -> Result<Vec<u8>, String> {
+        match text.strip_prefix("\\x").or_else(|| text.strip_prefix("\\X")) {
+            Some(digits) => Self::decode_hex(digits),
+            None => Self::decode_escape(text),
+        }
+    }
+
+    fn decode_hex(digits: &str) -> Result<Vec<u8>, String> {
+        let mut bytes = Vec::with_capacity(digits.len() / 2);
+        let mut chars = digits.chars().filter(|c| !c.is_ascii_whitespace());
+        while let Some(high) = chars.next() {
+            let high = high.to_digit(16)
+                .ok_or_else(|| format!("invalid hexadecimal digit: \"{high}\""))?;
+            let low = chars.next()
+                .ok_or_else(|| "invalid hexadecimal data: odd number of digits".to_string())?;
+            let low = low.to_digit(16)
+                .ok_or_else(|| format!("invalid hexadecimal digit: \"{low}\""))?;
+            bytes.push((high * 16 + low) as u8);
+        }
+        Ok(bytes)
+    }
+
+    fn decode_escape(text: &str) -> Result<Vec<u8>, String> {
+        let raw = text.as_bytes();
+        let mut bytes = Vec::with_capacity(raw.len());
+        let mut i = 0;
+        while i < raw.len() {
+            if raw[i] != b'\\' {
+                bytes.push(raw[i]);
+                i += 1;
+            } else if raw.get(i + 1) == Some(&b'\\') {
+                bytes.push(b'\\');
+                i += 2;
+            } else {
+                let octal = raw.get(i + 1..i + 4)
+                    .filter(|digits| digits[0] <= b'3' && digits.iter().all(|d| (b'0'..=b'7').contains(d)))
+                    .ok_or_else(|| "invalid input syntax for type bytea".to_string())?;
+                bytes.push(octal.iter().fold(0u8, |acc, d| acc * 8 + (d - b'0')));
+                i += 4;
+            }
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_formats() {
+        let bytes = [0xde, 0xad, b'A', b'\\', 0x00, b' '];
+        assert_eq!(ByteaFormat::Hex.encode(&bytes), "\\xdead415c0020");
+        assert_eq!(ByteaFormat::Escape.encode(&bytes), "\\336\\255A\\\\\\000 ");
+        assert_eq!(ByteaFormat::Hex.encode(&[]), "\\x");
+    }
+
+    #[test]
+    fn test_decode_round_trip() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        for format in [ByteaFormat::Hex, ByteaFormat::Escape] {
+            assert_eq!(ByteaFormat::decode(&format.encode(&bytes)).unwrap(), bytes);
+        }
+        assert_eq!(ByteaFormat::decode("\\xDE AD be ef").unwrap(), vec![0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(ByteaFormat::decode("abc").unwrap(), b"abc".to_vec());
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert!(ByteaFormat::decode("\\x123").is_err());
+        assert!(ByteaFormat::decode("\\xzz").is_err());
+        assert!(ByteaFormat::decode("a\\b").is_err());
+        assert!(ByteaFormat::decode("\\400").is_err());
+        assert_eq!(ByteaFormat::from_setting("Escape"), Some(ByteaFormat::Escape));
+        assert_eq!(ByteaFormat::from_setting("base64"), None);
+    }
+}
@@ -109,6 +109,8 @@ pub fn parse_timestamp_to_microseconds(timestamp_str: &str) -> Option<i64> {
         "%Y-%m-%d %H:%M:%S",        // YYYY-MM-DD HH:MM:SS
         "%Y-%m-%dT%H:%M:%S%.f",     // ISO format with T
         "%Y-%m-%dT%H:%M:%S",        // ISO format with T
+        "%Y-%m-%d %H:%M",           // YYYY-MM-DD HH:MM
+        "%Y-%m-%dT%H:%M",           // ISO format without seconds
     ];
     
     for format in &formats {
@@ -7,10 +7,30 @@ pub struct DecimalHandler;
 impl DecimalHandler {
     /// Convert a string to rust_decimal::Decimal
     pub fn parse_decimal(s: &str) -> Result<Decimal, String> {
+        let s = s.trim();
         Decimal::from_str(s)
+            .or_else(|e| Decimal::from_scientific(s).map_err(|_| e))
             .map_err(|e| format!("Invalid numeric value: {e}"))
     }
     
+    /// Convert a REAL to rust_decimal::Decimal through its shortest round-trip representation.
+    ///
+    /// NUMERIC columns have SQLite's numeric affinity, so '0.1' comes back as the
+    /// REAL 0.1; its exact binary value (0.1000000000000000055...) is not what was stored.
+    pub fn from_f64(f: f64) -> Result<Decimal, String> {
+        if f.is_nan() {
+            return Err("Cannot convert NaN to decimal".to_string());
+        }
+        if f.is_infinite() {
+            return Err("Cannot convert infinity to decimal".to_string());
+        }
+        let text = f.to_string();
+        Decimal::from_str(&text)
+            .or_else(|_| Decimal::from_scientific(&text))
+            .or_else(|_| Decimal::try_from(f))
+            .map_err(|e| format!("Cannot convert float {f} to decimal: {e}"))
+    }
+    
     /// Convert rust_decimal to PostgreSQL binary NUMERIC format
     pub fn encode_numeric(decimal: &Decimal) -> Vec<u8> {
         // PostgreSQL NUMERIC format:
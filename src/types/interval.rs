@@ -0,0 +1,510 @@
+/// PostgreSQL INTERVAL support
+///
+/// An interval keeps months, days and microseconds apart, as PostgreSQL does, because
+/// their lengths vary: a month has 28 to 31 days and adding one to a timestamp must land
+/// on the same day of the following month.
+///
+/// Storage in SQLite: intervals are stored as their text form, e.g. `1 year 2 mons 3 days`,
+/// and computed with by the interval_* functions. Older databases may hold INTEGER
+/// microseconds, which are still read.
+use chrono::{Datelike, Months, NaiveDate, NaiveDateTime, Timelike};
+use std::fmt;
+
+use crate::types::datetime_utils::{datetime_to_microseconds, microseconds_to_datetime};
+
+pub const MICROS_PER_SECOND: i64 = 1_000_000;
+pub const MICROS_PER_MINUTE: i64 = 60 * MICROS_PER_SECOND;
+pub const MICROS_PER_HOUR: i64 = 60 * MICROS_PER_MINUTE;
+pub const MICROS_PER_DAY: i64 = 24 * MICROS_PER_HOUR;
+const DAYS_PER_MONTH: i32 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Interval {
+    pub months: i32,
+    pub days: i32,
+    pub micros: i64,
+}
+
+impl Interval {
+    pub fn new(months: i32, days: i32, micros: i64) -> Self {
+        Self { months, days, micros }
+    }
+
+    /// Interval from stored INTEGER microseconds, whole days split off
+    pub fn from_micros(total: i64) -> Self {
+        Self {
+            months: 0,
+            days: (total / MICROS_PER_DAY) as i32,
+            micros: total % MICROS_PER_DAY,
+        }
+    }
+
+    /// Parse a stored value: INTEGER microseconds or the interval text form
+    pub fn from_storage(value: &str) -> Result<Self, String> {
+        match value.trim().parse::<i64>() {
+            Ok(micros) => Ok(Self::from_micros(micros)),
+            Err(_) => Self::parse(value),
+        }
+    }
+
+    /// Parse PostgreSQL interval input: `1 year 2 months 3 days 04:05:06`, `@ 3 days ago`,
+    /// `1.5 hours`, `-02:30`, ISO 8601 `P1Y2M3DT4H5M6S`, or a bare number of seconds
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid input syntax for type interval: \"{input}\"");
+        let lower = input.trim().to_lowercase();
+        if lower.is_empty() {
+            return Err(invalid());
+        }
+        if let Some(iso) = lower.strip_prefix('p') {
+            return Self::parse_iso8601(iso).ok_or_else(invalid);
+        }
+
+        let body = lower.strip_prefix('@').unwrap_or(&lower).trim();
+        let (body, ago) = match body.strip_suffix("ago") {
+            Some(rest) if rest.is_empty() || rest.ends_with(char::is_whitespace) => (rest.trim_end(), true),
+            _ => (body, false),
+        };
+
+        let mut interval = Self::default();
+        let mut tokens = body.split_whitespace().peekable();
+        while let Some(token) = tokens.next() {
+            if token.contains(':') {
+                interval.micros += parse_clock(token).ok_or_else(invalid)?;
+                continue;
+            }
+
+            let split = token
+                .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+                .unwrap_or(token.len());
+            let (number, unit) = token.split_at(split);
+            let value = number.parse::<f64>().map_err(|_| invalid())?;
+            let unit = if !unit.is_empty() {
+                unit.to_string()
+            } else if let Some(next) = tokens.next_if(|t| t.starts_with(|c: char| c.is_ascii_alphabetic())) {
+                next.to_string()
+            } else {
+                "second".to_string()
+            };
+            if !interval.add_unit(value, unit.trim_end_matches(',')) {
+                return Err(invalid());
+            }
+        }
+
+        Ok(if ago { interval.negate() } else { interval })
+    }
+
+    /// `[nY][nM][nW][nD][T[nH][nM][nS]]`, after the leading `P`
+    fn parse_iso8601(text: &str) -> Option<Self> {
+        let mut interval = Self::default();
+        let mut in_time = false;
+        let mut number = String::new();
+        for c in text.chars() {
+            match c {
+                't' if number.is_empty() => in_time = true,
+                '0'..='9' | '.' | '-' | '+' => number.push(c),
+                _ => {
+                    let value = number.parse::<f64>().ok()?;
+                    number.clear();
+                    let unit = match (c, in_time) {
+                        ('y', false) => "year",
+                        ('m', false) => "month",
+                        ('w', false) => "week",
+                        ('d', false) => "day",
+                        ('h', true) => "hour",
+                        ('m', true) => "minute",
+                        ('s', true) => "second",
+                        _ => return None,
+                    };
+                    interval.add_unit(value, unit);
+                }
+            }
+        }
+        number.is_empty().then_some(interval)
+    }
+
+    /// Add `value` units; fractions spill into the next smaller field (a month as 30 days)
+    fn add_unit(&mut self, value: f64, unit: &str) -> bool {
+        match unit {
+            "microsecond" | "microseconds" | "us" | "usec" | "usecs" => self.add_micros(value),
+            "millisecond" | "milliseconds" | "ms" | "msec" | "msecs" => self.add_micros(value * 1_000.0),
+            "second" | "seconds" | "s" | "sec" | "secs" => self.add_micros(value * MICROS_PER_SECOND as f64),
+            "minute" | "minutes" | "m" | "min" | "mins" => self.add_micros(value * MICROS_PER_MINUTE as f64),
+            "hour" | "hours" | "h" | "hr" | "hrs" => self.add_micros(value * MICROS_PER_HOUR as f64),
+            "day" | "days" | "d" => self.add_days(value),
+            "week" | "weeks" | "w" => self.add_days(value * 7.0),
+            "month" | "months" | "mon" | "mons" => self.add_months(value),
+            "year" | "years" | "y" | "yr" | "yrs" => self.add_months(value * 12.0),
+            "decade" | "decades" => self.add_months(value * 120.0),
+            "century" | "centuries" => self.add_months(value * 1_200.0),
+            "millennium" | "millennia" | "millenniums" => self.add_months(value * 12_000.0),
+            _ => return false,
+        }
+        true
+    }
+
+    fn add_months(&mut self, value: f64) {
+        let whole = value.trunc();
+        self.months += whole as i32;
+        self.add_days((value - whole) * DAYS_PER_MONTH as f64);
+    }
+
+    fn add_days(&mut self, value: f64) {
+        let whole = value.trunc();
+        self.days += whole as i32;
+        self.add_micros((value - whole) * MICROS_PER_DAY as f64);
+    }
+
+    fn add_micros(&mut self, value: f64) {
+        self.micros += value.round() as i64;
+    }
+
+    pub fn negate(&self) -> Self {
+        Self { months: -self.months, days: -self.days, micros: -self.micros }
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        Self {
+            months: self.months + other.months,
+            days: self.days + other.days,
+            micros: self.micros + other.micros,
+        }
+    }
+
+    /// Value to store in SQLite: the interval text
+    pub fn to_storage(&self) -> String {
+        self.to_string()
+    }
+
+    /// The storage value as a SQL literal
+    pub fn to_sql_literal(&self) -> String {
+        format!("'{self}'")
+    }
+
+    /// Length in microseconds with a month as 30 days, which is how PostgreSQL
+    /// compares and sorts intervals
+    pub fn span_micros(&self) -> i128 {
+        (self.months as i128 * DAYS_PER_MONTH as i128 + self.days as i128) * MICROS_PER_DAY as i128
+            + self.micros as i128
+    }
+
+    /// `interval * factor`: fractional months spill into days and fractional days into
+    /// the time part
+    pub fn scale(&self, factor: f64) -> Option<Self> {
+        let months = self.months as f64 * factor;
+        let days = self.days as f64 * factor + months.fract() * DAYS_PER_MONTH as f64;
+        let micros = self.micros as f64 * factor + days.fract() * MICROS_PER_DAY as f64;
+        let in_range = |value: f64, max: f64| value.is_finite() && value.abs() <= max;
+        if !in_range(months, i32::MAX as f64) || !in_range(days, i32::MAX as f64) || !in_range(micros, i64::MAX as f64) {
+            return None;
+        }
+        Some(Self { months: months.trunc() as i32, days: days.trunc() as i32, micros: micros.round() as i64 })
+    }
+
+    /// Binary wire format: microseconds (int8), days (int4), months (int4)
+    pub fn to_binary(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(16);
+        bytes.extend_from_slice(&self.micros.to_be_bytes());
+        bytes.extend_from_slice(&self.days.to_be_bytes());
+        bytes.extend_from_slice(&self.months.to_be_bytes());
+        bytes
+    }
+
+    pub fn from_binary(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != 16 {
+            return None;
+        }
+        Some(Self {
+            micros: i64::from_be_bytes(bytes[0..8].try_into().ok()?),
+            days: i32::from_be_bytes(bytes[8..12].try_into().ok()?),
+            months: i32::from_be_bytes(bytes[12..16].try_into().ok()?),
+        })
+    }
+
+    /// Add to a timestamp in microseconds since epoch: months first (clamping to the end
+    /// of shorter months), then days, then the time part
+    pub fn add_to_timestamp(&self, timestamp: i64) -> Option<i64> {
+        let datetime = microseconds_to_datetime(timestamp)?;
+        let datetime = if self.months >= 0 {
+            datetime.checked_add_months(Months::new(self.months as u32))?
+        } else {
+            datetime.checked_sub_months(Months::new(self.months.unsigned_abs()))?
+        };
+        Some(datetime_to_microseconds(&datetime) + self.days as i64 * MICROS_PER_DAY + self.micros)
+    }
+
+    /// `age(a, b)`: the difference in years, months and days rather than elapsed time
+    pub fn age(a: &NaiveDateTime, b: &NaiveDateTime) -> Self {
+        if a < b {
+            return Self::age(b, a).negate();
+        }
+        let mut months = (a.year() - b.year()) * 12 + a.month() as i32 - b.month() as i32;
+        let mut days = a.day() as i32 - b.day() as i32;
+        let mut micros = time_of_day_micros(a) - time_of_day_micros(b);
+        if micros < 0 {
+            micros += MICROS_PER_DAY;
+            days -= 1;
+        }
+        if days < 0 {
+            days += days_in_month(b.year(), b.month());
+            months -= 1;
+        }
+        Self { months, days, micros }
+    }
+
+    /// Move 24-hour periods into days
+    pub fn justify_hours(&self) -> Self {
+        let mut result = Self {
+            months: self.months,
+            days: self.days + (self.micros / MICROS_PER_DAY) as i32,
+            micros: self.micros % MICROS_PER_DAY,
+        };
+        if result.days > 0 && result.micros < 0 {
+            result.micros += MICROS_PER_DAY;
+            result.days -= 1;
+        } else if result.days < 0 && result.micros > 0 {
+            result.micros -= MICROS_PER_DAY;
+            result.days += 1;
+        }
+        result
+    }
+
+    /// Move 30-day periods into months
+    pub fn justify_days(&self) -> Self {
+        let mut result = Self {
+            months: self.months + self.days / DAYS_PER_MONTH,
+            days: self.days % DAYS_PER_MONTH,
+            micros: self.micros,
+        };
+        if result.months > 0 && result.days < 0 {
+            result.days += DAYS_PER_MONTH;
+            result.months -= 1;
+        } else if result.months < 0 && result.days > 0 {
+            result.days -= DAYS_PER_MONTH;
+            result.months += 1;
+        }
+        result
+    }
+
+    /// justify_hours and justify_days, with the signs of all fields made to agree
+    pub fn justify_interval(&self) -> Self {
+        let mut result = Self {
+            months: self.months,
+            days: self.days + (self.micros / MICROS_PER_DAY) as i32,
+            micros: self.micros % MICROS_PER_DAY,
+        };
+        result.months += result.days / DAYS_PER_MONTH;
+        result.days %= DAYS_PER_MONTH;
+
+        if result.months > 0 && (result.days < 0 || (result.days == 0 && result.micros < 0)) {
+            result.days += DAYS_PER_MONTH;
+            result.months -= 1;
+        } else if result.months < 0 && (result.days > 0 || (result.days == 0 && result.micros > 0)) {
+            result.days -= DAYS_PER_MONTH;
+            result.months += 1;
+        }
+        if result.days > 0 && result.micros < 0 {
+            result.micros += MICROS_PER_DAY;
+            result.days -= 1;
+        } else if result.days < 0 && result.micros > 0 {
+            result.micros -= MICROS_PER_DAY;
+            result.days += 1;
+        }
+        result
+    }
+
+    /// EXTRACT(field FROM interval)
+    pub fn extract(&self, field: &str) -> Option<f64> {
+        let years = (self.months / 12) as f64;
+        let seconds = (self.micros % MICROS_PER_MINUTE) as f64 / MICROS_PER_SECOND as f64;
+        let value = match field.to_lowercase().as_str() {
+            "epoch" => {
+                years * 365.25 * 86_400.0
+                    + (self.months % 12) as f64 * DAYS_PER_MONTH as f64 * 86_400.0
+                    + self.days as f64 * 86_400.0
+                    + self.micros as f64 / MICROS_PER_SECOND as f64
+            }
+            "millennium" | "millennia" => (self.months / 12_000) as f64,
+            "century" | "centuries" => (self.months / 1_200) as f64,
+            "decade" | "decades" => (self.months / 120) as f64,
+            "year" | "years" => years,
+            "quarter" => ((self.months % 12) / 3 + 1) as f64,
+            "month" | "months" => (self.months % 12) as f64,
+            "day" | "days" => self.days as f64,
+            "hour" | "hours" => (self.micros / MICROS_PER_HOUR) as f64,
+            "minute" | "minutes" => ((self.micros / MICROS_PER_MINUTE) % 60) as f64,
+            "second" | "seconds" => seconds,
+            "millisecond" | "milliseconds" => seconds * 1_000.0,
+            "microsecond" | "microseconds" => (self.micros % MICROS_PER_MINUTE) as f64,
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+impl fmt::Display for Interval {
+    /// PostgreSQL's default `postgres` IntervalStyle
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        let mut negative_before = false;
+        let mut push_field = |parts: &mut Vec<String>, value: i32, unit: &str| {
+            if value != 0 {
+                let sign = if negative_before && value > 0 { "+" } else { "" };
+                let plural = if value == 1 { "" } else { "s" };
+                parts.push(format!("{sign}{value} {unit}{plural}"));
+                negative_before |= value < 0;
+            }
+        };
+        push_field(&mut parts, self.months / 12, "year");
+        push_field(&mut parts, self.months % 12, "mon");
+        push_field(&mut parts, self.days, "day");
+
+        if self.micros != 0 || parts.is_empty() {
+            let sign = if self.micros < 0 {
+                "-"
+            } else if negative_before {
+                "+"
+            } else {
+                ""
+            };
+            let micros = self.micros.unsigned_abs();
+            let hours = micros / MICROS_PER_HOUR as u64;
+            let minutes = (micros / MICROS_PER_MINUTE as u64) % 60;
+            let seconds = (micros / MICROS_PER_SECOND as u64) % 60;
+            let fraction = micros % MICROS_PER_SECOND as u64;
+            if fraction > 0 {
+                parts.push(format!("{sign}{hours:02}:{minutes:02}:{seconds:02}.{fraction:06}"));
+            } else {
+                parts.push(format!("{sign}{hours:02}:{minutes:02}:{seconds:02}"));
+            }
+        }
+
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+/// `[+-]H:M[:S[.f]]` as microseconds
+fn parse_clock(token: &str) -> Option<i64> {
+    let (negative, body) = match token.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, token.strip_prefix('+').unwrap_or(token)),
+    };
+    let mut fields = body.split(':');
+    let hours = fields.next()?.parse::<i64>().ok()?;
+    let minutes = fields.next()?.parse::<i64>().ok()?;
+    let seconds = match fields.next() {
+        Some(seconds) => seconds.parse::<f64>().ok()?,
+        None => 0.0,
+    };
+    if fields.next().is_some() {
+        return None;
+    }
+    let micros = hours * MICROS_PER_HOUR + minutes * MICROS_PER_MINUTE
+        + (seconds * MICROS_PER_SECOND as f64).round() as i64;
+    Some(if negative { -micros } else { micros })
+}
+
+fn time_of_day_micros(datetime: &NaiveDateTime) -> i64 {
+    datetime.num_seconds_from_midnight() as i64 * MICROS_PER_SECOND
+        + (datetime.nanosecond() / 1_000) as i64
+}
+
+fn days_in_month(year: i32, month: u32) -> i32 {
+    let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    NaiveDate::from_ymd_opt(next_year, next_month, 1)
+        .and_then(|first| first.pred_opt())
+        .map(|last| last.day() as i32)
+        .unwrap_or(30)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timestamp(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_format() {
+        let cases = [
+            ("1 year 2 months 3 days 04:05:06", "1 year 2 mons 3 days 04:05:06"),
+            ("1 day", "1 day"),
+            ("36 hours", "36:00:00"),
+            ("1.5 years", "1 year 6 mons"),
+            ("2 weeks", "14 days"),
+            ("@ 3 days 2 hours ago", "-3 days -02:00:00"),
+            ("-1 days +02:30:00", "-1 days +02:30:00"),
+            ("P1Y2M3DT4H5M6.5S", "1 year 2 mons 3 days 04:05:06.500000"),
+            ("90", "00:01:30"),
+            ("1 mon 1.5 days", "1 mon 1 day 12:00:00"),
+            ("0 seconds", "00:00:00"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(Interval::parse(input).unwrap().to_string(), expected, "input: {input}");
+            assert_eq!(Interval::parse(expected).unwrap().to_string(), expected, "round trip: {expected}");
+        }
+        assert!(Interval::parse("1 fortnight").is_err());
+        assert!(Interval::parse("").is_err());
+    }
+
+    #[test]
+    fn test_storage_and_binary() {
+        let day = Interval::parse("1 day 02:30:00").unwrap();
+        assert_eq!(day.to_storage(), "1 day 02:30:00");
+        assert_eq!(day.to_sql_literal(), "'1 day 02:30:00'");
+        // INTEGER microseconds from older databases
+        assert_eq!(Interval::from_storage("95400000000").unwrap().to_string(), "1 day 02:30:00");
+
+        let month = Interval::parse("1 month 2 days").unwrap();
+        assert_eq!(month.to_storage(), "1 mon 2 days");
+        assert_eq!(month.to_sql_literal(), "'1 mon 2 days'");
+        assert_eq!(Interval::from_storage("1 mon 2 days").unwrap(), month);
+
+        let bytes = Interval::new(14, 3, 1_500_000).to_binary();
+        assert_eq!(bytes.len(), 16);
+        assert_eq!(Interval::from_binary(&bytes), Some(Interval::new(14, 3, 1_500_000)));
+    }
+
+    #[test]
+    fn test_timestamp_arithmetic_and_age() {
+        let jan31 = datetime_to_microseconds(&timestamp("2024-01-31 10:00:00"));
+        let month = Interval::parse("1 month").unwrap();
+        assert_eq!(month.add_to_timestamp(jan31), Some(datetime_to_microseconds(&timestamp("2024-02-29 10:00:00"))));
+        assert_eq!(month.negate().add_to_timestamp(jan31), Some(datetime_to_microseconds(&timestamp("2023-12-31 10:00:00"))));
+
+        let age = Interval::age(&timestamp("2001-04-10 00:00:00"), &timestamp("1957-06-13 00:00:00"));
+        assert_eq!(age.to_string(), "43 years 9 mons 27 days");
+        let age = Interval::age(&timestamp("2024-01-01 00:00:00"), &timestamp("2024-03-15 12:00:00"));
+        assert_eq!(age.to_string(), "-2 mons -14 days -12:00:00");
+    }
+
+    #[test]
+    fn test_scale_and_span() {
+        let interval = Interval::parse("1 mon 1 day 01:00:00").unwrap();
+        assert_eq!(interval.scale(3.0).unwrap().to_string(), "3 mons 3 days 03:00:00");
+        assert_eq!(interval.scale(0.5).unwrap().to_string(), "15 days 12:30:00");
+        assert_eq!(interval.scale(-1.0).unwrap(), interval.negate());
+        assert!(interval.scale(f64::INFINITY).is_none());
+
+        // A month compares as 30 days
+        assert_eq!(Interval::parse("1 mon").unwrap().span_micros(), Interval::parse("30 days").unwrap().span_micros());
+        assert!(Interval::parse("1 day").unwrap().span_micros() > Interval::parse("23:59:59").unwrap().span_micros());
+    }
+
+    #[test]
+    fn test_justify_and_extract() {
+        assert_eq!(Interval::parse("27 hours").unwrap().justify_hours().to_string(), "1 day 03:00:00");
+        assert_eq!(Interval::parse("65 days").unwrap().justify_days().to_string(), "2 mons 5 days");
+        assert_eq!(Interval::parse("1 mon -1 hour").unwrap().justify_interval().to_string(), "29 days 23:00:00");
+
+        let interval = Interval::parse("1 year 2 months 3 days 04:05:06.5").unwrap();
+        assert_eq!(interval.extract("year"), Some(1.0));
+        assert_eq!(interval.extract("month"), Some(2.0));
+        assert_eq!(interval.extract("day"), Some(3.0));
+        assert_eq!(interval.extract("hour"), Some(4.0));
+        assert_eq!(interval.extract("minute"), Some(5.0));
+        assert_eq!(interval.extract("second"), Some(6.5));
+        assert_eq!(Interval::parse("1 day 1 hour").unwrap().extract("epoch"), Some(90_000.0));
+        assert_eq!(interval.extract("fortnight"), None);
+    }
+}
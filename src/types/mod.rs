@@ -7,9 +7,16 @@ pub mod query_context_analyzer;
 pub mod aggregate_type_fixer;
 pub mod value_converter;
 pub mod decimal_handler;
+pub mod array_handler;
 pub mod datetime_utils;
+pub mod interval;
+pub mod range;
+pub mod network;
+pub mod bytea;
+pub mod xml;
 pub mod numeric_utils;
 pub mod type_resolution;
+pub mod type_checker;
 
 pub use type_mapper::{TypeMapper, PgType};
 pub use uuid::{UuidHandler, generate_uuid_v4};
@@ -17,4 +24,11 @@ pub use sqlite_type_info::{get_pg_type_oid_from_sqlite, sqlite_type_to_pg_oid, i
 pub use schema_type_mapper::SchemaTypeMapper;
 pub use query_context_analyzer::QueryContextAnalyzer;
 pub use value_converter::ValueConverter;
-pub use decimal_handler::DecimalHandler;
\ No newline at end of file
+pub use decimal_handler::DecimalHandler;
+pub use array_handler::ArrayHandler;
+pub use interval::Interval;
+pub use range::{Range, RangeKind};
+pub use network::{InetValue, MacAddr, NetworkKind};
+pub use bytea::ByteaFormat;
+pub use xml::XmlOption;
+pub use type_checker::TypeChecker;
\ No newline at end of file
@@ -0,0 +1,360 @@
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use crate::types::PgType;
+
+/// Address family codes used by the inet/cidr binary format (PGSQL_AF_INET, PGSQL_AF_INET6)
+const PGSQL_AF_INET: u8 = 2;
+const PGSQL_AF_INET6: u8 = 3;
+
+/// The network address types stored as canonical text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkKind {
+    Inet,
+    Cidr,
+    Macaddr,
+}
+
+impl NetworkKind {
+    /// Look up a network kind by its type name (e.g. "inet")
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "inet" => Some(NetworkKind::Inet),
+            "cidr" => Some(NetworkKind::Cidr),
+            "macaddr" => Some(NetworkKind::Macaddr),
+            _ => None,
+        }
+    }
+
+    pub fn from_pg_type(pg_type: PgType) -> Option<Self> {
+        match pg_type {
+            PgType::Inet => Some(NetworkKind::Inet),
+            PgType::Cidr => Some(NetworkKind::Cidr),
+            PgType::Macaddr => Some(NetworkKind::Macaddr),
+            _ => None,
+        }
+    }
+
+    /// Validate a value written as text and return its canonical form
+    pub fn canonicalize(self, text: &str) -> Result<String, String> {
+        match self {
+            NetworkKind::Inet => InetValue::parse_inet(text).map(|value| value.to_string()),
+            NetworkKind::Cidr => InetValue::parse_cidr(text).map(|value| value.to_string()),
+            NetworkKind::Macaddr => MacAddr::parse(text).map(|value| value.to_string()),
+        }
+    }
+
+    /// Decode a binary-format parameter and return its canonical form
+    ///
+    /// Network parameters are described to clients as TEXT, so binary-format values are
+    /// usually plain UTF-8; the PostgreSQL binary encodings are accepted as well.
+    pub fn decode_param(self, bytes: &[u8]) -> Result<String, String> {
+        let decoded = match self {
+            NetworkKind::Inet | NetworkKind::Cidr => InetValue::from_binary(bytes)
+                .map(|value| InetValue { is_cidr: self == NetworkKind::Cidr, ..value }.to_string()),
+            NetworkKind::Macaddr => MacAddr::from_binary(bytes).map(|mac| mac.to_string()),
+        };
+        decoded
+            .or_else(|err| std::str::from_utf8(bytes).map(str::to_string).map_err(|_| err))
+            .and_then(|text| self.canonicalize(&text))
+    }
+}
+
+impl fmt::Display for NetworkKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            NetworkKind::Inet => "inet",
+            NetworkKind::Cidr => "cidr",
+            NetworkKind::Macaddr => "macaddr",
+        })
+    }
+}
+
+/// An INET or CIDR value: an IPv4 or IPv6 address with a netmask length
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InetValue {
+    pub addr: IpAddr,
+    pub bits: u8,
+    /// CIDR values are networks and always display their netmask
+    pub is_cidr: bool,
+}
+
+impl InetValue {
+    /// Parse an inet value: an address with an optional /bits netmask
+    pub fn parse_inet(text: &str) -> Result<Self, String> {
+        Self::parse(text, false)
+    }
+
+    /// Parse a cidr value; bits to the right of the netmask must be zero
+    pub fn parse_cidr(text: &str) -> Result<Self, String> {
+        let value = Self::parse(text, true)?;
+        if value.network_addr() != value.addr {
+            return Err(format!("invalid cidr value: \"{}\": value has bits set to right of mask", text.trim()));
+        }
+        Ok(value)
+    }
+
+    fn parse(text: &str, is_cidr: bool) -> Result<Self, String> {
+        let trimmed = text.trim();
+        let invalid = || format!(
+            "invalid input syntax for type {}: \"{trimmed}\"",
+            if is_cidr { "cidr" } else { "inet" }
+        );
+
+        let (addr_text, bits_text) = match trimmed.split_once('/') {
+            Some((addr, bits)) => (addr, Some(bits)),
+            None => (trimmed, None),
+        };
+        // cidr accepts abbreviated IPv4 networks such as '192.168.1', netmask covering the given octets
+        let octets = addr_text.split('.').count();
+        let (addr, default_bits) = if is_cidr && octets < 4 && !addr_text.contains(':') {
+            let padded = format!("{addr_text}{}", ".0".repeat(4 - octets));
+            (padded.parse::<IpAddr>().map_err(|_| invalid())?, Some(8 * octets as u8))
+        } else {
+            (addr_text.parse::<IpAddr>().map_err(|_| invalid())?, None)
+        };
+        let max_bits = max_bits(addr);
+        let bits = match bits_text {
+            Some(bits) => bits.parse::<u8>().ok().filter(|&bits| bits <= max_bits).ok_or_else(invalid)?,
+            None => default_bits.unwrap_or(max_bits),
+        };
+
+        Ok(InetValue { addr, bits, is_cidr })
+    }
+
+    /// 4 for IPv4, 6 for IPv6
+    pub fn family(&self) -> i32 {
+        match self.addr {
+            IpAddr::V4(_) => 4,
+            IpAddr::V6(_) => 6,
+        }
+    }
+
+    /// The address with the host bits cleared
+    pub fn network_addr(&self) -> IpAddr {
+        mask_addr(self.addr, self.bits, false)
+    }
+
+    /// The address with the host bits set
+    pub fn broadcast_addr(&self) -> IpAddr {
+        mask_addr(self.addr, self.bits, true)
+    }
+
+    /// The netmask as an address, e.g. 255.255.255.0 for /24
+    pub fn netmask_addr(&self) -> IpAddr {
+        let all_ones = match self.addr {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::BROADCAST),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from(u128::MAX)),
+        };
+        mask_addr(all_ones, self.bits, false)
+    }
+
+    /// The network part of the address as a cidr value
+    pub fn network(&self) -> InetValue {
+        InetValue { addr: self.network_addr(), bits: self.bits, is_cidr: true }
+    }
+
+    /// Whether `other` lies within this network; strictly (`<<`) or also when equal (`<<=`)
+    pub fn contains(&self, other: &InetValue, or_equal: bool) -> bool {
+        if self.family() != other.family() {
+            return false;
+        }
+        let narrower = if or_equal { other.bits >= self.bits } else { other.bits > self.bits };
+        narrower && mask_addr(other.addr, self.bits, false) == self.network_addr()
+    }
+
+    /// Whether either network contains or equals the other (`&&`)
+    pub fn overlaps(&self, other: &InetValue) -> bool {
+        self.contains(other, true) || other.contains(self, true)
+    }
+
+    /// Binary wire format: family, bits, is_cidr flag, address length, address bytes
+    pub fn to_binary(&self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(20);
+        match self.addr {
+            IpAddr::V4(addr) => {
+                result.extend_from_slice(&[PGSQL_AF_INET, self.bits, self.is_cidr as u8, 4]);
+                result.extend_from_slice(&addr.octets());
+            }
+            IpAddr::V6(addr) => {
+                result.extend_from_slice(&[PGSQL_AF_INET6, self.bits, self.is_cidr as u8, 16]);
+                result.extend_from_slice(&addr.octets());
+            }
+        }
+        result
+    }
+
+    /// Decode the binary wire format
+    pub fn from_binary(bytes: &[u8]) -> Result<Self, String> {
+        let [family, bits, is_cidr, len, address @ ..] = bytes else {
+            return Err("invalid length in external \"inet\" value".to_string());
+        };
+        let addr = match (*family, *len as usize, address.len()) {
+            (PGSQL_AF_INET, 4, 4) => IpAddr::V4(Ipv4Addr::new(address[0], address[1], address[2], address[3])),
+            (PGSQL_AF_INET6, 16, 16) => {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(address);
+                IpAddr::V6(Ipv6Addr::from(octets))
+            }
+            _ => return Err("invalid address family in external \"inet\" value".to_string()),
+        };
+        if *bits > max_bits(addr) {
+            return Err("invalid bits in external \"inet\" value".to_string());
+        }
+        Ok(InetValue { addr, bits: *bits, is_cidr: *is_cidr != 0 })
+    }
+}
+
+impl fmt::Display for InetValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // inet omits a full-length netmask; cidr always shows it
+        if self.is_cidr || self.bits != max_bits(self.addr) {
+            write!(f, "{}/{}", self.addr, self.bits)
+        } else {
+            write!(f, "{}", self.addr)
+        }
+    }
+}
+
+fn max_bits(addr: IpAddr) -> u8 {
+    match addr {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+/// Keep the first `bits` bits of an address, clearing (or setting) the rest
+fn mask_addr(addr: IpAddr, bits: u8, set_host_bits: bool) -> IpAddr {
+    match addr {
+        IpAddr::V4(v4) => {
+            let host_mask = u32::MAX.checked_shr(bits as u32).unwrap_or(0);
+            let value = u32::from(v4);
+            IpAddr::V4(Ipv4Addr::from(if set_host_bits { value | host_mask } else { value & !host_mask }))
+        }
+        IpAddr::V6(v6) => {
+            let host_mask = u128::MAX.checked_shr(bits as u32).unwrap_or(0);
+            let value = u128::from(v6);
+            IpAddr::V6(Ipv6Addr::from(if set_host_bits { value | host_mask } else { value & !host_mask }))
+        }
+    }
+}
+
+/// A MACADDR value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MacAddr(pub [u8; 6]);
+
+impl MacAddr {
+    /// Parse the formats PostgreSQL accepts: '08:00:2b:01:02:03', '08-00-2b-01-02-03',
+    /// '08002b:010203', '08002b-010203', '0800.2b01.0203', '0800-2b01-0203' and '08002b010203'
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let trimmed = text.trim();
+        let invalid = || format!("invalid input syntax for type macaddr: \"{trimmed}\"");
+
+        let separator = trimmed.chars().find(|c| matches!(c, ':' | '-' | '.'));
+        let groups: Vec<&str> = match separator {
+            Some(separator) => trimmed.split(separator).collect(),
+            None => vec![trimmed],
+        };
+        let group_len = 12 / groups.len();
+        let valid_separator = match groups.len() {
+            1 => true,
+            2 | 6 => matches!(separator, Some(':' | '-')),
+            3 => matches!(separator, Some('.' | '-')),
+            _ => false,
+        };
+        let valid_grouping = valid_separator
+            && groups.iter().all(|group| group.len() == group_len && group.bytes().all(|b| b.is_ascii_hexdigit()));
+        if !valid_grouping {
+            return Err(invalid());
+        }
+
+        let digits = groups.concat();
+        let mut bytes = [0u8; 6];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+        }
+        Ok(MacAddr(bytes))
+    }
+
+    /// Decode the binary wire format (six bytes)
+    pub fn from_binary(bytes: &[u8]) -> Result<Self, String> {
+        <[u8; 6]>::try_from(bytes)
+            .map(MacAddr)
+            .map_err(|_| "invalid length in external \"macaddr\" value".to_string())
+    }
+}
+
+impl fmt::Display for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{a:02x}:{b:02x}:{c:02x}:{d:02x}:{e:02x}:{g:02x}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inet_canonical_form() {
+        assert_eq!(InetValue::parse_inet("192.168.1.5").unwrap().to_string(), "192.168.1.5");
+        assert_eq!(InetValue::parse_inet("192.168.1.5/32").unwrap().to_string(), "192.168.1.5");
+        assert_eq!(InetValue::parse_inet(" 192.168.1.5/24 ").unwrap().to_string(), "192.168.1.5/24");
+        assert_eq!(InetValue::parse_inet("2001:DB8:0:0::1").unwrap().to_string(), "2001:db8::1");
+        assert!(InetValue::parse_inet("192.168.1.300").is_err());
+        assert!(InetValue::parse_inet("192.168.1.5/33").is_err());
+        assert!(InetValue::parse_inet("not an address").is_err());
+    }
+
+    #[test]
+    fn test_cidr_requires_network_address() {
+        assert_eq!(InetValue::parse_cidr("10.0.0.0/8").unwrap().to_string(), "10.0.0.0/8");
+        assert_eq!(InetValue::parse_cidr("10.1.2.3").unwrap().to_string(), "10.1.2.3/32");
+        assert_eq!(InetValue::parse_cidr("192.168.1").unwrap().to_string(), "192.168.1.0/24");
+        assert!(InetValue::parse_inet("192.168.1").is_err());
+        assert!(InetValue::parse_cidr("10.1.2.3/8").unwrap_err().contains("bits set to right of mask"));
+    }
+
+    #[test]
+    fn test_inet_functions() {
+        let value = InetValue::parse_inet("192.168.1.5/24").unwrap();
+        assert_eq!(value.network().to_string(), "192.168.1.0/24");
+        assert_eq!(value.broadcast_addr().to_string(), "192.168.1.255");
+        assert_eq!(value.netmask_addr().to_string(), "255.255.255.0");
+
+        let net = InetValue::parse_cidr("192.168.0.0/16").unwrap();
+        assert!(net.contains(&value, false));
+        assert!(!value.contains(&net, false));
+        assert!(net.contains(&net, true));
+        assert!(!net.contains(&net, false));
+        assert!(value.overlaps(&net));
+        assert!(!net.contains(&InetValue::parse_inet("::1").unwrap(), true));
+    }
+
+    #[test]
+    fn test_inet_binary_roundtrip() {
+        for text in ["10.0.0.1", "10.0.0.0/8", "2001:db8::1/64"] {
+            let value = InetValue::parse_inet(text).unwrap();
+            assert_eq!(InetValue::from_binary(&value.to_binary()).unwrap(), value);
+        }
+        assert_eq!(InetValue::parse_inet("10.0.0.1").unwrap().to_binary(), vec![2, 32, 0, 4, 10, 0, 0, 1]);
+    }
+
+    #[test]
+    fn test_decode_param() {
+        let binary = InetValue::parse_inet("10.1.0.0/16").unwrap().to_binary();
+        assert_eq!(NetworkKind::Cidr.decode_param(&binary).unwrap(), "10.1.0.0/16");
+        assert_eq!(NetworkKind::Inet.decode_param(b"10.0.0.1/32").unwrap(), "10.0.0.1");
+        assert_eq!(NetworkKind::Macaddr.decode_param(&[8, 0, 0x2b, 1, 2, 3]).unwrap(), "08:00:2b:01:02:03");
+        assert!(NetworkKind::Inet.decode_param(b"nonsense").is_err());
+    }
+
+    #[test]
+    fn test_macaddr_formats() {
+        for text in ["08:00:2b:01:02:03", "08-00-2B-01-02-03", "08002b:010203", "0800.2b01.0203", "08002b010203"] {
+            assert_eq!(MacAddr::parse(text).unwrap().to_string(), "08:00:2b:01:02:03", "{text}");
+        }
+        assert!(MacAddr::parse("08:00:2b:01:02").is_err());
+        assert!(MacAddr::parse("08:00:2b:01:02:0g").is_err());
+        assert!(MacAddr::parse("0800:2b01:0203").is_err());
+    }
+}
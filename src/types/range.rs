@@ -0,0 +1,549 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+use rust_decimal::Decimal;
+use crate::types::{datetime_utils, PgType, ValueConverter};
+
+/// The built-in PostgreSQL range types
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeKind {
+    Int4,
+    Int8,
+    Num,
+    Ts,
+    Tstz,
+    Date,
+}
+
+impl RangeKind {
+    /// Look up a range kind by its type name (e.g. "int4range")
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "int4range" => Some(RangeKind::Int4),
+            "int8range" => Some(RangeKind::Int8),
+            "numrange" => Some(RangeKind::Num),
+            "tsrange" => Some(RangeKind::Ts),
+            "tstzrange" => Some(RangeKind::Tstz),
+            "daterange" => Some(RangeKind::Date),
+            _ => None,
+        }
+    }
+
+    pub fn from_pg_type(pg_type: PgType) -> Option<Self> {
+        match pg_type {
+            PgType::Int4range => Some(RangeKind::Int4),
+            PgType::Int8range => Some(RangeKind::Int8),
+            PgType::Numrange => Some(RangeKind::Num),
+            PgType::Tsrange => Some(RangeKind::Ts),
+            PgType::Tstzrange => Some(RangeKind::Tstz),
+            PgType::Daterange => Some(RangeKind::Date),
+            _ => None,
+        }
+    }
+
+    pub fn pg_type(self) -> PgType {
+        match self {
+            RangeKind::Int4 => PgType::Int4range,
+            RangeKind::Int8 => PgType::Int8range,
+            RangeKind::Num => PgType::Numrange,
+            RangeKind::Ts => PgType::Tsrange,
+            RangeKind::Tstz => PgType::Tstzrange,
+            RangeKind::Date => PgType::Daterange,
+        }
+    }
+
+    /// The element type of the range
+    pub fn subtype(self) -> PgType {
+        match self {
+            RangeKind::Int4 => PgType::Int4,
+            RangeKind::Int8 => PgType::Int8,
+            RangeKind::Num => PgType::Numeric,
+            RangeKind::Ts => PgType::Timestamp,
+            RangeKind::Tstz => PgType::Timestamptz,
+            RangeKind::Date => PgType::Date,
+        }
+    }
+
+    /// Discrete ranges are canonicalized to the `[lower,upper)` form
+    fn is_discrete(self) -> bool {
+        matches!(self, RangeKind::Int4 | RangeKind::Int8 | RangeKind::Date)
+    }
+
+    /// Parse a bound or element written as text
+    pub fn parse_value(self, text: &str) -> Option<RangeValue> {
+        let text = text.trim();
+        match self {
+            RangeKind::Int4 => text.parse::<i32>().ok().map(|v| RangeValue::Int(v as i64)),
+            RangeKind::Int8 => text.parse::<i64>().ok().map(RangeValue::Int),
+            RangeKind::Num => Decimal::from_str(text).or_else(|_| Decimal::from_scientific(text)).ok().map(RangeValue::Num),
+            RangeKind::Date => datetime_utils::parse_date_to_days(text)
+                .or_else(|| datetime_utils::parse_timestamp_to_microseconds(text).map(|micros| micros.div_euclid(86_400_000_000)))
+                .map(RangeValue::Date),
+            RangeKind::Ts => datetime_utils::parse_timestamp_to_microseconds(text)
+                .or_else(|| datetime_utils::parse_date_to_days(text).map(|days| days * 86_400_000_000))
+                .map(RangeValue::Timestamp),
+            RangeKind::Tstz => ValueConverter::pg_to_sqlite(&expand_zone_offset(text), PgType::Timestamptz).ok()
+                .and_then(|micros| micros.parse::<i64>().ok())
+                .or_else(|| datetime_utils::parse_date_to_days(text).map(|days| days * 86_400_000_000))
+                .map(RangeValue::Timestamp),
+        }
+    }
+
+    /// Convert an element in SQLite storage form: integers, days for dates and
+    /// microseconds for timestamps
+    pub fn value_from_integer(self, value: i64) -> RangeValue {
+        match self {
+            RangeKind::Int4 | RangeKind::Int8 => RangeValue::Int(value),
+            RangeKind::Num => RangeValue::Num(Decimal::from(value)),
+            RangeKind::Date => RangeValue::Date(value),
+            RangeKind::Ts | RangeKind::Tstz => RangeValue::Timestamp(value),
+        }
+    }
+
+    /// Guess the kind of a range literal from the shape of its bounds
+    fn infer(lower: Option<&str>, upper: Option<&str>) -> Option<Self> {
+        let sample = lower.or(upper)?;
+        let candidates = if sample.contains(':') {
+            let has_zone = sample.ends_with('Z')
+                || sample.rfind(['+', '-']).is_some_and(|pos| pos > 10);
+            if has_zone { [RangeKind::Tstz, RangeKind::Ts] } else { [RangeKind::Ts, RangeKind::Tstz] }
+        } else if sample.len() >= 10 && sample.as_bytes().get(4) == Some(&b'-') {
+            [RangeKind::Date, RangeKind::Ts]
+        } else {
+            [RangeKind::Int8, RangeKind::Num]
+        };
+        candidates.into_iter().find(|kind| {
+            lower.is_none_or(|v| kind.parse_value(v).is_some()) && upper.is_none_or(|v| kind.parse_value(v).is_some())
+        })
+    }
+}
+
+/// Expand an hour-only zone offset ("+02") to the "+0200" form the timestamptz parser expects
+fn expand_zone_offset(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let n = bytes.len();
+    if n > 13 && matches!(bytes[n - 3], b'+' | b'-') && bytes[n - 2..].iter().all(u8::is_ascii_digit) {
+        format!("{text}00")
+    } else {
+        text.to_string()
+    }
+}
+
+impl fmt::Display for RangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.pg_type().name())
+    }
+}
+
+/// A range bound in SQLite storage terms
+#[derive(Debug, Clone, PartialEq)]
+pub enum RangeValue {
+    Int(i64),
+    Num(Decimal),
+    /// Days since 1970-01-01
+    Date(i64),
+    /// Microseconds since 1970-01-01 (UTC for tstzrange)
+    Timestamp(i64),
+}
+
+impl PartialOrd for RangeValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (RangeValue::Int(a), RangeValue::Int(b)) => a.partial_cmp(b),
+            (RangeValue::Num(a), RangeValue::Num(b)) => a.partial_cmp(b),
+            (RangeValue::Int(a), RangeValue::Num(b)) => Decimal::from(*a).partial_cmp(b),
+            (RangeValue::Num(a), RangeValue::Int(b)) => a.partial_cmp(&Decimal::from(*b)),
+            (RangeValue::Date(a), RangeValue::Date(b)) => a.partial_cmp(b),
+            (RangeValue::Timestamp(a), RangeValue::Timestamp(b)) => a.partial_cmp(b),
+            (RangeValue::Date(a), RangeValue::Timestamp(b)) => (a * 86_400_000_000).partial_cmp(b),
+            (RangeValue::Timestamp(a), RangeValue::Date(b)) => a.partial_cmp(&(b * 86_400_000_000)),
+            _ => None,
+        }
+    }
+}
+
+impl RangeValue {
+    /// The next value of a discrete type
+    fn successor(&self, kind: RangeKind) -> Result<RangeValue, String> {
+        match self {
+            RangeValue::Int(v) => {
+                let max = if kind == RangeKind::Int4 { i32::MAX as i64 } else { i64::MAX };
+                if *v >= max {
+                    return Err("integer out of range".to_string());
+                }
+                Ok(RangeValue::Int(v + 1))
+            }
+            RangeValue::Date(v) => Ok(RangeValue::Date(v + 1)),
+            other => Ok(other.clone()),
+        }
+    }
+
+    fn format(&self, kind: RangeKind) -> String {
+        match self {
+            RangeValue::Int(v) => v.to_string(),
+            RangeValue::Num(v) => v.to_string(),
+            RangeValue::Date(days) => datetime_utils::format_days_to_date(*days),
+            RangeValue::Timestamp(micros) => {
+                let timestamp = datetime_utils::format_microseconds_to_timestamp(*micros);
+                if kind == RangeKind::Tstz {
+                    format!("\"{timestamp}+00\"")
+                } else {
+                    format!("\"{timestamp}\"")
+                }
+            }
+        }
+    }
+}
+
+/// A range value, canonicalized the way PostgreSQL stores it
+///
+/// Ranges are stored in SQLite as their canonical text form, e.g. `[1,11)` for
+/// `int4range(1, 10, '[]')` or `empty`. Missing bounds are unbounded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Range {
+    pub kind: RangeKind,
+    pub empty: bool,
+    pub lower: Option<RangeValue>,
+    pub upper: Option<RangeValue>,
+    pub lower_inc: bool,
+    pub upper_inc: bool,
+}
+
+impl Range {
+    pub fn empty(kind: RangeKind) -> Self {
+        Range { kind, empty: true, lower: None, upper: None, lower_inc: false, upper_inc: false }
+    }
+
+    /// Build a range from its bounds, as the range constructor functions do
+    pub fn new(
+        kind: RangeKind,
+        lower: Option<RangeValue>,
+        upper: Option<RangeValue>,
+        lower_inc: bool,
+        upper_inc: bool,
+    ) -> Result<Self, String> {
+        let mut range = Range {
+            kind,
+            empty: false,
+            lower_inc: lower_inc && lower.is_some(),
+            upper_inc: upper_inc && upper.is_some(),
+            lower,
+            upper,
+        };
+
+        if let (Some(lower), Some(upper)) = (&range.lower, &range.upper) {
+            match lower.partial_cmp(upper) {
+                Some(Ordering::Greater) => {
+                    return Err("range lower bound must be less than or equal to range upper bound".to_string());
+                }
+                Some(Ordering::Equal) if !(range.lower_inc && range.upper_inc) => return Ok(Self::empty(kind)),
+                _ => {}
+            }
+        }
+
+        if kind.is_discrete() {
+            if let Some(lower) = range.lower.as_mut()
+                && !range.lower_inc
+            {
+                *lower = lower.successor(kind)?;
+                range.lower_inc = true;
+            }
+            if let Some(upper) = range.upper.as_mut()
+                && range.upper_inc
+            {
+                *upper = upper.successor(kind)?;
+                range.upper_inc = false;
+            }
+            if range.lower.is_some() && range.lower == range.upper {
+                return Ok(Self::empty(kind));
+            }
+        }
+
+        Ok(range)
+    }
+
+    /// Build a range from bounds and a bounds specifier such as '[)'
+    pub fn with_bounds(
+        kind: RangeKind,
+        lower: Option<RangeValue>,
+        upper: Option<RangeValue>,
+        bounds: &str,
+    ) -> Result<Self, String> {
+        let (lower_inc, upper_inc) = match bounds {
+            "[)" => (true, false),
+            "[]" => (true, true),
+            "(]" => (false, true),
+            "()" => (false, false),
+            _ => return Err(format!("invalid range bound flags: \"{bounds}\"")),
+        };
+        Self::new(kind, lower, upper, lower_inc, upper_inc)
+    }
+
+    /// Parse a range literal of the given kind
+    pub fn parse(kind: RangeKind, text: &str) -> Result<Self, String> {
+        let (lower, upper, lower_inc, upper_inc) = Self::split_literal(text)
+            .ok_or_else(|| format!("malformed range literal: \"{text}\""))?;
+        let Some((lower, upper)) = lower.zip(upper) else {
+            return Ok(Self::empty(kind));
+        };
+        let parse_bound = |bound: Option<String>| match bound {
+            None => Ok(None),
+            Some(value) => kind.parse_value(&value).map(Some)
+                .ok_or_else(|| format!("invalid input syntax for type {}: \"{value}\"", kind.subtype().name())),
+        };
+        Self::new(kind, parse_bound(lower)?, parse_bound(upper)?, lower_inc, upper_inc)
+    }
+
+    /// Parse a range literal without knowing its type, as operators on stored
+    /// values must. Returns None for anything that is not a range literal.
+    pub fn parse_inferred(text: &str) -> Option<Self> {
+        let (lower, upper, _, _) = Self::split_literal(text)?;
+        let Some((lower, upper)) = lower.zip(upper) else {
+            return Some(Self::empty(RangeKind::Int8));
+        };
+        let kind = RangeKind::infer(lower.as_deref(), upper.as_deref())?;
+        Self::parse(kind, text).ok()
+    }
+
+    /// Split a literal into its bounds. Returns `(None, None, ..)` for `empty`
+    /// and `Some(None)` for an unbounded side.
+    #[allow(clippy::type_complexity)]
+    fn split_literal(text: &str) -> Option<(Option<Option<String>>, Option<Option<String>>, bool, bool)> {
+        let text = text.trim();
+        if text.eq_ignore_ascii_case("empty") {
+            return Some((None, None, false, false));
+        }
+
+        let lower_inc = match text.chars().next()? {
+            '[' => true,
+            '(' => false,
+            _ => return None,
+        };
+        let upper_inc = match text.chars().last()? {
+            ']' => true,
+            ')' => false,
+            _ => return None,
+        };
+        let inner = text.get(1..text.len() - 1)?;
+
+        // Split at the single comma outside double quotes
+        let mut bounds = Vec::with_capacity(2);
+        let mut current = String::new();
+        let mut quoted = false;
+        let mut was_quoted = false;
+        for ch in inner.chars() {
+            match ch {
+                '"' => {
+                    quoted = !quoted;
+                    was_quoted = true;
+                }
+                ',' if !quoted => {
+                    bounds.push((std::mem::take(&mut current), was_quoted));
+                    was_quoted = false;
+                }
+                _ => current.push(ch),
+            }
+        }
+        bounds.push((current, was_quoted));
+        if quoted || bounds.len() != 2 {
+            return None;
+        }
+
+        let mut bounds = bounds.into_iter().map(|(value, was_quoted)| {
+            let value = if was_quoted { value } else { value.trim().to_string() };
+            (!value.is_empty() || was_quoted).then_some(value)
+        });
+        Some((Some(bounds.next()?), Some(bounds.next()?), lower_inc, upper_inc))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.empty
+    }
+
+    /// Whether the range contains an element
+    pub fn contains_value(&self, value: &RangeValue) -> bool {
+        if self.empty {
+            return false;
+        }
+        let above_lower = match &self.lower {
+            None => true,
+            Some(lower) => match lower.partial_cmp(value) {
+                Some(Ordering::Less) => true,
+                Some(Ordering::Equal) => self.lower_inc,
+                _ => false,
+            },
+        };
+        let below_upper = match &self.upper {
+            None => true,
+            Some(upper) => match upper.partial_cmp(value) {
+                Some(Ordering::Greater) => true,
+                Some(Ordering::Equal) => self.upper_inc,
+                _ => false,
+            },
+        };
+        above_lower && below_upper
+    }
+
+    /// Whether the range contains every element of another range
+    pub fn contains_range(&self, other: &Range) -> bool {
+        if other.empty {
+            return true;
+        }
+        if self.empty {
+            return false;
+        }
+        let lower_ok = match (&self.lower, &other.lower) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(a), Some(b)) => match a.partial_cmp(b) {
+                Some(Ordering::Less) => true,
+                Some(Ordering::Equal) => self.lower_inc || !other.lower_inc,
+                _ => false,
+            },
+        };
+        let upper_ok = match (&self.upper, &other.upper) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(a), Some(b)) => match a.partial_cmp(b) {
+                Some(Ordering::Greater) => true,
+                Some(Ordering::Equal) => self.upper_inc || !other.upper_inc,
+                _ => false,
+            },
+        };
+        lower_ok && upper_ok
+    }
+
+    /// Whether the ranges have an element in common
+    pub fn overlaps(&self, other: &Range) -> bool {
+        if self.empty || other.empty {
+            return false;
+        }
+        Self::starts_before_end(&self.lower, self.lower_inc, &other.upper, other.upper_inc)
+            && Self::starts_before_end(&other.lower, other.lower_inc, &self.upper, self.upper_inc)
+    }
+
+    /// Whether every element of the range is less than every element of another, `<<`
+    pub fn is_before(&self, other: &Range) -> bool {
+        if self.empty || other.empty {
+            return false;
+        }
+        match (&self.upper, &other.lower) {
+            (Some(upper), Some(lower)) => match upper.partial_cmp(lower) {
+                Some(Ordering::Less) => true,
+                Some(Ordering::Equal) => !(self.upper_inc && other.lower_inc),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Whether the ranges touch without overlapping, `-|-`
+    pub fn is_adjacent(&self, other: &Range) -> bool {
+        if self.empty || other.empty {
+            return false;
+        }
+        let touches = |upper: &Option<RangeValue>, upper_inc: bool, lower: &Option<RangeValue>, lower_inc: bool| {
+            matches!((upper, lower), (Some(upper), Some(lower)) if upper == lower && upper_inc != lower_inc)
+        };
+        touches(&self.upper, self.upper_inc, &other.lower, other.lower_inc)
+            || touches(&other.upper, other.upper_inc, &self.lower, self.lower_inc)
+    }
+
+    fn starts_before_end(lower: &Option<RangeValue>, lower_inc: bool, upper: &Option<RangeValue>, upper_inc: bool) -> bool {
+        match (lower, upper) {
+            (Some(lower), Some(upper)) => match lower.partial_cmp(upper) {
+                Some(Ordering::Less) => true,
+                Some(Ordering::Equal) => lower_inc && upper_inc,
+                _ => false,
+            },
+            _ => true,
+        }
+    }
+
+    /// Text of the lower bound, as returned by lower()
+    pub fn lower_text(&self) -> Option<String> {
+        self.lower.as_ref().map(|value| value.format(self.kind).trim_matches('"').to_string())
+    }
+
+    /// Text of the upper bound, as returned by upper()
+    pub fn upper_text(&self) -> Option<String> {
+        self.upper.as_ref().map(|value| value.format(self.kind).trim_matches('"').to_string())
+    }
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.empty {
+            return f.write_str("empty");
+        }
+        write!(
+            f,
+            "{}{},{}{}",
+            if self.lower_inc { '[' } else { '(' },
+            self.lower.as_ref().map(|value| value.format(self.kind)).unwrap_or_default(),
+            self.upper.as_ref().map(|value| value.format(self.kind)).unwrap_or_default(),
+            if self.upper_inc { ']' } else { ')' },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discrete_ranges_are_canonical() {
+        let range = Range::parse(RangeKind::Int4, "[1,10]").unwrap();
+        assert_eq!(range.to_string(), "[1,11)");
+        assert_eq!(Range::parse(RangeKind::Int4, "(1,10)").unwrap().to_string(), "[2,10)");
+        assert_eq!(Range::parse(RangeKind::Int4, "[5,5)").unwrap().to_string(), "empty");
+        assert_eq!(Range::parse(RangeKind::Int8, "(,10]").unwrap().to_string(), "(,11)");
+        assert_eq!(Range::parse(RangeKind::Date, "[2024-01-01,2024-01-31]").unwrap().to_string(), "[2024-01-01,2024-02-01)");
+        assert!(Range::parse(RangeKind::Int4, "[10,1)").is_err());
+        assert!(Range::parse(RangeKind::Int4, "1,10").is_err());
+    }
+
+    #[test]
+    fn test_continuous_ranges() {
+        assert_eq!(Range::parse(RangeKind::Num, "(1.5, 2.5]").unwrap().to_string(), "(1.5,2.5]");
+        assert_eq!(
+            Range::parse(RangeKind::Ts, "[2024-01-01 10:00, 2024-01-01 12:00)").unwrap().to_string(),
+            "[\"2024-01-01 10:00:00\",\"2024-01-01 12:00:00\")"
+        );
+        assert_eq!(
+            Range::parse(RangeKind::Tstz, "[\"2024-01-01 10:00:00+02\",)").unwrap().to_string(),
+            "[\"2024-01-01 08:00:00+00\",)"
+        );
+    }
+
+    #[test]
+    fn test_range_operations() {
+        let range = Range::parse(RangeKind::Int4, "[1,10)").unwrap();
+        assert!(range.contains_value(&RangeValue::Int(1)));
+        assert!(!range.contains_value(&RangeValue::Int(10)));
+        assert!(range.contains_range(&Range::parse(RangeKind::Int4, "[2,5]").unwrap()));
+        assert!(!range.contains_range(&Range::parse(RangeKind::Int4, "[2,10]").unwrap()));
+        assert!(range.overlaps(&Range::parse(RangeKind::Int4, "[9,20)").unwrap()));
+        assert!(!range.overlaps(&Range::parse(RangeKind::Int4, "[10,20)").unwrap()));
+        assert!(!range.overlaps(&Range::empty(RangeKind::Int4)));
+        let next = Range::parse(RangeKind::Int4, "[10,20)").unwrap();
+        assert!(range.is_before(&next) && !next.is_before(&range));
+        assert!(range.is_adjacent(&next) && next.is_adjacent(&range));
+        assert!(!range.is_adjacent(&Range::parse(RangeKind::Int4, "[11,20)").unwrap()));
+        assert_eq!(range.lower_text().as_deref(), Some("1"));
+        assert_eq!(range.upper_text().as_deref(), Some("10"));
+    }
+
+    #[test]
+    fn test_parse_inferred() {
+        assert_eq!(Range::parse_inferred("[1,11)").map(|r| r.kind), Some(RangeKind::Int8));
+        assert_eq!(Range::parse_inferred("[1.5,2)").map(|r| r.kind), Some(RangeKind::Num));
+        assert_eq!(Range::parse_inferred("[2024-01-01,2024-02-01)").map(|r| r.kind), Some(RangeKind::Date));
+        assert_eq!(
+            Range::parse_inferred("[\"2024-01-01 08:00:00+00\",)").map(|r| r.kind),
+            Some(RangeKind::Tstz)
+        );
+        assert!(Range::parse_inferred("[\"a\",\"b\"]").is_none());
+        assert!(Range::parse_inferred("hello").is_none());
+    }
+}
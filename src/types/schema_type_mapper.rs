@@ -1,7 +1,25 @@
 use rusqlite::Connection;
 use crate::types::PgType;
 use crate::metadata::EnumMetadata;
+use crate::rewriter::ExpressionTypeResolver;
 use regex;
+use sqlparser::ast::{Expr, SelectItem, SetExpr, Statement};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use once_cell::sync::Lazy;
+use crate::cache::LruCache;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A window function call with its OVER clause and alias, e.g. "row_number() OVER (ORDER BY id) AS rn"
+static WINDOW_ALIAS_REGEX: Lazy<regex::Regex> = Lazy::new(|| {
+    regex::Regex::new(r"(?i)\b(\w+)\s*\((?:[^()]|\([^()]*\))*\)\s*(?:FILTER\s*\([^()]*\)\s*)?OVER\s*(?:\w+|\((?:[^()]|\([^()]*\))*\))\s+(?:AS\s+)?(\w+)\b").unwrap()
+});
+
+/// Select-list aliases of recently described queries, see `projection_aliases`
+static PROJECTION_ALIASES: Lazy<LruCache<String, Arc<HashMap<String, bool>>>> =
+    Lazy::new(|| LruCache::new(256, Duration::from_secs(600)));
 
 /// Maps between PostgreSQL and SQLite types using actual schema information
 pub struct SchemaTypeMapper;
@@ -18,14 +36,31 @@ impl SchemaTypeMapper {
             return Some(Self::pg_type_string_to_oid(&pg_type));
         }
         
-        // Fall back to SQLite schema
-        if let Ok(sqlite_type) = Self::get_sqlite_column_type(conn, table_name, column_name) {
+        // Fall back to SQLite schema for plain tables; what views and table-valued
+        // functions declare depends on the expressions they select
+        if Self::is_sqlite_table(conn, table_name)
+            && let Ok(sqlite_type) = Self::get_sqlite_column_type(conn, table_name, column_name) {
+            // PostgreSQL's number types keep their type; anything else, REAL included,
+            // maps by its SQLite storage class
+            let oid = Self::pg_type_string_to_oid(&sqlite_type);
+            if matches!(PgType::from_oid(oid), Some(PgType::Int2 | PgType::Int4 | PgType::Int8 | PgType::Numeric | PgType::Float8)) {
+                return Some(oid);
+            }
             return Some(Self::sqlite_type_to_pg_oid(&sqlite_type));
         }
         
         None
     }
     
+    /// Whether a name refers to a table rather than a view or virtual table
+    fn is_sqlite_table(conn: &Connection, table_name: &str) -> bool {
+        conn.query_row(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            [table_name],
+            |_| Ok(()),
+        ).is_ok()
+    }
+
     /// Get SQLite column type from PRAGMA table_info
     fn get_sqlite_column_type(
         conn: &Connection,
@@ -101,6 +136,11 @@ impl SchemaTypeMapper {
         
         let upper_type = pg_type.to_uppercase();
         
+        // Array types: INTEGER[], VARCHAR(10)[][], INTEGER ARRAY or the _int4 alias
+        if let Some(array_oid) = Self::array_type_string_to_oid(upper_type.trim()) {
+            return array_oid;
+        }
+        
         // Handle parametric types by removing parameters
         let base_type = if let Some(paren_pos) = upper_type.find('(') {
             upper_type[..paren_pos].trim()
@@ -113,8 +153,9 @@ impl SchemaTypeMapper {
             "SMALLINT" | "INT2" => PgType::Int2.to_oid(),
             "INTEGER" | "INT" | "INT4" => PgType::Int4.to_oid(),
             "BIGINT" | "INT8" => PgType::Int8.to_oid(),
-            "SERIAL" => PgType::Int4.to_oid(), // Serial is int4 with sequence
-            "BIGSERIAL" => PgType::Int8.to_oid(), // Bigserial is int8 with sequence
+            "SMALLSERIAL" | "SERIAL2" => PgType::Int2.to_oid(), // Smallserial is int2 with sequence
+            "SERIAL" | "SERIAL4" => PgType::Int4.to_oid(), // Serial is int4 with sequence
+            "BIGSERIAL" | "SERIAL8" => PgType::Int8.to_oid(), // Bigserial is int8 with sequence
             
             // Floating point
             "REAL" | "FLOAT4" => PgType::Float4.to_oid(),
@@ -150,15 +191,13 @@ impl SchemaTypeMapper {
             // Money
             "MONEY" => PgType::Money.to_oid(),
             
-            // Array types
-            "TEXT[]" | "_TEXT" => PgType::TextArray.to_oid(),
-            "INT[]" | "INT4[]" | "INTEGER[]" | "_INT4" => PgType::Int4Array.to_oid(),
-            "BIGINT[]" | "INT8[]" | "_INT8" => PgType::Int8Array.to_oid(),
-            
             // Range types
             "INT4RANGE" => PgType::Int4range.to_oid(),
             "INT8RANGE" => PgType::Int8range.to_oid(),
             "NUMRANGE" => PgType::Numrange.to_oid(),
+            "TSRANGE" => PgType::Tsrange.to_oid(),
+            "TSTZRANGE" => PgType::Tstzrange.to_oid(),
+            "DATERANGE" => PgType::Daterange.to_oid(),
             
             // Network types
             "CIDR" => PgType::Cidr.to_oid(),
@@ -170,13 +209,39 @@ impl SchemaTypeMapper {
             "BIT VARYING" | "VARBIT" => PgType::Varbit.to_oid(),
             "BIT" => PgType::Bit.to_oid(),
             
-            // Default - might be an ENUM type, return a special marker
-            _ => {
-                // For unknown types, we'll return TEXT but the caller should check
-                // if it's actually an ENUM type
-                PgType::Text.to_oid()
-            }
+            "XML" => PgType::Xml.to_oid(),
+            
+            // ENUM types are reported by their own OID once the ENUM cache knows them,
+            // other unknown types as TEXT
+            _ => crate::cache::global_enum_cache().type_oid(pg_type.trim())
+                .unwrap_or(PgType::Text.to_oid()),
+        }
+    }
+    
+    /// Map an array type string to the array OID of its element type
+    fn array_type_string_to_oid(upper_type: &str) -> Option<i32> {
+        let (element, explicit) = if let Some(pos) = upper_type.find('[') {
+            (upper_type[..pos].trim(), true)
+        } else if let Some(element) = upper_type.strip_suffix(" ARRAY") {
+            (element.trim(), true)
+        } else {
+            (upper_type.strip_prefix('_')?, false)
+        };
+        
+        let element_oid = Self::pg_type_string_to_oid(element);
+        
+        // The _name alias only counts when the element is a known type
+        if !explicit && element_oid == PgType::Text.to_oid() && element != "TEXT" {
+            return None;
         }
+        
+        // Arrays of ENUM types have array types of their own, arrays of other unknown
+        // element types are reported as text[]
+        PgType::from_oid(element_oid)
+            .and_then(|t| t.array_type())
+            .map(|t| t.to_oid())
+            .or_else(|| crate::cache::global_enum_cache().array_type_oid(element_oid))
+            .or(Some(PgType::TextArray.to_oid()))
     }
     
     /// Get PostgreSQL type OID, checking for ENUM types
@@ -286,6 +351,49 @@ impl SchemaTypeMapper {
         PgType::Text.to_oid() // Default to text
     }
     
+    /// Check whether a result column is a boolean expression
+    ///
+    /// Aliased columns are looked up in the query's select list. Unaliased
+    /// expressions such as `count(*) > 0` arrive with the expression text as their
+    /// column name, which is parsed instead.
+    pub fn is_boolean_result_column(column_name: &str, query: Option<&str>) -> bool {
+        if let Some(query) = query
+            && let Some(&is_boolean) = Self::projection_aliases(query).get(&column_name.to_lowercase())
+        {
+            return is_boolean;
+        }
+
+        let dialect = PostgreSqlDialect {};
+        match Parser::new(&dialect).try_with_sql(column_name).and_then(|mut parser| parser.parse_expr()) {
+            Ok(Expr::Identifier(_)) | Err(_) => false,
+            Ok(expr) => ExpressionTypeResolver::is_boolean_expr(&expr),
+        }
+    }
+
+    /// The aliases in a query's select list, lower-cased, and whether each one names
+    /// a boolean expression. Parsed once per query rather than once per column.
+    fn projection_aliases(query: &str) -> Arc<HashMap<String, bool>> {
+        if let Some(aliases) = PROJECTION_ALIASES.get(&query.to_string()) {
+            return aliases;
+        }
+
+        let mut aliases = HashMap::new();
+        if let Ok(statements) = Parser::parse_sql(&PostgreSqlDialect {}, query)
+            && let Some(Statement::Query(parsed)) = statements.first()
+            && let SetExpr::Select(select) = &*parsed.body
+        {
+            for item in &select.projection {
+                if let SelectItem::ExprWithAlias { expr, alias } = item {
+                    aliases.insert(alias.value.to_lowercase(), ExpressionTypeResolver::is_boolean_expr(expr));
+                }
+            }
+        }
+
+        let aliases = Arc::new(aliases);
+        PROJECTION_ALIASES.insert(query.to_string(), aliases.clone());
+        aliases
+    }
+    
     /// Get type OID for aggregate functions
     pub fn get_aggregate_return_type(
         function_name: &str,
@@ -295,6 +403,19 @@ impl SchemaTypeMapper {
         Self::get_aggregate_return_type_with_query(function_name, conn, table_name, None)
     }
     
+    /// Result type of the ranking window functions, from the upper-cased expression
+    fn window_function_return_type(upper: &str) -> Option<i32> {
+        if upper.starts_with("ROW_NUMBER(") || upper.starts_with("RANK(") || upper.starts_with("DENSE_RANK(") {
+            Some(PgType::Int8.to_oid()) // bigint
+        } else if upper.starts_with("NTILE(") {
+            Some(PgType::Int4.to_oid()) // int4
+        } else if upper.starts_with("PERCENT_RANK(") || upper.starts_with("CUME_DIST(") {
+            Some(PgType::Float8.to_oid()) // float8
+        } else {
+            None
+        }
+    }
+    
     /// Get type OID for aggregate functions with optional query context
     pub fn get_aggregate_return_type_with_query(
         function_name: &str,
@@ -302,6 +423,11 @@ impl SchemaTypeMapper {
         table_name: Option<&str>,
         query: Option<&str>
     ) -> Option<i32> {
+        // Comparisons and predicates over aggregates (e.g. count(*) > 0) are booleans
+        if Self::is_boolean_result_column(function_name, query) {
+            return Some(PgType::Bool.to_oid());
+        }
+        
         let upper = function_name.to_uppercase();
         
         // Handle aliased columns - if it's just a simple name, skip function detection
@@ -309,16 +435,29 @@ impl SchemaTypeMapper {
         if !function_name.contains('(') && !function_name.contains(' ') {
             // If we have the query, try to find what function produces this alias
             if let Some(q) = query {
+                // Window functions, e.g. "row_number() OVER (ORDER BY id) AS rn" or "count(*) OVER w AS c"
+                if let Some(captures) = WINDOW_ALIAS_REGEX.captures_iter(q)
+                    .find(|captures| captures[2].eq_ignore_ascii_case(function_name)) {
+                        let actual_function = format!("{}(", captures[1].to_uppercase());
+                        if let Some(oid) = Self::window_function_return_type(&actual_function) {
+                            return Some(oid);
+                        }
+                        if actual_function == "COUNT(" {
+                            return Some(PgType::Int8.to_oid()); // bigint
+                        }
+                    }
+                
                 // Look for patterns like "sum(...) AS function_name" or "avg(...) AS function_name"
                 // This handles both simple aggregates and aggregate expressions
                 let pattern = format!(r"(?i)([\w_]+)\s*\([^)]+\)\s+(?:AS\s+)?{}\b", regex::escape(function_name));
-                if let Ok(re) = regex::Regex::new(&pattern)
+                if q.contains('(')
+                    && let Ok(re) = regex::Regex::new(&pattern)
                     && let Some(captures) = re.captures(q) {
                         let actual_function = captures[1].to_uppercase();
                         // Check if this is an aggregate function
                         if matches!(actual_function.as_str(), "SUM" | "AVG" | "MAX" | "MIN" | "COUNT" | 
                                    "ARRAY_AGG" | "JSON_AGG" | "JSONB_AGG" | "STRING_AGG" |
-                                   "JSON_OBJECT_AGG" | "JSONB_OBJECT_AGG") {
+                                   "JSON_OBJECT_AGG" | "JSONB_OBJECT_AGG" | "BOOL_AND" | "BOOL_OR" | "EVERY") {
                             // For SUM/AVG on arithmetic expressions, always return NUMERIC
                             if actual_function == "SUM" || actual_function == "AVG" {
                                 return Some(PgType::Numeric.to_oid());
@@ -334,7 +473,8 @@ impl SchemaTypeMapper {
                 // 2. Clients expect to get strings, not PostgreSQL arrays
                 // 3. Binary array encoding is not yet implemented
                 let concat_pattern = format!(r"\w+\s*\|\|\s*[^\s]+\s+(?:AS\s+)?{}\b", regex::escape(function_name));
-                if let Ok(re) = regex::Regex::new(&concat_pattern)
+                if q.contains("||")
+                    && let Ok(re) = regex::Regex::new(&concat_pattern)
                     && re.is_match(q) {
                         // This is an array concatenation operation - return as TEXT
                         return Some(PgType::Text.to_oid());
@@ -348,10 +488,15 @@ impl SchemaTypeMapper {
             return Some(PgType::Int8.to_oid()); // bigint
         }
         
+        if let Some(oid) = Self::window_function_return_type(&upper) {
+            return Some(oid);
+        }
+        
         // Decimal arithmetic functions that return numeric
         if upper.starts_with("DECIMAL_ADD(") || upper.starts_with("DECIMAL_SUB(") || 
            upper.starts_with("DECIMAL_MUL(") || upper.starts_with("DECIMAL_DIV(") ||
-           upper.starts_with("DECIMAL_FROM_TEXT(") {
+           upper.starts_with("DECIMAL_FROM_TEXT(") || upper.starts_with("DECIMAL_SUM(") ||
+           upper.starts_with("DECIMAL_AVG(") {
             return Some(PgType::Numeric.to_oid()); // numeric
         }
         
@@ -360,6 +505,26 @@ impl SchemaTypeMapper {
             return Some(PgType::Int4.to_oid()); // int4
         }
         
+        // JSON path predicates return booleans
+        if upper.starts_with("JSONB_PATH_EXISTS(") || upper.starts_with("JSONB_PATH_MATCH(") {
+            return Some(PgType::Bool.to_oid()); // bool
+        }
+        
+        // Range constructors and range predicates
+        for range_type in ["INT4RANGE", "INT8RANGE", "NUMRANGE", "TSRANGE", "TSTZRANGE", "DATERANGE"] {
+            if upper.starts_with(&format!("{range_type}(")) ||
+               (upper.starts_with("PG_RANGE_FROM_TEXT(") && upper.contains(&format!("'{range_type}'"))) {
+                return Some(Self::pg_type_string_to_oid(range_type));
+            }
+        }
+        if upper.starts_with("ISEMPTY(") || upper.starts_with("LOWER_INC(") || upper.starts_with("UPPER_INC(") ||
+           upper.starts_with("LOWER_INF(") || upper.starts_with("UPPER_INF(") ||
+           upper.starts_with("RANGE_OVERLAPS(") || upper.starts_with("RANGE_CONTAINS") || upper.starts_with("RANGE_CONTAINED_BY(") ||
+           upper.starts_with("RANGE_ADJACENT(") || upper.starts_with("RANGE_BEFORE(") || upper.starts_with("RANGE_AFTER(") ||
+           upper.starts_with("ELEM_CONTAINED_BY_RANGE(") {
+            return Some(PgType::Bool.to_oid()); // bool
+        }
+        
         // JSON functions that return text
         if upper.starts_with("JSON_GROUP_ARRAY(") || upper.starts_with("JSON_ARRAY(") || 
            upper.starts_with("JSON_OBJECT(") || upper.starts_with("JSON_EXTRACT(") {
@@ -396,8 +561,16 @@ impl SchemaTypeMapper {
             return Some(PgType::Timestamp.to_oid()); // epoch as timestamp
         }
         
-        if upper.starts_with("AGE(") {
-            return Some(PgType::Interval.to_oid()); // interval (INTEGER microseconds)
+        if upper.starts_with("AGE(") || upper.starts_with("JUSTIFY_") {
+            return Some(PgType::Interval.to_oid()); // interval (stored as interval text)
+        }
+        
+        if upper.starts_with("INTERVAL_ADD(") {
+            return Some(PgType::Timestamp.to_oid()); // timestamp + interval
+        }
+        
+        if upper.starts_with("INTERVAL_PART(") {
+            return Some(PgType::Float8.to_oid()); // EXTRACT from an interval
         }
         
         // Time functions that return INTEGER microseconds (stored as time type)
@@ -409,7 +582,47 @@ impl SchemaTypeMapper {
         if upper.starts_with("EXTRACT(") {
             return Some(PgType::Float8.to_oid()); // float8
         }
-        
+
+        // Statistical aggregates return float8; percentile_cont over an array of fractions returns an array
+        if upper.starts_with("PERCENTILE_CONT(") {
+            return Some(if upper.starts_with("PERCENTILE_CONT('[") { PgType::Text } else { PgType::Float8 }.to_oid());
+        }
+        if ["STDDEV(", "STDDEV_SAMP(", "STDDEV_POP(", "VARIANCE(", "VAR_SAMP(", "VAR_POP(", "CORR(", "COVAR_SAMP(", "COVAR_POP("]
+            .iter().any(|prefix| upper.starts_with(prefix)) {
+            return Some(PgType::Float8.to_oid()); // float8
+        }
+
+        if upper.starts_with("BOOL_AND(") || upper.starts_with("BOOL_OR(") || upper.starts_with("EVERY(") {
+            return Some(PgType::Bool.to_oid()); // bool
+        }
+
+        if upper.starts_with("PG_CANCEL_BACKEND(") || upper.starts_with("PG_TERMINATE_BACKEND(") ||
+           upper.starts_with("PG_RELOAD_CONF(") || upper.starts_with("PG_TRY_ADVISORY_LOCK(") ||
+           upper.starts_with("PG_TRY_ADVISORY_XACT_LOCK(") || upper.starts_with("PG_ADVISORY_UNLOCK(") ||
+           upper.starts_with("TXID_VISIBLE_IN_SNAPSHOT(") || upper.starts_with("PG_VISIBLE_IN_SNAPSHOT(") {
+            return Some(PgType::Bool.to_oid()); // bool
+        }
+
+        if upper.starts_with("XML_IS_WELL_FORMED") || upper.starts_with("XPATH_EXISTS(") {
+            return Some(PgType::Bool.to_oid()); // bool
+        }
+
+        if upper.starts_with("XPATH(") {
+            return Some(PgType::XmlArray.to_oid()); // xml[] (JSON array)
+        }
+
+        if upper.starts_with("PG_XML_PARSE(") {
+            return Some(PgType::Xml.to_oid()); // xml
+        }
+
+        if upper.starts_with("STRING_AGG(") {
+            return Some(PgType::Text.to_oid()); // text
+        }
+
+        if upper.starts_with("WIDTH_BUCKET(") {
+            return Some(PgType::Int4.to_oid()); // int4
+        }
+
         // For other aggregates, we need to know the column type
         if let Some(column_name) = crate::types::QueryContextAnalyzer::extract_column_from_aggregation(function_name) {
             // Try to get the column type from schema
@@ -445,31 +658,15 @@ impl SchemaTypeMapper {
         }
         
         // Array functions
-        // NOTE: Return TEXT instead of array types because data is stored as JSON strings
-        if upper.starts_with("ARRAY_AGG(") {
-            return Some(PgType::Text.to_oid()); // text (JSON array)
-        }
-        
         if upper.starts_with("ARRAY_LENGTH(") || upper.starts_with("ARRAY_UPPER(") || 
            upper.starts_with("ARRAY_LOWER(") || upper.starts_with("ARRAY_NDIMS(") {
             return Some(PgType::Int4.to_oid()); // int4
         }
         
-        if upper.starts_with("ARRAY_APPEND(") || upper.starts_with("ARRAY_PREPEND(") || 
-           upper.starts_with("ARRAY_CAT(") || upper.starts_with("ARRAY_REMOVE(") || 
-           upper.starts_with("ARRAY_REPLACE(") || upper.starts_with("ARRAY_SLICE(") ||
-           upper.starts_with("STRING_TO_ARRAY(") {
-            return Some(PgType::Text.to_oid()); // text (JSON array)
-        }
-        
         if upper.starts_with("ARRAY_POSITION(") {
             return Some(PgType::Int4.to_oid()); // int4
         }
         
-        if upper.starts_with("ARRAY_POSITIONS(") {
-            return Some(PgType::Text.to_oid()); // text (JSON array)
-        }
-        
         if upper.starts_with("ARRAY_TO_STRING(") || upper.starts_with("UNNEST(") {
             return Some(PgType::Text.to_oid()); // text
         }
@@ -107,6 +107,10 @@ pub fn sqlite_type_to_pg_oid(sqlite_type: &str) -> i32 {
         return PgType::Uuid.to_oid(); // uuid
     }
     
+    if type_upper.contains("JSONB") {
+        return PgType::Jsonb.to_oid(); // jsonb
+    }
+    
     if type_upper.contains("JSON") {
         return PgType::Json.to_oid(); // json
     }
@@ -0,0 +1,564 @@
+//! Result types of SELECT queries, inferred from the sqlparser AST.
+//!
+//! Describe has to report a type for every result column before any row exists.
+//! Columns are looked up in the schema, and expressions are typed the way
+//! PostgreSQL types them: operator result types, function signatures, and the
+//! common type of CASE branches and COALESCE arguments. Anything the checker
+//! can't type is left as None for the caller to decide.
+
+use rusqlite::Connection;
+use sqlparser::ast::{
+    AccessExpr, BinaryOperator, DataType, Expr, Function, FunctionArg, FunctionArgExpr, FunctionArguments,
+    ObjectNamePart, Query, Select, SelectItem, SelectItemQualifiedWildcardKind, SetExpr, Statement,
+    Subscript, TableFactor, UnaryOperator, Value, ValueWithSpan,
+};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use crate::functions::signatures::{self, FUNCTION_SIGNATURES};
+use crate::rewriter::{ExpressionTypeResolver, QueryContext};
+use crate::types::{PgType, SchemaTypeMapper};
+
+/// Infers the types of result columns and expressions
+pub struct TypeChecker<'a> {
+    resolver: ExpressionTypeResolver<'a>,
+}
+
+impl<'a> TypeChecker<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        Self { resolver: ExpressionTypeResolver::new(conn) }
+    }
+
+    /// Types of the result columns of a SELECT, one per column with wildcards
+    /// expanded. None if the statement isn't a query the checker can follow.
+    pub fn result_column_types(&mut self, sql: &str) -> Option<Vec<Option<PgType>>> {
+        let statements = Parser::parse_sql(&PostgreSqlDialect {}, sql).ok()?;
+        match statements.as_slice() {
+            [Statement::Query(query)] => self.query_types(query, &QueryContext::default()),
+            _ => None,
+        }
+    }
+
+    /// Types of the columns of a query. The branches of a set operation are unified
+    /// column by column, like PostgreSQL resolves UNION types.
+    fn query_types(&mut self, query: &Query, outer: &QueryContext) -> Option<Vec<Option<PgType>>> {
+        // Only the CTEs are shared, each SELECT reads its own FROM
+        let mut cte_columns = self.resolver.build_context(query).cte_columns;
+        for (name, columns) in &outer.cte_columns {
+            cte_columns.entry(name.clone()).or_insert_with(|| columns.clone());
+        }
+        self.set_expr_types(&query.body, &QueryContext { cte_columns, ..QueryContext::default() })
+    }
+
+    fn set_expr_types(&mut self, body: &SetExpr, context: &QueryContext) -> Option<Vec<Option<PgType>>> {
+        match body {
+            SetExpr::Select(select) => self.select_types(select, context),
+            SetExpr::Query(query) => self.query_types(query, context),
+            SetExpr::SetOperation { left, right, .. } => {
+                let left = self.set_expr_types(left, context)?;
+                let right = self.set_expr_types(right, context)?;
+                if left.len() != right.len() {
+                    return None;
+                }
+                Some(left.into_iter().zip(right)
+                    .map(|(left, right)| match (left, right) {
+                        (Some(left), Some(right)) => Self::unify(left, right),
+                        (known, None) | (None, known) => known,
+                    })
+                    .collect())
+            }
+            _ => None,
+        }
+    }
+
+    fn select_types(&mut self, select: &Select, base: &QueryContext) -> Option<Vec<Option<PgType>>> {
+        let mut context = base.clone();
+        for table in &select.from {
+            self.resolver.process_table_with_joins(&table.relation, &table.joins, &mut context);
+        }
+
+        let mut types = Vec::new();
+        for item in &select.projection {
+            match item {
+                SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => {
+                    types.push(self.infer(expr, &context));
+                }
+                SelectItem::Wildcard(_) => {
+                    for table in &select.from {
+                        for relation in std::iter::once(&table.relation).chain(table.joins.iter().map(|join| &join.relation)) {
+                            types.extend(self.relation_types(relation, &context)?);
+                        }
+                    }
+                }
+                SelectItem::QualifiedWildcard(SelectItemQualifiedWildcardKind::ObjectName(name), _) => {
+                    let name = Self::last_part(&name.0)?;
+                    let columns = match context.derived_table_columns.get(&name) {
+                        Some(columns) => columns.clone(),
+                        None => {
+                            let table = context.table_aliases.get(&name).cloned().unwrap_or(name);
+                            self.resolver.relation_columns(&table, &context)
+                        }
+                    };
+                    if columns.is_empty() {
+                        return None;
+                    }
+                    types.extend(columns.into_iter().map(|(_, pg_type)| Some(pg_type)));
+                }
+                SelectItem::QualifiedWildcard(..) => return None,
+            }
+        }
+        Some(types)
+    }
+
+    /// Column types of one FROM item, for expanding `*`
+    fn relation_types(&mut self, relation: &TableFactor, context: &QueryContext) -> Option<Vec<Option<PgType>>> {
+        let columns = match relation {
+            TableFactor::Table { name, alias, .. } => {
+                match alias.as_ref().and_then(|alias| context.derived_table_columns.get(&alias.name.value)) {
+                    Some(columns) => columns.clone(),
+                    None => self.resolver.relation_columns(&name.to_string(), context),
+                }
+            }
+            TableFactor::Derived { alias: Some(alias), .. } => context.derived_table_columns.get(&alias.name.value)?.clone(),
+            _ => return None,
+        };
+        if columns.is_empty() {
+            return None;
+        }
+        Some(columns.into_iter().map(|(_, pg_type)| Some(pg_type)).collect())
+    }
+
+    /// Type of an expression, None when it can't be told
+    pub fn infer(&mut self, expr: &Expr, context: &QueryContext) -> Option<PgType> {
+        match expr {
+            Expr::Identifier(ident) => self.resolver.column_type(None, &ident.value, context),
+            Expr::CompoundIdentifier(parts) if parts.len() >= 2 => {
+                let table = &parts[parts.len() - 2].value;
+                self.resolver.column_type(Some(table), &parts[parts.len() - 1].value, context)
+            }
+            Expr::Value(ValueWithSpan { value, .. }) => Self::literal_type(value),
+            Expr::Cast { data_type, .. } | Expr::TypedString { data_type, .. } => Self::data_type(data_type),
+            Expr::Nested(inner) | Expr::Collate { expr: inner, .. } => self.infer(inner, context),
+            // -9223372036854775808 only fits bigint with its sign
+            Expr::UnaryOp { op: UnaryOperator::Minus, expr: operand } => match operand.as_ref() {
+                Expr::Value(ValueWithSpan { value: Value::Number(n, long), .. }) => {
+                    Self::literal_type(&Value::Number(format!("-{n}"), *long))
+                }
+                operand => self.infer(operand, context),
+            },
+            Expr::UnaryOp { op: UnaryOperator::Plus | UnaryOperator::PGBitwiseNot, expr } => self.infer(expr, context),
+            Expr::BinaryOp { left, op, right } => self.binary_op_type(expr, left, op, right, context),
+            Expr::Case { conditions, else_result, .. } => {
+                let results: Vec<&Expr> = conditions.iter().map(|when| &when.result)
+                    .chain(else_result.as_deref())
+                    .collect();
+                self.common_type(&results, context)
+            }
+            Expr::Function(func) => self.function_type(func, context),
+            Expr::Ceil { expr, .. } | Expr::Floor { expr, .. } => self.infer(expr, context).and_then(Self::rounded_type),
+            Expr::Position { .. } => Some(PgType::Int4),
+            Expr::Substring { .. } | Expr::Trim { .. } | Expr::Overlay { .. } => Some(PgType::Text),
+            Expr::Interval(_) => Some(PgType::Interval),
+            Expr::Extract { field, .. } => Some(Self::date_part_type(&field.to_string())),
+            Expr::Array(array) => {
+                let elements: Vec<&Expr> = array.elem.iter().collect();
+                self.common_type(&elements, context)?.array_type()
+            }
+            // Slices keep the type of the array they are taken from
+            Expr::CompoundFieldAccess { root, access_chain }
+                if matches!(access_chain.as_slice(), [AccessExpr::Subscript(Subscript::Slice { .. })]) =>
+            {
+                self.infer(root, context).filter(PgType::is_array)
+            }
+            Expr::Subquery(query) => self.query_types(query, context)?.into_iter().next()?,
+            _ if ExpressionTypeResolver::is_boolean_expr(expr) => Some(PgType::Bool),
+            _ => None,
+        }
+    }
+
+    fn literal_type(value: &Value) -> Option<PgType> {
+        match value {
+            Value::Number(n, _) => Some(match n.parse::<i64>() {
+                Ok(i) if i32::try_from(i).is_ok() => PgType::Int4,
+                Ok(_) => PgType::Int8,
+                Err(_) => PgType::Numeric,
+            }),
+            Value::Boolean(_) => Some(PgType::Bool),
+            Value::Null | Value::Placeholder(_) => None,
+            // A quoted literal in a select list resolves to text
+            _ => Some(PgType::Text),
+        }
+    }
+
+    /// Date parts are computed from the stored microseconds, whole numbers except
+    /// for the fractional parts
+    fn date_part_type(field: &str) -> PgType {
+        match field.trim_matches('\'').to_lowercase().as_str() {
+            "second" | "seconds" | "milliseconds" | "microseconds" | "epoch" | "julian" => PgType::Float8,
+            _ => PgType::Int4,
+        }
+    }
+
+    fn data_type(data_type: &DataType) -> Option<PgType> {
+        PgType::from_oid(SchemaTypeMapper::pg_type_string_to_oid(&data_type.to_string()))
+    }
+
+    /// Whether an expression is a quoted or NULL literal, which takes the type of the
+    /// other branches rather than imposing its own
+    fn is_untyped_literal(expr: &Expr) -> bool {
+        matches!(expr, Expr::Value(ValueWithSpan {
+            value: Value::Null | Value::SingleQuotedString(_) | Value::DollarQuotedString(_), ..
+        }))
+    }
+
+    /// Common type of the branches of a CASE or the arguments of COALESCE and the like
+    fn common_type(&mut self, exprs: &[&Expr], context: &QueryContext) -> Option<PgType> {
+        let mut common = None;
+        let mut has_text_literal = false;
+        for expr in exprs {
+            if Self::is_untyped_literal(expr) {
+                has_text_literal |= !matches!(expr, Expr::Value(ValueWithSpan { value: Value::Null, .. }));
+                continue;
+            }
+            let pg_type = self.infer(expr, context)?;
+            common = Some(match common {
+                None => pg_type,
+                Some(common) => Self::unify(common, pg_type)?,
+            });
+        }
+        common.or(has_text_literal.then_some(PgType::Text))
+    }
+
+    /// The type two values of types `a` and `b` are both converted to
+    fn unify(a: PgType, b: PgType) -> Option<PgType> {
+        use PgType::*;
+        if a == b {
+            return Some(a);
+        }
+        // Numbers convert up to the widest, integers to real included
+        if let (Some(rank_a), Some(rank_b)) = (Self::numeric_rank(a), Self::numeric_rank(b)) {
+            return Some(if rank_a >= rank_b { a } else { b });
+        }
+        match (a, b) {
+            (Text | Varchar | Char, Text | Varchar | Char) => Some(Text),
+            (Date, Timestamp) | (Timestamp, Date) => Some(Timestamp),
+            (Date | Timestamp, Timestamptz) | (Timestamptz, Date | Timestamp) => Some(Timestamptz),
+            (Json, Jsonb) | (Jsonb, Json) => Some(Jsonb),
+            _ => None,
+        }
+    }
+
+    /// Result type of arithmetic between two numbers: the wider of the two, with
+    /// real only when both sides are real
+    fn promote_numeric(a: PgType, b: PgType) -> Option<PgType> {
+        let wider = if Self::numeric_rank(a)? >= Self::numeric_rank(b)? { a } else { b };
+        Some(if wider == PgType::Float4 && a != b { PgType::Float8 } else { wider })
+    }
+
+    /// Order of the number types by how much they hold
+    fn numeric_rank(pg_type: PgType) -> Option<u8> {
+        match pg_type {
+            PgType::Int2 => Some(0),
+            PgType::Int4 => Some(1),
+            PgType::Int8 => Some(2),
+            PgType::Numeric => Some(3),
+            PgType::Float4 => Some(4),
+            PgType::Float8 => Some(5),
+            _ => None,
+        }
+    }
+
+    fn is_integer(pg_type: PgType) -> bool {
+        matches!(pg_type, PgType::Int2 | PgType::Int4 | PgType::Int8)
+    }
+
+    fn is_network(pg_type: PgType) -> bool {
+        matches!(pg_type, PgType::Inet | PgType::Cidr)
+    }
+
+    fn is_number(pg_type: PgType) -> bool {
+        Self::is_integer(pg_type) || matches!(pg_type, PgType::Numeric | PgType::Float4 | PgType::Float8)
+    }
+
+    /// ceil(), floor(), round() and the like keep numeric and compute everything else in float
+    fn rounded_type(pg_type: PgType) -> Option<PgType> {
+        match pg_type {
+            PgType::Numeric => Some(PgType::Numeric),
+            t if Self::is_number(t) => Some(PgType::Float8),
+            _ => None,
+        }
+    }
+
+    fn binary_op_type(&mut self, expr: &Expr, left: &Expr, op: &BinaryOperator, right: &Expr, context: &QueryContext) -> Option<PgType> {
+        use BinaryOperator::*;
+        match op {
+            Plus | Minus | Multiply | Divide | Modulo => {
+                let (left, right) = (self.infer(left, context)?, self.infer(right, context)?);
+                Self::datetime_arithmetic(left, op, right).or_else(|| Self::promote_numeric(left, right))
+            }
+            StringConcat => {
+                let (left, right) = (self.infer(left, context), self.infer(right, context));
+                // || appends to arrays as well as strings
+                Some(left.filter(PgType::is_array).or(right.filter(PgType::is_array)).unwrap_or(PgType::Text))
+            }
+            PGExp => {
+                let (left, right) = (self.infer(left, context)?, self.infer(right, context)?);
+                Some(if left == PgType::Numeric || right == PgType::Numeric { PgType::Numeric } else { PgType::Float8 })
+            }
+            BitwiseAnd | BitwiseOr | PGBitwiseXor | PGBitwiseShiftLeft | PGBitwiseShiftRight => {
+                let (left, right) = (self.infer(left, context)?, self.infer(right, context)?);
+                // << and >> between networks test containment
+                if matches!(op, PGBitwiseShiftLeft | PGBitwiseShiftRight) && (Self::is_network(left) || Self::is_network(right)) {
+                    Some(PgType::Bool)
+                } else if left == right {
+                    Some(left)
+                } else if Self::is_integer(left) && Self::is_integer(right) {
+                    Self::promote_numeric(left, right)
+                } else {
+                    None
+                }
+            }
+            // <<= and >>= reach the parser as custom operators
+            Custom(name) if matches!(name.as_str(), "<<=" | ">>=") => Some(PgType::Bool),
+            Arrow | HashArrow => self.infer(left, context).filter(|t| matches!(t, PgType::Json | PgType::Jsonb)),
+            LongArrow | HashLongArrow => Some(PgType::Text),
+            PGRegexMatch | PGRegexIMatch | PGRegexNotMatch | PGRegexNotIMatch |
+            PGLikeMatch | PGILikeMatch | PGNotLikeMatch | PGNotILikeMatch | PGStartsWith | PGOverlap |
+            AtArrow | ArrowAt | AtAt | Question | QuestionAnd | QuestionPipe | AtQuestion => Some(PgType::Bool),
+            _ if ExpressionTypeResolver::is_boolean_expr(expr) => Some(PgType::Bool),
+            _ => None,
+        }
+    }
+
+    /// Date and time arithmetic, following PostgreSQL's operators
+    fn datetime_arithmetic(left: PgType, op: &BinaryOperator, right: PgType) -> Option<PgType> {
+        use BinaryOperator::{Divide, Minus, Multiply, Plus};
+        use PgType::*;
+        Some(match (left, op, right) {
+            (Date, Plus | Minus, t) | (t, Plus, Date) if Self::is_integer(t) => Date,
+            (Date, Minus, Date) => Int4,
+            (Date, Plus | Minus, Interval) | (Interval, Plus, Date) => Timestamp,
+            (Date, Plus, Time) | (Time, Plus, Date) => Timestamp,
+            (Timestamp | Timestamptz, Plus | Minus, Interval) => left,
+            (Interval, Plus, Timestamp | Timestamptz) => right,
+            (Timestamp, Minus, Timestamp) | (Timestamptz, Minus, Timestamptz) => Interval,
+            (Time, Plus | Minus, Interval) | (Interval, Plus, Time) => Time,
+            (Time, Minus, Time) => Interval,
+            (Interval, Plus | Minus, Interval) => Interval,
+            (Interval, Multiply | Divide, t) | (t, Multiply, Interval) if Self::is_number(t) => Interval,
+            // Timestamps are stored as microseconds, which intervals move as plain numbers
+            (t, Plus | Minus, Interval) if Self::is_integer(t) => Int8,
+            _ => return None,
+        })
+    }
+
+    fn function_type(&mut self, func: &Function, context: &QueryContext) -> Option<PgType> {
+        // pg_catalog.lower() is lower()
+        let name = Self::last_part(&func.name.0)?.to_lowercase();
+        let args: Vec<&Expr> = match &func.args {
+            FunctionArguments::List(list) => list.args.iter()
+                .filter_map(|arg| match arg {
+                    FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => Some(expr),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let arg_type = |checker: &mut Self, i: usize| args.get(i).and_then(|arg| checker.infer(arg, context));
+
+        match name.as_str() {
+            "count" => Some(PgType::Int8),
+            "sum" => match arg_type(self, 0)? {
+                PgType::Int2 | PgType::Int4 => Some(PgType::Int8),
+                PgType::Int8 | PgType::Numeric => Some(PgType::Numeric),
+                t @ (PgType::Float4 | PgType::Float8 | PgType::Interval | PgType::Money) => Some(t),
+                _ => None,
+            },
+            "avg" => match arg_type(self, 0)? {
+                t if Self::is_integer(t) || t == PgType::Numeric => Some(PgType::Numeric),
+                PgType::Float4 | PgType::Float8 => Some(PgType::Float8),
+                PgType::Interval => Some(PgType::Interval),
+                _ => None,
+            },
+            "min" | "max" | "abs" | "nullif" | "lag" | "lead" | "first_value" | "last_value" | "nth_value" => arg_type(self, 0),
+            "coalesce" | "greatest" | "least" => self.common_type(&args, context),
+            "row_number" | "rank" | "dense_rank" => Some(PgType::Int8),
+            "ntile" => Some(PgType::Int4),
+            "percent_rank" | "cume_dist" => Some(PgType::Float8),
+            // Ordered-set aggregates pick a value of their WITHIN GROUP ordering
+            "mode" | "percentile_disc" => func.within_group.first().and_then(|order| self.infer(&order.expr, context)),
+            "percentile_cont" => Some(PgType::Float8),
+            "bool_and" | "bool_or" | "every" => Some(PgType::Bool),
+            "ceil" | "ceiling" | "floor" | "sign" => arg_type(self, 0).and_then(Self::rounded_type),
+            "round" | "trunc" if args.len() == 1 => arg_type(self, 0).and_then(Self::rounded_type),
+            "round" | "trunc" => Some(PgType::Numeric),
+            "mod" => Self::promote_numeric(arg_type(self, 0)?, arg_type(self, 1)?),
+            // The translated << and >> test network containment and still shift integers
+            "network_sub" | "network_sup" => match (arg_type(self, 0)?, arg_type(self, 1)?) {
+                (left, right) if Self::is_integer(left) && Self::is_integer(right) => Some(left),
+                (left, right) if Self::is_network(left) || Self::is_network(right) => Some(PgType::Bool),
+                _ => None,
+            },
+            "sqrt" | "cbrt" | "exp" | "ln" | "log" | "log10" | "power" | "pow" => {
+                let numeric = (0..args.len()).any(|i| arg_type(self, i) == Some(PgType::Numeric));
+                Some(if numeric { PgType::Numeric } else { PgType::Float8 })
+            }
+            // The date and time functions return what pgsqlite stores: microseconds and
+            // date parts as numbers
+            "date_part" | "extract" => match args.first() {
+                Some(Expr::Value(ValueWithSpan { value: Value::SingleQuotedString(field), .. })) => Some(Self::date_part_type(field)),
+                _ => None,
+            },
+            "date_trunc" | "to_timestamp" => Some(PgType::Int8),
+            "date" => Some(PgType::Date),
+            "now" | "current_timestamp" | "localtimestamp" | "age" | "make_date" | "make_time" |
+            "justify_days" | "justify_hours" | "justify_interval" => None,
+            // The interval operators and aggregates from IntervalTranslator
+            "interval_add" | "interval_sub" => arg_type(self, 0),
+            "interval_mul" | "interval_div" | "interval_sum" | "interval_avg" | "interval_min" | "interval_max" => Some(PgType::Interval),
+            // SQLite built-ins and translated functions, which have no registered signature
+            "length" | "char_length" | "character_length" | "octet_length" | "strpos" => Some(PgType::Int4),
+            "substr" | "substring" | "replace" | "trim" | "btrim" | "ltrim" | "rtrim" |
+            "concat" | "concat_ws" | "initcap" | "md5" | "to_char" | "format" => Some(PgType::Text),
+            "array_agg" => match arg_type(self, 0)? {
+                // Booleans, dates and times would be collected as the numbers they are stored as
+                PgType::Bool | PgType::Date | PgType::Time | PgType::Timetz | PgType::Timestamp |
+                PgType::Timestamptz | PgType::Interval => None,
+                // Arrays aggregate into a multidimensional array of the same type
+                t if t.is_array() => Some(t),
+                t => t.array_type(),
+            },
+            // The array functions return the type of the array they are given
+            "array_append" | "array_remove" | "array_replace" => arg_type(self, 0).filter(PgType::is_array),
+            "array_prepend" => arg_type(self, 1).filter(PgType::is_array),
+            "array_cat" => arg_type(self, 0).filter(PgType::is_array).or_else(|| arg_type(self, 1).filter(PgType::is_array)),
+            // The JSON builders and aggregates send their JSON as text
+            _ => Self::signature_type(&name, args.len())
+                .filter(|pg_type| !matches!(pg_type, PgType::Json | PgType::Jsonb)),
+        }
+    }
+
+    /// Result type of a registered function from its signatures, when every overload
+    /// taking that many arguments agrees on a concrete type
+    fn signature_type(name: &str, arg_count: usize) -> Option<PgType> {
+        let mut returns = FUNCTION_SIGNATURES.iter()
+            .filter(|signature| signature.name == name)
+            .filter(|signature| match signature.args.last() {
+                Some(last) if last.starts_with("VARIADIC ") => arg_count + 1 >= signature.args.len(),
+                _ => signature.args.len() == arg_count,
+            })
+            .map(|signature| signature.returns);
+        let first = returns.next()?;
+        if returns.any(|other| other != first) {
+            return None;
+        }
+        PgType::from_oid(signatures::type_oid(first)?)
+    }
+
+    fn last_part(parts: &[ObjectNamePart]) -> Option<String> {
+        parts.last().map(|ObjectNamePart::Identifier(ident)| ident.value.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::TypeMetadata;
+
+    fn connection() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        TypeMetadata::init(&conn).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE orders (id INTEGER, customer_id INTEGER, price TEXT, quantity INTEGER, weight REAL, placed_at INTEGER, status TEXT, tags TEXT);
+             CREATE TABLE customers (id INTEGER, name TEXT, born INTEGER);
+             INSERT INTO __pgsqlite_schema VALUES
+                 ('orders', 'id', 'INTEGER', 'INTEGER'), ('orders', 'customer_id', 'INTEGER', 'INTEGER'),
+                 ('orders', 'price', 'NUMERIC(10,2)', 'TEXT'), ('orders', 'quantity', 'SMALLINT', 'INTEGER'),
+                 ('orders', 'weight', 'REAL', 'REAL'), ('orders', 'placed_at', 'TIMESTAMP', 'INTEGER'),
+                 ('orders', 'status', 'VARCHAR(10)', 'TEXT'), ('orders', 'tags', 'TEXT[]', 'TEXT'),
+                 ('customers', 'id', 'BIGINT', 'INTEGER'), ('customers', 'name', 'TEXT', 'TEXT'),
+                 ('customers', 'born', 'DATE', 'INTEGER');"
+        ).unwrap();
+        conn
+    }
+
+    fn types(conn: &Connection, sql: &str) -> Vec<Option<PgType>> {
+        TypeChecker::new(conn).result_column_types(sql).unwrap()
+    }
+
+    #[test]
+    fn test_operators() {
+        use PgType::*;
+        let conn = connection();
+        assert_eq!(
+            types(&conn, "SELECT price * quantity AS total_amount, quantity + 1, quantity * 2.5, weight * 2, weight * weight, id / 2 FROM orders"),
+            [Some(Numeric), Some(Int4), Some(Numeric), Some(Float8), Some(Float4), Some(Int4)]
+        );
+        assert_eq!(
+            types(&conn, "SELECT o.status || '!', o.price > 10, o.placed_at + INTERVAL '1 day', o.placed_at - o.placed_at, c.born + 7, c.born - c.born FROM orders o JOIN customers c ON c.id = o.customer_id"),
+            [Some(Text), Some(Bool), Some(Timestamp), Some(Interval), Some(Date), Some(Int4)]
+        );
+        assert_eq!(types(&conn, "SELECT -id, $1, NULL, 'x', 0.0 FROM orders"), [Some(Int4), None, None, Some(Text), Some(Numeric)]);
+        // Date and time functions return what pgsqlite stores
+        assert_eq!(
+            types(&conn, "SELECT EXTRACT(YEAR FROM placed_at), extract('epoch', placed_at), date_trunc('hour', placed_at), placed_at + INTERVAL '1 day', now() FROM orders"),
+            [Some(Int4), Some(Float8), Some(Int8), Some(Timestamp), None]
+        );
+        assert_eq!(types(&conn, "SELECT 1686840645000000 + INTERVAL '1 day'"), [Some(Int8)]);
+        // << and >> test network containment, and shift integers
+        assert_eq!(
+            types(&conn, "SELECT '10.0.0.1'::inet << '10.0.0.0/8'::cidr, '10.0.0.0/8'::cidr >> '10.0.0.1', '10.0.0.1'::inet <<= '10.0.0.0/8', 1 << 4, network_sub(1, 4), network_supeq('10.0.0.0/8'::cidr, '10.0.0.1')"),
+            [Some(Bool), Some(Bool), Some(Bool), Some(Int4), Some(Int4), Some(Bool)]
+        );
+        assert_eq!(
+            types(&conn, "SELECT mode() WITHIN GROUP (ORDER BY id), percentile_cont(0.5) WITHIN GROUP (ORDER BY price) FROM orders"),
+            [Some(Int4), Some(Float8)]
+        );
+    }
+
+    #[test]
+    fn test_functions() {
+        use PgType::*;
+        let conn = connection();
+        assert_eq!(
+            types(&conn, "SELECT sum(price * quantity) AS total_amount, sum(id), sum(weight), avg(quantity), max(placed_at), count(*) FROM orders"),
+            [Some(Numeric), Some(Int8), Some(Float4), Some(Numeric), Some(Timestamp), Some(Int8)]
+        );
+        assert_eq!(
+            types(&conn, "SELECT round(price, 2), round(weight), upper(status), pg_catalog.length(status), row_number() OVER (), lag(price) OVER () FROM orders"),
+            [Some(Numeric), Some(Float8), Some(Text), Some(Int4), Some(Int8), Some(Numeric)]
+        );
+        assert_eq!(types(&conn, "SELECT some_unknown_function(id) FROM orders"), [None]);
+        assert_eq!(
+            types(&conn, "SELECT array_agg(id), array_append(tags, 'x'), array_prepend(1, array_agg(id)), array_cat('{a}', tags), tags[1:2], array_remove(status, 'x') FROM orders"),
+            [Some(Int4Array), Some(TextArray), Some(Int4Array), Some(TextArray), Some(TextArray), None]
+        );
+    }
+
+    #[test]
+    fn test_case_and_coalesce() {
+        use PgType::*;
+        let conn = connection();
+        assert_eq!(
+            types(&conn, "SELECT CASE WHEN id > 1 THEN price ELSE 0 END, CASE WHEN id > 1 THEN 'big' ELSE NULL END, coalesce(quantity, id), coalesce(weight, 0), coalesce(status, name) FROM orders JOIN customers ON customers.id = orders.customer_id"),
+            [Some(Numeric), Some(Text), Some(Int4), Some(Float4), Some(Text)]
+        );
+        // Branches without a common type can't be told
+        assert_eq!(types(&conn, "SELECT CASE WHEN id > 1 THEN price ELSE placed_at END FROM orders"), [None]);
+    }
+
+    #[test]
+    fn test_columns() {
+        use PgType::*;
+        let conn = connection();
+        // Unqualified columns of a table joined without an alias
+        assert_eq!(types(&conn, "SELECT status, name AS customer FROM orders JOIN customers ON customers.id = orders.customer_id"), [Some(Varchar), Some(Text)]);
+        assert_eq!(types(&conn, "SELECT * FROM customers"), [Some(Int8), Some(Text), Some(Date)]);
+        assert_eq!(types(&conn, "SELECT c.*, o.id FROM customers c, orders o"), [Some(Int8), Some(Text), Some(Date), Some(Int4)]);
+        assert_eq!(
+            types(&conn, "WITH t AS (SELECT price * quantity AS total FROM orders) SELECT total, (SELECT max(born) FROM customers) FROM t"),
+            [Some(Numeric), Some(Date)]
+        );
+        assert_eq!(types(&conn, "SELECT id FROM orders UNION SELECT id FROM customers"), [Some(Int8)]);
+        assert_eq!(TypeChecker::new(&conn).result_column_types("INSERT INTO orders (id) VALUES (1)"), None);
+    }
+}
@@ -27,12 +27,16 @@ pub enum PgType {
     Int4range = 3904,
     Int8range = 3926,
     Numrange = 3906,
+    Tsrange = 3908,
+    Tstzrange = 3910,
+    Daterange = 3912,
     Cidr = 650,
     Inet = 869,
     Macaddr = 829,
     Macaddr8 = 774,
     Bit = 1560,
     Varbit = 1562,
+    Xml = 142,
     Unknown = 705,
     // Full-text search types
     Tsvector = 3614,
@@ -63,12 +67,16 @@ pub enum PgType {
     Int4rangeArray = 3905,
     Int8rangeArray = 3927,
     NumrangeArray = 3907,
+    TsrangeArray = 3909,
+    TstzrangeArray = 3911,
+    DaterangeArray = 3913,
     CidrArray = 651,
     InetArray = 1041,
     MacaddrArray = 1040,
     Macaddr8Array = 775,
     BitArray = 1561,
     VarbitArray = 1563,
+    XmlArray = 143,
 }
 
 impl PgType {
@@ -98,12 +106,16 @@ impl PgType {
             3904 => Some(PgType::Int4range),
             3926 => Some(PgType::Int8range),
             3906 => Some(PgType::Numrange),
+            3908 => Some(PgType::Tsrange),
+            3910 => Some(PgType::Tstzrange),
+            3912 => Some(PgType::Daterange),
             650 => Some(PgType::Cidr),
             869 => Some(PgType::Inet),
             829 => Some(PgType::Macaddr),
             774 => Some(PgType::Macaddr8),
             1560 => Some(PgType::Bit),
             1562 => Some(PgType::Varbit),
+            142 => Some(PgType::Xml),
             705 => Some(PgType::Unknown),
             // Full-text search types
             3614 => Some(PgType::Tsvector),
@@ -134,12 +146,16 @@ impl PgType {
             3905 => Some(PgType::Int4rangeArray),
             3927 => Some(PgType::Int8rangeArray),
             3907 => Some(PgType::NumrangeArray),
+            3909 => Some(PgType::TsrangeArray),
+            3911 => Some(PgType::TstzrangeArray),
+            3913 => Some(PgType::DaterangeArray),
             651 => Some(PgType::CidrArray),
             1041 => Some(PgType::InetArray),
             1040 => Some(PgType::MacaddrArray),
             775 => Some(PgType::Macaddr8Array),
             1561 => Some(PgType::BitArray),
             1563 => Some(PgType::VarbitArray),
+            143 => Some(PgType::XmlArray),
             _ => None,
         }
     }
@@ -174,12 +190,16 @@ impl PgType {
             PgType::Int4range => "int4range",
             PgType::Int8range => "int8range",
             PgType::Numrange => "numrange",
+            PgType::Tsrange => "tsrange",
+            PgType::Tstzrange => "tstzrange",
+            PgType::Daterange => "daterange",
             PgType::Cidr => "cidr",
             PgType::Inet => "inet",
             PgType::Macaddr => "macaddr",
             PgType::Macaddr8 => "macaddr8",
             PgType::Bit => "bit",
             PgType::Varbit => "varbit",
+            PgType::Xml => "xml",
             PgType::Unknown => "unknown",
             // Full-text search types
             PgType::Tsvector => "tsvector",
@@ -210,12 +230,16 @@ impl PgType {
             PgType::Int4rangeArray => "_int4range",
             PgType::Int8rangeArray => "_int8range",
             PgType::NumrangeArray => "_numrange",
+            PgType::TsrangeArray => "_tsrange",
+            PgType::TstzrangeArray => "_tstzrange",
+            PgType::DaterangeArray => "_daterange",
             PgType::CidrArray => "_cidr",
             PgType::InetArray => "_inet",
             PgType::MacaddrArray => "_macaddr",
             PgType::Macaddr8Array => "_macaddr8",
             PgType::BitArray => "_bit",
             PgType::VarbitArray => "_varbit",
+            PgType::XmlArray => "_xml",
         }
     }
 
@@ -228,8 +252,9 @@ impl PgType {
             PgType::DateArray | PgType::TimeArray | PgType::TimestampArray | PgType::TimestamptzArray |
             PgType::TimetzArray | PgType::IntervalArray | PgType::NumericArray | PgType::ByteaArray |
             PgType::MoneyArray | PgType::Int4rangeArray | PgType::Int8rangeArray | PgType::NumrangeArray |
+            PgType::TsrangeArray | PgType::TstzrangeArray | PgType::DaterangeArray |
             PgType::CidrArray | PgType::InetArray | PgType::MacaddrArray | PgType::Macaddr8Array |
-            PgType::BitArray | PgType::VarbitArray
+            PgType::BitArray | PgType::VarbitArray | PgType::XmlArray
         )
     }
 
@@ -269,12 +294,16 @@ impl PgType {
             PgType::Int4rangeArray => Some(PgType::Int4range),
             PgType::Int8rangeArray => Some(PgType::Int8range),
             PgType::NumrangeArray => Some(PgType::Numrange),
+            PgType::TsrangeArray => Some(PgType::Tsrange),
+            PgType::TstzrangeArray => Some(PgType::Tstzrange),
+            PgType::DaterangeArray => Some(PgType::Daterange),
             PgType::CidrArray => Some(PgType::Cidr),
             PgType::InetArray => Some(PgType::Inet),
             PgType::MacaddrArray => Some(PgType::Macaddr),
             PgType::Macaddr8Array => Some(PgType::Macaddr8),
             PgType::BitArray => Some(PgType::Bit),
             PgType::VarbitArray => Some(PgType::Varbit),
+            PgType::XmlArray => Some(PgType::Xml),
             _ => None,
         }
     }
@@ -306,12 +335,16 @@ impl PgType {
             PgType::Int4range => Some(PgType::Int4rangeArray),
             PgType::Int8range => Some(PgType::Int8rangeArray),
             PgType::Numrange => Some(PgType::NumrangeArray),
+            PgType::Tsrange => Some(PgType::TsrangeArray),
+            PgType::Tstzrange => Some(PgType::TstzrangeArray),
+            PgType::Daterange => Some(PgType::DaterangeArray),
             PgType::Cidr => Some(PgType::CidrArray),
             PgType::Inet => Some(PgType::InetArray),
             PgType::Macaddr => Some(PgType::MacaddrArray),
             PgType::Macaddr8 => Some(PgType::Macaddr8Array),
             PgType::Bit => Some(PgType::BitArray),
             PgType::Varbit => Some(PgType::VarbitArray),
+            PgType::Xml => Some(PgType::XmlArray),
             _ => None,
         }
     }
@@ -353,13 +386,18 @@ impl TypeMapper {
         mapper.pg_to_sqlite.insert("time".to_string(), "INTEGER".to_string());
         mapper.pg_to_sqlite.insert("timestamp".to_string(), "INTEGER".to_string());
         mapper.pg_to_sqlite.insert("timestamptz".to_string(), "INTEGER".to_string());
-        mapper.pg_to_sqlite.insert("numeric".to_string(), "DECIMAL".to_string());
-        mapper.pg_to_sqlite.insert("decimal".to_string(), "DECIMAL".to_string());
+        // Canonical decimal text, so no digits are lost to REAL
+        mapper.pg_to_sqlite.insert("numeric".to_string(), "TEXT".to_string());
+        mapper.pg_to_sqlite.insert("decimal".to_string(), "TEXT".to_string());
         mapper.pg_to_sqlite.insert("bytea".to_string(), "BLOB".to_string());
         
         // Additional mappings from PRD
         mapper.pg_to_sqlite.insert("serial".to_string(), "INTEGER".to_string());
         mapper.pg_to_sqlite.insert("bigserial".to_string(), "INTEGER".to_string());
+        mapper.pg_to_sqlite.insert("smallserial".to_string(), "INTEGER".to_string());
+        mapper.pg_to_sqlite.insert("serial2".to_string(), "INTEGER".to_string());
+        mapper.pg_to_sqlite.insert("serial4".to_string(), "INTEGER".to_string());
+        mapper.pg_to_sqlite.insert("serial8".to_string(), "INTEGER".to_string());
         mapper.pg_to_sqlite.insert("character varying".to_string(), "TEXT".to_string());
         mapper.pg_to_sqlite.insert("character".to_string(), "TEXT".to_string());
         mapper.pg_to_sqlite.insert("timestamp with time zone".to_string(), "INTEGER".to_string());
@@ -367,7 +405,7 @@ impl TypeMapper {
         mapper.pg_to_sqlite.insert("time with time zone".to_string(), "INTEGER".to_string());
         mapper.pg_to_sqlite.insert("time without time zone".to_string(), "INTEGER".to_string());
         mapper.pg_to_sqlite.insert("timetz".to_string(), "INTEGER".to_string());
-        mapper.pg_to_sqlite.insert("interval".to_string(), "INTEGER".to_string());
+        mapper.pg_to_sqlite.insert("interval".to_string(), "TEXT".to_string());
         mapper.pg_to_sqlite.insert("datetime".to_string(), "INTEGER".to_string());
         
         // New type mappings
@@ -375,6 +413,9 @@ impl TypeMapper {
         mapper.pg_to_sqlite.insert("int4range".to_string(), "TEXT".to_string());
         mapper.pg_to_sqlite.insert("int8range".to_string(), "TEXT".to_string());
         mapper.pg_to_sqlite.insert("numrange".to_string(), "TEXT".to_string());
+        mapper.pg_to_sqlite.insert("tsrange".to_string(), "TEXT".to_string());
+        mapper.pg_to_sqlite.insert("tstzrange".to_string(), "TEXT".to_string());
+        mapper.pg_to_sqlite.insert("daterange".to_string(), "TEXT".to_string());
         mapper.pg_to_sqlite.insert("cidr".to_string(), "TEXT".to_string());
         mapper.pg_to_sqlite.insert("inet".to_string(), "TEXT".to_string());
         mapper.pg_to_sqlite.insert("macaddr".to_string(), "TEXT".to_string());
@@ -382,6 +423,7 @@ impl TypeMapper {
         mapper.pg_to_sqlite.insert("bit".to_string(), "TEXT".to_string());
         mapper.pg_to_sqlite.insert("bit varying".to_string(), "TEXT".to_string());
         mapper.pg_to_sqlite.insert("varbit".to_string(), "TEXT".to_string());
+        mapper.pg_to_sqlite.insert("xml".to_string(), "TEXT".to_string());
         
         // Full-text search types
         mapper.pg_to_sqlite.insert("tsvector".to_string(), "TEXT".to_string());
@@ -444,7 +486,7 @@ impl TypeMapper {
             "real" | "REAL" | "float4" | "FLOAT4" => return "DECIMAL",
             "double precision" | "DOUBLE PRECISION" | "float8" | "FLOAT8" => return "DECIMAL",
             "varchar" | "VARCHAR" => return "TEXT",
-            "numeric" | "NUMERIC" | "decimal" | "DECIMAL" => return "DECIMAL",
+            "numeric" | "NUMERIC" | "decimal" | "DECIMAL" => return "TEXT",
             _ => {}
         }
         
@@ -462,8 +504,9 @@ impl TypeMapper {
         
         // Handle SERIAL types specially - they need AUTOINCREMENT
         match normalized_type.to_uppercase().as_str() {
-            "SERIAL" => "INTEGER PRIMARY KEY AUTOINCREMENT".to_string(),
-            "BIGSERIAL" => "INTEGER PRIMARY KEY AUTOINCREMENT".to_string(),
+            "SMALLSERIAL" | "SERIAL2" | "SERIAL" | "SERIAL4" | "BIGSERIAL" | "SERIAL8" => {
+                "INTEGER PRIMARY KEY AUTOINCREMENT".to_string()
+            }
             _ => {
                 // Check for parametric types first
                 if let Some(base_type) = self.extract_base_type(&normalized_type) {
@@ -711,6 +754,8 @@ mod tests {
         assert_eq!(mapper.pg_to_sqlite_for_create_table("serial"), "INTEGER PRIMARY KEY AUTOINCREMENT");
         assert_eq!(mapper.pg_to_sqlite_for_create_table("BIGSERIAL"), "INTEGER PRIMARY KEY AUTOINCREMENT");
         assert_eq!(mapper.pg_to_sqlite_for_create_table("bigserial"), "INTEGER PRIMARY KEY AUTOINCREMENT");
+        assert_eq!(mapper.pg_to_sqlite_for_create_table("SMALLSERIAL"), "INTEGER PRIMARY KEY AUTOINCREMENT");
+        assert_eq!(mapper.pg_to_sqlite_for_create_table("serial8"), "INTEGER PRIMARY KEY AUTOINCREMENT");
     }
     
     #[test]
@@ -720,7 +765,7 @@ mod tests {
         // Test parametric types
         assert_eq!(mapper.pg_to_sqlite_for_create_table("VARCHAR(255)"), "TEXT");
         assert_eq!(mapper.pg_to_sqlite_for_create_table("CHAR(10)"), "TEXT");
-        assert_eq!(mapper.pg_to_sqlite_for_create_table("NUMERIC(10,2)"), "DECIMAL");
+        assert_eq!(mapper.pg_to_sqlite_for_create_table("NUMERIC(10,2)"), "TEXT");
         assert_eq!(mapper.pg_to_sqlite_for_create_table("BIT(8)"), "TEXT");
         assert_eq!(mapper.pg_to_sqlite_for_create_table("CHARACTER VARYING(100)"), "TEXT");
     }
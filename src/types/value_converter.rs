@@ -1,8 +1,9 @@
 use crate::types::type_mapper::PgType;
-use std::net::{Ipv4Addr, Ipv6Addr};
 use regex::Regex;
-use chrono::{NaiveTime, Timelike};
 use crate::types::datetime_utils;
+use crate::types::interval::Interval;
+use crate::types::range::{Range, RangeKind};
+use crate::types::network::NetworkKind;
 use once_cell::sync::Lazy;
 
 // Pre-compiled regex patterns
@@ -10,10 +11,6 @@ static MONEY_REGEX: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r"^[\$€£¥]?-?\d+(\.\d{1,2})?$|^-[\$€£¥]\d+(\.\d{1,2})?$").unwrap()
 });
 
-static RANGE_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"^[\[\(]-?\d+,-?\d+[\]\)]$").unwrap()
-});
-
 static TIMETZ_REGEX: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r"^(\d{2}:\d{2}:\d{2}(?:\.\d+)?)([-+]\d{2}:?\d{2})$").unwrap()
 });
@@ -22,10 +19,6 @@ static TIMESTAMPTZ_REGEX: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r"^(.+?)([-+]\d{2}:?\d{2})$").unwrap()
 });
 
-static INTERVAL_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"(?:(\d+)\s+days?\s*)?(?:(\d{2}):(\d{2}):(\d{2})(?:\.(\d+))?)?").unwrap()
-});
-
 static TIMEZONE_OFFSET_REGEX: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r"^([-+])(\d{2}):?(\d{2})$").unwrap()
 });
@@ -37,7 +30,8 @@ impl ValueConverter {
     pub fn pg_to_sqlite(value: &str, pg_type: PgType) -> Result<String, String> {
         match pg_type {
             PgType::Money => Self::convert_money(value),
-            PgType::Int4range | PgType::Int8range | PgType::Numrange => Self::convert_range(value),
+            PgType::Int4range | PgType::Int8range | PgType::Numrange |
+            PgType::Tsrange | PgType::Tstzrange | PgType::Daterange => Self::convert_range(value, pg_type),
             PgType::Cidr => Self::convert_cidr(value),
             PgType::Inet => Self::convert_inet(value),
             PgType::Macaddr => Self::convert_macaddr(value),
@@ -57,10 +51,9 @@ impl ValueConverter {
     pub fn sqlite_to_pg(value: &str, pg_type: PgType) -> Result<String, String> {
         match pg_type {
             PgType::Money => Ok(value.to_string()), // Money is stored as-is
-            PgType::Int4range | PgType::Int8range | PgType::Numrange => Ok(value.to_string()), // Ranges stored as-is
-            PgType::Cidr => Ok(value.to_string()), // CIDR stored as-is
-            PgType::Inet => Ok(value.to_string()), // INET stored as-is
-            PgType::Macaddr => Ok(value.to_string()), // MAC addresses stored as-is
+            PgType::Int4range | PgType::Int8range | PgType::Numrange |
+            PgType::Tsrange | PgType::Tstzrange | PgType::Daterange => Ok(value.to_string()), // Ranges stored as canonical text
+            PgType::Cidr | PgType::Inet | PgType::Macaddr => Ok(value.to_string()), // Network types stored as canonical text
             PgType::Macaddr8 => Ok(value.to_string()),
             PgType::Bit | PgType::Varbit => Ok(value.to_string()), // Bit strings stored as-is
             PgType::Date => Self::convert_unix_to_date(value),
@@ -86,92 +79,27 @@ impl ValueConverter {
         }
     }
     
-    /// Validate and convert range values
-    fn convert_range(value: &str) -> Result<String, String> {
-        // Range format: [lower,upper) or (lower,upper] or [lower,upper] or (lower,upper)
-        if RANGE_REGEX.is_match(value.trim()) {
-            Ok(value.trim().to_string())
-        } else {
-            Err(format!("Invalid range format: {value}"))
-        }
+    /// Validate range values and convert them to canonical form
+    fn convert_range(value: &str, pg_type: PgType) -> Result<String, String> {
+        let kind = RangeKind::from_pg_type(pg_type).ok_or_else(|| format!("Not a range type: {pg_type:?}"))?;
+        Range::parse(kind, value)
+            .map(|range| range.to_string())
+            .map_err(|e| format!("Invalid range format: {value}: {e}"))
     }
     
-    /// Validate and convert CIDR values
+    /// Validate CIDR values and convert them to canonical form
     fn convert_cidr(value: &str) -> Result<String, String> {
-        let trimmed = value.trim();
-        
-        // Split on '/'
-        let parts: Vec<&str> = trimmed.split('/').collect();
-        if parts.len() != 2 {
-            return Err(format!("Invalid CIDR format: {value}"));
-        }
-        
-        let ip_part = parts[0];
-        let prefix_part = parts[1];
-        
-        // Validate IP address
-        if !Self::is_valid_ip(ip_part) {
-            return Err(format!("Invalid IP address in CIDR: {ip_part}"));
-        }
-        
-        // Validate prefix length
-        let prefix: u8 = prefix_part.parse()
-            .map_err(|_| format!("Invalid prefix length: {prefix_part}"))?;
-        
-        if ip_part.contains(':') {
-            // IPv6
-            if prefix > 128 {
-                return Err(format!("IPv6 prefix length cannot exceed 128: {prefix}"));
-            }
-        } else {
-            // IPv4
-            if prefix > 32 {
-                return Err(format!("IPv4 prefix length cannot exceed 32: {prefix}"));
-            }
-        }
-        
-        Ok(trimmed.to_string())
+        NetworkKind::Cidr.canonicalize(value)
     }
     
-    /// Validate and convert INET values
+    /// Validate INET values and convert them to canonical form
     fn convert_inet(value: &str) -> Result<String, String> {
-        let trimmed = value.trim();
-        
-        // INET can be just an IP address or IP/prefix
-        if trimmed.contains('/') {
-            Self::convert_cidr(trimmed)
-        } else if Self::is_valid_ip(trimmed) {
-            Ok(trimmed.to_string())
-        } else {
-            Err(format!("Invalid INET format: {value}"))
-        }
+        NetworkKind::Inet.canonicalize(value)
     }
     
-    /// Validate and convert MAC address (6 bytes)
+    /// Validate MAC addresses (6 bytes) and convert them to canonical form
     fn convert_macaddr(value: &str) -> Result<String, String> {
-        let trimmed = value.trim();
-        
-        // Support colon and hyphen separators
-        let normalized = if trimmed.contains(':') {
-            trimmed.to_string()
-        } else if trimmed.contains('-') {
-            trimmed.replace('-', ":")
-        } else {
-            return Err(format!("Invalid MAC address format: {value}"));
-        };
-        
-        let parts: Vec<&str> = normalized.split(':').collect();
-        if parts.len() != 6 {
-            return Err(format!("MAC address must have 6 parts: {value}"));
-        }
-        
-        for part in &parts {
-            if part.len() != 2 || !part.chars().all(|c| c.is_ascii_hexdigit()) {
-                return Err(format!("Invalid MAC address part: {part}"));
-            }
-        }
-        
-        Ok(normalized)
+        NetworkKind::Macaddr.canonicalize(value)
     }
     
     /// Validate and convert MAC address (8 bytes)
@@ -220,11 +148,6 @@ impl ValueConverter {
         }
     }
     
-    /// Check if a string is a valid IP address (IPv4 or IPv6)
-    fn is_valid_ip(s: &str) -> bool {
-        s.parse::<Ipv4Addr>().is_ok() || s.parse::<Ipv6Addr>().is_ok()
-    }
-    
     // DateTime conversion functions
     
     /// Convert PostgreSQL DATE to epoch days (stored as INTEGER)
@@ -341,76 +264,15 @@ impl ValueConverter {
         Ok(format!("{timestamp_str}+00:00"))
     }
     
-    /// Convert PostgreSQL INTERVAL to microseconds (stored as INTEGER)
-    fn convert_interval_to_seconds(value: &str) -> Result<String, String> {
-        // Simple interval parsing for common formats
-        // Full PostgreSQL interval parsing is complex, this handles basic cases
-        let trimmed = value.trim();
-        
-        // Handle simple numeric intervals (e.g., "3600000000" microseconds)
-        if let Ok(micros) = trimmed.parse::<i64>() {
-            return Ok(micros.to_string());
-        }
-        
-        // Handle HH:MM:SS format
-        if let Ok(time) = NaiveTime::parse_from_str(trimmed, "%H:%M:%S%.f")
-            .or_else(|_| NaiveTime::parse_from_str(trimmed, "%H:%M:%S")) {
-            let micros = time.num_seconds_from_midnight() as i64 * 1_000_000 
-                + (time.nanosecond() / 1000) as i64;
-            return Ok(micros.to_string());
-        }
-        
-        // Handle verbose format (e.g., "1 day 02:30:00")
-        if let Some(caps) = INTERVAL_REGEX.captures(trimmed) {
-            let days = caps.get(1).map(|m| m.as_str().parse::<i64>().unwrap_or(0)).unwrap_or(0);
-            let hours = caps.get(2).map(|m| m.as_str().parse::<i64>().unwrap_or(0)).unwrap_or(0);
-            let minutes = caps.get(3).map(|m| m.as_str().parse::<i64>().unwrap_or(0)).unwrap_or(0);
-            let seconds = caps.get(4).map(|m| m.as_str().parse::<i64>().unwrap_or(0)).unwrap_or(0);
-            let fraction = caps.get(5).map(|m| {
-                let fraction_str = m.as_str();
-                // Parse the fractional part and convert to microseconds
-                
-                if fraction_str.len() <= 6 {
-                    // Pad with zeros if needed
-                    let padded = format!("{fraction_str:0<6}");
-                    padded.parse::<i64>().unwrap_or(0)
-                } else {
-                    // Truncate to 6 digits
-                    fraction_str[..6].parse::<i64>().unwrap_or(0)
-                }
-            }).unwrap_or(0);
-            
-            let total_micros = (days * 86400 + hours * 3600 + minutes * 60 + seconds) * 1_000_000 + fraction;
-            return Ok(total_micros.to_string());
-        }
-        
-        Err(format!("Unsupported interval format: {value}"))
+    /// Convert PostgreSQL INTERVAL to its storage form, the interval text
+    pub fn convert_interval_to_seconds(value: &str) -> Result<String, String> {
+        // Integers are microseconds in the storage form of older databases
+        Interval::from_storage(value.trim()).map(|interval| interval.to_storage())
     }
     
-    /// Convert microseconds to PostgreSQL INTERVAL
-    fn convert_seconds_to_interval(value: &str) -> Result<String, String> {
-        let total_micros = value.parse::<i64>()
-            .map_err(|e| format!("Invalid microseconds value: {value} ({e})"))?;
-        
-        let days = total_micros / (86400 * 1_000_000);
-        let remaining_micros = total_micros % (86400 * 1_000_000);
-        let hours = remaining_micros / (3600 * 1_000_000);
-        let minutes = (remaining_micros % (3600 * 1_000_000)) / (60 * 1_000_000);
-        let seconds = (remaining_micros % (60 * 1_000_000)) / 1_000_000;
-        let microseconds = remaining_micros % 1_000_000;
-        
-        let mut parts = Vec::new();
-        if days > 0 {
-            parts.push(format!("{} day{}", days, if days == 1 { "" } else { "s" }));
-        }
-        
-        if microseconds > 0 {
-            parts.push(format!("{hours:02}:{minutes:02}:{seconds:02}.{microseconds:06}"));
-        } else {
-            parts.push(format!("{hours:02}:{minutes:02}:{seconds:02}"));
-        }
-        
-        Ok(parts.join(" "))
+    /// Convert a stored interval (microseconds or interval text) to PostgreSQL INTERVAL
+    pub fn convert_seconds_to_interval(value: &str) -> Result<String, String> {
+        Interval::from_storage(value).map(|interval| interval.to_string())
     }
     
     /// Parse timezone offset string (±HH:MM or ±HHMM) to seconds
@@ -520,18 +382,22 @@ mod tests {
     
     #[test]
     fn test_interval_conversion() {
-        // Test simple microseconds
-        assert_eq!(ValueConverter::convert_interval_to_seconds("3600000000").unwrap(), "3600000000");
+        // Test microseconds stored by older databases
+        assert_eq!(ValueConverter::convert_interval_to_seconds("3600000000").unwrap(), "01:00:00");
         
         // Test HH:MM:SS format
-        assert_eq!(ValueConverter::convert_interval_to_seconds("01:30:00").unwrap(), "5400000000"); // 1.5 hours in microseconds
+        assert_eq!(ValueConverter::convert_interval_to_seconds("01:30:00").unwrap(), "01:30:00");
         
         // Test verbose format
-        let result = ValueConverter::convert_interval_to_seconds("1 day 02:30:00").unwrap();
-        assert_eq!(result, "95400000000"); // (86400 + 9000) seconds * 1_000_000 microseconds
+        let result = ValueConverter::convert_interval_to_seconds("1 day 2 hours 30 minutes").unwrap();
+        assert_eq!(result, "1 day 02:30:00");
         
         // Test microseconds to interval
         assert_eq!(ValueConverter::convert_seconds_to_interval("95400000000").unwrap(), "1 day 02:30:00");
         assert_eq!(ValueConverter::convert_seconds_to_interval("5400500000").unwrap(), "01:30:00.500000");
+        
+        // Months are kept apart from days
+        assert_eq!(ValueConverter::convert_interval_to_seconds("1 year 2 months").unwrap(), "1 year 2 mons");
+        assert_eq!(ValueConverter::convert_seconds_to_interval("1 year 2 mons").unwrap(), "1 year 2 mons");
     }
 }
\ No newline at end of file
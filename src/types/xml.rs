@@ -0,0 +1,263 @@
+use std::fmt;
+use sxd_document::{Package, dom, parser};
+use sxd_xpath::{Context, Factory, Value, nodeset::Node};
+
+/// Name of the element content is wrapped in, as XML content may have several
+/// top-level nodes but a parser wants a single root element
+const CONTENT_ROOT: &str = "__pgsqlite_content";
+
+/// Whether an xml value must be a document with a single root element or may be
+/// any content, PostgreSQL's XML OPTION. Values of xml columns are content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XmlOption {
+    Document,
+    Content,
+}
+
+impl XmlOption {
+    /// Look up an option by its keyword (e.g. "DOCUMENT")
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "document" => Some(XmlOption::Document),
+            "content" => Some(XmlOption::Content),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for XmlOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            XmlOption::Document => "document",
+            XmlOption::Content => "content",
+        })
+    }
+}
+
+/// Check that `text` is well-formed XML, as xml values are stored as the text they were
+/// written as
+pub fn validate(text: &str, option: XmlOption) -> Result<(), String> {
+    if is_well_formed(text, option) {
+        return Ok(());
+    }
+    Err(match option {
+        XmlOption::Document => "invalid XML document".to_string(),
+        XmlOption::Content => "invalid XML content".to_string(),
+    })
+}
+
+/// Whether `text` is well-formed XML of the kind `option` asks for, like xml_is_well_formed()
+pub fn is_well_formed(text: &str, option: XmlOption) -> bool {
+    if option == XmlOption::Document {
+        return parse_document(text).is_some();
+    }
+
+    let (declaration, body) = split_declaration(text);
+    // Content starting with a DOCTYPE is read as a document, as in PostgreSQL
+    if body.trim_start().starts_with("<!DOCTYPE") {
+        return parse_document(text).is_some();
+    }
+    parser::parse(&format!("{declaration}<{CONTENT_ROOT}>{body}</{CONTENT_ROOT}>")).is_ok()
+}
+
+/// Parse a document with a single root element
+fn parse_document(text: &str) -> Option<Package> {
+    let package = parser::parse(text).ok()?;
+    let is_document = package.as_document().root().children().iter()
+        .any(|child| matches!(child, dom::ChildOfRoot::Element(_)));
+    is_document.then_some(package)
+}
+
+/// Split off the XML declaration (`<?xml version="1.0"?>`) content may start with
+fn split_declaration(text: &str) -> (&str, &str) {
+    let trimmed = text.trim_start();
+    let is_declaration = trimmed.strip_prefix("<?xml")
+        .and_then(|rest| rest.chars().next())
+        .is_some_and(|next| next.is_whitespace() || next == '?');
+    match trimmed.find("?>").filter(|_| is_declaration) {
+        Some(end) => trimmed.split_at(end + 2),
+        None => ("", text),
+    }
+}
+
+/// Evaluate an XPath 1.0 expression against a document, like PostgreSQL's xpath()
+///
+/// Nodes are returned as XML text in document order; numbers, booleans and strings as
+/// a single value. `namespaces` maps the prefixes the expression uses to namespace URIs.
+pub fn xpath(path: &str, xml: &str, namespaces: &[(String, String)]) -> Result<Vec<String>, String> {
+    let package = parse_document(xml).ok_or_else(|| "could not parse XML document".to_string())?;
+    let document = package.as_document();
+    for child in document.root().children() {
+        if let dom::ChildOfRoot::Element(element) = child {
+            merge_text_nodes(element);
+        }
+    }
+    let expression = Factory::new().build(path).ok().flatten()
+        .ok_or_else(|| "invalid XPath expression".to_string())?;
+
+    let mut context = Context::new();
+    for (prefix, uri) in namespaces {
+        context.set_namespace(prefix, uri);
+    }
+    let value = expression.evaluate(&context, document.root())
+        .map_err(|e| format!("could not evaluate XPath expression: {e}"))?;
+
+    Ok(match value {
+        Value::Nodeset(nodes) => nodes.document_order().into_iter().map(serialize_node).collect(),
+        other => vec![escape(&other.string(), false)],
+    })
+}
+
+/// Join the text nodes the parser splits at character and entity references, so
+/// `text()` finds the text of an element as a single node
+fn merge_text_nodes(element: dom::Element) {
+    let mut previous: Option<dom::Text> = None;
+    for child in element.children() {
+        match child {
+            dom::ChildOfElement::Text(text) => match previous {
+                Some(first) => {
+                    first.set_text(&format!("{}{}", first.text(), text.text()));
+                    element.remove_child(text);
+                }
+                None => previous = Some(text),
+            },
+            dom::ChildOfElement::Element(child) => {
+                merge_text_nodes(child);
+                previous = None;
+            }
+            _ => previous = None,
+        }
+    }
+}
+
+/// The XML text of a node from an XPath result. Attributes and text are their escaped
+/// value; elements carry the namespace declarations made on them but not inherited ones.
+fn serialize_node(node: Node) -> String {
+    let mut out = String::new();
+    match node {
+        Node::Element(element) => write_element(element, &mut out),
+        Node::Root(root) => {
+            for child in root.children() {
+                match child {
+                    dom::ChildOfRoot::Element(element) => write_element(element, &mut out),
+                    dom::ChildOfRoot::Comment(comment) => write_comment(comment, &mut out),
+                    dom::ChildOfRoot::ProcessingInstruction(pi) => write_processing_instruction(pi, &mut out),
+                }
+            }
+        }
+        Node::Attribute(attribute) => out.push_str(&escape(attribute.value(), false)),
+        Node::Text(text) => out.push_str(&escape(text.text(), false)),
+        Node::Comment(comment) => write_comment(comment, &mut out),
+        Node::ProcessingInstruction(pi) => write_processing_instruction(pi, &mut out),
+        Node::Namespace(namespace) => out.push_str(&escape(namespace.uri(), false)),
+    }
+    out
+}
+
+fn write_element(element: dom::Element, out: &mut String) {
+    let name = Node::Element(element).prefixed_name().unwrap_or_default();
+    out.push('<');
+    out.push_str(&name);
+
+    let parent = match element.parent() {
+        Some(dom::ParentOfChild::Element(parent)) => Some(parent),
+        _ => None,
+    };
+    if let Some(uri) = element.default_namespace_uri()
+        && parent.and_then(|parent| parent.recursive_default_namespace_uri()) != Some(uri) {
+        out.push_str(&format!(" xmlns=\"{}\"", escape(uri, true)));
+    }
+    let inherited = parent.map(|parent| parent.namespaces_in_scope()).unwrap_or_default();
+    for namespace in element.namespaces_in_scope() {
+        let declared_here = namespace.prefix() != "xml"
+            && !inherited.iter().any(|outer| outer.prefix() == namespace.prefix() && outer.uri() == namespace.uri());
+        if declared_here {
+            out.push_str(&format!(" xmlns:{}=\"{}\"", namespace.prefix(), escape(namespace.uri(), true)));
+        }
+    }
+    for attribute in element.attributes() {
+        let name = Node::Attribute(attribute).prefixed_name().unwrap_or_default();
+        out.push_str(&format!(" {name}=\"{}\"", escape(attribute.value(), true)));
+    }
+
+    let children = element.children();
+    if children.is_empty() {
+        out.push_str("/>");
+        return;
+    }
+    out.push('>');
+    for child in children {
+        match child {
+            dom::ChildOfElement::Element(child) => write_element(child, out),
+            dom::ChildOfElement::Text(text) => out.push_str(&escape(text.text(), false)),
+            dom::ChildOfElement::Comment(comment) => write_comment(comment, out),
+            dom::ChildOfElement::ProcessingInstruction(pi) => write_processing_instruction(pi, out),
+        }
+    }
+    out.push_str(&format!("</{name}>"));
+}
+
+fn write_comment(comment: dom::Comment, out: &mut String) {
+    out.push_str(&format!("<!--{}-->", comment.text()));
+}
+
+fn write_processing_instruction(pi: dom::ProcessingInstruction, out: &mut String) {
+    match pi.value() {
+        Some(value) => out.push_str(&format!("<?{} {value}?>", pi.target())),
+        None => out.push_str(&format!("<?{}?>", pi.target())),
+    }
+}
+
+/// Escape text for element content, or for a double-quoted attribute value
+fn escape(text: &str, attribute: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' if attribute => escaped.push_str("&quot;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_well_formed() {
+        assert!(is_well_formed("<a><b/></a>", XmlOption::Document));
+        assert!(is_well_formed("<?xml version=\"1.0\"?><a/>", XmlOption::Document));
+        assert!(!is_well_formed("text<a/>", XmlOption::Document));
+        assert!(!is_well_formed("<a></b>", XmlOption::Document));
+
+        // Content may be text and several elements, after an optional declaration
+        assert!(is_well_formed("abc<a/><b>x</b>", XmlOption::Content));
+        assert!(is_well_formed("<?xml version=\"1.0\"?>text", XmlOption::Content));
+        assert!(is_well_formed("", XmlOption::Content));
+        assert!(!is_well_formed("<a>", XmlOption::Content));
+        assert!(!is_well_formed("a & b", XmlOption::Content));
+        assert_eq!(validate("<a>", XmlOption::Content), Err("invalid XML content".to_string()));
+    }
+
+    #[test]
+    fn test_xpath() {
+        let xml = r#"<book id="7"><title lang="en">Rust &amp; XML</title><title>Second</title></book>"#;
+        assert_eq!(xpath("/book/title/text()", xml, &[]).unwrap(), vec!["Rust &amp; XML", "Second"]);
+        assert_eq!(xpath("/book/@id", xml, &[]).unwrap(), vec!["7"]);
+        assert_eq!(xpath("//title[@lang]", xml, &[]).unwrap(), vec![r#"<title lang="en">Rust &amp; XML</title>"#]);
+        assert_eq!(xpath("count(//title)", xml, &[]).unwrap(), vec!["2"]);
+        assert_eq!(xpath("//missing", xml, &[]).unwrap(), Vec::<String>::new());
+        assert!(xpath("//[", xml, &[]).is_err());
+        assert!(xpath("/a", "a<b/>", &[]).is_err());
+
+        // Prefixes resolve through the namespace mappings
+        let xml = r#"<my:a xmlns:my="http://example.com"><my:b/></my:a>"#;
+        let namespaces = vec![("n".to_string(), "http://example.com".to_string())];
+        assert_eq!(xpath("/n:a/n:b", xml, &namespaces).unwrap(), vec!["<my:b/>"]);
+        assert_eq!(xpath("/n:a", xml, &namespaces).unwrap(), vec![xml]);
+    }
+}
@@ -0,0 +1,105 @@
+use rusqlite::Connection;
+
+/// Length rule for a BIT or BIT VARYING column
+#[derive(Debug, Clone)]
+pub struct BitConstraint {
+    pub table_name: String,
+    pub column_name: String,
+    pub length: Option<i32>,  // None for VARBIT without a limit
+    pub varying: bool,        // false for BIT(n), which requires exactly n digits
+}
+
+impl BitConstraint {
+    /// Build the constraint for a column declared with a bit string type.
+    ///
+    /// Returns None for any other type. A bare BIT means BIT(1).
+    pub fn from_column(table_name: &str, column_name: &str, pg_type: &str, type_modifier: Option<i32>) -> Option<Self> {
+        let base_type = pg_type.split('(').next().unwrap_or(pg_type).trim().to_lowercase();
+        let varying = match base_type.as_str() {
+            "bit" => false,
+            "varbit" | "bit varying" => true,
+            _ => return None,
+        };
+        Some(BitConstraint {
+            table_name: table_name.to_string(),
+            column_name: column_name.to_string(),
+            length: if varying { type_modifier } else { Some(type_modifier.unwrap_or(1)) },
+            varying,
+        })
+    }
+
+    /// Create BEFORE INSERT/UPDATE triggers validating the column's bit strings.
+    ///
+    /// Values must consist of binary digits (22P02). BIT(n) values must have
+    /// exactly n digits (22026) and BIT VARYING(n) values at most n (22001).
+    /// The messages match PostgreSQL's so `PgError::from_message` can recover the code.
+    pub fn create_triggers(&self, conn: &Connection) -> Result<(), rusqlite::Error> {
+        let table = &self.table_name;
+        let column = &self.column_name;
+
+        let length_check = match (self.length, self.varying) {
+            (Some(length), false) => format!(
+                "SELECT RAISE(ABORT, 'bit string length ' || length(NEW.\"{column}\") || ' does not match type bit({length})') \
+                 WHERE length(NEW.\"{column}\") <> {length};"
+            ),
+            (Some(length), true) => format!(
+                "SELECT RAISE(ABORT, 'bit string too long for type bit varying({length})') \
+                 WHERE length(NEW.\"{column}\") > {length};"
+            ),
+            (None, _) => String::new(),
+        };
+
+        for (event, action) in [("insert", "INSERT".to_string()), ("update", format!("UPDATE OF \"{column}\""))] {
+            let trigger_sql = format!(
+                r#"CREATE TRIGGER IF NOT EXISTS "__pgsqlite_bit_{event}_{table}_{column}"
+                BEFORE {action} ON "{table}"
+                FOR EACH ROW
+                WHEN NEW."{column}" IS NOT NULL
+                BEGIN
+                    SELECT RAISE(ABORT, '"' || substr(ltrim(NEW."{column}", '01'), 1, 1) || '" is not a valid binary digit')
+                    WHERE ltrim(NEW."{column}", '01') <> '';
+                    {length_check}
+                END"#
+            );
+            conn.execute(&trigger_sql, [])?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE t (id INTEGER, fixed TEXT, var TEXT)", []).unwrap();
+        BitConstraint::from_column("t", "fixed", "BIT(4)", Some(4)).unwrap().create_triggers(&conn).unwrap();
+        BitConstraint::from_column("t", "var", "VARBIT(3)", Some(3)).unwrap().create_triggers(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_from_column() {
+        let bare = BitConstraint::from_column("t", "c", "BIT", None).unwrap();
+        assert_eq!((bare.length, bare.varying), (Some(1), false));
+        let varying = BitConstraint::from_column("t", "c", "bit varying", None).unwrap();
+        assert_eq!((varying.length, varying.varying), (None, true));
+        assert!(BitConstraint::from_column("t", "c", "VARCHAR(4)", Some(4)).is_none());
+    }
+
+    #[test]
+    fn test_bit_triggers() {
+        let conn = setup();
+        conn.execute("INSERT INTO t VALUES (1, '0101', '11')", []).unwrap();
+        conn.execute("INSERT INTO t VALUES (2, NULL, NULL)", []).unwrap();
+
+        let err = conn.execute("INSERT INTO t VALUES (3, '010', NULL)", []).unwrap_err();
+        assert!(err.to_string().contains("bit string length 3 does not match type bit(4)"));
+        let err = conn.execute("INSERT INTO t VALUES (3, NULL, '1111')", []).unwrap_err();
+        assert!(err.to_string().contains("bit string too long for type bit varying(3)"));
+        let err = conn.execute("UPDATE t SET fixed = '0120' WHERE id = 1", []).unwrap_err();
+        assert!(err.to_string().contains("\"2\" is not a valid binary digit"));
+    }
+}
@@ -0,0 +1,285 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rusqlite::Connection;
+
+/// `[CONSTRAINT name] EXCLUDE [USING method] (elements) [rest]`
+static EXCLUDE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^\s*(?:CONSTRAINT\s+("[^"]+"|\w+)\s+)?EXCLUDE\s+(?:USING\s+(\w+)\s*)?\((.*?)\)\s*(.*?)\s*$"#).unwrap()
+});
+
+/// One `column WITH operator` element of an EXCLUDE constraint
+static ELEMENT_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^\s*("[^"]+"|\w+)\s+WITH\s+(\S+)\s*$"#).unwrap()
+});
+
+/// The operators an exclusion constraint can compare columns with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExclusionOperator {
+    /// `=`, the scalar columns a conflict must share, e.g. the room of a booking
+    Equal,
+    /// `&&`, range columns whose values must not overlap
+    Overlaps,
+}
+
+impl ExclusionOperator {
+    fn from_symbol(symbol: &str) -> Option<Self> {
+        match symbol {
+            "=" => Some(ExclusionOperator::Equal),
+            "&&" => Some(ExclusionOperator::Overlaps),
+            _ => None,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            ExclusionOperator::Equal => "=",
+            ExclusionOperator::Overlaps => "&&",
+        }
+    }
+}
+
+/// An EXCLUDE constraint, such as `EXCLUDE USING gist (room WITH =, during WITH &&)`
+///
+/// SQLite has no exclusion constraints, so one is enforced by BEFORE INSERT/UPDATE
+/// triggers that look for an existing row sharing the `=` columns whose `&&` ranges
+/// overlap the new row's, using range_overlaps(). An index named after the constraint,
+/// on the `=` columns followed by the range columns, lets the lookup read only the
+/// rows with the same key, from the index alone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExclusionConstraint {
+    pub table_name: String,
+    pub constraint_name: String,
+    pub elements: Vec<(String, ExclusionOperator)>,
+}
+
+impl ExclusionConstraint {
+    /// Parse a table constraint of a CREATE TABLE statement.
+    ///
+    /// Returns Ok(None) for anything other than an EXCLUDE constraint, and an error
+    /// for EXCLUDE constraints using features the triggers can't reproduce.
+    pub fn from_definition(table_name: &str, definition: &str) -> Result<Option<Self>, String> {
+        let Some(caps) = EXCLUDE_REGEX.captures(definition) else {
+            return Ok(None);
+        };
+        if !caps[4].is_empty() {
+            return Err(format!("EXCLUDE constraints do not support \"{}\"", &caps[4]));
+        }
+        if let Some(method) = caps.get(2)
+            && !method.as_str().eq_ignore_ascii_case("gist") {
+            return Err(format!("access method \"{}\" does not support exclusion constraints", method.as_str()));
+        }
+
+        let mut elements = Vec::new();
+        for element in caps[3].split(',') {
+            let parts = ELEMENT_REGEX.captures(element)
+                .ok_or_else(|| format!("EXCLUDE constraints only support columns, not \"{}\"", element.trim()))?;
+            let operator = ExclusionOperator::from_symbol(&parts[2])
+                .ok_or_else(|| format!("EXCLUDE constraints only support the = and && operators, not {}", &parts[2]))?;
+            elements.push((parts[1].trim_matches('"').to_string(), operator));
+        }
+        if !elements.iter().any(|(_, operator)| *operator == ExclusionOperator::Overlaps) {
+            return Err("EXCLUDE constraints need a range column compared with &&; use UNIQUE for = alone".to_string());
+        }
+
+        // PostgreSQL names an unnamed constraint after the table and its columns
+        let constraint_name = match caps.get(1) {
+            Some(name) => name.as_str().trim_matches('"').to_string(),
+            None => {
+                let columns: Vec<&str> = elements.iter().map(|(column, _)| column.as_str()).collect();
+                format!("{table_name}_{}_excl", columns.join("_"))
+            }
+        };
+        Ok(Some(ExclusionConstraint { table_name: table_name.to_string(), constraint_name, elements }))
+    }
+
+    /// Create the index and triggers enforcing the constraint
+    pub fn create(&self, conn: &Connection) -> Result<(), rusqlite::Error> {
+        self.create_index(conn)?;
+        self.create_triggers(conn)
+    }
+
+    /// Index the `=` columns first, so the triggers find the rows a new row could conflict with
+    pub fn create_index(&self, conn: &Connection) -> Result<(), rusqlite::Error> {
+        let mut columns: Vec<&(String, ExclusionOperator)> = self.elements.iter().collect();
+        columns.sort_by_key(|(_, operator)| *operator == ExclusionOperator::Overlaps);
+        let columns: Vec<String> = columns.iter().map(|(column, _)| format!("\"{column}\"")).collect();
+        conn.execute(
+            &format!("CREATE INDEX IF NOT EXISTS \"{}\" ON \"{}\" ({})", self.constraint_name, self.table_name, columns.join(", ")),
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Create BEFORE INSERT/UPDATE triggers rejecting conflicting rows.
+    ///
+    /// The message matches PostgreSQL's so `PgError::from_message` can recover 23P01.
+    /// Rows with a NULL in any column never conflict, as NULL compares as unknown.
+    pub fn create_triggers(&self, conn: &Connection) -> Result<(), rusqlite::Error> {
+        let table = &self.table_name;
+        let name = &self.constraint_name;
+        let conditions: Vec<String> = self.elements.iter()
+            .map(|(column, operator)| match operator {
+                ExclusionOperator::Equal => format!("existing.\"{column}\" = NEW.\"{column}\""),
+                ExclusionOperator::Overlaps => format!("range_overlaps(existing.\"{column}\", NEW.\"{column}\")"),
+            })
+            .collect();
+        let columns: Vec<String> = self.elements.iter().map(|(column, _)| format!("\"{column}\"")).collect();
+
+        for (event, action, other_rows) in [
+            ("insert", "INSERT".to_string(), ""),
+            ("update", format!("UPDATE OF {}", columns.join(", ")), " AND existing.rowid <> OLD.rowid"),
+        ] {
+            let trigger_sql = format!(
+                r#"CREATE TRIGGER IF NOT EXISTS "__pgsqlite_exclusion_{event}_{name}"
+                BEFORE {action} ON "{table}"
+                FOR EACH ROW
+                BEGIN
+                    SELECT RAISE(ABORT, 'conflicting key value violates exclusion constraint "{name}"')
+                    WHERE EXISTS (SELECT 1 FROM "{table}" AS existing WHERE {}{other_rows});
+                END"#,
+                conditions.join(" AND "),
+            );
+            conn.execute(&trigger_sql, [])?;
+        }
+        Ok(())
+    }
+
+    /// Record the constraint, so its triggers can be recreated when the table is rebuilt
+    pub fn record(&self, conn: &Connection) -> Result<(), rusqlite::Error> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS __pgsqlite_exclusion_constraints (
+                table_name TEXT NOT NULL,
+                column_name TEXT NOT NULL,
+                constraint_name TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                operator TEXT NOT NULL,
+                PRIMARY KEY (table_name, constraint_name, position)
+            )",
+            [],
+        )?;
+        conn.execute(
+            "DELETE FROM __pgsqlite_exclusion_constraints WHERE table_name = ?1 AND constraint_name = ?2",
+            [&self.table_name, &self.constraint_name],
+        )?;
+        for (position, (column, operator)) in self.elements.iter().enumerate() {
+            conn.execute(
+                "INSERT INTO __pgsqlite_exclusion_constraints (table_name, column_name, constraint_name, position, operator)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                rusqlite::params![self.table_name, column, self.constraint_name, position as i64, operator.symbol()],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// The recorded exclusion constraints of a table
+    pub fn load(conn: &Connection, table_name: &str) -> Result<Vec<Self>, rusqlite::Error> {
+        if !Self::has_metadata(conn)? {
+            return Ok(Vec::new());
+        }
+        let mut stmt = conn.prepare(
+            "SELECT constraint_name, column_name, operator FROM __pgsqlite_exclusion_constraints
+             WHERE table_name = ?1 ORDER BY constraint_name, position",
+        )?;
+        let rows = stmt.query_map([table_name], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))?;
+
+        let mut constraints: Vec<Self> = Vec::new();
+        for row in rows {
+            let (constraint_name, column, operator) = row?;
+            let Some(operator) = ExclusionOperator::from_symbol(&operator) else {
+                continue;
+            };
+            match constraints.last_mut() {
+                Some(last) if last.constraint_name == constraint_name => last.elements.push((column, operator)),
+                _ => constraints.push(ExclusionConstraint {
+                    table_name: table_name.to_string(),
+                    constraint_name,
+                    elements: vec![(column, operator)],
+                }),
+            }
+        }
+        Ok(constraints)
+    }
+
+    /// Forget the constraints of tables that no longer exist
+    pub fn prune(conn: &Connection) -> Result<(), rusqlite::Error> {
+        if !Self::has_metadata(conn)? {
+            return Ok(());
+        }
+        conn.execute(
+            "DELETE FROM __pgsqlite_exclusion_constraints
+             WHERE table_name NOT IN (SELECT name FROM sqlite_master WHERE type = 'table')",
+            [],
+        )?;
+        Ok(())
+    }
+
+    fn has_metadata(conn: &Connection) -> Result<bool, rusqlite::Error> {
+        conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '__pgsqlite_exclusion_constraints')",
+            [],
+            |row| row.get(0),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::functions::range_functions::register_range_functions(&conn).unwrap();
+        conn.execute("CREATE TABLE bookings (id INTEGER PRIMARY KEY, room INTEGER, during TEXT)", []).unwrap();
+        let constraint = ExclusionConstraint::from_definition("bookings", "EXCLUDE USING gist (room WITH =, during WITH &&)")
+            .unwrap()
+            .unwrap();
+        constraint.create(&conn).unwrap();
+        constraint.record(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_from_definition() {
+        let constraint = ExclusionConstraint::from_definition("bookings", "CONSTRAINT no_double_booking EXCLUDE (room WITH =, \"during\" WITH &&)")
+            .unwrap()
+            .unwrap();
+        assert_eq!(constraint.constraint_name, "no_double_booking");
+        assert_eq!(constraint.elements, vec![
+            ("room".to_string(), ExclusionOperator::Equal),
+            ("during".to_string(), ExclusionOperator::Overlaps),
+        ]);
+        let unnamed = ExclusionConstraint::from_definition("bookings", "EXCLUDE USING gist (room WITH =, during WITH &&)").unwrap().unwrap();
+        assert_eq!(unnamed.constraint_name, "bookings_room_during_excl");
+
+        assert!(ExclusionConstraint::from_definition("bookings", "UNIQUE (room)").unwrap().is_none());
+        assert!(ExclusionConstraint::from_definition("bookings", "EXCLUDE USING gist (during WITH <>)").is_err());
+        assert!(ExclusionConstraint::from_definition("bookings", "EXCLUDE USING gist (during WITH &&) WHERE (active)").is_err());
+        assert!(ExclusionConstraint::from_definition("bookings", "EXCLUDE USING gist (room WITH =)").is_err());
+    }
+
+    #[test]
+    fn test_exclusion_triggers() {
+        let conn = setup();
+        conn.execute("INSERT INTO bookings VALUES (1, 101, '[2024-03-01,2024-03-05)')", []).unwrap();
+        // Another room, an adjacent stay and a NULL range don't conflict
+        conn.execute("INSERT INTO bookings VALUES (2, 102, '[2024-03-01,2024-03-05)')", []).unwrap();
+        conn.execute("INSERT INTO bookings VALUES (3, 101, '[2024-03-05,2024-03-07)')", []).unwrap();
+        conn.execute("INSERT INTO bookings VALUES (4, 101, NULL)", []).unwrap();
+
+        let err = conn.execute("INSERT INTO bookings VALUES (5, 101, '[2024-03-04,2024-03-06)')", []).unwrap_err();
+        assert!(err.to_string().contains("conflicting key value violates exclusion constraint \"bookings_room_during_excl\""));
+        // A row doesn't conflict with itself when updated
+        conn.execute("UPDATE bookings SET during = '[2024-03-01,2024-03-04)' WHERE id = 1", []).unwrap();
+        assert!(conn.execute("UPDATE bookings SET room = 101 WHERE id = 2", []).is_err());
+
+        let loaded = ExclusionConstraint::load(&conn, "bookings").unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].elements.len(), 2);
+        let plan: String = conn.query_row(
+            "EXPLAIN QUERY PLAN SELECT 1 FROM bookings WHERE room = 101 AND range_overlaps(during, '[2024-03-01,2024-03-02)')",
+            [],
+            |row| row.get(3),
+        ).unwrap();
+        assert!(plan.contains("bookings_room_during_excl"), "{plan}");
+    }
+}
@@ -1,10 +1,14 @@
 pub mod string_constraints;
+pub mod bit_constraints;
+pub mod exclusion_constraints;
 pub mod numeric_constraints;
 pub mod numeric_triggers;
 pub mod insert_validator;
 pub mod numeric_validator;
 
 pub use string_constraints::{StringConstraintValidator, StringConstraint};
+pub use bit_constraints::BitConstraint;
+pub use exclusion_constraints::{ExclusionConstraint, ExclusionOperator};
 pub use numeric_constraints::{NumericConstraintValidator, NumericConstraint};
 pub use numeric_triggers::NumericTriggers;
 pub use insert_validator::{InsertValidator, UpdateValidator};
@@ -110,13 +110,52 @@ impl StringConstraintValidator {
         
         // Insert into string constraints table
         for constraint in constraints {
-            let (table_name, column_name, max_length, is_char) = constraint?;
-            conn.execute(
-                "INSERT OR REPLACE INTO __pgsqlite_string_constraints 
-                 (table_name, column_name, max_length, is_char_type) 
-                 VALUES (?1, ?2, ?3, ?4)",
-                rusqlite::params![table_name, column_name, max_length, is_char as i32],
-            )?;
+            let (table_name, column_name, max_length, is_char_type) = constraint?;
+            Self::record_constraint(conn, &StringConstraint { table_name, column_name, max_length, is_char_type })?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Store a column's length limit and create the triggers that enforce it
+    pub fn record_constraint(conn: &Connection, constraint: &StringConstraint) -> Result<(), rusqlite::Error> {
+        conn.execute(
+            "INSERT OR REPLACE INTO __pgsqlite_string_constraints 
+             (table_name, column_name, max_length, is_char_type) 
+             VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![constraint.table_name, constraint.column_name, constraint.max_length, constraint.is_char_type as i32],
+        )?;
+        
+        Self::create_length_triggers(conn, constraint)
+    }
+    
+    /// Create BEFORE INSERT/UPDATE triggers rejecting values longer than the column allows.
+    ///
+    /// Triggers see every write, whichever execution path (fast paths, prepared
+    /// statements, COPY) produced it. The error message uses the format of
+    /// `PgError::StringDataRightTruncation` so it can be mapped back to 22001.
+    pub fn create_length_triggers(conn: &Connection, constraint: &StringConstraint) -> Result<(), rusqlite::Error> {
+        let table = &constraint.table_name;
+        let column = &constraint.column_name;
+        let max_length = constraint.max_length;
+        let type_name = if constraint.is_char_type {
+            format!("character({max_length})")
+        } else {
+            format!("character varying({max_length})")
+        };
+        let message_column = column.replace('\'', "''");
+        
+        for (event, action) in [("insert", "INSERT".to_string()), ("update", format!("UPDATE OF \"{column}\""))] {
+            let trigger_sql = format!(
+                r#"CREATE TRIGGER IF NOT EXISTS "__pgsqlite_varchar_{event}_{table}_{column}"
+                BEFORE {action} ON "{table}"
+                FOR EACH ROW
+                WHEN NEW."{column}" IS NOT NULL AND length(NEW."{column}") > {max_length}
+                BEGIN
+                    SELECT RAISE(ABORT, 'value too long for type {type_name} in column {message_column} (' || length(NEW."{column}") || ' characters, maximum is {max_length})');
+                END"#
+            );
+            conn.execute(&trigger_sql, [])?;
         }
         
         Ok(())
@@ -320,6 +359,28 @@ mod tests {
         assert!(validator.validate_value("users", "name", "Any length string should be OK").is_ok());
     }
     
+    #[test]
+    fn test_length_triggers() {
+        let conn = setup_test_db();
+        conn.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)", []).unwrap();
+        
+        StringConstraintValidator::record_constraint(&conn, &StringConstraint {
+            table_name: "users".to_string(),
+            column_name: "name".to_string(),
+            max_length: 3,
+            is_char_type: false,
+        }).unwrap();
+        
+        assert!(conn.execute("INSERT INTO users (id, name) VALUES (1, '你好世')", []).is_ok());
+        assert!(conn.execute("INSERT INTO users (id, name) VALUES (2, NULL)", []).is_ok());
+        
+        let err = conn.execute("INSERT INTO users (id, name) VALUES (3, 'café')", []).unwrap_err();
+        assert!(err.to_string().contains("value too long for type character varying(3) in column name (4 characters, maximum is 3)"));
+        
+        assert!(conn.execute("UPDATE users SET name = 'abcd' WHERE id = 1", []).is_err());
+        assert!(conn.execute("UPDATE users SET name = 'abc' WHERE id = 1", []).is_ok());
+    }
+    
     #[test]
     fn test_cache_invalidation() {
         let validator = StringConstraintValidator::new();
@@ -0,0 +1,111 @@
+mod common;
+use common::{free_port, spawn_server_with, wait_until_listening};
+use std::time::{Duration, Instant};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::{Client, NoTls};
+
+async fn connect(host: &str, port: u16, user: &str) -> Result<Client, tokio_postgres::Error> {
+    let (client, connection) = tokio_postgres::connect(
+        &format!("host={host} port={port} dbname=main user={user}"),
+        NoTls,
+    ).await?;
+    tokio::spawn(connection);
+    Ok(client)
+}
+
+/// Connect once a connection slot is free; the one a client just closed, or the server's
+/// startup check, is given back once the server noticed
+async fn connect_when_free(port: u16, user: &str) -> Client {
+    let started = Instant::now();
+    loop {
+        match connect("127.0.0.1", port, user).await {
+            Ok(client) => return client,
+            Err(e) => {
+                assert!(started.elapsed() < Duration::from_secs(10), "slot was not released: {e}");
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        }
+    }
+}
+
+/// The admin port lets admin roles in while the server is at --max-connections
+#[tokio::test]
+async fn test_admin_port_bypasses_connection_limit() {
+    let socket_dir = tempfile::tempdir().unwrap();
+    let mut admin_port = 0;
+    let server = spawn_server_with(|command| {
+        admin_port = free_port();
+        command
+            .args(["--in-memory", "--log-level", "error", "--max-connections", "1"])
+            .args(["--admin-port", &admin_port.to_string(), "--admin-users", "postgres, ops"])
+            .arg("--socket-dir")
+            .arg(socket_dir.path());
+    }).await;
+    let port = server.port;
+    wait_until_listening(admin_port).await;
+
+    let client = connect_when_free(port, "app").await;
+    client.simple_query("SELECT 1").await.unwrap();
+
+    // The regular listener is full
+    let err = connect("127.0.0.1", port, "app").await.err().expect("second client should be refused");
+    assert_eq!(err.code(), Some(&SqlState::TOO_MANY_CONNECTIONS), "unexpected error: {err:?}");
+
+    // Admin roles still get in, over TCP and the admin Unix socket
+    let admin = connect("127.0.0.1", admin_port, "postgres").await.unwrap();
+    let row = admin.query_one("SELECT 1::int4", &[]).await.unwrap();
+    assert_eq!(row.get::<_, i32>(0), 1);
+    let ops = connect("127.0.0.1", admin_port, "ops").await.unwrap();
+    ops.simple_query("SELECT 1").await.unwrap();
+    #[cfg(unix)]
+    {
+        let local = connect(socket_dir.path().to_str().unwrap(), admin_port, "postgres").await.unwrap();
+        local.simple_query("SELECT 1").await.unwrap();
+    }
+
+    // Other roles are turned away from the admin port
+    let err = connect("127.0.0.1", admin_port, "app").await.err().expect("non-admin role should be refused");
+    assert_eq!(err.code(), Some(&SqlState::INVALID_AUTHORIZATION_SPECIFICATION), "unexpected error: {err:?}");
+
+    // A slot frees up once the regular client leaves
+    drop(client);
+    connect_when_free(port, "app").await.simple_query("SELECT 1").await.unwrap();
+}
+
+/// Admin sessions have their own small limit, and the admin socket is private to the server's user
+#[tokio::test]
+async fn test_admin_port_limits() {
+    let socket_dir = tempfile::tempdir().unwrap();
+    let mut admin_port = 0;
+    let server = spawn_server_with(|command| {
+        admin_port = free_port();
+        command
+            .args(["--in-memory", "--log-level", "error", "--admin-max-connections", "1"])
+            .args(["--admin-port", &admin_port.to_string()])
+            .arg("--socket-dir")
+            .arg(socket_dir.path());
+    }).await;
+    let port = server.port;
+    wait_until_listening(admin_port).await;
+
+    let admin = connect_when_free(admin_port, "postgres").await;
+    admin.simple_query("SELECT 1").await.unwrap();
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let admin_socket = socket_dir.path().join(format!(".s.PGSQL.{admin_port}"));
+        let mode = std::fs::metadata(&admin_socket).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        let regular_socket = socket_dir.path().join(format!(".s.PGSQL.{port}"));
+        let mode = std::fs::metadata(&regular_socket).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o777);
+    }
+
+    let err = connect("127.0.0.1", admin_port, "postgres").await.err().expect("second admin session should be refused");
+    assert_eq!(err.code(), Some(&SqlState::TOO_MANY_CONNECTIONS), "unexpected error: {err:?}");
+
+    // The slot is given back when the admin session ends
+    drop(admin);
+    connect_when_free(admin_port, "postgres").await.simple_query("SELECT 1").await.unwrap();
+}
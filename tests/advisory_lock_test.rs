@@ -0,0 +1,122 @@
+mod common;
+use common::setup_test_server;
+use std::time::{Duration, Instant};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::{Client, SimpleQueryMessage};
+
+async fn scalar(client: &Client, sql: &str) -> Option<String> {
+    client.simple_query(sql).await.unwrap().into_iter().find_map(|msg| match msg {
+        SimpleQueryMessage::Row(row) => Some(row.get(0).map(str::to_string)),
+        _ => None,
+    }).flatten()
+}
+
+/// Check that the session running `task` is still waiting for a lock
+async fn assert_blocked<T>(task: &tokio::task::JoinHandle<T>) {
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(!task.is_finished(), "lock was granted while held by another session");
+}
+
+/// Session locks are held until unlocked as often as they were taken, or the session ends
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_session_advisory_locks() {
+    let server = setup_test_server().await;
+    let (a, b) = (server.connect().await, server.connect().await);
+
+    assert_eq!(scalar(&a, "SELECT pg_try_advisory_lock(1)").await.as_deref(), Some("t"));
+    assert_eq!(scalar(&a, "SELECT pg_advisory_lock(1)").await.as_deref(), Some(""));
+    assert_eq!(scalar(&b, "SELECT pg_try_advisory_lock(1)").await.as_deref(), Some("f"));
+    // The bigint and the two-integer keys are separate
+    assert_eq!(scalar(&b, "SELECT pg_try_advisory_lock(0, 1)").await.as_deref(), Some("t"));
+    assert_eq!(scalar(&b, "SELECT pg_advisory_unlock(1)").await.as_deref(), Some("f"));
+
+    // b waits for the lock until a unlocked it as often as it locked it
+    let waiter = tokio::spawn(async move {
+        b.query("SELECT pg_advisory_lock($1::int8)", &[&1i64]).await.unwrap();
+        b
+    });
+    assert_blocked(&waiter).await;
+    assert_eq!(scalar(&a, "SELECT pg_advisory_unlock(1)").await.as_deref(), Some("t"));
+    assert_blocked(&waiter).await;
+    assert_eq!(scalar(&a, "SELECT pg_advisory_unlock(1)").await.as_deref(), Some("t"));
+    let b = tokio::time::timeout(Duration::from_secs(10), waiter).await.unwrap().unwrap();
+    assert_eq!(scalar(&a, "SELECT pg_try_advisory_lock(1)").await.as_deref(), Some("f"));
+
+    // Locks are released when their session ends, and by pg_advisory_unlock_all()
+    drop(b);
+    let started = Instant::now();
+    while scalar(&a, "SELECT pg_try_advisory_lock(1)").await.as_deref() != Some("t") {
+        assert!(started.elapsed() < Duration::from_secs(10), "lock outlived its session");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    a.simple_query("SELECT pg_advisory_unlock_all()").await.unwrap();
+    let c = server.connect().await;
+    assert_eq!(scalar(&c, "SELECT pg_try_advisory_lock(1)").await.as_deref(), Some("t"));
+    assert_eq!(scalar(&c, "SELECT pg_try_advisory_lock(0, 1)").await.as_deref(), Some("t"));
+}
+
+/// Transaction locks are released when the transaction ends, or the statement outside a block
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_transaction_advisory_locks() {
+    let server = setup_test_server().await;
+    let (a, b) = (server.connect().await, server.connect().await);
+
+    a.batch_execute("CREATE TABLE jobs (id INTEGER PRIMARY KEY)").await.unwrap();
+    a.batch_execute("BEGIN").await.unwrap();
+    a.simple_query("SELECT pg_advisory_xact_lock(7, 8)").await.unwrap();
+    assert_eq!(scalar(&a, "SELECT pg_try_advisory_xact_lock(9)").await.as_deref(), Some("t"));
+    a.batch_execute("INSERT INTO jobs VALUES (1)").await.unwrap();
+    assert_eq!(scalar(&b, "SELECT pg_try_advisory_lock(7, 8)").await.as_deref(), Some("f"));
+    assert_eq!(scalar(&b, "SELECT pg_try_advisory_lock(9)").await.as_deref(), Some("f"));
+    // pg_advisory_unlock() only releases session locks
+    assert_eq!(scalar(&a, "SELECT pg_advisory_unlock(7, 8)").await.as_deref(), Some("f"));
+    a.batch_execute("ROLLBACK").await.unwrap();
+    assert_eq!(scalar(&b, "SELECT pg_try_advisory_xact_lock(7, 8)").await.as_deref(), Some("t"));
+    assert_eq!(scalar(&b, "SELECT pg_try_advisory_lock(9)").await.as_deref(), Some("t"));
+}
+
+/// Waits end with an error on a deadlock, pg_cancel_backend() and statement_timeout
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_advisory_lock_waits_end() {
+    let server = setup_test_server().await;
+    let (a, b) = (server.connect().await, server.connect().await);
+
+    a.simple_query("SELECT pg_advisory_lock(10)").await.unwrap();
+    b.simple_query("SELECT pg_advisory_lock(11)").await.unwrap();
+    let waiter = tokio::spawn(async move {
+        a.simple_query("SELECT pg_advisory_lock(11)").await.unwrap();
+        a
+    });
+    assert_blocked(&waiter).await;
+    // A wait inside a larger statement, which SQLite runs
+    let err = b.simple_query("SELECT 1, pg_advisory_lock(10)").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::T_R_DEADLOCK_DETECTED), "{err:?}");
+    let err = b.simple_query("SELECT pg_advisory_lock(10)").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::T_R_DEADLOCK_DETECTED), "{err:?}");
+    assert_eq!(scalar(&b, "SELECT pg_advisory_unlock(11)").await.as_deref(), Some("t"));
+    let a = tokio::time::timeout(Duration::from_secs(10), waiter).await.unwrap().unwrap();
+
+    let mut b = b;
+    for query in ["SELECT pg_advisory_lock(10)", "SELECT 1, pg_advisory_lock(10)"] {
+        b.batch_execute("SET statement_timeout = '200ms'").await.unwrap();
+        let err = b.simple_query(query).await.unwrap_err();
+        assert_eq!(err.as_db_error().unwrap().message(), "canceling statement due to statement timeout", "{query}");
+        b.batch_execute("SET statement_timeout = 0").await.unwrap();
+
+        let pid = scalar(&b, "SELECT pg_backend_pid()").await.unwrap();
+        let waiter = tokio::spawn(async move {
+            let result = b.simple_query(query).await;
+            (b, result)
+        });
+        assert_blocked(&waiter).await;
+        assert_eq!(scalar(&a, &format!("SELECT pg_cancel_backend({pid})")).await.as_deref(), Some("t"));
+        let (client, result) = tokio::time::timeout(Duration::from_secs(10), waiter).await.unwrap().unwrap();
+        let err = result.unwrap_err();
+        assert_eq!(err.code(), Some(&SqlState::QUERY_CANCELED), "{query}: {err:?}");
+        b = client;
+    }
+
+    // Canceled waiters left the queue
+    assert_eq!(scalar(&a, "SELECT pg_advisory_unlock(10)").await.as_deref(), Some("t"));
+    assert_eq!(scalar(&b, "SELECT pg_try_advisory_lock(10)").await.as_deref(), Some("t"));
+}
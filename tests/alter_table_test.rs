@@ -0,0 +1,140 @@
+mod common;
+use common::*;
+use tokio_postgres::types::Type;
+
+async fn setup() -> TestServer {
+    let server = setup_test_server().await;
+    server.client.batch_execute(
+        "CREATE TABLE orders (
+            id SERIAL PRIMARY KEY,
+            customer VARCHAR(50) NOT NULL,
+            quantity INTEGER,
+            code TEXT UNIQUE
+        );
+        CREATE INDEX orders_quantity_idx ON orders (quantity);
+        INSERT INTO orders (customer, quantity, code) VALUES ('alice', 2, 'A1'), ('bob', 5, 'B2');"
+    ).await.unwrap();
+    server
+}
+
+fn error_code(error: &tokio_postgres::Error) -> &str {
+    error.as_db_error().map(|e| e.code().code()).unwrap_or_default()
+}
+
+#[tokio::test]
+async fn test_add_column_records_type_and_default() {
+    let server = setup().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "ALTER TABLE orders ADD COLUMN total NUMERIC(10,2) DEFAULT 0, ADD COLUMN note VARCHAR(5) NOT NULL DEFAULT 'none';
+         ALTER TABLE orders ADD COLUMN IF NOT EXISTS note TEXT"
+    ).await.unwrap();
+
+    let stmt = client.prepare("SELECT total, note FROM orders").await.unwrap();
+    let types: Vec<Type> = stmt.columns().iter().map(|c| c.type_().clone()).collect();
+    assert_eq!(types, [Type::NUMERIC, Type::VARCHAR]);
+
+    let row = client.query_one("SELECT total::text, note FROM orders WHERE customer = 'bob'", &[]).await.unwrap();
+    assert_eq!(row.get::<_, String>(0), "0.00");
+    assert_eq!(row.get::<_, String>(1), "none");
+
+    // The new column's length is enforced like one created with the table
+    let err = client.execute("INSERT INTO orders (customer, note) VALUES ('carol', 'too long')", &[]).await.unwrap_err();
+    assert_eq!(error_code(&err), "22001");
+
+    let err = client.batch_execute("ALTER TABLE orders ADD COLUMN customer TEXT").await.unwrap_err();
+    assert_eq!(error_code(&err), "42701");
+    let err = client.batch_execute("ALTER TABLE orders ADD COLUMN required INTEGER NOT NULL").await.unwrap_err();
+    assert_eq!(error_code(&err), "23502");
+}
+
+#[tokio::test]
+async fn test_drop_and_rename_columns() {
+    let server = setup().await;
+    let client = &server.client;
+
+    // code is UNIQUE, which SQLite can only drop by rebuilding the table
+    client.batch_execute("ALTER TABLE orders DROP COLUMN code, DROP COLUMN IF EXISTS missing").await.unwrap();
+    client.execute("ALTER TABLE orders RENAME COLUMN quantity TO amount", &[]).await.unwrap();
+    client.batch_execute("ALTER TABLE orders RENAME TO purchases").await.unwrap();
+
+    let stmt = client.prepare("SELECT * FROM purchases").await.unwrap();
+    let columns: Vec<(&str, Type)> = stmt.columns().iter().map(|c| (c.name(), c.type_().clone())).collect();
+    assert_eq!(columns, [("id", Type::INT4), ("customer", Type::VARCHAR), ("amount", Type::INT4)]);
+
+    // The serial keeps counting after the rebuild
+    let id: i32 = client.query_one("INSERT INTO purchases (customer, amount) VALUES ('carol', 1) RETURNING id", &[]).await.unwrap().get(0);
+    assert_eq!(id, 3);
+
+    let err = client.batch_execute("ALTER TABLE purchases DROP COLUMN code").await.unwrap_err();
+    assert_eq!(error_code(&err), "42703");
+    let err = client.batch_execute("ALTER TABLE orders RENAME TO purchases").await.unwrap_err();
+    assert_eq!(error_code(&err), "42P01");
+    client.batch_execute("ALTER TABLE IF EXISTS orders DROP COLUMN code").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_alter_column_type_and_constraints() {
+    let server = setup().await;
+    let client = &server.client;
+
+    client.batch_execute("ALTER TABLE orders ALTER COLUMN quantity TYPE BIGINT").await.unwrap();
+    client.batch_execute("ALTER TABLE orders ALTER COLUMN code TYPE VARCHAR(2)").await.unwrap();
+    let stmt = client.prepare("SELECT quantity, code FROM orders").await.unwrap();
+    let types: Vec<Type> = stmt.columns().iter().map(|c| c.type_().clone()).collect();
+    assert_eq!(types, [Type::INT8, Type::VARCHAR]);
+
+    let err = client.batch_execute("ALTER TABLE orders ALTER COLUMN code TYPE VARCHAR(1)").await.unwrap_err();
+    assert_eq!(error_code(&err), "22001");
+    let err = client.batch_execute("ALTER TABLE orders ALTER COLUMN code TYPE INTEGER").await.unwrap_err();
+    assert_eq!(error_code(&err), "42804");
+
+    client.batch_execute(
+        "ALTER TABLE orders ALTER COLUMN quantity SET DEFAULT 1, ALTER COLUMN customer DROP NOT NULL;
+         INSERT INTO orders (code) VALUES ('C3')"
+    ).await.unwrap();
+    let row = client.query_one("SELECT customer, quantity FROM orders WHERE code = 'C3'", &[]).await.unwrap();
+    assert_eq!(row.get::<_, Option<String>>(0), None);
+    assert_eq!(row.get::<_, i64>(1), 1);
+
+    let err = client.batch_execute("ALTER TABLE orders ALTER COLUMN customer SET NOT NULL").await.unwrap_err();
+    assert_eq!(error_code(&err), "23502");
+
+    // Indexes survive the rebuilds
+    let indexes: i64 = client.query_one(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'index' AND name = 'orders_quantity_idx'", &[]
+    ).await.unwrap().get(0);
+    assert_eq!(indexes, 1);
+}
+
+#[tokio::test]
+async fn test_prepared_statements_after_alter_table() {
+    let server = setup().await;
+    let client = &server.client;
+
+    let all = client.prepare("SELECT * FROM orders WHERE id = $1").await.unwrap();
+    let quantity = client.prepare("SELECT customer, quantity FROM orders WHERE id = $1").await.unwrap();
+    let customer = client.prepare("SELECT customer FROM orders WHERE id = $1").await.unwrap();
+    client.query_one(&all, &[&1i32]).await.unwrap();
+
+    // A column that none of the statements return changes nothing for them
+    client.batch_execute("ALTER TABLE orders DROP COLUMN code").await.unwrap();
+    let row = client.query_one(&quantity, &[&2i32]).await.unwrap();
+    assert_eq!(row.get::<_, i32>(1), 5);
+
+    // Statements whose result columns changed fail until prepared again
+    client.batch_execute("ALTER TABLE orders ADD COLUMN note TEXT, ALTER COLUMN quantity TYPE TEXT").await.unwrap();
+    let err = client.query_one(&all, &[&1i32]).await.unwrap_err();
+    assert_eq!(error_code(&err), "0A000");
+    assert!(err.to_string().contains("cached plan must not change result type"), "{err}");
+    let err = client.query_one(&quantity, &[&1i32]).await.unwrap_err();
+    assert_eq!(error_code(&err), "0A000");
+    let row = client.query_one(&customer, &[&1i32]).await.unwrap();
+    assert_eq!(row.get::<_, String>(0), "alice");
+
+    let all = client.prepare("SELECT * FROM orders WHERE id = $1").await.unwrap();
+    assert_eq!(all.columns().len(), 4);
+    let row = client.query_one(&all, &[&2i32]).await.unwrap();
+    assert_eq!(row.get::<_, String>(2), "5");
+}
@@ -270,9 +270,9 @@ async fn test_arithmetic_integer_columns() {
     // Arithmetic on integer columns should work
     let rows = client.query("SELECT quantity * price AS total_value FROM inventory WHERE id = 1", &[]).await.unwrap();
     assert_eq!(rows.len(), 1);
-    // With alias, we detect arithmetic and set FLOAT8 type, so we need to read as f64
-    let total: f64 = rows[0].get(0);
-    assert!((total - 250.0).abs() < 0.01);
+    // INTEGER * INTEGER is INTEGER, as in PostgreSQL
+    let total: i32 = rows[0].get(0);
+    assert_eq!(total, 250);
     
     // Division might return float
     // TODO: This test fails because CAST translation tries to use get_mut_connection
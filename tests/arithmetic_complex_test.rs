@@ -213,17 +213,17 @@ async fn test_mixed_type_arithmetic() {
     let result: f64 = rows[0].get(0);
     assert!((result - 12.5).abs() < 0.01);
     
-    // Test INTEGER * DECIMAL
+    // Test INTEGER * DECIMAL, which is NUMERIC as in PostgreSQL
     let rows = client.query("SELECT int_col * decimal_col AS int_times_decimal FROM mixed WHERE id = 1", &[]).await.unwrap();
     assert_eq!(rows.len(), 1);
-    let result: f64 = rows[0].get(0);
-    assert!((result - 1000.0).abs() < 0.01);
+    let result: rust_decimal::Decimal = rows[0].get(0);
+    assert_eq!(result, rust_decimal::Decimal::from(1000));
     
-    // Test REAL / INTEGER
+    // Test DECIMAL / INTEGER
     let rows = client.query("SELECT decimal_col / int_col AS decimal_div_int FROM mixed WHERE id = 1", &[]).await.unwrap();
     assert_eq!(rows.len(), 1);
-    let result: f64 = rows[0].get(0);
-    assert!((result - 10.0).abs() < 0.01);
+    let result: rust_decimal::Decimal = rows[0].get(0);
+    assert_eq!(result, rust_decimal::Decimal::from(10));
     
     server_handle.abort();
 }
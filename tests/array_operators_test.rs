@@ -209,8 +209,8 @@ async fn test_array_functions() {
         "SELECT array_positions('[1, 2, 3, 2, 4, 2]', 2)",
         &[]
     ).await.unwrap();
-    let positions: String = row.get(0);
-    assert_eq!(positions, "[2,4,6]"); // 1-based indices
+    let positions: Vec<i32> = row.get(0);
+    assert_eq!(positions, vec![2, 4, 6]); // 1-based indices
     
     server.abort();
 }
@@ -247,12 +247,29 @@ async fn test_array_aggregation() {
         "SELECT array_agg(DISTINCT product ORDER BY product) AS products FROM sales",
         &[]
     ).await.unwrap();
-    let products: String = row.get(0);
+    let products: Vec<String> = row.get(0);
     // Should contain all distinct products in order
-    assert!(products.contains("Laptop"));
-    assert!(products.contains("Phone"));
-    assert!(products.contains("Tablet"));
-    
+    assert_eq!(products, vec!["Laptop", "Phone", "Tablet"]);
+
+    // ORDER BY inside the aggregate orders its elements, with and without DISTINCT
+    let row = client.query_one(
+        "SELECT array_agg(id ORDER BY id DESC) AS ids,
+                array_agg(DISTINCT product ORDER BY product DESC) AS products
+         FROM sales",
+        &[]
+    ).await.unwrap();
+    assert_eq!(row.get::<_, Vec<i32>>(0), vec![5, 4, 3, 2, 1]);
+    assert_eq!(row.get::<_, Vec<String>>(1), vec!["Tablet", "Phone", "Laptop"]);
+
+    let rows = client.simple_query(
+        "SELECT product, array_agg(amount ORDER BY amount DESC) FROM sales GROUP BY product ORDER BY product"
+    ).await.unwrap();
+    let amounts: Vec<&str> = rows.iter().filter_map(|m| match m {
+        tokio_postgres::SimpleQueryMessage::Row(row) => row.get(1),
+        _ => None,
+    }).collect();
+    assert_eq!(amounts, vec!["{1500,1200}", "{900,800}", "{600}"]);
+
     // Test array_agg with GROUP BY
     let rows = client.query(
         "SELECT product, array_agg(amount) AS amounts 
@@ -299,6 +316,82 @@ async fn test_array_slice() {
     server.abort();
 }
 
+#[tokio::test]
+async fn test_array_functions_null_and_empty() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+    
+    client.execute("CREATE TABLE scores (id INTEGER PRIMARY KEY, points INTEGER[])", &[]).await.unwrap();
+    client.simple_query(
+        "INSERT INTO scores (id, points) VALUES (1, '{3,1,null,1}'), (2, '{}'), (3, NULL)"
+    ).await.unwrap();
+    
+    let rows = client.simple_query(
+        "SELECT cardinality(points), array_length(points, 1), array_position(points, 1), \
+                array_to_string(points, ',', '-') FROM scores ORDER BY id"
+    ).await.unwrap();
+    let values: Vec<Vec<Option<&str>>> = rows.iter()
+        .filter_map(|msg| match msg {
+            tokio_postgres::SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i)).collect()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(values, vec![
+        vec![Some("4"), Some("4"), Some("2"), Some("3,1,-,1")],
+        vec![Some("0"), None, None, Some("")],
+        vec![None, None, None, None],
+    ]);
+    
+    // Appending to a NULL array starts a new one; removing NULLs works
+    client.simple_query("UPDATE scores SET points = array_append(points, 7) WHERE id = 3").await.unwrap();
+    let row = client.query_one(
+        "SELECT array_remove(points, NULL), array_positions(points, 1) FROM scores WHERE id = 1",
+        &[]
+    ).await.unwrap();
+    assert_eq!(row.get::<_, Vec<i32>>(0), vec![3, 1, 1]);
+    assert_eq!(row.get::<_, Vec<i32>>(1), vec![2, 4]);
+    let row = client.query_one("SELECT cardinality(points) AS n FROM scores WHERE id = 3", &[]).await.unwrap();
+    assert_eq!(row.get::<_, i32>(0), 1);
+    
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_array_functions_keep_array_type() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+    
+    client.execute("CREATE TABLE lists (id INTEGER PRIMARY KEY, nums INTEGER[], tags TEXT[])", &[]).await.unwrap();
+    client.simple_query("INSERT INTO lists (id, nums, tags) VALUES (1, '{1,2,3}', '{x,y}')").await.unwrap();
+    
+    // Results print as array literals in the text protocol
+    let rows = client.simple_query(
+        "SELECT array_append(nums, 4), array_prepend(0, nums), array_cat(nums, nums), array_remove(nums, 2), \
+                array_replace(tags, 'x', 'z'), nums[2:3] FROM lists"
+    ).await.unwrap();
+    let values: Vec<Vec<Option<&str>>> = rows.iter()
+        .filter_map(|msg| match msg {
+            tokio_postgres::SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i)).collect()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(values, vec![vec![
+        Some("{1,2,3,4}"), Some("{0,1,2,3}"), Some("{1,2,3,1,2,3}"), Some("{1,3}"), Some(r#"{"z","y"}"#), Some("{2,3}"),
+    ]]);
+    
+    // and are typed as the argument's array type
+    let row = client.query_one(
+        "SELECT array_append(nums, 4), array_remove(nums, 2) AS removed, array_cat(tags, tags), nums[2:3] FROM lists",
+        &[]
+    ).await.unwrap();
+    assert_eq!(row.get::<_, Vec<i32>>(0), vec![1, 2, 3, 4]);
+    assert_eq!(row.get::<_, Vec<i32>>(1), vec![1, 3]);
+    assert_eq!(row.get::<_, Vec<String>>(2), vec!["x", "y", "x", "y"]);
+    assert_eq!(row.get::<_, Vec<i32>>(3), vec![2, 3]);
+    
+    server.abort();
+}
+
 #[tokio::test]
 #[ignore] // TODO: Fix unnest integration test - translation not working in test environment
 async fn test_unnest_with_ordinality() {
@@ -369,4 +462,52 @@ async fn test_unnest_with_ordinality() {
     assert_eq!(val2, "d");
     
     server.abort();
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_array_operators_with_parameters() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TABLE items (id INTEGER PRIMARY KEY, tags TEXT[], nums INTEGER[])", &[]).await.unwrap();
+    client.simple_query("INSERT INTO items VALUES (1, '{a,b}', '{1,2,3}'), (2, '{b,c}', '{4,5}'), (3, '{}', '{}')").await.unwrap();
+
+    let ids = |rows: Vec<tokio_postgres::Row>| rows.iter().map(|r| r.get::<_, i32>(0)).collect::<Vec<_>>();
+
+    // x = ANY($1) / x <> ALL($1) with an array parameter, the common ORM pattern
+    let rows = client.query("SELECT id FROM items WHERE id = ANY($1) ORDER BY id", &[&vec![1i32, 3]]).await.unwrap();
+    assert_eq!(ids(rows), vec![1, 3]);
+    let rows = client.query("SELECT id FROM items WHERE id <> ALL($1) ORDER BY id", &[&vec![1i32, 3]]).await.unwrap();
+    assert_eq!(ids(rows), vec![2]);
+
+    // Containment and overlap against parameters
+    let rows = client.query("SELECT id FROM items WHERE nums @> $1", &[&vec![4i32]]).await.unwrap();
+    assert_eq!(ids(rows), vec![2]);
+    let rows = client.query("SELECT id FROM items WHERE nums && $1 ORDER BY id", &[&vec![3i32, 4]]).await.unwrap();
+    assert_eq!(ids(rows), vec![1, 2]);
+    let rows = client.query("SELECT id FROM items WHERE nums <@ $1 ORDER BY id", &[&vec![1i32, 2, 3, 9]]).await.unwrap();
+    assert_eq!(ids(rows), vec![1, 3]);
+
+    // PostgreSQL array literals, ANY with whitespace and other comparison operators
+    let simple_ids = |messages: Vec<tokio_postgres::SimpleQueryMessage>| -> Vec<String> {
+        messages.iter().filter_map(|m| match m {
+            tokio_postgres::SimpleQueryMessage::Row(row) => row.get(0).map(str::to_string),
+            _ => None,
+        }).collect()
+    };
+    let cases = [
+        ("SELECT id FROM items WHERE tags @> '{b}' ORDER BY id", vec!["1", "2"]),
+        ("SELECT id FROM items WHERE tags && '{c,z}'", vec!["2"]),
+        ("SELECT id FROM items WHERE 'a' = ANY (tags)", vec!["1"]),
+        ("SELECT id FROM items WHERE id = ANY(ARRAY[1,2]) ORDER BY id", vec!["1", "2"]),
+        ("SELECT id FROM items WHERE id = ANY('{1,2}') ORDER BY id", vec!["1", "2"]),
+        ("SELECT id FROM items WHERE 4 > ANY(nums)", vec!["1"]),
+        ("SELECT id FROM items WHERE 3 <> ALL(nums) ORDER BY id", vec!["2", "3"]),
+    ];
+    for (query, expected) in cases {
+        let messages = client.simple_query(query).await.unwrap();
+        assert_eq!(simple_ids(messages), expected, "{query}");
+    }
+
+    server.abort();
+}
@@ -0,0 +1,117 @@
+mod common;
+use common::setup_test_server;
+
+#[tokio::test]
+async fn test_array_parameters_round_trip() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE TABLE array_items (
+            id INTEGER PRIMARY KEY,
+            nums INTEGER[],
+            tags TEXT[],
+            big BIGINT[],
+            scores FLOAT8[],
+            flags BOOLEAN[]
+        )"
+    ).await.unwrap();
+
+    let tags = vec!["plain".to_string(), "with space".to_string(), "a,b".to_string(), "quote\"back\\slash".to_string(), "{brace}".to_string()];
+    client.execute(
+        "INSERT INTO array_items (id, nums, tags, big, scores, flags) VALUES ($1, $2, $3, $4, $5, $6)",
+        &[&1i32, &vec![1i32, 2, 3], &tags, &vec![1i64 << 40], &vec![1.5f64, -2.25], &vec![true, false]],
+    ).await.unwrap();
+
+    let row = client.query_one("SELECT nums, tags, big, scores, flags FROM array_items WHERE id = 1", &[]).await.unwrap();
+    assert_eq!(row.get::<_, Vec<i32>>(0), vec![1, 2, 3]);
+    assert_eq!(row.get::<_, Vec<String>>(1), tags);
+    assert_eq!(row.get::<_, Vec<i64>>(2), vec![1i64 << 40]);
+    assert_eq!(row.get::<_, Vec<f64>>(3), vec![1.5, -2.25]);
+    assert_eq!(row.get::<_, Vec<bool>>(4), vec![true, false]);
+
+    // NULL elements, empty arrays and NULL arrays
+    client.execute(
+        "INSERT INTO array_items (id, nums, tags) VALUES ($1, $2, $3)",
+        &[&2i32, &vec![Some(7i32), None], &Vec::<String>::new()],
+    ).await.unwrap();
+
+    let row = client.query_one("SELECT nums, tags, big FROM array_items WHERE id = 2", &[]).await.unwrap();
+    assert_eq!(row.get::<_, Vec<Option<i32>>>(0), vec![Some(7), None]);
+    assert!(row.get::<_, Vec<String>>(1).is_empty());
+    assert_eq!(row.get::<_, Option<Vec<i64>>>(2), None);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_array_literals_in_text_protocol() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE TABLE array_literals (id INTEGER PRIMARY KEY, nums INTEGER[], tags TEXT[], grid INTEGER[][]);
+         INSERT INTO array_literals (id, nums, tags, grid) VALUES (1, '{1,2,3}', '{\"a b\",c,NULL}', '{{1,2},{3,4}}');"
+    ).await.unwrap();
+
+    // Simple protocol returns PostgreSQL array literals rather than the stored JSON
+    let rows = client.simple_query("SELECT nums, tags, grid FROM array_literals").await.unwrap();
+    let values: Vec<(String, String, String)> = rows.iter().filter_map(|msg| match msg {
+        tokio_postgres::SimpleQueryMessage::Row(row) => Some((
+            row.get(0).unwrap().to_string(),
+            row.get(1).unwrap().to_string(),
+            row.get(2).unwrap().to_string(),
+        )),
+        _ => None,
+    }).collect();
+    assert_eq!(values, vec![("{1,2,3}".to_string(), r#"{"a b","c",NULL}"#.to_string(), "{{1,2},{3,4}}".to_string())]);
+
+    // Extended protocol reports array OIDs and sends binary arrays
+    let stmt = client.prepare("SELECT nums, tags, grid FROM array_literals").await.unwrap();
+    assert_eq!(stmt.columns()[0].type_(), &tokio_postgres::types::Type::INT4_ARRAY);
+    assert_eq!(stmt.columns()[1].type_(), &tokio_postgres::types::Type::TEXT_ARRAY);
+    assert_eq!(stmt.columns()[2].type_(), &tokio_postgres::types::Type::INT4_ARRAY);
+
+    let row = client.query_one(&stmt, &[]).await.unwrap();
+    assert_eq!(row.get::<_, Vec<i32>>(0), vec![1, 2, 3]);
+    assert_eq!(row.get::<_, Vec<Option<String>>>(1), vec![Some("a b".to_string()), Some("c".to_string()), None]);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_array_expressions() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE TABLE array_sources (id INTEGER PRIMARY KEY, name TEXT);
+         INSERT INTO array_sources (id, name) VALUES (1, 'one'), (2, 'two');"
+    ).await.unwrap();
+
+    // Aggregates, casts and array-returning functions print as array literals
+    let rows = client.simple_query(
+        "SELECT array_agg(id), '{}'::int[], '{4,5}'::int[], string_to_array('x,y', ',') FROM array_sources"
+    ).await.unwrap();
+    let values: Vec<Vec<String>> = rows.iter().filter_map(|msg| match msg {
+        tokio_postgres::SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).unwrap().to_string()).collect()),
+        _ => None,
+    }).collect();
+    assert_eq!(values, vec![vec!["{1,2}".to_string(), "{}".to_string(), "{4,5}".to_string(), r#"{"x","y"}"#.to_string()]]);
+
+    // Invalid array input is a 22P02
+    let err = client.simple_query("SELECT '{1,x}'::int[]").await.unwrap_err();
+    assert_eq!(err.as_db_error().unwrap().code(), &tokio_postgres::error::SqlState::INVALID_TEXT_REPRESENTATION);
+
+    // Extended protocol reports the array types
+    let stmt = client.prepare("SELECT array_agg(id) AS ids, array_agg(name), '{}'::int[] FROM array_sources").await.unwrap();
+    assert_eq!(stmt.columns()[0].type_(), &tokio_postgres::types::Type::INT4_ARRAY);
+    assert_eq!(stmt.columns()[1].type_(), &tokio_postgres::types::Type::TEXT_ARRAY);
+    assert_eq!(stmt.columns()[2].type_(), &tokio_postgres::types::Type::INT4_ARRAY);
+    let row = client.query_one(&stmt, &[]).await.unwrap();
+    assert_eq!(row.get::<_, Vec<i32>>(0), vec![1, 2]);
+    assert_eq!(row.get::<_, Vec<String>>(1), vec!["one".to_string(), "two".to_string()]);
+    assert!(row.get::<_, Vec<i32>>(2).is_empty());
+
+    server.abort();
+}
@@ -0,0 +1,68 @@
+mod common;
+use common::setup_test_server;
+use tokio_postgres::SimpleQueryMessage;
+
+async fn query_row(client: &tokio_postgres::Client, sql: &str) -> Vec<Option<String>> {
+    client.simple_query(sql).await.unwrap().iter()
+        .find_map(|msg| match msg {
+            SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).map(|s| s.to_string())).collect()),
+            _ => None,
+        })
+        .unwrap()
+}
+
+fn s(value: &str) -> Option<String> {
+    Some(value.to_string())
+}
+
+#[tokio::test]
+async fn test_array_subscripts_and_slices() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TABLE series (id INTEGER PRIMARY KEY, tags TEXT[], vals INTEGER[], grid INTEGER[][])", &[]).await.unwrap();
+    client.simple_query(
+        "INSERT INTO series (id, tags, vals, grid) VALUES (1, '{red,green,blue}', '{10,20,30,40,50}', '{{1,2},{3,4}}')"
+    ).await.unwrap();
+
+    // Literal, out of range, multi-dimensional and computed subscripts
+    let row = query_row(client, "SELECT tags[2], tags[9], grid[2][1], vals[id + 1] FROM series WHERE id = 1").await;
+    assert_eq!(row, vec![s("green"), None, s("3"), s("20")]);
+
+    // Slices with both, lower-only and upper-only bounds
+    let row = query_row(client, "SELECT vals[2:3], vals[4:], vals[:2] FROM series WHERE id = 1").await;
+    assert_eq!(row, vec![s("{20,30}"), s("{40,50}"), s("{10,20}")]);
+
+    // Subscripts inside string literals are left alone
+    let row = query_row(client, "SELECT 'tags[1]' FROM series WHERE vals[1] = 10").await;
+    assert_eq!(row, vec![s("tags[1]")]);
+
+    // Extended protocol with a bound subscript
+    let row = client.query_one("SELECT tags[$1] FROM series WHERE id = 1", &[&3i32]).await.unwrap();
+    assert_eq!(row.get::<_, String>(0), "blue");
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_array_subscript_update() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TABLE series (id INTEGER PRIMARY KEY, tags TEXT[], vals INTEGER[])", &[]).await.unwrap();
+    client.simple_query("INSERT INTO series (id, tags, vals) VALUES (1, '{red,green,blue}', '{10,20,30}'), (2, NULL, NULL)").await.unwrap();
+
+    client.simple_query("UPDATE series SET tags[2] = 'teal', vals[1] = vals[1] + 5 WHERE id = 1").await.unwrap();
+    client.execute("UPDATE series SET vals[$1] = $2 WHERE id = 1", &[&5i32, &99i32]).await.unwrap();
+    let row = query_row(client, "SELECT tags, vals FROM series WHERE id = 1").await;
+    assert_eq!(row, vec![s(r#"{"red","teal","blue"}"#), s("{15,20,30,NULL,99}")]);
+
+    // Assigning into a NULL array creates it
+    client.simple_query("UPDATE series SET tags[1] = 'first' WHERE id = 2").await.unwrap();
+    let row = query_row(client, "SELECT tags FROM series WHERE id = 2").await;
+    assert_eq!(row, vec![s(r#"{"first"}"#)]);
+
+    assert!(client.simple_query("UPDATE series SET vals[0] = 1 WHERE id = 1").await.is_err());
+
+    server.abort();
+}
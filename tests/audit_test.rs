@@ -0,0 +1,106 @@
+mod common;
+use common::setup_test_server;
+use serde_json::{Value, json};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::{Client, SimpleQueryMessage};
+
+async fn rows(client: &Client, sql: &str) -> Vec<Vec<Option<String>>> {
+    client.simple_query(sql).await.unwrap().into_iter()
+        .filter_map(|message| match message {
+            SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).map(str::to_string)).collect()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn parse_json(text: &Option<String>) -> Value {
+    text.as_deref().map_or(Value::Null, |text| serde_json::from_str(text).unwrap())
+}
+
+/// Inserts, updates and deletes are recorded with the old and new rows and the session that made them
+#[tokio::test]
+async fn test_enable_audit_records_changes() {
+    let server = setup_test_server().await;
+    let alice = server.connect_with("user=alice application_name=billing").await.unwrap();
+    let bob = server.connect_with("user=bob application_name=reports").await.unwrap();
+
+    alice.batch_execute(
+        "CREATE TABLE accounts (id INTEGER PRIMARY KEY, owner TEXT, active BOOLEAN, opened DATE, tags TEXT[], photo BYTEA)"
+    ).await.unwrap();
+    assert_eq!(rows(&alice, "SELECT pgsqlite.enable_audit('accounts')").await, vec![vec![Some("accounts_audit".to_string())]]);
+
+    // unixepoch('subsec') has millisecond precision
+    let started = chrono::Utc::now().naive_utc() - chrono::Duration::milliseconds(1);
+    alice.batch_execute("INSERT INTO accounts VALUES (1, 'Ada', true, '2024-01-02', ARRAY['vip'], '\\xdead')").await.unwrap();
+    bob.execute("UPDATE accounts SET owner = $1, active = false WHERE id = 1", &[&"Grace"]).await.unwrap();
+    alice.batch_execute("DELETE FROM accounts WHERE id = 1").await.unwrap();
+
+    let audit = rows(&alice, "SELECT operation, old_row, new_row, actor, application_name FROM accounts_audit ORDER BY audit_id").await;
+    let ada = json!({"id": 1, "owner": "Ada", "active": true, "opened": "2024-01-02", "tags": ["vip"], "photo": "\\xdead"});
+    let grace = json!({"id": 1, "owner": "Grace", "active": false, "opened": "2024-01-02", "tags": ["vip"], "photo": "\\xdead"});
+    let summary: Vec<_> = audit.iter()
+        .map(|row| (row[0].clone().unwrap(), parse_json(&row[1]), parse_json(&row[2]), row[3].clone().unwrap(), row[4].clone().unwrap()))
+        .collect();
+    assert_eq!(summary, vec![
+        ("INSERT".to_string(), Value::Null, ada.clone(), "alice".to_string(), "billing".to_string()),
+        ("UPDATE".to_string(), ada, grace.clone(), "bob".to_string(), "reports".to_string()),
+        ("DELETE".to_string(), grace, Value::Null, "alice".to_string(), "billing".to_string()),
+    ]);
+
+    // JSON operators read into the recorded rows, and each change has the time it was made
+    assert_eq!(
+        rows(&bob, "SELECT new_row->>'owner' FROM accounts_audit WHERE operation = 'UPDATE'").await,
+        vec![vec![Some("Grace".to_string())]]
+    );
+    let finished = chrono::Utc::now().naive_utc();
+    for row in rows(&bob, "SELECT changed_at FROM accounts_audit").await {
+        let changed_at = chrono::NaiveDateTime::parse_from_str(row[0].as_deref().unwrap(), "%Y-%m-%d %H:%M:%S%.f").unwrap();
+        assert!(started <= changed_at && changed_at <= finished, "{changed_at} not between {started} and {finished}");
+    }
+
+    // A rolled back change leaves no audit row
+    alice.batch_execute("BEGIN; INSERT INTO accounts (id, owner) VALUES (2, 'Linus'); ROLLBACK").await.unwrap();
+    assert_eq!(rows(&alice, "SELECT COUNT(*) FROM accounts_audit").await, vec![vec![Some("3".to_string())]]);
+}
+
+/// Audit triggers follow ALTER TABLE, and dropping either table stops the auditing
+#[tokio::test]
+async fn test_audit_follows_schema_changes() {
+    let server = setup_test_server().await;
+    let client = server.connect_with("user=postgres application_name=migrations").await.unwrap();
+
+    client.batch_execute("CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)").await.unwrap();
+    client.batch_execute("SELECT pgsqlite.enable_audit('public.items')").await.unwrap();
+
+    // New columns appear in the rows recorded afterwards, also once the table is renamed
+    client.batch_execute("ALTER TABLE items ADD COLUMN price INTEGER DEFAULT 5").await.unwrap();
+    client.batch_execute("INSERT INTO items (id, name) VALUES (1, 'pen')").await.unwrap();
+    client.batch_execute("ALTER TABLE items RENAME TO products").await.unwrap();
+    client.batch_execute("UPDATE products SET price = 7").await.unwrap();
+    let audit = rows(&client, "SELECT new_row FROM items_audit ORDER BY audit_id").await;
+    assert_eq!(audit.iter().map(|row| parse_json(&row[0])).collect::<Vec<_>>(), vec![
+        json!({"id": 1, "name": "pen", "price": 5}),
+        json!({"id": 1, "name": "pen", "price": 7}),
+    ]);
+
+    // Enabling it again keeps the audit table and its rows
+    let row = client.query_one("SELECT pgsqlite.enable_audit('products')", &[]).await.unwrap();
+    assert_eq!(row.get::<_, String>("enable_audit"), "items_audit");
+    assert_eq!(rows(&client, "SELECT COUNT(*) FROM items_audit").await, vec![vec![Some("2".to_string())]]);
+
+    // An audit table isn't audited itself, and the table has to exist
+    let err = client.simple_query("SELECT pgsqlite.enable_audit('items_audit')").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::WRONG_OBJECT_TYPE));
+    let err = client.simple_query("SELECT pgsqlite.enable_audit('missing')").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::UNDEFINED_TABLE));
+
+    // Writes keep working once the audit table is gone
+    client.batch_execute("DROP TABLE items_audit").await.unwrap();
+    client.batch_execute("DELETE FROM products").await.unwrap();
+
+    // Dropping an audited table keeps its audit table
+    client.batch_execute("CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT)").await.unwrap();
+    client.batch_execute("SELECT pgsqlite.enable_audit('notes'); INSERT INTO notes VALUES (1, 'hi')").await.unwrap();
+    client.batch_execute("DROP TABLE notes").await.unwrap();
+    assert_eq!(rows(&client, "SELECT operation FROM notes_audit").await, vec![vec![Some("INSERT".to_string())]]);
+}
@@ -0,0 +1,70 @@
+mod common;
+use common::setup_test_server;
+use tokio_postgres::SimpleQueryMessage;
+
+async fn column(client: &tokio_postgres::Client, query: &str) -> Vec<String> {
+    client.simple_query(query).await.unwrap().into_iter()
+        .filter_map(|message| match message {
+            SimpleQueryMessage::Row(row) => Some(row.get(0).unwrap_or("NULL").to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+const ANALYZED: &str = "SELECT DISTINCT tbl FROM sqlite_stat1 ORDER BY tbl";
+const AUTOANALYZED: &str = "SELECT relname || ':' || autoanalyze_count || ':' || (last_autoanalyze IS NOT NULL)
+    FROM pg_stat_user_tables WHERE autoanalyze_count > 0 ORDER BY relname";
+
+#[tokio::test]
+async fn test_auto_analyze_on_first_complex_query() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    // 1000 rows is the default --auto-analyze-min-rows
+    client.batch_execute(
+        "CREATE TABLE customers (id INTEGER PRIMARY KEY, region TEXT);
+         CREATE TABLE orders (id INTEGER PRIMARY KEY, customer_id INTEGER, total REAL);
+         CREATE INDEX orders_customer ON orders (customer_id);
+         CREATE TABLE refunds (id INTEGER PRIMARY KEY, order_id INTEGER);
+         INSERT INTO customers VALUES (1, 'north'), (2, 'south');
+         INSERT INTO orders (customer_id, total)
+             WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 1500)
+             SELECT i % 2 + 1, i FROM n;
+         INSERT INTO refunds (order_id)
+             WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 1200)
+             SELECT i FROM n",
+    ).await.unwrap();
+
+    // A lookup on one table is left alone
+    assert_eq!(column(client, "SELECT count(*) FROM orders WHERE customer_id = 1").await, vec!["750"]);
+    assert!(column(client, "SELECT name FROM sqlite_master WHERE name = 'sqlite_stat1'").await.is_empty());
+
+    // A join analyzes the large tables it reads; the small one waits until it has grown
+    assert_eq!(
+        column(client, "SELECT c.region, sum(o.total) FROM orders o JOIN customers c ON c.id = o.customer_id GROUP BY c.region ORDER BY 1").await,
+        vec!["north", "south"]
+    );
+    assert_eq!(column(client, ANALYZED).await, vec!["orders"]);
+    assert_eq!(column(client, AUTOANALYZED).await, vec!["orders:1:1"]);
+
+    // Analyzed tables aren't analyzed again, and the extended protocol analyzes too
+    let rows = client.query(
+        "SELECT count(*) FROM orders WHERE id IN (SELECT order_id FROM refunds WHERE order_id > $1::int8)",
+        &[&100i64],
+    ).await.unwrap();
+    assert_eq!(rows[0].get::<_, i64>(0), 1100);
+    assert_eq!(column(client, AUTOANALYZED).await, vec!["orders:1:1", "refunds:1:1"]);
+
+    // A table analyzed by hand is left alone, and the record follows a renamed table
+    client.batch_execute(
+        "CREATE TABLE events (id INTEGER PRIMARY KEY);
+         INSERT INTO events (id) WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 1000) SELECT i FROM n;
+         ANALYZE events;
+         ALTER TABLE refunds RENAME TO returns;
+         DROP TABLE orders",
+    ).await.unwrap();
+    column(client, "SELECT count(*) FROM events e, returns r WHERE e.id = r.order_id").await;
+    assert_eq!(column(client, AUTOANALYZED).await, vec!["returns:1:1"]);
+
+    server.abort();
+}
@@ -0,0 +1,71 @@
+mod common;
+use common::setup_test_server;
+use std::time::{Duration, Instant};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::{Client, SimpleQueryMessage};
+
+async fn scalar(client: &Client, sql: &str) -> Option<String> {
+    client.simple_query(sql).await.unwrap().into_iter().find_map(|msg| match msg {
+        SimpleQueryMessage::Row(row) => Some(row.get(0).map(str::to_string)),
+        _ => None,
+    }).flatten()
+}
+
+// Runs until interrupted
+const LONG_QUERY: &str = "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 2000000000) SELECT count(*) FROM n";
+
+/// pg_cancel_backend() aborts another session's statement, pg_terminate_backend() closes it
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_cancel_and_terminate_backend() {
+    let server = setup_test_server().await;
+    let worker = server.connect_with("application_name=worker").await.unwrap();
+    let admin = server.connect_with("application_name=admin").await.unwrap();
+
+    // Each session reports its own pid, and pg_stat_activity lists both
+    let pid: i32 = scalar(&worker, "SELECT pg_backend_pid()").await.unwrap().parse().unwrap();
+    let admin_pid: i32 = scalar(&admin, "SELECT pg_backend_pid()").await.unwrap().parse().unwrap();
+    assert_ne!(pid, admin_pid);
+    let listed = scalar(&admin, &format!("SELECT application_name FROM pg_stat_activity WHERE pid = {pid}")).await;
+    assert_eq!(listed.as_deref(), Some("worker"));
+
+    // Unknown pids are reported as not signalled
+    assert_eq!(scalar(&admin, "SELECT pg_cancel_backend(-1)").await.as_deref(), Some("f"));
+    assert_eq!(scalar(&admin, "SELECT pg_terminate_backend(-1)").await.as_deref(), Some("f"));
+
+    // Cancel the running statement; the session stays usable
+    let running = tokio::spawn(async move {
+        let result = worker.simple_query(LONG_QUERY).await;
+        (worker, result)
+    });
+    wait_for_state(&admin, pid, "active").await;
+    assert_eq!(scalar(&admin, &format!("SELECT pg_cancel_backend({pid})")).await.as_deref(), Some("t"));
+    let (worker, result) = running.await.unwrap();
+    let err = result.err().expect("statement should be canceled");
+    assert_eq!(err.code(), Some(&SqlState::QUERY_CANCELED), "unexpected error: {err:?}");
+    assert_eq!(scalar(&worker, "SELECT 1").await.as_deref(), Some("1"));
+
+    // Canceling an idle session leaves its next statement alone
+    assert_eq!(scalar(&admin, &format!("SELECT pg_cancel_backend({pid})")).await.as_deref(), Some("t"));
+    assert_eq!(scalar(&worker, "SELECT count(*) FROM (VALUES (1), (2)) v").await.as_deref(), Some("2"));
+
+    // Terminate the session in the middle of a statement
+    let running = tokio::spawn(async move { worker.simple_query(LONG_QUERY).await });
+    wait_for_state(&admin, pid, "active").await;
+    assert_eq!(scalar(&admin, &format!("SELECT pg_terminate_backend({pid}, 5000)")).await.as_deref(), Some("t"));
+    let err = running.await.unwrap().err().expect("session should be terminated");
+    assert_eq!(err.code(), Some(&SqlState::ADMIN_SHUTDOWN), "unexpected error: {err:?}");
+    let remaining = scalar(&admin, &format!("SELECT count(*) FROM pg_stat_activity WHERE pid = {pid}")).await;
+    assert_eq!(remaining.as_deref(), Some("0"));
+}
+
+async fn wait_for_state(client: &Client, pid: i32, state: &str) {
+    let started = Instant::now();
+    loop {
+        let current = scalar(client, &format!("SELECT state FROM pg_stat_activity WHERE pid = {pid}")).await;
+        if current.as_deref() == Some(state) {
+            return;
+        }
+        assert!(started.elapsed() < Duration::from_secs(10), "backend {pid} never became {state}: {current:?}");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
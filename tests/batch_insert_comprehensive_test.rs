@@ -222,14 +222,11 @@ async fn test_batch_insert_constraint_violations() {
     assert!(result.is_err(), "Negative age should fail");
     
     // Test 3: Length constraint validation
-    // Note: String length constraints are not yet validated at the application layer
-    // This is a known limitation - constraints are enforced by SQLite CHECK constraints
     let result = client.simple_query(
         "INSERT INTO constraint_test (id, email, age, name) VALUES
             (6, 'long@example.com', 30, 'VeryLongNameThatExceedsLimit')"
     ).await;
-    // Currently this succeeds because string validation is not implemented
-    assert!(result.is_ok(), "String constraints not enforced yet");
+    assert!(result.is_err(), "Name longer than VARCHAR limit should fail");
     
     server.abort();
 }
@@ -0,0 +1,38 @@
+mod common;
+use common::spawn_server;
+use std::time::{Duration, Instant};
+use tokio_postgres::NoTls;
+
+/// Average time to connect, run one statement and disconnect
+async fn connect_latency(fast_startup: bool, iterations: u32) -> Duration {
+    let mut args = vec!["--in-memory", "--log-level", "error"];
+    if fast_startup {
+        args.push("--fast-startup");
+    }
+    let server = spawn_server(args).await;
+    let conn_str = format!("host=127.0.0.1 port={} dbname=main user=postgres", server.port);
+
+    let start = Instant::now();
+    for _ in 0..iterations {
+        let (client, connection) = tokio_postgres::connect(&conn_str, NoTls).await.unwrap();
+        let handle = tokio::spawn(connection);
+        client.simple_query("SELECT 1").await.unwrap();
+        drop(client);
+        let _ = handle.await;
+    }
+    start.elapsed() / iterations
+}
+
+#[tokio::test]
+#[ignore] // Run with: cargo test --test benchmark_connect_latency -- --ignored --nocapture
+async fn benchmark_connect_latency() {
+    println!("\n=== Connect Latency Benchmark ===\n");
+    let iterations = 500;
+
+    let regular = connect_latency(false, iterations).await;
+    let fast = connect_latency(true, iterations).await;
+
+    println!("Regular startup: {:.3}ms per connection", regular.as_secs_f64() * 1000.0);
+    println!("Fast startup:    {:.3}ms per connection", fast.as_secs_f64() * 1000.0);
+    println!("Speedup:         {:.2}x", regular.as_secs_f64() / fast.as_secs_f64());
+}
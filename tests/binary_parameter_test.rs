@@ -0,0 +1,173 @@
+mod common;
+use common::setup_test_server;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+use tokio_postgres::SimpleQueryMessage;
+use tokio_postgres::types::{FromSql, IsNull, ToSql, Type, to_sql_checked};
+
+/// A UUID in the 16 byte binary format Npgsql and pgx send by default
+#[derive(Debug, PartialEq)]
+struct BinaryUuid([u8; 16]);
+
+impl BinaryUuid {
+    fn parse(text: &str) -> Self {
+        BinaryUuid(*uuid::Uuid::parse_str(text).unwrap().as_bytes())
+    }
+}
+
+impl<'a> FromSql<'a> for BinaryUuid {
+    fn from_sql(_: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        Ok(BinaryUuid(raw.try_into()?))
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::UUID
+    }
+}
+
+impl ToSql for BinaryUuid {
+    fn to_sql(&self, _: &Type, out: &mut bytes::BytesMut) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        out.extend_from_slice(&self.0);
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::UUID
+    }
+
+    to_sql_checked!();
+}
+
+/// A JSON or JSONB document in the binary format, where JSONB starts with a version byte
+#[derive(Debug)]
+struct BinaryJson {
+    version: Option<u8>,
+    text: &'static str,
+}
+
+impl ToSql for BinaryJson {
+    fn to_sql(&self, _: &Type, out: &mut bytes::BytesMut) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        out.extend(self.version);
+        out.extend_from_slice(self.text.as_bytes());
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::JSON || *ty == Type::JSONB
+    }
+
+    to_sql_checked!();
+}
+
+async fn simple_values(client: &tokio_postgres::Client, query: &str) -> Vec<Option<String>> {
+    client.simple_query(query).await.unwrap().into_iter()
+        .filter_map(|message| match message {
+            SimpleQueryMessage::Row(row) => Some(row.get(0).map(str::to_string)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_binary_uuid_parameters() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute("CREATE TABLE devices (id INTEGER PRIMARY KEY, uid UUID, owners UUID[])").await.unwrap();
+
+    let uid = BinaryUuid::parse("a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11");
+    let owners = vec![
+        BinaryUuid::parse("6ba7b810-9dad-11d1-80b4-00c04fd430c8"),
+        BinaryUuid::parse("6ba7b811-9dad-11d1-80b4-00c04fd430c8"),
+    ];
+    client.execute(
+        "INSERT INTO devices (id, uid, owners) VALUES ($1, $2, $3)",
+        &[&1i32, &uid, &owners],
+    ).await.unwrap();
+
+    // Stored in the same text form as a UUID literal
+    client.batch_execute("INSERT INTO devices (id, uid) VALUES (2, 'b1ffcd88-8d1a-4ef8-bb6d-6bb9bd380a22')").await.unwrap();
+    assert_eq!(
+        simple_values(client, "SELECT uid FROM devices ORDER BY id").await,
+        vec![Some("a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11".to_string()), Some("b1ffcd88-8d1a-4ef8-bb6d-6bb9bd380a22".to_string())]
+    );
+
+    let rows = client.query("SELECT id FROM devices WHERE uid = $1", &[&uid]).await.unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].get::<_, i32>(0), 1);
+
+    let row = client.query_one("SELECT uid, owners FROM devices WHERE id = 1", &[]).await.unwrap();
+    assert_eq!(row.get::<_, BinaryUuid>(0), uid);
+    assert_eq!(row.get::<_, Vec<BinaryUuid>>(1), owners);
+
+    // A UUID needs exactly 16 bytes
+    #[derive(Debug)]
+    struct ShortUuid;
+    impl ToSql for ShortUuid {
+        fn to_sql(&self, _: &Type, out: &mut bytes::BytesMut) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+            out.extend_from_slice(&[0xa0, 0xee]);
+            Ok(IsNull::No)
+        }
+
+        fn accepts(ty: &Type) -> bool {
+            *ty == Type::UUID
+        }
+
+        to_sql_checked!();
+    }
+    assert!(client.execute("INSERT INTO devices (id, uid) VALUES ($1, $2)", &[&3i32, &ShortUuid]).await.is_err());
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_binary_json_and_jsonb_parameters() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute("CREATE TABLE documents (id INTEGER PRIMARY KEY, body JSON, doc JSONB)").await.unwrap();
+
+    let body = BinaryJson { version: None, text: r#"{"title": "notes", "pages": 3}"# };
+    let doc = BinaryJson { version: Some(1), text: r#"{"name": "pgsqlite", "tags": ["sqlite", "postgres"]}"# };
+    client.execute(
+        "INSERT INTO documents (id, body, doc) VALUES ($1, $2, $3)",
+        &[&1i32, &body, &doc],
+    ).await.unwrap();
+
+    assert_eq!(simple_values(client, "SELECT body FROM documents WHERE id = 1").await, vec![Some(body.text.to_string())]);
+    assert_eq!(simple_values(client, "SELECT doc->>'name' FROM documents WHERE id = 1").await, vec![Some("pgsqlite".to_string())]);
+    assert_eq!(simple_values(client, "SELECT body->>'pages' FROM documents WHERE id = 1").await, vec![Some("3".to_string())]);
+
+    // Unknown JSONB versions and malformed documents are rejected
+    let future_version = BinaryJson { version: Some(2), text: "{}" };
+    let err = client.execute("INSERT INTO documents (id, doc) VALUES ($1, $2)", &[&2i32, &future_version]).await.unwrap_err();
+    assert!(err.to_string().contains("jsonb version"), "{err}");
+    let malformed = BinaryJson { version: Some(1), text: "{\"name\": " };
+    assert!(client.execute("INSERT INTO documents (id, doc) VALUES ($1, $2)", &[&3i32, &malformed]).await.is_err());
+
+    let count = client.query_one("SELECT COUNT(*) FROM documents", &[]).await.unwrap();
+    assert_eq!(count.get::<_, i64>(0), 1);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_binary_numeric_array_parameters() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute("CREATE TABLE ledgers (id INTEGER PRIMARY KEY, amounts NUMERIC[])").await.unwrap();
+
+    // More digits than a float holds, and a NULL element
+    let amounts = vec![
+        Some(Decimal::from_str("12345678901234567.891").unwrap()),
+        None,
+        Some(Decimal::from_str("-0.005").unwrap()),
+    ];
+    client.execute("INSERT INTO ledgers (id, amounts) VALUES ($1, $2)", &[&1i32, &amounts]).await.unwrap();
+
+    let row = client.query_one("SELECT amounts FROM ledgers WHERE id = 1", &[]).await.unwrap();
+    assert_eq!(row.get::<_, Vec<Option<Decimal>>>(0), amounts);
+
+    server.abort();
+}
@@ -0,0 +1,87 @@
+mod common;
+use common::setup_test_server;
+use tokio_postgres::SimpleQueryMessage;
+use tokio_postgres::error::SqlState;
+
+async fn query_row(client: &tokio_postgres::Client, sql: &str) -> Vec<Option<String>> {
+    client.simple_query(sql).await.unwrap().iter()
+        .find_map(|msg| match msg {
+            SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).map(|s| s.to_string())).collect()),
+            _ => None,
+        })
+        .unwrap()
+}
+
+fn s(value: &str) -> Option<String> {
+    Some(value.to_string())
+}
+
+#[tokio::test]
+async fn test_bit_columns_enforce_length() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TABLE flags (id INTEGER PRIMARY KEY, fixed BIT(4), var VARBIT(6), single BIT)", &[]).await.unwrap();
+
+    client.simple_query("INSERT INTO flags (id, fixed, var, single) VALUES (1, B'1010', B'110', B'1')").await.unwrap();
+    client.execute("INSERT INTO flags (id, fixed, var) VALUES (2, '0110', '101011')", &[]).await.unwrap();
+
+    let row = query_row(client, "SELECT fixed, var, single FROM flags WHERE id = 1").await;
+    assert_eq!(row, vec![s("1010"), s("110"), s("1")]);
+
+    // BIT(n) needs exactly n digits
+    let err = client.simple_query("INSERT INTO flags (id, fixed) VALUES (3, B'101')").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::STRING_DATA_LENGTH_MISMATCH), "unexpected error: {err:?}");
+    assert_eq!(err.as_db_error().unwrap().message(), "bit string length 3 does not match type bit(4)");
+    let err = client.execute("INSERT INTO flags (id, single) VALUES (3, '10')", &[]).await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::STRING_DATA_LENGTH_MISMATCH), "unexpected error: {err:?}");
+
+    // VARBIT(n) allows up to n digits
+    let err = client.execute("UPDATE flags SET var = '1111111' WHERE id = 1", &[]).await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::STRING_DATA_RIGHT_TRUNCATION), "unexpected error: {err:?}");
+    assert_eq!(err.as_db_error().unwrap().message(), "bit string too long for type bit varying(6)");
+
+    // Only binary digits are accepted
+    let err = client.simple_query("INSERT INTO flags (id, fixed) VALUES (3, '1021')").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::INVALID_TEXT_REPRESENTATION), "unexpected error: {err:?}");
+    assert_eq!(err.as_db_error().unwrap().message(), "\"2\" is not a valid binary digit");
+
+    let row = query_row(client, "SELECT count(*) FROM flags").await;
+    assert_eq!(row, vec![s("2")]);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_bitwise_operators() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TABLE masks (id INTEGER PRIMARY KEY, bits BIT(4), n INTEGER)", &[]).await.unwrap();
+    client.simple_query("INSERT INTO masks (id, bits, n) VALUES (1, B'1100', 12), (2, B'0101', 5)").await.unwrap();
+
+    let row = query_row(client, "SELECT bits & B'1010', bits | B'0011', bits # B'1111', ~bits FROM masks WHERE id = 1").await;
+    assert_eq!(row, vec![s("1000"), s("1111"), s("0011"), s("0011")]);
+
+    let row = query_row(client, "SELECT bits << 1, bits >> 2 FROM masks WHERE id = 2").await;
+    assert_eq!(row, vec![s("1010"), s("0001")]);
+
+    // Integers keep their usual meaning, and SQLite's missing # works too
+    let row = query_row(client, "SELECT n & 4, n | 3, n # 6 FROM masks WHERE id = 1").await;
+    assert_eq!(row, vec![s("4"), s("15"), s("10")]);
+
+    let row = query_row(client, "SELECT id FROM masks WHERE bits & B'0100' = B'0100' ORDER BY id").await;
+    assert_eq!(row, vec![s("1")]);
+
+    let row = query_row(client, "SELECT '101'::bit(5) AS padded, B'10111'::varbit(2) AS cut, 5::bit(4) AS from_int").await;
+    assert_eq!(row, vec![s("10100"), s("10"), s("0101")]);
+
+    let err = client.simple_query("SELECT B'10' & B'101'").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::STRING_DATA_LENGTH_MISMATCH), "unexpected error: {err:?}");
+
+    // Operators inside string literals are left alone
+    let row = query_row(client, "SELECT 'a & b | c # d'").await;
+    assert_eq!(row, vec![s("a & b | c # d")]);
+
+    server.abort();
+}
@@ -0,0 +1,139 @@
+mod common;
+use common::setup_test_server;
+use tokio_postgres::SimpleQueryMessage;
+use tokio_postgres::types::Type;
+
+async fn query_row(client: &tokio_postgres::Client, sql: &str) -> Vec<Option<String>> {
+    client.simple_query(sql).await.unwrap().iter()
+        .find_map(|msg| match msg {
+            SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).map(|s| s.to_string())).collect()),
+            _ => None,
+        })
+        .unwrap()
+}
+
+fn t() -> Option<String> {
+    Some("t".to_string())
+}
+
+fn f() -> Option<String> {
+    Some("f".to_string())
+}
+
+#[tokio::test]
+async fn test_boolean_text_output() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TABLE flags (id INTEGER PRIMARY KEY, active BOOLEAN, name TEXT)", &[]).await.unwrap();
+    client.execute("INSERT INTO flags (id, active, name) VALUES (1, true, 'a'), (2, false, NULL)", &[]).await.unwrap();
+
+    let row = query_row(client, "SELECT active FROM flags WHERE id = 1").await;
+    assert_eq!(row, vec![t()]);
+
+    let row = query_row(client, "SELECT count(*) > 0, count(*) = 5 FROM flags").await;
+    assert_eq!(row, vec![t(), f()]);
+
+    let row = query_row(client, "SELECT count(*) > 0 AS has_rows, max(id) < 2 AS small FROM flags").await;
+    assert_eq!(row, vec![t(), f()]);
+
+    let row = query_row(
+        client,
+        "SELECT name IS NULL, NOT active, id IN (1, 3), name LIKE 'a%', id BETWEEN 1 AND 1, \
+         EXISTS (SELECT 1 FROM flags WHERE active) FROM flags WHERE id = 2",
+    ).await;
+    assert_eq!(row, vec![t(), t(), f(), None, f(), t()]);
+
+    let row = query_row(client, "SELECT CASE WHEN id = 1 THEN true ELSE false END AS first FROM flags WHERE id = 1").await;
+    assert_eq!(row, vec![t()]);
+
+    let row = query_row(client, "UPDATE flags SET active = true WHERE id = 2 RETURNING active").await;
+    assert_eq!(row, vec![t()]);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_boolean_extended_output() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TABLE switches (id INTEGER PRIMARY KEY, enabled BOOLEAN)", &[]).await.unwrap();
+    client.execute("INSERT INTO switches (id, enabled) VALUES ($1, $2), (2, false)", &[&1i32, &true]).await.unwrap();
+
+    // Binary results decode as bool, one byte per value
+    let rows = client.query("SELECT enabled FROM switches ORDER BY id", &[]).await.unwrap();
+    assert_eq!(rows[0].columns()[0].type_(), &Type::BOOL);
+    let values: Vec<bool> = rows.iter().map(|row| row.get(0)).collect();
+    assert_eq!(values, vec![true, false]);
+
+    let row = client.query_one("SELECT count(*) > 1 AS many, enabled = $1 FROM switches WHERE id = 1", &[&true]).await.unwrap();
+    assert_eq!(row.columns()[0].type_(), &Type::BOOL);
+    assert_eq!(row.columns()[1].type_(), &Type::BOOL);
+    assert!(!row.get::<_, bool>(0));
+    assert!(row.get::<_, bool>(1));
+
+    let row = client.query_one("SELECT id IS NOT NULL, true, 1 = 2 FROM switches WHERE id = 2", &[]).await.unwrap();
+    assert_eq!((row.get::<_, bool>(0), row.get::<_, bool>(1), row.get::<_, bool>(2)), (true, true, false));
+
+    let row = client.query_one("UPDATE switches SET enabled = true WHERE id = 2 RETURNING enabled", &[]).await.unwrap();
+    assert_eq!(row.columns()[0].type_(), &Type::BOOL);
+    assert!(row.get::<_, bool>(0));
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_boolean_names_as_aliases() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TABLE votes (id INTEGER PRIMARY KEY, label TEXT)", &[]).await.unwrap();
+    client.execute("INSERT INTO votes (id, label) VALUES (1, 'yes')", &[]).await.unwrap();
+
+    // Columns named like boolean literals keep the type of their expression
+    let row = client.query_one("SELECT id AS \"true\", label AS \"false\", id = 1 AS matched FROM votes", &[]).await.unwrap();
+    assert_ne!(row.columns()[0].type_(), &Type::BOOL);
+    assert_eq!(row.columns()[1].type_(), &Type::TEXT);
+    assert_eq!(row.get::<_, String>(1), "yes");
+    assert_eq!(row.columns()[2].type_(), &Type::BOOL);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_boolean_functions_and_returning() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TABLE tasks (id INTEGER PRIMARY KEY, done BOOLEAN, note TEXT)", &[]).await.unwrap();
+    client.execute("INSERT INTO tasks (id, done, note) VALUES (1, true, NULL), (2, NULL, 'x')", &[]).await.unwrap();
+
+    // COALESCE, NULLIF and CASE over booleans are boolean
+    let sql = "SELECT coalesce(done, false), nullif(done, false), CASE WHEN id = 1 THEN done ELSE false END FROM tasks WHERE id = 2";
+    assert_eq!(query_row(client, sql).await, vec![f(), None, f()]);
+    let row = client.query_one(sql, &[]).await.unwrap();
+    for column in row.columns() {
+        assert_eq!(column.type_(), &Type::BOOL);
+    }
+    assert!(!row.get::<_, bool>(0));
+    let row = client.query_one("SELECT coalesce(done, false) AS finished FROM tasks WHERE id = 1", &[]).await.unwrap();
+    assert!(row.get::<_, bool>(0));
+
+    // IS [NOT] NULL in RETURNING, for each statement and protocol
+    let row = query_row(client, "INSERT INTO tasks (id, done) VALUES (3, false) RETURNING note IS NOT NULL, done IS NULL AS unknown").await;
+    assert_eq!(row, vec![f(), f()]);
+    let row = query_row(client, "UPDATE tasks SET note = 'y' WHERE id = 3 RETURNING note IS NOT NULL AS has_note").await;
+    assert_eq!(row, vec![t()]);
+    let row = query_row(client, "DELETE FROM tasks WHERE id = 3 RETURNING done IS NULL").await;
+    assert_eq!(row, vec![f()]);
+
+    let row = client.query_one("INSERT INTO tasks (id, done) VALUES (4, true) RETURNING note IS NOT NULL, coalesce(done, false)", &[]).await.unwrap();
+    assert_eq!(row.columns()[0].type_(), &Type::BOOL);
+    assert_eq!((row.get::<_, bool>(0), row.get::<_, bool>(1)), (false, true));
+    let row = client.query_one("UPDATE tasks SET note = 'z' WHERE id = 4 RETURNING note IS NOT NULL AS has_note", &[]).await.unwrap();
+    assert_eq!(row.columns()[0].type_(), &Type::BOOL);
+    assert!(row.get::<_, bool>(0));
+
+    server.abort();
+}
@@ -0,0 +1,81 @@
+mod common;
+use common::setup_test_server;
+use tokio_postgres::SimpleQueryMessage;
+
+async fn query_row(client: &tokio_postgres::Client, sql: &str) -> Vec<Option<String>> {
+    client.simple_query(sql).await.unwrap().iter()
+        .find_map(|msg| match msg {
+            SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).map(|s| s.to_string())).collect()),
+            _ => None,
+        })
+        .unwrap()
+}
+
+fn s(value: &str) -> Option<String> {
+    Some(value.to_string())
+}
+
+#[tokio::test]
+async fn test_bytea_hex_and_escape_output() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TABLE blobs (id INTEGER PRIMARY KEY, data BYTEA)", &[]).await.unwrap();
+    client.simple_query(
+        r"INSERT INTO blobs (id, data) VALUES (1, '\xDEADbeef'), (2, 'a\\b\001'), (3, 'plain')",
+    ).await.unwrap();
+
+    // Hex input is decoded into a BLOB rather than stored as text
+    let row = query_row(client, "SELECT length(data), typeof(data) FROM blobs WHERE id = 1").await;
+    assert_eq!(row, vec![s("4"), s("blob")]);
+
+    // Default output is hex
+    let row = query_row(client, "SHOW bytea_output").await;
+    assert_eq!(row, vec![s("hex")]);
+    let row = query_row(client, "SELECT data FROM blobs WHERE id = 1").await;
+    assert_eq!(row, vec![s(r"\xdeadbeef")]);
+    let row = query_row(client, "SELECT id, data FROM blobs ORDER BY id").await;
+    assert_eq!(row, vec![s("1"), s(r"\xdeadbeef")]);
+    let row = query_row(client, "SELECT data FROM blobs WHERE id = 2").await;
+    assert_eq!(row, vec![s(r"\x615c6201")]);
+    let row = query_row(client, "SELECT data FROM blobs WHERE id = 3").await;
+    assert_eq!(row, vec![s(r"\x706c61696e")]);
+    let row = query_row(client, r"SELECT '\x0001ff'::bytea AS raw").await;
+    assert_eq!(row, vec![s(r"\x0001ff")]);
+
+    // Escape output
+    client.simple_query("SET bytea_output = 'escape'").await.unwrap();
+    let row = query_row(client, "SHOW bytea_output").await;
+    assert_eq!(row, vec![s("escape")]);
+    let row = query_row(client, "SELECT data FROM blobs WHERE id = 1").await;
+    assert_eq!(row, vec![s(r"\336\255\276\357")]);
+    let row = query_row(client, "SELECT data FROM blobs WHERE id = 2").await;
+    assert_eq!(row, vec![s(r"a\\b\001")]);
+
+    assert!(client.simple_query("SET bytea_output = 'base64'").await.is_err());
+    assert!(client.simple_query(r"INSERT INTO blobs (id, data) VALUES (4, '\x123')").await.is_err());
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_bytea_binary_passthrough() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TABLE files (id INTEGER PRIMARY KEY, content BYTEA)", &[]).await.unwrap();
+
+    // Bytes that are not valid UTF-8 survive the round trip untouched
+    let content: Vec<u8> = vec![0x00, 0xff, 0xfe, b'\\', b'x', 0x80];
+    client.execute("INSERT INTO files (id, content) VALUES ($1, $2)", &[&1i32, &content]).await.unwrap();
+
+    let row = client.query_one("SELECT content FROM files WHERE id = $1", &[&1i32]).await.unwrap();
+    let fetched: Vec<u8> = row.get(0);
+    assert_eq!(fetched, content);
+
+    // The same value read through the simple protocol is hex-encoded
+    let row = query_row(client, "SELECT content FROM files WHERE id = 1").await;
+    assert_eq!(row, vec![s(r"\x00fffe5c7880")]);
+
+    server.abort();
+}
@@ -0,0 +1,63 @@
+mod common;
+use common::*;
+
+async fn table_names(client: &tokio_postgres::Client) -> Vec<String> {
+    let mut names: Vec<String> = client.query("SELECT relname FROM pg_catalog.pg_class WHERE relkind = 'r'", &[])
+        .await.unwrap().iter()
+        .map(|row| row.get(0))
+        .filter(|name: &String| !name.starts_with("__pgsqlite") && !name.starts_with("pg_"))
+        .collect();
+    names.sort();
+    names
+}
+
+async fn column_names(client: &tokio_postgres::Client) -> Vec<String> {
+    let rows = |messages: Vec<tokio_postgres::SimpleQueryMessage>| messages.into_iter()
+        .filter_map(|message| match message {
+            tokio_postgres::SimpleQueryMessage::Row(row) => row.get(0).map(str::to_string),
+            _ => None,
+        })
+        .collect::<Vec<String>>();
+    let oid = rows(client.simple_query("SELECT oid FROM pg_catalog.pg_class WHERE relname = 'items'").await.unwrap()).remove(0);
+    rows(client.simple_query(&format!("SELECT attname FROM pg_catalog.pg_attribute WHERE attrelid = {oid} AND attnum > 0")).await.unwrap())
+}
+
+#[tokio::test]
+async fn test_catalog_cache_follows_ddl() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute("CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)").await.unwrap();
+    assert_eq!(table_names(client).await, vec!["items"]);
+    // A repeated query is answered from the cache and sees the same catalog
+    assert_eq!(table_names(client).await, vec!["items"]);
+    assert_eq!(column_names(client).await, vec!["id", "name"]);
+
+    client.batch_execute("CREATE TABLE orders (id INTEGER PRIMARY KEY)").await.unwrap();
+    assert_eq!(table_names(client).await, vec!["items", "orders"]);
+
+    client.batch_execute("ALTER TABLE items ADD COLUMN price REAL").await.unwrap();
+    assert_eq!(column_names(client).await, vec!["id", "name", "price"]);
+
+    client.batch_execute("DROP TABLE orders").await.unwrap();
+    assert_eq!(table_names(client).await, vec!["items"]);
+}
+
+#[tokio::test]
+async fn test_catalog_cache_in_transactions() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute("CREATE TABLE items (id INTEGER PRIMARY KEY)").await.unwrap();
+    assert_eq!(table_names(client).await, vec!["items"]);
+
+    client.batch_execute("BEGIN; CREATE TABLE drafts (id INTEGER PRIMARY KEY)").await.unwrap();
+    client.batch_execute("ROLLBACK").await.unwrap();
+    assert_eq!(table_names(client).await, vec!["items"]);
+
+    // Catalog reads inside the transaction bypass the cache, and its end invalidates what others cached
+    client.batch_execute("BEGIN; CREATE TABLE orders (id INTEGER PRIMARY KEY)").await.unwrap();
+    table_names(client).await;
+    client.batch_execute("COMMIT").await.unwrap();
+    assert_eq!(table_names(client).await, vec!["items", "orders"]);
+}
@@ -0,0 +1,97 @@
+mod common;
+use common::setup_test_server;
+use tokio_postgres::{Client, SimpleQueryMessage};
+
+async fn rows(client: &Client, sql: &str) -> Vec<Vec<Option<String>>> {
+    client.simple_query(sql).await.unwrap().into_iter()
+        .filter_map(|msg| match msg {
+            SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).map(str::to_string)).collect()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn row(values: &[&str]) -> Vec<Option<String>> {
+    values.iter().map(|v| Some(v.to_string())).collect()
+}
+
+/// pg_indexes, pg_index and pg_constraint reflect the live schema
+#[tokio::test]
+async fn test_index_and_constraint_catalogs() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute("
+        CREATE TABLE users (id SERIAL PRIMARY KEY, email VARCHAR(100) UNIQUE NOT NULL, age INTEGER CHECK (age >= 0));
+        CREATE TABLE orders (
+            id INTEGER PRIMARY KEY,
+            user_id INTEGER REFERENCES users(id) ON DELETE CASCADE,
+            code TEXT,
+            region TEXT,
+            UNIQUE (code, region)
+        );
+        CREATE INDEX orders_user_idx ON orders (user_id);
+    ").await.unwrap();
+
+    // Implicit indexes carry PostgreSQL's constraint index names
+    let indexes = rows(client, "SELECT indexname, indexdef FROM pg_indexes WHERE tablename = 'orders' ORDER BY indexname").await;
+    assert_eq!(indexes, vec![
+        row(&["orders_code_region_key", "CREATE UNIQUE INDEX orders_code_region_key ON public.orders USING btree (code, region)"]),
+        row(&["orders_pkey", "CREATE UNIQUE INDEX orders_pkey ON public.orders USING btree (id)"]),
+        row(&["orders_user_idx", "CREATE INDEX orders_user_idx ON public.orders USING btree (user_id)"]),
+    ]);
+
+    // pg_index joins to pg_class for both the index and its table
+    let index_rows = rows(client, "
+        SELECT c.relname, i.indisprimary, i.indisunique, i.indnatts, i.indkey
+        FROM pg_index i
+        JOIN pg_class c ON c.oid = i.indexrelid
+        JOIN pg_class t ON t.oid = i.indrelid
+        WHERE t.relname = 'orders'
+        ORDER BY c.relname
+    ").await;
+    assert_eq!(index_rows, vec![
+        row(&["orders_code_region_key", "f", "t", "2", "3 4"]),
+        row(&["orders_pkey", "t", "t", "1", "1"]),
+        row(&["orders_user_idx", "f", "f", "1", "2"]),
+    ]);
+
+    // Primary key, unique, foreign key and CHECK constraints
+    let constraints = rows(client, "
+        SELECT conname, contype, conkey, confkey, confdeltype, consrc
+        FROM pg_constraint
+        WHERE conrelid IN (SELECT oid FROM pg_class WHERE relname IN ('users', 'orders') AND relkind = 'r')
+        ORDER BY conname
+    ").await;
+    let expected: Vec<Vec<Option<String>>> = vec![
+        vec![Some("orders_code_region_key".into()), Some("u".into()), Some("{3,4}".into()), None, Some(" ".into()), Some("UNIQUE (code, region)".into())],
+        vec![Some("orders_pkey".into()), Some("p".into()), Some("{1}".into()), None, Some(" ".into()), Some("PRIMARY KEY (id)".into())],
+        row(&["orders_user_id_fkey", "f", "{2}", "{1}", "c", "FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE"]),
+        vec![Some("users_age_check".into()), Some("c".into()), None, None, Some(" ".into()), Some("CHECK (age >= 0)".into())],
+        vec![Some("users_email_key".into()), Some("u".into()), Some("{2}".into()), None, Some(" ".into()), Some("UNIQUE (email)".into())],
+        vec![Some("users_pkey".into()), Some("p".into()), Some("{1}".into()), None, Some(" ".into()), Some("PRIMARY KEY (id)".into())],
+    ];
+    assert_eq!(constraints, expected);
+
+    // The foreign key points at the referenced table
+    let referenced = rows(client, "
+        SELECT t.relname FROM pg_constraint con JOIN pg_class t ON t.oid = con.confrelid
+        WHERE con.conname = 'orders_user_id_fkey'
+    ").await;
+    assert_eq!(referenced, vec![row(&["users"])]);
+
+    // The definition functions resolve the views' OIDs
+    let oid = rows(client, "SELECT oid FROM pg_constraint WHERE conname = 'orders_user_id_fkey'").await[0][0].clone().unwrap();
+    let definition = rows(client, &format!("SELECT pg_get_constraintdef({oid})")).await;
+    assert_eq!(definition, vec![row(&["FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE"])]);
+    let oid = rows(client, "SELECT indexrelid FROM pg_index i JOIN pg_class c ON c.oid = i.indexrelid WHERE c.relname = 'orders_user_idx'").await[0][0].clone().unwrap();
+    let definition = rows(client, &format!("SELECT pg_get_indexdef({oid})")).await;
+    assert_eq!(definition, vec![row(&["CREATE INDEX orders_user_idx ON public.orders USING btree (user_id)"])]);
+
+    // Schema changes show up immediately
+    client.batch_execute("DROP INDEX orders_user_idx; DROP TABLE orders").await.unwrap();
+    let remaining = rows(client, "SELECT count(*) FROM pg_indexes WHERE tablename = 'orders'").await;
+    assert_eq!(remaining, vec![row(&["0"])]);
+    let remaining = rows(client, "SELECT count(*) FROM pg_constraint WHERE conname LIKE 'orders%'").await;
+    assert_eq!(remaining, vec![row(&["0"])]);
+}
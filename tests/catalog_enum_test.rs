@@ -147,7 +147,7 @@ async fn test_pg_attribute_enum_types() {
     // Verify the type is in pg_type
     let type_rows = client.query(
         "SELECT typname, typtype FROM pg_catalog.pg_type WHERE oid = $1",
-        &[&(atttypid as i32)]
+        &[&atttypid]
     ).await.unwrap();
     
     assert_eq!(type_rows.len(), 1, "Should find the ENUM type in pg_type");
@@ -161,7 +161,7 @@ async fn test_pg_attribute_enum_types() {
     // Verify pg_enum has the values
     let enum_rows = client.query(
         "SELECT enumlabel FROM pg_catalog.pg_enum WHERE enumtypid = $1 ORDER BY enumsortorder",
-        &[&(atttypid as i32)]
+        &[&atttypid]
     ).await.unwrap();
     
     assert_eq!(enum_rows.len(), 3, "Should find 3 enum values");
@@ -226,7 +226,7 @@ async fn test_pg_enum_filtering() {
     } else {
         our_types[1].get(0)
     };
-    let color_oid: i32 = color_oid_str.parse().expect("Failed to parse OID as i32");
+    let color_oid: u32 = color_oid_str.parse().expect("Failed to parse OID as u32");
     
     // Test filtering pg_enum by type OID
     let color_values = client.query(
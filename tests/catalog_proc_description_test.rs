@@ -0,0 +1,97 @@
+mod common;
+use common::setup_test_server;
+use tokio_postgres::{Client, SimpleQueryMessage};
+
+async fn rows(client: &Client, sql: &str) -> Vec<Vec<Option<String>>> {
+    client.simple_query(sql).await.unwrap().into_iter()
+        .filter_map(|msg| match msg {
+            SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).map(str::to_string)).collect()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn row(values: &[&str]) -> Vec<Option<String>> {
+    values.iter().map(|v| Some(v.to_string())).collect()
+}
+
+/// pg_proc lists the registered functions with their signatures
+#[tokio::test]
+async fn test_pg_proc_lists_registered_functions() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    let split_part = rows(client, "
+        SELECT proname, pronargs, proargtypes, prorettype, prokind
+        FROM pg_proc WHERE proname = 'split_part'
+    ").await;
+    assert_eq!(split_part, vec![row(&["split_part", "3", "25 25 23", "25", "f"])]);
+
+    let aggregate = rows(client, "SELECT prokind FROM pg_proc WHERE proname = 'string_agg'").await;
+    assert_eq!(aggregate, vec![row(&["a"])]);
+
+    // Overloads get distinct OIDs
+    let overloads = rows(client, "SELECT count(DISTINCT oid) FROM pg_proc WHERE proname = 'obj_description'").await;
+    assert_eq!(overloads, vec![row(&["2"])]);
+
+    // Every function lives in pg_catalog
+    let namespaces = rows(client, "
+        SELECT DISTINCT n.nspname FROM pg_proc p JOIN pg_namespace n ON n.oid = p.pronamespace
+    ").await;
+    assert_eq!(namespaces, vec![row(&["pg_catalog"])]);
+}
+
+/// COMMENT ON stores descriptions that the description functions read back
+#[tokio::test]
+async fn test_comment_on_and_descriptions() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute("
+        CREATE TABLE orders (id INTEGER PRIMARY KEY, total NUMERIC(10,2));
+        COMMENT ON TABLE orders IS 'Customer orders';
+        COMMENT ON COLUMN public.orders.total IS 'Order total, tax included';
+        COMMENT ON FUNCTION split_part(text, text, integer) IS 'Split a string';
+    ").await.unwrap();
+
+    let table = rows(client, "
+        SELECT obj_description(c.oid, 'pg_class'), obj_description(c.oid), col_description(c.oid, 2)
+        FROM pg_class c WHERE c.relname = 'orders' AND c.relkind = 'r'
+    ").await;
+    assert_eq!(table, vec![row(&["Customer orders", "Customer orders", "Order total, tax included"])]);
+
+    let function = rows(client, "
+        SELECT d.description FROM pg_description d JOIN pg_proc p ON p.oid = d.objoid
+        WHERE p.proname = 'split_part'
+    ").await;
+    assert_eq!(function, vec![row(&["Split a string"])]);
+
+    // IS NULL removes the comment
+    client.batch_execute("COMMENT ON TABLE orders IS NULL").await.unwrap();
+    let removed = rows(client, "
+        SELECT obj_description(oid, 'pg_class') FROM pg_class WHERE relname = 'orders' AND relkind = 'r'
+    ").await;
+    assert_eq!(removed, vec![vec![None]]);
+
+    // Dropping the table drops its remaining comments
+    client.batch_execute("DROP TABLE orders").await.unwrap();
+    let remaining = rows(client, "SELECT count(*) FROM pg_description WHERE classoid = 1259").await;
+    assert_eq!(remaining, vec![row(&["0"])]);
+}
+
+/// COMMENT ON an unknown object reports the PostgreSQL error
+#[tokio::test]
+async fn test_comment_on_missing_objects() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    let err = client.batch_execute("COMMENT ON TABLE missing IS 'x'").await.unwrap_err();
+    assert_eq!(err.code().unwrap().code(), "42P01");
+
+    client.batch_execute("CREATE TABLE items (id INTEGER PRIMARY KEY)").await.unwrap();
+    let err = client.batch_execute("COMMENT ON COLUMN items.missing IS 'x'").await.unwrap_err();
+    assert_eq!(err.code().unwrap().code(), "42703");
+
+    let err = client.batch_execute("COMMENT ON FUNCTION no_such_function() IS 'x'").await.unwrap_err();
+    assert_eq!(err.code().unwrap().code(), "42883");
+}
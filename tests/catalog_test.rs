@@ -27,7 +27,7 @@ async fn test_catalog_interceptor() {
     
     let response = result.unwrap().unwrap();
     assert_eq!(response.columns, vec!["oid", "typname"]);
-    assert_eq!(response.rows.len(), 36); // Should return all types (18 basic + 18 array types)
+    assert_eq!(response.rows.len(), 48); // Should return all types (18 basic + 6 range + 24 array types)
     
     // Test complex JOIN query
     let query = "SELECT t.typname, t.typtype, n.nspname 
@@ -0,0 +1,81 @@
+mod common;
+use common::setup_test_server;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::SimpleQueryMessage;
+
+async fn column(client: &tokio_postgres::Client, query: &str) -> Vec<String> {
+    client.simple_query(query).await.unwrap().into_iter()
+        .filter_map(|message| match message {
+            SimpleQueryMessage::Row(row) => Some(row.get(0).unwrap_or("NULL").to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+const CLUSTERED: &str = "SELECT c.relname FROM pg_index i JOIN pg_class c ON c.oid = i.indexrelid WHERE i.indisclustered = 't'";
+
+#[tokio::test]
+async fn test_cluster_table() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE TABLE readings (sensor TEXT, at INTEGER, value REAL);
+         INSERT INTO readings VALUES ('b', 2, 1.5), ('a', 3, 2.5), ('b', 1, 3.5), ('a', 1, 4.5);
+         CREATE INDEX readings_sensor_at ON readings (sensor, at DESC);
+         CREATE INDEX readings_recent ON readings (at) WHERE at > 1",
+    ).await.unwrap();
+
+    // The rows are rewritten in index order, which a full scan returns them in
+    client.batch_execute("CLUSTER readings USING readings_sensor_at").await.unwrap();
+    assert_eq!(
+        column(client, "SELECT sensor || at FROM readings").await,
+        vec!["a3", "a1", "b2", "b1"]
+    );
+    assert_eq!(column(client, "SELECT sum(value) FROM readings").await, vec!["12"]);
+    assert_eq!(column(client, CLUSTERED).await, vec!["readings_sensor_at"]);
+    assert_eq!(column(client, "SELECT count(*) FROM pg_stat_progress_cluster").await, vec!["0"]);
+
+    // Later rows land at the end until the table is clustered again on the remembered index
+    client.batch_execute("INSERT INTO readings VALUES ('a', 2, 5.5)").await.unwrap();
+    client.execute("CLUSTER readings", &[]).await.unwrap();
+    assert_eq!(
+        column(client, "SELECT sensor || at FROM readings").await,
+        vec!["a3", "a2", "a1", "b2", "b1"]
+    );
+
+    // Constraint indexes go by their PostgreSQL names, and follow a renamed table
+    client.batch_execute(
+        "CREATE TABLE tags (id SERIAL PRIMARY KEY, name TEXT UNIQUE);
+         INSERT INTO tags (name) VALUES ('z'), ('a');
+         CLUSTER tags_name_key ON tags;
+         ALTER TABLE tags RENAME TO labels",
+    ).await.unwrap();
+    assert_eq!(column(client, &format!("{CLUSTERED} ORDER BY c.relname")).await, vec!["labels_name_key", "readings_sensor_at"]);
+    client.batch_execute("CLUSTER labels; CLUSTER labels USING labels_pkey; INSERT INTO labels (name) VALUES ('m')").await.unwrap();
+    assert_eq!(column(client, "SELECT max(id) FROM labels").await, vec!["3"]);
+
+    for (statement, code) in [
+        ("CLUSTER missing", SqlState::UNDEFINED_TABLE),
+        ("CLUSTER readings USING missing_idx", SqlState::UNDEFINED_OBJECT),
+        ("CLUSTER readings USING readings_recent", SqlState::FEATURE_NOT_SUPPORTED),
+        ("CLUSTER (ANALYZE) readings", SqlState::SYNTAX_ERROR),
+    ] {
+        let err = client.simple_query(statement).await.unwrap_err();
+        assert_eq!(err.code(), Some(&code), "{statement}: {err:?}");
+    }
+    client.batch_execute("CREATE TABLE fresh (id INTEGER)").await.unwrap();
+    let err = client.simple_query("CLUSTER fresh").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::UNDEFINED_OBJECT));
+
+    // A named table may be clustered inside a transaction block, every table may not
+    client.batch_execute("BEGIN; CLUSTER readings").await.unwrap();
+    let err = client.simple_query("CLUSTER").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::ACTIVE_SQL_TRANSACTION));
+    client.batch_execute("ROLLBACK; CLUSTER VERBOSE").await.unwrap();
+
+    client.batch_execute("DROP TABLE readings; CREATE TABLE readings (sensor TEXT); CREATE INDEX readings_sensor_at ON readings (sensor)").await.unwrap();
+    assert_eq!(column(client, CLUSTERED).await, vec!["labels_pkey"]);
+
+    server.abort();
+}
@@ -1,9 +1,12 @@
 use tokio::net::TcpListener;
 use tokio_postgres::{Client, NoTls};
+use std::process::{Child, Command, Stdio};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
 pub struct TestServer {
+    #[allow(dead_code)]
     pub client: Client,
     #[allow(dead_code)]
     pub port: u16,
@@ -18,6 +21,18 @@ impl TestServer {
     pub fn abort(self) {
         self.server_handle.abort();
     }
+
+    /// Open another session to the server
+    #[allow(dead_code)]
+    pub async fn connect(&self) -> Client {
+        connect(self.port, "dbname=test user=testuser").await.unwrap()
+    }
+
+    /// Open another session with extra connection parameters, e.g. `user=ops`
+    #[allow(dead_code)]
+    pub async fn connect_with(&self, params: &str) -> Result<Client, tokio_postgres::Error> {
+        connect(self.port, &format!("dbname=test user=testuser {params}")).await
+    }
 }
 
 impl Drop for TestServer {
@@ -76,9 +91,15 @@ where
         // Add a small delay to ensure changes propagate
         tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
         
-        let (stream, addr) = listener.accept().await.unwrap();
-        if let Err(e) = pgsqlite::handle_test_connection_with_pool(stream, addr, db_handler).await {
-            eprintln!("Connection handling error: {e}");
+        // Every connection is a session of its own, as with the real server
+        loop {
+            let (stream, addr) = listener.accept().await.unwrap();
+            let db_handler = db_handler.clone();
+            tokio::spawn(async move {
+                if let Err(e) = pgsqlite::handle_test_connection_with_pool(stream, addr, db_handler).await {
+                    eprintln!("Connection handling error: {e}");
+                }
+            });
         }
     });
     
@@ -101,4 +122,102 @@ where
         server_handle,
         db_path,
     }
-}
\ No newline at end of file
+}
+
+/// Connect to a server on localhost; later parameters in `params` override earlier ones
+async fn connect(port: u16, params: &str) -> Result<Client, tokio_postgres::Error> {
+    let (client, connection) = tokio_postgres::connect(&format!("host=127.0.0.1 port={port} {params}"), NoTls).await?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("Connection error: {e}");
+        }
+    });
+    Ok(client)
+}
+
+/// The pgsqlite binary started by a test, killed when dropped
+#[allow(dead_code)]
+pub struct ServerProcess {
+    pub child: Child,
+    pub port: u16,
+}
+
+impl Drop for ServerProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[allow(dead_code)]
+impl ServerProcess {
+    /// Open a session as postgres to the main database
+    pub async fn connect(&self) -> Client {
+        self.connect_with("").await.unwrap()
+    }
+
+    /// Open a session with extra connection parameters, e.g. `user=ops dbname=sales`
+    pub async fn connect_with(&self, params: &str) -> Result<Client, tokio_postgres::Error> {
+        connect(self.port, &format!("dbname=main user=postgres {params}")).await
+    }
+}
+
+/// A TCP port nothing listens on right now
+#[allow(dead_code)]
+pub fn free_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+/// Wait until something accepts connections on `port`, e.g. a server's second listener
+#[allow(dead_code)]
+pub async fn wait_until_listening(port: u16) {
+    let started = Instant::now();
+    while std::net::TcpStream::connect(("127.0.0.1", port)).is_err() {
+        assert!(started.elapsed() < Duration::from_secs(30), "nothing listens on port {port}");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+/// Start the pgsqlite binary with `args` on a free port and wait until it accepts connections
+#[allow(dead_code)]
+pub async fn spawn_server<I, S>(args: I) -> ServerProcess
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<std::ffi::OsStr>,
+{
+    let args: Vec<std::ffi::OsString> = args.into_iter().map(|arg| arg.as_ref().to_owned()).collect();
+    spawn_server_with(|command| {
+        command.args(&args);
+    }).await
+}
+
+/// Start the pgsqlite binary on a free port once `configure` set its arguments and output,
+/// which are discarded unless it pipes them, and wait until it accepts connections.
+///
+/// Another process can take the port between it being picked and the server binding it,
+/// so a server that exits before it listens is started again on another port; `configure`
+/// runs for every attempt, so ports it picks itself are picked again too.
+#[allow(dead_code)]
+pub async fn spawn_server_with(mut configure: impl FnMut(&mut Command)) -> ServerProcess {
+    let started = Instant::now();
+    'attempts: loop {
+        let port = free_port();
+        let mut command = Command::new(env!("CARGO_BIN_EXE_pgsqlite"));
+        command.stdout(Stdio::null()).stderr(Stdio::null());
+        configure(&mut command);
+        command.args(["--port", &port.to_string()]);
+        let mut server = ServerProcess { child: command.spawn().expect("Failed to start server"), port };
+
+        while std::net::TcpStream::connect(("127.0.0.1", port)).is_err() {
+            assert!(started.elapsed() < Duration::from_secs(30), "server did not start");
+            if server.child.try_wait().unwrap().is_some() {
+                continue 'attempts;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        // What answered can be whatever took the port, if the server exited on failing to bind it
+        if server.child.try_wait().unwrap().is_none() {
+            return server;
+        }
+    }
+}
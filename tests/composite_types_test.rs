@@ -0,0 +1,125 @@
+mod common;
+use common::setup_test_server;
+use tokio_postgres::SimpleQueryMessage;
+use tokio_postgres::error::SqlState;
+
+async fn query_rows(client: &tokio_postgres::Client, sql: &str) -> Vec<Vec<Option<String>>> {
+    client.simple_query(sql).await.unwrap().iter()
+        .filter_map(|msg| match msg {
+            SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).map(|s| s.to_string())).collect()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn s(value: &str) -> Option<String> {
+    Some(value.to_string())
+}
+
+#[tokio::test]
+async fn test_composite_columns() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TYPE address AS (street TEXT, zip INTEGER)", &[]).await.unwrap();
+    client.execute("CREATE TABLE people (id INTEGER PRIMARY KEY, name TEXT, home address)", &[]).await.unwrap();
+
+    client.simple_query("INSERT INTO people (id, name, home) VALUES (1, 'Ann', ROW('Main St', 12345))").await.unwrap();
+    client.execute("INSERT INTO people (id, name, home) VALUES (2, 'Bob', '(Elm,500)')", &[]).await.unwrap();
+    client.execute("INSERT INTO people (id, name, home) VALUES ($1, $2, $3)", &[&3i32, &"Cy", &"(\"1st Ave\",)"]).await.unwrap();
+
+    let rows = query_rows(client, "SELECT home, (home).street, (p.home).zip FROM people p ORDER BY id").await;
+    assert_eq!(rows, vec![
+        vec![s("(\"Main St\",12345)"), s("Main St"), s("12345")],
+        vec![s("(Elm,500)"), s("Elm"), s("500")],
+        vec![s("(\"1st Ave\",)"), s("1st Ave"), None],
+    ]);
+
+    // Field access works in WHERE and through the extended protocol
+    let rows = client.query("SELECT name FROM people WHERE (home).zip > $1 ORDER BY id", &[&1000i32]).await.unwrap();
+    let names: Vec<String> = rows.iter().map(|row| row.get(0)).collect();
+    assert_eq!(names, vec!["Ann"]);
+
+    client.simple_query("UPDATE people SET home = ROW('Oak', 42) WHERE id = 2").await.unwrap();
+    let rows = query_rows(client, "SELECT (home).street FROM people WHERE id = 2").await;
+    assert_eq!(rows, vec![vec![s("Oak")]]);
+
+    // Values must be records with one field per attribute
+    let err = client.execute("INSERT INTO people (id, home) VALUES (4, '(a,1,extra)')", &[]).await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::INVALID_TEXT_REPRESENTATION), "unexpected error: {err:?}");
+    assert_eq!(err.as_db_error().unwrap().message(), "malformed record literal: \"(a,1,extra)\"");
+
+    // row_to_json nests composite columns as objects
+    let rows = query_rows(client, "SELECT row_to_json(t) FROM (SELECT id, home FROM people WHERE id = 1) t").await;
+    assert_eq!(rows, vec![vec![s(r#"{"id":1,"home":{"street":"Main St","zip":12345}}"#)]]);
+    let rows = query_rows(client, "SELECT row_to_json(home), row_to_json(p.home) FROM people p WHERE id = 1").await;
+    assert_eq!(rows, vec![vec![s(r#"{"street":"Main St","zip":12345}"#), s(r#"{"street":"Main St","zip":12345}"#)]]);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_composite_type_catalog_and_ddl() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TYPE money_amount AS (amount NUMERIC(10, 2), currency VARCHAR(3))", &[]).await.unwrap();
+
+    let rows = query_rows(client, "SELECT typname, typtype, CAST(typrelid AS TEXT) FROM pg_catalog.pg_type WHERE typtype = 'c'").await;
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0][0], s("money_amount"));
+    assert_eq!(rows[0][1], s("c"));
+    let typrelid = rows[0][2].clone().unwrap();
+
+    let rows = query_rows(client, &format!("SELECT attname, atttypid::text, attnum FROM pg_catalog.pg_attribute WHERE attrelid = {typrelid}")).await;
+    assert_eq!(rows, vec![
+        vec![s("amount"), s("1700"), s("1")],
+        vec![s("currency"), s("1043"), s("2")],
+    ]);
+
+    let err = client.execute("CREATE TYPE money_amount AS (x INTEGER)", &[]).await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::DUPLICATE_OBJECT), "unexpected error: {err:?}");
+
+    client.execute("CREATE TABLE prices (id INTEGER PRIMARY KEY, price money_amount)", &[]).await.unwrap();
+    let err = client.simple_query("DROP TYPE money_amount").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::DEPENDENT_OBJECTS_STILL_EXIST), "unexpected error: {err:?}");
+
+    client.execute("DROP TABLE prices", &[]).await.unwrap();
+    client.simple_query("DROP TYPE money_amount").await.unwrap();
+    let rows = query_rows(client, "SELECT typname FROM pg_catalog.pg_type WHERE typtype = 'c'").await;
+    assert!(rows.is_empty());
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_composite_arrays() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE TYPE point2 AS (x INTEGER, y INTEGER);
+         CREATE TABLE shapes (id INTEGER PRIMARY KEY, corners point2[]);
+         INSERT INTO shapes VALUES (1, '{\"(1,2)\",\"(3,4)\"}')"
+    ).await.unwrap();
+    assert_eq!(query_rows(client, "SELECT corners FROM shapes").await, vec![vec![s("{\"(1,2)\",\"(3,4)\"}")]]);
+
+    // Every element must be a record of the type
+    let err = client.simple_query("INSERT INTO shapes VALUES (2, '{\"(1,2,3)\"}')").await.unwrap_err();
+    assert!(err.to_string().contains("malformed record literal: \"(1,2,3)\""), "unexpected error: {err:?}");
+
+    // The array type is listed in pg_type with the composite type as its element
+    let rows = query_rows(client, "SELECT CAST(oid AS TEXT), CAST(typarray AS TEXT) FROM pg_catalog.pg_type WHERE typtype = 'c'").await;
+    let (type_oid, array_oid) = (rows[0][0].clone().unwrap(), rows[0][1].clone().unwrap());
+    assert_ne!(array_oid, "0");
+    let rows = query_rows(client, &format!("SELECT typname, typtype, CAST(typelem AS TEXT) FROM pg_catalog.pg_type WHERE oid = {array_oid}")).await;
+    assert_eq!(rows, vec![vec![s("_point2"), s("b"), Some(type_oid)]]);
+
+    let err = client.simple_query("DROP TYPE point2").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::DEPENDENT_OBJECTS_STILL_EXIST), "unexpected error: {err:?}");
+    client.batch_execute("DROP TABLE shapes; DROP TYPE point2").await.unwrap();
+    let rows = query_rows(client, &format!("SELECT typname FROM pg_catalog.pg_type WHERE oid = {array_oid}")).await;
+    assert!(rows.is_empty());
+
+    server.abort();
+}
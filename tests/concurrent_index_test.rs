@@ -0,0 +1,76 @@
+mod common;
+use common::setup_test_server;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::SimpleQueryMessage;
+
+async fn scalar(client: &tokio_postgres::Client, query: &str) -> Option<String> {
+    client.simple_query(query).await.unwrap().into_iter()
+        .find_map(|message| match message {
+            SimpleQueryMessage::Row(row) => Some(row.get(0).map(str::to_string)),
+            _ => None,
+        })
+        .flatten()
+}
+
+#[tokio::test]
+async fn test_create_index_concurrently() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    // More rows than one scan batch
+    client.batch_execute(
+        "CREATE TABLE items (id SERIAL PRIMARY KEY, code TEXT, qty INTEGER);
+         INSERT INTO items (code, qty) SELECT 'c' || value, value % 100 FROM generate_series(1, 25000)",
+    ).await.unwrap();
+
+    client.batch_execute("CREATE INDEX CONCURRENTLY items_qty ON items (qty)").await.unwrap();
+    client.execute("CREATE UNIQUE INDEX CONCURRENTLY items_code ON items (code) WHERE qty > 0", &[]).await.unwrap();
+    assert_eq!(
+        scalar(client, "SELECT string_agg(indexname, ',' ORDER BY indexname) FROM pg_indexes WHERE tablename = 'items'").await.as_deref(),
+        Some("items_code,items_pkey,items_qty")
+    );
+    assert_eq!(scalar(client, "SELECT count(*) FROM items WHERE qty = 7").await.as_deref(), Some("250"));
+    assert_eq!(scalar(client, "SELECT count(*) FROM pg_stat_progress_create_index").await.as_deref(), Some("0"));
+
+    // IF NOT EXISTS skips a taken name with a notice
+    client.batch_execute("CREATE INDEX CONCURRENTLY IF NOT EXISTS items_qty ON items (code)").await.unwrap();
+
+    for (statement, code) in [
+        ("CREATE INDEX CONCURRENTLY items_qty ON items (code)", SqlState::DUPLICATE_TABLE),
+        ("CREATE INDEX CONCURRENTLY missing_idx ON missing (code)", SqlState::UNDEFINED_TABLE),
+        ("CREATE UNIQUE INDEX CONCURRENTLY items_qty_key ON items (qty)", SqlState::UNIQUE_VIOLATION),
+        ("DROP INDEX CONCURRENTLY items_qty CASCADE", SqlState::FEATURE_NOT_SUPPORTED),
+        ("DROP INDEX CONCURRENTLY items_qty, items_code", SqlState::FEATURE_NOT_SUPPORTED),
+    ] {
+        let err = client.simple_query(statement).await.unwrap_err();
+        assert_eq!(err.code(), Some(&code), "{statement}: {err:?}");
+    }
+    // A failed unique build leaves no index behind
+    assert_eq!(scalar(client, "SELECT count(*) FROM pg_indexes WHERE indexname = 'items_qty_key'").await.as_deref(), Some("0"));
+
+    // Neither statement runs inside a transaction block
+    client.batch_execute("BEGIN").await.unwrap();
+    let err = client.simple_query("CREATE INDEX CONCURRENTLY items_id_qty ON items (id, qty)").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::ACTIVE_SQL_TRANSACTION));
+    client.batch_execute("ROLLBACK; BEGIN").await.unwrap();
+    let err = client.simple_query("DROP INDEX CONCURRENTLY items_qty").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::ACTIVE_SQL_TRANSACTION));
+    client.batch_execute("ROLLBACK").await.unwrap();
+
+    client.batch_execute("DROP INDEX CONCURRENTLY items_qty").await.unwrap();
+    client.execute("DROP INDEX CONCURRENTLY IF EXISTS items_qty", &[]).await.unwrap();
+    assert_eq!(
+        scalar(client, "SELECT string_agg(indexname, ',' ORDER BY indexname) FROM pg_indexes WHERE tablename = 'items'").await.as_deref(),
+        Some("items_code,items_pkey")
+    );
+
+    // Without a name the index is named the way PostgreSQL names it
+    client.batch_execute("CREATE INDEX CONCURRENTLY ON items (qty)").await.unwrap();
+    client.execute("CREATE INDEX CONCURRENTLY ON items (qty)", &[]).await.unwrap();
+    assert_eq!(
+        scalar(client, "SELECT string_agg(indexname, ',' ORDER BY indexname) FROM pg_indexes WHERE tablename = 'items'").await.as_deref(),
+        Some("items_code,items_pkey,items_qty_idx,items_qty_idx1")
+    );
+
+    server.abort();
+}
@@ -0,0 +1,90 @@
+mod common;
+use common::{free_port, spawn_server_with};
+use std::process::Command;
+use std::time::{Duration, Instant};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::{Client, NoTls, SimpleQueryMessage};
+
+async fn connect(port: u16, user: &str) -> Result<Client, tokio_postgres::Error> {
+    let (client, connection) = tokio_postgres::connect(
+        &format!("host=127.0.0.1 port={port} dbname=main user={user}"),
+        NoTls,
+    ).await?;
+    tokio::spawn(connection);
+    Ok(client)
+}
+
+async fn scalar(client: &Client, query: &str) -> String {
+    client.simple_query(query).await.unwrap().into_iter()
+        .find_map(|message| match message {
+            SimpleQueryMessage::Row(row) => row.get(0).map(str::to_string),
+            _ => None,
+        })
+        .unwrap()
+}
+
+/// Wait until the admin port lets `user` in, or turns them away
+async fn wait_for_admin(admin_port: u16, user: &str, allowed: bool) {
+    let started = Instant::now();
+    loop {
+        match connect(admin_port, user).await {
+            Ok(_) if allowed => return,
+            Err(e) if !allowed => {
+                assert_eq!(e.code(), Some(&SqlState::INVALID_AUTHORIZATION_SPECIFICATION), "unexpected error: {e:?}");
+                return;
+            }
+            _ => {
+                assert!(started.elapsed() < Duration::from_secs(10), "{user} still {} on the admin port", if allowed { "refused" } else { "allowed" });
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        }
+    }
+}
+
+/// pg_reload_conf() and SIGHUP reread --config-file, changing what a reload may change
+#[tokio::test]
+async fn test_reload_config_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_file = dir.path().join("pgsqlite.conf");
+    std::fs::write(&config_file, "# Admins\nadmin_users = postgres\nlog_level = error\n").unwrap();
+
+    let mut admin_port = 0;
+    let server = spawn_server_with(|command| {
+        admin_port = free_port();
+        command
+            .args(["--in-memory", "--admin-port", &admin_port.to_string()])
+            .arg("--config-file")
+            .arg(&config_file)
+            .arg("--socket-dir")
+            .arg(dir.path());
+    }).await;
+    let port = server.port;
+    let client = connect(port, "app").await.unwrap();
+    wait_for_admin(admin_port, "postgres", true).await;
+    wait_for_admin(admin_port, "ops", false).await;
+    let loaded = scalar(&client, "SELECT pg_conf_load_time()").await;
+
+    // The admin roles change, the port needs a restart and stays
+    std::fs::write(&config_file, format!("admin_users = 'postgres, ops'\nlog_level = error\nport = {}\n", free_port())).unwrap();
+    assert_eq!(scalar(&client, "SELECT pg_reload_conf()").await, "t");
+    wait_for_admin(admin_port, "ops", true).await;
+    assert!(scalar(&client, "SELECT pg_conf_load_time()").await > loaded);
+    connect(port, "app").await.unwrap();
+
+    // A file with errors is not applied
+    std::fs::write(&config_file, "admin_users = postgres\nno_such_setting = on\n").unwrap();
+    let loaded = scalar(&client, "SELECT pg_conf_load_time()").await;
+    assert_eq!(scalar(&client, "SELECT pg_reload_conf()").await, "t");
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(scalar(&client, "SELECT pg_conf_load_time()").await, loaded);
+    wait_for_admin(admin_port, "ops", true).await;
+
+    #[cfg(unix)]
+    {
+        std::fs::write(&config_file, "admin_users = postgres\n").unwrap();
+        let status = Command::new("kill").args(["-HUP", &server.child.id().to_string()]).status().unwrap();
+        assert!(status.success());
+        wait_for_admin(admin_port, "ops", false).await;
+        assert_eq!(scalar(&client, "SELECT 1").await, "1");
+    }
+}
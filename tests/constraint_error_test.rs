@@ -0,0 +1,110 @@
+mod common;
+use common::*;
+use tokio_postgres::error::{DbError, SqlState};
+
+async fn setup() -> TestServer {
+    let server = setup_test_server().await;
+    server.client.batch_execute(
+        "CREATE TABLE accounts (
+            id INTEGER PRIMARY KEY,
+            email VARCHAR(100) UNIQUE,
+            name TEXT NOT NULL,
+            region TEXT,
+            code TEXT,
+            balance INTEGER CHECK (balance >= 0),
+            CONSTRAINT region_code_unique UNIQUE (region, code),
+            CONSTRAINT balance_cap CHECK (balance < 1000)
+        );
+        INSERT INTO accounts (id, email, name, region, code, balance) VALUES (1, 'a@example.com', 'alice', 'eu', 'A', 10);"
+    ).await.unwrap();
+    server
+}
+
+fn db_error(error: &tokio_postgres::Error) -> &DbError {
+    error.as_db_error().unwrap_or_else(|| panic!("{error:?}"))
+}
+
+#[tokio::test]
+async fn test_unique_violation() {
+    let server = setup().await;
+    let client = &server.client;
+
+    let err = client.execute("INSERT INTO accounts (id, email, name) VALUES ($1, $2, $3)", &[&2i32, &"a@example.com", &"bob"])
+        .await.unwrap_err();
+    let err = db_error(&err);
+    assert_eq!(err.code(), &SqlState::UNIQUE_VIOLATION);
+    assert_eq!(err.message(), "duplicate key value violates unique constraint \"accounts_email_key\"");
+    assert_eq!(err.schema(), Some("public"));
+    assert_eq!(err.table(), Some("accounts"));
+    assert_eq!(err.constraint(), Some("accounts_email_key"));
+
+    let err = client.batch_execute("INSERT INTO accounts (id, name) VALUES (1, 'bob')").await.unwrap_err();
+    assert_eq!(db_error(&err).constraint(), Some("accounts_pkey"));
+
+    let err = client.batch_execute("UPDATE accounts SET region = 'eu', code = 'A' WHERE id = 1;
+        INSERT INTO accounts (id, name, region, code) VALUES (2, 'bob', 'eu', 'A')").await.unwrap_err();
+    assert_eq!(db_error(&err).constraint(), Some("region_code_unique"));
+}
+
+#[tokio::test]
+async fn test_check_violation() {
+    let server = setup().await;
+    let client = &server.client;
+
+    let err = client.execute("UPDATE accounts SET balance = $1 WHERE id = 1", &[&-5i32]).await.unwrap_err();
+    let err = db_error(&err);
+    assert_eq!(err.code(), &SqlState::CHECK_VIOLATION);
+    assert_eq!(err.table(), Some("accounts"));
+    // Unnamed checks go by the name pg_constraint lists, which PostgreSQL would give them
+    let listed: String = client.query_one("SELECT conname FROM pg_constraint WHERE contype = 'c' AND consrc LIKE '%>= 0%'", &[])
+        .await.unwrap().get(0);
+    assert_eq!(listed, "accounts_balance_check");
+    assert_eq!(err.constraint(), Some(listed.as_str()));
+    assert_eq!(err.message(), format!("new row for relation \"accounts\" violates check constraint \"{listed}\""));
+
+    let err = client.batch_execute("UPDATE accounts SET balance = 5000").await.unwrap_err();
+    assert_eq!(db_error(&err).constraint(), Some("balance_cap"));
+}
+
+/// Unnamed CHECK constraints are named after their table and the one column they use
+#[tokio::test]
+async fn test_check_constraint_names() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+    client.batch_execute(
+        "CREATE TABLE parent (
+            id INTEGER PRIMARY KEY,
+            qty INTEGER CHECK (qty > 0) CHECK (qty < 100),
+            lo INTEGER,
+            hi INTEGER,
+            note TEXT CHECK (length(note) > 1 AND note <> 'lo'),
+            CHECK (lo < hi),
+            CONSTRAINT positive_lo CHECK ((lo) >= (0))
+        )",
+    ).await.unwrap();
+
+    let names: Vec<String> = client.query("SELECT conname FROM pg_constraint WHERE contype = 'c' ORDER BY conname", &[])
+        .await.unwrap().iter().map(|row| row.get(0)).collect();
+    assert_eq!(names, ["parent_check", "parent_note_check", "parent_qty_check", "parent_qty_check1", "positive_lo"]);
+
+    let err = client.batch_execute("INSERT INTO parent (id, qty) VALUES (1, 0)").await.unwrap_err();
+    assert_eq!(db_error(&err).constraint(), Some("parent_qty_check"));
+    let err = client.batch_execute("INSERT INTO parent (id, qty) VALUES (1, 200)").await.unwrap_err();
+    assert_eq!(db_error(&err).constraint(), Some("parent_qty_check1"));
+}
+
+#[tokio::test]
+async fn test_not_null_violation() {
+    let server = setup().await;
+    let client = &server.client;
+
+    let err = client.execute("INSERT INTO accounts (id, name) VALUES ($1, $2)", &[&2i32, &None::<String>]).await.unwrap_err();
+    let err = db_error(&err);
+    assert_eq!(err.code(), &SqlState::NOT_NULL_VIOLATION);
+    assert_eq!(err.message(), "null value in column \"name\" of relation \"accounts\" violates not-null constraint");
+    assert_eq!(err.table(), Some("accounts"));
+    assert_eq!(err.column(), Some("name"));
+
+    let err = client.batch_execute("UPDATE accounts SET name = NULL").await.unwrap_err();
+    assert_eq!(db_error(&err).code(), &SqlState::NOT_NULL_VIOLATION);
+}
@@ -0,0 +1,75 @@
+mod common;
+use common::setup_test_server;
+use futures::{pin_mut, TryStreamExt};
+use tokio_postgres::SimpleQueryMessage;
+use tokio_postgres::error::SqlState;
+
+async fn copy_out(client: &tokio_postgres::Client, sql: &str) -> String {
+    let stream = client.copy_out(sql).await.unwrap();
+    pin_mut!(stream);
+    let mut output = Vec::new();
+    while let Some(chunk) = stream.try_next().await.unwrap() {
+        output.extend_from_slice(&chunk);
+    }
+    String::from_utf8(output).unwrap()
+}
+
+#[tokio::test]
+async fn test_copy_to_stdout_formats() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT, active BOOLEAN, added DATE)", &[]).await.unwrap();
+    client.execute(
+        "INSERT INTO items (id, name, active, added) VALUES \
+         (1, 'plain', true, '2024-01-15'), (2, 'tab\there', false, NULL), (3, 'comma, \"quoted\"', NULL, '2024-02-29')",
+        &[],
+    ).await.unwrap();
+
+    let text = copy_out(client, "COPY items TO STDOUT").await;
+    assert_eq!(text, "1\tplain\tt\t2024-01-15\n2\ttab\\there\tf\t\\N\n3\tcomma, \"quoted\"\t\\N\t2024-02-29\n");
+
+    let csv = copy_out(client, "COPY items (id, name) TO STDOUT WITH (FORMAT csv, HEADER)").await;
+    assert_eq!(csv, "id,name\n1,plain\n2,tab\there\n3,\"comma, \"\"quoted\"\"\"\n");
+
+    let query = copy_out(client, "COPY (SELECT id, name FROM items WHERE id > 1 ORDER BY id DESC) TO STDOUT WITH CSV").await;
+    assert_eq!(query, "3,\"comma, \"\"quoted\"\"\"\n2,tab\there\n");
+
+    let Err(err) = client.copy_out("COPY missing TO STDOUT").await else {
+        panic!("COPY from a missing table should fail");
+    };
+    assert_eq!(err.code(), Some(&SqlState::UNDEFINED_TABLE), "unexpected error: {err:?}");
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_copy_to_large_table_in_chunks() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TABLE events (id INTEGER PRIMARY KEY, payload TEXT)", &[]).await.unwrap();
+    client.simple_query(
+        "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 2500) \
+         INSERT INTO events (id, payload) SELECT i, 'event ' || i FROM n"
+    ).await.unwrap();
+    // A gap in the rowids must not end the export early
+    client.execute("DELETE FROM events WHERE id BETWEEN 900 AND 1200", &[]).await.unwrap();
+
+    let output = copy_out(client, "COPY events TO STDOUT").await;
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 2199);
+    assert_eq!(lines[0], "1\tevent 1");
+    assert_eq!(lines[899], "1201\tevent 1201");
+    assert_eq!(lines[2198], "2500\tevent 2500");
+
+    // The progress view is empty once the export has finished
+    let messages = client.simple_query("SELECT count(*) FROM pg_stat_progress_copy").await.unwrap();
+    let count = messages.iter().find_map(|msg| match msg {
+        SimpleQueryMessage::Row(row) => row.get(0).map(str::to_string),
+        _ => None,
+    });
+    assert_eq!(count.as_deref(), Some("0"));
+
+    server.abort();
+}
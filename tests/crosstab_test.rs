@@ -0,0 +1,76 @@
+mod common;
+use common::setup_test_server;
+use tokio_postgres::SimpleQueryMessage;
+use tokio_postgres::error::SqlState;
+
+async fn query_rows(client: &tokio_postgres::Client, sql: &str) -> Vec<Vec<Option<String>>> {
+    client.simple_query(sql).await.unwrap().iter()
+        .filter_map(|msg| match msg {
+            SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).map(|s| s.to_string())).collect()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn s(value: &str) -> Option<String> {
+    Some(value.to_string())
+}
+
+async fn setup_sales(client: &tokio_postgres::Client) {
+    client.execute("CREATE TABLE sales (region TEXT, manager TEXT, quarter TEXT, amount INTEGER)", &[]).await.unwrap();
+    client.execute(
+        "INSERT INTO sales (region, manager, quarter, amount) VALUES \
+         ('east', 'ann', 'q1', 10), ('east', 'ann', 'q2', 20), ('west', 'bob', 'q2', 5), ('west', 'bob', 'q3', 7)",
+        &[],
+    ).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_crosstab_by_position() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+    setup_sales(client).await;
+
+    let rows = query_rows(client,
+        "SELECT * FROM crosstab('SELECT region, quarter, amount FROM sales ORDER BY 1, 2') \
+         AS ct(region text, first_quarter int, second_quarter int)").await;
+    assert_eq!(rows, vec![
+        vec![s("east"), s("10"), s("20")],
+        vec![s("west"), s("5"), s("7")],
+    ]);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_crosstab_with_categories() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+    setup_sales(client).await;
+
+    // Extra columns between row_name and category are carried over; missing categories are NULL
+    let sql = "SELECT region, manager, q1, q3 FROM crosstab(\
+                   'SELECT region, manager, quarter, amount FROM sales ORDER BY 1', \
+                   'SELECT DISTINCT quarter FROM sales ORDER BY 1') \
+               AS ct(region text, manager text, q1 int, q2 int, q3 int) ORDER BY region";
+    let rows = query_rows(client, sql).await;
+    assert_eq!(rows, vec![
+        vec![s("east"), s("ann"), s("10"), None],
+        vec![s("west"), s("bob"), None, s("7")],
+    ]);
+
+    // The extended protocol sees the same rows
+    let rows = client.query(sql, &[]).await.unwrap();
+    let regions: Vec<(String, Option<i32>)> = rows.iter().map(|row| (row.get(0), row.get(2))).collect();
+    assert_eq!(regions, vec![("east".to_string(), Some(10)), ("west".to_string(), None)]);
+
+    // A column list that doesn't match the categories is rejected
+    let err = client.simple_query(
+        "SELECT * FROM crosstab('SELECT region, quarter, amount FROM sales', 'SELECT DISTINCT quarter FROM sales') \
+         AS ct(region text, q1 int)"
+    ).await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::DATATYPE_MISMATCH), "unexpected error: {err:?}");
+    assert!(err.to_string().contains("crosstab returns 4"), "unexpected error: {err}");
+
+    server.abort();
+}
@@ -0,0 +1,100 @@
+mod common;
+use common::setup_test_server;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+use tokio_postgres::types::Type;
+
+/// Values of each row of a simple query
+async fn simple_rows(client: &tokio_postgres::Client, query: &str) -> Vec<Vec<String>> {
+    let messages = client.simple_query(query).await.unwrap();
+    messages.iter().filter_map(|m| match m {
+        tokio_postgres::SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).unwrap_or("NULL").to_string()).collect()),
+        _ => None,
+    }).collect()
+}
+
+async fn setup_orders(client: &tokio_postgres::Client) {
+    client.batch_execute(
+        "CREATE TABLE orders (id SERIAL PRIMARY KEY, amount NUMERIC(10,2), placed TIMESTAMPTZ, qty INTEGER, active BOOLEAN);
+         INSERT INTO orders (amount, placed, qty, active) VALUES
+             (1.50, '2024-01-01 00:00:00+00', 2, true),
+             (2.25, '2024-01-02 00:00:00+00', 3, false);"
+    ).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_materialized_hints() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+    setup_orders(client).await;
+
+    let rows = simple_rows(client, "WITH o AS MATERIALIZED (SELECT id, qty FROM orders) SELECT * FROM o ORDER BY id").await;
+    assert_eq!(rows, [["1", "2"], ["2", "3"]]);
+
+    let rows = client.query("WITH o AS NOT MATERIALIZED (SELECT id, qty FROM orders WHERE id = $1) SELECT qty FROM o", &[&2i32]).await.unwrap();
+    assert_eq!(rows[0].get::<_, i32>(0), 3);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_column_types_through_ctes() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+    setup_orders(client).await;
+
+    // Column lists and chained CTEs keep the types of what they select, so booleans
+    // and timestamps are sent as such rather than as their storage
+    let rows = simple_rows(client, "WITH o(x, y) AS (SELECT qty, active FROM orders) SELECT x, y FROM o ORDER BY x").await;
+    assert_eq!(rows, [["2", "t"], ["3", "f"]]);
+    let rows = simple_rows(client, "WITH a AS (SELECT id, placed FROM orders), b AS (SELECT * FROM a) SELECT b.placed AS at FROM b ORDER BY b.id").await;
+    assert_eq!(rows, [["2024-01-01 00:00:00+00"], ["2024-01-02 00:00:00+00"]]);
+
+    // The extended protocol describes and runs WITH queries like any SELECT
+    let statement = client.prepare("WITH o(x, y) AS (SELECT qty, active FROM orders) SELECT x, y FROM o ORDER BY x").await.unwrap();
+    assert_eq!(statement.columns().iter().map(|c| c.type_().clone()).collect::<Vec<_>>(), [Type::INT4, Type::BOOL]);
+    let rows = client.query(&statement, &[]).await.unwrap();
+    assert_eq!((rows[0].get::<_, i32>(0), rows[0].get::<_, bool>(1)), (2, true));
+
+    let statement = client.prepare("WITH d AS (SELECT id, amount * 2 AS dbl FROM orders) SELECT id, dbl FROM d").await.unwrap();
+    assert_eq!(statement.columns()[1].type_(), &Type::NUMERIC);
+    let statement = client.prepare("WITH RECURSIVE t(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM t WHERE n < 3) SELECT n FROM t").await.unwrap();
+    assert_eq!(statement.columns()[0].type_(), &Type::INT4);
+
+    let statement = client.prepare("WITH d AS (SELECT id, amount, placed FROM orders) SELECT amount, placed FROM d WHERE id = $1").await.unwrap();
+    assert_eq!(statement.columns().iter().map(|c| c.type_().clone()).collect::<Vec<_>>(), [Type::NUMERIC, Type::TIMESTAMPTZ]);
+    let row = client.query_one(&statement, &[&1i32]).await.unwrap();
+    assert_eq!(row.get::<_, Decimal>(0), Decimal::from_str("1.50").unwrap());
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_recursive_ctes() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    let rows = simple_rows(client, "WITH RECURSIVE t(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM t WHERE n < 3) SELECT n FROM t").await;
+    assert_eq!(rows, [["1"], ["2"], ["3"]]);
+    let rows = client.query("WITH RECURSIVE t(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM t WHERE n < $1) SELECT n FROM t", &[&4i32]).await.unwrap();
+    assert_eq!(rows.iter().map(|row| row.get::<_, i32>(0)).collect::<Vec<_>>(), [1, 2, 3, 4]);
+
+    // UNION drops rows already produced, which ends the recursion
+    let rows = simple_rows(client, "WITH RECURSIVE t(n) AS (SELECT 1 UNION SELECT (n + 1) % 3 FROM t) SELECT n FROM t ORDER BY n").await;
+    assert_eq!(rows, [["0"], ["1"], ["2"]]);
+
+    // Forms PostgreSQL rejects are rejected with its errors
+    let code = |error: tokio_postgres::Error| error.code().unwrap().code().to_string();
+    let error = client.simple_query("WITH RECURSIVE t(n) AS (SELECT n FROM t) SELECT n FROM t").await.unwrap_err();
+    assert_eq!(code(error), "42P19");
+    let error = client.simple_query("WITH RECURSIVE t(n) AS (SELECT 1 INTERSECT SELECT n FROM t) SELECT n FROM t").await.unwrap_err();
+    assert_eq!(code(error), "42P19");
+    let error = client.prepare("WITH RECURSIVE t(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM t UNION ALL SELECT n + 2 FROM t) SELECT n FROM t").await.unwrap_err();
+    assert_eq!(code(error), "42P19");
+
+    // Without RECURSIVE a CTE cannot read itself
+    let error = client.simple_query("WITH t(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM t WHERE n < 3) SELECT n FROM t").await.unwrap_err();
+    assert_eq!(code(error), "42P01");
+
+    server.abort();
+}
@@ -79,14 +79,14 @@ async fn test_boolean_representations() {
     let rows = client.query("SELECT id FROM bool_test WHERE flag = true", &[]).await.unwrap();
     assert_eq!(rows.len(), 2);
     
-    // Test boolean in expressions (SQLite returns integers for comparisons)
+    // Test boolean in expressions (comparisons are typed as bool)
     let row = client.query_one("SELECT 1 = 1", &[]).await.unwrap();
-    let val: i32 = row.get(0);
-    assert_eq!(val, 1); // SQLite returns 1 for true (no metadata available for expressions)
+    let val: bool = row.get(0);
+    assert!(val);
     
     let row = client.query_one("SELECT 1 = 0", &[]).await.unwrap();
-    let val: i32 = row.get(0);
-    assert_eq!(val, 0); // SQLite returns 0 for false (no metadata available for expressions)
+    let val: bool = row.get(0);
+    assert!(!val);
     
     server.abort();
 }
@@ -130,8 +130,8 @@ async fn test_null_handling() {
     
     // Test NULL in expressions
     let row = client.query_one("SELECT NULL IS NULL", &[]).await.unwrap();
-    let val: i32 = row.get(0);
-    assert_eq!(val, 1); // true
+    let val: bool = row.get(0);
+    assert!(val);
     
     // Test COALESCE
     let row = client.query_one("SELECT COALESCE(nullable_int, 0) FROM null_test WHERE id = 1", &[]).await.unwrap();
@@ -453,14 +453,14 @@ async fn test_special_values() {
     let val: i64 = row.get(0);
     assert_eq!(val, i64::MIN);
     
-    // Test zero values (SQLite converts 0.0 to integer in expressions)
+    // Test zero values; 0.0 is a numeric literal, as in PostgreSQL
     let row = client.query_one("SELECT 0, 0.0, ''", &[]).await.unwrap();
     let int_zero: i32 = row.get(0);
-    let float_zero_as_int: i32 = row.get(1); // SQLite treats 0.0 as integer in expressions
+    let numeric_zero: rust_decimal::Decimal = row.get(1);
     let empty_string: String = row.get(2);
     
     assert_eq!(int_zero, 0);
-    assert_eq!(float_zero_as_int, 0); // 0.0 becomes 0 as integer
+    assert_eq!(numeric_zero, rust_decimal::Decimal::ZERO);
     assert_eq!(empty_string, "");
     
     server.abort();
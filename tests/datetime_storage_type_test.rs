@@ -46,10 +46,11 @@ async fn test_datetime_types_stored_as_integer() {
         let sqlite_type: String = row.get(2);
         println!("  Column {cid}: {name} -> {sqlite_type}");
         
-        // All datetime columns should be stored as INTEGER
+        // All datetime columns should be stored as INTEGER, intervals as their text form
+        let expected = if name == "interval_col" { "TEXT" } else { "INTEGER" };
         if name != "id" {
-            assert_eq!(sqlite_type, "INTEGER", 
-                "Column {name} should be stored as INTEGER, but got {sqlite_type}");
+            assert_eq!(sqlite_type, expected, 
+                "Column {name} should be stored as {expected}, but got {sqlite_type}");
         }
     }
     
@@ -134,9 +135,10 @@ async fn test_pgsqlite_schema_metadata() {
         let sqlite_type: String = row.get(2);
         println!("  {column_name} -> pg_type: {pg_type}, sqlite_type: {sqlite_type}");
         
-        // All datetime types should be stored as INTEGER in SQLite
-        assert_eq!(sqlite_type, "INTEGER", 
-            "Column {column_name} should have sqlite_type INTEGER, but got {sqlite_type}");
+        // All datetime types should be stored as INTEGER in SQLite, intervals as TEXT
+        let expected = if column_name == "interval_col" { "TEXT" } else { "INTEGER" };
+        assert_eq!(sqlite_type, expected, 
+            "Column {column_name} should have sqlite_type {expected}, but got {sqlite_type}");
         
         // Verify the PostgreSQL type is preserved correctly
         match column_name.as_str() {
@@ -0,0 +1,61 @@
+mod common;
+use common::setup_test_server;
+
+#[tokio::test]
+async fn test_insert_select_parameter_types() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TABLE orders (id INTEGER PRIMARY KEY, total INTEGER, placed_at TIMESTAMP)", &[]).await.unwrap();
+    client.execute("CREATE TABLE archive (order_id INTEGER, archived_at TIMESTAMP, total INTEGER)", &[]).await.unwrap();
+    client.simple_query(
+        "INSERT INTO orders (id, total, placed_at) VALUES (1, 50, '2024-01-01 10:00:00'), (2, 150, '2024-02-01 10:00:00'), (3, 250, '2024-03-01 10:00:00')",
+    ).await.unwrap();
+
+    let archived_at = chrono::NaiveDate::from_ymd_opt(2025, 5, 17).unwrap().and_hms_micro_opt(8, 15, 0, 125_000).unwrap();
+    let cutoff = chrono::NaiveDate::from_ymd_opt(2024, 1, 15).unwrap().and_hms_opt(0, 0, 0).unwrap();
+
+    // $1 takes archive.archived_at, $2 orders.total and $3 orders.placed_at; all sent in binary
+    let inserted = client.execute(
+        "INSERT INTO archive (order_id, archived_at, total) SELECT o.id, $1, o.total FROM orders o WHERE o.total < $2 AND o.placed_at > $3",
+        &[&archived_at, &200i32, &cutoff],
+    ).await.unwrap();
+    assert_eq!(inserted, 1);
+
+    let row = client.query_one("SELECT order_id, archived_at, total FROM archive", &[]).await.unwrap();
+    assert_eq!(row.get::<_, i32>(0), 2);
+    assert_eq!(row.get::<_, chrono::NaiveDateTime>(1), archived_at);
+    assert_eq!(row.get::<_, i32>(2), 150);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_update_from_parameter_types() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TABLE suppliers (id INTEGER PRIMARY KEY, rating INTEGER)", &[]).await.unwrap();
+    client.execute("CREATE TABLE items (id INTEGER PRIMARY KEY, supplier_id INTEGER, price INTEGER, reviewed_at TIMESTAMP)", &[]).await.unwrap();
+    client.execute("INSERT INTO suppliers (id, rating) VALUES (1, 2), (2, 5)", &[]).await.unwrap();
+    client.execute("INSERT INTO items (id, supplier_id, price) VALUES (10, 1, 100), (11, 2, 200), (12, 2, 300)", &[]).await.unwrap();
+
+    let reviewed_at = chrono::NaiveDate::from_ymd_opt(2025, 7, 4).unwrap().and_hms_opt(16, 45, 30).unwrap();
+
+    // SET parameters take the target columns' types, WHERE parameters the compared columns'
+    let updated = client.execute(
+        "UPDATE items AS i SET price = $1, reviewed_at = $2 FROM suppliers s WHERE s.id = i.supplier_id AND s.rating >= $3 AND i.price < $4",
+        &[&999i32, &reviewed_at, &4i32, &250i32],
+    ).await.unwrap();
+    assert_eq!(updated, 1);
+
+    let rows = client.query("SELECT id, price, reviewed_at FROM items ORDER BY id", &[]).await.unwrap();
+    let rows: Vec<(i32, i32, Option<chrono::NaiveDateTime>)> = rows.iter().map(|r| (r.get(0), r.get(1), r.get(2))).collect();
+    assert_eq!(rows, vec![(10, 100, None), (11, 999, Some(reviewed_at)), (12, 300, None)]);
+
+    // Plain UPDATE statements are typed the same way
+    let updated = client.execute("UPDATE items SET reviewed_at = $1 WHERE id = $2", &[&reviewed_at, &12i32]).await.unwrap();
+    assert_eq!(updated, 1);
+
+    server.abort();
+}
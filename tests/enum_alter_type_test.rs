@@ -0,0 +1,96 @@
+mod common;
+use common::*;
+
+fn sqlstate(err: &tokio_postgres::Error) -> &str {
+    err.as_db_error().map(|e| e.code().code()).unwrap_or_default()
+}
+
+async fn labels(client: &tokio_postgres::Client) -> Vec<String> {
+    client.query(
+        "SELECT enumlabel FROM pg_enum WHERE enumtypid = (SELECT oid FROM pg_type WHERE typname = 'mood') ORDER BY enumsortorder",
+        &[],
+    ).await.unwrap().iter().map(|row| row.get(0)).collect()
+}
+
+#[tokio::test]
+async fn test_add_value_positions() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute("CREATE TYPE mood AS ENUM ('happy', 'sad')").await.unwrap();
+    client.batch_execute("ALTER TYPE mood ADD VALUE 'ok' BEFORE 'sad'").await.unwrap();
+    client.batch_execute("ALTER TYPE mood ADD VALUE 'calm' AFTER 'happy'").await.unwrap();
+    client.batch_execute("ALTER TYPE mood ADD VALUE 'elated' BEFORE 'happy'").await.unwrap();
+    client.batch_execute("ALTER TYPE mood ADD VALUE 'angry'").await.unwrap();
+    assert_eq!(labels(client).await, ["elated", "happy", "calm", "ok", "sad", "angry"]);
+
+    let err = client.batch_execute("ALTER TYPE mood ADD VALUE 'ok'").await.unwrap_err();
+    assert_eq!(sqlstate(&err), "42710", "{err}");
+    assert!(err.to_string().contains("enum label \"ok\" already exists"), "{err}");
+    client.batch_execute("ALTER TYPE mood ADD VALUE IF NOT EXISTS 'ok'").await.unwrap();
+
+    let err = client.batch_execute("ALTER TYPE mood ADD VALUE 'meh' AFTER 'nope'").await.unwrap_err();
+    assert_eq!(sqlstate(&err), "22023", "{err}");
+    let err = client.batch_execute("ALTER TYPE nope ADD VALUE 'meh'").await.unwrap_err();
+    assert_eq!(sqlstate(&err), "42704", "{err}");
+
+    // Prepared statements go through the same handler
+    client.execute("ALTER TYPE mood ADD VALUE 'tired'", &[]).await.unwrap();
+    assert_eq!(labels(client).await.last().map(String::as_str), Some("tired"));
+}
+
+#[tokio::test]
+async fn test_rename_value() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE TYPE mood AS ENUM ('happy', 'sad');
+         CREATE TABLE diary (id INTEGER PRIMARY KEY, mood mood, moods mood[]);
+         INSERT INTO diary VALUES (1, 'sad', ARRAY['sad', 'happy']), (2, 'happy', ARRAY['happy'])"
+    ).await.unwrap();
+
+    client.batch_execute("ALTER TYPE mood RENAME VALUE 'sad' TO 'blue'").await.unwrap();
+    assert_eq!(labels(client).await, ["happy", "blue"]);
+
+    // Stored values follow the label
+    let rows = client.query("SELECT mood::text, moods::text FROM diary ORDER BY id", &[]).await.unwrap();
+    assert_eq!(rows[0].get::<_, String>(0), "blue");
+    assert!(rows[0].get::<_, String>(1).contains("blue"), "{:?}", rows[0]);
+    assert!(!rows[0].get::<_, String>(1).contains("sad"), "{:?}", rows[0]);
+    assert_eq!(rows[1].get::<_, String>(0), "happy");
+
+    let err = client.batch_execute("INSERT INTO diary VALUES (3, 'sad', NULL)").await.unwrap_err();
+    assert!(err.to_string().contains("invalid input value for enum mood: \"sad\""), "{err}");
+    client.batch_execute("INSERT INTO diary VALUES (3, 'blue', ARRAY['blue'])").await.unwrap();
+
+    let err = client.batch_execute("ALTER TYPE mood RENAME VALUE 'sad' TO 'grey'").await.unwrap_err();
+    assert_eq!(sqlstate(&err), "22023", "{err}");
+    let err = client.batch_execute("ALTER TYPE mood RENAME VALUE 'blue' TO 'happy'").await.unwrap_err();
+    assert_eq!(sqlstate(&err), "42710", "{err}");
+}
+
+#[tokio::test]
+async fn test_drop_type_cascade_drops_columns() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE TYPE mood AS ENUM ('happy', 'sad');
+         CREATE TABLE diary (id INTEGER PRIMARY KEY, mood mood, moods mood[], note TEXT);
+         INSERT INTO diary VALUES (1, 'sad', ARRAY['happy'], 'first')"
+    ).await.unwrap();
+    client.query("SELECT * FROM diary", &[]).await.unwrap();
+
+    let err = client.batch_execute("DROP TYPE mood").await.unwrap_err();
+    assert_eq!(sqlstate(&err), "2BP01", "{err}");
+
+    client.batch_execute("DROP TYPE mood CASCADE").await.unwrap();
+    let rows = client.query("SELECT * FROM diary", &[]).await.unwrap();
+    assert_eq!(rows[0].columns().iter().map(|c| c.name()).collect::<Vec<_>>(), ["id", "note"]);
+    assert_eq!(rows[0].get::<_, String>(1), "first");
+
+    // The name is free again
+    client.batch_execute("CREATE TYPE mood AS ENUM ('calm')").await.unwrap();
+    assert_eq!(labels(client).await, ["calm"]);
+}
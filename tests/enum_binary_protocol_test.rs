@@ -0,0 +1,112 @@
+mod common;
+use common::setup_test_server;
+use tokio_postgres::types::{FromSql, IsNull, Kind, ToSql, Type, to_sql_checked};
+
+/// An ENUM value as drivers map one, by its label in the binary format
+#[derive(Debug, PartialEq)]
+struct Mood(String);
+
+impl<'a> FromSql<'a> for Mood {
+    fn from_sql(_: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        Ok(Mood(std::str::from_utf8(raw)?.to_string()))
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        ty.name() == "mood" && matches!(ty.kind(), Kind::Enum(_))
+    }
+}
+
+impl ToSql for Mood {
+    fn to_sql(&self, _: &Type, out: &mut bytes::BytesMut) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        out.extend_from_slice(self.0.as_bytes());
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        ty.name() == "mood" && matches!(ty.kind(), Kind::Enum(_))
+    }
+
+    to_sql_checked!();
+}
+
+#[tokio::test]
+async fn test_enum_columns_and_parameters_use_enum_oid() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE TYPE mood AS ENUM ('happy', 'sad');
+         CREATE TABLE people (id INTEGER PRIMARY KEY, mood mood);
+         INSERT INTO people VALUES (1, 'happy')"
+    ).await.unwrap();
+
+    // The driver looks the type up in pg_type and pg_enum by its OID
+    let stmt = client.prepare("SELECT id, mood FROM people").await.unwrap();
+    let mood_type = stmt.columns()[1].type_();
+    assert_eq!(mood_type.name(), "mood");
+    assert_eq!(mood_type.schema(), "public");
+    assert_eq!(mood_type.kind(), &Kind::Enum(vec!["happy".to_string(), "sad".to_string()]));
+
+    let row = client.query_one(&stmt, &[]).await.unwrap();
+    assert_eq!(row.get::<_, Mood>(1), Mood("happy".to_string()));
+
+    let stmt = client.prepare("INSERT INTO people (id, mood) VALUES ($1, $2)").await.unwrap();
+    assert_eq!(stmt.params()[1].oid(), mood_type.oid());
+    client.execute(&stmt, &[&2i32, &Mood("sad".to_string())]).await.unwrap();
+
+    let rows = client.query("SELECT id FROM people WHERE mood = $1", &[&Mood("sad".to_string())]).await.unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].get::<_, i32>(0), 2);
+
+    // Labels are still checked when they arrive in the binary format
+    let err = client.execute(&stmt, &[&3i32, &Mood("angry".to_string())]).await.unwrap_err();
+    assert!(err.to_string().contains("invalid input value for enum mood"), "{err}");
+}
+
+#[tokio::test]
+async fn test_enum_arrays_use_array_type_oid() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE TYPE mood AS ENUM ('happy', 'sad');
+         CREATE TABLE diary (id INTEGER PRIMARY KEY, moods mood[]);
+         INSERT INTO diary VALUES (1, '{happy,sad}')"
+    ).await.unwrap();
+
+    // The array type is looked up by its own OID and leads to the ENUM type
+    let stmt = client.prepare("SELECT moods FROM diary WHERE id = 1").await.unwrap();
+    let array_type = stmt.columns()[0].type_().clone();
+    assert_eq!(array_type.name(), "_mood");
+    let Kind::Array(member) = array_type.kind() else {
+        panic!("not an array type: {array_type:?}");
+    };
+    assert_eq!(member.name(), "mood");
+    assert!(matches!(member.kind(), Kind::Enum(_)));
+
+    let row = client.query_one(&stmt, &[]).await.unwrap();
+    assert_eq!(row.get::<_, Vec<Mood>>(0), [Mood("happy".to_string()), Mood("sad".to_string())]);
+
+    let insert = client.prepare("INSERT INTO diary (id, moods) VALUES ($1, $2)").await.unwrap();
+    assert_eq!(insert.params()[1], array_type);
+    client.execute(&insert, &[&2i32, &vec![Mood("sad".to_string())]]).await.unwrap();
+    let row = client.query_one("SELECT moods FROM diary WHERE id = 2", &[]).await.unwrap();
+    assert_eq!(row.get::<_, Vec<Mood>>(0), [Mood("sad".to_string())]);
+
+    // Every element must be a label of the ENUM
+    let err = client.execute(&insert, &[&3i32, &vec![Mood("happy".to_string()), Mood("angry".to_string())]]).await.unwrap_err();
+    assert!(err.to_string().contains("invalid input value for enum mood: \"angry\""), "{err}");
+    let err = client.batch_execute("INSERT INTO diary VALUES (4, ARRAY['meh'])").await.unwrap_err();
+    assert!(err.to_string().contains("invalid input value for enum mood: \"meh\""), "{err}");
+    client.batch_execute("INSERT INTO diary VALUES (5, ARRAY['happy', 'happy'])").await.unwrap();
+    client.batch_execute("INSERT INTO diary VALUES (8, NULL)").await.unwrap();
+
+    // Rebuilding the table keeps validating every element of the array
+    client.batch_execute("ALTER TABLE diary ADD COLUMN note TEXT").await.unwrap();
+    client.batch_execute("INSERT INTO diary VALUES (6, ARRAY['sad', 'happy'], 'ok')").await.unwrap();
+    let err = client.batch_execute("INSERT INTO diary VALUES (7, ARRAY['meh'], 'no')").await.unwrap_err();
+    assert!(err.to_string().contains("invalid input value for enum mood: \"meh\""), "{err}");
+
+    // The array column depends on the ENUM type
+    assert!(client.batch_execute("DROP TYPE mood").await.is_err());
+}
@@ -1,5 +1,6 @@
 mod common;
 use common::setup_test_server;
+use tokio_postgres::types::Type;
 
 #[tokio::test]
 async fn test_enum_type_oid_in_schema() {
@@ -102,8 +103,9 @@ async fn test_enum_with_text_protocol() {
         .await
         .expect("Failed to create table");
     
-    // Use prepared statement but query with text values only
-    let stmt = client.prepare("INSERT INTO tasks (id, status) VALUES ($1, $2)")
+    // Use prepared statement but query with text values only; the parameter would
+    // otherwise take the ENUM type, which &str doesn't bind to
+    let stmt = client.prepare_typed("INSERT INTO tasks (id, status) VALUES ($1, $2)", &[Type::INT4, Type::TEXT])
         .await
         .expect("Failed to prepare statement");
     
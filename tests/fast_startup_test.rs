@@ -0,0 +1,87 @@
+mod common;
+use common::{spawn_server, ServerProcess};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+async fn start_server(fast_startup: bool) -> ServerProcess {
+    let mut args = vec!["--in-memory", "--log-level", "error"];
+    if fast_startup {
+        args.push("--fast-startup");
+    }
+    spawn_server(args).await
+}
+
+/// Send a StartupMessage and collect the backend messages up to ReadyForQuery
+async fn handshake(stream: &mut TcpStream) -> Vec<(u8, Vec<u8>)> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&196608i32.to_be_bytes());
+    for value in ["user", "postgres", "database", "main"] {
+        body.extend_from_slice(value.as_bytes());
+        body.push(0);
+    }
+    body.push(0);
+    let mut startup = ((body.len() + 4) as i32).to_be_bytes().to_vec();
+    startup.extend_from_slice(&body);
+    stream.write_all(&startup).await.unwrap();
+
+    let mut messages = Vec::new();
+    loop {
+        let tag = stream.read_u8().await.unwrap();
+        let len = stream.read_i32().await.unwrap() as usize;
+        let mut payload = vec![0u8; len - 4];
+        stream.read_exact(&mut payload).await.unwrap();
+        messages.push((tag, payload));
+        if tag == b'Z' {
+            return messages;
+        }
+    }
+}
+
+fn parameter_names(messages: &[(u8, Vec<u8>)]) -> Vec<String> {
+    messages.iter()
+        .filter(|(tag, _)| *tag == b'S')
+        .map(|(_, payload)| {
+            let end = payload.iter().position(|b| *b == 0).unwrap();
+            String::from_utf8(payload[..end].to_vec()).unwrap()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_fast_startup_handshake() {
+    let server = start_server(true).await;
+
+    let mut stream = TcpStream::connect(("127.0.0.1", server.port)).await.unwrap();
+    let messages = handshake(&mut stream).await;
+    let tags: Vec<u8> = messages.iter().map(|(tag, _)| *tag).collect();
+    assert_eq!(tags.first(), Some(&b'R'));
+    assert_eq!(&tags[tags.len() - 2..], b"KZ");
+
+    // Only the parameters drivers need are reported
+    let mut names = parameter_names(&messages);
+    names.sort();
+    let mut expected: Vec<String> = pgsqlite::protocol::startup::FAST_STARTUP_PARAMETERS.iter().map(|s| s.to_string()).collect();
+    expected.sort();
+    assert_eq!(names, expected);
+
+    // BackendKeyData has the session's pid and a zero secret
+    let (_, key_data) = &messages[messages.len() - 2];
+    assert_eq!(&key_data[4..8], &[0, 0, 0, 0]);
+    drop(stream);
+
+    // Regular clients work over the trimmed handshake
+    let client = server.connect().await;
+    let row = client.query_one("SELECT 1::int4", &[]).await.unwrap();
+    assert_eq!(row.get::<_, i32>(0), 1);
+    let rows = client.simple_query("SHOW IntervalStyle").await.unwrap();
+    assert!(!rows.is_empty());
+}
+
+#[tokio::test]
+async fn test_default_startup_reports_all_parameters() {
+    let server = start_server(false).await;
+
+    let mut stream = TcpStream::connect(("127.0.0.1", server.port)).await.unwrap();
+    let messages = handshake(&mut stream).await;
+    assert!(parameter_names(&messages).contains(&"IntervalStyle".to_string()));
+}
@@ -0,0 +1,58 @@
+use tokio::net::TcpListener;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::NoTls;
+use uuid::Uuid;
+
+/// Errors for features pgsqlite lacks come as feature_not_supported with a HINT at the alternative
+#[tokio::test]
+async fn test_unsupported_features_have_hints() {
+    let test_id = Uuid::new_v4().to_string().replace("-", "");
+    let db_path = format!("/tmp/pgsqlite_test_{test_id}.db");
+    let db_path_clone = db_path.clone();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let db_handler = std::sync::Arc::new(pgsqlite::session::DbHandler::new(&db_path_clone).unwrap());
+        let (stream, addr) = listener.accept().await.unwrap();
+        let _ = pgsqlite::handle_test_connection_with_pool(stream, addr, db_handler).await;
+    });
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    let (client, connection) = tokio_postgres::connect(
+        &format!("host=localhost port={port} dbname=test user=testuser"),
+        NoTls,
+    ).await.unwrap();
+    tokio::spawn(connection);
+
+    client.batch_execute("CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)").await.unwrap();
+
+    for (query, hint) in [
+        // Statements SQLite can't parse
+        ("LISTEN item_changes", "poll a table for changes"),
+        ("CREATE SEQUENCE item_ids", "SERIAL"),
+        ("CREATE MATERIALIZED VIEW item_names AS SELECT name FROM items", "CREATE TABLE ... AS SELECT"),
+        // Features pgsqlite refuses itself
+        ("COPY items TO STDOUT (FORMAT binary)", "FORMAT text or FORMAT csv"),
+        ("CREATE FUNCTION f() RETURNS int AS $$ SELECT 1; SELECT 2 $$ LANGUAGE sql", "function of its own"),
+    ] {
+        let err = client.simple_query(query).await.unwrap_err();
+        let db_error = err.as_db_error().unwrap_or_else(|| panic!("{query}: {err:?}"));
+        assert_eq!(db_error.code(), &SqlState::FEATURE_NOT_SUPPORTED, "{query}: {db_error:?}");
+        assert!(db_error.hint().is_some_and(|h| h.contains(hint)), "{query}: {db_error:?}");
+    }
+
+    client.simple_query("SET pgsqlite.strict_compatibility = error").await.unwrap();
+    let err = client.query("SELECT name FROM items WHERE id = $1 FOR UPDATE", &[&1i32]).await.unwrap_err();
+    assert_eq!(
+        err.as_db_error().unwrap().hint(),
+        Some("SET pgsqlite.strict_compatibility = warn runs the statement anyway, with a warning.")
+    );
+
+    // Other errors keep their code and get no hint
+    let err = client.simple_query("SELECT name FORM items").await.unwrap_err();
+    assert_ne!(err.code(), Some(&SqlState::FEATURE_NOT_SUPPORTED));
+    assert_eq!(err.as_db_error().unwrap().hint(), None);
+
+    let _ = std::fs::remove_file(db_path);
+}
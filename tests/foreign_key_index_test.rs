@@ -0,0 +1,108 @@
+use futures::{stream, StreamExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::{AsyncMessage, NoTls, SimpleQueryMessage};
+use uuid::Uuid;
+
+/// Connect to a fresh server and collect the notices it sends
+async fn connect_with_notices() -> (tokio_postgres::Client, mpsc::UnboundedReceiver<String>, String) {
+    let test_id = Uuid::new_v4().to_string().replace("-", "");
+    let db_path = format!("/tmp/pgsqlite_test_{test_id}.db");
+    let db_path_clone = db_path.clone();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let db_handler = std::sync::Arc::new(pgsqlite::session::DbHandler::new(&db_path_clone).unwrap());
+        let (stream, addr) = listener.accept().await.unwrap();
+        let _ = pgsqlite::handle_test_connection_with_pool(stream, addr, db_handler).await;
+    });
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    let (client, mut connection) = tokio_postgres::connect(
+        &format!("host=localhost port={port} dbname=test user=testuser"),
+        NoTls,
+    ).await.unwrap();
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut messages = stream::poll_fn(move |cx| connection.poll_message(cx));
+    tokio::spawn(async move {
+        while let Some(Ok(message)) = messages.next().await {
+            if let AsyncMessage::Notice(notice) = message {
+                let _ = tx.send(notice.message().to_string());
+            }
+        }
+    });
+
+    (client, rx, db_path)
+}
+
+fn drain(rx: &mut mpsc::UnboundedReceiver<String>) -> Vec<String> {
+    std::iter::from_fn(|| rx.try_recv().ok()).collect()
+}
+
+async fn index_names(client: &tokio_postgres::Client, table: &str) -> Vec<String> {
+    client.simple_query(&format!(
+        "SELECT name FROM sqlite_master WHERE type = 'index' AND tbl_name = '{table}' AND sql IS NOT NULL ORDER BY name"
+    )).await.unwrap().iter()
+        .filter_map(|msg| match msg {
+            SimpleQueryMessage::Row(row) => row.get(0).map(str::to_string),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_auto_index_foreign_keys() {
+    let (client, mut notices, db_path) = connect_with_notices().await;
+
+    client.simple_query("CREATE TABLE customers (id INTEGER PRIMARY KEY, region TEXT, code TEXT, UNIQUE (region, code))").await.unwrap();
+
+    // Off by default
+    client.simple_query("CREATE TABLE invoices (id INTEGER PRIMARY KEY, customer_id INTEGER REFERENCES customers(id))").await.unwrap();
+    assert!(index_names(&client, "invoices").await.is_empty());
+    assert!(drain(&mut notices).is_empty());
+
+    client.simple_query("SET pgsqlite.auto_index_foreign_keys = on").await.unwrap();
+    let rows = client.simple_query("SHOW pgsqlite.auto_index_foreign_keys").await.unwrap();
+    assert!(rows.iter().any(|msg| matches!(msg, SimpleQueryMessage::Row(row) if row.get(0) == Some("on"))));
+
+    // Uncovered foreign keys get an index; one led by an existing index does not
+    client.simple_query(
+        "CREATE TABLE orders (
+            id INTEGER PRIMARY KEY,
+            customer_id INTEGER REFERENCES customers(id),
+            invoice_id INTEGER REFERENCES invoices(id) UNIQUE,
+            region TEXT,
+            code TEXT,
+            FOREIGN KEY (region, code) REFERENCES customers(region, code)
+        )"
+    ).await.unwrap();
+    assert_eq!(index_names(&client, "orders").await, ["orders_customer_id_idx", "orders_region_code_idx"]);
+    let mut messages = drain(&mut notices);
+    messages.sort();
+    assert_eq!(messages, [
+        "created index \"orders_customer_id_idx\" on foreign key column \"customer_id\" of table \"orders\"",
+        "created index \"orders_region_code_idx\" on foreign key columns \"region\", \"code\" of table \"orders\"",
+    ]);
+
+    // ALTER TABLE ... ADD COLUMN ... REFERENCES, through the extended protocol
+    client.execute("ALTER TABLE invoices ADD COLUMN order_id INTEGER REFERENCES orders(id)", &[]).await.unwrap();
+    assert_eq!(index_names(&client, "invoices").await, ["invoices_customer_id_idx", "invoices_order_id_idx"]);
+    assert_eq!(drain(&mut notices).len(), 2);
+
+    // Rerunning DDL on an indexed table creates nothing new
+    client.execute("ALTER TABLE orders ADD COLUMN note TEXT", &[]).await.unwrap();
+    assert!(drain(&mut notices).is_empty());
+
+    let err = client.simple_query("SET pgsqlite.auto_index_foreign_keys = maybe").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::INVALID_PARAMETER_VALUE));
+
+    client.simple_query("SET pgsqlite.auto_index_foreign_keys = off").await.unwrap();
+    client.simple_query("CREATE TABLE refunds (id INTEGER PRIMARY KEY, order_id INTEGER REFERENCES orders(id))").await.unwrap();
+    assert!(index_names(&client, "refunds").await.is_empty());
+    assert!(drain(&mut notices).is_empty());
+
+    let _ = std::fs::remove_file(db_path);
+}
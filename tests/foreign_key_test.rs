@@ -0,0 +1,148 @@
+mod common;
+use common::*;
+
+async fn setup() -> TestServer {
+    let server = setup_test_server().await;
+    server.client.batch_execute(
+        "CREATE TABLE customers (id INTEGER PRIMARY KEY, name TEXT);
+        CREATE TABLE orders (
+            id INTEGER PRIMARY KEY,
+            customer_id INTEGER CONSTRAINT orders_customer REFERENCES public.customers (id) ON DELETE CASCADE
+        );
+        CREATE TABLE invoices (id INTEGER PRIMARY KEY, customer_id INTEGER REFERENCES customers ON DELETE SET NULL);
+        INSERT INTO customers VALUES (1, 'alice'), (2, 'bob');
+        INSERT INTO orders VALUES (10, 1), (11, 2);
+        INSERT INTO invoices VALUES (100, 2);"
+    ).await.unwrap();
+    server
+}
+
+fn db_error(error: &tokio_postgres::Error) -> &tokio_postgres::error::DbError {
+    error.as_db_error().unwrap_or_else(|| panic!("{error:?}"))
+}
+
+#[tokio::test]
+async fn test_violations_name_the_constraint() {
+    let server = setup().await;
+    let client = &server.client;
+
+    let err = client.execute("INSERT INTO orders (id, customer_id) VALUES ($1, $2)", &[&12i32, &99i32]).await.unwrap_err();
+    let err = db_error(&err);
+    assert_eq!(err.code().code(), "23503");
+    assert_eq!(err.message(), "insert or update on table \"orders\" violates foreign key constraint \"orders_customer\"");
+    assert_eq!(err.detail(), Some("Key (customer_id)=(99) is not present in table \"customers\"."));
+    assert_eq!(err.table(), Some("orders"));
+    assert_eq!(err.constraint(), Some("orders_customer"));
+
+    // Deleting a parent is blocked by a constraint without an ON DELETE action
+    client.batch_execute("CREATE TABLE notes (id INTEGER PRIMARY KEY, customer_id INTEGER REFERENCES customers (id));
+        INSERT INTO notes VALUES (1, 2)").await.unwrap();
+    let err = client.batch_execute("DELETE FROM customers WHERE id = 2").await.unwrap_err();
+    let err = db_error(&err);
+    assert_eq!(err.code().code(), "23503");
+    assert_eq!(err.message(), "update or delete on table \"customers\" violates foreign key constraint \"notes_customer_id_fkey\" on table \"notes\"");
+    assert_eq!(err.detail(), Some("Key (id)=(2) is still referenced from table \"notes\"."));
+    assert_eq!(err.table(), Some("notes"));
+
+    // The failed statement changed nothing
+    let count: i64 = client.query_one("SELECT COUNT(*) FROM orders", &[]).await.unwrap().get(0);
+    assert_eq!(count, 2);
+}
+
+#[tokio::test]
+async fn test_referential_actions() {
+    let server = setup().await;
+    let client = &server.client;
+
+    client.execute("DELETE FROM customers WHERE id = 2", &[]).await.unwrap();
+
+    let orders: Vec<i32> = client.query("SELECT id FROM orders ORDER BY id", &[]).await.unwrap()
+        .iter().map(|row| row.get(0)).collect();
+    assert_eq!(orders, [10]);
+    let row = client.query_one("SELECT customer_id FROM invoices WHERE id = 100", &[]).await.unwrap();
+    assert_eq!(row.get::<_, Option<i32>>(0), None);
+}
+
+#[tokio::test]
+async fn test_create_table_checks_references() {
+    let server = setup().await;
+    let client = &server.client;
+
+    let err = client.batch_execute("CREATE TABLE broken (x INTEGER REFERENCES missing (id))").await.unwrap_err();
+    assert_eq!(db_error(&err).code().code(), "42P01");
+    let err = client.batch_execute("CREATE TABLE broken (x INTEGER REFERENCES customers (nope))").await.unwrap_err();
+    assert_eq!(db_error(&err).code().code(), "42703");
+    let err = client.batch_execute("CREATE TABLE broken (x TEXT REFERENCES customers (name))").await.unwrap_err();
+    assert_eq!(db_error(&err).code().code(), "42830");
+
+    // None of the rejected tables were left behind
+    client.batch_execute("CREATE TABLE broken (x INTEGER)").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_alter_table_constraints() {
+    let server = setup().await;
+    let client = &server.client;
+
+    client.batch_execute("CREATE TABLE payments (id INTEGER PRIMARY KEY, order_id INTEGER);
+        INSERT INTO payments VALUES (1, 10), (2, 42)").await.unwrap();
+
+    // Existing rows are checked when the constraint is added
+    let err = client.batch_execute("ALTER TABLE payments ADD CONSTRAINT payments_order FOREIGN KEY (order_id) REFERENCES orders (id)")
+        .await.unwrap_err();
+    let err = db_error(&err);
+    assert_eq!(err.code().code(), "23503");
+    assert_eq!(err.detail(), Some("Key (order_id)=(42) is not present in table \"orders\"."));
+
+    client.batch_execute("DELETE FROM payments WHERE id = 2;
+        ALTER TABLE payments ADD CONSTRAINT payments_order FOREIGN KEY (order_id) REFERENCES orders (id)").await.unwrap();
+    let err = client.batch_execute("INSERT INTO payments VALUES (3, 42)").await.unwrap_err();
+    assert_eq!(db_error(&err).constraint(), Some("payments_order"));
+
+    client.batch_execute("ALTER TABLE payments DROP CONSTRAINT payments_order;
+        INSERT INTO payments VALUES (3, 42)").await.unwrap();
+    let err = client.batch_execute("ALTER TABLE payments DROP CONSTRAINT payments_order").await.unwrap_err();
+    assert_eq!(db_error(&err).code().code(), "42704");
+    client.batch_execute("ALTER TABLE payments DROP CONSTRAINT IF EXISTS payments_order").await.unwrap();
+
+    // Rebuilding a referenced table keeps the rows that point at it
+    client.batch_execute("ALTER TABLE customers ADD COLUMN email TEXT UNIQUE").await.unwrap();
+    let count: i64 = client.query_one("SELECT COUNT(*) FROM orders", &[]).await.unwrap().get(0);
+    assert_eq!(count, 2);
+}
+
+#[tokio::test]
+async fn test_drop_referenced_table() {
+    let server = setup().await;
+    let client = &server.client;
+
+    let err = client.batch_execute("DROP TABLE customers").await.unwrap_err();
+    let err = db_error(&err);
+    assert_eq!(err.code().code(), "2BP01");
+    assert!(err.message().contains("constraint orders_customer on table orders depends on table customers"), "{}", err.message());
+
+    // CASCADE drops the constraints, not the rows of the referencing tables
+    client.batch_execute("DROP TABLE customers CASCADE").await.unwrap();
+    let count: i64 = client.query_one("SELECT COUNT(*) FROM orders", &[]).await.unwrap().get(0);
+    assert_eq!(count, 2);
+    client.execute("INSERT INTO orders VALUES (12, 99)", &[]).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_commit_reports_deferred_violation() {
+    let server = setup().await;
+    let client = &server.client;
+
+    client.batch_execute("CREATE TABLE notes (
+            id INTEGER PRIMARY KEY,
+            customer_id INTEGER REFERENCES customers (id) DEFERRABLE INITIALLY DEFERRED
+        );
+        BEGIN;
+        INSERT INTO notes VALUES (1, 99)").await.unwrap();
+    let err = client.batch_execute("COMMIT").await.unwrap_err();
+    assert_eq!(db_error(&err).code().code(), "23503");
+
+    // The transaction was rolled back
+    let count: i64 = client.query_one("SELECT COUNT(*) FROM notes", &[]).await.unwrap().get(0);
+    assert_eq!(count, 0);
+}
@@ -0,0 +1,108 @@
+#![cfg(unix)]
+
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// SIGTERM stops the server cleanly and removes its Unix socket
+#[test]
+fn test_sigterm_shuts_down_gracefully() {
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let socket_dir = tempfile::tempdir().unwrap();
+    let socket_path = socket_dir.path().join(format!(".s.PGSQL.{port}"));
+
+    let mut server = Command::new(env!("CARGO_BIN_EXE_pgsqlite"))
+        .args(["--in-memory", "--port", &port.to_string(), "--log-level", "error"])
+        .arg("--socket-dir")
+        .arg(socket_dir.path())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to start server");
+
+    let started = Instant::now();
+    while !socket_path.exists() {
+        assert!(started.elapsed() < Duration::from_secs(30), "server did not create its socket");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    let status = Command::new("kill").args(["-TERM", &server.id().to_string()]).status().unwrap();
+    assert!(status.success());
+
+    let started = Instant::now();
+    let exit = loop {
+        if let Some(exit) = server.try_wait().unwrap() {
+            break exit;
+        }
+        if started.elapsed() > Duration::from_secs(30) {
+            let _ = server.kill();
+            panic!("server did not stop after SIGTERM");
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    assert!(exit.success(), "server exited with {exit}");
+    assert!(!socket_path.exists(), "the Unix socket should be removed on shutdown");
+}
+
+/// With --shutdown-grace the server refuses new clients the way PostgreSQL does while
+/// it shuts down, serves the open session, and exits once that session ends
+#[tokio::test]
+async fn test_shutdown_grace_refuses_new_connections() {
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let socket_dir = tempfile::tempdir().unwrap();
+    let config = format!("host=127.0.0.1 port={port} dbname=main user=postgres");
+    let connect = || tokio_postgres::connect(&config, tokio_postgres::NoTls);
+
+    let mut server = Command::new(env!("CARGO_BIN_EXE_pgsqlite"))
+        .args(["--in-memory", "--port", &port.to_string(), "--log-level", "error", "--shutdown-grace", "60"])
+        .arg("--socket-dir")
+        .arg(socket_dir.path())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to start server");
+
+    let started = Instant::now();
+    let client = loop {
+        match connect().await {
+            Ok((client, connection)) => {
+                tokio::spawn(connection);
+                break client;
+            }
+            Err(e) => {
+                assert!(started.elapsed() < Duration::from_secs(30), "server did not start: {e}");
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        }
+    };
+
+    let status = Command::new("kill").args(["-TERM", &server.id().to_string()]).status().unwrap();
+    assert!(status.success());
+
+    // Clients that connect before the signal is handled are let in and leave again
+    let started = Instant::now();
+    let err = loop {
+        match connect().await {
+            Ok(_) => {
+                assert!(started.elapsed() < Duration::from_secs(30), "server kept accepting connections");
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+            Err(e) => break e,
+        }
+    };
+    assert_eq!(err.code(), Some(&tokio_postgres::error::SqlState::CANNOT_CONNECT_NOW), "unexpected error: {err:?}");
+    client.simple_query("SELECT 1").await.unwrap();
+
+    drop(client);
+    let started = Instant::now();
+    let exit = loop {
+        if let Some(exit) = server.try_wait().unwrap() {
+            break exit;
+        }
+        if started.elapsed() > Duration::from_secs(30) {
+            let _ = server.kill();
+            panic!("server did not stop after its last session ended");
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    };
+    assert!(exit.success(), "server exited with {exit}");
+}
@@ -0,0 +1,140 @@
+mod common;
+use common::{spawn_server_with, ServerProcess};
+use futures::{stream, StreamExt};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::{AsyncMessage, Client, NoTls};
+
+async fn show(client: &Client, parameter: &str) -> String {
+    client.query_one(&format!("SHOW {parameter}"), &[]).await.unwrap().get(0)
+}
+
+/// Start a server syncing commits every `delay_ms` on `db_path`, and connect to it
+async fn start_server(db_path: &Path, delay_ms: u64) -> (ServerProcess, Client) {
+    let server = spawn_server_with(|command| {
+        command
+            .args(["--log-level", "error", "--group-commit-delay-ms", &delay_ms.to_string()])
+            .arg("--database")
+            .arg(db_path)
+            .arg("--socket-dir")
+            .arg(db_path.parent().unwrap());
+    }).await;
+    let client = server.connect().await;
+    (server, client)
+}
+
+/// With --group-commit-delay-ms, commits acknowledged to concurrent sessions survive the
+/// server being killed
+#[tokio::test]
+async fn test_group_commit_acknowledges_durable_commits() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("events.db");
+    let (mut server, client) = start_server(&db_path, 5).await;
+    client.batch_execute("CREATE TABLE events (id SERIAL PRIMARY KEY, worker INTEGER, note TEXT)").await.unwrap();
+
+    // Autocommit inserts from concurrent sessions
+    let mut writers = Vec::new();
+    for worker in 0..8 {
+        let client = server.connect().await;
+        writers.push(tokio::spawn(async move {
+            for _ in 0..20 {
+                client.execute("INSERT INTO events (worker, note) VALUES ($1, 'autocommit')", &[&worker]).await.unwrap();
+            }
+        }));
+    }
+    for writer in writers {
+        writer.await.unwrap();
+    }
+
+    // An explicit transaction is synced at its COMMIT
+    client.batch_execute(
+        "BEGIN; INSERT INTO events (worker, note) VALUES (8, 'first'); \
+         INSERT INTO events (worker, note) VALUES (8, 'second'); COMMIT"
+    ).await.unwrap();
+    let row = client.query_one("SELECT count(*) FROM events", &[]).await.unwrap();
+    assert_eq!(row.get::<_, i64>(0), 162);
+
+    // Everything acknowledged is on disk, in the database or its WAL
+    server.child.kill().unwrap();
+    server.child.wait().unwrap();
+    let conn = rusqlite::Connection::open(&db_path).unwrap();
+    let count: i64 = conn.query_row("SELECT count(*) FROM events", [], |row| row.get(0)).unwrap();
+    assert_eq!(count, 162);
+}
+
+/// synchronous_commit = off acknowledges commits without waiting for the group's fsync,
+/// for the session or, with SET LOCAL, for one transaction
+#[tokio::test]
+async fn test_synchronous_commit_off_skips_the_wait() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("events.db");
+    // Long enough that a commit waiting for the flush is told apart from one that doesn't
+    let delay = Duration::from_millis(500);
+    let (_server, client) = start_server(&db_path, delay.as_millis() as u64).await;
+    client.batch_execute("CREATE TABLE events (id INTEGER PRIMARY KEY, note TEXT)").await.unwrap();
+
+    assert_eq!(show(&client, "synchronous_commit").await, "on");
+    let started = Instant::now();
+    client.execute("INSERT INTO events (note) VALUES ('synced')", &[]).await.unwrap();
+    assert!(started.elapsed() >= delay);
+
+    client.batch_execute("SET synchronous_commit = off").await.unwrap();
+    assert_eq!(show(&client, "synchronous_commit").await, "off");
+    let started = Instant::now();
+    for _ in 0..3 {
+        client.execute("INSERT INTO events (note) VALUES ('unsynced')", &[]).await.unwrap();
+    }
+    assert!(started.elapsed() < delay, "waited {:?}", started.elapsed());
+
+    client.batch_execute("SET synchronous_commit = local").await.unwrap();
+    let started = Instant::now();
+    client.batch_execute("BEGIN; SET LOCAL synchronous_commit = off; INSERT INTO events (note) VALUES ('local'); COMMIT").await.unwrap();
+    assert!(started.elapsed() < delay, "waited {:?}", started.elapsed());
+    assert_eq!(show(&client, "synchronous_commit").await, "local");
+    // Past the flush the unwaited commit scheduled, a commit waits a whole delay again
+    tokio::time::sleep(delay).await;
+    let started = Instant::now();
+    client.execute("INSERT INTO events (note) VALUES ('synced')", &[]).await.unwrap();
+    assert!(started.elapsed() >= delay);
+
+    let err = client.batch_execute("SET synchronous_commit = sometimes").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::INVALID_PARAMETER_VALUE));
+    let row = client.query_one("SELECT count(*) FROM events", &[]).await.unwrap();
+    assert_eq!(row.get::<_, i64>(0), 6);
+}
+
+/// SET synchronous_commit = off is accepted whether or not commits are synced, and says
+/// so when they aren't
+#[tokio::test]
+async fn test_synchronous_commit_off_without_synced_commits() {
+    let dir = tempfile::tempdir().unwrap();
+    for (delay_ms, file, notified) in [(0, "normal.db", true), (50, "grouped.db", false)] {
+        let (server, _) = start_server(&dir.path().join(file), delay_ms).await;
+        let (client, mut connection) = tokio_postgres::connect(
+            &format!("host=127.0.0.1 port={} dbname=main user=postgres", server.port),
+            NoTls,
+        ).await.unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut messages = stream::poll_fn(move |cx| connection.poll_message(cx));
+        tokio::spawn(async move {
+            while let Some(Ok(message)) = messages.next().await {
+                if let AsyncMessage::Notice(notice) = message {
+                    let _ = tx.send(notice.message().to_string());
+                }
+            }
+        });
+
+        // The default --pragma-synchronous NORMAL doesn't sync commits in WAL mode
+        client.batch_execute("SET synchronous_commit = off").await.unwrap();
+        assert_eq!(show(&client, "synchronous_commit").await, "off");
+        let notices: Vec<String> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert_eq!(
+            notices.iter().any(|notice| notice.starts_with("synchronous_commit = off has no effect")),
+            notified,
+            "delay {delay_ms}: {notices:?}"
+        );
+        client.batch_execute("SET synchronous_commit = on").await.unwrap();
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,130 @@
+mod common;
+use common::setup_test_server;
+
+#[tokio::test]
+async fn test_serial_returning_id() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE TABLE serial_items (
+            id BIGSERIAL PRIMARY KEY,
+            name TEXT NOT NULL
+        )"
+    ).await.unwrap();
+
+    let first = client.query_one("INSERT INTO serial_items (name) VALUES ('a') RETURNING id", &[]).await.unwrap();
+    let second = client.query_one("INSERT INTO serial_items (name) VALUES ('b') RETURNING id", &[]).await.unwrap();
+
+    let first_id: i64 = first.get(0);
+    let second_id: i64 = second.get(0);
+    assert_eq!(first_id, 1);
+    assert_eq!(second_id, 2);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_identity_columns_in_information_schema() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE TABLE identity_items (
+            id INTEGER GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+            seq INTEGER GENERATED BY DEFAULT AS IDENTITY,
+            legacy_id SERIAL,
+            name TEXT
+        )"
+    ).await.unwrap();
+
+    client.simple_query("INSERT INTO identity_items (name) VALUES ('a')").await.unwrap();
+    client.simple_query("INSERT INTO identity_items (name) VALUES ('b')").await.unwrap();
+
+    // Non-rowid identity columns are still numbered
+    let rows = client.simple_query("SELECT id, seq, legacy_id FROM identity_items ORDER BY id").await.unwrap();
+    let values: Vec<(String, String, String)> = rows.iter().filter_map(|msg| match msg {
+        tokio_postgres::SimpleQueryMessage::Row(row) => Some((
+            row.get(0).unwrap().to_string(),
+            row.get(1).unwrap().to_string(),
+            row.get(2).unwrap().to_string(),
+        )),
+        _ => None,
+    }).collect();
+    assert_eq!(values, vec![
+        ("1".to_string(), "1".to_string(), "1".to_string()),
+        ("2".to_string(), "2".to_string(), "2".to_string()),
+    ]);
+
+    let rows = client.simple_query(
+        "SELECT column_name, is_identity, identity_generation, column_default FROM information_schema.columns WHERE table_name = 'identity_items'"
+    ).await.unwrap();
+    let columns: Vec<(String, String, Option<String>, Option<String>)> = rows.iter().filter_map(|msg| match msg {
+        tokio_postgres::SimpleQueryMessage::Row(row) => Some((
+            row.get(0).unwrap().to_string(),
+            row.get(1).unwrap().to_string(),
+            row.get(2).map(|s| s.to_string()),
+            row.get(3).map(|s| s.to_string()),
+        )),
+        _ => None,
+    }).collect();
+
+    assert_eq!(columns.len(), 4);
+    assert_eq!(columns[0], ("id".to_string(), "YES".to_string(), Some("ALWAYS".to_string()), None));
+    assert_eq!(columns[1], ("seq".to_string(), "YES".to_string(), Some("BY DEFAULT".to_string()), None));
+    assert_eq!(columns[2].1, "NO");
+    assert_eq!(columns[2].3.as_deref(), Some("nextval('identity_items_legacy_id_seq'::regclass)"));
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_identity_sequence_semantics() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE TABLE tickets (
+            id INTEGER GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+            seq INTEGER GENERATED ALWAYS AS IDENTITY,
+            note TEXT
+        );
+        CREATE TABLE logs (
+            id SERIAL,
+            msg TEXT
+        )"
+    ).await.unwrap();
+
+    // GENERATED ALWAYS columns refuse explicit values
+    for statement in [
+        "INSERT INTO tickets (id, note) VALUES (5, 'a')",
+        "INSERT INTO tickets (id, note) VALUES (-1, 'a')",
+        "INSERT INTO tickets VALUES (5, 5, 'a')",
+        "INSERT INTO tickets (seq, note) VALUES (5, 'a')",
+    ] {
+        let err = client.batch_execute(statement).await.unwrap_err();
+        assert_eq!(err.code(), Some(&tokio_postgres::error::SqlState::GENERATED_ALWAYS), "{err:?}");
+    }
+
+    let err = client.execute("INSERT INTO tickets (id, note) VALUES ($1, $2)", &[&-1i32, &"a"]).await.unwrap_err();
+    assert_eq!(err.code(), Some(&tokio_postgres::error::SqlState::GENERATED_ALWAYS), "{err:?}");
+
+    // Values of non-rowid identity columns are not reused after a DELETE
+    client.batch_execute(
+        "INSERT INTO tickets (note) VALUES ('a');
+         INSERT INTO tickets (note) VALUES ('b');
+         DELETE FROM tickets WHERE note = 'b';
+         INSERT INTO tickets (note) VALUES ('c')"
+    ).await.unwrap();
+    let rows = client.simple_query("SELECT seq FROM tickets WHERE note = 'c'").await.unwrap();
+    let seq = rows.iter().find_map(|msg| match msg {
+        tokio_postgres::SimpleQueryMessage::Row(row) => row.get(0).map(str::to_string),
+        _ => None,
+    });
+    assert_eq!(seq.as_deref(), Some("3"));
+
+    // A SERIAL column is not a primary key unless declared as one
+    client.batch_execute("INSERT INTO logs (id, msg) VALUES (1, 'x'); INSERT INTO logs (id, msg) VALUES (1, 'y')").await.unwrap();
+
+    server.abort();
+}
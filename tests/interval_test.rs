@@ -0,0 +1,148 @@
+mod common;
+use common::setup_test_server;
+use tokio_postgres::SimpleQueryMessage;
+use tokio_postgres::types::{FromSql, Type};
+
+async fn query_column(client: &tokio_postgres::Client, sql: &str) -> Vec<Option<String>> {
+    client.simple_query(sql).await.unwrap().iter()
+        .filter_map(|msg| match msg {
+            SimpleQueryMessage::Row(row) => Some(row.get(0).map(|s| s.to_string())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Binary INTERVAL as (microseconds, days, months)
+#[derive(Debug, PartialEq)]
+struct RawInterval(i64, i32, i32);
+
+impl<'a> FromSql<'a> for RawInterval {
+    fn from_sql(_ty: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        Ok(RawInterval(
+            i64::from_be_bytes(raw[0..8].try_into()?),
+            i32::from_be_bytes(raw[8..12].try_into()?),
+            i32::from_be_bytes(raw[12..16].try_into()?),
+        ))
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::INTERVAL
+    }
+}
+
+#[tokio::test]
+async fn test_interval_storage_and_encoding() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TABLE plans (id INTEGER PRIMARY KEY, period INTERVAL)", &[]).await.unwrap();
+    client.simple_query(
+        "INSERT INTO plans (id, period) VALUES (1, '1 year 2 months'), (2, '36 hours'), (3, '@ 3 days ago')",
+    ).await.unwrap();
+
+    let periods = query_column(client, "SELECT period FROM plans ORDER BY id").await;
+    assert_eq!(periods, vec![
+        Some("1 year 2 mons".to_string()),
+        Some("36:00:00".to_string()),
+        Some("-3 days".to_string()),
+    ]);
+
+    // Binary results carry months, days and microseconds separately
+    let rows = client.query("SELECT period FROM plans ORDER BY id", &[]).await.unwrap();
+    let periods: Vec<RawInterval> = rows.iter().map(|row| row.get(0)).collect();
+    assert_eq!(periods, vec![
+        RawInterval(0, 0, 14),
+        RawInterval(129_600_000_000, 0, 0),
+        RawInterval(0, -3, 0),
+    ]);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_interval_arithmetic_and_functions() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TABLE subscriptions (id INTEGER PRIMARY KEY, started_at TIMESTAMP)", &[]).await.unwrap();
+    client.simple_query("INSERT INTO subscriptions (id, started_at) VALUES (1, '2024-01-31 09:00:00')").await.unwrap();
+
+    // Months are calendar months, clamped to the end of shorter months
+    let next = query_column(client, "SELECT started_at + INTERVAL '1 month' FROM subscriptions").await;
+    assert_eq!(next, vec![Some("2024-02-29 09:00:00".to_string())]);
+
+    let age = query_column(client, "SELECT age('2001-04-10', '1957-06-13')").await;
+    assert_eq!(age, vec![Some("43 years 9 mons 27 days".to_string())]);
+
+    let justified = query_column(client, "SELECT justify_interval(INTERVAL '1 mon -1 hour')").await;
+    assert_eq!(justified, vec![Some("29 days 23:00:00".to_string())]);
+    let justified = query_column(client, "SELECT justify_hours(INTERVAL '50 hours')").await;
+    assert_eq!(justified, vec![Some("2 days 02:00:00".to_string())]);
+
+    let epoch = query_column(client, "SELECT EXTRACT(EPOCH FROM INTERVAL '1 day 1 hour')").await;
+    assert_eq!(epoch, vec![Some("90000".to_string())]);
+    let months = query_column(client, "SELECT EXTRACT(MONTH FROM age('2024-03-15', '2024-01-01'))").await;
+    assert_eq!(months, vec![Some("2".to_string())]);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_interval_operators_on_columns_and_now() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute(
+        "CREATE TABLE jobs (id INTEGER PRIMARY KEY, due_at TIMESTAMP, on_day DATE, took INTERVAL)",
+        &[],
+    ).await.unwrap();
+    client.simple_query(
+        "INSERT INTO jobs (id, due_at, on_day, took) VALUES
+         (1, '2024-01-31 00:00', '2024-01-31', '1 day 2 hours'),
+         (2, '2099-06-01 12:00:00', '2024-02-01', '90 minutes'),
+         (3, now() + INTERVAL '1 day', '2024-03-01', INTERVAL '3 hours')",
+    ).await.unwrap();
+
+    // now() is timestamp text; adding to it and comparing it with stored timestamps works
+    let later = query_column(client, "SELECT now() + INTERVAL '1 day' > now()").await;
+    assert_eq!(later, vec![Some("t".to_string())]);
+    let future = query_column(client, "SELECT id FROM jobs WHERE due_at > now() ORDER BY id").await;
+    assert_eq!(future, vec![Some("2".to_string()), Some("3".to_string())]);
+    let recent = query_column(client, "SELECT id FROM jobs WHERE due_at < now() - INTERVAL '1 year'").await;
+    assert_eq!(recent, vec![Some("1".to_string())]);
+
+    // Stored timestamps, dates and intervals
+    let ends = query_column(client, "SELECT due_at + took FROM jobs WHERE id < 3 ORDER BY id").await;
+    assert_eq!(ends, vec![Some("2024-02-01 02:00:00".to_string()), Some("2099-06-01 13:30:00".to_string())]);
+    let next = query_column(client, "SELECT on_day + INTERVAL '1 month' FROM jobs WHERE id = 1").await;
+    assert_eq!(next, vec![Some("2024-02-29 00:00:00".to_string())]);
+    let shifted = query_column(client, "SELECT '2024-01-31 00:00'::timestamp + INTERVAL '1 month'").await;
+    assert_eq!(shifted, vec![Some("2024-02-29 00:00:00".to_string())]);
+    let doubled = query_column(client, "SELECT took * 2 FROM jobs ORDER BY id").await;
+    assert_eq!(doubled, vec![
+        Some("2 days 04:00:00".to_string()),
+        Some("03:00:00".to_string()),
+        Some("06:00:00".to_string()),
+    ]);
+    let tripled = query_column(client, "SELECT INTERVAL '1 day' * 3").await;
+    assert_eq!(tripled, vec![Some("3 days".to_string())]);
+
+    // Intervals compare and sort by length
+    let longest = query_column(client, "SELECT id FROM jobs ORDER BY took DESC").await;
+    assert_eq!(longest, vec![Some("1".to_string()), Some("3".to_string()), Some("2".to_string())]);
+    let long = query_column(client, "SELECT id FROM jobs WHERE took > INTERVAL '2 hours' ORDER BY id").await;
+    assert_eq!(long, vec![Some("1".to_string()), Some("3".to_string())]);
+
+    // Aggregates return intervals, typed as such in both protocols
+    let totals = query_column(client, "SELECT sum(took) FROM jobs").await;
+    assert_eq!(totals, vec![Some("1 day 06:30:00".to_string())]);
+    let rows = client.query("SELECT sum(took), avg(took), max(took), due_at + took FROM jobs WHERE id = 1", &[]).await.unwrap();
+    let types: Vec<Type> = rows[0].columns().iter().map(|column| column.type_().clone()).collect();
+    assert_eq!(types, vec![Type::INTERVAL, Type::INTERVAL, Type::INTERVAL, Type::TIMESTAMP]);
+    assert_eq!(rows[0].get::<_, RawInterval>(0), RawInterval(7_200_000_000, 1, 0));
+
+    let later: bool = client.query_one("SELECT now() + INTERVAL '1 day' > now()", &[]).await.unwrap().get(0);
+    assert!(later);
+
+    server.abort();
+}
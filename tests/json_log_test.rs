@@ -0,0 +1,81 @@
+mod common;
+use common::spawn_server_with;
+use std::io::Read;
+use std::process::Stdio;
+use std::time::Duration;
+
+/// The structured events of the log, in order
+fn events(log: &str) -> Vec<serde_json::Value> {
+    log.lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap_or_else(|e| panic!("not JSON: {line}: {e}")))
+        .filter(|line| line["target"] == "pgsqlite::events")
+        .collect()
+}
+
+/// --log-format json logs each connection and statement as JSON objects that share
+/// the session's UUID, and a statement's objects its statement_id
+#[tokio::test]
+async fn test_json_log_events() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut server = spawn_server_with(|command| {
+        command
+            .args(["--in-memory", "--log-format", "json", "--log-level", "warn,pgsqlite::events=info"])
+            .arg("--socket-dir")
+            .arg(dir.path())
+            .stdout(Stdio::piped());
+    }).await;
+
+    let (client, connection) = tokio_postgres::connect(
+        &format!("host=127.0.0.1 port={} dbname=main user=app application_name=itest", server.port),
+        tokio_postgres::NoTls,
+    ).await.unwrap();
+    let connection = tokio::spawn(connection);
+    client.batch_execute("CREATE TABLE t (id INTEGER PRIMARY KEY); INSERT INTO t VALUES (1), (2)").await.unwrap();
+    assert_eq!(client.query("SELECT id FROM t WHERE id > $1::int8", &[&0i64]).await.unwrap().len(), 2);
+    let e = client.simple_query("INSERT INTO t VALUES (1)").await.unwrap_err();
+    assert_eq!(e.code().unwrap().code(), "23505");
+    drop(client);
+    connection.await.unwrap().unwrap();
+
+    // Wait for the connection_close, then stop the server to read the whole log
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let _ = server.child.kill();
+    let _ = server.child.wait();
+    let mut log = String::new();
+    server.child.stdout.take().unwrap().read_to_string(&mut log).unwrap();
+    let events = events(&log);
+
+    let names: Vec<&str> = events.iter().map(|event| event["event"].as_str().unwrap()).collect();
+    assert_eq!(names, [
+        "connection_open",
+        "query_start", "query_end",
+        "query_start", "query_end",
+        "query_start", "query_end",
+        "query_start", "query_error",
+        "connection_close",
+    ], "{log}");
+
+    let open = &events[0];
+    assert_eq!(open["user"], "app");
+    assert_eq!(open["database"], "main");
+    assert_eq!(open["application_name"], "itest");
+    let session_id = open["session_id"].as_str().unwrap();
+    assert_eq!(session_id.len(), 36);
+    assert!(events.iter().all(|event| event["session_id"] == session_id && event["pid"] == open["pid"]));
+
+    // The statements are numbered in order, and each start and end share the number
+    let statements: Vec<_> = events[1..9].chunks(2).collect();
+    for (i, pair) in statements.iter().enumerate() {
+        assert_eq!(pair[0]["statement_id"], i as u64 + 1);
+        assert_eq!(pair[1]["statement_id"], i as u64 + 1);
+    }
+    assert_eq!(statements[0][0]["query"], "CREATE TABLE t (id INTEGER PRIMARY KEY)");
+    assert_eq!(statements[1][1]["rows"], 2);
+    assert_eq!(statements[2][0]["query"], "SELECT id FROM t WHERE id > $1::int8");
+    assert_eq!(statements[2][1]["rows"], 2);
+    assert!(statements[2][1]["duration_ms"].as_f64().unwrap() >= 0.0);
+    assert_eq!(statements[3][1]["level"], "WARN");
+    assert_eq!(statements[3][1]["sqlstate"], "23505");
+    assert!(statements[3][1]["error"].as_str().unwrap().contains("unique"), "{log}");
+    assert!(events[9]["duration_ms"].as_f64().unwrap() > 0.0);
+}
@@ -0,0 +1,171 @@
+mod common;
+use common::setup_test_server;
+use tokio_postgres::SimpleQueryMessage;
+
+async fn query_column(client: &tokio_postgres::Client, sql: &str) -> Vec<Option<String>> {
+    client.simple_query(sql).await.unwrap().iter()
+        .filter_map(|msg| match msg {
+            SimpleQueryMessage::Row(row) => Some(row.get(0).map(|s| s.to_string())),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_jsonb_modification_and_build_functions() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TABLE docs (id INTEGER PRIMARY KEY, body JSONB)", &[]).await.unwrap();
+    client.execute(
+        r#"INSERT INTO docs (id, body) VALUES (1, '{"name": "widget", "tags": ["a", "b"], "meta": {"color": null}}')"#,
+        &[],
+    ).await.unwrap();
+
+    client.execute(
+        r#"UPDATE docs SET body = jsonb_set(body, '{meta,color}', '"red"') WHERE id = 1"#,
+        &[],
+    ).await.unwrap();
+    client.execute(
+        r#"UPDATE docs SET body = jsonb_insert(body, '{tags,0}', '"first"') WHERE id = 1"#,
+        &[],
+    ).await.unwrap();
+    let body = query_column(client, "SELECT body FROM docs WHERE id = 1").await;
+    let body: serde_json::Value = serde_json::from_str(body[0].as_deref().unwrap()).unwrap();
+    assert_eq!(body["meta"]["color"], "red");
+    assert_eq!(body["tags"], serde_json::json!(["first", "a", "b"]));
+
+    let stripped = query_column(client, r#"SELECT jsonb_strip_nulls('{"a": 1, "b": null}')"#).await;
+    assert_eq!(stripped, vec![Some(r#"{"a":1}"#.to_string())]);
+
+    let built = query_column(client, "SELECT jsonb_build_object('id', id, 'name', body->>'name') FROM docs").await;
+    assert_eq!(built, vec![Some(r#"{"id":1,"name":"widget"}"#.to_string())]);
+
+    let aggregated = query_column(client, "SELECT jsonb_agg(body->'meta') FROM docs").await;
+    assert_eq!(aggregated, vec![Some(r#"[{"color":"red"}]"#.to_string())]);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_set_returning_json_functions_in_from() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    let elements = query_column(client, r#"SELECT value FROM jsonb_array_elements('[1, "two", {"three": 3}, true]')"#).await;
+    assert_eq!(elements, vec![
+        Some("1".to_string()),
+        Some(r#""two""#.to_string()),
+        Some(r#"{"three":3}"#.to_string()),
+        Some("true".to_string()),
+    ]);
+
+    let texts = query_column(client, r#"SELECT e.value FROM jsonb_array_elements_text('["x", null, false]') AS e"#).await;
+    assert_eq!(texts, vec![Some("x".to_string()), None, Some("false".to_string())]);
+
+    let keys = query_column(client, r#"SELECT k FROM jsonb_object_keys('{"b": 1, "a": 2}') AS k ORDER BY k"#).await;
+    assert_eq!(keys, vec![Some("a".to_string()), Some("b".to_string())]);
+
+    client.execute("CREATE TABLE orders (id INTEGER PRIMARY KEY, lines JSONB)", &[]).await.unwrap();
+    client.execute(
+        r#"INSERT INTO orders (id, lines) VALUES (1, '[{"sku": "A", "qty": 2}, {"sku": "B", "qty": 1}]'), (2, '[{"sku": "C", "qty": 5}]')"#,
+        &[],
+    ).await.unwrap();
+    let skus = query_column(
+        client,
+        "SELECT l.value FROM orders o JOIN jsonb_array_elements(o.lines) AS l ON true ORDER BY o.id, l.key",
+    ).await;
+    assert_eq!(skus.len(), 3);
+
+    // After a comma or LATERAL the function is joined to the table the same way
+    for sql in [
+        "SELECT o.id || l.value FROM orders o, jsonb_array_elements(o.lines) l ORDER BY o.id, l.key",
+        "SELECT o.id || l.value FROM orders AS o, LATERAL jsonb_array_elements(o.lines) AS l ORDER BY o.id, l.key",
+        "SELECT o.id || l.value FROM orders o CROSS JOIN LATERAL jsonb_array_elements(o.lines) AS l ORDER BY o.id, l.key",
+    ] {
+        let rows = query_column(client, sql).await;
+        assert_eq!(rows, vec![
+            Some(r#"1{"sku":"A","qty":2}"#.to_string()),
+            Some(r#"1{"sku":"B","qty":1}"#.to_string()),
+            Some(r#"2{"sku":"C","qty":5}"#.to_string()),
+        ], "{sql}");
+    }
+    let skus = query_column(
+        client,
+        "SELECT l.value->>'sku' FROM orders o, jsonb_array_elements(o.lines) l WHERE (l.value->>'qty')::int > 1 ORDER BY 1",
+    ).await;
+    assert_eq!(skus, vec![Some("A".to_string()), Some("C".to_string())]);
+    let keys = query_column(client, "SELECT o.id || ':' || e.key FROM orders o, LATERAL jsonb_each(o.lines->0) e ORDER BY 1").await;
+    assert_eq!(keys, vec![Some("1:qty".to_string()), Some("1:sku".to_string()), Some("2:qty".to_string()), Some("2:sku".to_string())]);
+
+    let paths = query_column(
+        client,
+        r#"SELECT sku FROM jsonb_path_query('[{"sku": "A", "qty": 2}, {"sku": "B", "qty": 1}]', '$[*] ? (@.qty > 1).sku') AS sku"#,
+    ).await;
+    assert_eq!(paths, vec![Some(r#""A""#.to_string())]);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_set_returning_json_functions_in_select_list() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TABLE docs (id INTEGER PRIMARY KEY, body JSONB)", &[]).await.unwrap();
+    client.execute(
+        r#"INSERT INTO docs (id, body) VALUES (1, '{"tags": ["a", null, 2], "n": 1}'), (2, '{"tags": []}')"#,
+        &[],
+    ).await.unwrap();
+
+    // Each element becomes a row of its own, next to the other columns
+    let tags = query_column(client, "SELECT id || ':' || jsonb_array_elements(body->'tags') FROM docs ORDER BY id").await;
+    assert_eq!(tags, vec![Some(r#"1:"a""#.to_string()), Some("1:null".to_string()), Some("1:2".to_string())]);
+    let statement = client.prepare("SELECT id, jsonb_array_elements(body->'tags') AS tag FROM docs").await.unwrap();
+    assert_eq!(statement.columns()[1].type_(), &tokio_postgres::types::Type::JSONB);
+
+    let texts = query_column(client, "SELECT jsonb_array_elements_text(body->'tags') FROM docs").await;
+    assert_eq!(texts, vec![Some("a".to_string()), None, Some("2".to_string())]);
+
+    let keys = query_column(client, "SELECT jsonb_object_keys(body) FROM docs ORDER BY 1").await;
+    assert_eq!(keys, vec![Some("n".to_string()), Some("tags".to_string()), Some("tags".to_string())]);
+
+    let paths = query_column(client, "SELECT jsonb_path_query(body, '$.tags[*] ? (@ != null)') FROM docs").await;
+    assert_eq!(paths, vec![Some(r#""a""#.to_string()), Some("2".to_string())]);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_jsonb_path_functions() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TABLE events (id INTEGER PRIMARY KEY, payload JSONB)", &[]).await.unwrap();
+    client.execute(
+        r#"INSERT INTO events (id, payload) VALUES
+           (1, '{"level": "error", "attempts": [1, 2, 3]}'),
+           (2, '{"level": "info", "attempts": [1]}')"#,
+        &[],
+    ).await.unwrap();
+
+    let ids = query_column(
+        client,
+        r#"SELECT id FROM events WHERE jsonb_path_exists(payload, '$ ? (@.attempts.size() > 2)') ORDER BY id"#,
+    ).await;
+    assert_eq!(ids, vec![Some("1".to_string())]);
+
+    let ids = query_column(
+        client,
+        r#"SELECT id FROM events WHERE jsonb_path_match(payload, '$.level == $lvl', '{"lvl": "info"}') ORDER BY id"#,
+    ).await;
+    assert_eq!(ids, vec![Some("2".to_string())]);
+
+    let first = query_column(client, "SELECT jsonb_path_query_first(payload, '$.attempts[last]') FROM events ORDER BY id").await;
+    assert_eq!(first, vec![Some("3".to_string()), Some("1".to_string())]);
+
+    let all = query_column(client, "SELECT jsonb_path_query_array(payload, '$.attempts[*] ? (@ > 1)') FROM events WHERE id = 1").await;
+    assert_eq!(all, vec![Some("[2,3]".to_string())]);
+
+    server.abort();
+}
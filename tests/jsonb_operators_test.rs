@@ -0,0 +1,91 @@
+mod common;
+use common::setup_test_server;
+
+/// First column of every row of a simple query
+async fn simple_column(client: &tokio_postgres::Client, query: &str) -> Vec<String> {
+    let messages = client.simple_query(query).await.unwrap();
+    messages.iter().filter_map(|m| match m {
+        tokio_postgres::SimpleQueryMessage::Row(row) => Some(row.get(0).unwrap_or("NULL").to_string()),
+        _ => None,
+    }).collect()
+}
+
+async fn create_docs(client: &tokio_postgres::Client) {
+    client.execute("CREATE TABLE docs (id INTEGER PRIMARY KEY, data JSONB, meta JSON)", &[]).await.unwrap();
+    client.simple_query(
+        r#"INSERT INTO docs VALUES
+        (1, '{"a": {"b": {"c": "deep"}}, "n": 5, "tags": ["x", "y"], "k": "v1"}', '{"m": 1}'),
+        (2, '{"a": {"b": {"c": "other"}}, "n": 2, "tags": ["z"]}', '{"m": 2}')"#,
+    ).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_jsonb_path_operators() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+    create_docs(client).await;
+
+    assert_eq!(simple_column(client, "SELECT data->'a'->'b'->>'c' FROM docs ORDER BY id").await, vec!["deep", "other"]);
+    assert_eq!(simple_column(client, "SELECT data #>> '{a,b,c}' FROM docs ORDER BY id").await, vec!["deep", "other"]);
+    assert_eq!(simple_column(client, "SELECT data #> '{a,b}' FROM docs WHERE id = 1").await, vec![r#"{"c":"deep"}"#]);
+    assert_eq!(simple_column(client, "SELECT data->'tags'->>0 FROM docs ORDER BY id").await, vec!["x", "z"]);
+
+    // WHERE and ORDER BY
+    assert_eq!(simple_column(client, "SELECT id FROM docs WHERE data->'a'->'b'->>'c' = 'deep'").await, vec!["1"]);
+    assert_eq!(simple_column(client, "SELECT id FROM docs ORDER BY (data->>'n')::int").await, vec!["2", "1"]);
+
+    // Extended protocol
+    let rows = client.query("SELECT id FROM docs WHERE data->>$1 = $2", &[&"k", &"v1"]).await.unwrap();
+    assert_eq!(rows.len(), 1);
+    let rows = client.query("SELECT data #>> '{a,b,c}' AS c FROM docs WHERE id = $1", &[&2i32]).await.unwrap();
+    assert_eq!(rows[0].get::<_, String>("c"), "other");
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_jsonb_containment_and_existence_operators() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+    create_docs(client).await;
+
+    let cases = [
+        (r#"SELECT id FROM docs WHERE data @> '{"n": 5}' ORDER BY id"#, vec![1]),
+        (r#"SELECT id FROM docs WHERE data @> '{"a": {"b": {"c": "other"}}}' ORDER BY id"#, vec![2]),
+        (r#"SELECT id FROM docs WHERE '{"n": 2}' <@ data ORDER BY id"#, vec![2]),
+        (r#"SELECT id FROM docs WHERE data->'tags' @> '["x"]' ORDER BY id"#, vec![1]),
+        ("SELECT id FROM docs WHERE data ? 'k' ORDER BY id", vec![1]),
+        ("SELECT id FROM docs WHERE data->'a' ? 'b' ORDER BY id", vec![1, 2]),
+        ("SELECT id FROM docs WHERE data->'tags' ? 'z' ORDER BY id", vec![2]),
+        ("SELECT id FROM docs WHERE data ?| array['k', 'zz'] ORDER BY id", vec![1]),
+        ("SELECT id FROM docs WHERE data ?| '{k,zz}' ORDER BY id", vec![1]),
+        ("SELECT id FROM docs WHERE data ?& array['a', 'tags'] ORDER BY id", vec![1, 2]),
+        ("SELECT id FROM docs WHERE data ?& array['a', 'k'] ORDER BY id", vec![1]),
+    ];
+    for (query, expected) in cases {
+        let simple: Vec<i32> = simple_column(client, query).await.iter().map(|v| v.parse().unwrap()).collect();
+        assert_eq!(simple, expected, "simple protocol: {query}");
+        let extended: Vec<i32> = client.query(query, &[]).await.unwrap().iter().map(|r| r.get(0)).collect();
+        assert_eq!(extended, expected, "extended protocol: {query}");
+    }
+
+    let rows = client.query("SELECT id FROM docs WHERE data ? $1", &[&"k"]).await.unwrap();
+    assert_eq!(rows.len(), 1);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_jsonb_row_description_types() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+    create_docs(client).await;
+
+    let stmt = client.prepare(
+        "SELECT data, meta, data->'a' AS a, meta->'m' AS m, data #> '{a}' AS pa, data->>'k' AS k FROM docs WHERE id = 1",
+    ).await.unwrap();
+    let oids: Vec<u32> = stmt.columns().iter().map(|c| c.type_().oid()).collect();
+    assert_eq!(oids, vec![3802, 114, 3802, 114, 3802, 25]);
+
+    server.abort();
+}
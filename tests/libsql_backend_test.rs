@@ -0,0 +1,325 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD_NO_PAD;
+use pgsqlite::session::{DbHandler, LibsqlBackend, StorageBackend};
+use rusqlite::types::Value as SqlValue;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_postgres::{NoTls, SimpleQueryMessage};
+use uuid::Uuid;
+
+const AUTH_TOKEN: &str = "secret-token";
+
+/// Minimal Hrana-over-HTTP server over a SQLite file, standing in for sqld/Turso
+struct MockLibsql {
+    path: String,
+    streams: Mutex<HashMap<String, rusqlite::Connection>>,
+}
+
+impl MockLibsql {
+    async fn start(path: &str) -> (Arc<MockLibsql>, String) {
+        let server = Arc::new(MockLibsql { path: path.to_string(), streams: Mutex::new(HashMap::new()) });
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let accepting = server.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(accepting.clone().serve(stream));
+            }
+        });
+        (server, url)
+    }
+
+    async fn serve(self: Arc<Self>, stream: TcpStream) {
+        let mut reader = BufReader::new(stream);
+        loop {
+            let mut request_line = String::new();
+            if reader.read_line(&mut request_line).await.unwrap_or(0) == 0 {
+                return;
+            }
+            let (mut length, mut authorized) = (0, false);
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).await.unwrap();
+                let header = header.trim_end().to_lowercase();
+                if header.is_empty() {
+                    break;
+                }
+                if let Some(value) = header.strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+                authorized |= header == format!("authorization: bearer {AUTH_TOKEN}");
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).await.unwrap();
+
+            let (status, response) = if !request_line.starts_with("POST /v2/pipeline") {
+                ("404 Not Found", json!({ "message": "not found" }))
+            } else if !authorized {
+                ("401 Unauthorized", json!({ "message": "unauthorized" }))
+            } else {
+                ("200 OK", self.pipeline(serde_json::from_slice(&body).unwrap()))
+            };
+            let response = response.to_string();
+            let reply = format!(
+                "HTTP/1.1 {status}\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{response}",
+                response.len()
+            );
+            reader.get_mut().write_all(reply.as_bytes()).await.unwrap();
+        }
+    }
+
+    fn pipeline(&self, request: Value) -> Value {
+        let mut streams = self.streams.lock().unwrap();
+        let mut conn = match request["baton"].as_str() {
+            Some(baton) => streams.remove(baton).expect("unknown baton"),
+            None => rusqlite::Connection::open(&self.path).unwrap(),
+        };
+
+        let mut closed = false;
+        let results: Vec<Value> = request["requests"].as_array().unwrap().iter()
+            .map(|req| match req["type"].as_str().unwrap() {
+                "execute" => match execute(&mut conn, &req["stmt"]) {
+                    Ok(result) => json!({ "type": "ok", "response": { "type": "execute", "result": result } }),
+                    Err(e) => json!({ "type": "error", "error": sqlite_error(e) }),
+                },
+                "close" => {
+                    closed = true;
+                    json!({ "type": "ok", "response": { "type": "close" } })
+                }
+                other => panic!("unexpected request {other}"),
+            })
+            .collect();
+
+        let baton = (!closed).then(|| {
+            let baton = Uuid::new_v4().to_string();
+            streams.insert(baton.clone(), conn);
+            baton
+        });
+        json!({ "baton": baton, "base_url": null, "results": results })
+    }
+
+    fn open_streams(&self) -> usize {
+        self.streams.lock().unwrap().len()
+    }
+}
+
+fn execute(conn: &mut rusqlite::Connection, stmt: &Value) -> Result<Value, rusqlite::Error> {
+    let args: Vec<SqlValue> = stmt["args"].as_array().unwrap().iter()
+        .map(|arg| match arg["type"].as_str().unwrap() {
+            "null" => SqlValue::Null,
+            "integer" => SqlValue::Integer(arg["value"].as_str().unwrap().parse().unwrap()),
+            "float" => SqlValue::Real(arg["value"].as_f64().unwrap()),
+            "text" => SqlValue::Text(arg["value"].as_str().unwrap().to_string()),
+            "blob" => SqlValue::Blob(STANDARD_NO_PAD.decode(arg["base64"].as_str().unwrap()).unwrap()),
+            other => panic!("unexpected value type {other}"),
+        })
+        .collect();
+
+    let mut prepared = conn.prepare(stmt["sql"].as_str().unwrap())?;
+    let cols: Vec<Value> = prepared.column_names().iter().map(|name| json!({ "name": name, "decltype": null })).collect();
+    let column_count = cols.len();
+    let mut rows = Vec::new();
+    let mut query = prepared.query(rusqlite::params_from_iter(args))?;
+    while let Some(row) = query.next()? {
+        let values: Vec<Value> = (0..column_count)
+            .map(|i| match row.get::<_, SqlValue>(i).unwrap() {
+                SqlValue::Null => json!({ "type": "null" }),
+                SqlValue::Integer(v) => json!({ "type": "integer", "value": v.to_string() }),
+                SqlValue::Real(v) => json!({ "type": "float", "value": v }),
+                SqlValue::Text(v) => json!({ "type": "text", "value": v }),
+                SqlValue::Blob(v) => json!({ "type": "blob", "base64": STANDARD_NO_PAD.encode(v) }),
+            })
+            .collect();
+        rows.push(values);
+    }
+    drop(query);
+    drop(prepared);
+    let affected = if column_count == 0 { conn.changes() } else { 0 };
+    Ok(json!({ "cols": cols, "rows": rows, "affected_row_count": affected, "last_insert_rowid": null }))
+}
+
+fn sqlite_error(error: rusqlite::Error) -> Value {
+    let code = match &error {
+        rusqlite::Error::SqliteFailure(e, _) if e.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_UNIQUE => "SQLITE_CONSTRAINT_UNIQUE",
+        rusqlite::Error::SqliteFailure(e, _) if e.code == rusqlite::ErrorCode::ConstraintViolation => "SQLITE_CONSTRAINT",
+        _ => "SQLITE_UNKNOWN",
+    };
+    json!({ "message": format!("SQLite error: {error}"), "code": code })
+}
+
+fn temp_db() -> String {
+    format!("/tmp/pgsqlite_libsql_{}.db", Uuid::new_v4().simple())
+}
+
+/// The backend runs statements, parameters and transactions on the remote database
+#[tokio::test]
+async fn test_libsql_backend_statements_and_transactions() {
+    let remote_path = temp_db();
+    let (server, url) = MockLibsql::start(&remote_path).await;
+    let backend = LibsqlBackend::new(&url, Some(AUTH_TOKEN.to_string())).unwrap();
+    assert_eq!(backend.name(), "libsql");
+
+    let session = Uuid::new_v4();
+    backend.create_session_connection(session).await.unwrap();
+    backend.execute_with_session("CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT UNIQUE, price REAL, data BLOB)", &session).await.unwrap();
+
+    let inserted = backend.execute_with_params(
+        "INSERT INTO items (id, name, price, data) VALUES ($1, $2, $3, $4)",
+        &[Some(b"1".to_vec()), Some(b"widget".to_vec()), Some(b"2.5".to_vec()), Some(vec![0xff, 0x00])],
+        &session,
+    ).await.unwrap();
+    assert_eq!(inserted.rows_affected, 1);
+    assert_eq!(server.open_streams(), 0);
+
+    let response = backend.query_with_session("SELECT id, name, price, data, NULL AS missing FROM items", &session).await.unwrap();
+    assert_eq!(response.columns, vec!["id", "name", "price", "data", "missing"]);
+    assert_eq!(response.rows, vec![vec![
+        Some(b"1".to_vec()), Some(b"widget".to_vec()), Some(b"2.5".to_vec()), Some(vec![0xff, 0x00]), None,
+    ]]);
+
+    // A rolled back transaction leaves nothing behind, and its stream is released
+    backend.begin_with_session(&session).await.unwrap();
+    backend.execute_with_session("INSERT INTO items (id, name) VALUES (2, 'gadget')", &session).await.unwrap();
+    let visible = backend.query_with_session("SELECT count(*) FROM items", &session).await.unwrap();
+    assert_eq!(visible.rows, vec![vec![Some(b"2".to_vec())]]);
+    backend.rollback_with_session(&session).await.unwrap();
+    let remaining = backend.query_with_session("SELECT count(*) FROM items", &session).await.unwrap();
+    assert_eq!(remaining.rows, vec![vec![Some(b"1".to_vec())]]);
+
+    backend.begin_with_session(&session).await.unwrap();
+    backend.execute_with_session("INSERT INTO items (id, name) VALUES (3, 'gizmo')", &session).await.unwrap();
+    backend.commit_with_session(&session).await.unwrap();
+    let committed = rusqlite::Connection::open(&remote_path).unwrap()
+        .query_row("SELECT name FROM items WHERE id = 3", [], |row| row.get::<_, String>(0)).unwrap();
+    assert_eq!(committed, "gizmo");
+
+    // Remote errors surface as the SQLite errors the local engine would raise
+    let err = backend.execute_with_session("INSERT INTO items (id, name) VALUES (4, 'widget')", &session).await.unwrap_err();
+    match err {
+        pgsqlite::PgSqliteError::Sqlite(rusqlite::Error::SqliteFailure(e, Some(message))) => {
+            assert_eq!(e.extended_code, rusqlite::ffi::SQLITE_CONSTRAINT_UNIQUE);
+            assert_eq!(message, "UNIQUE constraint failed: items.name");
+        }
+        other => panic!("unexpected error {other:?}"),
+    }
+
+    // Bad credentials are reported, not retried
+    let unauthorized = LibsqlBackend::new(&url, Some("wrong".to_string())).unwrap();
+    unauthorized.create_session_connection(session).await.unwrap();
+    let err = unauthorized.query_with_session("SELECT 1", &session).await.unwrap_err();
+    assert!(err.to_string().contains("401"), "{err}");
+
+    backend.remove_session_connection(&session);
+    assert!(backend.query_with_session("SELECT 1", &session).await.is_err());
+    assert!(LibsqlBackend::new("postgres://localhost", None).is_err());
+    let _ = std::fs::remove_file(remote_path);
+}
+
+/// Through DbHandler, user data lives remotely while the local database caches the schema
+#[tokio::test]
+async fn test_remote_database_through_wire_protocol() {
+    let remote_path = temp_db();
+    let (_remote, url) = MockLibsql::start(&remote_path).await;
+
+    // Tables created by other libSQL clients are cached locally on attach
+    rusqlite::Connection::open(&remote_path).unwrap()
+        .execute_batch("CREATE TABLE existing (id INTEGER PRIMARY KEY, label TEXT); INSERT INTO existing VALUES (1, 'from remote');")
+        .unwrap();
+
+    let local_path = temp_db();
+    let mut db_handler = DbHandler::new(&local_path).unwrap();
+    let cached = db_handler.attach_remote(Arc::new(LibsqlBackend::new(&url, Some(AUTH_TOKEN.to_string())).unwrap())).await.unwrap();
+    assert_eq!(cached, 1);
+    let db_handler = Arc::new(db_handler);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = tokio::spawn(async move {
+        let (stream, addr) = listener.accept().await.unwrap();
+        let _ = pgsqlite::handle_test_connection_with_pool(stream, addr, db_handler).await;
+    });
+    let (client, connection) = tokio_postgres::connect(&format!("host=localhost port={port} dbname=test user=testuser"), NoTls).await.unwrap();
+    tokio::spawn(connection);
+
+    let rows = |messages: Vec<SimpleQueryMessage>| -> Vec<Vec<Option<String>>> {
+        messages.into_iter()
+            .filter_map(|msg| match msg {
+                SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).map(str::to_string)).collect()),
+                _ => None,
+            })
+            .collect()
+    };
+
+    let existing = rows(client.simple_query("SELECT label FROM existing").await.unwrap());
+    assert_eq!(existing, vec![vec![Some("from remote".to_string())]]);
+
+    client.batch_execute("
+        CREATE TABLE orders (id SERIAL PRIMARY KEY, customer VARCHAR(50), total NUMERIC(10,2));
+        INSERT INTO orders (customer, total) VALUES ('alice', 12.50);
+        BEGIN;
+        INSERT INTO orders (customer, total) VALUES ('bob', 3.25);
+        COMMIT;
+    ").await.unwrap();
+
+    // Extended protocol parameters and PostgreSQL types from the local metadata cache
+    let stmt = client.prepare("SELECT customer, total FROM orders WHERE customer = $1").await.unwrap();
+    let row = client.query_one(&stmt, &[&"bob"]).await.unwrap();
+    assert_eq!(row.get::<_, String>(0), "bob");
+    assert_eq!(row.get::<_, rust_decimal::Decimal>(1).to_string(), "3.25");
+
+    // Catalogs answer from the local cache
+    let tables = rows(client.simple_query("SELECT relname FROM pg_class WHERE relname = 'orders' AND relkind = 'r'").await.unwrap());
+    assert_eq!(tables, vec![vec![Some("orders".to_string())]]);
+
+    // Features working on table data in the local database are refused rather than run on the cache
+    for query in [
+        "COPY orders TO STDOUT",
+        "VACUUM",
+        "ALTER TABLE orders ADD COLUMN note TEXT",
+        "CREATE VIEW big_orders AS SELECT * FROM orders WHERE total > 10",
+        "SELECT pgsqlite.set_mask('orders', 'customer', 'full')",
+        "SELECT pgsqlite.enable_audit('orders')",
+    ] {
+        let e = client.simple_query(query).await.unwrap_err();
+        assert_eq!(e.code(), Some(&tokio_postgres::error::SqlState::FEATURE_NOT_SUPPORTED), "{query}: {e:?}");
+    }
+
+    // Rows were written to the remote database only
+    let remote_count: i64 = rusqlite::Connection::open(&remote_path).unwrap()
+        .query_row("SELECT count(*) FROM orders", [], |row| row.get(0)).unwrap();
+    assert_eq!(remote_count, 2);
+    let local_count: i64 = rusqlite::Connection::open(&local_path).unwrap()
+        .query_row("SELECT count(*) FROM orders", [], |row| row.get(0)).unwrap();
+    assert_eq!(local_count, 0);
+
+    drop(client);
+    server.abort();
+    for path in [remote_path, local_path] {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// Sessions that end inside a transaction do not leave a stream open on the server
+#[tokio::test]
+async fn test_abandoned_transaction_closes_stream() {
+    let remote_path = temp_db();
+    let (server, url) = MockLibsql::start(&remote_path).await;
+
+    let backend = LibsqlBackend::new(&url, Some(AUTH_TOKEN.to_string())).unwrap();
+    let session = Uuid::new_v4();
+    backend.create_session_connection(session).await.unwrap();
+    backend.begin_with_session(&session).await.unwrap();
+    assert_eq!(server.open_streams(), 1);
+    backend.remove_session_connection(&session);
+
+    // The close request is sent in the background; a second session can then write
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    assert_eq!(server.open_streams(), 0);
+    let other = Uuid::new_v4();
+    backend.create_session_connection(other).await.unwrap();
+    backend.execute_with_session("CREATE TABLE t (id INTEGER)", &other).await.unwrap();
+    let _ = std::fs::remove_file(remote_path);
+}
@@ -0,0 +1,162 @@
+mod common;
+use common::spawn_server_with;
+use futures::{pin_mut, TryStreamExt};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::{Client, SimpleQueryMessage};
+
+async fn rows(client: &Client, query: &str) -> Vec<String> {
+    client.simple_query(query).await.unwrap().into_iter()
+        .filter_map(|message| match message {
+            SimpleQueryMessage::Row(row) => Some(
+                (0..row.len()).map(|i| row.get(i).unwrap_or("NULL")).collect::<Vec<_>>().join("|"),
+            ),
+            _ => None,
+        })
+        .collect()
+}
+
+async fn copy_out(client: &Client, sql: &str) -> String {
+    let stream = client.copy_out(sql).await.unwrap();
+    pin_mut!(stream);
+    let mut output = Vec::new();
+    while let Some(chunk) = stream.try_next().await.unwrap() {
+        output.extend_from_slice(&chunk);
+    }
+    String::from_utf8(output).unwrap()
+}
+
+/// Masks set by an admin role hide the values from other roles, wherever they read them
+#[tokio::test]
+async fn test_column_masks() {
+    let dir = tempfile::tempdir().unwrap();
+    let server = spawn_server_with(|command| {
+        command
+            .args(["--admin-users", "postgres", "--database"])
+            .arg(dir.path().join("masked.db"))
+            .arg("--socket-dir")
+            .arg(dir.path());
+    }).await;
+    let admin = server.connect().await;
+    admin.batch_execute(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT, card VARCHAR(19), ssn TEXT, city TEXT);
+         INSERT INTO users VALUES
+             (1, 'jane.doe@example.com', '4111-1111-1111-1234', '555-12-3456', 'Oslo'),
+             (2, 'bob@example.com', NULL, '555-98-7654', 'Oslo');
+         CREATE TABLE orders (id INTEGER PRIMARY KEY, user_id INTEGER, total INTEGER);
+         INSERT INTO orders VALUES (10, 1, 30), (11, 2, 12)",
+    ).await.unwrap();
+
+    assert_eq!(rows(&admin, "SELECT pgsqlite.set_mask('users', 'email', 'partial', '^([^@]*)@')").await, vec!["t"]);
+    assert_eq!(rows(&admin, "SELECT pgsqlite.set_mask('users', 'card', 'partial')").await, vec!["t"]);
+    assert_eq!(rows(&admin, "SELECT pgsqlite.set_mask('users', 'ssn', 'full')").await, vec!["t"]);
+    assert_eq!(rows(&admin, "SELECT pgsqlite.set_mask('users', 'ssn', 'full')").await, vec!["f"]);
+
+    // Masks only apply to text columns with a known method and a valid pattern
+    for (query, state) in [
+        ("SELECT pgsqlite.set_mask('users', 'id', 'full')", SqlState::DATATYPE_MISMATCH),
+        ("SELECT pgsqlite.set_mask('users', 'phone', 'full')", SqlState::UNDEFINED_COLUMN),
+        ("SELECT pgsqlite.set_mask('accounts', 'email', 'full')", SqlState::UNDEFINED_TABLE),
+        ("SELECT pgsqlite.set_mask('users', 'city', 'redact')", SqlState::INVALID_PARAMETER_VALUE),
+        ("SELECT pgsqlite.set_mask('users', 'city', 'hash', '.')", SqlState::INVALID_PARAMETER_VALUE),
+        ("SELECT pgsqlite.set_mask('users', 'city', 'partial', '(')", SqlState::INVALID_REGULAR_EXPRESSION),
+    ] {
+        let e = admin.simple_query(query).await.unwrap_err();
+        assert_eq!(e.code(), Some(&state), "{query}: {e:?}");
+    }
+
+    // Admin roles read the values as they are
+    assert_eq!(
+        rows(&admin, "SELECT email, card, ssn FROM users WHERE id = 1").await,
+        vec!["jane.doe@example.com|4111-1111-1111-1234|555-12-3456"]
+    );
+
+    let dev = server.connect_with("user=dev").await.unwrap();
+    assert_eq!(
+        rows(&dev, "SELECT * FROM users ORDER BY id").await,
+        vec![
+            "1|********@example.com|***************1234|********|Oslo",
+            "2|***@example.com|NULL|********|Oslo",
+        ]
+    );
+    // Filters, joins, subqueries and the extended protocol see the masked values too
+    assert!(rows(&dev, "SELECT id FROM users WHERE email = 'jane.doe@example.com'").await.is_empty());
+    assert_eq!(
+        rows(&dev, "SELECT u.email, o.total FROM orders o JOIN users u ON u.id = o.user_id WHERE o.id = 11").await,
+        vec!["***@example.com|12"]
+    );
+    assert_eq!(
+        rows(&dev, "SELECT count(*) FROM (SELECT ssn FROM users) s WHERE ssn = '555-98-7654'").await,
+        vec!["0"]
+    );
+    let row = dev.query_one("SELECT card FROM users WHERE id = $1::int8", &[&1i64]).await.unwrap();
+    assert_eq!(row.get::<_, String>(0), "***************1234");
+
+    // Tables created from a query and COPY get the masked values
+    dev.batch_execute("CREATE TABLE contacts AS SELECT id, email FROM users").await.unwrap();
+    assert_eq!(
+        rows(&admin, "SELECT email FROM contacts ORDER BY id").await,
+        vec!["********@example.com", "***@example.com"]
+    );
+    assert_eq!(copy_out(&dev, "COPY users (id, ssn) TO STDOUT").await, "1\t********\n2\t********\n");
+    assert_eq!(copy_out(&dev, "COPY (SELECT email FROM users WHERE id = 2) TO STDOUT").await, "***@example.com\n");
+
+    // Other roles can't lift the masks
+    let e = dev.simple_query("SELECT pgsqlite.drop_mask('users', 'ssn')").await.unwrap_err();
+    assert_eq!(e.code(), Some(&SqlState::INSUFFICIENT_PRIVILEGE));
+    let e = dev.simple_query("DELETE FROM __pgsqlite_column_masks").await.unwrap_err();
+    assert_eq!(e.code(), Some(&SqlState::INSUFFICIENT_PRIVILEGE));
+
+    // Every statement of several is masked, and RETURNING masks what UPDATE and DELETE return
+    assert_eq!(
+        rows(&dev, "SELECT 1; SELECT ssn FROM users WHERE id = 1").await,
+        vec!["1", "********"]
+    );
+    assert_eq!(
+        rows(&dev, "UPDATE users SET city = 'Bergen' WHERE id = 2 RETURNING id, email, ssn").await,
+        vec!["2|***@example.com|********"]
+    );
+    assert_eq!(
+        rows(&dev, "DELETE FROM orders WHERE id = 10 RETURNING (SELECT ssn FROM users WHERE id = 1)").await,
+        vec!["********"]
+    );
+
+    // Statements the masks can't be applied to are refused rather than run unmasked
+    for query in [
+        "UPDATE users SET city = email WHERE id = 2",
+        "UPDATE users SET city = 'x' FROM (SELECT 1) one WHERE city = (SELECT email FROM main.users WHERE id = 1)",
+        "SELECT email FROM users WHERE email GLOB '*@*'",
+        "WITH changed AS (UPDATE users SET city = 'Oslo' RETURNING email) SELECT * FROM changed",
+    ] {
+        let e = dev.simple_query(query).await.unwrap_err();
+        assert_eq!(e.code(), Some(&SqlState::INSUFFICIENT_PRIVILEGE), "{query}: {e:?}");
+    }
+    assert_eq!(
+        rows(&admin, "SELECT city FROM users ORDER BY id").await,
+        vec!["Oslo", "Bergen"]
+    );
+
+    // Masks follow renamed columns and tables, and are listed in pg_column_masks
+    admin.batch_execute(
+        "ALTER TABLE users RENAME COLUMN email TO mail;
+         ALTER TABLE users RENAME TO people",
+    ).await.unwrap();
+    assert_eq!(
+        rows(&admin, "SELECT relname, attname, method, coalesce(pattern, '') FROM pg_column_masks ORDER BY attname").await,
+        vec!["people|card|partial|", "people|mail|partial|^([^@]*)@", "people|ssn|full|"]
+    );
+    assert_eq!(rows(&dev, "SELECT mail FROM people WHERE id = 2").await, vec!["***@example.com"]);
+
+    // A dropped mask shows the values again, and a switch to hash keeps equal values equal
+    assert_eq!(rows(&admin, "SELECT pgsqlite.drop_mask('people', 'card')").await, vec!["t"]);
+    assert_eq!(rows(&admin, "SELECT pgsqlite.drop_mask('people', 'card')").await, vec!["f"]);
+    assert_eq!(rows(&admin, "SELECT pgsqlite.set_mask('people', 'mail', 'hash')").await, vec!["t"]);
+    assert_eq!(rows(&dev, "SELECT card FROM people WHERE id = 1").await, vec!["4111-1111-1111-1234"]);
+    assert_eq!(
+        rows(&dev, "SELECT count(DISTINCT mail), max(length(mail)) FROM (SELECT mail FROM people UNION ALL SELECT mail FROM people) m").await,
+        vec!["2|64"]
+    );
+
+    // Masks are dropped with their table
+    admin.batch_execute("DROP TABLE people").await.unwrap();
+    assert!(rows(&admin, "SELECT relname FROM pg_column_masks").await.is_empty());
+}
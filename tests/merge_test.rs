@@ -0,0 +1,135 @@
+use tokio::net::TcpListener;
+use tokio_postgres::{Client, NoTls, SimpleQueryMessage};
+use uuid::Uuid;
+
+async fn connect() -> (Client, String) {
+    let test_id = Uuid::new_v4().to_string().replace("-", "");
+    let db_path = format!("/tmp/pgsqlite_test_{test_id}.db");
+    let db_path_clone = db_path.clone();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let db_handler = std::sync::Arc::new(pgsqlite::session::DbHandler::new(&db_path_clone).unwrap());
+        let (stream, addr) = listener.accept().await.unwrap();
+        let _ = pgsqlite::handle_test_connection_with_pool(stream, addr, db_handler).await;
+    });
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    let (client, connection) = tokio_postgres::connect(
+        &format!("host=localhost port={port} dbname=test user=testuser"),
+        NoTls,
+    ).await.unwrap();
+    tokio::spawn(connection);
+    (client, db_path)
+}
+
+async fn merged(client: &Client, query: &str) -> u64 {
+    let messages = client.simple_query(query).await.unwrap();
+    match messages.last() {
+        Some(SimpleQueryMessage::CommandComplete(rows)) => *rows,
+        other => panic!("{query}: {other:?}"),
+    }
+}
+
+async fn stock(client: &Client) -> Vec<(i32, String, i32)> {
+    client.query("SELECT id, name, qty FROM stock ORDER BY id", &[]).await.unwrap()
+        .iter()
+        .map(|row| (row.get(0), row.get(1), row.get(2)))
+        .collect()
+}
+
+/// MERGE updates, deletes and inserts by the first clause each row satisfies
+#[tokio::test]
+async fn test_merge_clauses() {
+    let (client, db_path) = connect().await;
+    client.batch_execute("
+        CREATE TABLE stock (id INTEGER PRIMARY KEY, name TEXT NOT NULL, qty INTEGER NOT NULL DEFAULT 1);
+        INSERT INTO stock VALUES (1, 'bolt', 10), (2, 'nut', 5), (3, 'gear', 2);
+        CREATE TABLE delivery (id INTEGER, name TEXT, qty INTEGER);
+        INSERT INTO delivery VALUES (1, 'bolt', 5), (2, 'nut', -5), (4, 'cog', 7), (5, 'spring', 0);
+    ").await.unwrap();
+
+    let rows = merged(&client, "
+        MERGE INTO stock s USING delivery d ON s.id = d.id
+        WHEN MATCHED AND s.qty + d.qty <= 0 THEN DELETE
+        WHEN MATCHED THEN UPDATE SET qty = s.qty + d.qty
+        WHEN NOT MATCHED AND d.qty > 0 THEN INSERT (id, name, qty) VALUES (d.id, d.name, d.qty)
+        WHEN NOT MATCHED THEN DO NOTHING
+    ").await;
+    assert_eq!(rows, 3);
+    assert_eq!(stock(&client).await, vec![
+        (1, "bolt".to_string(), 15),
+        (3, "gear".to_string(), 2),
+        (4, "cog".to_string(), 7),
+    ]);
+
+    // A VALUES source with column aliases, a DEFAULT, and target rows no source row matches
+    let rows = merged(&client, "
+        MERGE INTO stock USING (VALUES (3, 'gear'), (6, 'axle')) AS v (id, name) ON stock.id = v.id
+        WHEN NOT MATCHED THEN INSERT (id, name, qty) VALUES (v.id, v.name, DEFAULT)
+        WHEN NOT MATCHED BY SOURCE AND stock.qty > 10 THEN UPDATE SET qty = 10
+        WHEN NOT MATCHED BY SOURCE THEN DELETE
+    ").await;
+    assert_eq!(rows, 3);
+    assert_eq!(stock(&client).await, vec![
+        (1, "bolt".to_string(), 10),
+        (3, "gear".to_string(), 2),
+        (6, "axle".to_string(), 1),
+    ]);
+
+    let _ = std::fs::remove_file(db_path);
+}
+
+/// A failing MERGE leaves the table as it was
+#[tokio::test]
+async fn test_merge_errors_roll_back() {
+    let (client, db_path) = connect().await;
+    client.batch_execute("
+        CREATE TABLE stock (id INTEGER PRIMARY KEY, name TEXT NOT NULL, qty INTEGER NOT NULL);
+        INSERT INTO stock VALUES (1, 'bolt', 10);
+        CREATE TABLE delivery (id INTEGER, name TEXT, qty INTEGER);
+        INSERT INTO delivery VALUES (1, 'bolt', 1), (1, 'bolt', 2), (2, NULL, 3);
+    ").await.unwrap();
+
+    // Two source rows match the same target row
+    let err = client.simple_query(
+        "MERGE INTO stock s USING delivery d ON s.id = d.id WHEN MATCHED THEN UPDATE SET qty = s.qty + d.qty"
+    ).await.unwrap_err();
+    assert_eq!(err.code().unwrap().code(), "21000");
+    assert_eq!(err.as_db_error().unwrap().message(), "MERGE command cannot affect row a second time");
+
+    // The UPDATE runs before the INSERT violating NOT NULL
+    client.batch_execute("DELETE FROM delivery WHERE qty = 2").await.unwrap();
+    let err = client.simple_query("
+        MERGE INTO stock s USING delivery d ON s.id = d.id
+        WHEN MATCHED THEN UPDATE SET qty = s.qty + d.qty
+        WHEN NOT MATCHED THEN INSERT VALUES (d.id, d.name, d.qty)
+    ").await.unwrap_err();
+    assert_eq!(err.code().unwrap().code(), "23502");
+    assert_eq!(stock(&client).await, vec![(1, "bolt".to_string(), 10)]);
+
+    let _ = std::fs::remove_file(db_path);
+}
+
+/// MERGE takes parameters in the extended protocol
+#[tokio::test]
+async fn test_merge_extended_protocol() {
+    let (client, db_path) = connect().await;
+    client.batch_execute("
+        CREATE TABLE stock (id INTEGER PRIMARY KEY, name TEXT NOT NULL, qty INTEGER NOT NULL);
+        INSERT INTO stock VALUES (1, 'bolt', 10);
+    ").await.unwrap();
+
+    let upsert = "MERGE INTO stock s USING (SELECT $1::int4 AS id, $2::text AS name, $3::int4 AS qty) AS d ON s.id = d.id \
+        WHEN MATCHED THEN UPDATE SET qty = s.qty + d.qty \
+        WHEN NOT MATCHED THEN INSERT (id, name, qty) VALUES (d.id, d.name, d.qty)";
+    assert_eq!(client.execute(upsert, &[&1i32, &"bolt", &5i32]).await.unwrap(), 1);
+    assert_eq!(client.execute(upsert, &[&2i32, &"nut's", &3i32]).await.unwrap(), 1);
+    assert_eq!(stock(&client).await, vec![
+        (1, "bolt".to_string(), 15),
+        (2, "nut's".to_string(), 3),
+    ]);
+
+    let _ = std::fs::remove_file(db_path);
+}
@@ -0,0 +1,107 @@
+mod common;
+use common::spawn_server_with;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::{Client, NoTls, SimpleQueryMessage};
+
+async fn column(client: &Client, sql: &str) -> Vec<String> {
+    client.simple_query(sql).await.unwrap().into_iter()
+        .filter_map(|message| match message {
+            SimpleQueryMessage::Row(row) => Some(row.get(0).unwrap_or_default().to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The startup database name picks one of the --databases files
+#[tokio::test]
+async fn test_database_parameter_selects_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = |file: &str| dir.path().join(file).to_str().unwrap().to_string();
+    let server = spawn_server_with(|command| {
+        command
+            .args(["--log-level", "error", "--database", &path("main.db")])
+            .args(["--databases", &format!("sales={},hr={}", path("sales.db"), path("hr.db"))])
+            .arg("--socket-dir")
+            .arg(dir.path());
+    }).await;
+    let sales = server.connect_with("dbname=sales").await.unwrap();
+    sales.batch_execute("CREATE TABLE orders (id INTEGER PRIMARY KEY, total NUMERIC); INSERT INTO orders (total) VALUES (9.5)").await.unwrap();
+    assert_eq!(column(&sales, "SELECT count(*) FROM orders").await, vec!["1"]);
+
+    // Each database is its own file
+    let hr = server.connect_with("dbname=hr").await.unwrap();
+    let err = hr.simple_query("SELECT * FROM orders").await.err().expect("orders should only exist in sales");
+    assert!(err.to_string().contains("orders"), "unexpected error: {err:?}");
+    let main = server.connect_with("dbname=main").await.unwrap();
+    assert!(main.simple_query("SELECT * FROM orders").await.is_err());
+    drop(main);
+
+    // pg_database lists all of them, and each session knows its own
+    assert_eq!(column(&hr, "SELECT datname FROM pg_database ORDER BY oid").await, vec!["main", "sales", "hr"]);
+    assert_eq!(column(&sales, "SELECT pgsqlite_datname()").await, vec!["sales"]);
+    assert_eq!(column(&hr, "SELECT pgsqlite_datname()").await, vec!["hr"]);
+    assert!(std::path::Path::new(&path("sales.db")).exists());
+
+    // The same query text describes each database's own table, however the sessions interleave
+    sales.batch_execute("CREATE TABLE staff (id INTEGER, region TEXT); INSERT INTO staff VALUES (1, 'north')").await.unwrap();
+    hr.batch_execute("CREATE TABLE staff (id INTEGER, salary NUMERIC, active BOOLEAN); INSERT INTO staff VALUES (1, 100, true)").await.unwrap();
+    let columns = "SELECT column_name FROM information_schema.columns WHERE table_name = 'staff' ORDER BY ordinal_position";
+    for _ in 0..2 {
+        assert_eq!(column(&sales, columns).await, vec!["id", "region"]);
+        assert_eq!(column(&hr, columns).await, vec!["id", "salary", "active"]);
+        let row = sales.query_one("SELECT * FROM staff", &[]).await.unwrap();
+        assert_eq!(row.get::<_, &str>(1), "north");
+        let row = hr.query_one("SELECT * FROM staff", &[]).await.unwrap();
+        assert!(row.get::<_, bool>(2));
+    }
+
+    // Names that aren't configured are refused
+    let err = server.connect_with("dbname=payroll").await.err().expect("unknown database should be refused");
+    assert_eq!(err.code(), Some(&SqlState::INVALID_CATALOG_NAME), "unexpected error: {err:?}");
+    assert!(err.to_string().contains("database \"payroll\" does not exist"), "unexpected error: {err:?}");
+}
+
+async fn connect_memory(port: u16, name: &str) -> Client {
+    let mut config = tokio_postgres::Config::new();
+    config.host("127.0.0.1").port(port).user("postgres").dbname(format!("file:{name}?mode=memory&cache=shared"));
+    let (client, connection) = config.connect(NoTls).await.unwrap();
+    tokio::spawn(connection);
+    client
+}
+
+/// With --in-memory, sessions asking for the same shared memory database see each other's data
+#[tokio::test]
+async fn test_shared_memory_databases() {
+    let dir = tempfile::tempdir().unwrap();
+    let server = spawn_server_with(|command| {
+        command.args(["--log-level", "error", "--in-memory", "--socket-dir"]).arg(dir.path());
+    }).await;
+    let port = server.port;
+    let private = server.connect().await;
+
+    let first = connect_memory(port, "suite1").await;
+    first.batch_execute("CREATE TABLE events (id SERIAL PRIMARY KEY, worker INTEGER, amount NUMERIC(10,2))").await.unwrap();
+
+    // Sessions of the same database write to it concurrently
+    let writers: Vec<_> = (0..4).map(|worker| tokio::spawn(async move {
+        let client = connect_memory(port, "suite1").await;
+        for _ in 0..10 {
+            client.execute("INSERT INTO events (worker, amount) VALUES ($1, 1.25)", &[&worker]).await.unwrap();
+        }
+    })).collect();
+    for writer in writers {
+        writer.await.unwrap();
+    }
+    assert_eq!(column(&first, "SELECT count(*) FROM events").await, vec!["40"]);
+    assert_eq!(column(&first, "SELECT sum(amount) FROM events").await, vec!["50.00"]);
+
+    // Other databases, and the private ones of plain in-memory sessions, don't have the table
+    let second = connect_memory(port, "suite2").await;
+    assert!(second.simple_query("SELECT * FROM events").await.is_err());
+    assert!(private.simple_query("SELECT * FROM events").await.is_err());
+
+    // The database outlives its sessions
+    drop(first);
+    let again = connect_memory(port, "suite1").await;
+    assert_eq!(column(&again, "SELECT count(DISTINCT worker) FROM events").await, vec!["4"]);
+}
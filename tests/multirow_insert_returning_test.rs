@@ -1,3 +1,5 @@
+mod common;
+use common::setup_test_server;
 use tokio::net::TcpListener;
 use tokio_postgres::NoTls;
 use uuid::Uuid;
@@ -205,4 +207,48 @@ async fn test_sqlalchemy_style_insert_returning() {
     let _ = std::fs::remove_file(format!("{db_path}-journal"));
     let _ = std::fs::remove_file(format!("{db_path}-wal"));
     let _ = std::fs::remove_file(format!("{db_path}-shm"));
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_sqlalchemy_insertmanyvalues_with_binary_parameters() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute(
+        "CREATE TABLE events (id SERIAL PRIMARY KEY, title VARCHAR(100), happened_at TIMESTAMP, weight INTEGER)",
+        &[],
+    ).await.unwrap();
+
+    let first = chrono::NaiveDate::from_ymd_opt(2025, 1, 25).unwrap().and_hms_micro_opt(12, 30, 15, 250_000).unwrap();
+    let second = chrono::NaiveDate::from_ymd_opt(1999, 12, 31).unwrap().and_hms_opt(23, 59, 59).unwrap();
+    let third = chrono::NaiveDate::from_ymd_opt(2030, 6, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+
+    // Both cast spellings SQLAlchemy emits, timestamps sent in binary format
+    for query in [
+        "INSERT INTO events (title, happened_at, weight) SELECT p0::VARCHAR, p1::TIMESTAMP WITHOUT TIME ZONE, p2::INTEGER \
+         FROM (VALUES ($1, $2, $3, 0), ($4, $5, $6, 1), ($7, $8, $9, 2)) AS imp_sen(p0, p1, p2, sen_counter) \
+         ORDER BY sen_counter RETURNING events.id, events.id AS id__1",
+        "INSERT INTO events (title, happened_at, weight) SELECT CAST(p0 AS VARCHAR), CAST(p1 AS TIMESTAMP WITHOUT TIME ZONE), CAST(p2 AS INTEGER) \
+         FROM (VALUES ($1, $2, $3, 0), ($4, $5, $6, 1), ($7, $8, $9, 2)) AS imp_sen(p0, p1, p2, sen_counter) \
+         ORDER BY sen_counter RETURNING events.id, events.id AS id__1",
+    ] {
+        client.execute("DELETE FROM events", &[]).await.unwrap();
+        let rows = client.query(
+            query,
+            &[&"one, with comma", &first, &1i32, &"two", &second, &2i32, &"three", &third, &3i32],
+        ).await.unwrap();
+        assert_eq!(rows.len(), 3);
+        let ids: Vec<i32> = rows.iter().map(|r| r.get(0)).collect();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]), "RETURNING rows keep parameter order: {ids:?}");
+
+        let stored = client.query("SELECT title, happened_at, weight FROM events ORDER BY id", &[]).await.unwrap();
+        let stored: Vec<(String, chrono::NaiveDateTime, i32)> = stored.iter().map(|r| (r.get(0), r.get(1), r.get(2))).collect();
+        assert_eq!(stored, vec![
+            ("one, with comma".to_string(), first, 1),
+            ("two".to_string(), second, 2),
+            ("three".to_string(), third, 3),
+        ]);
+    }
+
+    server.abort();
+}
@@ -0,0 +1,112 @@
+mod common;
+use common::setup_test_server;
+use tokio_postgres::SimpleQueryMessage;
+
+async fn query_row(client: &tokio_postgres::Client, sql: &str) -> Vec<Option<String>> {
+    client.simple_query(sql).await.unwrap().iter()
+        .find_map(|msg| match msg {
+            SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).map(|s| s.to_string())).collect()),
+            _ => None,
+        })
+        .unwrap()
+}
+
+fn s(value: &str) -> Option<String> {
+    Some(value.to_string())
+}
+
+#[tokio::test]
+async fn test_network_columns_canonical_storage() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute(
+        "CREATE TABLE access_log (id INTEGER PRIMARY KEY, client_addr INET, subnet CIDR, device MACADDR)",
+        &[],
+    ).await.unwrap();
+    client.simple_query(
+        "INSERT INTO access_log (id, client_addr, subnet, device) VALUES \
+         (1, '10.0.0.1/32', '10.0.0.0/8', '08-00-2B-01-02-03'), \
+         (2, '2001:DB8::1/64', '192.168.1', '0800.2b01.0204')",
+    ).await.unwrap();
+
+    let row = query_row(client, "SELECT client_addr, subnet, device FROM access_log WHERE id = 1").await;
+    assert_eq!(row, vec![s("10.0.0.1"), s("10.0.0.0/8"), s("08:00:2b:01:02:03")]);
+    let row = query_row(client, "SELECT client_addr, subnet, device FROM access_log WHERE id = 2").await;
+    assert_eq!(row, vec![s("2001:db8::1/64"), s("192.168.1.0/24"), s("08:00:2b:01:02:04")]);
+
+    // Bound text parameters are validated and canonicalized too
+    client.execute(
+        "INSERT INTO access_log (id, client_addr, device) VALUES ($1, $2, $3)",
+        &[&3i32, &"172.16.0.5/32", &"AA:BB:CC:DD:EE:FF"],
+    ).await.unwrap();
+    let row = query_row(client, "SELECT client_addr, device FROM access_log WHERE id = 3").await;
+    assert_eq!(row, vec![s("172.16.0.5"), s("aa:bb:cc:dd:ee:ff")]);
+
+    // Invalid values are rejected
+    assert!(client.simple_query("INSERT INTO access_log (id, client_addr) VALUES (4, '300.1.1.1')").await.is_err());
+    assert!(client.simple_query("INSERT INTO access_log (id, subnet) VALUES (4, '10.0.0.1/8')").await.is_err());
+    assert!(client.simple_query("INSERT INTO access_log (id, device) VALUES (4, '08:00:2b')").await.is_err());
+    assert!(client.execute("INSERT INTO access_log (id, client_addr) VALUES ($1, $2)", &[&4i32, &"not an address"]).await.is_err());
+
+    // Invalid input is reported like PostgreSQL does
+    for (sql, value) in [
+        ("SELECT 'nonsense'::inet", "nonsense"),
+        ("INSERT INTO access_log (id, client_addr) VALUES (4, '10.0.0.256')", "10.0.0.256"),
+    ] {
+        let err = client.simple_query(sql).await.unwrap_err();
+        let db_err = err.as_db_error().unwrap();
+        assert_eq!(db_err.code(), &tokio_postgres::error::SqlState::INVALID_TEXT_REPRESENTATION);
+        assert_eq!(db_err.message(), format!("invalid input syntax for type inet: \"{value}\""));
+    }
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_network_functions_and_operators() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    let row = query_row(
+        client,
+        "SELECT host('192.168.1.5/24'::inet), network('192.168.1.5/24'::inet), masklen('192.168.1.5/24'::inet), broadcast('192.168.1.5/24'::inet)",
+    ).await;
+    assert_eq!(row, vec![s("192.168.1.5"), s("192.168.1.0/24"), s("24"), s("192.168.1.255/24")]);
+
+    client.execute("CREATE TABLE hosts (id INTEGER PRIMARY KEY, addr INET)", &[]).await.unwrap();
+    client.simple_query(
+        "INSERT INTO hosts (id, addr) VALUES (1, '10.1.2.3'), (2, '10.200.0.1'), (3, '192.168.0.10')",
+    ).await.unwrap();
+
+    let rows = client.simple_query("SELECT id FROM hosts WHERE addr << '10.0.0.0/8' ORDER BY id").await.unwrap();
+    let ids: Vec<&str> = rows.iter()
+        .filter_map(|msg| match msg {
+            SimpleQueryMessage::Row(row) => row.get(0),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(ids, vec!["1", "2"]);
+
+    let row = query_row(client, "SELECT id FROM hosts WHERE '10.1.0.0/16'::cidr >>= addr").await;
+    assert_eq!(row, vec![s("1")]);
+    let row = query_row(client, "SELECT id FROM hosts WHERE addr && '192.168.0.0/24'::cidr").await;
+    assert_eq!(row, vec![s("3")]);
+
+    // The containment operators return booleans
+    let row = query_row(client, "SELECT addr << '10.0.0.0/8'::cidr AS inside, '10.0.0.0/8'::cidr >> addr, addr <<= '192.168.0.0/24'::cidr FROM hosts WHERE id = 3").await;
+    assert_eq!(row, vec![s("f"), s("f"), s("t")]);
+
+    // Extended protocol
+    let rows = client.query("SELECT id FROM hosts WHERE addr << '10.0.0.0/8' ORDER BY id", &[]).await.unwrap();
+    let ids: Vec<i32> = rows.iter().map(|row| row.get(0)).collect();
+    assert_eq!(ids, vec![1, 2]);
+    let row = client.query_one("SELECT addr << '10.0.0.0/8'::cidr, '192.168.0.10'::inet << '192.168.0.0/24'::cidr FROM hosts WHERE id = 1", &[]).await.unwrap();
+    assert_eq!((row.get::<_, bool>(0), row.get::<_, bool>(1)), (true, true));
+
+    // Integer shifts are unaffected
+    let row = query_row(client, "SELECT 1 << 4, 256 >> 2").await;
+    assert_eq!(row, vec![s("16"), s("64")]);
+
+    server.abort();
+}
@@ -0,0 +1,44 @@
+mod common;
+use common::setup_test_server;
+use tokio_postgres::SimpleQueryMessage;
+
+async fn query_column(client: &tokio_postgres::Client, sql: &str) -> Vec<Option<String>> {
+    client.simple_query(sql).await.unwrap().iter()
+        .filter_map(|msg| match msg {
+            SimpleQueryMessage::Row(row) => Some(row.get(0).map(|s| s.to_string())),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_default_null_ordering_matches_postgres() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TABLE players (id INTEGER PRIMARY KEY, score INTEGER)", &[]).await.unwrap();
+    client.execute("INSERT INTO players (id, score) VALUES (1, 20), (2, NULL), (3, 10)", &[]).await.unwrap();
+
+    // NULLs sort as the largest value: last ascending, first descending
+    let ids = query_column(client, "SELECT id FROM players ORDER BY score").await;
+    assert_eq!(ids, vec![Some("3".to_string()), Some("1".to_string()), Some("2".to_string())]);
+    let ids = query_column(client, "SELECT id FROM players ORDER BY score DESC").await;
+    assert_eq!(ids, vec![Some("2".to_string()), Some("1".to_string()), Some("3".to_string())]);
+
+    // Explicit NULLS FIRST / LAST are honored
+    let ids = query_column(client, "SELECT id FROM players ORDER BY score NULLS FIRST").await;
+    assert_eq!(ids, vec![Some("2".to_string()), Some("3".to_string()), Some("1".to_string())]);
+    let ids = query_column(client, "SELECT id FROM players ORDER BY score DESC NULLS LAST").await;
+    assert_eq!(ids, vec![Some("1".to_string()), Some("3".to_string()), Some("2".to_string())]);
+
+    // Keyset-style pagination through the extended protocol sees the same order
+    let rows = client.query("SELECT id FROM players ORDER BY score LIMIT $1 OFFSET $2", &[&2i64, &1i64]).await.unwrap();
+    let ids: Vec<i32> = rows.iter().map(|row| row.get(0)).collect();
+    assert_eq!(ids, vec![1, 2]);
+
+    // Window ORDER BY follows the same rule
+    let ranks = query_column(client, "SELECT rank() OVER (ORDER BY score DESC) FROM players ORDER BY id").await;
+    assert_eq!(ranks, vec![Some("2".to_string()), Some("1".to_string()), Some("3".to_string())]);
+
+    server.abort();
+}
@@ -64,13 +64,13 @@ fn test_create_table_translator_numeric_parsing() {
     
     let (translated_sql, type_mappings) = CreateTableTranslator::translate(create_sql).unwrap();
     
-    // Verify NUMERIC types are translated to DECIMAL for SQLite
-    assert!(translated_sql.contains("DECIMAL"));
+    // Verify NUMERIC types are stored as decimal text in SQLite
+    assert!(translated_sql.contains("price TEXT"));
     
     // Check type modifiers are extracted correctly
     let price_mapping = type_mappings.get("test_numeric.price").unwrap();
     assert_eq!(price_mapping.pg_type, "NUMERIC(10,2)");
-    assert_eq!(price_mapping.sqlite_type, "DECIMAL");
+    assert_eq!(price_mapping.sqlite_type, "TEXT");
     
     // Decode type modifier for price (10,2)
     let modifier = price_mapping.type_modifier.unwrap();
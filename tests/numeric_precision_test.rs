@@ -0,0 +1,195 @@
+mod common;
+use common::*;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+use tokio_postgres::SimpleQueryMessage;
+
+#[tokio::test]
+async fn test_numeric_sum_is_exact() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute(
+        "CREATE TABLE ledger (id INTEGER PRIMARY KEY, account TEXT, amount NUMERIC(12,2))",
+        &[]
+    ).await.unwrap();
+    for id in 1..=10 {
+        client.execute(&format!("INSERT INTO ledger (id, account, amount) VALUES ({id}, 'a', '0.10')"), &[]).await.unwrap();
+    }
+    client.execute("INSERT INTO ledger (id, account, amount) VALUES (11, 'b', '19.99'), (12, 'b', '0.01')", &[]).await.unwrap();
+
+    // Floating point accumulation would give 0.9999999999999999
+    let messages = client.simple_query("SELECT SUM(amount) FROM ledger WHERE account = 'a'").await.unwrap();
+    let total = messages.iter().find_map(|msg| match msg {
+        SimpleQueryMessage::Row(row) => row.get(0).map(|s| s.to_string()),
+        _ => None,
+    }).unwrap();
+    assert_eq!(Decimal::from_str(&total).unwrap(), Decimal::from_str("1.00").unwrap());
+
+    // Binary results carry the exact value too
+    let rows = client.query(
+        "SELECT account, sum(amount) AS total, avg(amount) AS average FROM ledger GROUP BY account ORDER BY total DESC",
+        &[]
+    ).await.unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].get::<_, String>("account"), "b");
+    assert_eq!(rows[0].get::<_, Decimal>("total"), Decimal::from_str("20.00").unwrap());
+    assert_eq!(rows[0].get::<_, Decimal>("average"), Decimal::from_str("10.00").unwrap());
+    assert_eq!(rows[1].get::<_, Decimal>("total"), Decimal::from_str("1.00").unwrap());
+    assert_eq!(rows[1].get::<_, Decimal>("average"), Decimal::from_str("0.10").unwrap());
+
+    // Aggregates over no rows are NULL
+    let row = client.query_one("SELECT SUM(amount) FROM ledger WHERE id > 100", &[]).await.unwrap();
+    assert_eq!(row.get::<_, Option<Decimal>>(0), None);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_numeric_totals_compare_and_sort_numerically() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute(
+        "CREATE TABLE orders (id INTEGER PRIMARY KEY, customer TEXT, amount NUMERIC(10,2))",
+        &[]
+    ).await.unwrap();
+    client.execute(
+        "INSERT INTO orders (id, customer, amount) VALUES \
+         (1, 'a', '6.00'), (2, 'a', '4.00'), (3, 'b', '9.00'), (4, 'c', '2.50'), (5, 'c', '0.50')",
+        &[]
+    ).await.unwrap();
+
+    // Totals of 10.00, 9.00 and 3.00 would sort and compare wrongly as text
+    let totals = |rows: Vec<tokio_postgres::Row>| rows.iter()
+        .map(|row| (row.get::<_, String>(0), row.get::<_, Decimal>(1)))
+        .collect::<Vec<_>>();
+    let expected = |pairs: &[(&str, &str)]| pairs.iter()
+        .map(|(customer, total)| (customer.to_string(), Decimal::from_str(total).unwrap()))
+        .collect::<Vec<_>>();
+
+    let rows = client.query(
+        "SELECT customer, sum(amount) AS total FROM orders GROUP BY customer ORDER BY total DESC",
+        &[]
+    ).await.unwrap();
+    assert_eq!(totals(rows), expected(&[("a", "10.00"), ("b", "9.00"), ("c", "3.00")]));
+
+    for (having, matches) in [
+        ("sum(amount) >= 9", vec![("a", "10.00"), ("b", "9.00")]),
+        ("sum(amount) <= 9", vec![("b", "9.00"), ("c", "3.00")]),
+        ("sum(amount) <> 9", vec![("a", "10.00"), ("c", "3.00")]),
+        ("sum(amount) > 9.5", vec![("a", "10.00")]),
+    ] {
+        let rows = client.query(
+            &format!("SELECT customer, sum(amount) AS total FROM orders GROUP BY customer HAVING {having} ORDER BY customer"),
+            &[]
+        ).await.unwrap();
+        assert_eq!(totals(rows), expected(&matches), "HAVING {having}");
+    }
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_numeric_window_sums_are_exact() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute(
+        "CREATE TABLE payments (id INTEGER PRIMARY KEY, amount NUMERIC(10,2))",
+        &[]
+    ).await.unwrap();
+    for id in 1..=10 {
+        client.execute(&format!("INSERT INTO payments (id, amount) VALUES ({id}, '0.10')"), &[]).await.unwrap();
+    }
+
+    // A running total in floating point reaches 0.30000000000000004 on the third row
+    let rows = client.query(
+        "SELECT id, sum(amount) OVER (ORDER BY id) AS running, avg(amount) OVER () AS average FROM payments ORDER BY id",
+        &[]
+    ).await.unwrap();
+    assert_eq!(rows.len(), 10);
+    assert_eq!(rows[2].get::<_, Decimal>("running"), Decimal::from_str("0.30").unwrap());
+    assert_eq!(rows[9].get::<_, Decimal>("running"), Decimal::from_str("1.00").unwrap());
+    assert_eq!(rows[9].get::<_, Decimal>("average"), Decimal::from_str("0.10").unwrap());
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_numeric_values_are_stored_exactly() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute(
+        "CREATE TABLE accounts (id INTEGER PRIMARY KEY, balance NUMERIC(20,2), rate NUMERIC)",
+        &[]
+    ).await.unwrap();
+    // More digits than a REAL holds
+    client.execute(
+        "INSERT INTO accounts (id, balance, rate) VALUES (1, 12345678901234567.89, 0.1), (2, 5.5, 0.2), (3, -7, 10)",
+        &[]
+    ).await.unwrap();
+
+    let text = |messages: Vec<SimpleQueryMessage>| -> Vec<Vec<String>> {
+        messages.iter().filter_map(|msg| match msg {
+            SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).unwrap_or("NULL").to_string()).collect()),
+            _ => None,
+        }).collect()
+    };
+
+    // Values keep every digit and the column's scale
+    let rows = text(client.simple_query("SELECT balance, rate FROM accounts ORDER BY id").await.unwrap());
+    assert_eq!(rows, vec![
+        vec!["12345678901234567.89".to_string(), "0.1".to_string()],
+        vec!["5.50".to_string(), "0.2".to_string()],
+        vec!["-7.00".to_string(), "10".to_string()],
+    ]);
+
+    // Arithmetic is exact
+    let rows = text(client.simple_query("SELECT balance + 1, rate + 0.2 FROM accounts WHERE id = 1").await.unwrap());
+    assert_eq!(rows, vec![vec!["12345678901234568.89".to_string(), "0.3".to_string()]]);
+
+    // Comparisons, ORDER BY and MIN/MAX go by value rather than by text
+    let rows = text(client.simple_query("SELECT id FROM accounts WHERE rate > 9 ORDER BY rate DESC").await.unwrap());
+    assert_eq!(rows, vec![vec!["3".to_string()]]);
+    let rows = text(client.simple_query("SELECT id FROM accounts ORDER BY rate").await.unwrap());
+    assert_eq!(rows, vec![vec!["1".to_string()], vec!["2".to_string()], vec!["3".to_string()]]);
+    let rows = text(client.simple_query("SELECT id FROM accounts WHERE rate BETWEEN 0.15 AND 9 OR rate IN (0.10)").await.unwrap());
+    assert_eq!(rows, vec![vec!["1".to_string()], vec!["2".to_string()]]);
+    let rows = text(client.simple_query("SELECT min(balance), max(rate), max(rate + 1) FROM accounts").await.unwrap());
+    assert_eq!(rows, vec![vec!["-7.00".to_string(), "10".to_string(), "11".to_string()]]);
+
+    // Assigned results are rounded to the column's scale
+    client.execute("UPDATE accounts SET balance = balance * 1.005 WHERE id = 2", &[]).await.unwrap();
+    let rows = text(client.simple_query("SELECT balance FROM accounts WHERE id = 2").await.unwrap());
+    assert_eq!(rows, vec![vec!["5.53".to_string()]]);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_numeric_parameters_are_stored_exactly() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute(
+        "CREATE TABLE prices (id INTEGER PRIMARY KEY, price NUMERIC(20,2))",
+        &[]
+    ).await.unwrap();
+    client.execute(
+        "INSERT INTO prices (id, price) VALUES ($1, $2)",
+        &[&1i32, &Decimal::from_str("12345678901234567.8").unwrap()]
+    ).await.unwrap();
+
+    let row = client.query_one("SELECT price FROM prices WHERE id = 1", &[]).await.unwrap();
+    assert_eq!(row.get::<_, Decimal>(0), Decimal::from_str("12345678901234567.80").unwrap());
+    let messages = client.simple_query("SELECT price FROM prices WHERE id = 1").await.unwrap();
+    let price = messages.iter().find_map(|msg| match msg {
+        SimpleQueryMessage::Row(row) => row.get(0).map(|s| s.to_string()),
+        _ => None,
+    }).unwrap();
+    assert_eq!(price, "12345678901234567.80");
+
+    server.abort();
+}
@@ -0,0 +1,90 @@
+mod common;
+use common::{setup_test_server, spawn_server_with, ServerProcess};
+
+/// Run the server binary on a database file and connect to it
+async fn start_server(database: &std::path::Path) -> (ServerProcess, tokio_postgres::Client) {
+    let server = spawn_server_with(|command| {
+        command.args(["--log-level", "error", "--database"]).arg(database);
+    }).await;
+    let client = server.connect().await;
+    (server, client)
+}
+
+async fn value(client: &tokio_postgres::Client, query: &str) -> String {
+    client.simple_query(query).await.unwrap().into_iter()
+        .find_map(|message| match message {
+            tokio_postgres::SimpleQueryMessage::Row(row) => row.get(0).map(str::to_string),
+            _ => None,
+        })
+        .unwrap_or_else(|| panic!("no row for {query}"))
+}
+
+#[tokio::test]
+async fn test_regclass_oid_matches_catalogs() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute("CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT DEFAULT 'none') WITH OIDS").await.unwrap();
+    // Same first characters and length as items, which gave both the same OID before they were stored
+    client.batch_execute("CREATE TABLE itemz (id INTEGER) WITHOUT OIDS").await.unwrap();
+    client.batch_execute("CREATE TABLE legacy (id INTEGER) WITH (oids = false)").await.unwrap();
+
+    let items = value(client, "SELECT 'items'::regclass::oid").await;
+    assert!(items.parse::<i64>().is_ok(), "{items}");
+    assert_eq!(value(client, "SELECT oid FROM pg_class WHERE relname = 'items'").await, items);
+    assert_eq!(value(client, "SELECT attrelid FROM pg_attribute WHERE attname = 'name'").await, items);
+    assert_eq!(value(client, "SELECT conrelid FROM pg_constraint WHERE conname = 'items_pkey'").await, items);
+    assert_eq!(value(client, "SELECT adrelid FROM pg_attrdef WHERE adsrc = '''none'''").await, items);
+    assert_eq!(value(client, "SELECT 'public.items'::regclass::oid").await, items);
+
+    let itemz = value(client, "SELECT 'itemz'::regclass::oid").await;
+    assert_ne!(itemz, items);
+    assert_eq!(value(client, "SELECT oid FROM pg_class WHERE relname = 'itemz'").await, itemz);
+
+    // A renamed table keeps its OID
+    client.batch_execute("ALTER TABLE itemz RENAME TO goods").await.unwrap();
+    assert_eq!(value(client, "SELECT 'goods'::regclass::oid").await, itemz);
+    assert_eq!(value(client, "SELECT 'goods'::regclass").await, "goods");
+}
+
+/// OIDs of an enum type and label, a composite type, a table and a serial column's sequence
+async fn object_oids(client: &tokio_postgres::Client) -> Vec<String> {
+    let composite = client.simple_query("SELECT oid, typname FROM pg_type WHERE typname IN ('address', 'mood')").await.unwrap().into_iter()
+        .find_map(|message| match message {
+            tokio_postgres::SimpleQueryMessage::Row(row) if row.get(1) == Some("address") => row.get(0).map(str::to_string),
+            _ => None,
+        })
+        .unwrap();
+    vec![
+        value(client, "SELECT oid FROM pg_type WHERE typname = 'mood'").await,
+        value(client, "SELECT oid FROM pg_enum WHERE enumlabel = 'sad'").await,
+        composite,
+        value(client, "SELECT oid FROM pg_class WHERE relname = 'people'").await,
+        value(client, "SELECT 'people_id_seq'::regclass::oid").await,
+    ]
+}
+
+#[tokio::test]
+async fn test_oids_survive_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let database = dir.path().join("oids.db");
+
+    let before = {
+        let (_server, client) = start_server(&database).await;
+        client.batch_execute(
+            "CREATE TYPE mood AS ENUM ('happy', 'sad');
+             CREATE TYPE address AS (street TEXT, city TEXT);
+             CREATE TABLE people (id SERIAL PRIMARY KEY, feeling mood)",
+        ).await.unwrap();
+        object_oids(&client).await
+    };
+    assert_eq!(before.iter().collect::<std::collections::HashSet<_>>().len(), before.len(), "{before:?}");
+
+    let (_server, client) = start_server(&database).await;
+    assert_eq!(object_oids(&client).await, before);
+
+    // A type created after a drop does not get an OID another object still has
+    client.batch_execute("DROP TYPE address; CREATE TYPE address AS (street TEXT)").await.unwrap();
+    let after = object_oids(&client).await;
+    assert!(!before.iter().enumerate().any(|(i, oid)| i != 2 && *oid == after[2]), "{after:?}");
+}
@@ -0,0 +1,53 @@
+mod common;
+use common::setup_test_server;
+
+#[tokio::test]
+async fn test_fetch_first_and_offset_rows() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)", &[]).await.unwrap();
+    for i in 1..=10 {
+        client.execute("INSERT INTO items (id, name) VALUES ($1, $2)", &[&i, &format!("item{i}")]).await.unwrap();
+    }
+
+    let ids = |rows: Vec<tokio_postgres::Row>| rows.iter().map(|r| r.get::<_, i32>(0)).collect::<Vec<_>>();
+
+    // SQL-standard syntax in the simple protocol
+    let messages = client.simple_query("SELECT id FROM items ORDER BY id OFFSET 2 ROWS FETCH FIRST 3 ROWS ONLY").await.unwrap();
+    let simple_ids: Vec<String> = messages.iter().filter_map(|m| match m {
+        tokio_postgres::SimpleQueryMessage::Row(row) => row.get(0).map(str::to_string),
+        _ => None,
+    }).collect();
+    assert_eq!(simple_ids, vec!["3", "4", "5"]);
+
+    // Extended protocol with parameters
+    let rows = client.query(
+        "SELECT id FROM items ORDER BY id OFFSET $1 ROWS FETCH NEXT $2 ROWS ONLY",
+        &[&4i64, &2i64],
+    ).await.unwrap();
+    assert_eq!(ids(rows), vec![5, 6]);
+
+    // OFFSET without LIMIT
+    let rows = client.query("SELECT id FROM items ORDER BY id OFFSET 8", &[]).await.unwrap();
+    assert_eq!(ids(rows), vec![9, 10]);
+
+    // OFFSET before LIMIT
+    let rows = client.query("SELECT id FROM items ORDER BY id OFFSET 1 LIMIT 2", &[]).await.unwrap();
+    assert_eq!(ids(rows), vec![2, 3]);
+
+    // LIMIT ALL and FETCH FIRST ROW ONLY
+    let rows = client.query("SELECT id FROM items ORDER BY id LIMIT ALL", &[]).await.unwrap();
+    assert_eq!(rows.len(), 10);
+    let rows = client.query("SELECT id FROM items ORDER BY id DESC FETCH FIRST ROW ONLY", &[]).await.unwrap();
+    assert_eq!(ids(rows), vec![10]);
+
+    // Keyset pagination: the next page starts after the last seen key
+    let rows = client.query(
+        "SELECT id FROM items WHERE id > $1 ORDER BY id FETCH FIRST 3 ROWS ONLY",
+        &[&7i32],
+    ).await.unwrap();
+    assert_eq!(ids(rows), vec![8, 9, 10]);
+
+    server.abort();
+}
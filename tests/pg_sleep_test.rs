@@ -0,0 +1,70 @@
+mod common;
+use common::setup_test_server;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn test_pg_sleep_simple_and_extended() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    let start = Instant::now();
+    let messages = client.simple_query("SELECT pg_sleep(0.2)").await.unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(200));
+    let rows = messages.iter().filter(|m| matches!(m, tokio_postgres::SimpleQueryMessage::Row(_))).count();
+    assert_eq!(rows, 1);
+
+    let start = Instant::now();
+    client.simple_query("SELECT pg_sleep_for('100 milliseconds')").await.unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(100));
+
+    // Extended protocol with a bound parameter
+    let start = Instant::now();
+    let rows = client.query("SELECT pg_sleep($1::float8)", &[&0.1f64]).await.unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(100));
+    assert_eq!(rows.len(), 1);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_pg_sleep_does_not_block_runtime() {
+    // The server runs on this test's single-threaded runtime, so a blocking sleep
+    // would starve the ticker task below
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    let ticks = Arc::new(AtomicUsize::new(0));
+    let ticker_ticks = ticks.clone();
+    let ticker = tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            ticker_ticks.fetch_add(1, Ordering::Relaxed);
+        }
+    });
+
+    client.simple_query("SELECT pg_sleep(0.3)").await.unwrap();
+    ticker.abort();
+
+    assert!(ticks.load(Ordering::Relaxed) >= 10, "runtime was blocked during pg_sleep");
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_pg_sleep_extended_sleeps_once() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    // Parse must not run the sleep while describing the statement
+    let start = Instant::now();
+    let rows = client.query("SELECT pg_sleep(0.5)", &[]).await.unwrap();
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(500));
+    assert!(elapsed < Duration::from_millis(900), "pg_sleep(0.5) took {elapsed:?}");
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].columns()[0].type_().oid(), 2278);
+
+    server.abort();
+}
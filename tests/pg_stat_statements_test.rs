@@ -0,0 +1,66 @@
+mod common;
+use common::setup_test_server;
+use tokio_postgres::SimpleQueryMessage;
+
+fn rows(messages: &[SimpleQueryMessage]) -> Vec<Vec<Option<String>>> {
+    messages.iter()
+        .filter_map(|msg| match msg {
+            SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).map(str::to_string)).collect()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_pg_stat_statements() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.simple_query("CREATE TABLE stat_items (id INTEGER PRIMARY KEY, name TEXT)").await.unwrap();
+    // Statements that differ only in literals share one entry
+    for id in 1..=3 {
+        client.simple_query(&format!("INSERT INTO stat_items (id, name) VALUES ({id}, 'item {id}')")).await.unwrap();
+    }
+    // Extended protocol executions are counted per Execute
+    for _ in 0..2 {
+        let found = client.query("SELECT id, name FROM stat_items WHERE id > $1", &[&1i32]).await.unwrap();
+        assert_eq!(found.len(), 2);
+    }
+
+    let messages = client.simple_query(
+        "SELECT query, calls, rows, total_exec_time >= mean_exec_time, max_exec_time >= min_exec_time \
+         FROM pg_stat_statements WHERE query LIKE '%stat_items%' AND query NOT LIKE '%pg_stat_statements%' \
+         ORDER BY query"
+    ).await.unwrap();
+    assert_eq!(rows(&messages), vec![
+        vec![
+            Some("CREATE TABLE stat_items (id INTEGER PRIMARY KEY, name TEXT)".to_string()),
+            Some("1".to_string()), Some("0".to_string()), Some("t".to_string()), Some("t".to_string()),
+        ],
+        vec![
+            Some("INSERT INTO stat_items (id, name) VALUES (1, 'item 1')".to_string()),
+            Some("3".to_string()), Some("3".to_string()), Some("t".to_string()), Some("t".to_string()),
+        ],
+        vec![
+            Some("SELECT id, name FROM stat_items WHERE id > $1".to_string()),
+            Some("2".to_string()), Some("4".to_string()), Some("t".to_string()), Some("t".to_string()),
+        ],
+    ]);
+
+    // queryid is the fingerprint shared by every variant of the statement
+    let messages = client.simple_query(
+        "SELECT queryid FROM pg_stat_statements WHERE query LIKE 'INSERT INTO stat_items%'"
+    ).await.unwrap();
+    let queryid: i64 = rows(&messages)[0][0].as_deref().unwrap().parse().unwrap();
+    assert_eq!(queryid as u64, pgsqlite::cache::QueryFingerprint::generate("INSERT INTO stat_items (id, name) VALUES (7, 'x')"));
+
+    // After a reset only the reset call itself is left
+    client.simple_query("SELECT pg_stat_statements_reset()").await.unwrap();
+    let messages = client.simple_query("SELECT query, calls FROM pg_stat_statements").await.unwrap();
+    assert_eq!(rows(&messages), vec![vec![
+        Some("SELECT pg_stat_statements_reset()".to_string()),
+        Some("1".to_string()),
+    ]]);
+
+    server.abort();
+}
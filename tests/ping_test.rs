@@ -0,0 +1,55 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use pgsqlite::ping::{ping, PingTarget};
+use tokio::net::TcpListener;
+
+fn target(port: u16) -> PingTarget {
+    PingTarget {
+        host: "127.0.0.1".to_string(),
+        port,
+        user: "postgres".to_string(),
+        database: "postgres".to_string(),
+        timeout: Duration::from_secs(3),
+    }
+}
+
+#[tokio::test]
+async fn test_ping_accepting_server() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("ping.db");
+    let db = Arc::new(pgsqlite::session::DbHandler::new(db_path.to_str().unwrap()).unwrap());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let server = tokio::spawn(async move {
+        let (stream, addr) = listener.accept().await.unwrap();
+        pgsqlite::handle_test_connection_with_pool(stream, addr, db).await
+    });
+
+    tokio::task::spawn_blocking(move || ping(&target(port))).await.unwrap().unwrap();
+    // Terminate ends the session cleanly
+    server.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn test_ping_fails_without_server() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    drop(listener);
+
+    let error = tokio::task::spawn_blocking(move || ping(&target(port))).await.unwrap().unwrap_err();
+    assert!(error.downcast_ref::<std::io::Error>().is_some(), "{error:?}");
+}
+
+#[tokio::test]
+async fn test_ping_fails_when_server_hangs_up() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        drop(stream);
+    });
+
+    assert!(tokio::task::spawn_blocking(move || ping(&target(port))).await.unwrap().is_err());
+}
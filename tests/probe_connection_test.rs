@@ -0,0 +1,87 @@
+mod common;
+use common::spawn_server_with;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::NoTls;
+
+fn startup_packet() -> Vec<u8> {
+    let mut body = 196608i32.to_be_bytes().to_vec();
+    body.extend_from_slice(b"user\0postgres\0database\0main\0\0");
+    let mut packet = ((body.len() + 4) as i32).to_be_bytes().to_vec();
+    packet.extend_from_slice(&body);
+    packet
+}
+
+fn request(code: i32) -> Vec<u8> {
+    let mut packet = 8i32.to_be_bytes().to_vec();
+    packet.extend_from_slice(&code.to_be_bytes());
+    packet
+}
+
+/// Port checks and pg_isready-style probes hang up at every stage of the startup
+/// handshake; none of that is an error worth logging, and a full server still
+/// answers with SQLSTATE 53300
+#[tokio::test]
+async fn test_probes_are_not_logged_as_errors() {
+    let socket_dir = tempfile::tempdir().unwrap();
+    let log_path = socket_dir.path().join("server.log");
+
+    // The server counts as started once a connection to it was made and left
+    let server = spawn_server_with(|command| {
+        command
+            .args(["--in-memory", "--log-level", "warn", "--max-connections", "1"])
+            .arg("--socket-dir")
+            .arg(socket_dir.path())
+            .stdout(std::fs::File::create(&log_path).unwrap());
+    }).await;
+    let port = server.port;
+
+    // Part of a packet
+    TcpStream::connect(("127.0.0.1", port)).unwrap().write_all(&[0, 0]).unwrap();
+
+    // SSL is declined, then the client gives up
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    stream.write_all(&request(80877103)).unwrap();
+    let mut answer = [0u8; 1];
+    stream.read_exact(&mut answer).unwrap();
+    assert_eq!(&answer, b"N");
+    drop(stream);
+
+    // GSSAPI encryption is declined too, and the startup packet that follows is served;
+    // pg_isready hangs up as soon as it sees the first reply
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    stream.write_all(&request(80877104)).unwrap();
+    stream.read_exact(&mut answer).unwrap();
+    assert_eq!(&answer, b"N");
+    stream.write_all(&startup_packet()).unwrap();
+    stream.read_exact(&mut answer).unwrap();
+    assert_eq!(&answer, b"R");
+    drop(stream);
+
+    // The probe's session slot is given back once it is noticed to be gone
+    let started = Instant::now();
+    let client = loop {
+        match tokio_postgres::connect(&format!("host=127.0.0.1 port={port} dbname=main user=postgres"), NoTls).await {
+            Ok((client, connection)) => {
+                tokio::spawn(connection);
+                break client;
+            }
+            Err(e) => {
+                assert!(started.elapsed() < Duration::from_secs(30), "server did not accept a client: {e}");
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        }
+    };
+    client.simple_query("SELECT 1").await.unwrap();
+
+    let err = tokio_postgres::connect(&format!("host=127.0.0.1 port={port} dbname=main user=postgres"), NoTls)
+        .await.err().expect("the server is full");
+    assert_eq!(err.code(), Some(&SqlState::TOO_MANY_CONNECTIONS), "unexpected error: {err:?}");
+
+    drop(client);
+    drop(server);
+    let log = std::fs::read_to_string(&log_path).unwrap();
+    assert!(!log.contains("ERROR"), "probes were logged as errors:\n{log}");
+}
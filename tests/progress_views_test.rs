@@ -0,0 +1,60 @@
+mod common;
+use common::setup_test_server;
+use tokio_postgres::SimpleQueryMessage;
+use tokio_postgres::error::SqlState;
+
+fn rows(messages: &[SimpleQueryMessage]) -> Vec<Vec<Option<String>>> {
+    messages.iter()
+        .filter_map(|msg| match msg {
+            SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).map(str::to_string)).collect()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_vacuum_and_progress_views() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TABLE logs (id INTEGER PRIMARY KEY, message TEXT)", &[]).await.unwrap();
+    client.simple_query(
+        "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 500) \
+         INSERT INTO logs (id, message) SELECT i, 'line ' || i FROM n"
+    ).await.unwrap();
+    client.execute("DELETE FROM logs WHERE id > 100", &[]).await.unwrap();
+
+    client.simple_query("VACUUM").await.unwrap();
+    client.simple_query("VACUUM (VERBOSE, ANALYZE) logs").await.unwrap();
+    client.execute("VACUUM FULL logs", &[]).await.unwrap();
+    let count: i64 = client.query_one("SELECT count(*) FROM logs", &[]).await.unwrap().get(0);
+    assert_eq!(count, 100);
+
+    let err = client.simple_query("VACUUM missing").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::UNDEFINED_TABLE), "unexpected error: {err:?}");
+
+    client.simple_query("BEGIN").await.unwrap();
+    let err = client.simple_query("VACUUM logs").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::ACTIVE_SQL_TRANSACTION), "unexpected error: {err:?}");
+    client.simple_query("ROLLBACK").await.unwrap();
+
+    // The last statement of the script sees the script itself in progress
+    let messages = client.simple_query(
+        "CREATE TABLE imported (id INTEGER PRIMARY KEY, name TEXT); \
+         INSERT INTO imported (id, name) VALUES (1, 'a'), (2, 'b'); \
+         SELECT phase, statements_total, statements_done FROM pg_stat_progress_import"
+    ).await.unwrap();
+    assert_eq!(rows(&messages), vec![vec![
+        Some("executing statements".to_string()),
+        Some("3".to_string()),
+        Some("2".to_string()),
+    ]]);
+
+    // Finished operations leave the views
+    for view in ["pg_stat_progress_copy", "pg_stat_progress_vacuum", "pg_stat_progress_import"] {
+        let messages = client.simple_query(&format!("SELECT count(*) FROM {view}")).await.unwrap();
+        assert_eq!(rows(&messages), vec![vec![Some("0".to_string())]], "{view}");
+    }
+
+    server.abort();
+}
@@ -0,0 +1,346 @@
+mod common;
+use common::*;
+use tokio_postgres::{Client, SimpleQueryMessage};
+
+// Query texts below are the ones psql 15 sends for each meta-command (captured with psql -E)
+
+const LIST_DATABASES: &str = r#"SELECT d.datname as "Name",
+       pg_catalog.pg_get_userbyid(d.datdba) as "Owner",
+       pg_catalog.pg_encoding_to_char(d.encoding) as "Encoding",
+       d.datcollate as "Collate",
+       d.datctype as "Ctype",
+       NULL as "ICU Locale",
+       'libc' AS "Locale Provider",
+       pg_catalog.array_to_string(d.datacl, E'\n') AS "Access privileges"
+FROM pg_catalog.pg_database d
+ORDER BY 1;"#;
+
+const LIST_TABLES: &str = r#"SELECT n.nspname as "Schema",
+  c.relname as "Name",
+  CASE c.relkind WHEN 'r' THEN 'table' WHEN 'v' THEN 'view' WHEN 'm' THEN 'materialized view' WHEN 'i' THEN 'index' WHEN 'S' THEN 'sequence' WHEN 't' THEN 'TOAST table' WHEN 'f' THEN 'foreign table' WHEN 'p' THEN 'partitioned table' WHEN 'I' THEN 'partitioned index' END as "Type",
+  pg_catalog.pg_get_userbyid(c.relowner) as "Owner"
+FROM pg_catalog.pg_class c
+     LEFT JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+     LEFT JOIN pg_catalog.pg_am am ON am.oid = c.relam
+WHERE c.relkind IN ('r','p','')
+      AND n.nspname <> 'pg_catalog'
+      AND n.nspname !~ '^pg_toast'
+      AND n.nspname <> 'information_schema'
+  AND pg_catalog.pg_table_is_visible(c.oid)
+ORDER BY 1,2;"#;
+
+const LIST_INDEXES: &str = r#"SELECT n.nspname as "Schema",
+  c.relname as "Name",
+  CASE c.relkind WHEN 'r' THEN 'table' WHEN 'v' THEN 'view' WHEN 'm' THEN 'materialized view' WHEN 'i' THEN 'index' WHEN 'S' THEN 'sequence' WHEN 't' THEN 'TOAST table' WHEN 'f' THEN 'foreign table' WHEN 'p' THEN 'partitioned table' WHEN 'I' THEN 'partitioned index' END as "Type",
+  pg_catalog.pg_get_userbyid(c.relowner) as "Owner",
+  c2.relname as "Table"
+FROM pg_catalog.pg_class c
+     LEFT JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+     LEFT JOIN pg_catalog.pg_am am ON am.oid = c.relam
+     LEFT JOIN pg_catalog.pg_index i ON i.indexrelid = c.oid
+     LEFT JOIN pg_catalog.pg_class c2 ON i.indrelid = c2.oid
+WHERE c.relkind IN ('i','I','')
+      AND n.nspname <> 'pg_catalog'
+      AND n.nspname !~ '^pg_toast'
+      AND n.nspname <> 'information_schema'
+  AND pg_catalog.pg_table_is_visible(c.oid)
+ORDER BY 1,2;"#;
+
+const LIST_FUNCTIONS: &str = r#"SELECT n.nspname as "Schema",
+  p.proname as "Name",
+  pg_catalog.pg_get_function_result(p.oid) as "Result data type",
+  pg_catalog.pg_get_function_arguments(p.oid) as "Argument data types",
+ CASE p.prokind
+  WHEN 'a' THEN 'agg'
+  WHEN 'w' THEN 'window'
+  WHEN 'p' THEN 'proc'
+  ELSE 'func'
+ END as "Type"
+FROM pg_catalog.pg_proc p
+     LEFT JOIN pg_catalog.pg_namespace n ON n.oid = p.pronamespace
+WHERE pg_catalog.pg_function_is_visible(p.oid)
+      AND n.nspname <> 'pg_catalog'
+      AND n.nspname <> 'information_schema'
+ORDER BY 1, 2, 4;"#;
+
+const LIST_SCHEMAS: &str = r#"SELECT n.nspname AS "Name",
+  pg_catalog.pg_get_userbyid(n.nspowner) AS "Owner"
+FROM pg_catalog.pg_namespace n
+WHERE n.nspname !~ '^pg_' AND n.nspname <> 'information_schema'
+ORDER BY 1;"#;
+
+const LIST_ROLES: &str = r#"SELECT r.rolname, r.rolsuper, r.rolinherit,
+  r.rolcreaterole, r.rolcreatedb, r.rolcanlogin,
+  r.rolconnlimit, r.rolvaliduntil,
+  ARRAY(SELECT b.rolname
+        FROM pg_catalog.pg_auth_members m
+        JOIN pg_catalog.pg_roles b ON (m.roleid = b.oid)
+        WHERE m.member = r.oid) as memberof
+, r.rolreplication
+, r.rolbypassrls
+FROM pg_catalog.pg_roles r
+WHERE r.rolname !~ '^pg_'
+ORDER BY 1;"#;
+
+const LOOKUP_RELATION: &str = r#"SELECT c.oid,
+  n.nspname,
+  c.relname
+FROM pg_catalog.pg_class c
+     LEFT JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+WHERE c.relname OPERATOR(pg_catalog.~) '^({name})$' COLLATE pg_catalog.default
+  AND pg_catalog.pg_table_is_visible(c.oid)
+ORDER BY 2, 3;"#;
+
+const RELATION_INFO: &str = r#"SELECT c.relchecks, c.relkind, c.relhasindex, c.relhasrules, c.relhastriggers, c.relrowsecurity, c.relforcerowsecurity, false AS relhasoids, c.relispartition, '', c.reltablespace, CASE WHEN c.reloftype = 0 THEN '' ELSE c.reloftype::pg_catalog.regtype::pg_catalog.text END, c.relpersistence, c.relreplident, am.amname
+FROM pg_catalog.pg_class c
+ LEFT JOIN pg_catalog.pg_class tc ON (c.reltoastrelid = tc.oid)
+LEFT JOIN pg_catalog.pg_am am ON (c.relam = am.oid)
+WHERE c.oid = '{oid}';"#;
+
+const COLUMNS: &str = r#"SELECT a.attname,
+  pg_catalog.format_type(a.atttypid, a.atttypmod),
+  (SELECT pg_catalog.pg_get_expr(d.adbin, d.adrelid, true)
+   FROM pg_catalog.pg_attrdef d
+   WHERE d.adrelid = a.attrelid AND d.adnum = a.attnum AND a.atthasdef),
+  a.attnotnull,
+  (SELECT c.collname FROM pg_catalog.pg_collation c, pg_catalog.pg_type t
+   WHERE c.oid = a.attcollation AND t.oid = a.atttypid AND a.attcollation <> t.typcollation) AS attcollation,
+  a.attidentity,
+  a.attgenerated
+FROM pg_catalog.pg_attribute a
+WHERE a.attrelid = '{oid}' AND a.attnum > 0 AND NOT a.attisdropped
+ORDER BY a.attnum;"#;
+
+const TABLE_INDEXES: &str = r#"SELECT c2.relname, i.indisprimary, i.indisunique, i.indisclustered, i.indisvalid, pg_catalog.pg_get_indexdef(i.indexrelid, 0, true),
+  pg_catalog.pg_get_constraintdef(con.oid, true), contype, condeferrable, condeferred, i.indisreplident, c2.reltablespace
+FROM pg_catalog.pg_class c, pg_catalog.pg_class c2, pg_catalog.pg_index i
+  LEFT JOIN pg_catalog.pg_constraint con ON (conrelid = i.indrelid AND conindid = i.indexrelid AND contype IN ('p','u','x'))
+WHERE c.oid = '{oid}' AND c.oid = i.indrelid AND i.indexrelid = c2.oid
+ORDER BY i.indisprimary DESC, c2.relname;"#;
+
+const CHECK_CONSTRAINTS: &str = r#"SELECT r.conname, pg_catalog.pg_get_constraintdef(r.oid, true)
+FROM pg_catalog.pg_constraint r
+WHERE r.conrelid = '{oid}' AND r.contype = 'c'
+ORDER BY 1;"#;
+
+const FOREIGN_KEYS: &str = r#"SELECT true as sametable, conname,
+  pg_catalog.pg_get_constraintdef(r.oid, true) as condef,
+  conrelid::pg_catalog.regclass AS ontable
+FROM pg_catalog.pg_constraint r
+WHERE r.conrelid = '{oid}' AND r.contype = 'f'
+     AND conparentid = 0
+ORDER BY conname"#;
+
+const REFERENCED_BY: &str = r#"SELECT conname, conrelid::pg_catalog.regclass AS ontable,
+       pg_catalog.pg_get_constraintdef(oid, true) AS condef
+  FROM pg_catalog.pg_constraint c
+ WHERE confrelid IN (SELECT pg_catalog.pg_partition_ancestors('{oid}')
+                     UNION ALL VALUES ('{oid}'::pg_catalog.regclass))
+       AND contype = 'f' AND conparentid = 0
+ORDER BY conname;"#;
+
+const INHERITS: &str = r#"SELECT c.oid::pg_catalog.regclass
+FROM pg_catalog.pg_class c, pg_catalog.pg_inherits i
+WHERE c.oid = i.inhparent AND i.inhrelid = '{oid}'
+  AND c.relkind != 'p' AND c.relkind != 'I'
+ORDER BY inhseqno;"#;
+
+const INHERITS_COUNT: &str = r#"SELECT pg_catalog.count(*) FROM pg_catalog.pg_inherits i WHERE i.inhparent = '{oid}';"#;
+
+struct QueryResult {
+    columns: Vec<String>,
+    rows: Vec<Vec<Option<String>>>,
+}
+
+async fn run(client: &Client, sql: &str) -> QueryResult {
+    let messages = client.simple_query(sql).await.unwrap_or_else(|e| panic!("{sql} failed: {e}"));
+    let mut result = QueryResult { columns: Vec::new(), rows: Vec::new() };
+    for message in messages {
+        if let SimpleQueryMessage::Row(row) = message {
+            if result.columns.is_empty() {
+                result.columns = row.columns().iter().map(|c| c.name().to_string()).collect();
+            }
+            result.rows.push((0..row.len()).map(|i| row.get(i).map(str::to_string)).collect());
+        }
+    }
+    result
+}
+
+async fn setup() -> TestServer {
+    let server = setup_test_server().await;
+    server.client.batch_execute(
+        "CREATE TABLE customers (
+            id SERIAL PRIMARY KEY,
+            name VARCHAR(50) NOT NULL,
+            email TEXT UNIQUE
+        );
+        CREATE TABLE orders (
+            id INTEGER PRIMARY KEY,
+            customer_id INTEGER REFERENCES customers(id),
+            total NUMERIC(10,2) DEFAULT 0 CHECK (total >= 0),
+            created_at TIMESTAMP
+        );
+        CREATE INDEX orders_created_idx ON orders (created_at);
+        CREATE VIEW big_orders AS SELECT id, total FROM orders WHERE total > 100;"
+    ).await.unwrap();
+    server
+}
+
+async fn lookup_oid(client: &Client, name: &str) -> String {
+    let result = run(client, &LOOKUP_RELATION.replace("{name}", name)).await;
+    assert_eq!(result.rows.len(), 1, "expected a single relation named {name}");
+    assert_eq!(result.rows[0][1].as_deref(), Some("public"));
+    assert_eq!(result.rows[0][2].as_deref(), Some(name));
+    result.rows[0][0].clone().unwrap()
+}
+
+fn column(result: &QueryResult, index: usize) -> Vec<Option<&str>> {
+    result.rows.iter().map(|row| row[index].as_deref()).collect()
+}
+
+#[tokio::test]
+async fn test_list_meta_commands() {
+    let server = setup().await;
+    let client = &server.client;
+
+    let databases = run(client, LIST_DATABASES).await;
+    assert_eq!(databases.columns, ["Name", "Owner", "Encoding", "Collate", "Ctype", "ICU Locale", "Locale Provider", "Access privileges"]);
+    assert_eq!(databases.rows.len(), 1);
+    assert_eq!(databases.rows[0][1].as_deref(), Some("postgres"));
+    assert_eq!(databases.rows[0][2].as_deref(), Some("UTF8"));
+
+    let tables = run(client, LIST_TABLES).await;
+    assert_eq!(tables.columns, ["Schema", "Name", "Type", "Owner"]);
+    assert_eq!(column(&tables, 1), [Some("customers"), Some("orders")]);
+    assert_eq!(column(&tables, 2), [Some("table"), Some("table")]);
+    assert_eq!(column(&tables, 0), [Some("public"), Some("public")]);
+
+    let indexes = run(client, LIST_INDEXES).await;
+    assert_eq!(indexes.columns, ["Schema", "Name", "Type", "Owner", "Table"]);
+    let orders_index = indexes.rows.iter()
+        .find(|row| row[1].as_deref() == Some("orders_created_idx"))
+        .expect("orders_created_idx should be listed");
+    assert_eq!(orders_index[2].as_deref(), Some("index"));
+    assert_eq!(orders_index[4].as_deref(), Some("orders"));
+
+    let functions = run(client, LIST_FUNCTIONS).await;
+    assert!(functions.rows.is_empty(), "built-in functions live in pg_catalog: {:?}", functions.rows);
+
+    let schemas = run(client, LIST_SCHEMAS).await;
+    assert_eq!(schemas.columns, ["Name", "Owner"]);
+    assert_eq!(schemas.rows, [[Some("public".to_string()), Some("postgres".to_string())]]);
+
+    let roles = run(client, LIST_ROLES).await;
+    assert_eq!(roles.rows.len(), 1);
+    assert_eq!(roles.columns[8], "memberof");
+    assert_eq!(roles.rows[0][0].as_deref(), Some("postgres"));
+    assert_eq!(roles.rows[0][1].as_deref(), Some("t"));
+    assert_eq!(roles.rows[0][8].as_deref(), Some("{}"));
+}
+
+#[tokio::test]
+async fn test_describe_table() {
+    let server = setup().await;
+    let client = &server.client;
+    let oid = lookup_oid(client, "orders").await;
+    let query = |template: &str| template.replace("{oid}", &oid);
+
+    let info = run(client, &query(RELATION_INFO)).await;
+    assert_eq!(info.rows.len(), 1);
+    assert_eq!(info.rows[0][0].as_deref(), Some("1"), "one check constraint");
+    assert_eq!(info.rows[0][1].as_deref(), Some("r"));
+    assert_eq!(info.rows[0][2].as_deref(), Some("t"), "relhasindex");
+    assert_eq!(info.rows[0][4].as_deref(), Some("t"), "relhastriggers gates the foreign key footer");
+
+    let columns = run(client, &query(COLUMNS)).await;
+    assert_eq!(column(&columns, 0), [Some("id"), Some("customer_id"), Some("total"), Some("created_at")]);
+    let total = &columns.rows[2];
+    assert_eq!(total[1].as_deref(), Some("numeric(10,2)"));
+    assert_eq!(total[2].as_deref(), Some("0"));
+    assert_eq!(columns.rows[0][3].as_deref(), Some("t"), "primary key is not null");
+
+    let indexes = run(client, &query(TABLE_INDEXES)).await;
+    assert_eq!(indexes.rows.len(), 2);
+    assert_eq!(indexes.rows[0][1].as_deref(), Some("t"), "primary key sorts first");
+    assert_eq!(indexes.rows[1][0].as_deref(), Some("orders_created_idx"));
+
+    let checks = run(client, &query(CHECK_CONSTRAINTS)).await;
+    assert_eq!(checks.rows.len(), 1);
+    assert_eq!(checks.rows[0][1].as_deref(), Some("CHECK (total >= 0)"));
+
+    let foreign_keys = run(client, &query(FOREIGN_KEYS)).await;
+    assert_eq!(foreign_keys.rows.len(), 1);
+    assert_eq!(foreign_keys.rows[0][2].as_deref(), Some("FOREIGN KEY (customer_id) REFERENCES customers(id)"));
+    assert_eq!(foreign_keys.rows[0][3].as_deref(), Some("orders"));
+
+    // Catalogs pgsqlite does not model answer with no rows rather than an error
+    assert!(run(client, &query(INHERITS)).await.rows.is_empty());
+    let inherits_count = run(client, &query(INHERITS_COUNT)).await;
+    assert_eq!(inherits_count.rows, [[Some("0".to_string())]]);
+}
+
+#[tokio::test]
+async fn test_describe_referenced_table() {
+    let server = setup().await;
+    let client = &server.client;
+    let oid = lookup_oid(client, "customers").await;
+    let query = |template: &str| template.replace("{oid}", &oid);
+
+    let columns = run(client, &query(COLUMNS)).await;
+    assert_eq!(columns.rows[0][2].as_deref(), Some("nextval('customers_id_seq'::regclass)"));
+    assert_eq!(columns.rows[1][1].as_deref(), Some("character varying(50)"));
+    assert_eq!(columns.rows[1][3].as_deref(), Some("t"));
+
+    let referenced_by = run(client, &query(REFERENCED_BY)).await;
+    assert_eq!(referenced_by.rows.len(), 1);
+    assert_eq!(referenced_by.rows[0][1].as_deref(), Some("orders"));
+    assert_eq!(referenced_by.rows[0][2].as_deref(), Some("FOREIGN KEY (customer_id) REFERENCES customers(id)"));
+}
+
+#[tokio::test]
+async fn test_schema_qualified_relations() {
+    let server = setup().await;
+    let client = &server.client;
+    client.batch_execute(
+        "CREATE SCHEMA tenant1;
+        CREATE TABLE tenant1.users (id SERIAL PRIMARY KEY, customer_id INTEGER REFERENCES public.customers(id));"
+    ).await.unwrap();
+    let in_schema = |template: &str, schema: &str| template.replace(
+        "  AND pg_catalog.pg_table_is_visible(c.oid)",
+        &format!("  AND n.nspname OPERATOR(pg_catalog.~) '^({schema})$' COLLATE pg_catalog.default"),
+    );
+
+    // \dt lists what the search_path finds, \dt tenant1.* the schema's tables
+    let tables = run(client, LIST_TABLES).await;
+    assert_eq!(column(&tables, 1), [Some("customers"), Some("orders")]);
+    let tables = run(client, &in_schema(LIST_TABLES, "tenant1")).await;
+    assert_eq!(column(&tables, 0), [Some("tenant1")]);
+    assert_eq!(column(&tables, 1), [Some("users")]);
+    let indexes = run(client, &in_schema(LIST_INDEXES, "tenant1")).await;
+    assert_eq!(column(&indexes, 1), [Some("users_pkey")]);
+    assert_eq!(column(&indexes, 4), [Some("users")]);
+
+    let schemas = run(client, LIST_SCHEMAS).await;
+    assert_eq!(column(&schemas, 0), [Some("public"), Some("tenant1")]);
+
+    // \d tenant1.users
+    let lookup = run(client, &in_schema(&LOOKUP_RELATION.replace("{name}", "users"), "tenant1")).await;
+    assert_eq!(lookup.rows.len(), 1);
+    assert_eq!(lookup.rows[0][1].as_deref(), Some("tenant1"));
+    let oid = lookup.rows[0][0].clone().unwrap();
+    let query = |template: &str| template.replace("{oid}", &oid);
+    let columns = run(client, &query(COLUMNS)).await;
+    assert_eq!(column(&columns, 0), [Some("id"), Some("customer_id")]);
+    assert_eq!(columns.rows[0][2].as_deref(), Some("nextval('tenant1.users_id_seq'::regclass)"));
+    let foreign_keys = run(client, &query(FOREIGN_KEYS)).await;
+    assert_eq!(foreign_keys.rows[0][1].as_deref(), Some("users_customer_id_fkey"));
+    assert_eq!(foreign_keys.rows[0][3].as_deref(), Some("tenant1.users"));
+
+    let namespaces = run(client, "SELECT relname, relnamespace::regnamespace FROM pg_class WHERE relname = 'users'").await;
+    assert_eq!(namespaces.rows, [[Some("users".to_string()), Some("tenant1".to_string())]]);
+
+    client.batch_execute("SET search_path = tenant1, public").await.unwrap();
+    let tables = run(client, LIST_TABLES).await;
+    assert_eq!(column(&tables, 1), [Some("customers"), Some("orders"), Some("users")]);
+    assert_eq!(column(&tables, 0), [Some("public"), Some("public"), Some("tenant1")]);
+}
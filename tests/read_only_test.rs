@@ -0,0 +1,111 @@
+mod common;
+use common::{free_port, spawn_server_with, ServerProcess};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::{Client, SimpleQueryMessage};
+
+async fn start_server(database: &Path, extra_args: &[&str]) -> ServerProcess {
+    spawn_server_with(|command| {
+        command.args(["--log-level", "error", "--database"]).arg(database).args(extra_args);
+    }).await
+}
+
+async fn scalar(client: &Client, sql: &str) -> Option<String> {
+    client.simple_query(sql).await.unwrap().into_iter().find_map(|msg| match msg {
+        SimpleQueryMessage::Row(row) => Some(row.get(0).map(str::to_string)),
+        _ => None,
+    }).flatten()
+}
+
+/// A --read-only server answers queries on a file another server writes, sees what that
+/// server commits, and refuses writes the way a standby does
+#[tokio::test]
+async fn test_read_only_server() {
+    let dir = tempfile::tempdir().unwrap();
+    let database = dir.path().join("shared.db");
+
+    let writer_server = start_server(&database, &[]).await;
+    let writer = writer_server.connect().await;
+    writer.batch_execute(
+        "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT NOT NULL, price NUMERIC(10,2));
+         INSERT INTO items VALUES (1, 'pen', 1.50)",
+    ).await.unwrap();
+
+    let reader_server = start_server(&database, &["--read-only"]).await;
+    let reader = reader_server.connect().await;
+
+    let row = reader.query_one("SELECT name FROM items WHERE id = $1", &[&1i32]).await.unwrap();
+    assert_eq!(row.get::<_, String>(0), "pen");
+    assert_eq!(scalar(&reader, "SELECT price FROM items").await, scalar(&writer, "SELECT price FROM items").await);
+    assert_eq!(scalar(&reader, "SHOW default_transaction_read_only").await.as_deref(), Some("on"));
+    assert_eq!(scalar(&reader, "SHOW transaction_read_only").await.as_deref(), Some("on"));
+    assert!(reader.query_one("SELECT pg_is_in_recovery()", &[]).await.unwrap().get::<_, bool>(0));
+    assert_eq!(scalar(&writer, "SHOW default_transaction_read_only").await.as_deref(), Some("off"));
+
+    let writes = [
+        ("INSERT INTO items VALUES (2, 'ink', 3)", "INSERT"),
+        ("UPDATE items SET price = 2", "UPDATE"),
+        ("DELETE FROM items", "DELETE"),
+        ("CREATE TABLE other (id INTEGER)", "CREATE TABLE"),
+        ("CREATE INDEX items_name_idx ON items (name)", "CREATE INDEX"),
+        ("ALTER TABLE items ADD COLUMN stock INTEGER", "ALTER TABLE"),
+        ("DROP TABLE items", "DROP TABLE"),
+        ("TRUNCATE items", "TRUNCATE"),
+    ];
+    for (sql, command) in writes {
+        let err = reader.simple_query(sql).await.unwrap_err();
+        assert_eq!(err.code(), Some(&SqlState::READ_ONLY_SQL_TRANSACTION), "{sql}: {err:?}");
+        assert_eq!(err.as_db_error().unwrap().message(), format!("cannot execute {command} in a read-only transaction"));
+    }
+    // SQLite refuses the writes the command check doesn't recognise
+    for sql in ["PRAGMA user_version = 7", "PRAGMA main.application_id = 7"] {
+        let err = reader.simple_query(sql).await.unwrap_err();
+        assert_eq!(err.code(), Some(&SqlState::READ_ONLY_SQL_TRANSACTION), "{sql}: {err:?}");
+    }
+    // Over the extended protocol too; the server answers a failed Execute with an extra
+    // ReadyForQuery, which tokio-postgres takes for a broken connection, so use one of its own
+    let extended = reader_server.connect().await;
+    let err = extended.execute("INSERT INTO items (id, name) VALUES ($1, $2)", &[&2i32, &"ink"]).await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::READ_ONLY_SQL_TRANSACTION), "{err:?}");
+    let err = reader.simple_query("BEGIN READ WRITE").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::READ_ONLY_SQL_TRANSACTION), "{err:?}");
+
+    // Read transactions still work
+    reader.batch_execute("BEGIN; SELECT count(*) FROM items; COMMIT").await.unwrap();
+
+    // The reader sees the writer's commits
+    writer.execute("INSERT INTO items VALUES (2, 'ink', 3)", &[]).await.unwrap();
+    assert_eq!(scalar(&reader, "SELECT count(*) FROM items").await.as_deref(), Some("2"));
+    assert_eq!(scalar(&writer, "SELECT count(*) FROM items").await.as_deref(), Some("2"));
+}
+
+/// A --read-only server never creates its database
+#[tokio::test]
+async fn test_read_only_server_needs_existing_database() {
+    let dir = tempfile::tempdir().unwrap();
+    let database = dir.path().join("missing.db");
+
+    let mut server = Command::new(env!("CARGO_BIN_EXE_pgsqlite"))
+        .args(["--log-level", "error", "--read-only", "--port", &free_port().to_string()])
+        .arg("--database")
+        .arg(&database)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to start server");
+    let started = Instant::now();
+    let exit = loop {
+        if let Some(exit) = server.try_wait().unwrap() {
+            break exit;
+        }
+        if started.elapsed() > Duration::from_secs(30) {
+            let _ = server.kill();
+            panic!("server started without a database");
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    };
+    assert!(!exit.success());
+    assert!(!database.exists());
+}
@@ -0,0 +1,135 @@
+mod common;
+use chrono::{DateTime, Utc};
+use common::{setup_test_server, setup_test_server_with_init};
+use pgsqlite::query::RetentionHandler;
+use pgsqlite::session::SessionStorage;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::SimpleQueryMessage;
+use uuid::Uuid;
+
+async fn scalar(client: &tokio_postgres::Client, query: &str) -> Option<String> {
+    client.simple_query(query).await.unwrap().into_iter()
+        .find_map(|message| match message {
+            SimpleQueryMessage::Row(row) => Some(row.get(0).map(str::to_string)),
+            _ => None,
+        })
+        .flatten()
+}
+
+#[tokio::test]
+async fn test_retention_policies() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE TABLE events (id INTEGER PRIMARY KEY, kind TEXT, created_at TIMESTAMPTZ);
+         CREATE TABLE visits (id INTEGER PRIMARY KEY, day DATE);
+         INSERT INTO visits VALUES (1, '2001-02-03'), (2, '2999-12-31')",
+    ).await.unwrap();
+    for (id, kind, created_at) in [
+        (1, "login", Some("2020-01-01T00:00:00Z")),
+        (2, "login", Some("2020-06-01T12:00:00Z")),
+        (3, "logout", Some("2999-01-01T00:00:00Z")),
+        (4, "logout", None),
+    ] {
+        let created_at = created_at.map(|at| at.parse::<DateTime<Utc>>().unwrap());
+        client.execute("INSERT INTO events (id, kind, created_at) VALUES ($1, $2, $3)", &[&id, &kind, &created_at]).await.unwrap();
+    }
+
+    assert_eq!(scalar(client, "SELECT pgsqlite.set_retention('events', 'created_at', '30 days')").await.as_deref(), Some("t"));
+    let again = client.query_one("SELECT pgsqlite.set_retention('events', 'CREATED_AT', '30 days')", &[]).await.unwrap();
+    assert!(!again.get::<_, bool>(0));
+    assert_eq!(scalar(client, "SELECT pgsqlite.set_retention('events', 'created_at', '1 year')").await.as_deref(), Some("t"));
+    assert_eq!(scalar(client, "SELECT pgsqlite.set_retention('visits', 'day', '1 mon')").await.as_deref(), Some("t"));
+
+    for (call, code) in [
+        ("pgsqlite.set_retention('missing', 'created_at', '1 day')", SqlState::UNDEFINED_TABLE),
+        ("pgsqlite.set_retention('events', 'missing', '1 day')", SqlState::UNDEFINED_COLUMN),
+        ("pgsqlite.set_retention('events', 'kind', '1 day')", SqlState::DATATYPE_MISMATCH),
+        ("pgsqlite.set_retention('events', 'created_at', 'a while')", SqlState::INVALID_DATETIME_FORMAT),
+        ("pgsqlite.set_retention('events', 'created_at', '-1 day')", SqlState::INVALID_PARAMETER_VALUE),
+    ] {
+        let err = client.simple_query(&format!("SELECT {call}")).await.unwrap_err();
+        assert_eq!(err.code(), Some(&code), "{call}: {err:?}");
+    }
+
+    // Expired rows go, newer rows and rows without a timestamp stay
+    let deleted = client.query_one("SELECT pgsqlite.run_retention()", &[]).await.unwrap();
+    assert_eq!(deleted.get::<_, i64>(0), 3);
+    let ids = client.query("SELECT id FROM events ORDER BY id", &[]).await.unwrap();
+    assert_eq!(ids.iter().map(|row| row.get::<_, i32>(0)).collect::<Vec<_>>(), vec![3, 4]);
+    assert_eq!(scalar(client, "SELECT string_agg(CAST(id AS TEXT), ',') FROM visits").await.as_deref(), Some("2"));
+    assert_eq!(scalar(client, "SELECT pgsqlite.run_retention()").await.as_deref(), Some("0"));
+
+    // Each policy keeps what its runs did
+    let rows = client.simple_query(
+        "SELECT relname, column_name, max_age, last_deleted, total_deleted, last_run IS NOT NULL
+         FROM pg_stat_retention ORDER BY relname",
+    ).await.unwrap();
+    let rows: Vec<Vec<String>> = rows.into_iter()
+        .filter_map(|message| match message {
+            SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).unwrap_or("").to_string()).collect()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(rows, vec![
+        vec!["events", "created_at", "1 year", "0", "2", "t"],
+        vec!["visits", "day", "1 mon", "0", "1", "t"],
+    ]);
+
+    // Policies follow renames and go away with their table
+    client.batch_execute("ALTER TABLE events RENAME COLUMN created_at TO happened_at; ALTER TABLE events RENAME TO activity").await.unwrap();
+    assert_eq!(
+        scalar(client, "SELECT relname || '.' || column_name FROM pg_stat_retention WHERE total_deleted = 2").await.as_deref(),
+        Some("activity.happened_at")
+    );
+    let happened_at = "2010-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+    client.execute("INSERT INTO activity (id, happened_at) VALUES ($1, $2)", &[&5i32, &happened_at]).await.unwrap();
+    assert_eq!(scalar(client, "SELECT pgsqlite.run_retention()").await.as_deref(), Some("1"));
+    client.batch_execute("DROP TABLE visits").await.unwrap();
+    assert_eq!(scalar(client, "SELECT count(*) FROM pg_stat_retention").await.as_deref(), Some("1"));
+    assert_eq!(scalar(client, "SELECT pgsqlite.drop_retention('activity')").await.as_deref(), Some("t"));
+    assert_eq!(scalar(client, "SELECT pgsqlite.drop_retention('activity')").await.as_deref(), Some("f"));
+    assert_eq!(scalar(client, "SELECT count(*) FROM pg_stat_retention").await.as_deref(), Some("0"));
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_retention_scheduler_deletes_in_batches() {
+    // What main.rs does every --retention-interval, with batches of two rows
+    let server = setup_test_server_with_init(|db| Box::pin(async move {
+        let db: Arc<dyn SessionStorage> = db;
+        tokio::spawn(async move {
+            let scheduler = Uuid::new_v4();
+            loop {
+                let _ = RetentionHandler::run_scheduled(&db, &scheduler, 2).await;
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        });
+        Ok(())
+    })).await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE TABLE log (id SERIAL PRIMARY KEY, message TEXT, logged_at TIMESTAMP);
+         INSERT INTO log (message, logged_at) VALUES
+             ('old', '2000-01-01 00:00:00'), ('old', '2000-01-02 00:00:00'), ('old', '2000-01-03 00:00:00'),
+             ('old', '2000-01-04 00:00:00'), ('old', '2000-01-05 00:00:00'), ('old', '2000-01-06 00:00:00'),
+             ('old', '2000-01-07 00:00:00'), ('new', '2999-01-01 00:00:00');
+         SELECT pgsqlite.set_retention('log', 'logged_at', '7 days')",
+    ).await.unwrap();
+
+    let started = Instant::now();
+    while scalar(client, "SELECT count(*) FROM log").await.as_deref() != Some("1") {
+        assert!(started.elapsed() < Duration::from_secs(10), "retention did not run");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(scalar(client, "SELECT message FROM log").await.as_deref(), Some("new"));
+    assert_eq!(scalar(client, "SELECT total_deleted FROM pg_stat_retention").await.as_deref(), Some("7"));
+    assert_eq!(scalar(client, "SELECT count(*) FROM pg_stat_progress_retention WHERE relname = 'log' AND rows_deleted > rows_total").await.as_deref(), Some("0"));
+
+    server.abort();
+}
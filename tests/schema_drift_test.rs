@@ -326,4 +326,33 @@ fn test_type_normalization() -> rusqlite::Result<()> {
     assert!(drift.is_empty());
     
     Ok(())
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_no_drift_serial_primary_key() -> rusqlite::Result<()> {
+    let mut conn = Connection::open_in_memory()?;
+    
+    // Initialize metadata table
+    TypeMetadata::init(&conn)?;
+    
+    // SERIAL primary keys become rowid aliases, which table_info reports as plain INTEGER
+    conn.execute(
+        "CREATE TABLE customers (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT)",
+        []
+    )?;
+    
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO __pgsqlite_schema (table_name, column_name, pg_type, sqlite_type)
+         VALUES 
+         ('customers', 'id', 'SERIAL', 'INTEGER PRIMARY KEY AUTOINCREMENT'),
+         ('customers', 'name', 'text', 'TEXT')",
+        []
+    )?;
+    tx.commit()?;
+    
+    let drift = SchemaDriftDetector::detect_drift(&conn).unwrap();
+    assert!(drift.is_empty());
+    
+    Ok(())
+}
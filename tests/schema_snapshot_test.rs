@@ -0,0 +1,77 @@
+mod common;
+use common::setup_test_server;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::SimpleQueryMessage;
+
+async fn rows(client: &tokio_postgres::Client, query: &str) -> Vec<Vec<String>> {
+    client.simple_query(query).await.unwrap().into_iter()
+        .filter_map(|message| match message {
+            SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).unwrap_or("NULL").to_string()).collect()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_schema_snapshot_diff() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+         CREATE TABLE orders (id SERIAL PRIMARY KEY, user_id INTEGER REFERENCES users(id), total NUMERIC(10,2));
+         CREATE INDEX orders_user_id_idx ON orders (user_id);
+         CREATE TABLE scratch (note TEXT)",
+    ).await.unwrap();
+    assert_eq!(rows(client, "SELECT pgsqlite.schema_snapshot('before')").await, vec![vec!["1"]]);
+
+    for (call, code) in [
+        ("pgsqlite.schema_snapshot('before')", SqlState::DUPLICATE_OBJECT),
+        ("pgsqlite.schema_snapshot('')", SqlState::INVALID_PARAMETER_VALUE),
+        ("* FROM pgsqlite.schema_diff('before', 'after')", SqlState::UNDEFINED_OBJECT),
+        ("* FROM pgsqlite.schema_diff(1, 7)", SqlState::UNDEFINED_OBJECT),
+    ] {
+        let err = client.simple_query(&format!("SELECT {call}")).await.unwrap_err();
+        assert_eq!(err.code(), Some(&code), "{call}: {err:?}");
+    }
+
+    client.batch_execute(
+        "ALTER TABLE users ADD COLUMN email VARCHAR(255) NOT NULL DEFAULT '';
+         CREATE UNIQUE INDEX users_email_idx ON users (email);
+         DROP INDEX orders_user_id_idx;
+         ALTER TABLE orders ALTER COLUMN total TYPE BIGINT;
+         DROP TABLE scratch;
+         CREATE TABLE tags (name TEXT UNIQUE)",
+    ).await.unwrap();
+    let id = client.query_one("SELECT pgsqlite.schema_snapshot()", &[]).await.unwrap();
+    assert_eq!(id.get::<_, i64>(0), 2);
+
+    assert_eq!(rows(client, "SELECT * FROM pgsqlite.schema_diff('before', 2)").await, vec![
+        vec!["column", "orders", "total", "changed", "numeric(10,2)", "bigint"],
+        vec!["index", "orders", "orders_user_id_idx", "removed", "CREATE INDEX orders_user_id_idx ON public.orders USING btree (user_id)", "NULL"],
+        vec!["table", "scratch", "scratch", "removed", "note text", "NULL"],
+        vec!["table", "tags", "tags", "added", "NULL", "name text"],
+        vec!["column", "users", "email", "added", "NULL", "varchar(255) NOT NULL DEFAULT ''"],
+        vec!["index", "users", "users_email_idx", "added", "NULL", "CREATE UNIQUE INDEX users_email_idx ON public.users USING btree (email)"],
+    ]);
+
+    // The same diff over the extended protocol, and nothing between a snapshot and itself
+    let changes = client.query("SELECT * FROM pgsqlite.schema_diff(2, 'before')", &[]).await.unwrap();
+    let changes: Vec<(String, String, String)> = changes.iter()
+        .map(|row| (row.get("table_name"), row.get("object_name"), row.get("change")))
+        .collect();
+    assert_eq!(changes.len(), 6);
+    assert!(changes.contains(&("scratch".to_string(), "scratch".to_string(), "added".to_string())));
+    assert!(changes.contains(&("users".to_string(), "email".to_string(), "removed".to_string())));
+    assert!(rows(client, "SELECT * FROM pgsqlite.schema_diff(2, 2)").await.is_empty());
+
+    // Constraints go with their columns
+    client.batch_execute("ALTER TABLE orders DROP COLUMN user_id").await.unwrap();
+    assert_eq!(rows(client, "SELECT pgsqlite.schema_snapshot('it''s done')").await, vec![vec!["3"]]);
+    assert_eq!(rows(client, "SELECT * FROM pgsqlite.schema_diff(2, 'it''s done')").await, vec![
+        vec!["column", "orders", "user_id", "removed", "integer", "NULL"],
+        vec!["constraint", "orders", "orders_user_id_fkey", "removed", "FOREIGN KEY (user_id) REFERENCES users(id)", "NULL"],
+    ]);
+
+    server.abort();
+}
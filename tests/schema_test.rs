@@ -0,0 +1,170 @@
+mod common;
+use common::*;
+
+fn error_code(error: &tokio_postgres::Error) -> &str {
+    error.as_db_error().map(|e| e.code().code()).unwrap_or_default()
+}
+
+async fn value(client: &tokio_postgres::Client, query: &str) -> String {
+    client.simple_query(query).await.unwrap().into_iter()
+        .find_map(|message| match message {
+            tokio_postgres::SimpleQueryMessage::Row(row) => row.get(0).map(str::to_string),
+            _ => None,
+        })
+        .unwrap_or_else(|| panic!("no row for {query}"))
+}
+
+#[tokio::test]
+async fn test_schema_qualified_tables() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE SCHEMA tenant1;
+         CREATE SCHEMA IF NOT EXISTS tenant1;
+         CREATE SCHEMA tenant2;
+         CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT);
+         CREATE TABLE tenant1.items (id INTEGER PRIMARY KEY, name TEXT);
+         CREATE TABLE tenant2.items (id INTEGER PRIMARY KEY, name TEXT);
+         INSERT INTO public.items VALUES (1, 'shared');
+         INSERT INTO tenant1.items VALUES (1, 'first');
+         INSERT INTO tenant2.items (id, name) VALUES (1, 'second')"
+    ).await.unwrap();
+
+    let err = client.batch_execute("CREATE SCHEMA tenant1").await.unwrap_err();
+    assert_eq!(error_code(&err), "42P06");
+
+    let row = client.query_one("SELECT tenant1.items.name FROM tenant1.items WHERE tenant1.items.id = $1", &[&1i32]).await.unwrap();
+    assert_eq!(row.get::<_, String>(0), "first");
+    let row = client.query_one("SELECT i.name FROM tenant2.items i", &[]).await.unwrap();
+    assert_eq!(row.get::<_, String>(0), "second");
+    let row = client.query_one("SELECT name FROM public.items", &[]).await.unwrap();
+    assert_eq!(row.get::<_, String>(0), "shared");
+
+    client.execute("UPDATE tenant1.items SET name = 'updated' WHERE items.id = 1", &[]).await.unwrap();
+    let rows = client.query(
+        "SELECT a.name, b.name FROM tenant1.items a JOIN tenant2.items b ON a.id = b.id", &[]
+    ).await.unwrap();
+    assert_eq!((rows[0].get::<_, String>(0), rows[0].get::<_, String>(1)), ("updated".to_string(), "second".to_string()));
+
+    let err = client.query("SELECT * FROM tenant3.items", &[]).await.unwrap_err();
+    assert!(err.as_db_error().is_some());
+}
+
+#[tokio::test]
+async fn test_search_path() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT);
+         INSERT INTO items VALUES (1, 'shared');
+         CREATE SCHEMA tenant1"
+    ).await.unwrap();
+
+    let row = client.query_one("SHOW search_path", &[]).await.unwrap();
+    assert_eq!(row.get::<_, String>(0), "\"$user\", public");
+
+    client.batch_execute("SET search_path TO tenant1, public").await.unwrap();
+    let row = client.query_one("SHOW search_path", &[]).await.unwrap();
+    assert_eq!(row.get::<_, String>(0), "tenant1, public");
+    let row = client.query_one("SELECT current_schema()", &[]).await.unwrap();
+    assert_eq!(row.get::<_, String>(0), "tenant1");
+
+    // Unqualified names fall through to public until the first schema has the table
+    let row = client.query_one("SELECT items.name FROM items WHERE items.id = 1", &[]).await.unwrap();
+    assert_eq!(row.get::<_, String>(0), "shared");
+
+    client.batch_execute(
+        "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT);
+         CREATE INDEX items_name_idx ON items (name);
+         INSERT INTO items VALUES (1, 'tenant')"
+    ).await.unwrap();
+    let row = client.query_one("SELECT items.name FROM items WHERE items.id = $1", &[&1i32]).await.unwrap();
+    assert_eq!(row.get::<_, String>(0), "tenant");
+    let row = client.query_one("SELECT name FROM tenant1.items", &[]).await.unwrap();
+    assert_eq!(row.get::<_, String>(0), "tenant");
+    let row = client.query_one("SELECT name FROM public.items", &[]).await.unwrap();
+    assert_eq!(row.get::<_, String>(0), "shared");
+
+    client.batch_execute("SET search_path TO DEFAULT").await.unwrap();
+    let row = client.query_one("SELECT name FROM items", &[]).await.unwrap();
+    assert_eq!(row.get::<_, String>(0), "shared");
+
+    // Without an existing schema on the path there is nowhere to create tables
+    client.batch_execute("SET search_path TO missing").await.unwrap();
+    let err = client.batch_execute("CREATE TABLE t (id INTEGER)").await.unwrap_err();
+    assert_eq!(error_code(&err), "3F000");
+}
+
+#[tokio::test]
+async fn test_schema_catalog() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE SCHEMA tenant1;
+         CREATE TABLE tenant1.items (id INTEGER PRIMARY KEY, name TEXT);
+         CREATE TABLE items (id INTEGER PRIMARY KEY)"
+    ).await.unwrap();
+
+    let rows = client.query("SELECT nspname FROM pg_namespace ORDER BY nspname", &[]).await.unwrap();
+    let names: Vec<String> = rows.iter().map(|r| r.get(0)).collect();
+    assert_eq!(names, ["pg_catalog", "public", "tenant1"]);
+
+    let rows = client.query(
+        "SELECT n.nspname, c.relname FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace
+         WHERE c.relname = 'items' AND c.relkind = 'r' ORDER BY n.nspname", &[]
+    ).await.unwrap();
+    let relations: Vec<(String, String)> = rows.iter().map(|r| (r.get(0), r.get(1))).collect();
+    assert_eq!(relations, [
+        ("public".to_string(), "items".to_string()),
+        ("tenant1".to_string(), "items".to_string()),
+    ]);
+
+    let oid = value(client, "SELECT 'tenant1.items'::regclass::oid").await;
+    assert_ne!(oid, value(client, "SELECT 'items'::regclass::oid").await);
+    assert_eq!(oid, value(
+        client,
+        "SELECT c.oid FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace WHERE n.nspname = 'tenant1' AND c.relname = 'items'",
+    ).await);
+}
+
+#[tokio::test]
+async fn test_drop_schema() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE SCHEMA tenant1;
+         CREATE TYPE tenant1.mood AS ENUM ('happy', 'sad');
+         CREATE TABLE tenant1.items (id INTEGER PRIMARY KEY, mood tenant1.mood);
+         CREATE VIEW tenant1.happy AS SELECT id FROM tenant1.items WHERE mood = 'happy';
+         INSERT INTO tenant1.items VALUES (1, 'happy')"
+    ).await.unwrap();
+
+    let err = client.batch_execute("DROP SCHEMA tenant1").await.unwrap_err();
+    assert_eq!(error_code(&err), "2BP01");
+    let err = client.batch_execute("DROP SCHEMA public").await.unwrap_err();
+    assert_eq!(error_code(&err), "0A000");
+    let err = client.batch_execute("DROP SCHEMA missing").await.unwrap_err();
+    assert_eq!(error_code(&err), "3F000");
+    client.batch_execute("DROP SCHEMA IF EXISTS missing").await.unwrap();
+
+    let row = client.query_one("SELECT count(*) FROM tenant1.happy", &[]).await.unwrap();
+    assert_eq!(row.get::<_, i64>(0), 1);
+
+    client.batch_execute("DROP SCHEMA tenant1 CASCADE").await.unwrap();
+    assert!(client.query("SELECT * FROM tenant1.items", &[]).await.is_err());
+    let rows = client.query("SELECT nspname FROM pg_namespace WHERE nspname = 'tenant1'", &[]).await.unwrap();
+    assert!(rows.is_empty());
+
+    // The name is free again, with nothing left over from before
+    client.batch_execute(
+        "CREATE SCHEMA tenant1;
+         CREATE TYPE tenant1.mood AS ENUM ('calm');
+         CREATE TABLE tenant1.items (id INTEGER, mood tenant1.mood)"
+    ).await.unwrap();
+    let row = client.query_one("SELECT count(*) FROM tenant1.items", &[]).await.unwrap();
+    assert_eq!(row.get::<_, i64>(0), 0);
+}
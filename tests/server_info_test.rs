@@ -0,0 +1,174 @@
+mod common;
+use common::{spawn_server_with, ServerProcess};
+use std::time::{Duration, Instant};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::{Client, SimpleQueryMessage};
+
+async fn start_server(dir: &std::path::Path) -> ServerProcess {
+    spawn_server_with(|command| {
+        command.args(["--log-level", "error", "--server-version", "16.2", "--database"]).arg(dir.join("info.db"));
+    }).await
+}
+
+async fn scalar(client: &Client, sql: &str) -> Option<String> {
+    client.simple_query(sql).await.unwrap().into_iter().find_map(|msg| match msg {
+        SimpleQueryMessage::Row(row) => Some(row.get(0).map(str::to_string)),
+        _ => None,
+    }).flatten()
+}
+
+/// --server-version sets what version(), SHOW and the startup parameters report, and the
+/// server functions describe the running server and connection
+#[tokio::test]
+async fn test_server_information_functions() {
+    let dir = tempfile::tempdir().unwrap();
+    let server = start_server(dir.path()).await;
+    let client = server.connect().await;
+
+    assert_eq!(scalar(&client, "SHOW server_version").await.as_deref(), Some("16.2"));
+    assert_eq!(scalar(&client, "SHOW server_version_num").await.as_deref(), Some("160002"));
+    assert!(scalar(&client, "SELECT version()").await.unwrap().starts_with("PostgreSQL 16.2 "));
+
+    // The start time is fixed when the server starts
+    let start_time = scalar(&client, "SELECT pg_postmaster_start_time()").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(scalar(&client, "SELECT pg_postmaster_start_time()").await.unwrap(), start_time);
+
+    assert_eq!(scalar(&client, "SELECT inet_server_addr()").await.as_deref(), Some("127.0.0.1"));
+    assert_eq!(scalar(&client, "SELECT inet_server_port()").await, Some(server.port.to_string()));
+    assert_eq!(scalar(&client, "SELECT inet_client_addr()").await.as_deref(), Some("127.0.0.1"));
+}
+
+/// txid_current() is the same throughout a transaction block and new for each transaction
+#[tokio::test]
+async fn test_txid_current() {
+    let dir = tempfile::tempdir().unwrap();
+    let server = start_server(dir.path()).await;
+    let client = server.connect().await;
+
+    let first: i64 = client.query_one("SELECT txid_current()", &[]).await.unwrap().get(0);
+    let second: i64 = client.query_one("SELECT txid_current()", &[]).await.unwrap().get(0);
+    assert!(second > first);
+
+    client.batch_execute("BEGIN").await.unwrap();
+    let in_block: i64 = client.query_one("SELECT txid_current()", &[]).await.unwrap().get(0);
+    let again: i64 = client.query_one("SELECT txid_current()", &[]).await.unwrap().get(0);
+    assert!(in_block > second);
+    assert_eq!(again, in_block);
+    client.batch_execute("COMMIT").await.unwrap();
+
+    let after: i64 = client.query_one("SELECT txid_current()", &[]).await.unwrap().get(0);
+    assert!(after > in_block);
+}
+
+fn first_row(messages: Vec<SimpleQueryMessage>) -> Vec<String> {
+    messages.into_iter().find_map(|msg| match msg {
+        SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).unwrap_or_default().to_string()).collect()),
+        _ => None,
+    }).unwrap()
+}
+
+/// txid_current_snapshot() lists other sessions' running transactions and stays the same
+/// throughout a transaction block; pg_current_wal_lsn() advances with each commit and,
+/// like transaction ids, keeps increasing when the server restarts
+#[tokio::test]
+async fn test_transaction_snapshots_and_wal_lsn() {
+    let dir = tempfile::tempdir().unwrap();
+    let server = start_server(dir.path()).await;
+    let client = server.connect().await;
+    let other = server.connect().await;
+    client.batch_execute("CREATE TABLE events (id INTEGER PRIMARY KEY)").await.unwrap();
+
+    other.batch_execute("BEGIN").await.unwrap();
+    let running: i64 = other.query_one("SELECT txid_current()", &[]).await.unwrap().get(0);
+    client.batch_execute("BEGIN").await.unwrap();
+    let snapshot: String = client.query_one("SELECT txid_current_snapshot()", &[]).await.unwrap().get(0);
+    let row = first_row(client.simple_query(&format!(
+        "SELECT txid_visible_in_snapshot({running}, '{snapshot}'), txid_snapshot_xmin('{snapshot}'), txid_snapshot_xmax('{snapshot}')"
+    )).await.unwrap());
+    assert_eq!(row[0], "f");
+    assert_eq!(row[1], running.to_string(), "{snapshot}");
+    assert!(row[2].parse::<i64>().unwrap() > running);
+    other.batch_execute("COMMIT").await.unwrap();
+    let again: String = client.query_one("SELECT pg_current_snapshot()", &[]).await.unwrap().get(0);
+    assert_eq!(again, snapshot);
+    client.batch_execute("COMMIT").await.unwrap();
+    let after: String = client.query_one("SELECT txid_current_snapshot()", &[]).await.unwrap().get(0);
+    let row = first_row(client.simple_query(&format!("SELECT txid_visible_in_snapshot({running}, '{after}')")).await.unwrap());
+    assert_eq!(row[0], "t", "{after}");
+
+    let assigned: Option<i64> = client.query_one("SELECT txid_current_if_assigned()", &[]).await.unwrap().get(0);
+    assert_eq!(assigned, None);
+
+    // A transaction with an id holds its snapshot's xmin back and sees itself
+    client.batch_execute("BEGIN").await.unwrap();
+    let own: i64 = client.query_one("SELECT txid_current()", &[]).await.unwrap().get(0);
+    let snapshot: String = client.query_one("SELECT txid_current_snapshot()", &[]).await.unwrap().get(0);
+    let row = first_row(client.simple_query(&format!(
+        "SELECT txid_snapshot_xmin('{snapshot}') <= {own}, txid_visible_in_snapshot({own}, '{snapshot}'), txid_snapshot_xmax('{snapshot}') > {own}"
+    )).await.unwrap());
+    assert_eq!(row, ["t", "t", "t"], "{snapshot}");
+    client.batch_execute("COMMIT").await.unwrap();
+
+    let lsn_value = |rows: Vec<SimpleQueryMessage>| first_row(rows).remove(0);
+    let before = lsn_value(client.simple_query("SELECT pg_current_wal_lsn()").await.unwrap());
+    let unchanged = lsn_value(client.simple_query("SELECT pg_current_wal_lsn()").await.unwrap());
+    assert_eq!(unchanged, before);
+    client.batch_execute("INSERT INTO events (id) VALUES (1)").await.unwrap();
+    let written = lsn_value(client.simple_query("SELECT pg_current_wal_lsn()").await.unwrap());
+    let diff = lsn_value(client.simple_query(&format!("SELECT pg_wal_lsn_diff('{written}', '{before}')")).await.unwrap());
+    assert!(diff.parse::<i64>().unwrap() > 0, "{before} -> {written}");
+
+    let xid: i64 = client.query_one("SELECT txid_current()", &[]).await.unwrap().get(0);
+    drop(server);
+    let server = start_server(dir.path()).await;
+    let client = server.connect().await;
+    let restarted: i64 = client.query_one("SELECT txid_current()", &[]).await.unwrap().get(0);
+    assert!(restarted > xid);
+    let restarted_lsn = lsn_value(client.simple_query("SELECT pg_current_wal_lsn()").await.unwrap());
+    let diff = lsn_value(client.simple_query(&format!("SELECT pg_wal_lsn_diff('{restarted_lsn}', '{written}')")).await.unwrap());
+    assert!(diff.parse::<i64>().unwrap() > 0, "{written} -> {restarted_lsn}");
+}
+
+/// pg_cancel_backend() and statement_timeout end a pg_sleep() early
+#[tokio::test]
+async fn test_pg_sleep_is_cancellable() {
+    let dir = tempfile::tempdir().unwrap();
+    let server = start_server(dir.path()).await;
+    let admin = server.connect().await;
+
+    // Both the standalone call the server awaits and a call inside a larger statement
+    for query in ["SELECT pg_sleep(30)", "SELECT 1, pg_sleep(30)"] {
+        let worker = server.connect().await;
+        let pid: i32 = scalar(&worker, "SELECT pg_backend_pid()").await.unwrap().parse().unwrap();
+        let started = Instant::now();
+        let running = tokio::spawn(async move { worker.simple_query(query).await });
+        wait_until_active(&admin, pid).await;
+        assert_eq!(scalar(&admin, &format!("SELECT pg_cancel_backend({pid})")).await.as_deref(), Some("t"));
+        let err = running.await.unwrap().err().expect("sleep should be canceled");
+        assert_eq!(err.code(), Some(&SqlState::QUERY_CANCELED), "{query}: {err:?}");
+        assert!(started.elapsed() < Duration::from_secs(10), "{query} was not canceled promptly");
+    }
+
+    let worker = server.connect().await;
+    worker.batch_execute("SET statement_timeout = '200ms'").await.unwrap();
+    for query in ["SELECT pg_sleep(30)", "SELECT 1, pg_sleep(30)"] {
+        let started = Instant::now();
+        let err = worker.simple_query(query).await.unwrap_err();
+        assert_eq!(err.code(), Some(&SqlState::QUERY_CANCELED), "{query}: {err:?}");
+        assert_eq!(err.as_db_error().unwrap().message(), "canceling statement due to statement timeout");
+        assert!(started.elapsed() < Duration::from_secs(10), "{query} outlived its statement_timeout");
+    }
+}
+
+async fn wait_until_active(client: &Client, pid: i32) {
+    let started = Instant::now();
+    loop {
+        let state = scalar(client, &format!("SELECT state FROM pg_stat_activity WHERE pid = {pid}")).await;
+        if state.as_deref() == Some("active") {
+            return;
+        }
+        assert!(started.elapsed() < Duration::from_secs(10), "backend {pid} never became active: {state:?}");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
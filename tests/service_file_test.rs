@@ -0,0 +1,79 @@
+mod common;
+use common::spawn_server_with;
+use std::io::{BufRead, BufReader};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio_postgres::{NoTls, SimpleQueryMessage};
+
+/// --print-urls prints a URL per database that clients connect with, and --service-file
+/// writes their sections next to the services already in the file
+#[tokio::test]
+async fn test_print_urls_and_service_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let service_file = dir.path().join("pg_service.conf");
+    std::fs::write(&service_file, "[prod]\nhost=db.example.com\n\n[app]\nhost=stale\n").unwrap();
+
+    let mut server = spawn_server_with(|command| {
+        command
+            .args(["--print-urls", "--log-level", "error"])
+            .arg("--database")
+            .arg(dir.path().join("app.db"))
+            .arg("--databases")
+            .arg(format!("logs={}", dir.path().join("logs.db").display()))
+            .arg("--service-file")
+            .arg(&service_file)
+            .arg("--socket-dir")
+            .arg(dir.path())
+            .stdout(Stdio::piped());
+    }).await;
+    let port = server.port;
+
+    // The URLs are printed once the server listens
+    let stdout = server.child.stdout.take().unwrap();
+    let urls: Vec<String> = tokio::task::spawn_blocking(move || {
+        BufReader::new(stdout).lines()
+            .map(|line| line.unwrap())
+            .filter(|line| line.starts_with("postgresql://"))
+            .take(2)
+            .collect()
+    }).await.unwrap();
+    assert_eq!(urls, [
+        format!("postgresql://postgres@localhost:{port}/app?sslmode=disable"),
+        format!("postgresql://postgres@localhost:{port}/logs?sslmode=disable"),
+    ]);
+
+    let started = Instant::now();
+    while std::fs::read_to_string(&service_file).unwrap().contains("stale") {
+        assert!(started.elapsed() < Duration::from_secs(10), "service file not written");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(std::fs::read_to_string(&service_file).unwrap(), format!("\
+[prod]
+host=db.example.com
+
+[app]
+host=localhost
+port={port}
+dbname=app
+user=postgres
+sslmode=disable
+
+[logs]
+host=localhost
+port={port}
+dbname=logs
+user=postgres
+sslmode=disable
+"));
+
+    for (url, database) in urls.iter().zip(["app", "logs"]) {
+        let (client, connection) = tokio_postgres::connect(url, NoTls).await.unwrap();
+        tokio::spawn(connection);
+        let messages = client.simple_query("SELECT pgsqlite_datname()").await.unwrap();
+        let datname = messages.iter().find_map(|message| match message {
+            SimpleQueryMessage::Row(row) => row.get(0),
+            _ => None,
+        });
+        assert_eq!(datname, Some(database));
+    }
+}
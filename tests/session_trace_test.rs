@@ -0,0 +1,104 @@
+use futures::{stream, StreamExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_postgres::{AsyncMessage, NoTls, SimpleQueryMessage};
+use uuid::Uuid;
+
+/// Connect to a fresh server and collect the notices it sends
+async fn connect_with_notices() -> (tokio_postgres::Client, mpsc::UnboundedReceiver<String>, String) {
+    let test_id = Uuid::new_v4().to_string().replace("-", "");
+    let db_path = format!("/tmp/pgsqlite_test_{test_id}.db");
+    let db_path_clone = db_path.clone();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let db_handler = std::sync::Arc::new(pgsqlite::session::DbHandler::new(&db_path_clone).unwrap());
+        let (stream, addr) = listener.accept().await.unwrap();
+        let _ = pgsqlite::handle_test_connection_with_pool(stream, addr, db_handler).await;
+    });
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    let (client, mut connection) = tokio_postgres::connect(
+        &format!("host=localhost port={port} dbname=test user=testuser"),
+        NoTls,
+    ).await.unwrap();
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut messages = stream::poll_fn(move |cx| connection.poll_message(cx));
+    tokio::spawn(async move {
+        while let Some(Ok(message)) = messages.next().await {
+            if let AsyncMessage::Notice(notice) = message {
+                let _ = tx.send(notice.message().to_string());
+            }
+        }
+    });
+
+    (client, rx, db_path)
+}
+
+fn drain(rx: &mut mpsc::UnboundedReceiver<String>) -> Vec<String> {
+    std::iter::from_fn(|| rx.try_recv().ok()).collect()
+}
+
+async fn show_trace(client: &tokio_postgres::Client) -> String {
+    client.simple_query("SHOW pgsqlite.trace").await.unwrap().iter()
+        .find_map(|msg| match msg {
+            SimpleQueryMessage::Row(row) => row.get(0).map(str::to_string),
+            _ => None,
+        })
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_session_trace() {
+    let (client, mut notices, db_path) = connect_with_notices().await;
+
+    client.simple_query("CREATE TABLE events (id INTEGER PRIMARY KEY, at TIMESTAMP)").await.unwrap();
+    client.simple_query("SELECT id FROM events").await.unwrap();
+    assert!(drain(&mut notices).is_empty(), "tracing is off by default");
+    assert_eq!(show_trace(&client).await, "off");
+
+    client.simple_query("SET pgsqlite.trace = on").await.unwrap();
+    assert_eq!(show_trace(&client).await, "on");
+    drain(&mut notices);
+
+    client.simple_query("SELECT id FROM events WHERE at > '2024-01-01'::timestamp").await.unwrap();
+    client.simple_query("SELECT 1").await.unwrap();
+    let trace = drain(&mut notices);
+    let expected = [
+        "pgsqlite.trace: statement: SELECT id FROM events WHERE at > '2024-01-01'::timestamp",
+        "pgsqlite.trace: translators: ",
+        "pgsqlite.trace: translated: ",
+        "pgsqlite.trace: strategy: Select via full translation path",
+        "pgsqlite.trace: finished in ",
+        "pgsqlite.trace: statement: SELECT 1",
+    ];
+    for (line, prefix) in trace.iter().zip(expected) {
+        assert!(line.starts_with(prefix), "expected {prefix:?}, got {line:?} in {trace:#?}");
+    }
+    assert!(!trace[2].ends_with("unchanged"), "the cast should have been translated: {trace:#?}");
+
+    // The extended protocol traces Parse with the inferred types, then Execute
+    let rows = client.query("SELECT id FROM events WHERE id = $1", &[&1i32]).await.unwrap();
+    assert!(rows.is_empty());
+    let trace = drain(&mut notices);
+    assert!(trace.iter().any(|line| line.starts_with("pgsqlite.trace: types: parameters [23]")), "{trace:#?}");
+    assert!(trace.iter().any(|line| line.starts_with("pgsqlite.trace: execute: SELECT id FROM events")), "{trace:#?}");
+    assert!(trace.last().is_some_and(|line| line.starts_with("pgsqlite.trace: finished in ")), "{trace:#?}");
+
+    // Failures are traced with the error
+    assert!(client.simple_query("SELECT * FROM missing").await.is_err());
+    let trace = drain(&mut notices);
+    assert!(trace.last().is_some_and(|line| line.starts_with("pgsqlite.trace: failed after ")), "{trace:#?}");
+
+    let err = client.simple_query("SET pgsqlite.trace = maybe").await.unwrap_err();
+    assert_eq!(err.code(), Some(&tokio_postgres::error::SqlState::INVALID_PARAMETER_VALUE));
+
+    client.simple_query("SET pgsqlite.trace TO off").await.unwrap();
+    drain(&mut notices);
+    client.simple_query("SELECT id FROM events").await.unwrap();
+    assert!(drain(&mut notices).is_empty());
+
+    let _ = std::fs::remove_file(db_path);
+}
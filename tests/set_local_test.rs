@@ -0,0 +1,75 @@
+mod common;
+use common::setup_test_server;
+
+async fn show(client: &tokio_postgres::Client, parameter: &str) -> String {
+    client.query_one(&format!("SHOW {parameter}"), &[]).await.unwrap().get(0)
+}
+
+#[tokio::test]
+async fn test_set_local_reverts_at_transaction_end() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+    client.batch_execute("SET statement_timeout = '30s'; SET TIME ZONE 'UTC'").await.unwrap();
+
+    client.batch_execute("BEGIN; SET LOCAL statement_timeout = '5s'; SET LOCAL app.tenant_id = '42'").await.unwrap();
+    assert_eq!(show(client, "statement_timeout").await, "5s");
+    assert_eq!(show(client, "app.tenant_id").await, "42");
+    client.batch_execute("COMMIT").await.unwrap();
+    assert_eq!(show(client, "statement_timeout").await, "30s");
+    assert_eq!(show(client, "app.tenant_id").await, "unset");
+
+    client.batch_execute("BEGIN; SET LOCAL TIME ZONE 'America/New_York'").await.unwrap();
+    assert_eq!(show(client, "TimeZone").await, "America/New_York");
+    client.batch_execute("ROLLBACK").await.unwrap();
+    assert_eq!(show(client, "TimeZone").await, "UTC");
+
+    // Through the extended protocol as well
+    client.execute("BEGIN", &[]).await.unwrap();
+    client.execute("SET LOCAL statement_timeout = '1s'", &[]).await.unwrap();
+    assert_eq!(show(client, "statement_timeout").await, "1s");
+    client.execute("COMMIT", &[]).await.unwrap();
+    assert_eq!(show(client, "statement_timeout").await, "30s");
+
+    // Outside a transaction block SET LOCAL only warns
+    client.batch_execute("SET LOCAL statement_timeout = '2s'").await.unwrap();
+    assert_eq!(show(client, "statement_timeout").await, "30s");
+}
+
+#[tokio::test]
+async fn test_set_in_transaction_follows_commit_or_rollback() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    // ROLLBACK undoes a plain SET too
+    client.batch_execute("BEGIN; SET search_path TO app; SET pgsqlite.trace = on").await.unwrap();
+    client.batch_execute("ROLLBACK").await.unwrap();
+    assert_eq!(show(client, "search_path").await, "\"$user\", public");
+    assert_eq!(show(client, "pgsqlite.trace").await, "off");
+
+    // COMMIT keeps the last plain SET, not a SET LOCAL made after it
+    client.batch_execute("BEGIN; SET search_path TO app; SET LOCAL search_path TO scratch").await.unwrap();
+    assert_eq!(show(client, "search_path").await, "scratch");
+    client.batch_execute("COMMIT").await.unwrap();
+    assert_eq!(show(client, "search_path").await, "app");
+
+    // A failed transaction rolls its settings back with it
+    client.batch_execute("BEGIN; SET LOCAL pgsqlite.strict_compatibility = error; SET search_path TO broken").await.unwrap();
+    assert!(client.batch_execute("SELECT * FROM missing_table").await.is_err());
+    client.batch_execute("ROLLBACK").await.unwrap();
+    assert_eq!(show(client, "search_path").await, "app");
+    assert_eq!(show(client, "pgsqlite.strict_compatibility").await, "off");
+}
+
+#[tokio::test]
+async fn test_set_and_show_with_trailing_semicolon() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    // psql sends each statement with its semicolon
+    client.simple_query("SET synchronous_commit = off;").await.unwrap();
+    let value = client.simple_query("SHOW synchronous_commit;").await.unwrap().into_iter().find_map(|msg| match msg {
+        tokio_postgres::SimpleQueryMessage::Row(row) => row.get(0).map(str::to_string),
+        _ => None,
+    });
+    assert_eq!(value.as_deref(), Some("off"));
+}
@@ -0,0 +1,77 @@
+mod common;
+use common::setup_test_server;
+use tokio_postgres::types::Type;
+
+/// Values of each row of a simple query
+async fn simple_rows(client: &tokio_postgres::Client, query: &str) -> Vec<Vec<String>> {
+    let messages = client.simple_query(query).await.unwrap();
+    messages.iter().filter_map(|m| match m {
+        tokio_postgres::SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).unwrap_or("NULL").to_string()).collect()),
+        _ => None,
+    }).collect()
+}
+
+async fn setup_articles(client: &tokio_postgres::Client) {
+    client.batch_execute(
+        "CREATE TABLE articles (id SERIAL PRIMARY KEY, tags TEXT[], body TEXT, value INTEGER);
+         INSERT INTO articles (tags, body, value) VALUES
+             (ARRAY['a', 'b'], 'x y z', 7),
+             (ARRAY['c'], 'w', 8),
+             ('{}', 'v', 9);"
+    ).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_set_returning_functions_in_select_list() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+    setup_articles(client).await;
+
+    // Each row repeats once per element, and rows with no elements are dropped
+    let rows = simple_rows(client, "SELECT id, value, unnest(tags) FROM articles ORDER BY id").await;
+    assert_eq!(rows, [["1", "7", "a"], ["1", "7", "b"], ["2", "8", "c"]]);
+    let rows = simple_rows(client, "SELECT generate_series(1, id) * 10 AS n FROM articles ORDER BY n").await;
+    assert_eq!(rows, [["10"], ["10"], ["10"], ["20"], ["20"], ["30"]]);
+    let rows = simple_rows(client, "SELECT id, regexp_split_to_table(body, ' ') AS word FROM articles WHERE id = 1").await;
+    assert_eq!(rows, [["1", "x"], ["1", "y"], ["1", "z"]]);
+
+    // Several functions advance together, the shorter ones padded with NULL
+    let rows = simple_rows(client, "SELECT id, unnest(tags), generate_series(1, 3) AS n FROM articles WHERE id < 3 ORDER BY id, n").await;
+    assert_eq!(rows, [
+        ["1", "a", "1"], ["1", "b", "2"], ["1", "NULL", "3"],
+        ["2", "c", "1"], ["2", "NULL", "2"], ["2", "NULL", "3"],
+    ]);
+    let rows = simple_rows(client, "SELECT unnest(ARRAY[1, 2]), unnest(ARRAY['x', 'y', 'z'])").await;
+    assert_eq!(rows, [["1", "x"], ["2", "y"], ["NULL", "z"]]);
+
+    // In subqueries and CTEs
+    let rows = simple_rows(client, "SELECT id FROM articles WHERE 'c' IN (SELECT unnest(tags))").await;
+    assert_eq!(rows, [["2"]]);
+    let rows = simple_rows(client, "WITH t AS (SELECT unnest(tags) AS tag FROM articles) SELECT count(*) FROM t").await;
+    assert_eq!(rows, [["3"]]);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_generate_series() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    let rows = simple_rows(client, "SELECT * FROM generate_series(10, 1, -4)").await;
+    assert_eq!(rows, [["10"], ["6"], ["2"]]);
+    let rows = simple_rows(client, "SELECT n, i FROM generate_series(3, 4) WITH ORDINALITY AS s(n, i)").await;
+    assert_eq!(rows, [["3", "1"], ["4", "2"]]);
+
+    // Named after the function, and typed int4
+    let statement = client.prepare("SELECT generate_series(1, 3)").await.unwrap();
+    assert_eq!(statement.columns()[0].name(), "generate_series");
+    assert_eq!(statement.columns()[0].type_(), &Type::INT4);
+    let rows = client.query("SELECT generate_series(1, $1) AS n", &[&3i32]).await.unwrap();
+    assert_eq!(rows.iter().map(|row| row.get::<_, i32>(0)).collect::<Vec<_>>(), [1, 2, 3]);
+
+    let error = client.simple_query("SELECT generate_series(1, 3, 0)").await.unwrap_err();
+    assert!(error.to_string().contains("step size cannot equal zero"), "{error}");
+
+    server.abort();
+}
@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use pgsqlite::session::DbHandler;
+use pgsqlite::shell::{Output, Shell};
+
+async fn setup(dir: &tempfile::TempDir) -> Shell {
+    let db_path = dir.path().join("shell.db");
+    let db = Arc::new(DbHandler::new(db_path.to_str().unwrap()).unwrap());
+    let mut shell = Shell::open(db, "shell").await.unwrap();
+    let outputs = shell.execute(
+        "CREATE TABLE customers (
+            id SERIAL PRIMARY KEY,
+            name VARCHAR(50) NOT NULL
+        );
+        CREATE TABLE orders (
+            id INTEGER PRIMARY KEY,
+            customer_id INTEGER REFERENCES customers(id),
+            total NUMERIC(10,2) DEFAULT 0
+        );
+        CREATE VIEW big_orders AS SELECT id, total FROM orders WHERE total > 100;"
+    ).await;
+    assert!(outputs.iter().all(|output| matches!(output, Output::Complete(_))), "{outputs:?}");
+    shell
+}
+
+#[tokio::test]
+async fn test_statements_return_decoded_results() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut shell = setup(&dir).await;
+
+    let outputs = shell.execute("INSERT INTO customers (name) VALUES ('alice'), ('bob') RETURNING id, name").await;
+    let Output::Rows { columns, rows, tag } = &outputs[0] else {
+        panic!("expected rows, got {outputs:?}");
+    };
+    assert_eq!(columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), ["id", "name"]);
+    assert_eq!(columns[0].type_oid, 23);
+    assert_eq!(rows[1], [Some("2".to_string()), Some("bob".to_string())]);
+    assert_eq!(tag, "INSERT 0 2");
+
+    let outputs = shell.execute("SELECT missing FROM customers").await;
+    assert!(
+        matches!(outputs.last(), Some(Output::Message(feedback)) if feedback.severity == "ERROR"),
+        "{outputs:?}"
+    );
+}
+
+#[tokio::test]
+async fn test_meta_commands() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut shell = setup(&dir).await;
+
+    let tables = shell.meta("\\dt").await.unwrap();
+    assert!(tables.contains("List of relations"), "{tables}");
+    assert!(tables.contains(" public | customers | table | postgres"), "{tables}");
+    assert!(!tables.contains("big_orders"), "{tables}");
+    assert!(tables.contains("(2 rows)"), "{tables}");
+
+    let orders = shell.meta("\\d orders").await.unwrap();
+    assert!(orders.contains("Table \"public.orders\""), "{orders}");
+    assert!(orders.contains(" total       | numeric(10,2) |           |          | 0"), "{orders}");
+    assert!(orders.contains("    \"orders_pkey\" PRIMARY KEY, btree (id)"), "{orders}");
+    assert!(orders.contains("Foreign-key constraints:"), "{orders}");
+
+    let view = shell.meta("\\d+ big_orders").await.unwrap();
+    assert!(view.contains("View \"public.big_orders\""), "{view}");
+    assert!(view.contains("View definition:"), "{view}");
+
+    assert_eq!(shell.meta("\\d nothing").await.unwrap(), "Did not find any relation named \"nothing\".\n");
+    assert!(shell.meta("\\dx").await.unwrap().starts_with("invalid command \\dx"));
+    assert_eq!(shell.meta("\\q").await, None);
+}
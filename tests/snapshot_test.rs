@@ -0,0 +1,115 @@
+use std::process::Command;
+use std::sync::Arc;
+
+use pgsqlite::session::DbHandler;
+use pgsqlite::shell::{Output, Shell};
+use pgsqlite::snapshot::{snapshot, SnapshotOptions};
+
+async fn setup(dir: &tempfile::TempDir, inserts: &str) -> Shell {
+    let db_path = dir.path().join("snapshot.db");
+    let db = Arc::new(DbHandler::new(db_path.to_str().unwrap()).unwrap());
+    let mut shell = Shell::open(db, "snapshot").await.unwrap();
+    let outputs = shell.execute(&format!(
+        "CREATE TABLE orders (
+            id INTEGER PRIMARY KEY,
+            note TEXT,
+            paid BOOLEAN,
+            total NUMERIC(10,2),
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        );
+        CREATE TABLE customers (id SERIAL PRIMARY KEY, name VARCHAR(50), token TEXT);
+        {inserts}"
+    )).await;
+    assert!(outputs.iter().all(|output| matches!(output, Output::Complete(_))), "{outputs:?}");
+    shell
+}
+
+#[tokio::test]
+async fn test_snapshot_formats_rows_as_copy_blocks() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut shell = setup(&dir, "
+        INSERT INTO customers (name, token) VALUES ('bob', 'a1'), ('alice', NULL);
+        INSERT INTO orders (id, note, paid, total, created_at) VALUES
+            (2, 'two\tlines\nhere', true, 12.5, '2024-01-02 03:04:05'),
+            (1, NULL, false, 3, '2024-01-01 00:00:00');
+    ").await;
+
+    let dump = snapshot(&mut shell, &SnapshotOptions::default()).await.unwrap();
+    assert_eq!(dump, "\
+COPY \"customers\" (\"id\", \"name\", \"token\") FROM stdin;
+1\tbob\ta1
+2\talice\t\\N
+\\.
+
+COPY \"orders\" (\"id\", \"note\", \"paid\", \"total\", \"created_at\") FROM stdin;
+1\t\\N\tf\t3.00\t2024-01-01 00:00:00
+2\ttwo\\tlines\\nhere\tt\t12.50\t2024-01-02 03:04:05
+\\.
+
+");
+}
+
+#[tokio::test]
+async fn test_normalized_snapshot_is_canonical() {
+    let options = SnapshotOptions {
+        normalize: true,
+        volatile_columns: vec!["created_at".to_string(), "customers.token".to_string()],
+    };
+
+    // The same data inserted in another order and at another time gives the same snapshot
+    let first = tempfile::tempdir().unwrap();
+    let mut shell = setup(&first, "
+        INSERT INTO customers (id, name, token) VALUES (1, 'bob', 'x'), (2, 'alice', 'y');
+        INSERT INTO orders (id, total) VALUES (10, 9.5), (9, 20);
+    ").await;
+    let dump = snapshot(&mut shell, &options).await.unwrap();
+    assert_eq!(dump, "\
+COPY \"customers\" (\"id\", \"name\") FROM stdin;
+1\tbob
+2\talice
+\\.
+
+COPY \"orders\" (\"id\", \"note\", \"paid\", \"total\") FROM stdin;
+9\t\\N\t\\N\t20.00
+10\t\\N\t\\N\t9.50
+\\.
+
+");
+
+    let second = tempfile::tempdir().unwrap();
+    let mut shell = setup(&second, "
+        INSERT INTO orders (id, total, created_at) VALUES (9, 20, '1999-12-31 23:59:59');
+        INSERT INTO customers (id, name, token) VALUES (2, 'alice', 'other'), (1, 'bob', NULL);
+        INSERT INTO orders (id, total) VALUES (10, 9.5);
+    ").await;
+    assert_eq!(snapshot(&mut shell, &options).await.unwrap(), dump);
+}
+
+/// `pgsqlite snapshot --normalize` takes the volatile columns from the configuration
+#[test]
+fn test_snapshot_command() {
+    let dir = tempfile::tempdir().unwrap();
+    let database = dir.path().join("snapshot.db");
+    let output = dir.path().join("snapshot.sql");
+    {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let mut shell = setup(&dir, "INSERT INTO customers (name, token) VALUES ('bob', 'secret');").await;
+            shell.execute("SELECT 1").await;
+        });
+    }
+
+    let status = Command::new(env!("CARGO_BIN_EXE_pgsqlite"))
+        .env("PGSQLITE_SNAPSHOT_VOLATILE_COLUMNS", "*.created_at,token")
+        .args(["snapshot", "--normalize", "--output"])
+        .arg(&output)
+        .arg(&database)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert_eq!(
+        std::fs::read_to_string(&output).unwrap(),
+        "COPY \"customers\" (\"id\", \"name\") FROM stdin;\n1\tbob\n\\.\n\n\
+         COPY \"orders\" (\"id\", \"note\", \"paid\", \"total\") FROM stdin;\n\\.\n\n"
+    );
+}
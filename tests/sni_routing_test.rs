@@ -0,0 +1,123 @@
+mod common;
+use common::spawn_server_with;
+use std::sync::Arc;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{DigitallySignedStruct, SignatureScheme};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Trusts the server's ephemeral certificate, whatever name it was issued for
+#[derive(Debug)]
+struct AcceptAnyCertificate(Arc<rustls::crypto::CryptoProvider>);
+
+impl ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(&self, _: &CertificateDer<'_>, _: &[CertificateDer<'_>], _: &ServerName<'_>, _: &[u8], _: UnixTime) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(&self, _: &[u8], _: &CertificateDer<'_>, _: &DigitallySignedStruct) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn verify_tls13_signature(&self, _: &[u8], _: &CertificateDer<'_>, _: &DigitallySignedStruct) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// Read backend messages up to ReadyForQuery, returning the first column of each
+/// DataRow or the message of an ErrorResponse
+async fn read_until_ready<S: AsyncRead + Unpin>(stream: &mut S) -> Result<Vec<String>, String> {
+    let mut values = Vec::new();
+    loop {
+        let tag = stream.read_u8().await.map_err(|e| e.to_string())?;
+        let len = stream.read_i32().await.map_err(|e| e.to_string())? as usize;
+        let mut body = vec![0u8; len - 4];
+        stream.read_exact(&mut body).await.map_err(|e| e.to_string())?;
+        match tag {
+            b'D' => {
+                let value_len = i32::from_be_bytes(body[2..6].try_into().unwrap()) as usize;
+                values.push(String::from_utf8_lossy(&body[6..6 + value_len]).into_owned());
+            }
+            b'E' => {
+                let fields: Vec<String> = body.split(|b| *b == 0).map(|f| String::from_utf8_lossy(f).into_owned()).collect();
+                let field = |code: char| fields.iter().find(|f| f.starts_with(code)).map(|f| f[1..].to_string()).unwrap_or_default();
+                return Err(format!("{}: {}", field('C'), field('M')));
+            }
+            b'Z' => return Ok(values),
+            _ => {}
+        }
+    }
+}
+
+async fn send_message<S: AsyncWrite + Unpin>(stream: &mut S, tag: Option<u8>, body: &[u8]) {
+    let mut message = Vec::new();
+    message.extend(tag);
+    message.extend(((body.len() + 4) as i32).to_be_bytes());
+    message.extend(body);
+    stream.write_all(&message).await.unwrap();
+    stream.flush().await.unwrap();
+}
+
+/// Connect over TLS with `server_name` as SNI, asking for `database`, and run `sql`
+async fn query_over_tls(port: u16, server_name: &str, database: &str, sql: &str) -> Result<Vec<String>, String> {
+    let mut tcp = tokio::net::TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    send_message(&mut tcp, None, &80877103i32.to_be_bytes()).await;
+    assert_eq!(tcp.read_u8().await.unwrap(), b'S');
+
+    let provider = Arc::new(rustls::crypto::aws_lc_rs::default_provider());
+    let config = rustls::ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(AcceptAnyCertificate(provider)))
+        .with_no_client_auth();
+    let connector = tokio_rustls::TlsConnector::from(Arc::new(config));
+    let mut tls = connector.connect(ServerName::try_from(server_name.to_string()).unwrap(), tcp).await.unwrap();
+
+    let mut startup = 196608i32.to_be_bytes().to_vec();
+    for part in ["user", "postgres", "database", database, ""] {
+        startup.extend(part.as_bytes());
+        startup.push(0);
+    }
+    send_message(&mut tls, None, &startup).await;
+    read_until_ready(&mut tls).await?;
+
+    send_message(&mut tls, Some(b'Q'), format!("{sql}\0").as_bytes()).await;
+    read_until_ready(&mut tls).await
+}
+
+/// With --ssl-sni-domain the TLS server name picks the database
+#[tokio::test]
+async fn test_sni_selects_database() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = |file: &str| dir.path().join(file).to_str().unwrap().to_string();
+    let server = spawn_server_with(|command| {
+        command
+            .args(["--log-level", "error", "--database", &path("main.db")])
+            .args(["--databases", &format!("sales={},hr={}", path("sales.db"), path("hr.db"))])
+            .args(["--ssl", "--ssl-ephemeral", "--ssl-sni-domain", "db.example.test"])
+            .arg("--socket-dir").arg(dir.path());
+    }).await;
+    let port = server.port;
+    let sales = server.connect_with("dbname=sales").await.unwrap();
+    sales.batch_execute("CREATE TABLE orders (id INTEGER PRIMARY KEY); INSERT INTO orders VALUES (1), (2)").await.unwrap();
+
+    // The server name wins over the database of the startup packet
+    let count = "SELECT count(*) FROM orders";
+    assert_eq!(query_over_tls(port, "sales.db.example.test", "main", count).await.unwrap(), ["2"]);
+    assert_eq!(query_over_tls(port, "SALES.DB.EXAMPLE.TEST", "hr", count).await.unwrap(), ["2"]);
+    let err = query_over_tls(port, "hr.db.example.test", "sales", count).await.unwrap_err();
+    assert!(err.contains("no such table: orders"), "{err}");
+
+    // A tenant without a database is refused like an unknown startup database
+    let err = query_over_tls(port, "nobody.db.example.test", "sales", count).await.unwrap_err();
+    assert!(err.starts_with("3D000"), "{err}");
+
+    // Other server names leave the choice to the startup packet
+    assert_eq!(query_over_tls(port, "localhost", "sales", count).await.unwrap(), ["2"]);
+    assert_eq!(query_over_tls(port, "a.sales.db.example.test", "sales", count).await.unwrap(), ["2"]);
+}
@@ -0,0 +1,121 @@
+mod common;
+use chrono::NaiveDateTime;
+use common::setup_test_server;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::SimpleQueryMessage;
+
+async fn ids(client: &tokio_postgres::Client, query: &str) -> Vec<i32> {
+    client.query(query, &[]).await.unwrap().iter().map(|row| row.get(0)).collect()
+}
+
+async fn scalar(client: &tokio_postgres::Client, query: &str) -> Option<String> {
+    client.simple_query(query).await.unwrap().into_iter()
+        .find_map(|message| match message {
+            SimpleQueryMessage::Row(row) => Some(row.get(0).map(str::to_string)),
+            _ => None,
+        })
+        .flatten()
+}
+
+#[tokio::test]
+async fn test_soft_delete_hides_and_marks_rows() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE TABLE customers (name TEXT PRIMARY KEY, city TEXT);
+         CREATE TABLE orders (id INTEGER PRIMARY KEY, customer TEXT, total INTEGER, placed_at TIMESTAMP, deleted_at TIMESTAMPTZ);
+         INSERT INTO customers VALUES ('alice', 'Oslo'), ('bob', 'Lima');
+         INSERT INTO orders (id, customer, total, placed_at) VALUES
+             (1, 'alice', 10, '2024-03-01 09:30:00'), (2, 'bob', 20, '2024-03-02 10:00:00'), (3, 'alice', 30, '2024-03-03 11:15:00')",
+    ).await.unwrap();
+
+    assert_eq!(scalar(client, "SELECT pgsqlite.enable_soft_delete('orders')").await.as_deref(), Some("t"));
+    let again = client.query_one("SELECT pgsqlite.enable_soft_delete('orders')", &[]).await.unwrap();
+    assert!(!again.get::<_, bool>(0));
+
+    // DELETE marks the row deleted and reports it as deleted
+    assert_eq!(client.execute("DELETE FROM orders WHERE id = $1", &[&2i32]).await.unwrap(), 1);
+    assert_eq!(client.execute("DELETE FROM orders WHERE id = 2", &[]).await.unwrap(), 0);
+    assert_eq!(ids(client, "SELECT id FROM orders ORDER BY id").await, vec![1, 3]);
+    assert_eq!(scalar(client, "SELECT count(*) FROM orders").await.as_deref(), Some("2"));
+
+    // Joins, subqueries and aliases see the same rows, with their column types intact
+    let rows = client.query(
+        "SELECT o.id, o.placed_at, c.city FROM orders o JOIN customers c ON c.name = o.customer ORDER BY o.id",
+        &[],
+    ).await.unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(
+        rows[1].get::<_, NaiveDateTime>(1),
+        NaiveDateTime::parse_from_str("2024-03-03 11:15:00", "%Y-%m-%d %H:%M:%S").unwrap()
+    );
+    assert_eq!(
+        scalar(client, "SELECT string_agg(name, ',') FROM customers c WHERE EXISTS (SELECT 1 FROM orders WHERE customer = c.name)").await.as_deref(),
+        Some("alice")
+    );
+    assert_eq!(
+        scalar(client, "WITH recent AS (SELECT * FROM orders WHERE total > 5) SELECT sum(total) FROM recent").await.as_deref(),
+        Some("40")
+    );
+
+    // UPDATE leaves deleted rows alone
+    assert_eq!(client.execute("UPDATE orders SET total = total + 1", &[]).await.unwrap(), 2);
+
+    let returned = client.query("DELETE FROM orders WHERE customer = 'alice' AND total > 20 RETURNING id", &[]).await.unwrap();
+    assert_eq!(returned.iter().map(|row| row.get::<_, i32>(0)).collect::<Vec<_>>(), vec![3]);
+    assert_eq!(ids(client, "SELECT id FROM orders").await, vec![1]);
+
+    // With rewriting off the session sees the deleted rows, and can restore them
+    client.batch_execute("SET pgsqlite.soft_delete = off").await.unwrap();
+    assert_eq!(scalar(client, "SHOW pgsqlite.soft_delete").await.as_deref(), Some("off"));
+    assert_eq!(ids(client, "SELECT id FROM orders WHERE deleted_at IS NOT NULL ORDER BY id").await, vec![2, 3]);
+    assert_eq!(scalar(client, "SELECT total FROM orders WHERE id = 2").await.as_deref(), Some("20"));
+    client.batch_execute("UPDATE orders SET deleted_at = NULL WHERE id = 2; SET pgsqlite.soft_delete = on").await.unwrap();
+    assert_eq!(ids(client, "SELECT id FROM orders ORDER BY id").await, vec![1, 2]);
+
+    // A renamed table keeps its soft deletes
+    client.batch_execute("ALTER TABLE orders RENAME TO purchases").await.unwrap();
+    client.batch_execute("DELETE FROM purchases WHERE id = 1").await.unwrap();
+    assert_eq!(ids(client, "SELECT id FROM purchases").await, vec![2]);
+
+    // Disabling restores plain deletes, and shows the deleted rows again
+    assert_eq!(scalar(client, "SELECT pgsqlite.disable_soft_delete('purchases')").await.as_deref(), Some("t"));
+    assert_eq!(ids(client, "SELECT id FROM purchases ORDER BY id").await, vec![1, 2, 3]);
+    client.batch_execute("DELETE FROM purchases WHERE id = 1").await.unwrap();
+    assert_eq!(ids(client, "SELECT id FROM purchases ORDER BY id").await, vec![2, 3]);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_soft_delete_requires_deleted_at_timestamp() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT);
+         CREATE TABLE drafts (id INTEGER PRIMARY KEY, deleted_at TEXT)",
+    ).await.unwrap();
+
+    let err = client.simple_query("SELECT pgsqlite.enable_soft_delete('notes')").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::UNDEFINED_COLUMN), "{err:?}");
+    let err = client.simple_query("SELECT pgsqlite.enable_soft_delete('drafts')").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::DATATYPE_MISMATCH), "{err:?}");
+    let err = client.simple_query("SELECT pgsqlite.enable_soft_delete('missing')").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::UNDEFINED_TABLE), "{err:?}");
+    assert_eq!(scalar(client, "SELECT pgsqlite.disable_soft_delete('notes')").await.as_deref(), Some("f"));
+
+    // A dropped table is forgotten, so a new table of the same name deletes normally
+    client.batch_execute(
+        "CREATE TABLE events (id INTEGER PRIMARY KEY, deleted_at TIMESTAMP);
+         SELECT pgsqlite.enable_soft_delete('events');
+         DROP TABLE events;
+         CREATE TABLE events (id INTEGER PRIMARY KEY, deleted_at TIMESTAMP);
+         INSERT INTO events (id) VALUES (1), (2);
+         DELETE FROM events WHERE id = 1",
+    ).await.unwrap();
+    assert_eq!(scalar(client, "SET pgsqlite.soft_delete = off; SELECT count(*) FROM events").await.as_deref(), Some("1"));
+
+    server.abort();
+}
@@ -0,0 +1,69 @@
+mod common;
+use common::setup_test_server;
+use tokio_postgres::SimpleQueryMessage;
+
+async fn query_rows(client: &tokio_postgres::Client, sql: &str) -> Vec<Vec<Option<String>>> {
+    client.simple_query(sql).await.unwrap().iter()
+        .filter_map(|msg| match msg {
+            SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).map(|s| s.to_string())).collect()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn s(value: &str) -> Option<String> {
+    Some(value.to_string())
+}
+
+#[tokio::test]
+async fn test_split_functions_on_constants() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    let rows = query_rows(client, r"SELECT word FROM regexp_split_to_table('the quick  brown fox', '\s+') AS word").await;
+    assert_eq!(rows, vec![vec![s("the")], vec![s("quick")], vec![s("brown")], vec![s("fox")]]);
+
+    let rows = query_rows(client, "SELECT part, n FROM string_to_table('a,,c', ',', '') WITH ORDINALITY AS t(part, n)").await;
+    assert_eq!(rows, vec![vec![s("a"), s("1")], vec![None, s("2")], vec![s("c"), s("3")]]);
+
+    // Without an alias the column is named after the function
+    let messages = client.simple_query("SELECT * FROM regexp_split_to_table('a1b22c', '[0-9]+')").await.unwrap();
+    let SimpleQueryMessage::Row(row) = messages.iter().find(|msg| matches!(msg, SimpleQueryMessage::Row(_))).unwrap() else {
+        unreachable!()
+    };
+    assert_eq!(row.columns()[0].name(), "regexp_split_to_table");
+
+    // Extended protocol
+    let rows = client.query("SELECT token FROM string_to_table($1, ' ') AS token WHERE token <> 'b'", &[&"a b c"]).await.unwrap();
+    let tokens: Vec<String> = rows.iter().map(|row| row.get(0)).collect();
+    assert_eq!(tokens, vec!["a", "c"]);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_split_functions_lateral() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TABLE docs (id INTEGER PRIMARY KEY, body TEXT)", &[]).await.unwrap();
+    client.execute("INSERT INTO docs (id, body) VALUES (1, 'red green'), (2, 'Blue  red')", &[]).await.unwrap();
+
+    let rows = query_rows(
+        client,
+        r"SELECT d.id, w.word FROM docs d, LATERAL regexp_split_to_table(lower(d.body), '\s+') AS w(word) ORDER BY d.id, w.word",
+    ).await;
+    assert_eq!(rows, vec![
+        vec![s("1"), s("green")], vec![s("1"), s("red")],
+        vec![s("2"), s("blue")], vec![s("2"), s("red")],
+    ]);
+
+    // Word counts, the usual tokenization query
+    let rows = query_rows(
+        client,
+        r"SELECT word, count(*) AS n FROM docs CROSS JOIN LATERAL regexp_split_to_table(docs.body, '\s+', 'i') AS word GROUP BY word ORDER BY n DESC, word LIMIT 1",
+    ).await;
+    assert_eq!(rows, vec![vec![s("red"), s("2")]]);
+
+    server.abort();
+}
@@ -0,0 +1,145 @@
+mod common;
+use common::*;
+
+fn error_code(error: &tokio_postgres::Error) -> &str {
+    error.as_db_error().map(|e| e.code().code()).unwrap_or_default()
+}
+
+/// Catalog values as text, as psql reads them
+async fn text_rows(client: &tokio_postgres::Client, query: &str) -> Vec<Vec<String>> {
+    client.simple_query(query).await.unwrap().into_iter()
+        .filter_map(|message| match message {
+            tokio_postgres::SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).unwrap_or_default().to_string()).collect()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_sql_functions_run_in_queries() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE TABLE items (id SERIAL PRIMARY KEY, name TEXT NOT NULL, price NUMERIC(10,2));
+         INSERT INTO items (name, price) VALUES ('pen', 1.50), ('ink', 3);
+         CREATE FUNCTION add_tax(price numeric, rate numeric) RETURNS numeric AS $$
+             SELECT price * (1 + rate)
+         $$ LANGUAGE sql IMMUTABLE;
+         CREATE FUNCTION price_of(item_name text) RETURNS numeric AS 'SELECT price FROM items WHERE name = item_name' LANGUAGE sql STABLE;
+         CREATE FUNCTION label(text, integer) RETURNS text LANGUAGE sql STRICT RETURN upper($1) || '#' || $2;"
+    ).await.unwrap();
+
+    assert_eq!(text_rows(client, "SELECT add_tax(price, 0.5), price_of('ink'), label(name, id) FROM items ORDER BY id").await, [
+        ["2.25", "3.00", "PEN#1"],
+        ["4.5", "3.00", "INK#2"],
+    ]);
+    assert_eq!(text_rows(client, "SELECT count(*) FROM items WHERE add_tax(price, 1) > 5").await, [["1"]]);
+    // STRICT functions return NULL for a NULL argument without running
+    assert_eq!(text_rows(client, "SELECT label(NULL, 1) IS NULL").await, [["t"]]);
+    let row = client.query_one("SELECT label($1, $2)", &[&"cap", &7i32]).await.unwrap();
+    assert_eq!(row.get::<_, String>(0), "CAP#7");
+
+    // Replacing a function takes effect at once, changing its result type does not
+    client.batch_execute("CREATE OR REPLACE FUNCTION label(text, integer) RETURNS text AS $$ SELECT $1 || '-' || $2 $$ LANGUAGE sql").await.unwrap();
+    assert_eq!(text_rows(client, "SELECT label('pen', 1)").await, [["pen-1"]]);
+    let err = client.batch_execute("CREATE OR REPLACE FUNCTION label(text, integer) RETURNS integer AS 'SELECT 1' LANGUAGE sql").await.unwrap_err();
+    assert_eq!(error_code(&err), "42P13");
+    let err = client.batch_execute("CREATE FUNCTION label(text, integer) RETURNS text AS 'SELECT 1' LANGUAGE sql").await.unwrap_err();
+    assert_eq!(error_code(&err), "42723");
+    let err = client.batch_execute("CREATE FUNCTION pairs() RETURNS SETOF integer AS 'SELECT 1' LANGUAGE sql").await.unwrap_err();
+    assert_eq!(error_code(&err), "0A000");
+    // Argument defaults are refused rather than ignored, however they are written
+    for create in [
+        "CREATE FUNCTION bump(n integer, step integer DEFAULT 1) RETURNS integer AS 'SELECT n + step' LANGUAGE sql",
+        "CREATE FUNCTION bump(n integer, step integer = 1) RETURNS integer RETURN n + step",
+        "CREATE FUNCTION bump(n integer, step integer=1) RETURNS integer RETURN n + step",
+    ] {
+        let err = client.batch_execute(create).await.unwrap_err();
+        assert_eq!(error_code(&err), "0A000", "{create}");
+        assert!(err.to_string().contains("argument defaults are not supported"), "{err}");
+    }
+
+    client.batch_execute("DROP FUNCTION label(text, integer)").await.unwrap();
+    assert!(client.simple_query("SELECT label('pen', 1)").await.is_err());
+}
+
+#[tokio::test]
+async fn test_sql_functions_in_pg_proc() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE FUNCTION add_tax(price numeric, rate numeric) RETURNS numeric AS $$ SELECT price * (1 + rate) $$ LANGUAGE sql IMMUTABLE STRICT;
+         CREATE FUNCTION touch() RETURNS trigger AS $$ BEGIN RETURN NEW; END $$ LANGUAGE plpgsql;"
+    ).await.unwrap();
+
+    assert_eq!(text_rows(client,
+        "SELECT proname, pronamespace, prolang, prorettype, pronargs, proargtypes, proargnames, provolatile, proisstrict, prosrc
+         FROM pg_proc WHERE proname IN ('add_tax', 'touch') ORDER BY proname"
+    ).await, [
+        ["add_tax", "2200", "14", "1700", "2", "1700 1700", "{price,rate}", "i", "t", " SELECT price * (1 + rate) "],
+        ["touch", "2200", "0", "2279", "0", "", "", "v", "f", " BEGIN RETURN NEW; END "],
+    ]);
+
+    // COMMENT ON FUNCTION finds them through pg_proc
+    client.batch_execute("COMMENT ON FUNCTION add_tax(numeric, numeric) IS 'price with tax'").await.unwrap();
+    assert_eq!(text_rows(client, "SELECT obj_description(oid, 'pg_proc') FROM pg_proc WHERE proname = 'add_tax'").await, [["price with tax"]]);
+}
+
+#[tokio::test]
+async fn test_sql_functions_reach_other_sessions() {
+    let database = std::env::temp_dir().join(format!("pgsqlite_sql_function_{}.db", uuid::Uuid::new_v4().simple()));
+    let start = || spawn_server_with(|command| {
+        command.args(["--log-level", "error", "--database"]).arg(&database);
+    });
+
+    let server = start().await;
+    let first = server.connect().await;
+    let second = server.connect().await;
+    first.batch_execute("CREATE FUNCTION double_it(n integer) RETURNS integer AS 'SELECT n * 2' LANGUAGE sql").await.unwrap();
+    assert_eq!(text_rows(&second, "SELECT double_it(21)").await, [["42"]]);
+    drop(server);
+
+    // Connections opened later register the stored functions
+    let server = start().await;
+    let client = server.connect().await;
+    assert_eq!(text_rows(&client, "SELECT double_it(4)").await, [["8"]]);
+
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{suffix}", database.display()));
+    }
+}
+
+#[tokio::test]
+async fn test_temp_functions_stay_in_session() {
+    let server = setup_test_server().await;
+    let test = server.connect().await;
+    let other = server.connect().await;
+    test.batch_execute(
+        "CREATE TEMP FUNCTION now() RETURNS timestamptz AS $$ SELECT '2024-01-01 12:00:00' $$ LANGUAGE sql;
+         CREATE FUNCTION pg_temp.fetch_rate(code text) RETURNS numeric LANGUAGE sql RETURN 1.25;"
+    ).await.unwrap();
+
+    // The stubs shadow the built-in now() and stand in for the helper in this session only
+    let stubbed = text_rows(&test, "SELECT now(), fetch_rate('EUR')").await;
+    assert!(stubbed[0][0].starts_with("2024-01-01 12:00:00"), "{stubbed:?}");
+    assert_eq!(stubbed[0][1], "1.25");
+    assert!(!text_rows(&other, "SELECT now()").await[0][0].starts_with("2024-01-01"));
+    assert!(other.simple_query("SELECT fetch_rate('EUR')").await.is_err());
+
+    let err = test.batch_execute("CREATE TEMP FUNCTION now() RETURNS timestamptz AS 'SELECT 1' LANGUAGE sql").await.unwrap_err();
+    assert_eq!(error_code(&err), "42723");
+    test.batch_execute("CREATE OR REPLACE TEMP FUNCTION fetch_rate(code text) RETURNS numeric LANGUAGE sql RETURN 2").await.unwrap();
+    assert_eq!(text_rows(&test, "SELECT fetch_rate('EUR')").await, [["2"]]);
+
+    // Dropping the stub brings back the built-in
+    test.batch_execute("DROP FUNCTION now()").await.unwrap();
+    assert!(!text_rows(&test, "SELECT now()").await[0][0].starts_with("2024-01-01"));
+    assert_eq!(text_rows(&test, "SELECT fetch_rate('EUR')").await, [["2"]]);
+
+    // A new session starts without them
+    drop(test);
+    let next = server.connect().await;
+    assert!(next.simple_query("SELECT fetch_rate('EUR')").await.is_err());
+}
@@ -0,0 +1,169 @@
+mod common;
+use common::spawn_server_with;
+use rcgen::{BasicConstraints, Certificate, CertificateParams, DistinguishedName, DnType, ExtendedKeyUsagePurpose, IsCa, KeyPair};
+use rustls::pki_types::{CertificateDer, ServerName};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_postgres::error::SqlState;
+use tokio_rustls::client::TlsStream;
+
+fn certificate((issuer, issuer_key): &(Certificate, KeyPair), name: &str, usage: ExtendedKeyUsagePurpose) -> (String, String) {
+    let key = KeyPair::generate().unwrap();
+    let mut params = CertificateParams::new(vec![name.to_string()]).unwrap();
+    params.distinguished_name.push(DnType::CommonName, name);
+    params.extended_key_usages = vec![usage];
+    (params.signed_by(&key, issuer, issuer_key).unwrap().pem(), key.serialize_pem())
+}
+
+fn certificate_authority(name: &str) -> (Certificate, KeyPair) {
+    let key = KeyPair::generate().unwrap();
+    let mut params = CertificateParams::new(Vec::new()).unwrap();
+    params.distinguished_name.push(DnType::CommonName, name);
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    (params.self_signed(&key).unwrap(), key)
+}
+
+fn client_config(ca_pem: &str, client: Option<&(String, String)>) -> Arc<rustls::ClientConfig> {
+    let mut roots = rustls::RootCertStore::empty();
+    for cert in rustls_pemfile::certs(&mut ca_pem.as_bytes()) {
+        roots.add(cert.unwrap()).unwrap();
+    }
+    let builder = rustls::ClientConfig::builder().with_root_certificates(roots);
+    Arc::new(match client {
+        Some((cert, key)) => {
+            let certs: Vec<CertificateDer<'static>> = rustls_pemfile::certs(&mut cert.as_bytes()).map(Result::unwrap).collect();
+            let key = rustls_pemfile::private_key(&mut key.as_bytes()).unwrap().unwrap();
+            builder.with_client_auth_cert(certs, key).unwrap()
+        }
+        None => builder.with_no_client_auth(),
+    })
+}
+
+/// Negotiate TLS on a new connection
+async fn connect_tls(port: u16, config: Arc<rustls::ClientConfig>) -> std::io::Result<TlsStream<TcpStream>> {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await?;
+    stream.write_all(&[0, 0, 0, 8, 0x04, 0xd2, 0x16, 0x2f]).await?;
+    let mut answer = [0u8; 1];
+    stream.read_exact(&mut answer).await?;
+    assert_eq!(&answer, b"S");
+    tokio_rustls::TlsConnector::from(config).connect(ServerName::try_from("localhost").unwrap(), stream).await
+}
+
+/// Read backend messages up to ReadyForQuery; the SQLSTATE of an ErrorResponse fails it.
+/// Returns whether a DataRow came along.
+async fn read_until_ready(stream: &mut TlsStream<TcpStream>) -> Result<bool, String> {
+    let mut data = false;
+    loop {
+        let mut header = [0u8; 5];
+        stream.read_exact(&mut header).await.map_err(|e| e.to_string())?;
+        let mut body = vec![0u8; u32::from_be_bytes(header[1..].try_into().unwrap()) as usize - 4];
+        stream.read_exact(&mut body).await.map_err(|e| e.to_string())?;
+        match header[0] {
+            b'Z' => return Ok(data),
+            b'D' => data = true,
+            b'E' => {
+                let code = body.split(|&b| b == 0)
+                    .find_map(|field| field.strip_prefix(b"C"))
+                    .map(|code| String::from_utf8_lossy(code).into_owned());
+                return Err(code.unwrap_or_default());
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Connect as `user` over TLS, returning the session or the SQLSTATE or I/O error refusing it
+async fn login(port: u16, config: Arc<rustls::ClientConfig>, user: &str) -> Result<TlsStream<TcpStream>, String> {
+    let mut stream = connect_tls(port, config).await.map_err(|e| e.to_string())?;
+    let mut startup = 196608u32.to_be_bytes().to_vec();
+    for part in ["user", user, "database", "main", ""] {
+        startup.extend_from_slice(part.as_bytes());
+        startup.push(0);
+    }
+    let mut message = ((startup.len() + 4) as u32).to_be_bytes().to_vec();
+    message.extend(startup);
+    stream.write_all(&message).await.map_err(|e| e.to_string())?;
+    read_until_ready(&mut stream).await?;
+    Ok(stream)
+}
+
+async fn select_one(stream: &mut TlsStream<TcpStream>) -> bool {
+    stream.write_all(b"Q\0\0\0\x0dSELECT 1\0").await.unwrap();
+    read_until_ready(stream).await.unwrap()
+}
+
+fn server_certificate(stream: &TlsStream<TcpStream>) -> Vec<u8> {
+    stream.get_ref().1.peer_certificates().unwrap()[0].to_vec()
+}
+
+/// --ssl-client-cert verify-full turns away clients without a certificate mapping to
+/// their role, and renewed server certificates are used without a restart
+#[tokio::test]
+async fn test_client_certificates_and_rotation() {
+    let dir = tempfile::tempdir().unwrap();
+    let ca = certificate_authority("pgsqlite test CA");
+    let ca_pem = ca.0.pem();
+    let (server_cert, server_key) = certificate(&ca, "localhost", ExtendedKeyUsagePurpose::ServerAuth);
+    let alice = certificate(&ca, "alice-laptop", ExtendedKeyUsagePurpose::ClientAuth);
+    let other_ca = certificate_authority("someone else's CA");
+    let mallory = certificate(&other_ca, "alice-laptop", ExtendedKeyUsagePurpose::ClientAuth);
+    let (cert_path, key_path, ca_path) = (dir.path().join("server.crt"), dir.path().join("server.key"), dir.path().join("ca.crt"));
+    std::fs::write(&cert_path, &server_cert).unwrap();
+    std::fs::write(&key_path, &server_key).unwrap();
+    std::fs::write(&ca_path, &ca_pem).unwrap();
+
+    let server = spawn_server_with(|command| {
+        command
+            .args(["--in-memory", "--ssl"])
+            .args(["--ssl-client-cert", "verify-full", "--ssl-cert-user-map", "alice-laptop=alice", "--ssl-cert-check-interval", "1"])
+            .arg("--ssl-cert").arg(&cert_path)
+            .arg("--ssl-key").arg(&key_path)
+            .arg("--ssl-ca").arg(&ca_path)
+            .arg("--socket-dir").arg(dir.path());
+    }).await;
+    let port = server.port;
+
+    let alice_client = client_config(&ca_pem, Some(&alice));
+    let mut session = login(port, alice_client.clone(), "alice").await.unwrap();
+    assert!(select_one(&mut session).await);
+
+    // Without TLS, without a certificate, or as a role the certificate doesn't map to
+    let plain = tokio_postgres::connect(&format!("host=127.0.0.1 port={port} user=alice dbname=main"), tokio_postgres::NoTls).await;
+    assert_eq!(plain.err().and_then(|e| e.code().cloned()), Some(SqlState::INVALID_AUTHORIZATION_SPECIFICATION));
+    assert_eq!(login(port, client_config(&ca_pem, None), "alice").await.err().as_deref(), Some("28000"));
+    assert_eq!(login(port, alice_client.clone(), "alice-laptop").await.err().as_deref(), Some("28000"));
+    assert!(login(port, client_config(&ca_pem, Some(&mallory)), "alice").await.is_err());
+
+    // A renewed server certificate is served to new connections; the open session carries on
+    let old_certificate = server_certificate(&session);
+    let (renewed_cert, renewed_key) = certificate(&ca, "localhost", ExtendedKeyUsagePurpose::ServerAuth);
+    std::fs::write(&key_path, &renewed_key).unwrap();
+    std::fs::write(&cert_path, &renewed_cert).unwrap();
+    let started = Instant::now();
+    loop {
+        if let Ok(stream) = login(port, alice_client.clone(), "alice").await
+            && server_certificate(&stream) != old_certificate {
+            break;
+        }
+        assert!(started.elapsed() < Duration::from_secs(15), "the renewed certificate was not picked up");
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+    assert!(select_one(&mut session).await);
+}
+
+#[test]
+fn test_common_name() {
+    let ca = certificate_authority("pgsqlite test CA");
+    let (cert, _) = certificate(&ca, "alice-laptop", ExtendedKeyUsagePurpose::ClientAuth);
+    let der = rustls_pemfile::certs(&mut cert.as_bytes()).next().unwrap().unwrap();
+    assert_eq!(pgsqlite::ssl::common_name(&der).as_deref(), Some("alice-laptop"));
+
+    let key = KeyPair::generate().unwrap();
+    let mut params = CertificateParams::new(vec!["localhost".to_string()]).unwrap();
+    params.distinguished_name = DistinguishedName::new();
+    let unnamed = params.self_signed(&key).unwrap();
+    assert_eq!(pgsqlite::ssl::common_name(unnamed.der()), None);
+    assert_eq!(pgsqlite::ssl::common_name(&[0x30, 0x82, 0xff]), None);
+}
@@ -9,15 +9,41 @@ mod tests {
     fn test_certificate_generation() {
         let config = Config {
             database: ":memory:".to_string(),
+            databases: Vec::new(),
             ssl: true,
             ssl_cert: None,
             ssl_key: None,
             ssl_ca: None,
             ssl_ephemeral: true,
+            ssl_min_version: "1.2".to_string(),
+            ssl_ciphers: None,
+            ssl_alpn: None,
+            ssl_session_cache_size: 1024,
+            ssl_session_tickets: false,
+            ssl_sni_domain: None,
+            ssl_client_cert: "off".to_string(),
+            ssl_cert_user_map: None,
+            ssl_cert_check_interval: 60,
             in_memory: true,
+            read_only: false,
             port: 5432,
             log_level: "info".to_string(),
+            log_format: "text".to_string(),
+            config_file: None,
+            file_settings: Vec::new(),
             no_tcp: false,
+            pipe_name: None,
+            no_pipe: false,
+            windows_service: false,
+            max_connections: 100,
+            shutdown_grace: 0,
+            print_urls: false,
+            service_file: None,
+            server_version: "15.0".to_string(),
+            admin_port: None,
+            admin_users: "postgres".to_string(),
+            admin_max_connections: 3,
+            fast_startup: false,
             socket_dir: "/tmp".to_string(),
             use_pooling: false,
             pool_size: 8,
@@ -37,6 +63,7 @@ mod tests {
             statement_pool_size: 100,
             cache_metrics_interval: 300,
             schema_cache_ttl: 300,
+            catalog_cache_size: 256,
             buffer_monitoring: false,
             buffer_pool_size: 50,
             buffer_initial_capacity: 4096,
@@ -54,7 +81,18 @@ mod tests {
             pragma_synchronous: "NORMAL".to_string(),
             pragma_cache_size: -64000,
             pragma_mmap_size: 268435456,
+            group_commit_delay_ms: 0,
+            strict_compatibility: "off".to_string(),
+            auto_index_foreign_keys: false,
+            stat_statements_max: 5000,
+            retention_interval: 60,
+            retention_batch_size: 1000,
+            auto_analyze_min_rows: 1000,
+            snapshot_volatile_columns: vec![],
             migrate: false,
+            libsql_url: None,
+            libsql_auth_token: None,
+            command: None,
         };
 
         let cert_manager = CertificateManager::new(Arc::new(config.clone()));
@@ -83,15 +121,41 @@ mod tests {
         
         let config = Config {
             database: db_path.to_string_lossy().to_string(),
+            databases: Vec::new(),
             ssl: true,
             ssl_cert: None,
             ssl_key: None,
             ssl_ca: None,
             ssl_ephemeral: false,
+            ssl_min_version: "1.2".to_string(),
+            ssl_ciphers: None,
+            ssl_alpn: None,
+            ssl_session_cache_size: 1024,
+            ssl_session_tickets: false,
+            ssl_sni_domain: None,
+            ssl_client_cert: "off".to_string(),
+            ssl_cert_user_map: None,
+            ssl_cert_check_interval: 60,
             in_memory: false,
+            read_only: false,
             port: 5432,
             log_level: "info".to_string(),
+            log_format: "text".to_string(),
+            config_file: None,
+            file_settings: Vec::new(),
             no_tcp: false,
+            pipe_name: None,
+            no_pipe: false,
+            windows_service: false,
+            max_connections: 100,
+            shutdown_grace: 0,
+            print_urls: false,
+            service_file: None,
+            server_version: "15.0".to_string(),
+            admin_port: None,
+            admin_users: "postgres".to_string(),
+            admin_max_connections: 3,
+            fast_startup: false,
             socket_dir: "/tmp".to_string(),
             use_pooling: false,
             pool_size: 8,
@@ -111,6 +175,7 @@ mod tests {
             statement_pool_size: 100,
             cache_metrics_interval: 300,
             schema_cache_ttl: 300,
+            catalog_cache_size: 256,
             buffer_monitoring: false,
             buffer_pool_size: 50,
             buffer_initial_capacity: 4096,
@@ -128,7 +193,18 @@ mod tests {
             pragma_synchronous: "NORMAL".to_string(),
             pragma_cache_size: -64000,
             pragma_mmap_size: 268435456,
+            group_commit_delay_ms: 0,
+            strict_compatibility: "off".to_string(),
+            auto_index_foreign_keys: false,
+            stat_statements_max: 5000,
+            retention_interval: 60,
+            retention_batch_size: 1000,
+            auto_analyze_min_rows: 1000,
+            snapshot_volatile_columns: vec![],
             migrate: false,
+            libsql_url: None,
+            libsql_auth_token: None,
+            command: None,
         };
 
         let cert_manager = CertificateManager::new(Arc::new(config.clone()));
@@ -164,15 +240,41 @@ mod tests {
     fn test_ssl_disabled_for_unix_sockets() {
         let config = Config {
             database: "test.db".to_string(),
+            databases: Vec::new(),
             ssl: true,
             ssl_cert: None,
             ssl_key: None,
             ssl_ca: None,
             ssl_ephemeral: false,
+            ssl_min_version: "1.2".to_string(),
+            ssl_ciphers: None,
+            ssl_alpn: None,
+            ssl_session_cache_size: 1024,
+            ssl_session_tickets: false,
+            ssl_sni_domain: None,
+            ssl_client_cert: "off".to_string(),
+            ssl_cert_user_map: None,
+            ssl_cert_check_interval: 60,
             in_memory: false,
+            read_only: false,
             port: 5432,
             log_level: "info".to_string(),
+            log_format: "text".to_string(),
+            config_file: None,
+            file_settings: Vec::new(),
             no_tcp: true, // TCP disabled, only Unix sockets
+            pipe_name: None,
+            no_pipe: false,
+            windows_service: false,
+            max_connections: 100,
+            shutdown_grace: 0,
+            print_urls: false,
+            service_file: None,
+            server_version: "15.0".to_string(),
+            admin_port: None,
+            admin_users: "postgres".to_string(),
+            admin_max_connections: 3,
+            fast_startup: false,
             socket_dir: "/tmp".to_string(),
             use_pooling: false,
             pool_size: 8,
@@ -192,6 +294,7 @@ mod tests {
             statement_pool_size: 100,
             cache_metrics_interval: 300,
             schema_cache_ttl: 300,
+            catalog_cache_size: 256,
             buffer_monitoring: false,
             buffer_pool_size: 50,
             buffer_initial_capacity: 4096,
@@ -209,10 +312,139 @@ mod tests {
             pragma_synchronous: "NORMAL".to_string(),
             pragma_cache_size: -64000,
             pragma_mmap_size: 268435456,
+            group_commit_delay_ms: 0,
+            strict_compatibility: "off".to_string(),
+            auto_index_foreign_keys: false,
+            stat_statements_max: 5000,
+            retention_interval: 60,
+            retention_batch_size: 1000,
+            auto_analyze_min_rows: 1000,
+            snapshot_volatile_columns: vec![],
             migrate: false,
+            libsql_url: None,
+            libsql_auth_token: None,
+            command: None,
         };
 
         // This should be validated in Config::load(), but we're testing the validation
         assert!(config.ssl && config.no_tcp, "Invalid SSL configuration should be caught");
     }
-}
\ No newline at end of file
+
+    /// Run one TLS handshake against `acceptor` and report how it went
+    async fn handshake(
+        acceptor: &tokio_rustls::TlsAcceptor,
+        client_config: &Arc<rustls::ClientConfig>,
+    ) -> std::io::Result<(Option<rustls::HandshakeKind>, Option<Vec<u8>>, Option<rustls::CipherSuite>)> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (client_io, server_io) = tokio::io::duplex(16 * 1024);
+        let acceptor = acceptor.clone();
+        let server = tokio::spawn(async move {
+            let mut stream = acceptor.accept(server_io).await?;
+            // Application data makes the client process the session tickets sent before it
+            stream.write_all(b"S").await?;
+            stream.flush().await?;
+            let mut buf = [0u8; 1];
+            let _ = stream.read(&mut buf).await;
+            Ok::<_, std::io::Error>(())
+        });
+
+        let connector = tokio_rustls::TlsConnector::from(client_config.clone());
+        let server_name = rustls::pki_types::ServerName::try_from("localhost").unwrap();
+        let mut stream = connector.connect(server_name, client_io).await?;
+        let mut byte = [0u8; 1];
+        stream.read_exact(&mut byte).await?;
+        let (_, connection) = stream.get_ref();
+        let result = (
+            connection.handshake_kind(),
+            connection.alpn_protocol().map(<[u8]>::to_vec),
+            connection.negotiated_cipher_suite().map(|suite| suite.suite()),
+        );
+        drop(stream);
+        let _ = server.await;
+        Ok(result)
+    }
+
+    fn client_config(cert_pem: &[u8], versions: &[&'static rustls::SupportedProtocolVersion]) -> Arc<rustls::ClientConfig> {
+        let mut roots = rustls::RootCertStore::empty();
+        for cert in rustls_pemfile::certs(&mut &cert_pem[..]) {
+            roots.add(cert.unwrap()).unwrap();
+        }
+        let mut config = rustls::ClientConfig::builder_with_protocol_versions(versions)
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        config.alpn_protocols = vec![b"postgresql".to_vec()];
+        Arc::new(config)
+    }
+
+    async fn acceptor_for(args: &[&str]) -> anyhow::Result<(tokio_rustls::TlsAcceptor, Vec<u8>)> {
+        use clap::Parser;
+        let config = Config::parse_from(["pgsqlite", "--in-memory", "--ssl", "--ssl-ephemeral"].iter().chain(args));
+        let (acceptor, source) = CertificateManager::new(Arc::new(config)).initialize().await?;
+        match source {
+            pgsqlite::ssl::CertificateSource::Generated { cert, .. } => Ok((acceptor, cert)),
+            _ => panic!("Expected generated certificates for in-memory database"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tls_session_resumption_and_alpn() {
+        let all_versions = rustls::ALL_VERSIONS;
+
+        // The session cache resumes the second connection of the same client
+        let (acceptor, cert) = acceptor_for(&["--ssl-alpn", "postgresql"]).await.unwrap();
+        let client = client_config(&cert, all_versions);
+        let (kind, alpn, _) = handshake(&acceptor, &client).await.unwrap();
+        assert_eq!(kind, Some(rustls::HandshakeKind::Full));
+        assert_eq!(alpn.as_deref(), Some(&b"postgresql"[..]));
+        let (kind, _, _) = handshake(&acceptor, &client).await.unwrap();
+        assert_eq!(kind, Some(rustls::HandshakeKind::Resumed));
+
+        // Without a cache or tickets every handshake is a full one
+        let (acceptor, cert) = acceptor_for(&["--ssl-session-cache-size", "0"]).await.unwrap();
+        let client = client_config(&cert, all_versions);
+        handshake(&acceptor, &client).await.unwrap();
+        let (kind, alpn, _) = handshake(&acceptor, &client).await.unwrap();
+        assert_eq!(kind, Some(rustls::HandshakeKind::Full));
+        assert_eq!(alpn, None);
+
+        // Stateless tickets resume without any server-side state
+        let (acceptor, cert) = acceptor_for(&["--ssl-session-cache-size", "0", "--ssl-session-tickets"]).await.unwrap();
+        let client = client_config(&cert, all_versions);
+        handshake(&acceptor, &client).await.unwrap();
+        let (kind, _, _) = handshake(&acceptor, &client).await.unwrap();
+        assert_eq!(kind, Some(rustls::HandshakeKind::Resumed));
+    }
+
+    #[tokio::test]
+    async fn test_tls_version_and_cipher_suites() {
+        let (acceptor, cert) = acceptor_for(&["--ssl-min-version", "1.3", "--ssl-ciphers", "tls13_aes_256_gcm_sha384"]).await.unwrap();
+        let (_, _, suite) = handshake(&acceptor, &client_config(&cert, rustls::ALL_VERSIONS)).await.unwrap();
+        assert_eq!(suite, Some(rustls::CipherSuite::TLS13_AES_256_GCM_SHA384));
+
+        // A TLS 1.2-only client is refused
+        assert!(handshake(&acceptor, &client_config(&cert, &[&rustls::version::TLS12])).await.is_err());
+
+        let Err(err) = acceptor_for(&["--ssl-ciphers", "TLS_RSA_WITH_RC4_128_MD5"]).await else {
+            panic!("Unknown cipher suite should be rejected");
+        };
+        assert!(err.to_string().contains("Unknown TLS cipher suite"), "{err}");
+
+        // Only TLS 1.2 suites can't serve a TLS 1.3-only server
+        assert!(acceptor_for(&["--ssl-min-version", "1.3", "--ssl-ciphers", "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256"]).await.is_err());
+    }
+
+    #[test]
+    fn test_sni_database() {
+        use clap::Parser;
+        let config = Config::parse_from(["pgsqlite", "--in-memory", "--ssl", "--ssl-sni-domain", ".db.example.com"]);
+        assert_eq!(config.sni_database("sales.db.example.com").as_deref(), Some("sales"));
+        assert_eq!(config.sni_database("Sales.DB.Example.com.").as_deref(), Some("sales"));
+        assert_eq!(config.sni_database("a.sales.db.example.com"), None);
+        assert_eq!(config.sni_database("db.example.com"), None);
+        assert_eq!(config.sni_database("salesdb.example.com"), None);
+
+        let config = Config::parse_from(["pgsqlite", "--in-memory", "--ssl"]);
+        assert_eq!(config.sni_database("sales.db.example.com"), None);
+    }
+}
@@ -0,0 +1,80 @@
+mod common;
+use common::setup_test_server;
+use std::time::{Duration, Instant};
+use tokio_postgres::error::SqlState;
+
+const LONG_QUERY: &str = "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 2000000000) SELECT count(*) FROM n";
+
+async fn show(client: &tokio_postgres::Client) -> String {
+    client.query_one("SHOW statement_timeout", &[]).await.unwrap().get(0)
+}
+
+fn assert_timed_out(err: tokio_postgres::Error) {
+    assert_eq!(err.code(), Some(&SqlState::QUERY_CANCELED), "unexpected error: {err:?}");
+    assert_eq!(err.as_db_error().unwrap().message(), "canceling statement due to statement timeout");
+}
+
+#[tokio::test]
+async fn test_statement_timeout_cancels_long_statements() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.batch_execute("SET statement_timeout = '200ms'").await.unwrap();
+    assert_eq!(show(client).await, "200ms");
+
+    let started = Instant::now();
+    assert_timed_out(client.simple_query(LONG_QUERY).await.unwrap_err());
+    assert!(started.elapsed() < Duration::from_secs(10), "took {:?}", started.elapsed());
+
+    // Through the extended protocol as well
+    assert_timed_out(client.query(LONG_QUERY, &[]).await.unwrap_err());
+
+    // The session keeps working, and statements within the limit are untouched
+    let row = client.query_one("SELECT count(*) FROM (SELECT 1 UNION ALL SELECT 2) t", &[]).await.unwrap();
+    assert_eq!(row.get::<_, i64>(0), 2);
+
+    // A timeout only covers the statement it was armed for
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    client.batch_execute("SET statement_timeout = 0").await.unwrap();
+    assert_eq!(show(client).await, "0");
+    client.batch_execute("CREATE TABLE t (id INTEGER PRIMARY KEY); INSERT INTO t VALUES (1)").await.unwrap();
+    assert_eq!(client.query_one("SELECT count(*) FROM t", &[]).await.unwrap().get::<_, i64>(0), 1);
+
+    // SET LOCAL bounds only the statements of its transaction block
+    client.batch_execute("BEGIN; SET LOCAL statement_timeout = 100").await.unwrap();
+    assert_timed_out(client.simple_query(LONG_QUERY).await.unwrap_err());
+    client.batch_execute("ROLLBACK").await.unwrap();
+    assert_eq!(show(client).await, "0");
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_statement_timeout_values() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    assert_eq!(show(client).await, "0");
+    for (value, shown) in [
+        ("1000", "1s"),
+        ("'1500'", "1500ms"),
+        ("'1.5s'", "1500ms"),
+        ("'90s'", "90s"),
+        ("'5min'", "5min"),
+        ("'2 h'", "2h"),
+        ("'1d'", "1d"),
+        ("'1500us'", "2ms"),
+        ("DEFAULT", "0"),
+    ] {
+        client.batch_execute(&format!("SET statement_timeout = {value}")).await.unwrap();
+        assert_eq!(show(client).await, shown, "SET statement_timeout = {value}");
+    }
+
+    for value in ["'soon'", "'-1'", "'10 parsecs'"] {
+        let err = client.batch_execute(&format!("SET statement_timeout = {value}")).await.unwrap_err();
+        assert_eq!(err.code(), Some(&SqlState::INVALID_PARAMETER_VALUE), "SET statement_timeout = {value}: {err:?}");
+    }
+    assert_eq!(show(client).await, "0");
+
+    server.abort();
+}
@@ -0,0 +1,128 @@
+mod common;
+use common::setup_test_server;
+use tokio_postgres::SimpleQueryMessage;
+use tokio_postgres::error::SqlState;
+
+async fn query_rows(client: &tokio_postgres::Client, sql: &str) -> Vec<Vec<Option<String>>> {
+    client.simple_query(sql).await.unwrap().iter()
+        .filter_map(|msg| match msg {
+            SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).map(|s| s.to_string())).collect()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn s(value: &str) -> Option<String> {
+    Some(value.to_string())
+}
+
+async fn setup_scores(client: &tokio_postgres::Client) {
+    client.execute("CREATE TABLE scores (id INTEGER PRIMARY KEY, grp TEXT, score INTEGER, hours REAL)", &[]).await.unwrap();
+    client.execute(
+        "INSERT INTO scores (id, grp, score, hours) VALUES \
+         (1, 'a', 1, 2.0), (2, 'a', 2, 4.1), (3, 'a', 2, 5.9), (4, 'a', 3, 8.0), (5, 'b', 10, NULL), (6, 'b', NULL, 1.0)",
+        &[],
+    ).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_ordered_set_aggregates() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+    setup_scores(client).await;
+
+    let rows = query_rows(client,
+        "SELECT percentile_cont(0.5) WITHIN GROUP (ORDER BY score), \
+                percentile_disc(0.5) WITHIN GROUP (ORDER BY score), \
+                percentile_disc(0.9) WITHIN GROUP (ORDER BY score DESC), \
+                mode() WITHIN GROUP (ORDER BY score) \
+         FROM scores").await;
+    assert_eq!(rows, vec![vec![s("2"), s("2"), s("1"), s("2")]]);
+
+    let rows = query_rows(client,
+        "SELECT grp, percentile_cont(0.25) WITHIN GROUP (ORDER BY score) FROM scores GROUP BY grp ORDER BY grp").await;
+    assert_eq!(rows, vec![vec![s("a"), s("1.75")], vec![s("b"), s("10")]]);
+
+    // The extended protocol goes through the same rewrite
+    let rows = client.query(
+        "SELECT percentile_cont(0.9) WITHIN GROUP (ORDER BY score) FROM scores WHERE grp = $1",
+        &[&"a"],
+    ).await.unwrap();
+    let value: f64 = rows[0].get(0);
+    assert!((value - 2.7).abs() < 1e-9, "percentile_cont = {value}");
+
+    let err = client.simple_query("SELECT percentile_cont(1.5) WITHIN GROUP (ORDER BY score) FROM scores").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::NUMERIC_VALUE_OUT_OF_RANGE), "unexpected error: {err:?}");
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_width_bucket_and_statistics() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+    setup_scores(client).await;
+
+    let rows = query_rows(client, "SELECT id, width_bucket(score, 0, 10, 5) FROM scores ORDER BY id").await;
+    let buckets: Vec<Option<String>> = rows.into_iter().map(|row| row[1].clone()).collect();
+    assert_eq!(buckets, vec![s("1"), s("2"), s("2"), s("2"), s("6"), None]);
+
+    let err = client.simple_query("SELECT width_bucket(score, 0, 10, 0) FROM scores").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::INVALID_ARGUMENT_FOR_WIDTH_BUCKET_FUNCTION), "unexpected error: {err:?}");
+
+    let rows = query_rows(client,
+        "SELECT var_pop(score), var_samp(score), stddev_pop(score), covar_pop(hours, score) FROM scores WHERE grp = 'a'").await;
+    assert_eq!(rows, vec![vec![s("0.5"), s("0.6666666666666666"), s("0.7071067811865476"), s("1.5")]]);
+
+    let rows = query_rows(client, "SELECT corr(hours, score), stddev(score) FROM scores WHERE grp = 'b'").await;
+    assert_eq!(rows, vec![vec![None, None]]);
+
+    let rows = query_rows(client, "SELECT round(corr(hours, score)::numeric, 4) FROM scores").await;
+    assert_eq!(rows, vec![vec![s("0.9578")]]);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_string_and_boolean_aggregates() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+    setup_scores(client).await;
+    client.execute("CREATE TABLE flags (id INTEGER PRIMARY KEY, grp TEXT, ok BOOLEAN, name TEXT)", &[]).await.unwrap();
+    client.execute(
+        "INSERT INTO flags (id, grp, ok, name) VALUES \
+         (1, 'a', true, 'pear'), (2, 'a', true, 'apple'), (3, 'b', true, NULL), (4, 'b', false, 'fig'), (5, 'c', NULL, NULL)",
+        &[],
+    ).await.unwrap();
+
+    let rows = query_rows(client,
+        "SELECT grp, string_agg(name, ', ' ORDER BY name), string_agg(name, '|' ORDER BY id DESC), \
+                bool_and(ok), bool_or(ok), every(ok) \
+         FROM flags GROUP BY grp ORDER BY grp").await;
+    assert_eq!(rows, vec![
+        vec![s("a"), s("apple, pear"), s("apple|pear"), s("t"), s("t"), s("t")],
+        vec![s("b"), s("fig"), s("fig"), s("f"), s("t"), s("f")],
+        vec![s("c"), None, None, None, None, None],
+    ]);
+
+    let rows = query_rows(client, "SELECT string_agg(grp || ':' || score, '/' ORDER BY score DESC NULLS LAST, id) FROM scores").await;
+    assert_eq!(rows, vec![vec![s("b:10/a:3/a:2/a:2/a:1")]]);
+
+    let rows = query_rows(client, "SELECT bool_and(score > 0), bool_or(score > 5) FROM scores").await;
+    assert_eq!(rows, vec![vec![s("t"), s("t")]]);
+
+    // Result types for the extended protocol
+    let row = client.query_one(
+        "SELECT string_agg(name, ',' ORDER BY name) AS names, bool_and(ok) AS all_ok, bool_or(ok) AS any_ok \
+         FROM flags WHERE grp = $1",
+        &[&"a"],
+    ).await.unwrap();
+    assert_eq!(row.get::<_, Option<String>>("names").as_deref(), Some("apple,pear"));
+    assert_eq!(row.get::<_, Option<bool>>("all_ok"), Some(true));
+    assert_eq!(row.get::<_, Option<bool>>("any_ok"), Some(true));
+
+    let row = client.query_one("SELECT mode() WITHIN GROUP (ORDER BY score) AS m FROM scores", &[]).await.unwrap();
+    assert_eq!(row.get::<_, Option<i32>>("m"), Some(2));
+
+    server.abort();
+}
@@ -0,0 +1,98 @@
+use futures::{stream, StreamExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::{AsyncMessage, NoTls, SimpleQueryMessage};
+use uuid::Uuid;
+
+/// Connect to a fresh server and collect the notices it sends
+async fn connect_with_notices() -> (tokio_postgres::Client, mpsc::UnboundedReceiver<String>, String) {
+    let test_id = Uuid::new_v4().to_string().replace("-", "");
+    let db_path = format!("/tmp/pgsqlite_test_{test_id}.db");
+    let db_path_clone = db_path.clone();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let db_handler = std::sync::Arc::new(pgsqlite::session::DbHandler::new(&db_path_clone).unwrap());
+        let (stream, addr) = listener.accept().await.unwrap();
+        let _ = pgsqlite::handle_test_connection_with_pool(stream, addr, db_handler).await;
+    });
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    let (client, mut connection) = tokio_postgres::connect(
+        &format!("host=localhost port={port} dbname=test user=testuser"),
+        NoTls,
+    ).await.unwrap();
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut messages = stream::poll_fn(move |cx| connection.poll_message(cx));
+    tokio::spawn(async move {
+        while let Some(Ok(message)) = messages.next().await {
+            if let AsyncMessage::Notice(notice) = message {
+                let _ = tx.send(notice.message().to_string());
+            }
+        }
+    });
+
+    (client, rx, db_path)
+}
+
+fn drain(rx: &mut mpsc::UnboundedReceiver<String>) -> Vec<String> {
+    std::iter::from_fn(|| rx.try_recv().ok()).collect()
+}
+
+async fn show_strict(client: &tokio_postgres::Client) -> String {
+    client.simple_query("SHOW pgsqlite.strict_compatibility").await.unwrap().iter()
+        .find_map(|msg| match msg {
+            SimpleQueryMessage::Row(row) => row.get(0).map(str::to_string),
+            _ => None,
+        })
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_strict_compatibility() {
+    let (client, mut notices, db_path) = connect_with_notices().await;
+
+    client.simple_query("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)").await.unwrap();
+    client.simple_query("INSERT INTO users (id, name) VALUES (1, 'ann'), (2, 'bob')").await.unwrap();
+
+    // Off by default: locking clauses are dropped silently
+    assert_eq!(show_strict(&client).await, "off");
+    let rows = client.query("SELECT name FROM users WHERE id = $1 FOR UPDATE", &[&1i32]).await.unwrap();
+    assert_eq!(rows[0].get::<_, String>(0), "ann");
+    let messages = client.simple_query("SELECT name FROM users ORDER BY id LIMIT 1 FOR SHARE SKIP LOCKED").await.unwrap();
+    assert!(messages.iter().any(|msg| matches!(msg, SimpleQueryMessage::Row(row) if row.get(0) == Some("ann"))));
+    assert!(drain(&mut notices).is_empty());
+
+    // warn: the statement runs and each approximation is reported
+    client.simple_query("SET pgsqlite.strict_compatibility = warn").await.unwrap();
+    assert_eq!(show_strict(&client).await, "warn");
+    client.simple_query(
+        "CREATE TABLE orders (id INTEGER PRIMARY KEY, user_id INTEGER REFERENCES users(id))"
+    ).await.unwrap();
+    client.simple_query("SELECT id FROM orders FOR NO KEY UPDATE").await.unwrap();
+    // Foreign keys are enforced, so only the locking clause is reported
+    assert_eq!(drain(&mut notices), [
+        "FOR NO KEY UPDATE is ignored: SQLite has no row-level locks",
+    ]);
+
+    // error: rejected with feature_not_supported before anything runs
+    client.simple_query("SET pgsqlite.strict_compatibility TO error").await.unwrap();
+    let err = client.simple_query("SELECT name FROM users FOR UPDATE").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::FEATURE_NOT_SUPPORTED));
+    let err = client.query("SELECT name FROM users ORDER BY name COLLATE \"en_US\"", &[]).await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::FEATURE_NOT_SUPPORTED));
+    assert!(err.as_db_error().unwrap().message().contains("COLLATE \"en_US\""));
+    client.simple_query("SELECT name FROM users ORDER BY name COLLATE NOCASE").await.unwrap();
+
+    let err = client.simple_query("SET pgsqlite.strict_compatibility = sometimes").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::INVALID_PARAMETER_VALUE));
+
+    client.simple_query("SET pgsqlite.strict_compatibility = off").await.unwrap();
+    client.simple_query("SELECT name FROM users FOR UPDATE").await.unwrap();
+    assert!(drain(&mut notices).is_empty());
+
+    let _ = std::fs::remove_file(db_path);
+}
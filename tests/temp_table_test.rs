@@ -0,0 +1,77 @@
+mod common;
+use common::spawn_server_with;
+use std::time::{Duration, Instant};
+use tokio_postgres::{Client, SimpleQueryMessage};
+
+async fn text_rows(client: &Client, query: &str) -> Vec<Vec<String>> {
+    client.simple_query(query).await.unwrap().into_iter()
+        .filter_map(|message| match message {
+            SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).unwrap_or_default().to_string()).collect()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Temporary tables belong to their session and go away with it
+#[tokio::test]
+async fn test_temp_tables_stay_in_session() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("temp.db");
+    let server = spawn_server_with(|command| {
+        command.args(["--log-level", "error"]).arg("--database").arg(&db_path).arg("--socket-dir").arg(dir.path());
+    }).await;
+
+    let test = server.connect_with("dbname=test").await.unwrap();
+    let other = server.connect_with("dbname=test").await.unwrap();
+    test.batch_execute(
+        "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT);
+         INSERT INTO items VALUES (1, 'permanent');
+         CREATE TEMP TABLE staging (id SERIAL PRIMARY KEY, amount NUMERIC(10,2), at TIMESTAMPTZ);
+         INSERT INTO staging (amount, at) VALUES (1.5, '2024-01-02 03:04:05+00'), (2.25, NULL);"
+    ).await.unwrap();
+
+    // Columns keep their PostgreSQL types, over both protocols
+    assert_eq!(text_rows(&test, "SELECT id, amount, at FROM staging ORDER BY id").await[0], ["1", "1.50", "2024-01-02 03:04:05+00"]);
+    let row = test.query_one("SELECT id, amount FROM pg_temp.staging WHERE id = $1", &[&2i32]).await.unwrap();
+    assert_eq!(row.columns()[1].type_(), &tokio_postgres::types::Type::NUMERIC);
+    assert_eq!(row.get::<_, i32>(0), 2);
+
+    // Other sessions neither see the table nor find it in the catalog
+    assert!(other.simple_query("SELECT * FROM staging").await.is_err());
+    assert!(text_rows(&other, "SELECT relname FROM pg_class WHERE relname LIKE '%staging%'").await.is_empty());
+    assert!(text_rows(&test, "SELECT relname FROM pg_class WHERE relname LIKE '%staging%'").await.is_empty());
+
+    // Each session can have its own table of the same name
+    other.batch_execute("CREATE TEMPORARY TABLE staging (code TEXT); INSERT INTO staging VALUES ('other')").await.unwrap();
+    assert_eq!(text_rows(&other, "SELECT * FROM staging").await, [["other"]]);
+    assert_eq!(text_rows(&test, "SELECT count(*) FROM staging").await, [["2"]]);
+
+    // A temporary table hides a permanent one of the same name
+    test.batch_execute("CREATE TEMP TABLE items (id INTEGER, name TEXT); INSERT INTO items VALUES (2, 'temporary')").await.unwrap();
+    assert_eq!(text_rows(&test, "SELECT name FROM items").await, [["temporary"]]);
+    assert_eq!(text_rows(&test, "SELECT name FROM public.items").await, [["permanent"]]);
+    assert_eq!(text_rows(&other, "SELECT name FROM items").await, [["permanent"]]);
+    test.batch_execute("DROP TABLE items").await.unwrap();
+    assert_eq!(text_rows(&test, "SELECT name FROM items").await, [["permanent"]]);
+
+    // Closing the session drops its tables and what the database recorded about them
+    drop(test);
+    let next = server.connect_with("dbname=test").await.unwrap();
+    assert!(next.simple_query("SELECT * FROM staging").await.is_err());
+    let conn = rusqlite::Connection::open(&db_path).unwrap();
+    let started = Instant::now();
+    loop {
+        let left: i64 = conn.query_row(
+            "SELECT count(*) FROM __pgsqlite_schema WHERE table_name LIKE 'pg\\_temp\\_%staging' ESCAPE '\\'",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        // The other session's staging table is still open
+        if left == 1 {
+            break;
+        }
+        assert!(started.elapsed() < Duration::from_secs(10), "{left} metadata rows left");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(text_rows(&other, "SELECT * FROM staging").await, [["other"]]);
+}
@@ -38,7 +38,7 @@ async fn test_set_parameter() {
     // Verify with SHOW
     let row = client.query_one("SHOW search_path", &[]).await.unwrap();
     let value: &str = row.get(0);
-    assert_eq!(value, "public,test");
+    assert_eq!(value, "public, test");
     
     // Test SET with = syntax
     client.execute("SET statement_timeout = '5min'", &[]).await.unwrap();
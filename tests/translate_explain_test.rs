@@ -0,0 +1,62 @@
+mod common;
+use common::setup_test_server;
+use tokio_postgres::SimpleQueryMessage;
+
+fn first_row(messages: &[SimpleQueryMessage]) -> Vec<Option<String>> {
+    messages.iter()
+        .find_map(|msg| match msg {
+            SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).map(str::to_string)).collect()),
+            _ => None,
+        })
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_pgsqlite_translate() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TABLE orders (id INTEGER PRIMARY KEY, placed_at TIMESTAMP, total NUMERIC(10,2))", &[]).await.unwrap();
+    client.execute("INSERT INTO orders (id, placed_at, total) VALUES (1, '2024-01-02 03:04:05', 9.50)", &[]).await.unwrap();
+
+    let messages = client.simple_query(
+        "SELECT pgsqlite.translate('SELECT id, placed_at FROM orders WHERE placed_at > ''2024-01-01''::timestamp \
+         ORDER BY id FETCH FIRST 5 ROWS ONLY')"
+    ).await.unwrap();
+    let row = first_row(&messages);
+    let translated = row[0].as_deref().unwrap();
+    assert!(translated.contains("LIMIT 5"), "{translated}");
+    assert!(!translated.contains("::timestamp"), "{translated}");
+    let translators = row[1].as_deref().unwrap();
+    assert!(translators.contains("cast") && translators.contains("pagination"), "{translators}");
+    assert_eq!(row[2].as_deref(), Some("id int4, placed_at timestamp"));
+    assert_eq!(row[3].as_deref(), Some(""));
+
+    // Nothing is executed
+    let messages = client.simple_query("SELECT pgsqlite.translate('UPDATE orders SET total = $1 WHERE id = $2')").await.unwrap();
+    let row = first_row(&messages);
+    assert_eq!(row[1].as_deref(), Some(""));
+    assert_eq!(row[3].as_deref(), Some("$1 numeric, $2 int4"));
+    client.simple_query("SELECT pgsqlite.translate('DELETE FROM orders')").await.unwrap();
+    let count: i64 = client.query_one("SELECT count(*) FROM orders", &[]).await.unwrap().get(0);
+    assert_eq!(count, 1);
+
+    // Parameter types and unpreparable SQL
+    let messages = client.simple_query(
+        "SELECT pgsqlite.translate('SELECT total FROM orders WHERE id = $1 AND placed_at < $2::timestamptz')"
+    ).await.unwrap();
+    let row = first_row(&messages);
+    assert_eq!(row[2].as_deref(), Some("total numeric"));
+    assert_eq!(row[3].as_deref(), Some("$1 int4, $2 timestamptz"));
+
+    let messages = client.simple_query("SELECT pgsqlite.translate('SELECT * FROM missing')").await.unwrap();
+    assert_eq!(first_row(&messages)[2], None);
+
+    // The extended protocol reports the same four text columns
+    let row = client.query_one("SELECT pgsqlite.translate('SELECT 1 AS one')", &[]).await.unwrap();
+    let columns: Vec<&str> = row.columns().iter().map(|c| c.name()).collect();
+    assert_eq!(columns, ["translated_query", "translators", "columns", "parameters"]);
+    assert_eq!(row.get::<_, Option<String>>(0).as_deref(), Some("SELECT 1 AS one"));
+
+    server.abort();
+}
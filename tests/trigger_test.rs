@@ -0,0 +1,157 @@
+mod common;
+use common::*;
+
+async fn setup() -> TestServer {
+    let server = setup_test_server().await;
+    server.client.batch_execute(
+        "CREATE TABLE items (
+            id SERIAL PRIMARY KEY,
+            name TEXT NOT NULL,
+            price NUMERIC(10,2),
+            updated_at TIMESTAMP
+        );
+        CREATE TABLE audit_log (id SERIAL PRIMARY KEY, item_id INTEGER, operation TEXT, old_price NUMERIC(10,2));
+        CREATE FUNCTION touch_item() RETURNS trigger AS $$
+        BEGIN
+            -- Keep the time of the last change
+            NEW.updated_at := now();
+            IF NEW.price < 0 THEN
+                RAISE EXCEPTION 'price of % cannot be negative', NEW.name USING ERRCODE = 'check_violation';
+            END IF;
+            RETURN NEW;
+        END;
+        $$ LANGUAGE plpgsql;
+        CREATE FUNCTION log_item() RETURNS trigger AS $$
+        BEGIN
+            IF TG_OP = 'DELETE' THEN
+                INSERT INTO audit_log (item_id, operation, old_price) VALUES (OLD.id, TG_OP, OLD.price);
+                RETURN OLD;
+            END IF;
+            INSERT INTO audit_log (item_id, operation, old_price) VALUES (NEW.id, TG_OP, OLD.price);
+            RETURN NEW;
+        END;
+        $$ LANGUAGE plpgsql;"
+    ).await.unwrap();
+    server
+}
+
+fn error_code(error: &tokio_postgres::Error) -> &str {
+    error.as_db_error().map(|e| e.code().code()).unwrap_or_default()
+}
+
+#[tokio::test]
+async fn test_before_trigger_assigns_and_raises() {
+    let server = setup().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE TRIGGER items_touch BEFORE INSERT OR UPDATE ON items FOR EACH ROW EXECUTE FUNCTION touch_item();
+         INSERT INTO items (name, price) VALUES ('pen', 1.50)"
+    ).await.unwrap();
+    let row = client.query_one("SELECT updated_at IS NOT NULL FROM items WHERE name = 'pen'", &[]).await.unwrap();
+    assert!(row.get::<_, bool>(0));
+
+    // RETURNING reports the row as the trigger left it, over both protocols
+    let messages = client.simple_query("INSERT INTO items (name, price) VALUES ('cap', 2.00) RETURNING name, updated_at").await.unwrap();
+    let returned: Vec<_> = messages.iter()
+        .filter_map(|message| match message {
+            tokio_postgres::SimpleQueryMessage::Row(row) => Some((row.get(0).map(str::to_string), row.get(1).is_some())),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(returned, [(Some("cap".to_string()), true)]);
+    let messages = client.simple_query("UPDATE items SET updated_at = NULL WHERE name = 'cap' RETURNING updated_at").await.unwrap();
+    assert!(messages.iter().any(|message| matches!(message, tokio_postgres::SimpleQueryMessage::Row(row) if row.get(0).is_some())));
+    let row = client.query_one("INSERT INTO items (name, price) VALUES ('nib', 3.00) RETURNING updated_at IS NOT NULL", &[]).await.unwrap();
+    assert!(row.get::<_, bool>(0));
+    client.batch_execute("DELETE FROM items WHERE name IN ('cap', 'nib')").await.unwrap();
+
+    let err = client.execute("INSERT INTO items (name, price) VALUES ('ink', -1)", &[]).await.unwrap_err();
+    assert_eq!(error_code(&err), "23514");
+    assert_eq!(err.as_db_error().unwrap().message(), "price of ink cannot be negative");
+    let err = client.execute("UPDATE items SET price = -2 WHERE name = 'pen'", &[]).await.unwrap_err();
+    assert_eq!(error_code(&err), "23514");
+    let row = client.query_one("SELECT count(*), max(price)::text FROM items", &[]).await.unwrap();
+    assert_eq!(row.get::<_, i64>(0), 1);
+    assert_eq!(row.get::<_, String>(1), "1.50");
+
+    let err = client.batch_execute("CREATE TRIGGER items_touch BEFORE UPDATE ON items FOR EACH ROW EXECUTE FUNCTION touch_item()").await.unwrap_err();
+    assert_eq!(error_code(&err), "42710");
+    let err = client.batch_execute("CREATE TRIGGER other AFTER UPDATE ON items FOR EACH ROW EXECUTE FUNCTION missing()").await.unwrap_err();
+    assert_eq!(error_code(&err), "42883");
+    let err = client.batch_execute("CREATE TRIGGER other AFTER UPDATE ON items FOR EACH STATEMENT EXECUTE FUNCTION touch_item()").await.unwrap_err();
+    assert_eq!(error_code(&err), "0A000");
+}
+
+#[tokio::test]
+async fn test_after_trigger_writes_audit_log() {
+    let server = setup().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE TRIGGER items_audit AFTER INSERT OR UPDATE OR DELETE ON items FOR EACH ROW EXECUTE FUNCTION log_item();
+         INSERT INTO items (name, price) VALUES ('pen', 1.50);
+         UPDATE items SET price = 2 WHERE name = 'pen';
+         DELETE FROM items WHERE name = 'pen'"
+    ).await.unwrap();
+
+    let rows = client.query("SELECT operation, old_price::text FROM audit_log ORDER BY id", &[]).await.unwrap();
+    let log: Vec<(String, Option<String>)> = rows.iter().map(|row| (row.get(0), row.get(1))).collect();
+    assert_eq!(log, [
+        ("INSERT".to_string(), None),
+        ("UPDATE".to_string(), Some("1.50".to_string())),
+        ("DELETE".to_string(), Some("2.00".to_string())),
+    ]);
+
+    // WHEN limits the rows the trigger fires for
+    client.batch_execute(
+        "CREATE OR REPLACE TRIGGER items_audit AFTER UPDATE OF price ON items FOR EACH ROW
+             WHEN (OLD.price IS DISTINCT FROM NEW.price) EXECUTE FUNCTION log_item();
+         DELETE FROM audit_log;
+         INSERT INTO items (name, price) VALUES ('ink', 3);
+         UPDATE items SET name = 'blue ink';
+         UPDATE items SET price = 3;
+         UPDATE items SET price = 4"
+    ).await.unwrap();
+    let row = client.query_one("SELECT count(*), max(old_price)::text FROM audit_log", &[]).await.unwrap();
+    assert_eq!(row.get::<_, i64>(0), 1);
+    assert_eq!(row.get::<_, String>(1), "3.00");
+}
+
+#[tokio::test]
+async fn test_trigger_catalog_and_drop() {
+    let server = setup().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE TRIGGER items_touch BEFORE INSERT OR UPDATE ON items FOR EACH ROW EXECUTE FUNCTION touch_item();
+         CREATE TRIGGER items_audit AFTER DELETE ON items FOR EACH ROW EXECUTE FUNCTION log_item();"
+    ).await.unwrap();
+
+    // Catalog columns are read as text, as psql does
+    let rows: Vec<Vec<String>> = client.simple_query(
+        "SELECT tgname, tgtype, pg_get_triggerdef(oid) FROM pg_trigger
+         WHERE tgrelid = (SELECT oid FROM pg_class WHERE relname = 'items') ORDER BY tgname"
+    ).await.unwrap().into_iter()
+        .filter_map(|message| match message {
+            tokio_postgres::SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).unwrap_or_default().to_string()).collect()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(rows, [
+        ["items_audit", "9", "CREATE TRIGGER items_audit AFTER DELETE ON items FOR EACH ROW EXECUTE FUNCTION log_item()"],
+        ["items_touch", "23", "CREATE TRIGGER items_touch BEFORE INSERT OR UPDATE ON items FOR EACH ROW EXECUTE FUNCTION touch_item()"],
+    ]);
+
+    // Functions with triggers are only dropped with them
+    let err = client.batch_execute("DROP FUNCTION touch_item()").await.unwrap_err();
+    assert_eq!(error_code(&err), "2BP01");
+    client.batch_execute("DROP TRIGGER items_audit ON items; DROP FUNCTION touch_item() CASCADE").await.unwrap();
+    let err = client.batch_execute("DROP TRIGGER items_touch ON items").await.unwrap_err();
+    assert_eq!(error_code(&err), "42704");
+    client.batch_execute("DROP TRIGGER IF EXISTS items_touch ON items; DROP FUNCTION IF EXISTS touch_item()").await.unwrap();
+
+    let row = client.query_one("SELECT count(*) FROM pg_trigger", &[]).await.unwrap();
+    assert_eq!(row.get::<_, i64>(0), 0);
+    client.execute("INSERT INTO items (name, price) VALUES ('pen', -1)", &[]).await.unwrap();
+}
@@ -0,0 +1,64 @@
+mod common;
+use common::setup_test_server;
+use tokio_postgres::types::Type;
+
+/// Result column types a prepared statement is described with
+async fn column_types(client: &tokio_postgres::Client, query: &str) -> Vec<Type> {
+    let statement = client.prepare(query).await.unwrap();
+    statement.columns().iter().map(|column| column.type_().clone()).collect()
+}
+
+#[tokio::test]
+async fn test_describe_expression_types() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+    client.batch_execute(
+        "CREATE TABLE customers (id BIGINT PRIMARY KEY, name TEXT, born DATE);
+         CREATE TABLE orders (id INTEGER PRIMARY KEY, customer_id BIGINT, price NUMERIC(10,2), quantity SMALLINT, weight REAL, status VARCHAR(10));"
+    ).await.unwrap();
+
+    // Described before any row exists, so nothing can be read off values
+    assert_eq!(
+        column_types(client, "SELECT o.id AS order_id, c.name AS customer, o.price * o.quantity AS total_amount, o.quantity + 1 AS next FROM orders o JOIN customers c ON c.id = o.customer_id").await,
+        [Type::INT4, Type::TEXT, Type::NUMERIC, Type::INT4]
+    );
+    assert_eq!(
+        column_types(client, "SELECT customer_id, sum(price * quantity) AS total_amount, sum(quantity) AS items, avg(weight) AS mean, count(*) AS n FROM orders GROUP BY customer_id").await,
+        [Type::INT8, Type::NUMERIC, Type::INT8, Type::FLOAT8, Type::INT8]
+    );
+    assert_eq!(
+        column_types(client, "SELECT CASE WHEN quantity > 10 THEN price ELSE 0 END AS discounted, coalesce(weight, 0) AS w, upper(status) AS s, born + 7 AS week_later FROM orders, customers").await,
+        [Type::NUMERIC, Type::FLOAT4, Type::TEXT, Type::DATE]
+    );
+
+    // With rows the types stay the same
+    client.batch_execute(
+        "INSERT INTO customers VALUES (1, 'ann', '1990-01-01');
+         INSERT INTO orders VALUES (1, 1, 2.50, 4, 1.5, 'new');"
+    ).await.unwrap();
+    let row = client.query_one(
+        "SELECT o.price * o.quantity AS total_amount, sum(o.quantity) OVER () AS items FROM orders o WHERE o.id = $1", &[&1i32]
+    ).await.unwrap();
+    assert_eq!(row.columns()[0].type_(), &Type::NUMERIC);
+    assert_eq!(row.get::<_, i64>(1), 4);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_describe_runs_nothing() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+    client.batch_execute("CREATE TABLE tickets (id INTEGER PRIMARY KEY, label TEXT)").await.unwrap();
+
+    // Preparing describes the columns without inserting a row
+    let insert = client.prepare("INSERT INTO tickets (id, label) VALUES ($1, $2) RETURNING id, label").await.unwrap();
+    assert_eq!(insert.columns().iter().map(|column| column.type_().clone()).collect::<Vec<_>>(), [Type::INT4, Type::TEXT]);
+    let count: i64 = client.query_one("SELECT count(*) FROM tickets", &[]).await.unwrap().get(0);
+    assert_eq!(count, 0);
+
+    let row = client.query_one(&insert, &[&7i32, &"first"]).await.unwrap();
+    assert_eq!((row.get::<_, i32>(0), row.get::<_, &str>(1)), (7, "first"));
+
+    server.abort();
+}
@@ -0,0 +1,90 @@
+mod common;
+use common::setup_test_server;
+
+/// Collect (column names, values) for each row of a simple query
+async fn simple_rows(client: &tokio_postgres::Client, query: &str) -> Vec<(Vec<String>, Vec<String>)> {
+    let messages = client.simple_query(query).await.unwrap();
+    messages.iter().filter_map(|m| match m {
+        tokio_postgres::SimpleQueryMessage::Row(row) => Some((
+            row.columns().iter().map(|c| c.name().to_string()).collect(),
+            (0..row.len()).map(|i| row.get(i).unwrap_or("NULL").to_string()).collect(),
+        )),
+        _ => None,
+    }).collect()
+}
+
+#[tokio::test]
+async fn test_standalone_values() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    let rows = simple_rows(client, "VALUES (1, 'a'), (2, 'b')").await;
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].0, vec!["column1", "column2"]);
+    assert_eq!(rows[0].1, vec!["1", "a"]);
+    assert_eq!(rows[1].1, vec!["2", "b"]);
+
+    let rows = simple_rows(client, "VALUES (1, 'a'), (2, 'b') ORDER BY 1 DESC").await;
+    assert_eq!(rows[0].1, vec!["2", "b"]);
+
+    let rows = client.query("VALUES ($1, 'x'), ('second', 'y')", &[&"first"]).await.unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].get::<_, String>("column1"), "first");
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_values_in_from_with_column_aliases() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    let rows = simple_rows(client, "SELECT * FROM (VALUES (1, 'a'), (2, 'b')) AS t(id, name)").await;
+    assert_eq!(rows[0].0, vec!["id", "name"]);
+    assert_eq!(rows.iter().map(|r| r.1.clone()).collect::<Vec<_>>(), vec![vec!["1", "a"], vec!["2", "b"]]);
+
+    let rows = simple_rows(client, "SELECT t.name FROM (VALUES (1, 'a'), (2, 'b')) t (id, name) WHERE t.id = 2").await;
+    assert_eq!(rows, vec![(vec!["name".to_string()], vec!["b".to_string()])]);
+
+    // Joining a VALUES list against a table
+    client.execute("CREATE TABLE prices (sku TEXT PRIMARY KEY, price INTEGER)", &[]).await.unwrap();
+    client.execute("INSERT INTO prices VALUES ('a1', 10), ('b2', 20)", &[]).await.unwrap();
+    let rows = simple_rows(
+        client,
+        "SELECT v.sku, p.price * v.qty FROM (VALUES ('a1', 3), ('b2', 1)) AS v(sku, qty) JOIN prices p ON p.sku = v.sku ORDER BY v.sku",
+    ).await;
+    assert_eq!(rows.iter().map(|r| r.1.clone()).collect::<Vec<_>>(), vec![vec!["a1", "30"], vec!["b2", "20"]]);
+
+    // Extended protocol with parameters inside the VALUES rows
+    let rows = client.query(
+        "SELECT t.name FROM (VALUES ($1, 2), ($2, 1)) AS t(name, position) ORDER BY t.position",
+        &[&"second", &"first"],
+    ).await.unwrap();
+    let names: Vec<String> = rows.iter().map(|r| r.get(0)).collect();
+    assert_eq!(names, vec!["first", "second"]);
+
+    // CTE column lists keep working
+    let rows = simple_rows(client, "WITH v(x) AS (VALUES (1), (2)) SELECT sum(x) FROM v").await;
+    assert_eq!(rows[0].1, vec!["3"]);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_long_values_lists() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    // More rows than SQLite allows terms in a compound SELECT
+    let rows: Vec<String> = (1..=600).map(|i| format!("({i}, 'n{i}')")).collect();
+    let values = rows.join(", ");
+
+    let result = simple_rows(client, &format!("SELECT count(*), sum(t.id) FROM (VALUES {values}) AS t(id, name)")).await;
+    assert_eq!(result[0].1, vec!["600", "180300"]);
+
+    let result = simple_rows(client, &format!("VALUES {values}")).await;
+    assert_eq!(result.len(), 600);
+    assert_eq!(result[599].1, vec!["600", "n600"]);
+
+    server.abort();
+}
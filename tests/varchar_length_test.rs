@@ -0,0 +1,51 @@
+mod common;
+use common::setup_test_server;
+use tokio_postgres::error::SqlState;
+
+fn assert_truncation(err: tokio_postgres::Error, column: &str, type_name: &str) {
+    assert_eq!(err.code(), Some(&SqlState::STRING_DATA_RIGHT_TRUNCATION), "unexpected error: {err:?}");
+    let db_error = err.as_db_error().unwrap();
+    assert_eq!(db_error.message(), format!("value too long for type {type_name}"));
+    assert_eq!(db_error.column(), Some(column));
+}
+
+#[tokio::test]
+async fn test_varchar_length_enforced_on_all_paths() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute(
+        "CREATE TABLE accounts (id INTEGER PRIMARY KEY, username VARCHAR(5), code CHAR(3), note TEXT)",
+        &[]
+    ).await.unwrap();
+
+    // Values within the limit are accepted, multibyte characters count once
+    client.simple_query("INSERT INTO accounts (id, username, code) VALUES (1, 'alice', 'abc')").await.unwrap();
+    client.execute("INSERT INTO accounts (id, username, code) VALUES ($1, $2, $3)", &[&2i32, &"héllo", &"xy"]).await.unwrap();
+
+    // Simple protocol
+    let err = client.simple_query("INSERT INTO accounts (id, username) VALUES (3, 'mallory')").await.unwrap_err();
+    assert_truncation(err, "username", "character varying(5)");
+
+    // Extended protocol with bound parameters
+    let err = client.execute("INSERT INTO accounts (id, username) VALUES ($1, $2)", &[&4i32, &"eavesdropper"]).await.unwrap_err();
+    assert_truncation(err, "username", "character varying(5)");
+
+    let err = client.execute("INSERT INTO accounts (id, code) VALUES (5, 'abcd')", &[]).await.unwrap_err();
+    assert_truncation(err, "code", "character(3)");
+
+    // Updates are checked too
+    let err = client.execute("UPDATE accounts SET username = $1 WHERE id = 1", &[&"alicia"]).await.unwrap_err();
+    assert_truncation(err, "username", "character varying(5)");
+    let err = client.simple_query("UPDATE accounts SET code = 'wxyz' WHERE id = 2").await.unwrap_err();
+    assert_truncation(err, "code", "character(3)");
+
+    // Unconstrained columns are unaffected
+    client.execute("UPDATE accounts SET note = $1 WHERE id = 1", &[&"a note well beyond five characters"]).await.unwrap();
+
+    let rows = client.query("SELECT username FROM accounts ORDER BY id", &[]).await.unwrap();
+    let names: Vec<String> = rows.iter().map(|row| row.get(0)).collect();
+    assert_eq!(names, vec!["alice", "héllo"]);
+
+    server.abort();
+}
@@ -0,0 +1,96 @@
+mod common;
+use common::*;
+use tokio_postgres::types::Type;
+use tokio_postgres::SimpleQueryMessage;
+
+async fn setup() -> TestServer {
+    let server = setup_test_server().await;
+    server.client.batch_execute(
+        "CREATE TABLE orders (
+            id SERIAL PRIMARY KEY,
+            customer VARCHAR(50) NOT NULL,
+            total NUMERIC(10,2),
+            paid BOOLEAN,
+            placed_at TIMESTAMP
+        );
+        INSERT INTO orders (customer, total, paid, placed_at) VALUES
+            ('alice', 120.50, true, '2024-01-15 10:30:00'),
+            ('bob', 80.00, false, '2024-02-01 09:00:00');"
+    ).await.unwrap();
+    server
+}
+
+#[tokio::test]
+async fn test_view_columns_keep_table_types() {
+    let server = setup().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE VIEW big_orders AS SELECT id AS order_id, customer, total, paid, placed_at FROM orders WHERE total > 100"
+    ).await.unwrap();
+
+    let stmt = client.prepare("SELECT * FROM big_orders").await.unwrap();
+    let types: Vec<Type> = stmt.columns().iter().map(|c| c.type_().clone()).collect();
+    assert_eq!(types, [Type::INT4, Type::VARCHAR, Type::NUMERIC, Type::BOOL, Type::TIMESTAMP]);
+
+    let rows = client.simple_query("SELECT order_id, customer, paid, placed_at FROM big_orders").await.unwrap();
+    let row = rows.iter().find_map(|m| match m {
+        SimpleQueryMessage::Row(row) => Some(row),
+        _ => None,
+    }).expect("one view row");
+    assert_eq!(row.get("customer"), Some("alice"));
+    assert_eq!(row.get("paid"), Some("t"));
+    assert_eq!(row.get("placed_at"), Some("2024-01-15 10:30:00"));
+
+    let relkind: String = client.query_one("SELECT relkind FROM pg_class WHERE relname = 'big_orders'", &[]).await.unwrap().get(0);
+    assert_eq!(relkind, "v");
+}
+
+#[tokio::test]
+async fn test_view_body_is_translated() {
+    let server = setup().await;
+    let client = &server.client;
+
+    client.batch_execute(
+        "CREATE VIEW order_days (customer, day, amount) AS SELECT customer, placed_at::date, total::text FROM orders"
+    ).await.unwrap();
+
+    let stmt = client.prepare("SELECT * FROM order_days").await.unwrap();
+    let names: Vec<&str> = stmt.columns().iter().map(|c| c.name()).collect();
+    assert_eq!(names, ["customer", "day", "amount"]);
+    assert_eq!(stmt.columns()[0].type_(), &Type::VARCHAR);
+
+    let rows = client.query("SELECT customer FROM order_days ORDER BY customer", &[]).await.unwrap();
+    let customers: Vec<String> = rows.iter().map(|r| r.get(0)).collect();
+    assert_eq!(customers, ["alice", "bob"]);
+}
+
+#[tokio::test]
+async fn test_replace_and_drop_view() {
+    let server = setup().await;
+    let client = &server.client;
+
+    client.batch_execute("CREATE VIEW recent AS SELECT id, placed_at FROM orders").await.unwrap();
+    client.batch_execute("CREATE VIEW IF NOT EXISTS recent AS SELECT 1 AS one").await.unwrap();
+    let err = client.batch_execute("CREATE VIEW recent AS SELECT 1 AS one").await.unwrap_err();
+    assert!(err.to_string().contains("already exists"), "{err}");
+    let err = client.batch_execute("CREATE OR REPLACE VIEW orders AS SELECT 1 AS one").await.unwrap_err();
+    assert!(err.to_string().contains("already exists"), "{err}");
+
+    client.batch_execute("CREATE OR REPLACE VIEW recent AS SELECT customer, total FROM orders").await.unwrap();
+    let stmt = client.prepare("SELECT * FROM recent").await.unwrap();
+    let types: Vec<Type> = stmt.columns().iter().map(|c| c.type_().clone()).collect();
+    assert_eq!(types, [Type::VARCHAR, Type::NUMERIC]);
+
+    client.batch_execute("CREATE VIEW totals AS SELECT customer, total FROM orders").await.unwrap();
+    client.batch_execute("DROP VIEW IF EXISTS recent, public.totals, missing CASCADE").await.unwrap();
+
+    let remaining: i64 = client.query_one(
+        "SELECT COUNT(*) FROM __pgsqlite_schema WHERE table_name IN ('recent', 'totals')", &[]
+    ).await.unwrap().get(0);
+    assert_eq!(remaining, 0);
+    let views = client.query("SELECT relname FROM pg_class WHERE relkind = 'v'", &[]).await.unwrap();
+    assert!(views.is_empty());
+
+    assert!(client.batch_execute("DROP VIEW recent").await.is_err());
+}
@@ -0,0 +1,93 @@
+mod common;
+use common::setup_test_server;
+use tokio_postgres::SimpleQueryMessage;
+
+async fn query_rows(client: &tokio_postgres::Client, sql: &str) -> Vec<Vec<Option<String>>> {
+    client.simple_query(sql).await.unwrap().iter()
+        .filter_map(|msg| match msg {
+            SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).map(|s| s.to_string())).collect()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn s(value: &str) -> Option<String> {
+    Some(value.to_string())
+}
+
+async fn setup_sales(client: &tokio_postgres::Client) {
+    client.execute(
+        "CREATE TABLE sales (id INTEGER PRIMARY KEY, region TEXT, amount INTEGER, sold_at TIMESTAMP, sold_on DATE)",
+        &[],
+    ).await.unwrap();
+    client.simple_query(
+        "INSERT INTO sales (id, region, amount, sold_at, sold_on) VALUES \
+         (1, 'east', 10, '2024-01-01 10:00:00', '2024-01-01'), (2, 'east', 20, '2024-01-03 10:00:00', '2024-01-03'), \
+         (3, 'west', 5, '2024-01-10 10:00:00', '2024-01-10'), (4, 'west', 7, '2024-01-20 10:00:00', '2024-01-20')"
+    ).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_range_frames_with_intervals() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+    setup_sales(client).await;
+
+    // Timestamps are stored as microseconds and dates as days; both see the same 7 day window
+    let expected = vec![
+        vec![s("1"), s("10")],
+        vec![s("2"), s("30")],
+        vec![s("3"), s("25")],
+        vec![s("4"), s("7")],
+    ];
+    let rows = query_rows(client,
+        "SELECT id, sum(amount) OVER (ORDER BY sold_at RANGE BETWEEN INTERVAL '7 days' PRECEDING AND CURRENT ROW) AS s \
+         FROM sales ORDER BY id").await;
+    assert_eq!(rows, expected);
+
+    let rows = query_rows(client,
+        "SELECT id, sum(amount) OVER w FROM sales \
+         WINDOW w AS (ORDER BY sold_on RANGE BETWEEN '1 week'::interval PRECEDING AND CURRENT ROW) ORDER BY id").await;
+    assert_eq!(rows, expected);
+
+    let rows = client.query(
+        "SELECT id, count(*) OVER (ORDER BY sold_at RANGE BETWEEN CURRENT ROW AND INTERVAL '10 days' FOLLOWING) AS c \
+         FROM sales WHERE region = $1 OR $1 = 'all' ORDER BY id",
+        &[&"all"],
+    ).await.unwrap();
+    let counts: Vec<i64> = rows.iter().map(|row| row.get(1)).collect();
+    assert_eq!(counts, vec![3, 2, 2, 1]);
+
+    // FILTER and named windows pass through to SQLite
+    let rows = query_rows(client,
+        "SELECT id, sum(amount) FILTER (WHERE amount > 5) OVER w, avg(amount) OVER w FROM sales \
+         WINDOW w AS (PARTITION BY region) ORDER BY id").await;
+    assert_eq!(rows[2], vec![s("3"), s("7"), s("6")]);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_window_function_result_types() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+    setup_sales(client).await;
+
+    let rows = client.query(
+        "SELECT id, row_number() OVER (ORDER BY id) AS rn, dense_rank() OVER (ORDER BY region) AS dr, \
+                ntile(3) OVER (ORDER BY id) AS bucket, percent_rank() OVER (ORDER BY amount) AS pr, \
+                cume_dist() OVER (ORDER BY amount), lag(amount) OVER (ORDER BY id) AS prev \
+         FROM sales ORDER BY id",
+        &[],
+    ).await.unwrap();
+
+    let types: Vec<&str> = rows[0].columns().iter().map(|column| column.type_().name()).collect();
+    assert_eq!(types, vec!["int4", "int8", "int8", "int4", "float8", "float8", "int4"]);
+
+    let first: (i64, i64, i32, f64, f64, Option<i32>) = (rows[0].get(1), rows[0].get(2), rows[0].get(3), rows[0].get(4), rows[0].get(5), rows[0].get(6));
+    assert_eq!(first, (1, 1, 1, 2.0 / 3.0, 0.75, None));
+    let last: (i64, i32, Option<i32>) = (rows[3].get(1), rows[3].get(3), rows[3].get(6));
+    assert_eq!(last, (4, 3, Some(5)));
+
+    server.abort();
+}
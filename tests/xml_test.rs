@@ -0,0 +1,116 @@
+mod common;
+use common::setup_test_server;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::types::Type;
+use tokio_postgres::SimpleQueryMessage;
+
+async fn query_row(client: &tokio_postgres::Client, sql: &str) -> Vec<Option<String>> {
+    client.simple_query(sql).await.unwrap().iter()
+        .find_map(|msg| match msg {
+            SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).map(|s| s.to_string())).collect()),
+            _ => None,
+        })
+        .unwrap()
+}
+
+fn s(value: &str) -> Option<String> {
+    Some(value.to_string())
+}
+
+#[tokio::test]
+async fn test_xml_columns() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TABLE docs (id INTEGER PRIMARY KEY, body XML)", &[]).await.unwrap();
+    client.simple_query(
+        "INSERT INTO docs (id, body) VALUES (1, '<book id=\"1\"><title>SQLite &amp; Rust</title></book>'), (2, 'plain text')",
+    ).await.unwrap();
+    client.execute("INSERT INTO docs (id, body) VALUES ($1, $2)", &[&3i32, &"<note>fragment</note><note/>"]).await.unwrap();
+
+    // Values are stored as written and described as xml
+    let row = query_row(client, "SELECT body FROM docs WHERE id = 1").await;
+    assert_eq!(row, vec![s("<book id=\"1\"><title>SQLite &amp; Rust</title></book>")]);
+    let row = query_row(client, "SELECT body FROM docs WHERE id = 3").await;
+    assert_eq!(row, vec![s("<note>fragment</note><note/>")]);
+    let statement = client.prepare("SELECT body FROM docs").await.unwrap();
+    assert_eq!(statement.columns()[0].type_(), &Type::XML);
+
+    // Content that isn't well-formed is rejected
+    let err = client.simple_query("INSERT INTO docs (id, body) VALUES (4, '<book><title></book>')").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::INVALID_XML_CONTENT), "{err:?}");
+    let err = client.execute("INSERT INTO docs (id, body) VALUES ($1, $2)", &[&4i32, &"a & b"]).await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::INVALID_XML_CONTENT), "{err:?}");
+    let err = client.simple_query("SELECT '<open>'::xml").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::INVALID_XML_CONTENT), "{err:?}");
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_xmlparse_and_xmlserialize() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    let row = query_row(
+        client,
+        "SELECT XMLPARSE(DOCUMENT '<?xml version=\"1.0\"?><a>1</a>'), XMLPARSE(CONTENT 'x<b/>'), \
+         XMLSERIALIZE(CONTENT '<c>(y)</c>'::xml AS text)",
+    ).await;
+    assert_eq!(row, vec![s("<?xml version=\"1.0\"?><a>1</a>"), s("x<b/>"), s("<c>(y)</c>")]);
+
+    let err = client.simple_query("SELECT XMLPARSE(DOCUMENT 'x<b/>')").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::INVALID_XML_DOCUMENT), "{err:?}");
+    let err = client.simple_query("SELECT XMLSERIALIZE(DOCUMENT 'x<b/>'::xml AS text)").await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::NOT_AN_XML_DOCUMENT), "{err:?}");
+
+    let row = query_row(
+        client,
+        "SELECT xml_is_well_formed('x<b/>'), xml_is_well_formed_document('x<b/>'), xml_is_well_formed_content('<a>')",
+    ).await;
+    assert_eq!(row, vec![s("t"), s("f"), s("f")]);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_xpath() {
+    let server = setup_test_server().await;
+    let client = &server.client;
+
+    client.execute("CREATE TABLE orders (id INTEGER PRIMARY KEY, payload XML)", &[]).await.unwrap();
+    client.simple_query(
+        "INSERT INTO orders (id, payload) VALUES \
+         (1, '<order status=\"paid\"><item sku=\"A1\">2</item><item sku=\"B2\">1</item></order>'), \
+         (2, '<order status=\"open\"/>')",
+    ).await.unwrap();
+
+    let row = query_row(client, "SELECT xpath('/order/item/@sku', payload) FROM orders WHERE id = 1").await;
+    assert_eq!(row, vec![s(r#"{"A1","B2"}"#)]);
+    let row = query_row(client, "SELECT xpath('//item[@sku=\"B2\"]', payload) FROM orders WHERE id = 1").await;
+    assert_eq!(row, vec![s("{\"<item sku=\\\"B2\\\">1</item>\"}")]);
+    let row = query_row(client, "SELECT xpath('sum(//item)', payload) FROM orders WHERE id = 1").await;
+    assert_eq!(row, vec![s(r#"{"3"}"#)]);
+
+    // xpath_exists() and the matches as text, as applications filter and extract with them
+    let rows = client.query(
+        "SELECT id, array_to_string(xpath('/order/@status', payload), ',') FROM orders \
+         WHERE xpath_exists('/order/item', payload) ORDER BY id",
+        &[],
+    ).await.unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].get::<_, Option<String>>(1).as_deref(), Some("paid"));
+
+    // Prefixes are bound through the namespace array
+    let row = query_row(
+        client,
+        "SELECT xpath('/x:feed/x:title/text()', '<feed xmlns=\"http://www.w3.org/2005/Atom\"><title>News</title></feed>', \
+         '{{x,http://www.w3.org/2005/Atom}}')",
+    ).await;
+    assert_eq!(row, vec![s(r#"{"News"}"#)]);
+
+    let err = client.simple_query("SELECT xpath('//[', payload) FROM orders").await.unwrap_err();
+    assert_eq!(err.as_db_error().unwrap().message(), "invalid XPath expression");
+
+    server.abort();
+}